@@ -0,0 +1,54 @@
+mod store;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_blobstore::runtime_config::spin::MakeBlobStore;
+use store::{BlobStoreAzureBlob, BlobStoreAzureBlobAuthOptions};
+
+/// A blob store that uses Azure Blob Storage as the backend.
+#[derive(Default)]
+pub struct AzureBlobStore {
+    _priv: (),
+}
+
+impl AzureBlobStore {
+    /// Creates a new `AzureBlobStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Azure Blob Storage blob store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobStoreRuntimeConfig {
+    /// The access key for the Azure Storage account.
+    key: Option<String>,
+    /// The Azure Storage account name.
+    account: String,
+    /// The Azure Blob Storage container that backs the container.
+    container: String,
+}
+
+impl MakeBlobStore for AzureBlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "azure_blob";
+
+    type RuntimeConfig = AzureBlobStoreRuntimeConfig;
+
+    type ContainerManager = BlobStoreAzureBlob;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::ContainerManager> {
+        let auth_options = match runtime_config.key {
+            Some(key) => BlobStoreAzureBlobAuthOptions::AccessKey(key),
+            None => BlobStoreAzureBlobAuthOptions::Environmental,
+        };
+        BlobStoreAzureBlob::new(
+            runtime_config.account,
+            runtime_config.container,
+            auth_options,
+        )
+    }
+}
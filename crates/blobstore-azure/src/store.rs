@@ -0,0 +1,249 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use azure_core::StatusCode;
+use azure_storage::StorageCredentials;
+use azure_storage_blobs::prelude::{
+    BlobBlockType, BlobSasPermissions, BlockId, BlockList, ClientBuilder, ContainerClient,
+};
+use futures::{StreamExt, TryStreamExt};
+use spin_core::async_trait;
+use spin_factor_blobstore::{
+    Container, ContainerManager, IncomingData, ObjectNames, ObjectReader, PresignMethod,
+    StreamObjectNames,
+};
+use spin_world::wasi::blobstore::types::{ContainerMetadata, ObjectMetadata};
+use time::OffsetDateTime;
+use tokio::io::AsyncReadExt;
+
+/// The size of each block of a block blob upload. Objects smaller than this
+/// are uploaded in a single request.
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+pub struct BlobStoreAzureBlob {
+    client: ContainerClient,
+}
+
+/// Azure Blob Storage enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum BlobStoreAzureBlobAuthOptions {
+    /// An access key for the storage account has been specified directly in runtime config
+    AccessKey(String),
+    /// Environmental indicates that the environment variables of the process should be used to
+    /// create the TokenCredential for the storage client. This will use the Azure Rust SDK's
+    /// DefaultCredentialChain to derive the TokenCredential based on what environment variables
+    /// have been set.
+    ///
+    /// See https://github.com/Azure/azure-sdk-for-rust/blob/main/sdk/identity/README.md for options.
+    Environmental,
+}
+
+impl BlobStoreAzureBlob {
+    pub fn new(
+        account: String,
+        container: String,
+        auth_options: BlobStoreAzureBlobAuthOptions,
+    ) -> Result<Self> {
+        let credentials = match auth_options {
+            BlobStoreAzureBlobAuthOptions::AccessKey(key) => {
+                StorageCredentials::access_key(account.clone(), key)
+            }
+            BlobStoreAzureBlobAuthOptions::Environmental => {
+                StorageCredentials::token_credential(azure_identity::create_default_credential()?)
+            }
+        };
+        let client = ClientBuilder::new(account, credentials).container_client(container);
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl ContainerManager for BlobStoreAzureBlob {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Container>> {
+        Ok(Arc::new(AzureBlobContainer {
+            name: name.to_owned(),
+            client: self.client.clone(),
+        }))
+    }
+
+    fn summary(&self, _container_name: &str) -> Option<String> {
+        Some(format!(
+            "Azure Blob Storage container: {}",
+            self.client.container_name()
+        ))
+    }
+}
+
+struct AzureBlobContainer {
+    /// The container label the guest opened
+    name: String,
+    client: ContainerClient,
+}
+
+#[async_trait]
+impl Container for AzureBlobContainer {
+    async fn exists(&self) -> Result<bool> {
+        Ok(self.client.exists().await?)
+    }
+
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn info(&self) -> Result<ContainerMetadata> {
+        // Azure does not report when a container was created.
+        Ok(ContainerMetadata {
+            name: self.name.clone(),
+            created_at: 0,
+        })
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let names: Vec<String> = list_object_names(self.client.clone()).try_collect().await?;
+        self.delete_objects(&names).await
+    }
+
+    async fn delete_object(&self, name: &str) -> Result<()> {
+        match self.client.blob_client(name).delete().await {
+            Ok(_) => Ok(()),
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_objects(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            self.delete_object(name).await?;
+        }
+        Ok(())
+    }
+
+    async fn has_object(&self, name: &str) -> Result<bool> {
+        Ok(self.client.blob_client(name).exists().await?)
+    }
+
+    async fn object_info(&self, name: &str) -> Result<ObjectMetadata> {
+        let properties = self.client.blob_client(name).get_properties().await?;
+        let properties = properties.blob.properties;
+        Ok(ObjectMetadata {
+            name: name.to_owned(),
+            container: self.name.clone(),
+            created_at: properties
+                .creation_time
+                .unix_timestamp()
+                .try_into()
+                .unwrap_or_default(),
+            size: properties.content_length,
+        })
+    }
+
+    async fn get_data(&self, name: &str, start: u64, end: u64) -> Result<IncomingData> {
+        let blob = self.client.blob_client(name);
+        let length = blob.get_properties().await?.blob.properties.content_length;
+        let end = end.min(length.saturating_sub(1));
+        if length == 0 || start > end {
+            return Ok(IncomingData::new(0, Box::pin(tokio::io::empty())));
+        }
+        let size = end - start + 1;
+        let chunks = blob
+            .get()
+            .range(start..end + 1)
+            .into_stream()
+            .map_ok(|response| response.data)
+            .try_flatten()
+            .map_err(std::io::Error::other);
+        Ok(IncomingData::new(
+            size,
+            Box::pin(tokio_util::io::StreamReader::new(chunks)),
+        ))
+    }
+
+    async fn write_data(&self, name: &str, mut data: ObjectReader) -> Result<()> {
+        let blob = self.client.blob_client(name);
+        let first = read_chunk(&mut data).await?;
+        if first.len() < BLOCK_SIZE {
+            blob.put_block_blob(first).await?;
+            return Ok(());
+        }
+
+        let mut blocks = vec![];
+        let mut chunk = first;
+        while !chunk.is_empty() {
+            let block_id = BlockId::new(format!("{:016}", blocks.len()));
+            blob.put_block(block_id.clone(), chunk).await?;
+            blocks.push(BlobBlockType::new_uncommitted(block_id));
+            chunk = read_chunk(&mut data).await?;
+        }
+        blob.put_block_list(BlockList { blocks }).await?;
+        Ok(())
+    }
+
+    async fn list_objects(&self) -> Result<Box<dyn ObjectNames>> {
+        Ok(Box::new(StreamObjectNames::new(
+            list_object_names(self.client.clone()).boxed(),
+        )))
+    }
+
+    async fn presign(
+        &self,
+        name: &str,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<String> {
+        let blob = self.client.blob_client(name);
+        let permissions = match method {
+            PresignMethod::Get => BlobSasPermissions {
+                read: true,
+                ..Default::default()
+            },
+            PresignMethod::Put => BlobSasPermissions {
+                create: true,
+                write: true,
+                ..Default::default()
+            },
+        };
+        let signature = blob
+            .shared_access_signature(permissions, OffsetDateTime::now_utc() + expires_in)
+            .await?;
+        Ok(blob.generate_signed_blob_url(&signature)?.to_string())
+    }
+}
+
+fn is_not_found(e: &azure_core::Error) -> bool {
+    e.as_http_error()
+        .is_some_and(|e| e.status() == StatusCode::NotFound)
+}
+
+/// Reads up to [`BLOCK_SIZE`] bytes, returning fewer only at end of stream.
+async fn read_chunk(data: &mut ObjectReader) -> Result<Vec<u8>> {
+    let mut chunk = vec![0; BLOCK_SIZE];
+    let mut len = 0;
+    while len < BLOCK_SIZE {
+        let n = data.read(&mut chunk[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    chunk.truncate(len);
+    Ok(chunk)
+}
+
+/// Lists every blob name in the container, fetching pages as the stream is consumed.
+fn list_object_names(
+    client: ContainerClient,
+) -> impl futures::Stream<Item = Result<String>> + Send + 'static {
+    client
+        .list_blobs()
+        .into_stream()
+        .map_ok(|page| {
+            let names: Vec<Result<String>> = page
+                .blobs
+                .blobs()
+                .map(|blob| Ok(blob.name.clone()))
+                .collect();
+            futures::stream::iter(names)
+        })
+        .map_err(anyhow::Error::from)
+        .try_flatten()
+}
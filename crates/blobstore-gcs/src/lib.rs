@@ -0,0 +1,50 @@
+mod store;
+
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_blobstore::runtime_config::spin::MakeBlobStore;
+use store::{BlobStoreGcs, BlobStoreGcsAuthOptions};
+
+/// A blob store that uses Google Cloud Storage as the backend.
+#[derive(Default)]
+pub struct GcsBlobStore {
+    _priv: (),
+}
+
+impl GcsBlobStore {
+    /// Creates a new `GcsBlobStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Google Cloud Storage blob store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GcsBlobStoreRuntimeConfig {
+    /// Path to a service account credentials file.
+    credentials_file: Option<PathBuf>,
+    /// The Cloud Storage bucket that backs the container.
+    bucket: String,
+}
+
+impl MakeBlobStore for GcsBlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "gcs";
+
+    type RuntimeConfig = GcsBlobStoreRuntimeConfig;
+
+    type ContainerManager = BlobStoreGcs;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::ContainerManager> {
+        let auth_options = match runtime_config.credentials_file {
+            Some(path) => BlobStoreGcsAuthOptions::CredentialsFile(path),
+            None => BlobStoreGcsAuthOptions::Environmental,
+        };
+        BlobStoreGcs::new(runtime_config.bucket, auth_options)
+    }
+}
@@ -0,0 +1,282 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use futures::{StreamExt, TryStreamExt};
+use google_cloud_storage::{
+    client::{google_cloud_auth::credentials::CredentialsFile, Client, ClientConfig},
+    http::{
+        buckets::get::GetBucketRequest,
+        objects::{
+            delete::DeleteObjectRequest,
+            download::Range,
+            get::GetObjectRequest,
+            list::ListObjectsRequest,
+            upload::{Media, UploadObjectRequest, UploadType},
+        },
+        Error as GcsError,
+    },
+};
+use spin_core::async_trait;
+use spin_factor_blobstore::{
+    Container, ContainerManager, IncomingData, ObjectNames, ObjectReader, StreamObjectNames,
+};
+use spin_world::wasi::blobstore::types::{ContainerMetadata, ObjectMetadata};
+use tokio::io::AsyncReadExt;
+
+pub struct BlobStoreGcs {
+    /// Cloud Storage bucket backing the container
+    bucket: Arc<String>,
+    auth_options: BlobStoreGcsAuthOptions,
+    /// Cloud Storage client, created on first use
+    client: async_once_cell::OnceCell<Client>,
+}
+
+/// Google Cloud Storage enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum BlobStoreGcsAuthOptions {
+    /// A service account credentials file has been specified directly in runtime config
+    CredentialsFile(PathBuf),
+    /// Environmental indicates that the environment of the process should be used to find
+    /// credentials. This uses Application Default Credentials: the file named by
+    /// `GOOGLE_APPLICATION_CREDENTIALS`, the gcloud CLI's credentials, or the metadata
+    /// server when running on Google Cloud.
+    Environmental,
+}
+
+impl BlobStoreGcs {
+    pub fn new(bucket: String, auth_options: BlobStoreGcsAuthOptions) -> Result<Self> {
+        Ok(Self {
+            bucket: Arc::new(bucket),
+            auth_options,
+            client: async_once_cell::OnceCell::new(),
+        })
+    }
+
+    async fn client(&self) -> Result<Client> {
+        let client = self
+            .client
+            .get_or_try_init(async {
+                let config = match &self.auth_options {
+                    BlobStoreGcsAuthOptions::CredentialsFile(path) => {
+                        let credentials =
+                            CredentialsFile::new_from_file(path.to_string_lossy().into_owned())
+                                .await
+                                .with_context(|| {
+                                    format!(
+                                        "failed to read GCS credentials file {}",
+                                        path.display()
+                                    )
+                                })?;
+                        ClientConfig::default()
+                            .with_credentials(credentials)
+                            .await?
+                    }
+                    BlobStoreGcsAuthOptions::Environmental => {
+                        ClientConfig::default().with_auth().await?
+                    }
+                };
+                anyhow::Ok(Client::new(config))
+            })
+            .await?;
+        Ok(client.clone())
+    }
+}
+
+#[async_trait]
+impl ContainerManager for BlobStoreGcs {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Container>> {
+        Ok(Arc::new(GcsContainer {
+            name: name.to_owned(),
+            client: self.client().await?,
+            bucket: self.bucket.clone(),
+        }))
+    }
+
+    fn summary(&self, _container_name: &str) -> Option<String> {
+        Some(format!("Google Cloud Storage bucket: {}", self.bucket))
+    }
+}
+
+struct GcsContainer {
+    /// The container label the guest opened
+    name: String,
+    // Client wraps an Arc so should be low cost to clone
+    client: Client,
+    bucket: Arc<String>,
+}
+
+impl GcsContainer {
+    fn object_request(&self, name: &str) -> GetObjectRequest {
+        GetObjectRequest {
+            bucket: self.bucket.to_string(),
+            object: name.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Container for GcsContainer {
+    async fn exists(&self) -> Result<bool> {
+        let request = GetBucketRequest {
+            bucket: self.bucket.to_string(),
+            ..Default::default()
+        };
+        match self.client.get_bucket(&request).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn info(&self) -> Result<ContainerMetadata> {
+        let request = GetBucketRequest {
+            bucket: self.bucket.to_string(),
+            ..Default::default()
+        };
+        let bucket = self.client.get_bucket(&request).await?;
+        Ok(ContainerMetadata {
+            name: self.name.clone(),
+            created_at: bucket
+                .time_created
+                .map(|t| t.unix_timestamp().try_into().unwrap_or_default())
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let names: Vec<String> = list_object_names(self.client.clone(), self.bucket.clone())
+            .try_collect()
+            .await?;
+        self.delete_objects(&names).await
+    }
+
+    async fn delete_object(&self, name: &str) -> Result<()> {
+        let request = DeleteObjectRequest {
+            bucket: self.bucket.to_string(),
+            object: name.to_owned(),
+            ..Default::default()
+        };
+        match self.client.delete_object(&request).await {
+            Ok(()) => Ok(()),
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_objects(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            self.delete_object(name).await?;
+        }
+        Ok(())
+    }
+
+    async fn has_object(&self, name: &str) -> Result<bool> {
+        match self.client.get_object(&self.object_request(name)).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn object_info(&self, name: &str) -> Result<ObjectMetadata> {
+        let object = self.client.get_object(&self.object_request(name)).await?;
+        Ok(ObjectMetadata {
+            name: name.to_owned(),
+            container: self.name.clone(),
+            created_at: object
+                .time_created
+                .map(|t| t.unix_timestamp().try_into().unwrap_or_default())
+                .unwrap_or_default(),
+            size: object.size.try_into().unwrap_or_default(),
+        })
+    }
+
+    async fn get_data(&self, name: &str, start: u64, end: u64) -> Result<IncomingData> {
+        let request = self.object_request(name);
+        let length: u64 = self
+            .client
+            .get_object(&request)
+            .await?
+            .size
+            .try_into()
+            .unwrap_or_default();
+        let end = end.min(length.saturating_sub(1));
+        if length == 0 || start > end {
+            return Ok(IncomingData::new(0, Box::pin(tokio::io::empty())));
+        }
+        let chunks = self
+            .client
+            .download_streamed_object(&request, &Range(Some(start), Some(end)))
+            .await?
+            .map_err(std::io::Error::other);
+        Ok(IncomingData::new(
+            end - start + 1,
+            Box::pin(tokio_util::io::StreamReader::new(chunks)),
+        ))
+    }
+
+    async fn write_data(&self, name: &str, mut data: ObjectReader) -> Result<()> {
+        // Simple uploads need the whole object up front.
+        let mut buf = Vec::new();
+        data.read_to_end(&mut buf).await?;
+        let request = UploadObjectRequest {
+            bucket: self.bucket.to_string(),
+            ..Default::default()
+        };
+        self.client
+            .upload_object(
+                &request,
+                buf,
+                &UploadType::Simple(Media::new(name.to_owned())),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_objects(&self) -> Result<Box<dyn ObjectNames>> {
+        Ok(Box::new(StreamObjectNames::new(
+            list_object_names(self.client.clone(), self.bucket.clone()).boxed(),
+        )))
+    }
+}
+
+fn is_not_found(e: &GcsError) -> bool {
+    matches!(e, GcsError::Response(response) if response.code == 404)
+}
+
+/// Lists every object name in the bucket, fetching pages as the stream is consumed.
+fn list_object_names(
+    client: Client,
+    bucket: Arc<String>,
+) -> impl futures::Stream<Item = Result<String>> + Send + 'static {
+    futures::stream::try_unfold(Some(None), move |token: Option<Option<String>>| {
+        let client = client.clone();
+        let bucket = bucket.clone();
+        async move {
+            let Some(page_token) = token else {
+                return Ok(None);
+            };
+            let request = ListObjectsRequest {
+                bucket: bucket.to_string(),
+                page_token,
+                ..Default::default()
+            };
+            let page = client.list_objects(&request).await?;
+            let names: Vec<String> = page
+                .items
+                .unwrap_or_default()
+                .into_iter()
+                .map(|object| object.name)
+                .collect();
+            let next = page.next_page_token.map(Some);
+            Ok::<_, anyhow::Error>(Some((names, next)))
+        }
+    })
+    .map_ok(|names| futures::stream::iter(names.into_iter().map(Ok)))
+    .try_flatten()
+}
@@ -0,0 +1,66 @@
+mod store;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_blobstore::runtime_config::spin::MakeBlobStore;
+use store::{BlobStoreS3, BlobStoreS3AuthOptions, BlobStoreS3RuntimeConfigOptions};
+
+/// A blob store that uses AWS S3 as the backend.
+#[derive(Default)]
+pub struct S3BlobStore {
+    _priv: (),
+}
+
+impl S3BlobStore {
+    /// Creates a new `S3BlobStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the AWS S3 blob store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct S3BlobStoreRuntimeConfig {
+    /// The access key for the AWS account role.
+    access_key: Option<String>,
+    /// The secret key for authorization on the AWS account.
+    secret_key: Option<String>,
+    /// The token for authorization on the AWS account.
+    token: Option<String>,
+    /// The AWS region where the bucket is located.
+    region: String,
+    /// The S3 bucket that backs the container.
+    bucket: String,
+    /// An alternative endpoint, for S3-compatible services such as MinIO.
+    endpoint: Option<String>,
+}
+
+impl MakeBlobStore for S3BlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "s3";
+
+    type RuntimeConfig = S3BlobStoreRuntimeConfig;
+
+    type ContainerManager = BlobStoreS3;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::ContainerManager> {
+        let S3BlobStoreRuntimeConfig {
+            access_key,
+            secret_key,
+            token,
+            region,
+            bucket,
+            endpoint,
+        } = runtime_config;
+        let auth_options = match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) => BlobStoreS3AuthOptions::RuntimeConfigValues(
+                BlobStoreS3RuntimeConfigOptions::new(access_key, secret_key, token),
+            ),
+            _ => BlobStoreS3AuthOptions::Environmental,
+        };
+        BlobStoreS3::new(region, bucket, endpoint, auth_options)
+    }
+}
@@ -0,0 +1,451 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_credential_types::Credentials;
+use aws_sdk_s3::{
+    config::{ProvideCredentials, SharedCredentialsProvider},
+    error::SdkError,
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier},
+    Client,
+};
+use futures::{StreamExt, TryStreamExt};
+use spin_core::async_trait;
+use spin_factor_blobstore::{
+    Container, ContainerManager, IncomingData, ObjectNames, ObjectReader, PresignMethod,
+    StreamObjectNames,
+};
+use spin_world::wasi::blobstore::types::{ContainerMetadata, ObjectMetadata};
+use tokio::io::AsyncReadExt;
+
+/// The size of each part of a multipart upload. Objects smaller than this are
+/// uploaded in a single request.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// The maximum number of keys S3 accepts in a single `DeleteObjects` request.
+const MAX_DELETE_BATCH: usize = 1000;
+
+pub struct BlobStoreS3 {
+    /// AWS region
+    region: String,
+    /// S3 bucket backing the container
+    bucket: Arc<String>,
+    /// S3 client
+    client: async_once_cell::Lazy<
+        Client,
+        std::pin::Pin<Box<dyn std::future::Future<Output = Client> + Send>>,
+    >,
+}
+
+/// AWS S3 runtime config literal options for authentication
+#[derive(Clone, Debug)]
+pub struct BlobStoreS3RuntimeConfigOptions {
+    access_key: String,
+    secret_key: String,
+    token: Option<String>,
+}
+
+impl BlobStoreS3RuntimeConfigOptions {
+    pub fn new(access_key: String, secret_key: String, token: Option<String>) -> Self {
+        Self {
+            access_key,
+            secret_key,
+            token,
+        }
+    }
+}
+
+impl ProvideCredentials for BlobStoreS3RuntimeConfigOptions {
+    fn provide_credentials<'a>(
+        &'a self,
+    ) -> aws_credential_types::provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        aws_credential_types::provider::future::ProvideCredentials::ready(Ok(Credentials::new(
+            self.access_key.clone(),
+            self.secret_key.clone(),
+            self.token.clone(),
+            None, // Optional expiration time
+            "spin_custom_aws_provider",
+        )))
+    }
+}
+
+/// AWS S3 enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum BlobStoreS3AuthOptions {
+    /// Runtime Config values indicates credentials have been specified directly
+    RuntimeConfigValues(BlobStoreS3RuntimeConfigOptions),
+    /// Environmental indicates that the environment variables of the process should be used to
+    /// create the SDK Config for the S3 client. This will use the AWS Rust SDK's
+    /// aws_config::load_defaults to derive credentials based on what environment variables
+    /// have been set.
+    ///
+    /// See https://docs.aws.amazon.com/cli/latest/userguide/cli-chap-authentication.html for options.
+    Environmental,
+}
+
+impl BlobStoreS3 {
+    pub fn new(
+        region: String,
+        bucket: String,
+        endpoint: Option<String>,
+        auth_options: BlobStoreS3AuthOptions,
+    ) -> Result<Self> {
+        let region_clone = region.clone();
+        let client_fut = Box::pin(async move {
+            let sdk_config = match auth_options {
+                BlobStoreS3AuthOptions::RuntimeConfigValues(config) => SdkConfig::builder()
+                    .credentials_provider(SharedCredentialsProvider::new(config))
+                    .region(Region::new(region_clone))
+                    .behavior_version(BehaviorVersion::latest())
+                    .build(),
+                BlobStoreS3AuthOptions::Environmental => {
+                    aws_config::defaults(BehaviorVersion::latest())
+                        .region(Region::new(region_clone))
+                        .load()
+                        .await
+                }
+            };
+            let mut config = aws_sdk_s3::config::Builder::from(&sdk_config);
+            if let Some(endpoint) = endpoint {
+                // S3-compatible services generally don't support virtual-hosted buckets.
+                config = config.endpoint_url(endpoint).force_path_style(true);
+            }
+            Client::from_conf(config.build())
+        });
+
+        Ok(Self {
+            region,
+            bucket: Arc::new(bucket),
+            client: async_once_cell::Lazy::from_future(client_fut),
+        })
+    }
+}
+
+#[async_trait]
+impl ContainerManager for BlobStoreS3 {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Container>> {
+        Ok(Arc::new(S3Container {
+            name: name.to_owned(),
+            client: self.client.get_unpin().await.clone(),
+            bucket: self.bucket.clone(),
+        }))
+    }
+
+    fn summary(&self, _container_name: &str) -> Option<String> {
+        Some(format!(
+            "AWS S3 region: {}, bucket: {}",
+            self.region, self.bucket
+        ))
+    }
+}
+
+struct S3Container {
+    /// The container label the guest opened
+    name: String,
+    // Client wraps an Arc so should be low cost to clone
+    client: Client,
+    bucket: Arc<String>,
+}
+
+impl S3Container {
+    async fn upload_parts(
+        &self,
+        name: &str,
+        upload_id: &str,
+        first: Vec<u8>,
+        data: &mut ObjectReader,
+    ) -> Result<Vec<CompletedPart>> {
+        let mut parts = vec![];
+        let mut chunk = first;
+        loop {
+            let part_number = parts.len() as i32 + 1;
+            let part = self
+                .client
+                .upload_part()
+                .bucket(self.bucket.as_str())
+                .key(name)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(chunk))
+                .send()
+                .await?;
+            parts.push(
+                CompletedPart::builder()
+                    .set_e_tag(part.e_tag)
+                    .part_number(part_number)
+                    .build(),
+            );
+            chunk = read_chunk(data).await?;
+            if chunk.is_empty() {
+                return Ok(parts);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Container for S3Container {
+    async fn exists(&self) -> Result<bool> {
+        match self
+            .client
+            .head_bucket()
+            .bucket(self.bucket.as_str())
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found() => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn info(&self) -> Result<ContainerMetadata> {
+        // S3 only reports bucket creation dates through `ListBuckets`, which
+        // needs account-wide permissions.
+        Ok(ContainerMetadata {
+            name: self.name.clone(),
+            created_at: 0,
+        })
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let names: Vec<String> = list_object_names(self.client.clone(), self.bucket.clone())
+            .try_collect()
+            .await?;
+        self.delete_objects(&names).await
+    }
+
+    async fn delete_object(&self, name: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(self.bucket.as_str())
+            .key(name)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_objects(&self, names: &[String]) -> Result<()> {
+        for batch in names.chunks(MAX_DELETE_BATCH) {
+            let objects = batch
+                .iter()
+                .map(|name| ObjectIdentifier::builder().key(name).build())
+                .collect::<Result<Vec<_>, _>>()?;
+            self.client
+                .delete_objects()
+                .bucket(self.bucket.as_str())
+                .delete(Delete::builder().set_objects(Some(objects)).build()?)
+                .send()
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn has_object(&self, name: &str) -> Result<bool> {
+        match self
+            .client
+            .head_object()
+            .bucket(self.bucket.as_str())
+            .key(name)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found() => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn object_info(&self, name: &str) -> Result<ObjectMetadata> {
+        let head = self
+            .client
+            .head_object()
+            .bucket(self.bucket.as_str())
+            .key(name)
+            .send()
+            .await?;
+        Ok(ObjectMetadata {
+            name: name.to_owned(),
+            container: self.name.clone(),
+            // S3 objects are immutable, so the last modified time is the creation time.
+            created_at: head
+                .last_modified()
+                .map(|t| t.secs().try_into().unwrap_or_default())
+                .unwrap_or_default(),
+            size: head
+                .content_length()
+                .unwrap_or_default()
+                .try_into()
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn get_data(&self, name: &str, start: u64, end: u64) -> Result<IncomingData> {
+        let range = if end == u64::MAX {
+            format!("bytes={start}-")
+        } else {
+            format!("bytes={start}-{end}")
+        };
+        let object = self
+            .client
+            .get_object()
+            .bucket(self.bucket.as_str())
+            .key(name)
+            .range(range)
+            .send()
+            .await?;
+        let size = object
+            .content_length()
+            .unwrap_or_default()
+            .try_into()
+            .unwrap_or_default();
+        Ok(IncomingData::new(
+            size,
+            Box::pin(object.body.into_async_read()),
+        ))
+    }
+
+    async fn write_data(&self, name: &str, mut data: ObjectReader) -> Result<()> {
+        let first = read_chunk(&mut data).await?;
+        if first.len() < PART_SIZE {
+            self.client
+                .put_object()
+                .bucket(self.bucket.as_str())
+                .key(name)
+                .body(ByteStream::from(first))
+                .send()
+                .await?;
+            return Ok(());
+        }
+
+        let upload = self
+            .client
+            .create_multipart_upload()
+            .bucket(self.bucket.as_str())
+            .key(name)
+            .send()
+            .await?;
+        let upload_id = upload
+            .upload_id()
+            .context("S3 did not return a multipart upload ID")?;
+
+        match self.upload_parts(name, upload_id, first, &mut data).await {
+            Ok(parts) => {
+                self.client
+                    .complete_multipart_upload()
+                    .bucket(self.bucket.as_str())
+                    .key(name)
+                    .upload_id(upload_id)
+                    .multipart_upload(
+                        CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await?;
+                Ok(())
+            }
+            Err(e) => {
+                // Best effort: an abandoned upload is also cleaned up by bucket lifecycle rules.
+                _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(self.bucket.as_str())
+                    .key(name)
+                    .upload_id(upload_id)
+                    .send()
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn list_objects(&self) -> Result<Box<dyn ObjectNames>> {
+        Ok(Box::new(StreamObjectNames::new(
+            list_object_names(self.client.clone(), self.bucket.clone()).boxed(),
+        )))
+    }
+
+    async fn presign(
+        &self,
+        name: &str,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<String> {
+        let config = PresigningConfig::expires_in(expires_in)?;
+        let request = match method {
+            PresignMethod::Get => {
+                self.client
+                    .get_object()
+                    .bucket(self.bucket.as_str())
+                    .key(name)
+                    .presigned(config)
+                    .await?
+            }
+            PresignMethod::Put => {
+                self.client
+                    .put_object()
+                    .bucket(self.bucket.as_str())
+                    .key(name)
+                    .presigned(config)
+                    .await?
+            }
+        };
+        Ok(request.uri().to_owned())
+    }
+}
+
+/// Reads up to [`PART_SIZE`] bytes, returning fewer only at end of stream.
+async fn read_chunk(data: &mut ObjectReader) -> Result<Vec<u8>> {
+    let mut chunk = vec![0; PART_SIZE];
+    let mut len = 0;
+    while len < PART_SIZE {
+        let n = data.read(&mut chunk[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    chunk.truncate(len);
+    Ok(chunk)
+}
+
+/// Lists every object name in the bucket, fetching pages as the stream is consumed.
+fn list_object_names(
+    client: Client,
+    bucket: Arc<String>,
+) -> impl futures::Stream<Item = Result<String>> + Send + 'static {
+    futures::stream::try_unfold(Some(None), move |token: Option<Option<String>>| {
+        let client = client.clone();
+        let bucket = bucket.clone();
+        async move {
+            let Some(token) = token else {
+                return Ok(None);
+            };
+            let page = client
+                .list_objects_v2()
+                .bucket(bucket.as_str())
+                .set_continuation_token(token)
+                .send()
+                .await?;
+            let names: Vec<String> = page
+                .contents()
+                .iter()
+                .filter_map(|object| object.key().map(str::to_owned))
+                .collect();
+            let next = page.next_continuation_token().map(|t| Some(t.to_owned()));
+            Ok::<_, anyhow::Error>(Some((names, next)))
+        }
+    })
+    .map_ok(|names| futures::stream::iter(names.into_iter().map(Ok)))
+    .try_flatten()
+}
@@ -0,0 +1,359 @@
+//! Caching of component builds.
+//!
+//! A component's build is keyed on a hash of its build commands and of the
+//! files matched by its `watch` patterns, which are its sources. When a build
+//! succeeds, the Wasm file it produced is stored under that key, so that a
+//! later build with the same sources can restore it instead of rebuilding.
+//! Components without `watch` patterns or a local source are never cached,
+//! because there is no way to tell whether they have changed.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use spin_common::{sha256, ui::quoted_path};
+
+use crate::{construct_workdir, manifest::ComponentBuildInfo};
+
+/// Bumped whenever the way keys are computed changes.
+const KEY_VERSION: &str = "spin-build-cache-v1";
+
+/// The environment variable holding a bearer token for an HTTP remote cache.
+pub const REMOTE_CACHE_TOKEN_ENV: &str = "SPIN_BUILD_CACHE_TOKEN";
+
+/// Where built components are cached.
+pub struct BuildCache {
+    dir: PathBuf,
+    remote: Option<RemoteCache>,
+    push: bool,
+}
+
+impl BuildCache {
+    /// Creates a cache in the given local directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            remote: None,
+            push: false,
+        }
+    }
+
+    /// Also fetches components missing from the local cache from a shared
+    /// remote cache, and if `push` is set, uploads components built locally.
+    pub fn with_remote(self, remote: RemoteCache, push: bool) -> Self {
+        Self {
+            remote: Some(remote),
+            push,
+            ..self
+        }
+    }
+
+    /// Puts the component built from the sources with the given key at
+    /// `output`, if it is cached. Returns whether it was.
+    pub(crate) async fn restore(&self, key: &str, output: &Path) -> Result<bool> {
+        let local = self.local_path(key);
+        if !local.exists() {
+            let Some(remote) = &self.remote else {
+                return Ok(false);
+            };
+            let content = match remote.get(key).await {
+                Ok(Some(content)) => content,
+                Ok(None) => return Ok(false),
+                Err(e) => {
+                    terminal::warn!("Couldn't read from the remote build cache: {e:#}");
+                    return Ok(false);
+                }
+            };
+            self.write_local(key, &content).await?;
+        }
+
+        if output.exists()
+            && sha256::hex_digest_from_file(output)? == sha256::hex_digest_from_file(&local)?
+        {
+            return Ok(true);
+        }
+        if let Some(parent) = output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(&local, output)
+            .await
+            .with_context(|| format!("Failed to restore {} from cache", quoted_path(output)))?;
+        Ok(true)
+    }
+
+    /// Caches the component built at `output` under the given key.
+    pub(crate) async fn store(&self, key: &str, output: &Path) -> Result<()> {
+        let content = tokio::fs::read(output)
+            .await
+            .with_context(|| format!("Failed to read build output {}", quoted_path(output)))?;
+        self.write_local(key, &content).await?;
+        if let (Some(remote), true) = (&self.remote, self.push) {
+            if let Err(e) = remote.put(key, content).await {
+                terminal::warn!("Couldn't upload to the remote build cache: {e:#}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_local(&self, key: &str, content: &[u8]) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create build cache {}", quoted_path(&self.dir)))?;
+        // Write then rename so that an interrupted write can't leave a
+        // partial entry behind.
+        let temp_path = self.dir.join(format!("{key}.partial"));
+        tokio::fs::write(&temp_path, content).await?;
+        tokio::fs::rename(&temp_path, self.local_path(key)).await?;
+        Ok(())
+    }
+
+    fn local_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.wasm"))
+    }
+}
+
+/// A cache shared between machines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteCache {
+    /// Entries are fetched with `GET <url>/<key>.wasm` and uploaded with `PUT`.
+    Http(url::Url),
+    /// Entries are objects in an S3 bucket, accessed with the credentials
+    /// from the environment.
+    S3 {
+        /// The bucket.
+        bucket: String,
+        /// The prefix of the entries' object keys.
+        prefix: String,
+    },
+}
+
+impl FromStr for RemoteCache {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let url = url::Url::parse(s).with_context(|| format!("Invalid remote cache URL {s:?}"))?;
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Http(url)),
+            "s3" => {
+                let Some(bucket) = url.host_str() else {
+                    bail!("S3 remote cache URL {s:?} must be of the form s3://bucket/prefix");
+                };
+                Ok(Self::S3 {
+                    bucket: bucket.to_owned(),
+                    prefix: url.path().trim_matches('/').to_owned(),
+                })
+            }
+            scheme => bail!("Unsupported remote cache scheme {scheme:?}: use http(s) or s3"),
+        }
+    }
+}
+
+impl RemoteCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Http(url) => {
+                let response = http_request(reqwest::Method::GET, url, key)?.send().await?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+            }
+            Self::S3 { bucket, prefix } => {
+                let result = s3_client()
+                    .await
+                    .get_object()
+                    .bucket(bucket)
+                    .key(s3_key(prefix, key))
+                    .send()
+                    .await;
+                match result {
+                    Ok(output) => Ok(Some(output.body.collect().await?.to_vec())),
+                    Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<()> {
+        match self {
+            Self::Http(url) => {
+                http_request(reqwest::Method::PUT, url, key)?
+                    .body(content)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Self::S3 { bucket, prefix } => {
+                s3_client()
+                    .await
+                    .put_object()
+                    .bucket(bucket)
+                    .key(s3_key(prefix, key))
+                    .body(content.into())
+                    .send()
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn http_request(
+    method: reqwest::Method,
+    base: &url::Url,
+    key: &str,
+) -> Result<reqwest::RequestBuilder> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    let url = base.join(&format!("{key}.wasm"))?;
+    let mut request = reqwest::Client::new().request(method, url);
+    if let Ok(token) = std::env::var(REMOTE_CACHE_TOKEN_ENV) {
+        request = request.bearer_auth(token);
+    }
+    Ok(request)
+}
+
+async fn s3_client() -> aws_sdk_s3::Client {
+    let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        .load()
+        .await;
+    aws_sdk_s3::Client::new(&config)
+}
+
+fn s3_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        format!("{key}.wasm")
+    } else {
+        format!("{prefix}/{key}.wasm")
+    }
+}
+
+/// Computes the key of a component's build from its build configuration and
+/// sources, or `None` if the component can't be cached.
+pub(crate) fn cache_key(build_info: &ComponentBuildInfo, app_dir: &Path) -> Result<Option<String>> {
+    let (Some(build), Some(source)) = (&build_info.build, &build_info.source) else {
+        return Ok(None);
+    };
+    if build.watch.is_empty() {
+        return Ok(None);
+    }
+    let workdir = construct_workdir(app_dir, build.workdir.as_ref())?;
+
+    let mut sources = vec![];
+    for pattern in &build.watch {
+        let full_pattern = workdir.join(pattern);
+        let full_pattern = full_pattern
+            .to_str()
+            .with_context(|| format!("Watch pattern {pattern:?} is not valid UTF-8"))?;
+        for path in glob::glob(full_pattern)
+            .with_context(|| format!("Invalid watch pattern {pattern:?}"))?
+        {
+            let path = path?;
+            if path.is_file() {
+                sources.push(path);
+            }
+        }
+    }
+    sources.sort();
+    sources.dedup();
+
+    let mut material = format!("{KEY_VERSION}\n{}\n{source}\n", build_info.id);
+    for command in build.commands() {
+        material.push_str(&format!("command {command}\n"));
+    }
+    if let Some(workdir) = &build.workdir {
+        material.push_str(&format!("workdir {workdir}\n"));
+    }
+    for path in sources {
+        let relative = path.strip_prefix(&workdir).unwrap_or(&path);
+        let digest = sha256::hex_digest_from_file(&path)
+            .with_context(|| format!("Failed to hash {}", quoted_path(&path)))?;
+        // Use forward slashes so that keys are the same on all platforms.
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        material.push_str(&format!("file {relative} {digest}\n"));
+    }
+    Ok(Some(sha256::hex_digest_from_bytes(material)))
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_manifest::schema::v2::ComponentBuildConfig;
+
+    use super::*;
+
+    fn component(watch: &[&str]) -> ComponentBuildInfo {
+        let build: ComponentBuildConfig =
+            toml::from_str(&format!("command = \"make\"\nwatch = {watch:?}")).unwrap();
+        ComponentBuildInfo {
+            id: "hello".into(),
+            build: Some(build),
+            source: Some("hello.wasm".into()),
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn key_depends_on_sources() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("src"))?;
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() {}")?;
+        std::fs::write(dir.path().join("README.md"), "hello")?;
+
+        let component = component(&["src/**/*.rs"]);
+        let key = cache_key(&component, dir.path())?.unwrap();
+        assert_eq!(Some(&key), cache_key(&component, dir.path())?.as_ref());
+
+        // Files not matched by the watch patterns don't affect the key
+        std::fs::write(dir.path().join("README.md"), "goodbye")?;
+        assert_eq!(Some(&key), cache_key(&component, dir.path())?.as_ref());
+
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() { }")?;
+        assert_ne!(Some(&key), cache_key(&component, dir.path())?.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn components_without_watch_patterns_are_not_cached() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(None, cache_key(&component(&[]), dir.path())?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn builds_are_restored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = BuildCache::new(dir.path().join("cache"));
+        let output = dir.path().join("out/hello.wasm");
+        assert!(!cache.restore("abc", &output).await?);
+
+        tokio::fs::create_dir_all(output.parent().unwrap()).await?;
+        tokio::fs::write(&output, b"wasm").await?;
+        cache.store("abc", &output).await?;
+
+        tokio::fs::remove_file(&output).await?;
+        assert!(cache.restore("abc", &output).await?);
+        assert_eq!(b"wasm".to_vec(), tokio::fs::read(&output).await?);
+        Ok(())
+    }
+
+    #[test]
+    fn remote_caches_are_parsed() {
+        assert_eq!(
+            RemoteCache::S3 {
+                bucket: "builds".into(),
+                prefix: "team/app".into()
+            },
+            "s3://builds/team/app/".parse().unwrap()
+        );
+        assert!(matches!(
+            "https://cache.example.com/spin".parse().unwrap(),
+            RemoteCache::Http(_)
+        ));
+        assert!("ftp://cache.example.com".parse::<RemoteCache>().is_err());
+    }
+}
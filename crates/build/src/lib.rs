@@ -2,21 +2,55 @@
 
 //! A library for building Spin components.
 
+pub mod cache;
 mod manifest;
 
 use anyhow::{anyhow, bail, Context, Result};
+use futures::{stream::FuturesUnordered, StreamExt};
 use manifest::ComponentBuildInfo;
 use spin_common::{paths::parent_dir, ui::quoted_path};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
 };
-use subprocess::{Exec, Redirection};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+};
+
+use crate::{cache::BuildCache, manifest::component_build_configs};
 
-use crate::manifest::component_build_configs;
+/// Options for building an application.
+pub struct BuildOptions {
+    /// Where to cache built components, if anywhere.
+    pub cache: Option<BuildCache>,
+    /// The most components to build at once. Components are only built once
+    /// the components they depend on have been.
+    pub jobs: usize,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            jobs: 1,
+        }
+    }
+}
 
 /// If present, run the build command of each component.
 pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()> {
+    build_with_options(manifest_file, component_ids, &BuildOptions::default()).await
+}
+
+/// If present, run the build command of each component, skipping components
+/// whose sources are unchanged if a cache is configured.
+pub async fn build_with_options(
+    manifest_file: &Path,
+    component_ids: &[String],
+    options: &BuildOptions,
+) -> Result<()> {
     let (components, manifest_err) =
         component_build_configs(manifest_file)
             .await
@@ -28,7 +62,7 @@ pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()>
             })?;
     let app_dir = parent_dir(manifest_file)?;
 
-    let build_result = build_components(component_ids, components, app_dir);
+    let build_result = build_components(component_ids, components, app_dir, options).await;
 
     if let Some(e) = manifest_err {
         terminal::warn!("The manifest has errors not related to the Wasm component build. Error details:\n{e:#}");
@@ -37,10 +71,11 @@ pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()>
     build_result
 }
 
-fn build_components(
+async fn build_components(
     component_ids: &[String],
     components: Vec<ComponentBuildInfo>,
     app_dir: PathBuf,
+    options: &BuildOptions,
 ) -> Result<(), anyhow::Error> {
     let components_to_build = if component_ids.is_empty() {
         components
@@ -68,17 +103,153 @@ fn build_components(
         return Ok(());
     }
 
-    components_to_build
-        .into_iter()
-        .map(|c| build_component(c, &app_dir))
-        .collect::<Result<Vec<_>, _>>()?;
+    let dependencies = build_dependencies(&components_to_build)?;
+    let output = if options.jobs > 1 {
+        Output::Prefixed
+    } else {
+        Output::Inherit
+    };
+
+    // Start each component once the components it depends on are built, with
+    // at most `jobs` building at once. After a failure, no more are started,
+    // but those already building are allowed to finish.
+    let mut pending = components_to_build;
+    let mut built = HashSet::new();
+    let mut building = FuturesUnordered::new();
+    let mut failure = None;
+    loop {
+        while failure.is_none() && building.len() < options.jobs.max(1) {
+            let Some(index) = pending
+                .iter()
+                .position(|c| dependencies[&c.id].iter().all(|d| built.contains(d)))
+            else {
+                break;
+            };
+            let component = pending.remove(index);
+            let app_dir = &app_dir;
+            building.push(async move {
+                let id = component.id.clone();
+                let result = match &options.cache {
+                    Some(cache) => build_component_cached(component, app_dir, cache, output).await,
+                    None => build_component(component, app_dir, output).await,
+                };
+                (id, result)
+            });
+        }
+        match building.next().await {
+            Some((id, Ok(()))) => {
+                built.insert(id);
+            }
+            Some((_, Err(e))) => {
+                failure.get_or_insert(e);
+            }
+            None => break,
+        }
+    }
+    if let Some(e) = failure {
+        return Err(e);
+    }
 
     terminal::step!("Finished", "building all Spin components");
     Ok(())
 }
 
+/// Where the output of build commands goes.
+#[derive(Clone, Copy)]
+enum Output {
+    /// Build commands write straight to the terminal.
+    Inherit,
+    /// Each line of output is prefixed with the component ID, so that the
+    /// output of concurrent builds can be told apart.
+    Prefixed,
+}
+
+/// Maps the ID of each component to the IDs of the components which must be
+/// built before it: those named in its `depends_on`, and those whose output
+/// it is composed with. Only components that are being built are included.
+fn build_dependencies(components: &[ComponentBuildInfo]) -> Result<HashMap<String, Vec<String>>> {
+    let ids: HashSet<_> = components.iter().map(|c| c.id.as_str()).collect();
+    let mut dependencies = HashMap::new();
+    for component in components {
+        let declared = component.build.iter().flat_map(|b| &b.depends_on);
+        let mut component_dependencies = vec![];
+        for dependency in declared.chain(&component.dependencies) {
+            if dependency == &component.id {
+                bail!("Component {} cannot depend on itself", component.id);
+            }
+            if ids.contains(dependency.as_str()) && !component_dependencies.contains(dependency) {
+                component_dependencies.push(dependency.clone());
+            }
+        }
+        dependencies.insert(component.id.clone(), component_dependencies);
+    }
+
+    // Check for cycles by repeatedly removing components with no unremoved
+    // dependencies: any left over are in a cycle.
+    let mut remaining: HashSet<_> = dependencies.keys().map(String::as_str).collect();
+    loop {
+        let ready: Vec<_> = remaining
+            .iter()
+            .copied()
+            .filter(|id| {
+                dependencies[*id]
+                    .iter()
+                    .all(|d| !remaining.contains(d.as_str()))
+            })
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for id in ready {
+            remaining.remove(id);
+        }
+    }
+    if !remaining.is_empty() {
+        let mut cycle: Vec<_> = remaining.into_iter().collect();
+        cycle.sort();
+        bail!(
+            "The build dependencies of components {} form a cycle",
+            cycle.join(", ")
+        );
+    }
+
+    Ok(dependencies)
+}
+
+/// Run the build command of the component, unless the output of a build
+/// from the same sources is cached.
+async fn build_component_cached(
+    build_info: ComponentBuildInfo,
+    app_dir: &Path,
+    cache: &BuildCache,
+    output: Output,
+) -> Result<()> {
+    let key = cache::cache_key(&build_info, app_dir)
+        .with_context(|| format!("Failed to hash sources of component {}", build_info.id))?;
+    let (Some(key), Some(source)) = (key, build_info.source.clone()) else {
+        return build_component(build_info, app_dir, output).await;
+    };
+    let wasm_path = app_dir.join(source);
+    if cache.restore(&key, &wasm_path).await? {
+        terminal::step!("Cached", "component {} is up to date", build_info.id);
+        return Ok(());
+    }
+    let id = build_info.id.clone();
+    build_component(build_info, app_dir, output).await?;
+    if wasm_path.exists() {
+        cache.store(&key, &wasm_path).await?;
+    } else {
+        tracing::debug!("Not caching component {id}: it has no output at {wasm_path:?}");
+    }
+    Ok(())
+}
+
 /// Run the build command of the component.
-fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()> {
+async fn build_component(
+    build_info: ComponentBuildInfo,
+    app_dir: &Path,
+    output: Output,
+) -> Result<()> {
     match build_info.build {
         Some(b) => {
             let command_count = b.commands().len();
@@ -111,12 +282,8 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                     println!("Working directory: {}", quoted_path(&workdir));
                 }
 
-                let exit_status = Exec::shell(command)
-                    .cwd(workdir)
-                    .stdout(Redirection::None)
-                    .stderr(Redirection::None)
-                    .stdin(Redirection::None)
-                    .popen()
+                let exit_status = run_command(&build_info.id, command, &workdir, output)
+                    .await
                     .map_err(|err| {
                         anyhow!(
                             "Cannot spawn build process '{:?}' for component {}: {}",
@@ -124,8 +291,7 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                             build_info.id,
                             err
                         )
-                    })?
-                    .wait()?;
+                    })?;
 
                 if !exit_status.success() {
                     bail!(
@@ -142,6 +308,67 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
     }
 }
 
+/// Runs a build command in the shell, waiting for it to finish.
+async fn run_command(
+    id: &str,
+    command: &str,
+    workdir: &Path,
+    output: Output,
+) -> std::io::Result<ExitStatus> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd.exe");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.current_dir(workdir);
+
+    match output {
+        Output::Inherit => cmd.status().await,
+        Output::Prefixed => {
+            // Concurrent builds can't share the terminal's input.
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            let mut child = cmd.spawn()?;
+            let stdout = child.stdout.take().expect("stdout should be piped");
+            let stderr = child.stderr.take().expect("stderr should be piped");
+            let (status, (), ()) = tokio::try_join!(
+                child.wait(),
+                print_prefixed(id, stdout, false),
+                print_prefixed(id, stderr, true),
+            )?;
+            Ok(status)
+        }
+    }
+}
+
+/// Prints each line read from `reader`, prefixed with the component ID.
+async fn print_prefixed(
+    id: &str,
+    reader: impl AsyncRead + Unpin,
+    to_stderr: bool,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = vec![];
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\r', '\n']);
+        if to_stderr {
+            eprintln!("[{id}] {text}");
+        } else {
+            println!("[{id}] {text}");
+        }
+    }
+}
+
 /// Constructs the absolute working directory in which to run the build command.
 fn construct_workdir(app_dir: &Path, workdir: Option<impl AsRef<Path>>) -> Result<PathBuf> {
     let mut cwd = app_dir.to_owned();
@@ -173,4 +400,72 @@ mod tests {
         let bad_trigger_file = test_data_root().join("bad_trigger.toml");
         build(&bad_trigger_file, &[]).await.unwrap();
     }
+
+    fn component(id: &str, depends_on: &[&str]) -> ComponentBuildInfo {
+        let build =
+            toml::from_str(&format!("command = \"make\"\ndepends_on = {depends_on:?}")).unwrap();
+        ComponentBuildInfo {
+            id: id.into(),
+            build: Some(build),
+            source: None,
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn build_dependencies_are_resolved() {
+        let mut composed = component("composed", &["lib"]);
+        composed.dependencies = vec!["helper".into(), "not-being-built".into()];
+        let dependencies =
+            build_dependencies(&[component("lib", &[]), component("helper", &[]), composed])
+                .unwrap();
+        assert!(dependencies["lib"].is_empty());
+        assert_eq!(vec!["lib", "helper"], dependencies["composed"]);
+    }
+
+    #[test]
+    fn build_dependency_cycles_are_rejected() {
+        let err = build_dependencies(&[
+            component("a", &["b"]),
+            component("b", &["c"]),
+            component("c", &["a"]),
+            component("d", &[]),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("a, b, c"), "{err}");
+
+        assert!(build_dependencies(&[component("a", &["a"])]).is_err());
+    }
+
+    #[tokio::test]
+    async fn dependencies_are_built_first() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("log");
+        let mut components = vec![];
+        for (id, depends_on) in [
+            ("app", &["lib-a", "lib-b"][..]),
+            ("lib-a", &[]),
+            ("lib-b", &[]),
+        ] {
+            let build = toml::from_str(&format!(
+                "command = \"echo {id} >> log\"\ndepends_on = {depends_on:?}"
+            ))?;
+            components.push(ComponentBuildInfo {
+                id: id.into(),
+                build: Some(build),
+                source: None,
+                dependencies: vec![],
+            });
+        }
+        let options = BuildOptions {
+            jobs: 4,
+            ..Default::default()
+        };
+        build_components(&[], components, dir.path().to_owned(), &options).await?;
+
+        let log = std::fs::read_to_string(log)?;
+        assert_eq!(Some("app"), log.lines().last());
+        assert_eq!(3, log.lines().count());
+        Ok(())
+    }
 }
@@ -31,6 +31,21 @@ fn build_configs_from_manifest(
         .map(|(id, c)| ComponentBuildInfo {
             id: id.to_string(),
             build: c.build,
+            source: match c.source {
+                v2::ComponentSource::Local(path) => Some(path),
+                _ => None,
+            },
+            dependencies: c
+                .dependencies
+                .inner
+                .into_values()
+                .filter_map(|dependency| match dependency {
+                    v2::ComponentDependency::AppComponent { component, .. } => {
+                        Some(component.to_string())
+                    }
+                    _ => None,
+                })
+                .collect(),
         })
         .collect()
 }
@@ -62,6 +77,14 @@ pub struct ComponentBuildInfo {
     #[serde(default)]
     pub id: String,
     pub build: Option<v2::ComponentBuildConfig>,
+    /// The path of the Wasm file the build produces, if it is local. This is
+    /// only known if the manifest could be fully loaded.
+    #[serde(skip)]
+    pub source: Option<String>,
+    /// The IDs of components in the app whose output this component is
+    /// composed with. As with `source`, only known from a fully loaded manifest.
+    #[serde(skip)]
+    pub dependencies: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -13,5 +13,6 @@ pub mod data_dir;
 pub mod paths;
 pub mod sha256;
 pub mod sloth;
+pub mod time;
 pub mod ui;
 pub mod url;
@@ -0,0 +1,43 @@
+//! Wall-clock time
+//!
+//! Times which are stored, or compared across processes and hosts (e.g.
+//! expiry times), have to use the wall clock rather than a monotonic one.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Return the whole seconds since the Unix epoch.
+pub fn now_secs() -> u64 {
+    since_epoch().as_secs()
+}
+
+/// Return the whole milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    since_epoch().as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Return the whole microseconds since the Unix epoch.
+pub fn now_micros() -> u64 {
+    since_epoch().as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+/// A clock set before the epoch reads as the epoch.
+fn since_epoch() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_agree() {
+        let secs = now_secs();
+        let millis = now_millis();
+        let micros = now_micros();
+        assert!(millis / 1000 >= secs);
+        assert!(micros / 1000 >= millis);
+        assert!(secs > 0);
+    }
+}
@@ -4,7 +4,7 @@ use semver::Version;
 use spin_app::locked::{self, InheritConfiguration, LockedComponent, LockedComponentDependency};
 use spin_common::{ui::quoted_path, url::parse_file_url};
 use spin_serde::{DependencyName, KebabId};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use thiserror::Error;
 use wac_graph::types::{Package, SubtypeChecker, WorldId};
 use wac_graph::{CompositionGraph, NodeId};
@@ -34,6 +34,107 @@ pub async fn compose<L: ComponentSourceLoader>(
     Composer::new(loader).compose(component).await
 }
 
+/// Composes a component with middleware components which wrap it, given
+/// outermost first.
+///
+/// Each middleware's imports are satisfied by the exports of the component it
+/// wraps (the next middleware in the list, or finally the component itself)
+/// where the names match; a middleware that satisfies none of its imports
+/// this way is an error. The composed component exports everything the
+/// outermost middleware exports, plus any exports of the wrapped components
+/// that no middleware overrides. Imports which are not satisfied by a
+/// wrapped component become imports of the composed component.
+pub fn compose_middleware(
+    component: Vec<u8>,
+    middleware: Vec<Vec<u8>>,
+) -> Result<Vec<u8>, ComposeError> {
+    let mut graph = CompositionGraph::new();
+
+    let (world_id, instantiation_id) =
+        register_middleware_package(&mut graph, "component", component)
+            .map_err(ComposeError::PrepareError)?;
+    // Which instantiation provides each export, innermost first so that
+    // outer layers override the exports they wrap.
+    let mut exports = graph.types()[world_id]
+        .exports
+        .keys()
+        .map(|name| (name.clone(), instantiation_id))
+        .collect::<IndexMap<_, _>>();
+    // An instance export may both satisfy a middleware import and be
+    // exported from the composition, but must only be aliased once.
+    let mut aliases = HashMap::new();
+
+    for (index, source) in middleware.into_iter().enumerate().rev() {
+        let (world_id, instantiation_id) =
+            register_middleware_package(&mut graph, &format!("middleware-{index}"), source)
+                .map_err(ComposeError::PrepareError)?;
+
+        let import_names = graph.types()[world_id]
+            .imports
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut wraps = false;
+        for import_name in import_names {
+            let Some(&provider_id) = exports.get(&import_name) else {
+                continue;
+            };
+            let export_id = alias_export(&mut graph, &mut aliases, provider_id, &import_name)
+                .map_err(ComposeError::PrepareError)?;
+            graph
+                .set_instantiation_argument(instantiation_id, &import_name, export_id)
+                .with_context(|| format!("middleware[{index}] cannot wrap '{import_name}'"))
+                .map_err(ComposeError::PrepareError)?;
+            wraps = true;
+        }
+        if !wraps {
+            return Err(ComposeError::UnmatchedMiddleware { index });
+        }
+
+        for export_name in graph.types()[world_id].exports.keys() {
+            exports.insert(export_name.clone(), instantiation_id);
+        }
+    }
+
+    for (export_name, provider_id) in exports {
+        let export_id = alias_export(&mut graph, &mut aliases, provider_id, &export_name)
+            .map_err(ComposeError::PrepareError)?;
+        graph
+            .export(export_id, &export_name)
+            .map_err(|e| ComposeError::PrepareError(e.into()))?;
+    }
+
+    graph
+        .encode(Default::default())
+        .map_err(|e| ComposeError::EncodeError(e.into()))
+}
+
+fn alias_export(
+    graph: &mut CompositionGraph,
+    aliases: &mut HashMap<(NodeId, String), NodeId>,
+    instantiation_id: NodeId,
+    export_name: &str,
+) -> anyhow::Result<NodeId> {
+    let key = (instantiation_id, export_name.to_owned());
+    if let Some(&export_id) = aliases.get(&key) {
+        return Ok(export_id);
+    }
+    let export_id = graph.alias_instance_export(instantiation_id, export_name)?;
+    aliases.insert(key, export_id);
+    Ok(export_id)
+}
+
+fn register_middleware_package(
+    graph: &mut CompositionGraph,
+    name: &str,
+    source: Vec<u8>,
+) -> anyhow::Result<(WorldId, NodeId)> {
+    let package = Package::from_bytes(name, None, source, graph.types_mut())?;
+    let world_id = package.ty();
+    let package_id = graph.register_package(package)?;
+    Ok((world_id, graph.instantiate(package_id)))
+}
+
 /// This trait is used to load component source code from a locked component source across various embdeddings.
 #[async_trait::async_trait]
 pub trait ComponentSourceLoader {
@@ -97,6 +198,10 @@ pub enum ComposeError {
         export_name: String,
         import_name: String,
     },
+    /// A middleware component doesn't import anything that the component it
+    /// wraps exports.
+    #[error("middleware[{index}] doesn't import any interface exported by the component it wraps")]
+    UnmatchedMiddleware { index: usize },
     /// An error occurred when building the composition graph
     #[error("an error occurred when preparing dependencies")]
     PrepareError(#[source] anyhow::Error),
@@ -575,4 +680,136 @@ mod test {
             }
         }
     }
+
+    /// A component exporting `test:app/handler`, whose `handle` returns
+    /// `value`.
+    fn handler(value: u32) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(component
+                (core module $m
+                    (func (export "handle") (result i32) i32.const {value}))
+                (core instance $i (instantiate $m))
+                (func $handle (result u32) (canon lift (core func $i "handle")))
+                (instance $handler (export "handle" (func $handle)))
+                (export "test:app/handler" (instance $handler))
+            )"#
+        ))
+        .unwrap()
+    }
+
+    /// Middleware wrapping `test:app/handler`, which applies the core
+    /// instructions `op` to the result of the `handle` it wraps.
+    fn middleware(op: &str) -> Vec<u8> {
+        middleware_with_imports(op, "")
+    }
+
+    fn middleware_with_imports(op: &str, imports: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(component
+                {imports}
+                (import "test:app/handler" (instance $inner
+                    (export "handle" (func (result u32)))))
+                (core func $inner-handle (canon lower (func $inner "handle")))
+                (core instance $inner-core (export "handle" (func $inner-handle)))
+                (core module $m
+                    (import "inner" "handle" (func $handle (result i32)))
+                    (func (export "handle") (result i32)
+                        call $handle
+                        {op}))
+                (core instance $i (instantiate $m (with "inner" (instance $inner-core))))
+                (func $handle (result u32) (canon lift (core func $i "handle")))
+                (instance $handler (export "handle" (func $handle)))
+                (export "test:app/handler" (instance $handler))
+            )"#
+        ))
+        .unwrap()
+    }
+
+    /// Calls `handle` on the `test:app/handler` export of `component`.
+    fn call_handler(component: &[u8]) -> u32 {
+        use wasmtime::component::{Component, Linker};
+
+        let engine = wasmtime::Engine::default();
+        let component = Component::new(&engine, component).unwrap();
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &component)
+            .unwrap();
+        let handler = instance
+            .get_export_index(&mut store, None, "test:app/handler")
+            .unwrap();
+        let handle = instance
+            .get_export_index(&mut store, Some(&handler), "handle")
+            .unwrap();
+        let handle = instance
+            .get_typed_func::<(), (u32,)>(&mut store, &handle)
+            .unwrap();
+        handle.call(&mut store, ()).unwrap().0
+    }
+
+    /// Returns the names of the imports and exports of `component`.
+    fn imports_and_exports(component: Vec<u8>) -> (Vec<String>, Vec<String>) {
+        let mut graph = CompositionGraph::new();
+        let package = Package::from_bytes("composed", None, component, graph.types_mut()).unwrap();
+        let world = &graph.types()[package.ty()];
+        (
+            world.imports.keys().cloned().collect(),
+            world.exports.keys().cloned().collect(),
+        )
+    }
+
+    #[test]
+    fn middleware_wraps_component_outermost_first() {
+        let add_ten = middleware("i32.const 10 i32.add");
+        let double = middleware("i32.const 2 i32.mul");
+
+        let composed = compose_middleware(handler(1), vec![add_ten.clone(), double.clone()]);
+        assert_eq!(call_handler(&composed.unwrap()), 12);
+
+        let composed = compose_middleware(handler(1), vec![double, add_ten]);
+        assert_eq!(call_handler(&composed.unwrap()), 22);
+    }
+
+    #[test]
+    fn unwrapped_imports_and_exports_are_kept() {
+        let component = wat::parse_str(
+            r#"(component
+                (core module $m
+                    (func (export "handle") (result i32) i32.const 1)
+                    (func (export "version") (result i32) i32.const 2))
+                (core instance $i (instantiate $m))
+                (func $handle (result u32) (canon lift (core func $i "handle")))
+                (func $version (result u32) (canon lift (core func $i "version")))
+                (instance $handler (export "handle" (func $handle)))
+                (instance $info (export "version" (func $version)))
+                (export "test:app/handler" (instance $handler))
+                (export "test:app/info" (instance $info))
+            )"#,
+        )
+        .unwrap();
+        let middleware = middleware_with_imports(
+            "",
+            r#"(import "test:app/config" (instance (export "get" (func (result u32)))))"#,
+        );
+
+        let composed = compose_middleware(component, vec![middleware]).unwrap();
+        let (imports, exports) = imports_and_exports(composed);
+        assert_eq!(imports, ["test:app/config"]);
+        assert_eq!(exports, ["test:app/handler", "test:app/info"]);
+    }
+
+    #[test]
+    fn middleware_which_wraps_nothing_is_an_error() {
+        let unrelated = wat::parse_str(
+            r#"(component
+                (import "test:app/other" (instance (export "get" (func (result u32)))))
+            )"#,
+        )
+        .unwrap();
+        let res = compose_middleware(handler(1), vec![middleware(""), unrelated]);
+        assert!(
+            matches!(res, Err(ComposeError::UnmatchedMiddleware { index: 1 })),
+            "{res:?}"
+        );
+    }
 }
@@ -25,7 +25,8 @@ pub use wasmtime::{
     Instance as ModuleInstance, Module, Trap,
 };
 
-pub use store::{AsState, Store, StoreBuilder};
+pub use limits::LimitExceeded;
+pub use store::{AsState, Interrupted, Store, StoreBuilder, StoreInterrupt};
 
 /// The default [`EngineBuilder::epoch_tick_interval`].
 pub const DEFAULT_EPOCH_TICK_INTERVAL: Duration = Duration::from_millis(10);
@@ -61,6 +62,30 @@ impl Config {
         Ok(())
     }
 
+    /// Enable fuel metering, which [`StoreBuilder::fuel`] requires.
+    ///
+    /// This slows down all Wasm execution, so should only be enabled if fuel
+    /// limits are used.
+    pub fn consume_fuel(&mut self, enable: bool) -> &mut Self {
+        self.inner.consume_fuel(enable);
+        self
+    }
+
+    /// Make guest code debuggable with a native debugger such as LLDB or GDB.
+    ///
+    /// Guests' DWARF debug info is translated to describe their compiled
+    /// code, which is registered with debuggers attached to this process,
+    /// and optimizations are disabled so that variables can be inspected.
+    /// Disabling only turns off debug info, leaving the optimization level
+    /// as it was configured.
+    pub fn debug_guests(&mut self, enable: bool) -> &mut Self {
+        self.inner.debug_info(enable);
+        if enable {
+            self.inner.cranelift_opt_level(wasmtime::OptLevel::None);
+        }
+        self
+    }
+
     /// Disable the pooling instance allocator.
     pub fn disable_pooling(&mut self) -> &mut Self {
         self.inner
@@ -201,6 +226,8 @@ fn use_pooling_allocator_by_default() -> bool {
 #[derive(Default)]
 pub struct State {
     store_limits: limits::StoreLimitsAsync,
+    /// Values that live as long as the store; see [`StoreBuilder::keep_alive`].
+    keep_alive: Vec<Box<dyn std::any::Any + Send>>,
 }
 
 impl State {
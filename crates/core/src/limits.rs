@@ -1,6 +1,6 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use wasmtime::ResourceLimiterAsync;
+use wasmtime::{ResourceLimiterAsync, Trap};
 
 /// Async implementation of wasmtime's `StoreLimits`: https://github.com/bytecodealliance/wasmtime/blob/main/crates/wasmtime/src/limits.rs
 /// Used to limit the memory use and table size of each Instance
@@ -61,6 +61,37 @@ impl StoreLimitsAsync {
     }
 }
 
+/// A resource limit that stopped an instance from being created or from
+/// running to completion.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LimitExceeded {
+    /// The component already had its maximum number of concurrent instances.
+    #[error("the component has reached its limit of {0} concurrent instances")]
+    Instances(usize),
+    /// The instance ran for longer than its execution time limit.
+    #[error("the instance exceeded its execution time limit")]
+    ExecutionTime,
+    /// The instance used up its fuel.
+    #[error("the instance ran out of fuel")]
+    Fuel,
+}
+
+impl LimitExceeded {
+    /// Returns the limit that caused `err`, if any.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        err.chain().find_map(|cause| {
+            if let Some(limit) = cause.downcast_ref::<Self>() {
+                return Some(limit.clone());
+            }
+            match cause.downcast_ref::<Trap>()? {
+                Trap::Interrupt => Some(Self::ExecutionTime),
+                Trap::OutOfFuel => Some(Self::Fuel),
+                _ => None,
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,4 +117,19 @@ mod tests {
         assert!(limits.table_growing(9, 10, None).await.unwrap());
         assert!(!limits.table_growing(10, 11, None).await.unwrap());
     }
+
+    #[test]
+    fn limit_exceeded_from_error() {
+        let err = anyhow::Error::new(LimitExceeded::Instances(3)).context("instantiating");
+        assert_eq!(
+            LimitExceeded::from_error(&err),
+            Some(LimitExceeded::Instances(3))
+        );
+
+        let err = anyhow::Error::new(Trap::OutOfFuel).context("calling guest");
+        assert_eq!(LimitExceeded::from_error(&err), Some(LimitExceeded::Fuel));
+
+        let err = anyhow::Error::new(Trap::UnreachableCodeReached);
+        assert_eq!(LimitExceeded::from_error(&err), None);
+    }
 }
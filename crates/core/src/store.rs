@@ -1,5 +1,12 @@
 use anyhow::Result;
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use wasmtime::{Trap, UpdateDeadline, WasmBacktrace, WasmCoreDump};
 
 use crate::{limits::StoreLimitsAsync, State, WasmtimeEngine};
 
@@ -9,12 +16,20 @@ use crate::EngineBuilder;
 /// A `Store` holds the runtime state of a Spin instance.
 ///
 /// In general, a `Store` is expected to live only for the lifetime of a single
-/// Spin trigger invocation.
+/// Spin trigger invocation. A `Store` which is reused for another invocation
+/// should have its limits renewed with [`Store::renew_limits`].
 ///
 /// A `Store` can be built with a [`StoreBuilder`].
 pub struct Store<T> {
     inner: wasmtime::Store<T>,
     epoch_tick_interval: Duration,
+    fuel: Option<u64>,
+    execution_time_limit: Option<Duration>,
+    /// The execution deadline, if the guest is checked on every epoch tick
+    /// to sample stacks or for interruption; the epoch deadline is then used
+    /// for those checks instead.
+    tick_deadline: Option<Arc<Mutex<Option<Instant>>>>,
+    interrupt: Option<StoreInterrupt>,
 }
 
 impl<T> Store<T> {
@@ -26,6 +41,10 @@ impl<T> Store<T> {
     ///
     /// See [`wasmtime::Store::set_epoch_deadline`](https://docs.rs/wasmtime/latest/wasmtime/struct.Store.html#method.set_epoch_deadline).
     pub fn set_deadline(&mut self, deadline: Instant) {
+        if let Some(tick_deadline) = &self.tick_deadline {
+            *tick_deadline.lock().unwrap() = Some(deadline);
+            return;
+        }
         let now = Instant::now();
         let duration = deadline - now;
         let ticks = if duration.is_zero() {
@@ -39,6 +58,41 @@ impl<T> Store<T> {
         self.inner.set_epoch_deadline(ticks);
     }
 
+    /// Renews the store's fuel and execution deadline, as if it had just been
+    /// built, so that it can be used for another invocation.
+    pub fn renew_limits(&mut self) -> Result<()> {
+        if let Some(fuel) = self.fuel {
+            self.inner.set_fuel(fuel)?;
+        }
+        if let Some(limit) = self.execution_time_limit {
+            self.set_deadline(Instant::now() + limit);
+        }
+        Ok(())
+    }
+
+    /// Returns a handle which interrupts the guest, if the store was made
+    /// interruptible with [`StoreBuilder::interruptible`].
+    pub fn interrupt_handle(&self) -> Option<StoreInterrupt> {
+        self.interrupt.clone()
+    }
+
+    /// Returns the fuel consumed so far, if the store was given fuel with
+    /// [`StoreBuilder::fuel`].
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        let remaining = self.inner.get_fuel().ok()?;
+        Some(self.fuel?.saturating_sub(remaining))
+    }
+
+    /// Serializes the core dump taken when the guest trapped with `err`, if
+    /// any.
+    ///
+    /// Core dumps are only taken if enabled with
+    /// [`wasmtime::Config::coredump_on_trap`].
+    pub fn core_dump(&mut self, err: &anyhow::Error, name: &str) -> Option<Vec<u8>> {
+        let dump = err.downcast_ref::<WasmCoreDump>()?;
+        Some(dump.serialize(&mut self.inner, name))
+    }
+
     /// Provides access to the inner [`wasmtime::Store`]'s data.
     pub fn data(&self) -> &T {
         self.inner.data()
@@ -83,8 +137,15 @@ pub struct StoreBuilder {
     engine: WasmtimeEngine,
     epoch_tick_interval: Duration,
     store_limits: StoreLimitsAsync,
+    execution_time_limit: Option<Duration>,
+    fuel: Option<u64>,
+    stack_sampler: Option<StackSampler>,
+    interrupt: Option<StoreInterrupt>,
+    keep_alive: Vec<Box<dyn std::any::Any + Send>>,
 }
 
+type StackSampler = Box<dyn FnMut(&WasmBacktrace) + Send + Sync>;
+
 impl StoreBuilder {
     // Called by Engine::store_builder.
     pub(crate) fn new(engine: WasmtimeEngine, epoch_tick_interval: Duration) -> Self {
@@ -92,6 +153,11 @@ impl StoreBuilder {
             engine,
             epoch_tick_interval,
             store_limits: StoreLimitsAsync::default(),
+            execution_time_limit: None,
+            fuel: None,
+            stack_sampler: None,
+            interrupt: None,
+            keep_alive: Vec::new(),
         }
     }
 
@@ -103,12 +169,56 @@ impl StoreBuilder {
         self.store_limits = StoreLimitsAsync::new(Some(max_memory_size), None);
     }
 
+    /// Sets a limit on how long the built [`Store`] may execute, starting
+    /// when it is built.
+    ///
+    /// This is enforced with the same rough deadline as
+    /// [`Store::set_deadline`].
+    pub fn execution_time_limit(&mut self, limit: Duration) {
+        self.execution_time_limit = Some(limit);
+    }
+
+    /// Sets the amount of fuel the built [`Store`] may consume.
+    ///
+    /// This requires fuel metering to be enabled with
+    /// [`crate::Config::consume_fuel`]; building the store will fail
+    /// otherwise.
+    pub fn fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// Samples the guest's stack once every epoch tick while it executes,
+    /// passing each sample to `sampler`.
+    ///
+    /// See [`EngineBuilder::epoch_tick_interval`].
+    pub fn sample_stacks(&mut self, sampler: impl FnMut(&WasmBacktrace) + Send + Sync + 'static) {
+        self.stack_sampler = Some(Box::new(sampler));
+    }
+
+    /// Lets the guest in the built [`Store`] be interrupted with the returned
+    /// handle, which is also available from [`Store::interrupt_handle`].
+    ///
+    /// The guest is then checked for interruption once every epoch tick
+    /// while it executes. See [`EngineBuilder::epoch_tick_interval`].
+    pub fn interruptible(&mut self) -> StoreInterrupt {
+        self.interrupt.get_or_insert_with(Default::default).clone()
+    }
+
+    /// Keeps `value` alive until the built [`Store`] is dropped.
+    ///
+    /// This can be used to tie a guard (e.g. a semaphore permit) to the
+    /// lifetime of an instance.
+    pub fn keep_alive(&mut self, value: impl std::any::Any + Send) {
+        self.keep_alive.push(Box::new(value));
+    }
+
     /// Builds a [`Store`] from this builder with given host state data.
     ///
     /// The `T` parameter must provide access to a [`State`] via `impl
     /// AsMut<State>`.
     pub fn build<T: AsState>(self, mut data: T) -> Result<Store<T>> {
         data.as_state().store_limits = self.store_limits;
+        data.as_state().keep_alive = self.keep_alive;
 
         let mut inner = wasmtime::Store::new(&self.engine, data);
         inner.limiter_async(|data| &mut data.as_state().store_limits);
@@ -119,13 +229,76 @@ impl StoreBuilder {
         // forever" for any plausible tick interval.
         inner.set_epoch_deadline(u64::MAX / 2);
 
-        Ok(Store {
+        if let Some(fuel) = self.fuel {
+            inner.set_fuel(fuel)?;
+        }
+
+        let check_every_tick = self.stack_sampler.is_some() || self.interrupt.is_some();
+        let tick_deadline = check_every_tick.then(|| {
+            let deadline = Arc::new(Mutex::new(None::<Instant>));
+            let tick_deadline = deadline.clone();
+            let mut sampler = self.stack_sampler;
+            let interrupt = self.interrupt.clone();
+            inner.set_epoch_deadline(1);
+            inner.epoch_deadline_callback(move |store| {
+                if interrupt
+                    .as_ref()
+                    .is_some_and(StoreInterrupt::is_interrupted)
+                {
+                    return Err(Interrupted.into());
+                }
+                let deadline = *deadline.lock().unwrap();
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(Trap::Interrupt.into());
+                }
+                if let Some(sampler) = &mut sampler {
+                    sampler(&WasmBacktrace::capture(&store));
+                }
+                Ok(UpdateDeadline::Continue(1))
+            });
+            tick_deadline
+        });
+
+        let mut store = Store {
             inner,
             epoch_tick_interval: self.epoch_tick_interval,
-        })
+            fuel: self.fuel,
+            execution_time_limit: self.execution_time_limit,
+            tick_deadline,
+            interrupt: self.interrupt,
+        };
+        if let Some(limit) = self.execution_time_limit {
+            store.set_deadline(Instant::now() + limit);
+        }
+        Ok(store)
+    }
+}
+
+/// Interrupts the guest executing in a [`Store`] from elsewhere, e.g. when
+/// the client it is serving has gone away.
+///
+/// See [`StoreBuilder::interruptible`].
+#[derive(Clone, Debug, Default)]
+pub struct StoreInterrupt(Arc<AtomicBool>);
+
+impl StoreInterrupt {
+    /// Makes the guest fail with [`Interrupted`] at the next epoch tick at
+    /// which it is executing.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether [`StoreInterrupt::interrupt`] has been called.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
     }
 }
 
+/// The error a guest fails with when interrupted with a [`StoreInterrupt`].
+#[derive(Debug, thiserror::Error)]
+#[error("the instance was interrupted")]
+pub struct Interrupted;
+
 /// For consumers that need to use a type other than [`State`] as the [`Store`]
 /// `data`, this trait must be implemented for that type.
 pub trait AsState {
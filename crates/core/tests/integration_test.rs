@@ -5,7 +5,10 @@ use std::{
 
 use anyhow::Context;
 use serde_json::json;
-use spin_core::{AsState, Component, Config, Engine, State, Store, StoreBuilder, Trap};
+use spin_core::{
+    AsState, Component, Config, Engine, Interrupted, LimitExceeded, State, Store, StoreBuilder,
+    Trap,
+};
 use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
 use spin_factors::{App, AsInstanceState, RuntimeFactors};
 use spin_locked_app::locked::LockedApp;
@@ -76,6 +79,91 @@ async fn test_set_deadline_violated() {
     assert_eq!(trap, Trap::Interrupt);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_time_limit_violated() {
+    let err = run_test(
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.execution_time_limit(Duration::from_millis(10));
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        LimitExceeded::from_error(&err),
+        Some(LimitExceeded::ExecutionTime)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_time_limit_violated_while_sampling_stacks() {
+    let err = run_test(
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.sample_stacks(|_| {});
+            store_builder.execution_time_limit(Duration::from_millis(10));
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        LimitExceeded::from_error(&err),
+        Some(LimitExceeded::ExecutionTime)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_interrupted() {
+    let err = run_test(
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.interruptible();
+        },
+        |store| {
+            store.interrupt_handle().expect("interruptible").interrupt();
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(err.is::<Interrupted>());
+    assert_eq!(LimitExceeded::from_error(&err), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuel_obeyed() {
+    run_test_with_config(
+        ["alloc", "1000"],
+        |config| {
+            config.consume_fuel(true);
+        },
+        |store_builder| {
+            store_builder.fuel(u64::MAX / 2);
+        },
+        |_| {},
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuel_violated() {
+    let err = run_test_with_config(
+        ["alloc", "1000"],
+        |config| {
+            config.consume_fuel(true);
+        },
+        |store_builder| {
+            store_builder.fuel(100);
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(LimitExceeded::from_error(&err), Some(LimitExceeded::Fuel));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_panic() {
     let err = run_test(["panic"], |_| {}, |_| {}).await.unwrap_err();
@@ -83,6 +171,33 @@ async fn test_panic() {
     assert_eq!(trap, Trap::UnreachableCodeReached);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_debug_guests() {
+    run_test_with_config(
+        ["alloc", "1000"],
+        |config| {
+            config.debug_guests(true);
+        },
+        |_| {},
+        |_| {},
+    )
+    .await
+    .unwrap();
+
+    let err = run_test_with_config(
+        ["panic"],
+        |config| {
+            config.debug_guests(true);
+        },
+        |_| {},
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::UnreachableCodeReached);
+}
+
 #[derive(RuntimeFactors)]
 struct TestFactors {
     wasi: WasiFactor,
@@ -109,6 +224,15 @@ async fn run_test(
     args: impl IntoIterator<Item = &'_ str>,
     update_store_builder: impl FnOnce(&mut StoreBuilder),
     update_store: impl FnOnce(&mut Store<TestState>),
+) -> anyhow::Result<()> {
+    run_test_with_config(args, |_| {}, update_store_builder, update_store).await
+}
+
+async fn run_test_with_config(
+    args: impl IntoIterator<Item = &'_ str>,
+    update_config: impl FnOnce(&mut Config),
+    update_store_builder: impl FnOnce(&mut StoreBuilder),
+    update_store: impl FnOnce(&mut Store<TestState>),
 ) -> anyhow::Result<()> {
     let mut factors = TestFactors {
         wasi: WasiFactor::new(DummyFilesMounter),
@@ -118,6 +242,7 @@ async fn run_test(
     config
         .wasmtime_config()
         .wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
+    update_config(&mut config);
 
     let mut builder = Engine::builder(&config).unwrap();
     factors.init(builder.linker())?;
@@ -10,6 +10,8 @@ use toml_edit::DocumentMut;
 
 /// Diagnoses for app manifest format problems.
 pub mod manifest;
+/// Diagnoses for runtime config problems.
+pub mod runtime_config;
 /// Diagnose for Rust-specific problems.
 pub mod rustlang;
 /// Test helpers.
@@ -38,7 +40,8 @@ impl Checkup {
             .add_diagnostic::<manifest::version::VersionDiagnostic>()
             .add_diagnostic::<manifest::trigger::TriggerDiagnostic>()
             .add_diagnostic::<rustlang::target::TargetDiagnostic>() // Do toolchain checks _before_ build check
-            .add_diagnostic::<wasm::missing::WasmMissingDiagnostic>();
+            .add_diagnostic::<wasm::missing::WasmMissingDiagnostic>()
+            .add_diagnostic::<runtime_config::backends::BackendDiagnostic>();
         Ok(checkup)
     }
 
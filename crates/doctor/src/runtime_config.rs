@@ -0,0 +1,69 @@
+/// Diagnose misconfigured or unreachable runtime config backends.
+pub mod backends;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use spin_common::{paths::parent_dir, ui::quoted_path};
+
+use crate::PatientApp;
+
+/// The environment variable `spin up` reads the runtime config file path from.
+const RUNTIME_CONFIG_FILE_ENV: &str = "RUNTIME_CONFIG_FILE";
+
+/// The runtime config file conventionally kept next to the app manifest.
+const DEFAULT_RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+
+/// PatientRuntimeConfig represents a runtime config file to be checked for
+/// problems.
+#[derive(Debug)]
+pub struct PatientRuntimeConfig {
+    /// Path to the runtime config file.
+    pub path: PathBuf,
+    /// Parsed runtime config TOML.
+    pub table: toml::Table,
+}
+
+impl PatientRuntimeConfig {
+    /// Finds the runtime config file the app would be run with: the one named
+    /// by `RUNTIME_CONFIG_FILE`, or else `runtime-config.toml` next to the
+    /// manifest. Returns None if there is no such file.
+    pub fn find(patient: &PatientApp) -> Result<Option<PathBuf>> {
+        let path = match std::env::var_os(RUNTIME_CONFIG_FILE_ENV) {
+            Some(path) => PathBuf::from(path),
+            None => parent_dir(&patient.manifest_path)?.join(DEFAULT_RUNTIME_CONFIG_FILE),
+        };
+        Ok(path.is_file().then_some(path))
+    }
+
+    /// Reads and parses the runtime config file at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read runtime config {}", quoted_path(path)))?;
+        let table = toml::from_str(&contents).with_context(|| {
+            format!(
+                "Couldn't parse runtime config {} as TOML",
+                quoted_path(path)
+            )
+        })?;
+        Ok(Self {
+            path: path.to_owned(),
+            table,
+        })
+    }
+
+    /// Returns the labelled tables under the given key, e.g. each
+    /// `[key_value_store.<label>]`.
+    pub fn labelled_tables<'a>(
+        &'a self,
+        key: &str,
+    ) -> impl Iterator<Item = (&'a str, &'a toml::Table)> + 'a {
+        self.table
+            .get(key)
+            .and_then(toml::Value::as_table)
+            .into_iter()
+            .flatten()
+            .filter_map(|(label, value)| Some((label.as_str(), value.as_table()?)))
+    }
+}
@@ -9,4 +9,16 @@ use crate::Key;
 pub trait Provider: Debug + Send + Sync {
     /// Returns the value at the given config path, if it exists.
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>>;
+
+    /// Returns the value a provider-specific reference, such as the path of
+    /// a secret, refers to, if it exists.
+    ///
+    /// Used to resolve secret references in runtime config. By default, the
+    /// reference is looked up as a variable name.
+    async fn get_reference(&self, reference: &str) -> anyhow::Result<Option<String>> {
+        match Key::new(reference) {
+            Ok(key) => self.get(&key).await,
+            Err(_) => Ok(None),
+        }
+    }
 }
@@ -0,0 +1,238 @@
+pub mod runtime_config;
+mod sink;
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
+};
+
+use serde::Serialize;
+use spin_factor_request_context::{RequestContextFactor, RequestContextHandle};
+use spin_factors::{
+    ConfigureAppContext, Error, Factor, FactorInstanceBuilder, PrepareContext, RuntimeFactors,
+};
+
+pub use runtime_config::RuntimeConfig;
+pub use sink::{AuditSink, FileAuditSink, OtlpAuditSink, SqliteAuditSink};
+
+/// A factor that records every component invocation to an audit log, if one
+/// is configured.
+///
+/// Triggers report each invocation's outcome with [`AuditHandle::finish`];
+/// other factors report the outbound hosts an invocation contacted with
+/// [`AuditHandle::record_outbound_host`].
+#[derive(Default)]
+pub struct AuditFactor {
+    _priv: (),
+}
+
+impl AuditFactor {
+    /// Create a new AuditFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for AuditFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let sink = ctx.take_runtime_config().map(|config| config.sink);
+        Ok(AppState {
+            app_id: ctx.app().id().to_owned(),
+            sink,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let Some(sink) = ctx.app_state().sink.clone() else {
+            return Ok(InstanceBuilder { handle: None });
+        };
+        let request_context = match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(builder) => Some(builder.handle()),
+            Err(Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let invocation = PendingInvocation {
+            started_at: SystemTime::now(),
+            start: Instant::now(),
+            app_id: ctx.app_state().app_id.clone(),
+            component_id: ctx.app_component().id().to_owned(),
+            request_context,
+            outbound_hosts: Default::default(),
+            finished: false,
+        };
+        Ok(InstanceBuilder {
+            handle: Some(AuditHandle {
+                invocation: Arc::new(Mutex::new(invocation)),
+                sink,
+            }),
+        })
+    }
+}
+
+pub struct AppState {
+    app_id: String,
+    /// Where records are written, if auditing is enabled.
+    sink: Option<Arc<dyn AuditSink>>,
+}
+
+/// A record of one component invocation.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditRecord {
+    /// When the invocation started, in RFC 3339 format.
+    pub timestamp: String,
+    pub app_id: String,
+    pub component_id: String,
+    /// The request ID of the invocation, if known.
+    pub request_id: Option<String>,
+    /// The type of the trigger that invoked the component, e.g. `http`.
+    pub trigger_type: String,
+    /// What the invocation was for, e.g. an HTTP route or a Redis channel.
+    pub target: String,
+    pub duration_ms: u64,
+    /// `ok` or `error`.
+    pub outcome: &'static str,
+    /// The error, if the invocation failed.
+    pub error: Option<String>,
+    /// The outbound hosts the invocation was allowed to contact, sorted.
+    pub outbound_hosts: Vec<String>,
+}
+
+/// The outcome of an invocation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Ok,
+    Error(String),
+}
+
+impl AuditOutcome {
+    /// The outcome of an invocation that returned `result`.
+    pub fn from_result<T>(result: &anyhow::Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Ok,
+            Err(err) => Self::Error(format!("{err:#}")),
+        }
+    }
+}
+
+/// A handle to the audit record of an instance's invocation.
+#[derive(Clone)]
+pub struct AuditHandle {
+    invocation: Arc<Mutex<PendingInvocation>>,
+    sink: Arc<dyn AuditSink>,
+}
+
+struct PendingInvocation {
+    started_at: SystemTime,
+    start: Instant,
+    app_id: String,
+    component_id: String,
+    request_context: Option<RequestContextHandle>,
+    outbound_hosts: BTreeSet<String>,
+    finished: bool,
+}
+
+impl AuditHandle {
+    /// Notes that the invocation was allowed to contact an outbound host.
+    pub fn record_outbound_host(&self, authority: &str) {
+        let mut invocation = self.invocation.lock().unwrap();
+        if !invocation.outbound_hosts.contains(authority) {
+            invocation.outbound_hosts.insert(authority.to_owned());
+        }
+    }
+
+    /// Starts recording a new invocation by the same instance, e.g. when an
+    /// instance is reused for another request.
+    pub fn restart(&self) {
+        let mut invocation = self.invocation.lock().unwrap();
+        invocation.started_at = SystemTime::now();
+        invocation.start = Instant::now();
+        invocation.outbound_hosts.clear();
+        invocation.finished = false;
+    }
+
+    /// Writes the invocation's record to the audit log.
+    ///
+    /// Only the first call for an invocation writes a record. Outbound hosts
+    /// contacted after this, e.g. while a response body is streamed, are not
+    /// recorded.
+    pub fn finish(&self, trigger_type: &str, target: &str, outcome: AuditOutcome) {
+        let record = {
+            let mut invocation = self.invocation.lock().unwrap();
+            if invocation.finished {
+                return;
+            }
+            invocation.finished = true;
+            let (outcome, error) = match outcome {
+                AuditOutcome::Ok => ("ok", None),
+                AuditOutcome::Error(err) => ("error", Some(err)),
+            };
+            AuditRecord {
+                timestamp: chrono::DateTime::<chrono::Utc>::from(invocation.started_at)
+                    .to_rfc3339(),
+                app_id: invocation.app_id.clone(),
+                component_id: invocation.component_id.clone(),
+                request_id: invocation
+                    .request_context
+                    .as_ref()
+                    .map(|context| context.get().request_id),
+                trigger_type: trigger_type.to_owned(),
+                target: target.to_owned(),
+                duration_ms: invocation.start.elapsed().as_millis() as u64,
+                outcome,
+                error,
+                outbound_hosts: invocation.outbound_hosts.iter().cloned().collect(),
+            }
+        };
+        if let Err(err) = self.sink.write(&record) {
+            tracing::error!(
+                "Failed to write audit record for component {}: {err:#}",
+                record.component_id
+            );
+        }
+    }
+}
+
+pub struct InstanceBuilder {
+    handle: Option<AuditHandle>,
+}
+
+impl InstanceBuilder {
+    /// Returns a handle to the instance's audit record, or `None` if auditing
+    /// is disabled.
+    pub fn handle(&self) -> Option<AuditHandle> {
+        self.handle.clone()
+    }
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState {
+            handle: self.handle,
+        })
+    }
+}
+
+pub struct InstanceState {
+    handle: Option<AuditHandle>,
+}
+
+impl InstanceState {
+    /// Returns a handle to the instance's audit record, or `None` if auditing
+    /// is disabled.
+    pub fn handle(&self) -> Option<&AuditHandle> {
+        self.handle.as_ref()
+    }
+}
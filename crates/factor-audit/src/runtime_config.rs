@@ -0,0 +1,21 @@
+pub mod spin;
+
+use std::sync::Arc;
+
+use crate::AuditSink;
+
+/// Runtime configuration for the audit log.
+#[derive(Clone)]
+pub struct RuntimeConfig {
+    /// Where audit records are written.
+    pub sink: Arc<dyn AuditSink>,
+}
+
+impl RuntimeConfig {
+    /// Creates a runtime config that writes audit records to `sink`.
+    pub fn new(sink: impl AuditSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+        }
+    }
+}
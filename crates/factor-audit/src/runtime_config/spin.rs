@@ -0,0 +1,59 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use crate::{FileAuditSink, OtlpAuditSink, RuntimeConfig, SqliteAuditSink};
+
+/// Get the runtime configuration for the audit log from a TOML table.
+///
+/// Relative paths are resolved against `runtime_config_dir`.
+///
+/// Expects table to be in one of the formats:
+/// ```toml
+/// [audit]
+/// type = "file"
+/// path = "audit/invocations.jsonl"
+/// ```
+/// ```toml
+/// [audit]
+/// type = "sqlite"
+/// path = "audit/invocations.db"
+/// ```
+/// ```toml
+/// [audit]
+/// type = "otlp"
+/// ```
+pub fn config_from_table(
+    table: &impl GetTomlValue,
+    runtime_config_dir: &Path,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("audit") else {
+        return Ok(None);
+    };
+    let toml: AuditToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [audit] table")?;
+    let config = match toml {
+        AuditToml::File { path } => {
+            RuntimeConfig::new(FileAuditSink::open(&runtime_config_dir.join(path))?)
+        }
+        AuditToml::Sqlite { path } => {
+            RuntimeConfig::new(SqliteAuditSink::open(&runtime_config_dir.join(path))?)
+        }
+        AuditToml::Otlp => RuntimeConfig::new(OtlpAuditSink::new()?),
+    };
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum AuditToml {
+    File { path: PathBuf },
+    Sqlite { path: PathBuf },
+    Otlp,
+}
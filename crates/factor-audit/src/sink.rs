@@ -0,0 +1,204 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write as _,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::Context as _;
+
+use crate::AuditRecord;
+
+/// A destination for audit records.
+pub trait AuditSink: Send + Sync {
+    /// Writes a record.
+    fn write(&self, record: &AuditRecord) -> anyhow::Result<()>;
+}
+
+/// Appends records to a file, one JSON object per line.
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Opens `path` for appending, creating it and its parent directories if
+    /// needed.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn write(&self, record: &AuditRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        // A single write keeps concurrent records on separate lines
+        self.file.lock().unwrap().write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// Inserts records into the `invocations` table of a SQLite database.
+pub struct SqliteAuditSink {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+impl SqliteAuditSink {
+    /// Opens the database at `path`, creating it and the `invocations` table
+    /// if needed.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory {}", parent.display()))?;
+        }
+        let connection = rusqlite::Connection::open(path)
+            .with_context(|| format!("failed to open audit database {}", path.display()))?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS invocations (
+                timestamp TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                component_id TEXT NOT NULL,
+                request_id TEXT,
+                trigger_type TEXT NOT NULL,
+                target TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                outcome TEXT NOT NULL,
+                error TEXT,
+                outbound_hosts TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl AuditSink for SqliteAuditSink {
+    fn write(&self, record: &AuditRecord) -> anyhow::Result<()> {
+        // Outbound hosts are stored as a JSON array
+        let outbound_hosts = serde_json::to_string(&record.outbound_hosts)?;
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO invocations VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                record.timestamp,
+                record.app_id,
+                record.component_id,
+                record.request_id,
+                record.trigger_type,
+                record.target,
+                record.duration_ms,
+                record.outcome,
+                record.error,
+                outbound_hosts,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// Exports records as OpenTelemetry logs.
+pub struct OtlpAuditSink {
+    _priv: (),
+}
+
+impl OtlpAuditSink {
+    /// Errors if OpenTelemetry log export isn't enabled, as records would
+    /// otherwise be lost.
+    pub fn new() -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            spin_telemetry::logs::otel_logs_enabled(),
+            "audit records can only be exported with OTLP if an OTLP logs endpoint is configured, e.g. with OTEL_EXPORTER_OTLP_ENDPOINT"
+        );
+        Ok(Self { _priv: () })
+    }
+}
+
+impl AuditSink for OtlpAuditSink {
+    fn write(&self, record: &AuditRecord) -> anyhow::Result<()> {
+        let mut attributes = vec![
+            ("spin.app_id", record.app_id.clone()),
+            ("spin.component_id", record.component_id.clone()),
+            ("spin.trigger_type", record.trigger_type.clone()),
+            ("spin.audit.outcome", record.outcome.to_owned()),
+        ];
+        if let Some(request_id) = &record.request_id {
+            attributes.push(("spin.request_id", request_id.clone()));
+        }
+        spin_telemetry::logs::export_audit_record(serde_json::to_string(record)?, attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(component_id: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: "2024-01-01T00:00:00+00:00".into(),
+            app_id: "app".into(),
+            component_id: component_id.into(),
+            request_id: Some("req-1".into()),
+            trigger_type: "http".into(),
+            target: "/...".into(),
+            duration_ms: 12,
+            outcome: "error",
+            error: Some("boom".into()),
+            outbound_hosts: vec!["example.com:443".into()],
+        }
+    }
+
+    #[test]
+    fn file_sink_appends_json_lines() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit/invocations.jsonl");
+        let sink = FileAuditSink::open(&path)?;
+        sink.write(&record("first"))?;
+        sink.write(&record("second"))?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines = contents
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["component_id"], "first");
+        assert_eq!(lines[1]["outbound_hosts"][0], "example.com:443");
+        assert_eq!(lines[1]["error"], "boom");
+        Ok(())
+    }
+
+    #[test]
+    fn sqlite_sink_inserts_rows() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.db");
+        SqliteAuditSink::open(&path)?.write(&record("first"))?;
+        // Reopening keeps existing records
+        SqliteAuditSink::open(&path)?.write(&record("second"))?;
+
+        let connection = rusqlite::Connection::open(&path)?;
+        let rows = connection
+            .prepare("SELECT component_id, outbound_hosts FROM invocations ORDER BY rowid")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>, _>>()?;
+        assert_eq!(
+            rows,
+            [
+                ("first".to_owned(), r#"["example.com:443"]"#.to_owned()),
+                ("second".to_owned(), r#"["example.com:443"]"#.to_owned()),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use spin_factor_audit::{AuditFactor, AuditOutcome, FileAuditSink, RuntimeConfig};
+use spin_factor_request_context::RequestContextFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    request_context: RequestContextFactor,
+    audit: AuditFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        request_context: RequestContextFactor::new(),
+        audit: AuditFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+#[tokio::test]
+async fn auditing_is_disabled_by_default() -> anyhow::Result<()> {
+    let state = test_env().build_instance_state().await?;
+    assert!(state.audit.handle().is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn finished_invocations_are_written_once() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("audit.jsonl");
+    let state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            request_context: None,
+            audit: Some(RuntimeConfig::new(FileAuditSink::open(&path)?)),
+        })?
+        .build_instance_state()
+        .await?;
+
+    let audit = state.audit.handle().expect("auditing should be enabled");
+    audit.record_outbound_host("example.com:443");
+    audit.record_outbound_host("api.example.com:443");
+    audit.record_outbound_host("example.com:443");
+    audit.finish("http", "/orders/...", AuditOutcome::Ok);
+    audit.finish("http", "/orders/...", AuditOutcome::Error("late".into()));
+
+    let contents = std::fs::read_to_string(&path)?;
+    let records = contents
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record["component_id"], "test-component");
+    assert_eq!(record["trigger_type"], "http");
+    assert_eq!(record["target"], "/orders/...");
+    assert_eq!(record["outcome"], "ok");
+    assert_eq!(
+        record["request_id"],
+        state.request_context.handle().get().request_id
+    );
+    assert_eq!(
+        record["outbound_hosts"],
+        serde_json::json!(["api.example.com:443", "example.com:443"])
+    );
+    Ok(())
+}
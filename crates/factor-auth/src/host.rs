@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use spin_common::time::now_secs;
+use spin_factor_outbound_networking::OutboundAllowedHosts;
+use spin_world::spin::auth::jwt::{self, Claims, Error, Validation};
+use tracing::{instrument, Level};
+
+use crate::{
+    jwks::{find_key, JwksCache},
+    token::{Algorithm, Token},
+};
+
+/// Clock skew tolerated when checking `exp` and `nbf`, if the guest doesn't say.
+const DEFAULT_LEEWAY_SECS: u32 = 60;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    jwks: Arc<JwksCache>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(allowed_hosts: OutboundAllowedHosts, jwks: Arc<JwksCache>) -> Self {
+        Self {
+            allowed_hosts,
+            jwks,
+        }
+    }
+}
+
+impl jwt::Host for InstanceState {
+    #[instrument(name = "spin_auth.validate_jwt", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn validate(&mut self, token: String, validation: Validation) -> Result<Claims, Error> {
+        let Validation {
+            jwks_url,
+            issuer,
+            audiences,
+            leeway_secs,
+        } = validation;
+
+        let token = Token::decode(&token)?;
+        let alg = Algorithm::parse(&token.header.alg)?;
+
+        if !self
+            .allowed_hosts
+            .check_url(&jwks_url, "https")
+            .await
+            .map_err(|e| Error::JwksUnavailable(e.to_string()))?
+        {
+            return Err(Error::AccessDenied);
+        }
+        let kid = token.header.kid.as_deref();
+        let keys = self.jwks.keys(&jwks_url, alg, kid).await?;
+        token.verify_signature(alg, find_key(&keys, alg, kid)?)?;
+
+        let leeway = leeway_secs.unwrap_or(DEFAULT_LEEWAY_SECS).into();
+        token.validate_claims(now_secs(), leeway, issuer.as_deref(), &audiences)
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,294 @@
+//! Fetching and caching JSON Web Key Sets.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use spin_world::spin::auth::jwt::Error;
+use tokio::time::Instant;
+
+use crate::token::Algorithm;
+
+/// How long a JWKS can be used before it is fetched again, by default.
+pub const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(10 * 60);
+/// The shortest interval between fetches of a JWKS when looking for a key it
+/// doesn't have. This stops tokens with made-up key IDs from hammering the
+/// identity provider.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to wait for the identity provider.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// The largest JWKS accepted, in bytes.
+const MAX_JWKS_LEN: usize = 1024 * 1024;
+
+/// A public key from a JWKS.
+#[derive(Debug)]
+pub(crate) struct Jwk {
+    kid: Option<String>,
+    alg: Option<String>,
+    key: KeyMaterial,
+}
+
+#[derive(Debug)]
+enum KeyMaterial {
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// An uncompressed SEC1 point.
+    EcP256(Vec<u8>),
+    EcP384(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+impl Jwk {
+    /// Whether this key may be used for `alg` (and has ID `kid`, if given).
+    fn matches(&self, alg: Algorithm, kid: Option<&str>) -> bool {
+        if kid.is_some() && self.kid.as_deref() != kid {
+            return false;
+        }
+        if self.alg.as_deref().is_some_and(|a| a != alg.name()) {
+            return false;
+        }
+        matches!(
+            (alg, &self.key),
+            (
+                Algorithm::RS256
+                    | Algorithm::RS384
+                    | Algorithm::RS512
+                    | Algorithm::PS256
+                    | Algorithm::PS384
+                    | Algorithm::PS512,
+                KeyMaterial::Rsa { .. }
+            ) | (Algorithm::ES256, KeyMaterial::EcP256(_))
+                | (Algorithm::ES384, KeyMaterial::EcP384(_))
+                | (Algorithm::EdDSA, KeyMaterial::Ed25519(_))
+        )
+    }
+
+    /// Checks `signature` over `message`.
+    pub fn verify(&self, alg: Algorithm, message: &[u8], signature: &[u8]) -> bool {
+        if !self.matches(alg, None) {
+            return false;
+        }
+        let result = match &self.key {
+            KeyMaterial::Rsa { n, e } => {
+                let params = match alg {
+                    Algorithm::RS256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+                    Algorithm::RS384 => &signature::RSA_PKCS1_2048_8192_SHA384,
+                    Algorithm::RS512 => &signature::RSA_PKCS1_2048_8192_SHA512,
+                    Algorithm::PS256 => &signature::RSA_PSS_2048_8192_SHA256,
+                    Algorithm::PS384 => &signature::RSA_PSS_2048_8192_SHA384,
+                    Algorithm::PS512 => &signature::RSA_PSS_2048_8192_SHA512,
+                    _ => return false,
+                };
+                RsaPublicKeyComponents { n, e }.verify(params, message, signature)
+            }
+            KeyMaterial::EcP256(point) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+            }
+            KeyMaterial::EcP384(point) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(message, signature)
+            }
+            KeyMaterial::Ed25519(key) => {
+                UnparsedPublicKey::new(&signature::ED25519, key).verify(message, signature)
+            }
+        };
+        result.is_ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct JwksJson {
+    keys: Vec<JwkJson>,
+}
+
+#[derive(Deserialize)]
+struct JwkJson {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    use_: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+/// Parses a JWKS, skipping keys which are not for signatures or are of
+/// unsupported types.
+pub(crate) fn parse_jwks(json: &[u8]) -> anyhow::Result<Vec<Jwk>> {
+    let jwks: JwksJson = serde_json::from_slice(json)?;
+    Ok(jwks
+        .keys
+        .into_iter()
+        .filter(|jwk| jwk.use_.as_deref().is_none_or(|u| u == "sig"))
+        .filter_map(|jwk| {
+            let decode = |v: &Option<String>| URL_SAFE_NO_PAD.decode(v.as_deref()?).ok();
+            let key = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+                ("RSA", _) => KeyMaterial::Rsa {
+                    n: decode(&jwk.n)?,
+                    e: decode(&jwk.e)?,
+                },
+                ("EC", Some(crv @ ("P-256" | "P-384"))) => {
+                    let mut point = vec![0x04];
+                    point.extend(decode(&jwk.x)?);
+                    point.extend(decode(&jwk.y)?);
+                    if crv == "P-256" {
+                        KeyMaterial::EcP256(point)
+                    } else {
+                        KeyMaterial::EcP384(point)
+                    }
+                }
+                ("OKP", Some("Ed25519")) => KeyMaterial::Ed25519(decode(&jwk.x)?),
+                _ => return None,
+            };
+            Some(Jwk {
+                kid: jwk.kid,
+                alg: jwk.alg,
+                key,
+            })
+        })
+        .collect())
+}
+
+/// JWKSs fetched by any instance of the app, keyed by URL.
+pub(crate) struct JwksCache {
+    client: reqwest::Client,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedJwks>>,
+}
+
+#[derive(Clone)]
+struct CachedJwks {
+    keys: Arc<Vec<Jwk>>,
+    fetched_at: Instant,
+}
+
+impl JwksCache {
+    pub fn new(ttl: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            ttl,
+            entries: Default::default(),
+        })
+    }
+
+    /// Returns the keys from the JWKS at `url`, which should include one for
+    /// `alg` and `kid`.
+    ///
+    /// The JWKS is fetched if it isn't cached or has expired, or if it
+    /// doesn't have the key and wasn't fetched very recently, since the
+    /// identity provider may have rotated its keys.
+    pub async fn keys(
+        &self,
+        url: &str,
+        alg: Algorithm,
+        kid: Option<&str>,
+    ) -> Result<Arc<Vec<Jwk>>, Error> {
+        let cached = self.entries.lock().unwrap().get(url).cloned();
+        if let Some(cached) = cached {
+            let age = cached.fetched_at.elapsed();
+            let has_key = cached.keys.iter().any(|k| k.matches(alg, kid));
+            if age < self.ttl && (has_key || age < MIN_REFETCH_INTERVAL) {
+                return Ok(cached.keys);
+            }
+        }
+        let keys = Arc::new(self.fetch(url).await?);
+        self.entries.lock().unwrap().insert(
+            url.to_owned(),
+            CachedJwks {
+                keys: keys.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(keys)
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Vec<Jwk>, Error> {
+        let unavailable = |e: &dyn std::fmt::Display| Error::JwksUnavailable(e.to_string());
+        let resp = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| unavailable(&e))?;
+        if resp
+            .content_length()
+            .is_some_and(|len| len > MAX_JWKS_LEN as u64)
+        {
+            return Err(unavailable(&"JWKS is too large"));
+        }
+        let body = resp.bytes().await.map_err(|e| unavailable(&e))?;
+        if body.len() > MAX_JWKS_LEN {
+            return Err(unavailable(&"JWKS is too large"));
+        }
+        parse_jwks(&body).map_err(|e| unavailable(&format!("invalid JWKS: {e}")))
+    }
+}
+
+/// Finds the key for `alg` and `kid` among `keys`.
+pub(crate) fn find_key(keys: &[Jwk], alg: Algorithm, kid: Option<&str>) -> Result<&Jwk, Error> {
+    keys.iter()
+        .find(|k| k.matches(alg, kid))
+        .ok_or(Error::UnknownKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use super::*;
+
+    fn ed25519_jwks(kid: &str, public_key: &[u8]) -> Vec<u8> {
+        serde_json::json!({
+            "keys": [
+                {"kty": "RSA", "use": "enc", "n": "AQAB", "e": "AQAB"},
+                {"kty": "oct", "k": "c2VjcmV0"},
+                {"kty": "OKP", "crv": "Ed25519", "kid": kid, "x": URL_SAFE_NO_PAD.encode(public_key)},
+            ]
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn parses_supported_signing_keys() {
+        let keys = parse_jwks(&ed25519_jwks("k1", &[0; 32])).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(find_key(&keys, Algorithm::EdDSA, Some("k1")).is_ok());
+        assert!(matches!(
+            find_key(&keys, Algorithm::EdDSA, Some("k2")),
+            Err(Error::UnknownKey)
+        ));
+        assert!(matches!(
+            find_key(&keys, Algorithm::RS256, None),
+            Err(Error::UnknownKey)
+        ));
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let keys = parse_jwks(&ed25519_jwks("k1", key_pair.public_key().as_ref())).unwrap();
+        let key = find_key(&keys, Algorithm::EdDSA, None).unwrap();
+
+        let signature = key_pair.sign(b"message");
+        assert!(key.verify(Algorithm::EdDSA, b"message", signature.as_ref()));
+        assert!(!key.verify(Algorithm::EdDSA, b"tampered", signature.as_ref()));
+        assert!(!key.verify(Algorithm::ES256, b"message", signature.as_ref()));
+    }
+}
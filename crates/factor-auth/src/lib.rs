@@ -0,0 +1,84 @@
+mod host;
+mod jwks;
+pub mod runtime_config;
+mod token;
+
+use std::sync::Arc;
+
+use spin_factor_outbound_networking::{OutboundAllowedHosts, OutboundNetworkingFactor};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+use jwks::JwksCache;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that validates JWTs for the guest, fetching signing keys from
+/// JWKS URLs and caching them across instances.
+///
+/// A JWKS URL must be allowed by the component's `allowed_outbound_hosts`,
+/// so this factor must come after [`OutboundNetworkingFactor`].
+#[derive(Default)]
+pub struct AuthFactor {
+    _priv: (),
+}
+
+impl AuthFactor {
+    /// Create a new AuthFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for AuthFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::auth::jwt::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let RuntimeConfig { jwks_cache_ttl } = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            jwks: Arc::new(JwksCache::new(jwks_cache_ttl)?),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let allowed_hosts = ctx
+            .instance_builder::<OutboundNetworkingFactor>()?
+            .allowed_hosts();
+        Ok(InstanceBuilder {
+            allowed_hosts,
+            jwks: ctx.app_state().jwks.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The JWKSs fetched by the app's instances.
+    jwks: Arc<JwksCache>,
+}
+
+pub struct InstanceBuilder {
+    allowed_hosts: OutboundAllowedHosts,
+    jwks: Arc<JwksCache>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_hosts, self.jwks))
+    }
+}
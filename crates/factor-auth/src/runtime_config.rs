@@ -0,0 +1,20 @@
+pub mod spin;
+
+use std::time::Duration;
+
+use crate::jwks::DEFAULT_JWKS_TTL;
+
+/// Runtime configuration for JWT validation.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// How long a fetched JWKS is used before it is fetched again.
+    pub jwks_cache_ttl: Duration,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            jwks_cache_ttl: DEFAULT_JWKS_TTL,
+        }
+    }
+}
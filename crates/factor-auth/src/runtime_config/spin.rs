@@ -0,0 +1,37 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for JWT validation from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [auth]
+/// jwks_cache_ttl_secs = 300
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("auth") else {
+        return Ok(None);
+    };
+    let toml: AuthToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [auth] table")?;
+    let mut config = RuntimeConfig::default();
+    if let Some(secs) = toml.jwks_cache_ttl_secs {
+        config.jwks_cache_ttl = Duration::from_secs(secs);
+    }
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthToml {
+    jwks_cache_ttl_secs: Option<u64>,
+}
@@ -0,0 +1,253 @@
+//! Decoding JWTs and checking their signatures and claims.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use spin_world::spin::auth::jwt::{Claims, Error};
+
+use crate::jwks::Jwk;
+
+/// A JWT whose signature has not yet been checked.
+pub(crate) struct Token<'a> {
+    pub header: Header,
+    /// The `header.payload` part of the token, which the signature covers.
+    signing_input: &'a str,
+    signature: Vec<u8>,
+    claims: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Header {
+    pub alg: String,
+    pub kid: Option<String>,
+}
+
+/// The signature algorithms tokens may use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    EdDSA,
+}
+
+impl Algorithm {
+    pub fn parse(alg: &str) -> Result<Self, Error> {
+        Ok(match alg {
+            "RS256" => Self::RS256,
+            "RS384" => Self::RS384,
+            "RS512" => Self::RS512,
+            "PS256" => Self::PS256,
+            "PS384" => Self::PS384,
+            "PS512" => Self::PS512,
+            "ES256" => Self::ES256,
+            "ES384" => Self::ES384,
+            "EdDSA" => Self::EdDSA,
+            _ => return Err(Error::UnsupportedAlgorithm(alg.to_owned())),
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RS256 => "RS256",
+            Self::RS384 => "RS384",
+            Self::RS512 => "RS512",
+            Self::PS256 => "PS256",
+            Self::PS384 => "PS384",
+            Self::PS512 => "PS512",
+            Self::ES256 => "ES256",
+            Self::ES384 => "ES384",
+            Self::EdDSA => "EdDSA",
+        }
+    }
+}
+
+impl<'a> Token<'a> {
+    /// Splits and decodes a compact-serialized JWS.
+    pub fn decode(token: &'a str) -> Result<Self, Error> {
+        let malformed = |msg: &str| Error::Malformed(msg.to_owned());
+        let (signing_input, signature) = token
+            .rsplit_once('.')
+            .ok_or_else(|| malformed("expected three dot-separated parts"))?;
+        let (header, payload) = signing_input
+            .split_once('.')
+            .ok_or_else(|| malformed("expected three dot-separated parts"))?;
+        let decode = |part: &str, name: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|_| malformed(&format!("{name} is not base64url")))
+        };
+        let header: Header = serde_json::from_slice(&decode(header, "header")?)
+            .map_err(|e| malformed(&format!("invalid header: {e}")))?;
+        let claims = serde_json::from_slice(&decode(payload, "payload")?)
+            .map_err(|e| malformed(&format!("payload is not a JSON object: {e}")))?;
+        Ok(Self {
+            header,
+            signing_input,
+            signature: decode(signature, "signature")?,
+            claims,
+        })
+    }
+
+    /// Checks the token's signature with `key`.
+    pub fn verify_signature(&self, alg: Algorithm, key: &Jwk) -> Result<(), Error> {
+        if key.verify(alg, self.signing_input.as_bytes(), &self.signature) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    /// Checks the token's time, issuer and audience claims at `now` and
+    /// returns its claims.
+    pub fn validate_claims(
+        self,
+        now: u64,
+        leeway: u64,
+        issuer: Option<&str>,
+        audiences: &[String],
+    ) -> Result<Claims, Error> {
+        let claims = self.claims;
+        let expires_at = numeric_date(&claims, "exp")?.ok_or(Error::Expired)?;
+        if expires_at.saturating_add(leeway) <= now {
+            return Err(Error::Expired);
+        }
+        if let Some(not_before) = numeric_date(&claims, "nbf")? {
+            if not_before > now.saturating_add(leeway) {
+                return Err(Error::NotYetValid);
+            }
+        }
+
+        let token_issuer = string_claim(&claims, "iss")?;
+        if let Some(issuer) = issuer {
+            if token_issuer.as_deref() != Some(issuer) {
+                return Err(Error::InvalidClaim("issuer does not match".into()));
+            }
+        }
+
+        let token_audiences = match claims.get("aud") {
+            None => vec![],
+            Some(Value::String(aud)) => vec![aud.clone()],
+            Some(Value::Array(auds)) => auds
+                .iter()
+                .map(|aud| aud.as_str().map(str::to_owned))
+                .collect::<Option<_>>()
+                .ok_or_else(|| Error::Malformed("aud must be strings".into()))?,
+            Some(_) => return Err(Error::Malformed("aud must be strings".into())),
+        };
+        if !audiences.is_empty() && !token_audiences.iter().any(|aud| audiences.contains(aud)) {
+            return Err(Error::InvalidClaim("audience does not match".into()));
+        }
+
+        Ok(Claims {
+            subject: string_claim(&claims, "sub")?,
+            issuer: token_issuer,
+            audiences: token_audiences,
+            expires_at,
+            issued_at: numeric_date(&claims, "iat")?,
+            json: Value::Object(claims).to_string(),
+        })
+    }
+}
+
+fn numeric_date(claims: &Map<String, Value>, name: &str) -> Result<Option<u64>, Error> {
+    match claims.get(name) {
+        None => Ok(None),
+        // NumericDate may be fractional; whole seconds are precise enough
+        Some(Value::Number(n)) => match n.as_u64().or_else(|| n.as_f64().map(|f| f as u64)) {
+            Some(secs) => Ok(Some(secs)),
+            None => Err(Error::Malformed(format!("{name} must be a number"))),
+        },
+        Some(_) => Err(Error::Malformed(format!("{name} must be a number"))),
+    }
+}
+
+fn string_claim(claims: &Map<String, Value>, name: &str) -> Result<Option<String>, Error> {
+    match claims.get(name) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(Error::Malformed(format!("{name} must be a string"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(header: Value, claims: Value) -> String {
+        let encode = |v: Value| URL_SAFE_NO_PAD.encode(v.to_string());
+        format!("{}.{}.c2ln", encode(header), encode(claims))
+    }
+
+    fn validate(claims: Value, issuer: Option<&str>, audiences: &[&str]) -> Result<Claims, Error> {
+        let token = token(serde_json::json!({"alg": "RS256"}), claims);
+        let audiences: Vec<String> = audiences.iter().map(|a| a.to_string()).collect();
+        Token::decode(&token)?.validate_claims(1000, 10, issuer, &audiences)
+    }
+
+    #[test]
+    fn decodes_header_and_signature() {
+        let token = token(
+            serde_json::json!({"alg": "ES256", "kid": "k1"}),
+            serde_json::json!({}),
+        );
+        let token = Token::decode(&token).unwrap();
+        assert_eq!(token.header.alg, "ES256");
+        assert_eq!(token.header.kid.as_deref(), Some("k1"));
+        assert_eq!(token.signature, b"sig");
+    }
+
+    #[test]
+    fn rejects_malformed_tokens() {
+        assert!(matches!(Token::decode("abc"), Err(Error::Malformed(_))));
+        assert!(matches!(Token::decode("a.b.c"), Err(Error::Malformed(_))));
+    }
+
+    #[test]
+    fn unsafe_algorithms_are_unsupported() {
+        for alg in ["none", "HS256"] {
+            assert!(matches!(
+                Algorithm::parse(alg),
+                Err(Error::UnsupportedAlgorithm(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn checks_expiry_with_leeway() {
+        let claims = validate(serde_json::json!({"exp": 995, "sub": "me"}), None, &[]).unwrap();
+        assert_eq!(claims.subject.as_deref(), Some("me"));
+        assert!(matches!(
+            validate(serde_json::json!({"exp": 990}), None, &[]),
+            Err(Error::Expired)
+        ));
+        assert!(matches!(
+            validate(serde_json::json!({}), None, &[]),
+            Err(Error::Expired)
+        ));
+        assert!(matches!(
+            validate(serde_json::json!({"exp": 2000, "nbf": 1011}), None, &[]),
+            Err(Error::NotYetValid)
+        ));
+    }
+
+    #[test]
+    fn checks_issuer_and_audience() {
+        let claims = serde_json::json!({"exp": 2000, "iss": "me", "aud": ["a", "b"]});
+        let valid = validate(claims.clone(), Some("me"), &["b", "c"]).unwrap();
+        assert_eq!(valid.audiences, ["a", "b"]);
+        assert!(matches!(
+            validate(claims.clone(), Some("you"), &[]),
+            Err(Error::InvalidClaim(_))
+        ));
+        assert!(matches!(
+            validate(claims, None, &["c"]),
+            Err(Error::InvalidClaim(_))
+        ));
+    }
+}
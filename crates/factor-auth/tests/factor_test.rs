@@ -0,0 +1,72 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use spin_factor_auth::AuthFactor;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::auth::jwt::{Error, Host, Validation};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    auth: AuthFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        auth: AuthFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["https://idp.example.com"]
+    })
+}
+
+fn token(alg: &str) -> String {
+    let encode = |json: &str| URL_SAFE_NO_PAD.encode(json);
+    format!(
+        "{}.{}.c2ln",
+        encode(&format!(r#"{{"alg":"{alg}"}}"#)),
+        encode(r#"{"exp":4102444800}"#)
+    )
+}
+
+fn validation(jwks_url: &str) -> Validation {
+    Validation {
+        jwks_url: jwks_url.into(),
+        issuer: None,
+        audiences: vec![],
+        leeway_secs: None,
+    }
+}
+
+#[tokio::test]
+async fn jwks_url_must_be_allowed() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+    let result = state
+        .auth
+        .validate(
+            token("EdDSA"),
+            validation("https://evil.example.com/jwks.json"),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::AccessDenied)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsafe_algorithms_are_rejected_before_fetching_keys() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+    for alg in ["none", "HS256"] {
+        let result = state
+            .auth
+            .validate(token(alg), validation("https://idp.example.com/jwks.json"))
+            .await;
+        assert!(matches!(result, Err(Error::UnsupportedAlgorithm(_))));
+    }
+    Ok(())
+}
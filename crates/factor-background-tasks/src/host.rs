@@ -0,0 +1,82 @@
+use spin_world::spin::background::tasks::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::{Task, TaskQueue};
+
+pub struct InstanceState {
+    component_id: String,
+    queue: Option<TaskQueue>,
+    /// Tasks spawned by this instance, submitted when it is dropped.
+    spawned: Vec<Task>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(component_id: String, queue: Option<TaskQueue>) -> Self {
+        Self {
+            component_id,
+            queue,
+            spawned: vec![],
+        }
+    }
+}
+
+impl Drop for InstanceState {
+    fn drop(&mut self) {
+        // The instance is dropped once its invocation has finished, which for
+        // HTTP is once the response has been handed to the server.
+        if let Some(queue) = &self.queue {
+            for task in self.spawned.drain(..) {
+                queue.submit(task);
+            }
+        }
+    }
+}
+
+impl tasks::Host for InstanceState {
+    #[instrument(name = "spin_background_tasks.spawn", skip(self, payload), err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn spawn_after_response(&mut self, name: String, payload: Vec<u8>) -> Result<(), Error> {
+        let queue = self.queue.as_ref().ok_or(Error::NotSupported)?;
+        let task = queue
+            .reserve(&self.component_id, name, payload)
+            .ok_or(Error::QueueFull)?;
+        self.spawned.push(task);
+        Ok(())
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks::Host;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn submits_spawned_tasks_when_dropped() {
+        let (queue, mut rx) = TaskQueue::new(2);
+        let mut state = InstanceState::new("component".into(), Some(queue));
+        state
+            .spawn_after_response("a".into(), b"payload".to_vec())
+            .await
+            .unwrap();
+        state
+            .spawn_after_response("b".into(), vec![])
+            .await
+            .unwrap();
+        assert!(matches!(
+            state.spawn_after_response("c".into(), vec![]).await,
+            Err(Error::QueueFull)
+        ));
+        assert!(rx.try_recv().is_err());
+
+        drop(state);
+        let task = rx.try_recv().unwrap();
+        assert_eq!(task.component_id(), "component");
+        assert_eq!(task.name(), "a");
+        assert_eq!(task.payload(), b"payload");
+        assert_eq!(rx.try_recv().unwrap().name(), "b");
+    }
+}
@@ -0,0 +1,94 @@
+mod host;
+mod queue;
+pub mod runtime_config;
+
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use queue::{Task, TaskQueue, TaskReceiver};
+pub use runtime_config::{RetryPolicy, RuntimeConfig};
+
+/// A factor that lets components spawn tasks to run after their current
+/// invocation has finished.
+///
+/// Tasks are only run for triggers that provide a [`TaskQueue`] through
+/// [`InstanceBuilder::set_task_queue`]; elsewhere spawning a task fails.
+#[derive(Default)]
+pub struct BackgroundTasksFactor {
+    _priv: (),
+}
+
+impl BackgroundTasksFactor {
+    /// Create a new BackgroundTasksFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for BackgroundTasksFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::background::tasks::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState { config })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        Ok(InstanceBuilder {
+            component_id: ctx.app_component().id().to_string(),
+            queue: None,
+        })
+    }
+}
+
+pub struct AppState {
+    config: RuntimeConfig,
+}
+
+impl AppState {
+    /// The most tasks that may be waiting or running at once.
+    pub fn queue_capacity(&self) -> usize {
+        self.config.queue_capacity
+    }
+
+    /// How failed tasks are retried.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.config.retry_policy
+    }
+}
+
+pub struct InstanceBuilder {
+    component_id: String,
+    queue: Option<TaskQueue>,
+}
+
+impl InstanceBuilder {
+    /// Sets the queue that tasks spawned by this instance are submitted to
+    /// once the instance is dropped.
+    pub fn set_task_queue(&mut self, queue: TaskQueue) {
+        self.queue = Some(queue);
+    }
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.component_id, self.queue))
+    }
+}
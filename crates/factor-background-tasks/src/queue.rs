@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+/// A background task spawned by a component.
+#[derive(Debug)]
+pub struct Task {
+    component_id: String,
+    name: String,
+    payload: Vec<u8>,
+    /// Holds the task's place in the queue until it is dropped.
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Task {
+    /// The ID of the component that spawned the task, and that runs it.
+    pub fn component_id(&self) -> &str {
+        &self.component_id
+    }
+
+    /// The name the component gave the task.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+/// The sending side of a bounded queue of background tasks.
+///
+/// A task holds its place in the queue from when it is spawned until it is
+/// dropped, so the bound covers tasks that are waiting and tasks that are
+/// running.
+#[derive(Clone)]
+pub struct TaskQueue {
+    tx: mpsc::UnboundedSender<Task>,
+    permits: Arc<Semaphore>,
+}
+
+/// The receiving side of a [`TaskQueue`], from which tasks are run.
+pub type TaskReceiver = mpsc::UnboundedReceiver<Task>;
+
+impl TaskQueue {
+    /// Creates a queue holding at most `capacity` tasks.
+    pub fn new(capacity: usize) -> (Self, TaskReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let queue = Self {
+            tx,
+            permits: Arc::new(Semaphore::new(capacity)),
+        };
+        (queue, rx)
+    }
+
+    /// Creates a task if the queue has room for it.
+    ///
+    /// The task isn't sent until [`TaskQueue::submit`] is called.
+    pub(crate) fn reserve(
+        &self,
+        component_id: &str,
+        name: String,
+        payload: Vec<u8>,
+    ) -> Option<Task> {
+        let permit = self.permits.clone().try_acquire_owned().ok()?;
+        Some(Task {
+            component_id: component_id.to_owned(),
+            name,
+            payload,
+            _permit: permit,
+        })
+    }
+
+    /// Sends a task to be run.
+    pub(crate) fn submit(&self, task: Task) {
+        if let Err(mpsc::error::SendError(task)) = self.tx.send(task) {
+            tracing::warn!(
+                "dropping background task {:?} for component {}: no task runner",
+                task.name,
+                task.component_id
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_waiting_and_running_tasks() {
+        let (queue, mut rx) = TaskQueue::new(1);
+        let task = queue.reserve("c", "a".into(), vec![]).unwrap();
+        assert!(queue.reserve("c", "b".into(), vec![]).is_none());
+
+        queue.submit(task);
+        let task = rx.try_recv().unwrap();
+        assert_eq!(task.name(), "a");
+        assert!(queue.reserve("c", "b".into(), vec![]).is_none());
+
+        drop(task);
+        assert!(queue.reserve("c", "b".into(), vec![]).is_some());
+    }
+}
@@ -0,0 +1,85 @@
+pub mod spin;
+
+use std::time::Duration;
+
+/// Runtime configuration for background tasks.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The most tasks that may be waiting or running at once.
+    pub queue_capacity: usize,
+    /// How failed tasks are retried.
+    pub retry_policy: RetryPolicy,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1024,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+/// How failed tasks are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The most times a task is run, including the first.
+    pub max_attempts: u32,
+    /// How long to wait before the first retry. The wait doubles on each
+    /// further retry, up to [`RetryPolicy::MAX_BACKOFF`].
+    pub initial_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// The longest wait between attempts.
+    pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Returns how long to wait before running a task again after its
+    /// `attempt`th attempt failed, or `None` if it shouldn't be retried.
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(
+            self.initial_backoff
+                .saturating_mul(factor)
+                .min(Self::MAX_BACKOFF),
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_attempts_run_out() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+        };
+        assert_eq!(policy.backoff(1), Some(Duration::from_millis(100)));
+        assert_eq!(policy.backoff(2), Some(Duration::from_millis(200)));
+        assert_eq!(policy.backoff(3), Some(Duration::from_millis(400)));
+        assert_eq!(policy.backoff(4), None);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            initial_backoff: Duration::from_secs(1),
+        };
+        assert_eq!(policy.backoff(50), Some(RetryPolicy::MAX_BACKOFF));
+    }
+}
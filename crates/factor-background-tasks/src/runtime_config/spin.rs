@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{RetryPolicy, RuntimeConfig};
+
+/// Get the runtime configuration for background tasks from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [background_tasks]
+/// queue_capacity = 1024
+/// max_attempts = 3
+/// initial_backoff_ms = 500
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("background_tasks") else {
+        return Ok(None);
+    };
+    let toml: BackgroundTasksToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [background_tasks] table")?;
+    let defaults = RuntimeConfig::default();
+    let config = RuntimeConfig {
+        queue_capacity: toml.queue_capacity.unwrap_or(defaults.queue_capacity),
+        retry_policy: RetryPolicy {
+            max_attempts: toml
+                .max_attempts
+                .unwrap_or(defaults.retry_policy.max_attempts),
+            initial_backoff: toml
+                .initial_backoff_ms
+                .map_or(defaults.retry_policy.initial_backoff, Duration::from_millis),
+        },
+    };
+    ensure!(
+        config.queue_capacity > 0,
+        "background_tasks.queue_capacity must be greater than zero"
+    );
+    ensure!(
+        config.retry_policy.max_attempts > 0,
+        "background_tasks.max_attempts must be greater than zero"
+    );
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackgroundTasksToml {
+    queue_capacity: Option<usize>,
+    max_attempts: Option<u32>,
+    initial_backoff_ms: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unset_fields_use_defaults() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [background_tasks]
+            max_attempts = 5
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert_eq!(
+            config.queue_capacity,
+            RuntimeConfig::default().queue_capacity
+        );
+        assert_eq!(config.retry_policy.max_attempts, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_empty_queue() {
+        let table: toml::Table = toml::toml! {
+            [background_tasks]
+            queue_capacity = 0
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
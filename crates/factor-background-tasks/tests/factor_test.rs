@@ -0,0 +1,30 @@
+use spin_factor_background_tasks::BackgroundTasksFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::background::tasks::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    background_tasks: BackgroundTasksFactor,
+}
+
+#[tokio::test]
+async fn spawning_fails_without_a_task_queue() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        background_tasks: BackgroundTasksFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+
+    assert!(matches!(
+        state
+            .background_tasks
+            .spawn_after_response("task".into(), vec![])
+            .await,
+        Err(Error::NotSupported)
+    ));
+    Ok(())
+}
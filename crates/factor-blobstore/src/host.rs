@@ -0,0 +1,598 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use spin_factors::{
+    wasmtime::component::{Resource, ResourceTable},
+    InitContext,
+};
+use spin_resource_table::Table;
+use spin_world::spin::blobstore::presign;
+use spin_world::wasi::blobstore::{
+    blobstore,
+    container::{self, Container as ContainerResource, StreamObjectNames},
+    types::{
+        self, ContainerMetadata, IncomingValue, InputStream, ObjectId, ObjectMetadata,
+        OutgoingValue as OutgoingValueResource, OutputStream,
+    },
+};
+use tokio::io::{AsyncReadExt, DuplexStream};
+use tracing::{instrument, Level};
+use wasmtime_wasi::{
+    p2::pipe::{AsyncReadStream, AsyncWriteStream},
+    runtime::AbortOnDropJoinHandle,
+};
+
+use crate::{
+    BlobStoreFactor, Container, ContainerManager, IncomingData, ObjectNames, PresignMethod,
+    PresignUnsupported,
+};
+
+const DEFAULT_TABLE_CAPACITY: u32 = 256;
+
+/// The capacity of the pipe between an outgoing value's body stream and the
+/// upload to the backing store.
+const OUTGOING_VALUE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The longest a pre-signed URL can be valid for, which is the longest S3
+/// allows.
+const MAX_PRESIGN_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+pub(crate) fn add_to_linker<C>(ctx: &mut C) -> anyhow::Result<()>
+where
+    C: InitContext<BlobStoreFactor>,
+{
+    fn get_blobstore<C>(store: &mut C::StoreData) -> BlobStoreDispatch<'_>
+    where
+        C: InitContext<BlobStoreFactor>,
+    {
+        let (state, table) = C::get_data_with_table(store);
+        BlobStoreDispatch { state, table }
+    }
+    let get_blobstore = get_blobstore::<C> as fn(&mut C::StoreData) -> BlobStoreDispatch<'_>;
+    let linker = ctx.linker();
+    blobstore::add_to_linker_get_host(linker, get_blobstore)?;
+    container::add_to_linker_get_host(linker, get_blobstore)?;
+    types::add_to_linker_get_host(linker, get_blobstore)?;
+    presign::add_to_linker_get_host(linker, get_blobstore)?;
+    Ok(())
+}
+
+pub struct InstanceState {
+    allowed_containers: HashSet<String>,
+    container_managers: Arc<HashMap<String, Arc<dyn ContainerManager>>>,
+    containers: Table<Arc<dyn Container>>,
+    incoming_values: Table<IncomingData>,
+    outgoing_values: Table<OutgoingValue>,
+    object_names: Table<Box<dyn ObjectNames>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_containers: HashSet<String>,
+        container_managers: Arc<HashMap<String, Arc<dyn ContainerManager>>>,
+    ) -> Self {
+        Self {
+            allowed_containers,
+            container_managers,
+            containers: Table::new(DEFAULT_TABLE_CAPACITY),
+            incoming_values: Table::new(DEFAULT_TABLE_CAPACITY),
+            outgoing_values: Table::new(DEFAULT_TABLE_CAPACITY),
+            object_names: Table::new(DEFAULT_TABLE_CAPACITY),
+        }
+    }
+
+    /// Returns the set of container labels this instance may access.
+    pub fn allowed_containers(&self) -> &HashSet<String> {
+        &self.allowed_containers
+    }
+
+    /// Returns the manager for the given container, if this instance may access it.
+    fn container_manager(&self, name: &str) -> Result<Arc<dyn ContainerManager>, String> {
+        if !self.allowed_containers.contains(name) {
+            return Err(format!("access to container {name:?} is not allowed"));
+        }
+        self.container_managers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("no such container {name:?}"))
+    }
+
+    /// Opens the given container, if this instance may access it.
+    ///
+    /// The returned future does not borrow `self`, so it can be awaited while
+    /// the instance state is otherwise in use.
+    fn open_container(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<Arc<dyn Container>, String>> + Send + 'static {
+        let manager = self.container_manager(name);
+        let name = name.to_owned();
+        async move { manager?.get(&name).await.map_err(|e| e.to_string()) }
+    }
+
+    fn get_container(&self, container: &Resource<ContainerResource>) -> Result<Arc<dyn Container>> {
+        self.containers
+            .get(container.rep())
+            .cloned()
+            .context("invalid container")
+    }
+
+    /// Copies an object, streaming its data between containers.
+    fn copy_object(
+        &self,
+        src: ObjectId,
+        dest: ObjectId,
+    ) -> impl Future<Output = Result<(), String>> + Send + 'static {
+        let src_container = self.open_container(&src.container);
+        let dest_container = self.open_container(&dest.container);
+        async move {
+            let src_container = src_container.await?;
+            let dest_container = dest_container.await?;
+            let data = src_container
+                .get_data(&src.object, 0, u64::MAX)
+                .await
+                .map_err(|e| e.to_string())?;
+            dest_container
+                .write_data(&dest.object, data.into_reader())
+                .await
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// The host side of an `outgoing-value`.
+///
+/// The guest writes the value through the write half of a pipe, and
+/// `write-data` streams the read half to the backing store.
+pub(crate) struct OutgoingValue {
+    reader: Option<DuplexStream>,
+    writer: Option<DuplexStream>,
+    /// The resource table rep of the body `output-stream`, once it has been taken.
+    body_rep: Option<u32>,
+    upload: Option<AbortOnDropJoinHandle<Result<()>>>,
+}
+
+impl OutgoingValue {
+    fn new() -> Self {
+        let (writer, reader) = tokio::io::duplex(OUTGOING_VALUE_BUFFER_SIZE);
+        Self {
+            reader: Some(reader),
+            writer: Some(writer),
+            body_rep: None,
+            upload: None,
+        }
+    }
+}
+
+pub(crate) struct BlobStoreDispatch<'a> {
+    state: &'a mut InstanceState,
+    table: &'a mut ResourceTable,
+}
+
+impl BlobStoreDispatch<'_> {
+    fn take_outgoing_value(&mut self, rep: u32) -> Result<OutgoingValue> {
+        let value = self
+            .state
+            .outgoing_values
+            .remove(rep)
+            .context("invalid outgoing-value")?;
+        if let Some(body_rep) = value.body_rep {
+            let body = Resource::<OutputStream>::new_borrow(body_rep);
+            anyhow::ensure!(
+                self.table.get(&body).is_err(),
+                "the output-stream of an outgoing-value must be dropped before the outgoing-value"
+            );
+        }
+        Ok(value)
+    }
+}
+
+impl blobstore::Host for BlobStoreDispatch<'_> {
+    async fn create_container(
+        &mut self,
+        name: String,
+    ) -> Result<Result<Resource<ContainerResource>, String>> {
+        Ok(Err(format!(
+            "cannot create container {name:?}: containers must be configured in runtime config"
+        )))
+    }
+
+    #[instrument(name = "spin_blobstore.get_container", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_container(
+        &mut self,
+        name: String,
+    ) -> Result<Result<Resource<ContainerResource>, String>> {
+        let container = match self.state.open_container(&name).await {
+            Ok(c) => c,
+            Err(e) => return Ok(Err(e)),
+        };
+        let rep = self
+            .state
+            .containers
+            .push(container)
+            .map_err(|()| anyhow::anyhow!("too many open containers"))?;
+        Ok(Ok(Resource::new_own(rep)))
+    }
+
+    async fn delete_container(&mut self, name: String) -> Result<Result<(), String>> {
+        Ok(Err(format!(
+            "cannot delete container {name:?}: containers must be configured in runtime config"
+        )))
+    }
+
+    #[instrument(name = "spin_blobstore.container_exists", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn container_exists(&mut self, name: String) -> Result<Result<bool, String>> {
+        let container = match self.state.open_container(&name).await {
+            Ok(c) => c,
+            Err(e) => return Ok(Err(e)),
+        };
+        Ok(container.exists().await.map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.copy_object", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn copy_object(&mut self, src: ObjectId, dest: ObjectId) -> Result<Result<(), String>> {
+        Ok(self.state.copy_object(src, dest).await)
+    }
+
+    #[instrument(name = "spin_blobstore.move_object", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn move_object(&mut self, src: ObjectId, dest: ObjectId) -> Result<Result<(), String>> {
+        let src_container = self.state.open_container(&src.container);
+        let copy = self.state.copy_object(src.clone(), dest);
+        Ok(async {
+            copy.await?;
+            src_container
+                .await?
+                .delete_object(&src.object)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        .await)
+    }
+}
+
+impl presign::Host for BlobStoreDispatch<'_> {
+    #[instrument(name = "spin_blobstore.presign_url", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn presign_url(
+        &mut self,
+        container: String,
+        object: String,
+        method: presign::Method,
+        expires_in_secs: u32,
+    ) -> Result<String, presign::Error> {
+        let expires_in = presign_expiry(expires_in_secs)?;
+        if !self.state.allowed_containers.contains(&container) {
+            return Err(presign::Error::AccessDenied);
+        }
+        let method = match method {
+            presign::Method::Get => PresignMethod::Get,
+            presign::Method::Put => PresignMethod::Put,
+        };
+        let container = self
+            .state
+            .open_container(&container)
+            .await
+            .map_err(presign::Error::Other)?;
+        container
+            .presign(&object, method, expires_in)
+            .await
+            .map_err(|err| {
+                if err.is::<PresignUnsupported>() {
+                    presign::Error::Unsupported
+                } else {
+                    presign::Error::Other(format!("{err:#}"))
+                }
+            })
+    }
+
+    fn convert_error(&mut self, error: presign::Error) -> anyhow::Result<presign::Error> {
+        Ok(error)
+    }
+}
+
+fn presign_expiry(expires_in_secs: u32) -> Result<Duration, presign::Error> {
+    let expires_in = Duration::from_secs(expires_in_secs.into());
+    if expires_in.is_zero() || expires_in > MAX_PRESIGN_EXPIRY {
+        return Err(presign::Error::InvalidExpiry(format!(
+            "expiry must be between 1 and {} seconds",
+            MAX_PRESIGN_EXPIRY.as_secs()
+        )));
+    }
+    Ok(expires_in)
+}
+
+impl container::Host for BlobStoreDispatch<'_> {}
+
+impl container::HostContainer for BlobStoreDispatch<'_> {
+    async fn name(&mut self, self_: Resource<ContainerResource>) -> Result<Result<String, String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(Ok(container.name().await))
+    }
+
+    #[instrument(name = "spin_blobstore.info", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn info(
+        &mut self,
+        self_: Resource<ContainerResource>,
+    ) -> Result<Result<ContainerMetadata, String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container.info().await.map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.get_data", skip(self, self_), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_data(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        name: String,
+        start: u64,
+        end: u64,
+    ) -> Result<Result<Resource<IncomingValue>, String>> {
+        let container = self.state.get_container(&self_)?;
+        let data = match container.get_data(&name, start, end).await {
+            Ok(data) => data,
+            Err(e) => return Ok(Err(e.to_string())),
+        };
+        let rep = self
+            .state
+            .incoming_values
+            .push(data)
+            .map_err(|()| anyhow::anyhow!("too many incoming values"))?;
+        Ok(Ok(Resource::new_own(rep)))
+    }
+
+    #[instrument(name = "spin_blobstore.write_data", skip(self, self_, data), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn write_data(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        name: String,
+        data: Resource<OutgoingValueResource>,
+    ) -> Result<Result<(), String>> {
+        let container = self.state.get_container(&self_)?;
+        let value = self
+            .state
+            .outgoing_values
+            .get_mut(data.rep())
+            .context("invalid outgoing-value")?;
+        let Some(reader) = value.reader.take() else {
+            return Ok(Err("outgoing value has already been written".to_string()));
+        };
+        value.upload = Some(wasmtime_wasi::runtime::spawn(async move {
+            container.write_data(&name, Box::pin(reader)).await
+        }));
+        Ok(Ok(()))
+    }
+
+    #[instrument(name = "spin_blobstore.list_objects", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn list_objects(
+        &mut self,
+        self_: Resource<ContainerResource>,
+    ) -> Result<Result<Resource<StreamObjectNames>, String>> {
+        let container = self.state.get_container(&self_)?;
+        let names = match container.list_objects().await {
+            Ok(names) => names,
+            Err(e) => return Ok(Err(e.to_string())),
+        };
+        let rep = self
+            .state
+            .object_names
+            .push(names)
+            .map_err(|()| anyhow::anyhow!("too many object name streams"))?;
+        Ok(Ok(Resource::new_own(rep)))
+    }
+
+    #[instrument(name = "spin_blobstore.delete_object", skip(self, self_), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn delete_object(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        name: String,
+    ) -> Result<Result<(), String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container
+            .delete_object(&name)
+            .await
+            .map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.delete_objects", skip(self, self_), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn delete_objects(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        names: Vec<String>,
+    ) -> Result<Result<(), String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container
+            .delete_objects(&names)
+            .await
+            .map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.has_object", skip(self, self_), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn has_object(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        name: String,
+    ) -> Result<Result<bool, String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container.has_object(&name).await.map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.object_info", skip(self, self_), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn object_info(
+        &mut self,
+        self_: Resource<ContainerResource>,
+        name: String,
+    ) -> Result<Result<ObjectMetadata, String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container
+            .object_info(&name)
+            .await
+            .map_err(|e| e.to_string()))
+    }
+
+    #[instrument(name = "spin_blobstore.clear", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn clear(&mut self, self_: Resource<ContainerResource>) -> Result<Result<(), String>> {
+        let container = self.state.get_container(&self_)?;
+        Ok(container.clear().await.map_err(|e| e.to_string()))
+    }
+
+    async fn drop(&mut self, rep: Resource<ContainerResource>) -> Result<()> {
+        self.state.containers.remove(rep.rep());
+        Ok(())
+    }
+}
+
+impl container::HostStreamObjectNames for BlobStoreDispatch<'_> {
+    async fn read_stream_object_names(
+        &mut self,
+        self_: Resource<StreamObjectNames>,
+        len: u64,
+    ) -> Result<Result<(Vec<String>, bool), String>> {
+        let names = self
+            .state
+            .object_names
+            .get_mut(self_.rep())
+            .context("invalid stream-object-names")?;
+        Ok(names.read(len).await.map_err(|e| e.to_string()))
+    }
+
+    async fn skip_stream_object_names(
+        &mut self,
+        self_: Resource<StreamObjectNames>,
+        num: u64,
+    ) -> Result<Result<(u64, bool), String>> {
+        let names = self
+            .state
+            .object_names
+            .get_mut(self_.rep())
+            .context("invalid stream-object-names")?;
+        Ok(names.skip(num).await.map_err(|e| e.to_string()))
+    }
+
+    async fn drop(&mut self, rep: Resource<StreamObjectNames>) -> Result<()> {
+        self.state.object_names.remove(rep.rep());
+        Ok(())
+    }
+}
+
+impl types::Host for BlobStoreDispatch<'_> {}
+
+impl types::HostOutgoingValue for BlobStoreDispatch<'_> {
+    async fn new_outgoing_value(&mut self) -> Result<Resource<OutgoingValueResource>> {
+        let rep = self
+            .state
+            .outgoing_values
+            .push(OutgoingValue::new())
+            .map_err(|()| anyhow::anyhow!("too many outgoing values"))?;
+        Ok(Resource::new_own(rep))
+    }
+
+    async fn outgoing_value_write_body(
+        &mut self,
+        self_: Resource<OutgoingValueResource>,
+    ) -> Result<Result<Resource<OutputStream>, ()>> {
+        let value = self
+            .state
+            .outgoing_values
+            .get_mut(self_.rep())
+            .context("invalid outgoing-value")?;
+        let Some(writer) = value.writer.take() else {
+            return Ok(Err(()));
+        };
+        let stream: OutputStream =
+            Box::new(AsyncWriteStream::new(OUTGOING_VALUE_BUFFER_SIZE, writer));
+        let body = self.table.push(stream)?;
+        value.body_rep = Some(body.rep());
+        Ok(Ok(body))
+    }
+
+    #[instrument(name = "spin_blobstore.finish", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn finish(
+        &mut self,
+        this: Resource<OutgoingValueResource>,
+    ) -> Result<Result<(), String>> {
+        let OutgoingValue { writer, upload, .. } = self.take_outgoing_value(this.rep())?;
+        // An unused writer would keep the upload from ever seeing end of stream.
+        drop(writer);
+        let Some(upload) = upload else {
+            return Ok(Err(
+                "outgoing value was not written to a container".to_string()
+            ));
+        };
+        Ok(upload.await.map_err(|e| e.to_string()))
+    }
+
+    async fn drop(&mut self, rep: Resource<OutgoingValueResource>) -> Result<()> {
+        // Dropping the value without finishing it aborts any pending upload.
+        self.take_outgoing_value(rep.rep())?;
+        Ok(())
+    }
+}
+
+impl types::HostIncomingValue for BlobStoreDispatch<'_> {
+    async fn incoming_value_consume_sync(
+        &mut self,
+        this: Resource<IncomingValue>,
+    ) -> Result<Result<Vec<u8>, String>> {
+        let data = self
+            .state
+            .incoming_values
+            .remove(this.rep())
+            .context("invalid incoming-value")?;
+        let mut buf = Vec::new();
+        Ok(data
+            .into_reader()
+            .read_to_end(&mut buf)
+            .await
+            .map(|_| buf)
+            .map_err(|e| e.to_string()))
+    }
+
+    async fn incoming_value_consume_async(
+        &mut self,
+        this: Resource<IncomingValue>,
+    ) -> Result<Result<Resource<InputStream>, String>> {
+        let data = self
+            .state
+            .incoming_values
+            .remove(this.rep())
+            .context("invalid incoming-value")?;
+        let stream: InputStream = Box::new(AsyncReadStream::new(data.into_reader()));
+        Ok(Ok(self.table.push(stream)?))
+    }
+
+    async fn size(&mut self, self_: Resource<IncomingValue>) -> Result<u64> {
+        let data = self
+            .state
+            .incoming_values
+            .get(self_.rep())
+            .context("invalid incoming-value")?;
+        Ok(data.size())
+    }
+
+    async fn drop(&mut self, rep: Resource<IncomingValue>) -> Result<()> {
+        self.state.incoming_values.remove(rep.rep());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presign_expiry_is_bounded() {
+        assert_eq!(presign_expiry(3600).unwrap(), Duration::from_secs(3600));
+        assert!(presign_expiry(604_800).is_ok());
+        assert!(matches!(
+            presign_expiry(0),
+            Err(presign::Error::InvalidExpiry(_))
+        ));
+        assert!(matches!(
+            presign_expiry(604_801),
+            Err(presign::Error::InvalidExpiry(_))
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+mod host;
+pub mod runtime_config;
+mod store;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::ensure;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+pub use store::{
+    Container, ContainerManager, IncomingData, ObjectNames, ObjectReader, PresignMethod,
+    PresignUnsupported, StreamObjectNames,
+};
+
+/// Metadata key for blob containers.
+pub const BLOB_CONTAINERS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("blob_containers");
+
+/// A factor that provides blob storage.
+#[derive(Default)]
+pub struct BlobStoreFactor {
+    _priv: (),
+}
+
+impl BlobStoreFactor {
+    /// Create a new BlobStoreFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for BlobStoreFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        host::add_to_linker(ctx)
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        let container_managers: HashMap<_, _> = runtime_config.into_iter().collect();
+
+        // Build component -> allowed containers map
+        let mut component_allowed_containers = HashMap::new();
+        for component in ctx.app().components() {
+            let component_id = component.id().to_string();
+            let containers = component
+                .get_metadata(BLOB_CONTAINERS_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for label in &containers {
+                ensure!(
+                    container_managers.contains_key(label),
+                    "unknown blob_containers label {label:?} for component {component_id:?}"
+                );
+            }
+            component_allowed_containers.insert(component_id, containers);
+        }
+
+        Ok(AppState {
+            container_managers: Arc::new(container_managers),
+            component_allowed_containers,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_containers = app_state
+            .component_allowed_containers
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_containers")
+            .clone();
+        Ok(InstanceBuilder {
+            container_managers: app_state.container_managers.clone(),
+            allowed_containers,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The container managers for the app, keyed by container label.
+    container_managers: Arc<HashMap<String, Arc<dyn ContainerManager>>>,
+    /// The allowed containers for each component.
+    ///
+    /// This is a map from component ID to the set of container labels that
+    /// the component is allowed to use.
+    component_allowed_containers: HashMap<String, HashSet<String>>,
+}
+
+impl AppState {
+    /// Returns the [`ContainerManager::summary`] for the given container label.
+    pub fn container_summary(&self, label: &str) -> Option<String> {
+        self.container_managers.get(label)?.summary(label)
+    }
+
+    /// Returns true if the given container label is used by any component.
+    pub fn container_is_used(&self, label: &str) -> bool {
+        self.component_allowed_containers
+            .values()
+            .any(|containers| containers.contains(label))
+    }
+}
+
+pub struct InstanceBuilder {
+    /// The container managers for the app, keyed by container label.
+    container_managers: Arc<HashMap<String, Arc<dyn ContainerManager>>>,
+    /// The allowed containers for this component instance.
+    allowed_containers: HashSet<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.allowed_containers,
+            self.container_managers,
+        ))
+    }
+}
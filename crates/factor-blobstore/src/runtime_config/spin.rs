@@ -0,0 +1,132 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use crate::{ContainerManager, RuntimeConfig};
+use anyhow::Context as _;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+use std::{collections::HashMap, sync::Arc};
+
+/// Defines the construction of a blob store from a serialized runtime config.
+pub trait MakeBlobStore: 'static + Send + Sync {
+    /// Unique type identifier for the store.
+    const RUNTIME_CONFIG_TYPE: &'static str;
+    /// Runtime configuration for the store.
+    ///
+    /// Its JSON schema documents and validates the store's runtime config.
+    type RuntimeConfig: DeserializeOwned + JsonSchema;
+    /// The container manager for the store.
+    type ContainerManager: ContainerManager;
+
+    /// Creates a new container manager from the runtime configuration.
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::ContainerManager>;
+}
+
+/// A function that creates a container manager from a TOML table.
+type StoreFromToml =
+    Arc<dyn Fn(toml::Table) -> anyhow::Result<Arc<dyn ContainerManager>> + Send + Sync>;
+
+/// Creates a `StoreFromToml` function from a `MakeBlobStore` implementation.
+fn store_from_toml_fn<T: MakeBlobStore>(provider_type: T) -> StoreFromToml {
+    Arc::new(move |table| {
+        let runtime_config: T::RuntimeConfig = table
+            .try_into()
+            .context("could not parse blob store runtime config")?;
+        let provider = provider_type
+            .make_store(runtime_config)
+            .context("could not make blob store from runtime config")?;
+        Ok(Arc::new(provider))
+    })
+}
+
+/// Converts from toml based runtime configuration into a [`RuntimeConfig`].
+///
+/// The various store types (i.e., the "type" field in the toml field) are
+/// registered with the resolver using `register_store_type`.
+#[derive(Default, Clone)]
+pub struct RuntimeConfigResolver {
+    /// A map of store types to a function that returns the appropriate
+    /// container manager from runtime config TOML.
+    store_types: HashMap<&'static str, StoreFromToml>,
+    /// A map of store types to the JSON schema of their runtime config.
+    schemas: HashMap<&'static str, RootSchema>,
+}
+
+impl RuntimeConfigResolver {
+    /// Create a new RuntimeConfigResolver.
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Registers a store type to the resolver.
+    pub fn register_store_type<T: MakeBlobStore>(&mut self, store_type: T) -> anyhow::Result<()> {
+        if self
+            .store_types
+            .insert(T::RUNTIME_CONFIG_TYPE, store_from_toml_fn(store_type))
+            .is_some()
+        {
+            anyhow::bail!("duplicate blob store type {:?}", T::RUNTIME_CONFIG_TYPE);
+        }
+        self.schemas.insert(
+            T::RUNTIME_CONFIG_TYPE,
+            schemars::schema_for!(T::RuntimeConfig),
+        );
+        Ok(())
+    }
+
+    /// The JSON schemas of the registered store types' runtime config, by
+    /// store type.
+    pub fn store_type_schemas(&self) -> impl Iterator<Item = (&'static str, &RootSchema)> {
+        self.schemas
+            .iter()
+            .map(|(&store_type, schema)| (store_type, schema))
+    }
+
+    /// Resolves a toml table into a runtime config.
+    pub fn resolve(&self, table: Option<&impl GetTomlValue>) -> anyhow::Result<RuntimeConfig> {
+        let Some(table) = table.and_then(|t| t.get("blob_container")) else {
+            return Ok(RuntimeConfig::default());
+        };
+        let table: HashMap<String, StoreConfig> = table.clone().try_into()?;
+
+        let mut runtime_config = RuntimeConfig::default();
+        for (label, config) in table {
+            let container_manager =
+                self.container_manager_from_config(config)
+                    .with_context(|| {
+                        format!("could not configure blob container with label '{label}'")
+                    })?;
+            runtime_config.add_container_manager(label, container_manager);
+        }
+        Ok(runtime_config)
+    }
+
+    /// Given a [`StoreConfig`], returns a container manager.
+    ///
+    /// Errors if there is no [`MakeBlobStore`] registered for the store config's type
+    /// or if the container manager cannot be created from the config.
+    fn container_manager_from_config(
+        &self,
+        config: StoreConfig,
+    ) -> anyhow::Result<Arc<dyn ContainerManager>> {
+        let config_type = config.type_.as_str();
+        let maker = self.store_types.get(config_type).with_context(|| {
+            format!(
+                "the blob store type '{config_type}' was not registered with the config resolver"
+            )
+        })?;
+        maker(config.config)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StoreConfig {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(flatten)]
+    pub config: toml::Table,
+}
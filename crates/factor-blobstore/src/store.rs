@@ -0,0 +1,202 @@
+use std::{pin::Pin, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures::{stream::BoxStream, StreamExt};
+use spin_core::async_trait;
+use spin_world::wasi::blobstore::types::{ContainerMetadata, ObjectMetadata};
+use tokio::io::AsyncRead;
+
+/// A reader over the contents of an object.
+pub type ObjectReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Provides access to the containers configured for an app.
+#[async_trait]
+pub trait ContainerManager: Sync + Send {
+    /// Returns a handle to the container with the given name.
+    async fn get(&self, name: &str) -> Result<Arc<dyn Container>>;
+
+    /// A human-readable summary of the given container's configuration
+    ///
+    /// Example: "AWS S3 bucket: my-bucket"
+    fn summary(&self, container_name: &str) -> Option<String> {
+        let _ = container_name;
+        None
+    }
+}
+
+/// A container of objects in a blob store.
+#[async_trait]
+pub trait Container: Sync + Send {
+    /// Returns whether the container exists in the backing store.
+    async fn exists(&self) -> Result<bool>;
+    /// Returns the name of the container.
+    async fn name(&self) -> String;
+    /// Returns metadata for the container.
+    async fn info(&self) -> Result<ContainerMetadata>;
+    /// Removes all objects from the container.
+    async fn clear(&self) -> Result<()>;
+    /// Deletes an object. Deleting a non-existent object is not an error.
+    async fn delete_object(&self, name: &str) -> Result<()>;
+    /// Deletes multiple objects.
+    async fn delete_objects(&self, names: &[String]) -> Result<()>;
+    /// Returns whether the object exists.
+    async fn has_object(&self, name: &str) -> Result<bool>;
+    /// Returns metadata for an object.
+    async fn object_info(&self, name: &str) -> Result<ObjectMetadata>;
+    /// Reads the byte range `start..=end` of an object.
+    async fn get_data(&self, name: &str, start: u64, end: u64) -> Result<IncomingData>;
+    /// Creates or replaces an object with the contents of `data`.
+    ///
+    /// `data` is read until it reaches end of stream.
+    async fn write_data(&self, name: &str, data: ObjectReader) -> Result<()>;
+    /// Lists the names of the objects in the container.
+    async fn list_objects(&self) -> Result<Box<dyn ObjectNames>>;
+    /// Returns a URL which allows anyone holding it to access an object with
+    /// `method`, without credentials, until `expires_in` has passed.
+    ///
+    /// Stores which can't sign URLs fail with [`PresignUnsupported`].
+    async fn presign(
+        &self,
+        name: &str,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<String> {
+        let _ = (name, method, expires_in);
+        Err(PresignUnsupported.into())
+    }
+}
+
+/// What a pre-signed URL allows its holder to do with an object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresignMethod {
+    /// Download the object with a `GET` request.
+    Get,
+    /// Create or replace the object with a `PUT` request.
+    Put,
+}
+
+/// The error returned by [`Container::presign`] for stores which can't sign
+/// URLs.
+#[derive(Debug)]
+pub struct PresignUnsupported;
+
+impl std::fmt::Display for PresignUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the blob store does not support pre-signed URLs")
+    }
+}
+
+impl std::error::Error for PresignUnsupported {}
+
+/// Data read from an object, along with the number of bytes it holds.
+pub struct IncomingData {
+    size: u64,
+    reader: ObjectReader,
+}
+
+impl IncomingData {
+    pub fn new(size: u64, reader: ObjectReader) -> Self {
+        Self { size, reader }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn into_reader(self) -> ObjectReader {
+        self.reader
+    }
+}
+
+/// A cursor over the names of the objects in a container.
+#[async_trait]
+pub trait ObjectNames: Send {
+    /// Reads up to `len` names, returning them along with whether the end of
+    /// the listing was reached.
+    async fn read(&mut self, len: u64) -> Result<(Vec<String>, bool)>;
+    /// Skips up to `num` names, returning the number skipped along with
+    /// whether the end of the listing was reached.
+    async fn skip(&mut self, num: u64) -> Result<(u64, bool)>;
+}
+
+/// An [`ObjectNames`] implementation over a stream of names, as produced by
+/// paginated listing APIs.
+pub struct StreamObjectNames {
+    stream: futures::stream::Peekable<BoxStream<'static, Result<String>>>,
+}
+
+impl StreamObjectNames {
+    pub fn new(stream: BoxStream<'static, Result<String>>) -> Self {
+        Self {
+            stream: stream.peekable(),
+        }
+    }
+
+    async fn at_end(&mut self) -> bool {
+        Pin::new(&mut self.stream).peek().await.is_none()
+    }
+}
+
+#[async_trait]
+impl ObjectNames for StreamObjectNames {
+    async fn read(&mut self, len: u64) -> Result<(Vec<String>, bool)> {
+        let mut names = Vec::new();
+        while (names.len() as u64) < len {
+            match self.stream.next().await {
+                Some(name) => names.push(name?),
+                None => return Ok((names, true)),
+            }
+        }
+        Ok((names, self.at_end().await))
+    }
+
+    async fn skip(&mut self, num: u64) -> Result<(u64, bool)> {
+        let mut skipped = 0;
+        while skipped < num {
+            match self.stream.next().await {
+                Some(name) => {
+                    name?;
+                    skipped += 1;
+                }
+                None => return Ok((skipped, true)),
+            }
+        }
+        Ok((skipped, self.at_end().await))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(n: usize) -> StreamObjectNames {
+        let names = (0..n).map(|i| Ok(format!("object-{i}")));
+        StreamObjectNames::new(futures::stream::iter(names).boxed())
+    }
+
+    #[tokio::test]
+    async fn read_reports_end_of_stream() -> Result<()> {
+        let mut names = names(3);
+        assert_eq!(
+            names.read(2).await?,
+            (vec!["object-0".to_owned(), "object-1".to_owned()], false)
+        );
+        assert_eq!(names.read(2).await?, (vec!["object-2".to_owned()], true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_of_exact_remainder_reports_end_of_stream() -> Result<()> {
+        let mut names = names(2);
+        assert!(names.read(2).await?.1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skip_counts_skipped_names() -> Result<()> {
+        let mut names = names(3);
+        assert_eq!(names.skip(2).await?, (2, false));
+        assert_eq!(names.skip(5).await?, (1, true));
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::bail;
+use spin_core::async_trait;
+use spin_factor_blobstore::{BlobStoreFactor, Container, ContainerManager, RuntimeConfig};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    blob_store: BlobStoreFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            blob_store: Some(value),
+        }
+    }
+}
+
+#[tokio::test]
+async fn works_when_allowed_container_is_defined() -> anyhow::Result<()> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_container_manager("uploads".into(), Arc::new(MockContainerManager));
+    let env = TestEnvironment::new(TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        blob_containers = ["uploads"]
+    });
+    let state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.blob_store.allowed_containers(),
+        &["uploads".into()].into_iter().collect::<HashSet<_>>()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_container_is_not_defined() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        blob_containers = ["uploads"]
+    });
+    let Err(err) = env
+        .runtime_config(RuntimeConfig::default())?
+        .build_instance_state()
+        .await
+    else {
+        bail!("expected instance build to fail but it didn't");
+    };
+
+    assert!(err
+        .to_string()
+        .contains(r#"unknown blob_containers label "uploads""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_containers_allowed_by_default() -> anyhow::Result<()> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_container_manager("uploads".into(), Arc::new(MockContainerManager));
+    let env = TestEnvironment::new(TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    });
+    let state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    assert!(state.blob_store.allowed_containers().is_empty());
+    Ok(())
+}
+
+struct MockContainerManager;
+
+#[async_trait]
+impl ContainerManager for MockContainerManager {
+    async fn get(&self, _name: &str) -> anyhow::Result<Arc<dyn Container>> {
+        bail!("not implemented")
+    }
+}
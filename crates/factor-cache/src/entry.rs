@@ -0,0 +1,103 @@
+//! The encoding of cache entries and fill locks in a key-value store.
+
+use std::time::Duration;
+
+/// Prefix for the keys of cache entries in the backing store.
+const ENTRY_KEY_PREFIX: &str = "spin-cache:entry:";
+/// Prefix for the keys of fill locks in the backing store.
+const LOCK_KEY_PREFIX: &str = "spin-cache:lock:";
+
+/// Returns the backing store key of the entry for `key`.
+pub(crate) fn entry_key(key: &str) -> String {
+    format!("{ENTRY_KEY_PREFIX}{key}")
+}
+
+/// Returns the backing store key of the fill lock for `key`.
+pub(crate) fn lock_key(key: &str) -> String {
+    format!("{LOCK_KEY_PREFIX}{key}")
+}
+
+/// Encodes a value as a big-endian expiry time, with zero meaning never,
+/// followed by the value itself.
+pub(crate) fn encode_entry(value: &[u8], ttl: Option<Duration>, now: u64) -> Vec<u8> {
+    let expires_at = ttl.map_or(0, |ttl| {
+        now.saturating_add(ttl.as_millis().try_into().unwrap_or(u64::MAX))
+            .max(1)
+    });
+    let mut entry = Vec::with_capacity(8 + value.len());
+    entry.extend_from_slice(&expires_at.to_be_bytes());
+    entry.extend_from_slice(value);
+    entry
+}
+
+/// Decodes an entry, returning `None` if it has expired or is malformed.
+pub(crate) fn decode_entry(mut entry: Vec<u8>, now: u64) -> Option<Vec<u8>> {
+    let expires_at = u64::from_be_bytes(entry.get(..8)?.try_into().ok()?);
+    if expires_at != 0 && expires_at <= now {
+        return None;
+    }
+    entry.drain(..8);
+    Some(entry)
+}
+
+/// A fill lock, held by `owner` until `expires_at`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Lock {
+    pub owner: u128,
+    pub expires_at: u64,
+}
+
+impl Lock {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut lock = Vec::with_capacity(24);
+        lock.extend_from_slice(&self.owner.to_be_bytes());
+        lock.extend_from_slice(&self.expires_at.to_be_bytes());
+        lock
+    }
+
+    /// Decodes a lock, returning `None` if it is malformed.
+    pub fn decode(lock: &[u8]) -> Option<Self> {
+        let (owner, expires_at) = lock.split_first_chunk::<16>()?;
+        Some(Self {
+            owner: u128::from_be_bytes(*owner),
+            expires_at: u64::from_be_bytes(expires_at.try_into().ok()?),
+        })
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_without_ttl_never_expires() {
+        let entry = encode_entry(b"value", None, 1000);
+        assert_eq!(decode_entry(entry, u64::MAX - 1).unwrap(), b"value");
+    }
+
+    #[test]
+    fn entry_expires_after_ttl() {
+        let entry = encode_entry(b"value", Some(Duration::from_millis(10)), 1000);
+        assert_eq!(decode_entry(entry.clone(), 1009).unwrap(), b"value");
+        assert!(decode_entry(entry, 1010).is_none());
+    }
+
+    #[test]
+    fn malformed_entry_is_ignored() {
+        assert!(decode_entry(b"short".to_vec(), 0).is_none());
+    }
+
+    #[test]
+    fn lock_round_trips() {
+        let lock = Lock {
+            owner: 42,
+            expires_at: 1000,
+        };
+        assert_eq!(Lock::decode(&lock.encode()), Some(lock));
+        assert!(Lock::decode(b"short").is_none());
+    }
+}
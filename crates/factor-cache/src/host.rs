@@ -0,0 +1,299 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+use spin_factors::wasmtime::component::Resource;
+use spin_resource_table::Table;
+use spin_world::spin::cache::cache::{self, Cache, Error, Fill, LockOptions, Lookup};
+use spin_world::v2::key_value;
+use tokio::time::Instant;
+use tracing::{instrument, Level};
+
+use crate::entry::{decode_entry, encode_entry, entry_key, lock_key, Lock};
+
+const DEFAULT_TABLE_CAPACITY: u32 = 256;
+
+/// How long a fill lock is held if the guest doesn't say.
+const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(30);
+/// How long to wait for another caller's fill if the guest doesn't say.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+/// The first and longest intervals between checks on another caller's fill.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct InstanceState {
+    allowed_stores: HashSet<String>,
+    store_manager: Arc<dyn StoreManager>,
+    caches: Table<Arc<dyn Store>>,
+    fills: Table<HostFill>,
+}
+
+/// A fill lock held by the guest.
+struct HostFill {
+    store: Arc<dyn Store>,
+    key: String,
+    owner: u128,
+    completed: bool,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_stores: HashSet<String>,
+        store_manager: Arc<dyn StoreManager>,
+    ) -> Self {
+        Self {
+            allowed_stores,
+            store_manager,
+            caches: Table::new(DEFAULT_TABLE_CAPACITY),
+            fills: Table::new(DEFAULT_TABLE_CAPACITY),
+        }
+    }
+
+    /// Returns the set of store labels this instance may use as caches.
+    pub fn allowed_stores(&self) -> &HashSet<String> {
+        &self.allowed_stores
+    }
+
+    fn get_cache(&self, cache: &Resource<Cache>) -> Result<Arc<dyn Store>, Error> {
+        self.caches
+            .get(cache.rep())
+            .cloned()
+            .ok_or_else(|| Error::Other("invalid cache".into()))
+    }
+}
+
+impl cache::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl cache::HostCache for InstanceState {
+    #[instrument(name = "spin_cache.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, label: String) -> Result<Resource<Cache>, Error> {
+        if !self.allowed_stores.contains(&label) {
+            return Err(Error::AccessDenied);
+        }
+        let store = self.store_manager.get(&label).await.map_err(to_cache_err)?;
+        self.caches
+            .push(store)
+            .map(Resource::new_own)
+            .map_err(|()| Error::Other("too many open caches".into()))
+    }
+
+    #[instrument(name = "spin_cache.get", skip(self, cache), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&mut self, cache: Resource<Cache>, key: String) -> Result<Option<Vec<u8>>, Error> {
+        let store = self.get_cache(&cache)?;
+        get_entry(store.as_ref(), &key).await
+    }
+
+    #[instrument(name = "spin_cache.set", skip(self, cache, value), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn set(
+        &mut self,
+        cache: Resource<Cache>,
+        key: String,
+        value: Vec<u8>,
+        ttl_ms: Option<u64>,
+    ) -> Result<(), Error> {
+        let store = self.get_cache(&cache)?;
+        set_entry(store.as_ref(), &key, &value, ttl_ms).await
+    }
+
+    #[instrument(name = "spin_cache.delete", skip(self, cache), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn delete(&mut self, cache: Resource<Cache>, key: String) -> Result<(), Error> {
+        let store = self.get_cache(&cache)?;
+        store.delete(&entry_key(&key)).await.map_err(to_cache_err)
+    }
+
+    #[instrument(name = "spin_cache.get_or_lock", skip(self, cache, options), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_or_lock(
+        &mut self,
+        cache: Resource<Cache>,
+        key: String,
+        options: LockOptions,
+    ) -> Result<Lookup, Error> {
+        let store = self.get_cache(&cache)?;
+        let lock_ttl = options
+            .lock_ttl_ms
+            .map_or(DEFAULT_LOCK_TTL, |ms| Duration::from_millis(ms.into()));
+        let wait_timeout = options
+            .wait_timeout_ms
+            .map_or(DEFAULT_WAIT_TIMEOUT, |ms| Duration::from_millis(ms.into()));
+        let owner = match get_or_lock(store.as_ref(), &key, lock_ttl, wait_timeout).await? {
+            Acquired::Value(value) => return Ok(Lookup::Hit(value)),
+            Acquired::Lock(owner) => owner,
+        };
+        let fill = HostFill {
+            store: store.clone(),
+            key: key.clone(),
+            owner,
+            completed: false,
+        };
+        match self.fills.push(fill) {
+            Ok(rep) => Ok(Lookup::Miss(Resource::new_own(rep))),
+            Err(()) => {
+                release_lock(store.as_ref(), &key, owner).await;
+                Err(Error::Other("too many pending fills".into()))
+            }
+        }
+    }
+
+    async fn drop(&mut self, cache: Resource<Cache>) -> anyhow::Result<()> {
+        self.caches.remove(cache.rep());
+        Ok(())
+    }
+}
+
+impl cache::HostFill for InstanceState {
+    async fn key(&mut self, fill: Resource<Fill>) -> anyhow::Result<String> {
+        Ok(self
+            .fills
+            .get(fill.rep())
+            .context("invalid fill")?
+            .key
+            .clone())
+    }
+
+    #[instrument(name = "spin_cache.fill", skip(self, fill, value), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn complete(
+        &mut self,
+        fill: Resource<Fill>,
+        value: Vec<u8>,
+        ttl_ms: Option<u64>,
+    ) -> Result<(), Error> {
+        let fill = self
+            .fills
+            .get_mut(fill.rep())
+            .ok_or_else(|| Error::Other("invalid fill".into()))?;
+        if fill.completed {
+            return Err(Error::Other("the fill has already been completed".into()));
+        }
+        set_entry(fill.store.as_ref(), &fill.key, &value, ttl_ms).await?;
+        fill.completed = true;
+        release_lock(fill.store.as_ref(), &fill.key, fill.owner).await;
+        Ok(())
+    }
+
+    async fn drop(&mut self, fill: Resource<Fill>) -> anyhow::Result<()> {
+        if let Some(fill) = self.fills.remove(fill.rep()) {
+            if !fill.completed {
+                release_lock(fill.store.as_ref(), &fill.key, fill.owner).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Acquired {
+    /// The entry was filled, possibly by another caller.
+    Value(Vec<u8>),
+    /// The caller now holds the fill lock, as the given owner.
+    Lock(u128),
+}
+
+/// Returns the entry for `key` or takes its fill lock, waiting up to
+/// `wait_timeout` for any other holder of the lock to fill it.
+async fn get_or_lock(
+    store: &dyn Store,
+    key: &str,
+    lock_ttl: Duration,
+    wait_timeout: Duration,
+) -> Result<Acquired, Error> {
+    let deadline = Instant::now() + wait_timeout;
+    let mut poll_interval = MIN_POLL_INTERVAL;
+    loop {
+        if let Some(value) = get_entry(store, key).await? {
+            return Ok(Acquired::Value(value));
+        }
+
+        let cas = store
+            .new_compare_and_swap(0, &lock_key(key))
+            .await
+            .map_err(to_cache_err)?;
+        let current = cas.current().await.map_err(to_cache_err)?;
+        let now = now_millis();
+        // A malformed lock can't be released by its owner, so treat it as expired.
+        let lock_is_free = current
+            .as_deref()
+            .and_then(Lock::decode)
+            .is_none_or(|lock| lock.is_expired(now));
+        if lock_is_free {
+            let owner = uuid::Uuid::new_v4().as_u128();
+            let lock = Lock {
+                owner,
+                expires_at: now.saturating_add(lock_ttl.as_millis().try_into().unwrap_or(u64::MAX)),
+            };
+            match cas.swap(lock.encode()).await {
+                Ok(()) => {
+                    // The previous holder may have filled the entry since it was checked.
+                    if let Some(value) = get_entry(store, key).await? {
+                        release_lock(store, key, owner).await;
+                        return Ok(Acquired::Value(value));
+                    }
+                    return Ok(Acquired::Lock(owner));
+                }
+                // Another caller took the lock first.
+                Err(SwapError::CasFailed(_)) => {}
+                Err(SwapError::Other(e)) => return Err(Error::Other(e)),
+            }
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::Timeout);
+        }
+        tokio::time::sleep(poll_interval.min(deadline - now)).await;
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+async fn get_entry(store: &dyn Store, key: &str) -> Result<Option<Vec<u8>>, Error> {
+    let entry = store.get(&entry_key(key)).await.map_err(to_cache_err)?;
+    Ok(entry.and_then(|entry| decode_entry(entry, now_millis())))
+}
+
+async fn set_entry(
+    store: &dyn Store,
+    key: &str,
+    value: &[u8],
+    ttl_ms: Option<u64>,
+) -> Result<(), Error> {
+    let entry = encode_entry(value, ttl_ms.map(Duration::from_millis), now_millis());
+    store
+        .set(&entry_key(key), &entry)
+        .await
+        .map_err(to_cache_err)
+}
+
+/// Releases the fill lock for `key` if it is still held by `owner`.
+///
+/// This is best effort: if the lock can't be released it is taken over once
+/// it expires.
+async fn release_lock(store: &dyn Store, key: &str, owner: u128) {
+    let lock_key = lock_key(key);
+    let held = match store.get(&lock_key).await {
+        Ok(lock) => lock
+            .as_deref()
+            .and_then(Lock::decode)
+            .is_some_and(|lock| lock.owner == owner),
+        Err(e) => {
+            tracing::warn!("failed to check cache fill lock for {key:?}: {e:?}");
+            return;
+        }
+    };
+    if held {
+        if let Err(e) = store.delete(&lock_key).await {
+            tracing::warn!("failed to release cache fill lock for {key:?}: {e:?}");
+        }
+    }
+}
+
+fn to_cache_err(e: key_value::Error) -> Error {
+    match e {
+        key_value::Error::NoSuchStore => Error::NoSuchStore,
+        key_value::Error::AccessDenied => Error::AccessDenied,
+        key_value::Error::StoreTableFull => Error::Other("too many open stores".into()),
+        key_value::Error::Other(e) => Error::Other(e),
+    }
+}
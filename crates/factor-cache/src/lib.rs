@@ -0,0 +1,104 @@
+mod entry;
+mod host;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use spin_factor_key_value::{KeyValueFactor, StoreManager, KEY_VALUE_STORES_KEY};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+
+/// A factor that provides caches with stampede protection on top of the
+/// app's key-value stores.
+///
+/// A component may use a store as a cache if it may use it as a key-value
+/// store, so this factor must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct CacheFactor {
+    _priv: (),
+}
+
+impl CacheFactor {
+    /// Create a new CacheFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for CacheFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::cache::cache::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        // Build component -> allowed stores map. The key-value factor has
+        // already checked that each label is defined.
+        let mut component_allowed_stores = HashMap::new();
+        for component in ctx.app().components() {
+            let key_value_stores = component
+                .get_metadata(KEY_VALUE_STORES_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            component_allowed_stores.insert(component.id().to_string(), key_value_stores);
+        }
+
+        Ok(AppState {
+            store_manager,
+            component_allowed_stores,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_stores = app_state
+            .component_allowed_stores
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_stores")
+            .clone();
+        Ok(InstanceBuilder {
+            store_manager: app_state.store_manager.clone(),
+            allowed_stores,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The stores each component is allowed to use, keyed by component ID.
+    component_allowed_stores: HashMap<String, HashSet<String>>,
+}
+
+pub struct InstanceBuilder {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The stores this component instance is allowed to use.
+    allowed_stores: HashSet<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_stores, self.store_manager))
+    }
+}
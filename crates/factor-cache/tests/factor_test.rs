@@ -0,0 +1,135 @@
+use std::{collections::HashSet, sync::Arc};
+
+use spin_factor_cache::CacheFactor;
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, RuntimeConfig,
+};
+use spin_factors::{wasmtime::component::Resource, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::cache::cache::{Error, HostCache, HostFill, LockOptions, Lookup};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    cache: CacheFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            key_value: Some(value),
+            cache: None,
+        }
+    }
+}
+
+async fn build_state(manifest: toml::Table) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut runtime_config = RuntimeConfig::default();
+    let store_manager = MemoryKeyValueStore::new().make_store(Default::default())?;
+    runtime_config.add_store_manager("default".into(), Arc::new(store_manager));
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        cache: CacheFactor::new(),
+    })
+    .extend_manifest(manifest);
+    env.runtime_config(runtime_config)?
+        .build_instance_state()
+        .await
+}
+
+fn allowed_default_store() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    }
+}
+
+fn quick_timeout() -> LockOptions {
+    LockOptions {
+        lock_ttl_ms: None,
+        wait_timeout_ms: Some(20),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn caches_follow_key_value_store_access() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store()).await?;
+    assert_eq!(
+        state.cache.allowed_stores(),
+        &["default".into()].into_iter().collect::<HashSet<_>>()
+    );
+    assert!(state.cache.open("default".into()).await.is_ok());
+
+    let mut state = build_state(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+    .await?;
+    assert!(matches!(
+        state.cache.open("default".into()).await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_one_caller_fills_a_missing_entry() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store()).await?;
+    let cache = state.cache.open("default".into()).await?;
+    let reuse = || Resource::new_borrow(cache.rep());
+
+    let Lookup::Miss(fill) = state
+        .cache
+        .get_or_lock(reuse(), "k".into(), quick_timeout())
+        .await?
+    else {
+        panic!("expected the first lookup to miss");
+    };
+    assert!(matches!(
+        state
+            .cache
+            .get_or_lock(reuse(), "k".into(), quick_timeout())
+            .await,
+        Err(Error::Timeout)
+    ));
+
+    state
+        .cache
+        .complete(Resource::new_borrow(fill.rep()), b"v".to_vec(), None)
+        .await?;
+    assert!(matches!(
+        state.cache.get_or_lock(reuse(), "k".into(), quick_timeout()).await?,
+        Lookup::Hit(value) if value == b"v"
+    ));
+    assert_eq!(
+        state.cache.get(reuse(), "k".into()).await?,
+        Some(b"v".to_vec())
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dropping_a_fill_releases_the_lock() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store()).await?;
+    let cache = state.cache.open("default".into()).await?;
+    let reuse = || Resource::new_borrow(cache.rep());
+
+    let Lookup::Miss(fill) = state
+        .cache
+        .get_or_lock(reuse(), "k".into(), quick_timeout())
+        .await?
+    else {
+        panic!("expected the first lookup to miss");
+    };
+    HostFill::drop(&mut state.cache, fill).await?;
+    assert!(matches!(
+        state
+            .cache
+            .get_or_lock(reuse(), "k".into(), quick_timeout())
+            .await?,
+        Lookup::Miss(_)
+    ));
+    Ok(())
+}
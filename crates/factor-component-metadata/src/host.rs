@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use spin_world::spin::component_metadata::metadata;
+
+use crate::ComponentMetadata;
+
+pub struct InstanceState {
+    metadata: Arc<ComponentMetadata>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(metadata: Arc<ComponentMetadata>) -> Self {
+        Self { metadata }
+    }
+
+    /// Returns the metadata of the instance's component.
+    pub fn metadata(&self) -> &ComponentMetadata {
+        &self.metadata
+    }
+}
+
+impl metadata::Host for InstanceState {
+    async fn get(&mut self) -> anyhow::Result<metadata::ComponentMetadata> {
+        let ComponentMetadata {
+            component_id,
+            app_name,
+            app_version,
+            trigger_type,
+            environment,
+        } = self.metadata.as_ref().clone();
+        Ok(metadata::ComponentMetadata {
+            component_id,
+            app_name,
+            app_version,
+            trigger_type,
+            environment,
+        })
+    }
+}
@@ -0,0 +1,110 @@
+mod host;
+pub mod runtime_config;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_app::{APP_NAME_KEY, APP_VERSION_KEY};
+use spin_factors::{
+    anyhow, ConfigureAppContext, Factor, InitContext, PrepareContext, RuntimeFactors,
+    SelfInstanceBuilder,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that tells the guest about the component it is running as, so
+/// that handlers can tag logs and metrics without duplicating this data into
+/// variables.
+#[derive(Default)]
+pub struct ComponentMetadataFactor {
+    _priv: (),
+}
+
+impl ComponentMetadataFactor {
+    /// Create a new ComponentMetadataFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for ComponentMetadataFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::component_metadata::metadata::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let RuntimeConfig { environment } = ctx.take_runtime_config().unwrap_or_default();
+        let app = ctx.app();
+        let app_name: String = app.get_metadata(APP_NAME_KEY)?.unwrap_or_default();
+        let app_version: Option<String> = app.get_metadata(APP_VERSION_KEY)?;
+
+        // A component bound to several triggers reports the first of them
+        let mut trigger_types = HashMap::new();
+        for trigger in app.triggers() {
+            if let Ok(component) = trigger.component() {
+                trigger_types
+                    .entry(component.id().to_owned())
+                    .or_insert_with(|| trigger.trigger_type().to_owned());
+            }
+        }
+
+        let component_metadata = app
+            .components()
+            .map(|component| {
+                let id = component.id().to_owned();
+                let metadata = ComponentMetadata {
+                    component_id: id.clone(),
+                    app_name: app_name.clone(),
+                    app_version: app_version.clone(),
+                    trigger_type: trigger_types.get(&id).cloned(),
+                    environment: environment.clone(),
+                };
+                (id, Arc::new(metadata))
+            })
+            .collect();
+        Ok(AppState { component_metadata })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceState> {
+        let component_id = ctx.app_component().id();
+        let metadata = ctx
+            .app_state()
+            .component_metadata
+            .get(component_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no metadata for component {component_id:?}"))?;
+        Ok(InstanceState::new(metadata))
+    }
+}
+
+pub struct AppState {
+    component_metadata: HashMap<String, Arc<ComponentMetadata>>,
+}
+
+/// What a guest can find out about the component it is running as.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComponentMetadata {
+    /// The ID of the component.
+    pub component_id: String,
+    /// The name of the app.
+    pub app_name: String,
+    /// The version of the app, if it declares one.
+    pub app_version: Option<String>,
+    /// The type of the trigger the component is bound to, if any.
+    pub trigger_type: Option<String>,
+    /// The deployment environment label from runtime config, if any.
+    pub environment: Option<String>,
+}
+
+impl SelfInstanceBuilder for InstanceState {}
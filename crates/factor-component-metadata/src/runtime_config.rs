@@ -0,0 +1,8 @@
+pub mod spin;
+
+/// Runtime configuration for component metadata.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// The deployment environment label reported to guests, e.g. `staging`.
+    pub environment: Option<String>,
+}
@@ -0,0 +1,36 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for component metadata from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [component_metadata]
+/// environment = "staging"
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("component_metadata") else {
+        return Ok(None);
+    };
+    let toml: ComponentMetadataToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [component_metadata] table")?;
+    if toml.environment.as_deref().is_some_and(str::is_empty) {
+        anyhow::bail!("component_metadata.environment must not be empty");
+    }
+    Ok(Some(RuntimeConfig {
+        environment: toml.environment,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComponentMetadataToml {
+    environment: Option<String>,
+}
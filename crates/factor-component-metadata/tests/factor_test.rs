@@ -0,0 +1,63 @@
+use spin_factor_component_metadata::{ComponentMetadataFactor, RuntimeConfig};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::component_metadata::metadata::Host as _;
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    component_metadata: ComponentMetadataFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            component_metadata: Some(value),
+        }
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        component_metadata: ComponentMetadataFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [application]
+        name = "test-app"
+        version = "1.2.3"
+
+        [[trigger.test-trigger]]
+        component = "test-component"
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+#[tokio::test]
+async fn metadata_describes_component_and_app() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let metadata = state.component_metadata.get().await?;
+    assert_eq!(metadata.component_id, "test-component");
+    assert_eq!(metadata.app_name, "test-app");
+    assert_eq!(metadata.app_version.as_deref(), Some("1.2.3"));
+    assert_eq!(metadata.trigger_type.as_deref(), Some("test-trigger"));
+    assert_eq!(metadata.environment, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn environment_comes_from_runtime_config() -> anyhow::Result<()> {
+    let state = test_env()
+        .runtime_config(RuntimeConfig {
+            environment: Some("staging".into()),
+        })?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.component_metadata.metadata().environment.as_deref(),
+        Some("staging")
+    );
+    Ok(())
+}
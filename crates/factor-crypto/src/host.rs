@@ -0,0 +1,106 @@
+use std::{collections::HashMap, sync::Arc};
+
+use spin_world::spin::crypto::crypto::{self, Error, HashAlgorithm};
+use tracing::{instrument, Level};
+
+use crate::primitives;
+
+pub struct InstanceState {
+    keys: Arc<HashMap<String, Vec<u8>>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(keys: Arc<HashMap<String, Vec<u8>>>) -> Self {
+        Self { keys }
+    }
+
+    /// Looks up the key material configured under `name`.
+    fn key(&self, name: &str) -> Result<&[u8], Error> {
+        if !crate::is_valid_key_name(name) {
+            return Err(Error::InvalidKeyName(name.to_owned()));
+        }
+        self.keys
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| Error::UndefinedKey(name.to_owned()))
+    }
+}
+
+impl crypto::Host for InstanceState {
+    async fn hash(&mut self, algorithm: HashAlgorithm, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        Ok(primitives::hash(algorithm, &data))
+    }
+
+    #[instrument(name = "spin_crypto.hmac", skip(self, data), err(level = Level::INFO))]
+    async fn hmac(
+        &mut self,
+        algorithm: HashAlgorithm,
+        key: String,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let key = self.key(&key)?;
+        Ok(primitives::hmac(algorithm, key, &data))
+    }
+
+    #[instrument(name = "spin_crypto.hmac_verify", skip(self, data, tag), err(level = Level::INFO))]
+    async fn hmac_verify(
+        &mut self,
+        algorithm: HashAlgorithm,
+        key: String,
+        data: Vec<u8>,
+        tag: Vec<u8>,
+    ) -> Result<bool, Error> {
+        let key = self.key(&key)?;
+        Ok(primitives::hmac_verify(algorithm, key, &data, &tag))
+    }
+
+    #[instrument(name = "spin_crypto.aes_gcm_encrypt", skip(self, plaintext, associated_data), err(level = Level::INFO))]
+    async fn aes_gcm_encrypt(
+        &mut self,
+        key: String,
+        plaintext: Vec<u8>,
+        associated_data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let key = self.key(&key)?;
+        primitives::aes_gcm_encrypt(key, plaintext, &associated_data)
+    }
+
+    #[instrument(name = "spin_crypto.aes_gcm_decrypt", skip(self, ciphertext, associated_data), err(level = Level::INFO))]
+    async fn aes_gcm_decrypt(
+        &mut self,
+        key: String,
+        ciphertext: Vec<u8>,
+        associated_data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let key = self.key(&key)?;
+        primitives::aes_gcm_decrypt(key, ciphertext, &associated_data)
+    }
+
+    #[instrument(name = "spin_crypto.ed25519_sign", skip(self, message), err(level = Level::INFO))]
+    async fn ed25519_sign(&mut self, key: String, message: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let key = self.key(&key)?;
+        primitives::ed25519_sign(key, &message)
+    }
+
+    async fn ed25519_public_key(&mut self, key: String) -> Result<Vec<u8>, Error> {
+        let key = self.key(&key)?;
+        primitives::ed25519_public_key(key)
+    }
+
+    async fn ed25519_verify(
+        &mut self,
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> anyhow::Result<bool> {
+        Ok(primitives::ed25519_verify(
+            &public_key,
+            &message,
+            &signature,
+        ))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
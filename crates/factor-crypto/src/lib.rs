@@ -0,0 +1,82 @@
+mod host;
+mod primitives;
+pub mod runtime_config;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that computes hashes, MACs, encryption and signatures for the
+/// guest with keys from the runtime config, so that key material is never
+/// handed to guest code.
+#[derive(Default)]
+pub struct CryptoFactor {
+    _priv: (),
+}
+
+impl CryptoFactor {
+    /// Create a new CryptoFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for CryptoFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::crypto::crypto::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let keys = ctx.take_runtime_config().unwrap_or_default().keys;
+        Ok(AppState {
+            keys: Arc::new(keys),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        Ok(InstanceBuilder {
+            keys: ctx.app_state().keys.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    keys: Arc<HashMap<String, Vec<u8>>>,
+}
+
+pub struct InstanceBuilder {
+    keys: Arc<HashMap<String, Vec<u8>>>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.keys))
+    }
+}
+
+/// Whether `name` is a valid key name: lowercase letters, digits and
+/// underscores, starting with a letter.
+pub(crate) fn is_valid_key_name(name: &str) -> bool {
+    name.bytes().next().is_some_and(|b| b.is_ascii_lowercase())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
@@ -0,0 +1,190 @@
+//! The cryptographic operations, on decoded key material.
+
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN},
+    digest, hmac,
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
+use spin_world::spin::crypto::crypto::{Error, HashAlgorithm};
+
+/// The length of an Ed25519 seed, as opposed to a PKCS#8 document.
+const ED25519_SEED_LEN: usize = 32;
+
+pub(crate) fn hash(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    let algorithm = match algorithm {
+        HashAlgorithm::Sha256 => &digest::SHA256,
+        HashAlgorithm::Sha384 => &digest::SHA384,
+        HashAlgorithm::Sha512 => &digest::SHA512,
+    };
+    digest::digest(algorithm, data).as_ref().to_vec()
+}
+
+fn hmac_key(algorithm: HashAlgorithm, key: &[u8]) -> hmac::Key {
+    let algorithm = match algorithm {
+        HashAlgorithm::Sha256 => hmac::HMAC_SHA256,
+        HashAlgorithm::Sha384 => hmac::HMAC_SHA384,
+        HashAlgorithm::Sha512 => hmac::HMAC_SHA512,
+    };
+    hmac::Key::new(algorithm, key)
+}
+
+pub(crate) fn hmac(algorithm: HashAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac_key(algorithm, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+pub(crate) fn hmac_verify(algorithm: HashAlgorithm, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    hmac::verify(&hmac_key(algorithm, key), data, tag).is_ok()
+}
+
+fn aes_gcm_key(key: &[u8]) -> Result<LessSafeKey, Error> {
+    let algorithm = match key.len() {
+        16 => &aead::AES_128_GCM,
+        32 => &aead::AES_256_GCM,
+        len => {
+            return Err(Error::InvalidKey(format!(
+                "AES-GCM keys must be 16 or 32 bytes, not {len}"
+            )))
+        }
+    };
+    let key = UnboundKey::new(algorithm, key)
+        .map_err(|_| Error::InvalidKey("invalid AES-GCM key".into()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+pub(crate) fn aes_gcm_encrypt(
+    key: &[u8],
+    plaintext: Vec<u8>,
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let key = aes_gcm_key(key)?;
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| Error::Other("failed to generate nonce".into()))?;
+    let mut in_out = plaintext;
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(associated_data),
+        &mut in_out,
+    )
+    .map_err(|_| Error::Other("encryption failed".into()))?;
+    let mut sealed = Vec::with_capacity(NONCE_LEN + in_out.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&in_out);
+    Ok(sealed)
+}
+
+pub(crate) fn aes_gcm_decrypt(
+    key: &[u8],
+    sealed: Vec<u8>,
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let key = aes_gcm_key(key)?;
+    if sealed.len() < NONCE_LEN {
+        return Err(Error::DecryptionFailed);
+    }
+    let mut in_out = sealed;
+    let ciphertext = in_out.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&in_out).map_err(|_| Error::DecryptionFailed)?;
+    let mut in_out = ciphertext;
+    let plaintext_len = key
+        .open_in_place(nonce, Aad::from(associated_data), &mut in_out)
+        .map_err(|_| Error::DecryptionFailed)?
+        .len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+fn ed25519_key_pair(key: &[u8]) -> Result<Ed25519KeyPair, Error> {
+    let key_pair = if key.len() == ED25519_SEED_LEN {
+        Ed25519KeyPair::from_seed_unchecked(key)
+    } else {
+        Ed25519KeyPair::from_pkcs8_maybe_unchecked(key)
+    };
+    key_pair.map_err(|e| Error::InvalidKey(format!("invalid Ed25519 key: {e}")))
+}
+
+pub(crate) fn ed25519_sign(key: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(ed25519_key_pair(key)?.sign(message).as_ref().to_vec())
+}
+
+pub(crate) fn ed25519_public_key(key: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(ed25519_key_pair(key)?.public_key().as_ref().to_vec())
+}
+
+pub(crate) fn ed25519_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    UnparsedPublicKey::new(&signature::ED25519, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_known_values() {
+        let digest = hash(HashAlgorithm::Sha256, b"abc");
+        assert_eq!(
+            digest[..4],
+            [0xba, 0x78, 0x16, 0xbf],
+            "SHA-256 of 'abc' starts ba7816bf"
+        );
+        assert_eq!(hash(HashAlgorithm::Sha512, b"").len(), 64);
+    }
+
+    #[test]
+    fn hmac_round_trips() {
+        let tag = hmac(HashAlgorithm::Sha384, b"key", b"data");
+        assert!(hmac_verify(HashAlgorithm::Sha384, b"key", b"data", &tag));
+        assert!(!hmac_verify(HashAlgorithm::Sha384, b"other", b"data", &tag));
+        assert!(!hmac_verify(HashAlgorithm::Sha256, b"key", b"data", &tag));
+    }
+
+    #[test]
+    fn aes_gcm_round_trips() {
+        let key = [7; 32];
+        let sealed = aes_gcm_encrypt(&key, b"secret".to_vec(), b"aad").unwrap();
+        assert_ne!(
+            sealed,
+            aes_gcm_encrypt(&key, b"secret".to_vec(), b"aad").unwrap(),
+            "nonces should be random"
+        );
+        assert_eq!(
+            aes_gcm_decrypt(&key, sealed.clone(), b"aad").unwrap(),
+            b"secret"
+        );
+        assert!(matches!(
+            aes_gcm_decrypt(&key, sealed.clone(), b"other"),
+            Err(Error::DecryptionFailed)
+        ));
+        assert!(matches!(
+            aes_gcm_decrypt(&[8; 16], sealed, b"aad"),
+            Err(Error::DecryptionFailed)
+        ));
+        assert!(matches!(
+            aes_gcm_encrypt(&[0; 20], vec![], b""),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            aes_gcm_decrypt(&key, vec![0; 3], b""),
+            Err(Error::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn ed25519_round_trips() {
+        let seed = [3; ED25519_SEED_LEN];
+        let signature = ed25519_sign(&seed, b"message").unwrap();
+        let public_key = ed25519_public_key(&seed).unwrap();
+        assert!(ed25519_verify(&public_key, b"message", &signature));
+        assert!(!ed25519_verify(&public_key, b"other", &signature));
+        assert!(matches!(
+            ed25519_sign(b"not a key", b""),
+            Err(Error::InvalidKey(_))
+        ));
+    }
+}
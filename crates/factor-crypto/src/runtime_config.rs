@@ -0,0 +1,10 @@
+pub mod spin;
+
+use std::collections::HashMap;
+
+/// Runtime configuration for crypto.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// The key material guests can use, by key name.
+    pub keys: HashMap<String, Vec<u8>>,
+}
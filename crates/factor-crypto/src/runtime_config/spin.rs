@@ -0,0 +1,90 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for crypto from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [crypto.keys]
+/// webhook_mac = "{{ vault:keys/webhook }}"
+/// signing = "nWGxne/9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A="
+/// ```
+///
+/// Each key's value is the base64-encoded key material; secret references
+/// are resolved before this is called.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("crypto") else {
+        return Ok(None);
+    };
+    let toml: CryptoToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [crypto] table")?;
+
+    let mut keys = HashMap::with_capacity(toml.keys.len());
+    for (name, value) in toml.keys {
+        anyhow::ensure!(
+            crate::is_valid_key_name(&name),
+            "invalid crypto key name {name:?}"
+        );
+        let key = STANDARD
+            .decode(value.trim())
+            .with_context(|| format!("crypto key {name:?} is not base64"))?;
+        keys.insert(name, key);
+    }
+    Ok(Some(RuntimeConfig { keys }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CryptoToml {
+    #[serde(default)]
+    keys: HashMap<String, String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(table: toml::Table) -> anyhow::Result<Option<RuntimeConfig>> {
+        config_from_table(&table)
+    }
+
+    #[test]
+    fn missing_table_is_none() {
+        assert!(config(toml::Table::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn keys_are_decoded() {
+        let config = config(toml::toml! {
+            [crypto.keys]
+            aes_key = "AAECAwQFBgcICQoLDA0ODw=="
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.keys["aes_key"], (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn bad_names_and_values_are_rejected() {
+        assert!(config(toml::toml! {
+            [crypto.keys]
+            "Not-A-Name" = "AAAA"
+        })
+        .is_err());
+        assert!(config(toml::toml! {
+            [crypto.keys]
+            not_base64 = "???"
+        })
+        .is_err());
+    }
+}
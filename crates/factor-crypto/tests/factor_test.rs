@@ -0,0 +1,94 @@
+use spin_factor_crypto::{runtime_config::spin::config_from_table, CryptoFactor, RuntimeConfig};
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::crypto::crypto::{Error, HashAlgorithm, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    crypto: CryptoFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            crypto: Some(value),
+        }
+    }
+}
+
+async fn build_state() -> anyhow::Result<TestFactorsInstanceState> {
+    let runtime_config = config_from_table(&toml! {
+        [crypto.keys]
+        aes_key = "AAECAwQFBgcICQoLDA0ODw=="
+    })?
+    .unwrap();
+    TestEnvironment::new(TestFactors {
+        crypto: CryptoFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [variables]
+        secret = { default = "AAECAwQFBgcICQoLDA0ODw==" }
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        variables = { variable_key = "{{ secret }}" }
+    })
+    .runtime_config(runtime_config)?
+    .build_instance_state()
+    .await
+}
+
+#[tokio::test]
+async fn keys_come_from_runtime_config() -> anyhow::Result<()> {
+    let mut state = build_state().await?;
+    let crypto = &mut state.crypto;
+
+    let sealed = crypto
+        .aes_gcm_encrypt("aes_key".into(), b"hello".to_vec(), vec![])
+        .await?;
+    let opened = crypto
+        .aes_gcm_decrypt("aes_key".into(), sealed, vec![])
+        .await?;
+    assert_eq!(opened, b"hello");
+
+    let tag = crypto
+        .hmac(HashAlgorithm::Sha256, "aes_key".into(), b"data".to_vec())
+        .await?;
+    assert!(
+        crypto
+            .hmac_verify(
+                HashAlgorithm::Sha256,
+                "aes_key".into(),
+                b"data".to_vec(),
+                tag
+            )
+            .await?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_or_invalid_keys_are_errors() -> anyhow::Result<()> {
+    let mut state = build_state().await?;
+    let crypto = &mut state.crypto;
+
+    assert!(matches!(
+        crypto.ed25519_sign("missing".into(), vec![]).await,
+        Err(Error::UndefinedKey(_))
+    ));
+    // Component variables are readable by the guest, so they are not keys.
+    assert!(matches!(
+        crypto.ed25519_sign("variable_key".into(), vec![]).await,
+        Err(Error::UndefinedKey(_))
+    ));
+    // A 16-byte key is neither an Ed25519 seed nor a PKCS#8 document.
+    assert!(matches!(
+        crypto.ed25519_sign("aes_key".into(), vec![]).await,
+        Err(Error::InvalidKey(_))
+    ));
+    assert!(matches!(
+        crypto.ed25519_sign("Not-A-Name".into(), vec![]).await,
+        Err(Error::InvalidKeyName(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use spin_factor_key_value::{Cas, StoreManager, SwapError};
+use spin_factors::wasmtime::component::Resource;
+use spin_resource_table::Table;
+use spin_world::spin::entities::entities::{self, Entity, Error};
+use spin_world::v2::key_value;
+use tracing::{instrument, Level};
+
+use crate::locks::{EntityGuard, EntityLocks};
+
+const DEFAULT_TABLE_CAPACITY: u32 = 256;
+
+/// How long to wait for another caller to release an entity if the guest
+/// doesn't say.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Prefix for the keys of entity state in the backing store.
+const STATE_KEY_PREFIX: &str = "spin-entity:";
+
+pub struct InstanceState {
+    allowed_stores: HashSet<String>,
+    store_manager: Arc<dyn StoreManager>,
+    locks: Arc<EntityLocks>,
+    entities: Table<HostEntity>,
+}
+
+/// An entity held by the guest.
+struct HostEntity {
+    id: String,
+    state: Option<Vec<u8>>,
+    /// Compares the stored state against the state that was loaded when saving.
+    cas: Arc<dyn Cas>,
+    committed: bool,
+    /// Held until the guest drops the entity.
+    _guard: EntityGuard,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_stores: HashSet<String>,
+        store_manager: Arc<dyn StoreManager>,
+        locks: Arc<EntityLocks>,
+    ) -> Self {
+        Self {
+            allowed_stores,
+            store_manager,
+            locks,
+            entities: Table::new(DEFAULT_TABLE_CAPACITY),
+        }
+    }
+
+    /// Returns the set of store labels this instance may keep entities in.
+    pub fn allowed_stores(&self) -> &HashSet<String> {
+        &self.allowed_stores
+    }
+
+    fn get_entity(&self, entity: &Resource<Entity>) -> anyhow::Result<&HostEntity> {
+        self.entities.get(entity.rep()).context("invalid entity")
+    }
+}
+
+impl entities::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl entities::HostEntity for InstanceState {
+    #[instrument(name = "spin_entities.acquire", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn acquire(
+        &mut self,
+        label: String,
+        id: String,
+        wait_timeout_ms: Option<u32>,
+    ) -> Result<Resource<Entity>, Error> {
+        if !self.allowed_stores.contains(&label) {
+            return Err(Error::AccessDenied);
+        }
+        let store = self
+            .store_manager
+            .get(&label)
+            .await
+            .map_err(to_entity_err)?;
+
+        let wait_timeout =
+            wait_timeout_ms.map_or(DEFAULT_WAIT_TIMEOUT, |ms| Duration::from_millis(ms.into()));
+        let guard = tokio::time::timeout(wait_timeout, self.locks.lock(&label, &id))
+            .await
+            .map_err(|_| Error::Timeout)?;
+
+        let cas = store
+            .new_compare_and_swap(0, &state_key(&id))
+            .await
+            .map_err(to_entity_err)?;
+        let state = cas.current().await.map_err(to_entity_err)?;
+        self.entities
+            .push(HostEntity {
+                id,
+                state,
+                cas,
+                committed: false,
+                _guard: guard,
+            })
+            .map(Resource::new_own)
+            .map_err(|()| Error::Other("too many acquired entities".into()))
+    }
+
+    async fn id(&mut self, entity: Resource<Entity>) -> anyhow::Result<String> {
+        Ok(self.get_entity(&entity)?.id.clone())
+    }
+
+    async fn state(&mut self, entity: Resource<Entity>) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.get_entity(&entity)?.state.clone())
+    }
+
+    async fn set_state(&mut self, entity: Resource<Entity>, state: Vec<u8>) -> anyhow::Result<()> {
+        self.entities
+            .get_mut(entity.rep())
+            .context("invalid entity")?
+            .state = Some(state);
+        Ok(())
+    }
+
+    #[instrument(name = "spin_entities.commit", skip(self, entity), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn commit(&mut self, entity: Resource<Entity>) -> Result<(), Error> {
+        let entity = self
+            .entities
+            .get_mut(entity.rep())
+            .ok_or_else(|| Error::Other("invalid entity".into()))?;
+        if entity.committed {
+            return Err(Error::Other("the entity has already been committed".into()));
+        }
+        // An entity that has never had state set has nothing to save.
+        let Some(state) = entity.state.clone() else {
+            entity.committed = true;
+            return Ok(());
+        };
+        match entity.cas.swap(state).await {
+            Ok(()) => {
+                entity.committed = true;
+                Ok(())
+            }
+            Err(SwapError::CasFailed(_)) => Err(Error::Conflict),
+            Err(SwapError::Other(e)) => Err(Error::Other(e)),
+        }
+    }
+
+    async fn drop(&mut self, entity: Resource<Entity>) -> anyhow::Result<()> {
+        self.entities.remove(entity.rep());
+        Ok(())
+    }
+}
+
+fn state_key(id: &str) -> String {
+    format!("{STATE_KEY_PREFIX}{id}")
+}
+
+fn to_entity_err(e: key_value::Error) -> Error {
+    match e {
+        key_value::Error::NoSuchStore => Error::NoSuchStore,
+        key_value::Error::AccessDenied => Error::AccessDenied,
+        key_value::Error::StoreTableFull => Error::Other("too many open stores".into()),
+        key_value::Error::Other(e) => Error::Other(e),
+    }
+}
@@ -0,0 +1,115 @@
+mod host;
+mod locks;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use spin_factor_key_value::{KeyValueFactor, StoreManager, KEY_VALUE_STORES_KEY};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+use locks::EntityLocks;
+
+/// A factor that provides single-writer entities whose state is kept in the
+/// app's key-value stores.
+///
+/// A component may keep entities in a store if it may use it as a key-value
+/// store, so this factor must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct EntitiesFactor {
+    _priv: (),
+}
+
+impl EntitiesFactor {
+    /// Create a new EntitiesFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for EntitiesFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::entities::entities::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        // Build component -> allowed stores map. The key-value factor has
+        // already checked that each label is defined.
+        let mut component_allowed_stores = HashMap::new();
+        for component in ctx.app().components() {
+            let key_value_stores = component
+                .get_metadata(KEY_VALUE_STORES_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            component_allowed_stores.insert(component.id().to_string(), key_value_stores);
+        }
+
+        Ok(AppState {
+            store_manager,
+            component_allowed_stores,
+            locks: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_stores = app_state
+            .component_allowed_stores
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_stores")
+            .clone();
+        Ok(InstanceBuilder {
+            store_manager: app_state.store_manager.clone(),
+            allowed_stores,
+            locks: app_state.locks.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The stores each component is allowed to use, keyed by component ID.
+    component_allowed_stores: HashMap<String, HashSet<String>>,
+    /// The locks serializing access to each entity, shared by all instances.
+    locks: Arc<EntityLocks>,
+}
+
+pub struct InstanceBuilder {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The stores this component instance is allowed to use.
+    allowed_stores: HashSet<String>,
+    /// The locks serializing access to each entity.
+    locks: Arc<EntityLocks>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.allowed_stores,
+            self.store_manager,
+            self.locks,
+        ))
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Serializes access to entities across the instances of an app.
+///
+/// Each entity gets its own lock, which is forgotten once no one holds or is
+/// waiting for it.
+#[derive(Default)]
+pub(crate) struct EntityLocks {
+    locks: Mutex<HashMap<(String, String), Weak<AsyncMutex<()>>>>,
+}
+
+/// Exclusive access to an entity, released when dropped.
+pub(crate) type EntityGuard = OwnedMutexGuard<()>;
+
+impl EntityLocks {
+    /// Waits for exclusive access to the entity `id` in the store `label`.
+    pub(crate) async fn lock(&self, label: &str, id: &str) -> EntityGuard {
+        self.entity_lock(label, id).lock_owned().await
+    }
+
+    fn entity_lock(&self, label: &str, id: &str) -> Arc<AsyncMutex<()>> {
+        let mut locks = self.locks.lock().unwrap();
+        let key = (label.to_owned(), id.to_owned());
+        if let Some(lock) = locks.get(&key).and_then(Weak::upgrade) {
+            return lock;
+        }
+        locks.retain(|_, lock| lock.strong_count() > 0);
+        let lock = Arc::new(AsyncMutex::new(()));
+        locks.insert(key, Arc::downgrade(&lock));
+        lock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serializes_access_per_entity() {
+        let locks = EntityLocks::default();
+        let guard = locks.lock("default", "cart-1").await;
+
+        // Other entities are unaffected.
+        let _other = locks.lock("default", "cart-2").await;
+        let _other_store = locks.lock("other", "cart-1").await;
+
+        assert!(locks
+            .entity_lock("default", "cart-1")
+            .try_lock_owned()
+            .is_err());
+        drop(guard);
+        assert!(locks
+            .entity_lock("default", "cart-1")
+            .try_lock_owned()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn forgets_released_locks() {
+        let locks = EntityLocks::default();
+        drop(locks.lock("default", "cart-1").await);
+        let _guard = locks.lock("default", "cart-2").await;
+        assert_eq!(locks.locks.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashSet, sync::Arc};
+
+use spin_factor_entities::EntitiesFactor;
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, RuntimeConfig, StoreManager,
+};
+use spin_factors::{wasmtime::component::Resource, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::entities::entities::{Error, HostEntity};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    entities: EntitiesFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            key_value: Some(value),
+            entities: None,
+        }
+    }
+}
+
+fn memory_store_manager() -> Arc<dyn StoreManager> {
+    let store_manager = MemoryKeyValueStore::new()
+        .make_store(Default::default())
+        .expect("in-memory store should be created");
+    Arc::new(store_manager)
+}
+
+async fn build_state(
+    manifest: toml::Table,
+    store_manager: Arc<dyn StoreManager>,
+) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store_manager("default".into(), store_manager);
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        entities: EntitiesFactor::new(),
+    })
+    .extend_manifest(manifest);
+    env.runtime_config(runtime_config)?
+        .build_instance_state()
+        .await
+}
+
+fn allowed_default_store() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn entities_follow_key_value_store_access() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store(), memory_store_manager()).await?;
+    assert_eq!(
+        state.entities.allowed_stores(),
+        &["default".into()].into_iter().collect::<HashSet<_>>()
+    );
+    assert!(state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await
+        .is_ok());
+
+    let mut state = build_state(
+        toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        },
+        memory_store_manager(),
+    )
+    .await?;
+    assert!(matches!(
+        state
+            .entities
+            .acquire("default".into(), "cart".into(), None)
+            .await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn committed_state_is_loaded_on_acquire() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store(), memory_store_manager()).await?;
+
+    let entity = state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await?;
+    let reuse = || Resource::new_borrow(entity.rep());
+    assert_eq!(state.entities.state(reuse()).await?, None);
+    state.entities.set_state(reuse(), b"apple".to_vec()).await?;
+    state.entities.commit(reuse()).await?;
+    HostEntity::drop(&mut state.entities, entity).await?;
+
+    let entity = state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await?;
+    let reuse = || Resource::new_borrow(entity.rep());
+    assert_eq!(state.entities.id(reuse()).await?, "cart");
+    assert_eq!(
+        state.entities.state(reuse()).await?,
+        Some(b"apple".to_vec())
+    );
+    // Changes that aren't committed are discarded.
+    state.entities.set_state(reuse(), b"pear".to_vec()).await?;
+    HostEntity::drop(&mut state.entities, entity).await?;
+
+    let entity = state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await?;
+    assert_eq!(
+        state
+            .entities
+            .state(Resource::new_borrow(entity.rep()))
+            .await?,
+        Some(b"apple".to_vec())
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_one_caller_holds_an_entity() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store(), memory_store_manager()).await?;
+
+    let entity = state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await?;
+    assert!(matches!(
+        state
+            .entities
+            .acquire("default".into(), "cart".into(), Some(20))
+            .await,
+        Err(Error::Timeout)
+    ));
+    assert!(state
+        .entities
+        .acquire("default".into(), "other-cart".into(), Some(20))
+        .await
+        .is_ok());
+
+    HostEntity::drop(&mut state.entities, entity).await?;
+    assert!(state
+        .entities
+        .acquire("default".into(), "cart".into(), Some(20))
+        .await
+        .is_ok());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn commit_fails_if_state_changed_elsewhere() -> anyhow::Result<()> {
+    let store_manager = memory_store_manager();
+    let mut state = build_state(allowed_default_store(), store_manager.clone()).await?;
+
+    let entity = state
+        .entities
+        .acquire("default".into(), "cart".into(), None)
+        .await?;
+    let reuse = || Resource::new_borrow(entity.rep());
+    // Simulate another host sharing the store.
+    let store = store_manager.get("default").await?;
+    store.set("spin-entity:cart", b"banana").await?;
+
+    state.entities.set_state(reuse(), b"apple".to_vec()).await?;
+    assert!(matches!(
+        state.entities.commit(reuse()).await,
+        Err(Error::Conflict)
+    ));
+    Ok(())
+}
@@ -0,0 +1,243 @@
+pub mod runtime_config;
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use serde::Deserialize;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, PrepareContext, RuntimeFactors,
+};
+
+pub use runtime_config::{FaultRule, RuntimeConfig};
+
+/// A factor that injects latency and errors into components' calls to host
+/// interfaces, to test how they cope with slow or failing backends.
+///
+/// Faults are configured per component and interface in the runtime config.
+/// The factors implementing the interfaces get an instance's
+/// [`FaultInjector`] from this factor's instance builder and call
+/// [`FaultInjector::inject`] before each operation.
+#[derive(Default)]
+pub struct FaultInjectionFactor {
+    _priv: (),
+}
+
+impl FaultInjectionFactor {
+    /// Create a new FaultInjectionFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for FaultInjectionFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let rules = ctx
+            .take_runtime_config()
+            .map(|config| config.rules)
+            .unwrap_or_default();
+        for rule in &rules {
+            for component_id in rule.components.iter().flatten() {
+                if ctx.app().get_component(component_id).is_none() {
+                    tracing::warn!(
+                        "Fault injection is configured for unknown component {component_id:?}"
+                    );
+                }
+            }
+        }
+        Ok(AppState { rules })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let component_id = ctx.app_component().id();
+        let faults: Vec<FaultRule> = ctx
+            .app_state()
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to_component(component_id))
+            .cloned()
+            .collect();
+        Ok(InstanceBuilder {
+            injector: FaultInjector {
+                faults: faults.into(),
+            },
+        })
+    }
+}
+
+pub struct AppState {
+    /// The configured faults, for all components.
+    rules: Vec<FaultRule>,
+}
+
+/// A host interface faults can be injected into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interface {
+    KeyValue,
+    Sqlite,
+    Redis,
+    OutboundHttp,
+}
+
+impl Interface {
+    /// The name of the interface in the runtime config.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::KeyValue => "key_value",
+            Self::Sqlite => "sqlite",
+            Self::Redis => "redis",
+            Self::OutboundHttp => "outbound_http",
+        }
+    }
+}
+
+/// Injects the faults configured for one component instance.
+#[derive(Clone, Default)]
+pub struct FaultInjector {
+    /// The rules that apply to the instance's component.
+    faults: Arc<[FaultRule]>,
+}
+
+impl FaultInjector {
+    /// Returns whether any faults are configured for the instance.
+    pub fn is_enabled(&self) -> bool {
+        !self.faults.is_empty()
+    }
+
+    /// Injects the faults configured for `interface`: waits for their
+    /// latency, then fails at their error rate.
+    ///
+    /// Called before each operation on the interface.
+    pub async fn inject(&self, interface: Interface) -> Result<(), InjectedFault> {
+        let rules = self
+            .faults
+            .iter()
+            .filter(|rule| rule.applies_to_interface(interface));
+        let mut latency = Duration::ZERO;
+        let mut fail = false;
+        for rule in rules {
+            latency += rule.latency;
+            fail |= rule.error_rate > 0.0 && rand::random_bool(rule.error_rate);
+        }
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        if fail {
+            tracing::debug!("Injecting {} error", interface.as_str());
+            return Err(InjectedFault { interface });
+        }
+        Ok(())
+    }
+}
+
+/// An error injected into a call to a host interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub interface: Interface,
+}
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "injected {} fault", self.interface.as_str())
+    }
+}
+
+impl std::error::Error for InjectedFault {}
+
+pub struct InstanceBuilder {
+    injector: FaultInjector,
+}
+
+impl InstanceBuilder {
+    /// Returns the instance's fault injector.
+    pub fn injector(&self) -> FaultInjector {
+        self.injector.clone()
+    }
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState {
+            injector: self.injector,
+        })
+    }
+}
+
+pub struct InstanceState {
+    injector: FaultInjector,
+}
+
+impl InstanceState {
+    /// Returns the instance's fault injector.
+    pub fn injector(&self) -> &FaultInjector {
+        &self.injector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn injector(rules: Vec<FaultRule>) -> FaultInjector {
+        FaultInjector {
+            faults: rules.into(),
+        }
+    }
+
+    fn rule(interfaces: Option<Vec<Interface>>, error_rate: f64) -> FaultRule {
+        FaultRule {
+            components: None,
+            interfaces: interfaces.map(|interfaces| interfaces.into_iter().collect()),
+            latency: Duration::ZERO,
+            error_rate,
+        }
+    }
+
+    #[tokio::test]
+    async fn injects_errors_into_matching_interfaces() {
+        let injector = injector(vec![rule(Some(vec![Interface::KeyValue]), 1.0)]);
+        assert_eq!(
+            injector.inject(Interface::KeyValue).await,
+            Err(InjectedFault {
+                interface: Interface::KeyValue
+            })
+        );
+        assert_eq!(injector.inject(Interface::Sqlite).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn rules_without_interfaces_apply_to_all() {
+        let injector = injector(vec![rule(None, 1.0)]);
+        assert!(injector.inject(Interface::Redis).await.is_err());
+        assert!(injector.inject(Interface::OutboundHttp).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn zero_error_rate_never_fails() {
+        let injector = injector(vec![rule(None, 0.0)]);
+        for _ in 0..100 {
+            assert_eq!(injector.inject(Interface::KeyValue).await, Ok(()));
+        }
+    }
+
+    #[tokio::test]
+    async fn latencies_of_matching_rules_add_up() {
+        let mut slow = rule(None, 0.0);
+        slow.latency = Duration::from_millis(20);
+        let injector = injector(vec![slow.clone(), slow]);
+        let start = std::time::Instant::now();
+        injector.inject(Interface::Sqlite).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,40 @@
+pub mod spin;
+
+use std::{collections::HashSet, time::Duration};
+
+use crate::Interface;
+
+/// Runtime configuration for fault injection.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// The faults to inject.
+    pub rules: Vec<FaultRule>,
+}
+
+/// Latency and errors to inject into some components' calls to some
+/// interfaces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultRule {
+    /// The IDs of the components the rule applies to, or `None` for all.
+    pub components: Option<HashSet<String>>,
+    /// The interfaces the rule applies to, or `None` for all.
+    pub interfaces: Option<HashSet<Interface>>,
+    /// The latency added to each call.
+    pub latency: Duration,
+    /// The fraction of calls, from 0 to 1, that fail.
+    pub error_rate: f64,
+}
+
+impl FaultRule {
+    pub(crate) fn applies_to_component(&self, component_id: &str) -> bool {
+        self.components
+            .as_ref()
+            .is_none_or(|components| components.contains(component_id))
+    }
+
+    pub(crate) fn applies_to_interface(&self, interface: Interface) -> bool {
+        self.interfaces
+            .as_ref()
+            .is_none_or(|interfaces| interfaces.contains(&interface))
+    }
+}
@@ -0,0 +1,116 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use crate::{FaultRule, Interface, RuntimeConfig};
+
+/// Get the runtime configuration for fault injection from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [[fault_injection]]
+/// components = ["checkout"] # optional, defaults to all components
+/// interfaces = ["key_value", "sqlite", "redis", "outbound_http"] # optional, defaults to all
+/// latency_ms = 250
+/// error_rate = 0.1
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("fault_injection") else {
+        return Ok(None);
+    };
+    let toml: Vec<FaultRuleToml> = table
+        .clone()
+        .try_into()
+        .context("failed to parse [[fault_injection]] tables")?;
+    let rules = toml
+        .into_iter()
+        .map(|rule| {
+            anyhow::ensure!(
+                (0.0..=1.0).contains(&rule.error_rate),
+                "fault injection `error_rate` must be between 0 and 1, got {}",
+                rule.error_rate
+            );
+            Ok(FaultRule {
+                components: rule
+                    .components
+                    .map(|components| components.into_iter().collect()),
+                interfaces: rule
+                    .interfaces
+                    .map(|interfaces| interfaces.into_iter().collect()),
+                latency: Duration::from_millis(rule.latency_ms),
+                error_rate: rule.error_rate,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Some(RuntimeConfig { rules }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FaultRuleToml {
+    components: Option<Vec<String>>,
+    interfaces: Option<Vec<Interface>>,
+    #[serde(default)]
+    latency_ms: u64,
+    #[serde(default)]
+    error_rate: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [[fault_injection]]
+            components = ["checkout"]
+            interfaces = ["key_value", "outbound_http"]
+            latency_ms = 250
+
+            [[fault_injection]]
+            error_rate = 0.5
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert_eq!(
+            config.rules,
+            [
+                FaultRule {
+                    components: Some(["checkout".to_owned()].into()),
+                    interfaces: Some([Interface::KeyValue, Interface::OutboundHttp].into()),
+                    latency: Duration::from_millis(250),
+                    error_rate: 0.0,
+                },
+                FaultRule {
+                    components: None,
+                    interfaces: None,
+                    latency: Duration::ZERO,
+                    error_rate: 0.5,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range_error_rates_are_rejected() {
+        let table: toml::Table = toml::toml! {
+            [[fault_injection]]
+            error_rate = 1.5
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+
+    #[test]
+    fn unknown_interfaces_are_rejected() {
+        let table: toml::Table = toml::toml! {
+            [[fault_injection]]
+            interfaces = ["mqtt"]
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
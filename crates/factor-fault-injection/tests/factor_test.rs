@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use spin_factor_fault_injection::{FaultInjectionFactor, FaultRule, Interface, RuntimeConfig};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    fault_injection: FaultInjectionFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        fault_injection: FaultInjectionFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+fn failing_rule(components: Option<&[&str]>) -> FaultRule {
+    FaultRule {
+        components: components
+            .map(|components| components.iter().map(|id| id.to_string()).collect()),
+        interfaces: None,
+        latency: Duration::ZERO,
+        error_rate: 1.0,
+    }
+}
+
+#[tokio::test]
+async fn no_faults_are_injected_by_default() -> anyhow::Result<()> {
+    let state = test_env().build_instance_state().await?;
+    let injector = state.fault_injection.injector();
+    assert!(!injector.is_enabled());
+    assert!(injector.inject(Interface::KeyValue).await.is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn faults_apply_to_configured_components() -> anyhow::Result<()> {
+    let state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            fault_injection: Some(RuntimeConfig {
+                rules: vec![
+                    failing_rule(Some(&["other-component"])),
+                    failing_rule(Some(&["test-component"])),
+                ],
+            }),
+        })?
+        .build_instance_state()
+        .await?;
+    let injector = state.fault_injection.injector();
+    assert!(injector.inject(Interface::Sqlite).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn faults_for_other_components_are_not_injected() -> anyhow::Result<()> {
+    let state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            fault_injection: Some(RuntimeConfig {
+                rules: vec![failing_rule(Some(&["other-component"]))],
+            }),
+        })?
+        .build_instance_state()
+        .await?;
+    assert!(!state.fault_injection.injector().is_enabled());
+    Ok(())
+}
@@ -0,0 +1,303 @@
+//! Feature flags and their evaluation.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+/// The total weight of a [`Rollout`]'s buckets, so a weight of 1 is 0.001%.
+const TOTAL_WEIGHT: u32 = 100_000;
+
+/// A set of feature flags, keyed by name.
+#[derive(Debug, Default)]
+pub struct FlagSet {
+    flags: HashMap<String, Flag>,
+}
+
+impl FlagSet {
+    pub(crate) fn new(flags: HashMap<String, Flag>) -> Self {
+        Self { flags }
+    }
+
+    /// Builds a set of flags from their definitions, as found in runtime
+    /// config or a key-value store.
+    pub fn from_definitions(definitions: HashMap<String, FlagDefinition>) -> anyhow::Result<Self> {
+        let flags = definitions
+            .into_iter()
+            .map(|(name, definition)| {
+                let flag = Flag::from_definition(&name, definition)
+                    .map_err(|e| anyhow::anyhow!("invalid feature flag {name:?}: {e}"))?;
+                Ok((name, flag))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { flags })
+    }
+
+    /// Whether `flag` is enabled for the context with `key` and
+    /// `attributes`. Unknown flags are disabled.
+    pub fn is_enabled(&self, flag: &str, key: &str, attributes: &[(String, String)]) -> bool {
+        self.flags
+            .get(flag)
+            .is_some_and(|f| f.is_enabled(flag, key, attributes))
+    }
+
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+/// A feature flag.
+///
+/// A flag which is on is evaluated by checking, in order, whether the key is
+/// targeted individually, whether a rule matches the context, and finally
+/// the flag's fallthrough rollout.
+#[derive(Debug)]
+pub(crate) struct Flag {
+    pub(crate) on: bool,
+    /// Keys the flag is always enabled for.
+    pub(crate) include: HashSet<String>,
+    /// Keys the flag is always disabled for.
+    pub(crate) exclude: HashSet<String>,
+    pub(crate) rules: Vec<Rule>,
+    pub(crate) fallthrough: Rollout,
+    /// Mixed into the hash of a key, so rollouts of different flags include
+    /// different keys.
+    pub(crate) salt: String,
+}
+
+/// A rule which applies a [`Rollout`] to contexts whose `attribute` has one
+/// of `values`.
+#[derive(Debug)]
+pub(crate) struct Rule {
+    pub(crate) attribute: String,
+    pub(crate) values: Vec<String>,
+    pub(crate) rollout: Rollout,
+}
+
+/// Which contexts a flag is enabled for, as consecutive buckets of the keys'
+/// hashes.
+#[derive(Debug, Clone)]
+pub(crate) struct Rollout {
+    /// Buckets as (weight, enabled), with weights out of [`TOTAL_WEIGHT`].
+    buckets: Vec<(u32, bool)>,
+}
+
+impl Rollout {
+    /// A rollout which is the same for every context.
+    pub fn fixed(enabled: bool) -> Self {
+        Self {
+            buckets: vec![(TOTAL_WEIGHT, enabled)],
+        }
+    }
+
+    /// A rollout which is enabled for `percent` of contexts.
+    pub fn percent(percent: f64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            (0.0..=100.0).contains(&percent),
+            "rollout percentage {percent} is not between 0 and 100"
+        );
+        let weight = (percent * (TOTAL_WEIGHT / 100) as f64).round() as u32;
+        Ok(Self::weighted(vec![
+            (weight, true),
+            (TOTAL_WEIGHT - weight, false),
+        ]))
+    }
+
+    /// A rollout with the given buckets, whose weights are out of 100,000.
+    pub fn weighted(buckets: Vec<(u32, bool)>) -> Self {
+        Self { buckets }
+    }
+
+    fn is_fixed(&self) -> Option<bool> {
+        let (first, rest) = self.buckets.split_first()?;
+        rest.iter()
+            .all(|(weight, enabled)| *weight == 0 || *enabled == first.1)
+            .then_some(first.1)
+    }
+
+    fn evaluate(&self, bucket: impl FnOnce() -> u32) -> bool {
+        if let Some(enabled) = self.is_fixed() {
+            return enabled;
+        }
+        let bucket = bucket();
+        let mut upper = 0;
+        for (weight, enabled) in &self.buckets {
+            upper += weight;
+            if bucket < upper {
+                return *enabled;
+            }
+        }
+        // Weights which don't add up to the total leave the remainder in the
+        // last bucket.
+        self.buckets.last().is_some_and(|(_, enabled)| *enabled)
+    }
+}
+
+impl Flag {
+    fn from_definition(name: &str, definition: FlagDefinition) -> anyhow::Result<Self> {
+        let rules = definition
+            .rules
+            .into_iter()
+            .map(|rule| {
+                Ok(Rule {
+                    attribute: rule.attribute,
+                    values: rule.values,
+                    rollout: Rollout::percent(rule.rollout_percent.unwrap_or(100.0))?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            on: definition.enabled,
+            include: definition.include.into_iter().collect(),
+            exclude: definition.exclude.into_iter().collect(),
+            rules,
+            fallthrough: Rollout::percent(definition.rollout_percent.unwrap_or(100.0))?,
+            salt: name.to_owned(),
+        })
+    }
+
+    fn is_enabled(&self, name: &str, key: &str, attributes: &[(String, String)]) -> bool {
+        if !self.on {
+            return false;
+        }
+        if self.include.contains(key) {
+            return true;
+        }
+        if self.exclude.contains(key) {
+            return false;
+        }
+        // Hashing is only needed for rollouts which aren't all-or-nothing
+        let key_bucket = || bucket(name, &self.salt, key);
+        let attribute = |attribute: &str| {
+            if attribute == "key" {
+                return Some(key);
+            }
+            attributes
+                .iter()
+                .find(|(n, _)| n == attribute)
+                .map(|(_, value)| value.as_str())
+        };
+        for rule in &self.rules {
+            if attribute(&rule.attribute)
+                .is_some_and(|value| rule.values.iter().any(|v| v == value))
+            {
+                return rule.rollout.evaluate(key_bucket);
+            }
+        }
+        self.fallthrough.evaluate(key_bucket)
+    }
+}
+
+/// Assigns `key` to a bucket between 0 and 100,000 for `flag`, the same way
+/// LaunchDarkly does, so rollouts evaluated by Spin include the same keys as
+/// those evaluated by LaunchDarkly SDKs.
+fn bucket(flag: &str, salt: &str, key: &str) -> u32 {
+    let digest = Sha1::digest(format!("{flag}.{salt}.{key}"));
+    // The first 15 hex digits of the digest
+    let prefix = u64::from_be_bytes(digest[..8].try_into().unwrap()) >> 4;
+    (prefix as f64 / 0xFFF_FFFF_FFFF_FFFFu64 as f64 * TOTAL_WEIGHT as f64) as u32
+}
+
+/// The definition of a flag in runtime config or a key-value store.
+///
+/// ```toml
+/// enabled = true
+/// include = ["beta-tester"]
+/// exclude = ["vip-customer"]
+/// rules = [{ attribute = "country", values = ["NZ"], rollout_percent = 100 }]
+/// rollout_percent = 25
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagDefinition {
+    /// Whether the flag is on. Flags which are off are disabled for everyone.
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    rules: Vec<RuleDefinition>,
+    /// The percentage of contexts the flag is enabled for if no rule
+    /// matches. Defaults to 100.
+    rollout_percent: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    attribute: String,
+    values: Vec<String>,
+    rollout_percent: Option<f64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(toml: toml::Table) -> FlagSet {
+        FlagSet::from_definitions(toml.try_into().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn targeting_takes_precedence_over_rollouts() {
+        let flags = flags(toml::toml! {
+            [off]
+            enabled = false
+            include = ["alice"]
+
+            [targeted]
+            rollout_percent = 0
+            include = ["alice"]
+            rules = [{ attribute = "country", values = ["NZ"] }]
+
+            [excluded]
+            exclude = ["alice"]
+        });
+        assert!(!flags.is_enabled("off", "alice", &[]));
+        assert!(flags.is_enabled("targeted", "alice", &[]));
+        assert!(!flags.is_enabled("targeted", "bob", &[]));
+        assert!(flags.is_enabled("targeted", "bob", &[("country".into(), "NZ".into())]));
+        assert!(!flags.is_enabled("excluded", "alice", &[]));
+        assert!(flags.is_enabled("excluded", "bob", &[]));
+        assert!(!flags.is_enabled("unknown", "alice", &[]));
+    }
+
+    #[test]
+    fn percentage_rollouts_are_proportional_and_differ_by_flag() {
+        let flags = flags(toml::toml! {
+            [quarter]
+            rollout_percent = 25
+
+            [other-quarter]
+            rollout_percent = 25
+        });
+        let keys = (0..10_000).map(|i| format!("user-{i}")).collect::<Vec<_>>();
+        let enabled = |flag| {
+            keys.iter()
+                .filter(|key| flags.is_enabled(flag, key, &[]))
+                .collect::<HashSet<_>>()
+        };
+        let quarter = enabled("quarter");
+        assert!((2_300..2_700).contains(&quarter.len()), "{}", quarter.len());
+        assert_ne!(quarter, enabled("other-quarter"));
+    }
+
+    #[test]
+    fn invalid_percentages_are_rejected() {
+        let definitions = toml::toml! {
+            [too-much]
+            rollout_percent = 101
+        };
+        assert!(FlagSet::from_definitions(definitions.try_into().unwrap()).is_err());
+    }
+}
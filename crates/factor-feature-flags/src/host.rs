@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use spin_world::spin::feature_flags::feature_flags::{self, EvaluationContext};
+
+use crate::provider::FlagCache;
+
+pub struct InstanceState {
+    pub(crate) flags: Arc<FlagCache>,
+}
+
+impl feature_flags::Host for InstanceState {
+    async fn is_enabled(
+        &mut self,
+        flag: String,
+        context: EvaluationContext,
+    ) -> anyhow::Result<bool> {
+        let flags = self.flags.flags().await;
+        Ok(flags.is_enabled(&flag, &context.key, &context.attributes))
+    }
+}
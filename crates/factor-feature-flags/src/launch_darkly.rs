@@ -0,0 +1,211 @@
+//! Flags from a LaunchDarkly-compatible relay, as served to server-side SDKs
+//! which poll `/sdk/latest-all`.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use crate::flag::{Flag, FlagSet, Rollout, Rule};
+
+#[derive(Deserialize)]
+struct Payload {
+    #[serde(default)]
+    flags: HashMap<String, LdFlag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LdFlag {
+    on: bool,
+    #[serde(default)]
+    variations: Vec<Json>,
+    off_variation: Option<usize>,
+    #[serde(default)]
+    fallthrough: VariationOrRollout,
+    #[serde(default)]
+    targets: Vec<Target>,
+    #[serde(default)]
+    rules: Vec<LdRule>,
+    #[serde(default)]
+    salt: String,
+    #[serde(default)]
+    deleted: bool,
+}
+
+#[derive(Default, Deserialize)]
+struct VariationOrRollout {
+    variation: Option<usize>,
+    rollout: Option<WeightedRollout>,
+}
+
+#[derive(Deserialize)]
+struct WeightedRollout {
+    variations: Vec<WeightedVariation>,
+}
+
+#[derive(Deserialize)]
+struct WeightedVariation {
+    variation: usize,
+    weight: u32,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    values: Vec<String>,
+    variation: usize,
+}
+
+#[derive(Deserialize)]
+struct LdRule {
+    #[serde(flatten)]
+    outcome: VariationOrRollout,
+    #[serde(default)]
+    clauses: Vec<Clause>,
+}
+
+#[derive(Deserialize)]
+struct Clause {
+    attribute: String,
+    op: String,
+    #[serde(default)]
+    values: Vec<Json>,
+    #[serde(default)]
+    negate: bool,
+}
+
+/// Parses the flags served by a relay.
+///
+/// Only flags whose variations are all booleans are included. Targeting
+/// rules are supported if they have a single `in` clause; other rules never
+/// match.
+pub(crate) fn parse_flags(json: &[u8]) -> anyhow::Result<FlagSet> {
+    let payload: Payload = serde_json::from_slice(json)?;
+    let flags = payload
+        .flags
+        .into_iter()
+        .filter(|(_, flag)| !flag.deleted)
+        .filter_map(|(name, flag)| {
+            let variations = flag
+                .variations
+                .iter()
+                .map(Json::as_bool)
+                .collect::<Option<Vec<_>>>()?;
+            Some((name, convert_flag(flag, &variations)))
+        })
+        .collect();
+    Ok(FlagSet::new(flags))
+}
+
+fn convert_flag(flag: LdFlag, variations: &[bool]) -> Flag {
+    let value = |variation: usize| variations.get(variation).copied().unwrap_or(false);
+    if !flag.on {
+        let off_value = flag.off_variation.is_some_and(value);
+        return Flag {
+            on: off_value,
+            include: Default::default(),
+            exclude: Default::default(),
+            rules: vec![],
+            fallthrough: Rollout::fixed(off_value),
+            salt: flag.salt,
+        };
+    }
+    let (include, exclude) = flag
+        .targets
+        .into_iter()
+        .partition::<Vec<_>, _>(|target| value(target.variation));
+    let rules = flag
+        .rules
+        .into_iter()
+        .filter_map(|rule| {
+            let [clause] = &rule.clauses[..] else {
+                return None;
+            };
+            if clause.op != "in" || clause.negate {
+                return None;
+            }
+            Some(Rule {
+                attribute: clause.attribute.clone(),
+                values: clause.values.iter().map(clause_value).collect(),
+                rollout: convert_rollout(&rule.outcome, value),
+            })
+        })
+        .collect();
+    Flag {
+        on: true,
+        include: include.into_iter().flat_map(|t| t.values).collect(),
+        exclude: exclude.into_iter().flat_map(|t| t.values).collect(),
+        rules,
+        fallthrough: convert_rollout(&flag.fallthrough, value),
+        salt: flag.salt,
+    }
+}
+
+fn convert_rollout(outcome: &VariationOrRollout, value: impl Fn(usize) -> bool) -> Rollout {
+    match (&outcome.rollout, outcome.variation) {
+        (Some(rollout), _) => Rollout::weighted(
+            rollout
+                .variations
+                .iter()
+                .map(|v| (v.weight, value(v.variation)))
+                .collect(),
+        ),
+        (None, Some(variation)) => Rollout::fixed(value(variation)),
+        (None, None) => Rollout::fixed(false),
+    }
+}
+
+fn clause_value(value: &Json) -> String {
+    match value {
+        Json::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_boolean_flags() {
+        let json = serde_json::json!({
+            "flags": {
+                "new-checkout": {
+                    "key": "new-checkout",
+                    "on": true,
+                    "variations": [true, false],
+                    "offVariation": 1,
+                    "fallthrough": {"rollout": {"variations": [
+                        {"variation": 0, "weight": 0},
+                        {"variation": 1, "weight": 100000},
+                    ]}},
+                    "targets": [{"values": ["alice"], "variation": 0}],
+                    "rules": [{
+                        "variation": 0,
+                        "clauses": [{"attribute": "country", "op": "in", "values": ["NZ"]}],
+                    }],
+                    "salt": "abc",
+                },
+                "killed": {
+                    "on": false,
+                    "variations": [true, false],
+                    "offVariation": 1,
+                    "fallthrough": {"variation": 0},
+                },
+                "banner-text": {
+                    "on": true,
+                    "variations": ["hello", "kia ora"],
+                    "fallthrough": {"variation": 0},
+                },
+            },
+            "segments": {},
+        });
+        let flags = parse_flags(json.to_string().as_bytes()).unwrap();
+        assert_eq!(flags.len(), 2);
+        assert!(flags.is_enabled("new-checkout", "alice", &[]));
+        assert!(!flags.is_enabled("new-checkout", "bob", &[]));
+        assert!(flags.is_enabled("new-checkout", "bob", &[("country".into(), "NZ".into())]));
+        assert!(!flags.is_enabled("killed", "alice", &[]));
+        assert!(!flags.is_enabled("banner-text", "alice", &[]));
+    }
+}
@@ -0,0 +1,102 @@
+mod flag;
+mod host;
+mod launch_darkly;
+mod provider;
+pub mod runtime_config;
+
+use std::sync::Arc;
+
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use flag::{FlagDefinition, FlagSet};
+pub use host::InstanceState;
+use provider::{FlagCache, FlagSource};
+pub use runtime_config::{ProviderConfig, RuntimeConfig};
+
+/// A factor that evaluates feature flags for the guest.
+///
+/// Flags are fetched from the provider configured for the app and evaluated
+/// host-side against a cache shared by all of the app's instances.
+///
+/// Flags may be stored in one of the app's key-value stores, so this factor
+/// must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct FeatureFlagsFactor {
+    _priv: (),
+}
+
+impl FeatureFlagsFactor {
+    /// Create a new FeatureFlagsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for FeatureFlagsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::feature_flags::feature_flags::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let RuntimeConfig {
+            provider,
+            refresh_interval,
+        } = ctx.take_runtime_config().unwrap_or_default();
+        let source = match provider {
+            ProviderConfig::Static(flags) => FlagSource::Static(flags),
+            ProviderConfig::LaunchDarkly { url, sdk_key } => FlagSource::relay(url, sdk_key)?,
+            ProviderConfig::KeyValue { store, key } => {
+                let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+                anyhow::ensure!(
+                    store_manager.is_defined(&store),
+                    "feature flag store {store:?} is not a defined key-value store"
+                );
+                FlagSource::KeyValue {
+                    store_manager,
+                    store,
+                    key,
+                }
+            }
+        };
+        Ok(AppState {
+            flags: Arc::new(FlagCache::new(source, refresh_interval)),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        Ok(InstanceBuilder {
+            flags: ctx.app_state().flags.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The flags fetched for the app's instances.
+    flags: Arc<FlagCache>,
+}
+
+pub struct InstanceBuilder {
+    flags: Arc<FlagCache>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState { flags: self.flags })
+    }
+}
@@ -0,0 +1,153 @@
+//! Fetching flags from providers and caching them for evaluation.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use spin_factor_key_value::StoreManager;
+use tokio::{sync::OnceCell, time::Instant};
+
+use crate::flag::{FlagDefinition, FlagSet};
+use crate::launch_darkly;
+
+/// How long to wait for a remote provider.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where an app's flags come from.
+pub(crate) enum FlagSource {
+    /// Flags defined in runtime config.
+    Static(Arc<FlagSet>),
+    /// Flags polled from a LaunchDarkly-compatible relay.
+    Relay {
+        client: reqwest::Client,
+        url: String,
+        sdk_key: String,
+    },
+    /// Flag definitions stored as JSON under `key` in a key-value store.
+    KeyValue {
+        store_manager: Arc<dyn StoreManager>,
+        store: String,
+        key: String,
+    },
+}
+
+impl FlagSource {
+    pub fn relay(url: String, sdk_key: String) -> anyhow::Result<Self> {
+        Ok(Self::Relay {
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            url,
+            sdk_key,
+        })
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Arc<FlagSet>> {
+        match self {
+            Self::Static(flags) => Ok(flags.clone()),
+            Self::Relay {
+                client,
+                url,
+                sdk_key,
+            } => {
+                let url = format!("{}/sdk/latest-all", url.trim_end_matches('/'));
+                let body = client
+                    .get(&url)
+                    .header(reqwest::header::AUTHORIZATION, sdk_key)
+                    .send()
+                    .await
+                    .and_then(|resp| resp.error_for_status())
+                    .with_context(|| format!("failed to fetch flags from {url}"))?
+                    .bytes()
+                    .await?;
+                let flags = launch_darkly::parse_flags(&body)
+                    .with_context(|| format!("invalid flags from {url}"))?;
+                Ok(Arc::new(flags))
+            }
+            Self::KeyValue {
+                store_manager,
+                store,
+                key,
+            } => {
+                let store = store_manager
+                    .get(store)
+                    .await
+                    .with_context(|| format!("failed to open key-value store {store:?}"))?;
+                let Some(json) = store.get(key).await? else {
+                    return Ok(Default::default());
+                };
+                let definitions: HashMap<String, FlagDefinition> = serde_json::from_slice(&json)
+                    .with_context(|| format!("invalid flag definitions under key {key:?}"))?;
+                Ok(Arc::new(FlagSet::from_definitions(definitions)?))
+            }
+        }
+    }
+}
+
+/// The flags last fetched from an app's provider.
+///
+/// Flags are fetched when first evaluated. After that, evaluations always use
+/// the cached flags, and flags older than the refresh interval are refetched
+/// in the background, so a slow or failing provider never delays a guest.
+pub(crate) struct FlagCache {
+    source: FlagSource,
+    refresh_interval: Duration,
+    loaded: OnceCell<()>,
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    flags: Arc<FlagSet>,
+    fetched_at: Instant,
+    refreshing: bool,
+}
+
+impl FlagCache {
+    pub fn new(source: FlagSource, refresh_interval: Duration) -> Self {
+        let loaded = OnceCell::new();
+        let mut flags = Arc::default();
+        if let FlagSource::Static(static_flags) = &source {
+            flags = static_flags.clone();
+            loaded.set(()).unwrap();
+        }
+        Self {
+            source,
+            refresh_interval,
+            loaded,
+            state: Mutex::new(CacheState {
+                flags,
+                fetched_at: Instant::now(),
+                refreshing: false,
+            }),
+        }
+    }
+
+    /// Returns the cached flags, fetching them if this is the first time
+    /// they are needed.
+    pub async fn flags(self: &Arc<Self>) -> Arc<FlagSet> {
+        self.loaded.get_or_init(|| self.refresh()).await;
+        let mut state = self.state.lock().unwrap();
+        if !matches!(self.source, FlagSource::Static(_))
+            && !state.refreshing
+            && state.fetched_at.elapsed() >= self.refresh_interval
+        {
+            state.refreshing = true;
+            let cache = self.clone();
+            tokio::spawn(async move { cache.refresh().await });
+        }
+        state.flags.clone()
+    }
+
+    async fn refresh(&self) {
+        let result = self.source.fetch().await;
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(flags) => state.flags = flags,
+            // Keep using the flags we have until the provider recovers
+            Err(e) => tracing::warn!("failed to refresh feature flags: {e:?}"),
+        }
+        state.fetched_at = Instant::now();
+        state.refreshing = false;
+    }
+}
@@ -0,0 +1,49 @@
+pub mod spin;
+
+use std::{sync::Arc, time::Duration};
+
+use crate::flag::FlagSet;
+
+/// How often flags from a remote provider are refreshed, by default.
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// The key flags are stored under in a key-value store, by default.
+pub const DEFAULT_KEY_VALUE_KEY: &str = "feature-flags";
+
+/// Runtime configuration for feature flags.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// Where flags come from.
+    pub provider: ProviderConfig,
+    /// How often flags from a remote provider are refreshed.
+    pub refresh_interval: Duration,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            provider: ProviderConfig::Static(Default::default()),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+        }
+    }
+}
+
+/// A provider of feature flags.
+#[derive(Clone, Debug)]
+pub enum ProviderConfig {
+    /// A fixed set of flags.
+    Static(Arc<FlagSet>),
+    /// A LaunchDarkly-compatible relay, such as the LaunchDarkly Relay Proxy.
+    LaunchDarkly {
+        /// The base URL of the relay.
+        url: String,
+        /// The server-side SDK key for the environment.
+        sdk_key: String,
+    },
+    /// Flag definitions stored as JSON in one of the app's key-value stores.
+    KeyValue {
+        /// The label of the key-value store.
+        store: String,
+        /// The key the definitions are stored under.
+        key: String,
+    },
+}
@@ -0,0 +1,160 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{ProviderConfig, RuntimeConfig, DEFAULT_KEY_VALUE_KEY};
+use crate::flag::{FlagDefinition, FlagSet};
+
+/// Get the runtime configuration for feature flags from a TOML table.
+///
+/// Expects table to be in one of the formats:
+/// ```toml
+/// [feature_flags]
+/// type = "static"
+/// [feature_flags.flags.new-checkout]
+/// rollout_percent = 25
+/// include = ["beta-tester"]
+///
+/// [feature_flags]
+/// type = "launch_darkly"
+/// url = "http://ld-relay:8030"
+/// sdk_key = "sdk-1234"
+/// refresh_interval_secs = 30
+///
+/// [feature_flags]
+/// type = "key_value"
+/// store = "flags"
+/// key = "feature-flags"
+/// refresh_interval_secs = 30
+/// ```
+///
+/// A key-value store holds flags as a JSON object of the same flag
+/// definitions as the static provider.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("feature_flags") else {
+        return Ok(None);
+    };
+    let toml: FeatureFlagsToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [feature_flags] table")?;
+
+    let mut config = RuntimeConfig::default();
+    let refresh_interval_secs = match toml {
+        FeatureFlagsToml::Static { flags } => {
+            let flags = FlagSet::from_definitions(flags)?;
+            config.provider = ProviderConfig::Static(Arc::new(flags));
+            None
+        }
+        FeatureFlagsToml::LaunchDarkly {
+            url,
+            sdk_key,
+            refresh_interval_secs,
+        } => {
+            anyhow::ensure!(
+                url.starts_with("http://") || url.starts_with("https://"),
+                "feature_flags.url must be an HTTP(S) URL"
+            );
+            config.provider = ProviderConfig::LaunchDarkly { url, sdk_key };
+            refresh_interval_secs
+        }
+        FeatureFlagsToml::KeyValue {
+            store,
+            key,
+            refresh_interval_secs,
+        } => {
+            config.provider = ProviderConfig::KeyValue {
+                store: store.unwrap_or_else(|| "default".into()),
+                key: key.unwrap_or_else(|| DEFAULT_KEY_VALUE_KEY.into()),
+            };
+            refresh_interval_secs
+        }
+    };
+    if let Some(secs) = refresh_interval_secs {
+        anyhow::ensure!(
+            secs > 0,
+            "feature_flags.refresh_interval_secs must be positive"
+        );
+        config.refresh_interval = Duration::from_secs(secs);
+    }
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum FeatureFlagsToml {
+    Static {
+        #[serde(default)]
+        flags: HashMap<String, FlagDefinition>,
+    },
+    LaunchDarkly {
+        url: String,
+        sdk_key: String,
+        refresh_interval_secs: Option<u64>,
+    },
+    KeyValue {
+        store: Option<String>,
+        key: Option<String>,
+        refresh_interval_secs: Option<u64>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(table: toml::Table) -> anyhow::Result<Option<RuntimeConfig>> {
+        config_from_table(&table)
+    }
+
+    #[test]
+    fn missing_table_is_none() {
+        assert!(config(toml::Table::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn static_flags_are_parsed() {
+        let parsed = config(toml::toml! {
+            [feature_flags]
+            type = "static"
+
+            [feature_flags.flags.new-checkout]
+            rollout_percent = 100
+        })
+        .unwrap()
+        .unwrap();
+        let ProviderConfig::Static(flags) = parsed.provider else {
+            panic!("expected static flags");
+        };
+        assert!(flags.is_enabled("new-checkout", "alice", &[]));
+    }
+
+    #[test]
+    fn remote_providers_are_parsed() {
+        let parsed = config(toml::toml! {
+            [feature_flags]
+            type = "key_value"
+            store = "flags"
+            refresh_interval_secs = 5
+        })
+        .unwrap()
+        .unwrap();
+        assert!(matches!(
+            parsed.provider,
+            ProviderConfig::KeyValue { store, key } if store == "flags" && key == DEFAULT_KEY_VALUE_KEY
+        ));
+        assert_eq!(parsed.refresh_interval, Duration::from_secs(5));
+
+        assert!(config(toml::toml! {
+            [feature_flags]
+            type = "launch_darkly"
+            url = "ld-relay:8030"
+            sdk_key = "sdk-1234"
+        })
+        .is_err());
+    }
+}
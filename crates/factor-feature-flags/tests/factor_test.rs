@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use spin_factor_feature_flags::{FeatureFlagsFactor, ProviderConfig, RuntimeConfig};
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, StoreManager,
+};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::feature_flags::feature_flags::{EvaluationContext, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    feature_flags: FeatureFlagsFactor,
+}
+
+fn memory_store_manager() -> Arc<dyn StoreManager> {
+    let store_manager = MemoryKeyValueStore::new()
+        .make_store(Default::default())
+        .expect("in-memory store should be created");
+    Arc::new(store_manager)
+}
+
+async fn build_state(
+    store_manager: Arc<dyn StoreManager>,
+    feature_flags: RuntimeConfig,
+) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut key_value = spin_factor_key_value::RuntimeConfig::default();
+    key_value.add_store_manager("default".into(), store_manager);
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        feature_flags: FeatureFlagsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    env.runtime_config(TestFactorsRuntimeConfig {
+        key_value: Some(key_value),
+        feature_flags: Some(feature_flags),
+    })?
+    .build_instance_state()
+    .await
+}
+
+fn context(key: &str, attributes: &[(&str, &str)]) -> EvaluationContext {
+    EvaluationContext {
+        key: key.into(),
+        attributes: attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn flags_are_disabled_without_a_provider() -> anyhow::Result<()> {
+    let mut state = build_state(memory_store_manager(), RuntimeConfig::default()).await?;
+    assert!(
+        !state
+            .feature_flags
+            .is_enabled("new-checkout".into(), context("alice", &[]))
+            .await?
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn static_flags_are_evaluated() -> anyhow::Result<()> {
+    let config = spin_factor_feature_flags::runtime_config::spin::config_from_table(&toml! {
+        [feature_flags]
+        type = "static"
+
+        [feature_flags.flags.new-checkout]
+        rollout_percent = 0
+        include = ["alice"]
+        rules = [{ attribute = "country", values = ["NZ"] }]
+    })?
+    .unwrap();
+    let mut state = build_state(memory_store_manager(), config).await?;
+    let flags = &mut state.feature_flags;
+
+    assert!(
+        flags
+            .is_enabled("new-checkout".into(), context("alice", &[]))
+            .await?
+    );
+    assert!(
+        !flags
+            .is_enabled("new-checkout".into(), context("bob", &[]))
+            .await?
+    );
+    assert!(
+        flags
+            .is_enabled("new-checkout".into(), context("bob", &[("country", "NZ")]))
+            .await?
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn flags_are_read_from_a_key_value_store() -> anyhow::Result<()> {
+    let store_manager = memory_store_manager();
+    store_manager
+        .get("default")
+        .await?
+        .set(
+            "feature-flags",
+            br#"{"new-checkout": {"include": ["alice"], "rollout_percent": 0}}"#,
+        )
+        .await?;
+    let config = RuntimeConfig {
+        provider: ProviderConfig::KeyValue {
+            store: "default".into(),
+            key: "feature-flags".into(),
+        },
+        ..Default::default()
+    };
+    let mut state = build_state(store_manager, config).await?;
+    let flags = &mut state.feature_flags;
+
+    assert!(
+        flags
+            .is_enabled("new-checkout".into(), context("alice", &[]))
+            .await?
+    );
+    assert!(
+        !flags
+            .is_enabled("new-checkout".into(), context("bob", &[]))
+            .await?
+    );
+    Ok(())
+}
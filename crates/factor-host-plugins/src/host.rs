@@ -0,0 +1,65 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use spin_world::spin::host_plugins::host_plugins::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::HostPlugin;
+
+pub struct InstanceState {
+    allowed_plugins: HashSet<String>,
+    plugins: Arc<HashMap<String, Arc<dyn HostPlugin>>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_plugins: HashSet<String>,
+        plugins: Arc<HashMap<String, Arc<dyn HostPlugin>>>,
+    ) -> Self {
+        Self {
+            allowed_plugins,
+            plugins,
+        }
+    }
+
+    /// Returns the set of plugin labels this instance may call.
+    pub fn allowed_plugins(&self) -> &HashSet<String> {
+        &self.allowed_plugins
+    }
+}
+
+impl host_plugins::Host for InstanceState {
+    #[instrument(name = "spin_host_plugins.call", skip(self, input), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn call(
+        &mut self,
+        plugin: String,
+        function: String,
+        input: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        if !self.allowed_plugins.contains(&plugin) {
+            return Err(Error::AccessDenied);
+        }
+        // The factor has already checked that each allowed label is defined.
+        let host_plugin = self
+            .plugins
+            .get(&plugin)
+            .ok_or_else(|| Error::Other(format!("host plugin {plugin:?} is not configured")))?;
+        host_plugin.call(&function, input).await.map_err(Into::into)
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(error: crate::Error) -> Self {
+        match error {
+            crate::Error::NoSuchFunction => Error::NoSuchFunction,
+            crate::Error::FunctionFailed(message) => Error::FunctionFailed(message),
+            crate::Error::Other(message) => Error::Other(message),
+        }
+    }
+}
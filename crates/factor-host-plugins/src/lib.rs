@@ -0,0 +1,133 @@
+mod host;
+pub mod library;
+mod plugin;
+pub mod process;
+pub mod runtime_config;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::ensure;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use host::InstanceState;
+pub use plugin::{Error, HostPlugin};
+pub use runtime_config::RuntimeConfig;
+
+/// Metadata key for host plugins.
+pub const HOST_PLUGINS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("host_plugins");
+
+/// A factor that exposes operator-provided host plugins to guests.
+///
+/// Host plugins are loaded from dynamic libraries or run as sub-processes, as
+/// declared in runtime config, so platform teams can offer internal APIs to
+/// components without changing Spin itself.
+#[derive(Default)]
+pub struct HostPluginsFactor {
+    _priv: (),
+}
+
+impl HostPluginsFactor {
+    /// Create a new HostPluginsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for HostPluginsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::host_plugins::host_plugins::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let plugins: HashMap<_, _> = ctx
+            .take_runtime_config()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+
+        // Build component -> allowed plugins map
+        let mut component_allowed_plugins = HashMap::new();
+        for component in ctx.app().components() {
+            let component_id = component.id().to_string();
+            let host_plugins = component
+                .get_metadata(HOST_PLUGINS_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for label in &host_plugins {
+                ensure!(
+                    plugins.contains_key(label),
+                    "unknown host_plugins label {label:?} for component {component_id:?}"
+                );
+            }
+            component_allowed_plugins.insert(component_id, host_plugins);
+        }
+
+        Ok(AppState {
+            plugins: Arc::new(plugins),
+            component_allowed_plugins,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_plugins = app_state
+            .component_allowed_plugins
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_plugins")
+            .clone();
+        Ok(InstanceBuilder {
+            plugins: app_state.plugins.clone(),
+            allowed_plugins,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The host plugins for the app, keyed by label.
+    plugins: Arc<HashMap<String, Arc<dyn HostPlugin>>>,
+    /// The allowed plugins for each component.
+    ///
+    /// This is a map from component ID to the set of plugin labels that the
+    /// component is allowed to call.
+    component_allowed_plugins: HashMap<String, HashSet<String>>,
+}
+
+impl AppState {
+    /// Returns the [`HostPlugin::summary`] for the given plugin label.
+    pub fn plugin_summary(&self, label: &str) -> Option<String> {
+        self.plugins.get(label)?.summary()
+    }
+}
+
+pub struct InstanceBuilder {
+    /// The host plugins for the app, keyed by label.
+    plugins: Arc<HashMap<String, Arc<dyn HostPlugin>>>,
+    /// The allowed plugins for this component instance.
+    allowed_plugins: HashSet<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_plugins, self.plugins))
+    }
+}
@@ -0,0 +1,120 @@
+//! Host plugins loaded from dynamic libraries.
+//!
+//! The library must export these C functions:
+//!
+//! ```c
+//! // Calls `function` with `input`. On return, `*output` points to a buffer of
+//! // `*output_len` bytes allocated by the library (or is null if empty). The
+//! // buffer holds the function's output if the function returns 0, or a UTF-8
+//! // error message if it returns 1. A return value of 2 means the library has
+//! // no such function.
+//! int32_t spin_host_plugin_call(
+//!     const uint8_t *function, size_t function_len,
+//!     const uint8_t *input, size_t input_len,
+//!     uint8_t **output, size_t *output_len);
+//!
+//! // Frees a buffer returned by `spin_host_plugin_call`.
+//! void spin_host_plugin_free(uint8_t *output, size_t output_len);
+//! ```
+//!
+//! Calls run on blocking threads and may run concurrently, so both functions
+//! must be thread safe.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use libloading::Library;
+use spin_core::async_trait;
+
+use crate::{Error, HostPlugin};
+
+type CallFn =
+    unsafe extern "C" fn(*const u8, usize, *const u8, usize, *mut *mut u8, *mut usize) -> i32;
+type FreeFn = unsafe extern "C" fn(*mut u8, usize);
+
+/// A host plugin loaded from a dynamic library.
+pub struct LibraryPlugin {
+    path: PathBuf,
+    call: CallFn,
+    free: FreeFn,
+    /// Keeps `call` and `free` valid.
+    library: Arc<Library>,
+}
+
+impl LibraryPlugin {
+    /// Loads the library at `path`.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and calls trust it to
+    /// implement the functions described in the [module docs](self).
+    pub unsafe fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let library = Library::new(&path)
+            .with_context(|| format!("failed to load host plugin library {}", path.display()))?;
+        let call = *library
+            .get::<CallFn>(b"spin_host_plugin_call\0")
+            .context("host plugin library does not export `spin_host_plugin_call`")?;
+        let free = *library
+            .get::<FreeFn>(b"spin_host_plugin_free\0")
+            .context("host plugin library does not export `spin_host_plugin_free`")?;
+        Ok(Self {
+            path,
+            call,
+            free,
+            library: Arc::new(library),
+        })
+    }
+}
+
+#[async_trait]
+impl HostPlugin for LibraryPlugin {
+    async fn call(&self, function: &str, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let (call, free, library) = (self.call, self.free, self.library.clone());
+        let function = function.to_owned();
+        tokio::task::spawn_blocking(move || {
+            // The library must stay loaded for the duration of the call.
+            let _library = library;
+            let mut output = std::ptr::null_mut();
+            let mut output_len = 0;
+            // SAFETY: the library was loaded trusting it to implement the
+            // documented functions, and it is still loaded.
+            let status = unsafe {
+                call(
+                    function.as_ptr(),
+                    function.len(),
+                    input.as_ptr(),
+                    input.len(),
+                    &mut output,
+                    &mut output_len,
+                )
+            };
+            let output = if output.is_null() {
+                vec![]
+            } else {
+                // SAFETY: as above; the library returned a buffer of
+                // `output_len` bytes, which we copy before freeing it.
+                unsafe {
+                    let bytes = std::slice::from_raw_parts(output, output_len).to_vec();
+                    free(output, output_len);
+                    bytes
+                }
+            };
+            match status {
+                0 => Ok(output),
+                1 => Err(Error::FunctionFailed(
+                    String::from_utf8_lossy(&output).into_owned(),
+                )),
+                2 => Err(Error::NoSuchFunction),
+                other => Err(Error::Other(format!(
+                    "host plugin returned unknown status {other}"
+                ))),
+            }
+        })
+        .await
+        .map_err(|err| Error::Other(format!("host plugin call failed: {err}")))?
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("library `{}`", self.path.display()))
+    }
+}
@@ -0,0 +1,31 @@
+use spin_core::async_trait;
+
+/// An error returned by a [`HostPlugin`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The plugin has no function with the given name.
+    #[error("no such function")]
+    NoSuchFunction,
+    /// The plugin function failed.
+    #[error("function failed: {0}")]
+    FunctionFailed(String),
+    /// The plugin couldn't be reached or misbehaved.
+    #[error("{0}")]
+    Other(String),
+}
+
+/// An operator-provided plugin offering functions to guests.
+///
+/// Functions take and return opaque bytes; the plugin defines their format.
+#[async_trait]
+pub trait HostPlugin: Send + Sync {
+    /// Calls the named function with the given input.
+    async fn call(&self, function: &str, input: Vec<u8>) -> Result<Vec<u8>, Error>;
+
+    /// A human-readable summary of the plugin's configuration
+    ///
+    /// Example: "process `/opt/plugins/billing`"
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
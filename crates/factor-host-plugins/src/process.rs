@@ -0,0 +1,277 @@
+//! Host plugins run as sub-processes.
+//!
+//! Spin starts the plugin program on the first call and exchanges messages
+//! with it over its stdin and stdout, one JSON object per line. The program's
+//! stderr is passed through to Spin's stderr.
+//!
+//! - Requests: `{"id": 1, "function": "charge", "input": "<base64>"}`
+//! - Successful responses: `{"id": 1, "output": "<base64>"}`
+//! - Failed responses: `{"id": 1, "error": {"kind": "failed", "message": "..."}}`
+//!   or `{"id": 1, "error": {"kind": "no-such-function"}}`
+//!
+//! Several requests may be outstanding at once, and responses may be sent in
+//! any order. If the program exits, outstanding calls fail and the next call
+//! starts it again.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use spin_core::async_trait;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, ChildStdout},
+    sync::oneshot,
+};
+
+use crate::{Error, HostPlugin};
+
+/// A host plugin run as a sub-process.
+pub struct ProcessPlugin {
+    command: PathBuf,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    /// The running process, if it has been started.
+    process: tokio::sync::Mutex<Option<Arc<Process>>>,
+    next_id: AtomicU64,
+}
+
+impl ProcessPlugin {
+    /// Creates a plugin which runs `command` with the given arguments and
+    /// additional environment variables.
+    pub fn new(command: PathBuf, args: Vec<String>, env: HashMap<String, String>) -> Self {
+        Self {
+            command,
+            args,
+            env,
+            process: Default::default(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Returns the running process, starting it if it isn't running.
+    async fn process(&self) -> Result<Arc<Process>, Error> {
+        let mut process = self.process.lock().await;
+        if let Some(running) = process
+            .as_ref()
+            .filter(|p| !p.exited.load(Ordering::Acquire))
+        {
+            return Ok(running.clone());
+        }
+        let started = Arc::new(self.start().map_err(|err| {
+            Error::Other(format!(
+                "failed to start host plugin {}: {err}",
+                self.command.display()
+            ))
+        })?);
+        *process = Some(started.clone());
+        Ok(started)
+    }
+
+    fn start(&self) -> std::io::Result<Process> {
+        let mut child = tokio::process::Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin should be piped");
+        let stdout = child.stdout.take().expect("stdout should be piped");
+
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let exited = Arc::new(AtomicBool::new(false));
+        tokio::spawn(read_responses(stdout, pending.clone(), exited.clone()));
+
+        Ok(Process {
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending,
+            exited,
+            _child: child,
+        })
+    }
+}
+
+#[async_trait]
+impl HostPlugin for ProcessPlugin {
+    async fn call(&self, function: &str, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let process = self.process().await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        process.pending.lock().unwrap().insert(id, sender);
+
+        let request = Request {
+            id,
+            function,
+            input: &input,
+        };
+        if let Err(err) = process.send(&request).await {
+            process.pending.lock().unwrap().remove(&id);
+            return Err(Error::Other(format!(
+                "failed to send to host plugin: {err}"
+            )));
+        }
+
+        match receiver.await {
+            Ok(ResponseOutcome::Output(output)) => Ok(output),
+            Ok(ResponseOutcome::Error(ResponseError::NoSuchFunction)) => Err(Error::NoSuchFunction),
+            Ok(ResponseOutcome::Error(ResponseError::Failed { message })) => {
+                Err(Error::FunctionFailed(message))
+            }
+            Err(_) => Err(Error::Other("host plugin exited during the call".into())),
+        }
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("process `{}`", self.command.display()))
+    }
+}
+
+/// Calls waiting for a response, keyed by request ID.
+type PendingCalls = Arc<Mutex<HashMap<u64, oneshot::Sender<ResponseOutcome>>>>;
+
+/// A running plugin process.
+struct Process {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: PendingCalls,
+    /// Set once the process has closed its stdout.
+    exited: Arc<AtomicBool>,
+    /// Held so the process is killed when the plugin is dropped.
+    _child: Child,
+}
+
+impl Process {
+    async fn send(&self, request: &Request<'_>) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(&line).await?;
+        stdin.flush().await
+    }
+}
+
+/// Routes responses to the calls waiting for them until the process closes
+/// its stdout.
+async fn read_responses(stdout: ChildStdout, pending: PendingCalls, exited: Arc<AtomicBool>) {
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(err) => {
+                tracing::error!("Error reading from host plugin: {err}");
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response: Response = match serde_json::from_str(&line) {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::error!("Invalid response from host plugin: {err}");
+                continue;
+            }
+        };
+        if let Some(sender) = pending.lock().unwrap().remove(&response.id) {
+            _ = sender.send(response.outcome);
+        }
+    }
+    exited.store(true, Ordering::Release);
+    // Dropping the senders fails the outstanding calls.
+    pending.lock().unwrap().clear();
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: u64,
+    function: &'a str,
+    #[serde(with = "base64_bytes")]
+    input: &'a [u8],
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Response {
+    id: u64,
+    #[serde(flatten)]
+    outcome: ResponseOutcome,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ResponseOutcome {
+    Output(#[serde(with = "base64_bytes")] Vec<u8>),
+    Error(ResponseError),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum ResponseError {
+    NoSuchFunction,
+    Failed { message: String },
+}
+
+mod base64_bytes {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_use_documented_format() {
+        let request = Request {
+            id: 1,
+            function: "charge",
+            input: b"hello",
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"id":1,"function":"charge","input":"aGVsbG8="}"#
+        );
+    }
+
+    #[test]
+    fn responses_use_documented_format() {
+        let parse = |s| serde_json::from_str::<Response>(s).unwrap();
+        assert_eq!(
+            parse(r#"{"id": 1, "output": "aGVsbG8="}"#),
+            Response {
+                id: 1,
+                outcome: ResponseOutcome::Output(b"hello".to_vec()),
+            }
+        );
+        assert_eq!(
+            parse(r#"{"id": 2, "error": {"kind": "no-such-function"}}"#).outcome,
+            ResponseOutcome::Error(ResponseError::NoSuchFunction)
+        );
+        assert_eq!(
+            parse(r#"{"id": 3, "error": {"kind": "failed", "message": "declined"}}"#).outcome,
+            ResponseOutcome::Error(ResponseError::Failed {
+                message: "declined".into()
+            })
+        );
+    }
+}
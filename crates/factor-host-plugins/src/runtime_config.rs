@@ -0,0 +1,35 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::HostPlugin;
+
+/// Runtime configuration for all host plugins.
+#[derive(Default, Clone)]
+pub struct RuntimeConfig {
+    /// Map of plugin labels to plugins.
+    plugins: HashMap<String, Arc<dyn HostPlugin>>,
+}
+
+impl RuntimeConfig {
+    /// Adds a plugin with the given label to the runtime configuration.
+    ///
+    /// If a plugin already exists for the given label, it will be replaced.
+    pub fn add_plugin(&mut self, label: String, plugin: Arc<dyn HostPlugin>) {
+        self.plugins.insert(label, plugin);
+    }
+
+    /// Returns whether a plugin exists with the given label.
+    pub fn has_plugin(&self, label: &str) -> bool {
+        self.plugins.contains_key(label)
+    }
+}
+
+impl IntoIterator for RuntimeConfig {
+    type Item = (String, Arc<dyn HostPlugin>);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Arc<dyn HostPlugin>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.plugins.into_iter()
+    }
+}
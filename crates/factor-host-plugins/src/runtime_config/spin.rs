@@ -0,0 +1,140 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use crate::{library::LibraryPlugin, process::ProcessPlugin, HostPlugin, RuntimeConfig};
+
+/// Get the runtime configuration for host plugins from a TOML table.
+///
+/// Relative paths are resolved against `runtime_config_dir`.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [host_plugin.billing]
+/// type = "process"
+/// command = "plugins/billing"
+/// args = ["--region", "eu"]
+/// env = { BILLING_URL = "https://billing.internal" }
+///
+/// [host_plugin.geo]
+/// type = "library"
+/// path = "plugins/libgeo.so"
+/// ```
+pub fn config_from_table(
+    table: &impl GetTomlValue,
+    runtime_config_dir: &Path,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("host_plugin") else {
+        return Ok(None);
+    };
+    let plugins: HashMap<String, HostPluginToml> = table
+        .clone()
+        .try_into()
+        .context("failed to parse [host_plugin] table")?;
+
+    let mut runtime_config = RuntimeConfig::default();
+    for (label, config) in plugins {
+        let plugin = config
+            .load(runtime_config_dir)
+            .with_context(|| format!("could not configure host plugin with label '{label}'"))?;
+        runtime_config.add_plugin(label, plugin);
+    }
+    Ok(Some(runtime_config))
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum HostPluginToml {
+    Process {
+        command: PathBuf,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    Library {
+        path: PathBuf,
+    },
+}
+
+impl HostPluginToml {
+    fn load(self, runtime_config_dir: &Path) -> anyhow::Result<Arc<dyn HostPlugin>> {
+        Ok(match self {
+            Self::Process { command, args, env } => {
+                // Bare program names are looked up on the PATH
+                let command = if command.components().count() > 1 {
+                    runtime_config_dir.join(command)
+                } else {
+                    command
+                };
+                Arc::new(ProcessPlugin::new(command, args, env))
+            }
+            Self::Library { path } => {
+                // SAFETY: the runtime operator vouches for the libraries
+                // declared in runtime config.
+                let plugin = unsafe { LibraryPlugin::load(runtime_config_dir.join(path))? };
+                Arc::new(plugin)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_plugins_are_configured() {
+        let table: toml::Table = toml::toml! {
+            [host_plugin.billing]
+            type = "process"
+            command = "plugins/billing"
+            args = ["--region", "eu"]
+
+            [host_plugin.echo]
+            type = "process"
+            command = "cat"
+        };
+        let runtime_config = config_from_table(&table, Path::new("/etc/spin"))
+            .unwrap()
+            .unwrap();
+        assert!(runtime_config.has_plugin("billing"));
+        assert!(runtime_config.has_plugin("echo"));
+
+        let summaries = runtime_config
+            .into_iter()
+            .map(|(label, plugin)| (label, plugin.summary().unwrap()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(summaries["billing"], "process `/etc/spin/plugins/billing`");
+        assert_eq!(summaries["echo"], "process `cat`");
+    }
+
+    #[test]
+    fn missing_library_is_an_error() {
+        let table: toml::Table = toml::toml! {
+            [host_plugin.geo]
+            type = "library"
+            path = "does-not-exist.so"
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_an_error() {
+        let table: toml::Table = toml::toml! {
+            [host_plugin.billing]
+            type = "process"
+            command = "billing"
+            cwd = "/tmp"
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+}
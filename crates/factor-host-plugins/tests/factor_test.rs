@@ -0,0 +1,123 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::bail;
+use spin_core::async_trait;
+use spin_factor_host_plugins::{Error, HostPlugin, HostPluginsFactor, RuntimeConfig};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::host_plugins::host_plugins::{self, Host as _};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    host_plugins: HostPluginsFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            host_plugins: Some(value),
+        }
+    }
+}
+
+fn runtime_config() -> RuntimeConfig {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_plugin("echo".into(), Arc::new(EchoPlugin));
+    runtime_config.add_plugin("other".into(), Arc::new(EchoPlugin));
+    runtime_config
+}
+
+#[tokio::test]
+async fn allowed_plugin_can_be_called() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        host_plugins: HostPluginsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        host_plugins = ["echo"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config())?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.host_plugins.allowed_plugins(),
+        &["echo".into()].into_iter().collect::<HashSet<_>>()
+    );
+
+    let output = state
+        .host_plugins
+        .call("echo".into(), "echo".into(), b"hello".to_vec())
+        .await?;
+    assert_eq!(output, b"hello");
+
+    let err = state
+        .host_plugins
+        .call("echo".into(), "missing".into(), vec![])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, host_plugins::Error::NoSuchFunction));
+    Ok(())
+}
+
+#[tokio::test]
+async fn plugin_not_in_manifest_is_denied() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        host_plugins: HostPluginsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        host_plugins = ["echo"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config())?
+        .build_instance_state()
+        .await?;
+
+    let err = state
+        .host_plugins
+        .call("other".into(), "echo".into(), vec![])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, host_plugins::Error::AccessDenied));
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_plugin_is_not_defined() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        host_plugins: HostPluginsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        host_plugins = ["billing"]
+    });
+    let Err(err) = env
+        .runtime_config(runtime_config())?
+        .build_instance_state()
+        .await
+    else {
+        bail!("expected instance build to fail but it didn't");
+    };
+
+    assert!(err
+        .to_string()
+        .contains(r#"unknown host_plugins label "billing""#));
+    Ok(())
+}
+
+struct EchoPlugin;
+
+#[async_trait]
+impl HostPlugin for EchoPlugin {
+    async fn call(&self, function: &str, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match function {
+            "echo" => Ok(input),
+            _ => Err(Error::NoSuchFunction),
+        }
+    }
+}
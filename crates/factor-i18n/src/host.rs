@@ -0,0 +1,77 @@
+use spin_world::spin::i18n::i18n::{self, DateLength, Error, Ordering, TimeLength, ZoneOffset};
+use tracing::{instrument, Level};
+
+use crate::{locale, time_zone};
+
+pub struct InstanceState {
+    _priv: (),
+}
+
+impl InstanceState {
+    pub(crate) fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl i18n::Host for InstanceState {
+    #[instrument(name = "spin_i18n.time_zones", skip(self))]
+    async fn time_zones(&mut self) -> anyhow::Result<Vec<String>> {
+        Ok(time_zone::names())
+    }
+
+    #[instrument(name = "spin_i18n.offset_at", skip(self), err(level = Level::INFO))]
+    async fn offset_at(
+        &mut self,
+        time_zone: String,
+        timestamp_ms: i64,
+    ) -> Result<ZoneOffset, Error> {
+        let time = time_zone::local_time(&time_zone, timestamp_ms)?;
+        Ok(time_zone::offset(&time))
+    }
+
+    #[instrument(name = "spin_i18n.format_date_time", skip(self), err(level = Level::INFO))]
+    async fn format_date_time(
+        &mut self,
+        locale: String,
+        time_zone: String,
+        timestamp_ms: i64,
+        date: Option<DateLength>,
+        time: Option<TimeLength>,
+    ) -> Result<String, Error> {
+        let locale = locale::parse(&locale)?;
+        let local_time = time_zone::local_time(&time_zone, timestamp_ms)?;
+        locale::format_date_time(&locale, &local_time, date, time)
+    }
+
+    #[instrument(name = "spin_i18n.format_number", skip(self), err(level = Level::INFO))]
+    async fn format_number(
+        &mut self,
+        locale: String,
+        value: f64,
+        fraction_digits: Option<u8>,
+    ) -> Result<String, Error> {
+        let locale = locale::parse(&locale)?;
+        locale::format_number(&locale, value, fraction_digits)
+    }
+
+    #[instrument(name = "spin_i18n.compare", skip(self, a, b), err(level = Level::INFO))]
+    async fn compare(&mut self, locale: String, a: String, b: String) -> Result<Ordering, Error> {
+        let collator = locale::collator(&locale::parse(&locale)?)?;
+        Ok(locale::ordering(collator.compare(&a, &b)))
+    }
+
+    #[instrument(name = "spin_i18n.sort", skip(self, strings), err(level = Level::INFO), fields(strings.len = strings.len()))]
+    async fn sort(
+        &mut self,
+        locale: String,
+        mut strings: Vec<String>,
+    ) -> Result<Vec<String>, Error> {
+        let collator = locale::collator(&locale::parse(&locale)?)?;
+        strings.sort_by(|a, b| collator.compare(a, b));
+        Ok(strings)
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,52 @@
+mod host;
+mod locale;
+mod time_zone;
+
+use spin_factors::{
+    anyhow, ConfigureAppContext, Factor, InitContext, PrepareContext, RuntimeFactors,
+    SelfInstanceBuilder,
+};
+
+pub use host::InstanceState;
+
+/// A factor that gives guests the IANA time zone database and locale-aware
+/// formatting and collation, so that components don't each have to include
+/// the data.
+#[derive(Default)]
+pub struct I18nFactor {
+    _priv: (),
+}
+
+impl I18nFactor {
+    /// Create a new I18nFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for I18nFactor {
+    type RuntimeConfig = ();
+    type AppState = ();
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::i18n::i18n::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(())
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        _ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceState> {
+        Ok(InstanceState::new())
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
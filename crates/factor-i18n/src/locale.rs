@@ -0,0 +1,103 @@
+//! Formatting and collation for a locale, with the locale data compiled into
+//! the host.
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Datelike as _, Timelike as _};
+use chrono_tz::Tz;
+use fixed_decimal::FixedDecimal;
+use icu::{
+    calendar::{DateTime as IcuDateTime, Gregorian},
+    collator::{Collator, CollatorOptions},
+    datetime::{options::length, TypedDateTimeFormatter},
+    decimal::FixedDecimalFormatter,
+    locid::Locale,
+    provider::DataLocale,
+};
+use spin_world::spin::i18n::i18n::{DateLength, Error, TimeLength};
+
+/// Parses a BCP 47 language tag.
+pub(crate) fn parse(locale: &str) -> Result<DataLocale, Error> {
+    let parsed: Locale = locale
+        .parse()
+        .map_err(|e| Error::InvalidLocale(format!("{locale:?}: {e}")))?;
+    Ok((&parsed).into())
+}
+
+pub(crate) fn collator(locale: &DataLocale) -> Result<Collator, Error> {
+    Collator::try_new(locale, CollatorOptions::new()).map_err(|e| Error::Other(e.to_string()))
+}
+
+/// Formats a local time as a date and/or time.
+pub(crate) fn format_date_time(
+    locale: &DataLocale,
+    time: &DateTime<Tz>,
+    date: Option<DateLength>,
+    time_length: Option<TimeLength>,
+) -> Result<String, Error> {
+    let date = date.map(|date| match date {
+        DateLength::Full => length::Date::Full,
+        DateLength::Long => length::Date::Long,
+        DateLength::Medium => length::Date::Medium,
+        DateLength::Short => length::Date::Short,
+    });
+    let time_length = time_length.map(|time| match time {
+        TimeLength::Medium => length::Time::Medium,
+        TimeLength::Short => length::Time::Short,
+    });
+    let options = match (date, time_length) {
+        (Some(date), Some(time)) => length::Bag::from_date_time_style(date, time),
+        (Some(date), None) => length::Bag::from_date_style(date),
+        (None, Some(time)) => length::Bag::from_time_style(time),
+        (None, None) => {
+            return Err(Error::InvalidArgument(
+                "at least one of a date and a time length must be given".into(),
+            ))
+        }
+    };
+    let formatter = TypedDateTimeFormatter::<Gregorian>::try_new(locale, options.into())
+        .map_err(|e| Error::Other(e.to_string()))?;
+    let local = IcuDateTime::try_new_gregorian_datetime(
+        time.year(),
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+    Ok(formatter.format_to_string(&local))
+}
+
+/// Formats a number, rounded to `fraction_digits` digits after the decimal
+/// separator if given.
+pub(crate) fn format_number(
+    locale: &DataLocale,
+    value: f64,
+    fraction_digits: Option<u8>,
+) -> Result<String, Error> {
+    if !value.is_finite() {
+        return Err(Error::InvalidArgument(format!(
+            "{value} can't be formatted as a number"
+        )));
+    }
+    // Rust prints floats without an exponent, in the shortest form which
+    // round trips, which is what FixedDecimal parses
+    let digits = match fraction_digits {
+        Some(fraction_digits) => format!("{value:.*}", usize::from(fraction_digits)),
+        None => value.to_string(),
+    };
+    let decimal: FixedDecimal = digits.parse().map_err(|e| Error::Other(format!("{e:?}")))?;
+    let formatter = FixedDecimalFormatter::try_new(locale, Default::default())
+        .map_err(|e| Error::Other(e.to_string()))?;
+    Ok(formatter.format_to_string(&decimal))
+}
+
+pub(crate) fn ordering(ordering: Ordering) -> spin_world::spin::i18n::i18n::Ordering {
+    use spin_world::spin::i18n::i18n::Ordering as Wit;
+    match ordering {
+        Ordering::Less => Wit::Less,
+        Ordering::Equal => Wit::Equal,
+        Ordering::Greater => Wit::Greater,
+    }
+}
@@ -0,0 +1,36 @@
+//! Time zones from the IANA time zone database.
+
+use chrono::{DateTime, Offset as _, TimeZone as _};
+use chrono_tz::{OffsetComponents as _, Tz};
+use spin_world::spin::i18n::i18n::{Error, ZoneOffset};
+
+/// The names of the time zones in the database.
+pub(crate) fn names() -> Vec<String> {
+    chrono_tz::TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name().to_owned())
+        .collect()
+}
+
+/// A time, in milliseconds since the Unix epoch, in a time zone.
+pub(crate) fn local_time(time_zone: &str, timestamp_ms: i64) -> Result<DateTime<Tz>, Error> {
+    let tz: Tz = time_zone
+        .parse()
+        .map_err(|_| Error::UnknownTimeZone(time_zone.to_owned()))?;
+    tz.timestamp_millis_opt(timestamp_ms)
+        .single()
+        .ok_or_else(|| Error::InvalidArgument(format!("timestamp {timestamp_ms} is out of range")))
+}
+
+/// The offset in effect at a local time.
+pub(crate) fn offset(time: &DateTime<Tz>) -> ZoneOffset {
+    let offset = time.offset();
+    // Zones without an abbreviation for an offset use the offset itself,
+    // e.g. "+03"
+    let abbreviation = time.format("%Z").to_string();
+    ZoneOffset {
+        utc_offset_seconds: offset.fix().local_minus_utc(),
+        is_dst: !offset.dst_offset().is_zero(),
+        abbreviation: (!abbreviation.starts_with(['+', '-'])).then_some(abbreviation),
+    }
+}
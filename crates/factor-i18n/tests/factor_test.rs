@@ -0,0 +1,134 @@
+use spin_factor_i18n::I18nFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::i18n::i18n::{DateLength, Error, Host, Ordering, TimeLength};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    i18n: I18nFactor,
+}
+
+async fn instance_state() -> anyhow::Result<TestFactorsInstanceState> {
+    TestEnvironment::new(TestFactors {
+        i18n: I18nFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+    .build_instance_state()
+    .await
+}
+
+/// 2024-07-02T16:04:05Z
+const SUMMER: i64 = 1_719_936_245_000;
+
+#[tokio::test]
+async fn time_zones_come_from_the_database() -> anyhow::Result<()> {
+    let mut state = instance_state().await?;
+    let zones = state.i18n.time_zones().await?;
+    assert!(zones.iter().any(|zone| zone == "Europe/Berlin"));
+
+    let offset = state
+        .i18n
+        .offset_at("Europe/Berlin".into(), SUMMER)
+        .await
+        .unwrap();
+    assert_eq!(offset.utc_offset_seconds, 7200);
+    assert!(offset.is_dst);
+    assert_eq!(offset.abbreviation.as_deref(), Some("CEST"));
+
+    let err = state
+        .i18n
+        .offset_at("Mars/Olympus_Mons".into(), SUMMER)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnknownTimeZone(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn dates_are_formatted_in_the_time_zone() -> anyhow::Result<()> {
+    let mut state = instance_state().await?;
+    let formatted = state
+        .i18n
+        .format_date_time(
+            "en-US".into(),
+            "America/New_York".into(),
+            SUMMER,
+            Some(DateLength::Long),
+            Some(TimeLength::Short),
+        )
+        .await
+        .unwrap();
+    assert!(formatted.contains("July 2, 2024"), "{formatted}");
+    assert!(formatted.contains("12:04"), "{formatted}");
+
+    let formatted = state
+        .i18n
+        .format_date_time(
+            "de-DE".into(),
+            "Europe/Berlin".into(),
+            SUMMER,
+            Some(DateLength::Short),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(formatted, "02.07.24");
+
+    let err = state
+        .i18n
+        .format_date_time("en-US".into(), "UTC".into(), SUMMER, None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn numbers_are_formatted_for_the_locale() -> anyhow::Result<()> {
+    let mut state = instance_state().await?;
+    let formatted = state
+        .i18n
+        .format_number("en-US".into(), 1234567.891, None)
+        .await
+        .unwrap();
+    assert_eq!(formatted, "1,234,567.891");
+    let formatted = state
+        .i18n
+        .format_number("de-DE".into(), 1234567.891, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(formatted, "1.234.567,89");
+
+    let err = state
+        .i18n
+        .format_number("not a locale".into(), 1.0, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidLocale(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn strings_sort_in_the_locale_order() -> anyhow::Result<()> {
+    let mut state = instance_state().await?;
+    let strings = vec!["z".to_owned(), "ä".to_owned(), "a".to_owned()];
+    assert_eq!(
+        state.i18n.sort("de".into(), strings.clone()).await.unwrap(),
+        ["a", "ä", "z"]
+    );
+    assert_eq!(
+        state.i18n.sort("sv".into(), strings).await.unwrap(),
+        ["a", "z", "ä"]
+    );
+    assert!(matches!(
+        state
+            .i18n
+            .compare("sv".into(), "ä".into(), "z".into())
+            .await,
+        Ok(Ordering::Greater)
+    ));
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Generation of time-ordered IDs.
+//!
+//! Each kind of ID keeps the timestamp of the last ID it generated. When the
+//! system clock hasn't moved past that timestamp, because it's still the
+//! same millisecond or the clock has been set back, the last timestamp is
+//! reused and the rest of the ID is incremented instead, so IDs never go
+//! backwards.
+
+use std::sync::Mutex;
+
+use spin_common::time::now_millis;
+use spin_world::spin::ids::ids::Error;
+
+use crate::RuntimeConfig;
+
+/// The random bits in a UUIDv7.
+const UUID_V7_RANDOM_BITS: u32 = 74;
+
+/// The random bits in a ULID.
+const ULID_RANDOM_BITS: u32 = 80;
+
+/// The bits of a snowflake ID's timestamp.
+const SNOWFLAKE_TIMESTAMP_BITS: u32 = 41;
+
+/// The bits of a snowflake ID's sequence number.
+const SNOWFLAKE_SEQUENCE_BITS: u32 = 12;
+
+/// The bits of a snowflake ID's node ID.
+pub(crate) const SNOWFLAKE_NODE_BITS: u32 = 10;
+
+/// The alphabet of Crockford's base32, used by ULIDs.
+const CROCKFORD_BASE32: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Generates UUIDv7s, ULIDs and snowflake IDs.
+pub struct IdGenerator {
+    config: RuntimeConfig,
+    uuid_v7: Mutex<RandomState>,
+    ulid: Mutex<RandomState>,
+    snowflake: Mutex<SnowflakeState>,
+}
+
+impl IdGenerator {
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            config,
+            uuid_v7: Default::default(),
+            ulid: Default::default(),
+            snowflake: Default::default(),
+        }
+    }
+
+    /// Generates a UUIDv7 as a lowercase hyphenated string.
+    pub fn uuid_v7(&self) -> String {
+        self.uuid_v7_at(now_millis(), rand::random())
+    }
+
+    /// Generates a ULID as a Crockford base32 string.
+    pub fn ulid(&self) -> String {
+        self.ulid_at(now_millis(), rand::random())
+    }
+
+    /// Generates a snowflake ID.
+    pub fn snowflake(&self) -> Result<u64, Error> {
+        self.snowflake_at(now_millis())
+    }
+
+    fn uuid_v7_at(&self, now: u64, random: u128) -> String {
+        let (ms, random) = self
+            .uuid_v7
+            .lock()
+            .unwrap()
+            .next(now, random, UUID_V7_RANDOM_BITS);
+        let rand_a = random >> 62;
+        let rand_b = random & ((1 << 62) - 1);
+        let uuid = ((u128::from(ms) & 0xffff_ffff_ffff) << 80)
+            | (0x7 << 76)
+            | (rand_a << 64)
+            | (0b10 << 62)
+            | rand_b;
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            uuid >> 96,
+            (uuid >> 80) & 0xffff,
+            (uuid >> 64) & 0xffff,
+            (uuid >> 48) & 0xffff,
+            uuid & 0xffff_ffff_ffff
+        )
+    }
+
+    fn ulid_at(&self, now: u64, random: u128) -> String {
+        let (ms, random) = self
+            .ulid
+            .lock()
+            .unwrap()
+            .next(now, random, ULID_RANDOM_BITS);
+        let ulid = ((u128::from(ms) & 0xffff_ffff_ffff) << 80) | random;
+        (0..26)
+            .map(|i| CROCKFORD_BASE32[((ulid >> (125 - 5 * i)) & 0x1f) as usize] as char)
+            .collect()
+    }
+
+    fn snowflake_at(&self, now: u64) -> Result<u64, Error> {
+        let node_id = self.config.node_id.ok_or(Error::NoNodeId)?;
+        let epoch = self.config.snowflake_epoch_ms;
+        let (ms, sequence) = self.snowflake.lock().unwrap().next(now);
+        let elapsed = ms.checked_sub(epoch).ok_or_else(|| {
+            Error::Other(format!(
+                "the clock is before the snowflake epoch ({epoch} ms since the Unix epoch)"
+            ))
+        })?;
+        if elapsed >> SNOWFLAKE_TIMESTAMP_BITS != 0 {
+            return Err(Error::Other(format!(
+                "the snowflake timestamp has run out; the epoch ({epoch} ms since the Unix epoch) is too long ago"
+            )));
+        }
+        Ok((elapsed << (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS))
+            | (u64::from(node_id) << SNOWFLAKE_SEQUENCE_BITS)
+            | sequence)
+    }
+}
+
+/// The last timestamp and random bits of an ID with random bits.
+#[derive(Default)]
+struct RandomState {
+    ms: u64,
+    random: u128,
+}
+
+impl RandomState {
+    /// Returns the timestamp and `bits` random bits of the next ID.
+    fn next(&mut self, now: u64, random: u128, bits: u32) -> (u64, u128) {
+        let mask = (1 << bits) - 1;
+        if now > self.ms {
+            self.ms = now;
+            self.random = random & mask;
+        } else if self.random < mask {
+            self.random += 1;
+        } else {
+            // The random bits have run out, so borrow the next millisecond
+            self.ms += 1;
+            self.random = random & mask;
+        }
+        (self.ms, self.random)
+    }
+}
+
+/// The last timestamp and sequence number of a snowflake ID.
+#[derive(Default)]
+struct SnowflakeState {
+    ms: u64,
+    sequence: u64,
+}
+
+impl SnowflakeState {
+    /// Returns the timestamp and sequence number of the next ID.
+    fn next(&mut self, now: u64) -> (u64, u64) {
+        if now > self.ms {
+            self.ms = now;
+            self.sequence = 0;
+        } else if self.sequence < (1 << SNOWFLAKE_SEQUENCE_BITS) - 1 {
+            self.sequence += 1;
+        } else {
+            // The sequence has run out, so borrow the next millisecond
+            self.ms += 1;
+            self.sequence = 0;
+        }
+        (self.ms, self.sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(node_id: Option<u16>) -> IdGenerator {
+        IdGenerator::new(RuntimeConfig {
+            node_id,
+            snowflake_epoch_ms: 1_000,
+        })
+    }
+
+    #[test]
+    fn uuid_v7_layout() {
+        let generator = generator(None);
+        let uuid = generator.uuid_v7_at(0x0123_4567_89ab, u128::MAX);
+        assert_eq!(uuid, "01234567-89ab-7fff-bfff-ffffffffffff");
+        let uuid = generator.uuid_v7_at(0x0123_4567_89ac, 0);
+        assert_eq!(uuid, "01234567-89ac-7000-8000-000000000000");
+    }
+
+    #[test]
+    fn ulid_layout() {
+        let generator = generator(None);
+        assert_eq!(generator.ulid_at(1, 0), "00000000010000000000000000");
+        assert_eq!(
+            generator.ulid_at(0xffff_ffff_ffff, u128::MAX),
+            "7ZZZZZZZZZZZZZZZZZZZZZZZZZ"
+        );
+    }
+
+    #[test]
+    fn ids_increase_when_the_clock_does_not() {
+        let generator = generator(Some(1));
+        let uuids = [
+            generator.uuid_v7_at(5_000, 10),
+            generator.uuid_v7_at(5_000, 3),
+            generator.uuid_v7_at(4_000, 0),
+        ];
+        assert!(uuids.is_sorted() && uuids[0] < uuids[1], "{uuids:?}");
+        let ulids = [
+            generator.ulid_at(5_000, 10),
+            generator.ulid_at(5_000, 3),
+            generator.ulid_at(4_000, 0),
+        ];
+        assert!(ulids.is_sorted() && ulids[0] < ulids[1], "{ulids:?}");
+        let snowflakes = [
+            generator.snowflake_at(5_000).unwrap(),
+            generator.snowflake_at(5_000).unwrap(),
+            generator.snowflake_at(4_000).unwrap(),
+        ];
+        assert_eq!(snowflakes[1] - snowflakes[0], 1);
+        assert_eq!(snowflakes[2] - snowflakes[1], 1);
+    }
+
+    #[test]
+    fn exhausted_random_bits_borrow_the_next_millisecond() {
+        let mut state = RandomState::default();
+        assert_eq!(state.next(7, u128::MAX, 4), (7, 0xf));
+        assert_eq!(state.next(7, 2, 4), (8, 2));
+        assert_eq!(state.next(8, 0, 4), (8, 3));
+    }
+
+    #[test]
+    fn snowflake_layout() {
+        let generator = generator(Some(0x3ff));
+        let id = generator.snowflake_at(1_005).unwrap();
+        assert_eq!(id, (5 << 22) | (0x3ff << 12));
+        for _ in 0..4095 {
+            generator.snowflake_at(1_005).unwrap();
+        }
+        // The sequence has run out for 1005 ms
+        assert_eq!(
+            generator.snowflake_at(1_005).unwrap(),
+            (6 << 22) | (0x3ff << 12)
+        );
+    }
+
+    #[test]
+    fn snowflake_errors() {
+        assert!(matches!(
+            generator(None).snowflake_at(5_000),
+            Err(Error::NoNodeId)
+        ));
+        assert!(matches!(
+            generator(Some(1)).snowflake_at(500),
+            Err(Error::Other(_))
+        ));
+        assert!(matches!(
+            generator(Some(1)).snowflake_at(1_000 + (1 << 41)),
+            Err(Error::Other(_))
+        ));
+    }
+}
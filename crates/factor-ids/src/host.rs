@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use spin_world::spin::ids::ids::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::IdGenerator;
+
+pub struct InstanceState {
+    generator: Arc<IdGenerator>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(generator: Arc<IdGenerator>) -> Self {
+        Self { generator }
+    }
+}
+
+impl ids::Host for InstanceState {
+    #[instrument(name = "spin_ids.uuid_v7", skip(self))]
+    async fn uuid_v7(&mut self) -> anyhow::Result<String> {
+        Ok(self.generator.uuid_v7())
+    }
+
+    #[instrument(name = "spin_ids.ulid", skip(self))]
+    async fn ulid(&mut self) -> anyhow::Result<String> {
+        Ok(self.generator.ulid())
+    }
+
+    #[instrument(name = "spin_ids.snowflake", skip(self), err(level = Level::INFO))]
+    async fn snowflake(&mut self) -> Result<u64, Error> {
+        self.generator.snowflake()
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
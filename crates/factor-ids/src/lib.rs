@@ -0,0 +1,84 @@
+mod generator;
+mod host;
+pub mod runtime_config;
+
+use std::sync::Arc;
+
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use generator::IdGenerator;
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that generates time-ordered unique IDs for the guest.
+///
+/// All components of an app share one generator, so the IDs they generate
+/// are strictly increasing. Snowflake IDs also need a node ID to be set in
+/// runtime config.
+#[derive(Default)]
+pub struct IdsFactor {
+    _priv: (),
+}
+
+impl IdsFactor {
+    /// Create a new IdsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for IdsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::ids::ids::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            generator: Arc::new(IdGenerator::new(config)),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        Ok(InstanceBuilder {
+            generator: ctx.app_state().generator.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The generator shared by all of the app's components.
+    generator: Arc<IdGenerator>,
+}
+
+impl AppState {
+    /// The generator shared by all of the app's components.
+    pub fn generator(&self) -> &Arc<IdGenerator> {
+        &self.generator
+    }
+}
+
+pub struct InstanceBuilder {
+    generator: Arc<IdGenerator>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.generator))
+    }
+}
@@ -0,0 +1,22 @@
+pub mod spin;
+
+/// Runtime configuration for ID generation.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The node ID put in snowflake IDs, which must be below 1024. Snowflake
+    /// IDs can't be generated without one.
+    pub node_id: Option<u16>,
+    /// The time snowflake timestamps count from, in milliseconds since the
+    /// Unix epoch.
+    pub snowflake_epoch_ms: u64,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            node_id: None,
+            // 2024-01-01T00:00:00Z
+            snowflake_epoch_ms: 1_704_067_200_000,
+        }
+    }
+}
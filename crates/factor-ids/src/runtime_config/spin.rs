@@ -0,0 +1,50 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+use crate::generator::SNOWFLAKE_NODE_BITS;
+
+/// Get the runtime configuration for ID generation from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [ids]
+/// node_id = 12
+/// snowflake_epoch_ms = 1704067200000
+/// ```
+///
+/// Each key is optional. Without a `node_id`, snowflake IDs can't be
+/// generated; `snowflake_epoch_ms` defaults to the start of 2024.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("ids") else {
+        return Ok(None);
+    };
+    let toml: IdsToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [ids] table")?;
+    if let Some(node_id) = toml.node_id {
+        anyhow::ensure!(
+            node_id < 1 << SNOWFLAKE_NODE_BITS,
+            "[ids] node_id must be below {}, got {node_id}",
+            1 << SNOWFLAKE_NODE_BITS
+        );
+    }
+    let default = RuntimeConfig::default();
+    Ok(Some(RuntimeConfig {
+        node_id: toml.node_id,
+        snowflake_epoch_ms: toml
+            .snowflake_epoch_ms
+            .unwrap_or(default.snowflake_epoch_ms),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdsToml {
+    node_id: Option<u16>,
+    snowflake_epoch_ms: Option<u64>,
+}
@@ -0,0 +1,58 @@
+use spin_factor_ids::{IdsFactor, RuntimeConfig};
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::ids::ids::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    ids: IdsFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        ids: IdsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+#[tokio::test]
+async fn ids_are_increasing() -> anyhow::Result<()> {
+    let mut state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            ids: Some(RuntimeConfig {
+                node_id: Some(7),
+                ..Default::default()
+            }),
+        })?
+        .build_instance_state()
+        .await?;
+
+    let mut uuids = vec![];
+    let mut ulids = vec![];
+    let mut snowflakes = vec![];
+    for _ in 0..100 {
+        uuids.push(state.ids.uuid_v7().await?);
+        ulids.push(state.ids.ulid().await?);
+        snowflakes.push(state.ids.snowflake().await.unwrap());
+    }
+    assert!(uuids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(ulids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(snowflakes.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert_eq!(&uuids[0][14..15], "7");
+    assert_eq!(ulids[0].len(), 26);
+    assert_eq!((snowflakes[0] >> 12) & 0x3ff, 7);
+    Ok(())
+}
+
+#[tokio::test]
+async fn snowflake_needs_node_id() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+    assert!(matches!(state.ids.snowflake().await, Err(Error::NoNodeId)));
+    // IDs with random bits don't need one
+    assert_eq!(state.ids.uuid_v7().await?.len(), 36);
+    Ok(())
+}
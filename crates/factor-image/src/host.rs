@@ -0,0 +1,95 @@
+use std::{collections::HashSet, sync::Arc};
+
+use spin_world::spin::image::image::{self, Error, ImageInfo, Operation, Output};
+use tracing::{instrument, Level};
+
+use crate::{processing, RuntimeConfig};
+
+pub struct InstanceState {
+    allowed_operations: HashSet<String>,
+    config: Arc<RuntimeConfig>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(allowed_operations: HashSet<String>, config: Arc<RuntimeConfig>) -> Self {
+        Self {
+            allowed_operations,
+            config,
+        }
+    }
+
+    fn ensure_allowed(&self, operation: &str) -> Result<(), Error> {
+        if self.allowed_operations.contains(operation) {
+            Ok(())
+        } else {
+            Err(Error::AccessDenied(format!(
+                "The component does not have access to the '{operation}' image operation. To give the component access, add '{operation}' to the 'image_operations' key for the component in your spin.toml manifest"
+            )))
+        }
+    }
+
+    /// Runs CPU-bound image work off the async executor.
+    async fn run<T: Send + 'static>(
+        &self,
+        work: impl FnOnce(&RuntimeConfig) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || work(&config))
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+    }
+}
+
+impl image::Host for InstanceState {
+    #[instrument(name = "spin_image.info", skip(self, image), err(level = Level::INFO))]
+    async fn info(&mut self, image: Vec<u8>) -> Result<ImageInfo, Error> {
+        processing::info(&image, &self.config)
+    }
+
+    #[instrument(name = "spin_image.process", skip(self, image, operations), err(level = Level::INFO), fields(image.size = image.len()))]
+    async fn process(
+        &mut self,
+        image: Vec<u8>,
+        operations: Vec<Operation>,
+        output: Output,
+    ) -> Result<Vec<u8>, Error> {
+        for operation in &operations {
+            self.ensure_allowed(match operation {
+                Operation::Resize(_) => "resize",
+                Operation::Crop(_) => "crop",
+            })?;
+        }
+        if processing::format(&image)? != output.format {
+            self.ensure_allowed("convert")?;
+        }
+        self.run(move |config| {
+            let decoded = processing::decode(&image, config)?;
+            let processed = operations.iter().try_fold(decoded, |image, operation| {
+                processing::apply(image, operation, config)
+            })?;
+            processing::encode(&processed, &output)
+        })
+        .await
+    }
+
+    #[instrument(name = "spin_image.strip_metadata", skip(self, image), err(level = Level::INFO), fields(image.size = image.len()))]
+    async fn strip_metadata(&mut self, image: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.ensure_allowed("strip-metadata")?;
+        let format = processing::format(&image)?;
+        self.run(move |config| {
+            let decoded = processing::decode(&image, config)?;
+            processing::encode(
+                &decoded,
+                &Output {
+                    format,
+                    quality: None,
+                },
+            )
+        })
+        .await
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
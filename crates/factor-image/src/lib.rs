@@ -0,0 +1,115 @@
+mod host;
+mod processing;
+pub mod runtime_config;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::ensure;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// Metadata key for the image operations a component may use.
+pub const IMAGE_OPERATIONS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("image_operations");
+
+/// The operations which can be listed in `image_operations`.
+pub const IMAGE_OPERATIONS: &[&str] = &["resize", "crop", "convert", "strip-metadata"];
+
+/// A factor that resizes, crops and converts images for the guest.
+///
+/// Components must list the operations they use in their manifest, and images
+/// are decoded within the limits set in runtime config.
+#[derive(Default)]
+pub struct ImageFactor {
+    _priv: (),
+}
+
+impl ImageFactor {
+    /// Create a new ImageFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for ImageFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::image::image::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+
+        let mut component_allowed_operations = HashMap::new();
+        for component in ctx.app().components() {
+            let component_id = component.id().to_string();
+            let operations = component
+                .get_metadata(IMAGE_OPERATIONS_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for operation in &operations {
+                ensure!(
+                    IMAGE_OPERATIONS.contains(&operation.as_str()),
+                    "unknown image_operations entry {operation:?} for component {component_id:?}; expected one of {IMAGE_OPERATIONS:?}"
+                );
+            }
+            component_allowed_operations.insert(component_id, operations);
+        }
+
+        Ok(AppState {
+            config: Arc::new(config),
+            component_allowed_operations,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_operations = app_state
+            .component_allowed_operations
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_operations")
+            .clone();
+        Ok(InstanceBuilder {
+            allowed_operations,
+            config: app_state.config.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The image processing limits for the app.
+    config: Arc<RuntimeConfig>,
+    /// The operations each component may use, keyed by component ID.
+    component_allowed_operations: HashMap<String, HashSet<String>>,
+}
+
+pub struct InstanceBuilder {
+    allowed_operations: HashSet<String>,
+    config: Arc<RuntimeConfig>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_operations, self.config))
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageError, ImageFormat, ImageReader, Limits,
+};
+use spin_world::spin::image::image::{
+    Error, Fit, Format, ImageInfo, Operation, Output, Region, ResizeOptions,
+};
+
+use crate::RuntimeConfig;
+
+/// The quality of lossy output when the guest doesn't choose one.
+const DEFAULT_QUALITY: u8 = 85;
+
+/// The speed of AVIF encoding, from 1 (slowest) to 10 (fastest).
+const AVIF_SPEED: u8 = 8;
+
+/// Guesses the format of `bytes` from its magic number.
+pub(crate) fn format(bytes: &[u8]) -> Result<Format, Error> {
+    let format = image::guess_format(bytes).map_err(image_to_wit_err)?;
+    from_image_format(format)
+}
+
+/// Reads the format and dimensions of `bytes` from its header.
+pub(crate) fn info(bytes: &[u8], config: &RuntimeConfig) -> Result<ImageInfo, Error> {
+    let (reader, format) = reader(bytes, config)?;
+    let (width, height) = reader.into_dimensions().map_err(image_to_wit_err)?;
+    Ok(ImageInfo {
+        format,
+        width,
+        height,
+    })
+}
+
+/// Decodes `bytes` within the configured limits.
+pub(crate) fn decode(bytes: &[u8], config: &RuntimeConfig) -> Result<DynamicImage, Error> {
+    let (mut reader, _) = reader(bytes, config)?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(config.max_dimension);
+    limits.max_image_height = Some(config.max_dimension);
+    limits.max_alloc = Some(config.max_memory_bytes);
+    reader.limits(limits);
+    reader.decode().map_err(image_to_wit_err)
+}
+
+fn reader<'a>(
+    bytes: &'a [u8],
+    config: &RuntimeConfig,
+) -> Result<(ImageReader<Cursor<&'a [u8]>>, Format), Error> {
+    if bytes.len() > config.max_input_bytes {
+        return Err(Error::LimitsExceeded(format!(
+            "image is {} bytes, but the limit is {}",
+            bytes.len(),
+            config.max_input_bytes
+        )));
+    }
+    let format = format(bytes)?;
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| Error::Other(e.to_string()))?;
+    Ok((reader, format))
+}
+
+/// Applies a pipeline step to `image`.
+pub(crate) fn apply(
+    image: DynamicImage,
+    operation: &Operation,
+    config: &RuntimeConfig,
+) -> Result<DynamicImage, Error> {
+    match *operation {
+        Operation::Resize(ResizeOptions { width, height, fit }) => {
+            if width == 0 || height == 0 {
+                return Err(Error::InvalidParameters(format!(
+                    "cannot resize to {width}x{height}"
+                )));
+            }
+            check_output_size(&image, width, height, config)?;
+            Ok(match fit {
+                Fit::Contain => image.resize(width, height, FilterType::Lanczos3),
+                Fit::Cover => image.resize_to_fill(width, height, FilterType::Lanczos3),
+                Fit::Fill => image.resize_exact(width, height, FilterType::Lanczos3),
+            })
+        }
+        Operation::Crop(Region {
+            x,
+            y,
+            width,
+            height,
+        }) => {
+            let in_bounds = u64::from(x) + u64::from(width) <= u64::from(image.width())
+                && u64::from(y) + u64::from(height) <= u64::from(image.height());
+            if width == 0 || height == 0 || !in_bounds {
+                return Err(Error::InvalidParameters(format!(
+                    "crop region {width}x{height} at ({x}, {y}) is not within the {}x{} image",
+                    image.width(),
+                    image.height()
+                )));
+            }
+            Ok(image.crop_imm(x, y, width, height))
+        }
+    }
+}
+
+/// Checks that resizing `image` to `width` by `height` stays within limits.
+fn check_output_size(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    config: &RuntimeConfig,
+) -> Result<(), Error> {
+    if width > config.max_dimension || height > config.max_dimension {
+        return Err(Error::LimitsExceeded(format!(
+            "{width}x{height} exceeds the maximum dimension of {}",
+            config.max_dimension
+        )));
+    }
+    let bytes = u64::from(width)
+        .saturating_mul(u64::from(height))
+        .saturating_mul(u64::from(image.color().bytes_per_pixel()));
+    if bytes > config.max_memory_bytes {
+        return Err(Error::LimitsExceeded(format!(
+            "a {width}x{height} image needs {bytes} bytes, but the limit is {}",
+            config.max_memory_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Encodes `image` as requested. The result carries no metadata.
+pub(crate) fn encode(image: &DynamicImage, output: &Output) -> Result<Vec<u8>, Error> {
+    let quality = match output.quality {
+        None => DEFAULT_QUALITY,
+        Some(quality @ 1..=100) => quality,
+        Some(quality) => {
+            return Err(Error::InvalidParameters(format!(
+                "quality {quality} is not between 1 and 100"
+            )))
+        }
+    };
+    let mut bytes = Vec::new();
+    let result = match output.format {
+        // JPEG has no alpha channel.
+        Format::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality)),
+        Format::Avif => DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(
+            AvifEncoder::new_with_speed_quality(&mut bytes, AVIF_SPEED, quality),
+        ),
+        Format::Png => image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png),
+        Format::Gif => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Gif),
+        Format::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::WebP),
+    };
+    result.map_err(image_to_wit_err)?;
+    Ok(bytes)
+}
+
+fn from_image_format(format: ImageFormat) -> Result<Format, Error> {
+    Ok(match format {
+        ImageFormat::Png => Format::Png,
+        ImageFormat::Jpeg => Format::Jpeg,
+        ImageFormat::Gif => Format::Gif,
+        ImageFormat::WebP => Format::Webp,
+        ImageFormat::Avif => Format::Avif,
+        other => {
+            return Err(Error::InvalidImage(format!(
+                "unsupported image format {other:?}"
+            )))
+        }
+    })
+}
+
+fn image_to_wit_err(err: ImageError) -> Error {
+    match err {
+        ImageError::Limits(_) => Error::LimitsExceeded(err.to_string()),
+        ImageError::Decoding(_) | ImageError::Unsupported(_) => {
+            Error::InvalidImage(err.to_string())
+        }
+        ImageError::Parameter(_) => Error::InvalidParameters(err.to_string()),
+        _ => Error::Other(err.to_string()),
+    }
+}
@@ -0,0 +1,22 @@
+pub mod spin;
+
+/// Runtime configuration for image processing.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The largest encoded image accepted, in bytes.
+    pub max_input_bytes: usize,
+    /// The largest width or height of an image read or produced, in pixels.
+    pub max_dimension: u32,
+    /// The most memory that a decoded or produced image may use, in bytes.
+    pub max_memory_bytes: u64,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: 32 * 1024 * 1024,
+            max_dimension: 16384,
+            max_memory_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for image processing from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [image_processing]
+/// max_input_mb = 32
+/// max_dimension = 16384
+/// max_memory_mb = 256
+/// ```
+///
+/// Each key is optional and defaults to the value shown.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("image_processing") else {
+        return Ok(None);
+    };
+    let toml: ImageProcessingToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [image_processing] table")?;
+    let default = RuntimeConfig::default();
+    Ok(Some(RuntimeConfig {
+        max_input_bytes: toml
+            .max_input_mb
+            .map_or(default.max_input_bytes, |mb| mb.saturating_mul(1024 * 1024)),
+        max_dimension: toml.max_dimension.unwrap_or(default.max_dimension),
+        max_memory_bytes: toml.max_memory_mb.map_or(default.max_memory_bytes, |mb| {
+            mb.saturating_mul(1024 * 1024)
+        }),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageProcessingToml {
+    max_input_mb: Option<usize>,
+    max_dimension: Option<u32>,
+    max_memory_mb: Option<u64>,
+}
@@ -0,0 +1,156 @@
+use std::io::Cursor;
+
+use spin_factor_image::{ImageFactor, RuntimeConfig};
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::image::image::{
+    Error, Fit, Format, Host, Operation, Output, Region, ResizeOptions,
+};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    image: ImageFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        image: ImageFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        image_operations = ["resize", "crop"]
+    })
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image::RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]))
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+const PNG: Output = Output {
+    format: Format::Png,
+    quality: None,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn allowed_operations_run_in_order() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let resized = state
+        .image
+        .process(
+            png(40, 20),
+            vec![
+                Operation::Resize(ResizeOptions {
+                    width: 10,
+                    height: 10,
+                    fit: Fit::Contain,
+                }),
+                Operation::Crop(Region {
+                    x: 2,
+                    y: 0,
+                    width: 6,
+                    height: 5,
+                }),
+            ],
+            PNG,
+        )
+        .await
+        .unwrap();
+    let info = state.image.info(resized).await.unwrap();
+    assert_eq!(info.format, Format::Png);
+    assert_eq!((info.width, info.height), (6, 5));
+
+    let err = state
+        .image
+        .process(
+            png(4, 4),
+            vec![Operation::Crop(Region {
+                x: 2,
+                y: 2,
+                width: 4,
+                height: 4,
+            })],
+            PNG,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidParameters(_)));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unlisted_operations_are_denied() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let err = state
+        .image
+        .process(
+            png(4, 4),
+            vec![],
+            Output {
+                format: Format::Webp,
+                quality: None,
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::AccessDenied(_)));
+
+    let err = state.image.strip_metadata(png(4, 4)).await.unwrap_err();
+    assert!(matches!(err, Error::AccessDenied(_)));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn limits_are_enforced() -> anyhow::Result<()> {
+    let mut state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            image: Some(RuntimeConfig {
+                max_dimension: 32,
+                ..Default::default()
+            }),
+        })?
+        .build_instance_state()
+        .await?;
+
+    let err = state
+        .image
+        .process(png(40, 20), vec![], PNG)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::LimitsExceeded(_)));
+
+    let err = state
+        .image
+        .process(
+            png(8, 8),
+            vec![Operation::Resize(ResizeOptions {
+                width: 64,
+                height: 64,
+                fit: Fit::Fill,
+            })],
+            PNG,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::LimitsExceeded(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_operations_are_rejected() {
+    let env = TestEnvironment::new(TestFactors {
+        image: ImageFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        image_operations = ["rotate"]
+    });
+    assert!(env.build_instance_state().await.is_err());
+}
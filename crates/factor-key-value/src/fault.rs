@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use spin_core::async_trait;
+use spin_factor_fault_injection::{FaultInjector, InjectedFault, Interface};
+
+use crate::{Cas, Error, Store};
+
+/// A [`Store`] which injects the faults configured for the instance before
+/// each operation.
+pub(crate) struct FaultInjectingStore {
+    inner: Arc<dyn Store>,
+    injector: FaultInjector,
+}
+
+impl FaultInjectingStore {
+    pub(crate) fn new(inner: Arc<dyn Store>, injector: FaultInjector) -> Self {
+        Self { inner, injector }
+    }
+
+    async fn inject(&self) -> Result<(), Error> {
+        self.injector
+            .inject(Interface::KeyValue)
+            .await
+            .map_err(fault_error)
+    }
+}
+
+fn fault_error(fault: InjectedFault) -> Error {
+    Error::Other(fault.to_string())
+}
+
+#[async_trait]
+impl Store for FaultInjectingStore {
+    async fn after_open(&self) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.after_open().await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.inject().await?;
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.delete(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.inject().await?;
+        self.inner.exists(key).await
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        self.inject().await?;
+        self.inner.get_keys().await
+    }
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
+        self.inject().await?;
+        self.inner.get_many(keys).await
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.set_many(key_values).await
+    }
+
+    async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.delete_many(keys).await
+    }
+
+    async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
+        self.inject().await?;
+        self.inner.increment(key, delta).await
+    }
+
+    async fn new_compare_and_swap(
+        &self,
+        bucket_rep: u32,
+        key: &str,
+    ) -> Result<Arc<dyn Cas>, Error> {
+        self.inject().await?;
+        self.inner.new_compare_and_swap(bucket_rep, key).await
+    }
+
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        self.inject().await?;
+        self.inner.get_json(key, path).await
+    }
+
+    async fn patch_json(&self, key: &str, path: &str, value: &str) -> Result<(), Error> {
+        self.inject().await?;
+        self.inner.patch_json(key, path, value).await
+    }
+}
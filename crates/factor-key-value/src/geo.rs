@@ -0,0 +1,420 @@
+//! Routing a key-value store across regional backends.
+//!
+//! A geo-replicated store reads from the backend for the region Spin runs in
+//! and writes to a primary backend, copying writes to every regional backend
+//! in the background. This suits apps deployed to several regions which
+//! share state: reads stay local, while the primary stays the source of
+//! truth. Regional reads may lag behind writes made in other regions.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::Context as _;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, ObjectValidation, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::Deserialize;
+use spin_core::async_trait;
+use tokio::sync::mpsc;
+
+use crate::runtime_config::spin::{MakeKeyValueStore, RuntimeConfigResolver, StoreConfig};
+use crate::{Cas, Error, Store, StoreManager, SwapError};
+
+/// The environment variable naming the region Spin runs in, if the store's
+/// runtime config doesn't.
+pub const REGION_ENV_VAR: &str = "SPIN_REGION";
+
+/// Makes geo-replicated stores from runtime config.
+///
+/// The primary and regional stores may be of any type registered with the
+/// resolver this is created with.
+pub struct GeoReplicatedKeyValueStore {
+    resolver: RuntimeConfigResolver,
+}
+
+impl GeoReplicatedKeyValueStore {
+    /// Creates a new `GeoReplicatedKeyValueStore` whose backends are resolved
+    /// by `resolver`.
+    pub fn new(resolver: RuntimeConfigResolver) -> Self {
+        Self { resolver }
+    }
+}
+
+/// Runtime configuration for a geo-replicated key-value store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GeoReplicatedKeyValueRuntimeConfig {
+    /// The region this Spin instance runs in. Defaults to the `SPIN_REGION`
+    /// environment variable. Reads go to the primary if there is no store
+    /// for the region.
+    region: Option<String>,
+    /// Whether writes are copied to every regional store after they are
+    /// written to the primary. Defaults to true; turn it off if the backends
+    /// replicate themselves, as Cosmos DB accounts with several regions do.
+    #[serde(default = "default_fan_out")]
+    fan_out: bool,
+    /// The store all writes go to, configured like any other store.
+    #[schemars(schema_with = "store_config_schema")]
+    primary: StoreConfig,
+    /// The regional stores, by region.
+    #[serde(default)]
+    #[schemars(schema_with = "store_configs_schema")]
+    regions: HashMap<String, StoreConfig>,
+}
+
+fn default_fan_out() -> bool {
+    true
+}
+
+impl MakeKeyValueStore for GeoReplicatedKeyValueStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "geo_replicated";
+
+    type RuntimeConfig = GeoReplicatedKeyValueRuntimeConfig;
+
+    type StoreManager = GeoReplicatedStoreManager;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::StoreManager> {
+        let primary = self
+            .resolver
+            .store_manager_from_config(runtime_config.primary)
+            .context("could not configure primary store")?;
+        let regions = runtime_config
+            .regions
+            .into_iter()
+            .map(|(region, config)| {
+                let store_manager = self
+                    .resolver
+                    .store_manager_from_config(config)
+                    .with_context(|| format!("could not configure store for region {region:?}"))?;
+                Ok(Replicator::new(region, store_manager))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let region = runtime_config
+            .region
+            .or_else(|| std::env::var(REGION_ENV_VAR).ok());
+        if let Some(region) = &region {
+            if !regions.iter().any(|r| &r.region == region) {
+                tracing::info!(
+                    "No key-value store for region {region:?}; reads will go to the primary"
+                );
+            }
+        }
+        Ok(GeoReplicatedStoreManager {
+            region,
+            primary,
+            regions: Arc::new(regions),
+            fan_out: runtime_config.fan_out,
+        })
+    }
+}
+
+/// A [`StoreManager`] which reads from the current region's backend and
+/// writes to a primary backend.
+pub struct GeoReplicatedStoreManager {
+    region: Option<String>,
+    primary: Arc<dyn StoreManager>,
+    regions: Arc<Vec<Replicator>>,
+    fan_out: bool,
+}
+
+impl GeoReplicatedStoreManager {
+    fn local(&self) -> Option<(&str, &Arc<dyn StoreManager>)> {
+        let region = self.region.as_deref()?;
+        self.regions
+            .iter()
+            .find(|r| r.region == region)
+            .map(|r| (r.region.as_str(), &r.store_manager))
+    }
+}
+
+#[async_trait]
+impl StoreManager for GeoReplicatedStoreManager {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        let primary = self.primary.get(name).await?;
+        let local = match self.local() {
+            Some((region, store_manager)) => match store_manager.get(name).await {
+                Ok(store) => Some(store),
+                Err(err) => {
+                    tracing::warn!(
+                        "Could not open key-value store {name:?} in region {region:?}, reading from the primary: {err}"
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let fan_out = (self.fan_out && !self.regions.is_empty()).then(|| FanOut {
+            store_name: name.to_owned(),
+            replicators: self.regions.clone(),
+        });
+        Ok(Arc::new(GeoReplicatedStore {
+            primary,
+            local,
+            fan_out,
+        }))
+    }
+
+    fn is_defined(&self, _store_name: &str) -> bool {
+        true
+    }
+
+    fn summary(&self, store_name: &str) -> Option<String> {
+        let primary = self
+            .primary
+            .summary(store_name)
+            .unwrap_or_else(|| "the primary".into());
+        Some(match self.local() {
+            Some((region, store_manager)) => {
+                let local = store_manager
+                    .summary(store_name)
+                    .unwrap_or_else(|| format!("region {region:?}"));
+                format!("geo-replicated, writing to {primary} and reading from {local}")
+            }
+            None => format!("geo-replicated, reading and writing {primary}"),
+        })
+    }
+}
+
+/// A store which reads from a regional store, if there is one, and writes to
+/// the primary.
+struct GeoReplicatedStore {
+    primary: Arc<dyn Store>,
+    local: Option<Arc<dyn Store>>,
+    fan_out: Option<FanOut>,
+}
+
+impl GeoReplicatedStore {
+    /// The store reads go to.
+    fn reader(&self) -> &Arc<dyn Store> {
+        self.local.as_ref().unwrap_or(&self.primary)
+    }
+
+    /// Copies a write which succeeded on the primary to the regional stores.
+    fn replicate(&self, write: Write) {
+        if let Some(fan_out) = &self.fan_out {
+            fan_out.send(write);
+        }
+    }
+}
+
+#[async_trait]
+impl Store for GeoReplicatedStore {
+    async fn after_open(&self) -> Result<(), Error> {
+        self.primary.after_open().await?;
+        if let Some(local) = &self.local {
+            local.after_open().await?;
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.reader().get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.primary.set(key, value).await?;
+        self.replicate(Write::Set(vec![(key.to_owned(), value.to_vec())]));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.primary.delete(key).await?;
+        self.replicate(Write::Delete(vec![key.to_owned()]));
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.reader().exists(key).await
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        self.reader().get_keys().await
+    }
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
+        self.reader().get_many(keys).await
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        self.primary.set_many(key_values.clone()).await?;
+        self.replicate(Write::Set(key_values));
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error> {
+        self.primary.delete_many(keys.clone()).await?;
+        self.replicate(Write::Delete(keys));
+        Ok(())
+    }
+
+    async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
+        let value = self.primary.increment(key.clone(), delta).await?;
+        self.replicate(Write::Increment(key, delta));
+        Ok(value)
+    }
+
+    async fn new_compare_and_swap(
+        &self,
+        bucket_rep: u32,
+        key: &str,
+    ) -> Result<Arc<dyn Cas>, Error> {
+        let inner = self.primary.new_compare_and_swap(bucket_rep, key).await?;
+        Ok(match &self.fan_out {
+            Some(fan_out) => Arc::new(ReplicatingCas {
+                inner,
+                key: key.to_owned(),
+                fan_out: fan_out.clone(),
+            }),
+            None => inner,
+        })
+    }
+
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        self.reader().get_json(key, path).await
+    }
+
+    // `patch_json` keeps its default, a compare and swap on the primary,
+    // since replicas need the whole patched document.
+}
+
+/// A compare-and-swap on the primary which copies successful swaps to the
+/// regional stores.
+struct ReplicatingCas {
+    inner: Arc<dyn Cas>,
+    key: String,
+    fan_out: FanOut,
+}
+
+#[async_trait]
+impl Cas for ReplicatingCas {
+    async fn current(&self) -> anyhow::Result<Option<Vec<u8>>, Error> {
+        self.inner.current().await
+    }
+
+    async fn swap(&self, value: Vec<u8>) -> anyhow::Result<(), SwapError> {
+        self.inner.swap(value.clone()).await?;
+        self.fan_out
+            .send(Write::Set(vec![(self.key.clone(), value)]));
+        Ok(())
+    }
+
+    async fn bucket_rep(&self) -> u32 {
+        self.inner.bucket_rep().await
+    }
+
+    async fn key(&self) -> String {
+        self.inner.key().await
+    }
+}
+
+/// A write to copy to the regional stores.
+#[derive(Clone)]
+enum Write {
+    Set(Vec<(String, Vec<u8>)>),
+    Delete(Vec<String>),
+    Increment(String, i64),
+}
+
+/// Copies writes to a store to the regional stores in the background.
+#[derive(Clone)]
+struct FanOut {
+    store_name: String,
+    replicators: Arc<Vec<Replicator>>,
+}
+
+impl FanOut {
+    fn send(&self, write: Write) {
+        for replicator in self.replicators.iter() {
+            replicator.send(self.store_name.clone(), write.clone());
+        }
+    }
+}
+
+/// Applies writes to one region's store, in the order they were made.
+struct Replicator {
+    region: String,
+    store_manager: Arc<dyn StoreManager>,
+    /// Sends writes to the task applying them, which is started on the
+    /// first write.
+    sender: OnceLock<mpsc::UnboundedSender<(String, Write)>>,
+}
+
+impl Replicator {
+    fn new(region: String, store_manager: Arc<dyn StoreManager>) -> Self {
+        Self {
+            region,
+            store_manager,
+            sender: OnceLock::new(),
+        }
+    }
+
+    fn send(&self, store_name: String, write: Write) {
+        let sender = self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(replicate(
+                self.region.clone(),
+                self.store_manager.clone(),
+                receiver,
+            ));
+            sender
+        });
+        // The task only stops once the sender is dropped.
+        _ = sender.send((store_name, write));
+    }
+}
+
+async fn replicate(
+    region: String,
+    store_manager: Arc<dyn StoreManager>,
+    mut writes: mpsc::UnboundedReceiver<(String, Write)>,
+) {
+    while let Some((store_name, write)) = writes.recv().await {
+        let result = async {
+            let store = store_manager.get(&store_name).await?;
+            match write {
+                Write::Set(key_values) => store.set_many(key_values).await,
+                Write::Delete(keys) => store.delete_many(keys).await,
+                Write::Increment(key, delta) => store.increment(key, delta).await.map(drop),
+            }
+        }
+        .await;
+        if let Err(err) = result {
+            tracing::warn!(
+                "Could not replicate write to key-value store {store_name:?} in region {region:?}: {err}"
+            );
+        }
+    }
+}
+
+/// The schema of a store's runtime config, which is checked against the
+/// schema of its type when the store is configured.
+fn store_config_schema(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            required: ["type".to_owned()].into(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+fn store_configs_schema(gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            additional_properties: Some(Box::new(store_config_schema(gen))),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
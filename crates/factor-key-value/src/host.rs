@@ -1,14 +1,24 @@
-use super::{Cas, SwapError};
+use super::{fault::FaultInjectingStore, json, tenant::TenantPrefixedStore, Cas, SwapError};
 use anyhow::{Context, Result};
 use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_fault_injection::FaultInjector;
+use spin_factor_policy::{Action, PolicyChecker};
+use spin_factor_request_context::{RequestContextHandle, TenantStore};
 use spin_resource_table::Table;
+use spin_world::spin::key_value::json as key_value_json;
 use spin_world::v2::key_value;
 use spin_world::wasi::keyvalue as wasi_keyvalue;
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use tracing::{instrument, Level};
 
 const DEFAULT_STORE_TABLE_CAPACITY: u32 = 256;
 
+/// The most keys returned by one `wasi:keyvalue` `list-keys` call.
+const LIST_KEYS_PAGE_SIZE: usize = 1000;
+
 pub use key_value::Error;
 
 #[async_trait]
@@ -41,13 +51,36 @@ pub trait Store: Sync + Send {
     async fn increment(&self, key: String, delta: i64) -> Result<i64, Error>;
     async fn new_compare_and_swap(&self, bucket_rep: u32, key: &str)
         -> Result<Arc<dyn Cas>, Error>;
+
+    /// Returns the JSON text of the part of the document at `key` found at
+    /// the JSON Pointer `path`.
+    ///
+    /// By default, this fetches the whole document; stores which can read
+    /// part of a document should override it.
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        json::get_json(self, key, path).await
+    }
+
+    /// Replaces the part of the document at `key` found at the JSON Pointer
+    /// `path` with the JSON text `value`.
+    ///
+    /// By default, this rewrites the whole document with a compare and swap,
+    /// retrying if the document changes concurrently; stores which can update
+    /// part of a document should override it.
+    async fn patch_json(&self, key: &str, path: &str, value: &str) -> Result<(), Error> {
+        json::patch_json(self, key, path, value).await
+    }
 }
 
 pub struct KeyValueDispatch {
     allowed_stores: HashSet<String>,
+    label_overrides: HashMap<String, String>,
     manager: Arc<dyn StoreManager>,
     stores: Table<Arc<dyn Store>>,
     compare_and_swaps: Table<Arc<dyn Cas>>,
+    request_context: Option<RequestContextHandle>,
+    fault_injector: Option<FaultInjector>,
+    policy: Option<PolicyChecker>,
 }
 
 impl KeyValueDispatch {
@@ -62,10 +95,77 @@ impl KeyValueDispatch {
     ) -> Self {
         Self {
             allowed_stores,
+            label_overrides: HashMap::new(),
             manager,
             stores: Table::new(capacity),
             compare_and_swaps: Table::new(capacity),
+            request_context: None,
+            fault_injector: None,
+            policy: None,
+        }
+    }
+
+    /// Opens the store with label `store_label` in place of each store with
+    /// label `label`.
+    ///
+    /// Only the labels are changed; which stores the component is allowed
+    /// to open is still decided by their labels in the manifest.
+    pub fn set_label_overrides(&mut self, label_overrides: HashMap<String, String>) {
+        self.label_overrides = label_overrides;
+    }
+
+    /// Scopes stores to the tenant of the invocation, if it has one.
+    pub fn set_request_context(&mut self, request_context: RequestContextHandle) {
+        self.request_context = Some(request_context);
+    }
+
+    /// Injects the configured faults into operations on the stores.
+    pub fn set_fault_injector(&mut self, fault_injector: FaultInjector) {
+        self.fault_injector = Some(fault_injector);
+    }
+
+    /// Consults the policy before each store is opened.
+    pub fn set_policy(&mut self, policy: PolicyChecker) {
+        self.policy = Some(policy);
+    }
+
+    /// Opens the store `name`, or the store the component uses in its place,
+    /// wrapped to inject the configured faults.
+    async fn open_store(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        let name = self.label_overrides.get(name).map_or(name, String::as_str);
+        if let Some(policy) = &self.policy {
+            let action = Action::KeyValueStore {
+                label: name.to_owned(),
+            };
+            if !policy.allows(action).await {
+                return Err(Error::AccessDenied);
+            }
         }
+        let store = self.open_tenant_store(name).await?;
+        Ok(match &self.fault_injector {
+            Some(injector) if injector.is_enabled() => {
+                Arc::new(FaultInjectingStore::new(store, injector.clone()))
+            }
+            _ => store,
+        })
+    }
+
+    /// Opens the store `name`, or the store the invocation's tenant uses in
+    /// its place.
+    async fn open_tenant_store(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        let Some(tenant) = self
+            .request_context
+            .as_ref()
+            .and_then(|request_context| request_context.tenant())
+        else {
+            return self.manager.get(name).await;
+        };
+        let TenantStore { label, key_prefix } = tenant.key_value_store(name);
+        let store = self.manager.get(label).await?;
+        Ok(match key_prefix {
+            Some(prefix) => Arc::new(TenantPrefixedStore::new(store, prefix)),
+            None => store,
+        })
     }
 
     pub fn get_store<T: 'static>(&self, store: Resource<T>) -> anyhow::Result<&Arc<dyn Store>> {
@@ -110,7 +210,7 @@ impl key_value::HostStore for KeyValueDispatch {
     async fn open(&mut self, name: String) -> Result<Result<Resource<key_value::Store>, Error>> {
         Ok(async {
             if self.allowed_stores.contains(&name) {
-                let store = self.manager.get(&name).await?;
+                let store = self.open_store(&name).await?;
                 store.after_open().await?;
                 let store_idx = self
                     .stores
@@ -180,6 +280,31 @@ impl key_value::HostStore for KeyValueDispatch {
     }
 }
 
+impl key_value_json::Host for KeyValueDispatch {
+    #[instrument(name = "spin_key_value.get_json", skip(self, store, key), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_json(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        path: String,
+    ) -> Result<Result<Option<String>, Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.get_json(&key, &path).await)
+    }
+
+    #[instrument(name = "spin_key_value.patch_json", skip(self, store, key, value), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn patch_json(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        path: String,
+        value: String,
+    ) -> Result<Result<(), Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.patch_json(&key, &path, &value).await)
+    }
+}
+
 fn to_wasi_err(e: Error) -> wasi_keyvalue::store::Error {
     match e {
         Error::AccessDenied => wasi_keyvalue::store::Error::AccessDenied,
@@ -195,7 +320,7 @@ impl wasi_keyvalue::store::Host for KeyValueDispatch {
         identifier: String,
     ) -> Result<Resource<wasi_keyvalue::store::Bucket>, wasi_keyvalue::store::Error> {
         if self.allowed_stores.contains(&identifier) {
-            let store = self.manager.get(&identifier).await.map_err(to_wasi_err)?;
+            let store = self.open_store(&identifier).await.map_err(to_wasi_err)?;
             store.after_open().await.map_err(to_wasi_err)?;
             let store_idx = self
                 .stores
@@ -259,16 +384,10 @@ impl wasi_keyvalue::store::HostBucket for KeyValueDispatch {
         self_: Resource<Bucket>,
         cursor: Option<String>,
     ) -> Result<wasi_keyvalue::store::KeyResponse, wasi_keyvalue::store::Error> {
-        match cursor {
-            Some(_) => Err(wasi_keyvalue::store::Error::Other(
-                "list_keys: cursor not supported".to_owned(),
-            )),
-            None => {
-                let store = self.get_store_wasi(self_)?;
-                let keys = store.get_keys().await.map_err(to_wasi_err)?;
-                Ok(wasi_keyvalue::store::KeyResponse { keys, cursor: None })
-            }
-        }
+        let store = self.get_store_wasi(self_)?;
+        let mut keys = store.get_keys().await.map_err(to_wasi_err)?;
+        keys.sort_unstable();
+        Ok(page_keys(keys, cursor.as_deref(), LIST_KEYS_PAGE_SIZE))
     }
 
     async fn drop(&mut self, rep: Resource<Bucket>) -> anyhow::Result<()> {
@@ -481,3 +600,49 @@ impl spin_world::v1::key_value::Host for KeyValueDispatch {
         <Self as key_value::HostStore>::drop(self, this).await
     }
 }
+
+/// Returns the page of the sorted `keys` after `cursor`, which is the last key
+/// of the previous page.
+fn page_keys(
+    keys: Vec<String>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> wasi_keyvalue::store::KeyResponse {
+    let start = match cursor {
+        Some(cursor) => keys.partition_point(|key| key.as_str() <= cursor),
+        None => 0,
+    };
+    let mut keys = keys
+        .into_iter()
+        .skip(start)
+        .take(page_size + 1)
+        .collect::<Vec<_>>();
+    let cursor = if keys.len() > page_size {
+        keys.truncate(page_size);
+        keys.last().cloned()
+    } else {
+        None
+    };
+    wasi_keyvalue::store::KeyResponse { keys, cursor }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_keys_pages_through_keys() {
+        let keys = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+
+        let first = page_keys(keys.clone(), None, 2);
+        assert_eq!(first.keys, ["a", "b"]);
+        assert_eq!(first.cursor.as_deref(), Some("b"));
+
+        let second = page_keys(keys.clone(), first.cursor.as_deref(), 2);
+        assert_eq!(second.keys, ["c", "d"]);
+
+        let last = page_keys(keys, second.cursor.as_deref(), 2);
+        assert_eq!(last.keys, ["e"]);
+        assert_eq!(last.cursor, None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Reading and patching parts of JSON documents, for stores which can't do so
+//! natively.
+//!
+//! Paths are JSON Pointers (RFC 6901); the empty path refers to the whole
+//! document.
+
+use serde_json::Value;
+
+use crate::{Error, Store, SwapError};
+
+/// How many times a patch is retried when the document changes under it.
+const PATCH_ATTEMPTS: usize = 10;
+
+/// Returns the JSON text of the part of the document at `key` found at `path`
+/// by fetching the whole document.
+pub async fn get_json<S: Store + ?Sized>(
+    store: &S,
+    key: &str,
+    path: &str,
+) -> Result<Option<String>, Error> {
+    match store.get(key).await? {
+        Some(document) => get_json_path(&document, path),
+        None => validate_path(path).map(|()| None),
+    }
+}
+
+/// Replaces the part of the document at `key` found at `path` by rewriting
+/// the whole document with a compare and swap, retrying if the document
+/// changes concurrently.
+pub async fn patch_json<S: Store + ?Sized>(
+    store: &S,
+    key: &str,
+    path: &str,
+    value: &str,
+) -> Result<(), Error> {
+    validate_path(path)?;
+    for _ in 0..PATCH_ATTEMPTS {
+        let cas = store.new_compare_and_swap(0, key).await?;
+        let document = cas.current().await?;
+        let patched = patch_json_path(document.as_deref(), path, value)?;
+        match cas.swap(patched).await {
+            Ok(()) => return Ok(()),
+            Err(SwapError::CasFailed(_)) => continue,
+            Err(SwapError::Other(err)) => return Err(Error::Other(err)),
+        }
+    }
+    Err(Error::Other(format!(
+        "could not patch {key:?}: it kept changing concurrently"
+    )))
+}
+
+/// Returns the JSON text of the part of `document` at `path`, if any.
+pub fn get_json_path(document: &[u8], path: &str) -> Result<Option<String>, Error> {
+    validate_path(path)?;
+    let document = parse_document(document)?;
+    document
+        .pointer(path)
+        .map(|value| serde_json::to_string(value).map_err(json_error))
+        .transpose()
+}
+
+/// Returns `document` with the part at `path` replaced by the JSON text
+/// `value`.
+///
+/// Object members which don't exist are added and `-` appends to an array, but
+/// the parent of `path` must exist. If `document` is `None`, `path` must be
+/// empty.
+pub fn patch_json_path(document: Option<&[u8]>, path: &str, value: &str) -> Result<Vec<u8>, Error> {
+    validate_path(path)?;
+    let value = serde_json::from_str::<Value>(value).map_err(json_error)?;
+    if path.is_empty() {
+        return serde_json::to_vec(&value).map_err(json_error);
+    }
+    let Some(document) = document else {
+        return Err(Error::Other(format!(
+            "cannot patch {path:?} in a document which doesn't exist"
+        )));
+    };
+    let mut document = parse_document(document)?;
+
+    let (parent, token) = path.rsplit_once('/').unwrap();
+    let token = unescape(token);
+    match document.pointer_mut(parent) {
+        Some(Value::Object(members)) => {
+            members.insert(token, value);
+        }
+        Some(Value::Array(items)) if token == "-" => items.push(value),
+        Some(Value::Array(items)) => {
+            let Some(item) = token.parse::<usize>().ok().and_then(|i| items.get_mut(i)) else {
+                return Err(Error::Other(format!(
+                    "array index in {path:?} is out of bounds"
+                )));
+            };
+            *item = value;
+        }
+        Some(_) => {
+            return Err(Error::Other(format!(
+                "the parent of {path:?} is not an object or array"
+            )))
+        }
+        None => {
+            return Err(Error::Other(format!(
+                "the parent of {path:?} doesn't exist"
+            )))
+        }
+    }
+    serde_json::to_vec(&document).map_err(json_error)
+}
+
+/// Checks that `path` is a valid JSON Pointer.
+pub fn validate_path(path: &str) -> Result<(), Error> {
+    if !path.is_empty() && !path.starts_with('/') {
+        return Err(Error::Other(format!(
+            "invalid JSON path {path:?}: paths must be empty or start with '/'"
+        )));
+    }
+    Ok(())
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens.
+pub fn path_tokens(path: &str) -> Result<Vec<String>, Error> {
+    validate_path(path)?;
+    Ok(path.split('/').skip(1).map(unescape).collect())
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn parse_document(document: &[u8]) -> Result<Value, Error> {
+    serde_json::from_slice(document)
+        .map_err(|err| Error::Other(format!("value is not a JSON document: {err}")))
+}
+
+fn json_error(err: serde_json::Error) -> Error {
+    Error::Other(format!("invalid JSON: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &[u8] = br#"{"name":"spin","tags":["wasm"],"a/b":{"c":1}}"#;
+
+    #[test]
+    fn get_json_path_finds_parts() {
+        let get = |path| get_json_path(DOCUMENT, path).unwrap();
+        assert_eq!(Some(r#""spin""#.to_owned()), get("/name"));
+        assert_eq!(Some(r#""wasm""#.to_owned()), get("/tags/0"));
+        assert_eq!(Some("1".to_owned()), get("/a~1b/c"));
+        assert_eq!(None, get("/missing"));
+        assert!(get("").unwrap().starts_with('{'));
+        assert!(get_json_path(b"not json", "").is_err());
+        assert!(get_json_path(DOCUMENT, "name").is_err());
+    }
+
+    #[test]
+    fn patch_json_path_updates_parts() {
+        let patch = |path, value| {
+            let document = patch_json_path(Some(DOCUMENT), path, value).unwrap();
+            serde_json::from_slice::<Value>(&document).unwrap()
+        };
+        assert_eq!("wasi", patch("/name", r#""wasi""#)["name"]);
+        assert_eq!(true, patch("/new", "true")["new"]);
+        assert_eq!("wasi", patch("/tags/-", r#""wasi""#)["tags"][1]);
+        assert_eq!(2, patch("/a~1b/c", "2")["a/b"]["c"]);
+        assert_eq!(3, patch("", "3"));
+    }
+
+    #[test]
+    fn patch_json_path_rejects_missing_parents() {
+        assert!(patch_json_path(Some(DOCUMENT), "/missing/child", "1").is_err());
+        assert!(patch_json_path(Some(DOCUMENT), "/tags/5", "1").is_err());
+        assert!(patch_json_path(Some(DOCUMENT), "/name/child", "1").is_err());
+        assert!(patch_json_path(Some(DOCUMENT), "name", "1").is_err());
+        assert!(patch_json_path(Some(DOCUMENT), "/name", "not json").is_err());
+        assert!(patch_json_path(None, "/name", "1").is_err());
+        assert!(patch_json_path(None, "", "1").is_ok());
+    }
+}
@@ -1,5 +1,9 @@
+mod fault;
+mod geo;
 mod host;
+pub mod json;
 pub mod runtime_config;
+mod tenant;
 mod util;
 
 use std::{
@@ -8,6 +12,9 @@ use std::{
 };
 
 use anyhow::ensure;
+use spin_factor_fault_injection::{FaultInjectionFactor, FaultInjector};
+use spin_factor_policy::{PolicyChecker, PolicyFactor};
+use spin_factor_request_context::{RequestContextFactor, RequestContextHandle};
 use spin_factors::{
     ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
 };
@@ -15,6 +22,10 @@ use spin_locked_app::MetadataKey;
 
 /// Metadata key for key-value stores.
 pub const KEY_VALUE_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("key_value_stores");
+pub use geo::{
+    GeoReplicatedKeyValueRuntimeConfig, GeoReplicatedKeyValueStore, GeoReplicatedStoreManager,
+    REGION_ENV_VAR,
+};
 pub use host::{log_cas_error, log_error, Error, KeyValueDispatch, Store, StoreManager};
 pub use runtime_config::RuntimeConfig;
 use spin_core::async_trait;
@@ -44,6 +55,7 @@ impl Factor for KeyValueFactor {
         ctx.link_bindings(spin_world::wasi::keyvalue::store::add_to_linker)?;
         ctx.link_bindings(spin_world::wasi::keyvalue::batch::add_to_linker)?;
         ctx.link_bindings(spin_world::wasi::keyvalue::atomics::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::key_value::json::add_to_linker)?;
         Ok(())
     }
 
@@ -51,7 +63,8 @@ impl Factor for KeyValueFactor {
         &self,
         mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        let store_managers = ctx.take_runtime_config().unwrap_or_default();
+        let mut store_managers = ctx.take_runtime_config().unwrap_or_default();
+        let component_label_overrides = store_managers.take_component_labels();
 
         let delegating_manager = DelegatingStoreManager::new(store_managers);
         let store_manager = Arc::new(delegating_manager);
@@ -65,26 +78,61 @@ impl Factor for KeyValueFactor {
                 .unwrap_or_default()
                 .into_iter()
                 .collect::<HashSet<_>>();
+            let label_overrides = component_label_overrides.get(&component_id);
             for label in &key_value_stores {
+                if let Some(store_label) = label_overrides.and_then(|o| o.get(label)) {
+                    ensure!(
+                        store_manager.is_defined(store_label),
+                        "unknown key-value store label {store_label:?} used in place of {label:?} for component {component_id:?}"
+                    );
+                    continue;
+                }
                 // TODO: port nicer errors from KeyValueComponent (via error type?)
                 ensure!(
                     store_manager.is_defined(label),
                     "unknown key_value_stores label {label:?} for component {component_id:?}"
                 );
             }
+            for label in label_overrides.into_iter().flat_map(|o| o.keys()) {
+                if !key_value_stores.contains(label) {
+                    tracing::warn!(
+                        "Runtime config overrides key-value store label {label:?} for component {component_id:?}, which doesn't use it"
+                    );
+                }
+            }
             component_allowed_stores.insert(component_id, key_value_stores);
             // TODO: warn (?) on unused store?
         }
 
+        for component_id in component_label_overrides.keys() {
+            if !component_allowed_stores.contains_key(component_id) {
+                tracing::warn!(
+                    "Runtime config overrides key-value store labels for unknown component {component_id:?}"
+                );
+            }
+        }
+
+        if let Ok(request_context) = ctx.app_state::<RequestContextFactor>() {
+            for (tenant_id, tenant) in request_context.tenants() {
+                for label in tenant.key_value_stores.values() {
+                    ensure!(
+                        store_manager.is_defined(label),
+                        "unknown key-value store label {label:?} for tenant {tenant_id:?}"
+                    );
+                }
+            }
+        }
+
         Ok(AppState {
             store_manager,
             component_allowed_stores,
+            component_label_overrides,
         })
     }
 
     fn prepare<T: RuntimeFactors>(
         &self,
-        ctx: PrepareContext<T, Self>,
+        mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<InstanceBuilder> {
         let app_state = ctx.app_state();
         let allowed_stores = app_state
@@ -92,9 +140,33 @@ impl Factor for KeyValueFactor {
             .get(ctx.app_component().id())
             .expect("component should be in component_stores")
             .clone();
+        let label_overrides = app_state
+            .component_label_overrides
+            .get(ctx.app_component().id())
+            .cloned()
+            .unwrap_or_default();
+        let request_context = match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(request_context) => Some(request_context.handle()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let fault_injector = match ctx.instance_builder::<FaultInjectionFactor>() {
+            Ok(fault_injection) => Some(fault_injection.injector()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let policy = match ctx.instance_builder::<PolicyFactor>() {
+            Ok(policy) => Some(policy.checker()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
         Ok(InstanceBuilder {
             store_manager: app_state.store_manager.clone(),
             allowed_stores,
+            label_overrides,
+            request_context,
+            fault_injector,
+            policy,
         })
     }
 }
@@ -113,6 +185,12 @@ pub struct AppState {
     /// This is a map from component ID to the set of store labels that the
     /// component is allowed to use.
     component_allowed_stores: HashMap<String, HashSet<String>>,
+    /// The store labels each component uses in place of the labels in the
+    /// manifest.
+    ///
+    /// This is a map from component ID to a map from manifest label to the
+    /// label of the store the component actually uses.
+    component_label_overrides: HashMap<String, HashMap<String, String>>,
 }
 
 impl AppState {
@@ -124,14 +202,47 @@ impl AppState {
     /// Returns true if the given store label is used by any component.
     pub fn store_is_used(&self, label: &str) -> bool {
         self.component_allowed_stores
-            .values()
-            .any(|stores| stores.contains(label))
+            .iter()
+            .any(|(component_id, stores)| {
+                let overrides = self.component_label_overrides.get(component_id);
+                stores.iter().any(|store| {
+                    overrides
+                        .and_then(|overrides| overrides.get(store))
+                        .unwrap_or(store)
+                        == label
+                })
+            })
+    }
+
+    /// Returns the labels of the stores used by any component, sorted.
+    pub fn used_stores(&self) -> Vec<String> {
+        let mut labels = self
+            .component_allowed_stores
+            .iter()
+            .flat_map(|(component_id, stores)| {
+                let overrides = self.component_label_overrides.get(component_id);
+                stores.iter().map(move |store| {
+                    overrides
+                        .and_then(|overrides| overrides.get(store))
+                        .unwrap_or(store)
+                        .clone()
+                })
+            })
+            .collect::<Vec<_>>();
+        labels.sort();
+        labels.dedup();
+        labels
     }
 
     /// Get a store by label.
     pub async fn get_store(&self, label: &str) -> Option<Arc<dyn Store>> {
         self.store_manager.get(label).await.ok()
     }
+
+    /// Returns the store manager for the app.
+    pub fn store_manager(&self) -> Arc<dyn StoreManager> {
+        self.store_manager.clone()
+    }
 }
 
 /// `SwapError` are errors that occur during compare and swap operations
@@ -174,6 +285,15 @@ pub struct InstanceBuilder {
     store_manager: Arc<AppStoreManager>,
     /// The allowed stores for this component instance.
     allowed_stores: HashSet<String>,
+    /// The store labels this component uses in place of the labels in the
+    /// manifest.
+    label_overrides: HashMap<String, String>,
+    /// Scopes stores to the invocation's tenant, if any.
+    request_context: Option<RequestContextHandle>,
+    /// Injects faults into the instance's store operations, if configured.
+    fault_injector: Option<FaultInjector>,
+    /// Consulted before the instance opens stores, if configured.
+    policy: Option<PolicyChecker>,
 }
 
 impl FactorInstanceBuilder for InstanceBuilder {
@@ -183,11 +303,23 @@ impl FactorInstanceBuilder for InstanceBuilder {
         let Self {
             store_manager,
             allowed_stores,
+            label_overrides,
+            request_context,
+            fault_injector,
+            policy,
         } = self;
-        Ok(KeyValueDispatch::new_with_capacity(
-            allowed_stores,
-            store_manager,
-            u32::MAX,
-        ))
+        let mut dispatch =
+            KeyValueDispatch::new_with_capacity(allowed_stores, store_manager, u32::MAX);
+        dispatch.set_label_overrides(label_overrides);
+        if let Some(request_context) = request_context {
+            dispatch.set_request_context(request_context);
+        }
+        if let Some(fault_injector) = fault_injector {
+            dispatch.set_fault_injector(fault_injector);
+        }
+        if let Some(policy) = policy {
+            dispatch.set_policy(policy);
+        }
+        Ok(dispatch)
     }
 }
@@ -9,6 +9,9 @@ use crate::StoreManager;
 pub struct RuntimeConfig {
     /// Map of store names to store managers.
     store_managers: HashMap<String, Arc<dyn StoreManager>>,
+    /// Map of component IDs to the store labels they use in place of the
+    /// labels in the manifest.
+    component_labels: HashMap<String, HashMap<String, String>>,
 }
 
 impl RuntimeConfig {
@@ -28,6 +31,28 @@ impl RuntimeConfig {
     pub fn get_store_manager(&self, label: &str) -> Option<Arc<dyn StoreManager>> {
         self.store_managers.get(label).cloned()
     }
+
+    /// Makes the component with the given ID use the store with label
+    /// `store_label` wherever it uses the store with label `label`.
+    ///
+    /// This lets one component use a different backend for a label than the
+    /// rest of the app, without changing the app's manifest.
+    pub fn override_component_label(
+        &mut self,
+        component_id: String,
+        label: String,
+        store_label: String,
+    ) {
+        self.component_labels
+            .entry(component_id)
+            .or_default()
+            .insert(label, store_label);
+    }
+
+    /// Takes the label overrides of each component, by component ID.
+    pub(crate) fn take_component_labels(&mut self) -> HashMap<String, HashMap<String, String>> {
+        std::mem::take(&mut self.component_labels)
+    }
 }
 
 impl IntoIterator for RuntimeConfig {
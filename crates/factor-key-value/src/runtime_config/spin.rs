@@ -2,6 +2,7 @@
 
 use crate::{RuntimeConfig, StoreManager};
 use anyhow::Context as _;
+use schemars::{schema::RootSchema, JsonSchema};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use spin_factors::runtime_config::toml::GetTomlValue;
@@ -12,7 +13,9 @@ pub trait MakeKeyValueStore: 'static + Send + Sync {
     /// Unique type identifier for the store.
     const RUNTIME_CONFIG_TYPE: &'static str;
     /// Runtime configuration for the store.
-    type RuntimeConfig: DeserializeOwned;
+    ///
+    /// Its JSON schema documents and validates the store's runtime config.
+    type RuntimeConfig: DeserializeOwned + JsonSchema;
     /// The store manager for the store.
     type StoreManager: StoreManager;
 
@@ -48,6 +51,8 @@ pub struct RuntimeConfigResolver {
     /// A map of store types to a function that returns the appropriate store
     /// manager from runtime config TOML.
     store_types: HashMap<&'static str, StoreFromToml>,
+    /// A map of store types to the JSON schema of their runtime config.
+    schemas: HashMap<&'static str, RootSchema>,
     /// A map of default store configurations for a label.
     defaults: HashMap<&'static str, StoreConfig>,
 }
@@ -93,15 +98,40 @@ impl RuntimeConfigResolver {
                 T::RUNTIME_CONFIG_TYPE
             );
         }
+        self.schemas.insert(
+            T::RUNTIME_CONFIG_TYPE,
+            schemars::schema_for!(T::RuntimeConfig),
+        );
         Ok(())
     }
 
+    /// The JSON schemas of the registered store types' runtime config, by
+    /// store type.
+    pub fn store_type_schemas(&self) -> impl Iterator<Item = (&'static str, &RootSchema)> {
+        self.schemas
+            .iter()
+            .map(|(&store_type, schema)| (store_type, schema))
+    }
+
     /// Resolves a toml table into a runtime config.
     ///
     /// The default stores are also added to the runtime config.
     pub fn resolve(&self, table: Option<&impl GetTomlValue>) -> anyhow::Result<RuntimeConfig> {
         let mut runtime_config = self.resolve_from_toml(table)?.unwrap_or_default();
 
+        if let Some(table) = table.and_then(|t| t.get("component_stores")) {
+            let components: HashMap<String, ComponentStores> = table.clone().try_into()?;
+            for (component_id, stores) in components {
+                for (label, store_label) in stores.key_value_store {
+                    runtime_config.override_component_label(
+                        component_id.clone(),
+                        label,
+                        store_label,
+                    );
+                }
+            }
+        }
+
         for (&label, config) in &self.defaults {
             if !runtime_config.store_managers.contains_key(label) {
                 let store_manager = self
@@ -139,7 +169,7 @@ impl RuntimeConfigResolver {
     ///
     /// Errors if there is no [`MakeKeyValueStore`] registered for the store config's type
     /// or if the store manager cannot be created from the config.
-    fn store_manager_from_config(
+    pub(crate) fn store_manager_from_config(
         &self,
         config: StoreConfig,
     ) -> anyhow::Result<Arc<dyn StoreManager>> {
@@ -151,6 +181,21 @@ impl RuntimeConfigResolver {
     }
 }
 
+/// The stores a component uses in place of the labels in the manifest.
+///
+/// Expects `component_stores` to be in the format:
+/// ```toml
+/// [component_stores.$component-id]
+/// key_value_store = { $label = "$store-label" }
+/// ```
+///
+/// Other kinds of stores may be overridden in the same table.
+#[derive(Deserialize)]
+struct ComponentStores {
+    #[serde(default)]
+    key_value_store: HashMap<String, String>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct StoreConfig {
     #[serde(rename = "type")]
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use spin_core::async_trait;
+
+use crate::{Cas, Error, Store, SwapError};
+
+/// A [`Store`] shared between tenants, which keeps a tenant's keys apart from
+/// other tenants' by prefixing them.
+pub(crate) struct TenantPrefixedStore {
+    inner: Arc<dyn Store>,
+    prefix: String,
+}
+
+impl TenantPrefixedStore {
+    pub(crate) fn new(inner: Arc<dyn Store>, prefix: String) -> Self {
+        Self { inner, prefix }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    fn keys(&self, keys: Vec<String>) -> Vec<String> {
+        keys.iter().map(|key| self.key(key)).collect()
+    }
+
+    fn strip(&self, key: String) -> String {
+        match key.strip_prefix(&self.prefix) {
+            Some(key) => key.to_owned(),
+            None => key,
+        }
+    }
+}
+
+#[async_trait]
+impl Store for TenantPrefixedStore {
+    async fn after_open(&self) -> Result<(), Error> {
+        self.inner.after_open().await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.get(&self.key(key)).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.inner.set(&self.key(key), value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.inner.delete(&self.key(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.inner.exists(&self.key(key)).await
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        let keys = self.inner.get_keys().await?;
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_owned))
+            .collect())
+    }
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
+        let values = self.inner.get_many(self.keys(keys)).await?;
+        Ok(values
+            .into_iter()
+            .map(|(key, value)| (self.strip(key), value))
+            .collect())
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        let key_values = key_values
+            .into_iter()
+            .map(|(key, value)| (self.key(&key), value))
+            .collect();
+        self.inner.set_many(key_values).await
+    }
+
+    async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error> {
+        self.inner.delete_many(self.keys(keys)).await
+    }
+
+    async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
+        self.inner.increment(self.key(&key), delta).await
+    }
+
+    async fn new_compare_and_swap(
+        &self,
+        bucket_rep: u32,
+        key: &str,
+    ) -> Result<Arc<dyn Cas>, Error> {
+        let inner = self
+            .inner
+            .new_compare_and_swap(bucket_rep, &self.key(key))
+            .await?;
+        Ok(Arc::new(TenantPrefixedCas {
+            inner,
+            prefix: self.prefix.clone(),
+        }))
+    }
+
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        self.inner.get_json(&self.key(key), path).await
+    }
+
+    async fn patch_json(&self, key: &str, path: &str, value: &str) -> Result<(), Error> {
+        self.inner.patch_json(&self.key(key), path, value).await
+    }
+}
+
+/// A [`Cas`] on a [`TenantPrefixedStore`], which reports the key without the
+/// tenant prefix.
+struct TenantPrefixedCas {
+    inner: Arc<dyn Cas>,
+    prefix: String,
+}
+
+#[async_trait]
+impl Cas for TenantPrefixedCas {
+    async fn current(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.current().await
+    }
+
+    async fn swap(&self, value: Vec<u8>) -> Result<(), SwapError> {
+        self.inner.swap(value).await
+    }
+
+    async fn bucket_rep(&self) -> u32 {
+        self.inner.bucket_rep().await
+    }
+
+    async fn key(&self) -> String {
+        let key = self.inner.key().await;
+        match key.strip_prefix(&self.prefix) {
+            Some(key) => key.to_owned(),
+            None => key,
+        }
+    }
+}
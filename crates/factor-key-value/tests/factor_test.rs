@@ -1,10 +1,18 @@
 use anyhow::bail;
-use spin_core::async_trait;
-use spin_factor_key_value::{Cas, KeyValueFactor, RuntimeConfig, Store, StoreManager};
-use spin_factors::RuntimeFactors;
+use spin_factor_key_value::{
+    runtime_config::spin::RuntimeConfigResolver, GeoReplicatedKeyValueStore, KeyValueFactor,
+    RuntimeConfig, StoreManager,
+};
+use spin_factor_policy::{ActionKind, Effect, PolicyFactor, PolicyRule, RulesPolicyEngine};
+use spin_factor_request_context::{RequestContextFactor, TenantConfig};
+use spin_factors::{App, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
 use spin_world::v2::key_value::{Error, HostStore};
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
@@ -19,10 +27,9 @@ impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
     }
 }
 
-#[tokio::test]
+#[tokio::test(flavor = "multi_thread")]
 async fn works_when_allowed_store_is_defined() -> anyhow::Result<()> {
-    let mut runtime_config = RuntimeConfig::default();
-    runtime_config.add_store_manager("default".into(), mock_store_manager());
+    let runtime_config = memory_stores(&["default"])?;
     let factors = TestFactors {
         key_value: KeyValueFactor::new(),
     };
@@ -45,7 +52,7 @@ async fn works_when_allowed_store_is_defined() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tokio::test]
+#[tokio::test(flavor = "multi_thread")]
 async fn errors_when_store_is_not_defined() -> anyhow::Result<()> {
     let runtime_config = RuntimeConfig::default();
     let factors = TestFactors {
@@ -71,10 +78,9 @@ async fn errors_when_store_is_not_defined() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tokio::test]
+#[tokio::test(flavor = "multi_thread")]
 async fn errors_when_store_is_not_allowed() -> anyhow::Result<()> {
-    let mut runtime_config = RuntimeConfig::default();
-    runtime_config.add_store_manager("default".into(), mock_store_manager());
+    let runtime_config = memory_stores(&["default"])?;
     let factors = TestFactors {
         key_value: KeyValueFactor::new(),
     };
@@ -98,78 +104,270 @@ async fn errors_when_store_is_not_allowed() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn mock_store_manager() -> Arc<dyn StoreManager> {
-    Arc::new(MockStoreManager)
+#[derive(RuntimeFactors)]
+struct TenantFactors {
+    request_context: RequestContextFactor,
+    key_value: KeyValueFactor,
 }
 
-struct MockStoreManager;
+#[tokio::test(flavor = "multi_thread")]
+async fn stores_are_scoped_to_tenants() -> anyhow::Result<()> {
+    let key_value = memory_stores(&["default", "acme"])?;
+    let shared = key_value.get_store_manager("default").unwrap();
+    let acme = key_value.get_store_manager("acme").unwrap();
+    let factors = TenantFactors {
+        request_context: RequestContextFactor::new(),
+        key_value: KeyValueFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            key_value_stores = ["default"]
+        })
+        .runtime_config(TenantFactorsRuntimeConfig {
+            request_context: Some(spin_factor_request_context::RuntimeConfig {
+                namespace_key_value: true,
+                tenants: HashMap::from([(
+                    "acme".to_owned(),
+                    TenantConfig {
+                        key_value_stores: HashMap::from([("default".into(), "acme".into())]),
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            }),
+            key_value: Some(key_value),
+        })?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
 
-#[async_trait]
-impl StoreManager for MockStoreManager {
-    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
-        let _ = name;
-        Ok(Arc::new(MockStore))
+    for tenant_id in [None, Some("acme"), Some("globex")] {
+        let mut builders = env.factors.prepare(&configured_app, "test-component")?;
+        builders
+            .request_context()
+            .set_tenant_id(tenant_id.map(Into::into));
+        let mut state = env.factors.build_instance_state(builders)?;
+        let store = state.key_value.open("default".into()).await?.unwrap();
+        let value = tenant_id.unwrap_or("app").as_bytes().to_vec();
+        state
+            .key_value
+            .set(store, "greeting".into(), value)
+            .await?
+            .unwrap();
     }
 
-    fn is_defined(&self, store_name: &str) -> bool {
-        let _ = store_name;
-        todo!()
-    }
+    assert_eq!(
+        values(&shared, "default").await?,
+        HashMap::from([
+            ("greeting".into(), b"app".to_vec()),
+            ("globex/greeting".into(), b"globex".to_vec()),
+        ])
+    );
+    assert_eq!(
+        values(&acme, "acme").await?,
+        HashMap::from([("greeting".into(), b"acme".to_vec())])
+    );
+    Ok(())
 }
 
-struct MockStore;
+#[tokio::test(flavor = "multi_thread")]
+async fn components_can_use_other_stores_in_place_of_labels() -> anyhow::Result<()> {
+    let mut runtime_config = memory_stores(&["default", "cache"])?;
+    let shared = runtime_config.get_store_manager("default").unwrap();
+    let cache = runtime_config.get_store_manager("cache").unwrap();
+    runtime_config.override_component_label("ingest".into(), "default".into(), "cache".into());
+    let factors = TestFactors {
+        key_value: KeyValueFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.ingest]
+            source = "does-not-exist.wasm"
+            key_value_stores = ["default"]
 
-#[async_trait]
-impl Store for MockStore {
-    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
-        let _ = key;
-        todo!()
-    }
-    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        let _ = (key, value);
-        todo!()
-    }
-    async fn delete(&self, key: &str) -> Result<(), Error> {
-        let _ = key;
-        todo!()
-    }
-    async fn exists(&self, key: &str) -> Result<bool, Error> {
-        let _ = key;
-        todo!()
-    }
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
-        todo!()
-    }
+            [component.web]
+            source = "does-not-exist.wasm"
+            key_value_stores = ["default"]
+        })
+        .runtime_config(runtime_config)?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    assert_eq!(
+        configured_app.app_state::<KeyValueFactor>()?.used_stores(),
+        ["cache", "default"]
+    );
 
-    async fn get_many(
-        &self,
-        keys: Vec<String>,
-    ) -> anyhow::Result<Vec<(String, Option<Vec<u8>>)>, Error> {
-        let _ = keys;
-        todo!()
+    for component_id in ["ingest", "web"] {
+        let builders = env.factors.prepare(&configured_app, component_id)?;
+        let mut state = env.factors.build_instance_state(builders)?;
+        assert!(state.key_value.open("cache".into()).await?.is_err());
+        let store = state.key_value.open("default".into()).await?.unwrap();
+        state
+            .key_value
+            .set(store, "writer".into(), component_id.as_bytes().to_vec())
+            .await?
+            .unwrap();
     }
 
-    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> anyhow::Result<(), Error> {
-        let _ = key_values;
-        todo!()
-    }
+    assert_eq!(
+        values(&shared, "default").await?,
+        HashMap::from([("writer".into(), b"web".to_vec())])
+    );
+    assert_eq!(
+        values(&cache, "cache").await?,
+        HashMap::from([("writer".into(), b"ingest".to_vec())])
+    );
+    Ok(())
+}
 
-    async fn delete_many(&self, keys: Vec<String>) -> anyhow::Result<(), Error> {
-        let _ = keys;
-        todo!()
-    }
+#[derive(RuntimeFactors)]
+struct PolicyFactors {
+    policy: PolicyFactor,
+    key_value: KeyValueFactor,
+}
 
-    async fn increment(&self, key: String, delta: i64) -> anyhow::Result<i64, Error> {
-        let (_, _) = (key, delta);
-        todo!()
+#[tokio::test(flavor = "multi_thread")]
+async fn stores_denied_by_policy_cannot_be_opened() -> anyhow::Result<()> {
+    let key_value = memory_stores(&["default", "payments"])?;
+    let policy = RulesPolicyEngine {
+        rules: vec![PolicyRule {
+            effect: Effect::Deny,
+            components: None,
+            actions: Some([ActionKind::KeyValueStore].into()),
+            targets: Some(vec!["payments".into()]),
+        }],
+        default: Effect::Allow,
+    };
+    let factors = PolicyFactors {
+        policy: PolicyFactor::new(),
+        key_value: KeyValueFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            key_value_stores = ["default", "payments"]
+        })
+        .runtime_config(PolicyFactorsRuntimeConfig {
+            policy: Some(spin_factor_policy::RuntimeConfig::new(policy)),
+            key_value: Some(key_value),
+        })?;
+    let mut state = env.build_instance_state().await?;
+    assert!(state.key_value.open("default".into()).await?.is_ok());
+    assert!(matches!(
+        state.key_value.open("payments".into()).await?,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn geo_replicated_stores_read_locally_and_write_to_primary() -> anyhow::Result<()> {
+    let mut resolver = RuntimeConfigResolver::new();
+    resolver.register_store_type(MemoryKeyValueStore::new())?;
+    resolver.register_store_type(GeoReplicatedKeyValueStore::new(resolver.clone()))?;
+    let runtime_config = resolver.resolve(Some(&toml! {
+        [key_value_store.replicated]
+        type = "geo_replicated"
+        region = "eu-west"
+        primary = { type = "memory" }
+        regions = { eu-west = { type = "memory" } }
+
+        [key_value_store.unreplicated]
+        type = "geo_replicated"
+        region = "eu-west"
+        fan_out = false
+        primary = { type = "memory" }
+        regions = { eu-west = { type = "memory" } }
+    }))?;
+    let open = |label: &'static str| {
+        let store_manager = runtime_config.get_store_manager(label).unwrap();
+        async move { store_manager.get(label).await }
+    };
+
+    // Writes reach the regional store, which reads go to, in the background
+    let replicated = open("replicated").await?;
+    replicated.set("greeting", b"hello").await?;
+    let mut value = None;
+    for _ in 0..100 {
+        value = replicated.get("greeting").await?;
+        if value.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
     }
+    assert_eq!(Some(b"hello".to_vec()), value);
+
+    // Without fan-out, writes only reach the primary
+    let unreplicated = open("unreplicated").await?;
+    unreplicated.set("greeting", b"hello").await?;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(None, unreplicated.get("greeting").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn json_documents_can_be_read_and_patched_in_part() -> anyhow::Result<()> {
+    let mut resolver = RuntimeConfigResolver::new();
+    resolver.register_store_type(MemoryKeyValueStore::new())?;
+    let runtime_config = resolver.resolve(Some(&toml! {
+        [key_value_store.default]
+        type = "memory"
+    }))?;
+    let store = runtime_config
+        .get_store_manager("default")
+        .unwrap()
+        .get("default")
+        .await?;
 
-    async fn new_compare_and_swap(
-        &self,
-        bucket_rep: u32,
-        key: &str,
-    ) -> anyhow::Result<Arc<dyn Cas>, Error> {
-        let (_, _) = (key, bucket_rep);
-        todo!()
+    store
+        .patch_json("doc", "", r#"{"name":"spin","tags":[]}"#)
+        .await?;
+    store.patch_json("doc", "/tags/-", r#""wasm""#).await?;
+    store.patch_json("doc", "/name", r#""fermyon""#).await?;
+    assert_eq!(
+        Some(r#""fermyon""#.to_owned()),
+        store.get_json("doc", "/name").await?
+    );
+    assert_eq!(
+        Some(r#"["wasm"]"#.to_owned()),
+        store.get_json("doc", "/tags").await?
+    );
+    assert_eq!(None, store.get_json("doc", "/missing").await?);
+    assert_eq!(None, store.get_json("missing", "/name").await?);
+
+    assert!(store.patch_json("missing", "/name", "1").await.is_err());
+    assert!(store.patch_json("doc", "/missing/name", "1").await.is_err());
+    store.set("bytes", b"not json").await?;
+    assert!(store.get_json("bytes", "").await.is_err());
+    Ok(())
+}
+
+/// Resolves runtime config with an in-memory store for each of `labels`.
+fn memory_stores(labels: &[&str]) -> anyhow::Result<RuntimeConfig> {
+    let mut resolver = RuntimeConfigResolver::new();
+    resolver.register_store_type(MemoryKeyValueStore::new())?;
+    let stores: toml::Table = labels
+        .iter()
+        .map(|label| (label.to_string(), toml! { type = "memory" }.into()))
+        .collect();
+    let mut runtime_config = toml::Table::new();
+    runtime_config.insert("key_value_store".into(), stores.into());
+    resolver.resolve(Some(&runtime_config))
+}
+
+/// Reads every value in the store with the given label.
+async fn values(
+    store_manager: &Arc<dyn StoreManager>,
+    label: &str,
+) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+    let store = store_manager.get(label).await?;
+    let mut values = HashMap::new();
+    for key in store.get_keys().await? {
+        if let Some(value) = store.get(&key).await? {
+            values.insert(key, value);
+        }
     }
+    Ok(values)
 }
@@ -0,0 +1,78 @@
+use std::{sync::Arc, time::Duration};
+
+use spin_factor_key_value::{Store, StoreManager};
+use spin_world::spin::leader_election::leader_election::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::{leadership::Leaderships, lease::to_leader_election_err};
+
+pub struct InstanceState {
+    allowed: bool,
+    store_label: String,
+    store_manager: Arc<dyn StoreManager>,
+    store: Option<Arc<dyn Store>>,
+    leaderships: Arc<Leaderships>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed: bool,
+        store_label: String,
+        store_manager: Arc<dyn StoreManager>,
+        leaderships: Arc<Leaderships>,
+    ) -> Self {
+        Self {
+            allowed,
+            store_label,
+            store_manager,
+            store: None,
+            leaderships,
+        }
+    }
+
+    /// Returns whether this instance may use leader election.
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Returns the leader election store, opening it on first use.
+    async fn store(&mut self) -> Result<Arc<dyn Store>, Error> {
+        if !self.allowed {
+            return Err(Error::AccessDenied);
+        }
+        if let Some(store) = &self.store {
+            return Ok(store.clone());
+        }
+        let store = self
+            .store_manager
+            .get(&self.store_label)
+            .await
+            .map_err(to_leader_election_err)?;
+        self.store = Some(store.clone());
+        Ok(store)
+    }
+}
+
+impl leader_election::Host for InstanceState {
+    #[instrument(name = "spin_leader_election.acquire_leadership", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn acquire_leadership(&mut self, name: String, ttl_ms: u64) -> Result<bool, Error> {
+        if ttl_ms == 0 {
+            return Err(Error::InvalidTtl);
+        }
+        let store = self.store().await?;
+        self.leaderships
+            .acquire(store, &name, Duration::from_millis(ttl_ms))
+            .await
+    }
+
+    #[instrument(name = "spin_leader_election.release_leadership", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn release_leadership(&mut self, name: String) -> Result<(), Error> {
+        let store = self.store().await?;
+        self.leaderships.release(store, &name).await;
+        Ok(())
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use spin_common::time::now_millis;
+use spin_factor_key_value::Store;
+use spin_world::spin::leader_election::leader_election::Error;
+
+use crate::lease;
+
+/// The shortest interval at which leases are renewed, so that tiny TTLs
+/// don't hammer the store.
+const MIN_RENEWAL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The leaderships held by this replica of an app.
+pub(crate) struct Leaderships {
+    /// Identifies this replica in leases.
+    owner: u128,
+    held: Mutex<HashMap<String, Heartbeat>>,
+}
+
+impl Leaderships {
+    pub fn new() -> Self {
+        Self {
+            owner: uuid::Uuid::new_v4().as_u128(),
+            held: Default::default(),
+        }
+    }
+
+    /// Acquires leadership of `name` if it isn't already held, returning
+    /// whether this replica is the leader.
+    pub async fn acquire(
+        &self,
+        store: Arc<dyn Store>,
+        name: &str,
+        ttl: Duration,
+    ) -> Result<bool, Error> {
+        if self
+            .held
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(Heartbeat::is_leader)
+        {
+            return Ok(true);
+        }
+        let expires_at = lease::acquire(store.as_ref(), name, self.owner, ttl).await?;
+        let mut held = self.held.lock().unwrap();
+        match expires_at {
+            Some(expires_at) => {
+                let heartbeat = Heartbeat::start(store, name, self.owner, ttl, expires_at);
+                held.insert(name.to_owned(), heartbeat);
+                Ok(true)
+            }
+            None => {
+                held.remove(name);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Stops renewing leadership of `name` and releases its lease.
+    pub async fn release(&self, store: Arc<dyn Store>, name: &str) {
+        // Dropping the heartbeat stops it renewing the lease
+        self.held.lock().unwrap().remove(name);
+        lease::release(store.as_ref(), name, self.owner).await;
+    }
+}
+
+/// Renews a lease in the background until dropped.
+struct Heartbeat {
+    /// When the lease expires as of its last renewal, or zero once it has
+    /// been lost to another replica.
+    expires_at: Arc<AtomicU64>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Heartbeat {
+    fn start(
+        store: Arc<dyn Store>,
+        name: &str,
+        owner: u128,
+        ttl: Duration,
+        expires_at: u64,
+    ) -> Self {
+        let expires_at = Arc::new(AtomicU64::new(expires_at));
+        let name = name.to_owned();
+        let renewed = expires_at.clone();
+        let task = tokio::spawn(async move {
+            let interval = (ttl / 3).max(MIN_RENEWAL_INTERVAL);
+            loop {
+                tokio::time::sleep(interval).await;
+                match lease::acquire(store.as_ref(), &name, owner, ttl).await {
+                    Ok(Some(expires_at)) => renewed.store(expires_at, Ordering::Relaxed),
+                    Ok(None) => {
+                        tracing::warn!("lost leadership of {name:?} to another replica");
+                        renewed.store(0, Ordering::Relaxed);
+                        return;
+                    }
+                    // Keep trying; leadership lapses if the lease expires first.
+                    Err(e) => tracing::warn!("failed to renew leadership of {name:?}: {e:?}"),
+                }
+            }
+        });
+        Self { expires_at, task }
+    }
+
+    fn is_leader(&self) -> bool {
+        self.expires_at.load(Ordering::Relaxed) > now_millis()
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,142 @@
+//! Leadership leases kept in a key-value store.
+//!
+//! A lease records the replica holding it and when it expires. A replica
+//! acquires a lease by swapping in its own record while the current one is
+//! absent, expired or already its own; renewing is acquiring again.
+
+use std::time::Duration;
+
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, SwapError};
+use spin_world::spin::leader_election::leader_election::Error;
+use spin_world::v2::key_value;
+
+/// Prefix for the keys of leases in the backing store.
+const LEASE_KEY_PREFIX: &str = "spin-leader-election:";
+
+/// Returns the backing store key of the lease for `name`.
+pub(crate) fn lease_key(name: &str) -> String {
+    format!("{LEASE_KEY_PREFIX}{name}")
+}
+
+/// A lease, held by the replica `owner` until `expires_at`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Lease {
+    pub owner: u128,
+    pub expires_at: u64,
+}
+
+impl Lease {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut lease = Vec::with_capacity(24);
+        lease.extend_from_slice(&self.owner.to_be_bytes());
+        lease.extend_from_slice(&self.expires_at.to_be_bytes());
+        lease
+    }
+
+    /// Decodes a lease, returning `None` if it is malformed.
+    pub fn decode(lease: &[u8]) -> Option<Self> {
+        let (owner, expires_at) = lease.split_first_chunk::<16>()?;
+        Some(Self {
+            owner: u128::from_be_bytes(*owner),
+            expires_at: u64::from_be_bytes(expires_at.try_into().ok()?),
+        })
+    }
+
+    /// Returns whether `owner` may take this lease at `now`.
+    pub fn is_available_to(&self, owner: u128, now: u64) -> bool {
+        self.owner == owner || self.expires_at <= now
+    }
+}
+
+/// Acquires or renews the lease on `name` for `owner`, returning when it
+/// expires, or `None` if another replica holds it.
+pub(crate) async fn acquire(
+    store: &dyn Store,
+    name: &str,
+    owner: u128,
+    ttl: Duration,
+) -> Result<Option<u64>, Error> {
+    let cas = store
+        .new_compare_and_swap(0, &lease_key(name))
+        .await
+        .map_err(to_leader_election_err)?;
+    let current = cas.current().await.map_err(to_leader_election_err)?;
+    let now = now_millis();
+    // A malformed lease can't be renewed by its owner, so treat it as expired.
+    let available = current
+        .as_deref()
+        .and_then(Lease::decode)
+        .is_none_or(|lease| lease.is_available_to(owner, now));
+    if !available {
+        return Ok(None);
+    }
+    let lease = Lease {
+        owner,
+        expires_at: now.saturating_add(ttl.as_millis().try_into().unwrap_or(u64::MAX)),
+    };
+    match cas.swap(lease.encode()).await {
+        Ok(()) => Ok(Some(lease.expires_at)),
+        // Another replica took the lease first.
+        Err(SwapError::CasFailed(_)) => Ok(None),
+        Err(SwapError::Other(e)) => Err(Error::Other(e)),
+    }
+}
+
+/// Releases the lease on `name` if it is still held by `owner`.
+///
+/// This is best effort: if the lease can't be released it is taken over once
+/// it expires.
+pub(crate) async fn release(store: &dyn Store, name: &str, owner: u128) {
+    let lease_key = lease_key(name);
+    let held = match store.get(&lease_key).await {
+        Ok(lease) => lease
+            .as_deref()
+            .and_then(Lease::decode)
+            .is_some_and(|lease| lease.owner == owner),
+        Err(e) => {
+            tracing::warn!("failed to check leadership lease for {name:?}: {e:?}");
+            return;
+        }
+    };
+    if held {
+        if let Err(e) = store.delete(&lease_key).await {
+            tracing::warn!("failed to release leadership lease for {name:?}: {e:?}");
+        }
+    }
+}
+
+pub(crate) fn to_leader_election_err(e: key_value::Error) -> Error {
+    match e {
+        key_value::Error::NoSuchStore => Error::NoSuchStore,
+        key_value::Error::AccessDenied => Error::AccessDenied,
+        key_value::Error::StoreTableFull => Error::Other("too many open stores".into()),
+        key_value::Error::Other(e) => Error::Other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lease_round_trips() {
+        let lease = Lease {
+            owner: 7,
+            expires_at: 42,
+        };
+        assert_eq!(Lease::decode(&lease.encode()), Some(lease));
+        assert_eq!(Lease::decode(b"short"), None);
+    }
+
+    #[test]
+    fn leases_are_available_to_their_owner_or_once_expired() {
+        let lease = Lease {
+            owner: 7,
+            expires_at: 100,
+        };
+        assert!(lease.is_available_to(7, 50));
+        assert!(!lease.is_available_to(8, 50));
+        assert!(lease.is_available_to(8, 100));
+    }
+}
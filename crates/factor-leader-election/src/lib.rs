@@ -0,0 +1,132 @@
+mod host;
+mod leadership;
+mod lease;
+pub mod runtime_config;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_factor_key_value::{KeyValueFactor, StoreManager, KEY_VALUE_STORES_KEY};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+use crate::leadership::Leaderships;
+
+/// A factor that elects one replica of a horizontally scaled app to lead
+/// each named role, e.g. to run singleton work such as cache warming.
+///
+/// Leadership is a lease kept in one of the app's key-value stores. A
+/// component may use leader election if it may use that store as a
+/// key-value store, so this factor must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct LeaderElectionFactor {
+    _priv: (),
+}
+
+impl LeaderElectionFactor {
+    /// Create a new LeaderElectionFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for LeaderElectionFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::leader_election::leader_election::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        let configured = ctx.take_runtime_config();
+        // An explicitly configured store must exist; the default store is
+        // only checked when a component uses leader election.
+        if let Some(config) = &configured {
+            anyhow::ensure!(
+                store_manager.is_defined(&config.store),
+                "leader election store {:?} is not a defined key-value store",
+                config.store
+            );
+        }
+        let RuntimeConfig { store } = configured.unwrap_or_default();
+
+        let mut component_allowed = HashMap::new();
+        for component in ctx.app().components() {
+            let allowed = component
+                .get_metadata(KEY_VALUE_STORES_KEY)?
+                .unwrap_or_default()
+                .contains(&store);
+            component_allowed.insert(component.id().to_string(), allowed);
+        }
+
+        Ok(AppState {
+            store_manager,
+            store,
+            component_allowed,
+            leaderships: Arc::new(Leaderships::new()),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed = *app_state
+            .component_allowed
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed");
+        Ok(InstanceBuilder {
+            store_manager: app_state.store_manager.clone(),
+            store: app_state.store.clone(),
+            allowed,
+            leaderships: app_state.leaderships.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The label of the key-value store holding leadership leases.
+    store: String,
+    /// Whether each component may use leader election, keyed by component ID.
+    component_allowed: HashMap<String, bool>,
+    /// The leaderships held by this replica, shared by all its instances.
+    leaderships: Arc<Leaderships>,
+}
+
+pub struct InstanceBuilder {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The label of the key-value store holding leadership leases.
+    store: String,
+    /// Whether this component instance may use leader election.
+    allowed: bool,
+    /// The leaderships held by this replica.
+    leaderships: Arc<Leaderships>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.allowed,
+            self.store,
+            self.store_manager,
+            self.leaderships,
+        ))
+    }
+}
@@ -0,0 +1,16 @@
+pub mod spin;
+
+/// Runtime configuration for leader election.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store holding leadership leases.
+    pub store: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            store: "default".into(),
+        }
+    }
+}
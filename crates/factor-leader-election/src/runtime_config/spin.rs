@@ -0,0 +1,34 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for leader election from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [leader_election]
+/// store = "leases"
+/// ```
+///
+/// Every replica of the app must use the same store, and it should be one
+/// which supports atomic compare-and-swap across replicas, such as Redis.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("leader_election") else {
+        return Ok(None);
+    };
+    let toml: LeaderElectionToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [leader_election] table")?;
+    Ok(Some(RuntimeConfig { store: toml.store }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LeaderElectionToml {
+    store: String,
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, RuntimeConfig, StoreManager,
+};
+use spin_factor_leader_election::LeaderElectionFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::leader_election::leader_election::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    leader_election: LeaderElectionFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            key_value: Some(value),
+            leader_election: None,
+        }
+    }
+}
+
+/// Creates in-memory stores, which replicas of an app can share.
+fn memory_store_manager() -> Arc<dyn StoreManager> {
+    let store_manager = MemoryKeyValueStore::new()
+        .make_store(Default::default())
+        .expect("in-memory store should be created");
+    Arc::new(store_manager)
+}
+
+/// Builds an instance of a new replica of an app whose stores are kept in
+/// `store_manager`.
+async fn build_state(
+    manifest: toml::Table,
+    store_manager: Arc<dyn StoreManager>,
+) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store_manager("default".into(), store_manager);
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        leader_election: LeaderElectionFactor::new(),
+    })
+    .extend_manifest(manifest);
+    env.runtime_config(runtime_config)?
+        .build_instance_state()
+        .await
+}
+
+fn allowed_default_store() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leader_election_follows_key_value_store_access() -> anyhow::Result<()> {
+    let stores = memory_store_manager();
+    let state = build_state(allowed_default_store(), stores.clone()).await?;
+    assert!(state.leader_election.allowed());
+
+    let mut state = build_state(
+        toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        },
+        stores,
+    )
+    .await?;
+    assert!(matches!(
+        state
+            .leader_election
+            .acquire_leadership("cron".into(), 1000)
+            .await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn one_replica_leads_each_name() -> anyhow::Result<()> {
+    let stores = memory_store_manager();
+    let mut first = build_state(allowed_default_store(), stores.clone()).await?;
+    let mut second = build_state(allowed_default_store(), stores).await?;
+    let (first, second) = (&mut first.leader_election, &mut second.leader_election);
+
+    assert!(first.acquire_leadership("cron".into(), 60_000).await?);
+    // Acquiring again while leading is a no-op
+    assert!(first.acquire_leadership("cron".into(), 60_000).await?);
+    assert!(!second.acquire_leadership("cron".into(), 60_000).await?);
+    // Names are led independently
+    assert!(second.acquire_leadership("warmer".into(), 60_000).await?);
+
+    first.release_leadership("cron".into()).await?;
+    assert!(second.acquire_leadership("cron".into(), 60_000).await?);
+    assert!(!first.acquire_leadership("cron".into(), 60_000).await?);
+
+    assert!(matches!(
+        first.acquire_leadership("cron".into(), 0).await,
+        Err(Error::InvalidTtl)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leadership_is_renewed_while_held() -> anyhow::Result<()> {
+    let stores = memory_store_manager();
+    let mut first = build_state(allowed_default_store(), stores.clone()).await?;
+    let mut second = build_state(allowed_default_store(), stores).await?;
+
+    assert!(
+        first
+            .leader_election
+            .acquire_leadership("cron".into(), 300)
+            .await?
+    );
+    // Without renewal the lease would have expired by now
+    tokio::time::sleep(std::time::Duration::from_millis(600)).await;
+    assert!(
+        !second
+            .leader_election
+            .acquire_leadership("cron".into(), 300)
+            .await?
+    );
+
+    // Once the leader is gone its lease expires and another replica takes over
+    drop(first);
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+    assert!(
+        second
+            .leader_election
+            .acquire_leadership("cron".into(), 300)
+            .await?
+    );
+    Ok(())
+}
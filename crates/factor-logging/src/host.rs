@@ -0,0 +1,85 @@
+use spin_factor_request_context::RequestContextHandle;
+use spin_telemetry::logs::{GuestLogLevel, GuestLogRecord, GuestLogValue};
+use spin_world::spin::logging::log::{self, Field, Level, Value};
+
+pub struct InstanceState {
+    component_id: String,
+    min_level: GuestLogLevel,
+    // Context of the invocation, attached to records
+    request_context: Option<RequestContextHandle>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        component_id: String,
+        min_level: GuestLogLevel,
+        request_context: Option<RequestContextHandle>,
+    ) -> Self {
+        Self {
+            component_id,
+            min_level,
+            request_context,
+        }
+    }
+
+    /// Returns whether records at `level` are emitted.
+    pub fn is_enabled(&self, level: GuestLogLevel) -> bool {
+        level >= self.min_level
+    }
+}
+
+impl log::Host for InstanceState {
+    async fn enabled(&mut self, level: Level) -> anyhow::Result<bool> {
+        Ok(self.is_enabled(to_log_level(level)))
+    }
+
+    async fn log(
+        &mut self,
+        level: Level,
+        target: Option<String>,
+        message: String,
+        fields: Vec<Field>,
+    ) -> anyhow::Result<()> {
+        let level = to_log_level(level);
+        if !self.is_enabled(level) {
+            return Ok(());
+        }
+        let request_id = self
+            .request_context
+            .as_ref()
+            .map(|context| context.get().request_id)
+            .filter(|request_id| !request_id.is_empty());
+        let fields = fields
+            .into_iter()
+            .map(|Field { key, value }| (key, to_log_value(value)))
+            .collect::<Vec<_>>();
+        spin_telemetry::logs::handle_guest_log(GuestLogRecord {
+            component_id: &self.component_id,
+            request_id: request_id.as_deref(),
+            level,
+            target: target.as_deref(),
+            message: &message,
+            fields: &fields,
+        });
+        Ok(())
+    }
+}
+
+fn to_log_level(level: Level) -> GuestLogLevel {
+    match level {
+        Level::Trace => GuestLogLevel::Trace,
+        Level::Debug => GuestLogLevel::Debug,
+        Level::Info => GuestLogLevel::Info,
+        Level::Warn => GuestLogLevel::Warn,
+        Level::Error => GuestLogLevel::Error,
+    }
+}
+
+fn to_log_value(value: Value) -> GuestLogValue {
+    match value {
+        Value::String(s) => GuestLogValue::String(s),
+        Value::Integer(i) => GuestLogValue::Integer(i),
+        Value::Float(x) => GuestLogValue::Float(x),
+        Value::Boolean(b) => GuestLogValue::Boolean(b),
+    }
+}
@@ -0,0 +1,68 @@
+mod host;
+pub mod runtime_config;
+
+use spin_factor_request_context::RequestContextFactor;
+use spin_factors::{
+    anyhow, ConfigureAppContext, Factor, InitContext, PrepareContext, RuntimeFactors,
+    SelfInstanceBuilder,
+};
+use spin_telemetry::logs::GuestLogLevel;
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that lets guests write structured, leveled log records, which
+/// flow into the host's tracing and OTel logs pipeline tagged with the
+/// component that wrote them.
+#[derive(Default)]
+pub struct LoggingFactor {
+    _priv: (),
+}
+
+impl LoggingFactor {
+    /// Create a new LoggingFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for LoggingFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::logging::log::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let RuntimeConfig { min_level } = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState { min_level })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceState> {
+        let component_id = ctx.app_component().id().to_owned();
+        let min_level = ctx.app_state().min_level;
+        // Records carry the request ID if the runtime provides one
+        let request_context = match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(request_context) => Some(request_context.handle()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        Ok(InstanceState::new(component_id, min_level, request_context))
+    }
+}
+
+pub struct AppState {
+    /// The lowest level of guest log records which are emitted.
+    min_level: GuestLogLevel,
+}
+
+impl SelfInstanceBuilder for InstanceState {}
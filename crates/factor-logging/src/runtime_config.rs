@@ -0,0 +1,18 @@
+pub mod spin;
+
+use spin_telemetry::logs::GuestLogLevel;
+
+/// Runtime configuration for guest logging.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The lowest level of guest log records which are emitted.
+    pub min_level: GuestLogLevel,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            min_level: GuestLogLevel::Info,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+use spin_telemetry::logs::GuestLogLevel;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for guest logging from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [guest_logging]
+/// level = "debug"
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("guest_logging") else {
+        return Ok(None);
+    };
+    let toml: GuestLoggingToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [guest_logging] table")?;
+    let min_level = match toml.level {
+        LevelToml::Trace => GuestLogLevel::Trace,
+        LevelToml::Debug => GuestLogLevel::Debug,
+        LevelToml::Info => GuestLogLevel::Info,
+        LevelToml::Warn => GuestLogLevel::Warn,
+        LevelToml::Error => GuestLogLevel::Error,
+    };
+    Ok(Some(RuntimeConfig { min_level }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GuestLoggingToml {
+    level: LevelToml,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LevelToml {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_level() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [guest_logging]
+            level = "debug"
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert_eq!(config.min_level, GuestLogLevel::Debug);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_levels_are_rejected() {
+        let table: toml::Table = toml::toml! {
+            [guest_logging]
+            level = "verbose"
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
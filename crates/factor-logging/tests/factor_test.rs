@@ -0,0 +1,76 @@
+use spin_factor_logging::{LoggingFactor, RuntimeConfig};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_telemetry::logs::GuestLogLevel;
+use spin_world::spin::logging::log::{Field, Host as _, Level, Value};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    logging: LoggingFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            logging: Some(value),
+        }
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        logging: LoggingFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+#[tokio::test]
+async fn info_and_above_are_enabled_by_default() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    assert!(!state.logging.enabled(Level::Debug).await?);
+    assert!(state.logging.enabled(Level::Info).await?);
+    assert!(state.logging.enabled(Level::Error).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn min_level_comes_from_runtime_config() -> anyhow::Result<()> {
+    let mut state = test_env()
+        .runtime_config(RuntimeConfig {
+            min_level: GuestLogLevel::Trace,
+        })?
+        .build_instance_state()
+        .await?;
+
+    assert!(state.logging.enabled(Level::Trace).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn structured_records_are_accepted() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    state
+        .logging
+        .log(
+            Level::Warn,
+            Some("orders".into()),
+            "order rejected".into(),
+            vec![
+                Field {
+                    key: "order_id".into(),
+                    value: Value::Integer(42),
+                },
+                Field {
+                    key: "reason".into(),
+                    value: Value::String("out of stock".into()),
+                },
+            ],
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::time::{Duration, SystemTime};
+
+use futures::{stream::BoxStream, Stream, StreamExt};
+use spin_core::async_trait;
+
+pub use spin_world::wasi::messaging::types::Error;
+
+/// A message exchanged with a broker.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Message {
+    /// The topic the message was received on, if any.
+    pub topic: Option<String>,
+    /// The format of `data`, if known.
+    pub content_type: Option<String>,
+    /// The message payload.
+    pub data: Vec<u8>,
+    /// Headers or attributes attached to the message.
+    pub metadata: Vec<(String, String)>,
+    /// Where replies to this message should be sent, if the broker supports replies.
+    pub reply_to: Option<String>,
+}
+
+impl Message {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            ..Default::default()
+        }
+    }
+}
+
+/// The messages delivered to a topic subscription, in the order the broker
+/// delivered them.
+pub type Subscription = BoxStream<'static, Result<Message, Error>>;
+
+/// Options for a request/reply exchange.
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    /// How long to wait for replies. `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// How many replies to wait for.
+    pub expected_replies: Option<u32>,
+}
+
+/// A message broker reachable through the messaging interfaces.
+#[async_trait]
+pub trait MessageBroker: Send + Sync {
+    /// Sends a message to the given topic.
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error>;
+
+    /// Sends a message to the given topic and waits for replies to it.
+    ///
+    /// Implementations that cannot route replies should return an error.
+    async fn request(
+        &self,
+        topic: &str,
+        message: Message,
+        options: RequestOptions,
+    ) -> Result<Vec<Message>, Error>;
+
+    /// Sends several messages to the given topic.
+    ///
+    /// By default the messages are sent one at a time, stopping at the first
+    /// failure. Brokers with a native batch operation should override this.
+    async fn send_batch(&self, topic: &str, messages: Vec<Message>) -> Result<(), Error> {
+        for message in messages {
+            self.send(topic, message).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends a message to the given topic, to be delivered no earlier than
+    /// `deliver_at`.
+    ///
+    /// Implementations that cannot defer delivery should return an error.
+    async fn schedule(
+        &self,
+        topic: &str,
+        message: Message,
+        deliver_at: SystemTime,
+    ) -> Result<(), Error> {
+        let _ = (message, deliver_at);
+        Err(Error::Other(format!(
+            "this message broker does not support scheduling messages to {topic:?}"
+        )))
+    }
+
+    /// Subscribes to messages sent to the given topic from now on.
+    ///
+    /// Implementations that cannot deliver messages to subscribers should
+    /// return an error.
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, Error> {
+        Err(Error::Other(format!(
+            "this message broker does not support subscribing to {topic:?}"
+        )))
+    }
+
+    /// A human-readable summary of the broker's configuration
+    ///
+    /// Example: "NATS at nats://localhost:4222"
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Gathers replies from `replies` according to `options`.
+///
+/// Without a timeout this waits for the expected number of replies (one by
+/// default). With a timeout it returns whatever arrived before the deadline,
+/// failing with [`Error::Timeout`] only if nothing did.
+pub async fn collect_replies(
+    replies: impl Stream<Item = Result<Message, Error>>,
+    options: &RequestOptions,
+) -> Result<Vec<Message>, Error> {
+    let limit = match (options.expected_replies, options.timeout) {
+        (Some(expected), _) => expected as usize,
+        (None, Some(_)) => usize::MAX,
+        (None, None) => 1,
+    };
+    let mut collected = vec![];
+    let collect = async {
+        let mut replies = std::pin::pin!(replies.take(limit));
+        while let Some(reply) = replies.next().await {
+            collected.push(reply?);
+        }
+        Ok::<_, Error>(())
+    };
+    let timed_out = match options.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, collect).await {
+            Ok(result) => {
+                result?;
+                false
+            }
+            Err(_) => true,
+        },
+        None => {
+            collect.await?;
+            false
+        }
+    };
+    if collected.is_empty() {
+        return Err(if timed_out {
+            Error::Timeout
+        } else {
+            Error::Other("no replies were received".into())
+        });
+    }
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replies(n: usize) -> impl Stream<Item = Result<Message, Error>> {
+        futures::stream::iter((0..n).map(|i| Ok(Message::new(vec![i as u8]))))
+    }
+
+    #[tokio::test]
+    async fn waits_for_one_reply_by_default() {
+        let replies = collect_replies(replies(3), &RequestOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn waits_for_expected_replies() {
+        let options = RequestOptions {
+            expected_replies: Some(2),
+            ..Default::default()
+        };
+        let replies = collect_replies(replies(3), &options).await.unwrap();
+        assert_eq!(replies.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn returns_partial_replies_on_timeout() {
+        let options = RequestOptions {
+            timeout: Some(Duration::from_millis(10)),
+            expected_replies: Some(3),
+        };
+        let replies = replies(1).chain(futures::stream::pending());
+        let replies = collect_replies(replies, &options).await.unwrap();
+        assert_eq!(replies.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn times_out_without_replies() {
+        let options = RequestOptions {
+            timeout: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let result = collect_replies(futures::stream::pending(), &options).await;
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+}
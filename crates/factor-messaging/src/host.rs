@@ -0,0 +1,351 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use spin_factors::wasmtime::component::Resource;
+use spin_resource_table::Table;
+use spin_world::spin::messaging::producer as spin_producer;
+use spin_world::wasi::messaging::{
+    producer,
+    request_reply::{self, RequestOptions as RequestOptionsResource},
+    types::{self, Client, Error, Message as MessageResource, Metadata, Topic},
+};
+use tracing::{instrument, Level};
+
+use crate::{Message, MessageBroker, RequestOptions};
+
+const DEFAULT_TABLE_CAPACITY: u32 = 256;
+
+pub struct InstanceState {
+    allowed_brokers: HashSet<String>,
+    brokers: Arc<HashMap<String, Arc<dyn MessageBroker>>>,
+    clients: Table<Arc<dyn MessageBroker>>,
+    messages: Table<HostMessage>,
+    request_options: Table<RequestOptions>,
+}
+
+/// A message held by the guest.
+struct HostMessage {
+    message: Message,
+    /// The broker the message was received from, used to route replies.
+    broker: Option<Arc<dyn MessageBroker>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_brokers: HashSet<String>,
+        brokers: Arc<HashMap<String, Arc<dyn MessageBroker>>>,
+    ) -> Self {
+        Self {
+            allowed_brokers,
+            brokers,
+            clients: Table::new(DEFAULT_TABLE_CAPACITY),
+            messages: Table::new(DEFAULT_TABLE_CAPACITY),
+            request_options: Table::new(DEFAULT_TABLE_CAPACITY),
+        }
+    }
+
+    /// Returns the set of broker labels this instance may connect to.
+    pub fn allowed_brokers(&self) -> &HashSet<String> {
+        &self.allowed_brokers
+    }
+
+    /// Hands a message received from `broker` to the guest, e.g. to pass to
+    /// its incoming handler. Replies to the message are sent through `broker`.
+    pub fn push_received_message(
+        &mut self,
+        message: Message,
+        broker: Arc<dyn MessageBroker>,
+    ) -> Result<Resource<MessageResource>, Error> {
+        self.push_message(message, Some(broker))
+    }
+
+    fn get_client(&self, client: &Resource<Client>) -> Result<Arc<dyn MessageBroker>, Error> {
+        self.clients
+            .get(client.rep())
+            .cloned()
+            .ok_or_else(|| Error::Other("invalid client".into()))
+    }
+
+    fn get_message(&self, message: &Resource<MessageResource>) -> Result<&HostMessage> {
+        self.messages.get(message.rep()).context("invalid message")
+    }
+
+    fn get_message_mut(&mut self, message: &Resource<MessageResource>) -> Result<&mut Message> {
+        self.messages
+            .get_mut(message.rep())
+            .map(|m| &mut m.message)
+            .context("invalid message")
+    }
+
+    fn take_message(&mut self, message: Resource<MessageResource>) -> Result<Message, Error> {
+        self.messages
+            .remove(message.rep())
+            .map(|m| m.message)
+            .ok_or_else(|| Error::Other("invalid message".into()))
+    }
+
+    fn push_message(
+        &mut self,
+        message: Message,
+        broker: Option<Arc<dyn MessageBroker>>,
+    ) -> Result<Resource<MessageResource>, Error> {
+        self.messages
+            .push(HostMessage { message, broker })
+            .map(Resource::new_own)
+            .map_err(|()| Error::Other("too many messages".into()))
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl types::HostClient for InstanceState {
+    #[instrument(name = "spin_messaging.connect", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn connect(&mut self, name: String) -> Result<Resource<Client>, Error> {
+        if !self.allowed_brokers.contains(&name) {
+            return Err(Error::PermissionDenied(format!(
+                "access to message broker {name:?} is not allowed"
+            )));
+        }
+        let broker = self
+            .brokers
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| Error::Connection(format!("no such message broker {name:?}")))?;
+        self.clients
+            .push(broker)
+            .map(Resource::new_own)
+            .map_err(|()| Error::Other("too many connected clients".into()))
+    }
+
+    async fn disconnect(&mut self, client: Resource<Client>) -> Result<(), Error> {
+        // Brokers are shared by the app, so there is nothing to tear down
+        // beyond checking the handle.
+        self.get_client(&client)?;
+        Ok(())
+    }
+
+    async fn drop(&mut self, client: Resource<Client>) -> Result<()> {
+        self.clients.remove(client.rep());
+        Ok(())
+    }
+}
+
+impl types::HostMessage for InstanceState {
+    async fn new(&mut self, data: Vec<u8>) -> Result<Resource<MessageResource>> {
+        self.messages
+            .push(HostMessage {
+                message: Message::new(data),
+                broker: None,
+            })
+            .map(Resource::new_own)
+            .map_err(|()| anyhow::anyhow!("too many messages"))
+    }
+
+    async fn topic(&mut self, message: Resource<MessageResource>) -> Result<Option<Topic>> {
+        Ok(self.get_message(&message)?.message.topic.clone())
+    }
+
+    async fn content_type(&mut self, message: Resource<MessageResource>) -> Result<Option<String>> {
+        Ok(self.get_message(&message)?.message.content_type.clone())
+    }
+
+    async fn set_content_type(
+        &mut self,
+        message: Resource<MessageResource>,
+        content_type: String,
+    ) -> Result<()> {
+        self.get_message_mut(&message)?.content_type = Some(content_type);
+        Ok(())
+    }
+
+    async fn data(&mut self, message: Resource<MessageResource>) -> Result<Vec<u8>> {
+        Ok(self.get_message(&message)?.message.data.clone())
+    }
+
+    async fn set_data(&mut self, message: Resource<MessageResource>, data: Vec<u8>) -> Result<()> {
+        self.get_message_mut(&message)?.data = data;
+        Ok(())
+    }
+
+    async fn metadata(&mut self, message: Resource<MessageResource>) -> Result<Option<Metadata>> {
+        let metadata = &self.get_message(&message)?.message.metadata;
+        Ok((!metadata.is_empty()).then(|| metadata.clone()))
+    }
+
+    async fn add_metadata(
+        &mut self,
+        message: Resource<MessageResource>,
+        key: String,
+        value: String,
+    ) -> Result<()> {
+        self.get_message_mut(&message)?.metadata.push((key, value));
+        Ok(())
+    }
+
+    async fn set_metadata(
+        &mut self,
+        message: Resource<MessageResource>,
+        meta: Metadata,
+    ) -> Result<()> {
+        self.get_message_mut(&message)?.metadata = meta;
+        Ok(())
+    }
+
+    async fn remove_metadata(
+        &mut self,
+        message: Resource<MessageResource>,
+        key: String,
+    ) -> Result<()> {
+        self.get_message_mut(&message)?
+            .metadata
+            .retain(|(k, _)| k != &key);
+        Ok(())
+    }
+
+    async fn drop(&mut self, message: Resource<MessageResource>) -> Result<()> {
+        self.messages.remove(message.rep());
+        Ok(())
+    }
+}
+
+impl producer::Host for InstanceState {
+    #[instrument(name = "spin_messaging.send", skip(self, c, message), err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn send(
+        &mut self,
+        c: Resource<Client>,
+        topic: Topic,
+        message: Resource<MessageResource>,
+    ) -> Result<(), Error> {
+        let broker = self.get_client(&c)?;
+        let message = self.take_message(message)?;
+        broker.send(&topic, message).await
+    }
+}
+
+impl spin_producer::Host for InstanceState {
+    #[instrument(name = "spin_messaging.send_batch", skip(self, c, messages), err(level = Level::INFO),
+        fields(otel.kind = "producer", messaging.batch.message_count = messages.len()))]
+    async fn send_batch(
+        &mut self,
+        c: Resource<Client>,
+        topic: Topic,
+        messages: Vec<Resource<MessageResource>>,
+    ) -> Result<(), Error> {
+        let broker = self.get_client(&c)?;
+        let messages = messages
+            .into_iter()
+            .map(|message| self.take_message(message))
+            .collect::<Result<Vec<_>, _>>()?;
+        broker.send_batch(&topic, messages).await
+    }
+
+    #[instrument(name = "spin_messaging.schedule", skip(self, c, message), err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn schedule(
+        &mut self,
+        c: Resource<Client>,
+        topic: Topic,
+        message: Resource<MessageResource>,
+        deliver_at_ms: u64,
+    ) -> Result<(), Error> {
+        let broker = self.get_client(&c)?;
+        let message = self.take_message(message)?;
+        let deliver_at = SystemTime::UNIX_EPOCH + Duration::from_millis(deliver_at_ms);
+        broker.schedule(&topic, message, deliver_at).await
+    }
+}
+
+impl request_reply::Host for InstanceState {
+    #[instrument(name = "spin_messaging.request", skip(self, c, message, options), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn request(
+        &mut self,
+        c: Resource<Client>,
+        topic: Topic,
+        message: Resource<MessageResource>,
+        options: Option<Resource<RequestOptionsResource>>,
+    ) -> Result<Vec<Resource<MessageResource>>, Error> {
+        let broker = self.get_client(&c)?;
+        let message = self
+            .messages
+            .get(message.rep())
+            .ok_or_else(|| Error::Other("invalid message".into()))?
+            .message
+            .clone();
+        let options = match options {
+            Some(options) => self
+                .request_options
+                .remove(options.rep())
+                .ok_or_else(|| Error::Other("invalid request-options".into()))?,
+            None => RequestOptions::default(),
+        };
+        let replies = broker.request(&topic, message, options).await?;
+        replies
+            .into_iter()
+            .map(|reply| self.push_message(reply, Some(broker.clone())))
+            .collect()
+    }
+
+    #[instrument(name = "spin_messaging.reply", skip_all, err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn reply(
+        &mut self,
+        reply_to: Resource<MessageResource>,
+        message: Resource<MessageResource>,
+    ) -> Result<(), Error> {
+        let original = self
+            .messages
+            .get(reply_to.rep())
+            .ok_or_else(|| Error::Other("invalid message".into()))?;
+        let (Some(topic), Some(broker)) =
+            (original.message.reply_to.clone(), original.broker.clone())
+        else {
+            return Err(Error::Other("the message does not accept replies".into()));
+        };
+        let message = self.take_message(message)?;
+        broker.send(&topic, message).await
+    }
+}
+
+impl request_reply::HostRequestOptions for InstanceState {
+    async fn new(&mut self) -> Result<Resource<RequestOptionsResource>> {
+        self.request_options
+            .push(RequestOptions::default())
+            .map(Resource::new_own)
+            .map_err(|()| anyhow::anyhow!("too many request options"))
+    }
+
+    async fn set_timeout_ms(
+        &mut self,
+        options: Resource<RequestOptionsResource>,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        self.request_options
+            .get_mut(options.rep())
+            .context("invalid request-options")?
+            .timeout = Some(Duration::from_millis(timeout_ms.into()));
+        Ok(())
+    }
+
+    async fn set_expected_replies(
+        &mut self,
+        options: Resource<RequestOptionsResource>,
+        expected_replies: u32,
+    ) -> Result<()> {
+        self.request_options
+            .get_mut(options.rep())
+            .context("invalid request-options")?
+            .expected_replies = Some(expected_replies);
+        Ok(())
+    }
+
+    async fn drop(&mut self, options: Resource<RequestOptionsResource>) -> Result<()> {
+        self.request_options.remove(options.rep());
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_core::async_trait;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    collect_replies, runtime_config::spin::MakeMessageBroker, Error, Message, MessageBroker,
+    RequestOptions, Subscription,
+};
+
+/// How many undelivered messages each subscriber may fall behind by.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// A message broker which delivers messages between subscribers in this
+/// process only.
+///
+/// Messages sent to a topic with no subscribers are dropped. This is intended
+/// for tests and local development rather than production use.
+pub struct InMemoryBroker {
+    capacity: usize,
+    topics: Mutex<HashMap<String, broadcast::Sender<Message>>>,
+    next_inbox: AtomicU64,
+}
+
+impl InMemoryBroker {
+    /// Creates a broker whose subscribers may each fall `capacity` messages behind.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            topics: Mutex::default(),
+            next_inbox: AtomicU64::new(0),
+        }
+    }
+
+    fn sender(&self, topic: &str) -> broadcast::Sender<Message> {
+        self.topics
+            .lock()
+            .unwrap()
+            .entry(topic.to_owned())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .clone()
+    }
+
+    fn subscription(&self, topic: &str) -> Subscription {
+        let receiver = self.sender(topic).subscribe();
+        Box::pin(futures::stream::unfold(
+            receiver,
+            |mut receiver| async move {
+                let item = match receiver.recv().await {
+                    Ok(message) => Ok(message),
+                    Err(RecvError::Lagged(skipped)) => Err(Error::Other(format!(
+                        "subscriber fell behind; {skipped} messages were dropped"
+                    ))),
+                    Err(RecvError::Closed) => return None,
+                };
+                Some((item, receiver))
+            },
+        ))
+    }
+}
+
+impl Default for InMemoryBroker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl MessageBroker for InMemoryBroker {
+    async fn send(&self, topic: &str, mut message: Message) -> Result<(), Error> {
+        message.topic = Some(topic.to_owned());
+        // Sending only fails when nobody is subscribed, in which case the
+        // message is dropped like any other unrouted message.
+        _ = self.sender(topic).send(message);
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        topic: &str,
+        mut message: Message,
+        options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        let inbox = format!("_INBOX.{}", self.next_inbox.fetch_add(1, Ordering::Relaxed));
+        // Subscribe before sending so that no replies are missed.
+        let replies = self.subscription(&inbox);
+        message.reply_to = Some(inbox.clone());
+        self.send(topic, message).await?;
+        let replies = collect_replies(replies, &options).await;
+        self.topics.lock().unwrap().remove(&inbox);
+        replies
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, Error> {
+        Ok(self.subscription(topic))
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some("in-memory".into())
+    }
+}
+
+/// Makes [`InMemoryBroker`]s from runtime configuration.
+#[derive(Default)]
+pub struct InMemoryMessageBroker {
+    _priv: (),
+}
+
+impl InMemoryMessageBroker {
+    /// Creates a new `InMemoryMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the in-memory message broker.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct InMemoryMessageBrokerRuntimeConfig {
+    /// How many undelivered messages each subscriber may fall behind by.
+    capacity: Option<usize>,
+}
+
+impl MakeMessageBroker for InMemoryMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "in_memory";
+
+    type RuntimeConfig = InMemoryMessageBrokerRuntimeConfig;
+
+    type MessageBroker = InMemoryBroker;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        let capacity = runtime_config.capacity.unwrap_or(DEFAULT_CAPACITY);
+        anyhow::ensure!(capacity > 0, "message broker capacity must be at least 1");
+        Ok(InMemoryBroker::new(capacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_to_every_subscriber() {
+        let broker = InMemoryBroker::default();
+        let mut first = broker.subscribe("orders").await.unwrap();
+        let mut second = broker.subscribe("orders").await.unwrap();
+        broker
+            .send("orders", Message::new(b"hello".to_vec()))
+            .await
+            .unwrap();
+        for subscription in [&mut first, &mut second] {
+            let message = subscription.next().await.unwrap().unwrap();
+            assert_eq!(message.data, b"hello");
+            assert_eq!(message.topic.as_deref(), Some("orders"));
+        }
+    }
+
+    #[tokio::test]
+    async fn drops_messages_without_subscribers() {
+        let broker = InMemoryBroker::default();
+        broker
+            .send("orders", Message::new(b"lost".to_vec()))
+            .await
+            .unwrap();
+        let mut subscription = broker.subscribe("orders").await.unwrap();
+        broker
+            .send("orders", Message::new(b"kept".to_vec()))
+            .await
+            .unwrap();
+        let message = subscription.next().await.unwrap().unwrap();
+        assert_eq!(message.data, b"kept");
+    }
+
+    #[tokio::test]
+    async fn routes_replies_to_requests() {
+        let broker = std::sync::Arc::new(InMemoryBroker::default());
+        let mut requests = broker.subscribe("echo").await.unwrap();
+        let responder = {
+            let broker = broker.clone();
+            tokio::spawn(async move {
+                let request = requests.next().await.unwrap().unwrap();
+                let reply_to = request.reply_to.unwrap();
+                broker
+                    .send(&reply_to, Message::new(request.data))
+                    .await
+                    .unwrap();
+            })
+        };
+        let replies = broker
+            .request(
+                "echo",
+                Message::new(b"ping".to_vec()),
+                RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+        responder.await.unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].data, b"ping");
+    }
+}
@@ -0,0 +1,144 @@
+mod broker;
+mod host;
+mod in_memory;
+pub mod runtime_config;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::ensure;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use broker::{collect_replies, Error, Message, MessageBroker, RequestOptions, Subscription};
+pub use host::InstanceState;
+pub use in_memory::{InMemoryBroker, InMemoryMessageBroker};
+pub use runtime_config::RuntimeConfig;
+
+/// Metadata key for message brokers.
+pub const MESSAGE_BROKERS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("message_brokers");
+
+/// A factor that provides outbound messaging through configurable brokers.
+#[derive(Default)]
+pub struct MessagingFactor {
+    _priv: (),
+}
+
+impl MessagingFactor {
+    /// Create a new MessagingFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for MessagingFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::wasi::messaging::types::add_to_linker)?;
+        ctx.link_bindings(spin_world::wasi::messaging::producer::add_to_linker)?;
+        ctx.link_bindings(spin_world::wasi::messaging::request_reply::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::messaging::producer::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let brokers: HashMap<_, _> = ctx
+            .take_runtime_config()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+
+        // Build component -> allowed brokers map
+        let mut component_allowed_brokers = HashMap::new();
+        for component in ctx.app().components() {
+            let component_id = component.id().to_string();
+            let message_brokers = component
+                .get_metadata(MESSAGE_BROKERS_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for label in &message_brokers {
+                ensure!(
+                    brokers.contains_key(label),
+                    "unknown message_brokers label {label:?} for component {component_id:?}"
+                );
+            }
+            component_allowed_brokers.insert(component_id, message_brokers);
+        }
+
+        Ok(AppState {
+            brokers: Arc::new(brokers),
+            component_allowed_brokers,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_brokers = app_state
+            .component_allowed_brokers
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_brokers")
+            .clone();
+        Ok(InstanceBuilder {
+            brokers: app_state.brokers.clone(),
+            allowed_brokers,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The brokers for the app, keyed by label.
+    brokers: Arc<HashMap<String, Arc<dyn MessageBroker>>>,
+    /// The allowed brokers for each component.
+    ///
+    /// This is a map from component ID to the set of broker labels that the
+    /// component is allowed to use.
+    component_allowed_brokers: HashMap<String, HashSet<String>>,
+}
+
+impl AppState {
+    /// Returns the broker with the given label.
+    pub fn broker(&self, label: &str) -> Option<Arc<dyn MessageBroker>> {
+        self.brokers.get(label).cloned()
+    }
+
+    /// Returns the [`MessageBroker::summary`] for the given broker label.
+    pub fn broker_summary(&self, label: &str) -> Option<String> {
+        self.brokers.get(label)?.summary()
+    }
+
+    /// Returns true if the given broker label is used by any component.
+    pub fn broker_is_used(&self, label: &str) -> bool {
+        self.component_allowed_brokers
+            .values()
+            .any(|brokers| brokers.contains(label))
+    }
+}
+
+pub struct InstanceBuilder {
+    /// The brokers for the app, keyed by label.
+    brokers: Arc<HashMap<String, Arc<dyn MessageBroker>>>,
+    /// The allowed brokers for this component instance.
+    allowed_brokers: HashSet<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_brokers, self.brokers))
+    }
+}
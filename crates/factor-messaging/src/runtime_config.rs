@@ -0,0 +1,40 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::MessageBroker;
+
+/// Runtime configuration for all message brokers.
+#[derive(Default, Clone)]
+pub struct RuntimeConfig {
+    /// Map of broker labels to brokers.
+    brokers: HashMap<String, Arc<dyn MessageBroker>>,
+}
+
+impl RuntimeConfig {
+    /// Adds a broker with the given label to the runtime configuration.
+    ///
+    /// If a broker already exists for the given label, it will be replaced.
+    pub fn add_broker(&mut self, label: String, broker: Arc<dyn MessageBroker>) {
+        self.brokers.insert(label, broker);
+    }
+
+    /// Returns whether a broker exists with the given label.
+    pub fn has_broker(&self, label: &str) -> bool {
+        self.brokers.contains_key(label)
+    }
+
+    /// Returns the broker with the given label.
+    pub fn get_broker(&self, label: &str) -> Option<Arc<dyn MessageBroker>> {
+        self.brokers.get(label).cloned()
+    }
+}
+
+impl IntoIterator for RuntimeConfig {
+    type Item = (String, Arc<dyn MessageBroker>);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Arc<dyn MessageBroker>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.brokers.into_iter()
+    }
+}
@@ -0,0 +1,150 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use crate::{MessageBroker, RuntimeConfig};
+use anyhow::Context as _;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+use std::{collections::HashMap, sync::Arc};
+
+/// Defines the construction of a message broker from a serialized runtime config.
+pub trait MakeMessageBroker: 'static + Send + Sync {
+    /// Unique type identifier for the broker.
+    const RUNTIME_CONFIG_TYPE: &'static str;
+    /// Runtime configuration for the broker.
+    ///
+    /// Its JSON schema documents and validates the broker's runtime config.
+    type RuntimeConfig: DeserializeOwned + JsonSchema;
+    /// The broker implementation.
+    type MessageBroker: MessageBroker;
+
+    /// Creates a new broker from the runtime configuration.
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker>;
+}
+
+/// A function that creates a message broker from a TOML table.
+type StoreFromToml =
+    Arc<dyn Fn(toml::Table) -> anyhow::Result<Arc<dyn MessageBroker>> + Send + Sync>;
+
+/// Creates a `StoreFromToml` function from a `MakeMessageBroker` implementation.
+fn store_from_toml_fn<T: MakeMessageBroker>(provider_type: T) -> StoreFromToml {
+    Arc::new(move |table| {
+        let runtime_config: T::RuntimeConfig = table
+            .try_into()
+            .context("could not parse message broker runtime config")?;
+        let provider = provider_type
+            .make_store(runtime_config)
+            .context("could not make message broker from runtime config")?;
+        Ok(Arc::new(provider))
+    })
+}
+
+/// Converts from toml based runtime configuration into a [`RuntimeConfig`].
+///
+/// The various broker types (i.e., the "type" field in the toml field) are
+/// registered with the resolver using `register_store_type`.
+#[derive(Default, Clone)]
+pub struct RuntimeConfigResolver {
+    /// A map of store types to a function that returns the appropriate
+    /// message broker from runtime config TOML.
+    store_types: HashMap<&'static str, StoreFromToml>,
+    /// A map of store types to the JSON schema of their runtime config.
+    schemas: HashMap<&'static str, RootSchema>,
+}
+
+impl RuntimeConfigResolver {
+    /// Create a new RuntimeConfigResolver.
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Registers a store type to the resolver.
+    pub fn register_store_type<T: MakeMessageBroker>(
+        &mut self,
+        store_type: T,
+    ) -> anyhow::Result<()> {
+        if self
+            .store_types
+            .insert(T::RUNTIME_CONFIG_TYPE, store_from_toml_fn(store_type))
+            .is_some()
+        {
+            anyhow::bail!("duplicate message broker type {:?}", T::RUNTIME_CONFIG_TYPE);
+        }
+        self.schemas.insert(
+            T::RUNTIME_CONFIG_TYPE,
+            schemars::schema_for!(T::RuntimeConfig),
+        );
+        Ok(())
+    }
+
+    /// Registers `alias` as another name for the registered `store_type`.
+    pub fn register_store_type_alias(
+        &mut self,
+        alias: &'static str,
+        store_type: &str,
+    ) -> anyhow::Result<()> {
+        let store_from_toml = self
+            .store_types
+            .get(store_type)
+            .with_context(|| format!("unknown message broker type {store_type:?}"))?
+            .clone();
+        if self.store_types.insert(alias, store_from_toml).is_some() {
+            anyhow::bail!("duplicate message broker type {alias:?}");
+        }
+        if let Some(schema) = self.schemas.get(store_type).cloned() {
+            self.schemas.insert(alias, schema);
+        }
+        Ok(())
+    }
+
+    /// The JSON schemas of the registered broker types' runtime config, by
+    /// broker type.
+    pub fn store_type_schemas(&self) -> impl Iterator<Item = (&'static str, &RootSchema)> {
+        self.schemas
+            .iter()
+            .map(|(&store_type, schema)| (store_type, schema))
+    }
+
+    /// Resolves a toml table into a runtime config.
+    pub fn resolve(&self, table: Option<&impl GetTomlValue>) -> anyhow::Result<RuntimeConfig> {
+        let Some(table) = table.and_then(|t| t.get("message_broker")) else {
+            return Ok(RuntimeConfig::default());
+        };
+        let table: HashMap<String, StoreConfig> = table.clone().try_into()?;
+
+        let mut runtime_config = RuntimeConfig::default();
+        for (label, config) in table {
+            let broker = self.broker_from_config(config).with_context(|| {
+                format!("could not configure message broker with label '{label}'")
+            })?;
+            runtime_config.add_broker(label, broker);
+        }
+        Ok(runtime_config)
+    }
+
+    /// Given a [`StoreConfig`], returns a message broker.
+    ///
+    /// Errors if there is no [`MakeMessageBroker`] registered for the store config's type
+    /// or if the message broker cannot be created from the config.
+    fn broker_from_config(&self, config: StoreConfig) -> anyhow::Result<Arc<dyn MessageBroker>> {
+        let config_type = config.type_.as_str();
+        let maker = self.store_types.get(config_type).with_context(|| {
+            format!(
+                "the message broker type '{config_type}' was not registered with the config resolver"
+            )
+        })?;
+        maker(config.config)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StoreConfig {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(flatten)]
+    pub config: toml::Table,
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::bail;
+use spin_core::async_trait;
+use spin_factor_messaging::{
+    Error, Message, MessageBroker, MessagingFactor, RequestOptions, RuntimeConfig,
+};
+use spin_factors::{wasmtime::component::Resource, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::messaging::producer::Host as _;
+use spin_world::wasi::messaging::{
+    producer::Host as _, types::HostClient as _, types::HostMessage as _,
+};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    messaging: MessagingFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            messaging: Some(value),
+        }
+    }
+}
+
+#[tokio::test]
+async fn works_when_allowed_broker_is_defined() -> anyhow::Result<()> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_broker("events".into(), Arc::new(MockBroker));
+    let env = TestEnvironment::new(TestFactors {
+        messaging: MessagingFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        message_brokers = ["events"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.messaging.allowed_brokers(),
+        &["events".into()].into_iter().collect::<HashSet<_>>()
+    );
+
+    let client = state.messaging.connect("events".into()).await?;
+    let message = state.messaging.new(b"hello".to_vec()).await?;
+    state
+        .messaging
+        .send(client, "orders".into(), message)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn batches_fall_back_to_single_sends() -> anyhow::Result<()> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_broker("events".into(), Arc::new(MockBroker));
+    let env = TestEnvironment::new(TestFactors {
+        messaging: MessagingFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        message_brokers = ["events"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    let client = state.messaging.connect("events".into()).await?;
+    let messages = vec![
+        state.messaging.new(b"hello".to_vec()).await?,
+        state.messaging.new(b"hello".to_vec()).await?,
+    ];
+    state
+        .messaging
+        .send_batch(
+            Resource::new_borrow(client.rep()),
+            "orders".into(),
+            messages,
+        )
+        .await?;
+
+    // The mock broker cannot defer delivery.
+    let message = state.messaging.new(b"hello".to_vec()).await?;
+    let result = state
+        .messaging
+        .schedule(client, "orders".into(), message, 1_700_000_000_000)
+        .await;
+    assert!(matches!(result, Err(Error::Other(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_broker_is_not_defined() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        messaging: MessagingFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        message_brokers = ["events"]
+    });
+    let Err(err) = env
+        .runtime_config(RuntimeConfig::default())?
+        .build_instance_state()
+        .await
+    else {
+        bail!("expected instance build to fail but it didn't");
+    };
+
+    assert!(err
+        .to_string()
+        .contains(r#"unknown message_brokers label "events""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn connect_fails_when_broker_is_not_allowed() -> anyhow::Result<()> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_broker("events".into(), Arc::new(MockBroker));
+    let env = TestEnvironment::new(TestFactors {
+        messaging: MessagingFactor::new(),
+    });
+    let mut state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    assert!(state.messaging.allowed_brokers().is_empty());
+    assert!(matches!(
+        state.messaging.connect("events".into()).await,
+        Err(Error::PermissionDenied(_))
+    ));
+    Ok(())
+}
+
+struct MockBroker;
+
+#[async_trait]
+impl MessageBroker for MockBroker {
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error> {
+        assert_eq!(topic, "orders");
+        assert_eq!(message.data, b"hello");
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        _topic: &str,
+        _message: Message,
+        _options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        Err(Error::Other("not implemented".into()))
+    }
+}
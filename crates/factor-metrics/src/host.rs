@@ -0,0 +1,68 @@
+use spin_telemetry::metrics::{record_guest_metric, GuestMetricError, GuestMetricKind};
+use spin_world::spin::metrics::metrics::{self, Error, Label};
+use tracing::{instrument, Level};
+
+pub struct InstanceState {
+    component_id: String,
+}
+
+impl InstanceState {
+    pub(crate) fn new(component_id: String) -> Self {
+        Self { component_id }
+    }
+
+    fn record(
+        &self,
+        kind: GuestMetricKind,
+        name: &str,
+        value: f64,
+        labels: Vec<Label>,
+    ) -> Result<(), Error> {
+        let labels = labels
+            .into_iter()
+            .map(|Label { key, value }| (key, value))
+            .collect::<Vec<_>>();
+        record_guest_metric(&self.component_id, kind, name, value, &labels).map_err(|e| match e {
+            GuestMetricError::InvalidName => Error::InvalidName,
+            GuestMetricError::InvalidValue => Error::InvalidValue,
+            GuestMetricError::KindMismatch => Error::KindMismatch,
+            GuestMetricError::TooManyMetrics => Error::TooManyMetrics,
+        })
+    }
+}
+
+impl metrics::Host for InstanceState {
+    #[instrument(name = "spin_metrics.increment_counter", skip(self, labels), err(level = Level::INFO))]
+    async fn increment_counter(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Vec<Label>,
+    ) -> Result<(), Error> {
+        self.record(GuestMetricKind::Counter, &name, value, labels)
+    }
+
+    #[instrument(name = "spin_metrics.set_gauge", skip(self, labels), err(level = Level::INFO))]
+    async fn set_gauge(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Vec<Label>,
+    ) -> Result<(), Error> {
+        self.record(GuestMetricKind::Gauge, &name, value, labels)
+    }
+
+    #[instrument(name = "spin_metrics.record_histogram", skip(self, labels), err(level = Level::INFO))]
+    async fn record_histogram(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Vec<Label>,
+    ) -> Result<(), Error> {
+        self.record(GuestMetricKind::Histogram, &name, value, labels)
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,50 @@
+mod host;
+
+use spin_factors::{
+    anyhow, ConfigureAppContext, Factor, InitContext, PrepareContext, RuntimeFactors,
+    SelfInstanceBuilder,
+};
+
+pub use host::InstanceState;
+
+/// A factor that lets guests record application metrics (counters, gauges
+/// and histograms), which the runtime aggregates and exports alongside its
+/// own metrics with the component ID attached.
+#[derive(Default)]
+pub struct MetricsFactor {
+    _priv: (),
+}
+
+impl MetricsFactor {
+    /// Create a new MetricsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for MetricsFactor {
+    type RuntimeConfig = ();
+    type AppState = ();
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::metrics::metrics::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(())
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceState> {
+        Ok(InstanceState::new(ctx.app_component().id().to_owned()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
@@ -0,0 +1,92 @@
+use spin_factor_metrics::MetricsFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::metrics::metrics::{Error, Host as _, Label};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    metrics: MetricsFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        metrics: MetricsFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+fn label(key: &str, value: &str) -> Label {
+    Label {
+        key: key.into(),
+        value: value.into(),
+    }
+}
+
+#[tokio::test]
+async fn metrics_are_recorded() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    state
+        .metrics
+        .increment_counter(
+            "factor_test.orders".into(),
+            1.0,
+            vec![label("status", "ok")],
+        )
+        .await?;
+    state
+        .metrics
+        .set_gauge("factor_test.queue_depth".into(), 12.0, vec![])
+        .await?;
+    state
+        .metrics
+        .record_histogram("factor_test.latency".into(), 0.25, vec![])
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn metrics_keep_their_kind() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    state
+        .metrics
+        .increment_counter("factor_test.retries".into(), 1.0, vec![])
+        .await?;
+    assert!(matches!(
+        state
+            .metrics
+            .set_gauge("factor_test.retries".into(), 1.0, vec![])
+            .await,
+        Err(Error::KindMismatch)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reserved_names_are_rejected() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    assert!(matches!(
+        state
+            .metrics
+            .increment_counter("spin.request_count".into(), 1.0, vec![])
+            .await,
+        Err(Error::InvalidName)
+    ));
+    assert!(matches!(
+        state
+            .metrics
+            .increment_counter(
+                "factor_test.requests".into(),
+                1.0,
+                vec![label("component_id", "other")],
+            )
+            .await,
+        Err(Error::InvalidName)
+    ));
+    Ok(())
+}
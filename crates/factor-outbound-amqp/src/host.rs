@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_networking::OutboundAllowedHosts;
+use spin_world::spin::amqp::amqp::{self as v3, Connection, Error, Properties};
+use tracing::{instrument, Level};
+
+use crate::ClientCreator;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    connections: spin_resource_table::Table<Arc<dyn AmqpClient>>,
+    create_client: Arc<dyn ClientCreator>,
+}
+
+impl InstanceState {
+    pub fn new(allowed_hosts: OutboundAllowedHosts, create_client: Arc<dyn ClientCreator>) -> Self {
+        Self {
+            allowed_hosts,
+            create_client,
+            connections: spin_resource_table::Table::new(1024),
+        }
+    }
+}
+
+#[async_trait]
+pub trait AmqpClient: Send + Sync {
+    async fn publish(
+        &self,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error>;
+}
+
+impl InstanceState {
+    async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        self.allowed_hosts.check_url(address, "amqp").await
+    }
+
+    async fn establish_connection(
+        &mut self,
+        address: String,
+    ) -> Result<Resource<Connection>, Error> {
+        let client = self.create_client.create(address).await?;
+        self.connections
+            .push(client)
+            .map(Resource::new_own)
+            .map_err(|_| Error::TooManyConnections)
+    }
+
+    async fn get_conn(&self, connection: Resource<Connection>) -> Result<&dyn AmqpClient, Error> {
+        self.connections
+            .get(connection.rep())
+            .ok_or(Error::Other(
+                "could not find connection for resource".into(),
+            ))
+            .map(|c| c.as_ref())
+    }
+}
+
+impl v3::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl v3::HostConnection for InstanceState {
+    #[instrument(name = "spin_outbound_amqp.open_connection", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, address: String) -> Result<Resource<Connection>, Error> {
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+        {
+            return Err(Error::ConnectionFailed(
+                "address is not permitted".to_string(),
+            ));
+        }
+        self.establish_connection(address).await
+    }
+
+    /// Publish a message to the AMQP broker and wait for the broker to confirm it.
+    #[instrument(name = "spin_outbound_amqp.publish", skip(self, connection, payload, properties), err(level = Level::INFO),
+        fields(otel.kind = "producer", otel.name = format!("{} publish", exchange), messaging.operation = "publish",
+        messaging.system = "rabbitmq", messaging.rabbitmq.destination.routing_key = routing_key))]
+    async fn publish(
+        &mut self,
+        connection: Resource<Connection>,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error> {
+        let conn = self.get_conn(connection).await.map_err(other_error)?;
+
+        conn.publish(exchange, routing_key, payload, properties)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+pub fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
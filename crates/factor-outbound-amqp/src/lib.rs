@@ -0,0 +1,168 @@
+mod host;
+
+use std::sync::Arc;
+
+use host::other_error;
+use host::InstanceState;
+use lapin::options::{BasicPublishOptions, ConfirmSelectOptions};
+use lapin::publisher_confirm::Confirmation;
+use lapin::types::{AMQPValue, FieldTable, LongString, ShortString};
+use lapin::{BasicProperties, Channel, Connection, ConnectionProperties};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::amqp::amqp::{Error, Properties};
+
+pub use host::AmqpClient;
+
+pub struct OutboundAmqpFactor {
+    create_client: Arc<dyn ClientCreator>,
+}
+
+impl OutboundAmqpFactor {
+    pub fn new(create_client: Arc<dyn ClientCreator>) -> Self {
+        Self { create_client }
+    }
+}
+
+impl Factor for OutboundAmqpFactor {
+    type RuntimeConfig = ();
+    type AppState = ();
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::amqp::amqp::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(())
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_hosts = ctx
+            .instance_builder::<OutboundNetworkingFactor>()?
+            .allowed_hosts();
+        Ok(InstanceState::new(
+            allowed_hosts,
+            self.create_client.clone(),
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+// This is a concrete implementation of the AMQP client using lapin.
+pub struct NetworkedAmqpClient {
+    // Kept so the connection stays open for as long as the channel is in use.
+    _connection: Connection,
+    channel: Channel,
+}
+
+impl NetworkedAmqpClient {
+    /// Create a [`ClientCreator`] that creates a [`NetworkedAmqpClient`].
+    pub fn creator() -> Arc<dyn ClientCreator> {
+        Arc::new(NetworkedClientCreator)
+    }
+
+    /// Connect to the broker at the given address and open a channel with publisher confirms enabled.
+    pub async fn connect(address: &str) -> Result<Self, Error> {
+        let connection = Connection::connect(address, ConnectionProperties::default())
+            .await
+            .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
+        let channel = connection
+            .create_channel()
+            .await
+            .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
+        Ok(Self {
+            _connection: connection,
+            channel,
+        })
+    }
+}
+
+#[async_trait]
+impl AmqpClient for NetworkedAmqpClient {
+    async fn publish(
+        &self,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error> {
+        let confirm = self
+            .channel
+            .basic_publish(
+                &exchange,
+                &routing_key,
+                BasicPublishOptions::default(),
+                &payload,
+                to_basic_properties(properties),
+            )
+            .await
+            .map_err(other_error)?;
+        // Wait for the broker to confirm the message.
+        match confirm.await.map_err(other_error)? {
+            Confirmation::Ack(_) | Confirmation::NotRequested => Ok(()),
+            Confirmation::Nack(_) => Err(Error::Rejected),
+        }
+    }
+}
+
+fn to_basic_properties(properties: Properties) -> BasicProperties {
+    let mut basic = BasicProperties::default();
+    if let Some(content_type) = properties.content_type {
+        basic = basic.with_content_type(ShortString::from(content_type));
+    }
+    if let Some(message_id) = properties.message_id {
+        basic = basic.with_message_id(ShortString::from(message_id));
+    }
+    if let Some(correlation_id) = properties.correlation_id {
+        basic = basic.with_correlation_id(ShortString::from(correlation_id));
+    }
+    if let Some(reply_to) = properties.reply_to {
+        basic = basic.with_reply_to(ShortString::from(reply_to));
+    }
+    if properties.persistent {
+        // Delivery mode 2 marks the message as persistent.
+        basic = basic.with_delivery_mode(2);
+    }
+    if !properties.headers.is_empty() {
+        let mut headers = FieldTable::default();
+        for (name, value) in properties.headers {
+            headers.insert(
+                ShortString::from(name),
+                AMQPValue::LongString(LongString::from(value)),
+            );
+        }
+        basic = basic.with_headers(headers);
+    }
+    basic
+}
+
+/// A trait for creating AMQP clients.
+#[async_trait]
+pub trait ClientCreator: Send + Sync {
+    async fn create(&self, address: String) -> Result<Arc<dyn AmqpClient>, Error>;
+}
+
+struct NetworkedClientCreator;
+
+#[async_trait]
+impl ClientCreator for NetworkedClientCreator {
+    async fn create(&self, address: String) -> Result<Arc<dyn AmqpClient>, Error> {
+        Ok(Arc::new(NetworkedAmqpClient::connect(&address).await?))
+    }
+}
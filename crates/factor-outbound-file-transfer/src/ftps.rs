@@ -0,0 +1,152 @@
+use std::{str::FromStr, time::UNIX_EPOCH};
+
+use spin_core::async_trait;
+use spin_factor_outbound_networking::TlsClientConfig;
+use spin_world::spin::file_transfer::file_transfer::{Entry, Error};
+use suppaftp::{
+    list, types::FtpError, AsyncRustlsConnector, AsyncRustlsFtpStream, DataStream, Status,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::{connection_failed, read_chunk, Download, FileServer, Upload};
+
+/// An FTP server which supports explicit TLS (`AUTH TLS`).
+pub struct FtpsServer {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+}
+
+impl FtpsServer {
+    async fn connect(&self, tls: &TlsClientConfig) -> Result<AsyncRustlsFtpStream, Error> {
+        let stream = AsyncRustlsFtpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(connection_failed)?;
+        let connector = AsyncRustlsConnector::from(tokio_rustls::TlsConnector::from(tls.inner()));
+        let mut stream = stream
+            .into_secure(connector, &self.host)
+            .await
+            .map_err(connection_failed)?;
+        stream
+            .login(&self.username, &self.password)
+            .await
+            .map_err(connection_failed)?;
+        Ok(stream)
+    }
+}
+
+#[async_trait]
+impl FileServer for FtpsServer {
+    async fn list(&self, path: &str, tls: &TlsClientConfig) -> Result<Vec<Entry>, Error> {
+        let mut stream = self.connect(tls).await?;
+        let lines = stream
+            .list(Some(path))
+            .await
+            .map_err(|e| ftp_error(path, e))?;
+        let _ = stream.quit().await;
+        Ok(lines
+            .iter()
+            .filter_map(|line| list::File::from_str(line).ok())
+            .map(|file| Entry {
+                name: file.name().to_owned(),
+                is_directory: file.is_directory(),
+                size: Some(file.size() as u64),
+                modified: file
+                    .modified()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|d| d.as_secs()),
+            })
+            .collect())
+    }
+
+    async fn get(&self, path: &str, tls: &TlsClientConfig) -> Result<Box<dyn Download>, Error> {
+        let mut stream = self.connect(tls).await?;
+        let data = stream
+            .retr_as_stream(path)
+            .await
+            .map_err(|e| ftp_error(path, e))?;
+        Ok(Box::new(FtpsTransfer {
+            stream,
+            data: Some(data),
+        }))
+    }
+
+    async fn put(&self, path: &str, tls: &TlsClientConfig) -> Result<Box<dyn Upload>, Error> {
+        let mut stream = self.connect(tls).await?;
+        let data = stream
+            .put_with_stream(path)
+            .await
+            .map_err(|e| ftp_error(path, e))?;
+        Ok(Box::new(FtpsTransfer {
+            stream,
+            data: Some(data),
+        }))
+    }
+}
+
+type FtpsDataStream = DataStream<tokio_rustls::client::TlsStream<tokio::net::TcpStream>>;
+
+/// A file being transferred over a connection's data stream.
+struct FtpsTransfer {
+    stream: AsyncRustlsFtpStream,
+    /// The data stream, until the transfer has completed.
+    data: Option<FtpsDataStream>,
+}
+
+impl FtpsTransfer {
+    fn data(&mut self) -> Result<&mut FtpsDataStream, Error> {
+        self.data
+            .as_mut()
+            .ok_or_else(|| Error::Other("transfer has already completed".into()))
+    }
+}
+
+#[async_trait]
+impl Download for FtpsTransfer {
+    async fn read(&mut self, max: usize) -> Result<Option<Vec<u8>>, Error> {
+        let Some(data) = self.data.as_mut() else {
+            return Ok(None);
+        };
+        let chunk = read_chunk(data, max).await?;
+        if chunk.is_none() {
+            let data = self.data.take().unwrap();
+            self.stream
+                .finalize_retr_stream(data)
+                .await
+                .map_err(|e| Error::Other(e.to_string()))?;
+        }
+        Ok(chunk)
+    }
+}
+
+#[async_trait]
+impl Upload for FtpsTransfer {
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.data()?
+            .write_all(data)
+            .await
+            .map_err(|e| Error::Other(format!("failed to write file: {e}")))
+    }
+
+    async fn finish(&mut self) -> Result<(), Error> {
+        let data = self
+            .data
+            .take()
+            .ok_or_else(|| Error::Other("transfer has already completed".into()))?;
+        self.stream
+            .finalize_put_stream(data)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+}
+
+fn ftp_error(path: &str, err: FtpError) -> Error {
+    match err {
+        FtpError::UnexpectedResponse(response) if response.status == Status::FileUnavailable => {
+            Error::NotFound(path.to_owned())
+        }
+        err => Error::Other(err.to_string()),
+    }
+}
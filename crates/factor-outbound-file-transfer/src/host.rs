@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_core::wasmtime::component::Resource;
+use spin_factor_outbound_networking::{
+    ComponentTlsClientConfigs, OutboundAllowedHosts, TlsClientConfig,
+};
+use spin_world::spin::file_transfer::file_transfer::{self as v3, Entry, Error};
+use tracing::{instrument, Level};
+
+use crate::{runtime_config::RuntimeConfig, Download, FileServer, Upload};
+
+/// The most bytes returned by a single `read` of a download.
+const MAX_READ: u32 = 1024 * 1024;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    component_tls_configs: ComponentTlsClientConfigs,
+    config: Option<Arc<RuntimeConfig>>,
+    downloads: spin_resource_table::Table<Box<dyn Download>>,
+    uploads: spin_resource_table::Table<Box<dyn Upload>>,
+}
+
+impl InstanceState {
+    pub fn new(
+        allowed_hosts: OutboundAllowedHosts,
+        component_tls_configs: ComponentTlsClientConfigs,
+        config: Option<Arc<RuntimeConfig>>,
+    ) -> Self {
+        Self {
+            allowed_hosts,
+            component_tls_configs,
+            config,
+            downloads: spin_resource_table::Table::new(1024),
+            uploads: spin_resource_table::Table::new(1024),
+        }
+    }
+
+    /// Looks up the server with the given label, checking the component may
+    /// use it, and resolves `path` against the server's root.
+    async fn server(
+        &self,
+        label: &str,
+        path: &str,
+    ) -> Result<(Arc<dyn FileServer>, String, TlsClientConfig), Error> {
+        let server = self
+            .config
+            .as_ref()
+            .and_then(|config| config.servers.get(label))
+            .ok_or_else(|| Error::NoSuchServer(label.to_owned()))?;
+        let url = url::Url::parse(&server.url).map_err(|e| Error::Other(e.to_string()))?;
+        if !self
+            .allowed_hosts
+            .check_url(&server.url, url.scheme())
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+        {
+            return Err(Error::ServerNotAllowed(label.to_owned()));
+        }
+        let path = server.resolve_path(path)?;
+        let tls = self
+            .component_tls_configs
+            .get_client_config(url.host_str().unwrap_or_default())
+            .clone();
+        Ok((server.server.clone(), path, tls))
+    }
+
+    fn get_upload(&mut self, upload: &Resource<v3::Upload>) -> Result<&mut dyn Upload, Error> {
+        self.uploads
+            .get_mut(upload.rep())
+            .ok_or_else(|| Error::Other("could not find upload for resource".into()))
+            .map(|u| u.as_mut())
+    }
+}
+
+impl v3::Host for InstanceState {
+    #[instrument(name = "spin_outbound_file_transfer.list", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn list(&mut self, server: String, path: String) -> Result<Vec<Entry>, Error> {
+        let (server, path, tls) = self.server(&server, &path).await?;
+        server.list(&path, &tls).await
+    }
+
+    #[instrument(name = "spin_outbound_file_transfer.get", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&mut self, server: String, path: String) -> Result<Resource<v3::Download>, Error> {
+        let (server, path, tls) = self.server(&server, &path).await?;
+        let download = server.get(&path, &tls).await?;
+        self.downloads
+            .push(download)
+            .map(Resource::new_own)
+            .map_err(|_| Error::Other("too many open downloads".into()))
+    }
+
+    #[instrument(name = "spin_outbound_file_transfer.put", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn put(&mut self, server: String, path: String) -> Result<Resource<v3::Upload>, Error> {
+        let (server, path, tls) = self.server(&server, &path).await?;
+        let upload = server.put(&path, &tls).await?;
+        self.uploads
+            .push(upload)
+            .map(Resource::new_own)
+            .map_err(|_| Error::Other("too many open uploads".into()))
+    }
+
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl v3::HostDownload for InstanceState {
+    async fn read(
+        &mut self,
+        download: Resource<v3::Download>,
+        max: u32,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.downloads
+            .get_mut(download.rep())
+            .ok_or_else(|| Error::Other("could not find download for resource".into()))?
+            .read(max.min(MAX_READ) as usize)
+            .await
+    }
+
+    async fn drop(&mut self, download: Resource<v3::Download>) -> anyhow::Result<()> {
+        self.downloads.remove(download.rep());
+        Ok(())
+    }
+}
+
+impl v3::HostUpload for InstanceState {
+    async fn write(&mut self, upload: Resource<v3::Upload>, data: Vec<u8>) -> Result<(), Error> {
+        self.get_upload(&upload)?.write(&data).await
+    }
+
+    async fn finish(&mut self, upload: Resource<v3::Upload>) -> Result<(), Error> {
+        self.get_upload(&upload)?.finish().await
+    }
+
+    async fn drop(&mut self, upload: Resource<v3::Upload>) -> anyhow::Result<()> {
+        self.uploads.remove(upload.rep());
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+mod ftps;
+mod host;
+pub mod runtime_config;
+mod sftp;
+
+use std::sync::Arc;
+
+use host::InstanceState;
+use runtime_config::RuntimeConfig;
+use spin_core::async_trait;
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, TlsClientConfig};
+use spin_factors::{
+    ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::file_transfer::file_transfer::{Entry, Error};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub use ftps::FtpsServer;
+pub use sftp::{SftpAuth, SftpServer};
+
+/// A factor that lets guests exchange files with SFTP and FTPS servers
+/// configured in runtime config.
+#[derive(Default)]
+pub struct OutboundFileTransferFactor {
+    _priv: (),
+}
+
+impl OutboundFileTransferFactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Factor for OutboundFileTransferFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = Option<Arc<RuntimeConfig>>;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::file_transfer::file_transfer::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(ctx.take_runtime_config().map(Arc::new))
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        Ok(InstanceState::new(
+            networking.allowed_hosts(),
+            networking.component_tls_configs(),
+            ctx.app_state().clone(),
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// A server files can be exchanged with.
+///
+/// Paths are absolute paths on the server, already confined to the server's
+/// configured root.
+#[async_trait]
+pub trait FileServer: Send + Sync {
+    /// Lists the directory at `path`.
+    async fn list(&self, path: &str, tls: &TlsClientConfig) -> Result<Vec<Entry>, Error>;
+
+    /// Starts downloading the file at `path`.
+    async fn get(&self, path: &str, tls: &TlsClientConfig) -> Result<Box<dyn Download>, Error>;
+
+    /// Starts uploading a file to `path`.
+    async fn put(&self, path: &str, tls: &TlsClientConfig) -> Result<Box<dyn Upload>, Error>;
+}
+
+/// The contents of a file being downloaded.
+#[async_trait]
+pub trait Download: Send {
+    /// Reads up to `max` bytes, or `None` at the end of the file.
+    async fn read(&mut self, max: usize) -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// The contents of a file being uploaded.
+#[async_trait]
+pub trait Upload: Send {
+    /// Writes `data` to the end of the file.
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// Completes the upload.
+    async fn finish(&mut self) -> Result<(), Error>;
+}
+
+/// Reads up to `max` bytes from `reader`, or `None` at the end of the file.
+async fn read_chunk(
+    reader: &mut (impl AsyncRead + Unpin),
+    max: usize,
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut buf = vec![0; max];
+    let n = reader
+        .read(&mut buf)
+        .await
+        .map_err(|e| Error::Other(format!("failed to read file: {e}")))?;
+    if n == 0 && max > 0 {
+        return Ok(None);
+    }
+    buf.truncate(n);
+    Ok(Some(buf))
+}
+
+fn connection_failed(err: impl std::fmt::Display) -> Error {
+    Error::ConnectionFailed(err.to_string())
+}
@@ -0,0 +1,70 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_world::spin::file_transfer::file_transfer::Error;
+
+use crate::FileServer;
+
+/// Runtime configuration for outbound file transfer.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    /// The servers guests may use, by label
+    pub servers: HashMap<String, ServerConfig>,
+}
+
+/// A server guests may exchange files with.
+pub struct ServerConfig {
+    /// The server's URL, e.g. `sftp://files.example.com:22`, which a
+    /// component's `allowed_outbound_hosts` must permit
+    pub url: String,
+    /// The directory on the server guests' paths are relative to
+    pub root: String,
+    pub server: Arc<dyn FileServer>,
+}
+
+impl ServerConfig {
+    /// Resolves a guest's path against the server's root.
+    ///
+    /// Paths may not contain `..`, so guests can't reach outside the root.
+    pub fn resolve_path(&self, path: &str) -> Result<String, Error> {
+        resolve_path(&self.root, path).ok_or_else(|| Error::InvalidPath(path.to_owned()))
+    }
+}
+
+fn resolve_path(root: &str, path: &str) -> Option<String> {
+    let mut resolved = root.trim_end_matches('/').to_owned();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            segment if segment.contains('\0') => return None,
+            segment => {
+                resolved.push('/');
+                resolved.push_str(segment);
+            }
+        }
+    }
+    if resolved.is_empty() {
+        resolved.push('/');
+    }
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_confined_to_root() {
+        let resolve = |path| resolve_path("/outbox/", path);
+        assert_eq!(resolve("orders.csv").unwrap(), "/outbox/orders.csv");
+        assert_eq!(resolve("/2024/./a.csv").unwrap(), "/outbox/2024/a.csv");
+        assert_eq!(resolve("").unwrap(), "/outbox");
+        assert!(resolve("../etc/passwd").is_none());
+        assert!(resolve("a/../../b").is_none());
+
+        assert_eq!(resolve_path("/", "").unwrap(), "/");
+        assert_eq!(resolve_path("/", "a/b").unwrap(), "/a/b");
+    }
+}
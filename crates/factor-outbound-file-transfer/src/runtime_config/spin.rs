@@ -0,0 +1,169 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{RuntimeConfig, ServerConfig};
+use crate::{FileServer, FtpsServer, SftpAuth, SftpServer};
+
+/// Get the runtime configuration for outbound file transfer from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [outbound_file_transfer.partner]
+/// url = "sftp://files.example.com"
+/// root = "/outbox"
+/// username = "spin"
+/// private_key_path = "/etc/spin/id_ed25519"
+/// host_key_fingerprint = "SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s"
+///
+/// [outbound_file_transfer.legacy]
+/// url = "ftps://ftp.example.com"
+/// username = "spin"
+/// password = "secret"
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("outbound_file_transfer") else {
+        return Ok(None);
+    };
+    let servers: HashMap<String, ServerToml> = value
+        .clone()
+        .try_into()
+        .context("failed to parse [outbound_file_transfer] table")?;
+    let servers = servers
+        .into_iter()
+        .map(|(label, toml)| {
+            let server = server_from_toml(toml)
+                .with_context(|| format!("invalid [outbound_file_transfer.{label}] table"))?;
+            Ok((label, server))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Some(RuntimeConfig { servers }))
+}
+
+fn server_from_toml(toml: ServerToml) -> anyhow::Result<ServerConfig> {
+    let url = url::Url::parse(&toml.url).context("invalid 'url'")?;
+    let host = url
+        .host_str()
+        .context("'url' must include a host")?
+        .to_owned();
+    anyhow::ensure!(
+        url.path().is_empty() || url.path() == "/",
+        "'url' must not include a path; use 'root' instead"
+    );
+    let server: Arc<dyn FileServer> = match url.scheme() {
+        "sftp" => {
+            let auth = match (toml.password, toml.private_key_path) {
+                (Some(password), None) => SftpAuth::Password(password),
+                (None, Some(path)) => SftpAuth::PrivateKey {
+                    path,
+                    passphrase: toml.private_key_passphrase,
+                },
+                _ => anyhow::bail!("exactly one of 'password' and 'private_key_path' must be set"),
+            };
+            let host_key_fingerprint = toml
+                .host_key_fingerprint
+                .context("'host_key_fingerprint' must be set for SFTP servers")?;
+            Arc::new(SftpServer {
+                host,
+                port: url.port().unwrap_or(22),
+                username: toml.username,
+                auth,
+                host_key_fingerprint,
+            })
+        }
+        "ftps" => {
+            anyhow::ensure!(
+                toml.private_key_path.is_none() && toml.host_key_fingerprint.is_none(),
+                "'private_key_path' and 'host_key_fingerprint' are only supported for SFTP servers"
+            );
+            let password = toml
+                .password
+                .context("'password' must be set for FTPS servers")?;
+            Arc::new(FtpsServer {
+                host,
+                port: url.port().unwrap_or(21),
+                username: toml.username,
+                password,
+            })
+        }
+        scheme => anyhow::bail!("unsupported scheme '{scheme}'; expected 'sftp' or 'ftps'"),
+    };
+    anyhow::ensure!(
+        toml.root.starts_with('/'),
+        "'root' must be an absolute path"
+    );
+    Ok(ServerConfig {
+        url: toml.url,
+        root: toml.root,
+        server,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServerToml {
+    url: String,
+    #[serde(default = "default_root")]
+    root: String,
+    username: String,
+    password: Option<String>,
+    private_key_path: Option<PathBuf>,
+    private_key_passphrase: Option<String>,
+    host_key_fingerprint: Option<String>,
+}
+
+fn default_root() -> String {
+    "/".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_servers() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_file_transfer.partner]
+            url = "sftp://files.example.com:2222"
+            root = "/outbox"
+            username = "spin"
+            private_key_path = "/etc/spin/id_ed25519"
+            host_key_fingerprint = "SHA256:abc"
+
+            [outbound_file_transfer.legacy]
+            url = "ftps://ftp.example.com"
+            username = "spin"
+            password = "secret"
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert_eq!(config.servers.len(), 2);
+        assert_eq!(config.servers["partner"].root, "/outbox");
+        assert_eq!(config.servers["legacy"].url, "ftps://ftp.example.com");
+        assert_eq!(config.servers["legacy"].root, "/");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_sftp_without_host_key_fingerprint() {
+        let table: toml::Table = toml::toml! {
+            [outbound_file_transfer.partner]
+            url = "sftp://files.example.com"
+            username = "spin"
+            password = "secret"
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_scheme() {
+        let table: toml::Table = toml::toml! {
+            [outbound_file_transfer.plain]
+            url = "ftp://ftp.example.com"
+            username = "spin"
+            password = "secret"
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
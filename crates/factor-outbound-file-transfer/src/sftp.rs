@@ -0,0 +1,202 @@
+use std::{path::PathBuf, sync::Arc};
+
+use russh::client::{self, Handle};
+use russh_keys::key::PublicKey;
+use russh_sftp::{
+    client::{error::Error as SftpError, fs::File, SftpSession},
+    protocol::StatusCode,
+};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::TlsClientConfig;
+use spin_world::spin::file_transfer::file_transfer::{Entry, Error};
+use tokio::io::AsyncWriteExt;
+
+use crate::{connection_failed, read_chunk, Download, FileServer, Upload};
+
+/// An SFTP server.
+pub struct SftpServer {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub auth: SftpAuth,
+    /// The SHA-256 fingerprint of the server's host key, as printed by
+    /// `ssh-keygen -l`, e.g. `SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s`
+    pub host_key_fingerprint: String,
+}
+
+/// How to authenticate to an SFTP server.
+pub enum SftpAuth {
+    Password(String),
+    PrivateKey {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+impl SftpServer {
+    async fn connect(&self) -> Result<Connection, Error> {
+        let handler = HostKeyCheck {
+            fingerprint: normalize_fingerprint(&self.host_key_fingerprint).to_owned(),
+        };
+        let mut handle = client::connect(
+            Arc::new(client::Config::default()),
+            (self.host.as_str(), self.port),
+            handler,
+        )
+        .await
+        .map_err(connection_failed)?;
+
+        let authenticated = match &self.auth {
+            SftpAuth::Password(password) => handle
+                .authenticate_password(&self.username, password)
+                .await
+                .map_err(connection_failed)?,
+            SftpAuth::PrivateKey { path, passphrase } => {
+                let key = russh_keys::load_secret_key(path, passphrase.as_deref())
+                    .map_err(connection_failed)?;
+                handle
+                    .authenticate_publickey(&self.username, Arc::new(key))
+                    .await
+                    .map_err(connection_failed)?
+            }
+        };
+        if !authenticated {
+            return Err(Error::ConnectionFailed(format!(
+                "authentication failed for user {}",
+                self.username
+            )));
+        }
+
+        let channel = handle
+            .channel_open_session()
+            .await
+            .map_err(connection_failed)?;
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .map_err(connection_failed)?;
+        let session = SftpSession::new(channel.into_stream())
+            .await
+            .map_err(connection_failed)?;
+        Ok(Connection {
+            _handle: handle,
+            session,
+        })
+    }
+}
+
+#[async_trait]
+impl FileServer for SftpServer {
+    async fn list(&self, path: &str, _tls: &TlsClientConfig) -> Result<Vec<Entry>, Error> {
+        let conn = self.connect().await?;
+        let entries = conn
+            .session
+            .read_dir(path)
+            .await
+            .map_err(|e| sftp_error(path, e))?;
+        Ok(entries
+            .map(|entry| {
+                let metadata = entry.metadata();
+                Entry {
+                    name: entry.file_name(),
+                    is_directory: metadata.is_dir(),
+                    size: metadata.size,
+                    modified: metadata.mtime.map(u64::from),
+                }
+            })
+            .collect())
+    }
+
+    async fn get(&self, path: &str, _tls: &TlsClientConfig) -> Result<Box<dyn Download>, Error> {
+        let conn = self.connect().await?;
+        let file = conn
+            .session
+            .open(path)
+            .await
+            .map_err(|e| sftp_error(path, e))?;
+        Ok(Box::new(SftpFile { _conn: conn, file }))
+    }
+
+    async fn put(&self, path: &str, _tls: &TlsClientConfig) -> Result<Box<dyn Upload>, Error> {
+        let conn = self.connect().await?;
+        let file = conn
+            .session
+            .create(path)
+            .await
+            .map_err(|e| sftp_error(path, e))?;
+        Ok(Box::new(SftpFile { _conn: conn, file }))
+    }
+}
+
+/// An authenticated SFTP session, which is closed when dropped.
+struct Connection {
+    _handle: Handle<HostKeyCheck>,
+    session: SftpSession,
+}
+
+struct SftpFile {
+    _conn: Connection,
+    file: File,
+}
+
+#[async_trait]
+impl Download for SftpFile {
+    async fn read(&mut self, max: usize) -> Result<Option<Vec<u8>>, Error> {
+        read_chunk(&mut self.file, max).await
+    }
+}
+
+#[async_trait]
+impl Upload for SftpFile {
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.file
+            .write_all(data)
+            .await
+            .map_err(|e| Error::Other(format!("failed to write file: {e}")))
+    }
+
+    async fn finish(&mut self) -> Result<(), Error> {
+        self.file
+            .shutdown()
+            .await
+            .map_err(|e| Error::Other(format!("failed to close file: {e}")))
+    }
+}
+
+/// Accepts only the server host key with the configured fingerprint.
+struct HostKeyCheck {
+    fingerprint: String,
+}
+
+#[async_trait]
+impl client::Handler for HostKeyCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &PublicKey) -> Result<bool, Self::Error> {
+        let fingerprint = key.fingerprint();
+        let matches = normalize_fingerprint(&fingerprint) == self.fingerprint;
+        if !matches {
+            tracing::warn!(
+                "SFTP server presented a host key with unexpected fingerprint {fingerprint}"
+            );
+        }
+        Ok(matches)
+    }
+}
+
+/// Strips the algorithm prefix and base64 padding from a fingerprint.
+fn normalize_fingerprint(fingerprint: &str) -> &str {
+    fingerprint
+        .strip_prefix("SHA256:")
+        .unwrap_or(fingerprint)
+        .trim_end_matches('=')
+}
+
+fn sftp_error(path: &str, err: SftpError) -> Error {
+    match err {
+        SftpError::Status(status) if status.status_code == StatusCode::NoSuchFile => {
+            Error::NotFound(path.to_owned())
+        }
+        err => Error::Other(err.to_string()),
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::bail;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_file_transfer::{
+    runtime_config::{RuntimeConfig, ServerConfig},
+    Download, FileServer, OutboundFileTransferFactor, Upload,
+};
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, TlsClientConfig};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::file_transfer::file_transfer::{
+    Entry, Error, Host, HostDownload, HostUpload,
+};
+
+type Files = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+/// Keeps files in memory, by absolute path.
+#[derive(Default)]
+struct MockFileServer {
+    files: Files,
+}
+
+#[async_trait]
+impl FileServer for MockFileServer {
+    async fn list(&self, path: &str, _tls: &TlsClientConfig) -> Result<Vec<Entry>, Error> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(file, contents)| {
+                Some(Entry {
+                    name: file.strip_prefix(&prefix)?.to_owned(),
+                    is_directory: false,
+                    size: Some(contents.len() as u64),
+                    modified: None,
+                })
+            })
+            .collect())
+    }
+
+    async fn get(&self, path: &str, _tls: &TlsClientConfig) -> Result<Box<dyn Download>, Error> {
+        let contents = self
+            .files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(path.to_owned()))?;
+        Ok(Box::new(MockDownload(contents)))
+    }
+
+    async fn put(&self, path: &str, _tls: &TlsClientConfig) -> Result<Box<dyn Upload>, Error> {
+        Ok(Box::new(MockUpload {
+            files: self.files.clone(),
+            path: path.to_owned(),
+            contents: vec![],
+        }))
+    }
+}
+
+struct MockDownload(Vec<u8>);
+
+#[async_trait]
+impl Download for MockDownload {
+    async fn read(&mut self, max: usize) -> Result<Option<Vec<u8>>, Error> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let rest = self.0.split_off(max.min(self.0.len()));
+        Ok(Some(std::mem::replace(&mut self.0, rest)))
+    }
+}
+
+struct MockUpload {
+    files: Files,
+    path: String,
+    contents: Vec<u8>,
+}
+
+#[async_trait]
+impl Upload for MockUpload {
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.contents.extend_from_slice(data);
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), Error> {
+        let contents = std::mem::take(&mut self.contents);
+        self.files
+            .lock()
+            .unwrap()
+            .insert(self.path.clone(), contents);
+        Ok(())
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    file_transfer: OutboundFileTransferFactor,
+}
+
+fn test_env(files: Files, manifest: toml::Table) -> anyhow::Result<TestEnvironment<TestFactors>> {
+    let server = ServerConfig {
+        url: "sftp://files.example.com".into(),
+        root: "/outbox".into(),
+        server: Arc::new(MockFileServer { files }),
+    };
+    TestEnvironment::new(TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        file_transfer: OutboundFileTransferFactor::new(),
+    })
+    .extend_manifest(manifest)
+    .runtime_config(TestFactorsRuntimeConfig {
+        file_transfer: Some(RuntimeConfig {
+            servers: [("partner".to_string(), server)].into(),
+        }),
+        ..Default::default()
+    })
+}
+
+fn allowed_manifest() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["sftp://files.example.com"]
+    }
+}
+
+#[tokio::test]
+async fn allowed_server_transfers_files() -> anyhow::Result<()> {
+    let files = Files::default();
+    let mut state = test_env(files.clone(), allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let upload = state
+        .file_transfer
+        .put("partner".into(), "orders.csv".into())
+        .await?;
+    let rep = upload.rep();
+    state
+        .file_transfer
+        .write(Resource::new_borrow(rep), b"id,qty\n".to_vec())
+        .await?;
+    state
+        .file_transfer
+        .write(Resource::new_borrow(rep), b"1,2\n".to_vec())
+        .await?;
+    state
+        .file_transfer
+        .finish(Resource::new_borrow(rep))
+        .await?;
+    HostUpload::drop(&mut state.file_transfer, upload).await?;
+    assert!(files.lock().unwrap().contains_key("/outbox/orders.csv"));
+
+    let entries = state
+        .file_transfer
+        .list("partner".into(), "/".into())
+        .await?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "orders.csv");
+    assert_eq!(entries[0].size, Some(11));
+
+    let download = state
+        .file_transfer
+        .get("partner".into(), "orders.csv".into())
+        .await?;
+    let rep = download.rep();
+    let mut contents = vec![];
+    while let Some(chunk) = state
+        .file_transfer
+        .read(Resource::new_borrow(rep), 4)
+        .await?
+    {
+        assert!(chunk.len() <= 4);
+        contents.extend(chunk);
+    }
+    HostDownload::drop(&mut state.file_transfer, download).await?;
+    assert_eq!(contents, b"id,qty\n1,2\n");
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_not_in_allowed_hosts_fails() -> anyhow::Result<()> {
+    let mut state = test_env(
+        Files::default(),
+        toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        },
+    )?
+    .build_instance_state()
+    .await?;
+
+    let Err(err) = state.file_transfer.list("partner".into(), "/".into()).await else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::ServerNotAllowed(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_server_fails() -> anyhow::Result<()> {
+    let mut state = test_env(Files::default(), allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let Err(err) = state.file_transfer.list("other".into(), "/".into()).await else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::NoSuchServer(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn path_outside_root_fails() -> anyhow::Result<()> {
+    let mut state = test_env(Files::default(), allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let Err(err) = state
+        .file_transfer
+        .get("partner".into(), "../etc/passwd".into())
+        .await
+    else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::InvalidPath(_)));
+    Ok(())
+}
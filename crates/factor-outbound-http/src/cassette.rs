@@ -0,0 +1,354 @@
+//! Recording and replaying outbound HTTP interactions.
+//!
+//! With a cassette configured, responses to outbound requests are recorded
+//! to a file, and requests matching a recorded interaction get its response
+//! without the network being used. This lets components that call
+//! third-party APIs be developed offline and tested deterministically.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use base64::Engine;
+use http::{request, HeaderMap};
+use serde::{Deserialize, Serialize};
+
+/// Where a cassette is stored, and how it is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CassetteConfig {
+    /// The file interactions are recorded to.
+    pub path: PathBuf,
+    pub mode: CassetteMode,
+    /// Whether requests only match interactions with the same body.
+    ///
+    /// Requests always have to have the same method and URI.
+    pub match_body: bool,
+    /// The request headers which have to be the same for requests to match.
+    ///
+    /// Only these headers are recorded, so that credentials in other headers
+    /// are kept out of the cassette.
+    pub match_headers: Vec<String>,
+}
+
+/// How a cassette is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Replays recorded interactions, and records requests which don't match
+    /// one.
+    #[default]
+    Auto,
+    /// Records every request, replacing the cassette's interactions.
+    Record,
+    /// Replays recorded interactions, and fails requests which don't match
+    /// one.
+    Replay,
+}
+
+/// The recorded interactions of an app, shared by its instances.
+pub(crate) struct Cassette {
+    config: CassetteConfig,
+    state: Mutex<CassetteState>,
+}
+
+#[derive(Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction has been replayed, so that repeated requests
+    /// get their recorded responses in order.
+    replayed: Vec<bool>,
+}
+
+impl Cassette {
+    /// Loads the cassette's recorded interactions, unless it is recording
+    /// them afresh.
+    pub fn open(config: CassetteConfig) -> anyhow::Result<Self> {
+        let interactions = match config.mode {
+            CassetteMode::Record => vec![],
+            CassetteMode::Auto if !config.path.exists() => vec![],
+            CassetteMode::Auto | CassetteMode::Replay => load(&config.path)?,
+        };
+        Ok(Self {
+            state: Mutex::new(CassetteState {
+                replayed: vec![false; interactions.len()],
+                interactions,
+            }),
+            config,
+        })
+    }
+
+    /// Describes a request for matching against recorded interactions.
+    pub fn request(&self, parts: &request::Parts, body: &[u8]) -> RecordedRequest {
+        let headers = self
+            .config
+            .match_headers
+            .iter()
+            .filter_map(|name| {
+                let value = parts.headers.get(name)?.to_str().ok()?;
+                Some((name.to_ascii_lowercase(), value.to_owned()))
+            })
+            .collect();
+        RecordedRequest {
+            method: parts.method.to_string(),
+            uri: parts.uri.to_string(),
+            headers,
+            body: self.config.match_body.then(|| RecordedBody::new(body)),
+        }
+    }
+
+    /// Returns the recorded response to a request, if there is one.
+    ///
+    /// Repeated requests get the responses recorded for them in order, and
+    /// then the last one again.
+    pub fn replay(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        if self.config.mode == CassetteMode::Record {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        let CassetteState {
+            interactions,
+            replayed,
+        } = &mut *state;
+        let matching: Vec<usize> = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.request == *request)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&index| !replayed[index])
+            .or(matching.last().copied())?;
+        replayed[index] = true;
+        tracing::debug!(
+            "Replaying recorded response to {} {}",
+            request.method,
+            request.uri
+        );
+        Some(interactions[index].response.clone())
+    }
+
+    /// Returns whether requests without a recorded response are sent.
+    pub fn records(&self) -> bool {
+        self.config.mode != CassetteMode::Replay
+    }
+
+    /// Records the response to a request, and saves the cassette.
+    ///
+    /// Failing to save the cassette shouldn't fail the request, so errors
+    /// are logged.
+    pub fn record(&self, request: RecordedRequest, response: RecordedResponse) {
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(Interaction { request, response });
+        // Recorded interactions aren't replayed by the run that recorded them
+        state.replayed.push(true);
+        if let Err(err) = save(&self.config.path, &state.interactions) {
+            tracing::error!(
+                "Failed to save outbound HTTP cassette {}: {err:#}",
+                self.config.path.display()
+            );
+        }
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<Vec<Interaction>> {
+    let contents = std::fs::read(path)
+        .with_context(|| format!("failed to read outbound HTTP cassette {}", path.display()))?;
+    let cassette: CassetteFile = serde_json::from_slice(&contents)
+        .with_context(|| format!("failed to parse outbound HTTP cassette {}", path.display()))?;
+    Ok(cassette.interactions)
+}
+
+fn save(path: &Path, interactions: &[Interaction]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_vec_pretty(&CassetteFile {
+        interactions: interactions.to_vec(),
+    })?;
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+/// The parts of a request which recorded interactions are matched on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RecordedRequest {
+    pub method: String,
+    pub uri: String,
+    /// The values of the matched headers, by lowercase name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<(String, String)>,
+    /// The body, if bodies are matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<RecordedBody>,
+}
+
+/// A recorded response.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    body: RecordedBody,
+}
+
+impl RecordedResponse {
+    pub fn new(status: u16, headers: &HeaderMap, body: &[u8]) -> Self {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), value)
+            })
+            .collect();
+        Self::from_pairs(status, headers, body)
+    }
+
+    /// Creates a response from its headers as name-value pairs.
+    pub fn from_pairs(status: u16, headers: Vec<(String, String)>, body: &[u8]) -> Self {
+        Self {
+            status,
+            headers,
+            body: RecordedBody::new(body),
+        }
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        self.body.to_vec()
+    }
+
+    /// Converts the response to an [`http::Response`].
+    pub fn to_http(&self) -> anyhow::Result<http::Response<bytes::Bytes>> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        Ok(builder.body(self.body().into())?)
+    }
+}
+
+/// A body, as text if it is UTF-8 so that cassettes can be read and edited.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedBody {
+    Text(String),
+    Base64(String),
+}
+
+impl RecordedBody {
+    fn new(body: &[u8]) -> Self {
+        match std::str::from_utf8(body) {
+            Ok(text) => Self::Text(text.to_owned()),
+            Err(_) => Self::Base64(base64::engine::general_purpose::STANDARD.encode(body)),
+        }
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.as_bytes().to_vec(),
+            // Cassettes may be edited by hand
+            Self::Base64(encoded) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .unwrap_or_else(|err| {
+                    tracing::warn!("Replaying invalid base64 response body: {err}");
+                    vec![]
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path, mode: CassetteMode) -> CassetteConfig {
+        CassetteConfig {
+            path: dir.join("cassette.json"),
+            mode,
+            match_body: true,
+            match_headers: vec!["x-api-version".into()],
+        }
+    }
+
+    fn request(cassette: &Cassette, body: &str) -> RecordedRequest {
+        let (parts, ()) = http::Request::post("https://api.example.com/items")
+            .header("authorization", "Bearer secret")
+            .header("x-api-version", "2")
+            .body(())
+            .unwrap()
+            .into_parts();
+        cassette.request(&parts, body.as_bytes())
+    }
+
+    fn response(body: &[u8]) -> RecordedResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "application/json".parse().unwrap());
+        RecordedResponse::new(201, &headers, body)
+    }
+
+    #[test]
+    fn replays_recorded_interactions() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let recorder = Cassette::open(config(dir.path(), CassetteMode::Record))?;
+        let req = request(&recorder, "{}");
+        assert!(recorder.replay(&req).is_none());
+        recorder.record(req, response(b"{\"id\":1}"));
+        let binary = request(&recorder, "binary");
+        recorder.record(binary, response(&[0xff, 0x00]));
+
+        let player = Cassette::open(config(dir.path(), CassetteMode::Replay))?;
+        assert!(!player.records());
+        let replayed = player.replay(&request(&player, "{}")).unwrap();
+        assert_eq!(replayed.status, 201);
+        assert_eq!(replayed.body(), b"{\"id\":1}");
+        let replayed = player.replay(&request(&player, "binary")).unwrap();
+        assert_eq!(replayed.body(), [0xff, 0x00]);
+        assert!(player.replay(&request(&player, "other")).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_requests_replay_in_order() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let recorder = Cassette::open(config(dir.path(), CassetteMode::Auto))?;
+        recorder.record(request(&recorder, "{}"), response(b"first"));
+        recorder.record(request(&recorder, "{}"), response(b"second"));
+
+        let player = Cassette::open(config(dir.path(), CassetteMode::Auto))?;
+        let replay = || player.replay(&request(&player, "{}")).unwrap().body();
+        assert_eq!(replay(), b"first");
+        assert_eq!(replay(), b"second");
+        assert_eq!(replay(), b"second");
+        Ok(())
+    }
+
+    #[test]
+    fn only_matched_headers_are_recorded() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cassette = Cassette::open(config(dir.path(), CassetteMode::Record))?;
+        cassette.record(request(&cassette, "{}"), response(b""));
+        let contents = std::fs::read_to_string(dir.path().join("cassette.json"))?;
+        assert!(contents.contains("x-api-version"));
+        assert!(!contents.contains("secret"));
+        Ok(())
+    }
+
+    #[test]
+    fn replaying_requires_a_cassette() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Cassette::open(config(dir.path(), CassetteMode::Replay)).is_err());
+        assert!(Cassette::open(config(dir.path(), CassetteMode::Auto)).is_ok());
+    }
+}
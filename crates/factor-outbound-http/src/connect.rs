@@ -0,0 +1,65 @@
+//! Connections for the clients which speak HTTP over hyper directly, rather
+//! than through `wasi:http`.
+
+use std::{sync::Arc, time::Duration};
+
+use rustls::pki_types::ServerName;
+use spin_factor_outbound_networking::{connect_tcp, BlockedNetworks, DnsResolver, TlsClientConfig};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time::timeout,
+};
+
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection, with or without TLS.
+pub(crate) trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// Opens a connection to `host`, over TLS if `tls_client_config` is set, in
+/// which case one of `alpn_protocols` is negotiated.
+///
+/// Addresses in blocked networks are skipped. Errors are described for the
+/// guest.
+pub(crate) async fn connect_io(
+    host: &str,
+    port: u16,
+    tls_client_config: Option<TlsClientConfig>,
+    alpn_protocols: &[&[u8]],
+    blocked_networks: &BlockedNetworks,
+    dns_resolver: &DnsResolver,
+) -> Result<Box<dyn Io>, String> {
+    let mut socket_addrs = dns_resolver
+        .lookup_host(host, port)
+        .await
+        .map_err(|err| format!("failed to resolve {host}: {err}"))?;
+    let blocked_addrs = blocked_networks.remove_blocked(&mut socket_addrs);
+    if socket_addrs.is_empty() && !blocked_addrs.is_empty() {
+        tracing::error!(
+            "error.type" = "destination_ip_prohibited",
+            ?blocked_addrs,
+            "all destination IP(s) prohibited by runtime config"
+        );
+        return Err("destination IP prohibited".into());
+    }
+
+    let tcp_stream = timeout(CONNECT_TIMEOUT, connect_tcp(&socket_addrs))
+        .await
+        .map_err(|_| "connection timed out".to_string())?
+        .map_err(|err| format!("connection failed: {err}"))?;
+
+    let Some(tls_client_config) = tls_client_config else {
+        return Ok(Box::new(tcp_stream));
+    };
+    let mut config = (*tls_client_config.inner()).clone();
+    config.alpn_protocols = alpn_protocols.iter().map(|p| p.to_vec()).collect();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+    let domain =
+        ServerName::try_from(host.to_owned()).map_err(|_| format!("invalid server name {host}"))?;
+    let stream = connector
+        .connect(domain, tcp_stream)
+        .await
+        .map_err(|err| format!("TLS handshake failed: {err}"))?;
+    Ok(Box::new(stream))
+}
@@ -0,0 +1,322 @@
+//! Client-side handling of `Expect: 100-continue` for outbound requests.
+//!
+//! hyper's HTTP/1 client writes the request body as soon as the request head
+//! has been sent, and silently skips any informational (1xx) responses. To
+//! honour `Expect: 100-continue`, the request body is held back until the
+//! server either sends `100 Continue` or a timeout elapses (RFC 9110 §10.1.1).
+//! Other informational responses, such as `103 Early Hints`, are skipped over.
+//! If the server answers with a final response instead, the body is never sent.
+//! Once released, the body is passed through unchanged, trailers included.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http::{header::EXPECT, HeaderValue, Request};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::oneshot,
+    time::Sleep,
+};
+use wasmtime_wasi_http::{bindings::http::types::ErrorCode, body::HyperOutgoingBody};
+
+/// How long to wait for `100 Continue` before sending the body anyway.
+const EXPECT_CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The length of `HTTP/1.1 100`, enough to read the status of a response.
+const STATUS_LINE_PREFIX_LEN: usize = 12;
+
+/// The blank line which ends the head of a response.
+const END_OF_HEAD: &[u8] = b"\r\n\r\n";
+
+/// If the request carries `Expect: 100-continue`, gates its body and returns
+/// the sender the connection should use to report the server's first status.
+pub(crate) fn gate_request_body(
+    request: Request<HyperOutgoingBody>,
+) -> (Request<HyperOutgoingBody>, Option<oneshot::Sender<bool>>) {
+    let expects_continue = request
+        .headers()
+        .get_all(EXPECT)
+        .iter()
+        .any(is_100_continue);
+    if !expects_continue || request.body().is_end_stream() {
+        return (request, None);
+    }
+    let (tx, rx) = oneshot::channel();
+    let request = request.map(|inner| {
+        ExpectContinueBody {
+            inner,
+            gate: Some(Gate {
+                decision: rx,
+                timeout: Box::pin(tokio::time::sleep(EXPECT_CONTINUE_TIMEOUT)),
+            }),
+        }
+        .boxed()
+    });
+    (request, Some(tx))
+}
+
+fn is_100_continue(value: &HeaderValue) -> bool {
+    value
+        .to_str()
+        .is_ok_and(|v| v.trim().eq_ignore_ascii_case("100-continue"))
+}
+
+/// An outgoing body which is not polled until the server agrees to receive it.
+struct ExpectContinueBody {
+    inner: HyperOutgoingBody,
+    gate: Option<Gate>,
+}
+
+struct Gate {
+    /// `true` once the server sends `100 Continue`, `false` if it sends a
+    /// final response first.
+    decision: oneshot::Receiver<bool>,
+    timeout: Pin<Box<Sleep>>,
+}
+
+impl Body for ExpectContinueBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(gate) = &mut this.gate {
+            let proceed = match Pin::new(&mut gate.decision).poll(cx) {
+                Poll::Ready(Ok(proceed)) => proceed,
+                // The connection went away before the server answered; let
+                // hyper surface the connection error.
+                Poll::Ready(Err(_)) => true,
+                Poll::Pending => match gate.timeout.as_mut().poll(cx) {
+                    Poll::Ready(()) => true,
+                    Poll::Pending => return Poll::Pending,
+                },
+            };
+            if !proceed {
+                // The server has already responded without reading the body.
+                // Never send it; the connection is not reused.
+                return Poll::Pending;
+            }
+            this.gate = None;
+        }
+        Pin::new(&mut this.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A connection wrapper which reports whether the first non-informational
+/// response the server sends is `100 Continue` or a final response.
+pub(crate) struct ContinueSniffer<S> {
+    inner: S,
+    decision: Option<oneshot::Sender<bool>>,
+    state: SniffState,
+}
+
+enum SniffState {
+    /// Collecting the start of a status line.
+    StatusLine(Vec<u8>),
+    /// Skipping the rest of an informational response other than `100`;
+    /// holds how much of [`END_OF_HEAD`] has been seen.
+    SkipInformational(usize),
+}
+
+impl<S> ContinueSniffer<S> {
+    pub(crate) fn new(inner: S, decision: Option<oneshot::Sender<bool>>) -> Self {
+        Self {
+            inner,
+            decision,
+            state: SniffState::StatusLine(Vec::new()),
+        }
+    }
+
+    fn sniff(&mut self, mut read: &[u8]) {
+        while self.decision.is_some() && !read.is_empty() {
+            match &mut self.state {
+                SniffState::StatusLine(status_line) => {
+                    let wanted = STATUS_LINE_PREFIX_LEN - status_line.len();
+                    let taken = read.len().min(wanted);
+                    status_line.extend_from_slice(&read[..taken]);
+                    read = &read[taken..];
+                    if status_line.len() < STATUS_LINE_PREFIX_LEN {
+                        return;
+                    }
+                    let proceed = match &status_line[9..STATUS_LINE_PREFIX_LEN] {
+                        b"100" => true,
+                        // Other informational responses (e.g. 103 Early Hints)
+                        // precede the real answer; keep waiting for it.
+                        [b'1', _, _] => {
+                            self.state = SniffState::SkipInformational(0);
+                            continue;
+                        }
+                        _ => false,
+                    };
+                    if let Some(tx) = self.decision.take() {
+                        _ = tx.send(proceed);
+                    }
+                }
+                SniffState::SkipInformational(matched) => {
+                    let Some(pos) = read.iter().position(|&byte| {
+                        *matched = match byte {
+                            _ if byte == END_OF_HEAD[*matched] => *matched + 1,
+                            b'\r' => 1,
+                            _ => 0,
+                        };
+                        *matched == END_OF_HEAD.len()
+                    }) else {
+                        return;
+                    };
+                    read = &read[pos + 1..];
+                    self.state = SniffState::StatusLine(Vec::new());
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ContinueSniffer<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            this.sniff(&buf.filled()[before..]);
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ContinueSniffer<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sniff(chunks: &[&[u8]]) -> Option<bool> {
+        let (tx, mut rx) = oneshot::channel();
+        let mut sniffer = ContinueSniffer::new((), Some(tx));
+        for chunk in chunks {
+            sniffer.sniff(chunk);
+        }
+        let decision = rx.try_recv().ok();
+        drop(sniffer);
+        decision
+    }
+
+    #[test]
+    fn detects_continue_across_reads() {
+        assert_eq!(sniff(&[b"HTTP/1.1 1", b"00 Continue\r\n\r\n"]), Some(true));
+    }
+
+    #[test]
+    fn detects_final_response() {
+        assert_eq!(
+            sniff(&[b"HTTP/1.1 417 Expectation Failed\r\n"]),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn other_informational_responses_keep_waiting() {
+        assert_eq!(
+            sniff(&[b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r\n"]),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_continue_after_early_hints() {
+        assert_eq!(
+            sniff(&[
+                b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r",
+                b"\n\r\nHTTP/1.1 100 Continue\r\n\r\n"
+            ]),
+            Some(true)
+        );
+        assert_eq!(
+            sniff(&[
+                b"HTTP/1.1 103 Early Hints\r\n\r\nHTTP/1.1 1",
+                b"00 Continue\r\n\r\n"
+            ]),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn detects_final_response_after_early_hints() {
+        assert_eq!(
+            sniff(&[b"HTTP/1.1 103 Early Hints\r\n\r\nHTTP/1.1 417 Expectation Failed\r\n"]),
+            Some(false)
+        );
+    }
+
+    #[tokio::test]
+    async fn released_body_forwards_trailers() {
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+        let frames = [
+            Ok::<_, ErrorCode>(Frame::data(Bytes::from_static(b"hello"))),
+            Ok(Frame::trailers(trailers.clone())),
+        ];
+        let body = http_body_util::StreamBody::new(futures::stream::iter(frames)).boxed();
+        let request = Request::builder()
+            .header(EXPECT, "100-continue")
+            .body(body)
+            .unwrap();
+
+        let (request, tx) = gate_request_body(request);
+        tx.expect("body should be gated").send(true).unwrap();
+
+        let collected = request.into_body().collect().await.unwrap();
+        assert_eq!(collected.trailers(), Some(&trailers));
+        assert_eq!(collected.to_bytes(), Bytes::from_static(b"hello"));
+    }
+}
@@ -0,0 +1,446 @@
+//! Host implementation of the `spin:grpc/client` interface.
+//!
+//! Messages are opaque bytes; this module only deals with gRPC's
+//! length-prefixed message framing, metadata and status trailers over HTTP/2.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{
+    header::{CONTENT_TYPE, TE},
+    uri::Scheme,
+    HeaderMap, HeaderName, HeaderValue, StatusCode, Uri,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, client::conn::http2::SendRequest};
+use hyper_util::rt::TokioExecutor;
+use spin_factor_outbound_networking::{BlockedNetworks, DnsResolver, TlsClientConfig};
+use spin_factors::wasmtime::component::Resource;
+use spin_world::spin::grpc::client::{
+    self, Error, Metadata, ResponseStream, Status, UnaryResponse,
+};
+use tokio::time::timeout;
+use tracing::{field::Empty, instrument, Level, Span};
+use wasmtime_wasi::runtime::AbortOnDropJoinHandle;
+use wasmtime_wasi_http::io::TokioIo;
+
+use crate::{
+    connect::{connect_io, CONNECT_TIMEOUT},
+    InstanceState,
+};
+
+/// The largest response message accepted; the default of most gRPC implementations.
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+/// The size of the compressed flag and length prefix before each message.
+const PREFIX_SIZE: usize = 5;
+
+impl client::Host for InstanceState {
+    #[instrument(name = "spin_outbound_grpc.unary", skip_all, err(level = Level::INFO),
+        fields(otel.kind = "client", otel.name = %method, rpc.system = "grpc", rpc.method = %method,
+        server.address = Empty))]
+    async fn unary(
+        &mut self,
+        endpoint: String,
+        method: String,
+        metadata: Metadata,
+        message: Vec<u8>,
+    ) -> Result<UnaryResponse, Error> {
+        let mut call = self
+            .start_call(&endpoint, &method, metadata, message)
+            .await?;
+        let Some(message) = call.next_message().await? else {
+            return Err(transport_error("server sent no response message"));
+        };
+        if call.next_message().await?.is_some() {
+            return Err(transport_error(
+                "server sent more than one response message to a unary call",
+            ));
+        }
+        Ok(UnaryResponse {
+            metadata: call.metadata,
+            message,
+        })
+    }
+
+    #[instrument(name = "spin_outbound_grpc.server_streaming", skip_all, err(level = Level::INFO),
+        fields(otel.kind = "client", otel.name = %method, rpc.system = "grpc", rpc.method = %method,
+        server.address = Empty))]
+    async fn server_streaming(
+        &mut self,
+        endpoint: String,
+        method: String,
+        metadata: Metadata,
+        message: Vec<u8>,
+    ) -> Result<Resource<ResponseStream>, Error> {
+        let call = self
+            .start_call(&endpoint, &method, metadata, message)
+            .await?;
+        self.grpc_calls
+            .push(call)
+            .map(Resource::new_own)
+            .map_err(|_| transport_error("too many open response streams"))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl client::HostResponseStream for InstanceState {
+    async fn metadata(&mut self, stream: Resource<ResponseStream>) -> anyhow::Result<Metadata> {
+        let call = self
+            .grpc_calls
+            .get(stream.rep())
+            .ok_or_else(|| anyhow::anyhow!("unknown response stream"))?;
+        Ok(call.metadata.clone())
+    }
+
+    async fn next(&mut self, stream: Resource<ResponseStream>) -> Result<Option<Vec<u8>>, Error> {
+        let call = self
+            .grpc_calls
+            .get_mut(stream.rep())
+            .ok_or_else(|| transport_error("unknown response stream"))?;
+        call.next_message().await
+    }
+
+    async fn drop(&mut self, stream: Resource<ResponseStream>) -> anyhow::Result<()> {
+        self.grpc_calls.remove(stream.rep());
+        Ok(())
+    }
+}
+
+impl InstanceState {
+    async fn start_call(
+        &self,
+        endpoint: &str,
+        method: &str,
+        metadata: Metadata,
+        message: Vec<u8>,
+    ) -> Result<GrpcCall, Error> {
+        let uri = call_uri(endpoint, method)?;
+        let is_allowed = self
+            .allowed_hosts
+            .check_url(endpoint, "https")
+            .await
+            .unwrap_or(false);
+        if !is_allowed {
+            return Err(Error::DestinationNotAllowed);
+        }
+
+        let mut request = http::Request::post(uri.clone())
+            .header(CONTENT_TYPE, "application/grpc")
+            .header(TE, "trailers")
+            .body(Full::new(encode_message(&message)?))
+            .map_err(|_| Error::InvalidUrl)?;
+        for (key, value) in metadata {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| transport_error(format!("invalid metadata key {key:?}")))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| transport_error(format!("invalid metadata value for {key:?}")))?;
+            request.headers_mut().append(name, value);
+        }
+        self.inject_request_context(request.headers_mut());
+        spin_telemetry::inject_trace_context(request.headers_mut());
+
+        let host = uri.host().unwrap_or_default();
+        Span::current().record("server.address", host);
+        let tls_client_config = (uri.scheme() == Some(&Scheme::HTTPS))
+            .then(|| self.component_tls_configs.get_client_config(host).clone());
+        let _permit = self.egress_throttle.acquire(message.len()).await;
+        let (mut sender, worker) = connect(
+            &uri,
+            tls_client_config,
+            &self.blocked_networks,
+            &self.dns_resolver,
+        )
+        .await?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|err| transport_error(format!("request failed: {err}")))?;
+        let (parts, body) = response.into_parts();
+        if parts.status != StatusCode::OK {
+            return Err(transport_error(format!(
+                "server responded with HTTP status {}",
+                parts.status
+            )));
+        }
+
+        Ok(GrpcCall {
+            metadata: metadata_from_headers(&parts.headers),
+            // A "trailers-only" response carries the status in its headers
+            status: status_from_headers(&parts.headers),
+            body,
+            decoder: MessageDecoder::default(),
+            _worker: worker,
+        })
+    }
+}
+
+/// An in-progress gRPC call.
+pub(crate) struct GrpcCall {
+    metadata: Metadata,
+    status: Option<Status>,
+    body: Incoming,
+    decoder: MessageDecoder,
+    // Drives the HTTP/2 connection for as long as the call is alive.
+    _worker: AbortOnDropJoinHandle<()>,
+}
+
+impl GrpcCall {
+    /// Returns the next response message, or `None` if the call ended with `OK`.
+    async fn next_message(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        loop {
+            if let Some(message) = self.decoder.decode()? {
+                return Ok(Some(message));
+            }
+            if let Some(status) = &self.status {
+                if status.code != 0 {
+                    return Err(Error::Status(status.clone()));
+                }
+                if !self.decoder.is_empty() {
+                    return Err(transport_error("response ended with an incomplete message"));
+                }
+                return Ok(None);
+            }
+            match self.body.frame().await {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => self.decoder.push(&data),
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            let status = status_from_headers(&trailers).ok_or_else(|| {
+                                transport_error("response trailers are missing grpc-status")
+                            })?;
+                            self.status = Some(status);
+                        }
+                    }
+                },
+                Some(Err(err)) => {
+                    return Err(transport_error(format!("error reading response: {err}")))
+                }
+                None => return Err(transport_error("response ended without a gRPC status")),
+            }
+        }
+    }
+}
+
+async fn connect(
+    uri: &Uri,
+    tls_client_config: Option<TlsClientConfig>,
+    blocked_networks: &BlockedNetworks,
+    dns_resolver: &DnsResolver,
+) -> Result<(SendRequest<Full<Bytes>>, AbortOnDropJoinHandle<()>), Error> {
+    // IPv6 literals keep their brackets in the URI host
+    let host = uri
+        .host()
+        .ok_or(Error::InvalidUrl)?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri
+        .port_u16()
+        .unwrap_or(if tls_client_config.is_some() { 443 } else { 80 });
+    // gRPC requires HTTP/2, which must be negotiated with ALPN
+    let io = connect_io(
+        host,
+        port,
+        tls_client_config,
+        &[b"h2"],
+        blocked_networks,
+        dns_resolver,
+    )
+    .await
+    .map_err(transport_error)?;
+    handshake(TokioIo::new(io)).await
+}
+
+async fn handshake<T>(io: T) -> Result<(SendRequest<Full<Bytes>>, AbortOnDropJoinHandle<()>), Error>
+where
+    T: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    let (sender, conn) = timeout(
+        CONNECT_TIMEOUT,
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), io),
+    )
+    .await
+    .map_err(|_| transport_error("connection timed out"))?
+    .map_err(|err| transport_error(format!("HTTP/2 handshake failed: {err}")))?;
+    let worker = wasmtime_wasi::runtime::spawn(async move {
+        if let Err(err) = conn.await {
+            tracing::warn!("gRPC connection error: {err}");
+        }
+    });
+    Ok((sender, worker))
+}
+
+/// Builds the URI for calling `method` on `endpoint`.
+///
+/// Any path on the endpoint is kept as a prefix, e.g. for services behind a gateway.
+fn call_uri(endpoint: &str, method: &str) -> Result<Uri, Error> {
+    let endpoint: Uri = endpoint.parse().map_err(|_| Error::InvalidUrl)?;
+    let (Some(scheme), Some(authority)) = (endpoint.scheme(), endpoint.authority()) else {
+        return Err(Error::InvalidUrl);
+    };
+    if *scheme != Scheme::HTTP && *scheme != Scheme::HTTPS {
+        return Err(Error::InvalidUrl);
+    }
+    if !method.starts_with('/') {
+        return Err(Error::InvalidUrl);
+    }
+    let prefix = endpoint.path().trim_end_matches('/');
+    Uri::builder()
+        .scheme(scheme.clone())
+        .authority(authority.clone())
+        .path_and_query(format!("{prefix}{method}"))
+        .build()
+        .map_err(|_| Error::InvalidUrl)
+}
+
+/// Frames `message` with an uncompressed length prefix.
+fn encode_message(message: &[u8]) -> Result<Bytes, Error> {
+    let len = u32::try_from(message.len()).map_err(|_| transport_error("message too large"))?;
+    let mut buf = BytesMut::with_capacity(PREFIX_SIZE + message.len());
+    buf.put_u8(0);
+    buf.put_u32(len);
+    buf.put_slice(message);
+    Ok(buf.freeze())
+}
+
+/// Splits length-prefixed messages out of response body data.
+#[derive(Default)]
+struct MessageDecoder {
+    buf: BytesMut,
+}
+
+impl MessageDecoder {
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the next complete message, if one has been buffered.
+    fn decode(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.buf.len() < PREFIX_SIZE {
+            return Ok(None);
+        }
+        if self.buf[0] != 0 {
+            // We never advertise `grpc-accept-encoding`, so servers shouldn't compress
+            return Err(transport_error("server sent a compressed message"));
+        }
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+        if len > MAX_MESSAGE_SIZE {
+            return Err(transport_error(format!(
+                "response message of {len} bytes exceeds the limit of {MAX_MESSAGE_SIZE} bytes"
+            )));
+        }
+        if self.buf.len() < PREFIX_SIZE + len {
+            return Ok(None);
+        }
+        self.buf.advance(PREFIX_SIZE);
+        Ok(Some(self.buf.split_to(len).to_vec()))
+    }
+}
+
+fn status_from_headers(headers: &HeaderMap) -> Option<Status> {
+    let code = headers.get("grpc-status")?.to_str().ok()?.parse().ok()?;
+    let message = headers
+        .get("grpc-message")
+        .and_then(|value| value.to_str().ok())
+        .map(percent_decode)
+        .unwrap_or_default();
+    Some(Status { code, message })
+}
+
+fn metadata_from_headers(headers: &HeaderMap) -> Metadata {
+    headers
+        .iter()
+        .filter(|(name, _)| *name != CONTENT_TYPE && !name.as_str().starts_with("grpc-"))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect()
+}
+
+/// Decodes a percent-encoded `grpc-message`, leaving malformed escapes as-is.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3);
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn transport_error(message: impl Into<String>) -> Error {
+    Error::Transport(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_call_uri() {
+        let uri = call_uri("https://orders.internal:8443", "/orders.v1.Orders/Get").unwrap();
+        assert_eq!(uri, "https://orders.internal:8443/orders.v1.Orders/Get");
+        let uri = call_uri("http://gateway.internal/grpc/", "/orders.v1.Orders/Get").unwrap();
+        assert_eq!(uri, "http://gateway.internal/grpc/orders.v1.Orders/Get");
+
+        assert!(call_uri("orders.internal:8443", "/orders.v1.Orders/Get").is_err());
+        assert!(call_uri("ftp://orders.internal", "/orders.v1.Orders/Get").is_err());
+        assert!(call_uri("https://orders.internal", "orders.v1.Orders/Get").is_err());
+    }
+
+    #[test]
+    fn decodes_split_messages() {
+        let mut data = encode_message(b"first").unwrap().to_vec();
+        data.extend_from_slice(&encode_message(b"").unwrap());
+        data.extend_from_slice(&encode_message(b"second").unwrap());
+
+        let mut decoder = MessageDecoder::default();
+        let mut messages = vec![];
+        // Feed one byte at a time to exercise partial prefixes and bodies
+        for byte in data {
+            decoder.push(&[byte]);
+            while let Some(message) = decoder.decode().unwrap() {
+                messages.push(message);
+            }
+        }
+        assert_eq!(messages, [b"first".to_vec(), vec![], b"second".to_vec()]);
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn rejects_compressed_and_oversized_messages() {
+        let mut decoder = MessageDecoder::default();
+        decoder.push(&[1, 0, 0, 0, 0]);
+        assert!(decoder.decode().is_err());
+
+        let mut decoder = MessageDecoder::default();
+        decoder.push(&[0, 0xff, 0xff, 0xff, 0xff]);
+        assert!(decoder.decode().is_err());
+    }
+
+    #[test]
+    fn parses_status() {
+        let mut headers = HeaderMap::new();
+        assert!(status_from_headers(&headers).is_none());
+        headers.insert("grpc-status", HeaderValue::from_static("5"));
+        headers.insert(
+            "grpc-message",
+            HeaderValue::from_static("order%20not%20found%"),
+        );
+        let status = status_from_headers(&headers).unwrap();
+        assert_eq!(status.code, 5);
+        assert_eq!(status.message, "order not found%");
+    }
+}
@@ -1,5 +1,15 @@
+pub mod cassette;
+mod connect;
+mod expect_continue;
+mod grpc;
 pub mod intercept;
+mod pool;
+pub mod response_cache;
+pub mod runtime_config;
+pub mod signing;
 mod spin;
+mod sse;
+mod throttle;
 mod wasi;
 pub mod wasi_2023_10_18;
 pub mod wasi_2023_11_10;
@@ -7,14 +17,22 @@ pub mod wasi_2023_11_10;
 use std::sync::Arc;
 
 use anyhow::Context;
+use cassette::Cassette;
 use http::{
     uri::{Authority, Parts, PathAndQuery, Scheme},
     HeaderValue, Uri,
 };
 use intercept::OutboundHttpInterceptor;
+use pool::ConnectionPool;
+use response_cache::ResponseCache;
+use runtime_config::{ConnectionPoolingConfig, RuntimeConfig};
+use signing::RequestSigning;
+use spin_factor_fault_injection::{FaultInjectionFactor, FaultInjector};
 use spin_factor_outbound_networking::{
-    BlockedNetworks, ComponentTlsClientConfigs, OutboundAllowedHosts, OutboundNetworkingFactor,
+    BlockedNetworks, ComponentTlsClientConfigs, DnsResolver, EgressThrottle, OutboundAllowedHosts,
+    OutboundNetworkingFactor,
 };
+use spin_factor_request_context::{RequestContextFactor, RequestContextHandle};
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
 };
@@ -32,21 +50,36 @@ pub struct OutboundHttpFactor {
 }
 
 impl Factor for OutboundHttpFactor {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
     type InstanceBuilder = InstanceState;
 
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::http::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::grpc::client::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::sse::client::add_to_linker)?;
         wasi::add_to_linker(ctx)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let RuntimeConfig {
+            connection_pooling,
+            request_signing,
+            cassette,
+            response_cache,
+        } = ctx.take_runtime_config().unwrap_or_default();
+        let cassette = cassette.map(Cassette::open).transpose()?.map(Arc::new);
+        Ok(AppState {
+            connection_pool: Arc::new(ConnectionPool::new(&connection_pooling)),
+            connection_pooling,
+            request_signing: RequestSigning::new(request_signing),
+            cassette,
+            response_cache: response_cache.map(|config| Arc::new(ResponseCache::new(config))),
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
@@ -57,27 +90,87 @@ impl Factor for OutboundHttpFactor {
         let allowed_hosts = outbound_networking.allowed_hosts();
         let blocked_networks = outbound_networking.blocked_networks();
         let component_tls_configs = outbound_networking.component_tls_configs();
+        let egress_throttle = outbound_networking.egress_throttle();
+        let dns_resolver = outbound_networking.dns_resolver();
+        // The request context is propagated if the runtime provides one
+        let request_context = match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(request_context) => Some(request_context.handle()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let fault_injector = match ctx.instance_builder::<FaultInjectionFactor>() {
+            Ok(fault_injection) => Some(fault_injection.injector()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
         Ok(InstanceState {
             wasi_http_ctx: WasiHttpCtx::new(),
             allowed_hosts,
             blocked_networks,
             component_tls_configs,
+            egress_throttle,
+            dns_resolver,
             self_request_origin: None,
             request_interceptor: None,
+            request_context,
+            fault_injector,
             spin_http_client: None,
+            connection_pooling: ctx.app_state().connection_pooling.clone(),
+            connection_pool: ctx.app_state().connection_pool.clone(),
+            component_id: ctx.app_component().id().into(),
+            request_signing: ctx.app_state().request_signing.clone(),
+            cassette: ctx.app_state().cassette.clone(),
+            response_cache: ctx.app_state().response_cache.clone(),
+            grpc_calls: spin_resource_table::Table::new(1024),
+            sse_streams: spin_resource_table::Table::new(1024),
         })
     }
 }
 
+pub struct AppState {
+    connection_pooling: ConnectionPoolingConfig,
+    /// Connections kept open for reuse by `wasi:http` requests.
+    connection_pool: Arc<ConnectionPool>,
+    request_signing: RequestSigning,
+    /// Records and replays outbound interactions, if configured.
+    cassette: Option<Arc<Cassette>>,
+    /// Caches responses to outbound GET requests, if configured.
+    response_cache: Option<Arc<ResponseCache>>,
+}
+
 pub struct InstanceState {
     wasi_http_ctx: WasiHttpCtx,
     allowed_hosts: OutboundAllowedHosts,
     blocked_networks: BlockedNetworks,
     component_tls_configs: ComponentTlsClientConfigs,
+    // Limits on the component's outbound requests
+    egress_throttle: EgressThrottle,
+    // Resolves the host names of outbound requests
+    dns_resolver: DnsResolver,
     self_request_origin: Option<SelfRequestOrigin>,
     request_interceptor: Option<Arc<dyn OutboundHttpInterceptor>>,
+    // Context of the invocation, added to outbound requests
+    request_context: Option<RequestContextHandle>,
+    // Injects faults into outbound requests, if configured
+    fault_injector: Option<FaultInjector>,
     // Connection-pooling client for 'fermyon:spin/http' interface
     spin_http_client: Option<reqwest::Client>,
+    // Settings used to build `spin_http_client`
+    connection_pooling: ConnectionPoolingConfig,
+    // Connections shared by the app's 'wasi:http' requests
+    connection_pool: Arc<ConnectionPool>,
+    // The component's ID, which its pooled connections are kept under
+    component_id: Arc<str>,
+    // Signers for requests to configured hosts
+    request_signing: RequestSigning,
+    // Records and replays outbound interactions, if configured
+    cassette: Option<Arc<Cassette>>,
+    // Caches responses to outbound GET requests, if configured
+    response_cache: Option<Arc<ResponseCache>>,
+    // In-progress streaming calls for the 'spin:grpc/client' interface
+    grpc_calls: spin_resource_table::Table<grpc::GrpcCall>,
+    // Open streams for the 'spin:sse/client' interface
+    sse_streams: spin_resource_table::Table<sse::EventSource>,
 }
 
 impl InstanceState {
@@ -104,8 +197,43 @@ impl InstanceState {
     }
 }
 
+impl InstanceState {
+    /// Adds the invocation's request context, if any, to outbound request
+    /// headers.
+    fn inject_request_context(&self, headers: &mut http::HeaderMap) {
+        if let Some(request_context) = &self.request_context {
+            request_context.inject_headers(headers);
+        }
+    }
+}
+
 impl SelfInstanceBuilder for InstanceState {}
 
+/// Counts an outbound request as active for as long as it is alive, so that
+/// requests which are cancelled stop being counted too.
+struct ActiveRequest {
+    server_address: String,
+}
+
+impl ActiveRequest {
+    fn new(server_address: String) -> Self {
+        spin_telemetry::metrics::counter!(
+            spin.outbound_http.active_requests = 1,
+            server_address = server_address.as_str()
+        );
+        Self { server_address }
+    }
+}
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        spin_telemetry::metrics::counter!(
+            spin.outbound_http.active_requests = -1,
+            server_address = self.server_address.as_str()
+        );
+    }
+}
+
 pub type Request = http::Request<wasmtime_wasi_http::body::HyperOutgoingBody>;
 pub type Response = http::Response<wasmtime_wasi_http::body::HyperIncomingBody>;
 
@@ -0,0 +1,266 @@
+//! Pooling of the connections `wasi:http` requests are sent over.
+//!
+//! HTTP/1 connections are returned to the pool once the response body has
+//! been read to the end; HTTP/2 connections as soon as the response head
+//! arrives, since they carry several requests at once. Connections are only
+//! shared between instances of the same component, which connect with the
+//! same TLS configuration.
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use hyper::{
+    body::{Body, Frame, Incoming, SizeHint},
+    client::conn::{http1, http2},
+    Response,
+};
+use wasmtime_wasi_http::body::HyperOutgoingBody;
+
+use crate::runtime_config::ConnectionPoolingConfig;
+
+/// How long an idle connection is kept open if not configured, the same as
+/// for the `fermyon:spin/http` client.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Idle connections to the servers of `wasi:http` requests.
+pub(crate) struct ConnectionPool {
+    max_idle_per_host: usize,
+    idle_timeout: Duration,
+    http2_prior_knowledge: bool,
+    idle: Mutex<HashMap<PoolKey, Vec<Idle>>>,
+}
+
+/// Identifies the connections which may be used for a request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct PoolKey {
+    pub component_id: Arc<str>,
+    pub use_tls: bool,
+    /// The server's `host:port`.
+    pub authority: String,
+}
+
+struct Idle {
+    sender: Sender,
+    since: Instant,
+}
+
+/// The sending half of a connection.
+pub(crate) enum Sender {
+    Http1(http1::SendRequest<HyperOutgoingBody>),
+    Http2(http2::SendRequest<HyperOutgoingBody>),
+}
+
+impl Sender {
+    pub(crate) async fn send_request(
+        &mut self,
+        request: http::Request<HyperOutgoingBody>,
+    ) -> hyper::Result<Response<Incoming>> {
+        match self {
+            Self::Http1(sender) => sender.send_request(request).await,
+            Self::Http2(sender) => sender.send_request(request).await,
+        }
+    }
+
+    /// Waits for the connection to be able to send a request, returning
+    /// `false` if it has closed.
+    async fn ready(&mut self) -> bool {
+        match self {
+            Self::Http1(sender) => sender.ready().await.is_ok(),
+            Self::Http2(sender) => sender.ready().await.is_ok(),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match self {
+            Self::Http1(sender) => sender.is_closed(),
+            Self::Http2(sender) => sender.is_closed(),
+        }
+    }
+
+    pub(crate) fn is_http2(&self) -> bool {
+        matches!(self, Self::Http2(_))
+    }
+}
+
+impl ConnectionPool {
+    pub(crate) fn new(config: &ConnectionPoolingConfig) -> Self {
+        Self {
+            max_idle_per_host: config.max_idle_per_host.unwrap_or(usize::MAX),
+            idle_timeout: config.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT),
+            http2_prior_knowledge: config.http2_prior_knowledge,
+            idle: Default::default(),
+        }
+    }
+
+    /// Whether new connections speak HTTP/2 without negotiating it.
+    pub(crate) fn http2_prior_knowledge(&self) -> bool {
+        self.http2_prior_knowledge
+    }
+
+    /// Whether connections may be kept for reuse at all.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_idle_per_host > 0
+    }
+
+    /// Takes an open idle connection for `key`, if there is one.
+    pub(crate) async fn checkout(&self, key: &PoolKey) -> Option<Sender> {
+        loop {
+            let mut idle = {
+                let mut pool = self.idle.lock().unwrap();
+                let conns = pool.get_mut(key)?;
+                conns.retain(|idle| {
+                    idle.since.elapsed() < self.idle_timeout && !idle.sender.is_closed()
+                });
+                let idle = conns.pop();
+                if conns.is_empty() {
+                    pool.remove(key);
+                }
+                idle?
+            };
+            if idle.sender.ready().await {
+                return Some(idle.sender);
+            }
+        }
+    }
+
+    /// Keeps a connection for reuse, unless `key` already has as many idle
+    /// connections as allowed.
+    pub(crate) fn checkin(&self, key: PoolKey, sender: Sender) {
+        if !self.is_enabled() || sender.is_closed() {
+            return;
+        }
+        let mut pool = self.idle.lock().unwrap();
+        let conns = pool.entry(key).or_default();
+        if conns.len() < self.max_idle_per_host {
+            conns.push(Idle {
+                sender,
+                since: Instant::now(),
+            });
+        }
+    }
+}
+
+/// A response body which returns its connection to the pool once it has
+/// been read to the end.
+pub(crate) struct CheckinBody<B> {
+    inner: B,
+    checkin: Option<(Arc<ConnectionPool>, PoolKey, Sender)>,
+}
+
+impl<B: Body> CheckinBody<B> {
+    pub(crate) fn new(inner: B, pool: Arc<ConnectionPool>, key: PoolKey, sender: Sender) -> Self {
+        let mut body = Self {
+            inner,
+            checkin: Some((pool, key, sender)),
+        };
+        if body.inner.is_end_stream() {
+            body.checkin();
+        }
+        body
+    }
+
+    fn checkin(&mut self) {
+        if let Some((pool, key, sender)) = self.checkin.take() {
+            pool.checkin(key, sender);
+        }
+    }
+}
+
+impl<B: Body<Data = Bytes> + Unpin> Body for CheckinBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = Pin::new(&mut this.inner).poll_frame(cx);
+        match &frame {
+            Poll::Ready(None) => this.checkin(),
+            // The connection may be in any state after an error
+            Poll::Ready(Some(Err(_))) => this.checkin = None,
+            _ => {}
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+    use wasmtime_wasi_http::io::TokioIo;
+
+    use super::*;
+
+    fn key(authority: &str) -> PoolKey {
+        PoolKey {
+            component_id: "test-component".into(),
+            use_tls: false,
+            authority: authority.into(),
+        }
+    }
+
+    /// Opens a connection, returning the server's end of it too, which
+    /// must be kept open for as long as the connection is used.
+    async fn connect() -> anyhow::Result<(Sender, TcpStream)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let stream = TcpStream::connect(listener.local_addr()?).await?;
+        let (server, _) = listener.accept().await?;
+        let (sender, conn) = http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(conn);
+        Ok((Sender::Http1(sender), server))
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_reused_per_key() -> anyhow::Result<()> {
+        let pool = ConnectionPool::new(&Default::default());
+        let (sender, _server) = connect().await?;
+        pool.checkin(key("a.test:80"), sender);
+        assert!(pool.checkout(&key("b.test:80")).await.is_none());
+        assert!(pool.checkout(&key("a.test:80")).await.is_some());
+        assert!(pool.checkout(&key("a.test:80")).await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_limited_per_host() -> anyhow::Result<()> {
+        let pool = ConnectionPool::new(&ConnectionPoolingConfig {
+            max_idle_per_host: Some(1),
+            ..Default::default()
+        });
+        let (first, _first_server) = connect().await?;
+        let (second, _second_server) = connect().await?;
+        pool.checkin(key("a.test:80"), first);
+        pool.checkin(key("a.test:80"), second);
+        assert!(pool.checkout(&key("a.test:80")).await.is_some());
+        assert!(pool.checkout(&key("a.test:80")).await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_connections_are_not_reused() -> anyhow::Result<()> {
+        let pool = ConnectionPool::new(&ConnectionPoolingConfig {
+            idle_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        let (sender, _server) = connect().await?;
+        pool.checkin(key("a.test:80"), sender);
+        assert!(pool.checkout(&key("a.test:80")).await.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,820 @@
+//! Host-side caching of responses to outbound GET requests.
+//!
+//! Runtime config can enable caching for a set of hosts and paths, so that
+//! components calling slow or flaky third-party APIs reuse responses as their
+//! `Cache-Control` headers allow. Besides `max-age`, the cache honors
+//! `stale-while-revalidate`, serving a stale response while a fresh one is
+//! fetched in the background, and `stale-if-error`, serving a stale response
+//! when the server can't be reached or fails.
+//!
+//! The cache is shared by all of an app's components. It follows the rules of
+//! a shared cache: responses marked `private` aren't stored, nor are responses
+//! to requests with an `Authorization` header unless they are marked `public`
+//! or have an `s-maxage`. Only requests made through `wasi:http` are cached.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use http::{
+    header::{
+        AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH, ETAG, IF_MATCH, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, IF_RANGE, IF_UNMODIFIED_SINCE, LAST_MODIFIED, RANGE, VARY,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode,
+};
+
+use crate::signing::host_matches;
+
+/// The statuses whose responses may be cached.
+const CACHEABLE_STATUSES: &[u16] = &[200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Which outbound responses are cached, and how many.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResponseCacheConfig {
+    /// Rules for caching responses from particular hosts and paths.
+    ///
+    /// A request is cached according to the first rule it matches, and not
+    /// at all if it matches none.
+    pub rules: Vec<CacheRule>,
+    /// The most responses kept at once.
+    pub max_entries: usize,
+    /// The largest response body kept, in bytes.
+    pub max_body_bytes: usize,
+}
+
+impl ResponseCacheConfig {
+    /// The most responses kept at once by default.
+    pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+    /// The largest response body kept by default, in bytes.
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+}
+
+/// Caches responses from matching hosts and paths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheRule {
+    /// The hosts whose responses are cached.
+    ///
+    /// A pattern is either an exact host name or `*.` followed by a domain,
+    /// which matches any subdomain of that domain.
+    pub hosts: Vec<String>,
+    /// The prefix of the paths whose responses are cached.
+    pub path_prefix: String,
+    /// How long responses without a `max-age` or `s-maxage` stay fresh.
+    ///
+    /// If `None`, such responses aren't cached.
+    pub default_ttl: Option<Duration>,
+    /// How long a stale response is served while it is revalidated, for
+    /// responses without a `stale-while-revalidate` directive.
+    pub stale_while_revalidate: Option<Duration>,
+    /// How long a stale response is served when the server can't provide a
+    /// fresh one, for responses without a `stale-if-error` directive.
+    pub stale_if_error: Option<Duration>,
+}
+
+impl CacheRule {
+    fn matches(&self, host: &str, path: &str) -> bool {
+        path.starts_with(&self.path_prefix)
+            && self.hosts.iter().any(|pattern| host_matches(pattern, host))
+    }
+}
+
+/// The cached responses of an app, shared by its instances.
+pub(crate) struct ResponseCache {
+    config: ResponseCacheConfig,
+    /// Responses by request URI.
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+/// Identifies a cacheable request.
+pub(crate) struct CacheKey {
+    uri: String,
+    /// The index of the rule the request matched.
+    rule: usize,
+}
+
+/// The result of looking up a request in the cache.
+pub(crate) enum Lookup {
+    /// A fresh response, which is served without the request being sent.
+    Fresh(CachedResponse),
+    /// A stale response, which is served while the request is sent to
+    /// revalidate it if `revalidate` is true. Only the first request for a
+    /// stale response revalidates it.
+    Stale {
+        cached: CachedResponse,
+        revalidate: bool,
+    },
+    /// No response can be served without the request being sent. A stale
+    /// response may still be used to make the request conditional, and be
+    /// served if it fails.
+    Miss { stale: Option<CachedResponse> },
+}
+
+struct Entry {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    /// The request's values of the headers the response varies on.
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    stored_at: Instant,
+    freshness: Freshness,
+    /// Whether a request is revalidating the response.
+    revalidating: bool,
+}
+
+/// How long a stored response can be used for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Freshness {
+    /// The response's age when it was stored.
+    initial_age: Duration,
+    /// How long after being stored the response stays fresh.
+    fresh_for: Duration,
+    stale_while_revalidate: Duration,
+    stale_if_error: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the key of a request if its response may come from the cache.
+    pub fn key<B>(&self, request: &Request<B>) -> Option<CacheKey> {
+        if request.method() != Method::GET || request.headers().contains_key(RANGE) {
+            return None;
+        }
+        let directives = CacheControl::parse(request.headers());
+        if directives.no_store || directives.no_cache {
+            return None;
+        }
+        let host = request.uri().host()?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let rule = self
+            .config
+            .rules
+            .iter()
+            .position(|rule| rule.matches(host, request.uri().path()))?;
+        Some(CacheKey {
+            uri: request.uri().to_string(),
+            rule,
+        })
+    }
+
+    /// Looks up the response to a request with `request_headers`.
+    pub fn lookup(&self, key: &CacheKey, request_headers: &HeaderMap, now: Instant) -> Lookup {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(&key.uri) else {
+            return Lookup::Miss { stale: None };
+        };
+        if !entry.vary_matches(request_headers) {
+            return Lookup::Miss { stale: None };
+        }
+        let elapsed = now.saturating_duration_since(entry.stored_at);
+        let freshness = entry.freshness;
+        if elapsed < freshness.fresh_for {
+            return Lookup::Fresh(entry.cached(elapsed, false));
+        }
+        let staleness = elapsed - freshness.fresh_for;
+        if staleness < freshness.stale_while_revalidate {
+            let revalidate = !entry.revalidating;
+            entry.revalidating = true;
+            return Lookup::Stale {
+                cached: entry.cached(elapsed, false),
+                revalidate,
+            };
+        }
+        let serve_on_error = staleness < freshness.stale_if_error;
+        if !serve_on_error && !entry.has_validators() {
+            entries.remove(&key.uri);
+            return Lookup::Miss { stale: None };
+        }
+        Lookup::Miss {
+            stale: Some(entry.cached(elapsed, serve_on_error)),
+        }
+    }
+
+    /// Returns whether a response with `status` and `headers` would be
+    /// stored, so that its body need only be buffered if it would.
+    pub fn would_store(
+        &self,
+        key: &CacheKey,
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> bool {
+        let too_large = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse::<usize>().ok())
+            .is_some_and(|len| len > self.config.max_body_bytes);
+        !too_large
+            && self
+                .freshness(key, request_headers, status, headers)
+                .is_some()
+    }
+
+    /// Stores the response to a request, if it may be cached.
+    ///
+    /// Returns whether the response was stored.
+    pub fn store(
+        &self,
+        key: &CacheKey,
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Bytes,
+        now: Instant,
+    ) -> bool {
+        if body.len() > self.config.max_body_bytes {
+            return false;
+        }
+        let Some(freshness) = self.freshness(key, request_headers, status, headers) else {
+            return false;
+        };
+        let vary = vary_names(headers)
+            .filter_map(|name| name.parse::<HeaderName>().ok())
+            .map(|name| {
+                let value = request_headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        let entry = Entry {
+            status,
+            headers: headers.clone(),
+            body,
+            vary,
+            stored_at: now,
+            freshness,
+            revalidating: false,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(key.uri.clone(), entry);
+        if entries.len() > self.config.max_entries {
+            evict(&mut entries, self.config.max_entries, now);
+        }
+        true
+    }
+
+    /// Updates a stored response with the headers of a `304 Not Modified`
+    /// response to a request revalidating it, and returns it.
+    pub fn refresh(
+        &self,
+        key: &CacheKey,
+        request_headers: &HeaderMap,
+        not_modified: &HeaderMap,
+        now: Instant,
+    ) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(&key.uri)?;
+        let mut headers = entry.headers.clone();
+        for name in not_modified.keys() {
+            headers.remove(name);
+        }
+        for (name, value) in not_modified {
+            headers.append(name, value.clone());
+        }
+        let Some(freshness) = self.freshness(key, request_headers, entry.status, &headers) else {
+            entries.remove(&key.uri);
+            return None;
+        };
+        entry.headers = headers;
+        entry.stored_at = now;
+        entry.freshness = freshness;
+        entry.revalidating = false;
+        Some(entry.cached(Duration::ZERO, false))
+    }
+
+    /// Lets the next request for a stale response revalidate it, after an
+    /// attempt which didn't replace it.
+    pub fn end_revalidation(&self, key: &CacheKey) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&key.uri) {
+            entry.revalidating = false;
+        }
+    }
+
+    fn freshness(
+        &self,
+        key: &CacheKey,
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Freshness> {
+        if !CACHEABLE_STATUSES.contains(&status.as_u16()) {
+            return None;
+        }
+        let directives = CacheControl::parse(headers);
+        if directives.no_store || directives.no_cache || directives.private {
+            return None;
+        }
+        if request_headers.contains_key(AUTHORIZATION)
+            && !directives.public
+            && directives.s_maxage.is_none()
+        {
+            return None;
+        }
+        if vary_names(headers).any(|name| name == "*") {
+            return None;
+        }
+        let rule = &self.config.rules[key.rule];
+        let lifetime = directives
+            .s_maxage
+            .or(directives.max_age)
+            .or(rule.default_ttl)?;
+        let initial_age = headers
+            .get(AGE)
+            .and_then(|age| age.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let (stale_while_revalidate, stale_if_error) = if directives.must_revalidate {
+            (Duration::ZERO, Duration::ZERO)
+        } else {
+            (
+                directives
+                    .stale_while_revalidate
+                    .or(rule.stale_while_revalidate)
+                    .unwrap_or_default(),
+                directives
+                    .stale_if_error
+                    .or(rule.stale_if_error)
+                    .unwrap_or_default(),
+            )
+        };
+        let freshness = Freshness {
+            initial_age,
+            fresh_for: lifetime.saturating_sub(initial_age),
+            stale_while_revalidate,
+            stale_if_error,
+        };
+        let usable = !freshness.fresh_for.is_zero()
+            || !freshness.stale_while_revalidate.is_zero()
+            || !freshness.stale_if_error.is_zero();
+        usable.then_some(freshness)
+    }
+}
+
+impl Entry {
+    fn vary_matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_headers.get(name) == value.as_ref())
+    }
+
+    fn has_validators(&self) -> bool {
+        self.headers.contains_key(ETAG) || self.headers.contains_key(LAST_MODIFIED)
+    }
+
+    fn cached(&self, elapsed: Duration, serve_on_error: bool) -> CachedResponse {
+        CachedResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            age: self.freshness.initial_age + elapsed,
+            serve_on_error,
+        }
+    }
+
+    /// Returns whether the response can no longer be served in any way.
+    fn is_expired(&self, now: Instant) -> bool {
+        let Freshness {
+            fresh_for,
+            stale_while_revalidate,
+            stale_if_error,
+            ..
+        } = self.freshness;
+        let usable_for = fresh_for + stale_while_revalidate.max(stale_if_error);
+        now.saturating_duration_since(self.stored_at) >= usable_for && !self.has_validators()
+    }
+}
+
+/// Makes room for a new entry by dropping expired responses, and then the
+/// oldest ones.
+fn evict(entries: &mut HashMap<String, Entry>, max_entries: usize, now: Instant) {
+    entries.retain(|_, entry| !entry.is_expired(now));
+    while entries.len() > max_entries {
+        let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.stored_at)
+            .map(|(uri, _)| uri.clone())
+        else {
+            break;
+        };
+        entries.remove(&oldest);
+    }
+}
+
+/// The names of the request headers a response varies on.
+fn vary_names(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+}
+
+/// A response served from the cache.
+pub(crate) struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    /// How long ago the response was generated.
+    age: Duration,
+    /// Whether the response may be served if the server fails.
+    serve_on_error: bool,
+}
+
+impl CachedResponse {
+    /// Returns whether the response may be served if the server can't be
+    /// reached or fails.
+    pub fn serve_on_error(&self) -> bool {
+        self.serve_on_error
+    }
+
+    /// Makes a request revalidating the response conditional, so that the
+    /// server needn't send it again if it hasn't changed.
+    ///
+    /// Returns false, leaving the request alone, if the request already has
+    /// conditions or the response has no validators.
+    pub fn add_validators(&self, request_headers: &mut HeaderMap) -> bool {
+        let conditional = [
+            IF_MATCH,
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
+            IF_UNMODIFIED_SINCE,
+            IF_RANGE,
+        ]
+        .iter()
+        .any(|name| request_headers.contains_key(name));
+        if conditional {
+            return false;
+        }
+        let mut added = false;
+        if let Some(etag) = self.headers.get(ETAG) {
+            request_headers.insert(IF_NONE_MATCH, etag.clone());
+            added = true;
+        }
+        if let Some(last_modified) = self.headers.get(LAST_MODIFIED) {
+            request_headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+            added = true;
+        }
+        added
+    }
+
+    /// Converts the response to an [`http::Response`] with its current age.
+    pub fn to_http(&self) -> http::Response<Bytes> {
+        let mut response = http::Response::new(self.body.clone());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert(AGE, HeaderValue::from(self.age.as_secs()));
+        response
+    }
+}
+
+/// The `Cache-Control` directives the cache acts on.
+#[derive(Debug, Default, PartialEq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    must_revalidate: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = Self::default();
+        let values = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok());
+        for directive in values.flat_map(|value| value.split(',')) {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim(), Some(argument.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = || {
+                argument
+                    .and_then(|argument| argument.parse().ok())
+                    .map(Duration::from_secs)
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => directives.no_store = true,
+                "no-cache" => directives.no_cache = true,
+                "private" => directives.private = true,
+                "public" => directives.public = true,
+                "must-revalidate" | "proxy-revalidate" => directives.must_revalidate = true,
+                "max-age" => directives.max_age = seconds(),
+                "s-maxage" => directives.s_maxage = seconds(),
+                "stale-while-revalidate" => directives.stale_while_revalidate = seconds(),
+                "stale-if-error" => directives.stale_if_error = seconds(),
+                _ => {}
+            }
+        }
+        directives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(rule: CacheRule) -> ResponseCache {
+        ResponseCache::new(ResponseCacheConfig {
+            rules: vec![rule],
+            max_entries: 2,
+            max_body_bytes: 16,
+        })
+    }
+
+    fn rule() -> CacheRule {
+        CacheRule {
+            hosts: vec!["api.example.com".into()],
+            path_prefix: "/v1/".into(),
+            default_ttl: None,
+            stale_while_revalidate: None,
+            stale_if_error: None,
+        }
+    }
+
+    fn get(uri: &str) -> Request<()> {
+        Request::get(uri).body(()).unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    fn store(cache: &ResponseCache, uri: &str, response_headers: &HeaderMap, now: Instant) -> bool {
+        let key = cache.key(&get(uri)).unwrap();
+        cache.store(
+            &key,
+            &HeaderMap::new(),
+            StatusCode::OK,
+            response_headers,
+            Bytes::from_static(b"cached"),
+            now,
+        )
+    }
+
+    fn lookup(cache: &ResponseCache, uri: &str, now: Instant) -> Lookup {
+        let key = cache.key(&get(uri)).unwrap();
+        cache.lookup(&key, &HeaderMap::new(), now)
+    }
+
+    #[test]
+    fn only_matching_gets_are_cached() {
+        let cache = cache(rule());
+        assert!(cache
+            .key(&get("https://api.example.com/v1/items"))
+            .is_some());
+        assert!(cache
+            .key(&get("https://api.example.com/v2/items"))
+            .is_none());
+        assert!(cache.key(&get("https://example.com/v1/items")).is_none());
+        let post = Request::post("https://api.example.com/v1/items")
+            .body(())
+            .unwrap();
+        assert!(cache.key(&post).is_none());
+        let no_cache = Request::get("https://api.example.com/v1/items")
+            .header("cache-control", "no-cache")
+            .body(())
+            .unwrap();
+        assert!(cache.key(&no_cache).is_none());
+    }
+
+    #[test]
+    fn serves_fresh_then_stale_while_revalidating() {
+        let cache = cache(rule());
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        let response_headers =
+            headers(&[("cache-control", "max-age=60, stale-while-revalidate=30")]);
+        assert!(store(&cache, uri, &response_headers, now));
+
+        let Lookup::Fresh(cached) = lookup(&cache, uri, now + Duration::from_secs(10)) else {
+            panic!("expected a fresh response");
+        };
+        let response = cached.to_http();
+        assert_eq!(response.body().as_ref(), b"cached");
+        assert_eq!(response.headers()[AGE], "10");
+
+        let later = now + Duration::from_secs(70);
+        let Lookup::Stale { revalidate, .. } = lookup(&cache, uri, later) else {
+            panic!("expected a stale response");
+        };
+        assert!(revalidate);
+        // Only the first request revalidates
+        let Lookup::Stale { revalidate, .. } = lookup(&cache, uri, later) else {
+            panic!("expected a stale response");
+        };
+        assert!(!revalidate);
+
+        assert!(matches!(
+            lookup(&cache, uri, now + Duration::from_secs(100)),
+            Lookup::Miss { stale: None }
+        ));
+    }
+
+    #[test]
+    fn serves_stale_on_error_within_window() {
+        let mut rule = rule();
+        rule.stale_if_error = Some(Duration::from_secs(300));
+        let cache = cache(rule);
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        assert!(store(
+            &cache,
+            uri,
+            &headers(&[("cache-control", "max-age=60")]),
+            now
+        ));
+
+        let Lookup::Miss { stale: Some(stale) } =
+            lookup(&cache, uri, now + Duration::from_secs(120))
+        else {
+            panic!("expected a stale fallback");
+        };
+        assert!(stale.serve_on_error());
+        assert!(matches!(
+            lookup(&cache, uri, now + Duration::from_secs(400)),
+            Lookup::Miss { stale: None }
+        ));
+    }
+
+    #[test]
+    fn must_revalidate_disables_stale_responses() {
+        let mut rule = rule();
+        rule.stale_if_error = Some(Duration::from_secs(300));
+        let cache = cache(rule);
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        let response_headers = headers(&[(
+            "cache-control",
+            "max-age=60, must-revalidate, stale-while-revalidate=30",
+        )]);
+        assert!(store(&cache, uri, &response_headers, now));
+        assert!(matches!(
+            lookup(&cache, uri, now + Duration::from_secs(70)),
+            Lookup::Miss { stale: None }
+        ));
+    }
+
+    #[test]
+    fn uncacheable_responses_are_not_stored() {
+        let cache = cache(rule());
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        for cache_control in ["no-store", "private, max-age=60", "no-cache"] {
+            let response_headers = headers(&[("cache-control", cache_control)]);
+            assert!(
+                !store(&cache, uri, &response_headers, now),
+                "{cache_control}"
+            );
+        }
+        // Without explicit freshness, the rule's default TTL is needed
+        assert!(!store(&cache, uri, &HeaderMap::new(), now));
+
+        let key = cache.key(&get(uri)).unwrap();
+        let authorized = headers(&[("authorization", "Bearer token")]);
+        let max_age = headers(&[("cache-control", "max-age=60")]);
+        assert!(!cache.would_store(&key, &authorized, StatusCode::OK, &max_age));
+        let public = headers(&[("cache-control", "public, max-age=60")]);
+        assert!(cache.would_store(&key, &authorized, StatusCode::OK, &public));
+        assert!(!cache.would_store(
+            &key,
+            &HeaderMap::new(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &max_age
+        ));
+        let large = headers(&[("cache-control", "max-age=60"), ("content-length", "17")]);
+        assert!(!cache.would_store(&key, &HeaderMap::new(), StatusCode::OK, &large));
+    }
+
+    #[test]
+    fn default_ttl_applies_and_age_counts() {
+        let mut rule = rule();
+        rule.default_ttl = Some(Duration::from_secs(60));
+        let cache = cache(rule);
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        assert!(store(&cache, uri, &headers(&[("age", "50")]), now));
+        assert!(matches!(
+            lookup(&cache, uri, now + Duration::from_secs(5)),
+            Lookup::Fresh(_)
+        ));
+        assert!(matches!(
+            lookup(&cache, uri, now + Duration::from_secs(15)),
+            Lookup::Miss { stale: None }
+        ));
+    }
+
+    #[test]
+    fn responses_vary_on_request_headers() {
+        let cache = cache(rule());
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        let key = cache.key(&get(uri)).unwrap();
+        let json = headers(&[("accept", "application/json")]);
+        let response_headers = headers(&[("cache-control", "max-age=60"), ("vary", "Accept")]);
+        assert!(cache.store(
+            &key,
+            &json,
+            StatusCode::OK,
+            &response_headers,
+            Bytes::new(),
+            now
+        ));
+        assert!(matches!(cache.lookup(&key, &json, now), Lookup::Fresh(_)));
+        let xml = headers(&[("accept", "application/xml")]);
+        assert!(matches!(
+            cache.lookup(&key, &xml, now),
+            Lookup::Miss { stale: None }
+        ));
+    }
+
+    #[test]
+    fn not_modified_refreshes_stored_response() {
+        let cache = cache(rule());
+        let uri = "https://api.example.com/v1/items";
+        let now = Instant::now();
+        let response_headers = headers(&[("cache-control", "max-age=60"), ("etag", "\"v1\"")]);
+        assert!(store(&cache, uri, &response_headers, now));
+
+        let later = now + Duration::from_secs(120);
+        let Lookup::Miss { stale: Some(stale) } = lookup(&cache, uri, later) else {
+            panic!("expected a stale response to revalidate");
+        };
+        assert!(!stale.serve_on_error());
+        let mut request_headers = HeaderMap::new();
+        assert!(stale.add_validators(&mut request_headers));
+        assert_eq!(request_headers[IF_NONE_MATCH], "\"v1\"");
+
+        let key = cache.key(&get(uri)).unwrap();
+        let refreshed = cache
+            .refresh(&key, &HeaderMap::new(), &HeaderMap::new(), later)
+            .unwrap();
+        assert_eq!(refreshed.to_http().body().as_ref(), b"cached");
+        assert!(matches!(
+            lookup(&cache, uri, later + Duration::from_secs(30)),
+            Lookup::Fresh(_)
+        ));
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let cache = cache(rule());
+        let now = Instant::now();
+        let response_headers = headers(&[("cache-control", "max-age=60")]);
+        for (index, path) in ["a", "b", "c"].into_iter().enumerate() {
+            let uri = format!("https://api.example.com/v1/{path}");
+            let stored_at = now + Duration::from_secs(index as u64);
+            assert!(store(&cache, &uri, &response_headers, stored_at));
+        }
+        let later = now + Duration::from_secs(5);
+        assert!(matches!(
+            lookup(&cache, "https://api.example.com/v1/a", later),
+            Lookup::Miss { stale: None }
+        ));
+        assert!(matches!(
+            lookup(&cache, "https://api.example.com/v1/c", later),
+            Lookup::Fresh(_)
+        ));
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let directives = CacheControl::parse(&headers(&[(
+            "cache-control",
+            "public, Max-Age=60, s-maxage=\"120\", stale-if-error=600",
+        )]));
+        assert_eq!(
+            directives,
+            CacheControl {
+                public: true,
+                max_age: Some(Duration::from_secs(60)),
+                s_maxage: Some(Duration::from_secs(120)),
+                stale_if_error: Some(Duration::from_secs(600)),
+                ..Default::default()
+            }
+        );
+    }
+}
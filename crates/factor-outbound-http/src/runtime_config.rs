@@ -0,0 +1,75 @@
+pub mod spin;
+
+use std::{sync::Arc, time::Duration};
+
+use spin_factor_outbound_networking::DnsResolver;
+
+use crate::{cassette::CassetteConfig, response_cache::ResponseCacheConfig, signing::SigningRule};
+
+/// Runtime configuration for outbound HTTP.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// Connection pooling settings for outbound HTTP requests.
+    pub connection_pooling: ConnectionPoolingConfig,
+    /// Rules for signing requests to particular hosts.
+    pub request_signing: Vec<SigningRule>,
+    /// Where outbound interactions are recorded to and replayed from, if
+    /// anywhere.
+    pub cassette: Option<CassetteConfig>,
+    /// Which responses to outbound GET requests are cached, if any.
+    pub response_cache: Option<ResponseCacheConfig>,
+}
+
+/// Connection pooling settings for outbound HTTP requests, made through
+/// either `fermyon:spin/http` or `wasi:http`.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionPoolingConfig {
+    /// The maximum number of idle connections kept open per host.
+    ///
+    /// If `None`, the number is unlimited.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept open before being closed.
+    ///
+    /// If `None`, connections are closed after 90 seconds idle.
+    pub idle_timeout: Option<Duration>,
+    /// If true, HTTP/2 is used without negotiation for all requests.
+    pub http2_prior_knowledge: bool,
+}
+
+impl ConnectionPoolingConfig {
+    /// Builds a [`reqwest::Client`] with these settings applied, which looks
+    /// up hosts with `dns_resolver`.
+    pub(crate) fn build_client(&self, dns_resolver: &DnsResolver) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .dns_resolver(Arc::new(ReqwestResolver(dns_resolver.clone())));
+        if let Some(max_idle_per_host) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle_per_host);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder.build().unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to build configured outbound HTTP client; using defaults: {err}"
+            );
+            reqwest::Client::new()
+        })
+    }
+}
+
+/// Adapts a [`DnsResolver`] to [`reqwest`].
+struct ReqwestResolver(DnsResolver);
+
+impl reqwest::dns::Resolve for ReqwestResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            // reqwest sets the port of the addresses itself
+            let addrs = resolver.lookup_host(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use spin_factors::{anyhow, runtime_config::toml::GetTomlValue};
+
+use super::{ConnectionPoolingConfig, RuntimeConfig};
+use crate::cassette::{CassetteConfig, CassetteMode};
+use crate::response_cache::{CacheRule, ResponseCacheConfig};
+use crate::signing::{AwsSigV4Signer, HmacSigner, RequestSigner, SignatureEncoding, SigningRule};
+
+/// Get the runtime configuration for outbound HTTP from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [outbound_http]
+/// max_idle_connections_per_host = 10
+/// idle_connection_timeout_secs = 90
+/// http2_prior_knowledge = false
+///
+/// [[outbound_http.signing]]
+/// type = "aws_sigv4"
+/// hosts = ["*.s3.us-east-1.amazonaws.com"]
+/// region = "us-east-1"
+/// service = "s3"
+/// access_key_id = "..."
+/// secret_access_key = "..."
+/// session_token = "..." # optional
+///
+/// [[outbound_http.signing]]
+/// type = "hmac_sha256"
+/// hosts = ["hooks.example.com"]
+/// secret = "..."
+/// header = "x-signature" # default
+/// encoding = "hex" # or "base64"; default "hex"
+/// prefix = "sha256=" # optional
+/// timestamp_header = "x-timestamp" # optional
+///
+/// [outbound_http.cassette]
+/// path = "cassettes/outbound.json"
+/// mode = "auto" # or "record" or "replay"; default "auto"
+/// match_body = false # default
+/// match_headers = ["x-api-version"] # optional
+///
+/// [outbound_http.cache]
+/// max_entries = 1000 # default
+/// max_body_bytes = 1048576 # default
+///
+/// [[outbound_http.cache.rules]]
+/// hosts = ["api.example.com", "*.example.net"]
+/// path_prefix = "/v1/" # default "/"
+/// default_ttl_secs = 60 # optional; used without max-age
+/// stale_while_revalidate_secs = 30 # optional; used without the directive
+/// stale_if_error_secs = 3600 # optional; used without the directive
+/// ```
+///
+/// A relative cassette path is resolved against `runtime_config_dir`.
+pub fn config_from_table(
+    table: &impl GetTomlValue,
+    runtime_config_dir: &Path,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("outbound_http") else {
+        return Ok(None);
+    };
+    let toml: OutboundHttpToml = value.clone().try_into()?;
+    Ok(Some(RuntimeConfig {
+        connection_pooling: ConnectionPoolingConfig {
+            max_idle_per_host: toml.max_idle_connections_per_host,
+            idle_timeout: toml.idle_connection_timeout_secs.map(Duration::from_secs),
+            http2_prior_knowledge: toml.http2_prior_knowledge,
+        },
+        request_signing: toml
+            .signing
+            .into_iter()
+            .map(SigningRuleToml::into_rule)
+            .collect::<anyhow::Result<_>>()?,
+        cassette: toml.cassette.map(|cassette| CassetteConfig {
+            path: runtime_config_dir.join(cassette.path),
+            mode: match cassette.mode {
+                CassetteModeToml::Auto => CassetteMode::Auto,
+                CassetteModeToml::Record => CassetteMode::Record,
+                CassetteModeToml::Replay => CassetteMode::Replay,
+            },
+            match_body: cassette.match_body,
+            match_headers: cassette.match_headers,
+        }),
+        response_cache: toml.cache.map(CacheToml::into_config).transpose()?,
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutboundHttpToml {
+    max_idle_connections_per_host: Option<usize>,
+    idle_connection_timeout_secs: Option<u64>,
+    #[serde(default)]
+    http2_prior_knowledge: bool,
+    #[serde(default)]
+    signing: Vec<SigningRuleToml>,
+    cassette: Option<CassetteToml>,
+    cache: Option<CacheToml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CassetteToml {
+    path: PathBuf,
+    #[serde(default)]
+    mode: CassetteModeToml,
+    #[serde(default)]
+    match_body: bool,
+    #[serde(default)]
+    match_headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CacheToml {
+    max_entries: Option<usize>,
+    max_body_bytes: Option<usize>,
+    rules: Vec<CacheRuleToml>,
+}
+
+impl CacheToml {
+    fn into_config(self) -> anyhow::Result<ResponseCacheConfig> {
+        let max_entries = self
+            .max_entries
+            .unwrap_or(ResponseCacheConfig::DEFAULT_MAX_ENTRIES);
+        anyhow::ensure!(
+            max_entries > 0,
+            "outbound_http.cache.max_entries must be at least 1"
+        );
+        let rules = self
+            .rules
+            .into_iter()
+            .map(|rule| {
+                anyhow::ensure!(
+                    !rule.hosts.is_empty(),
+                    "outbound_http.cache.rules must list at least one host"
+                );
+                Ok(CacheRule {
+                    hosts: rule.hosts,
+                    path_prefix: rule.path_prefix.unwrap_or_else(|| "/".into()),
+                    default_ttl: rule.default_ttl_secs.map(Duration::from_secs),
+                    stale_while_revalidate: rule
+                        .stale_while_revalidate_secs
+                        .map(Duration::from_secs),
+                    stale_if_error: rule.stale_if_error_secs.map(Duration::from_secs),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(ResponseCacheConfig {
+            rules,
+            max_entries,
+            max_body_bytes: self
+                .max_body_bytes
+                .unwrap_or(ResponseCacheConfig::DEFAULT_MAX_BODY_BYTES),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CacheRuleToml {
+    hosts: Vec<String>,
+    path_prefix: Option<String>,
+    default_ttl_secs: Option<u64>,
+    stale_while_revalidate_secs: Option<u64>,
+    stale_if_error_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CassetteModeToml {
+    #[default]
+    Auto,
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SigningRuleToml {
+    AwsSigv4 {
+        hosts: Vec<String>,
+        region: String,
+        service: String,
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+    },
+    HmacSha256 {
+        hosts: Vec<String>,
+        secret: String,
+        header: Option<String>,
+        #[serde(default)]
+        encoding: SignatureEncodingToml,
+        #[serde(default)]
+        prefix: String,
+        timestamp_header: Option<String>,
+    },
+}
+
+impl SigningRuleToml {
+    fn into_rule(self) -> anyhow::Result<SigningRule> {
+        let (hosts, signer) = match self {
+            Self::AwsSigv4 {
+                hosts,
+                region,
+                service,
+                access_key_id,
+                secret_access_key,
+                session_token,
+            } => (
+                hosts,
+                RequestSigner::AwsSigV4(AwsSigV4Signer {
+                    region,
+                    service,
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                }),
+            ),
+            Self::HmacSha256 {
+                hosts,
+                secret,
+                header,
+                encoding,
+                prefix,
+                timestamp_header,
+            } => {
+                let header = header.as_deref().unwrap_or("x-signature").parse()?;
+                let timestamp_header = timestamp_header.map(|h| h.parse()).transpose()?;
+                let encoding = match encoding {
+                    SignatureEncodingToml::Hex => SignatureEncoding::Hex,
+                    SignatureEncodingToml::Base64 => SignatureEncoding::Base64,
+                };
+                (
+                    hosts,
+                    RequestSigner::HmacSha256(HmacSigner {
+                        secret,
+                        header,
+                        encoding,
+                        prefix,
+                        timestamp_header,
+                    }),
+                )
+            }
+        };
+        anyhow::ensure!(
+            !hosts.is_empty(),
+            "outbound_http.signing rules must list at least one host"
+        );
+        Ok(SigningRule { hosts, signer })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SignatureEncodingToml {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_connection_pooling_settings() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_http]
+            max_idle_connections_per_host = 4
+            idle_connection_timeout_secs = 30
+            http2_prior_knowledge = true
+        };
+        let config = config_from_table(&table, Path::new("."))?.unwrap();
+        let pooling = config.connection_pooling;
+        assert_eq!(pooling.max_idle_per_host, Some(4));
+        assert_eq!(pooling.idle_timeout, Some(Duration::from_secs(30)));
+        assert!(pooling.http2_prior_knowledge);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_table_is_none() -> anyhow::Result<()> {
+        assert!(config_from_table(&toml::Table::new(), Path::new("."))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_signing_rules() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [[outbound_http.signing]]
+            type = "aws_sigv4"
+            hosts = ["*.s3.us-east-1.amazonaws.com"]
+            region = "us-east-1"
+            service = "s3"
+            access_key_id = "AKID"
+            secret_access_key = "secret"
+
+            [[outbound_http.signing]]
+            type = "hmac_sha256"
+            hosts = ["hooks.example.com"]
+            secret = "secret"
+            encoding = "base64"
+            timestamp_header = "x-timestamp"
+        };
+        let config = config_from_table(&table, Path::new("."))?.unwrap();
+        let [aws, hmac] = &config.request_signing[..] else {
+            panic!("expected two signing rules");
+        };
+        assert_eq!(aws.hosts, ["*.s3.us-east-1.amazonaws.com"]);
+        let RequestSigner::AwsSigV4(aws) = &aws.signer else {
+            panic!("expected a SigV4 signer");
+        };
+        assert_eq!(aws.service, "s3");
+        let RequestSigner::HmacSha256(hmac) = &hmac.signer else {
+            panic!("expected an HMAC signer");
+        };
+        assert_eq!(hmac.header, "x-signature");
+        assert_eq!(hmac.encoding, SignatureEncoding::Base64);
+        assert_eq!(hmac.timestamp_header.as_ref().unwrap(), "x-timestamp");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_cassette() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_http.cassette]
+            path = "cassettes/outbound.json"
+            mode = "replay"
+            match_headers = ["x-api-version"]
+        };
+        let config = config_from_table(&table, Path::new("/app"))?.unwrap();
+        assert_eq!(
+            config.cassette,
+            Some(CassetteConfig {
+                path: PathBuf::from("/app/cassettes/outbound.json"),
+                mode: CassetteMode::Replay,
+                match_body: false,
+                match_headers: vec!["x-api-version".into()],
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_response_cache() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_http.cache]
+            max_entries = 10
+
+            [[outbound_http.cache.rules]]
+            hosts = ["api.example.com"]
+            stale_if_error_secs = 3600
+        };
+        let config = config_from_table(&table, Path::new("."))?.unwrap();
+        assert_eq!(
+            config.response_cache,
+            Some(ResponseCacheConfig {
+                rules: vec![CacheRule {
+                    hosts: vec!["api.example.com".into()],
+                    path_prefix: "/".into(),
+                    default_ttl: None,
+                    stale_while_revalidate: None,
+                    stale_if_error: Some(Duration::from_secs(3600)),
+                }],
+                max_entries: 10,
+                max_body_bytes: ResponseCacheConfig::DEFAULT_MAX_BODY_BYTES,
+            })
+        );
+
+        let table: toml::Table = toml::toml! {
+            [[outbound_http.cache.rules]]
+            hosts = []
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signing_rules_need_hosts() {
+        let table: toml::Table = toml::toml! {
+            [[outbound_http.signing]]
+            type = "hmac_sha256"
+            hosts = []
+            secret = "secret"
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let table: toml::Table = toml::toml! {
+            [outbound_http]
+            max_connections = 4
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+}
@@ -1,11 +1,14 @@
+use std::time::SystemTime;
+
 use http_body_util::BodyExt;
+use spin_factor_fault_injection::Interface;
 use spin_world::v1::{
     http as spin_http,
     http_types::{self, HttpError, Method, Request, Response},
 };
 use tracing::{field::Empty, instrument, Level, Span};
 
-use crate::intercept::InterceptOutcome;
+use crate::{cassette::RecordedResponse, intercept::InterceptOutcome, ActiveRequest};
 
 impl spin_http::Host for crate::InstanceState {
     #[instrument(name = "spin_outbound_http.send_request", skip_all, err(level = Level::INFO),
@@ -69,6 +72,7 @@ impl spin_http::Host for crate::InstanceState {
             HttpError::RuntimeError
         })?;
 
+        self.inject_request_context(req.headers_mut());
         spin_telemetry::inject_trace_context(req.headers_mut());
 
         if let Some(interceptor) = &self.request_interceptor {
@@ -85,18 +89,93 @@ impl spin_http::Host for crate::InstanceState {
             }
         }
 
+        // Requests are matched against the cassette before they are signed, so
+        // that signatures are neither recorded nor have to match
+        let recording = match &self.cassette {
+            Some(cassette) => {
+                let (parts, body) = req.into_parts();
+                let recorded = cassette.request(&parts, &body);
+                if let Some(resp) = cassette.replay(&recorded) {
+                    span.record("http.response.status_code", resp.status);
+                    let body = resp.body();
+                    return Ok(Response {
+                        status: resp.status,
+                        headers: Some(resp.headers),
+                        body: Some(body),
+                    });
+                }
+                if !cassette.records() {
+                    tracing::error!(
+                        "No recorded response to outbound request {} {}",
+                        recorded.method,
+                        recorded.uri
+                    );
+                    return Err(HttpError::RuntimeError);
+                }
+                req = http::Request::from_parts(parts, body);
+                Some((cassette.clone(), recorded))
+            }
+            None => None,
+        };
+
+        if let Some(signer) = req
+            .uri()
+            .host()
+            .and_then(|host| self.request_signing.signer_for(host))
+        {
+            let (mut parts, body) = req.into_parts();
+            signer
+                .sign(&mut parts, &body, SystemTime::now())
+                .map_err(|err| {
+                    tracing::error!("Failed to sign outbound request: {err:#}");
+                    HttpError::RuntimeError
+                })?;
+            req = http::Request::from_parts(parts, body);
+        }
+
         // Convert http::Request to reqwest::Request
         let req = reqwest::Request::try_from(req).map_err(|_| HttpError::InvalidUrl)?;
 
         // Allow reuse of Client's internal connection pool for multiple requests
         // in a single component execution
-        let client = self.spin_http_client.get_or_insert_with(Default::default);
+        let connection_pooling = &self.connection_pooling;
+        let dns_resolver = &self.dns_resolver;
+        let client = self
+            .spin_http_client
+            .get_or_insert_with(|| connection_pooling.build_client(dns_resolver));
+
+        let body_len = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, <[u8]>::len);
+        if let Some(injector) = &self.fault_injector {
+            injector
+                .inject(Interface::OutboundHttp)
+                .await
+                .map_err(|fault| {
+                    tracing::info!("Failing outbound request: {fault}");
+                    HttpError::RuntimeError
+                })?;
+        }
+        let _permit = self.egress_throttle.acquire(body_len).await;
 
-        let resp = client.execute(req).await.map_err(log_reqwest_error)?;
+        let active = ActiveRequest::new(req.url().host_str().unwrap_or_default().to_owned());
+        let resp = client.execute(req).await;
+        drop(active);
+        let resp = resp.map_err(log_reqwest_error)?;
 
         tracing::trace!("Returning response from outbound request to {req_url}");
         span.record("http.response.status_code", resp.status().as_u16());
-        response_from_reqwest(resp).await
+        let resp = response_from_reqwest(resp).await?;
+        if let Some((cassette, recorded)) = recording {
+            let headers = resp.headers.clone().unwrap_or_default();
+            let body = resp.body.as_deref().unwrap_or_default();
+            cassette.record(
+                recorded,
+                RecordedResponse::from_pairs(resp.status, headers, body),
+            );
+        }
+        Ok(resp)
     }
 }
 
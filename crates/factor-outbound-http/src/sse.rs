@@ -0,0 +1,547 @@
+//! Host implementation of the `spin:sse/client` interface.
+//!
+//! Events are parsed as specified for `EventSource` in the HTML standard,
+//! over HTTP/1.1.
+
+use std::{borrow::Cow, collections::VecDeque, time::Duration};
+
+use bytes::Bytes;
+use http::{
+    header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE, HOST},
+    uri::Scheme,
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use spin_factor_outbound_networking::{BlockedNetworks, DnsResolver, TlsClientConfig};
+use spin_factors::wasmtime::component::Resource;
+use spin_world::spin::sse::client::{
+    self, Error, Event, EventStream, Reconnect, Request, UnexpectedResponse,
+};
+use tokio::time::timeout;
+use tracing::{field::Empty, instrument, Level, Span};
+use wasmtime_wasi::runtime::AbortOnDropJoinHandle;
+use wasmtime_wasi_http::io::TokioIo;
+
+use crate::{
+    connect::{connect_io, CONNECT_TIMEOUT},
+    InstanceState,
+};
+
+/// The largest event accepted.
+const MAX_EVENT_SIZE: usize = 1024 * 1024;
+/// How much of a response which isn't an event stream is returned.
+const MAX_UNEXPECTED_BODY: usize = 64 * 1024;
+/// How long to wait before reconnecting, unless the server sets `retry`.
+const DEFAULT_RETRY: Duration = Duration::from_secs(1);
+/// The longest wait before reconnecting, unless the server sets a longer
+/// `retry`.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+impl client::Host for InstanceState {
+    #[instrument(name = "spin_outbound_sse.connect", skip_all, err(level = Level::INFO),
+        fields(otel.kind = "client", url.full = %request.url, server.address = Empty))]
+    async fn connect(
+        &mut self,
+        request: Request,
+        reconnect: Option<Reconnect>,
+    ) -> Result<Resource<EventStream>, Error> {
+        let uri = parse_url(&request.url)?;
+        let is_allowed = self
+            .allowed_hosts
+            .check_url(&request.url, "https")
+            .await
+            .unwrap_or(false);
+        if !is_allowed {
+            return Err(Error::DestinationNotAllowed);
+        }
+
+        let method = match &request.method {
+            Some(method) => Method::from_bytes(method.as_bytes())
+                .map_err(|_| Error::InvalidRequest(format!("invalid method {method:?}")))?,
+            None => Method::GET,
+        };
+        let mut headers = HeaderMap::new();
+        for (key, value) in request.headers {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| Error::InvalidRequest(format!("invalid header name {key:?}")))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| Error::InvalidRequest(format!("invalid value for header {key:?}")))?;
+            headers.append(name, value);
+        }
+        let authority = uri.authority().map(|a| a.as_str()).unwrap_or_default();
+        headers.insert(HOST, HeaderValue::from_str(authority).unwrap());
+        headers
+            .entry(ACCEPT)
+            .or_insert(HeaderValue::from_static("text/event-stream"));
+        headers
+            .entry(CACHE_CONTROL)
+            .or_insert(HeaderValue::from_static("no-cache"));
+        self.inject_request_context(&mut headers);
+        spin_telemetry::inject_trace_context(&mut headers);
+
+        let host = uri.host().unwrap_or_default();
+        Span::current().record("server.address", host);
+        let tls_client_config = (uri.scheme() == Some(&Scheme::HTTPS))
+            .then(|| self.component_tls_configs.get_client_config(host).clone());
+        let body = Bytes::from(request.body.unwrap_or_default());
+        let _permit = self.egress_throttle.acquire(body.len()).await;
+
+        let mut source = EventSource {
+            target: Target {
+                method,
+                uri,
+                headers,
+                body,
+                tls_client_config,
+                blocked_networks: self.blocked_networks.clone(),
+                dns_resolver: self.dns_resolver.clone(),
+            },
+            reconnect,
+            connection: None,
+            parser: EventParser::default(),
+            attempts: 0,
+            ended: false,
+        };
+        source.open().await?;
+        self.sse_streams
+            .push(source)
+            .map(Resource::new_own)
+            .map_err(|_| transport_error("too many open event streams"))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl client::HostEventStream for InstanceState {
+    async fn next(&mut self, stream: Resource<EventStream>) -> Result<Option<Event>, Error> {
+        let source = self
+            .sse_streams
+            .get_mut(stream.rep())
+            .ok_or_else(|| transport_error("unknown event stream"))?;
+        source.next().await
+    }
+
+    async fn drop(&mut self, stream: Resource<EventStream>) -> anyhow::Result<()> {
+        self.sse_streams.remove(stream.rep());
+        Ok(())
+    }
+}
+
+/// An open event stream.
+pub(crate) struct EventSource {
+    target: Target,
+    reconnect: Option<Reconnect>,
+    /// The current connection, if there is one.
+    connection: Option<Connection>,
+    parser: EventParser,
+    /// The number of attempts to reconnect since an event was last received.
+    attempts: u32,
+    ended: bool,
+}
+
+/// Where an event stream is requested from.
+struct Target {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+    tls_client_config: Option<TlsClientConfig>,
+    blocked_networks: BlockedNetworks,
+    dns_resolver: DnsResolver,
+}
+
+struct Connection {
+    body: Incoming,
+    // Drives the HTTP connection for as long as the stream is read.
+    _worker: AbortOnDropJoinHandle<()>,
+}
+
+impl EventSource {
+    /// Returns the next event, or `None` once the stream has ended.
+    async fn next(&mut self) -> Result<Option<Event>, Error> {
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                self.attempts = 0;
+                return Ok(Some(event));
+            }
+            if self.ended {
+                return Ok(None);
+            }
+            let Some(connection) = &mut self.connection else {
+                self.reconnect().await?;
+                continue;
+            };
+            let frame = connection.body.frame().await;
+            match frame {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        self.parser.push(&data)?;
+                    }
+                }
+                Some(Err(err)) => {
+                    self.connection_lost(Some(format!("error reading stream: {err}")))?
+                }
+                None => self.connection_lost(None)?,
+            }
+        }
+    }
+
+    /// Handles the loss of the connection, either because it failed with
+    /// `error` or because the server ended the stream.
+    fn connection_lost(&mut self, error: Option<String>) -> Result<(), Error> {
+        self.connection = None;
+        self.parser.discard_incomplete();
+        match (&self.reconnect, error) {
+            (Some(_), Some(error)) => {
+                tracing::debug!("Event stream connection failed: {error}");
+            }
+            (Some(reconnect), None) if reconnect.on_end => {}
+            (_, None) => self.ended = true,
+            (None, Some(error)) => return Err(transport_error(error)),
+        }
+        Ok(())
+    }
+
+    /// Reconnects, waiting longer before each attempt.
+    async fn reconnect(&mut self) -> Result<(), Error> {
+        let max_attempts = self.reconnect.as_ref().map_or(0, |r| r.max_attempts);
+        let mut last_error = "connection lost".to_string();
+        loop {
+            if self.attempts >= max_attempts {
+                return Err(transport_error(format!(
+                    "gave up reconnecting after {} attempts: {last_error}",
+                    self.attempts
+                )));
+            }
+            self.attempts += 1;
+            tokio::time::sleep(backoff(self.parser.retry, self.attempts)).await;
+            match self.open().await {
+                Ok(()) => return Ok(()),
+                Err(Error::Transport(error)) => last_error = error,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Connects to the server. If the server responds with 204 No Content,
+    /// which tells clients not to reconnect, the stream is ended.
+    async fn open(&mut self) -> Result<(), Error> {
+        let target = &self.target;
+        // IPv6 literals keep their brackets in the URI host
+        let host = target
+            .uri
+            .host()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = target
+            .uri
+            .port_u16()
+            .unwrap_or(if target.tls_client_config.is_some() {
+                443
+            } else {
+                80
+            });
+        let io = connect_io(
+            host,
+            port,
+            target.tls_client_config.clone(),
+            &[b"http/1.1"],
+            &target.blocked_networks,
+            &target.dns_resolver,
+        )
+        .await
+        .map_err(transport_error)?;
+        let (mut sender, conn) = timeout(
+            CONNECT_TIMEOUT,
+            hyper::client::conn::http1::handshake(TokioIo::new(io)),
+        )
+        .await
+        .map_err(|_| transport_error("connection timed out"))?
+        .map_err(|err| transport_error(format!("HTTP handshake failed: {err}")))?;
+        let worker = wasmtime_wasi::runtime::spawn(async move {
+            if let Err(err) = conn.await {
+                tracing::debug!("Event stream connection error: {err}");
+            }
+        });
+
+        let response = sender
+            .send_request(target.request(self.parser.last_event_id.as_deref()))
+            .await
+            .map_err(|err| transport_error(format!("request failed: {err}")))?;
+        if response.status() == StatusCode::NO_CONTENT {
+            self.ended = true;
+            return Ok(());
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        if response.status() != StatusCode::OK || !is_event_stream(content_type.as_deref()) {
+            let status = response.status().as_u16();
+            let body = read_prefix(response.into_body(), MAX_UNEXPECTED_BODY).await;
+            return Err(Error::UnexpectedResponse(UnexpectedResponse {
+                status,
+                content_type,
+                body,
+            }));
+        }
+        self.connection = Some(Connection {
+            body: response.into_body(),
+            _worker: worker,
+        });
+        Ok(())
+    }
+}
+
+impl Target {
+    fn request(&self, last_event_id: Option<&str>) -> http::Request<Full<Bytes>> {
+        let path = self.uri.path_and_query().map_or("/", |p| p.as_str());
+        let mut request = http::Request::new(Full::new(self.body.clone()));
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = path.parse().unwrap();
+        *request.headers_mut() = self.headers.clone();
+        if let Some(value) = last_event_id.and_then(|id| HeaderValue::from_str(id).ok()) {
+            request.headers_mut().insert("last-event-id", value);
+        }
+        request
+    }
+}
+
+/// Parses events out of an event stream.
+#[derive(Default)]
+struct EventParser {
+    /// The start of a line which hasn't been ended yet.
+    line: Vec<u8>,
+    /// Whether the last line was ended by a CR, so a LF which follows it
+    /// belongs to the same line ending.
+    after_cr: bool,
+    /// Whether the first line of the stream, which may start with a byte
+    /// order mark, has been parsed.
+    started: bool,
+    event_type: String,
+    data: String,
+    last_event_id: Option<String>,
+    /// The reconnection time the server set, if any.
+    retry: Option<Duration>,
+    events: VecDeque<Event>,
+}
+
+impl EventParser {
+    fn push(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if std::mem::take(&mut self.after_cr) && data[0] == b'\n' {
+            data = &data[1..];
+        }
+        while let Some(end) = data.iter().position(|&b| b == b'\n' || b == b'\r') {
+            self.line.extend_from_slice(&data[..end]);
+            let line = std::mem::take(&mut self.line);
+            self.parse_line(&line);
+            data = match (data[end], data.get(end + 1)) {
+                (b'\r', Some(b'\n')) => &data[end + 2..],
+                (b'\r', None) => {
+                    self.after_cr = true;
+                    &[]
+                }
+                _ => &data[end + 1..],
+            };
+        }
+        self.line.extend_from_slice(data);
+        if self.line.len() + self.data.len() > MAX_EVENT_SIZE {
+            return Err(transport_error(format!(
+                "event exceeds the limit of {MAX_EVENT_SIZE} bytes"
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &[u8]) {
+        let mut line = String::from_utf8_lossy(line);
+        if !std::mem::replace(&mut self.started, true) {
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                line = Cow::Owned(rest.to_owned());
+            }
+        }
+        if line.is_empty() {
+            self.dispatch();
+            return;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (&*line, ""),
+        };
+        match field {
+            // A line starting with a colon is a comment
+            "" => {}
+            "event" => self.event_type = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = (!value.is_empty()).then(|| value.to_owned());
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self) {
+        let event_type = std::mem::take(&mut self.event_type);
+        if self.data.is_empty() {
+            return;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        self.events.push_back(Event {
+            event: if event_type.is_empty() {
+                "message".into()
+            } else {
+                event_type
+            },
+            data,
+            id: self.last_event_id.clone(),
+        });
+    }
+
+    fn next_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Discards an event which the connection was lost before the end of.
+    fn discard_incomplete(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.event_type.clear();
+        self.data.clear();
+    }
+}
+
+/// Returns how long to wait before the given attempt to reconnect.
+fn backoff(retry: Option<Duration>, attempt: u32) -> Duration {
+    let base = retry.unwrap_or(DEFAULT_RETRY);
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF.max(base))
+}
+
+fn is_event_stream(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"))
+}
+
+fn parse_url(url: &str) -> Result<Uri, Error> {
+    let invalid = || Error::InvalidRequest(format!("invalid URL {url:?}"));
+    let uri: Uri = url.parse().map_err(|_| invalid())?;
+    match uri.scheme() {
+        Some(scheme) if *scheme == Scheme::HTTP || *scheme == Scheme::HTTPS => {}
+        _ => return Err(invalid()),
+    }
+    if uri.host().is_none() {
+        return Err(invalid());
+    }
+    Ok(uri)
+}
+
+/// Reads up to `max` bytes of `body`.
+async fn read_prefix(mut body: Incoming, max: usize) -> Vec<u8> {
+    let mut prefix = vec![];
+    while prefix.len() < max {
+        match body.frame().await {
+            Some(Ok(frame)) => {
+                if let Ok(data) = frame.into_data() {
+                    prefix.extend_from_slice(&data);
+                }
+            }
+            _ => break,
+        }
+    }
+    prefix.truncate(max);
+    prefix
+}
+
+fn transport_error(message: impl Into<String>) -> Error {
+    Error::Transport(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<(String, String, Option<String>)> {
+        let mut parser = EventParser::default();
+        for chunk in chunks {
+            parser.push(chunk.as_bytes()).unwrap();
+        }
+        std::iter::from_fn(|| parser.next_event())
+            .map(|e| (e.event, e.data, e.id))
+            .collect()
+    }
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let events = parse(&[
+            "\u{feff}: keep-alive\r",
+            "\nevent: delta\r\ndata: {\"text\"",
+            ":\"Hel\"}\r\n\r\nid: 7\ndata:lo\ndata\n\n",
+            "data: unterminated",
+        ]);
+        assert_eq!(
+            events,
+            [
+                ("delta".into(), "{\"text\":\"Hel\"}".into(), None),
+                ("message".into(), "lo\n".into(), Some("7".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_events_without_data() {
+        let mut parser = EventParser::default();
+        parser
+            .push(b"event: ping\n\nid: 3\nretry: 2500\n\nretry: soon\n\n")
+            .unwrap();
+        assert!(parser.next_event().is_none());
+        assert_eq!(parser.last_event_id.as_deref(), Some("3"));
+        assert_eq!(parser.retry, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn discards_incomplete_events() {
+        let mut parser = EventParser::default();
+        parser.push(b"id: 1\ndata: partial\n").unwrap();
+        parser.discard_incomplete();
+        parser.push(b"data: whole\n\n").unwrap();
+        let event = parser.next_event().unwrap();
+        assert_eq!(event.data, "whole");
+        assert_eq!(event.id.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn backoff_grows_to_limit() {
+        assert_eq!(backoff(None, 1), DEFAULT_RETRY);
+        assert_eq!(backoff(None, 3), DEFAULT_RETRY * 4);
+        assert_eq!(backoff(None, 100), MAX_BACKOFF);
+        let retry = Duration::from_secs(60);
+        assert_eq!(backoff(Some(retry), 5), retry);
+    }
+
+    #[test]
+    fn recognizes_event_stream_content_type() {
+        assert!(is_event_stream(Some("text/event-stream")));
+        assert!(is_event_stream(Some("Text/Event-Stream; charset=utf-8")));
+        assert!(!is_event_stream(Some("application/json")));
+        assert!(!is_event_stream(None));
+    }
+}
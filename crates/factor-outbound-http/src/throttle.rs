@@ -0,0 +1,81 @@
+//! Bandwidth throttling of outbound request bodies.
+//!
+//! Each frame of the body is accounted against the component's
+//! [`EgressThrottle`] as it is sent; if that takes the component over its
+//! `bytes_per_second`, the next frame is held back until the debt is repaid.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::Request;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use spin_factor_outbound_networking::EgressThrottle;
+use tokio::time::Sleep;
+use wasmtime_wasi_http::{bindings::http::types::ErrorCode, body::HyperOutgoingBody};
+
+/// Throttles the body of `request` by `throttle`'s bandwidth limit.
+pub(crate) fn throttle_request_body(
+    request: Request<HyperOutgoingBody>,
+    throttle: EgressThrottle,
+) -> Request<HyperOutgoingBody> {
+    if request.body().is_end_stream() {
+        return request;
+    }
+    request.map(|inner| {
+        ThrottledBody {
+            inner,
+            throttle,
+            delay: None,
+        }
+        .boxed()
+    })
+}
+
+/// An outgoing body which paces its frames to a bandwidth limit.
+struct ThrottledBody {
+    inner: HyperOutgoingBody,
+    throttle: EgressThrottle,
+    /// Set while the previous frame's bytes are being paid back.
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Body for ThrottledBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(delay) = &mut this.delay {
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.delay = None;
+        }
+        let frame = Pin::new(&mut this.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &frame {
+            if let Some(data) = frame.data_ref() {
+                let wait = this.throttle.reserve_bytes(data.len());
+                if !wait.is_zero() {
+                    this.delay = Some(Box::pin(tokio::time::sleep(wait)));
+                }
+            }
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -1,15 +1,27 @@
-use std::{error::Error, sync::Arc};
+use std::{
+    error::Error,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::Context;
-use http::{header::HOST, Request};
-use http_body_util::BodyExt;
+use bytes::Bytes;
+use http::{header::HOST, HeaderMap, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper_util::rt::TokioExecutor;
+use spin_factor_fault_injection::{FaultInjector, Interface};
 use spin_factor_outbound_networking::{
-    BlockedNetworks, ComponentTlsClientConfigs, OutboundAllowedHosts, TlsClientConfig,
+    connect_tcp, BlockedNetworks, ComponentTlsClientConfigs, DnsResolver, EgressThrottle,
+    OutboundAllowedHosts, TlsClientConfig,
 };
 use spin_factors::{wasmtime::component::ResourceTable, RuntimeFactorsInstanceState};
-use tokio::{net::TcpStream, time::timeout};
+use tokio::{sync::oneshot, time::timeout};
 use tracing::{field::Empty, instrument, Instrument};
-use wasmtime_wasi::p2::{IoImpl, IoView};
+use wasmtime_wasi::{
+    p2::{IoImpl, IoView},
+    runtime::AbortOnDropJoinHandle,
+};
 use wasmtime_wasi_http::{
     bindings::http::types::ErrorCode,
     body::HyperOutgoingBody,
@@ -19,8 +31,16 @@ use wasmtime_wasi_http::{
 };
 
 use crate::{
+    cassette::{Cassette, RecordedRequest, RecordedResponse},
+    connect::Io,
+    expect_continue::{gate_request_body, ContinueSniffer},
     intercept::{InterceptOutcome, OutboundHttpInterceptor},
-    wasi_2023_10_18, wasi_2023_11_10, InstanceState, OutboundHttpFactor, SelfRequestOrigin,
+    pool::{CheckinBody, ConnectionPool, PoolKey, Sender},
+    response_cache::{CacheKey, CachedResponse, Lookup, ResponseCache},
+    signing::RequestSigning,
+    throttle::throttle_request_body,
+    wasi_2023_10_18, wasi_2023_11_10, ActiveRequest, InstanceState, OutboundHttpFactor,
+    SelfRequestOrigin,
 };
 
 pub(crate) fn add_to_linker<C>(ctx: &mut C) -> anyhow::Result<()>
@@ -54,6 +74,17 @@ impl OutboundHttpFactor {
     }
 }
 
+/// How many chunks of an outgoing body a guest may write ahead of it being
+/// sent.
+///
+/// A guest proxying a body can splice the incoming body's stream into the
+/// outgoing body's, which hands each frame to the outgoing request as is,
+/// without copying it through guest memory. Guests which read bodies into
+/// their own memory and write them back still copy them. Each splice moves
+/// only as much as the buffer has room for, so buffering several chunks lets
+/// splices keep moving frames rather than waiting for each one to be sent.
+const OUTGOING_BODY_BUFFER_CHUNKS: usize = 8;
+
 pub(crate) struct WasiHttpImplInner<'a> {
     state: &'a mut InstanceState,
     table: &'a mut ResourceTable,
@@ -70,6 +101,10 @@ impl WasiHttpView for WasiHttpImplInner<'_> {
         &mut self.state.wasi_http_ctx
     }
 
+    fn outgoing_body_buffer_chunks(&mut self) -> usize {
+        OUTGOING_BODY_BUFFER_CHUNKS
+    }
+
     #[instrument(
         name = "spin_outbound_http.send_request",
         skip_all,
@@ -85,9 +120,10 @@ impl WasiHttpView for WasiHttpImplInner<'_> {
     )]
     fn send_request(
         &mut self,
-        request: Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
+        mut request: Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
         config: wasmtime_wasi_http::types::OutgoingRequestConfig,
     ) -> wasmtime_wasi_http::HttpResult<wasmtime_wasi_http::types::HostFutureIncomingResponse> {
+        self.state.inject_request_context(request.headers_mut());
         Ok(HostFutureIncomingResponse::Pending(
             wasmtime_wasi::runtime::spawn(
                 send_request_impl(
@@ -98,6 +134,14 @@ impl WasiHttpView for WasiHttpImplInner<'_> {
                     self.state.request_interceptor.clone(),
                     self.state.self_request_origin.clone(),
                     self.state.blocked_networks.clone(),
+                    self.state.egress_throttle.clone(),
+                    self.state.dns_resolver.clone(),
+                    self.state.request_signing.clone(),
+                    self.state.fault_injector.clone(),
+                    self.state.cassette.clone(),
+                    self.state.response_cache.clone(),
+                    self.state.connection_pool.clone(),
+                    self.state.component_id.clone(),
                 )
                 .in_current_span(),
             ),
@@ -105,6 +149,7 @@ impl WasiHttpView for WasiHttpImplInner<'_> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn send_request_impl(
     mut request: Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
     mut config: wasmtime_wasi_http::types::OutgoingRequestConfig,
@@ -113,6 +158,14 @@ async fn send_request_impl(
     request_interceptor: Option<Arc<dyn OutboundHttpInterceptor>>,
     self_request_origin: Option<SelfRequestOrigin>,
     blocked_networks: BlockedNetworks,
+    egress_throttle: EgressThrottle,
+    dns_resolver: DnsResolver,
+    request_signing: RequestSigning,
+    fault_injector: Option<FaultInjector>,
+    cassette: Option<Arc<Cassette>>,
+    response_cache: Option<Arc<ResponseCache>>,
+    connection_pool: Arc<ConnectionPool>,
+    component_id: Arc<str>,
 ) -> anyhow::Result<Result<IncomingResponse, ErrorCode>> {
     // wasmtime-wasi-http fills in scheme and authority for relative URLs
     // (e.g. https://:443/<path>), which makes them hard to reason about.
@@ -190,20 +243,379 @@ async fn send_request_impl(
         }
     }
 
-    let authority = request.uri().authority().context("authority not set")?;
-    span.record("server.address", authority.host());
-    if let Some(port) = authority.port() {
-        span.record("server.port", port.as_u16());
+    // Requests are matched against the cassette before they are signed, so
+    // that signatures are neither recorded nor have to match
+    let recording = match cassette {
+        Some(cassette) => {
+            let (parts, body) = request.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(err) => return Ok(Err(err)),
+            };
+            let recorded = cassette.request(&parts, &body);
+            if let Some(resp) = cassette.replay(&recorded) {
+                let resp = match resp.to_http() {
+                    Ok(resp) => {
+                        resp.map(|body| Full::new(body).map_err(|never| match never {}).boxed())
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to replay recorded response: {err:#}");
+                        return Ok(Err(ErrorCode::InternalError(Some(
+                            "invalid recorded response".to_string(),
+                        ))));
+                    }
+                };
+                let resp = IncomingResponse {
+                    resp,
+                    worker: None,
+                    between_bytes_timeout: config.between_bytes_timeout,
+                };
+                return Ok(Ok(resp));
+            }
+            if !cassette.records() {
+                tracing::error!(
+                    "No recorded response to outbound request {} {}",
+                    recorded.method,
+                    recorded.uri
+                );
+                return Ok(Err(ErrorCode::InternalError(Some(format!(
+                    "no recorded response to {} {}",
+                    recorded.method, recorded.uri
+                )))));
+            }
+            let body = Full::new(body).map_err(|never| match never {}).boxed();
+            request = Request::from_parts(parts, body);
+            Some((cassette, recorded))
+        }
+        None => None,
+    };
+
+    let between_bytes_timeout = config.between_bytes_timeout;
+    let upstream = Upstream {
+        config,
+        tls_client_config,
+        blocked_networks,
+        egress_throttle,
+        dns_resolver,
+        request_signing,
+        fault_injector,
+        connection_pool,
+        component_id,
+    };
+
+    // Responses are looked up in the cache after the cassette, so that
+    // recorded interactions take precedence, and before signing, which
+    // cached responses don't need
+    let caching = match response_cache.and_then(|cache| Some((cache.key(&request)?, cache))) {
+        Some((key, cache)) => {
+            let (mut parts, body) = request.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(err) => return Ok(Err(err)),
+            };
+            // The key doesn't cover the body, so requests with one aren't
+            // cached
+            let caching = if body.is_empty() {
+                match cache.lookup(&key, &parts.headers, Instant::now()) {
+                    Lookup::Fresh(cached) => {
+                        return Ok(Ok(full_response(cached.to_http(), between_bytes_timeout)));
+                    }
+                    Lookup::Stale { cached, revalidate } => {
+                        if revalidate {
+                            let mut revalidation = Request::from_parts(parts, empty_body());
+                            cached.add_validators(revalidation.headers_mut());
+                            tokio::spawn(
+                                revalidate_response(cache, key, revalidation, upstream)
+                                    .in_current_span(),
+                            );
+                        }
+                        return Ok(Ok(full_response(cached.to_http(), between_bytes_timeout)));
+                    }
+                    Lookup::Miss { stale } => {
+                        let request_headers = parts.headers.clone();
+                        let validated = stale
+                            .as_ref()
+                            .is_some_and(|stale| stale.add_validators(&mut parts.headers));
+                        Some(Caching {
+                            cache,
+                            key,
+                            request_headers,
+                            stale,
+                            validated,
+                        })
+                    }
+                }
+            } else {
+                None
+            };
+            let body = Full::new(body).map_err(|never| match never {}).boxed();
+            request = Request::from_parts(parts, body);
+            caching
+        }
+        None => None,
+    };
+
+    let resp = upstream.send(request).await?;
+    let resp = match caching {
+        Some(caching) => caching.finish(resp, between_bytes_timeout).await,
+        None => resp,
+    };
+    match (recording, resp) {
+        (Some((cassette, recorded)), Ok(resp)) => {
+            Ok(record_response(&cassette, recorded, resp).await)
+        }
+        (_, resp) => Ok(resp),
+    }
+}
+
+/// Sends requests on to their servers, once they aren't answered by the
+/// cassette or the cache.
+struct Upstream {
+    config: wasmtime_wasi_http::types::OutgoingRequestConfig,
+    tls_client_config: TlsClientConfig,
+    blocked_networks: BlockedNetworks,
+    egress_throttle: EgressThrottle,
+    dns_resolver: DnsResolver,
+    request_signing: RequestSigning,
+    fault_injector: Option<FaultInjector>,
+    connection_pool: Arc<ConnectionPool>,
+    component_id: Arc<str>,
+}
+
+impl Upstream {
+    async fn send(
+        self,
+        mut request: Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
+    ) -> anyhow::Result<Result<IncomingResponse, ErrorCode>> {
+        let Self {
+            config,
+            tls_client_config,
+            blocked_networks,
+            egress_throttle,
+            dns_resolver,
+            request_signing,
+            fault_injector,
+            connection_pool,
+            component_id,
+        } = self;
+        let span = tracing::Span::current();
+
+        if let Some(signer) = request
+            .uri()
+            .host()
+            .and_then(|host| request_signing.signer_for(host))
+        {
+            // Signatures cover the body, so it has to be buffered
+            let (mut parts, body) = request.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(err) => return Ok(Err(err)),
+            };
+            if let Err(err) = signer.sign(&mut parts, &body, SystemTime::now()) {
+                tracing::error!("Failed to sign outbound request: {err:#}");
+                return Ok(Err(ErrorCode::InternalError(Some(
+                    "failed to sign request".to_string(),
+                ))));
+            }
+            let body = Full::new(body).map_err(|never| match never {}).boxed();
+            request = Request::from_parts(parts, body);
+        }
+
+        let authority = request.uri().authority().context("authority not set")?;
+        span.record("server.address", authority.host());
+        if let Some(port) = authority.port() {
+            span.record("server.port", port.as_u16());
+        }
+
+        let server_address = authority.host().to_owned();
+        if let Some(injector) = fault_injector {
+            if let Err(fault) = injector.inject(Interface::OutboundHttp).await {
+                return Ok(Err(ErrorCode::InternalError(Some(fault.to_string()))));
+            }
+        }
+        // The request counts against the component's concurrency limit until
+        // its response head arrives
+        let _permit = egress_throttle.acquire(0).await;
+        let request = throttle_request_body(request, egress_throttle);
+        let _active = ActiveRequest::new(server_address);
+        let resp = send_request_handler(
+            request,
+            config,
+            tls_client_config,
+            blocked_networks,
+            dns_resolver,
+            &connection_pool,
+            component_id,
+        )
+        .await;
+        Ok(resp)
+    }
+}
+
+/// A request whose response may be stored in, or served from, the cache.
+struct Caching {
+    cache: Arc<ResponseCache>,
+    key: CacheKey,
+    /// The request's headers, without any validators added by the cache.
+    request_headers: HeaderMap,
+    /// The stale response to the request, if there is one.
+    stale: Option<CachedResponse>,
+    /// Whether the request was made conditional on `stale` being current.
+    validated: bool,
+}
+
+impl Caching {
+    /// Serves the stale response if the server failed or says it is still
+    /// current, and otherwise stores the server's response.
+    async fn finish(
+        self,
+        resp: Result<IncomingResponse, ErrorCode>,
+        between_bytes_timeout: Duration,
+    ) -> Result<IncomingResponse, ErrorCode> {
+        let Self {
+            cache,
+            key,
+            request_headers,
+            stale,
+            validated,
+        } = self;
+        let failed = match &resp {
+            Ok(resp) => resp.resp.status().is_server_error(),
+            Err(_) => true,
+        };
+        if failed {
+            if let Some(stale) = stale.filter(CachedResponse::serve_on_error) {
+                tracing::info!("Serving stale cached response after outbound request failed");
+                return Ok(full_response(stale.to_http(), between_bytes_timeout));
+            }
+            return resp;
+        }
+        let resp = resp?;
+        if validated && resp.resp.status() == StatusCode::NOT_MODIFIED {
+            let now = Instant::now();
+            let current = cache
+                .refresh(&key, &request_headers, resp.resp.headers(), now)
+                .or(stale)
+                .expect("validated requests have a stale response");
+            return Ok(full_response(current.to_http(), between_bytes_timeout));
+        }
+        store_response(&cache, &key, &request_headers, resp).await
+    }
+}
+
+/// Revalidates a stale cached response in the background.
+async fn revalidate_response(
+    cache: Arc<ResponseCache>,
+    key: CacheKey,
+    request: Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
+    upstream: Upstream,
+) {
+    let request_headers = request.headers().clone();
+    match upstream.send(request).await {
+        Ok(Ok(resp)) if resp.resp.status() == StatusCode::NOT_MODIFIED => {
+            cache.refresh(&key, &request_headers, resp.resp.headers(), Instant::now());
+        }
+        Ok(Ok(resp)) if !resp.resp.status().is_server_error() => {
+            if let Err(err) = store_response(&cache, &key, &request_headers, resp).await {
+                tracing::warn!("Failed to revalidate cached response: {err}");
+            }
+        }
+        Ok(Ok(resp)) => {
+            tracing::warn!(
+                "Failed to revalidate cached response: server responded {}",
+                resp.resp.status()
+            );
+        }
+        Ok(Err(err)) => tracing::warn!("Failed to revalidate cached response: {err}"),
+        Err(err) => tracing::warn!("Failed to revalidate cached response: {err:#}"),
+    }
+    cache.end_revalidation(&key);
+}
+
+/// Buffers the body of a response to store it in `cache`, if it may be
+/// cached.
+async fn store_response(
+    cache: &ResponseCache,
+    key: &CacheKey,
+    request_headers: &HeaderMap,
+    resp: IncomingResponse,
+) -> Result<IncomingResponse, ErrorCode> {
+    if !cache.would_store(
+        key,
+        request_headers,
+        resp.resp.status(),
+        resp.resp.headers(),
+    ) {
+        return Ok(resp);
+    }
+    let IncomingResponse {
+        resp,
+        worker,
+        between_bytes_timeout,
+    } = resp;
+    let (parts, body) = resp.into_parts();
+    let body = body.collect().await?.to_bytes();
+    cache.store(
+        key,
+        request_headers,
+        parts.status,
+        &parts.headers,
+        body.clone(),
+        Instant::now(),
+    );
+    let body = Full::new(body).map_err(|never| match never {}).boxed();
+    Ok(IncomingResponse {
+        resp: Response::from_parts(parts, body),
+        worker,
+        between_bytes_timeout,
+    })
+}
+
+/// Makes a response whose body is already in memory.
+fn full_response(resp: Response<Bytes>, between_bytes_timeout: Duration) -> IncomingResponse {
+    IncomingResponse {
+        resp: resp.map(|body| Full::new(body).map_err(|never| match never {}).boxed()),
+        worker: None,
+        between_bytes_timeout,
     }
+}
 
-    Ok(send_request_handler(request, config, tls_client_config, blocked_networks).await)
+fn empty_body() -> wasmtime_wasi_http::body::HyperOutgoingBody {
+    Full::new(Bytes::new())
+        .map_err(|never| match never {})
+        .boxed()
+}
+
+/// Buffers the body of a response to record it in `cassette`.
+async fn record_response(
+    cassette: &Cassette,
+    request: RecordedRequest,
+    resp: IncomingResponse,
+) -> Result<IncomingResponse, ErrorCode> {
+    let IncomingResponse {
+        resp,
+        worker,
+        between_bytes_timeout,
+    } = resp;
+    let (parts, body) = resp.into_parts();
+    let body = body.collect().await?.to_bytes();
+    let recorded = RecordedResponse::new(parts.status.as_u16(), &parts.headers, &body);
+    cassette.record(request, recorded);
+    let body = Full::new(body).map_err(|never| match never {}).boxed();
+    Ok(IncomingResponse {
+        resp: Response::from_parts(parts, body),
+        worker,
+        between_bytes_timeout,
+    })
 }
 
 /// This is a fork of wasmtime_wasi_http::default_send_request_handler function
 /// forked from bytecodealliance/wasmtime commit-sha 29a76b68200fcfa69c8fb18ce6c850754279a05b
-/// This fork provides the ability to configure client cert auth for mTLS
+/// This fork provides the ability to configure client cert auth for mTLS, and
+/// reuses connections from `connection_pool`
+#[allow(clippy::too_many_arguments)]
 async fn send_request_handler(
-    mut request: http::Request<HyperOutgoingBody>,
+    request: http::Request<HyperOutgoingBody>,
     wasmtime_wasi_http::types::OutgoingRequestConfig {
         use_tls,
         connect_timeout,
@@ -212,23 +624,138 @@ async fn send_request_handler(
     }: wasmtime_wasi_http::types::OutgoingRequestConfig,
     tls_client_config: TlsClientConfig,
     blocked_networks: BlockedNetworks,
+    dns_resolver: DnsResolver,
+    connection_pool: &Arc<ConnectionPool>,
+    component_id: Arc<str>,
 ) -> Result<wasmtime_wasi_http::types::IncomingResponse, ErrorCode> {
-    let authority_str = if let Some(authority) = request.uri().authority() {
-        if authority.port().is_some() {
-            authority.to_string()
-        } else {
-            let port = if use_tls { 443 } else { 80 };
-            format!("{}:{port}", authority)
-        }
-    } else {
+    let Some(authority) = request.uri().authority() else {
         return Err(ErrorCode::HttpRequestUriInvalid);
     };
+    // IPv6 literals are bracketed in the authority but not in the TLS
+    // server name
+    let host = authority
+        .host()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_owned();
+    let port = authority
+        .port_u16()
+        .unwrap_or(if use_tls { 443 } else { 80 });
+    let authority_str = if authority.port().is_some() {
+        authority.to_string()
+    } else {
+        format!("{authority}:{port}")
+    };
 
-    // Resolve the authority to IP addresses
-    let mut socket_addrs = tokio::net::lookup_host(&authority_str)
+    let http2 = connection_pool.http2_prior_knowledge();
+    // hyper doesn't wait for `100 Continue` itself; hold the body back until
+    // the server asks for it. Only HTTP/1 connections are sniffed for it, and
+    // those which are aren't pooled.
+    let (mut request, continue_tx) = if http2 {
+        (request, None)
+    } else {
+        gate_request_body(request)
+    };
+    let pool_key = (continue_tx.is_none() && connection_pool.is_enabled()).then(|| PoolKey {
+        component_id,
+        use_tls,
+        authority: authority_str.clone(),
+    });
+
+    let pooled_sender = match &pool_key {
+        Some(key) => connection_pool.checkout(key).await,
+        None => None,
+    };
+    let (mut sender, worker) = match pooled_sender {
+        Some(sender) => (sender, None),
+        None => {
+            open_connection(
+                &host,
+                port,
+                &authority_str,
+                use_tls,
+                connect_timeout,
+                tls_client_config,
+                blocked_networks,
+                dns_resolver,
+                continue_tx,
+                http2,
+                pool_key.is_some(),
+            )
+            .await?
+        }
+    };
+
+    // at this point, the request contains the scheme and the authority, but
+    // the http packet should only include those if addressing a proxy, so
+    // remove them here, since SendRequest::send_request does not do it for us.
+    // HTTP/2 carries them in pseudo-headers, so needs them kept.
+    if !sender.is_http2() {
+        *request.uri_mut() = http::Uri::builder()
+            .path_and_query(
+                request
+                    .uri()
+                    .path_and_query()
+                    .map(|p| p.as_str())
+                    .unwrap_or("/"),
+            )
+            .build()
+            .expect("comes from valid request");
+    }
+
+    let resp = timeout(first_byte_timeout, sender.send_request(request))
         .await
-        .map_err(|_| dns_error("address not available".into(), 0))?
-        .collect::<Vec<_>>();
+        .map_err(|_| ErrorCode::ConnectionReadTimeout)?
+        .map_err(hyper_request_error)?
+        .map(|body| body.map_err(hyper_request_error).boxed());
+
+    tracing::Span::current().record("http.response.status_code", resp.status().as_u16());
+
+    let resp = match pool_key {
+        // HTTP/2 connections can carry other requests while this response is
+        // read
+        Some(key) if sender.is_http2() => {
+            connection_pool.checkin(key, sender);
+            resp
+        }
+        Some(key) => {
+            resp.map(|body| CheckinBody::new(body, connection_pool.clone(), key, sender).boxed())
+        }
+        None => resp,
+    };
+
+    Ok(wasmtime_wasi_http::types::IncomingResponse {
+        resp,
+        worker,
+        between_bytes_timeout,
+    })
+}
+
+/// Opens a new connection to send a request over, returning the task which
+/// drives it if it is not `pooled`.
+#[allow(clippy::too_many_arguments)]
+async fn open_connection(
+    host: &str,
+    port: u16,
+    authority_str: &str,
+    use_tls: bool,
+    connect_timeout: Duration,
+    tls_client_config: TlsClientConfig,
+    blocked_networks: BlockedNetworks,
+    dns_resolver: DnsResolver,
+    continue_tx: Option<oneshot::Sender<bool>>,
+    http2: bool,
+    pooled: bool,
+) -> Result<(Sender, Option<AbortOnDropJoinHandle<()>>), ErrorCode> {
+    // Resolve the authority to IP addresses
+    let mut socket_addrs =
+        dns_resolver
+            .lookup_host(host, port)
+            .await
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::PermissionDenied => ErrorCode::DestinationIpProhibited,
+                _ => dns_error("address not available".into(), 0),
+            })?;
 
     // Remove blocked IPs
     let blocked_addrs = blocked_networks.remove_blocked(&mut socket_addrs);
@@ -241,7 +768,12 @@ async fn send_request_handler(
         return Err(ErrorCode::DestinationIpProhibited);
     }
 
-    let tcp_stream = timeout(connect_timeout, TcpStream::connect(socket_addrs.as_slice()))
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.outbound_http.connections_opened = 1,
+        server_address = authority_str
+    );
+
+    let tcp_stream = timeout(connect_timeout, connect_tcp(&socket_addrs))
         .await
         .map_err(|_| ErrorCode::ConnectionTimeout)?
         .map_err(|err| match err.kind() {
@@ -249,7 +781,7 @@ async fn send_request_handler(
             _ => ErrorCode::ConnectionRefused,
         })?;
 
-    let (mut sender, worker) = if use_tls {
+    let stream: Box<dyn Io> = if use_tls {
         #[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
         {
             return Err(ErrorCode::InternalError(Some(
@@ -260,9 +792,14 @@ async fn send_request_handler(
         #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
         {
             use rustls::pki_types::ServerName;
-            let connector = tokio_rustls::TlsConnector::from(tls_client_config.inner());
-            let mut parts = authority_str.split(':');
-            let host = parts.next().unwrap_or(&authority_str);
+            let mut config = tls_client_config.inner();
+            if http2 {
+                // Servers may only speak HTTP/2 over TLS if it is negotiated
+                let mut h2_config = (*config).clone();
+                h2_config.alpn_protocols = vec![b"h2".to_vec()];
+                config = Arc::new(h2_config);
+            }
+            let connector = tokio_rustls::TlsConnector::from(config);
             let domain = ServerName::try_from(host)
                 .map_err(|e| {
                     tracing::warn!("dns lookup error: {e:?}");
@@ -273,76 +810,54 @@ async fn send_request_handler(
                 tracing::warn!("tls protocol error: {e:?}");
                 ErrorCode::TlsProtocolError
             })?;
-            let stream = TokioIo::new(stream);
-
-            let (sender, conn) = timeout(
-                connect_timeout,
-                hyper::client::conn::http1::handshake(stream),
-            )
-            .await
-            .map_err(|_| ErrorCode::ConnectionTimeout)?
-            .map_err(hyper_request_error)?;
-
-            let worker = wasmtime_wasi::runtime::spawn(async move {
-                match conn.await {
-                    Ok(()) => {}
-                    // TODO: shouldn't throw away this error and ideally should
-                    // surface somewhere.
-                    Err(e) => tracing::warn!("dropping error {e}"),
-                }
-            });
-
-            (sender, worker)
+            Box::new(stream)
         }
     } else {
-        let tcp_stream = TokioIo::new(tcp_stream);
+        Box::new(tcp_stream)
+    };
+    let stream = TokioIo::new(ContinueSniffer::new(stream, continue_tx));
+
+    if http2 {
         let (sender, conn) = timeout(
             connect_timeout,
-            // TODO: we should plumb the builder through the http context, and use it here
-            hyper::client::conn::http1::handshake(tcp_stream),
+            hyper::client::conn::http2::handshake(TokioExecutor::new(), stream),
         )
         .await
         .map_err(|_| ErrorCode::ConnectionTimeout)?
         .map_err(hyper_request_error)?;
-
-        let worker = wasmtime_wasi::runtime::spawn(async move {
-            match conn.await {
-                Ok(()) => {}
-                // TODO: same as above, shouldn't throw this error away.
-                Err(e) => tracing::warn!("dropping error {e}"),
-            }
-        });
-
-        (sender, worker)
-    };
-
-    // at this point, the request contains the scheme and the authority, but
-    // the http packet should only include those if addressing a proxy, so
-    // remove them here, since SendRequest::send_request does not do it for us
-    *request.uri_mut() = http::Uri::builder()
-        .path_and_query(
-            request
-                .uri()
-                .path_and_query()
-                .map(|p| p.as_str())
-                .unwrap_or("/"),
+        Ok((Sender::Http2(sender), spawn_connection(conn, pooled)))
+    } else {
+        let (sender, conn) = timeout(
+            connect_timeout,
+            hyper::client::conn::http1::handshake(stream),
         )
-        .build()
-        .expect("comes from valid request");
-
-    let resp = timeout(first_byte_timeout, sender.send_request(request))
         .await
-        .map_err(|_| ErrorCode::ConnectionReadTimeout)?
-        .map_err(hyper_request_error)?
-        .map(|body| body.map_err(hyper_request_error).boxed());
-
-    tracing::Span::current().record("http.response.status_code", resp.status().as_u16());
+        .map_err(|_| ErrorCode::ConnectionTimeout)?
+        .map_err(hyper_request_error)?;
+        Ok((Sender::Http1(sender), spawn_connection(conn, pooled)))
+    }
+}
 
-    Ok(wasmtime_wasi_http::types::IncomingResponse {
-        resp,
-        worker: Some(worker),
-        between_bytes_timeout,
-    })
+/// Drives a connection in the background. Pooled connections outlive the
+/// response they were opened for, so aren't aborted when it is dropped.
+fn spawn_connection<F>(conn: F, pooled: bool) -> Option<AbortOnDropJoinHandle<()>>
+where
+    F: Future<Output = hyper::Result<()>> + Send + 'static,
+{
+    let conn = async move {
+        match conn.await {
+            Ok(()) => {}
+            // TODO: shouldn't throw away this error and ideally should
+            // surface somewhere.
+            Err(e) => tracing::warn!("dropping error {e}"),
+        }
+    };
+    if pooled {
+        tokio::spawn(conn);
+        None
+    } else {
+        Some(wasmtime_wasi::runtime::spawn(conn))
+    }
 }
 
 /// Translate a [`hyper::Error`] to a wasi-http `ErrorCode` in the context of a request.
@@ -365,3 +880,58 @@ fn dns_error(rcode: String, info_code: u16) -> ErrorCode {
         info_code: Some(info_code),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::StreamBody;
+    use hyper::body::Frame;
+    use wasmtime_wasi::p2::{InputStream, OutputStream, Pollable};
+    use wasmtime_wasi_http::{
+        body::{HostIncomingBody, HostOutgoingBody, StreamContext},
+        types::DEFAULT_OUTGOING_BODY_CHUNK_SIZE,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn spliced_frames_are_buffered_without_copying() {
+        // An incoming body as hyper delivers it, in frames smaller than a
+        // chunk
+        let frames = (0..OUTGOING_BODY_BUFFER_CHUNKS)
+            .map(|i| Bytes::from(vec![i as u8; 16 * 1024]))
+            .collect::<Vec<_>>();
+        let incoming = StreamBody::new(futures::stream::iter(
+            frames
+                .clone()
+                .into_iter()
+                .map(|frame| Ok::<_, ErrorCode>(Frame::data(frame))),
+        ))
+        .boxed();
+        let mut incoming = HostIncomingBody::new(incoming, Duration::from_secs(10));
+        let mut input = incoming.take_stream().unwrap();
+
+        let (mut outgoing, mut body) = HostOutgoingBody::new(
+            StreamContext::Request,
+            None,
+            OUTGOING_BODY_BUFFER_CHUNKS,
+            DEFAULT_OUTGOING_BODY_CHUNK_SIZE,
+        );
+        let mut output = outgoing.take_output_stream().unwrap();
+
+        // Splice the way `wasi:io` does; every frame fits in the buffer
+        // before any of the outgoing body is sent
+        for _ in &frames {
+            input.ready().await;
+            let permit = output.check_write().unwrap();
+            assert!(permit > 0, "buffer filled before all frames were spliced");
+            let frame = input.read(permit).unwrap();
+            output.write(frame).unwrap();
+        }
+
+        for frame in &frames {
+            let sent = body.frame().await.unwrap().unwrap().into_data().unwrap();
+            assert_eq!(sent.as_ptr(), frame.as_ptr(), "frame was copied");
+            assert_eq!(sent.len(), frame.len());
+        }
+    }
+}
@@ -104,6 +104,232 @@ async fn disallowed_private_ips_fails() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn grpc_disallowed_endpoint_fails() -> anyhow::Result<()> {
+    use spin_world::spin::grpc::client::{Error, Host};
+
+    let mut state = test_instance_state("https://allowed.test", true).await?;
+
+    let res = state
+        .http
+        .unary(
+            "https://denied.test".into(),
+            "/orders.v1.Orders/Get".into(),
+            vec![],
+            vec![],
+        )
+        .await;
+    assert!(matches!(res, Err(Error::DestinationNotAllowed)));
+
+    let res = state
+        .http
+        .unary(
+            "allowed.test".into(),
+            "/orders.v1.Orders/Get".into(),
+            vec![],
+            vec![],
+        )
+        .await;
+    assert!(matches!(res, Err(Error::InvalidUrl)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn sse_disallowed_url_fails() -> anyhow::Result<()> {
+    use spin_world::spin::sse::client::{Error, Host, Request};
+
+    let mut state = test_instance_state("https://allowed.test", true).await?;
+
+    let request = |url: &str| Request {
+        url: url.into(),
+        method: None,
+        headers: vec![],
+        body: None,
+    };
+    let res = state
+        .http
+        .connect(request("https://denied.test"), None)
+        .await;
+    assert!(matches!(res, Err(Error::DestinationNotAllowed)));
+    let res = state.http.connect(request("allowed.test"), None).await;
+    assert!(matches!(res, Err(Error::InvalidRequest(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn sse_reconnects_with_last_event_id() -> anyhow::Result<()> {
+    use spin_world::spin::sse::client::{Error, Host, HostEventStream, Reconnect, Request};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Serves one event per connection, then closes it
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(async move {
+        let mut requests = vec![];
+        for body in ["retry: 10\nid: 1\ndata: first\n\n", "data: second\n\n"] {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = stream.read(&mut request).await.unwrap();
+            requests.push(String::from_utf8_lossy(&request[..n]).to_lowercase());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{body}"
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    });
+
+    let mut state = test_instance_state(&format!("http://{addr}"), true).await?;
+    let request = Request {
+        url: format!("http://{addr}/events"),
+        method: None,
+        headers: vec![],
+        body: None,
+    };
+    let reconnect = Reconnect {
+        max_attempts: 1,
+        on_end: true,
+    };
+    let stream = state.http.connect(request, Some(reconnect)).await?;
+    let rep = stream.rep();
+    let stream = || wasmtime::component::Resource::new_borrow(rep);
+
+    let first = state.http.next(stream()).await?.unwrap();
+    assert_eq!(first.data, "first");
+    let second = state.http.next(stream()).await?.unwrap();
+    assert_eq!(second.data, "second");
+    assert_eq!(second.id.as_deref(), Some("1"));
+
+    let requests = server.await?;
+    assert!(requests[0].contains("accept: text/event-stream"));
+    assert!(!requests[0].contains("last-event-id"));
+    assert!(requests[1].contains("last-event-id: 1"));
+
+    // The server has gone away, so the one attempt to reconnect fails
+    let res = state.http.next(stream()).await;
+    assert!(matches!(res, Err(Error::Transport(_))), "{res:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_bodies_larger_than_the_outgoing_buffer_are_streamed() -> anyhow::Result<()> {
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper::body::Frame;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wasmtime_wasi_http::types::DEFAULT_OUTGOING_BODY_CHUNK_SIZE;
+
+    // More chunks than a guest may buffer of an outgoing body
+    const CHUNKS: usize = 16;
+    let body_len = CHUNKS * DEFAULT_OUTGOING_BODY_CHUNK_SIZE;
+
+    // Reads the request's body and responds with its length
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = vec![];
+        let mut buf = vec![0; 64 * 1024];
+        let header_len = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(
+                n > 0,
+                "connection closed before the request's headers were read"
+            );
+            received.extend_from_slice(&buf[..n]);
+            if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let mut body_read = received.len() - header_len;
+        while body_read < body_len {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(
+                n > 0,
+                "connection closed after {body_read} bytes of the body"
+            );
+            body_read += n;
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body_read}",
+            body_read.to_string().len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        body_read
+    });
+
+    let mut state = test_instance_state(&format!("http://{addr}"), true).await?;
+    let mut wasi_http = OutboundHttpFactor::get_wasi_http_impl(&mut state).unwrap();
+    let chunks = (0..CHUNKS).map(|i| {
+        Ok::<_, ErrorCode>(Frame::data(Bytes::from(vec![
+            i as u8;
+            DEFAULT_OUTGOING_BODY_CHUNK_SIZE
+        ])))
+    });
+    let body = StreamBody::new(futures::stream::iter(chunks)).boxed();
+    let req = Request::post(format!("http://{addr}/upload"))
+        .header(http::header::CONTENT_LENGTH, body_len)
+        .body(body)?;
+    let mut future_resp = wasi_http.send_request(req, test_request_config())?;
+    future_resp.ready().await;
+    let resp = match future_resp.unwrap_ready().unwrap() {
+        Ok(resp) => resp,
+        Err(err) => bail!("expected Ok, got {err:?}"),
+    };
+    assert_eq!(resp.resp.status(), http::StatusCode::OK);
+    assert_eq!(server.await?, body_len);
+    Ok(())
+}
+
+#[tokio::test]
+async fn connections_are_reused_across_requests() -> anyhow::Result<()> {
+    use http_body_util::BodyExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Accepts a single connection and answers every request sent over it
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        drop(listener);
+        let mut served = 0;
+        let mut received = vec![];
+        let mut buf = vec![0; 4096];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            received.extend_from_slice(&buf[..n]);
+            while let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                received.drain(..end + 4);
+                served += 1;
+                let response = format!("HTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\n{served}");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        }
+    });
+
+    let mut state = test_instance_state(&format!("http://{addr}"), true).await?;
+    let mut wasi_http = OutboundHttpFactor::get_wasi_http_impl(&mut state).unwrap();
+    for expected in ["1", "2"] {
+        let req = Request::get(format!("http://{addr}/")).body(Default::default())?;
+        let mut future_resp = wasi_http.send_request(req, test_request_config())?;
+        future_resp.ready().await;
+        let resp = match future_resp.unwrap_ready().unwrap() {
+            Ok(resp) => resp,
+            Err(err) => bail!("expected Ok, got {err:?}"),
+        };
+        // The connection is returned to the pool once the body has been read
+        let body = match resp.resp.into_body().collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(err) => bail!("expected Ok, got {err:?}"),
+        };
+        assert_eq!(body, expected);
+    }
+    Ok(())
+}
+
 async fn test_instance_state(
     allowed_outbound_hosts: &str,
     allow_private_ips: bool,
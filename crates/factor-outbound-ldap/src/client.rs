@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, LdapError, LdapResult, SearchEntry};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::TlsClientConfig;
+use spin_world::spin::ldap::ldap::{Attribute, Entry, Error, Scope};
+
+use crate::{Directory, Search};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// LDAP result codes (RFC 4511)
+const SUCCESS: u32 = 0;
+const SIZE_LIMIT_EXCEEDED: u32 = 4;
+const NO_SUCH_OBJECT: u32 = 32;
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// A directory reached over LDAP, with a connection per operation.
+pub struct LdapDirectory {
+    /// An `ldap://` or `ldaps://` URL.
+    pub url: String,
+    /// Whether to upgrade `ldap://` connections with StartTLS.
+    pub starttls: bool,
+    /// The DN and password of the account searches are made as, if any.
+    pub service_account: Option<(String, String)>,
+}
+
+impl LdapDirectory {
+    async fn connect(&self, tls: &TlsClientConfig) -> Result<Ldap, Error> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(CONNECT_TIMEOUT)
+            .set_starttls(self.starttls)
+            .set_config(tls.inner());
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &self.url)
+            .await
+            .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
+        tokio::spawn(async move {
+            if let Err(e) = conn.drive().await {
+                tracing::warn!("LDAP connection failed: {e}");
+            }
+        });
+        Ok(ldap)
+    }
+}
+
+#[async_trait]
+impl Directory for LdapDirectory {
+    async fn bind(&self, name: &str, password: &str, tls: &TlsClientConfig) -> Result<bool, Error> {
+        let mut ldap = self.connect(tls).await?;
+        let result = ldap
+            .simple_bind(name, password)
+            .await
+            .map_err(other_error)?;
+        let _ = ldap.unbind().await;
+        match result.rc {
+            SUCCESS => Ok(true),
+            INVALID_CREDENTIALS => Ok(false),
+            _ => Err(result_error(&result)),
+        }
+    }
+
+    async fn search(&self, search: &Search, tls: &TlsClientConfig) -> Result<Vec<Entry>, Error> {
+        let mut ldap = self.connect(tls).await?;
+        if let Some((dn, password)) = &self.service_account {
+            let result = ldap.simple_bind(dn, password).await.map_err(other_error)?;
+            if result.rc != SUCCESS {
+                return Err(Error::ConnectionFailed(format!(
+                    "failed to bind as service account: {}",
+                    result_error(&result)
+                )));
+            }
+        }
+        let scope = match search.scope {
+            Scope::Base => ldap3::Scope::Base,
+            Scope::OneLevel => ldap3::Scope::OneLevel,
+            Scope::Subtree => ldap3::Scope::Subtree,
+        };
+        let result = ldap
+            .with_search_options(
+                ldap3::SearchOptions::new()
+                    .sizelimit(search.size_limit.try_into().unwrap_or(i32::MAX)),
+            )
+            .search(&search.base, scope, &search.filter, &search.attributes)
+            .await;
+        let _ = ldap.unbind().await;
+        let ldap3::SearchResult(entries, result) = result.map_err(|e| match e {
+            LdapError::FilterParsing => Error::InvalidFilter(search.filter.clone()),
+            e => other_error(e),
+        })?;
+        match result.rc {
+            SUCCESS => Ok(entries
+                .into_iter()
+                .map(|entry| convert_entry(SearchEntry::construct(entry)))
+                .collect()),
+            SIZE_LIMIT_EXCEEDED => Err(Error::SizeLimitExceeded),
+            NO_SUCH_OBJECT => Ok(vec![]),
+            _ => Err(result_error(&result)),
+        }
+    }
+}
+
+fn convert_entry(entry: SearchEntry) -> Entry {
+    let mut attributes = BTreeMap::<String, Attribute>::new();
+    for (name, values) in entry.attrs {
+        attributes
+            .entry(name.clone())
+            .or_insert_with(|| empty_attribute(name))
+            .values = values;
+    }
+    for (name, values) in entry.bin_attrs {
+        attributes
+            .entry(name.clone())
+            .or_insert_with(|| empty_attribute(name))
+            .binary_values = values;
+    }
+    Entry {
+        dn: entry.dn,
+        attributes: attributes.into_values().collect(),
+    }
+}
+
+fn empty_attribute(name: String) -> Attribute {
+    Attribute {
+        name,
+        values: vec![],
+        binary_values: vec![],
+    }
+}
+
+fn result_error(result: &LdapResult) -> Error {
+    Error::Other(format!("rc={}: {}", result.rc, result.text))
+}
+
+fn other_error(err: impl std::fmt::Display) -> Error {
+    Error::Other(err.to_string())
+}
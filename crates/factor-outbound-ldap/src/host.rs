@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_factor_outbound_networking::{
+    ComponentTlsClientConfigs, OutboundAllowedHosts, TlsClientConfig,
+};
+use spin_world::spin::ldap::ldap::{self as v3, Entry, Error, Scope, SearchRequest};
+use tracing::{instrument, Level};
+
+use crate::{
+    runtime_config::{DirectoryConfig, RuntimeConfig},
+    Search,
+};
+
+/// The filter used to fetch a single entry, which every entry matches.
+const ANY_ENTRY: &str = "(objectClass=*)";
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    component_tls_configs: ComponentTlsClientConfigs,
+    config: Option<Arc<RuntimeConfig>>,
+}
+
+impl InstanceState {
+    pub fn new(
+        allowed_hosts: OutboundAllowedHosts,
+        component_tls_configs: ComponentTlsClientConfigs,
+        config: Option<Arc<RuntimeConfig>>,
+    ) -> Self {
+        Self {
+            allowed_hosts,
+            component_tls_configs,
+            config,
+        }
+    }
+
+    /// Looks up the directory with the given label, checking the component
+    /// may use it.
+    async fn directory(&self, label: &str) -> Result<(&DirectoryConfig, TlsClientConfig), Error> {
+        let directory = self
+            .config
+            .as_ref()
+            .and_then(|config| config.directories.get(label))
+            .ok_or_else(|| Error::NoSuchDirectory(label.to_owned()))?;
+        let url = url::Url::parse(&directory.url).map_err(|e| Error::Other(e.to_string()))?;
+        if !self
+            .allowed_hosts
+            .check_url(&directory.url, url.scheme())
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+        {
+            return Err(Error::DirectoryNotAllowed(label.to_owned()));
+        }
+        let tls = self
+            .component_tls_configs
+            .get_client_config(url.host_str().unwrap_or_default())
+            .clone();
+        Ok((directory, tls))
+    }
+}
+
+impl v3::Host for InstanceState {
+    #[instrument(name = "spin_outbound_ldap.bind", skip(self, password), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn bind(
+        &mut self,
+        directory: String,
+        name: String,
+        password: String,
+    ) -> Result<bool, Error> {
+        let (directory, tls) = self.directory(&directory).await?;
+        // Directories treat a bind with an empty password as an
+        // unauthenticated bind, which succeeds for any name.
+        if password.is_empty() {
+            return Ok(false);
+        }
+        directory.directory.bind(&name, &password, &tls).await
+    }
+
+    #[instrument(name = "spin_outbound_ldap.search", skip(self, request), err(level = Level::INFO),
+        fields(otel.kind = "client", ldap.filter = %request.filter))]
+    async fn search(
+        &mut self,
+        directory: String,
+        request: SearchRequest,
+    ) -> Result<Vec<Entry>, Error> {
+        let (directory, tls) = self.directory(&directory).await?;
+        let search = Search {
+            base: directory.resolve_base(request.base)?,
+            scope: request.scope,
+            filter: request.filter,
+            attributes: request.attributes,
+            size_limit: request.size_limit.map_or(directory.max_results, |limit| {
+                limit.min(directory.max_results)
+            }),
+        };
+        directory.directory.search(&search, &tls).await
+    }
+
+    #[instrument(name = "spin_outbound_ldap.get_entry", skip(self, attributes), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_entry(
+        &mut self,
+        directory: String,
+        dn: String,
+        attributes: Vec<String>,
+    ) -> Result<Option<Entry>, Error> {
+        let (directory, tls) = self.directory(&directory).await?;
+        let search = Search {
+            base: directory.resolve_base(Some(dn))?,
+            scope: Scope::Base,
+            filter: ANY_ENTRY.into(),
+            attributes,
+            size_limit: 1,
+        };
+        let entries = directory.directory.search(&search, &tls).await?;
+        Ok(entries.into_iter().next())
+    }
+
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,85 @@
+mod client;
+mod host;
+pub mod runtime_config;
+
+use std::sync::Arc;
+
+use host::InstanceState;
+use runtime_config::RuntimeConfig;
+use spin_core::async_trait;
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, TlsClientConfig};
+use spin_factors::{
+    ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::ldap::ldap::{Entry, Error, Scope};
+
+pub use client::LdapDirectory;
+
+/// A factor that lets guests bind to and search LDAP directories configured
+/// in runtime config.
+#[derive(Default)]
+pub struct OutboundLdapFactor {
+    _priv: (),
+}
+
+impl OutboundLdapFactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Factor for OutboundLdapFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = Option<Arc<RuntimeConfig>>;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::ldap::ldap::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(ctx.take_runtime_config().map(Arc::new))
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        Ok(InstanceState::new(
+            networking.allowed_hosts(),
+            networking.component_tls_configs(),
+            ctx.app_state().clone(),
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// A directory guests may bind to and search.
+#[async_trait]
+pub trait Directory: Send + Sync {
+    /// Binds as `name`, returning false if the directory rejects the
+    /// credentials.
+    async fn bind(&self, name: &str, password: &str, tls: &TlsClientConfig) -> Result<bool, Error>;
+
+    /// Searches the directory, as its service account if it has one.
+    ///
+    /// A base which does not exist matches no entries.
+    async fn search(&self, search: &Search, tls: &TlsClientConfig) -> Result<Vec<Entry>, Error>;
+}
+
+/// A search whose base and size limit have been checked against the
+/// directory's configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    pub base: String,
+    pub scope: Scope,
+    pub filter: String,
+    pub attributes: Vec<String>,
+    pub size_limit: u32,
+}
@@ -0,0 +1,76 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_world::spin::ldap::ldap::Error;
+
+use crate::Directory;
+
+/// Runtime configuration for outbound LDAP.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    /// The directories guests may use, by label
+    pub directories: HashMap<String, DirectoryConfig>,
+}
+
+/// A directory guests may bind to and search.
+pub struct DirectoryConfig {
+    /// The directory's URL, e.g. `ldaps://dc1.corp.example.com`, which a
+    /// component's `allowed_outbound_hosts` must permit
+    pub url: String,
+    /// The DN guests' searches must be within
+    pub base_dn: String,
+    /// The most entries a search may return
+    pub max_results: u32,
+    pub directory: Arc<dyn Directory>,
+}
+
+impl DirectoryConfig {
+    /// Returns the base of a guest's search, which defaults to the
+    /// directory's base DN and must be within it.
+    pub fn resolve_base(&self, base: Option<String>) -> Result<String, Error> {
+        let Some(base) = base else {
+            return Ok(self.base_dn.clone());
+        };
+        if is_within(&base, &self.base_dn) {
+            Ok(base)
+        } else {
+            Err(Error::InvalidBase(base))
+        }
+    }
+}
+
+/// Returns whether `dn` is `base` or one of its descendants.
+///
+/// DNs are compared case-insensitively and ignoring spaces around separators,
+/// which is how directories such as Active Directory compare them.
+fn is_within(dn: &str, base: &str) -> bool {
+    let dn = normalize_dn(dn);
+    let base = normalize_dn(base);
+    base.is_empty() || dn == base || dn.ends_with(&format!(",{base}"))
+}
+
+fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(|rdn| {
+            let (attr, value) = rdn.split_once('=').unwrap_or((rdn, ""));
+            format!("{}={}", attr.trim(), value.trim()).to_lowercase()
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bases_are_confined_to_base_dn() {
+        let base = "DC=corp,DC=example,DC=com";
+        assert!(is_within("dc=corp, dc=example, dc=com", base));
+        assert!(is_within("OU=Staff,DC=corp,DC=example,DC=com", base));
+        assert!(!is_within("DC=example,DC=com", base));
+        assert!(!is_within("OU=Staff,DC=othercorp,DC=example,DC=com", base));
+        assert!(is_within("DC=example,DC=com", ""));
+    }
+}
@@ -0,0 +1,146 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{DirectoryConfig, RuntimeConfig};
+use crate::LdapDirectory;
+
+/// The most entries a search may return, by default.
+const DEFAULT_MAX_RESULTS: u32 = 1000;
+
+/// Get the runtime configuration for outbound LDAP from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [outbound_ldap.corp]
+/// url = "ldaps://dc1.corp.example.com"
+/// base_dn = "DC=corp,DC=example,DC=com"
+/// bind_dn = "CN=spin,OU=Service Accounts,DC=corp,DC=example,DC=com"
+/// bind_password = "secret"
+/// max_results = 500
+/// ```
+///
+/// `ldap://` URLs may set `starttls = true` to upgrade the connection.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("outbound_ldap") else {
+        return Ok(None);
+    };
+    let directories: HashMap<String, DirectoryToml> = value
+        .clone()
+        .try_into()
+        .context("failed to parse [outbound_ldap] table")?;
+    let directories = directories
+        .into_iter()
+        .map(|(label, toml)| {
+            let directory = directory_from_toml(toml)
+                .with_context(|| format!("invalid [outbound_ldap.{label}] table"))?;
+            Ok((label, directory))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Some(RuntimeConfig { directories }))
+}
+
+fn directory_from_toml(toml: DirectoryToml) -> anyhow::Result<DirectoryConfig> {
+    let url = url::Url::parse(&toml.url).context("invalid 'url'")?;
+    anyhow::ensure!(url.host_str().is_some(), "'url' must include a host");
+    match url.scheme() {
+        "ldap" => {
+            if !toml.starttls {
+                tracing::warn!(
+                    "LDAP directory {} is not configured to use TLS; credentials will be sent in plain text",
+                    toml.url
+                );
+            }
+        }
+        "ldaps" => anyhow::ensure!(
+            !toml.starttls,
+            "'starttls' is only supported for ldap:// URLs"
+        ),
+        scheme => anyhow::bail!("unsupported scheme '{scheme}'; expected 'ldap' or 'ldaps'"),
+    }
+    let service_account = match (toml.bind_dn, toml.bind_password) {
+        (Some(dn), Some(password)) => Some((dn, password)),
+        (None, None) => None,
+        _ => anyhow::bail!("'bind_dn' and 'bind_password' must be set together"),
+    };
+    anyhow::ensure!(toml.max_results > 0, "'max_results' must be greater than 0");
+    Ok(DirectoryConfig {
+        directory: Arc::new(LdapDirectory {
+            url: toml.url.clone(),
+            starttls: toml.starttls,
+            service_account,
+        }),
+        url: toml.url,
+        base_dn: toml.base_dn,
+        max_results: toml.max_results,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DirectoryToml {
+    url: String,
+    base_dn: String,
+    bind_dn: Option<String>,
+    bind_password: Option<String>,
+    #[serde(default)]
+    starttls: bool,
+    #[serde(default = "default_max_results")]
+    max_results: u32,
+}
+
+fn default_max_results() -> u32 {
+    DEFAULT_MAX_RESULTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directories() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_ldap.corp]
+            url = "ldaps://dc1.corp.example.com"
+            base_dn = "DC=corp,DC=example,DC=com"
+            bind_dn = "CN=spin,DC=corp,DC=example,DC=com"
+            bind_password = "secret"
+
+            [outbound_ldap.lab]
+            url = "ldap://ldap.lab.example.com"
+            base_dn = "dc=lab"
+            starttls = true
+            max_results = 50
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert_eq!(config.directories.len(), 2);
+        assert_eq!(config.directories["corp"].max_results, DEFAULT_MAX_RESULTS);
+        assert_eq!(config.directories["lab"].base_dn, "dc=lab");
+        assert_eq!(config.directories["lab"].max_results, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bind_dn_without_password() {
+        let table: toml::Table = toml::toml! {
+            [outbound_ldap.corp]
+            url = "ldaps://dc1.corp.example.com"
+            base_dn = "DC=corp,DC=example,DC=com"
+            bind_dn = "CN=spin,DC=corp,DC=example,DC=com"
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+
+    #[test]
+    fn rejects_starttls_with_ldaps() {
+        let table: toml::Table = toml::toml! {
+            [outbound_ldap.corp]
+            url = "ldaps://dc1.corp.example.com"
+            base_dn = "DC=corp,DC=example,DC=com"
+            starttls = true
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+use spin_core::async_trait;
+use spin_factor_outbound_ldap::{
+    runtime_config::{DirectoryConfig, RuntimeConfig},
+    Directory, OutboundLdapFactor, Search,
+};
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, TlsClientConfig};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::ldap::ldap::{Attribute, Entry, Error, Host, Scope, SearchRequest};
+
+/// Accepts one user's password, and records searches.
+#[derive(Default)]
+struct MockDirectory {
+    searches: Mutex<Vec<Search>>,
+}
+
+#[async_trait]
+impl Directory for MockDirectory {
+    async fn bind(
+        &self,
+        name: &str,
+        password: &str,
+        _tls: &TlsClientConfig,
+    ) -> Result<bool, Error> {
+        Ok(name == "alice@corp.example.com" && password == "hunter2")
+    }
+
+    async fn search(&self, search: &Search, _tls: &TlsClientConfig) -> Result<Vec<Entry>, Error> {
+        self.searches.lock().unwrap().push(search.clone());
+        Ok(vec![Entry {
+            dn: format!("CN=Alice,{}", search.base),
+            attributes: vec![Attribute {
+                name: "mail".into(),
+                values: vec!["alice@example.com".into()],
+                binary_values: vec![],
+            }],
+        }])
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    ldap: OutboundLdapFactor,
+}
+
+fn test_env(
+    directory: Arc<MockDirectory>,
+    manifest: toml::Table,
+) -> anyhow::Result<TestEnvironment<TestFactors>> {
+    let directory = DirectoryConfig {
+        url: "ldaps://dc1.corp.example.com".into(),
+        base_dn: "DC=corp,DC=example,DC=com".into(),
+        max_results: 100,
+        directory,
+    };
+    TestEnvironment::new(TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        ldap: OutboundLdapFactor::new(),
+    })
+    .extend_manifest(manifest)
+    .runtime_config(TestFactorsRuntimeConfig {
+        ldap: Some(RuntimeConfig {
+            directories: [("corp".to_string(), directory)].into(),
+        }),
+        ..Default::default()
+    })
+}
+
+fn allowed_manifest() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["ldaps://dc1.corp.example.com"]
+    }
+}
+
+fn search_request(base: Option<&str>, size_limit: Option<u32>) -> SearchRequest {
+    SearchRequest {
+        base: base.map(Into::into),
+        scope: Scope::Subtree,
+        filter: "(sAMAccountName=alice)".into(),
+        attributes: vec!["mail".into()],
+        size_limit,
+    }
+}
+
+#[tokio::test]
+async fn bind_checks_credentials() -> anyhow::Result<()> {
+    let directory = Arc::new(MockDirectory::default());
+    let mut state = test_env(directory, allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let user = || "alice@corp.example.com".to_string();
+    assert!(
+        state
+            .ldap
+            .bind("corp".into(), user(), "hunter2".into())
+            .await?
+    );
+    assert!(
+        !state
+            .ldap
+            .bind("corp".into(), user(), "wrong".into())
+            .await?
+    );
+    assert!(!state.ldap.bind("corp".into(), user(), "".into()).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_is_confined_to_base_dn() -> anyhow::Result<()> {
+    let directory = Arc::new(MockDirectory::default());
+    let mut state = test_env(directory.clone(), allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let entries = state
+        .ldap
+        .search("corp".into(), search_request(None, Some(1000)))
+        .await?;
+    assert_eq!(entries[0].dn, "CN=Alice,DC=corp,DC=example,DC=com");
+    state
+        .ldap
+        .search(
+            "corp".into(),
+            search_request(Some("OU=Staff,DC=corp,DC=example,DC=com"), Some(10)),
+        )
+        .await?;
+    {
+        let searches = directory.searches.lock().unwrap();
+        assert_eq!(searches[0].base, "DC=corp,DC=example,DC=com");
+        assert_eq!(searches[0].size_limit, 100);
+        assert_eq!(searches[1].base, "OU=Staff,DC=corp,DC=example,DC=com");
+        assert_eq!(searches[1].size_limit, 10);
+    }
+
+    let Err(err) = state
+        .ldap
+        .search(
+            "corp".into(),
+            search_request(Some("DC=example,DC=com"), None),
+        )
+        .await
+    else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::InvalidBase(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_entry_searches_base_scope() -> anyhow::Result<()> {
+    let directory = Arc::new(MockDirectory::default());
+    let mut state = test_env(directory.clone(), allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let entry = state
+        .ldap
+        .get_entry(
+            "corp".into(),
+            "OU=Staff,DC=corp,DC=example,DC=com".into(),
+            vec![],
+        )
+        .await?;
+    assert!(entry.is_some());
+    let searches = directory.searches.lock().unwrap();
+    assert_eq!(searches[0].scope, Scope::Base);
+    assert_eq!(searches[0].size_limit, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn directory_not_in_allowed_hosts_fails() -> anyhow::Result<()> {
+    let directory = Arc::new(MockDirectory::default());
+    let mut state = test_env(
+        directory,
+        toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        },
+    )?
+    .build_instance_state()
+    .await?;
+
+    let Err(err) = state
+        .ldap
+        .search("corp".into(), search_request(None, None))
+        .await
+    else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::DirectoryNotAllowed(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_directory_fails() -> anyhow::Result<()> {
+    let directory = Arc::new(MockDirectory::default());
+    let mut state = test_env(directory, allowed_manifest())?
+        .build_instance_state()
+        .await?;
+
+    let Err(err) = state
+        .ldap
+        .bind("other".into(), "alice".into(), "hunter2".into())
+        .await
+    else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::NoSuchDirectory(_)));
+    Ok(())
+}
@@ -3,22 +3,30 @@ use std::{sync::Arc, time::Duration};
 use anyhow::Result;
 use spin_core::{async_trait, wasmtime::component::Resource};
 use spin_factor_outbound_networking::OutboundAllowedHosts;
+use spin_world::spin::mqtt::mqtt as v3;
 use spin_world::v2::mqtt::{self as v2, Connection, Error, Qos};
 use tracing::{instrument, Level};
 
+use crate::runtime_config::SessionConfig;
 use crate::ClientCreator;
 
 pub struct InstanceState {
     allowed_hosts: OutboundAllowedHosts,
     connections: spin_resource_table::Table<Arc<dyn MqttClient>>,
     create_client: Arc<dyn ClientCreator>,
+    session: Arc<SessionConfig>,
 }
 
 impl InstanceState {
-    pub fn new(allowed_hosts: OutboundAllowedHosts, create_client: Arc<dyn ClientCreator>) -> Self {
+    pub fn new(
+        allowed_hosts: OutboundAllowedHosts,
+        create_client: Arc<dyn ClientCreator>,
+        session: Arc<SessionConfig>,
+    ) -> Self {
         Self {
             allowed_hosts,
             create_client,
+            session,
             connections: spin_resource_table::Table::new(1024),
         }
     }
@@ -26,7 +34,13 @@ impl InstanceState {
 
 #[async_trait]
 pub trait MqttClient: Send + Sync {
-    async fn publish_bytes(&self, topic: String, qos: Qos, payload: Vec<u8>) -> Result<(), Error>;
+    async fn publish_bytes(
+        &self,
+        topic: String,
+        qos: Qos,
+        retain: bool,
+        payload: Vec<u8>,
+    ) -> Result<(), Error>;
 }
 
 impl InstanceState {
@@ -34,20 +48,55 @@ impl InstanceState {
         self.allowed_hosts.check_url(address, "mqtt").await
     }
 
-    async fn establish_connection(
+    async fn open_connection<T: 'static>(
+        &mut self,
+        address: String,
+        username: String,
+        password: String,
+        keep_alive_interval: u64,
+    ) -> Result<Resource<T>, Error> {
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| v2::Error::Other(e.to_string()))?
+        {
+            return Err(v2::Error::ConnectionFailed(format!(
+                "address {address} is not permitted"
+            )));
+        }
+        self.establish_connection(
+            address,
+            username,
+            password,
+            Duration::from_secs(keep_alive_interval),
+        )
+        .await
+    }
+
+    async fn establish_connection<T: 'static>(
         &mut self,
         address: String,
         username: String,
         password: String,
         keep_alive_interval: Duration,
-    ) -> Result<Resource<Connection>, Error> {
+    ) -> Result<Resource<T>, Error> {
+        let client = (self.create_client).create(
+            address,
+            username,
+            password,
+            keep_alive_interval,
+            &self.session,
+        )?;
         self.connections
-            .push((self.create_client).create(address, username, password, keep_alive_interval)?)
+            .push(client)
             .map(Resource::new_own)
             .map_err(|_| Error::TooManyConnections)
     }
 
-    async fn get_conn(&self, connection: Resource<Connection>) -> Result<&dyn MqttClient, Error> {
+    async fn get_conn<T: 'static>(
+        &self,
+        connection: Resource<T>,
+    ) -> Result<&dyn MqttClient, Error> {
         self.connections
             .get(connection.rep())
             .ok_or(Error::Other(
@@ -72,22 +121,8 @@ impl v2::HostConnection for InstanceState {
         password: String,
         keep_alive_interval: u64,
     ) -> Result<Resource<Connection>, Error> {
-        if !self
-            .is_address_allowed(&address)
+        self.open_connection(address, username, password, keep_alive_interval)
             .await
-            .map_err(|e| v2::Error::Other(e.to_string()))?
-        {
-            return Err(v2::Error::ConnectionFailed(format!(
-                "address {address} is not permitted"
-            )));
-        }
-        self.establish_connection(
-            address,
-            username,
-            password,
-            Duration::from_secs(keep_alive_interval),
-        )
-        .await
     }
 
     /// Publish a message to the MQTT broker.
@@ -107,7 +142,7 @@ impl v2::HostConnection for InstanceState {
     ) -> Result<(), Error> {
         let conn = self.get_conn(connection).await.map_err(other_error)?;
 
-        conn.publish_bytes(topic, qos, payload).await?;
+        conn.publish_bytes(topic, qos, false, payload).await?;
 
         Ok(())
     }
@@ -118,6 +153,46 @@ impl v2::HostConnection for InstanceState {
     }
 }
 
+impl v3::Host for InstanceState {}
+
+impl v3::HostConnection for InstanceState {
+    #[instrument(name = "spin_outbound_mqtt.open_connection", skip(self, password), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(
+        &mut self,
+        address: String,
+        username: String,
+        password: String,
+        keep_alive_interval: u64,
+    ) -> Result<Resource<v3::Connection>, Error> {
+        self.open_connection(address, username, password, keep_alive_interval)
+            .await
+    }
+
+    /// Publish a message to the MQTT broker, optionally asking the broker to retain it.
+    #[instrument(name = "spin_outbound_mqtt.publish", skip(self, connection, payload), err(level = Level::INFO),
+        fields(otel.kind = "producer", otel.name = format!("{} publish", topic), messaging.operation = "publish",
+        messaging.system = "mqtt", messaging.mqtt.retain = retain))]
+    async fn publish(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        topic: String,
+        payload: Vec<u8>,
+        qos: Qos,
+        retain: bool,
+    ) -> Result<(), Error> {
+        let conn = self.get_conn(connection).await.map_err(other_error)?;
+
+        conn.publish_bytes(topic, qos, retain, payload).await?;
+
+        Ok(())
+    }
+
+    async fn drop(&mut self, connection: Resource<v3::Connection>) -> anyhow::Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
 pub fn other_error(e: impl std::fmt::Display) -> Error {
     Error::Other(e.to_string())
 }
@@ -1,4 +1,5 @@
 mod host;
+pub mod runtime_config;
 
 use std::sync::Arc;
 use std::time::Duration;
@@ -6,6 +7,7 @@ use std::time::Duration;
 use host::other_error;
 use host::InstanceState;
 use rumqttc::{AsyncClient, Event, Incoming, Outgoing, QoS};
+use runtime_config::{RuntimeConfig, SessionConfig};
 use spin_core::async_trait;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factors::{
@@ -27,20 +29,22 @@ impl OutboundMqttFactor {
 }
 
 impl Factor for OutboundMqttFactor {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = Arc<SessionConfig>;
     type InstanceBuilder = InstanceState;
 
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v2::mqtt::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::mqtt::mqtt::add_to_linker)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(Arc::new(runtime_config.session))
     }
 
     fn prepare<T: RuntimeFactors>(
@@ -53,6 +57,7 @@ impl Factor for OutboundMqttFactor {
         Ok(InstanceState::new(
             allowed_hosts,
             self.create_client.clone(),
+            ctx.app_state().clone(),
         ))
     }
 }
@@ -70,22 +75,27 @@ const MQTT_CHANNEL_CAP: usize = 1000;
 impl NetworkedMqttClient {
     /// Create a [`ClientCreator`] that creates a [`NetworkedMqttClient`].
     pub fn creator() -> Arc<dyn ClientCreator> {
-        Arc::new(|address, username, password, keep_alive_interval| {
-            Ok(Arc::new(NetworkedMqttClient::create(
-                address,
-                username,
-                password,
-                keep_alive_interval,
-            )?) as _)
-        })
+        Arc::new(
+            |address, username, password, keep_alive_interval, session: &SessionConfig| {
+                Ok(Arc::new(NetworkedMqttClient::create(
+                    address,
+                    username,
+                    password,
+                    keep_alive_interval,
+                    session,
+                )?) as _)
+            },
+        )
     }
 
-    /// Create a new [`NetworkedMqttClient`] with the given address, username, password, and keep alive interval.
+    /// Create a new [`NetworkedMqttClient`] with the given address, username, password, keep alive interval
+    /// and session options.
     pub fn create(
         address: String,
         username: String,
         password: String,
         keep_alive_interval: Duration,
+        session: &SessionConfig,
     ) -> Result<Self, Error> {
         let mut conn_opts = rumqttc::MqttOptions::parse_url(address).map_err(|e| {
             tracing::error!("MQTT URL parse error: {e:?}");
@@ -93,6 +103,15 @@ impl NetworkedMqttClient {
         })?;
         conn_opts.set_credentials(username, password);
         conn_opts.set_keep_alive(keep_alive_interval);
+        conn_opts.set_clean_session(!session.persistent);
+        if let Some(last_will) = &session.last_will {
+            conn_opts.set_last_will(rumqttc::LastWill::new(
+                &last_will.topic,
+                last_will.payload.clone(),
+                to_rumqttc_qos(last_will.qos),
+                last_will.retain,
+            ));
+        }
         let (client, event_loop) = AsyncClient::new(conn_opts, MQTT_CHANNEL_CAP);
         Ok(Self {
             inner: client,
@@ -103,15 +122,17 @@ impl NetworkedMqttClient {
 
 #[async_trait]
 impl MqttClient for NetworkedMqttClient {
-    async fn publish_bytes(&self, topic: String, qos: Qos, payload: Vec<u8>) -> Result<(), Error> {
-        let qos = match qos {
-            Qos::AtMostOnce => rumqttc::QoS::AtMostOnce,
-            Qos::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
-            Qos::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
-        };
+    async fn publish_bytes(
+        &self,
+        topic: String,
+        qos: Qos,
+        retain: bool,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let qos = to_rumqttc_qos(qos);
         // Message published to EventLoop (not MQTT Broker)
         self.inner
-            .publish_bytes(topic, qos, false, payload.into())
+            .publish_bytes(topic, qos, retain, payload.into())
             .await
             .map_err(other_error)?;
 
@@ -136,6 +157,14 @@ impl MqttClient for NetworkedMqttClient {
     }
 }
 
+fn to_rumqttc_qos(qos: Qos) -> QoS {
+    match qos {
+        Qos::AtMostOnce => QoS::AtMostOnce,
+        Qos::AtLeastOnce => QoS::AtLeastOnce,
+        Qos::ExactlyOnce => QoS::ExactlyOnce,
+    }
+}
+
 /// A trait for creating MQTT client.
 #[async_trait]
 pub trait ClientCreator: Send + Sync {
@@ -145,12 +174,15 @@ pub trait ClientCreator: Send + Sync {
         username: String,
         password: String,
         keep_alive_interval: Duration,
+        session: &SessionConfig,
     ) -> Result<Arc<dyn MqttClient>, Error>;
 }
 
 impl<F> ClientCreator for F
 where
-    F: Fn(String, String, String, Duration) -> Result<Arc<dyn MqttClient>, Error> + Send + Sync,
+    F: Fn(String, String, String, Duration, &SessionConfig) -> Result<Arc<dyn MqttClient>, Error>
+        + Send
+        + Sync,
 {
     fn create(
         &self,
@@ -158,7 +190,8 @@ where
         username: String,
         password: String,
         keep_alive_interval: Duration,
+        session: &SessionConfig,
     ) -> Result<Arc<dyn MqttClient>, Error> {
-        self(address, username, password, keep_alive_interval)
+        self(address, username, password, keep_alive_interval, session)
     }
 }
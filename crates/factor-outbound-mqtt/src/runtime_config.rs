@@ -0,0 +1,33 @@
+pub mod spin;
+
+use spin_world::v2::mqtt::Qos;
+
+/// Runtime configuration for outbound MQTT.
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    /// Session options applied to every connection
+    pub session: SessionConfig,
+}
+
+/// Session options applied to every connection.
+#[derive(Clone, Debug, Default)]
+pub struct SessionConfig {
+    /// If true, ask the broker to keep the session (subscriptions and
+    /// undelivered QoS 1 and 2 messages) when the client disconnects.
+    ///
+    /// Persistent sessions are keyed on the client ID, so the connection
+    /// address should include a stable `client_id`.
+    pub persistent: bool,
+    /// A message the broker publishes on the client's behalf if it
+    /// disconnects ungracefully.
+    pub last_will: Option<LastWill>,
+}
+
+/// An MQTT last-will message.
+#[derive(Clone, Debug)]
+pub struct LastWill {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: Qos,
+    pub retain: bool,
+}
@@ -0,0 +1,114 @@
+use anyhow::Context;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+use spin_world::v2::mqtt::Qos;
+
+use super::{LastWill, RuntimeConfig, SessionConfig};
+
+/// Get the runtime configuration for outbound MQTT from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [outbound_mqtt]
+/// persistent_session = true
+///
+/// [outbound_mqtt.last_will]
+/// topic = "devices/status"
+/// payload = "offline"
+/// qos = 1
+/// retain = true
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("outbound_mqtt") else {
+        return Ok(None);
+    };
+    let toml: OutboundMqttToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [outbound_mqtt] table")?;
+    let last_will = toml
+        .last_will
+        .map(LastWillToml::load)
+        .transpose()
+        .context("failed to parse [outbound_mqtt.last_will] table")?;
+    Ok(Some(RuntimeConfig {
+        session: SessionConfig {
+            persistent: toml.persistent_session,
+            last_will,
+        },
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutboundMqttToml {
+    #[serde(default)]
+    persistent_session: bool,
+    last_will: Option<LastWillToml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LastWillToml {
+    topic: String,
+    #[serde(default)]
+    payload: String,
+    #[serde(default)]
+    qos: u8,
+    #[serde(default)]
+    retain: bool,
+}
+
+impl LastWillToml {
+    fn load(self) -> anyhow::Result<LastWill> {
+        let qos = match self.qos {
+            0 => Qos::AtMostOnce,
+            1 => Qos::AtLeastOnce,
+            2 => Qos::ExactlyOnce,
+            other => anyhow::bail!("invalid qos {other}; must be 0, 1 or 2"),
+        };
+        Ok(LastWill {
+            topic: self.topic,
+            payload: self.payload.into_bytes(),
+            qos,
+            retain: self.retain,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_session_config() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_mqtt]
+            persistent_session = true
+
+            [outbound_mqtt.last_will]
+            topic = "devices/status"
+            payload = "offline"
+            qos = 2
+            retain = true
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert!(config.session.persistent);
+        let last_will = config.session.last_will.unwrap();
+        assert_eq!(last_will.topic, "devices/status");
+        assert_eq!(last_will.payload, b"offline");
+        assert!(matches!(last_will.qos, Qos::ExactlyOnce));
+        assert!(last_will.retain);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_qos() {
+        let table: toml::Table = toml::toml! {
+            [outbound_mqtt.last_will]
+            topic = "devices/status"
+            qos = 3
+        };
+        assert!(config_from_table(&table).is_err());
+    }
+}
@@ -3,11 +3,13 @@ use std::time::Duration;
 
 use anyhow::{bail, Result};
 use spin_core::async_trait;
+use spin_factor_outbound_mqtt::runtime_config::SessionConfig;
 use spin_factor_outbound_mqtt::{ClientCreator, MqttClient, OutboundMqttFactor};
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::{anyhow, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::mqtt::mqtt as v3;
 use spin_world::v2::mqtt::{self as v2, Error, HostConnection, Qos};
 
 pub struct MockMqttClient {}
@@ -18,6 +20,7 @@ impl MqttClient for MockMqttClient {
         &self,
         _topic: String,
         _qos: Qos,
+        _retain: bool,
         _payload: Vec<u8>,
     ) -> Result<(), Error> {
         Ok(())
@@ -31,6 +34,7 @@ impl ClientCreator for MockMqttClient {
         _username: String,
         _password: String,
         _keep_alive_interval: Duration,
+        _session: &SessionConfig,
     ) -> Result<Arc<dyn MqttClient>, Error> {
         Ok(Arc::new(MockMqttClient {}))
     }
@@ -130,3 +134,29 @@ async fn exercise_publish() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn exercise_retained_publish() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let res = v3::HostConnection::open(
+        &mut state.mqtt,
+        "mqtt://mqtt.test:1883".to_string(),
+        "username".to_string(),
+        "password".to_string(),
+        1,
+    )
+    .await?;
+
+    v3::HostConnection::publish(
+        &mut state.mqtt,
+        res,
+        "message".to_string(),
+        b"test message".to_vec(),
+        Qos::ExactlyOnce,
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,5 +1,9 @@
+use std::net::IpAddr;
+
 use anyhow::Result;
 use spin_core::wasmtime::component::Resource;
+use spin_world::spin::mysql::mysql::{self as v3};
+use spin_world::spin::sql::types as sql_types;
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql::{self as v2, Connection};
 use spin_world::v2::rdbms_types as v2_types;
@@ -7,35 +11,64 @@ use spin_world::v2::rdbms_types::ParameterValue;
 use tracing::field::Empty;
 use tracing::{instrument, Level};
 
-use crate::client::Client;
+use crate::client::{Client, ClientFactory};
 use crate::InstanceState;
 
-impl<C: Client> InstanceState<C> {
-    async fn open_connection(&mut self, address: &str) -> Result<Resource<Connection>, v2::Error> {
+impl<CF: ClientFactory> InstanceState<CF> {
+    async fn open_connection<Conn: 'static>(
+        &mut self,
+        address: &str,
+    ) -> Result<Resource<Conn>, v3::Error> {
+        let address = self.connection_aliases.resolve(address);
+        let resolved_ips = self.resolve_host(address).await?;
         self.connections
             .push(
-                C::build_client(address)
+                self.client_factory
+                    .get_client(address, &resolved_ips)
                     .await
-                    .map_err(|e| v2::Error::ConnectionFailed(format!("{e:?}")))?,
+                    .map_err(|e| v3::Error::ConnectionFailed(format!("{e:?}")))?,
             )
-            .map_err(|_| v2::Error::ConnectionFailed("too many connections".into()))
+            .map_err(|_| v3::Error::ConnectionFailed("too many connections".into()))
             .map(Resource::new_own)
     }
 
-    async fn get_client(&mut self, connection: Resource<Connection>) -> Result<&mut C, v2::Error> {
+    async fn get_client<Conn: 'static>(
+        &mut self,
+        connection: Resource<Conn>,
+    ) -> Result<&mut CF::Client, v3::Error> {
         self.connections
             .get_mut(connection.rep())
-            .ok_or_else(|| v2::Error::ConnectionFailed("no connection found".into()))
+            .ok_or_else(|| v3::Error::ConnectionFailed("no connection found".into()))
     }
 
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        let address = self.connection_aliases.resolve(address);
         self.allowed_hosts.check_url(address, "mysql").await
     }
+
+    /// Resolves the host in `address` to the IP addresses to connect to, so
+    /// that the connection is made to an address the resolver vetted rather
+    /// than to whatever the host resolves to when connecting.
+    async fn resolve_host(&self, address: &str) -> Result<Vec<IpAddr>, v3::Error> {
+        let host = url::Url::parse(address)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+            .ok_or_else(|| v3::Error::ConnectionFailed(format!("{address} has no host")))?;
+        let addrs = self
+            .dns_resolver
+            .lookup_host(&host, 0)
+            .await
+            .map_err(|err| {
+                tracing::info!(%err, "Refusing MySQL connection to '{host}'");
+                v3::Error::ConnectionFailed(format!("address {host} is not permitted"))
+            })?;
+        Ok(addrs.iter().map(|addr| addr.ip()).collect())
+    }
 }
 
-impl<C: Client> v2::Host for InstanceState<C> {}
+impl<CF: ClientFactory> v2::Host for InstanceState<CF> {}
 
-impl<C: Client> v2::HostConnection for InstanceState<C> {
+impl<CF: ClientFactory> v2::HostConnection for InstanceState<CF> {
     #[instrument(name = "spin_outbound_mysql.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
     async fn open(&mut self, address: String) -> Result<Resource<Connection>, v2::Error> {
         spin_factor_outbound_networking::record_address_fields(&address);
@@ -49,7 +82,7 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
                 "address {address} is not permitted"
             )));
         }
-        self.open_connection(&address).await
+        self.open_connection(&address).await.map_err(Into::into)
     }
 
     #[instrument(name = "spin_outbound_mysql.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
@@ -59,10 +92,12 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<(), v2::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         self.get_client(connection)
             .await?
             .execute(statement, params)
             .await
+            .map_err(Into::into)
     }
 
     #[instrument(name = "spin_outbound_mysql.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
@@ -72,10 +107,12 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<v2_types::RowSet, v2::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         self.get_client(connection)
             .await?
             .query(statement, params)
             .await
+            .map_err(Into::into)
     }
 
     async fn drop(&mut self, connection: Resource<Connection>) -> Result<()> {
@@ -84,12 +121,85 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
     }
 }
 
-impl<C: Send> v2_types::Host for InstanceState<C> {
+impl<CF: ClientFactory> v3::Host for InstanceState<CF> {}
+
+impl<CF: ClientFactory> v3::HostConnection for InstanceState<CF> {
+    #[instrument(name = "spin_outbound_mysql.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
+    async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v3::Error> {
+        spin_factor_outbound_networking::record_address_fields(&address);
+
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| v3::Error::Other(e.to_string()))?
+        {
+            return Err(v3::Error::ConnectionFailed(format!(
+                "address {address} is not permitted"
+            )));
+        }
+        self.open_connection(&address).await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn execute(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<(), v3::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        self.get_client(connection)
+            .await?
+            .execute(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn query(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<v2_types::RowSet, v3::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        self.get_client(connection)
+            .await?
+            .query(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.execute_many", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement, db.operation.batch.size = params.len()))]
+    async fn execute_many(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<Vec<ParameterValue>>,
+    ) -> Result<u64, v3::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        self.get_client(connection)
+            .await?
+            .execute_many(statement, params)
+            .await
+    }
+
+    async fn drop(&mut self, connection: Resource<v3::Connection>) -> Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+impl<CF: ClientFactory> v2_types::Host for InstanceState<CF> {
     fn convert_error(&mut self, error: v2::Error) -> Result<v2::Error> {
         Ok(error)
     }
 }
 
+impl<CF: ClientFactory> sql_types::Host for InstanceState<CF> {
+    fn convert_error(&mut self, error: sql_types::Error) -> Result<sql_types::Error> {
+        Ok(error)
+    }
+}
+
 /// Delegate a function call to the v2::HostConnection implementation
 macro_rules! delegate {
     ($self:ident.$name:ident($address:expr, $($arg:expr),*)) => {{
@@ -100,7 +210,7 @@ macro_rules! delegate {
         }
         let connection = match $self.open_connection(&$address).await {
             Ok(c) => c,
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(v2::Error::from(e).into()),
         };
         <Self as v2::HostConnection>::$name($self, connection, $($arg),*)
             .await
@@ -108,7 +218,7 @@ macro_rules! delegate {
     }};
 }
 
-impl<C: Client> v1::Host for InstanceState<C> {
+impl<CF: ClientFactory> v1::Host for InstanceState<CF> {
     async fn execute(
         &mut self,
         address: String,
@@ -1,50 +1,66 @@
 pub mod client;
 mod host;
+pub mod runtime_config;
 
-use client::Client;
-use mysql_async::Conn as MysqlClient;
-use spin_factor_outbound_networking::{OutboundAllowedHosts, OutboundNetworkingFactor};
+use std::sync::Arc;
+
+use client::{ClientFactory, PooledMysqlClientFactory};
+use spin_factor_outbound_networking::{
+    ConnectionAliases, DnsResolver, EgressThrottle, OutboundAllowedHosts, OutboundNetworkingFactor,
+};
 use spin_factors::{Factor, InitContext, RuntimeFactors, SelfInstanceBuilder};
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql::{self as v2};
 
-pub struct OutboundMysqlFactor<C = MysqlClient> {
-    _phantom: std::marker::PhantomData<C>,
+pub struct OutboundMysqlFactor<CF = PooledMysqlClientFactory> {
+    _phantom: std::marker::PhantomData<CF>,
 }
 
-impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
-    type RuntimeConfig = ();
-    type AppState = ();
-    type InstanceBuilder = InstanceState<C>;
+impl<CF: ClientFactory> Factor for OutboundMysqlFactor<CF> {
+    type RuntimeConfig = runtime_config::RuntimeConfig;
+    type AppState = AppState<CF>;
+    type InstanceBuilder = InstanceState<CF>;
 
     fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(v1::add_to_linker)?;
         ctx.link_bindings(v2::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::mysql::mysql::add_to_linker)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: spin_factors::ConfigureAppContext<T, Self>,
+        mut ctx: spin_factors::ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let mut runtime_config: runtime_config::RuntimeConfig =
+            ctx.take_runtime_config().unwrap_or_default();
+        let connection_aliases = Arc::new(std::mem::take(&mut runtime_config.connections));
+        Ok(AppState {
+            client_factory: Arc::new(CF::new(runtime_config)),
+            connection_aliases,
+        })
     }
 
     fn prepare<T: spin_factors::RuntimeFactors>(
         &self,
         mut ctx: spin_factors::PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let egress_throttle = outbound_networking.egress_throttle();
+        let dns_resolver = outbound_networking.dns_resolver();
         Ok(InstanceState {
             allowed_hosts,
+            egress_throttle,
+            dns_resolver,
+            client_factory: ctx.app_state().client_factory.clone(),
+            connection_aliases: ctx.app_state().connection_aliases.clone(),
             connections: Default::default(),
         })
     }
 }
 
-impl<C> Default for OutboundMysqlFactor<C> {
+impl<CF> Default for OutboundMysqlFactor<CF> {
     fn default() -> Self {
         Self {
             _phantom: Default::default(),
@@ -52,15 +68,24 @@ impl<C> Default for OutboundMysqlFactor<C> {
     }
 }
 
-impl<C> OutboundMysqlFactor<C> {
+impl<CF> OutboundMysqlFactor<CF> {
     pub fn new() -> Self {
         Self::default()
     }
 }
 
-pub struct InstanceState<C> {
+pub struct AppState<CF> {
+    client_factory: Arc<CF>,
+    connection_aliases: Arc<ConnectionAliases>,
+}
+
+pub struct InstanceState<CF: ClientFactory> {
     allowed_hosts: OutboundAllowedHosts,
-    connections: spin_resource_table::Table<C>,
+    egress_throttle: EgressThrottle,
+    dns_resolver: DnsResolver,
+    client_factory: Arc<CF>,
+    connection_aliases: Arc<ConnectionAliases>,
+    connections: spin_resource_table::Table<CF::Client>,
 }
 
-impl<C: Send + 'static> SelfInstanceBuilder for InstanceState<C> {}
+impl<CF: ClientFactory> SelfInstanceBuilder for InstanceState<CF> {}
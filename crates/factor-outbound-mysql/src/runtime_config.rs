@@ -0,0 +1,98 @@
+pub mod spin;
+
+use std::time::Duration;
+
+use spin_factor_outbound_networking::ConnectionAliases;
+
+/// Runtime configuration for outbound MySQL.
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    /// TLS configs, matched against the host of a connection address
+    pub tls_configs: Vec<TlsRuntimeConfig>,
+    /// Configuration for the connection pool kept for each connection address
+    pub pool: PoolConfig,
+    /// Named connections which guests can open in place of an address
+    pub connections: ConnectionAliases,
+}
+
+impl RuntimeConfig {
+    /// Returns the TLS configuration that applies to the given host, if any.
+    pub fn tls_config_for_host(&self, host: &str) -> Option<&TlsRuntimeConfig> {
+        self.tls_configs
+            .iter()
+            .find(|config| config.hosts.iter().any(|h| h == host))
+    }
+}
+
+/// Connection pool configuration.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// The number of idle connections the pool keeps open.
+    pub min_size: usize,
+    /// The maximum number of open connections per pool.
+    pub max_size: usize,
+    /// How long to wait for a free connection before failing.
+    pub acquire_timeout: Duration,
+    /// The number of prepared statements each connection caches. Zero disables caching.
+    pub statement_cache_size: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 16,
+            acquire_timeout: Duration::from_secs(30),
+            statement_cache_size: 32,
+        }
+    }
+}
+
+/// TLS configuration for connections to one or more MySQL host(s).
+#[derive(Debug, Default)]
+pub struct TlsRuntimeConfig {
+    /// The host(s) this configuration applies to.
+    pub hosts: Vec<String>,
+    /// The SSL mode to use. If set, overrides any `ssl-mode` in the connection string.
+    pub ssl_mode: Option<SslMode>,
+    /// PEM-encoded CA certs that should be considered valid roots, in
+    /// addition to the system roots.
+    pub root_certificates: Vec<Vec<u8>>,
+    /// A client identity to present to the server.
+    pub client_identity: Option<ClientIdentity>,
+}
+
+/// A PKCS#12 archive containing a client certificate chain and private key.
+#[derive(Debug)]
+pub struct ClientIdentity {
+    pub pkcs12_der: Vec<u8>,
+    pub password: Option<String>,
+}
+
+/// A MySQL `ssl-mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SslMode {
+    /// Never use TLS.
+    Disabled,
+    /// Always use TLS, verifying the server certificate.
+    ///
+    /// Spin does not support opportunistic TLS, so `PREFERRED` is treated as `REQUIRED`.
+    Required,
+    /// Always use TLS, verifying the server certificate chain but not its host name.
+    VerifyCa,
+    /// Always use TLS, verifying the server certificate chain and host name.
+    VerifyIdentity,
+}
+
+impl SslMode {
+    /// Parses a mode, case-insensitively, as it appears in a connection string.
+    pub(crate) fn parse(mode: &str) -> Option<Self> {
+        match mode.to_uppercase().replace('-', "_").as_str() {
+            "DISABLED" => Some(Self::Disabled),
+            "PREFERRED" | "REQUIRED" => Some(Self::Required),
+            "VERIFY_CA" => Some(Self::VerifyCa),
+            "VERIFY_IDENTITY" => Some(Self::VerifyIdentity),
+            _ => None,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::net::IpAddr;
 use std::ops::Range;
 
 use anyhow::{bail, ensure, Context};
@@ -10,6 +11,10 @@ const ALLOWED_HTTP_KEY: MetadataKey<Vec<String>> = MetadataKey::new("allowed_htt
 pub const SERVICE_CHAINING_DOMAIN: &str = "spin.internal";
 pub const SERVICE_CHAINING_DOMAIN_SUFFIX: &str = ".spin.internal";
 
+/// The suffix of schemes that address a Unix domain socket rather than a
+/// network host, e.g. `redis+unix:///var/run/redis.sock`.
+pub const UNIX_SOCKET_SCHEME_SUFFIX: &str = "+unix";
+
 /// Get the raw values of the `allowed_outbound_hosts` locked app metadata key.
 ///
 /// This has support for converting the old `allowed_http_hosts` key to the new `allowed_outbound_hosts` key.
@@ -97,7 +102,16 @@ impl AllowedHostConfig {
                 _ => bail!("{url:?} does not contain a scheme (e.g., 'http://' or '*://')\nLearn more: https://spinframework.dev/v3/http-outbound#granting-http-permissions-to-components"),
             }
         };
-        let (host, rest) = rest.rsplit_once(':').unwrap_or((rest, ""));
+        if let Some(base) = scheme.strip_suffix(UNIX_SOCKET_SCHEME_SUFFIX) {
+            SchemeConfig::validate_scheme(base)?;
+            return Ok(Self {
+                scheme: SchemeConfig::List(vec![scheme.into()]),
+                host: HostConfig::parse_socket_path(rest)?,
+                port: PortConfig::Any,
+                original,
+            });
+        }
+        let (host, rest) = split_host_port(rest);
         let port = match rest.split_once('/') {
             Some((port, path)) => {
                 if !path.is_empty() {
@@ -163,18 +177,25 @@ impl SchemeConfig {
             return Ok(Self::Any);
         }
 
-        if scheme.starts_with('{') {
-            // TODO:
-            bail!("scheme lists are not yet supported")
+        if let Some(schemes) = parse_list(scheme)? {
+            for scheme in &schemes {
+                Self::validate_scheme(scheme)?;
+            }
+            return Ok(Self::List(schemes.into_iter().map(Into::into).collect()));
         }
 
-        if scheme.chars().any(|c| !c.is_alphabetic()) {
-            anyhow::bail!(" scheme {scheme:?} contains non alphabetic character");
-        }
+        Self::validate_scheme(scheme)?;
 
         Ok(Self::List(vec![scheme.into()]))
     }
 
+    fn validate_scheme(scheme: &str) -> anyhow::Result<()> {
+        if scheme.is_empty() || scheme.chars().any(|c| !c.is_alphabetic()) {
+            anyhow::bail!(" scheme {scheme:?} contains non alphabetic character");
+        }
+        Ok(())
+    }
+
     pub fn allows_any(&self) -> bool {
         matches!(self, Self::Any)
     }
@@ -194,6 +215,9 @@ pub enum HostConfig {
     ToSelf,
     List(Vec<String>),
     Cidr(ip_network::IpNetwork),
+    /// The path of a Unix domain socket, or of a directory of sockets if it
+    /// ends in `/*`
+    SocketPath(String),
 }
 
 impl HostConfig {
@@ -207,15 +231,33 @@ impl HostConfig {
             return Ok(Self::ToSelf);
         }
 
-        if host.starts_with('{') {
-            ensure!(host.ends_with('}'));
-            bail!("host lists are not yet supported")
+        if let Some(hosts) = parse_list(host)? {
+            let mut list = Vec::with_capacity(hosts.len());
+            for host in hosts {
+                match Self::parse(host)? {
+                    Self::List(l) => list.extend(l),
+                    _ => bail!(
+                        "Invalid allowed host {host}: host lists may only contain exact host names"
+                    ),
+                }
+            }
+            return Ok(Self::List(list));
         }
 
         if let Ok(net) = ip_network::IpNetwork::from_str_truncate(host) {
             return Ok(Self::Cidr(net));
         }
 
+        // Bracketed IPv6 literals are kept bracketed (as in URLs), but
+        // normalized so that e.g. `[0:0::1]` matches `[::1]`
+        if let Some(literal) = host.strip_prefix('[') {
+            let ip: std::net::Ipv6Addr = literal
+                .strip_suffix(']')
+                .and_then(|literal| literal.parse().ok())
+                .with_context(|| format!("Invalid allowed host {host}: invalid IPv6 address"))?;
+            return Ok(Self::List(vec![format!("[{ip}]")]));
+        }
+
         if matches!(host.split('/').nth(1), Some(path) if !path.is_empty()) {
             bail!("hosts must not contain paths");
         }
@@ -237,6 +279,21 @@ impl HostConfig {
         Ok(Self::List(vec![host.into()]))
     }
 
+    fn parse_socket_path(path: &str) -> anyhow::Result<Self> {
+        let path = path.trim();
+        ensure!(
+            path.starts_with('/'),
+            "Invalid allowed socket path {path:?}: paths must be absolute, e.g. 'redis+unix:///var/run/redis.sock'"
+        );
+        let literal = path.strip_suffix("/*").unwrap_or(path);
+        if literal.contains('*') {
+            bail!(
+                "Invalid allowed socket path {path:?}: wildcards are allowed only as a final '/*'"
+            );
+        }
+        Ok(Self::SocketPath(path.into()))
+    }
+
     fn allows(&self, host: &str) -> bool {
         match self {
             HostConfig::Any => true,
@@ -244,11 +301,18 @@ impl HostConfig {
             HostConfig::List(l) => l.iter().any(|h| h.as_str() == host),
             HostConfig::ToSelf => false,
             HostConfig::Cidr(c) => {
+                let host = host.trim_start_matches('[').trim_end_matches(']');
                 let Ok(ip) = host.parse::<std::net::IpAddr>() else {
                     return false;
                 };
                 c.contains(ip)
             }
+            HostConfig::SocketPath(path) => match path.strip_suffix('*') {
+                Some(dir) => host
+                    .strip_prefix(dir)
+                    .is_some_and(|name| !name.is_empty() && !name.contains('/')),
+                None => path == host,
+            },
         }
     }
 
@@ -260,12 +324,25 @@ impl HostConfig {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PortConfig {
     Any,
+    /// The well-known port of whichever scheme the request uses
+    SchemeDefault,
     List(Vec<IndividualPortConfig>),
 }
 
 impl PortConfig {
     fn parse(port: &str, scheme: &str) -> anyhow::Result<PortConfig> {
         if port.is_empty() {
+            if let Some(schemes) = parse_list(scheme)? {
+                // Each scheme in a list gets its own default port, e.g.
+                // `{http,https}://example.com` allows ports 80 and 443 respectively.
+                for scheme in schemes {
+                    ensure!(
+                        well_known_port(scheme).is_some(),
+                        "no port was provided and the scheme {scheme:?} does not have a known default port number"
+                    );
+                }
+                return Ok(PortConfig::SchemeDefault);
+            }
             return well_known_port(scheme)
                 .map(|p| PortConfig::List(vec![IndividualPortConfig::Port(p)]))
                 .with_context(|| format!("no port was provided and the scheme {scheme:?} does not have a known default port number"));
@@ -274,9 +351,12 @@ impl PortConfig {
             return Ok(PortConfig::Any);
         }
 
-        if port.starts_with('{') {
-            // TODO:
-            bail!("port lists are not yet supported")
+        if let Some(ports) = parse_list(port)? {
+            let ports = ports
+                .into_iter()
+                .map(IndividualPortConfig::parse)
+                .collect::<anyhow::Result<_>>()?;
+            return Ok(Self::List(ports));
         }
 
         let port = IndividualPortConfig::parse(port)?;
@@ -287,6 +367,10 @@ impl PortConfig {
     fn allows(&self, port: Option<u16>, scheme: &str) -> bool {
         match self {
             PortConfig::Any => true,
+            PortConfig::SchemeDefault => match well_known_port(scheme) {
+                Some(default) => port.unwrap_or(default) == default,
+                None => false,
+            },
             PortConfig::List(l) => {
                 let port = match port.or_else(|| well_known_port(scheme)) {
                     Some(p) => p,
@@ -328,14 +412,40 @@ impl IndividualPortConfig {
     }
 }
 
+/// Splits a `{a,b,c}` list into its trimmed items.
+///
+/// Returns `None` if `value` is not a list.
+fn parse_list(value: &str) -> anyhow::Result<Option<Vec<&str>>> {
+    let Some(inner) = value.strip_prefix('{') else {
+        return Ok(None);
+    };
+    let Some(inner) = inner.strip_suffix('}') else {
+        bail!("list {value:?} is missing a closing '}}'");
+    };
+    let items = inner.split(',').map(str::trim).collect::<Vec<_>>();
+    ensure!(
+        items.iter().all(|item| !item.is_empty()),
+        "list {value:?} contains an empty item"
+    );
+    Ok(Some(items))
+}
+
 fn well_known_port(scheme: &str) -> Option<u16> {
     match scheme {
         "postgres" => Some(5432),
         "mysql" => Some(3306),
         "redis" => Some(6379),
         "mqtt" => Some(1883),
+        "amqp" => Some(5672),
+        "amqps" => Some(5671),
+        "sftp" => Some(22),
+        "ftps" => Some(21),
+        "ldap" => Some(389),
+        "ldaps" => Some(636),
         "http" => Some(80),
         "https" => Some(443),
+        "ws" => Some(80),
+        "wss" => Some(443),
         _ => None,
     }
 }
@@ -346,6 +456,18 @@ pub enum AllowedHostsConfig {
     SpecificHosts(Vec<AllowedHostConfig>),
 }
 
+impl std::fmt::Display for AllowedHostsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AllowedHostsConfig::All => f.write_str("*://*:*"),
+            AllowedHostsConfig::SpecificHosts(hosts) => {
+                let hosts = hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+                write!(f, "[{}]", hosts.join(", "))
+            }
+        }
+    }
+}
+
 enum PartialAllowedHostConfig {
     Exact(AllowedHostConfig),
     Unresolved(spin_expressions::Template),
@@ -409,6 +531,43 @@ impl AllowedHostsConfig {
         }
     }
 
+    /// Returns the exact host names allowed for the given scheme and port.
+    ///
+    /// Socket addresses carry only an IP, so these names must be resolved to
+    /// match them. IP literals, CIDRs and wildcard subdomains are not included.
+    pub fn host_names_for(&self, scheme: &str, port: u16) -> Vec<&str> {
+        match self {
+            AllowedHostsConfig::All => vec![],
+            AllowedHostsConfig::SpecificHosts(hosts) => hosts
+                .iter()
+                .filter(|h| h.scheme.allows(scheme) && h.port.allows(Some(port), scheme))
+                .flat_map(|h| match &h.host {
+                    HostConfig::List(names) => names.as_slice(),
+                    _ => &[],
+                })
+                .map(String::as_str)
+                .filter(|name| name.parse::<IpAddr>().is_err())
+                .collect(),
+        }
+    }
+
+    /// Determine if connections to a host name are allowed with any scheme
+    /// and port.
+    ///
+    /// Leading service labels, as in `_sip._tcp.example.com`, are ignored, so
+    /// that the SRV and TXT records of an allowed host can be looked up.
+    pub fn allows_host_name(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let mut name = name.as_str();
+        while let Some((_, rest)) = name.strip_prefix('_').and_then(|name| name.split_once('.')) {
+            name = rest;
+        }
+        match self {
+            AllowedHostsConfig::All => true,
+            AllowedHostsConfig::SpecificHosts(hosts) => hosts.iter().any(|h| h.host.allows(name)),
+        }
+    }
+
     pub fn allows_relative_url(&self, schemes: &[&str]) -> bool {
         match self {
             AllowedHostsConfig::All => true,
@@ -438,10 +597,31 @@ impl OutboundUrl {
     /// Parse a URL.
     ///
     /// If parsing `url` fails, `{scheme}://` is prepended to `url` and parsing is tried again.
+    /// URLs with a `+unix` scheme have no host; their socket path stands in for it.
     pub fn parse(url: impl Into<String>, scheme: &str) -> anyhow::Result<Self> {
         let mut url = url.into();
         let original = url.clone();
 
+        if let Some((url_scheme, _)) = url.split_once("://") {
+            if url_scheme.ends_with(UNIX_SOCKET_SCHEME_SUFFIX) {
+                let parsed = url::Url::parse(&url)?;
+                ensure!(
+                    parsed.host_str().unwrap_or_default().is_empty(),
+                    "{url:?} must not have a host component"
+                );
+                ensure!(
+                    parsed.path().starts_with('/'),
+                    "{url:?} does not have a socket path"
+                );
+                return Ok(Self {
+                    scheme: parsed.scheme().to_owned(),
+                    host: parsed.path().to_owned(),
+                    port: None,
+                    original,
+                });
+            }
+        }
+
         // Ensure that the authority is url encoded. Since the authority is ignored after this,
         // we can always url encode the authority even if it is already encoded.
         if let Some(at) = url.find('@') {
@@ -516,7 +696,7 @@ pub fn parse_service_chaining_target(url: &http::Uri) -> Option<String> {
 }
 
 fn parse_service_chaining_host(host: &str) -> Option<String> {
-    let (host, _) = host.rsplit_once(':').unwrap_or((host, ""));
+    let (host, _) = split_host_port(host);
 
     let (first, rest) = host.split_once('.')?;
 
@@ -527,6 +707,19 @@ fn parse_service_chaining_host(host: &str) -> Option<String> {
     }
 }
 
+/// Splits `authority` into its host and whatever follows the port separator.
+///
+/// A bracketed IPv6 literal is kept whole, brackets included.
+fn split_host_port(authority: &str) -> (&str, &str) {
+    if authority.starts_with('[') {
+        if let Some(end) = authority.find(']') {
+            let (host, rest) = authority.split_at(end + 1);
+            return (host, rest.strip_prefix(':').unwrap_or(rest));
+        }
+    }
+    authority.rsplit_once(':').unwrap_or((authority, ""))
+}
+
 #[cfg(test)]
 mod test {
     impl AllowedHostConfig {
@@ -622,6 +815,15 @@ mod test {
             ),
             AllowedHostConfig::parse("https://spin.fermyon.dev").unwrap()
         );
+
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("wss"),
+                HostConfig::new("spin.fermyon.dev"),
+                PortConfig::new(443)
+            ),
+            AllowedHostConfig::parse("wss://spin.fermyon.dev").unwrap()
+        );
     }
 
     #[test]
@@ -759,7 +961,39 @@ mod test {
             AllowedHostConfig::parse("http://[::1]:8001").unwrap()
         );
 
-        assert!(AllowedHostConfig::parse("http://[::1]").is_err())
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("http"),
+                HostConfig::new("[::1]"),
+                PortConfig::new(80)
+            ),
+            AllowedHostConfig::parse("http://[::1]").unwrap()
+        );
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("https"),
+                HostConfig::new("[2001:db8::1]"),
+                PortConfig::Any
+            ),
+            AllowedHostConfig::parse("https://[2001:db8:0::1]:*").unwrap()
+        );
+        assert!(AllowedHostConfig::parse("http://[::1:8001").is_err());
+        assert!(AllowedHostConfig::parse("http://[not-an-ip]:80").is_err());
+    }
+
+    #[test]
+    fn test_allowed_hosts_match_ipv6_urls() {
+        let allowed = AllowedHostsConfig::parse(
+            &["https://[::1]:8443", "*://fd00::/8:80"],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        let url = |url: &str| OutboundUrl::parse(url, "https").unwrap();
+        assert!(allowed.allows(&url("https://[::1]:8443/path")));
+        assert!(allowed.allows(&url("https://[0::1]:8443")));
+        assert!(!allowed.allows(&url("https://[::2]:8443")));
+        assert!(allowed.allows(&url("http://[fd00::abcd]:80")));
+        assert!(!allowed.allows(&url("http://[fe80::1]:80")));
     }
 
     #[test]
@@ -883,6 +1117,90 @@ mod test {
         assert!(allowed.allows(&OutboundUrl::parse("user%3Apass%23word@xyz.com", "mysql").unwrap()));
     }
 
+    #[test]
+    fn test_allowed_hosts_accepts_lists() {
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::List(vec!["http".into(), "https".into()]),
+                HostConfig::List(vec!["a.example.com".into(), "b.example.com".into()]),
+                PortConfig::List(vec![
+                    IndividualPortConfig::Port(80),
+                    IndividualPortConfig::Range(8000..9000)
+                ])
+            ),
+            AllowedHostConfig::parse(
+                "{http, https}://{a.example.com,b.example.com}:{80,8000..9000}"
+            )
+            .unwrap()
+        );
+        assert!(AllowedHostConfig::parse("http://{a.example.com,*.example.com}").is_err());
+        assert!(AllowedHostConfig::parse("http://{a.example.com").is_err());
+        assert!(AllowedHostConfig::parse("http://example.com:{80,}").is_err());
+    }
+
+    #[test]
+    fn test_scheme_list_uses_default_port_per_scheme() {
+        let allowed =
+            AllowedHostsConfig::parse(&["{http,https}://example.com"], &dummy_resolver()).unwrap();
+        assert!(allowed.allows(&OutboundUrl::parse("http://example.com", "http").unwrap()));
+        assert!(allowed.allows(&OutboundUrl::parse("https://example.com", "https").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("http://example.com:443", "http").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("ftp://example.com:21", "ftp").unwrap()));
+        assert!(AllowedHostConfig::parse("{http,tcp}://example.com").is_err());
+    }
+
+    #[test]
+    fn test_host_names_for_socket_scheme_and_port() {
+        let allowed = AllowedHostsConfig::parse(
+            &[
+                "tcp://statsd.internal:8125",
+                "{tcp,udp}://{a.example.com,b.example.com}:9000",
+                "udp://*.example.org:53",
+                "tcp://10.0.0.1:8125",
+                "http://web.example.com:8125",
+            ],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        assert_eq!(vec!["statsd.internal"], allowed.host_names_for("tcp", 8125));
+        assert_eq!(
+            vec!["a.example.com", "b.example.com"],
+            allowed.host_names_for("udp", 9000)
+        );
+        assert!(allowed.host_names_for("udp", 53).is_empty());
+        assert!(allowed.host_names_for("udp", 8125).is_empty());
+        assert!(AllowedHostsConfig::All.host_names_for("tcp", 80).is_empty());
+    }
+
+    #[test]
+    fn test_allows_host_name_ignores_service_labels() {
+        let allowed = AllowedHostsConfig::parse(
+            &["https://example.com", "tcp://*.internal:5432"],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        assert!(allowed.allows_host_name("example.com"));
+        assert!(allowed.allows_host_name("Example.COM."));
+        assert!(allowed.allows_host_name("_sip._tcp.example.com"));
+        assert!(allowed.allows_host_name("db.internal"));
+        assert!(!allowed.allows_host_name("www.example.com"));
+        assert!(!allowed.allows_host_name("_dmarc.other.com"));
+        assert!(AllowedHostsConfig::All.allows_host_name("other.com"));
+    }
+
+    #[test]
+    fn test_allowed_hosts_display_lists_patterns() {
+        let allowed = AllowedHostsConfig::parse(
+            &["https://example.com", "redis://cache.example.com:6380"],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        assert_eq!(
+            "[https://example.com, redis://cache.example.com:6380]",
+            allowed.to_string()
+        );
+    }
+
     #[test]
     fn test_cidr() {
         let allowed =
@@ -890,6 +1208,56 @@ mod test {
         assert!(allowed.allows(&OutboundUrl::parse("tcp://127.0.0.1:63551", "tcp").unwrap()));
     }
 
+    #[test]
+    fn test_allowed_hosts_accepts_socket_paths() {
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("redis+unix"),
+                HostConfig::SocketPath("/var/run/redis.sock".into()),
+                PortConfig::Any
+            ),
+            AllowedHostConfig::parse("redis+unix:///var/run/redis.sock").unwrap()
+        );
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("redis+unix"),
+                HostConfig::SocketPath("/var/run/redis/*".into()),
+                PortConfig::Any
+            ),
+            AllowedHostConfig::parse("redis+unix:///var/run/redis/*").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_allowed_hosts_rejects_invalid_socket_paths() {
+        assert!(AllowedHostConfig::parse("redis+unix://var/run/redis.sock").is_err());
+        assert!(AllowedHostConfig::parse("redis+unix:///var/*/redis.sock").is_err());
+        assert!(AllowedHostConfig::parse("redis+unix:///var/run/redis*").is_err());
+        assert!(AllowedHostConfig::parse("re-dis+unix:///var/run/redis.sock").is_err());
+    }
+
+    #[test]
+    fn test_allowed_hosts_can_be_socket_paths() {
+        let allowed = AllowedHostsConfig::parse(
+            &[
+                "redis+unix:///var/run/redis.sock",
+                "redis+unix:///tmp/sockets/*",
+            ],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        let allows = |url| allowed.allows(&OutboundUrl::parse(url, "redis").unwrap());
+
+        assert!(allows("redis+unix:///var/run/redis.sock"));
+        assert!(allows("redis+unix:///var/run/redis.sock?db=1"));
+        assert!(allows("redis+unix:///tmp/sockets/cache.sock"));
+        assert!(!allows("redis+unix:///var/run/other.sock"));
+        assert!(!allows("redis+unix:///tmp/sockets/nested/cache.sock"));
+        assert!(!allows("unix+unix:///var/run/redis.sock"));
+        assert!(!allows("redis://localhost:6379"));
+        assert!(OutboundUrl::parse("redis+unix://localhost/var/run/redis.sock", "redis").is_err());
+    }
+
     #[tokio::test]
     async fn validate_service_chaining_for_components_fails() {
         let manifest = toml::toml! {
@@ -0,0 +1,110 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use tokio::net::TcpStream;
+
+/// How long to wait for a connection attempt before racing it with the next
+/// address (RFC 8305 recommends 250ms).
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to the first reachable address of `addrs` ("Happy Eyeballs").
+///
+/// Addresses are tried alternating between IPv6 and IPv4, starting with the
+/// family of the first address. Each attempt gets a head start before the
+/// next one begins, so a host whose IPv6 (or IPv4) route is broken doesn't
+/// stall the connection until the attempt times out.
+pub async fn connect_tcp(addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+    let mut pending = interleave_families(addrs).into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = None;
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some(addr) => attempts.push(TcpStream::connect(addr)),
+                None => break,
+            }
+        }
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => {
+                    last_err = Some(err);
+                    // Don't wait out the delay once an attempt has failed
+                    if let Some(addr) = pending.next() {
+                        attempts.push(TcpStream::connect(addr));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if !pending.as_slice().is_empty() => {
+                if let Some(addr) = pending.next() {
+                    attempts.push(TcpStream::connect(addr));
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            "no addresses to connect to",
+        )
+    }))
+}
+
+/// Orders `addrs` so that address families alternate, starting with the
+/// family of the first address.
+fn interleave_families(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return vec![];
+    };
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .iter()
+        .copied()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    let mut other = other.into_iter();
+    let mut ordered = Vec::with_capacity(addrs.len());
+    for addr in preferred {
+        ordered.push(addr);
+        ordered.extend(other.next());
+    }
+    ordered.extend(other);
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn families_are_interleaved() {
+        let resolved = addrs(&["[::1]:80", "[::2]:80", "[::3]:80", "10.0.0.1:80"]);
+        assert_eq!(
+            interleave_families(&resolved),
+            addrs(&["[::1]:80", "10.0.0.1:80", "[::2]:80", "[::3]:80"])
+        );
+        let resolved = addrs(&["10.0.0.1:80", "10.0.0.2:80", "[::1]:80"]);
+        assert_eq!(
+            interleave_families(&resolved),
+            addrs(&["10.0.0.1:80", "[::1]:80", "10.0.0.2:80"])
+        );
+        assert!(interleave_families(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn unreachable_addresses_are_skipped() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let stream = connect_tcp(&[closed_addr, listener.local_addr().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+
+        connect_tcp(&[]).await.unwrap_err();
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use hickory_resolver::{
+    config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts},
+    error::ResolveErrorKind,
+    proto::{
+        op::ResponseCode,
+        rr::{Name, RData, RecordType},
+    },
+    TokioAsyncResolver,
+};
+
+use crate::BlockedNetworks;
+
+/// DNS settings for outbound connections.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DnsConfig {
+    /// Name servers to query instead of the system resolver.
+    pub servers: Vec<SocketAddr>,
+    /// How long lookups are cached for. With custom `servers` this caps the
+    /// TTL of the records; with the system resolver, which doesn't report
+    /// TTLs, lookups are cached for exactly this long.
+    pub cache_ttl: Option<Duration>,
+    /// If true, resolved addresses in the blocked networks are dropped, and
+    /// host names which resolve only to blocked addresses are refused, so
+    /// that with `block_private_networks` an allowed name can't be pointed
+    /// into a private network (DNS rebinding).
+    pub enforce_resolved_ips: bool,
+}
+
+/// Resolves the host names of outbound connections.
+///
+/// A resolver is shared by all of an app's instances, so that they share its
+/// cache.
+#[derive(Clone)]
+pub struct DnsResolver {
+    inner: Arc<ResolverInner>,
+}
+
+struct ResolverInner {
+    /// The resolver for custom name servers, if any are configured.
+    custom: Option<TokioAsyncResolver>,
+    /// Cached system resolver lookups, if caching is enabled.
+    cache: Option<LookupCache>,
+    /// The networks resolved addresses are checked against, if
+    /// [`DnsConfig::enforce_resolved_ips`] is set.
+    enforced_blocked_networks: Option<BlockedNetworks>,
+    /// Caps the TTL of looked up records.
+    cache_ttl: Option<Duration>,
+    /// The resolver for the system's name servers, created on the first
+    /// record lookup without custom name servers.
+    system: OnceLock<Option<TokioAsyncResolver>>,
+}
+
+impl Default for DnsResolver {
+    fn default() -> Self {
+        Self::new(&DnsConfig::default(), BlockedNetworks::default())
+    }
+}
+
+impl DnsResolver {
+    pub(crate) fn new(config: &DnsConfig, blocked_networks: BlockedNetworks) -> Self {
+        let custom = (!config.servers.is_empty()).then(|| {
+            let mut resolver_config = ResolverConfig::new();
+            for server in &config.servers {
+                resolver_config.add_name_server(NameServerConfig::new(*server, Protocol::Udp));
+                resolver_config.add_name_server(NameServerConfig::new(*server, Protocol::Tcp));
+            }
+            TokioAsyncResolver::tokio(resolver_config, resolver_opts(config.cache_ttl))
+        });
+        let cache = match (&custom, config.cache_ttl) {
+            (None, Some(ttl)) if !ttl.is_zero() => Some(LookupCache::new(ttl)),
+            _ => None,
+        };
+        Self {
+            inner: Arc::new(ResolverInner {
+                custom,
+                cache,
+                enforced_blocked_networks: config.enforce_resolved_ips.then_some(blocked_networks),
+                cache_ttl: config.cache_ttl,
+                system: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Resolves `host` to the socket addresses to connect to on `port`.
+    ///
+    /// IP literals (including bracketed IPv6 literals) are returned as is.
+    /// Resolved addresses may be refused; see
+    /// [`DnsConfig::enforce_resolved_ips`].
+    pub async fn lookup_host(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        let ips = self.lookup_ips(host).await?;
+        let ips = self.check_resolved_ips(host, ips)?;
+        Ok(ips
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect())
+    }
+
+    /// Looks up the records of type `record_type` for `name`.
+    ///
+    /// Unlike host name lookups, record lookups always query name servers
+    /// directly, so that the records' TTLs are known. A name with no records
+    /// of the type has an empty list of records; a name which doesn't exist
+    /// fails with [`io::ErrorKind::NotFound`].
+    pub async fn lookup_records(
+        &self,
+        name: &str,
+        record_type: DnsRecordType,
+    ) -> io::Result<Vec<DnsRecord>> {
+        let name = Name::from_utf8(name)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let resolver = self.record_resolver()?;
+        let lookup = match resolver.lookup(name, record_type.into()).await {
+            Ok(lookup) => lookup,
+            Err(err) => {
+                return match err.kind() {
+                    ResolveErrorKind::NoRecordsFound { response_code, .. }
+                        if *response_code == ResponseCode::NXDomain =>
+                    {
+                        Err(io::Error::new(io::ErrorKind::NotFound, err))
+                    }
+                    ResolveErrorKind::NoRecordsFound { .. } => Ok(vec![]),
+                    _ => Err(io::Error::other(err)),
+                };
+            }
+        };
+        Ok(lookup
+            .record_iter()
+            .filter_map(|record| {
+                Some(DnsRecord {
+                    data: record_data(record.data()?)?,
+                    ttl: record.ttl(),
+                })
+            })
+            .collect())
+    }
+
+    fn record_resolver(&self) -> io::Result<&TokioAsyncResolver> {
+        if let Some(resolver) = &self.inner.custom {
+            return Ok(resolver);
+        }
+        self.inner
+            .system
+            .get_or_init(|| {
+                let (config, _) = hickory_resolver::system_conf::read_system_conf()
+                    .inspect_err(|err| tracing::error!(%err, "Failed to read system DNS config"))
+                    .ok()?;
+                Some(TokioAsyncResolver::tokio(
+                    config,
+                    resolver_opts(self.inner.cache_ttl),
+                ))
+            })
+            .as_ref()
+            .ok_or_else(|| io::Error::other("the system DNS config could not be read"))
+    }
+
+    async fn lookup_ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        if let Some(resolver) = &self.inner.custom {
+            let lookup = resolver.lookup_ip(host).await.map_err(io::Error::other)?;
+            return Ok(lookup.iter().collect());
+        }
+        if let Some(ips) = self.inner.cache.as_ref().and_then(|cache| cache.get(host)) {
+            return Ok(ips);
+        }
+        let ips: Vec<IpAddr> = tokio::net::lookup_host((host, 0))
+            .await?
+            .map(|addr| addr.ip())
+            .collect();
+        if let Some(cache) = &self.inner.cache {
+            cache.insert(host, ips.clone());
+        }
+        Ok(ips)
+    }
+
+    fn check_resolved_ips(&self, host: &str, mut ips: Vec<IpAddr>) -> io::Result<Vec<IpAddr>> {
+        let Some(blocked_networks) = &self.inner.enforced_blocked_networks else {
+            return Ok(ips);
+        };
+        let blocked = blocked_networks.remove_blocked(&mut ips);
+        if ips.is_empty() && !blocked.is_empty() {
+            tracing::error!(
+                "error.type" = "destination_ip_prohibited",
+                ?blocked,
+                host,
+                "host name resolved only to blocked addresses"
+            );
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{host} resolved to a prohibited address"),
+            ));
+        }
+        Ok(ips)
+    }
+}
+
+fn resolver_opts(cache_ttl: Option<Duration>) -> ResolverOpts {
+    let mut opts = ResolverOpts::default();
+    if let Some(ttl) = cache_ttl {
+        opts.positive_max_ttl = Some(ttl);
+        opts.negative_max_ttl = Some(ttl);
+    }
+    opts
+}
+
+/// The types of records [`DnsResolver::lookup_records`] can look up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Txt,
+    Srv,
+    Mx,
+}
+
+impl From<DnsRecordType> for RecordType {
+    fn from(record_type: DnsRecordType) -> Self {
+        match record_type {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Txt => RecordType::TXT,
+            DnsRecordType::Srv => RecordType::SRV,
+            DnsRecordType::Mx => RecordType::MX,
+        }
+    }
+}
+
+/// A record returned by [`DnsResolver::lookup_records`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsRecord {
+    pub data: DnsRecordData,
+    /// How many seconds the record may be cached for.
+    pub ttl: u32,
+}
+
+/// The data of a [`DnsRecord`]. Host names are given without a trailing dot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DnsRecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    /// The record's character strings, concatenated
+    Txt(String),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+}
+
+/// Converts record data, or returns `None` for types that can't be looked
+/// up, such as the CNAMEs followed to the records.
+fn record_data(data: &RData) -> Option<DnsRecordData> {
+    let host_name = |name: &Name| name.to_utf8().trim_end_matches('.').to_owned();
+    Some(match data {
+        RData::A(a) => DnsRecordData::A(a.0),
+        RData::AAAA(aaaa) => DnsRecordData::Aaaa(aaaa.0),
+        RData::TXT(txt) => DnsRecordData::Txt(
+            txt.txt_data()
+                .iter()
+                .map(|chunk| String::from_utf8_lossy(chunk))
+                .collect(),
+        ),
+        RData::SRV(srv) => DnsRecordData::Srv {
+            priority: srv.priority(),
+            weight: srv.weight(),
+            port: srv.port(),
+            target: host_name(srv.target()),
+        },
+        RData::MX(mx) => DnsRecordData::Mx {
+            preference: mx.preference(),
+            exchange: host_name(mx.exchange()),
+        },
+        _ => return None,
+    })
+}
+
+/// System resolver lookups, each kept for a fixed time.
+struct LookupCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, Vec<IpAddr>)>>,
+}
+
+impl LookupCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    fn get(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(host) {
+            Some((expires, ips)) if *expires > Instant::now() => Some(ips.clone()),
+            Some(_) => {
+                entries.remove(host);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, host: &str, ips: Vec<IpAddr>) {
+        let expires = Instant::now() + self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (expires, _)| *expires > Instant::now());
+        entries.insert(host.to_owned(), (expires, ips));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enforcing(blocked_networks: BlockedNetworks) -> DnsResolver {
+        DnsResolver::new(
+            &DnsConfig {
+                enforce_resolved_ips: true,
+                ..Default::default()
+            },
+            blocked_networks,
+        )
+    }
+
+    fn private_blocked() -> BlockedNetworks {
+        BlockedNetworks::new([], true)
+    }
+
+    #[tokio::test]
+    async fn ip_literals_are_not_resolved() {
+        let addrs = enforcing(private_blocked())
+            .lookup_host("[::1]", 8080)
+            .await
+            .unwrap();
+        assert_eq!(addrs, ["[::1]:8080".parse().unwrap()]);
+    }
+
+    #[test]
+    fn blocked_resolutions_are_refused_when_enforced() {
+        let private: Vec<IpAddr> = vec![
+            "10.0.0.1".parse().unwrap(),
+            "fd00::1".parse().unwrap(),
+            "::ffff:10.0.0.2".parse().unwrap(),
+        ];
+        enforcing(private_blocked())
+            .check_resolved_ips("rebind.example.com", private.clone())
+            .unwrap_err();
+        assert_eq!(
+            DnsResolver::new(&DnsConfig::default(), private_blocked())
+                .check_resolved_ips("rebind.example.com", private.clone())
+                .unwrap(),
+            private
+        );
+        // Private networks are only refused if the operator blocks them
+        assert_eq!(
+            enforcing(BlockedNetworks::default())
+                .check_resolved_ips("rebind.example.com", private.clone())
+                .unwrap(),
+            private
+        );
+
+        let mixed = vec![
+            "10.0.0.1".parse().unwrap(),
+            "93.184.216.34".parse().unwrap(),
+        ];
+        assert_eq!(
+            enforcing(private_blocked())
+                .check_resolved_ips("example.com", mixed)
+                .unwrap(),
+            ["93.184.216.34".parse::<IpAddr>().unwrap()]
+        );
+
+        // So are the operator's other blocked networks, including through
+        // IPv4-compatible addresses
+        let blocked = BlockedNetworks::new(["93.184.0.0/16".parse().unwrap()], false);
+        enforcing(blocked)
+            .check_resolved_ips("example.com", vec!["::93.184.216.34".parse().unwrap()])
+            .unwrap_err();
+    }
+
+    #[test]
+    fn record_data_is_converted() {
+        use hickory_resolver::proto::rr::rdata::{CNAME, MX, SRV, TXT};
+
+        let txt = RData::TXT(TXT::new(vec!["v=spf1 ".into(), "-all".into()]));
+        assert_eq!(
+            record_data(&txt),
+            Some(DnsRecordData::Txt("v=spf1 -all".into()))
+        );
+
+        let target = Name::from_utf8("sip.example.com.").unwrap();
+        let srv = RData::SRV(SRV::new(10, 5, 5060, target));
+        assert_eq!(
+            record_data(&srv),
+            Some(DnsRecordData::Srv {
+                priority: 10,
+                weight: 5,
+                port: 5060,
+                target: "sip.example.com".into(),
+            })
+        );
+
+        let exchange = Name::from_utf8("mail.example.com.").unwrap();
+        let mx = RData::MX(MX::new(20, exchange.clone()));
+        assert_eq!(
+            record_data(&mx),
+            Some(DnsRecordData::Mx {
+                preference: 20,
+                exchange: "mail.example.com".into(),
+            })
+        );
+
+        assert_eq!(record_data(&RData::CNAME(CNAME(exchange))), None);
+    }
+
+    #[test]
+    fn cache_entries_expire() {
+        let cache = LookupCache::new(Duration::from_secs(60));
+        cache.insert("example.com", vec!["93.184.216.34".parse().unwrap()]);
+        assert!(cache.get("example.com").is_some());
+        assert!(cache.get("other.example.com").is_none());
+
+        let cache = LookupCache::new(Duration::ZERO);
+        cache.insert("example.com", vec!["93.184.216.34".parse().unwrap()]);
+        assert!(cache.get("example.com").is_none());
+    }
+}
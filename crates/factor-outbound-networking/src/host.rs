@@ -0,0 +1,96 @@
+use std::io;
+
+use spin_factors::anyhow;
+use spin_world::spin::networking::{allowed_hosts as v3, dns};
+use tracing::{instrument, Level};
+
+use crate::{DnsRecordData, DnsRecordType, InstanceState, OutboundUrl};
+
+impl v3::Host for InstanceState {
+    #[instrument(name = "spin_outbound_networking.is_allowed", skip(self), err(level = Level::INFO))]
+    async fn is_allowed(&mut self, url: String) -> Result<bool, v3::Error> {
+        // The URL must carry its own scheme; there is no sensible default here.
+        if !url.contains("://") {
+            return Err(v3::Error::InvalidUrl(format!(
+                "{url:?} does not contain a scheme"
+            )));
+        }
+        let url = OutboundUrl::parse(&url, "")
+            .map_err(|err| v3::Error::InvalidUrl(format!("{err:#}")))?;
+        self.allowed_hosts
+            .is_url_allowed(&url)
+            .await
+            .map_err(|err| v3::Error::Other(format!("{err:#}")))
+    }
+
+    fn convert_error(&mut self, error: v3::Error) -> anyhow::Result<v3::Error> {
+        Ok(error)
+    }
+}
+
+impl dns::Host for InstanceState {
+    #[instrument(name = "spin_outbound_networking.dns_lookup", skip(self), err(level = Level::INFO))]
+    async fn lookup(
+        &mut self,
+        name: String,
+        kind: dns::RecordType,
+    ) -> Result<Vec<dns::DnsRecord>, dns::Error> {
+        if !self
+            .allowed_hosts
+            .check_dns_name(&name)
+            .await
+            .map_err(|err| dns::Error::Other(format!("{err:#}")))?
+        {
+            return Err(dns::Error::AccessDenied);
+        }
+        let record_type = match kind {
+            dns::RecordType::A => DnsRecordType::A,
+            dns::RecordType::Aaaa => DnsRecordType::Aaaa,
+            dns::RecordType::Txt => DnsRecordType::Txt,
+            dns::RecordType::Srv => DnsRecordType::Srv,
+            dns::RecordType::Mx => DnsRecordType::Mx,
+        };
+        let records = self
+            .dns_resolver
+            .lookup_records(&name, record_type)
+            .await
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::InvalidInput => dns::Error::InvalidName(err.to_string()),
+                io::ErrorKind::NotFound => dns::Error::NotFound,
+                _ => dns::Error::Other(err.to_string()),
+            })?;
+        Ok(records
+            .into_iter()
+            .map(|record| dns::DnsRecord {
+                data: match record.data {
+                    DnsRecordData::A(ip) => dns::RecordData::A(ip.to_string()),
+                    DnsRecordData::Aaaa(ip) => dns::RecordData::Aaaa(ip.to_string()),
+                    DnsRecordData::Txt(text) => dns::RecordData::Txt(text),
+                    DnsRecordData::Srv {
+                        priority,
+                        weight,
+                        port,
+                        target,
+                    } => dns::RecordData::Srv(dns::Srv {
+                        priority,
+                        weight,
+                        port,
+                        target,
+                    }),
+                    DnsRecordData::Mx {
+                        preference,
+                        exchange,
+                    } => dns::RecordData::Mx(dns::Mx {
+                        preference,
+                        exchange,
+                    }),
+                },
+                ttl: record.ttl,
+            })
+            .collect())
+    }
+
+    fn convert_error(&mut self, error: dns::Error) -> anyhow::Result<dns::Error> {
+        Ok(error)
+    }
+}
@@ -1,14 +1,25 @@
 mod allowed_hosts;
 mod blocked_networks;
+mod connect;
+mod connection_aliases;
+mod dns;
+mod host;
 pub mod runtime_config;
+mod throttle;
 mod tls;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
 
 use futures_util::{
     future::{BoxFuture, Shared},
     FutureExt,
 };
+use spin_factor_audit::{AuditFactor, AuditHandle};
+use spin_factor_policy::{Action, PolicyChecker, PolicyFactor};
 use spin_factor_variables::VariablesFactor;
 use spin_factor_wasi::{SocketAddrUse, WasiFactor};
 use spin_factors::{
@@ -17,7 +28,7 @@ use spin_factors::{
 };
 use url::Url;
 
-use crate::{runtime_config::RuntimeConfig, tls::TlsClientConfigs};
+use crate::{runtime_config::RuntimeConfig, throttle::EgressThrottles, tls::TlsClientConfigs};
 
 pub use crate::allowed_hosts::{
     allowed_outbound_hosts, is_service_chaining_host, parse_service_chaining_target,
@@ -25,8 +36,19 @@ pub use crate::allowed_hosts::{
     OutboundUrl, SERVICE_CHAINING_DOMAIN_SUFFIX,
 };
 pub use crate::blocked_networks::BlockedNetworks;
+pub use crate::connect::connect_tcp;
+pub use crate::connection_aliases::ConnectionAliases;
+pub use crate::dns::{DnsRecord, DnsRecordData, DnsRecordType, DnsResolver};
+pub use crate::throttle::{EgressPermit, EgressThrottle};
 pub use crate::tls::{ComponentTlsClientConfigs, TlsClientConfig};
 
+/// The tracing target used for the structured deny-audit log.
+///
+/// Every outbound request rejected by `allowed_outbound_hosts` is recorded as
+/// an event with this target, so it can be filtered separately, e.g. with
+/// `RUST_LOG=spin_outbound_networking::deny_audit=info`.
+pub const DENY_AUDIT_TARGET: &str = "spin_outbound_networking::deny_audit";
+
 pub type SharedFutureResult<T> = Shared<BoxFuture<'static, Result<Arc<T>, Arc<anyhow::Error>>>>;
 
 #[derive(Default)]
@@ -51,6 +73,12 @@ impl Factor for OutboundNetworkingFactor {
     type AppState = AppState;
     type InstanceBuilder = InstanceBuilder;
 
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::networking::allowed_hosts::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::networking::dns::add_to_linker)?;
+        Ok(())
+    }
+
     fn configure_app<T: RuntimeFactors>(
         &self,
         mut ctx: ConfigureAppContext<T, Self>,
@@ -73,15 +101,25 @@ impl Factor for OutboundNetworkingFactor {
             client_tls_configs,
             blocked_ip_networks: block_networks,
             block_private_networks,
+            component_throttles,
+            dns,
         } = ctx.take_runtime_config().unwrap_or_default();
 
         let blocked_networks = BlockedNetworks::new(block_networks, block_private_networks);
         let tls_client_configs = TlsClientConfigs::new(client_tls_configs)?;
+        let egress_throttles = EgressThrottles::new(
+            component_throttles
+                .iter()
+                .map(|(component_id, limits)| (component_id.as_str(), limits)),
+        );
+        let dns_resolver = DnsResolver::new(&dns, blocked_networks.clone());
 
         Ok(AppState {
             component_allowed_hosts,
             blocked_networks,
             tls_client_configs,
+            egress_throttles,
+            dns_resolver,
         })
     }
 
@@ -116,14 +154,32 @@ impl Factor for OutboundNetworkingFactor {
         .map(|res| res.map(Arc::new).map_err(Arc::new))
         .boxed()
         .shared();
+        let audit = match ctx.instance_builder::<AuditFactor>() {
+            Ok(builder) => builder.handle(),
+            Err(Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let policy = match ctx.instance_builder::<PolicyFactor>() {
+            Ok(builder) => builder.checker(),
+            Err(Error::NoSuchFactor(_)) => PolicyChecker::default(),
+            Err(err) => return Err(err.into()),
+        };
         let allowed_hosts = OutboundAllowedHosts {
+            component_id: ctx.app_component().id().into(),
             allowed_hosts_future: allowed_hosts_future.clone(),
             disallowed_host_handler: self.disallowed_host_handler.clone(),
+            audit,
+            policy,
+            dns_resolver: ctx.app_state().dns_resolver.clone(),
+            resolved_host_names: Default::default(),
         };
         let blocked_networks = ctx.app_state().blocked_networks.clone();
 
+        let may_allow_sockets = may_allow_sockets(&hosts);
         match ctx.instance_builder::<WasiFactor>() {
             Ok(wasi_builder) => {
+                // Sockets to allowed host names need name lookup
+                wasi_builder.allow_ip_name_lookup(may_allow_sockets);
                 // Update Wasi socket allowed ports
                 let allowed_hosts = allowed_hosts.clone();
                 wasi_builder.outbound_socket_addr_check(move |addr, addr_use| {
@@ -138,7 +194,7 @@ impl Factor for OutboundNetworkingFactor {
                             | SocketAddrUse::UdpOutgoingDatagram => "udp",
                         };
                         if !allowed_hosts
-                            .check_url(&addr.to_string(), scheme)
+                            .check_socket_addr(addr, scheme)
                             .await
                             .unwrap_or(
                                 // TODO: should this trap (somehow)?
@@ -168,10 +224,17 @@ impl Factor for OutboundNetworkingFactor {
             .tls_client_configs
             .get_component_tls_configs(ctx.app_component().id());
 
+        let egress_throttle = ctx
+            .app_state()
+            .egress_throttles
+            .get(ctx.app_component().id());
+
         Ok(InstanceBuilder {
             allowed_hosts,
             blocked_networks: ctx.app_state().blocked_networks.clone(),
             component_tls_client_configs: component_tls_configs,
+            egress_throttle,
+            dns_resolver: ctx.app_state().dns_resolver.clone(),
         })
     }
 }
@@ -183,12 +246,18 @@ pub struct AppState {
     blocked_networks: BlockedNetworks,
     /// TLS client configs
     tls_client_configs: TlsClientConfigs,
+    /// Outbound call throttles, shared by all instances of a component
+    egress_throttles: EgressThrottles,
+    /// Resolver for outbound connections, shared by all instances
+    dns_resolver: DnsResolver,
 }
 
 pub struct InstanceBuilder {
     allowed_hosts: OutboundAllowedHosts,
     blocked_networks: BlockedNetworks,
     component_tls_client_configs: ComponentTlsClientConfigs,
+    egress_throttle: EgressThrottle,
+    dns_resolver: DnsResolver,
 }
 
 impl InstanceBuilder {
@@ -203,21 +272,49 @@ impl InstanceBuilder {
     pub fn component_tls_configs(&self) -> ComponentTlsClientConfigs {
         self.component_tls_client_configs.clone()
     }
+
+    /// Returns the throttle that outbound factors apply to the component's
+    /// calls.
+    pub fn egress_throttle(&self) -> EgressThrottle {
+        self.egress_throttle.clone()
+    }
+
+    /// Returns the resolver outbound factors use for host names.
+    pub fn dns_resolver(&self) -> DnsResolver {
+        self.dns_resolver.clone()
+    }
 }
 
 impl FactorInstanceBuilder for InstanceBuilder {
-    type InstanceState = ();
+    type InstanceState = InstanceState;
 
     fn build(self) -> anyhow::Result<Self::InstanceState> {
-        Ok(())
+        Ok(InstanceState {
+            allowed_hosts: self.allowed_hosts,
+            dns_resolver: self.dns_resolver,
+        })
     }
 }
 
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    dns_resolver: DnsResolver,
+}
+
 /// A check for whether a URL is allowed by the outbound networking configuration.
 #[derive(Clone)]
 pub struct OutboundAllowedHosts {
+    component_id: Arc<str>,
     allowed_hosts_future: SharedFutureResult<AllowedHostsConfig>,
     disallowed_host_handler: Option<Arc<dyn DisallowedHostHandler>>,
+    /// Records the hosts the instance contacts, if auditing is enabled.
+    audit: Option<AuditHandle>,
+    /// Consulted about connections to allowed hosts.
+    policy: PolicyChecker,
+    /// Resolves allowed host names for socket address checks.
+    dns_resolver: DnsResolver,
+    /// Allowed host name -> the IPs it resolved to for this instance
+    resolved_host_names: Arc<Mutex<HashMap<String, Arc<[IpAddr]>>>>,
 }
 
 impl OutboundAllowedHosts {
@@ -225,6 +322,7 @@ impl OutboundAllowedHosts {
     ///
     /// Calls the [`DisallowedHostHandler`] if set and URL is disallowed.
     /// If `url` cannot be parsed, `{scheme}://` is prepended to `url` and retried.
+    /// URLs the allowed hosts permit may still be denied by the policy.
     pub async fn check_url(&self, url: &str, scheme: &str) -> anyhow::Result<bool> {
         tracing::debug!("Checking outbound networking request to '{url}'");
         let url = match OutboundUrl::parse(url, scheme) {
@@ -238,12 +336,66 @@ impl OutboundAllowedHosts {
         };
 
         let allowed_hosts = self.resolve().await?;
-        let is_allowed = allowed_hosts.allows(&url);
-        if !is_allowed {
+        if !allowed_hosts.allows(&url) {
             tracing::debug!("Disallowed outbound networking request to '{url}'");
-            self.report_disallowed_host(url.scheme(), &url.authority());
+            self.report_disallowed_host(url.scheme(), &url.authority(), &allowed_hosts);
+            return Ok(false);
         }
-        Ok(is_allowed)
+        Ok(self.allow_host(&url).await)
+    }
+
+    /// Checks a socket address against allowed hosts
+    ///
+    /// The address is allowed if it matches an allowed host directly or if it
+    /// is one of the IPs that an allowed host name resolves to.
+    /// Calls the [`DisallowedHostHandler`] if set and the address is disallowed.
+    pub async fn check_socket_addr(&self, addr: SocketAddr, scheme: &str) -> anyhow::Result<bool> {
+        tracing::debug!("Checking outbound {scheme} socket to '{addr}'");
+        let url = OutboundUrl::parse(addr.to_string(), scheme)?;
+        let allowed_hosts = self.resolve().await?;
+        if allowed_hosts.allows(&url) {
+            return Ok(self.allow_host(&url).await);
+        }
+        for name in allowed_hosts.host_names_for(scheme, addr.port()) {
+            if self.resolve_host_name(name).await.contains(&addr.ip()) {
+                return Ok(self.allow_host(&url).await);
+            }
+        }
+        tracing::debug!("Disallowed outbound {scheme} socket to '{addr}'");
+        self.report_disallowed_host(scheme, &url.authority(), &allowed_hosts);
+        Ok(false)
+    }
+
+    /// Checks address against allowed hosts without reporting disallowed hosts
+    ///
+    /// Unlike [`OutboundAllowedHosts::check_url`], the [`DisallowedHostHandler`]
+    /// is never called; this is intended for "would this be allowed?" queries.
+    pub async fn is_url_allowed(&self, url: &OutboundUrl) -> anyhow::Result<bool> {
+        Ok(self.resolve().await?.allows(url))
+    }
+
+    /// Checks whether a DNS name may be looked up
+    ///
+    /// A name may be looked up if the allowed hosts permit connecting to it
+    /// with any scheme and port; see [`AllowedHostsConfig::allows_host_name`].
+    /// Calls the [`DisallowedHostHandler`] if set and the name is disallowed.
+    pub async fn check_dns_name(&self, name: &str) -> anyhow::Result<bool> {
+        tracing::debug!("Checking DNS lookup of '{name}'");
+        let allowed_hosts = self.resolve().await?;
+        if !allowed_hosts.allows_host_name(name) {
+            tracing::debug!("Disallowed DNS lookup of '{name}'");
+            self.report_disallowed_host("dns", name, &allowed_hosts);
+            return Ok(false);
+        }
+        let action = Action::OutboundConnection {
+            scheme: "dns".into(),
+            authority: name.into(),
+        };
+        if !self.policy.allows(action).await {
+            return Ok(false);
+        }
+        self.record_allowed_host(name);
+        Ok(true)
     }
 
     /// Checks if allowed hosts permit relative requests
@@ -254,16 +406,42 @@ impl OutboundAllowedHosts {
         tracing::debug!("Checking relative outbound networking request with schemes {schemes:?}");
         let allowed_hosts = self.resolve().await?;
         let is_allowed = allowed_hosts.allows_relative_url(schemes);
-        if !is_allowed {
+        if is_allowed {
+            self.record_allowed_host("self");
+        } else {
             tracing::debug!(
                 "Disallowed relative outbound networking request with schemes {schemes:?}"
             );
             let scheme = schemes.first().unwrap_or(&"");
-            self.report_disallowed_host(scheme, "self");
+            self.report_disallowed_host(scheme, "self", &allowed_hosts);
         }
         Ok(is_allowed)
     }
 
+    /// Returns the IPs an allowed host name resolves to.
+    ///
+    /// Each name is looked up at most once per instance, so that checking
+    /// every socket address (or UDP datagram) doesn't repeat the lookup. A
+    /// name which fails to resolve matches no IPs.
+    async fn resolve_host_name(&self, name: &str) -> Arc<[IpAddr]> {
+        let cached = self.resolved_host_names.lock().unwrap().get(name).cloned();
+        if let Some(ips) = cached {
+            return ips;
+        }
+        let ips: Arc<[IpAddr]> = match self.dns_resolver.lookup_host(name, 0).await {
+            Ok(addrs) => addrs.iter().map(SocketAddr::ip).collect(),
+            Err(err) => {
+                tracing::debug!(%err, "Failed to resolve allowed host '{name}'");
+                Arc::new([])
+            }
+        };
+        self.resolved_host_names
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), ips.clone());
+        ips
+    }
+
     async fn resolve(&self) -> anyhow::Result<Arc<AllowedHostsConfig>> {
         self.allowed_hosts_future
             .clone()
@@ -271,7 +449,46 @@ impl OutboundAllowedHosts {
             .map_err(anyhow::Error::msg)
     }
 
-    fn report_disallowed_host(&self, scheme: &str, authority: &str) {
+    /// Consults the policy about connecting to a URL the allowed hosts
+    /// permit, recording the host if the connection is allowed.
+    async fn allow_host(&self, url: &OutboundUrl) -> bool {
+        let authority = url.authority();
+        let action = Action::OutboundConnection {
+            scheme: url.scheme().to_owned(),
+            authority: authority.clone(),
+        };
+        if !self.policy.allows(action).await {
+            return false;
+        }
+        self.record_allowed_host(&authority);
+        true
+    }
+
+    fn record_allowed_host(&self, authority: &str) {
+        if let Some(audit) = &self.audit {
+            audit.record_outbound_host(authority);
+        }
+    }
+
+    fn report_disallowed_host(
+        &self,
+        scheme: &str,
+        authority: &str,
+        allowed_hosts: &AllowedHostsConfig,
+    ) {
+        tracing::info!(
+            target: DENY_AUDIT_TARGET,
+            component_id = %self.component_id,
+            scheme,
+            authority,
+            configured_patterns = %allowed_hosts,
+            "Outbound network destination denied by allowed_outbound_hosts"
+        );
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.outbound_networking_denied = 1,
+            component_id = self.component_id.as_ref(),
+            scheme = scheme
+        );
         if let Some(handler) = &self.disallowed_host_handler {
             handler.handle_disallowed_host(scheme, authority);
         }
@@ -288,6 +505,16 @@ impl<F: Fn(&str, &str) + Send + Sync> DisallowedHostHandler for F {
     }
 }
 
+/// Returns true if any of the raw allowed hosts could permit TCP or UDP sockets.
+///
+/// Templated schemes can't be resolved at this point, so are assumed to.
+fn may_allow_sockets(hosts: &[String]) -> bool {
+    hosts.iter().any(|host| {
+        let scheme = host.split_once("://").map_or("", |(scheme, _)| scheme);
+        scheme == "*" || scheme.contains("tcp") || scheme.contains("udp") || scheme.contains("{{")
+    })
+}
+
 /// Records the address host, port, and database as fields on the current tracing span.
 ///
 /// This should only be called from within a function that has been instrumented with a span.
@@ -1,8 +1,13 @@
 #[cfg(feature = "spin-cli")]
 pub mod spin;
 
+use std::collections::HashMap;
+
 pub use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 
+pub use crate::dns::DnsConfig;
+pub use crate::throttle::ThrottleLimits;
+
 /// Runtime configuration for outbound networking.
 #[derive(Debug, Default)]
 pub struct RuntimeConfig {
@@ -12,6 +17,10 @@ pub struct RuntimeConfig {
     pub block_private_networks: bool,
     /// TLS client configs
     pub client_tls_configs: Vec<ClientTlsRuntimeConfig>,
+    /// Outbound call throttles, by component ID
+    pub component_throttles: HashMap<String, ThrottleLimits>,
+    /// DNS resolution settings
+    pub dns: DnsConfig,
 }
 
 /// TLS configuration for one or more component(s) and host(s).
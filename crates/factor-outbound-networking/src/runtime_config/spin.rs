@@ -5,10 +5,13 @@ use serde::{Deserialize, Deserializer};
 use spin_factors::runtime_config::toml::GetTomlValue;
 use std::{
     borrow::Cow,
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use super::ClientTlsRuntimeConfig;
+use super::{ClientTlsRuntimeConfig, DnsConfig, ThrottleLimits};
 
 /// Spin's default handling of the runtime configuration for outbound networking.
 pub struct SpinRuntimeConfig {
@@ -32,6 +35,9 @@ impl SpinRuntimeConfig {
     /// ````toml
     /// [outbound_networking]
     /// block_networks = ["1.1.1.1/32", "private"]
+    /// dns_servers = ["1.1.1.1", "[2606:4700:4700::1111]:53"]
+    /// dns_cache_ttl_secs = 30
+    /// enforce_resolved_ips = true
     ///
     /// [[client_tls]]
     /// component_ids = ["example-component"]
@@ -40,41 +46,56 @@ impl SpinRuntimeConfig {
     /// ca_roots_file = "path/to/roots.crt"
     /// client_cert_file = "path/to/client.crt"
     /// client_private_key_file = "path/to/client.key"
+    ///
+    /// [[outbound_throttle]]
+    /// component_ids = ["example-component"]
+    /// requests_per_second = 50
+    /// max_concurrent = 10
+    /// bytes_per_second = 1048576
     /// ```
     pub fn config_from_table(
         &self,
         table: &impl GetTomlValue,
     ) -> anyhow::Result<Option<super::RuntimeConfig>> {
-        let maybe_blocked_networks = self
-            .blocked_networks_from_table(table)
+        let maybe_outbound_networking = self
+            .outbound_networking_from_table(table)
             .context("failed to parse [outbound_networking] table")?;
         let maybe_tls_configs = self
             .tls_configs_from_table(table)
             .context("failed to parse [[client_tls]] table")?;
-
-        if maybe_blocked_networks.is_none() && maybe_tls_configs.is_none() {
+        let maybe_throttles = self
+            .throttles_from_table(table)
+            .context("failed to parse [[outbound_throttle]] table")?;
+
+        if maybe_outbound_networking.is_none()
+            && maybe_tls_configs.is_none()
+            && maybe_throttles.is_none()
+        {
             return Ok(None);
         }
 
-        let (blocked_ip_networks, block_private_networks) =
-            maybe_blocked_networks.unwrap_or_default();
+        let (blocked_ip_networks, block_private_networks, dns) =
+            maybe_outbound_networking.unwrap_or_default();
 
         let client_tls_configs = maybe_tls_configs.unwrap_or_default();
+        let component_throttles = maybe_throttles.unwrap_or_default();
 
         let runtime_config = super::RuntimeConfig {
             blocked_ip_networks,
             block_private_networks,
             client_tls_configs,
+            component_throttles,
+            dns,
         };
         Ok(Some(runtime_config))
     }
 
-    /// Attempts to parse (blocked_ip_networks, block_private_networks) from a
-    /// `[outbound_networking]` table.
-    fn blocked_networks_from_table(
+    /// Attempts to parse (blocked_ip_networks, block_private_networks, dns)
+    /// from a `[outbound_networking]` table.
+    fn outbound_networking_from_table(
         &self,
         table: &impl GetTomlValue,
-    ) -> anyhow::Result<Option<(Vec<ip_network::IpNetwork>, bool)>> {
+    ) -> anyhow::Result<Option<(Vec<ip_network::IpNetwork>, bool, DnsConfig)>> {
         let Some(value) = table.get("outbound_networking") else {
             return Ok(None);
         };
@@ -90,7 +111,19 @@ impl SpinRuntimeConfig {
                 }
             }
         }
-        Ok(Some((ip_networks, private_networks)))
+
+        let dns = DnsConfig {
+            servers: outbound_networking
+                .dns_servers
+                .iter()
+                .map(|server| parse_dns_server(server))
+                .collect::<anyhow::Result<_>>()?,
+            cache_ttl: outbound_networking
+                .dns_cache_ttl_secs
+                .map(Duration::from_secs),
+            enforce_resolved_ips: outbound_networking.enforce_resolved_ips,
+        };
+        Ok(Some((ip_networks, private_networks, dns)))
     }
 
     fn tls_configs_from_table<T: GetTomlValue>(
@@ -110,6 +143,60 @@ impl SpinRuntimeConfig {
         Ok(Some(tls_configs))
     }
 
+    fn throttles_from_table<T: GetTomlValue>(
+        &self,
+        table: &T,
+    ) -> anyhow::Result<Option<HashMap<String, ThrottleLimits>>> {
+        let Some(array) = table.get("outbound_throttle") else {
+            return Ok(None);
+        };
+        let toml_throttles: Vec<ThrottleToml> = array.clone().try_into()?;
+
+        let mut throttles = HashMap::new();
+        for ThrottleToml {
+            component_ids,
+            requests_per_second,
+            max_concurrent,
+            bytes_per_second,
+        } in toml_throttles
+        {
+            ensure!(
+                !component_ids.is_empty(),
+                "'component_ids' list may not be empty"
+            );
+            for (name, limit) in [
+                ("requests_per_second", requests_per_second),
+                ("bytes_per_second", bytes_per_second),
+            ] {
+                if let Some(limit) = limit {
+                    ensure!(
+                        limit.is_finite() && limit > 0.0,
+                        "'{name}' must be a positive number"
+                    );
+                }
+            }
+            ensure!(
+                max_concurrent != Some(0),
+                "'max_concurrent' must be at least 1"
+            );
+            let limits = ThrottleLimits {
+                requests_per_second,
+                max_concurrent,
+                bytes_per_second,
+            };
+            for component_id in component_ids {
+                let component_id = component_id.to_string();
+                if throttles
+                    .insert(component_id.clone(), limits.clone())
+                    .is_some()
+                {
+                    bail!("component {component_id:?} has more than one throttle");
+                }
+            }
+        }
+        Ok(Some(throttles))
+    }
+
     fn load_tls_config(
         &self,
         toml_config: ClientTlsToml,
@@ -203,6 +290,15 @@ struct ClientTlsToml {
     client_private_key_file: Option<PathBuf>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThrottleToml {
+    component_ids: Vec<spin_serde::KebabId>,
+    requests_per_second: Option<f64>,
+    max_concurrent: Option<usize>,
+    bytes_per_second: Option<f64>,
+}
+
 fn deserialize_hosts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
     let hosts = Vec::<String>::deserialize(deserializer)?;
     for host in &hosts {
@@ -216,6 +312,22 @@ fn deserialize_hosts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<S
 struct OutboundNetworkingToml {
     #[serde(default)]
     block_networks: Vec<CidrOrPrivate>,
+    #[serde(default)]
+    dns_servers: Vec<String>,
+    dns_cache_ttl_secs: Option<u64>,
+    #[serde(default)]
+    enforce_resolved_ips: bool,
+}
+
+/// Parses a name server address, which defaults to port 53.
+fn parse_dns_server(server: &str) -> anyhow::Result<SocketAddr> {
+    if let Ok(addr) = server.parse() {
+        return Ok(addr);
+    }
+    let ip: IpAddr = server
+        .parse()
+        .with_context(|| format!("invalid DNS server {server:?}; expected an IP address"))?;
+    Ok(SocketAddr::new(ip, 53))
 }
 
 #[derive(Debug)]
@@ -298,6 +410,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dns_config() -> anyhow::Result<()> {
+        let config = SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [outbound_networking]
+                dns_servers = ["1.1.1.1", "[2606:4700:4700::1111]:5353"]
+                dns_cache_ttl_secs = 30
+                enforce_resolved_ips = true
+            })?
+            .context("expected config, got None")?;
+        assert_eq!(
+            config.dns,
+            DnsConfig {
+                servers: vec![
+                    "1.1.1.1:53".parse()?,
+                    "[2606:4700:4700::1111]:5353".parse()?
+                ],
+                cache_ttl: Some(Duration::from_secs(30)),
+                enforce_resolved_ips: true,
+            }
+        );
+
+        SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [outbound_networking]
+                dns_servers = ["dns.example.com"]
+            })
+            .unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_min_tls_config() -> anyhow::Result<()> {
         let config = SpinRuntimeConfig::new("/doesnt-matter");
@@ -358,6 +501,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_throttle_config() -> anyhow::Result<()> {
+        let config = SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [[outbound_throttle]]
+                component_ids = ["component-a", "component-b"]
+                requests_per_second = 5
+                max_concurrent = 2
+            })?
+            .context("expected config, got None")?;
+        assert_eq!(config.component_throttles.len(), 2);
+        assert_eq!(
+            config.component_throttles["component-a"],
+            ThrottleLimits {
+                requests_per_second: Some(5.0),
+                max_concurrent: Some(2),
+                bytes_per_second: None,
+            }
+        );
+
+        SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [[outbound_throttle]]
+                component_ids = ["component-a"]
+                bytes_per_second = 0
+            })
+            .unwrap_err();
+        SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [[outbound_throttle]]
+                component_ids = ["component-a"]
+                max_concurrent = 1
+
+                [[outbound_throttle]]
+                component_ids = ["component-a"]
+                requests_per_second = 1
+            })
+            .unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_invalid_cert() {
         let config = SpinRuntimeConfig::new(TESTDATA_DIR);
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits on a component's outbound calls.
+///
+/// Each limit is optional; a limit that isn't set isn't enforced.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThrottleLimits {
+    /// The number of outbound calls that may be started per second.
+    pub requests_per_second: Option<f64>,
+    /// The number of outbound calls that may be in flight at once.
+    pub max_concurrent: Option<usize>,
+    /// The number of bytes that may be sent per second.
+    pub bytes_per_second: Option<f64>,
+}
+
+impl ThrottleLimits {
+    fn is_unlimited(&self) -> bool {
+        self.requests_per_second.is_none()
+            && self.max_concurrent.is_none()
+            && self.bytes_per_second.is_none()
+    }
+}
+
+/// Builds the [`EgressThrottle`] shared by every instance of each component.
+#[derive(Debug, Default)]
+pub(crate) struct EgressThrottles {
+    by_component: HashMap<String, EgressThrottle>,
+}
+
+impl EgressThrottles {
+    pub(crate) fn new<'a>(
+        configs: impl IntoIterator<Item = (&'a str, &'a ThrottleLimits)>,
+    ) -> Self {
+        let by_component = configs
+            .into_iter()
+            .filter(|(_, limits)| !limits.is_unlimited())
+            .map(|(component_id, limits)| (component_id.to_owned(), EgressThrottle::new(limits)))
+            .collect();
+        Self { by_component }
+    }
+
+    /// Returns the throttle for the given component.
+    pub(crate) fn get(&self, component_id: &str) -> EgressThrottle {
+        self.by_component
+            .get(component_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Throttles a component's outbound calls, across all of its instances.
+///
+/// The default throttle doesn't limit anything.
+#[derive(Clone, Debug, Default)]
+pub struct EgressThrottle {
+    inner: Option<Arc<ThrottleInner>>,
+}
+
+#[derive(Debug)]
+struct ThrottleInner {
+    requests: Option<TokenBucket>,
+    concurrency: Option<Arc<Semaphore>>,
+    bytes: Option<TokenBucket>,
+}
+
+/// Held for the duration of an outbound call; counts towards
+/// [`ThrottleLimits::max_concurrent`] until dropped.
+#[must_use]
+pub struct EgressPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl EgressThrottle {
+    /// Creates a throttle enforcing `limits`.
+    pub fn new(limits: &ThrottleLimits) -> Self {
+        let inner = ThrottleInner {
+            requests: limits.requests_per_second.map(TokenBucket::new),
+            concurrency: limits
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            bytes: limits.bytes_per_second.map(TokenBucket::new),
+        };
+        Self {
+            inner: Some(Arc::new(inner)),
+        }
+    }
+
+    /// Waits until an outbound call sending `bytes` may start.
+    ///
+    /// The call counts as in flight until the returned permit is dropped.
+    pub async fn acquire(&self, bytes: usize) -> EgressPermit {
+        let Some(inner) = &self.inner else {
+            return EgressPermit { _permit: None };
+        };
+        if let Some(requests) = &inner.requests {
+            sleep_if_throttled(requests.reserve(1.0)).await;
+        }
+        let permit = match &inner.concurrency {
+            // The semaphore is never closed
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        self.send_bytes(bytes).await;
+        EgressPermit { _permit: permit }
+    }
+
+    /// Waits until `bytes` more may be sent.
+    pub async fn send_bytes(&self, bytes: usize) {
+        sleep_if_throttled(self.reserve_bytes(bytes)).await;
+    }
+
+    /// Accounts for `bytes` being sent, returning how long the sender should
+    /// wait before sending more.
+    pub fn reserve_bytes(&self, bytes: usize) -> Duration {
+        match self.inner.as_ref().and_then(|inner| inner.bytes.as_ref()) {
+            Some(bucket) if bytes > 0 => bucket.reserve(bytes as f64),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+async fn sleep_if_throttled(delay: Duration) {
+    if !delay.is_zero() {
+        tracing::debug!(?delay, "Outbound call throttled");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// A token bucket refilled at `rate` tokens per second, holding up to one
+/// second's worth.
+///
+/// Reservations may take the bucket into debt, which callers repay by
+/// waiting; this keeps large sends from starving behind small ones.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        let rate = rate.max(f64::MIN_POSITIVE);
+        Self {
+            rate,
+            state: Mutex::new(BucketState {
+                tokens: rate,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes `tokens` from the bucket, returning how long to wait until they
+    /// have been refilled.
+    fn reserve(&self, tokens: f64) -> Duration {
+        self.reserve_at(tokens, Instant::now())
+    }
+
+    fn reserve_at(&self, tokens: f64, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.rate);
+        state.updated = now;
+        state.tokens -= tokens;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_paces() {
+        let bucket = TokenBucket::new(2.0);
+        let start = Instant::now();
+        assert_eq!(bucket.reserve_at(1.0, start), Duration::ZERO);
+        assert_eq!(bucket.reserve_at(1.0, start), Duration::ZERO);
+        assert_eq!(bucket.reserve_at(1.0, start), Duration::from_millis(500));
+        // Half a second later the debt has been repaid
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.reserve_at(1.0, later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn bucket_does_not_accumulate_beyond_one_second() {
+        let bucket = TokenBucket::new(10.0);
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(bucket.reserve_at(10.0, later), Duration::ZERO);
+        assert_eq!(bucket.reserve_at(5.0, later), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn concurrency_is_limited_across_clones() {
+        let throttle = EgressThrottle::new(&ThrottleLimits {
+            max_concurrent: Some(1),
+            ..Default::default()
+        });
+        let permit = throttle.acquire(0).await;
+        let other = throttle.clone();
+        let waiting = tokio::spawn(async move { other.acquire(0).await });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        drop(permit);
+        let _ = waiting.await.unwrap();
+    }
+
+    #[test]
+    fn unthrottled_components_share_nothing() {
+        let limits = ThrottleLimits::default();
+        let throttles = EgressThrottles::new([("idle", &limits)]);
+        assert!(throttles.get("idle").inner.is_none());
+        assert!(throttles.get("other").inner.is_none());
+        assert_eq!(
+            throttles.get("other").reserve_bytes(1 << 30),
+            Duration::ZERO
+        );
+    }
+}
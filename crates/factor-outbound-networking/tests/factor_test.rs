@@ -66,6 +66,44 @@ async fn configures_wasi_socket_addr_check() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn socket_addr_check_resolves_allowed_host_names() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        wasi: WasiFactor::new(DummyFilesMounter),
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["tcp://localhost:8125"]
+    });
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+
+    let network_resource = wasi.instance_network()?;
+    let network = wasi.table().get(&network_resource)?;
+
+    network
+        .check_socket_addr("127.0.0.1:8125".parse().unwrap(), SocketAddrUse::TcpConnect)
+        .await?;
+    for (not_allowed, addr_use) in [
+        ("127.0.0.1:8126", SocketAddrUse::TcpConnect),
+        ("127.0.0.1:8125", SocketAddrUse::UdpConnect),
+        ("127.0.0.2:8125", SocketAddrUse::TcpConnect),
+    ] {
+        assert_eq!(
+            network
+                .check_socket_addr(not_allowed.parse().unwrap(), addr_use)
+                .await
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn wasi_factor_is_optional() -> anyhow::Result<()> {
     #[derive(RuntimeFactors)]
@@ -81,3 +119,49 @@ async fn wasi_factor_is_optional() -> anyhow::Result<()> {
     .await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn guest_can_query_allowed_hosts() -> anyhow::Result<()> {
+    use spin_world::spin::networking::allowed_hosts::{Error, Host};
+
+    let factors = TestFactors {
+        wasi: WasiFactor::new(DummyFilesMounter),
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["{http,https}://*.example.com", "https://10.0.0.0/8:{443,8443}"]
+    });
+    let mut state = env.build_instance_state().await?;
+
+    for allowed in [
+        "http://hooks.example.com",
+        "https://hooks.example.com/path",
+        "https://10.1.2.3:8443",
+    ] {
+        assert!(
+            state.networking.is_allowed(allowed.into()).await?,
+            "{allowed}"
+        );
+    }
+    for not_allowed in [
+        "https://example.org",
+        "https://hooks.example.com:8080",
+        "https://10.1.2.3:9000",
+    ] {
+        assert!(
+            !state.networking.is_allowed(not_allowed.into()).await?,
+            "{not_allowed}"
+        );
+    }
+    assert!(matches!(
+        state
+            .networking
+            .is_allowed("hooks.example.com".into())
+            .await,
+        Err(Error::InvalidUrl(_))
+    ));
+    Ok(())
+}
@@ -1,6 +1,10 @@
+use std::net::IpAddr;
+
 use anyhow::Result;
 use spin_core::wasmtime::component::Resource;
-use spin_world::spin::postgres::postgres::{self as v3};
+use spin_world::spin::postgres3_0_0::postgres::{self as v3};
+use spin_world::spin::postgres4_0_0::postgres::{self as v4};
+use spin_world::spin::sql::types as sql_types;
 use spin_world::v1::postgres as v1;
 use spin_world::v1::rdbms_types as v1_types;
 use spin_world::v2::postgres::{self as v2};
@@ -9,37 +13,73 @@ use tracing::field::Empty;
 use tracing::instrument;
 use tracing::Level;
 
-use crate::client::Client;
-use crate::InstanceState;
+use crate::client::{extract_verify_ssl_mode, Client, ClientFactory, RowStream};
+use crate::{InstanceState, PreparedStatement};
 
-impl<C: Client> InstanceState<C> {
+impl<CF: ClientFactory> InstanceState<CF> {
     async fn open_connection<Conn: 'static>(
         &mut self,
         address: &str,
-    ) -> Result<Resource<Conn>, v3::Error> {
+    ) -> Result<Resource<Conn>, v4::Error> {
+        let address = self.connection_aliases.resolve(address);
+        let hostaddrs = self.resolve_hostaddrs(address).await?;
         self.connections
             .push(
-                C::build_client(address)
+                self.client_factory
+                    .get_client(address, &hostaddrs)
                     .await
-                    .map_err(|e| v3::Error::ConnectionFailed(format!("{e:?}")))?,
+                    .map_err(|e| v4::Error::ConnectionFailed(format!("{e:?}")))?,
             )
-            .map_err(|_| v3::Error::ConnectionFailed("too many connections".into()))
+            .map_err(|_| v4::Error::ConnectionFailed("too many connections".into()))
             .map(Resource::new_own)
     }
 
     async fn get_client<Conn: 'static>(
         &mut self,
         connection: Resource<Conn>,
-    ) -> Result<&C, v3::Error> {
+    ) -> Result<&CF::Client, v4::Error> {
         self.connections
             .get(connection.rep())
-            .ok_or_else(|| v3::Error::ConnectionFailed("no connection found".into()))
+            .ok_or_else(|| v4::Error::ConnectionFailed("no connection found".into()))
+    }
+
+    /// Returns a connection to its pool, rolling back any transaction the
+    /// guest left open on it, which recycling doesn't end.
+    async fn close_connection<Conn: 'static>(&mut self, connection: Resource<Conn>) {
+        let Some(client) = self.connections.remove(connection.rep()) else {
+            return;
+        };
+        if let Err(e) = client.batch_execute("ROLLBACK").await {
+            tracing::warn!("failed to roll back dropped Postgres connection: {e:?}");
+        }
+    }
+
+    async fn get_statement(
+        &self,
+        statement: &Resource<v4::PreparedStatement>,
+    ) -> Result<(&CF::Client, &<CF::Client as Client>::PreparedStatement), v4::Error> {
+        let prepared = self
+            .statements
+            .get(statement.rep())
+            .ok_or_else(|| v4::Error::Other("no prepared statement found".into()))?;
+        let client = self
+            .connections
+            .get(prepared.connection)
+            .ok_or_else(|| v4::Error::ConnectionFailed("no connection found".into()))?;
+        Ok((client, &prepared.statement))
     }
 
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        let address = self.connection_aliases.resolve(address);
+        let (address, _) = extract_verify_ssl_mode(address);
         let Ok(config) = address.parse::<tokio_postgres::Config>() else {
             return Ok(false);
         };
+        // Connections are made to the addresses the hosts resolve to, not to
+        // addresses given by the guest
+        if !config.get_hostaddrs().is_empty() {
+            return Ok(false);
+        }
         for (i, host) in config.get_hosts().iter().enumerate() {
             match host {
                 tokio_postgres::config::Host::Tcp(address) => {
@@ -63,6 +103,41 @@ impl<C: Client> InstanceState<C> {
         }
         Ok(true)
     }
+
+    /// Resolves each of the hosts in `address` to the IP address to connect
+    /// to, so that the connection is made to an address the resolver vetted
+    /// rather than to whatever the host resolves to when connecting.
+    async fn resolve_hostaddrs(&self, address: &str) -> Result<Vec<IpAddr>, v4::Error> {
+        let (address, _) = extract_verify_ssl_mode(address);
+        let config = address
+            .parse::<tokio_postgres::Config>()
+            .map_err(|e| v4::Error::ConnectionFailed(format!("{e:?}")))?;
+        let mut hostaddrs = vec![];
+        for host in config.get_hosts() {
+            let host = match host {
+                tokio_postgres::config::Host::Tcp(host) => host,
+                #[cfg(unix)]
+                tokio_postgres::config::Host::Unix(_) => {
+                    return Err(v4::Error::ConnectionFailed(
+                        "only TCP connections are supported".into(),
+                    ))
+                }
+            };
+            let addrs = self
+                .dns_resolver
+                .lookup_host(host, 0)
+                .await
+                .map_err(|err| {
+                    tracing::info!(%err, "Refusing Postgres connection to '{host}'");
+                    v4::Error::ConnectionFailed(format!("address {host} is not permitted"))
+                })?;
+            let addr = addrs.first().ok_or_else(|| {
+                v4::Error::ConnectionFailed(format!("{host} did not resolve to any address"))
+            })?;
+            hostaddrs.push(addr.ip());
+        }
+        Ok(hostaddrs)
+    }
 }
 
 fn v2_params_to_v3(
@@ -71,8 +146,8 @@ fn v2_params_to_v3(
     params.into_iter().map(|p| p.try_into()).collect()
 }
 
-impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnection
-    for InstanceState<C>
+impl<CF: ClientFactory> spin_world::spin::postgres3_0_0::postgres::HostConnection
+    for InstanceState<CF>
 {
     #[instrument(name = "spin_outbound_pg.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
     async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v3::Error> {
@@ -87,7 +162,7 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
                 "address {address} is not permitted"
             )));
         }
-        self.open_connection(&address).await
+        self.open_connection(&address).await.map_err(Into::into)
     }
 
     #[instrument(name = "spin_outbound_pg.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
@@ -97,10 +172,12 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
         statement: String,
         params: Vec<v3::ParameterValue>,
     ) -> Result<u64, v3::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         self.get_client(connection)
             .await?
             .execute(statement, params)
             .await
+            .map_err(Into::into)
     }
 
     #[instrument(name = "spin_outbound_pg.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
@@ -110,30 +187,293 @@ impl<C: Send + Sync + Client> spin_world::spin::postgres::postgres::HostConnecti
         statement: String,
         params: Vec<v3::ParameterValue>,
     ) -> Result<v3::RowSet, v3::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         self.get_client(connection)
             .await?
             .query(statement, params)
             .await
+            .map_err(Into::into)
     }
 
     async fn drop(&mut self, connection: Resource<v3::Connection>) -> anyhow::Result<()> {
-        self.connections.remove(connection.rep());
+        self.close_connection(connection).await;
+        Ok(())
+    }
+}
+
+impl<CF: ClientFactory> v4::Host for InstanceState<CF> {}
+
+impl<CF: ClientFactory> v4::HostConnection for InstanceState<CF> {
+    #[instrument(name = "spin_outbound_pg.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
+    async fn open(&mut self, address: String) -> Result<Resource<v4::Connection>, v4::Error> {
+        spin_factor_outbound_networking::record_address_fields(&address);
+
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| v4::Error::Other(e.to_string()))?
+        {
+            return Err(v4::Error::ConnectionFailed(format!(
+                "address {address} is not permitted"
+            )));
+        }
+        self.open_connection(&address).await
+    }
+
+    #[instrument(name = "spin_outbound_pg.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn execute(
+        &mut self,
+        connection: Resource<v4::Connection>,
+        statement: String,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<u64, v4::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        self.get_client(connection)
+            .await?
+            .execute(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn query(
+        &mut self,
+        connection: Resource<v4::Connection>,
+        statement: String,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<v3::RowSet, v4::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        self.get_client(connection)
+            .await?
+            .query(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.begin", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn begin(&mut self, connection: Resource<v4::Connection>) -> Result<(), v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        self.get_client(connection)
+            .await?
+            .batch_execute("BEGIN")
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.commit", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn commit(&mut self, connection: Resource<v4::Connection>) -> Result<(), v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        self.get_client(connection)
+            .await?
+            .batch_execute("COMMIT")
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.rollback", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn rollback(&mut self, connection: Resource<v4::Connection>) -> Result<(), v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        self.get_client(connection)
+            .await?
+            .batch_execute("ROLLBACK")
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.copy_in", skip(self, connection, columns, rows), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = format!("COPY {table}")))]
+    async fn copy_in(
+        &mut self,
+        connection: Resource<v4::Connection>,
+        table: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<v3::ParameterValue>>,
+    ) -> Result<u64, v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        self.get_client(connection)
+            .await?
+            .copy_in(&table, &columns, rows)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_pg.prepare", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn prepare(
+        &mut self,
+        connection: Resource<v4::Connection>,
+        statement: String,
+    ) -> Result<Resource<v4::PreparedStatement>, v4::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        let connection = connection.rep();
+        let client = self
+            .connections
+            .get(connection)
+            .ok_or_else(|| v4::Error::ConnectionFailed("no connection found".into()))?;
+        let statement = client.prepare(statement).await?;
+        self.statements
+            .push(PreparedStatement {
+                connection,
+                statement,
+            })
+            .map_err(|_| v4::Error::Other("too many prepared statements".into()))
+            .map(Resource::new_own)
+    }
+
+    #[instrument(name = "spin_outbound_pg.query_stream", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", otel.name = statement))]
+    async fn query_stream(
+        &mut self,
+        connection: Resource<v4::Connection>,
+        statement: String,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<Resource<v4::RowStream>, v4::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
+        let stream = self
+            .get_client(connection)
+            .await?
+            .query_stream(statement, params)
+            .await?;
+        self.row_streams
+            .push(stream)
+            .map_err(|_| v4::Error::Other("too many row streams".into()))
+            .map(Resource::new_own)
+    }
+
+    async fn drop(&mut self, connection: Resource<v4::Connection>) -> anyhow::Result<()> {
+        self.close_connection(connection).await;
+        Ok(())
+    }
+}
+
+impl<CF: ClientFactory> v4::HostPreparedStatement for InstanceState<CF> {
+    #[instrument(name = "spin_outbound_pg.execute_prepared", skip(self, statement, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn execute(
+        &mut self,
+        statement: Resource<v4::PreparedStatement>,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<u64, v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        let (client, statement) = self.get_statement(&statement).await?;
+        client.execute_prepared(statement, params).await
+    }
+
+    #[instrument(name = "spin_outbound_pg.query_prepared", skip(self, statement, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn query(
+        &mut self,
+        statement: Resource<v4::PreparedStatement>,
+        params: Vec<v3::ParameterValue>,
+    ) -> Result<v3::RowSet, v4::Error> {
+        let _permit = self.egress_throttle.acquire(0).await;
+        let (client, statement) = self.get_statement(&statement).await?;
+        client.query_prepared(statement, params).await
+    }
+
+    async fn drop(&mut self, statement: Resource<v4::PreparedStatement>) -> anyhow::Result<()> {
+        self.statements.remove(statement.rep());
         Ok(())
     }
 }
 
-impl<C: Send> v2_types::Host for InstanceState<C> {
+impl<CF: ClientFactory> v4::HostRowStream for InstanceState<CF> {
+    async fn columns(
+        &mut self,
+        stream: Resource<v4::RowStream>,
+    ) -> anyhow::Result<Vec<v3::Column>> {
+        let stream = self
+            .row_streams
+            .get(stream.rep())
+            .ok_or_else(|| anyhow::anyhow!("no row stream found"))?;
+        Ok(stream.columns().to_vec())
+    }
+
+    #[instrument(name = "spin_outbound_pg.row_stream_next", skip(self, stream), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql"))]
+    async fn next(
+        &mut self,
+        stream: Resource<v4::RowStream>,
+        max_rows: u32,
+    ) -> Result<Vec<v3::Row>, v4::Error> {
+        if max_rows == 0 {
+            return Err(v4::Error::BadParameter("max-rows must not be zero".into()));
+        }
+        let stream = self
+            .row_streams
+            .get_mut(stream.rep())
+            .ok_or_else(|| v4::Error::Other("no row stream found".into()))?;
+        stream.next(max_rows as usize).await
+    }
+
+    async fn drop(&mut self, stream: Resource<v4::RowStream>) -> anyhow::Result<()> {
+        self.row_streams.remove(stream.rep());
+        Ok(())
+    }
+}
+
+impl<CF: ClientFactory> v4::HostNotificationStream for InstanceState<CF> {
+    #[instrument(name = "spin_outbound_pg.listen", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
+    async fn listen(
+        &mut self,
+        address: String,
+        channel: String,
+    ) -> Result<Resource<v4::NotificationStream>, v4::Error> {
+        spin_factor_outbound_networking::record_address_fields(&address);
+
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| v4::Error::Other(e.to_string()))?
+        {
+            return Err(v4::Error::ConnectionFailed(format!(
+                "address {address} is not permitted"
+            )));
+        }
+        let address = self.connection_aliases.resolve(&address);
+        let hostaddrs = self.resolve_hostaddrs(address).await?;
+        let listener = self
+            .client_factory
+            .listen(address, &hostaddrs, std::slice::from_ref(&channel))
+            .await
+            .map_err(|e| v4::Error::ConnectionFailed(format!("{e:?}")))?;
+        self.listeners
+            .push(listener)
+            .map_err(|_| v4::Error::ConnectionFailed("too many connections".into()))
+            .map(Resource::new_own)
+    }
+
+    async fn next(
+        &mut self,
+        stream: Resource<v4::NotificationStream>,
+    ) -> Result<Option<v4::Notification>, v4::Error> {
+        let listener = self
+            .listeners
+            .get_mut(stream.rep())
+            .ok_or_else(|| v4::Error::ConnectionFailed("no notification stream found".into()))?;
+        let notification = listener
+            .next()
+            .await
+            .map_err(|e| v4::Error::Other(format!("{e:?}")))?;
+        Ok(notification.map(|n| v4::Notification {
+            channel: n.channel().to_owned(),
+            payload: n.payload().to_owned(),
+            process_id: n.process_id(),
+        }))
+    }
+
+    async fn drop(&mut self, stream: Resource<v4::NotificationStream>) -> anyhow::Result<()> {
+        self.listeners.remove(stream.rep());
+        Ok(())
+    }
+}
+
+impl<CF: ClientFactory> v2_types::Host for InstanceState<CF> {
     fn convert_error(&mut self, error: v2::Error) -> Result<v2::Error> {
         Ok(error)
     }
 }
 
-impl<C: Send + Sync + Client> v3::Host for InstanceState<C> {
+impl<CF: ClientFactory> v3::Host for InstanceState<CF> {
     fn convert_error(&mut self, error: v3::Error) -> Result<v3::Error> {
         Ok(error)
     }
 }
 
+impl<CF: ClientFactory> sql_types::Host for InstanceState<CF> {
+    fn convert_error(&mut self, error: sql_types::Error) -> Result<sql_types::Error> {
+        Ok(error)
+    }
+}
+
 /// Delegate a function call to the v3::HostConnection implementation
 macro_rules! delegate {
     ($self:ident.$name:ident($address:expr, $($arg:expr),*)) => {{
@@ -144,7 +484,7 @@ macro_rules! delegate {
         }
         let connection = match $self.open_connection(&$address).await {
             Ok(c) => c,
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(v3::Error::from(e).into()),
         };
         <Self as v3::HostConnection>::$name($self, connection, $($arg),*)
             .await
@@ -152,9 +492,9 @@ macro_rules! delegate {
     }};
 }
 
-impl<C: Send + Sync + Client> v2::Host for InstanceState<C> {}
+impl<CF: ClientFactory> v2::Host for InstanceState<CF> {}
 
-impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
+impl<CF: ClientFactory> v2::HostConnection for InstanceState<CF> {
     #[instrument(name = "spin_outbound_pg.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "postgresql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
     async fn open(&mut self, address: String) -> Result<Resource<v2::Connection>, v2::Error> {
         spin_factor_outbound_networking::record_address_fields(&address);
@@ -178,6 +518,7 @@ impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<v2_types::ParameterValue>,
     ) -> Result<u64, v2::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         Ok(self
             .get_client(connection)
             .await?
@@ -192,6 +533,7 @@ impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<v2_types::ParameterValue>,
     ) -> Result<v2_types::RowSet, v2::Error> {
+        let _permit = self.egress_throttle.acquire(statement.len()).await;
         Ok(self
             .get_client(connection)
             .await?
@@ -206,7 +548,7 @@ impl<C: Send + Sync + Client> v2::HostConnection for InstanceState<C> {
     }
 }
 
-impl<C: Send + Sync + Client> v1::Host for InstanceState<C> {
+impl<CF: ClientFactory> v1::Host for InstanceState<CF> {
     async fn execute(
         &mut self,
         address: String,
@@ -1,51 +1,70 @@
 pub mod client;
 mod host;
+pub mod runtime_config;
 
-use client::Client;
-use spin_factor_outbound_networking::{OutboundAllowedHosts, OutboundNetworkingFactor};
+use std::sync::Arc;
+
+use client::{Client, ClientFactory, NotificationListener, PooledTokioClientFactory};
+use spin_factor_outbound_networking::{
+    ConnectionAliases, DnsResolver, EgressThrottle, OutboundAllowedHosts, OutboundNetworkingFactor,
+};
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
 };
-use tokio_postgres::Client as PgClient;
 
-pub struct OutboundPgFactor<C = PgClient> {
-    _phantom: std::marker::PhantomData<C>,
+pub struct OutboundPgFactor<CF = PooledTokioClientFactory> {
+    _phantom: std::marker::PhantomData<CF>,
 }
 
-impl<C: Send + Sync + Client + 'static> Factor for OutboundPgFactor<C> {
-    type RuntimeConfig = ();
-    type AppState = ();
-    type InstanceBuilder = InstanceState<C>;
+impl<CF: ClientFactory> Factor for OutboundPgFactor<CF> {
+    type RuntimeConfig = runtime_config::RuntimeConfig;
+    type AppState = AppState<CF>;
+    type InstanceBuilder = InstanceState<CF>;
 
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::postgres::add_to_linker)?;
         ctx.link_bindings(spin_world::v2::postgres::add_to_linker)?;
-        ctx.link_bindings(spin_world::spin::postgres::postgres::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::postgres3_0_0::postgres::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::postgres4_0_0::postgres::add_to_linker)?;
         Ok(())
     }
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let mut runtime_config: runtime_config::RuntimeConfig =
+            ctx.take_runtime_config().unwrap_or_default();
+        let connection_aliases = Arc::new(std::mem::take(&mut runtime_config.connections));
+        Ok(AppState {
+            client_factory: Arc::new(CF::new(runtime_config)),
+            connection_aliases,
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
         &self,
         mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let egress_throttle = outbound_networking.egress_throttle();
+        let dns_resolver = outbound_networking.dns_resolver();
         Ok(InstanceState {
             allowed_hosts,
+            egress_throttle,
+            dns_resolver,
+            client_factory: ctx.app_state().client_factory.clone(),
+            connection_aliases: ctx.app_state().connection_aliases.clone(),
             connections: Default::default(),
+            statements: Default::default(),
+            listeners: Default::default(),
+            row_streams: Default::default(),
         })
     }
 }
 
-impl<C> Default for OutboundPgFactor<C> {
+impl<CF> Default for OutboundPgFactor<CF> {
     fn default() -> Self {
         Self {
             _phantom: Default::default(),
@@ -53,15 +72,42 @@ impl<C> Default for OutboundPgFactor<C> {
     }
 }
 
-impl<C> OutboundPgFactor<C> {
+impl<CF> OutboundPgFactor<CF> {
     pub fn new() -> Self {
         Self::default()
     }
 }
 
-pub struct InstanceState<C> {
+pub struct AppState<CF> {
+    client_factory: Arc<CF>,
+    connection_aliases: Arc<ConnectionAliases>,
+}
+
+impl<CF> AppState<CF> {
+    /// The factory of the app's clients, which applies the runtime config's
+    /// pool and TLS settings.
+    pub fn client_factory(&self) -> &Arc<CF> {
+        &self.client_factory
+    }
+}
+
+pub struct InstanceState<CF: ClientFactory> {
     allowed_hosts: OutboundAllowedHosts,
-    connections: spin_resource_table::Table<C>,
+    egress_throttle: EgressThrottle,
+    dns_resolver: DnsResolver,
+    client_factory: Arc<CF>,
+    connection_aliases: Arc<ConnectionAliases>,
+    connections: spin_resource_table::Table<CF::Client>,
+    statements:
+        spin_resource_table::Table<PreparedStatement<<CF::Client as Client>::PreparedStatement>>,
+    listeners: spin_resource_table::Table<NotificationListener>,
+    row_streams: spin_resource_table::Table<<CF::Client as Client>::RowStream>,
 }
 
-impl<C: Send + 'static> SelfInstanceBuilder for InstanceState<C> {}
+impl<CF: ClientFactory> SelfInstanceBuilder for InstanceState<CF> {}
+
+/// A prepared statement along with the connection it was prepared on.
+struct PreparedStatement<S> {
+    connection: u32,
+    statement: S,
+}
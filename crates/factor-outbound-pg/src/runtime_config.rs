@@ -0,0 +1,95 @@
+pub mod spin;
+
+use std::time::Duration;
+
+use spin_factor_outbound_networking::ConnectionAliases;
+
+/// Runtime configuration for outbound Postgres.
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    /// TLS configs, matched against the host of a connection address
+    pub tls_configs: Vec<TlsRuntimeConfig>,
+    /// Configuration for the connection pool kept for each connection address
+    pub pool: PoolConfig,
+    /// Named connections which guests can open in place of an address
+    pub connections: ConnectionAliases,
+}
+
+impl RuntimeConfig {
+    /// Returns the TLS configuration that applies to the given host, if any.
+    pub fn tls_config_for_host(&self, host: &str) -> Option<&TlsRuntimeConfig> {
+        self.tls_configs
+            .iter()
+            .find(|config| config.hosts.iter().any(|h| h == host))
+    }
+}
+
+/// Connection pool configuration.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// The number of connections opened as soon as a pool is created.
+    pub min_size: usize,
+    /// The maximum number of open connections per pool.
+    pub max_size: usize,
+    /// How long to wait for a free connection before failing.
+    pub acquire_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 16,
+            acquire_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// TLS configuration for connections to one or more Postgres host(s).
+#[derive(Debug, Default)]
+pub struct TlsRuntimeConfig {
+    /// The host(s) this configuration applies to.
+    pub hosts: Vec<String>,
+    /// The SSL mode to use. If set, overrides any `sslmode` in the connection string.
+    pub ssl_mode: Option<SslMode>,
+    /// PEM-encoded CA certs that should be considered valid roots, in
+    /// addition to the system roots.
+    pub root_certificates: Vec<Vec<u8>>,
+    /// A certificate and private key to be used as the client certificate.
+    pub client_identity: Option<ClientIdentity>,
+}
+
+/// A PEM-encoded client certificate chain and PKCS#8 private key.
+#[derive(Debug)]
+pub struct ClientIdentity {
+    pub cert_chain_pem: Vec<u8>,
+    pub private_key_pem: Vec<u8>,
+}
+
+/// A libpq-style `sslmode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SslMode {
+    /// Never use TLS.
+    Disable,
+    /// Use TLS if the server supports it.
+    Prefer,
+    /// Always use TLS, verifying the server certificate.
+    Require,
+    /// Always use TLS, verifying the server certificate chain but not its host name.
+    VerifyCa,
+    /// Always use TLS, verifying the server certificate chain and host name.
+    VerifyFull,
+}
+
+impl SslMode {
+    pub(crate) fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "disable" => Some(Self::Disable),
+            "prefer" => Some(Self::Prefer),
+            "require" => Some(Self::Require),
+            "verify-ca" => Some(Self::VerifyCa),
+            "verify-full" => Some(Self::VerifyFull),
+            _ => None,
+        }
+    }
+}
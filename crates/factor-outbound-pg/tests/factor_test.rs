@@ -1,28 +1,34 @@
+use std::net::IpAddr;
+
 use anyhow::{bail, Result};
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
-use spin_factor_outbound_pg::client::Client;
+use spin_factor_outbound_pg::client::{Client, ClientFactory, NotificationListener, RowStream};
+use spin_factor_outbound_pg::runtime_config::RuntimeConfig;
 use spin_factor_outbound_pg::OutboundPgFactor;
 use spin_factor_variables::VariablesFactor;
-use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors::{anyhow, wasmtime::component::Resource, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::async_trait;
-use spin_world::spin::postgres::postgres::Error as PgError;
-use spin_world::spin::postgres::postgres::HostConnection;
-use spin_world::spin::postgres::postgres::{self as v2};
-use spin_world::spin::postgres::postgres::{ParameterValue, RowSet};
+use spin_world::spin::postgres3_0_0::postgres::Error as PgError;
+use spin_world::spin::postgres3_0_0::postgres::HostConnection;
+use spin_world::spin::postgres3_0_0::postgres::{
+    Column, DbDataType, DbValue, ParameterValue, RowSet,
+};
+use spin_world::spin::postgres4_0_0::postgres as v4;
+use spin_world::spin::sql::types::{DatabaseError, ErrorKind};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
     variables: VariablesFactor,
     networking: OutboundNetworkingFactor,
-    pg: OutboundPgFactor<MockClient>,
+    pg: OutboundPgFactor<MockClientFactory>,
 }
 
 fn factors() -> TestFactors {
     TestFactors {
         variables: VariablesFactor::default(),
         networking: OutboundNetworkingFactor::new(),
-        pg: OutboundPgFactor::<MockClient>::new(),
+        pg: OutboundPgFactor::<MockClientFactory>::new(),
     }
 }
 
@@ -54,6 +60,28 @@ async fn disallowed_host_fails() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn disallowed_host_cannot_be_listened_to() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+
+    let res = v4::HostNotificationStream::listen(
+        &mut state.pg,
+        "postgres://postgres.test:5432/test".to_string(),
+        "events".to_string(),
+    )
+    .await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, v4::Error::ConnectionFailed(_)));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn allowed_host_succeeds() -> anyhow::Result<()> {
     let mut state = test_env().build_instance_state().await?;
@@ -103,23 +131,189 @@ async fn exercise_query() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn query_failures_are_classified() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection =
+        v4::HostConnection::open(&mut state.pg, "postgres://localhost:5432/test".to_string())
+            .await?;
+    let res = v4::HostConnection::execute(
+        &mut state.pg,
+        Resource::new_borrow(connection.rep()),
+        "INSERT INTO duplicate VALUES (1)".to_string(),
+        vec![],
+    )
+    .await;
+    let Err(v4::Error::QueryFailed(err)) = res else {
+        bail!("expected QueryFailed");
+    };
+    assert_eq!(err.kind, ErrorKind::ConstraintViolation);
+    assert_eq!(err.code.as_deref(), Some("23505"));
+
+    // Earlier interface versions only see the message
+    let connection = state
+        .pg
+        .open("postgres://localhost:5432/test".to_string())
+        .await?;
+    let res = state
+        .pg
+        .execute(
+            connection,
+            "INSERT INTO duplicate VALUES (1)".to_string(),
+            vec![],
+        )
+        .await;
+    assert!(matches!(res, Err(PgError::QueryFailed(msg)) if msg == "duplicate key"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_transaction_and_prepared_statement() -> anyhow::Result<()> {
+    use spin_world::spin::postgres4_0_0::postgres::HostPreparedStatement;
+
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection =
+        v4::HostConnection::open(&mut state.pg, "postgres://localhost:5432/test".to_string())
+            .await?;
+    let statement = v4::HostConnection::prepare(
+        &mut state.pg,
+        Resource::new_borrow(connection.rep()),
+        "INSERT INTO test VALUES ($1)".to_string(),
+    )
+    .await?;
+
+    v4::HostConnection::begin(&mut state.pg, Resource::new_borrow(connection.rep())).await?;
+    HostPreparedStatement::execute(
+        &mut state.pg,
+        Resource::new_borrow(statement.rep()),
+        vec![ParameterValue::Int32(1)],
+    )
+    .await?;
+    v4::HostConnection::commit(&mut state.pg, Resource::new_borrow(connection.rep())).await?;
+
+    // Prepared statements are unusable once their connection is gone
+    v4::HostConnection::drop(&mut state.pg, connection).await?;
+    let res =
+        HostPreparedStatement::query(&mut state.pg, Resource::new_borrow(statement.rep()), vec![])
+            .await;
+    assert!(matches!(res, Err(v4::Error::ConnectionFailed(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_copy_in() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection =
+        v4::HostConnection::open(&mut state.pg, "postgres://localhost:5432/test".to_string())
+            .await?;
+    let inserted = v4::HostConnection::copy_in(
+        &mut state.pg,
+        connection,
+        "test".to_string(),
+        vec!["id".to_string()],
+        vec![
+            vec![ParameterValue::Int32(1)],
+            vec![ParameterValue::Int32(2)],
+        ],
+    )
+    .await?;
+    assert_eq!(inserted, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_query_stream() -> anyhow::Result<()> {
+    use spin_world::spin::postgres4_0_0::postgres::HostRowStream;
+
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection =
+        v4::HostConnection::open(&mut state.pg, "postgres://localhost:5432/test".to_string())
+            .await?;
+    let stream = v4::HostConnection::query_stream(
+        &mut state.pg,
+        connection,
+        "SELECT id FROM test".to_string(),
+        vec![],
+    )
+    .await?;
+
+    let columns = HostRowStream::columns(&mut state.pg, Resource::new_borrow(stream.rep())).await?;
+    assert_eq!(columns.len(), 1);
+    assert_eq!(columns[0].name, "id");
+
+    // The mock produces five rows
+    let mut pages = vec![];
+    loop {
+        let page =
+            HostRowStream::next(&mut state.pg, Resource::new_borrow(stream.rep()), 2).await?;
+        if page.is_empty() {
+            break;
+        }
+        pages.push(page.len());
+    }
+    assert_eq!(pages, [2, 2, 1]);
+
+    let res = HostRowStream::next(&mut state.pg, Resource::new_borrow(stream.rep()), 0).await;
+    assert!(matches!(res, Err(v4::Error::BadParameter(_))));
+
+    Ok(())
+}
+
+pub struct MockClientFactory {}
+
+#[async_trait]
+impl ClientFactory for MockClientFactory {
+    type Client = MockClient;
+
+    fn new(_runtime_config: RuntimeConfig) -> Self {
+        MockClientFactory {}
+    }
+
+    async fn get_client(
+        &self,
+        _address: &str,
+        _hostaddrs: &[IpAddr],
+    ) -> anyhow::Result<Self::Client> {
+        Ok(MockClient {})
+    }
+
+    async fn listen(
+        &self,
+        _address: &str,
+        _hostaddrs: &[IpAddr],
+        _channels: &[String],
+    ) -> anyhow::Result<NotificationListener> {
+        bail!("notifications are not supported by the mock client")
+    }
+}
+
 // TODO: We can expand this mock to track calls and simulate return values
 pub struct MockClient {}
 
 #[async_trait]
 impl Client for MockClient {
-    async fn build_client(_address: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(MockClient {})
-    }
+    type PreparedStatement = String;
+    type RowStream = MockRowStream;
 
     async fn execute(
         &self,
-        _statement: String,
+        statement: String,
         _params: Vec<ParameterValue>,
-    ) -> Result<u64, v2::Error> {
+    ) -> Result<u64, v4::Error> {
+        if statement.contains("duplicate") {
+            return Err(v4::Error::QueryFailed(DatabaseError {
+                kind: ErrorKind::from_sqlstate("23505"),
+                code: Some("23505".to_string()),
+                message: "duplicate key".to_string(),
+            }));
+        }
         Ok(0)
     }
 
@@ -127,10 +321,77 @@ impl Client for MockClient {
         &self,
         _statement: String,
         _params: Vec<ParameterValue>,
-    ) -> Result<RowSet, v2::Error> {
+    ) -> Result<RowSet, v4::Error> {
+        Ok(RowSet {
+            columns: vec![],
+            rows: vec![],
+        })
+    }
+
+    async fn batch_execute(&self, _statements: &str) -> Result<(), v4::Error> {
+        Ok(())
+    }
+
+    async fn prepare(&self, statement: String) -> Result<String, v4::Error> {
+        Ok(statement)
+    }
+
+    async fn execute_prepared(
+        &self,
+        _statement: &String,
+        _params: Vec<ParameterValue>,
+    ) -> Result<u64, v4::Error> {
+        Ok(0)
+    }
+
+    async fn copy_in(
+        &self,
+        _table: &str,
+        _columns: &[String],
+        rows: Vec<Vec<ParameterValue>>,
+    ) -> Result<u64, v4::Error> {
+        Ok(rows.len() as u64)
+    }
+
+    async fn query_prepared(
+        &self,
+        _statement: &String,
+        _params: Vec<ParameterValue>,
+    ) -> Result<RowSet, v4::Error> {
         Ok(RowSet {
             columns: vec![],
             rows: vec![],
         })
     }
+    async fn query_stream(
+        &self,
+        _statement: String,
+        _params: Vec<ParameterValue>,
+    ) -> Result<MockRowStream, v4::Error> {
+        Ok(MockRowStream {
+            columns: vec![Column {
+                name: "id".to_string(),
+                data_type: DbDataType::Int32,
+            }],
+            remaining: 5,
+        })
+    }
+}
+
+pub struct MockRowStream {
+    columns: Vec<Column>,
+    remaining: usize,
+}
+
+#[async_trait]
+impl RowStream for MockRowStream {
+    fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    async fn next(&mut self, max_rows: usize) -> Result<Vec<Vec<DbValue>>, v4::Error> {
+        let count = max_rows.min(self.remaining);
+        self.remaining -= count;
+        Ok((0..count).map(|i| vec![DbValue::Int32(i as i32)]).collect())
+    }
 }
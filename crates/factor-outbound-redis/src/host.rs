@@ -1,7 +1,15 @@
-use anyhow::Result;
-use redis::{aio::MultiplexedConnection, AsyncCommands, FromRedisValue, Value};
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context, Result};
+use redis::{
+    aio::MultiplexedConnection, AsyncCommands, ConnectionAddr, ConnectionInfo, FromRedisValue,
+    Value,
+};
 use spin_core::wasmtime::component::Resource;
-use spin_factor_outbound_networking::OutboundAllowedHosts;
+use spin_factor_fault_injection::{FaultInjector, Interface};
+use spin_factor_outbound_networking::{
+    connect_tcp, ConnectionAliases, DnsResolver, EgressThrottle, OutboundAllowedHosts,
+};
 use spin_world::v1::{redis as v1, redis_types};
 use spin_world::v2::redis::{
     self as v2, Connection as RedisConnection, Error, RedisParameter, RedisResult,
@@ -9,25 +17,74 @@ use spin_world::v2::redis::{
 use tracing::field::Empty;
 use tracing::{instrument, Level};
 
+use crate::InMemoryRedis;
+
 pub struct InstanceState {
     pub allowed_hosts: OutboundAllowedHosts,
-    pub connections: spin_resource_table::Table<MultiplexedConnection>,
+    pub connection_aliases: Arc<ConnectionAliases>,
+    /// Limits on the component's outbound commands.
+    pub egress_throttle: EgressThrottle,
+    /// Vets the hosts connections are made to.
+    pub dns_resolver: DnsResolver,
+    /// If set, connections are made to this instead of a real server.
+    pub in_memory: Option<Arc<InMemoryRedis>>,
+    /// Whether to embed the current trace context in published messages.
+    pub inject_trace_context: bool,
+    /// Injects faults into connections and commands, if configured.
+    pub fault_injector: Option<FaultInjector>,
+    pub connections: spin_resource_table::Table<Connection>,
+}
+
+/// An open connection.
+pub enum Connection {
+    Server(MultiplexedConnection),
+    InMemory(Arc<InMemoryRedis>),
 }
 
 impl InstanceState {
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        let address = self.connection_aliases.resolve(address);
         self.allowed_hosts.check_url(address, "redis").await
     }
 
+    async fn inject_fault(&self) -> Result<(), Error> {
+        match &self.fault_injector {
+            Some(injector) => injector.inject(Interface::Redis).await.map_err(other_error),
+            None => Ok(()),
+        }
+    }
+
     async fn establish_connection(
         &mut self,
         address: String,
     ) -> Result<Resource<RedisConnection>, Error> {
-        let conn = redis::Client::open(address.as_str())
-            .map_err(|_| Error::InvalidAddress)?
-            .get_multiplexed_async_connection()
-            .await
-            .map_err(other_error)?;
+        self.inject_fault().await?;
+        let address = self.connection_aliases.resolve(&address);
+        let client = redis::Client::open(address).map_err(|_| Error::InvalidAddress)?;
+        let conn = match &self.in_memory {
+            Some(in_memory) => Connection::InMemory(in_memory.clone()),
+            None => {
+                let info = client.get_connection_info();
+                let conn = match &info.addr {
+                    ConnectionAddr::Tcp(host, port) | ConnectionAddr::TcpTls { host, port, .. } => {
+                        // Connect to the addresses the resolver vetted, rather
+                        // than letting the client resolve the host again
+                        let addrs = self
+                            .dns_resolver
+                            .lookup_host(host, *port)
+                            .await
+                            .map_err(|_| Error::InvalidAddress)?;
+                        connect_server(info, &addrs).await.map_err(other_error)?
+                    }
+                    // Unix socket (`redis+unix://`) addresses have no host to vet
+                    _ => client
+                        .get_multiplexed_async_connection()
+                        .await
+                        .map_err(other_error)?,
+                };
+                Connection::Server(conn)
+            }
+        };
         self.connections
             .push(conn)
             .map(Resource::new_own)
@@ -37,7 +94,8 @@ impl InstanceState {
     async fn get_conn(
         &mut self,
         connection: Resource<RedisConnection>,
-    ) -> Result<&mut MultiplexedConnection, Error> {
+    ) -> Result<&mut Connection, Error> {
+        self.inject_fault().await?;
         self.connections
             .get_mut(connection.rep())
             .ok_or(Error::Other(
@@ -46,6 +104,30 @@ impl InstanceState {
     }
 }
 
+/// Connects to the Redis server described by `info` at one of `addrs`.
+async fn connect_server(
+    info: &ConnectionInfo,
+    addrs: &[SocketAddr],
+) -> anyhow::Result<MultiplexedConnection> {
+    let stream = connect_tcp(addrs).await?;
+    let (conn, driver) = match &info.addr {
+        ConnectionAddr::TcpTls { host, insecure, .. } => {
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(*insecure)
+                .danger_accept_invalid_hostnames(*insecure)
+                .build()?;
+            let stream = tokio_native_tls::TlsConnector::from(connector)
+                .connect(host, stream)
+                .await
+                .context("TLS handshake failed")?;
+            MultiplexedConnection::new(&info.redis, stream).await?
+        }
+        _ => MultiplexedConnection::new(&info.redis, stream).await?,
+    };
+    tokio::spawn(driver);
+    Ok(conn)
+}
+
 impl v2::Host for crate::InstanceState {
     fn convert_error(&mut self, error: Error) -> Result<Error> {
         Ok(error)
@@ -75,7 +157,20 @@ impl v2::HostConnection for crate::InstanceState {
         channel: String,
         payload: Vec<u8>,
     ) -> Result<(), Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let payload = if self.inject_trace_context {
+            spin_telemetry::inject_trace_context_into_payload(&payload)
+        } else {
+            payload
+        };
+        let _permit = self
+            .egress_throttle
+            .acquire(channel.len() + payload.len())
+            .await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            // Nobody is subscribed to the in-memory server
+            Connection::InMemory(_) => return Ok(()),
+        };
         // The `let () =` syntax is needed to suppress a warning when the result type is inferred.
         // You can read more about the issue here: <https://github.com/redis-rs/redis-rs/issues/1228>
         let () = conn
@@ -91,7 +186,11 @@ impl v2::HostConnection for crate::InstanceState {
         connection: Resource<RedisConnection>,
         key: String,
     ) -> Result<Option<Vec<u8>>, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self.egress_throttle.acquire(key.len()).await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.get(&key),
+        };
         let value = conn.get(&key).await.map_err(other_error)?;
         Ok(value)
     }
@@ -103,7 +202,14 @@ impl v2::HostConnection for crate::InstanceState {
         key: String,
         value: Vec<u8>,
     ) -> Result<(), Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self.egress_throttle.acquire(key.len() + value.len()).await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => {
+                redis.set(&key, value);
+                return Ok(());
+            }
+        };
         // The `let () =` syntax is needed to suppress a warning when the result type is inferred.
         // You can read more about the issue here: <https://github.com/redis-rs/redis-rs/issues/1228>
         let () = conn.set(&key, &value).await.map_err(other_error)?;
@@ -116,7 +222,11 @@ impl v2::HostConnection for crate::InstanceState {
         connection: Resource<RedisConnection>,
         key: String,
     ) -> Result<i64, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self.egress_throttle.acquire(key.len()).await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.incr_by(&key, 1),
+        };
         let value = conn.incr(&key, 1).await.map_err(other_error)?;
         Ok(value)
     }
@@ -127,7 +237,14 @@ impl v2::HostConnection for crate::InstanceState {
         connection: Resource<RedisConnection>,
         keys: Vec<String>,
     ) -> Result<u32, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self
+            .egress_throttle
+            .acquire(keys.iter().map(String::len).sum())
+            .await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return Ok(redis.del(&keys)),
+        };
         let value = conn.del(&keys).await.map_err(other_error)?;
         Ok(value)
     }
@@ -139,7 +256,14 @@ impl v2::HostConnection for crate::InstanceState {
         key: String,
         values: Vec<String>,
     ) -> Result<u32, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self
+            .egress_throttle
+            .acquire(key.len() + values.iter().map(String::len).sum::<usize>())
+            .await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.sadd(&key, &values),
+        };
         let value = conn.sadd(&key, &values).await.map_err(|e| {
             if e.kind() == redis::ErrorKind::TypeError {
                 Error::TypeError
@@ -156,7 +280,11 @@ impl v2::HostConnection for crate::InstanceState {
         connection: Resource<RedisConnection>,
         key: String,
     ) -> Result<Vec<String>, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self.egress_throttle.acquire(key.len()).await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.smembers(&key),
+        };
         let value = conn.smembers(&key).await.map_err(other_error)?;
         Ok(value)
     }
@@ -168,7 +296,14 @@ impl v2::HostConnection for crate::InstanceState {
         key: String,
         values: Vec<String>,
     ) -> Result<u32, Error> {
-        let conn = self.get_conn(connection).await.map_err(other_error)?;
+        let _permit = self
+            .egress_throttle
+            .acquire(key.len() + values.iter().map(String::len).sum::<usize>())
+            .await;
+        let conn = match self.get_conn(connection).await.map_err(other_error)? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.srem(&key, &values),
+        };
         let value = conn.srem(&key, &values).await.map_err(other_error)?;
         Ok(value)
     }
@@ -180,7 +315,14 @@ impl v2::HostConnection for crate::InstanceState {
         command: String,
         arguments: Vec<RedisParameter>,
     ) -> Result<Vec<RedisResult>, Error> {
-        let conn = self.get_conn(connection).await?;
+        let _permit = self
+            .egress_throttle
+            .acquire(command.len() + arguments.iter().map(parameter_len).sum::<usize>())
+            .await;
+        let conn = match self.get_conn(connection).await? {
+            Connection::Server(conn) => conn,
+            Connection::InMemory(redis) => return redis.execute(&command, arguments),
+        };
         let mut cmd = redis::cmd(&command);
         arguments.iter().for_each(|value| match value {
             RedisParameter::Int64(v) => {
@@ -207,6 +349,14 @@ fn other_error(e: impl std::fmt::Display) -> Error {
     Error::Other(e.to_string())
 }
 
+/// The number of bytes a parameter takes up in a command.
+fn parameter_len(parameter: &RedisParameter) -> usize {
+    match parameter {
+        RedisParameter::Int64(_) => std::mem::size_of::<i64>(),
+        RedisParameter::Binary(v) => v.len(),
+    }
+}
+
 /// Delegate a function call to the v2::HostConnection implementation
 macro_rules! delegate {
     ($self:ident.$name:ident($address:expr, $($arg:expr),*)) => {{
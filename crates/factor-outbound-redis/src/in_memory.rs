@@ -0,0 +1,229 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use spin_world::v2::redis::{Error, RedisParameter, RedisResult};
+
+/// An in-memory stand-in for a Redis server, shared by every connection an
+/// app opens.
+///
+/// It supports the commands the `redis` interface exposes directly, which is
+/// enough to test components without a real server. Published messages are
+/// accepted but not delivered anywhere.
+#[derive(Debug, Default)]
+pub struct InMemoryRedis {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+#[derive(Debug)]
+enum Entry {
+    Bytes(Vec<u8>),
+    Set(BTreeSet<String>),
+}
+
+impl InMemoryRedis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.entries.lock().unwrap().get(key) {
+            None => Ok(None),
+            Some(Entry::Bytes(value)) => Ok(Some(value.clone())),
+            Some(Entry::Set(_)) => Err(Error::TypeError),
+        }
+    }
+
+    pub fn set(&self, key: &str, value: Vec<u8>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), Entry::Bytes(value));
+    }
+
+    pub fn incr_by(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let current = match entries.get(key) {
+            None => 0,
+            Some(Entry::Bytes(value)) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or_else(|| Error::Other("value is not an integer".into()))?,
+            Some(Entry::Set(_)) => return Err(Error::TypeError),
+        };
+        let value = current
+            .checked_add(delta)
+            .ok_or_else(|| Error::Other("increment would overflow".into()))?;
+        entries.insert(key.to_owned(), Entry::Bytes(value.to_string().into_bytes()));
+        Ok(value)
+    }
+
+    pub fn del(&self, keys: &[String]) -> u32 {
+        let mut entries = self.entries.lock().unwrap();
+        keys.iter()
+            .filter(|key| entries.remove(key.as_str()).is_some())
+            .count() as u32
+    }
+
+    pub fn sadd(&self, key: &str, values: &[String]) -> Result<u32, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .entry(key.to_owned())
+            .or_insert_with(|| Entry::Set(Default::default()));
+        let Entry::Set(set) = entry else {
+            return Err(Error::TypeError);
+        };
+        Ok(values
+            .iter()
+            .filter(|value| set.insert(value.to_string()))
+            .count() as u32)
+    }
+
+    pub fn srem(&self, key: &str, values: &[String]) -> Result<u32, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(key) else {
+            return Ok(0);
+        };
+        let Entry::Set(set) = entry else {
+            return Err(Error::TypeError);
+        };
+        let removed = values.iter().filter(|value| set.remove(*value)).count() as u32;
+        if set.is_empty() {
+            entries.remove(key);
+        }
+        Ok(removed)
+    }
+
+    pub fn smembers(&self, key: &str) -> Result<Vec<String>, Error> {
+        match self.entries.lock().unwrap().get(key) {
+            None => Ok(vec![]),
+            Some(Entry::Set(set)) => Ok(set.iter().cloned().collect()),
+            Some(Entry::Bytes(_)) => Err(Error::TypeError),
+        }
+    }
+
+    /// Runs an arbitrary command, returning results in the same shape as a
+    /// real server would.
+    pub fn execute(
+        &self,
+        command: &str,
+        arguments: Vec<RedisParameter>,
+    ) -> Result<Vec<RedisResult>, Error> {
+        let mut args = arguments
+            .into_iter()
+            .map(|arg| match arg {
+                RedisParameter::Int64(v) => v.to_string().into_bytes(),
+                RedisParameter::Binary(v) => v,
+            })
+            .collect::<VecDeque<_>>();
+        let mut next_bytes = || {
+            args.pop_front()
+                .ok_or_else(|| Error::Other(format!("wrong number of arguments for {command}")))
+        };
+        let rest = |args: VecDeque<Vec<u8>>| {
+            args.into_iter()
+                .map(to_string)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let results = match command.to_ascii_uppercase().as_str() {
+            "GET" => self
+                .get(&to_string(next_bytes()?)?)?
+                .map(RedisResult::Binary)
+                .into_iter()
+                .collect(),
+            "SET" => {
+                let key = to_string(next_bytes()?)?;
+                let value = next_bytes()?;
+                self.set(&key, value);
+                vec![]
+            }
+            "INCR" => vec![RedisResult::Int64(
+                self.incr_by(&to_string(next_bytes()?)?, 1)?,
+            )],
+            "INCRBY" => {
+                let key = to_string(next_bytes()?)?;
+                let delta = to_string(next_bytes()?)?
+                    .parse()
+                    .map_err(|_| Error::Other("value is not an integer".into()))?;
+                vec![RedisResult::Int64(self.incr_by(&key, delta)?)]
+            }
+            "DEL" => {
+                vec![RedisResult::Int64(self.del(&rest(args)?).into())]
+            }
+            "SADD" | "SREM" => {
+                let key = to_string(next_bytes()?)?;
+                let values = rest(args)?;
+                let count = if command.eq_ignore_ascii_case("SADD") {
+                    self.sadd(&key, &values)?
+                } else {
+                    self.srem(&key, &values)?
+                };
+                vec![RedisResult::Int64(count.into())]
+            }
+            "SMEMBERS" => self
+                .smembers(&to_string(next_bytes()?)?)?
+                .into_iter()
+                .map(|member| RedisResult::Binary(member.into_bytes()))
+                .collect(),
+            // Nobody is subscribed, so no one receives the message
+            "PUBLISH" => vec![RedisResult::Int64(0)],
+            _ => {
+                return Err(Error::Other(format!(
+                    "command {command} is not supported by the in-memory Redis"
+                )))
+            }
+        };
+        Ok(results)
+    }
+}
+
+fn to_string(arg: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(arg).map_err(|_| Error::Other("argument is not valid UTF-8".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(value: &str) -> RedisParameter {
+        RedisParameter::Binary(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn values_and_sets_are_stored() {
+        let redis = InMemoryRedis::new();
+        redis.set("greeting", b"hello".to_vec());
+        assert_eq!(redis.get("greeting").unwrap(), Some(b"hello".to_vec()));
+        assert_eq!(redis.incr_by("count", 1).unwrap(), 1);
+        assert_eq!(redis.incr_by("count", 2).unwrap(), 3);
+        assert!(matches!(redis.incr_by("greeting", 1), Err(Error::Other(_))));
+
+        let members = ["a".to_owned(), "b".to_owned(), "a".to_owned()];
+        assert_eq!(redis.sadd("letters", &members).unwrap(), 2);
+        assert_eq!(redis.smembers("letters").unwrap(), ["a", "b"]);
+        assert!(matches!(
+            redis.sadd("greeting", &members),
+            Err(Error::TypeError)
+        ));
+        assert_eq!(redis.srem("letters", &members).unwrap(), 2);
+        assert!(redis.smembers("letters").unwrap().is_empty());
+
+        assert_eq!(redis.del(&["greeting".into(), "missing".into()]), 1);
+        assert_eq!(redis.get("greeting").unwrap(), None);
+    }
+
+    #[test]
+    fn commands_return_server_shaped_results() {
+        let redis = InMemoryRedis::new();
+        let results = redis
+            .execute("set", vec![binary("key"), binary("41")])
+            .unwrap();
+        assert!(results.is_empty());
+        let results = redis
+            .execute("INCRBY", vec![binary("key"), RedisParameter::Int64(1)])
+            .unwrap();
+        assert!(matches!(results[..], [RedisResult::Int64(42)]));
+        let results = redis.execute("GET", vec![binary("key")]).unwrap();
+        assert!(matches!(&results[..], [RedisResult::Binary(v)] if v == b"42"));
+        assert!(redis.execute("FLUSHALL", vec![]).is_err());
+    }
+}
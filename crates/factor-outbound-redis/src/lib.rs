@@ -1,11 +1,18 @@
 mod host;
+mod in_memory;
+pub mod runtime_config;
+
+use std::sync::Arc;
 
 use host::InstanceState;
-use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_fault_injection::FaultInjectionFactor;
+use spin_factor_outbound_networking::{ConnectionAliases, OutboundNetworkingFactor};
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
 };
 
+pub use in_memory::InMemoryRedis;
+
 /// The [`Factor`] for `fermyon:spin/outbound-redis`.
 #[derive(Default)]
 pub struct OutboundRedisFactor {
@@ -19,8 +26,8 @@ impl OutboundRedisFactor {
 }
 
 impl Factor for OutboundRedisFactor {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = runtime_config::RuntimeConfig;
+    type AppState = AppState;
     type InstanceBuilder = InstanceState;
 
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
@@ -31,23 +38,58 @@ impl Factor for OutboundRedisFactor {
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            connection_aliases: Arc::new(runtime_config.connections),
+            in_memory: runtime_config
+                .in_memory
+                .then(|| Arc::new(InMemoryRedis::new())),
+            inject_trace_context: runtime_config.inject_trace_context,
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
         &self,
         mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let egress_throttle = outbound_networking.egress_throttle();
+        let dns_resolver = outbound_networking.dns_resolver();
+        let fault_injector = match ctx.instance_builder::<FaultInjectionFactor>() {
+            Ok(fault_injection) => Some(fault_injection.injector()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
         Ok(InstanceState {
             allowed_hosts,
+            egress_throttle,
+            dns_resolver,
+            connection_aliases: ctx.app_state().connection_aliases.clone(),
+            in_memory: ctx.app_state().in_memory.clone(),
+            inject_trace_context: ctx.app_state().inject_trace_context,
+            fault_injector,
             connections: spin_resource_table::Table::new(1024),
         })
     }
 }
 
+pub struct AppState {
+    connection_aliases: Arc<ConnectionAliases>,
+    /// The server all of the app's connections use, if it uses an in-memory
+    /// server.
+    in_memory: Option<Arc<InMemoryRedis>>,
+    /// Whether published messages carry the publisher's trace context.
+    inject_trace_context: bool,
+}
+
+impl AppState {
+    /// The in-memory server the app's connections use, if any.
+    pub fn in_memory(&self) -> Option<&Arc<InMemoryRedis>> {
+        self.in_memory.as_ref()
+    }
+}
+
 impl SelfInstanceBuilder for InstanceState {}
@@ -0,0 +1,16 @@
+pub mod spin;
+
+use spin_factor_outbound_networking::ConnectionAliases;
+
+/// Runtime configuration for outbound Redis.
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    /// Named connections which guests can open in place of an address
+    pub connections: ConnectionAliases,
+    /// Whether to connect to an in-memory server instead of the requested
+    /// address, e.g. for testing.
+    pub in_memory: bool,
+    /// Whether to embed the current trace context in published messages, so
+    /// that subscribers can continue the trace.
+    pub inject_trace_context: bool,
+}
@@ -1,8 +1,8 @@
 use anyhow::bail;
-use spin_factor_outbound_networking::OutboundNetworkingFactor;
-use spin_factor_outbound_redis::OutboundRedisFactor;
+use spin_factor_outbound_networking::{ConnectionAliases, OutboundNetworkingFactor};
+use spin_factor_outbound_redis::{runtime_config::RuntimeConfig, OutboundRedisFactor};
 use spin_factor_variables::VariablesFactor;
-use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors::{anyhow, App, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v2::redis::{Error, HostConnection};
 
@@ -41,3 +41,121 @@ async fn no_outbound_hosts_fails() -> anyhow::Result<()> {
     assert!(matches!(err, Error::InvalidAddress));
     Ok(())
 }
+
+#[tokio::test]
+async fn named_connection_is_checked_against_allowed_hosts() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let connections = ConnectionAliases::from_table(
+        &toml! {
+            [redis_connection.default]
+            address = "redis://redis.test:8080"
+        },
+        "redis_connection",
+    )?;
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            allowed_outbound_hosts = ["redis://allowed.test:8080"]
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            redis: Some(RuntimeConfig {
+                connections,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })?;
+    let mut state = env.build_instance_state().await?;
+    let connection = state.redis.open("default".to_string()).await;
+
+    let Err(err) = connection else {
+        bail!("expected Error, got Ok");
+    };
+
+    assert!(matches!(err, Error::InvalidAddress));
+    Ok(())
+}
+
+#[tokio::test]
+async fn in_memory_server_is_shared_by_instances() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            allowed_outbound_hosts = ["redis://redis.test:6379"]
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            redis: Some(RuntimeConfig {
+                in_memory: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let instance_state = |component_id| {
+        let builders = env.factors.prepare(&configured_app, component_id)?;
+        env.factors.build_instance_state(builders)
+    };
+
+    let mut writer = instance_state("test-component")?;
+    let connection = writer.redis.open("redis://redis.test:6379".into()).await?;
+    writer
+        .redis
+        .set(connection, "greeting".into(), b"hello".to_vec())
+        .await?;
+
+    let mut reader = instance_state("test-component")?;
+    let connection = reader.redis.open("redis://redis.test:6379".into()).await?;
+    let value = reader.redis.get(connection, "greeting".into()).await?;
+    assert_eq!(value.as_deref(), Some(&b"hello"[..]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn socket_addresses_are_checked_against_allowed_hosts() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            allowed_outbound_hosts = ["redis+unix:///var/run/redis.sock"]
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            redis: Some(RuntimeConfig {
+                in_memory: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })?;
+    let mut state = env.build_instance_state().await?;
+
+    state
+        .redis
+        .open("redis+unix:///var/run/redis.sock".into())
+        .await?;
+    let connection = state
+        .redis
+        .open("redis+unix:///var/run/other.sock".into())
+        .await;
+
+    let Err(err) = connection else {
+        bail!("expected Error, got Ok");
+    };
+
+    assert!(matches!(err, Error::InvalidAddress));
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, MultiPart};
+use spin_world::spin::smtp::smtp::{self as v3, Error, Message};
+use tracing::{instrument, Level};
+
+use crate::runtime_config::{AllowedRecipientDomains, RuntimeConfig};
+
+pub struct InstanceState {
+    relay: Option<Arc<RuntimeConfig>>,
+}
+
+impl InstanceState {
+    pub fn new(relay: Option<Arc<RuntimeConfig>>) -> Self {
+        Self { relay }
+    }
+}
+
+impl v3::Host for InstanceState {
+    #[instrument(name = "spin_outbound_smtp.send", skip_all, err(level = Level::INFO),
+        fields(otel.kind = "client", recipients = message.to.len() + message.cc.len() + message.bcc.len()))]
+    async fn send(&mut self, message: Message) -> Result<(), Error> {
+        let Some(relay) = &self.relay else {
+            return Err(Error::NoRelayConfigured);
+        };
+        let message = build_message(message, &relay.allowed_recipient_domains)?;
+        relay.sender.send(message).await
+    }
+
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+fn build_message(
+    message: Message,
+    allowed_recipient_domains: &AllowedRecipientDomains,
+) -> Result<lettre::Message, Error> {
+    if message.to.is_empty() && message.cc.is_empty() && message.bcc.is_empty() {
+        return Err(Error::Other("message has no recipients".into()));
+    }
+
+    let mut builder = lettre::Message::builder()
+        .from(parse_mailbox(&message.from)?)
+        .subject(message.subject);
+    if let Some(reply_to) = &message.reply_to {
+        builder = builder.reply_to(parse_mailbox(reply_to)?);
+    }
+    for to in &message.to {
+        builder = builder.to(parse_recipient(to, allowed_recipient_domains)?);
+    }
+    for cc in &message.cc {
+        builder = builder.cc(parse_recipient(cc, allowed_recipient_domains)?);
+    }
+    for bcc in &message.bcc {
+        builder = builder.bcc(parse_recipient(bcc, allowed_recipient_domains)?);
+    }
+
+    let built = match (message.text_body, message.html_body) {
+        (Some(text), Some(html)) => {
+            builder.multipart(MultiPart::alternative_plain_html(text, html))
+        }
+        (None, Some(html)) => builder.header(ContentType::TEXT_HTML).body(html),
+        (text, None) => builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(text.unwrap_or_default()),
+    };
+    built.map_err(|e| Error::Other(e.to_string()))
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, Error> {
+    address
+        .parse()
+        .map_err(|_| Error::InvalidAddress(address.to_string()))
+}
+
+fn parse_recipient(
+    address: &str,
+    allowed_recipient_domains: &AllowedRecipientDomains,
+) -> Result<Mailbox, Error> {
+    let mailbox = parse_mailbox(address)?;
+    if !allowed_recipient_domains.allows(mailbox.email.domain()) {
+        return Err(Error::RecipientNotAllowed(mailbox.email.to_string()));
+    }
+    Ok(mailbox)
+}
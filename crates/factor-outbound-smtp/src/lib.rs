@@ -0,0 +1,120 @@
+mod host;
+pub mod runtime_config;
+
+use std::sync::Arc;
+
+use host::InstanceState;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use runtime_config::RuntimeConfig;
+use spin_core::async_trait;
+use spin_factors::{
+    ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::smtp::smtp::Error;
+
+#[derive(Default)]
+pub struct OutboundSmtpFactor {
+    _priv: (),
+}
+
+impl OutboundSmtpFactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Factor for OutboundSmtpFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = Option<Arc<RuntimeConfig>>;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::smtp::smtp::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(ctx.take_runtime_config().map(Arc::new))
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        Ok(InstanceState::new(ctx.app_state().clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// Sends fully-built email messages.
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, message: lettre::Message) -> Result<(), Error>;
+}
+
+/// How the connection to an SMTP relay is secured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TlsMode {
+    /// Connect in plain text and upgrade with `STARTTLS`, failing if the relay doesn't support it.
+    #[default]
+    StartTls,
+    /// Connect over TLS from the start (usually port 465).
+    Tls,
+    /// Never use TLS. Only suitable for local relays.
+    None,
+}
+
+/// Settings for connecting to an SMTP relay.
+#[derive(Clone, Debug)]
+pub struct RelayConfig {
+    pub host: String,
+    /// The port to connect to. Defaults to the usual port for the TLS mode.
+    pub port: Option<u16>,
+    pub tls: TlsMode,
+    pub credentials: Option<(String, String)>,
+}
+
+// This is a concrete implementation of a mail sender using lettre.
+pub struct SmtpRelay {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpRelay {
+    /// Create a new [`SmtpRelay`] from the given settings.
+    ///
+    /// No connection is made until the first message is sent.
+    pub fn new(config: RelayConfig) -> anyhow::Result<Self> {
+        let mut builder = match config.tls {
+            TlsMode::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            TlsMode::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            TlsMode::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = config.credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpRelay {
+    async fn send(&self, message: lettre::Message) -> Result<(), Error> {
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| Error::SendFailed(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+pub mod spin;
+
+use std::sync::Arc;
+
+use crate::MailSender;
+
+/// Runtime configuration for outbound SMTP.
+pub struct RuntimeConfig {
+    /// The relay messages are sent through
+    pub sender: Arc<dyn MailSender>,
+    /// The recipient domains guests may send to
+    pub allowed_recipient_domains: AllowedRecipientDomains,
+}
+
+/// A list of recipient domain patterns.
+///
+/// A pattern is either an exact domain (`example.com`), a wildcard matching any
+/// subdomain (`*.example.com`), or `*` to allow every domain.
+#[derive(Clone, Debug, Default)]
+pub struct AllowedRecipientDomains(Vec<String>);
+
+impl AllowedRecipientDomains {
+    pub fn new(patterns: impl IntoIterator<Item = String>) -> Self {
+        Self(
+            patterns
+                .into_iter()
+                .map(|p| p.to_ascii_lowercase())
+                .collect(),
+        )
+    }
+
+    /// Returns true if mail may be sent to the given domain.
+    pub fn allows(&self, domain: &str) -> bool {
+        let domain = domain.to_ascii_lowercase();
+        self.0.iter().any(|pattern| {
+            if pattern == "*" {
+                return true;
+            }
+            match pattern.strip_prefix("*.") {
+                Some(parent) => domain
+                    .strip_suffix(parent)
+                    .is_some_and(|prefix| prefix.ends_with('.')),
+                None => *pattern == domain,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_recipient_domains() {
+        let domains = AllowedRecipientDomains::new(["example.com".into(), "*.example.org".into()]);
+        assert!(domains.allows("example.com"));
+        assert!(domains.allows("EXAMPLE.com"));
+        assert!(!domains.allows("mail.example.com"));
+        assert!(domains.allows("mail.example.org"));
+        assert!(!domains.allows("example.org"));
+        assert!(!domains.allows("badexample.org"));
+
+        assert!(AllowedRecipientDomains::new(["*".into()]).allows("anywhere.test"));
+        assert!(!AllowedRecipientDomains::default().allows("example.com"));
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{AllowedRecipientDomains, RuntimeConfig};
+use crate::{RelayConfig, SmtpRelay, TlsMode};
+
+/// Get the runtime configuration for outbound SMTP from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [outbound_smtp]
+/// host = "smtp.example.com"
+/// port = 587
+/// tls = "starttls"
+/// username = "mailer"
+/// password = "secret"
+/// allowed_recipient_domains = ["example.com", "*.example.org"]
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some((relay, allowed_recipient_domains)) = relay_config_from_table(table)? else {
+        return Ok(None);
+    };
+    let sender = SmtpRelay::new(relay).context("failed to configure SMTP relay")?;
+    Ok(Some(RuntimeConfig {
+        sender: Arc::new(sender),
+        allowed_recipient_domains,
+    }))
+}
+
+fn relay_config_from_table(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<(RelayConfig, AllowedRecipientDomains)>> {
+    let Some(value) = table.get("outbound_smtp") else {
+        return Ok(None);
+    };
+    let toml: OutboundSmtpToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [outbound_smtp] table")?;
+    let credentials = match (toml.username, toml.password) {
+        (Some(username), Some(password)) => Some((username, password)),
+        (None, None) => None,
+        _ => anyhow::bail!("[outbound_smtp] 'username' and 'password' must be set together"),
+    };
+    let relay = RelayConfig {
+        host: toml.host,
+        port: toml.port,
+        tls: toml.tls.into(),
+        credentials,
+    };
+    Ok(Some((
+        relay,
+        AllowedRecipientDomains::new(toml.allowed_recipient_domains),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutboundSmtpToml {
+    host: String,
+    port: Option<u16>,
+    #[serde(default)]
+    tls: TlsModeToml,
+    username: Option<String>,
+    password: Option<String>,
+    #[serde(default)]
+    allowed_recipient_domains: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TlsModeToml {
+    #[default]
+    StartTls,
+    Tls,
+    None,
+}
+
+impl From<TlsModeToml> for TlsMode {
+    fn from(value: TlsModeToml) -> Self {
+        match value {
+            TlsModeToml::StartTls => TlsMode::StartTls,
+            TlsModeToml::Tls => TlsMode::Tls,
+            TlsModeToml::None => TlsMode::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relay_config() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_smtp]
+            host = "smtp.example.com"
+            port = 2525
+            tls = "none"
+            username = "mailer"
+            password = "secret"
+            allowed_recipient_domains = ["example.com"]
+        };
+        let (relay, domains) = relay_config_from_table(&table)?.unwrap();
+        assert_eq!(relay.host, "smtp.example.com");
+        assert_eq!(relay.port, Some(2525));
+        assert_eq!(relay.tls, TlsMode::None);
+        assert_eq!(
+            relay.credentials,
+            Some(("mailer".to_string(), "secret".to_string()))
+        );
+        assert!(domains.allows("example.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn defaults_to_starttls() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [outbound_smtp]
+            host = "smtp.example.com"
+        };
+        let (relay, domains) = relay_config_from_table(&table)?.unwrap();
+        assert_eq!(relay.tls, TlsMode::StartTls);
+        assert_eq!(relay.port, None);
+        assert!(relay.credentials.is_none());
+        assert!(!domains.allows("example.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_username_without_password() {
+        let table: toml::Table = toml::toml! {
+            [outbound_smtp]
+            host = "smtp.example.com"
+            username = "mailer"
+        };
+        assert!(relay_config_from_table(&table).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+use spin_core::async_trait;
+use spin_factor_outbound_smtp::runtime_config::{AllowedRecipientDomains, RuntimeConfig};
+use spin_factor_outbound_smtp::{MailSender, OutboundSmtpFactor};
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::TestEnvironment;
+use spin_world::spin::smtp::smtp::{Error, Host, Message};
+
+#[derive(Default)]
+struct MockMailSender {
+    sent: Mutex<Vec<lettre::Message>>,
+}
+
+#[async_trait]
+impl MailSender for MockMailSender {
+    async fn send(&self, message: lettre::Message) -> Result<(), Error> {
+        self.sent.lock().unwrap().push(message);
+        Ok(())
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    smtp: OutboundSmtpFactor,
+}
+
+fn test_env(sender: Arc<MockMailSender>) -> anyhow::Result<TestEnvironment<TestFactors>> {
+    TestEnvironment::new(TestFactors {
+        smtp: OutboundSmtpFactor::new(),
+    })
+    .runtime_config(TestFactorsRuntimeConfig {
+        smtp: Some(RuntimeConfig {
+            sender,
+            allowed_recipient_domains: AllowedRecipientDomains::new(["example.com".into()]),
+        }),
+    })
+}
+
+fn message(to: &str) -> Message {
+    Message {
+        from: "Spin <noreply@example.com>".into(),
+        to: vec![to.into()],
+        cc: vec![],
+        bcc: vec![],
+        reply_to: None,
+        subject: "Hello".into(),
+        text_body: Some("Hello from Spin".into()),
+        html_body: None,
+    }
+}
+
+#[tokio::test]
+async fn allowed_recipient_is_sent() -> anyhow::Result<()> {
+    let sender = Arc::new(MockMailSender::default());
+    let mut state = test_env(sender.clone())?.build_instance_state().await?;
+
+    state.smtp.send(message("alice@example.com")).await?;
+
+    let sent = sender.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    let recipients = sent[0].envelope().to();
+    assert_eq!(recipients.len(), 1);
+    assert_eq!(recipients[0].to_string(), "alice@example.com");
+    Ok(())
+}
+
+#[tokio::test]
+async fn disallowed_recipient_fails() -> anyhow::Result<()> {
+    let sender = Arc::new(MockMailSender::default());
+    let mut state = test_env(sender.clone())?.build_instance_state().await?;
+
+    let Err(err) = state.smtp.send(message("mallory@example.net")).await else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::RecipientNotAllowed(_)));
+    assert!(sender.sent.lock().unwrap().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_relay_configured_fails() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        smtp: OutboundSmtpFactor::new(),
+    });
+    let mut state = env.build_instance_state().await?;
+
+    let Err(err) = state.smtp.send(message("alice@example.com")).await else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::NoRelayConfigured));
+    Ok(())
+}
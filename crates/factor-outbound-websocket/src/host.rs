@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_networking::{
+    BlockedNetworks, ComponentTlsClientConfigs, DnsResolver, OutboundAllowedHosts,
+};
+use spin_world::spin::websocket::websocket::{self as v3, CloseFrame, Connection, Error, Message};
+use tracing::{instrument, Level};
+
+use crate::{ClientCreator, ConnectOptions};
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    blocked_networks: BlockedNetworks,
+    component_tls_configs: ComponentTlsClientConfigs,
+    dns_resolver: DnsResolver,
+    connections: spin_resource_table::Table<Box<dyn WebSocketClient>>,
+    create_client: Arc<dyn ClientCreator>,
+}
+
+impl InstanceState {
+    pub fn new(
+        allowed_hosts: OutboundAllowedHosts,
+        blocked_networks: BlockedNetworks,
+        component_tls_configs: ComponentTlsClientConfigs,
+        dns_resolver: DnsResolver,
+        create_client: Arc<dyn ClientCreator>,
+    ) -> Self {
+        Self {
+            allowed_hosts,
+            blocked_networks,
+            component_tls_configs,
+            dns_resolver,
+            create_client,
+            connections: spin_resource_table::Table::new(1024),
+        }
+    }
+}
+
+/// An open WebSocket connection.
+#[async_trait]
+pub trait WebSocketClient: Send {
+    /// Sends a text, binary or ping frame.
+    async fn send(&mut self, frame: OutgoingFrame) -> Result<(), Error>;
+
+    /// Waits for the next message, returning `None` once the connection has closed.
+    async fn receive(&mut self) -> Result<Option<Message>, Error>;
+
+    /// Starts the closing handshake.
+    async fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), Error>;
+}
+
+/// A frame sent by the guest.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutgoingFrame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+}
+
+impl InstanceState {
+    async fn is_url_allowed(&self, url: &str) -> Result<bool> {
+        self.allowed_hosts.check_url(url, "wss").await
+    }
+
+    async fn establish_connection(
+        &mut self,
+        url: String,
+        headers: Vec<(String, String)>,
+    ) -> Result<Resource<Connection>, Error> {
+        let host = url_host(&url).ok_or(Error::InvalidUrl)?;
+        let options = ConnectOptions {
+            headers,
+            tls_client_config: self.component_tls_configs.get_client_config(host).clone(),
+            blocked_networks: self.blocked_networks.clone(),
+            dns_resolver: self.dns_resolver.clone(),
+        };
+        let client = self.create_client.create(url, options).await?;
+        self.connections
+            .push(client)
+            .map(Resource::new_own)
+            .map_err(|_| Error::TooManyConnections)
+    }
+
+    fn get_conn(
+        &mut self,
+        connection: &Resource<Connection>,
+    ) -> Result<&mut dyn WebSocketClient, Error> {
+        self.connections
+            .get_mut(connection.rep())
+            .ok_or(Error::Other(
+                "could not find connection for resource".into(),
+            ))
+            .map(|c| c.as_mut())
+    }
+
+    async fn send(
+        &mut self,
+        connection: Resource<Connection>,
+        frame: OutgoingFrame,
+    ) -> Result<(), Error> {
+        self.get_conn(&connection)?.send(frame).await
+    }
+}
+
+impl v3::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl v3::HostConnection for InstanceState {
+    #[instrument(name = "spin_outbound_websocket.open_connection", skip(self, url, headers), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(
+        &mut self,
+        url: String,
+        headers: Vec<(String, String)>,
+    ) -> Result<Resource<Connection>, Error> {
+        if !matches!(url_scheme(&url), Some("ws" | "wss")) {
+            return Err(Error::InvalidUrl);
+        }
+        if !self
+            .is_url_allowed(&url)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+        {
+            return Err(Error::ConnectionFailed(
+                "address is not permitted".to_string(),
+            ));
+        }
+        self.establish_connection(url, headers).await
+    }
+
+    #[instrument(name = "spin_outbound_websocket.send_text", skip_all, err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn send_text(
+        &mut self,
+        connection: Resource<Connection>,
+        text: String,
+    ) -> Result<(), Error> {
+        self.send(connection, OutgoingFrame::Text(text)).await
+    }
+
+    #[instrument(name = "spin_outbound_websocket.send_binary", skip_all, err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn send_binary(
+        &mut self,
+        connection: Resource<Connection>,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.send(connection, OutgoingFrame::Binary(data)).await
+    }
+
+    async fn ping(
+        &mut self,
+        connection: Resource<Connection>,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.send(connection, OutgoingFrame::Ping(payload)).await
+    }
+
+    #[instrument(name = "spin_outbound_websocket.receive", skip_all, err(level = Level::INFO), fields(otel.kind = "consumer"))]
+    async fn receive(
+        &mut self,
+        connection: Resource<Connection>,
+    ) -> Result<Option<Message>, Error> {
+        self.get_conn(&connection)?.receive().await
+    }
+
+    async fn close(
+        &mut self,
+        connection: Resource<Connection>,
+        frame: Option<CloseFrame>,
+    ) -> Result<(), Error> {
+        self.get_conn(&connection)?.close(frame).await
+    }
+
+    async fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+/// Returns the scheme of `url`, e.g. `wss`.
+fn url_scheme(url: &str) -> Option<&str> {
+    url.split_once("://").map(|(scheme, _)| scheme)
+}
+
+/// Returns the host of `url`, without any userinfo, port or IPv6 brackets.
+fn url_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = match host_port.strip_prefix('[') {
+        Some(bracketed) => bracketed.split_once(']')?.0,
+        None => host_port.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+pub fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_url_host() {
+        assert_eq!(url_host("wss://example.com/feed"), Some("example.com"));
+        assert_eq!(url_host("ws://user@example.com:8080"), Some("example.com"));
+        assert_eq!(url_host("wss://[::1]:443/?q=1"), Some("::1"));
+        assert_eq!(url_host("wss:///feed"), None);
+        assert_eq!(url_host("example.com"), None);
+    }
+}
@@ -0,0 +1,227 @@
+mod host;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use futures::{SinkExt, StreamExt};
+use host::other_error;
+use host::InstanceState;
+use spin_core::async_trait;
+use spin_factor_outbound_networking::{
+    BlockedNetworks, DnsResolver, OutboundNetworkingFactor, TlsClientConfig,
+};
+use spin_factors::{
+    ConfigureAppContext, Factor, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::websocket::websocket::{CloseFrame, Error, Message};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        protocol::CloseFrame as WsCloseFrame,
+        Message as WsMessage,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+pub use host::{OutgoingFrame, WebSocketClient};
+
+pub struct OutboundWebSocketFactor {
+    create_client: Arc<dyn ClientCreator>,
+}
+
+impl OutboundWebSocketFactor {
+    pub fn new(create_client: Arc<dyn ClientCreator>) -> Self {
+        Self { create_client }
+    }
+}
+
+impl Factor for OutboundWebSocketFactor {
+    type RuntimeConfig = ();
+    type AppState = ();
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::websocket::websocket::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(())
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        Ok(InstanceState::new(
+            outbound_networking.allowed_hosts(),
+            outbound_networking.blocked_networks(),
+            outbound_networking.component_tls_configs(),
+            outbound_networking.dns_resolver(),
+            self.create_client.clone(),
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// How a connection should be established.
+pub struct ConnectOptions {
+    /// Headers sent with the opening handshake.
+    pub headers: Vec<(String, String)>,
+    /// The TLS configuration for `wss://` connections to the URL's host.
+    pub tls_client_config: TlsClientConfig,
+    /// Networks that must not be connected to.
+    pub blocked_networks: BlockedNetworks,
+    /// Resolves the URL's host.
+    pub dns_resolver: DnsResolver,
+}
+
+// This is a concrete implementation of the WebSocket client using tokio-tungstenite.
+pub struct NetworkedWebSocketClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl NetworkedWebSocketClient {
+    /// Create a [`ClientCreator`] that creates a [`NetworkedWebSocketClient`].
+    pub fn creator() -> Arc<dyn ClientCreator> {
+        Arc::new(NetworkedClientCreator)
+    }
+
+    /// Connect to the server at the given URL and complete the opening handshake.
+    pub async fn connect(url: &str, options: ConnectOptions) -> Result<Self, Error> {
+        let mut request = url.into_client_request().map_err(|_| Error::InvalidUrl)?;
+        for (name, value) in options.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(other_error)?;
+            let value = HeaderValue::from_str(&value).map_err(other_error)?;
+            request.headers_mut().append(name, value);
+        }
+
+        let uri = request.uri();
+        let host = uri
+            .host()
+            .ok_or(Error::InvalidUrl)?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned();
+        let default_port = if uri.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        };
+        let port = uri.port_u16().unwrap_or(default_port);
+
+        let mut addrs: Vec<SocketAddr> = options
+            .dns_resolver
+            .lookup_host(&host, port)
+            .await
+            .map_err(connection_failed)?;
+        let blocked_addrs = options.blocked_networks.remove_blocked(&mut addrs);
+        if addrs.is_empty() && !blocked_addrs.is_empty() {
+            return Err(Error::ConnectionFailed(
+                "destination address is blocked".into(),
+            ));
+        }
+        let tcp_stream = TcpStream::connect(addrs.as_slice())
+            .await
+            .map_err(connection_failed)?;
+
+        let connector = Connector::Rustls(options.tls_client_config.inner());
+        let (stream, _response) = tokio_tungstenite::client_async_tls_with_config(
+            request,
+            tcp_stream,
+            None,
+            Some(connector),
+        )
+        .await
+        .map_err(connection_failed)?;
+        Ok(Self { stream })
+    }
+}
+
+#[async_trait]
+impl WebSocketClient for NetworkedWebSocketClient {
+    async fn send(&mut self, frame: OutgoingFrame) -> Result<(), Error> {
+        let message = match frame {
+            OutgoingFrame::Text(text) => WsMessage::text(text),
+            OutgoingFrame::Binary(data) => WsMessage::binary(data),
+            OutgoingFrame::Ping(payload) => WsMessage::Ping(payload.into()),
+        };
+        self.stream.send(message).await.map_err(ws_error)
+    }
+
+    async fn receive(&mut self) -> Result<Option<Message>, Error> {
+        loop {
+            let message = match self.stream.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(
+                    tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed,
+                ))
+                | None => return Ok(None),
+                Some(Err(e)) => return Err(ws_error(e)),
+            };
+            return Ok(Some(match message {
+                WsMessage::Text(text) => Message::Text(text.as_str().to_owned()),
+                WsMessage::Binary(data) => Message::Binary(data.to_vec()),
+                WsMessage::Pong(payload) => Message::Pong(payload.to_vec()),
+                WsMessage::Close(frame) => Message::Close(frame.map(|frame| CloseFrame {
+                    code: frame.code.into(),
+                    reason: frame.reason.as_str().to_owned(),
+                })),
+                // Pings are answered by tungstenite, and raw frames are only
+                // seen when writing.
+                WsMessage::Ping(_) | WsMessage::Frame(_) => continue,
+            }));
+        }
+    }
+
+    async fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), Error> {
+        let frame = frame.map(|frame| WsCloseFrame {
+            code: frame.code.into(),
+            reason: frame.reason.into(),
+        });
+        self.stream.close(frame).await.map_err(ws_error)
+    }
+}
+
+fn connection_failed(e: impl std::fmt::Display) -> Error {
+    Error::ConnectionFailed(e.to_string())
+}
+
+fn ws_error(e: tungstenite::Error) -> Error {
+    match e {
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => Error::Closed,
+        e => other_error(e),
+    }
+}
+
+/// A trait for creating WebSocket clients.
+#[async_trait]
+pub trait ClientCreator: Send + Sync {
+    async fn create(
+        &self,
+        url: String,
+        options: ConnectOptions,
+    ) -> Result<Box<dyn WebSocketClient>, Error>;
+}
+
+struct NetworkedClientCreator;
+
+#[async_trait]
+impl ClientCreator for NetworkedClientCreator {
+    async fn create(
+        &self,
+        url: String,
+        options: ConnectOptions,
+    ) -> Result<Box<dyn WebSocketClient>, Error> {
+        Ok(Box::new(
+            NetworkedWebSocketClient::connect(&url, options).await?,
+        ))
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_outbound_websocket::{
+    ClientCreator, ConnectOptions, OutboundWebSocketFactor, OutgoingFrame, WebSocketClient,
+};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::websocket::websocket::{CloseFrame, Error, HostConnection, Message};
+
+/// Echoes text and binary messages, answers pings with pongs, and
+/// acknowledges a close.
+#[derive(Default)]
+pub struct MockWebSocketClient {
+    pending: Vec<Message>,
+    closed: bool,
+}
+
+#[async_trait]
+impl WebSocketClient for MockWebSocketClient {
+    async fn send(&mut self, frame: OutgoingFrame) -> Result<(), Error> {
+        if self.closed {
+            return Err(Error::Closed);
+        }
+        self.pending.push(match frame {
+            OutgoingFrame::Text(text) => Message::Text(text),
+            OutgoingFrame::Binary(data) => Message::Binary(data),
+            OutgoingFrame::Ping(payload) => Message::Pong(payload),
+        });
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<Option<Message>, Error> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.pending.remove(0)))
+    }
+
+    async fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), Error> {
+        self.closed = true;
+        self.pending.push(Message::Close(frame));
+        Ok(())
+    }
+}
+
+pub struct MockClientCreator;
+
+#[async_trait]
+impl ClientCreator for MockClientCreator {
+    async fn create(
+        &self,
+        _url: String,
+        _options: ConnectOptions,
+    ) -> Result<Box<dyn WebSocketClient>, Error> {
+        Ok(Box::new(MockWebSocketClient::default()))
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    websocket: OutboundWebSocketFactor,
+}
+
+fn factors() -> TestFactors {
+    TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        websocket: OutboundWebSocketFactor::new(Arc::new(MockClientCreator)),
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["wss://stream.example.com"]
+    })
+}
+
+#[tokio::test]
+async fn disallowed_host_fails() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let res = state
+        .websocket
+        .open("wss://other.example.com/feed".to_string(), vec![])
+        .await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::ConnectionFailed(_)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn non_websocket_url_fails() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let res = state
+        .websocket
+        .open("https://stream.example.com/feed".to_string(), vec![])
+        .await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::InvalidUrl));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_send_receive_and_close() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection = state
+        .websocket
+        .open(
+            "wss://stream.example.com/feed".to_string(),
+            vec![("authorization".to_string(), "Bearer token".to_string())],
+        )
+        .await?;
+    let rep = connection.rep();
+    let conn = || spin_core::wasmtime::component::Resource::new_borrow(rep);
+
+    state
+        .websocket
+        .send_text(conn(), "subscribe".to_string())
+        .await?;
+    state.websocket.ping(conn(), b"hb".to_vec()).await?;
+    assert!(matches!(
+        state.websocket.receive(conn()).await?,
+        Some(Message::Text(text)) if text == "subscribe"
+    ));
+    assert!(matches!(
+        state.websocket.receive(conn()).await?,
+        Some(Message::Pong(payload)) if payload == b"hb"
+    ));
+
+    state.websocket.close(conn(), None).await?;
+    assert!(matches!(
+        state.websocket.receive(conn()).await?,
+        Some(Message::Close(None))
+    ));
+    assert!(state.websocket.receive(conn()).await?.is_none());
+    assert!(matches!(
+        state.websocket.send_binary(conn(), vec![1]).await,
+        Err(Error::Closed)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+mod opa;
+mod rules;
+pub mod runtime_config;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, PrepareContext, RuntimeFactors,
+};
+
+pub use opa::OpaPolicyEngine;
+pub use rules::{ActionKind, Effect, PolicyRule, RulesPolicyEngine};
+pub use runtime_config::RuntimeConfig;
+
+/// The tracing target of policy denials.
+pub const DENY_AUDIT_TARGET: &str = "spin_policy::deny_audit";
+
+/// A factor that consults a policy before components open outbound
+/// connections or stores, if one is configured.
+///
+/// The policy is set in the runtime config rather than the app manifest, so
+/// that platform operators can enforce rules across every app they run. The
+/// factors implementing the capabilities get an instance's [`PolicyChecker`]
+/// from this factor's instance builder and call [`PolicyChecker::allows`]
+/// after their own checks pass.
+#[derive(Default)]
+pub struct PolicyFactor {
+    _priv: (),
+}
+
+impl PolicyFactor {
+    /// Create a new PolicyFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for PolicyFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(AppState {
+            app_id: ctx.app().id().into(),
+            config: ctx.take_runtime_config(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let policy = app_state.config.as_ref().map(|config| Policy {
+            engine: config.engine.clone(),
+            fail_open: config.fail_open,
+            app_id: app_state.app_id.clone(),
+            component_id: ctx.app_component().id().into(),
+            decisions: Default::default(),
+        });
+        Ok(InstanceBuilder {
+            checker: PolicyChecker {
+                policy: policy.map(Arc::new),
+            },
+        })
+    }
+}
+
+pub struct AppState {
+    app_id: Arc<str>,
+    /// The policy, if one is configured.
+    config: Option<RuntimeConfig>,
+}
+
+/// Something a component is about to do which the policy is consulted on.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Connecting to an allowed outbound host.
+    OutboundConnection { scheme: String, authority: String },
+    /// Opening the key-value store with a label.
+    KeyValueStore { label: String },
+    /// Opening the SQLite database with a label.
+    SqliteDatabase { label: String },
+}
+
+impl Action {
+    /// The kind of the action.
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Self::OutboundConnection { .. } => ActionKind::OutboundConnection,
+            Self::KeyValueStore { .. } => ActionKind::KeyValueStore,
+            Self::SqliteDatabase { .. } => ActionKind::SqliteDatabase,
+        }
+    }
+
+    /// What the action is on: the authority of an outbound connection, or
+    /// the label of a store.
+    pub fn target(&self) -> &str {
+        match self {
+            Self::OutboundConnection { authority, .. } => authority,
+            Self::KeyValueStore { label } | Self::SqliteDatabase { label } => label,
+        }
+    }
+}
+
+/// The input a policy decides on.
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyInput<'a> {
+    pub app_id: &'a str,
+    pub component_id: &'a str,
+    pub action: &'a Action,
+}
+
+/// Decides whether components may do things.
+#[async_trait]
+pub trait PolicyEngine: Send + Sync {
+    /// Returns whether the policy allows the input's action.
+    async fn authorize(&self, input: &PolicyInput<'_>) -> anyhow::Result<bool>;
+}
+
+/// Consults the policy on behalf of one component instance.
+#[derive(Clone, Default)]
+pub struct PolicyChecker {
+    /// The policy, or `None` if none is configured.
+    policy: Option<Arc<Policy>>,
+}
+
+struct Policy {
+    engine: Arc<dyn PolicyEngine>,
+    fail_open: bool,
+    app_id: Arc<str>,
+    component_id: Arc<str>,
+    /// The decisions already made for the instance, so that the policy is
+    /// consulted once per action.
+    decisions: Mutex<HashMap<Action, bool>>,
+}
+
+impl PolicyChecker {
+    /// Returns whether a policy is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    /// Returns whether the policy allows the instance to do `action`.
+    ///
+    /// Everything is allowed if no policy is configured. If the policy
+    /// can't be consulted, the action is denied unless the policy is
+    /// configured to fail open.
+    pub async fn allows(&self, action: Action) -> bool {
+        let Some(policy) = &self.policy else {
+            return true;
+        };
+        if let Some(&allowed) = policy.decisions.lock().unwrap().get(&action) {
+            return allowed;
+        }
+        let input = PolicyInput {
+            app_id: &policy.app_id,
+            component_id: &policy.component_id,
+            action: &action,
+        };
+        let allowed = match policy.engine.authorize(&input).await {
+            Ok(allowed) => allowed,
+            Err(err) => {
+                tracing::error!(
+                    component_id = %policy.component_id,
+                    ?action,
+                    "Failed to consult policy: {err:#}"
+                );
+                // Errors aren't cached, so that the policy is consulted again
+                return policy.fail_open;
+            }
+        };
+        if !allowed {
+            tracing::info!(
+                target: DENY_AUDIT_TARGET,
+                component_id = %policy.component_id,
+                action = ?action.kind(),
+                target = action.target(),
+                "Denied by policy"
+            );
+        }
+        policy.decisions.lock().unwrap().insert(action, allowed);
+        allowed
+    }
+}
+
+pub struct InstanceBuilder {
+    checker: PolicyChecker,
+}
+
+impl InstanceBuilder {
+    /// Returns the instance's policy checker.
+    pub fn checker(&self) -> PolicyChecker {
+        self.checker.clone()
+    }
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState {
+            checker: self.checker,
+        })
+    }
+}
+
+pub struct InstanceState {
+    checker: PolicyChecker,
+}
+
+impl InstanceState {
+    /// Returns the instance's policy checker.
+    pub fn checker(&self) -> &PolicyChecker {
+        &self.checker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Allows key-value stores, fails for SQLite databases and denies
+    /// everything else, counting how often it is consulted.
+    #[derive(Default)]
+    struct CountingEngine {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PolicyEngine for CountingEngine {
+        async fn authorize(&self, input: &PolicyInput<'_>) -> anyhow::Result<bool> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match input.action {
+                Action::KeyValueStore { .. } => Ok(true),
+                Action::SqliteDatabase { .. } => anyhow::bail!("unavailable"),
+                Action::OutboundConnection { .. } => Ok(false),
+            }
+        }
+    }
+
+    fn checker(engine: Arc<CountingEngine>, fail_open: bool) -> PolicyChecker {
+        PolicyChecker {
+            policy: Some(Arc::new(Policy {
+                engine,
+                fail_open,
+                app_id: "app".into(),
+                component_id: "component".into(),
+                decisions: Default::default(),
+            })),
+        }
+    }
+
+    fn kv(label: &str) -> Action {
+        Action::KeyValueStore {
+            label: label.into(),
+        }
+    }
+
+    fn sqlite() -> Action {
+        Action::SqliteDatabase {
+            label: "default".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn decisions_are_cached() {
+        let engine = Arc::new(CountingEngine::default());
+        let checker = checker(engine.clone(), false);
+        assert!(checker.allows(kv("default")).await);
+        assert!(checker.allows(kv("default")).await);
+        assert_eq!(engine.calls.load(Ordering::SeqCst), 1);
+        let connection = Action::OutboundConnection {
+            scheme: "https".into(),
+            authority: "example.com".into(),
+        };
+        assert!(!checker.allows(connection).await);
+        assert_eq!(engine.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn errors_deny_unless_failing_open() {
+        let engine = Arc::new(CountingEngine::default());
+        assert!(!checker(engine.clone(), false).allows(sqlite()).await);
+        assert!(checker(engine, true).allows(sqlite()).await);
+    }
+
+    #[tokio::test]
+    async fn everything_is_allowed_without_a_policy() {
+        let checker = PolicyChecker::default();
+        assert!(!checker.is_enabled());
+        assert!(checker.allows(sqlite()).await);
+    }
+
+    #[test]
+    fn actions_serialize_with_their_type() {
+        let json = serde_json::to_value(kv("cache")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "key_value_store", "label": "cache" })
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{PolicyEngine, PolicyInput};
+
+/// A policy decided by an external authorizer speaking the Open Policy Agent
+/// data API, so that policies can be written in Rego.
+///
+/// The input is posted to the URL of a rule as `{"input": ...}`. The action
+/// is allowed if the rule's `result` is `true`, or an object whose `allow` is
+/// `true`; an undefined result denies it.
+pub struct OpaPolicyEngine {
+    client: reqwest::Client,
+    url: reqwest::Url,
+}
+
+impl OpaPolicyEngine {
+    /// Creates an engine consulting the rule at `url`, e.g.
+    /// `http://localhost:8181/v1/data/spin/authz`.
+    pub fn new(url: &str, timeout: Duration) -> anyhow::Result<Self> {
+        let url = url
+            .parse()
+            .with_context(|| format!("invalid policy URL {url:?}"))?;
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl PolicyEngine for OpaPolicyEngine {
+    async fn authorize(&self, input: &PolicyInput<'_>) -> anyhow::Result<bool> {
+        let body = serde_json::to_vec(&serde_json::json!({ "input": input }))?;
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("policy request failed")?;
+        let response: Value = serde_json::from_slice(&response.bytes().await?)
+            .context("policy response is not JSON")?;
+        decision(&response)
+    }
+}
+
+/// Reads the decision from a data API response.
+fn decision(response: &Value) -> anyhow::Result<bool> {
+    match response.get("result") {
+        None => Ok(false),
+        Some(Value::Bool(allowed)) => Ok(*allowed),
+        Some(Value::Object(result)) => match result.get("allow") {
+            None => Ok(false),
+            Some(Value::Bool(allowed)) => Ok(*allowed),
+            Some(other) => anyhow::bail!("policy result `allow` is not a boolean: {other}"),
+        },
+        Some(other) => anyhow::bail!("policy result is not a boolean or object: {other}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reads_decisions() {
+        assert!(decision(&json!({ "result": true })).unwrap());
+        assert!(decision(&json!({ "result": { "allow": true, "reason": "ok" } })).unwrap());
+        assert!(!decision(&json!({ "result": { "allow": false } })).unwrap());
+        assert!(!decision(&json!({ "result": {} })).unwrap());
+        // An undefined rule has no result
+        assert!(!decision(&json!({})).unwrap());
+        assert!(decision(&json!({ "result": "yes" })).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{Action, PolicyEngine, PolicyInput};
+
+/// A policy of rules embedded in the runtime config.
+///
+/// The first rule matching an action decides it; actions no rule matches get
+/// the default effect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RulesPolicyEngine {
+    pub rules: Vec<PolicyRule>,
+    pub default: Effect,
+}
+
+/// Whether a rule allows or denies the actions it matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// The kinds of [`Action`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    OutboundConnection,
+    KeyValueStore,
+    SqliteDatabase,
+}
+
+/// A rule allowing or denying some components some actions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyRule {
+    pub effect: Effect,
+    /// The IDs of the components the rule applies to, or `None` for all.
+    pub components: Option<HashSet<String>>,
+    /// The kinds of actions the rule applies to, or `None` for all.
+    pub actions: Option<HashSet<ActionKind>>,
+    /// Patterns of the targets the rule applies to, or `None` for all.
+    ///
+    /// Targets are the authorities of outbound connections and the labels of
+    /// stores. A pattern is either a target, `*` for any target, or
+    /// `*.<domain>` for any host in the domain. Patterns without a port match
+    /// a host on any port.
+    pub targets: Option<Vec<String>>,
+}
+
+impl PolicyRule {
+    fn matches(&self, input: &PolicyInput<'_>) -> bool {
+        self.components
+            .as_ref()
+            .is_none_or(|components| components.contains(input.component_id))
+            && self
+                .actions
+                .as_ref()
+                .is_none_or(|actions| actions.contains(&input.action.kind()))
+            && self.targets.as_ref().is_none_or(|targets| {
+                targets
+                    .iter()
+                    .any(|pattern| target_matches(pattern, input.action))
+            })
+    }
+}
+
+fn target_matches(pattern: &str, action: &Action) -> bool {
+    let target = action.target();
+    if pattern == "*" || pattern == target {
+        return true;
+    }
+    let Action::OutboundConnection { .. } = action else {
+        return false;
+    };
+    let host = match target.rsplit_once(':') {
+        // Bracketed IPv6 addresses contain colons
+        Some((host, port)) if !port.contains(']') => host,
+        _ => target,
+    };
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+#[async_trait]
+impl PolicyEngine for RulesPolicyEngine {
+    async fn authorize(&self, input: &PolicyInput<'_>) -> anyhow::Result<bool> {
+        let effect = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(input))
+            .map_or(self.default, |rule| rule.effect);
+        Ok(effect == Effect::Allow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(authority: &str) -> Action {
+        Action::OutboundConnection {
+            scheme: "https".into(),
+            authority: authority.into(),
+        }
+    }
+
+    async fn allows(engine: &RulesPolicyEngine, component_id: &str, action: &Action) -> bool {
+        let input = PolicyInput {
+            app_id: "app",
+            component_id,
+            action,
+        };
+        engine.authorize(&input).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_corporate_hosts_are_allowed() {
+        let engine = RulesPolicyEngine {
+            rules: vec![
+                PolicyRule {
+                    effect: Effect::Allow,
+                    components: None,
+                    actions: Some([ActionKind::OutboundConnection].into()),
+                    targets: Some(vec!["*.corp.example.com".into(), "10.0.0.1:5432".into()]),
+                },
+                PolicyRule {
+                    effect: Effect::Deny,
+                    components: None,
+                    actions: Some([ActionKind::OutboundConnection].into()),
+                    targets: None,
+                },
+            ],
+            default: Effect::Allow,
+        };
+        for (authority, allowed) in [
+            ("api.corp.example.com:443", true),
+            ("a.b.corp.example.com", true),
+            ("corp.example.com:443", false),
+            ("evilcorp.example.com:443", false),
+            ("10.0.0.1:5432", true),
+            ("10.0.0.1:5433", false),
+        ] {
+            assert_eq!(
+                allows(&engine, "api", &connection(authority)).await,
+                allowed,
+                "{authority}"
+            );
+        }
+        let store = Action::KeyValueStore {
+            label: "default".into(),
+        };
+        assert!(allows(&engine, "api", &store).await);
+    }
+
+    #[tokio::test]
+    async fn rules_apply_to_their_components() {
+        let engine = RulesPolicyEngine {
+            rules: vec![PolicyRule {
+                effect: Effect::Allow,
+                components: Some(["billing".to_owned()].into()),
+                actions: Some([ActionKind::SqliteDatabase].into()),
+                targets: Some(vec!["payments".into()]),
+            }],
+            default: Effect::Deny,
+        };
+        let payments = Action::SqliteDatabase {
+            label: "payments".into(),
+        };
+        assert!(allows(&engine, "billing", &payments).await);
+        assert!(!allows(&engine, "web", &payments).await);
+    }
+
+    #[test]
+    fn host_patterns_ignore_ports_and_brackets() {
+        assert!(target_matches(
+            "example.com",
+            &connection("example.com:8080")
+        ));
+        assert!(target_matches("[::1]", &connection("[::1]")));
+        assert!(!target_matches("*.example.com", &connection("example.com")));
+        let store = Action::KeyValueStore {
+            label: "cache".into(),
+        };
+        assert!(!target_matches("*.cache", &store));
+    }
+}
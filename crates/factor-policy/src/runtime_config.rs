@@ -0,0 +1,26 @@
+pub mod spin;
+
+use std::sync::Arc;
+
+use crate::PolicyEngine;
+
+/// Runtime configuration for the policy.
+#[derive(Clone)]
+pub struct RuntimeConfig {
+    /// Decides whether components may do things.
+    pub engine: Arc<dyn PolicyEngine>,
+    /// Whether actions are allowed when the engine can't decide them, e.g.
+    /// because an external authorizer is unreachable.
+    pub fail_open: bool,
+}
+
+impl RuntimeConfig {
+    /// Creates a runtime config consulting `engine`, denying actions it
+    /// can't decide.
+    pub fn new(engine: impl PolicyEngine + 'static) -> Self {
+        Self {
+            engine: Arc::new(engine),
+            fail_open: false,
+        }
+    }
+}
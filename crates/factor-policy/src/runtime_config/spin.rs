@@ -0,0 +1,152 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use crate::{ActionKind, Effect, OpaPolicyEngine, PolicyRule, RulesPolicyEngine, RuntimeConfig};
+
+/// How long to wait for an external authorizer, unless `timeout_ms` is set.
+const DEFAULT_OPA_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Get the runtime configuration for the policy from a TOML table.
+///
+/// Expects table to be in one of the formats:
+/// ```toml
+/// [policy]
+/// type = "rules"
+/// default = "allow" # optional, the effect of actions no rule matches
+///
+/// [[policy.rules]]
+/// effect = "deny"
+/// components = ["web"] # optional, defaults to all components
+/// actions = ["outbound_connection", "key_value_store", "sqlite_database"] # optional, defaults to all
+/// targets = ["*.example.com", "payments"] # optional, defaults to all
+/// ```
+/// ```toml
+/// [policy]
+/// type = "opa"
+/// url = "http://localhost:8181/v1/data/spin/authz"
+/// timeout_ms = 500 # optional
+/// fail_open = false # optional, whether to allow actions if the authorizer fails
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("policy") else {
+        return Ok(None);
+    };
+    let toml: PolicyToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [policy] table")?;
+    let config = match toml {
+        PolicyToml::Rules { default, rules } => RuntimeConfig::new(RulesPolicyEngine {
+            rules: rules
+                .into_iter()
+                .map(|rule| PolicyRule {
+                    effect: rule.effect,
+                    components: rule
+                        .components
+                        .map(|components| components.into_iter().collect()),
+                    actions: rule.actions.map(|actions| actions.into_iter().collect()),
+                    targets: rule.targets,
+                })
+                .collect(),
+            default,
+        }),
+        PolicyToml::Opa {
+            url,
+            timeout_ms,
+            fail_open,
+        } => {
+            let timeout = timeout_ms.map_or(DEFAULT_OPA_TIMEOUT, Duration::from_millis);
+            RuntimeConfig {
+                fail_open,
+                ..RuntimeConfig::new(OpaPolicyEngine::new(&url, timeout)?)
+            }
+        }
+    };
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum PolicyToml {
+    Rules {
+        #[serde(default)]
+        default: Effect,
+        #[serde(default)]
+        rules: Vec<PolicyRuleToml>,
+    },
+    Opa {
+        url: String,
+        timeout_ms: Option<u64>,
+        #[serde(default)]
+        fail_open: bool,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyRuleToml {
+    effect: Effect,
+    components: Option<Vec<String>>,
+    actions: Option<Vec<ActionKind>>,
+    targets: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [policy]
+            type = "rules"
+
+            [[policy.rules]]
+            effect = "allow"
+            actions = ["outbound_connection"]
+            targets = ["*.corp.example.com"]
+
+            [[policy.rules]]
+            effect = "deny"
+            actions = ["outbound_connection"]
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert!(!config.fail_open);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_opa() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [policy]
+            type = "opa"
+            url = "http://localhost:8181/v1/data/spin/authz"
+            fail_open = true
+        };
+        let config = config_from_table(&table)?.unwrap();
+        assert!(config.fail_open);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let unknown_action: toml::Table = toml::toml! {
+            [policy]
+            type = "rules"
+            rules = [{ effect = "deny", actions = ["mqtt"] }]
+        };
+        assert!(config_from_table(&unknown_action).is_err());
+
+        let rules_cannot_fail: toml::Table = toml::toml! {
+            [policy]
+            type = "rules"
+            fail_open = true
+        };
+        assert!(config_from_table(&rules_cannot_fail).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use spin_factor_policy::{
+    Action, ActionKind, Effect, PolicyFactor, PolicyRule, RulesPolicyEngine, RuntimeConfig,
+};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    policy: PolicyFactor,
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        policy: PolicyFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+fn store(label: &str) -> Action {
+    Action::KeyValueStore {
+        label: label.into(),
+    }
+}
+
+#[tokio::test]
+async fn everything_is_allowed_by_default() -> anyhow::Result<()> {
+    let state = test_env().build_instance_state().await?;
+    let checker = state.policy.checker();
+    assert!(!checker.is_enabled());
+    assert!(checker.allows(store("default")).await);
+    Ok(())
+}
+
+#[tokio::test]
+async fn configured_policy_is_consulted() -> anyhow::Result<()> {
+    let engine = RulesPolicyEngine {
+        rules: vec![PolicyRule {
+            effect: Effect::Deny,
+            components: Some(["test-component".to_owned()].into()),
+            actions: Some([ActionKind::KeyValueStore].into()),
+            targets: Some(vec!["production".into()]),
+        }],
+        default: Effect::Allow,
+    };
+    let state = test_env()
+        .runtime_config(TestFactorsRuntimeConfig {
+            policy: Some(RuntimeConfig::new(engine)),
+        })?
+        .build_instance_state()
+        .await?;
+    let checker = state.policy.checker();
+    assert!(checker.is_enabled());
+    assert!(!checker.allows(store("production")).await);
+    assert!(checker.allows(store("default")).await);
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! The generic cell rate algorithm (GCRA).
+//!
+//! GCRA keeps a single timestamp per key, the "theoretical arrival time"
+//! (TAT) of the next action, which makes it cheap to update atomically with
+//! a compare-and-swap. It allows bursts of up to `limit` actions and then
+//! one action every `window / limit`.
+
+use spin_world::spin::rate_limit::rate_limit::Decision;
+
+/// Prefix for the keys of rate limit state in the backing store.
+const STATE_KEY_PREFIX: &str = "spin-rate-limit:";
+
+/// Returns the backing store key of the state for `key`.
+pub(crate) fn state_key(key: &str) -> String {
+    format!("{STATE_KEY_PREFIX}{key}")
+}
+
+/// Encodes a TAT as big-endian microseconds.
+pub(crate) fn encode_tat(tat: u64) -> Vec<u8> {
+    tat.to_be_bytes().to_vec()
+}
+
+/// Decodes a TAT, treating malformed state as absent.
+pub(crate) fn decode_tat(state: &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(state.try_into().ok()?))
+}
+
+/// Returns whether stored state no longer limits anything at `now`, because
+/// its TAT has passed or it is malformed, and so can be deleted.
+pub(crate) fn is_expired(state: &[u8], now: u64) -> bool {
+    decode_tat(state).is_none_or(|tat| tat <= now)
+}
+
+/// Decides whether an action at `now` is allowed given the stored TAT,
+/// returning the decision and, if it is allowed, the TAT to store.
+///
+/// `limit` and `window_micros` must be non-zero.
+pub(crate) fn check(
+    stored_tat: Option<u64>,
+    now: u64,
+    limit: u32,
+    window_micros: u64,
+) -> (Decision, Option<u64>) {
+    let interval = (window_micros / u64::from(limit)).max(1);
+    let tat = stored_tat.unwrap_or(now).max(now);
+    let new_tat = tat.saturating_add(interval);
+
+    if new_tat - now > window_micros {
+        let decision = Decision {
+            allowed: false,
+            remaining: 0,
+            retry_after_ms: micros_to_ms(new_tat - window_micros - now),
+            reset_after_ms: micros_to_ms(tat - now),
+        };
+        return (decision, None);
+    }
+    let remaining = (window_micros - (new_tat - now)) / interval;
+    let decision = Decision {
+        allowed: true,
+        remaining: remaining.try_into().unwrap_or(u32::MAX),
+        retry_after_ms: 0,
+        reset_after_ms: micros_to_ms(new_tat - now),
+    };
+    (decision, Some(new_tat))
+}
+
+/// Rounds up, so that a caller retrying after the given time isn't early.
+fn micros_to_ms(micros: u64) -> u64 {
+    micros.div_ceil(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000;
+
+    #[test]
+    fn allows_a_burst_then_spaces_actions() {
+        let (first, tat) = check(None, 0, 2, SECOND);
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+
+        let (second, tat) = check(tat, 0, 2, SECOND);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+        assert_eq!(second.reset_after_ms, 1000);
+
+        let (third, denied_tat) = check(tat, 0, 2, SECOND);
+        assert!(!third.allowed);
+        assert_eq!(third.retry_after_ms, 500);
+        assert_eq!(denied_tat, None);
+
+        let (later, _) = check(tat, SECOND / 2, 2, SECOND);
+        assert!(later.allowed);
+        assert_eq!(later.remaining, 0);
+    }
+
+    #[test]
+    fn stale_state_is_a_full_limit() {
+        let (decision, _) = check(Some(10), 10 * SECOND, 5, SECOND);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 4);
+    }
+
+    #[test]
+    fn state_expires_once_its_tat_passes() {
+        assert!(!is_expired(&encode_tat(SECOND), 0));
+        assert!(is_expired(&encode_tat(SECOND), SECOND));
+        assert!(is_expired(b"short", 0));
+    }
+
+    #[test]
+    fn state_round_trips() {
+        assert_eq!(decode_tat(&encode_tat(42)), Some(42));
+        assert_eq!(decode_tat(b"short"), None);
+    }
+}
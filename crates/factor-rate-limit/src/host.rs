@@ -0,0 +1,160 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use spin_common::time::now_micros;
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+use spin_world::spin::rate_limit::rate_limit::{self, Decision, Error};
+use spin_world::v2::key_value;
+use tracing::{instrument, Level};
+
+use crate::gcra::{check, decode_tat, encode_tat, is_expired, state_key};
+
+/// How many times to retry a compare-and-swap which lost a race with another
+/// caller before giving up.
+const MAX_SWAP_ATTEMPTS: usize = 16;
+
+/// How often expired rate limit state is deleted, in microseconds.
+const SWEEP_INTERVAL_MICROS: u64 = 60 * 60 * 1_000_000;
+
+pub struct InstanceState {
+    allowed: bool,
+    store_label: String,
+    store_manager: Arc<dyn StoreManager>,
+    store: Option<Arc<dyn Store>>,
+    last_sweep: Arc<AtomicU64>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed: bool,
+        store_label: String,
+        store_manager: Arc<dyn StoreManager>,
+        last_sweep: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            allowed,
+            store_label,
+            store_manager,
+            store: None,
+            last_sweep,
+        }
+    }
+
+    /// Returns whether this instance may use rate limits.
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Returns the rate limit store, opening it on first use.
+    async fn store(&mut self) -> Result<Arc<dyn Store>, Error> {
+        if !self.allowed {
+            return Err(Error::AccessDenied);
+        }
+        if let Some(store) = &self.store {
+            return Ok(store.clone());
+        }
+        let store = self
+            .store_manager
+            .get(&self.store_label)
+            .await
+            .map_err(to_rate_limit_err)?;
+        self.store = Some(store.clone());
+        Ok(store)
+    }
+
+    /// Deletes the state of keys whose TAT has passed, if it has been
+    /// [`SWEEP_INTERVAL_MICROS`] since the app last did so.
+    ///
+    /// Expired state allows as much as no state, but would otherwise be kept
+    /// forever for keys which aren't used again. An update racing with the
+    /// deletion may be lost, which at worst gives that key one more burst.
+    async fn sweep_if_due(&self, store: &dyn Store) {
+        let now = now_micros();
+        let last_sweep = self.last_sweep.load(Ordering::Relaxed);
+        if now < last_sweep.saturating_add(SWEEP_INTERVAL_MICROS)
+            || self
+                .last_sweep
+                .compare_exchange(last_sweep, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        let keys = match store.get_keys().await {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("Failed to list rate limit state: {e:?}");
+                return;
+            }
+        };
+        let prefix = state_key("");
+        for key in keys.into_iter().filter(|key| key.starts_with(&prefix)) {
+            match store.get(&key).await {
+                Ok(Some(state)) if is_expired(&state, now) => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to read rate limit state {key:?}: {e:?}");
+                    continue;
+                }
+            }
+            if let Err(e) = store.delete(&key).await {
+                tracing::warn!("Failed to delete rate limit state {key:?}: {e:?}");
+            }
+        }
+    }
+}
+
+impl rate_limit::Host for InstanceState {
+    #[instrument(name = "spin_rate_limit.rate_limit", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn rate_limit(
+        &mut self,
+        key: String,
+        limit: u32,
+        window_ms: u64,
+    ) -> Result<Decision, Error> {
+        if limit == 0 || window_ms == 0 {
+            return Err(Error::InvalidLimit);
+        }
+        let window_micros = window_ms.saturating_mul(1000);
+        let store = self.store().await?;
+        self.sweep_if_due(store.as_ref()).await;
+        let state_key = state_key(&key);
+
+        for _ in 0..MAX_SWAP_ATTEMPTS {
+            let cas = store
+                .new_compare_and_swap(0, &state_key)
+                .await
+                .map_err(to_rate_limit_err)?;
+            let current = cas.current().await.map_err(to_rate_limit_err)?;
+            let stored_tat = current.as_deref().and_then(decode_tat);
+            let (decision, new_tat) = check(stored_tat, now_micros(), limit, window_micros);
+            let Some(new_tat) = new_tat else {
+                // Denied actions don't change the state
+                return Ok(decision);
+            };
+            match cas.swap(encode_tat(new_tat)).await {
+                Ok(()) => return Ok(decision),
+                // Another caller updated the state first; decide again.
+                Err(SwapError::CasFailed(_)) => {}
+                Err(SwapError::Other(e)) => return Err(Error::Other(e)),
+            }
+        }
+        Err(Error::Other(format!(
+            "too many concurrent updates to rate limit {key:?}"
+        )))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+fn to_rate_limit_err(e: key_value::Error) -> Error {
+    match e {
+        key_value::Error::NoSuchStore => Error::NoSuchStore,
+        key_value::Error::AccessDenied => Error::AccessDenied,
+        key_value::Error::StoreTableFull => Error::Other("too many open stores".into()),
+        key_value::Error::Other(e) => Error::Other(e),
+    }
+}
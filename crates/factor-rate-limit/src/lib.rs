@@ -0,0 +1,132 @@
+mod gcra;
+mod host;
+pub mod runtime_config;
+
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use spin_factor_key_value::{KeyValueFactor, StoreManager, KEY_VALUE_STORES_KEY};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that provides rate limits shared by every instance and replica
+/// of an app, kept in one of the app's key-value stores.
+///
+/// A component may use rate limits if it may use the rate limit store as a
+/// key-value store, so this factor must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct RateLimitFactor {
+    _priv: (),
+}
+
+impl RateLimitFactor {
+    /// Create a new RateLimitFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for RateLimitFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::rate_limit::rate_limit::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        let configured = ctx.take_runtime_config();
+        // An explicitly configured store must exist; the default store is
+        // only checked when a component uses rate limits.
+        if let Some(config) = &configured {
+            anyhow::ensure!(
+                store_manager.is_defined(&config.store),
+                "rate limit store {:?} is not a defined key-value store",
+                config.store
+            );
+        }
+        let RuntimeConfig { store } = configured.unwrap_or_default();
+
+        let mut component_allowed = HashMap::new();
+        for component in ctx.app().components() {
+            let allowed = component
+                .get_metadata(KEY_VALUE_STORES_KEY)?
+                .unwrap_or_default()
+                .contains(&store);
+            component_allowed.insert(component.id().to_string(), allowed);
+        }
+
+        Ok(AppState {
+            store_manager,
+            store,
+            component_allowed,
+            last_sweep: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed = *app_state
+            .component_allowed
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed");
+        Ok(InstanceBuilder {
+            store_manager: app_state.store_manager.clone(),
+            store: app_state.store.clone(),
+            allowed,
+            last_sweep: app_state.last_sweep.clone(),
+        })
+    }
+}
+
+pub struct AppState {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The label of the key-value store holding rate limit state.
+    store: String,
+    /// Whether each component may use rate limits, keyed by component ID.
+    component_allowed: HashMap<String, bool>,
+    /// When expired state was last deleted, in microseconds since the Unix
+    /// epoch. Starts at zero, so the first use after startup deletes it.
+    last_sweep: Arc<AtomicU64>,
+}
+
+pub struct InstanceBuilder {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The label of the key-value store holding rate limit state.
+    store: String,
+    /// Whether this component instance may use rate limits.
+    allowed: bool,
+    /// When expired state was last deleted, shared across the app.
+    last_sweep: Arc<AtomicU64>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.allowed,
+            self.store,
+            self.store_manager,
+            self.last_sweep,
+        ))
+    }
+}
@@ -0,0 +1,16 @@
+pub mod spin;
+
+/// Runtime configuration for rate limits.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store holding rate limit state.
+    pub store: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            store: "default".into(),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for rate limits from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [rate_limit]
+/// store = "rate-limits"
+/// ```
+///
+/// The store should be one which supports atomic compare-and-swap across
+/// replicas, such as Redis or Azure Cosmos DB.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("rate_limit") else {
+        return Ok(None);
+    };
+    let toml: RateLimitToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [rate_limit] table")?;
+    Ok(Some(RuntimeConfig { store: toml.store }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RateLimitToml {
+    store: String,
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, RuntimeConfig, StoreManager,
+};
+use spin_factor_rate_limit::RateLimitFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::rate_limit::rate_limit::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    rate_limit: RateLimitFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            key_value: Some(value),
+            rate_limit: None,
+        }
+    }
+}
+
+fn memory_store_manager() -> Arc<dyn StoreManager> {
+    let store_manager = MemoryKeyValueStore::new()
+        .make_store(Default::default())
+        .expect("in-memory store should be created");
+    Arc::new(store_manager)
+}
+
+async fn build_state(
+    manifest: toml::Table,
+    store_manager: Arc<dyn StoreManager>,
+) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store_manager("default".into(), store_manager);
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        rate_limit: RateLimitFactor::new(),
+    })
+    .extend_manifest(manifest);
+    env.runtime_config(runtime_config)?
+        .build_instance_state()
+        .await
+}
+
+fn allowed_default_store() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rate_limits_follow_key_value_store_access() -> anyhow::Result<()> {
+    let state = build_state(allowed_default_store(), memory_store_manager()).await?;
+    assert!(state.rate_limit.allowed());
+
+    let mut state = build_state(
+        toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        },
+        memory_store_manager(),
+    )
+    .await?;
+    assert!(matches!(
+        state.rate_limit.rate_limit("k".into(), 1, 1000).await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn actions_beyond_the_limit_are_denied() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store(), memory_store_manager()).await?;
+    let rate_limit = &mut state.rate_limit;
+
+    for remaining in [2, 1, 0] {
+        let decision = rate_limit.rate_limit("k".into(), 3, 60_000).await?;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, remaining);
+    }
+    let denied = rate_limit.rate_limit("k".into(), 3, 60_000).await?;
+    assert!(!denied.allowed);
+    assert!(denied.retry_after_ms > 0);
+
+    // Keys are limited independently
+    assert!(
+        rate_limit
+            .rate_limit("other".into(), 3, 60_000)
+            .await?
+            .allowed
+    );
+    assert!(matches!(
+        rate_limit.rate_limit("k".into(), 0, 60_000).await,
+        Err(Error::InvalidLimit)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expired_state_is_deleted() -> anyhow::Result<()> {
+    let store_manager = memory_store_manager();
+    let store = store_manager.get("default").await?;
+    let expired = 1u64.to_be_bytes();
+    let current = u64::MAX.to_be_bytes();
+    store.set("spin-rate-limit:expired", &expired).await?;
+    store.set("spin-rate-limit:current", &current).await?;
+    store.set("unrelated", &expired).await?;
+
+    // The first rate limit checked after startup deletes expired state.
+    let mut state = build_state(allowed_default_store(), store_manager).await?;
+    state.rate_limit.rate_limit("k".into(), 3, 60_000).await?;
+
+    assert!(!store.exists("spin-rate-limit:expired").await?);
+    assert!(store.exists("spin-rate-limit:current").await?);
+    assert!(store.exists("unrelated").await?);
+    assert!(store.exists("spin-rate-limit:k").await?);
+    Ok(())
+}
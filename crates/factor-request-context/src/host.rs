@@ -0,0 +1,32 @@
+use spin_world::spin::request_context::context;
+
+use crate::RequestContextHandle;
+
+pub struct InstanceState {
+    handle: RequestContextHandle,
+}
+
+impl InstanceState {
+    pub(crate) fn new(handle: RequestContextHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Returns a handle to the instance's context.
+    pub fn handle(&self) -> &RequestContextHandle {
+        &self.handle
+    }
+}
+
+impl context::Host for InstanceState {
+    async fn request_id(&mut self) -> anyhow::Result<String> {
+        Ok(self.handle.get().request_id)
+    }
+
+    async fn tenant_id(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.handle.get().tenant_id)
+    }
+
+    async fn traceparent(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.handle.get().traceparent)
+    }
+}
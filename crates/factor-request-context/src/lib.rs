@@ -0,0 +1,333 @@
+mod host;
+pub mod runtime_config;
+mod tenant;
+
+use std::sync::{Arc, RwLock};
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+pub use tenant::{
+    is_valid_tenant_id, InvalidTenantId, TenantConfig, TenantMatch, TenantScope, TenantSource,
+    TenantStore,
+};
+
+use tenant::Tenancy;
+
+/// The header carrying the request ID on inbound and outbound HTTP requests.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const TRACEPARENT_HEADER: HeaderName = HeaderName::from_static("traceparent");
+const TRACESTATE_HEADER: HeaderName = HeaderName::from_static("tracestate");
+
+/// The longest inbound request ID that is accepted rather than replaced.
+const MAX_REQUEST_ID_LEN: usize = 200;
+
+/// A factor that gives each trigger invocation a [`RequestContext`], which is
+/// available to the guest and propagated on outbound calls.
+#[derive(Default)]
+pub struct RequestContextFactor {
+    _priv: (),
+}
+
+impl RequestContextFactor {
+    /// Create a new RequestContextFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for RequestContextFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::request_context::context::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let RuntimeConfig {
+            tenant_id_header,
+            tenant_source,
+            namespace_key_value,
+            tenants,
+        } = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            tenancy: Tenancy {
+                source: tenant_source,
+                outbound_header: tenant_id_header,
+                namespace_key_value,
+                tenants: Arc::new(tenants),
+            },
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        Ok(InstanceBuilder {
+            handle: RequestContextHandle {
+                context: Arc::new(RwLock::new(RequestContext::new_invocation())),
+                tenancy: ctx.app_state().tenancy.clone(),
+            },
+        })
+    }
+}
+
+pub struct AppState {
+    tenancy: Tenancy,
+}
+
+impl AppState {
+    /// Derives the tenant of an inbound HTTP request from its headers, host or
+    /// path, according to the configured [`TenantSource`].
+    pub fn resolve_tenant<'a>(
+        &self,
+        headers: &HeaderMap,
+        host: Option<&str>,
+        path: &'a str,
+    ) -> Result<TenantMatch<'a>, InvalidTenantId> {
+        self.tenancy.resolve(headers, host, path)
+    }
+
+    /// The configured per-tenant overrides, by tenant ID.
+    pub fn tenants(&self) -> impl Iterator<Item = (&str, &TenantConfig)> {
+        self.tenancy
+            .tenants
+            .iter()
+            .map(|(tenant_id, config)| (tenant_id.as_str(), config))
+    }
+}
+
+/// The context of a single trigger invocation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    /// The ID of the invocation.
+    pub request_id: String,
+    /// The tenant the invocation is on behalf of.
+    pub tenant_id: Option<String>,
+    /// The W3C `traceparent` of the invocation.
+    pub traceparent: Option<String>,
+    /// The W3C `tracestate` accompanying `traceparent`.
+    pub tracestate: Option<String>,
+}
+
+impl RequestContext {
+    /// A context for a new invocation, with a new request ID and the trace
+    /// context of the current span, if it is being traced.
+    fn new_invocation() -> Self {
+        let mut trace_headers = HeaderMap::new();
+        spin_telemetry::inject_trace_context(&mut trace_headers);
+        Self {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: None,
+            traceparent: header_str(&trace_headers, &TRACEPARENT_HEADER),
+            tracestate: header_str(&trace_headers, &TRACESTATE_HEADER),
+        }
+    }
+}
+
+/// A handle to an instance's [`RequestContext`].
+///
+/// Other factors are prepared before the trigger fills in the context, so they
+/// hold a handle and read the context when the guest makes an outbound call.
+#[derive(Clone, Debug)]
+pub struct RequestContextHandle {
+    context: Arc<RwLock<RequestContext>>,
+    tenancy: Tenancy,
+}
+
+impl RequestContextHandle {
+    /// Returns the current context.
+    pub fn get(&self) -> RequestContext {
+        self.context.read().unwrap().clone()
+    }
+
+    /// Returns the resources of the tenant the invocation is on behalf of, if
+    /// any.
+    pub fn tenant(&self) -> Option<TenantScope> {
+        let context = self.context.read().unwrap();
+        let tenant_id = context.tenant_id.as_deref()?;
+        Some(self.tenancy.scope(tenant_id))
+    }
+
+    /// Starts a new invocation by the same instance, e.g. when an instance is
+    /// reused for another request, replacing the context with a new one.
+    pub fn restart(&self) {
+        *self.context.write().unwrap() = RequestContext::new_invocation();
+    }
+
+    /// Sets the tenant ID.
+    pub fn set_tenant_id(&self, tenant_id: Option<String>) {
+        self.context.write().unwrap().tenant_id = tenant_id;
+    }
+
+    /// Fills in the context from the headers of an inbound HTTP request.
+    ///
+    /// The request ID is taken from `x-request-id` and, if tenants are
+    /// identified by a header, the tenant ID from that header. The inbound
+    /// trace context is used only if the invocation isn't traced itself;
+    /// otherwise the invocation's span is the better parent for outbound calls.
+    pub fn set_from_http_headers(&self, headers: &HeaderMap) {
+        let mut context = self.context.write().unwrap();
+        if let Some(request_id) =
+            header_str(headers, &REQUEST_ID_HEADER).filter(|id| is_valid_request_id(id))
+        {
+            context.request_id = request_id;
+        }
+        if let TenantSource::Header(tenant_id_header) = &self.tenancy.source {
+            context.tenant_id =
+                header_str(headers, tenant_id_header).filter(|id| is_valid_tenant_id(id));
+        }
+        if context.traceparent.is_none() {
+            context.traceparent = header_str(headers, &TRACEPARENT_HEADER);
+            context.tracestate = header_str(headers, &TRACESTATE_HEADER);
+        }
+    }
+
+    /// Adds the context to the headers of an outbound HTTP request.
+    ///
+    /// Headers that are already set are kept.
+    pub fn inject_headers(&self, headers: &mut HeaderMap) {
+        let context = self.context.read().unwrap();
+        let mut inject = |name: &HeaderName, value: Option<&String>| {
+            let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) else {
+                return;
+            };
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value);
+            }
+        };
+        inject(&REQUEST_ID_HEADER, Some(&context.request_id));
+        if let Some(tenant_id_header) = &self.tenancy.outbound_header {
+            inject(tenant_id_header, context.tenant_id.as_ref());
+        }
+        inject(&TRACEPARENT_HEADER, context.traceparent.as_ref());
+        inject(&TRACESTATE_HEADER, context.tracestate.as_ref());
+    }
+}
+
+pub struct InstanceBuilder {
+    handle: RequestContextHandle,
+}
+
+impl InstanceBuilder {
+    /// Returns a handle to the instance's context.
+    pub fn handle(&self) -> RequestContextHandle {
+        self.handle.clone()
+    }
+
+    /// Sets the request ID.
+    pub fn set_request_id(&mut self, request_id: impl Into<String>) {
+        self.handle.context.write().unwrap().request_id = request_id.into();
+    }
+
+    /// Sets the tenant ID.
+    pub fn set_tenant_id(&mut self, tenant_id: Option<String>) {
+        self.handle.set_tenant_id(tenant_id);
+    }
+
+    /// Fills in the context from the headers of an inbound HTTP request.
+    ///
+    /// See [`RequestContextHandle::set_from_http_headers`].
+    pub fn set_from_http_headers(&mut self, headers: &HeaderMap) {
+        self.handle.set_from_http_headers(headers);
+    }
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.handle))
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    let value = headers.get(name)?.to_str().ok()?.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(tenant_id_header: Option<&'static str>) -> InstanceBuilder {
+        InstanceBuilder {
+            handle: RequestContextHandle {
+                context: Arc::new(RwLock::new(RequestContext {
+                    request_id: "generated".into(),
+                    ..Default::default()
+                })),
+                tenancy: Tenancy {
+                    source: tenant_id_header.map_or(TenantSource::None, |name| {
+                        TenantSource::Header(HeaderName::from_static(name))
+                    }),
+                    outbound_header: tenant_id_header.map(HeaderName::from_static),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn context_is_filled_in_from_inbound_headers() {
+        let mut builder = builder(Some("x-tenant"));
+        let mut inbound = HeaderMap::new();
+        inbound.insert(REQUEST_ID_HEADER, HeaderValue::from_static("abc-123"));
+        inbound.insert("x-tenant", HeaderValue::from_static("acme"));
+        inbound.insert(
+            TRACEPARENT_HEADER,
+            HeaderValue::from_static("00-abc-def-01"),
+        );
+        builder.set_from_http_headers(&inbound);
+
+        assert_eq!(
+            builder.handle().get(),
+            RequestContext {
+                request_id: "abc-123".into(),
+                tenant_id: Some("acme".into()),
+                traceparent: Some("00-abc-def-01".into()),
+                tracestate: None,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_inbound_request_id_is_ignored() {
+        let mut builder = builder(None);
+        let mut inbound = HeaderMap::new();
+        inbound.insert(REQUEST_ID_HEADER, HeaderValue::from_static("has spaces"));
+        builder.set_from_http_headers(&inbound);
+
+        assert_eq!(builder.handle().get().request_id, "generated");
+    }
+
+    #[test]
+    fn inject_headers_keeps_existing_headers() {
+        let mut builder = builder(Some("x-tenant"));
+        builder.set_tenant_id(Some("acme".into()));
+
+        let mut outbound = HeaderMap::new();
+        outbound.insert(REQUEST_ID_HEADER, HeaderValue::from_static("guest-set"));
+        builder.handle().inject_headers(&mut outbound);
+
+        assert_eq!(outbound[REQUEST_ID_HEADER], "guest-set");
+        assert_eq!(outbound["x-tenant"], "acme");
+        assert!(!outbound.contains_key(TRACEPARENT_HEADER));
+    }
+}
@@ -0,0 +1,20 @@
+pub mod spin;
+
+use std::collections::HashMap;
+
+use http::HeaderName;
+
+use crate::{TenantConfig, TenantSource};
+
+/// Runtime configuration for request contexts.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// The HTTP header carrying the tenant ID on outbound requests.
+    pub tenant_id_header: Option<HeaderName>,
+    /// Where the tenant of an inbound HTTP request is taken from.
+    pub tenant_source: TenantSource,
+    /// Whether tenants sharing a key-value store have their keys kept apart.
+    pub namespace_key_value: bool,
+    /// Per-tenant resource overrides, keyed by tenant ID.
+    pub tenants: HashMap<String, TenantConfig>,
+}
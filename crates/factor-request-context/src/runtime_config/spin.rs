@@ -0,0 +1,124 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context as _};
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+use crate::{is_valid_tenant_id, TenantConfig, TenantSource};
+
+/// Get the runtime configuration for request contexts from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [request_context]
+/// # Propagate the tenant ID on outbound requests in this header
+/// tenant_id_header = "x-tenant-id"
+/// # Take the tenant of inbound requests from the hostname, the first path
+/// # segment or, only behind a proxy which sets it, `tenant_id_header`:
+/// # tenant_id_host_suffix = ".example.com"
+/// # tenant_id_path_prefix = true
+/// # trust_tenant_id_header = true
+/// namespace_key_value = true
+///
+/// [request_context.tenants.acme]
+/// key_value_stores = { default = "acme" }
+/// sqlite_databases = { default = "acme" }
+/// variables = { plan = "enterprise" }
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("request_context") else {
+        return Ok(None);
+    };
+    let toml: RequestContextToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [request_context] table")?;
+    let tenant_id_header = toml
+        .tenant_id_header
+        .map(|name| {
+            name.parse()
+                .with_context(|| format!("invalid tenant_id_header {name:?}"))
+        })
+        .transpose()?;
+    // Clients can set the tenant header themselves, so it only identifies
+    // the tenant when the operator says a proxy sets it.
+    let tenant_source = match (
+        toml.tenant_id_host_suffix,
+        toml.tenant_id_path_prefix,
+        toml.trust_tenant_id_header,
+    ) {
+        (None, false, false) => TenantSource::None,
+        (Some(suffix), false, false) => TenantSource::HostSuffix(suffix),
+        (None, true, false) => TenantSource::PathPrefix,
+        (None, false, true) => match &tenant_id_header {
+            Some(name) => TenantSource::Header(name.clone()),
+            None => bail!("trust_tenant_id_header requires tenant_id_header to be set"),
+        },
+        _ => bail!(
+            "only one of tenant_id_host_suffix, tenant_id_path_prefix and trust_tenant_id_header may be set"
+        ),
+    };
+    if let Some(tenant_id) = toml.tenants.keys().find(|id| !is_valid_tenant_id(id)) {
+        bail!("invalid tenant ID {tenant_id:?} in [request_context.tenants]");
+    }
+    Ok(Some(RuntimeConfig {
+        tenant_id_header,
+        tenant_source,
+        namespace_key_value: toml.namespace_key_value,
+        tenants: toml.tenants,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RequestContextToml {
+    tenant_id_header: Option<String>,
+    tenant_id_host_suffix: Option<String>,
+    #[serde(default)]
+    tenant_id_path_prefix: bool,
+    #[serde(default)]
+    trust_tenant_id_header: bool,
+    #[serde(default)]
+    namespace_key_value: bool,
+    #[serde(default)]
+    tenants: HashMap<String, TenantConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant_source(table: toml::Table) -> anyhow::Result<TenantSource> {
+        let table = toml::Table::from_iter([("request_context".into(), table.into())]);
+        Ok(config_from_table(&table)?.unwrap().tenant_source)
+    }
+
+    #[test]
+    fn tenant_header_is_only_used_inbound_when_trusted() -> anyhow::Result<()> {
+        let source = tenant_source(toml::toml! {
+            tenant_id_header = "x-tenant"
+        })?;
+        assert_eq!(source, TenantSource::None);
+
+        let source = tenant_source(toml::toml! {
+            tenant_id_header = "x-tenant"
+            trust_tenant_id_header = true
+        })?;
+        assert_eq!(source, TenantSource::Header("x-tenant".parse()?));
+
+        assert!(tenant_source(toml::toml! {
+            trust_tenant_id_header = true
+        })
+        .is_err());
+        assert!(tenant_source(toml::toml! {
+            tenant_id_header = "x-tenant"
+            trust_tenant_id_header = true
+            tenant_id_path_prefix = true
+        })
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::HashMap, sync::Arc};
+
+use http::{HeaderMap, HeaderName};
+use serde::Deserialize;
+
+/// The longest accepted tenant ID.
+const MAX_TENANT_ID_LEN: usize = 64;
+
+/// Where the HTTP trigger takes the tenant of a request from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TenantSource {
+    /// The tenant isn't taken from the request.
+    #[default]
+    None,
+    /// The tenant is the value of a request header.
+    ///
+    /// Clients can send any value, so this must only be used behind a trusted
+    /// proxy which sets the header itself, replacing whatever the client
+    /// sent. Otherwise a client can act as any tenant and use its data.
+    Header(HeaderName),
+    /// The tenant is the part of the request's hostname before this suffix,
+    /// e.g. `acme` for `acme.example.com` with the suffix `.example.com`.
+    HostSuffix(String),
+    /// The tenant is the first segment of the request path, which is removed
+    /// before the request is routed.
+    PathPrefix,
+}
+
+/// Resources a tenant uses in place of the app's own.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    /// Key-value store labels mapped to the store labels the tenant uses.
+    #[serde(default)]
+    pub key_value_stores: HashMap<String, String>,
+    /// SQLite database labels mapped to the database labels the tenant uses.
+    #[serde(default)]
+    pub sqlite_databases: HashMap<String, String>,
+    /// Variable values which take precedence over those the app resolves.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// The tenant of an inbound HTTP request.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TenantMatch<'a> {
+    /// The tenant ID, if the request has one.
+    pub tenant_id: Option<String>,
+    /// The request path with any tenant prefix removed.
+    pub path: &'a str,
+}
+
+/// The tenant named by a request isn't a valid tenant ID.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid tenant ID {0:?}")]
+pub struct InvalidTenantId(pub String);
+
+/// Tenancy settings shared by every instance of an app.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tenancy {
+    pub(crate) source: TenantSource,
+    pub(crate) outbound_header: Option<HeaderName>,
+    pub(crate) namespace_key_value: bool,
+    pub(crate) tenants: Arc<HashMap<String, TenantConfig>>,
+}
+
+impl Tenancy {
+    pub(crate) fn resolve<'a>(
+        &self,
+        headers: &HeaderMap,
+        host: Option<&str>,
+        path: &'a str,
+    ) -> Result<TenantMatch<'a>, InvalidTenantId> {
+        let (tenant_id, path) = match &self.source {
+            TenantSource::None => (None, path),
+            TenantSource::Header(name) => (crate::header_str(headers, name), path),
+            TenantSource::HostSuffix(suffix) => {
+                // Strip any port; IPv6 literals have no subdomains to match
+                let host = host
+                    .filter(|host| !host.starts_with('['))
+                    .map(|host| host.split(':').next().unwrap_or_default());
+                let tenant_id = host
+                    .and_then(|host| strip_suffix_ignore_case(host, suffix))
+                    .filter(|tenant_id| !tenant_id.is_empty())
+                    .map(str::to_ascii_lowercase);
+                (tenant_id, path)
+            }
+            TenantSource::PathPrefix => {
+                let rest = path.strip_prefix('/').unwrap_or(path);
+                match rest.split_once('/') {
+                    Some((tenant_id, _)) => (Some(tenant_id.to_owned()), &rest[tenant_id.len()..]),
+                    None if !rest.is_empty() => (Some(rest.to_owned()), "/"),
+                    None => (None, path),
+                }
+            }
+        };
+        match tenant_id {
+            Some(tenant_id) if !is_valid_tenant_id(&tenant_id) => Err(InvalidTenantId(tenant_id)),
+            tenant_id => Ok(TenantMatch { tenant_id, path }),
+        }
+    }
+
+    pub(crate) fn scope(&self, tenant_id: &str) -> TenantScope {
+        TenantScope {
+            id: tenant_id.to_owned(),
+            config: self.tenants.get(tenant_id).cloned().unwrap_or_default(),
+            namespace_key_value: self.namespace_key_value,
+        }
+    }
+}
+
+/// The resources an invocation on behalf of a tenant uses.
+#[derive(Clone, Debug)]
+pub struct TenantScope {
+    id: String,
+    config: TenantConfig,
+    namespace_key_value: bool,
+}
+
+/// A key-value store as seen by a tenant.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TenantStore<'a> {
+    /// The label of the store to open.
+    pub label: &'a str,
+    /// A prefix added to every key, which keeps tenants sharing the store
+    /// apart.
+    pub key_prefix: Option<String>,
+}
+
+impl TenantScope {
+    /// The tenant ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the store the tenant uses in place of the store `label`.
+    ///
+    /// A store remapped for the tenant is used as is. Otherwise the store is
+    /// shared with other tenants, with keys prefixed by the tenant ID if key-value
+    /// namespacing is enabled.
+    pub fn key_value_store<'a>(&'a self, label: &'a str) -> TenantStore<'a> {
+        match self.config.key_value_stores.get(label) {
+            Some(label) => TenantStore {
+                label,
+                key_prefix: None,
+            },
+            None => TenantStore {
+                label,
+                key_prefix: self.namespace_key_value.then(|| format!("{}/", self.id)),
+            },
+        }
+    }
+
+    /// Returns the label of the database the tenant uses in place of the
+    /// database `label`.
+    pub fn sqlite_database<'a>(&'a self, label: &'a str) -> &'a str {
+        self.config
+            .sqlite_databases
+            .get(label)
+            .map_or(label, String::as_str)
+    }
+
+    /// The tenant's variables, which override the app's.
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.config.variables
+    }
+}
+
+fn strip_suffix_ignore_case<'a>(host: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = host.len().checked_sub(suffix.len())?;
+    (host.is_char_boundary(split) && host[split..].eq_ignore_ascii_case(suffix))
+        .then(|| &host[..split])
+}
+
+/// Whether `id` can be used as a tenant ID.
+///
+/// Tenant IDs end up in store keys and headers, so they are restricted to
+/// ASCII alphanumerics, `-` and `_`.
+pub fn is_valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn tenancy(source: TenantSource) -> Tenancy {
+        Tenancy {
+            source,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tenant_from_host_suffix() {
+        let tenancy = tenancy(TenantSource::HostSuffix(".example.com".into()));
+        let resolve = |host| {
+            tenancy
+                .resolve(&HeaderMap::new(), host, "/orders")
+                .unwrap()
+                .tenant_id
+        };
+        assert_eq!(resolve(Some("Acme.EXAMPLE.com:3000")), Some("acme".into()));
+        assert_eq!(resolve(Some("example.com")), None);
+        assert_eq!(resolve(Some(".example.com")), None);
+        assert_eq!(resolve(None), None);
+        assert!(tenancy
+            .resolve(&HeaderMap::new(), Some("a.b.example.com"), "/")
+            .is_err());
+    }
+
+    #[test]
+    fn tenant_from_path_prefix_is_stripped() {
+        let tenancy = tenancy(TenantSource::PathPrefix);
+        let resolve = |path| tenancy.resolve(&HeaderMap::new(), None, path).unwrap();
+        assert_eq!(
+            resolve("/acme/orders/1"),
+            TenantMatch {
+                tenant_id: Some("acme".into()),
+                path: "/orders/1"
+            }
+        );
+        assert_eq!(
+            resolve("/acme"),
+            TenantMatch {
+                tenant_id: Some("acme".into()),
+                path: "/"
+            }
+        );
+        assert_eq!(
+            resolve("/"),
+            TenantMatch {
+                tenant_id: None,
+                path: "/"
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_tenant_ids_are_rejected() {
+        let tenancy = tenancy(TenantSource::Header(HeaderName::from_static("x-tenant")));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant", HeaderValue::from_static("../acme"));
+        assert!(tenancy.resolve(&headers, None, "/").is_err());
+        assert!(!is_valid_tenant_id(&"a".repeat(65)));
+        assert!(is_valid_tenant_id("acme_co-1"));
+    }
+
+    #[test]
+    fn scope_applies_tenant_overrides() {
+        let tenancy = Tenancy {
+            namespace_key_value: true,
+            tenants: Arc::new(HashMap::from([(
+                "acme".to_owned(),
+                TenantConfig {
+                    key_value_stores: HashMap::from([("default".into(), "acme-kv".into())]),
+                    sqlite_databases: HashMap::from([("default".into(), "acme-db".into())]),
+                    variables: HashMap::from([("plan".into(), "enterprise".into())]),
+                },
+            )])),
+            ..Default::default()
+        };
+
+        let acme = tenancy.scope("acme");
+        assert_eq!(
+            acme.key_value_store("default"),
+            TenantStore {
+                label: "acme-kv",
+                key_prefix: None
+            }
+        );
+        assert_eq!(
+            acme.key_value_store("cache").key_prefix.as_deref(),
+            Some("acme/")
+        );
+        assert_eq!(acme.sqlite_database("default"), "acme-db");
+        assert_eq!(acme.variables()["plan"], "enterprise");
+
+        let globex = tenancy.scope("globex");
+        assert_eq!(globex.sqlite_database("default"), "default");
+        assert!(globex.variables().is_empty());
+    }
+}
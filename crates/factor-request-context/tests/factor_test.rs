@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use http::{HeaderMap, HeaderName};
+use spin_factor_request_context::{
+    RequestContextFactor, RuntimeConfig, TenantConfig, REQUEST_ID_HEADER,
+};
+use spin_factors::{App, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::request_context::context::Host as _;
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    request_context: RequestContextFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            request_context: Some(value),
+        }
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        request_context: RequestContextFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+}
+
+#[tokio::test]
+async fn each_instance_gets_a_request_id() -> anyhow::Result<()> {
+    let mut first = test_env().build_instance_state().await?;
+    let second = test_env().build_instance_state().await?;
+
+    let request_id = first.request_context.request_id().await?;
+    assert!(!request_id.is_empty());
+    assert_ne!(request_id, second.request_context.handle().get().request_id);
+    assert_eq!(first.request_context.tenant_id().await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_header_comes_from_runtime_config() -> anyhow::Result<()> {
+    let state = test_env()
+        .runtime_config(RuntimeConfig {
+            tenant_id_header: Some(HeaderName::from_static("x-tenant")),
+            ..Default::default()
+        })?
+        .build_instance_state()
+        .await?;
+
+    let mut outbound = HeaderMap::new();
+    state.request_context.handle().inject_headers(&mut outbound);
+    assert!(outbound.contains_key(REQUEST_ID_HEADER));
+    // No tenant has been set, so there is nothing to propagate
+    assert!(!outbound.contains_key("x-tenant"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_scope_uses_tenant_overrides() -> anyhow::Result<()> {
+    let env = test_env().runtime_config(RuntimeConfig {
+        tenants: HashMap::from([(
+            "acme".to_owned(),
+            TenantConfig {
+                sqlite_databases: HashMap::from([("default".into(), "acme".into())]),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    })?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+
+    let mut builders = env.factors.prepare(&configured_app, "test-component")?;
+    let handle = builders.request_context().handle();
+    assert!(handle.tenant().is_none());
+
+    builders
+        .request_context()
+        .set_tenant_id(Some("acme".into()));
+    let tenant = handle.tenant().unwrap();
+    assert_eq!(tenant.id(), "acme");
+    assert_eq!(tenant.sqlite_database("default"), "acme");
+    assert_eq!(tenant.sqlite_database("other"), "other");
+    Ok(())
+}
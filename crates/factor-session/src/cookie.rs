@@ -0,0 +1,132 @@
+//! Signed session cookies.
+//!
+//! A session cookie holds the session ID followed by a `.` and the base64url
+//! HMAC-SHA256 of the ID, so that guests can reject forged IDs without a trip
+//! to the backing store.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs and verifies session cookies.
+pub(crate) struct CookieSigner {
+    name: String,
+    key: Vec<u8>,
+    secure: bool,
+}
+
+impl CookieSigner {
+    pub fn new(name: String, key: Vec<u8>, secure: bool) -> Self {
+        Self { name, key, secure }
+    }
+
+    /// Returns a `Set-Cookie` header value carrying the signed session ID.
+    ///
+    /// The cookie has no `Max-Age`; the session's lifetime is enforced by
+    /// its expiry in the backing store.
+    pub fn set_cookie(&self, id: &str) -> String {
+        let value = format!("{id}.{}", self.sign(id));
+        self.cookie(&value, "")
+    }
+
+    /// Returns a `Set-Cookie` header value which removes the session cookie.
+    pub fn clear_cookie(&self) -> String {
+        self.cookie("", "; Max-Age=0")
+    }
+
+    /// Finds the session cookie in a `Cookie` header value and returns the
+    /// session ID if its signature is valid.
+    pub fn id_from_cookie(&self, header: &str) -> Option<String> {
+        header
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .filter(|(name, _)| *name == self.name)
+            .find_map(|(_, value)| self.verify(value.trim_matches('"')))
+    }
+
+    fn cookie(&self, value: &str, extra_attributes: &str) -> String {
+        let secure = if self.secure { "; Secure" } else { "" };
+        format!(
+            "{}={value}; Path=/; HttpOnly; SameSite=Lax{secure}{extra_attributes}",
+            self.name
+        )
+    }
+
+    fn mac(&self, id: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(id.as_bytes());
+        mac
+    }
+
+    fn sign(&self, id: &str) -> String {
+        URL_SAFE_NO_PAD.encode(self.mac(id).finalize().into_bytes())
+    }
+
+    fn verify(&self, value: &str) -> Option<String> {
+        let (id, signature) = value.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        // `verify_slice` compares in constant time
+        self.mac(id).verify_slice(&signature).ok()?;
+        Some(id.to_owned())
+    }
+}
+
+/// Generates a new, unguessable session ID.
+pub(crate) fn new_session_id() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(key: &[u8]) -> CookieSigner {
+        CookieSigner::new("sid".into(), key.to_vec(), true)
+    }
+
+    fn cookie_value(set_cookie: &str) -> &str {
+        set_cookie
+            .split(';')
+            .next()
+            .unwrap()
+            .strip_prefix("sid=")
+            .unwrap()
+    }
+
+    #[test]
+    fn signed_cookie_round_trips() {
+        let signer = signer(b"key");
+        let set_cookie = signer.set_cookie("abc");
+        assert!(set_cookie.ends_with("; Path=/; HttpOnly; SameSite=Lax; Secure"));
+
+        let header = format!("theme=dark; sid={}", cookie_value(&set_cookie));
+        assert_eq!(signer.id_from_cookie(&header).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn forged_or_foreign_cookies_are_rejected() {
+        let set_cookie = signer(b"other key").set_cookie("abc");
+        let signer = signer(b"key");
+        let header = format!("sid={}", cookie_value(&set_cookie));
+        assert_eq!(signer.id_from_cookie(&header), None);
+        assert_eq!(signer.id_from_cookie("sid=abc"), None);
+        assert_eq!(signer.id_from_cookie("sid=abc.!!!"), None);
+        assert_eq!(signer.id_from_cookie(""), None);
+    }
+
+    #[test]
+    fn clear_cookie_expires_immediately() {
+        assert_eq!(
+            signer(b"key").clear_cookie(),
+            "sid=; Path=/; HttpOnly; SameSite=Lax; Secure; Max-Age=0"
+        );
+    }
+
+    #[test]
+    fn session_ids_are_distinct() {
+        assert_ne!(new_session_id(), new_session_id());
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, StoreManager};
+use spin_world::spin::session::session::{self, Error};
+use spin_world::v2::key_value;
+use tracing::{instrument, Level};
+
+use crate::{
+    cookie::new_session_id,
+    record::{session_key, Record},
+    SessionConfig,
+};
+
+pub struct InstanceState {
+    allowed: bool,
+    store_manager: Arc<dyn StoreManager>,
+    config: Arc<SessionConfig>,
+    store: Option<Arc<dyn Store>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed: bool,
+        store_manager: Arc<dyn StoreManager>,
+        config: Arc<SessionConfig>,
+    ) -> Self {
+        Self {
+            allowed,
+            store_manager,
+            config,
+            store: None,
+        }
+    }
+
+    /// Returns whether this instance may use sessions.
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Returns the session store, opening it on first use.
+    async fn store(&mut self) -> Result<Arc<dyn Store>, Error> {
+        if !self.allowed {
+            return Err(Error::AccessDenied);
+        }
+        if let Some(store) = &self.store {
+            return Ok(store.clone());
+        }
+        let store = self
+            .store_manager
+            .get(&self.config.store)
+            .await
+            .map_err(to_session_err)?;
+        self.store = Some(store.clone());
+        Ok(store)
+    }
+
+    async fn write(&self, store: &dyn Store, id: &str, data: Vec<u8>) -> Result<(), Error> {
+        let record = Record::new(data, self.config.idle_timeout, now_millis());
+        store
+            .set(&session_key(id), &record.encode())
+            .await
+            .map_err(to_session_err)
+    }
+}
+
+impl session::Host for InstanceState {
+    #[instrument(name = "spin_session.create", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn create(&mut self, data: Vec<u8>) -> Result<String, Error> {
+        let store = self.store().await?;
+        let id = new_session_id();
+        self.write(store.as_ref(), &id, data).await?;
+        Ok(id)
+    }
+
+    #[instrument(name = "spin_session.get", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&mut self, id: String) -> Result<Option<Vec<u8>>, Error> {
+        let store = self.store().await?;
+        let now = now_millis();
+        let Some(record) = read(store.as_ref(), &id, now).await? else {
+            return Ok(None);
+        };
+        if record.needs_refresh(self.config.idle_timeout, now) {
+            let refreshed = Record::new(record.data, self.config.idle_timeout, now);
+            store
+                .set(&session_key(&id), &refreshed.encode())
+                .await
+                .map_err(to_session_err)?;
+            return Ok(Some(refreshed.data));
+        }
+        Ok(Some(record.data))
+    }
+
+    #[instrument(name = "spin_session.update", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn update(&mut self, id: String, data: Vec<u8>) -> Result<bool, Error> {
+        let store = self.store().await?;
+        if read(store.as_ref(), &id, now_millis()).await?.is_none() {
+            return Ok(false);
+        }
+        self.write(store.as_ref(), &id, data).await?;
+        Ok(true)
+    }
+
+    #[instrument(name = "spin_session.destroy", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn destroy(&mut self, id: String) -> Result<(), Error> {
+        let store = self.store().await?;
+        store
+            .delete(&session_key(&id))
+            .await
+            .map_err(to_session_err)
+    }
+
+    async fn set_cookie(&mut self, id: String) -> anyhow::Result<String> {
+        Ok(self.config.cookies.set_cookie(&id))
+    }
+
+    async fn clear_cookie(&mut self) -> anyhow::Result<String> {
+        Ok(self.config.cookies.clear_cookie())
+    }
+
+    async fn id_from_cookie(&mut self, cookie_header: String) -> anyhow::Result<Option<String>> {
+        Ok(self.config.cookies.id_from_cookie(&cookie_header))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+/// Reads a session, treating expired sessions as missing.
+///
+/// Key-value stores have no expiry of their own, so an expired session is
+/// deleted when it is next read.
+async fn read(store: &dyn Store, id: &str, now: u64) -> Result<Option<Record>, Error> {
+    let key = session_key(id);
+    let Some(record) = store.get(&key).await.map_err(to_session_err)? else {
+        return Ok(None);
+    };
+    let record = Record::decode(record, now);
+    if record.is_none() {
+        if let Err(e) = store.delete(&key).await {
+            tracing::warn!("failed to delete expired session: {e:?}");
+        }
+    }
+    Ok(record)
+}
+
+fn to_session_err(e: key_value::Error) -> Error {
+    match e {
+        key_value::Error::NoSuchStore => Error::NoSuchStore,
+        key_value::Error::AccessDenied => Error::AccessDenied,
+        key_value::Error::StoreTableFull => Error::Other("too many open stores".into()),
+        key_value::Error::Other(e) => Error::Other(e),
+    }
+}
@@ -0,0 +1,154 @@
+mod cookie;
+mod host;
+mod record;
+pub mod runtime_config;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use spin_factor_key_value::{KeyValueFactor, StoreManager, KEY_VALUE_STORES_KEY};
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+use cookie::CookieSigner;
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+
+/// A factor that provides server-side sessions with signed cookies on top of
+/// one of the app's key-value stores.
+///
+/// A component may use sessions if it may use the session store as a
+/// key-value store, so this factor must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct SessionFactor {
+    _priv: (),
+}
+
+impl SessionFactor {
+    /// Create a new SessionFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for SessionFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::session::session::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        let configured = ctx.take_runtime_config();
+        // An explicitly configured store must exist; the default store is
+        // only checked when a component uses sessions.
+        if let Some(config) = &configured {
+            anyhow::ensure!(
+                store_manager.is_defined(&config.store),
+                "session store {:?} is not a defined key-value store",
+                config.store
+            );
+        }
+        let RuntimeConfig {
+            store,
+            signing_key,
+            cookie_name,
+            idle_timeout,
+            secure_cookie,
+        } = configured.unwrap_or_default();
+
+        let mut component_allowed = HashMap::new();
+        for component in ctx.app().components() {
+            let allowed = component
+                .get_metadata(KEY_VALUE_STORES_KEY)?
+                .unwrap_or_default()
+                .contains(&store);
+            component_allowed.insert(component.id().to_string(), allowed);
+        }
+
+        let signing_key = signing_key.unwrap_or_else(|| {
+            // Every process signs with its own key, so sessions are lost on
+            // restart and aren't recognized by other replicas
+            if component_allowed.values().any(|allowed| *allowed) {
+                tracing::warn!(
+                    "no session signing key configured; session cookies will not outlive this process or be accepted by other instances of the app"
+                );
+            }
+            rand::random::<[u8; 32]>().to_vec()
+        });
+
+        Ok(AppState {
+            store_manager,
+            config: Arc::new(SessionConfig {
+                store,
+                idle_timeout,
+                cookies: CookieSigner::new(cookie_name, signing_key, secure_cookie),
+            }),
+            component_allowed,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed = *app_state
+            .component_allowed
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed");
+        Ok(InstanceBuilder {
+            store_manager: app_state.store_manager.clone(),
+            config: app_state.config.clone(),
+            allowed,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The session configuration for the app.
+    config: Arc<SessionConfig>,
+    /// Whether each component may use sessions, keyed by component ID.
+    component_allowed: HashMap<String, bool>,
+}
+
+/// The resolved session configuration for an app.
+pub(crate) struct SessionConfig {
+    /// The label of the key-value store holding sessions.
+    pub store: String,
+    /// How long a session lasts without being used.
+    pub idle_timeout: Duration,
+    /// Signs and verifies session cookies.
+    pub cookies: CookieSigner,
+}
+
+pub struct InstanceBuilder {
+    /// The key-value store manager for the app.
+    store_manager: Arc<dyn StoreManager>,
+    /// The session configuration for the app.
+    config: Arc<SessionConfig>,
+    /// Whether this component instance may use sessions.
+    allowed: bool,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.allowed,
+            self.store_manager,
+            self.config,
+        ))
+    }
+}
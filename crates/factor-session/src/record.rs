@@ -0,0 +1,88 @@
+//! The encoding of sessions in a key-value store.
+
+use std::time::Duration;
+
+/// Prefix for the keys of sessions in the backing store.
+const SESSION_KEY_PREFIX: &str = "spin-session:";
+
+/// Returns the backing store key of the session with `id`.
+pub(crate) fn session_key(id: &str) -> String {
+    format!("{SESSION_KEY_PREFIX}{id}")
+}
+
+/// A session as stored in the backing store.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Record {
+    pub expires_at: u64,
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// A session holding `data` which expires `idle_timeout` after `now`.
+    pub fn new(data: Vec<u8>, idle_timeout: Duration, now: u64) -> Self {
+        let timeout = idle_timeout.as_millis().try_into().unwrap_or(u64::MAX);
+        Self {
+            expires_at: now.saturating_add(timeout),
+            data,
+        }
+    }
+
+    /// Encodes the session as a big-endian expiry time followed by its data.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut record = Vec::with_capacity(8 + self.data.len());
+        record.extend_from_slice(&self.expires_at.to_be_bytes());
+        record.extend_from_slice(&self.data);
+        record
+    }
+
+    /// Decodes a session, returning `None` if it has expired or is malformed.
+    pub fn decode(mut record: Vec<u8>, now: u64) -> Option<Self> {
+        let expires_at = u64::from_be_bytes(record.get(..8)?.try_into().ok()?);
+        if expires_at <= now {
+            return None;
+        }
+        record.drain(..8);
+        Some(Self {
+            expires_at,
+            data: record,
+        })
+    }
+
+    /// Whether the session is due to have its expiry extended.
+    ///
+    /// Sessions are rewritten only once half of the idle timeout has passed,
+    /// so that reading a session doesn't always cost a write.
+    pub fn needs_refresh(&self, idle_timeout: Duration, now: u64) -> bool {
+        let half_timeout: u64 = (idle_timeout.as_millis() / 2)
+            .try_into()
+            .unwrap_or(u64::MAX);
+        self.expires_at.saturating_sub(now) < half_timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn record_round_trips_until_expiry() {
+        let record = Record::new(b"data".to_vec(), IDLE, 1000);
+        let decoded = Record::decode(record.encode(), 1099).unwrap();
+        assert_eq!(decoded, record);
+        assert!(Record::decode(record.encode(), 1100).is_none());
+    }
+
+    #[test]
+    fn malformed_record_is_ignored() {
+        assert!(Record::decode(b"short".to_vec(), 0).is_none());
+    }
+
+    #[test]
+    fn record_is_refreshed_after_half_the_idle_timeout() {
+        let record = Record::new(Vec::new(), IDLE, 1000);
+        assert!(!record.needs_refresh(IDLE, 1050));
+        assert!(record.needs_refresh(IDLE, 1051));
+    }
+}
@@ -0,0 +1,36 @@
+pub mod spin;
+
+use std::time::Duration;
+
+/// The default name of the session cookie.
+pub const DEFAULT_COOKIE_NAME: &str = "spin-session";
+/// How long a session lasts without being used, by default.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Runtime configuration for sessions.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store holding sessions.
+    pub store: String,
+    /// The key used to sign session cookies. If unset, a random key is
+    /// generated, so cookies are valid only for the life of the process.
+    pub signing_key: Option<Vec<u8>>,
+    /// The name of the session cookie.
+    pub cookie_name: String,
+    /// How long a session lasts without being used.
+    pub idle_timeout: Duration,
+    /// Whether session cookies are marked `Secure`.
+    pub secure_cookie: bool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            store: "default".into(),
+            signing_key: None,
+            cookie_name: DEFAULT_COOKIE_NAME.into(),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            secure_cookie: true,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// The shortest signing key accepted, in bytes.
+const MIN_SIGNING_KEY_LEN: usize = 32;
+
+/// Get the runtime configuration for sessions from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [session]
+/// store = "sessions"
+/// signing_key = "at-least-32-bytes-of-secret-material"
+/// cookie_name = "my-app-session"
+/// idle_timeout_secs = 3600
+/// secure_cookie = true
+/// ```
+///
+/// All fields are optional.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("session") else {
+        return Ok(None);
+    };
+    let toml: SessionToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [session] table")?;
+
+    let mut config = RuntimeConfig::default();
+    if let Some(store) = toml.store {
+        config.store = store;
+    }
+    if let Some(signing_key) = toml.signing_key {
+        anyhow::ensure!(
+            signing_key.len() >= MIN_SIGNING_KEY_LEN,
+            "session.signing_key must be at least {MIN_SIGNING_KEY_LEN} bytes"
+        );
+        config.signing_key = Some(signing_key.into_bytes());
+    }
+    if let Some(cookie_name) = toml.cookie_name {
+        anyhow::ensure!(
+            is_valid_cookie_name(&cookie_name),
+            "invalid session.cookie_name {cookie_name:?}"
+        );
+        config.cookie_name = cookie_name;
+    }
+    if let Some(secs) = toml.idle_timeout_secs {
+        anyhow::ensure!(secs > 0, "session.idle_timeout_secs must be positive");
+        config.idle_timeout = Duration::from_secs(secs);
+    }
+    if let Some(secure_cookie) = toml.secure_cookie {
+        config.secure_cookie = secure_cookie;
+    }
+    Ok(Some(config))
+}
+
+/// Whether `name` is a cookie name token (RFC 6265 §4.1.1).
+fn is_valid_cookie_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionToml {
+    store: Option<String>,
+    signing_key: Option<String>,
+    cookie_name: Option<String>,
+    idle_timeout_secs: Option<u64>,
+    secure_cookie: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(table: toml::Table) -> anyhow::Result<Option<RuntimeConfig>> {
+        config_from_table(&table)
+    }
+
+    #[test]
+    fn missing_table_is_none() {
+        assert!(config(toml::Table::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn fields_override_defaults() {
+        let config = config(toml::toml! {
+            [session]
+            store = "sessions"
+            signing_key = "0123456789abcdef0123456789abcdef"
+            idle_timeout_secs = 60
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.store, "sessions");
+        assert_eq!(config.idle_timeout, Duration::from_secs(60));
+        assert_eq!(config.cookie_name, super::super::DEFAULT_COOKIE_NAME);
+        assert!(config.secure_cookie);
+    }
+
+    #[test]
+    fn short_signing_keys_and_bad_cookie_names_are_rejected() {
+        assert!(config(toml::toml! {
+            [session]
+            signing_key = "too short"
+        })
+        .is_err());
+        assert!(config(toml::toml! {
+            [session]
+            cookie_name = "bad;name"
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use spin_factor_key_value::{
+    runtime_config::spin::MakeKeyValueStore, KeyValueFactor, RuntimeConfig,
+};
+use spin_factor_session::SessionFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::session::session::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    session: SessionFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            key_value: Some(value),
+            session: None,
+        }
+    }
+}
+
+async fn build_state(manifest: toml::Table) -> anyhow::Result<TestFactorsInstanceState> {
+    let mut runtime_config = RuntimeConfig::default();
+    let store_manager = MemoryKeyValueStore::new().make_store(Default::default())?;
+    runtime_config.add_store_manager("default".into(), Arc::new(store_manager));
+    let env = TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        session: SessionFactor::new(),
+    })
+    .extend_manifest(manifest);
+    env.runtime_config(runtime_config)?
+        .build_instance_state()
+        .await
+}
+
+fn allowed_default_store() -> toml::Table {
+    toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_follow_key_value_store_access() -> anyhow::Result<()> {
+    let state = build_state(allowed_default_store()).await?;
+    assert!(state.session.allowed());
+
+    let mut state = build_state(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    })
+    .await?;
+    assert!(matches!(
+        state.session.create(b"data".to_vec()).await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_lifecycle() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store()).await?;
+    let session = &mut state.session;
+
+    let id = session.create(b"first".to_vec()).await?;
+    assert_eq!(
+        session.get(id.clone()).await?.as_deref(),
+        Some(&b"first"[..])
+    );
+
+    assert!(session.update(id.clone(), b"second".to_vec()).await?);
+    assert_eq!(
+        session.get(id.clone()).await?.as_deref(),
+        Some(&b"second"[..])
+    );
+
+    session.destroy(id.clone()).await?;
+    assert_eq!(session.get(id.clone()).await?, None);
+    assert!(!session.update(id, b"third".to_vec()).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_ids_round_trip_through_cookies() -> anyhow::Result<()> {
+    let mut state = build_state(allowed_default_store()).await?;
+    let session = &mut state.session;
+
+    let id = session.create(Vec::new()).await?;
+    let set_cookie = session.set_cookie(id.clone()).await?;
+    let cookie = set_cookie.split(';').next().unwrap().to_owned();
+    assert_eq!(session.id_from_cookie(cookie).await?, Some(id.clone()));
+
+    let forged = format!("spin-session={id}.AAAA");
+    assert_eq!(session.id_from_cookie(forged).await?, None);
+    Ok(())
+}
@@ -1,22 +1,38 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use spin_factor_fault_injection::{FaultInjector, Interface};
+use spin_factor_policy::{Action, PolicyChecker};
+use spin_factor_request_context::RequestContextHandle;
 use spin_factors::wasmtime::component::Resource;
 use spin_factors::{anyhow, SelfInstanceBuilder};
-use spin_world::spin::sqlite::sqlite as v3;
+use spin_world::spin::sqlite3_0_0::sqlite as v3;
+use spin_world::spin::sqlite4_0_0::{search, sqlite as v4};
 use spin_world::v1::sqlite as v1;
 use spin_world::v2::sqlite as v2;
 use tracing::field::Empty;
 use tracing::{instrument, Level};
 
+use crate::search::SearchIndex;
 use crate::{Connection, ConnectionCreator};
 
 pub struct InstanceState {
     allowed_databases: Arc<HashSet<String>>,
     /// A resource table of connections.
     connections: spin_resource_table::Table<Box<dyn Connection>>,
+    /// A resource table of open search indexes.
+    indexes: spin_resource_table::Table<SearchIndex>,
     /// A map from database label to connection creators.
     connection_creators: HashMap<String, Arc<dyn ConnectionCreator>>,
+    /// A map from database label to the label of the database opened in its
+    /// place.
+    label_overrides: HashMap<String, String>,
+    /// Used to open the databases of the invocation's tenant, if any.
+    request_context: Option<RequestContextHandle>,
+    /// Injects faults into the instance's queries, if configured.
+    fault_injector: Option<FaultInjector>,
+    /// Consulted before the instance opens databases, if configured.
+    policy: Option<PolicyChecker>,
 }
 
 impl InstanceState {
@@ -30,7 +46,47 @@ impl InstanceState {
         Self {
             allowed_databases,
             connections: spin_resource_table::Table::new(256),
+            indexes: spin_resource_table::Table::new(256),
             connection_creators,
+            label_overrides: HashMap::new(),
+            request_context: None,
+            fault_injector: None,
+            policy: None,
+        }
+    }
+
+    /// Opens the database with label `database` in place of each database
+    /// with label `label`.
+    ///
+    /// Which databases may be opened is still decided by their labels in the
+    /// manifest.
+    pub fn set_label_overrides(&mut self, label_overrides: HashMap<String, String>) {
+        self.label_overrides = label_overrides;
+    }
+
+    /// Opens databases on behalf of the tenant of the invocation, if it has
+    /// one.
+    pub fn set_request_context(&mut self, request_context: RequestContextHandle) {
+        self.request_context = Some(request_context);
+    }
+
+    /// Injects the configured faults into opening databases and queries.
+    pub fn set_fault_injector(&mut self, fault_injector: FaultInjector) {
+        self.fault_injector = Some(fault_injector);
+    }
+
+    /// Consults the policy before each database is opened.
+    pub fn set_policy(&mut self, policy: PolicyChecker) {
+        self.policy = Some(policy);
+    }
+
+    async fn inject_fault(&self) -> Result<(), v4::Error> {
+        match &self.fault_injector {
+            Some(injector) => injector
+                .inject(Interface::Sqlite)
+                .await
+                .map_err(|fault| v4::Error::Io(fault.to_string())),
+            None => Ok(()),
         }
     }
 
@@ -38,39 +94,75 @@ impl InstanceState {
     fn get_connection<T: 'static>(
         &self,
         connection: Resource<T>,
-    ) -> Result<&dyn Connection, v3::Error> {
+    ) -> Result<&dyn Connection, v4::Error> {
         self.connections
             .get(connection.rep())
             .map(|conn| conn.as_ref())
-            .ok_or(v3::Error::InvalidConnection)
+            .ok_or(v4::Error::InvalidConnection)
     }
 
-    async fn open_impl<T: 'static>(&mut self, database: String) -> Result<Resource<T>, v3::Error> {
+    async fn open_impl<T: 'static>(&mut self, database: String) -> Result<Resource<T>, v4::Error> {
+        let conn = self.open_connection(database).await?;
+        self.connections
+            .push(conn)
+            .map_err(|()| v4::Error::Io("too many connections opened".to_string()))
+            .map(Resource::new_own)
+    }
+
+    /// Opens a new connection to the database with label `database`, if the
+    /// component may use it.
+    async fn open_connection(&self, database: String) -> Result<Box<dyn Connection>, v4::Error> {
         if !self.allowed_databases.contains(&database) {
-            return Err(v3::Error::AccessDenied);
+            return Err(v4::Error::AccessDenied);
+        }
+        self.inject_fault().await?;
+        let database = self
+            .label_overrides
+            .get(&database)
+            .cloned()
+            .unwrap_or(database);
+        if let Some(policy) = &self.policy {
+            let action = Action::SqliteDatabase {
+                label: database.clone(),
+            };
+            if !policy.allows(action).await {
+                return Err(v4::Error::AccessDenied);
+            }
         }
+        let tenant = self
+            .request_context
+            .as_ref()
+            .and_then(|request_context| request_context.tenant());
+        let database = match &tenant {
+            Some(tenant) => tenant.sqlite_database(&database),
+            None => &database,
+        };
         let conn = self
             .connection_creators
-            .get(&database)
-            .ok_or(v3::Error::NoSuchDatabase)?
-            .create_connection(&database)
+            .get(database)
+            .ok_or(v4::Error::NoSuchDatabase)?
+            .create_connection(database)
             .await?;
         tracing::Span::current().record(
             "sqlite.backend",
             conn.summary().as_deref().unwrap_or("unknown"),
         );
-        self.connections
-            .push(conn)
-            .map_err(|()| v3::Error::Io("too many connections opened".to_string()))
-            .map(Resource::new_own)
+        Ok(conn)
+    }
+
+    fn get_index(&self, index: &Resource<search::Index>) -> Result<&SearchIndex, v4::Error> {
+        self.indexes
+            .get(index.rep())
+            .ok_or(v4::Error::InvalidConnection)
     }
 
     async fn execute_impl<T: 'static>(
         &mut self,
         connection: Resource<T>,
         query: String,
-        parameters: Vec<v3::Value>,
-    ) -> Result<v3::QueryResult, v3::Error> {
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
+        self.inject_fault().await?;
         let conn = self.get_connection(connection)?;
         tracing::Span::current().record(
             "sqlite.backend",
@@ -87,31 +179,31 @@ impl InstanceState {
 
 impl SelfInstanceBuilder for InstanceState {}
 
-impl v3::Host for InstanceState {
-    fn convert_error(&mut self, error: v3::Error) -> anyhow::Result<v3::Error> {
+impl v4::Host for InstanceState {
+    fn convert_error(&mut self, error: v4::Error) -> anyhow::Result<v4::Error> {
         Ok(error)
     }
 }
 
-impl v3::HostConnection for InstanceState {
+impl v4::HostConnection for InstanceState {
     #[instrument(name = "spin_sqlite.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
-    async fn open(&mut self, database: String) -> Result<Resource<v3::Connection>, v3::Error> {
+    async fn open(&mut self, database: String) -> Result<Resource<v4::Connection>, v4::Error> {
         self.open_impl(database).await
     }
 
     #[instrument(name = "spin_sqlite.execute", skip(self, connection, parameters), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", otel.name = query, sqlite.backend = Empty))]
     async fn execute(
         &mut self,
-        connection: Resource<v3::Connection>,
+        connection: Resource<v4::Connection>,
         query: String,
-        parameters: Vec<v3::Value>,
-    ) -> Result<v3::QueryResult, v3::Error> {
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
         self.execute_impl(connection, query, parameters).await
     }
 
     async fn changes(
         &mut self,
-        connection: Resource<v3::Connection>,
+        connection: Resource<v4::Connection>,
     ) -> spin_factors::wasmtime::Result<u64> {
         let conn = match self.get_connection(connection) {
             Ok(c) => c,
@@ -126,7 +218,7 @@ impl v3::HostConnection for InstanceState {
 
     async fn last_insert_rowid(
         &mut self,
-        connection: Resource<v3::Connection>,
+        connection: Resource<v4::Connection>,
     ) -> spin_factors::wasmtime::Result<i64> {
         let conn = match self.get_connection(connection) {
             Ok(c) => c,
@@ -139,12 +231,116 @@ impl v3::HostConnection for InstanceState {
         conn.last_insert_rowid().await.map_err(|e| e.into())
     }
 
+    async fn drop(&mut self, connection: Resource<v4::Connection>) -> anyhow::Result<()> {
+        let _ = self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+impl v3::Host for InstanceState {
+    fn convert_error(&mut self, error: v3::Error) -> anyhow::Result<v3::Error> {
+        Ok(error)
+    }
+}
+
+impl v3::HostConnection for InstanceState {
+    #[instrument(name = "spin_sqlite.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
+    async fn open(&mut self, database: String) -> Result<Resource<v3::Connection>, v3::Error> {
+        self.open_impl(database).await.map_err(to_v3_error)
+    }
+
+    #[instrument(name = "spin_sqlite.execute", skip(self, connection, parameters), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", otel.name = query, sqlite.backend = Empty))]
+    async fn execute(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        query: String,
+        parameters: Vec<v3::Value>,
+    ) -> Result<v3::QueryResult, v3::Error> {
+        self.execute_impl(connection, query, parameters)
+            .await
+            .map_err(to_v3_error)
+    }
+
+    async fn changes(
+        &mut self,
+        connection: Resource<v3::Connection>,
+    ) -> spin_factors::wasmtime::Result<u64> {
+        <Self as v4::HostConnection>::changes(self, Resource::new_borrow(connection.rep())).await
+    }
+
+    async fn last_insert_rowid(
+        &mut self,
+        connection: Resource<v3::Connection>,
+    ) -> spin_factors::wasmtime::Result<i64> {
+        <Self as v4::HostConnection>::last_insert_rowid(
+            self,
+            Resource::new_borrow(connection.rep()),
+        )
+        .await
+    }
+
     async fn drop(&mut self, connection: Resource<v3::Connection>) -> anyhow::Result<()> {
         let _ = self.connections.remove(connection.rep());
         Ok(())
     }
 }
 
+impl search::Host for InstanceState {}
+
+impl search::HostIndex for InstanceState {
+    #[instrument(name = "spin_sqlite.search.open", skip(self, fields), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", sqlite.backend = Empty))]
+    async fn open(
+        &mut self,
+        database: String,
+        name: String,
+        fields: Vec<String>,
+    ) -> Result<Resource<search::Index>, v4::Error> {
+        self.inject_fault().await?;
+        let conn = self.open_connection(database).await?;
+        let index = SearchIndex::open(conn, &name, fields).await?;
+        self.indexes
+            .push(index)
+            .map_err(|()| v4::Error::Io("too many indexes opened".to_string()))
+            .map(Resource::new_own)
+    }
+
+    #[instrument(name = "spin_sqlite.search.add", skip_all, err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite", documents = documents.len()))]
+    async fn add(
+        &mut self,
+        index: Resource<search::Index>,
+        documents: Vec<search::Document>,
+    ) -> Result<(), v4::Error> {
+        self.inject_fault().await?;
+        self.get_index(&index)?.add(documents).await
+    }
+
+    #[instrument(name = "spin_sqlite.search.remove", skip_all, err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite"))]
+    async fn remove(
+        &mut self,
+        index: Resource<search::Index>,
+        ids: Vec<String>,
+    ) -> Result<(), v4::Error> {
+        self.inject_fault().await?;
+        self.get_index(&index)?.remove(ids).await
+    }
+
+    #[instrument(name = "spin_sqlite.search.query", skip(self, index, options), err(level = Level::INFO), fields(otel.kind = "client", db.system = "sqlite"))]
+    async fn query(
+        &mut self,
+        index: Resource<search::Index>,
+        query: String,
+        options: search::QueryOptions,
+    ) -> Result<Vec<search::Hit>, v4::Error> {
+        self.inject_fault().await?;
+        self.get_index(&index)?.query(query, options).await
+    }
+
+    async fn drop(&mut self, index: Resource<search::Index>) -> anyhow::Result<()> {
+        let _ = self.indexes.remove(index.rep());
+        Ok(())
+    }
+}
+
 impl v2::Host for InstanceState {
     fn convert_error(&mut self, error: v2::Error) -> anyhow::Result<v2::Error> {
         Ok(error)
@@ -182,7 +378,7 @@ impl v2::HostConnection for InstanceState {
 
 impl v1::Host for InstanceState {
     async fn open(&mut self, database: String) -> Result<u32, v1::Error> {
-        let result = <Self as v3::HostConnection>::open(self, database).await;
+        let result = <Self as v4::HostConnection>::open(self, database).await;
         result.map_err(to_legacy_error).map(|s| s.rep())
     }
 
@@ -193,7 +389,7 @@ impl v1::Host for InstanceState {
         parameters: Vec<spin_world::v1::sqlite::Value>,
     ) -> Result<spin_world::v1::sqlite::QueryResult, v1::Error> {
         let this = Resource::new_borrow(connection);
-        let result = <Self as v3::HostConnection>::execute(
+        let result = <Self as v4::HostConnection>::execute(
             self,
             this,
             query,
@@ -212,88 +408,101 @@ impl v1::Host for InstanceState {
     }
 }
 
-fn to_v2_error(error: v3::Error) -> v2::Error {
+fn to_v3_error(error: v4::Error) -> v3::Error {
+    match error {
+        v4::Error::NoSuchDatabase => v3::Error::NoSuchDatabase,
+        v4::Error::AccessDenied => v3::Error::AccessDenied,
+        v4::Error::InvalidConnection => v3::Error::InvalidConnection,
+        v4::Error::DatabaseFull => v3::Error::DatabaseFull,
+        v4::Error::QueryFailed(e) => v3::Error::Io(e.message),
+        v4::Error::Io(s) => v3::Error::Io(s),
+    }
+}
+
+fn to_v2_error(error: v4::Error) -> v2::Error {
     match error {
-        v3::Error::NoSuchDatabase => v2::Error::NoSuchDatabase,
-        v3::Error::AccessDenied => v2::Error::AccessDenied,
-        v3::Error::InvalidConnection => v2::Error::InvalidConnection,
-        v3::Error::DatabaseFull => v2::Error::DatabaseFull,
-        v3::Error::Io(s) => v2::Error::Io(s),
+        v4::Error::NoSuchDatabase => v2::Error::NoSuchDatabase,
+        v4::Error::AccessDenied => v2::Error::AccessDenied,
+        v4::Error::InvalidConnection => v2::Error::InvalidConnection,
+        v4::Error::DatabaseFull => v2::Error::DatabaseFull,
+        v4::Error::QueryFailed(e) => v2::Error::Io(e.message),
+        v4::Error::Io(s) => v2::Error::Io(s),
     }
 }
 
-fn to_legacy_error(error: v3::Error) -> v1::Error {
+fn to_legacy_error(error: v4::Error) -> v1::Error {
     match error {
-        v3::Error::NoSuchDatabase => v1::Error::NoSuchDatabase,
-        v3::Error::AccessDenied => v1::Error::AccessDenied,
-        v3::Error::InvalidConnection => v1::Error::InvalidConnection,
-        v3::Error::DatabaseFull => v1::Error::DatabaseFull,
-        v3::Error::Io(s) => v1::Error::Io(s),
+        v4::Error::NoSuchDatabase => v1::Error::NoSuchDatabase,
+        v4::Error::AccessDenied => v1::Error::AccessDenied,
+        v4::Error::InvalidConnection => v1::Error::InvalidConnection,
+        v4::Error::DatabaseFull => v1::Error::DatabaseFull,
+        v4::Error::QueryFailed(e) => v1::Error::Io(e.message),
+        v4::Error::Io(s) => v1::Error::Io(s),
     }
 }
 
-fn to_v2_query_result(result: v3::QueryResult) -> v2::QueryResult {
+fn to_v2_query_result(result: v4::QueryResult) -> v2::QueryResult {
     v2::QueryResult {
         columns: result.columns,
         rows: result.rows.into_iter().map(to_v2_row_result).collect(),
     }
 }
 
-fn to_legacy_query_result(result: v3::QueryResult) -> v1::QueryResult {
+fn to_legacy_query_result(result: v4::QueryResult) -> v1::QueryResult {
     v1::QueryResult {
         columns: result.columns,
         rows: result.rows.into_iter().map(to_legacy_row_result).collect(),
     }
 }
 
-fn to_v2_row_result(result: v3::RowResult) -> v2::RowResult {
+fn to_v2_row_result(result: v4::RowResult) -> v2::RowResult {
     v2::RowResult {
         values: result.values.into_iter().map(to_v2_value).collect(),
     }
 }
 
-fn to_legacy_row_result(result: v3::RowResult) -> v1::RowResult {
+fn to_legacy_row_result(result: v4::RowResult) -> v1::RowResult {
     v1::RowResult {
         values: result.values.into_iter().map(to_legacy_value).collect(),
     }
 }
 
-fn to_v2_value(value: v3::Value) -> v2::Value {
+fn to_v2_value(value: v4::Value) -> v2::Value {
     match value {
-        v3::Value::Integer(i) => v2::Value::Integer(i),
-        v3::Value::Real(r) => v2::Value::Real(r),
-        v3::Value::Text(t) => v2::Value::Text(t),
-        v3::Value::Blob(b) => v2::Value::Blob(b),
-        v3::Value::Null => v2::Value::Null,
+        v4::Value::Integer(i) => v2::Value::Integer(i),
+        v4::Value::Real(r) => v2::Value::Real(r),
+        v4::Value::Text(t) => v2::Value::Text(t),
+        v4::Value::Blob(b) => v2::Value::Blob(b),
+        v4::Value::Null => v2::Value::Null,
     }
 }
 
-fn to_legacy_value(value: v3::Value) -> v1::Value {
+fn to_legacy_value(value: v4::Value) -> v1::Value {
     match value {
-        v3::Value::Integer(i) => v1::Value::Integer(i),
-        v3::Value::Real(r) => v1::Value::Real(r),
-        v3::Value::Text(t) => v1::Value::Text(t),
-        v3::Value::Blob(b) => v1::Value::Blob(b),
-        v3::Value::Null => v1::Value::Null,
+        v4::Value::Integer(i) => v1::Value::Integer(i),
+        v4::Value::Real(r) => v1::Value::Real(r),
+        v4::Value::Text(t) => v1::Value::Text(t),
+        v4::Value::Blob(b) => v1::Value::Blob(b),
+        v4::Value::Null => v1::Value::Null,
     }
 }
 
-fn from_v2_value(value: v2::Value) -> v3::Value {
+fn from_v2_value(value: v2::Value) -> v4::Value {
     match value {
-        v2::Value::Integer(i) => v3::Value::Integer(i),
-        v2::Value::Real(r) => v3::Value::Real(r),
-        v2::Value::Text(t) => v3::Value::Text(t),
-        v2::Value::Blob(b) => v3::Value::Blob(b),
-        v2::Value::Null => v3::Value::Null,
+        v2::Value::Integer(i) => v4::Value::Integer(i),
+        v2::Value::Real(r) => v4::Value::Real(r),
+        v2::Value::Text(t) => v4::Value::Text(t),
+        v2::Value::Blob(b) => v4::Value::Blob(b),
+        v2::Value::Null => v4::Value::Null,
     }
 }
 
-fn from_legacy_value(value: v1::Value) -> v3::Value {
+fn from_legacy_value(value: v1::Value) -> v4::Value {
     match value {
-        v1::Value::Integer(i) => v3::Value::Integer(i),
-        v1::Value::Real(r) => v3::Value::Real(r),
-        v1::Value::Text(t) => v3::Value::Text(t),
-        v1::Value::Blob(b) => v3::Value::Blob(b),
-        v1::Value::Null => v3::Value::Null,
+        v1::Value::Integer(i) => v4::Value::Integer(i),
+        v1::Value::Real(r) => v4::Value::Real(r),
+        v1::Value::Text(t) => v4::Value::Text(t),
+        v1::Value::Blob(b) => v4::Value::Blob(b),
+        v1::Value::Null => v4::Value::Null,
     }
 }
@@ -1,5 +1,6 @@
 mod host;
 pub mod runtime_config;
+mod search;
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -7,13 +8,18 @@ use std::sync::Arc;
 use host::InstanceState;
 
 use async_trait::async_trait;
+use spin_factor_fault_injection::FaultInjectionFactor;
+use spin_factor_policy::PolicyFactor;
+use spin_factor_request_context::RequestContextFactor;
 use spin_factors::{anyhow, Factor};
 use spin_locked_app::MetadataKey;
-use spin_world::spin::sqlite::sqlite as v3;
+use spin_world::spin::sqlite3_0_0::sqlite as v3;
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
 use spin_world::v1::sqlite as v1;
 use spin_world::v2::sqlite as v2;
 
 pub use runtime_config::RuntimeConfig;
+pub use search::MAX_SEARCH_LIMIT;
 
 #[derive(Default)]
 pub struct SqliteFactor {
@@ -36,6 +42,8 @@ impl Factor for SqliteFactor {
         ctx.link_bindings(v1::add_to_linker)?;
         ctx.link_bindings(v2::add_to_linker)?;
         ctx.link_bindings(v3::add_to_linker)?;
+        ctx.link_bindings(v4::add_to_linker)?;
+        ctx.link_bindings(spin_world::spin::sqlite4_0_0::search::add_to_linker)?;
         Ok(())
     }
 
@@ -43,10 +51,10 @@ impl Factor for SqliteFactor {
         &self,
         mut ctx: spin_factors::ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        let connection_creators = ctx
-            .take_runtime_config()
-            .unwrap_or_default()
-            .connection_creators;
+        let RuntimeConfig {
+            connection_creators,
+            component_labels,
+        } = ctx.take_runtime_config().unwrap_or_default();
 
         let allowed_databases = ctx
             .app()
@@ -65,16 +73,47 @@ impl Factor for SqliteFactor {
             })
             .collect::<anyhow::Result<HashMap<_, _>>>()?;
 
-        ensure_allowed_databases_are_configured(&allowed_databases, |label| {
+        ensure_allowed_databases_are_configured(&allowed_databases, |component_id, label| {
+            let label = component_labels
+                .get(component_id)
+                .and_then(|labels| labels.get(label))
+                .map_or(label, String::as_str);
             connection_creators.contains_key(label)
         })?;
+        for (component_id, labels) in &component_labels {
+            let Some(allowed) = allowed_databases.get(component_id) else {
+                tracing::warn!(
+                    "Runtime config overrides SQLite database labels for unknown component {component_id:?}"
+                );
+                continue;
+            };
+            for label in labels.keys() {
+                if !allowed.contains(label) {
+                    tracing::warn!(
+                        "Runtime config overrides SQLite database label {label:?} for component {component_id:?}, which doesn't use it"
+                    );
+                }
+            }
+        }
+
+        if let Ok(request_context) = ctx.app_state::<RequestContextFactor>() {
+            for (tenant_id, tenant) in request_context.tenants() {
+                for label in tenant.sqlite_databases.values() {
+                    anyhow::ensure!(
+                        connection_creators.contains_key(label),
+                        "unknown SQLite database label {label:?} for tenant {tenant_id:?}"
+                    );
+                }
+            }
+        }
 
-        Ok(AppState::new(allowed_databases, connection_creators))
+        Ok(AppState::new(allowed_databases, connection_creators)
+            .with_component_labels(component_labels))
     }
 
     fn prepare<T: spin_factors::RuntimeFactors>(
         &self,
-        ctx: spin_factors::PrepareContext<T, Self>,
+        mut ctx: spin_factors::PrepareContext<T, Self>,
     ) -> spin_factors::anyhow::Result<Self::InstanceBuilder> {
         let allowed_databases = ctx
             .app_state()
@@ -82,22 +121,45 @@ impl Factor for SqliteFactor {
             .get(ctx.app_component().id())
             .cloned()
             .unwrap_or_default();
-        Ok(InstanceState::new(
+        let mut state = InstanceState::new(
             allowed_databases,
             ctx.app_state().connection_creators.clone(),
-        ))
+        );
+        if let Some(labels) = ctx
+            .app_state()
+            .component_labels
+            .get(ctx.app_component().id())
+        {
+            state.set_label_overrides(labels.clone());
+        }
+        match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(request_context) => state.set_request_context(request_context.handle()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        match ctx.instance_builder::<FaultInjectionFactor>() {
+            Ok(fault_injection) => state.set_fault_injector(fault_injection.injector()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        match ctx.instance_builder::<PolicyFactor>() {
+            Ok(policy) => state.set_policy(policy.checker()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(state)
     }
 }
 
 /// Ensure that all the databases in the allowed databases list for each component are configured
 fn ensure_allowed_databases_are_configured(
     allowed_databases: &HashMap<String, Arc<HashSet<String>>>,
-    is_configured: impl Fn(&str) -> bool,
+    is_configured: impl Fn(&str, &str) -> bool,
 ) -> anyhow::Result<()> {
     let mut errors = Vec::new();
     for (component_id, allowed_dbs) in allowed_databases {
         for allowed in allowed_dbs.iter() {
-            if !is_configured(allowed) {
+            if !is_configured(component_id, allowed) {
                 errors.push(format!(
                     "- Component {component_id} uses database '{allowed}'"
                 ));
@@ -131,6 +193,9 @@ pub struct AppState {
     allowed_databases: HashMap<String, Arc<HashSet<String>>>,
     /// A mapping from database label to a connection creator.
     connection_creators: HashMap<String, Arc<dyn ConnectionCreator>>,
+    /// A map from component id to the database labels it uses in place of
+    /// the labels in the manifest.
+    component_labels: HashMap<String, HashMap<String, String>>,
 }
 
 impl AppState {
@@ -142,16 +207,30 @@ impl AppState {
         Self {
             allowed_databases,
             connection_creators,
+            component_labels: HashMap::new(),
         }
     }
 
+    /// Makes components use other databases in place of the labels in the
+    /// manifest.
+    ///
+    /// Takes a map from component id to a map from manifest label to the
+    /// label of the database the component uses.
+    pub fn with_component_labels(
+        mut self,
+        component_labels: HashMap<String, HashMap<String, String>>,
+    ) -> Self {
+        self.component_labels = component_labels;
+        self
+    }
+
     /// Get a connection for a given database label.
     ///
     /// Returns `None` if there is no connection creator for the given label.
     pub async fn get_connection(
         &self,
         label: &str,
-    ) -> Option<Result<Box<dyn Connection>, v3::Error>> {
+    ) -> Option<Result<Box<dyn Connection>, v4::Error>> {
         let connection = self
             .connection_creators
             .get(label)?
@@ -160,11 +239,39 @@ impl AppState {
         Some(connection)
     }
 
+    /// Returns the labels of the databases used by any component, sorted.
+    pub fn used_databases(&self) -> Vec<String> {
+        let mut labels = self
+            .allowed_databases
+            .iter()
+            .flat_map(|(component_id, databases)| {
+                let labels = self.component_labels.get(component_id);
+                databases.iter().map(move |database| {
+                    labels
+                        .and_then(|labels| labels.get(database))
+                        .unwrap_or(database)
+                        .clone()
+                })
+            })
+            .collect::<Vec<_>>();
+        labels.sort();
+        labels.dedup();
+        labels
+    }
+
     /// Returns true if the given database label is used by any component.
     pub fn database_is_used(&self, label: &str) -> bool {
         self.allowed_databases
-            .values()
-            .any(|stores| stores.contains(label))
+            .iter()
+            .any(|(component_id, databases)| {
+                let labels = self.component_labels.get(component_id);
+                databases.iter().any(|database| {
+                    labels
+                        .and_then(|labels| labels.get(database))
+                        .unwrap_or(database)
+                        == label
+                })
+            })
     }
 }
 
@@ -177,7 +284,7 @@ pub trait ConnectionCreator: Send + Sync {
     async fn create_connection(
         &self,
         label: &str,
-    ) -> Result<Box<dyn Connection + 'static>, v3::Error>;
+    ) -> Result<Box<dyn Connection + 'static>, v4::Error>;
 }
 
 #[async_trait]
@@ -188,9 +295,9 @@ where
     async fn create_connection(
         &self,
         label: &str,
-    ) -> Result<Box<dyn Connection + 'static>, v3::Error> {
+    ) -> Result<Box<dyn Connection + 'static>, v4::Error> {
         let _ = label;
-        (self)().map_err(|_| v3::Error::InvalidConnection)
+        (self)().map_err(|_| v4::Error::InvalidConnection)
     }
 }
 
@@ -200,14 +307,14 @@ pub trait Connection: Send + Sync {
     async fn query(
         &self,
         query: &str,
-        parameters: Vec<v3::Value>,
-    ) -> Result<v3::QueryResult, v3::Error>;
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error>;
 
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()>;
 
-    async fn changes(&self) -> Result<u64, v3::Error>;
+    async fn changes(&self) -> Result<u64, v4::Error>;
 
-    async fn last_insert_rowid(&self) -> Result<i64, v3::Error>;
+    async fn last_insert_rowid(&self) -> Result<i64, v4::Error>;
 
     /// A human-readable summary of the connection's configuration
     ///
@@ -8,4 +8,7 @@ use crate::ConnectionCreator;
 #[derive(Default)]
 pub struct RuntimeConfig {
     pub connection_creators: HashMap<String, Arc<dyn ConnectionCreator>>,
+    /// Maps component IDs to the database labels they use in place of the
+    /// labels in the manifest.
+    pub component_labels: HashMap<String, HashMap<String, String>>,
 }
@@ -0,0 +1,207 @@
+use spin_world::spin::sqlite4_0_0::search::{Document, Hit, QueryOptions};
+use spin_world::spin::sqlite4_0_0::sqlite::{Error, Value};
+
+use crate::Connection;
+
+/// The maximum number of hits a single search may return.
+pub const MAX_SEARCH_LIMIT: u32 = 1000;
+
+/// The prefix of the names of the FTS5 tables backing indexes, so that they
+/// don't collide with an app's own tables.
+const TABLE_PREFIX: &str = "spin_search_";
+/// The column of every index table holding document IDs.
+const ID_COLUMN: &str = "spin_id";
+/// Names FTS5 reserves for hidden columns, which can't be field names.
+const RESERVED_COLUMNS: &[&str] = &[ID_COLUMN, "rank", "rowid"];
+/// The number of tokens in each highlight.
+const HIGHLIGHT_TOKENS: u32 = 16;
+
+/// A full-text index backed by a SQLite FTS5 table.
+pub struct SearchIndex {
+    connection: Box<dyn Connection>,
+    table: String,
+    fields: Vec<String>,
+}
+
+impl SearchIndex {
+    /// Opens the index `name` in the database of `connection`, creating it
+    /// with `fields` if it doesn't exist.
+    pub async fn open(
+        connection: Box<dyn Connection>,
+        name: &str,
+        fields: Vec<String>,
+    ) -> Result<Self, Error> {
+        check_name("index", name)?;
+        if fields.is_empty() {
+            return Err(Error::Io("an index needs at least one field".into()));
+        }
+        for (i, field) in fields.iter().enumerate() {
+            check_name("field", field)?;
+            if RESERVED_COLUMNS.contains(&field.to_ascii_lowercase().as_str()) {
+                return Err(Error::Io(format!(
+                    "{field:?} can't be used as a field name"
+                )));
+            }
+            if fields[..i].iter().any(|f| f.eq_ignore_ascii_case(field)) {
+                return Err(Error::Io(format!(
+                    "field {field:?} is listed more than once"
+                )));
+            }
+        }
+
+        let table = format!("{TABLE_PREFIX}{name}");
+        let columns = fields
+            .iter()
+            .map(|field| format!("\"{field}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        connection
+            .query(
+                &format!(
+                    "CREATE VIRTUAL TABLE IF NOT EXISTS {table} USING fts5({ID_COLUMN} UNINDEXED, {columns}, tokenize = 'porter unicode61')"
+                ),
+                vec![],
+            )
+            .await?;
+        let existing = connection
+            .query(&format!("SELECT * FROM {table} LIMIT 0"), vec![])
+            .await?
+            .columns;
+        if existing.get(1..) != Some(&fields[..]) {
+            let existing = existing.get(1..).unwrap_or_default().join(", ");
+            return Err(Error::Io(format!(
+                "index {name:?} already exists with the fields {existing}"
+            )));
+        }
+
+        Ok(Self {
+            connection,
+            table,
+            fields,
+        })
+    }
+
+    /// Adds documents to the index, replacing any with the same IDs.
+    pub async fn add(&self, documents: Vec<Document>) -> Result<(), Error> {
+        let mut rows = Vec::with_capacity(documents.len());
+        for document in documents {
+            let mut values = vec![Value::Null; self.fields.len()];
+            for (field, text) in document.fields {
+                let Some(i) = self.fields.iter().position(|f| f == &field) else {
+                    return Err(Error::Io(format!(
+                        "document {:?} has the field {field:?}, which isn't one of the index's fields",
+                        document.id
+                    )));
+                };
+                values[i] = Value::Text(text);
+            }
+            rows.push((document.id, values));
+        }
+
+        let placeholders = vec!["?"; self.fields.len() + 1].join(", ");
+        let insert = format!("INSERT INTO {} VALUES ({placeholders})", self.table);
+        for (id, values) in rows {
+            // FTS5 tables have no unique constraints, so replacing a document
+            // means deleting it first.
+            self.remove_one(&id).await?;
+            let mut parameters = vec![Value::Text(id)];
+            parameters.extend(values);
+            self.connection.query(&insert, parameters).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the documents with the given IDs from the index.
+    pub async fn remove(&self, ids: Vec<String>) -> Result<(), Error> {
+        for id in ids {
+            self.remove_one(&id).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove_one(&self, id: &str) -> Result<(), Error> {
+        self.connection
+            .query(
+                &format!("DELETE FROM {} WHERE {ID_COLUMN} = ?", self.table),
+                vec![Value::Text(id.to_owned())],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Searches the index, most relevant documents first.
+    pub async fn query(&self, query: String, options: QueryOptions) -> Result<Vec<Hit>, Error> {
+        let limit = options.limit.min(MAX_SEARCH_LIMIT);
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        let result = self
+            .connection
+            .query(
+                &self.query_sql(),
+                vec![
+                    Value::Text(query),
+                    Value::Text(options.highlight_start),
+                    Value::Text(options.highlight_end),
+                    Value::Integer(limit.into()),
+                    Value::Integer(options.offset.into()),
+                ],
+            )
+            .await?;
+        result
+            .rows
+            .into_iter()
+            .map(|row| {
+                let mut values = row.values.into_iter();
+                let (Some(Value::Text(id)), Some(Value::Real(rank))) =
+                    (values.next(), values.next())
+                else {
+                    return Err(Error::Io("unexpected search result".into()));
+                };
+                let highlights = self
+                    .fields
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(field, value)| match value {
+                        Value::Text(text) if !text.is_empty() => Some((field.clone(), text)),
+                        _ => None,
+                    })
+                    .collect();
+                Ok(Hit {
+                    id,
+                    // FTS5's BM25 ranks are lower for more relevant documents.
+                    score: -rank,
+                    highlights,
+                })
+            })
+            .collect()
+    }
+
+    /// The SQL for a search, taking the query, the highlight markers, the
+    /// limit and the offset as parameters.
+    fn query_sql(&self) -> String {
+        let table = &self.table;
+        let highlights: String = (1..=self.fields.len())
+            .map(|column| format!(", snippet({table}, {column}, ?2, ?3, '…', {HIGHLIGHT_TOKENS})"))
+            .collect();
+        format!(
+            "SELECT {ID_COLUMN}, bm25({table}){highlights} FROM {table} WHERE {table} MATCH ?1 ORDER BY rank LIMIT ?4 OFFSET ?5"
+        )
+    }
+}
+
+/// Checks that an index or field name can be used unquoted in SQL.
+fn check_name(kind: &str, name: &str) -> Result<(), Error> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Io(format!(
+            "invalid {kind} name {name:?}: only letters, digits and underscores are allowed"
+        )))
+    }
+}
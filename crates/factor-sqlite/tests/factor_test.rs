@@ -1,16 +1,23 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
+use search::HostIndex as _;
 use spin_factor_sqlite::{RuntimeConfig, SqliteFactor};
 use spin_factors::{
     anyhow::{self, bail, Context as _},
+    wasmtime::component::Resource,
     RuntimeFactors,
 };
 use spin_factors_test::{toml, TestEnvironment};
-use spin_world::{async_trait, spin::sqlite::sqlite as v3, v2::sqlite as v2};
-use v2::HostConnection as _;
+use spin_world::{
+    async_trait,
+    spin::sql::types::{DatabaseError, ErrorKind},
+    spin::sqlite3_0_0::sqlite as v3,
+    spin::sqlite4_0_0::{search, sqlite as v4},
+    v2::sqlite as v2,
+};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
@@ -54,7 +61,7 @@ async fn errors_when_database_not_allowed() -> anyhow::Result<()> {
         .context("build_instance_state failed")?;
 
     assert!(matches!(
-        state.sqlite.open("foo".into()).await,
+        v2::HostConnection::open(&mut state.sqlite, "foo".into()).await,
         Err(spin_world::v2::sqlite::Error::AccessDenied)
     ));
 
@@ -71,6 +78,7 @@ async fn it_works_when_database_is_configured() -> anyhow::Result<()> {
     let runtime_config = TestFactorsRuntimeConfig {
         sqlite: Some(RuntimeConfig {
             connection_creators,
+            ..Default::default()
         }),
     };
     let env = TestEnvironment::new(factors)
@@ -91,10 +99,274 @@ async fn it_works_when_database_is_configured() -> anyhow::Result<()> {
         &["foo".into()].into_iter().collect::<HashSet<_>>()
     );
 
-    assert!(state.sqlite.open("foo".into()).await.is_ok());
+    assert!(v2::HostConnection::open(&mut state.sqlite, "foo".into())
+        .await
+        .is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_failures_are_reported_as_io_errors_through_v3() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        sqlite: SqliteFactor::new(),
+    };
+    let mut connection_creators = HashMap::new();
+    connection_creators.insert("foo".to_owned(), Arc::new(MockConnectionCreator) as _);
+    let runtime_config = TestFactorsRuntimeConfig {
+        sqlite: Some(RuntimeConfig {
+            connection_creators,
+            ..Default::default()
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            sqlite_databases = ["foo"]
+        })
+        .runtime_config(runtime_config)?;
+    let mut state = env.build_instance_state().await?;
+
+    let connection = v4::HostConnection::open(&mut state.sqlite, "foo".into()).await?;
+    let err = v4::HostConnection::execute(&mut state.sqlite, connection, "SELEC".into(), vec![])
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        v4::Error::QueryFailed(DatabaseError {
+            kind: ErrorKind::SyntaxError,
+            ..
+        })
+    ));
+
+    let connection = v3::HostConnection::open(&mut state.sqlite, "foo".into()).await?;
+    let err = v3::HostConnection::execute(&mut state.sqlite, connection, "SELEC".into(), vec![])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, v3::Error::Io(message) if message == "Mock connection"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn components_can_use_other_databases_in_place_of_labels() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        sqlite: SqliteFactor::new(),
+    };
+    let mut connection_creators = HashMap::new();
+    connection_creators.insert("events".to_owned(), Arc::new(MockConnectionCreator) as _);
+    let runtime_config = TestFactorsRuntimeConfig {
+        sqlite: Some(RuntimeConfig {
+            connection_creators,
+            component_labels: HashMap::from([(
+                "test-component".to_owned(),
+                HashMap::from([("default".to_owned(), "events".to_owned())]),
+            )]),
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            sqlite_databases = ["default"]
+        })
+        .runtime_config(runtime_config)?;
+
+    let mut state = env
+        .build_instance_state()
+        .await
+        .context("build_instance_state failed")?;
+
+    assert!(
+        v3::HostConnection::open(&mut state.sqlite, "default".into())
+            .await
+            .is_ok()
+    );
+    assert!(matches!(
+        v3::HostConnection::open(&mut state.sqlite, "events".into()).await,
+        Err(v3::Error::AccessDenied)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_indexes_rank_and_highlight_documents() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        sqlite: SqliteFactor::new(),
+    };
+    let connection = Arc::new(Mutex::new(rusqlite::Connection::open_in_memory()?));
+    let mut connection_creators = HashMap::new();
+    connection_creators.insert(
+        "default".to_owned(),
+        Arc::new(InMemoryConnectionCreator(connection)) as _,
+    );
+    let runtime_config = TestFactorsRuntimeConfig {
+        sqlite: Some(RuntimeConfig {
+            connection_creators,
+            ..Default::default()
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            sqlite_databases = ["default"]
+        })
+        .runtime_config(runtime_config)?;
+    let mut state = env.build_instance_state().await?;
+
+    let fields = vec!["title".to_owned(), "body".to_owned()];
+    let index =
+        search::HostIndex::open(&mut state.sqlite, "default".into(), "docs".into(), fields).await?;
+    let document = |id: &str, title: &str, body: &str| search::Document {
+        id: id.into(),
+        fields: vec![("title".into(), title.into()), ("body".into(), body.into())],
+    };
+    state
+        .sqlite
+        .add(
+            Resource::new_borrow(index.rep()),
+            vec![
+                document("a", "Gardening", "Growing tomatoes in pots"),
+                document("b", "Wasm", "Running wasm components with wasm tooling"),
+                document("c", "Cooking", "A recipe using wasm-grown tomatoes"),
+            ],
+        )
+        .await?;
+    // Replacing a document doesn't duplicate it
+    state
+        .sqlite
+        .add(
+            Resource::new_borrow(index.rep()),
+            vec![document("a", "Gardening", "Growing peppers in pots")],
+        )
+        .await?;
+
+    let options = search::QueryOptions {
+        limit: 10,
+        offset: 0,
+        highlight_start: "[".into(),
+        highlight_end: "]".into(),
+    };
+    let hits = state
+        .sqlite
+        .query(
+            Resource::new_borrow(index.rep()),
+            "wasm".into(),
+            options.clone(),
+        )
+        .await?;
+    let ids: Vec<_> = hits.iter().map(|hit| hit.id.as_str()).collect();
+    assert_eq!(vec!["b", "c"], ids);
+    assert!(hits[0].score > hits[1].score);
+    assert!(hits[0]
+        .highlights
+        .contains(&("title".to_owned(), "[Wasm]".to_owned())));
+
+    let hits = state
+        .sqlite
+        .query(
+            Resource::new_borrow(index.rep()),
+            "tomatoes".into(),
+            options.clone(),
+        )
+        .await?;
+    assert_eq!(1, hits.len());
+    assert_eq!("c", hits[0].id);
+
+    state
+        .sqlite
+        .remove(Resource::new_borrow(index.rep()), vec!["b".into()])
+        .await?;
+    let hits = state
+        .sqlite
+        .query(Resource::new_borrow(index.rep()), "wasm".into(), options)
+        .await?;
+    assert_eq!(1, hits.len());
+
+    // Reopening with other fields fails
+    let fields = vec!["title".to_owned()];
+    assert!(
+        search::HostIndex::open(&mut state.sqlite, "default".into(), "docs".into(), fields)
+            .await
+            .is_err()
+    );
     Ok(())
 }
 
+/// A connection creator whose connections share one in-memory database.
+struct InMemoryConnectionCreator(Arc<Mutex<rusqlite::Connection>>);
+
+#[async_trait]
+impl spin_factor_sqlite::ConnectionCreator for InMemoryConnectionCreator {
+    async fn create_connection(
+        &self,
+        label: &str,
+    ) -> Result<Box<dyn spin_factor_sqlite::Connection + 'static>, v4::Error> {
+        let _ = label;
+        Ok(Box::new(InMemoryConnection(self.0.clone())))
+    }
+}
+
+/// A connection to an in-memory database which supports plain queries only.
+struct InMemoryConnection(Arc<Mutex<rusqlite::Connection>>);
+
+#[async_trait]
+impl spin_factor_sqlite::Connection for InMemoryConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
+        let io = |e: rusqlite::Error| v4::Error::Io(e.to_string());
+        let connection = self.0.lock().unwrap();
+        let mut statement = connection.prepare(query).map_err(io)?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect();
+        let column_count = columns.len();
+        let parameters = parameters.into_iter().map(|value| match value {
+            v4::Value::Integer(i) => rusqlite::types::Value::Integer(i),
+            v4::Value::Real(r) => rusqlite::types::Value::Real(r),
+            v4::Value::Text(t) => rusqlite::types::Value::Text(t),
+            v4::Value::Blob(b) => rusqlite::types::Value::Blob(b),
+            v4::Value::Null => rusqlite::types::Value::Null,
+        });
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(parameters), |row| {
+                let values = (0..column_count)
+                    .map(|i| {
+                        Ok(match row.get::<_, rusqlite::types::Value>(i)? {
+                            rusqlite::types::Value::Integer(i) => v4::Value::Integer(i),
+                            rusqlite::types::Value::Real(r) => v4::Value::Real(r),
+                            rusqlite::types::Value::Text(t) => v4::Value::Text(t),
+                            rusqlite::types::Value::Blob(b) => v4::Value::Blob(b),
+                            rusqlite::types::Value::Null => v4::Value::Null,
+                        })
+                    })
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(v4::RowResult { values })
+            })
+            .map_err(io)?
+            .collect::<rusqlite::Result<_>>()
+            .map_err(io)?;
+        Ok(v4::QueryResult { columns, rows })
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        Ok(self.0.lock().unwrap().execute_batch(statements)?)
+    }
+
+    async fn changes(&self) -> Result<u64, v4::Error> {
+        Ok(self.0.lock().unwrap().changes())
+    }
+
+    async fn last_insert_rowid(&self) -> Result<i64, v4::Error> {
+        Ok(self.0.lock().unwrap().last_insert_rowid())
+    }
+}
+
 /// A connection creator that returns a mock connection.
 struct MockConnectionCreator;
 
@@ -103,7 +375,7 @@ impl spin_factor_sqlite::ConnectionCreator for MockConnectionCreator {
     async fn create_connection(
         &self,
         label: &str,
-    ) -> Result<Box<dyn spin_factor_sqlite::Connection + 'static>, v3::Error> {
+    ) -> Result<Box<dyn spin_factor_sqlite::Connection + 'static>, v4::Error> {
         let _ = label;
         Ok(Box::new(MockConnection))
     }
@@ -117,10 +389,14 @@ impl spin_factor_sqlite::Connection for MockConnection {
     async fn query(
         &self,
         query: &str,
-        parameters: Vec<v3::Value>,
-    ) -> Result<v3::QueryResult, v3::Error> {
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
         let _ = (query, parameters);
-        Err(v3::Error::Io("Mock connection".into()))
+        Err(v4::Error::QueryFailed(DatabaseError {
+            kind: ErrorKind::SyntaxError,
+            code: Some("1".into()),
+            message: "Mock connection".into(),
+        }))
     }
 
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
@@ -128,11 +404,11 @@ impl spin_factor_sqlite::Connection for MockConnection {
         bail!("Mock connection")
     }
 
-    async fn changes(&self) -> Result<u64, v3::Error> {
+    async fn changes(&self) -> Result<u64, v4::Error> {
         Ok(123)
     }
 
-    async fn last_insert_rowid(&self) -> Result<i64, v3::Error> {
+    async fn last_insert_rowid(&self) -> Result<i64, v4::Error> {
         Ok(456)
     }
 }
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use spin_world::spin::templates::templates::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::Templates;
+
+pub struct InstanceState {
+    templates: Option<Arc<Templates>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(templates: Option<Arc<Templates>>) -> Self {
+        Self { templates }
+    }
+}
+
+impl templates::Host for InstanceState {
+    #[instrument(name = "spin_templates.render", skip(self, data), err(level = Level::INFO))]
+    async fn render(&mut self, name: String, data: String) -> Result<String, Error> {
+        let template = self
+            .templates
+            .as_ref()
+            .and_then(|templates| templates.get(&name))
+            .ok_or_else(|| Error::NoSuchTemplate(name.clone()))?;
+        let data: serde_json::Value =
+            serde_json::from_str(&data).map_err(|e| Error::InvalidData(e.to_string()))?;
+        if !data.is_object() {
+            return Err(Error::InvalidData("data must be a JSON object".into()));
+        }
+        let globals =
+            liquid::model::to_object(&data).map_err(|e| Error::InvalidData(e.to_string()))?;
+        template
+            .render(&globals)
+            .map_err(|e| Error::RenderFailed(e.to_string()))
+    }
+
+    async fn list_templates(&mut self) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .templates
+            .iter()
+            .flat_map(|templates| templates.names())
+            .map(str::to_owned)
+            .collect())
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,104 @@
+mod host;
+mod templates;
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use host::InstanceState;
+pub use templates::Templates;
+
+/// Metadata key for the directory of a component's files holding its
+/// templates.
+pub const TEMPLATES_KEY: MetadataKey<String> = MetadataKey::new("templates");
+
+/// A factor that renders the templates mounted with a component.
+///
+/// Templates are parsed once, when the app is configured, rather than by the
+/// guest on every request.
+#[derive(Default)]
+pub struct TemplatesFactor {
+    _priv: (),
+}
+
+impl TemplatesFactor {
+    /// Create a new TemplatesFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for TemplatesFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::templates::templates::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let mut component_templates = HashMap::new();
+        for component in ctx.app().components() {
+            let Some(dir) = component.get_metadata(TEMPLATES_KEY)? else {
+                continue;
+            };
+            let templates =
+                Templates::load_from_files(component.files(), &dir).with_context(|| {
+                    format!(
+                        "failed to load templates for component {:?}",
+                        component.id()
+                    )
+                })?;
+            component_templates.insert(component.id().to_string(), Arc::new(templates));
+        }
+        Ok(AppState {
+            component_templates,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let templates = ctx
+            .app_state()
+            .component_templates
+            .get(ctx.app_component().id())
+            .cloned();
+        Ok(InstanceBuilder { templates })
+    }
+}
+
+pub struct AppState {
+    /// The parsed templates of each component which has any, keyed by
+    /// component ID.
+    component_templates: HashMap<String, Arc<Templates>>,
+}
+
+impl AppState {
+    /// Returns the templates of the given component, if it has any.
+    pub fn templates(&self, component_id: &str) -> Option<&Arc<Templates>> {
+        self.component_templates.get(component_id)
+    }
+}
+
+pub struct InstanceBuilder {
+    templates: Option<Arc<Templates>>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.templates))
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use spin_common::{ui::quoted_path, url::parse_file_url};
+use spin_locked_app::locked::ContentPath;
+
+type Partials = liquid::partials::EagerCompiler<liquid::partials::InMemorySource>;
+
+/// A component's parsed templates, keyed by name.
+pub struct Templates {
+    templates: BTreeMap<String, liquid::Template>,
+}
+
+impl Templates {
+    /// Loads the templates in the guest directory `guest_dir`, which must be
+    /// in one of the component's `files` mounts.
+    pub fn load_from_files<'a>(
+        files: impl IntoIterator<Item = &'a ContentPath>,
+        guest_dir: &str,
+    ) -> Result<Self> {
+        // The most specific mount containing the directory provides it.
+        let mut found: Option<(&Path, PathBuf)> = None;
+        for file in files {
+            let Some(source) = file.content.source.as_deref() else {
+                continue;
+            };
+            let Ok(rel) = Path::new(guest_dir).strip_prefix(&file.path) else {
+                continue;
+            };
+            if found.as_ref().is_some_and(|(mount, _)| {
+                mount.components().count() >= file.path.components().count()
+            }) {
+                continue;
+            }
+            found = Some((&file.path, parse_file_url(source)?.join(rel)));
+        }
+        let Some((_, dir)) = found else {
+            bail!("templates directory {guest_dir:?} is not in the component's files");
+        };
+        Self::load(&dir)
+    }
+
+    /// Loads the templates in `dir`, named by their paths relative to it.
+    pub fn load(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            bail!("templates directory {} does not exist", quoted_path(dir));
+        }
+        let mut sources = BTreeMap::new();
+        for entry in walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .sort_by_file_name()
+        {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let rel = entry.path().strip_prefix(dir)?;
+            let name = rel
+                .components()
+                .map(|c| c.as_os_str().to_str())
+                .collect::<Option<Vec<_>>>()
+                .with_context(|| format!("template name {rel:?} is not valid UTF-8"))?
+                .join("/");
+            let source = std::fs::read_to_string(entry.path())
+                .with_context(|| format!("failed to read template {name:?}"))?;
+            sources.insert(name, source);
+        }
+
+        // Every template may be included by the others.
+        let mut partials = Partials::empty();
+        for (name, source) in &sources {
+            partials.add(name, source);
+        }
+        let parser = liquid::ParserBuilder::with_stdlib()
+            .partials(partials)
+            .build()
+            .context("failed to build template parser")?;
+        let templates = sources
+            .iter()
+            .map(|(name, source)| {
+                let template = parser
+                    .parse(source)
+                    .with_context(|| format!("invalid template {name:?}"))?;
+                Ok((name.clone(), template))
+            })
+            .collect::<Result<_>>()?;
+        tracing::debug!(
+            "Loaded {} templates from {}",
+            sources.len(),
+            quoted_path(dir)
+        );
+        Ok(Self { templates })
+    }
+
+    /// Returns the template with the given name.
+    pub fn get(&self, name: &str) -> Option<&liquid::Template> {
+        self.templates.get(name)
+    }
+
+    /// The names of the templates, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+}
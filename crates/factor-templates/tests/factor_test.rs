@@ -0,0 +1,96 @@
+use spin_factor_templates::TemplatesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::TestEnvironment;
+use spin_world::spin::templates::templates::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    templates: TemplatesFactor,
+}
+
+fn test_env(
+    dir: &std::path::Path,
+    templates: &str,
+) -> anyhow::Result<TestEnvironment<TestFactors>> {
+    let manifest = format!(
+        r#"
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        files = [{{ source = {dir:?}, destination = "/assets/templates" }}]
+        templates = {templates:?}
+        "#,
+        dir = dir.to_str().unwrap(),
+    );
+    let factors = TestFactors {
+        templates: TemplatesFactor::new(),
+    };
+    Ok(TestEnvironment::new(factors).extend_manifest(manifest.parse()?))
+}
+
+fn write_templates() -> anyhow::Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("partials"))?;
+    std::fs::write(
+        dir.path().join("partials/footer.html"),
+        "<footer>{{ site }}</footer>",
+    )?;
+    std::fs::write(
+        dir.path().join("page.html"),
+        r#"<h1>{{ title | upcase }}</h1>{% for item in items %}<p>{{ item }}</p>{% endfor %}{% include "partials/footer.html" %}"#,
+    )?;
+    Ok(dir)
+}
+
+#[tokio::test]
+async fn templates_render_with_includes() -> anyhow::Result<()> {
+    let dir = write_templates()?;
+    let mut state = test_env(dir.path(), "/assets/templates")?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.templates.list_templates().await?,
+        ["page.html", "partials/footer.html"]
+    );
+    let html = state
+        .templates
+        .render(
+            "page.html".into(),
+            r#"{"title": "hello", "items": ["a", "b"], "site": "spin"}"#.into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(html, "<h1>HELLO</h1><p>a</p><p>b</p><footer>spin</footer>");
+    Ok(())
+}
+
+#[tokio::test]
+async fn render_errors_are_reported() -> anyhow::Result<()> {
+    let dir = write_templates()?;
+    let mut state = test_env(dir.path(), "/assets/templates")?
+        .build_instance_state()
+        .await?;
+
+    let err = state
+        .templates
+        .render("missing.html".into(), "{}".into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::NoSuchTemplate(name) if name == "missing.html"));
+
+    let err = state
+        .templates
+        .render("page.html".into(), "[1, 2]".into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidData(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn templates_outside_files_are_rejected() -> anyhow::Result<()> {
+    let dir = write_templates()?;
+    let env = test_env(dir.path(), "/elsewhere")?;
+    assert!(env.build_instance_state().await.is_err());
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use spin_world::spin::timers::scheduler::{self, Error, TimerId};
+use tracing::{instrument, Level};
+
+use crate::TimerStore;
+
+pub struct InstanceState {
+    store: TimerStore,
+    component_id: String,
+    has_timer_trigger: bool,
+}
+
+impl InstanceState {
+    pub(crate) fn new(store: TimerStore, component_id: String, has_timer_trigger: bool) -> Self {
+        Self {
+            store,
+            component_id,
+            has_timer_trigger,
+        }
+    }
+
+    fn ensure_configured(&self) -> Result<(), Error> {
+        if self.has_timer_trigger {
+            Ok(())
+        } else {
+            Err(Error::NotConfigured)
+        }
+    }
+}
+
+impl scheduler::Host for InstanceState {
+    #[instrument(name = "spin_timers.schedule", skip(self, payload), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn schedule(&mut self, after_ms: u64, payload: Vec<u8>) -> Result<TimerId, Error> {
+        self.ensure_configured()?;
+        self.store
+            .schedule(
+                &self.component_id,
+                Duration::from_millis(after_ms),
+                &payload,
+            )
+            .await
+            .map_err(|e| Error::Other(format!("{e:#}")))
+    }
+
+    #[instrument(name = "spin_timers.cancel", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn cancel(&mut self, id: TimerId) -> Result<bool, Error> {
+        self.ensure_configured()?;
+        self.store
+            .cancel(&self.component_id, &id)
+            .await
+            .map_err(|e| Error::Other(format!("{e:#}")))
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
@@ -0,0 +1,117 @@
+mod host;
+pub mod runtime_config;
+mod store;
+
+use std::collections::HashSet;
+
+use anyhow::ensure;
+
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+pub use store::{Timer, TimerStore};
+
+/// The trigger type that delivers timers.
+pub const TIMER_TRIGGER_TYPE: &str = "timer";
+
+/// A factor that lets components schedule durable timers, which the timer
+/// trigger delivers back to them.
+///
+/// Timers are persisted in one of the app's key-value stores, so this factor
+/// must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct TimersFactor {
+    _priv: (),
+}
+
+impl TimersFactor {
+    /// Create a new TimersFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for TimersFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::timers::scheduler::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        // Only components with a timer trigger can receive timers.
+        let mut timer_components = HashSet::new();
+        for trigger in ctx.app().triggers_with_type(TIMER_TRIGGER_TYPE) {
+            timer_components.insert(trigger.component()?.id().to_string());
+        }
+        let label = runtime_config.key_value_store;
+        ensure!(
+            timer_components.is_empty() || store_manager.is_defined(&label),
+            "timers are configured to use key-value store {label:?}, which is not defined"
+        );
+        let store = TimerStore::new(store_manager, label);
+
+        Ok(AppState {
+            store,
+            timer_components,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let component_id = ctx.app_component().id();
+        Ok(InstanceBuilder {
+            store: app_state.store.clone(),
+            component_id: component_id.to_string(),
+            has_timer_trigger: app_state.timer_components.contains(component_id),
+        })
+    }
+}
+
+pub struct AppState {
+    /// Where the app's timers are persisted.
+    store: TimerStore,
+    /// The IDs of the components with a timer trigger.
+    timer_components: HashSet<String>,
+}
+
+impl AppState {
+    /// Returns the store the app's timers are persisted in.
+    pub fn timer_store(&self) -> &TimerStore {
+        &self.store
+    }
+}
+
+pub struct InstanceBuilder {
+    store: TimerStore,
+    component_id: String,
+    has_timer_trigger: bool,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(
+            self.store,
+            self.component_id,
+            self.has_timer_trigger,
+        ))
+    }
+}
@@ -0,0 +1,19 @@
+pub mod spin;
+
+/// The key-value store timers are persisted in if runtime config doesn't say.
+pub const DEFAULT_KEY_VALUE_STORE: &str = "default";
+
+/// Runtime configuration for timers.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store timers are persisted in.
+    pub key_value_store: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            key_value_store: DEFAULT_KEY_VALUE_STORE.into(),
+        }
+    }
+}
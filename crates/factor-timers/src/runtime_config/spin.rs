@@ -0,0 +1,31 @@
+use anyhow::Context;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for timers from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [timers]
+/// key_value_store = "timers"
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("timers") else {
+        return Ok(None);
+    };
+    let toml: TimersToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [timers] table")?;
+    Ok(Some(RuntimeConfig {
+        key_value_store: toml.key_value_store,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimersToml {
+    key_value_store: String,
+}
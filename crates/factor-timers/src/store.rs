@@ -0,0 +1,153 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, StoreManager};
+
+/// Prefix for the keys of timers in the backing store.
+const KEY_PREFIX: &str = "spin-timers:";
+
+/// A timer that is due for delivery.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timer {
+    pub id: String,
+    /// When the timer is due, in milliseconds since the Unix epoch.
+    pub due_at: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Timers persisted in a key-value store.
+///
+/// Each timer is stored under a key made from the scheduling component's ID
+/// and the timer ID. Timer IDs begin with the zero-padded due time, so timers
+/// sort in the order they are due.
+#[derive(Clone)]
+pub struct TimerStore {
+    store_manager: Arc<dyn StoreManager>,
+    label: Arc<str>,
+}
+
+impl TimerStore {
+    pub fn new(store_manager: Arc<dyn StoreManager>, label: impl Into<Arc<str>>) -> Self {
+        Self {
+            store_manager,
+            label: label.into(),
+        }
+    }
+
+    /// The label of the backing key-value store.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    async fn store(&self) -> Result<Arc<dyn Store>> {
+        self.store_manager
+            .get(&self.label)
+            .await
+            .with_context(|| format!("failed to open timer store {:?}", self.label))
+    }
+
+    /// Schedules a timer for `component_id`, returning its ID.
+    pub async fn schedule(
+        &self,
+        component_id: &str,
+        after: Duration,
+        payload: &[u8],
+    ) -> Result<String> {
+        let due_at = now_millis().saturating_add(after.as_millis().try_into().unwrap_or(u64::MAX));
+        let id = format!("{due_at:020}-{}", uuid::Uuid::new_v4().simple());
+        self.store()
+            .await?
+            .set(&timer_key(component_id, &id), payload)
+            .await
+            .context("failed to persist timer")?;
+        Ok(id)
+    }
+
+    /// Cancels a timer, returning whether it was still pending.
+    pub async fn cancel(&self, component_id: &str, id: &str) -> Result<bool> {
+        let store = self.store().await?;
+        let key = timer_key(component_id, id);
+        if !store
+            .exists(&key)
+            .await
+            .context("failed to look up timer")?
+        {
+            return Ok(false);
+        }
+        store.delete(&key).await.context("failed to delete timer")?;
+        Ok(true)
+    }
+
+    /// Returns the timers for `component_id` that are due at `now`, in the
+    /// order they became due.
+    ///
+    /// Listing timers lists every key in the backing store, so timers are best
+    /// kept in a store of their own if the app keeps many other keys.
+    pub async fn due(&self, component_id: &str, now: u64) -> Result<Vec<Timer>> {
+        let store = self.store().await?;
+        let prefix = timer_key(component_id, "");
+        let mut ids: Vec<(u64, String)> = store
+            .get_keys()
+            .await
+            .context("failed to list timers")?
+            .into_iter()
+            .filter_map(|key| {
+                let id = key.strip_prefix(&prefix)?;
+                let due_at = due_at(id)?;
+                (due_at <= now).then(|| (due_at, id.to_owned()))
+            })
+            .collect();
+        ids.sort();
+
+        let mut timers = Vec::with_capacity(ids.len());
+        for (due_at, id) in ids {
+            // The timer may have been cancelled since it was listed.
+            let Some(payload) = store
+                .get(&timer_key(component_id, &id))
+                .await
+                .context("failed to read timer")?
+            else {
+                continue;
+            };
+            timers.push(Timer {
+                id,
+                due_at,
+                payload,
+            });
+        }
+        Ok(timers)
+    }
+
+    /// Removes a delivered timer.
+    pub async fn remove(&self, component_id: &str, id: &str) -> Result<()> {
+        self.store()
+            .await?
+            .delete(&timer_key(component_id, id))
+            .await
+            .context("failed to delete timer")
+    }
+}
+
+fn timer_key(component_id: &str, id: &str) -> String {
+    format!("{KEY_PREFIX}{component_id}:{id}")
+}
+
+/// Parses the due time from the start of a timer ID.
+fn due_at(id: &str) -> Option<u64> {
+    id.split_once('-')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_ids_sort_by_due_time() {
+        let early = format!("{:020}-a", 9);
+        let late = format!("{:020}-a", 10);
+        assert!(early < late);
+        assert_eq!(due_at(&early), Some(9));
+        assert_eq!(due_at("not-a-timer"), None);
+    }
+}
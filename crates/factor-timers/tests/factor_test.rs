@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use spin_factor_key_value::{runtime_config::spin::MakeKeyValueStore, KeyValueFactor};
+use spin_factor_timers::{RuntimeConfig, TimersFactor};
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::timers::scheduler::{Error, Host};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    timers: TimersFactor,
+}
+
+fn env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        timers: TimersFactor::new(),
+    })
+}
+
+fn runtime_config(timers: Option<RuntimeConfig>) -> anyhow::Result<TestFactorsRuntimeConfig> {
+    let mut key_value = spin_factor_key_value::RuntimeConfig::default();
+    let store_manager = MemoryKeyValueStore::new().make_store(Default::default())?;
+    key_value.add_store_manager("default".into(), Arc::new(store_manager));
+    Ok(TestFactorsRuntimeConfig {
+        key_value: Some(key_value),
+        timers,
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn schedules_and_cancels_timers() -> anyhow::Result<()> {
+    let env = env().extend_manifest(toml! {
+        [[trigger.timer]]
+        component = "test-component"
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env
+        .runtime_config(runtime_config(None)?)?
+        .build_instance_state()
+        .await?;
+
+    let id = state.timers.schedule(0, b"payload".to_vec()).await?;
+    assert!(state.timers.cancel(id.clone()).await?);
+    assert!(!state.timers.cancel(id).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn schedule_fails_without_timer_trigger() -> anyhow::Result<()> {
+    let env = env().extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env
+        .runtime_config(runtime_config(None)?)?
+        .build_instance_state()
+        .await?;
+
+    assert!(matches!(
+        state.timers.schedule(0, vec![]).await,
+        Err(Error::NotConfigured)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn errors_when_store_is_not_defined() -> anyhow::Result<()> {
+    let env = env().extend_manifest(toml! {
+        [[trigger.timer]]
+        component = "test-component"
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let timers = RuntimeConfig {
+        key_value_store: "timers".into(),
+    };
+    let Err(err) = env
+        .runtime_config(runtime_config(Some(timers))?)?
+        .build_instance_state()
+        .await
+    else {
+        anyhow::bail!("expected instance build to fail but it didn't");
+    };
+    assert!(err.to_string().contains(r#"key-value store "timers""#));
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use spin_factors::anyhow;
 use spin_world::{v1, v2::variables, wasi::config as wasi_config};
 use tracing::{instrument, Level};
@@ -8,6 +10,9 @@ impl variables::Host for InstanceState {
     #[instrument(name = "spin_variables.get", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
     async fn get(&mut self, key: String) -> Result<String, variables::Error> {
         let key = spin_expressions::Key::new(&key).map_err(expressions_to_variables_err)?;
+        if let Some(value) = self.tenant_variables().remove(key.as_str()) {
+            return Ok(value);
+        }
         self.expression_resolver
             .resolve(&self.component_id, key)
             .await
@@ -51,7 +56,17 @@ impl wasi_config::store::Host for InstanceState {
             .expression_resolver
             .resolve_all(&self.component_id)
             .await;
-        all.map_err(|e| {
+        all.map(|mut all| {
+            let mut tenant_variables = self.tenant_variables();
+            for (key, value) in &mut all {
+                if let Some(tenant_value) = tenant_variables.remove(key) {
+                    *value = tenant_value;
+                }
+            }
+            all.extend(tenant_variables);
+            all
+        })
+        .map_err(|e| {
             match expressions_to_variables_err(e) {
                 variables::Error::Undefined(msg) => wasi_config::store::Error::Io(msg), // this shouldn't happen but just in case
                 variables::Error::InvalidName(msg) => wasi_config::store::Error::Io(msg), // this shouldn't happen but just in case
@@ -69,6 +84,18 @@ impl wasi_config::store::Host for InstanceState {
     }
 }
 
+impl InstanceState {
+    /// The variables of the invocation's tenant, which take precedence over
+    /// the app's.
+    fn tenant_variables(&self) -> HashMap<String, String> {
+        self.request_context
+            .as_ref()
+            .and_then(|request_context| request_context.tenant())
+            .map(|tenant| tenant.variables().clone())
+            .unwrap_or_default()
+    }
+}
+
 fn expressions_to_variables_err(err: spin_expressions::Error) -> variables::Error {
     use spin_expressions::Error;
     match err {
@@ -1,10 +1,16 @@
 mod host;
+mod redaction;
 pub mod runtime_config;
 
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::Arc,
+};
 
+use redaction::SecretRedactingProvider;
 use runtime_config::RuntimeConfig;
 use spin_expressions::{ProviderResolver as ExpressionResolver, Template};
+use spin_factor_request_context::{RequestContextFactor, RequestContextHandle};
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, InitContext, PrepareContext, RuntimeFactors,
     SelfInstanceBuilder,
@@ -50,34 +56,80 @@ impl Factor for VariablesFactor {
             )?;
         }
 
+        let secrets: Arc<HashSet<String>> = Arc::new(
+            app.variables()
+                .filter(|(_, variable)| variable.secret)
+                .map(|(name, _)| name.clone())
+                .collect(),
+        );
+        let component_secrets = app
+            .components()
+            .map(|component| {
+                let referenced = component
+                    .config()
+                    .filter_map(|(_, value)| Template::new(value.as_str()).ok())
+                    .flat_map(|template| {
+                        template
+                            .variables()
+                            .filter(|name| secrets.contains(*name))
+                            .map(String::from)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                (component.id().to_owned(), referenced)
+            })
+            .collect();
+
+        // Secrets resolved by providers are redacted from logs and traces
         let providers = ctx.take_runtime_config().unwrap_or_default();
         for provider in providers {
-            expression_resolver.add_provider(provider);
+            expression_resolver.add_provider(Box::new(SecretRedactingProvider::new(
+                provider,
+                secrets.clone(),
+            )));
         }
 
         Ok(AppState {
             expression_resolver: Arc::new(expression_resolver),
+            component_secrets,
         })
     }
 
     fn prepare<T: RuntimeFactors>(
         &self,
-        ctx: PrepareContext<T, Self>,
+        mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<InstanceState> {
         let component_id = ctx.app_component().id().to_string();
         let expression_resolver = ctx.app_state().expression_resolver.clone();
+        let request_context = match ctx.instance_builder::<RequestContextFactor>() {
+            Ok(request_context) => Some(request_context.handle()),
+            Err(spin_factors::Error::NoSuchFactor(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
         Ok(InstanceState {
             component_id,
             expression_resolver,
+            request_context,
         })
     }
 }
 
 pub struct AppState {
     expression_resolver: Arc<ExpressionResolver>,
+    /// The secret variables each component's variables refer to, by
+    /// component ID.
+    component_secrets: BTreeMap<String, BTreeSet<String>>,
 }
 
 impl AppState {
+    /// Returns the secret variables each component can read, by component
+    /// ID.
+    ///
+    /// A component can read a secret if one of its variables refers to it.
+    pub fn component_secrets(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.component_secrets
+    }
+
     pub async fn resolve_expression(
         &self,
         expr: impl Into<Box<str>>,
@@ -90,6 +142,8 @@ impl AppState {
 pub struct InstanceState {
     component_id: String,
     expression_resolver: Arc<ExpressionResolver>,
+    /// Used to overlay the variables of the invocation's tenant, if any.
+    request_context: Option<RequestContextHandle>,
 }
 
 impl InstanceState {
@@ -0,0 +1,36 @@
+use std::{collections::HashSet, sync::Arc};
+
+use spin_expressions::{async_trait::async_trait, Key, Provider};
+use spin_factors::anyhow;
+
+/// A [`Provider`] that registers the values it provides for secret
+/// variables to be redacted from logs and traces.
+#[derive(Debug)]
+pub(crate) struct SecretRedactingProvider {
+    inner: Box<dyn Provider>,
+    /// The names of the app's secret variables.
+    secrets: Arc<HashSet<String>>,
+}
+
+impl SecretRedactingProvider {
+    pub fn new(inner: Box<dyn Provider>, secrets: Arc<HashSet<String>>) -> Self {
+        Self { inner, secrets }
+    }
+}
+
+#[async_trait]
+impl Provider for SecretRedactingProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let value = self.inner.get(key).await?;
+        if let Some(value) = &value {
+            if self.secrets.contains(key.as_str()) {
+                spin_telemetry::redaction::add_secret(value);
+            }
+        }
+        Ok(value)
+    }
+
+    async fn get_reference(&self, reference: &str) -> anyhow::Result<Option<String>> {
+        self.inner.get_reference(reference).await
+    }
+}
@@ -1,6 +1,9 @@
+use std::collections::{BTreeSet, HashMap};
+
 use spin_expressions::{Key, Provider};
+use spin_factor_request_context::{RequestContextFactor, TenantConfig};
 use spin_factor_variables::{runtime_config::RuntimeConfig, VariablesFactor};
-use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors::{anyhow, App, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v2::variables::Host;
 
@@ -35,6 +38,99 @@ async fn provider_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn provided_secrets_are_redacted() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let providers = vec![Box::new(MockProvider) as _];
+    let runtime_config = TestFactorsRuntimeConfig {
+        variables: Some(RuntimeConfig { providers }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            foo = { required = true }
+            password = { required = true, secret = true }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { baz = "<{{ foo }}>", db_url = "postgres://app:{{ password }}@db" }
+        })
+        .runtime_config(runtime_config)?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let component_secrets = configured_app
+        .app_state::<VariablesFactor>()?
+        .component_secrets();
+    assert_eq!(
+        component_secrets["test-component"],
+        BTreeSet::from(["password".to_owned()])
+    );
+
+    let builders = env.factors.prepare(&configured_app, "test-component")?;
+    let mut state = env.factors.build_instance_state(builders)?;
+    let db_url = state.variables.get("db_url".into()).await?;
+    assert_eq!(
+        spin_telemetry::redaction::redact(&format!("failed to connect to {db_url}")),
+        "failed to connect to postgres://app:[REDACTED]@db"
+    );
+    Ok(())
+}
+
+#[derive(RuntimeFactors)]
+struct TenantFactors {
+    request_context: RequestContextFactor,
+    variables: VariablesFactor,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tenant_variables_take_precedence() -> anyhow::Result<()> {
+    let factors = TenantFactors {
+        request_context: RequestContextFactor::new(),
+        variables: VariablesFactor::default(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            plan = { default = "free" }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { plan = "{{ plan }}" }
+        })
+        .runtime_config(TenantFactorsRuntimeConfig {
+            request_context: Some(spin_factor_request_context::RuntimeConfig {
+                tenants: HashMap::from([(
+                    "acme".to_owned(),
+                    TenantConfig {
+                        variables: HashMap::from([("plan".into(), "enterprise".into())]),
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })?;
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let instance_state = |tenant_id: Option<&str>| {
+        let mut builders = env.factors.prepare(&configured_app, "test-component")?;
+        builders
+            .request_context()
+            .set_tenant_id(tenant_id.map(Into::into));
+        env.factors.build_instance_state(builders)
+    };
+
+    let mut state = instance_state(None)?;
+    assert_eq!(state.variables.get("plan".into()).await?, "free");
+    let mut state = instance_state(Some("acme"))?;
+    assert_eq!(state.variables.get("plan".into()).await?, "enterprise");
+    let mut state = instance_state(Some("globex"))?;
+    assert_eq!(state.variables.get("plan".into()).await?, "free");
+    Ok(())
+}
+
 #[derive(Debug)]
 struct MockProvider;
 
@@ -43,6 +139,7 @@ impl Provider for MockProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
         match key.as_str() {
             "foo" => Ok(Some("bar".to_string())),
+            "password" => Ok(Some("hunter2-password".to_string())),
             _ => Ok(None),
         }
     }
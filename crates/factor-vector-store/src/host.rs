@@ -0,0 +1,117 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use spin_factors::wasmtime::component::Resource;
+use spin_resource_table::Table;
+use spin_world::spin::vector_store::vector_store::{
+    self, Error, Point, QueryOptions, ScoredPoint, Store,
+};
+use tracing::{instrument, Level};
+
+use crate::{VectorStore, MAX_TOP_K};
+
+const DEFAULT_TABLE_CAPACITY: u32 = 256;
+
+pub struct InstanceState {
+    allowed_stores: HashSet<String>,
+    stores: Arc<HashMap<String, Arc<dyn VectorStore>>>,
+    open_stores: Table<Arc<dyn VectorStore>>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(
+        allowed_stores: HashSet<String>,
+        stores: Arc<HashMap<String, Arc<dyn VectorStore>>>,
+    ) -> Self {
+        Self {
+            allowed_stores,
+            stores,
+            open_stores: Table::new(DEFAULT_TABLE_CAPACITY),
+        }
+    }
+
+    /// Returns the set of store labels this instance may open.
+    pub fn allowed_stores(&self) -> &HashSet<String> {
+        &self.allowed_stores
+    }
+
+    fn get_store(&self, store: &Resource<Store>) -> Result<Arc<dyn VectorStore>, Error> {
+        self.open_stores
+            .get(store.rep())
+            .cloned()
+            .ok_or_else(|| Error::Other("invalid store".into()))
+    }
+}
+
+impl vector_store::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl vector_store::HostStore for InstanceState {
+    #[instrument(name = "spin_vector_store.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, label: String) -> Result<Resource<Store>, Error> {
+        if !self.allowed_stores.contains(&label) {
+            return Err(Error::AccessDenied);
+        }
+        let store = self.stores.get(&label).cloned().ok_or(Error::NoSuchStore)?;
+        self.open_stores
+            .push(store)
+            .map(Resource::new_own)
+            .map_err(|()| Error::Other("too many open stores".into()))
+    }
+
+    #[instrument(name = "spin_vector_store.upsert", skip(self, store, points), err(level = Level::INFO), fields(otel.kind = "client", points = points.len()))]
+    async fn upsert(&mut self, store: Resource<Store>, points: Vec<Point>) -> Result<(), Error> {
+        let store = self.get_store(&store)?;
+        if points.is_empty() {
+            return Ok(());
+        }
+        store.upsert(points).await
+    }
+
+    #[instrument(name = "spin_vector_store.query", skip(self, store, vector, options), err(level = Level::INFO), fields(otel.kind = "client", top_k = options.top_k))]
+    async fn query(
+        &mut self,
+        store: Resource<Store>,
+        vector: Vec<f32>,
+        options: QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        let store = self.get_store(&store)?;
+        if options.top_k > MAX_TOP_K {
+            return Err(Error::InvalidInput(format!(
+                "top-k may be at most {MAX_TOP_K}"
+            )));
+        }
+        if options.top_k == 0 {
+            return Ok(vec![]);
+        }
+        store.query(vector, &options).await
+    }
+
+    #[instrument(name = "spin_vector_store.get", skip(self, store, ids), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&mut self, store: Resource<Store>, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        let store = self.get_store(&store)?;
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        store.get(ids).await
+    }
+
+    #[instrument(name = "spin_vector_store.delete", skip(self, store, ids), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn delete(&mut self, store: Resource<Store>, ids: Vec<String>) -> Result<(), Error> {
+        let store = self.get_store(&store)?;
+        if ids.is_empty() {
+            return Ok(());
+        }
+        store.delete(ids).await
+    }
+
+    async fn drop(&mut self, store: Resource<Store>) -> anyhow::Result<()> {
+        self.open_stores.remove(store.rep());
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use spin_core::async_trait;
+
+use crate::{
+    check_dimensions, exact_search, runtime_config::spin::MakeVectorStore, Error, Metric, Point,
+    QueryOptions, ScoredPoint, VectorStore,
+};
+
+/// A vector store which keeps its points in this process only.
+///
+/// Points are lost when the process exits. This is intended for tests and
+/// local development rather than production use.
+#[derive(Default)]
+pub struct InMemoryStore {
+    metric: Metric,
+    dimensions: Option<u32>,
+    points: Mutex<BTreeMap<String, Point>>,
+}
+
+impl InMemoryStore {
+    /// Creates an empty store measuring distances with `metric`, which only
+    /// accepts vectors of `dimensions` dimensions if set.
+    pub fn new(metric: Metric, dimensions: Option<u32>) -> Self {
+        Self {
+            metric,
+            dimensions,
+            points: Mutex::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        for point in &points {
+            check_dimensions(self.dimensions, &point.vector)?;
+        }
+        let mut stored = self.points.lock().unwrap();
+        for point in points {
+            stored.insert(point.id.clone(), point);
+        }
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        check_dimensions(self.dimensions, &vector)?;
+        let points: Vec<_> = self.points.lock().unwrap().values().cloned().collect();
+        Ok(exact_search(points, self.metric, &vector, options))
+    }
+
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        let stored = self.points.lock().unwrap();
+        Ok(ids
+            .iter()
+            .filter_map(|id| stored.get(id).cloned())
+            .collect())
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        let mut stored = self.points.lock().unwrap();
+        for id in ids {
+            stored.remove(&id);
+        }
+        Ok(())
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some("a temporary in-memory store".into())
+    }
+}
+
+/// Makes [`InMemoryStore`]s from runtime config.
+#[derive(Default)]
+pub struct InMemoryVectorStore {
+    _priv: (),
+}
+
+impl InMemoryVectorStore {
+    /// Creates a new `InMemoryVectorStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the in-memory vector store.
+#[derive(Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct InMemoryVectorStoreRuntimeConfig {
+    /// How distances between vectors are measured. Defaults to cosine.
+    #[serde(default)]
+    metric: Metric,
+    /// The number of dimensions every vector must have, if any.
+    dimensions: Option<u32>,
+}
+
+impl MakeVectorStore for InMemoryVectorStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "memory";
+
+    type RuntimeConfig = InMemoryVectorStoreRuntimeConfig;
+
+    type VectorStore = InMemoryStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore> {
+        Ok(InMemoryStore::new(
+            runtime_config.metric,
+            runtime_config.dimensions,
+        ))
+    }
+}
@@ -0,0 +1,150 @@
+mod host;
+mod in_memory;
+pub mod runtime_config;
+mod store;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::ensure;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+use spin_locked_app::MetadataKey;
+
+pub use host::InstanceState;
+pub use in_memory::{InMemoryStore, InMemoryVectorStore, InMemoryVectorStoreRuntimeConfig};
+pub use runtime_config::RuntimeConfig;
+pub use store::{
+    check_dimensions, exact_search, matches_filters, metadata_from_json, metadata_to_json,
+    Comparison, Error, Filter, Metric, Point, QueryOptions, ScoredPoint, Value, VectorStore,
+};
+
+/// Metadata key for vector stores.
+pub const VECTOR_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("vector_stores");
+
+/// The label of the vector store available without runtime config.
+pub const DEFAULT_VECTOR_STORE_LABEL: &str = "default";
+
+/// The most points a single query may return.
+pub const MAX_TOP_K: u32 = 1000;
+
+/// A factor that provides vector search through configurable stores.
+#[derive(Default)]
+pub struct VectorStoreFactor {
+    _priv: (),
+}
+
+impl VectorStoreFactor {
+    /// Create a new VectorStoreFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for VectorStoreFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::vector_store::vector_store::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let stores: HashMap<_, _> = ctx
+            .take_runtime_config()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+
+        // Build component -> allowed stores map
+        let mut component_allowed_stores = HashMap::new();
+        for component in ctx.app().components() {
+            let component_id = component.id().to_string();
+            let vector_stores = component
+                .get_metadata(VECTOR_STORES_KEY)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for label in &vector_stores {
+                ensure!(
+                    stores.contains_key(label),
+                    "unknown vector_stores label {label:?} for component {component_id:?}"
+                );
+            }
+            component_allowed_stores.insert(component_id, vector_stores);
+        }
+
+        Ok(AppState {
+            stores: Arc::new(stores),
+            component_allowed_stores,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        let allowed_stores = app_state
+            .component_allowed_stores
+            .get(ctx.app_component().id())
+            .expect("component should be in component_allowed_stores")
+            .clone();
+        Ok(InstanceBuilder {
+            stores: app_state.stores.clone(),
+            allowed_stores,
+        })
+    }
+}
+
+pub struct AppState {
+    /// The stores for the app, keyed by label.
+    stores: Arc<HashMap<String, Arc<dyn VectorStore>>>,
+    /// The allowed stores for each component.
+    ///
+    /// This is a map from component ID to the set of store labels that the
+    /// component is allowed to use.
+    component_allowed_stores: HashMap<String, HashSet<String>>,
+}
+
+impl AppState {
+    /// Returns the store with the given label.
+    pub fn store(&self, label: &str) -> Option<Arc<dyn VectorStore>> {
+        self.stores.get(label).cloned()
+    }
+
+    /// Returns the [`VectorStore::summary`] for the given store label.
+    pub fn store_summary(&self, label: &str) -> Option<String> {
+        self.stores.get(label)?.summary()
+    }
+
+    /// Returns true if the given store label is used by any component.
+    pub fn store_is_used(&self, label: &str) -> bool {
+        self.component_allowed_stores
+            .values()
+            .any(|stores| stores.contains(label))
+    }
+}
+
+pub struct InstanceBuilder {
+    /// The stores for the app, keyed by label.
+    stores: Arc<HashMap<String, Arc<dyn VectorStore>>>,
+    /// The allowed stores for this component instance.
+    allowed_stores: HashSet<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.allowed_stores, self.stores))
+    }
+}
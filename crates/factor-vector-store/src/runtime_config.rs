@@ -0,0 +1,40 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::VectorStore;
+
+/// Runtime configuration for all vector stores.
+#[derive(Default, Clone)]
+pub struct RuntimeConfig {
+    /// Map of store labels to stores.
+    stores: HashMap<String, Arc<dyn VectorStore>>,
+}
+
+impl RuntimeConfig {
+    /// Adds a store with the given label to the runtime configuration.
+    ///
+    /// If a store already exists for the given label, it will be replaced.
+    pub fn add_store(&mut self, label: String, store: Arc<dyn VectorStore>) {
+        self.stores.insert(label, store);
+    }
+
+    /// Returns whether a store exists with the given label.
+    pub fn has_store(&self, label: &str) -> bool {
+        self.stores.contains_key(label)
+    }
+
+    /// Returns the store with the given label.
+    pub fn get_store(&self, label: &str) -> Option<Arc<dyn VectorStore>> {
+        self.stores.get(label).cloned()
+    }
+}
+
+impl IntoIterator for RuntimeConfig {
+    type Item = (String, Arc<dyn VectorStore>);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Arc<dyn VectorStore>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.stores.into_iter()
+    }
+}
@@ -0,0 +1,167 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use crate::{RuntimeConfig, VectorStore};
+use anyhow::Context as _;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use spin_factors::runtime_config::toml::GetTomlValue;
+use std::{collections::HashMap, sync::Arc};
+
+/// Defines the construction of a vector store from a serialized runtime config.
+pub trait MakeVectorStore: 'static + Send + Sync {
+    /// Unique type identifier for the store.
+    const RUNTIME_CONFIG_TYPE: &'static str;
+    /// Runtime configuration for the store.
+    ///
+    /// Its JSON schema documents and validates the store's runtime config.
+    type RuntimeConfig: DeserializeOwned + JsonSchema;
+    /// The store implementation.
+    type VectorStore: VectorStore;
+
+    /// Creates a new store from the runtime configuration.
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore>;
+}
+
+/// A function that creates a vector store from a TOML table.
+type StoreFromToml = Arc<dyn Fn(toml::Table) -> anyhow::Result<Arc<dyn VectorStore>> + Send + Sync>;
+
+/// Creates a `StoreFromToml` function from a `MakeVectorStore` implementation.
+fn store_from_toml_fn<T: MakeVectorStore>(provider_type: T) -> StoreFromToml {
+    Arc::new(move |table| {
+        let runtime_config: T::RuntimeConfig = table
+            .try_into()
+            .context("could not parse vector store runtime config")?;
+        let provider = provider_type
+            .make_store(runtime_config)
+            .context("could not make vector store from runtime config")?;
+        Ok(Arc::new(provider))
+    })
+}
+
+/// Converts from toml based runtime configuration into a [`RuntimeConfig`].
+///
+/// The various store types (i.e., the "type" field in the toml field) are
+/// registered with the resolver using `register_store_type`. The default store
+/// for a label is registered using `add_default_store`.
+#[derive(Default, Clone)]
+pub struct RuntimeConfigResolver {
+    /// A map of store types to a function that returns the appropriate
+    /// vector store from runtime config TOML.
+    store_types: HashMap<&'static str, StoreFromToml>,
+    /// A map of store types to the JSON schema of their runtime config.
+    schemas: HashMap<&'static str, RootSchema>,
+    /// A map of default store configurations for a label.
+    defaults: HashMap<&'static str, StoreConfig>,
+}
+
+impl RuntimeConfigResolver {
+    /// Create a new RuntimeConfigResolver.
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Adds a default store configuration for a label.
+    ///
+    /// Users must ensure that the store type for `config` has been registered with
+    /// the resolver using [`Self::register_store_type`].
+    pub fn add_default_store<T>(
+        &mut self,
+        label: &'static str,
+        config: T::RuntimeConfig,
+    ) -> anyhow::Result<()>
+    where
+        T: MakeVectorStore,
+        T::RuntimeConfig: Serialize,
+    {
+        self.defaults.insert(
+            label,
+            StoreConfig::new(T::RUNTIME_CONFIG_TYPE.to_owned(), config)?,
+        );
+        Ok(())
+    }
+
+    /// Registers a store type to the resolver.
+    pub fn register_store_type<T: MakeVectorStore>(&mut self, store_type: T) -> anyhow::Result<()> {
+        if self
+            .store_types
+            .insert(T::RUNTIME_CONFIG_TYPE, store_from_toml_fn(store_type))
+            .is_some()
+        {
+            anyhow::bail!("duplicate vector store type {:?}", T::RUNTIME_CONFIG_TYPE);
+        }
+        self.schemas.insert(
+            T::RUNTIME_CONFIG_TYPE,
+            schemars::schema_for!(T::RuntimeConfig),
+        );
+        Ok(())
+    }
+
+    /// The JSON schemas of the registered store types' runtime config, by
+    /// store type.
+    pub fn store_type_schemas(&self) -> impl Iterator<Item = (&'static str, &RootSchema)> {
+        self.schemas
+            .iter()
+            .map(|(&store_type, schema)| (store_type, schema))
+    }
+
+    /// Resolves a toml table into a runtime config.
+    ///
+    /// The default stores are also added to the runtime config.
+    pub fn resolve(&self, table: Option<&impl GetTomlValue>) -> anyhow::Result<RuntimeConfig> {
+        let mut runtime_config = RuntimeConfig::default();
+        if let Some(table) = table.and_then(|t| t.get("vector_store")) {
+            let table: HashMap<String, StoreConfig> = table.clone().try_into()?;
+            for (label, config) in table {
+                let store = self.store_from_config(config).with_context(|| {
+                    format!("could not configure vector store with label '{label}'")
+                })?;
+                runtime_config.add_store(label, store);
+            }
+        }
+
+        for (&label, config) in &self.defaults {
+            if !runtime_config.has_store(label) {
+                let store = self.store_from_config(config.clone()).with_context(|| {
+                    format!("could not configure vector store with label '{label}'")
+                })?;
+                runtime_config.add_store(label.to_owned(), store);
+            }
+        }
+        Ok(runtime_config)
+    }
+
+    /// Given a [`StoreConfig`], returns a vector store.
+    ///
+    /// Errors if there is no [`MakeVectorStore`] registered for the store config's type
+    /// or if the vector store cannot be created from the config.
+    fn store_from_config(&self, config: StoreConfig) -> anyhow::Result<Arc<dyn VectorStore>> {
+        let config_type = config.type_.as_str();
+        let maker = self.store_types.get(config_type).with_context(|| {
+            format!(
+                "the vector store type '{config_type}' was not registered with the config resolver"
+            )
+        })?;
+        maker(config.config)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StoreConfig {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(flatten)]
+    pub config: toml::Table,
+}
+
+impl StoreConfig {
+    pub fn new<T>(type_: String, config: T) -> anyhow::Result<Self>
+    where
+        T: Serialize,
+    {
+        Ok(Self {
+            type_,
+            config: toml::value::Table::try_from(config)?,
+        })
+    }
+}
@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use spin_core::async_trait;
+
+pub use spin_world::spin::vector_store::vector_store::{
+    Comparison, Error, Filter, Point, QueryOptions, ScoredPoint, Value,
+};
+
+/// A store of vectors reachable through the vector store interface.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Inserts points, replacing any existing points with the same IDs.
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error>;
+
+    /// Returns the `options.top_k` points closest to `vector` which satisfy
+    /// `options.filters`, closest first.
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error>;
+
+    /// Returns the points with the given IDs, skipping IDs which don't exist.
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error>;
+
+    /// Deletes the points with the given IDs, ignoring IDs which don't exist.
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error>;
+
+    /// A human-readable summary of the store's configuration
+    ///
+    /// Example: "Qdrant collection \"docs\" at http://localhost:6333"
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
+
+/// How the distance between two vectors is measured.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// One minus the cosine of the angle between the vectors.
+    #[default]
+    Cosine,
+    /// The straight-line distance between the vectors.
+    Euclidean,
+    /// The negated dot product of the vectors.
+    Dot,
+}
+
+impl Metric {
+    /// The distance between two vectors of the same length.
+    pub fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        let dot = || a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        match self {
+            Self::Cosine => {
+                let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 {
+                    1.0
+                } else {
+                    1.0 - dot() / norms
+                }
+            }
+            Self::Euclidean => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt(),
+            Self::Dot => -dot(),
+        }
+    }
+}
+
+/// Checks that a vector has the number of dimensions a store expects, if it
+/// expects any particular number.
+pub fn check_dimensions(dimensions: Option<u32>, vector: &[f32]) -> Result<(), Error> {
+    match dimensions {
+        Some(dimensions) if vector.len() != dimensions as usize => {
+            Err(Error::InvalidInput(format!(
+                "expected a vector of {dimensions} dimensions but got {}",
+                vector.len()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether a point's metadata satisfies all of the filters.
+pub fn matches_filters(metadata: &[(String, Value)], filters: &[Filter]) -> bool {
+    filters.iter().all(|filter| {
+        let Some((_, value)) = metadata.iter().find(|(key, _)| key == &filter.key) else {
+            return false;
+        };
+        let Some(ordering) = compare_values(value, &filter.value) else {
+            return false;
+        };
+        match filter.comparison {
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::NotEqual => ordering.is_ne(),
+            Comparison::LessThan => ordering.is_lt(),
+            Comparison::LessThanOrEqual => ordering.is_le(),
+            Comparison::GreaterThan => ordering.is_gt(),
+            Comparison::GreaterThanOrEqual => ordering.is_ge(),
+        }
+    })
+}
+
+/// Compares two metadata values, or returns `None` if they are of types that
+/// can't be compared. Integers and floats compare as numbers.
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Finds the points closest to `vector` by comparing it with every point.
+///
+/// This is for stores without an index of their own.
+pub fn exact_search(
+    points: impl IntoIterator<Item = Point>,
+    metric: Metric,
+    vector: &[f32],
+    options: &QueryOptions,
+) -> Vec<ScoredPoint> {
+    let mut scored: Vec<_> = points
+        .into_iter()
+        .filter(|point| point.vector.len() == vector.len())
+        .filter(|point| matches_filters(&point.metadata, &options.filters))
+        .map(|point| ScoredPoint {
+            distance: metric.distance(vector, &point.vector),
+            id: point.id,
+            metadata: point.metadata,
+            vector: options.include_vectors.then_some(point.vector),
+        })
+        .collect();
+    scored.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    scored.truncate(options.top_k as usize);
+    scored
+}
+
+/// Converts metadata to a JSON object, for stores which keep it as JSON.
+pub fn metadata_to_json(
+    metadata: &[(String, Value)],
+) -> serde_json::Map<String, serde_json::Value> {
+    metadata
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Text(s) => serde_json::Value::from(s.as_str()),
+                Value::Integer(i) => serde_json::Value::from(*i),
+                Value::Float(f) => serde_json::Value::from(*f),
+                Value::Boolean(b) => serde_json::Value::from(*b),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// Converts a JSON object back to metadata, skipping values which aren't
+/// metadata values.
+pub fn metadata_from_json(
+    json: serde_json::Map<String, serde_json::Value>,
+) -> Vec<(String, Value)> {
+    json.into_iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => Value::Text(s),
+                serde_json::Value::Bool(b) => Value::Boolean(b),
+                serde_json::Value::Number(n) => match n.as_i64() {
+                    Some(i) => Value::Integer(i),
+                    None => Value::Float(n.as_f64()?),
+                },
+                _ => return None,
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(id: &str, vector: &[f32], category: &str) -> Point {
+        Point {
+            id: id.into(),
+            vector: vector.to_vec(),
+            metadata: vec![("category".into(), Value::Text(category.into()))],
+        }
+    }
+
+    #[test]
+    fn distances_follow_metric() {
+        let (a, b) = ([1.0, 0.0], [0.0, 2.0]);
+        assert_eq!(1.0, Metric::Cosine.distance(&a, &b));
+        assert_eq!(0.0, Metric::Cosine.distance(&a, &[3.0, 0.0]));
+        assert_eq!(5.0f32.sqrt(), Metric::Euclidean.distance(&a, &b));
+        assert_eq!(-3.0, Metric::Dot.distance(&a, &[3.0, 1.0]));
+    }
+
+    #[test]
+    fn search_returns_closest_matching_points() {
+        let points = vec![
+            point("a", &[1.0, 0.0], "news"),
+            point("b", &[0.9, 0.1], "blog"),
+            point("c", &[0.0, 1.0], "news"),
+            point("d", &[0.8, 0.2], "news"),
+        ];
+        let options = QueryOptions {
+            top_k: 2,
+            filters: vec![Filter {
+                key: "category".into(),
+                comparison: Comparison::Equal,
+                value: Value::Text("news".into()),
+            }],
+            include_vectors: false,
+        };
+        let results = exact_search(points, Metric::Cosine, &[1.0, 0.0], &options);
+        let ids: Vec<_> = results.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(vec!["a", "d"], ids);
+        assert!(results[0].vector.is_none());
+    }
+
+    #[test]
+    fn numbers_compare_across_types() {
+        let metadata = vec![("year".to_owned(), Value::Integer(2024))];
+        let filter = |comparison, value| Filter {
+            key: "year".into(),
+            comparison,
+            value,
+        };
+        assert!(matches_filters(
+            &metadata,
+            &[filter(Comparison::GreaterThan, Value::Float(2023.5))]
+        ));
+        assert!(!matches_filters(
+            &metadata,
+            &[filter(Comparison::LessThan, Value::Integer(2000))]
+        ));
+        // Values of other types never match
+        assert!(!matches_filters(
+            &metadata,
+            &[filter(Comparison::NotEqual, Value::Text("2024".into()))]
+        ));
+        // Nor do missing keys
+        assert!(!matches_filters(
+            &[],
+            &[filter(Comparison::NotEqual, Value::Integer(1))]
+        ));
+    }
+
+    #[test]
+    fn metadata_round_trips_through_json() {
+        let metadata = vec![
+            ("a".to_owned(), Value::Text("x".into())),
+            ("b".to_owned(), Value::Integer(3)),
+            ("c".to_owned(), Value::Float(1.5)),
+            ("d".to_owned(), Value::Boolean(true)),
+        ];
+        let json = metadata_to_json(&metadata);
+        let round_tripped = metadata_from_json(json);
+        assert_eq!(format!("{metadata:?}"), format!("{round_tripped:?}"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::bail;
+use spin_factor_vector_store::{
+    Error, InMemoryStore, Metric, Point, QueryOptions, RuntimeConfig, Value, VectorStoreFactor,
+};
+use spin_factors::{wasmtime::component::Resource, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::vector_store::vector_store::HostStore as _;
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    vector_store: VectorStoreFactor,
+}
+
+impl From<RuntimeConfig> for TestFactorsRuntimeConfig {
+    fn from(value: RuntimeConfig) -> Self {
+        Self {
+            vector_store: Some(value),
+        }
+    }
+}
+
+fn runtime_config(dimensions: Option<u32>) -> RuntimeConfig {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store(
+        "docs".into(),
+        Arc::new(InMemoryStore::new(Metric::Cosine, dimensions)),
+    );
+    runtime_config
+}
+
+fn point(id: &str, vector: &[f32]) -> Point {
+    Point {
+        id: id.into(),
+        vector: vector.to_vec(),
+        metadata: vec![("title".into(), Value::Text(id.to_uppercase()))],
+    }
+}
+
+#[tokio::test]
+async fn points_can_be_upserted_and_queried() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        vector_store: VectorStoreFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        vector_stores = ["docs"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config(Some(2)))?
+        .build_instance_state()
+        .await?;
+
+    assert_eq!(
+        state.vector_store.allowed_stores(),
+        &["docs".into()].into_iter().collect::<HashSet<_>>()
+    );
+
+    let store = state.vector_store.open("docs".into()).await?;
+    let reuse = || Resource::new_borrow(store.rep());
+    state
+        .vector_store
+        .upsert(
+            reuse(),
+            vec![point("a", &[1.0, 0.0]), point("b", &[0.0, 1.0])],
+        )
+        .await?;
+
+    let options = QueryOptions {
+        top_k: 1,
+        filters: vec![],
+        include_vectors: true,
+    };
+    let results = state
+        .vector_store
+        .query(reuse(), vec![0.1, 0.9], options)
+        .await?;
+    assert_eq!(1, results.len());
+    assert_eq!("b", results[0].id);
+    assert_eq!(Some(vec![0.0, 1.0]), results[0].vector);
+
+    let options = QueryOptions {
+        top_k: 1,
+        filters: vec![],
+        include_vectors: false,
+    };
+    let result = state
+        .vector_store
+        .query(reuse(), vec![1.0, 0.0, 0.0], options)
+        .await;
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    state.vector_store.delete(reuse(), vec!["b".into()]).await?;
+    let points = state
+        .vector_store
+        .get(reuse(), vec!["a".into(), "b".into()])
+        .await?;
+    assert_eq!(
+        vec!["a"],
+        points.iter().map(|p| p.id.as_str()).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_store_is_not_defined() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        vector_store: VectorStoreFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        vector_stores = ["docs"]
+    });
+    let Err(err) = env
+        .runtime_config(RuntimeConfig::default())?
+        .build_instance_state()
+        .await
+    else {
+        bail!("expected instance build to fail but it didn't");
+    };
+
+    assert!(err
+        .to_string()
+        .contains(r#"unknown vector_stores label "docs""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn open_fails_when_store_is_not_allowed() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        vector_store: VectorStoreFactor::new(),
+    });
+    let mut state = env
+        .runtime_config(runtime_config(None))?
+        .build_instance_state()
+        .await?;
+
+    assert!(state.vector_store.allowed_stores().is_empty());
+    assert!(matches!(
+        state.vector_store.open("docs".into()).await,
+        Err(Error::AccessDenied)
+    ));
+    Ok(())
+}
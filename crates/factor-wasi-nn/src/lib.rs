@@ -0,0 +1,105 @@
+pub mod runtime_config;
+
+use std::{collections::HashMap, sync::Arc};
+
+use spin_factors::{
+    anyhow, ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext,
+    RuntimeFactors,
+};
+use wasmtime_wasi_nn::{
+    registry::GraphRegistry,
+    wit::{WasiNnCtx, WasiNnView},
+    Backend, Graph, Registry,
+};
+
+pub use runtime_config::RuntimeConfig;
+
+/// The factor for `wasi:nn`.
+///
+/// Components may only use the models named in runtime config, which are
+/// loaded once for the app; loading a model from bytes is not supported.
+#[derive(Default)]
+pub struct WasiNnFactor {
+    _priv: (),
+}
+
+impl WasiNnFactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Factor for WasiNnFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        link_wasi_nn(ctx)
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        Ok(AppState {
+            models: Arc::new(runtime_config.models),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        Ok(InstanceBuilder {
+            models: ctx.app_state().models.clone(),
+        })
+    }
+}
+
+fn link_wasi_nn<C: InitContext<WasiNnFactor>>(ctx: &mut C) -> anyhow::Result<()> {
+    wasmtime_wasi_nn::wit::add_to_linker(ctx.linker(), get_wasi_nn::<C>)
+}
+
+fn get_wasi_nn<C: InitContext<WasiNnFactor>>(data: &mut C::StoreData) -> WasiNnView<'_> {
+    let (state, table) = C::get_data_with_table(data);
+    WasiNnView::new(table, &mut state.ctx)
+}
+
+pub struct AppState {
+    /// Maps model names -> loaded models
+    models: Arc<HashMap<String, Graph>>,
+}
+
+pub struct InstanceBuilder {
+    models: Arc<HashMap<String, Graph>>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        // With no backends, guests can't load models other than the named ones.
+        let registry = Registry::from(NamedModels((*self.models).clone()));
+        let ctx = WasiNnCtx::new(Vec::<Backend>::new(), registry);
+        Ok(InstanceState { ctx })
+    }
+}
+
+pub struct InstanceState {
+    ctx: WasiNnCtx,
+}
+
+/// The models available to an instance, by name.
+struct NamedModels(HashMap<String, Graph>);
+
+impl GraphRegistry for NamedModels {
+    fn get(&self, name: &str) -> Option<&Graph> {
+        self.0.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Graph> {
+        self.0.get_mut(name)
+    }
+}
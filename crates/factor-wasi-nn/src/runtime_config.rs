@@ -0,0 +1,26 @@
+pub mod spin;
+
+use std::collections::HashMap;
+
+use wasmtime_wasi_nn::Graph;
+
+/// Runtime configuration for `wasi:nn`.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    /// Maps model names -> loaded models
+    models: HashMap<String, Graph>,
+}
+
+impl RuntimeConfig {
+    /// Makes the given model available to components by name.
+    ///
+    /// If a model already exists with the given name, it will be replaced.
+    pub fn add_model(&mut self, name: String, model: Graph) {
+        self.models.insert(name, model);
+    }
+
+    /// Returns whether a model exists with the given name.
+    pub fn has_model(&self, name: &str) -> bool {
+        self.models.contains_key(name)
+    }
+}
@@ -0,0 +1,156 @@
+//! Runtime configuration implementation used by Spin CLI.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+use wasmtime_wasi_nn::Graph;
+
+use crate::RuntimeConfig;
+
+/// Get the runtime configuration for `wasi:nn` from a TOML table.
+///
+/// Relative paths are resolved against `runtime_config_dir`.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [wasi_nn.models.classifier]
+/// type = "onnx"
+/// path = "models/classifier"  # a directory containing `model.onnx`
+/// ```
+pub fn config_from_table(
+    table: &impl GetTomlValue,
+    runtime_config_dir: &Path,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(table) = table.get("wasi_nn") else {
+        return Ok(None);
+    };
+    let toml: WasiNnToml = table
+        .clone()
+        .try_into()
+        .context("failed to parse [wasi_nn] table")?;
+
+    let mut runtime_config = RuntimeConfig::default();
+    for (name, model) in toml.models {
+        let graph = model
+            .load(runtime_config_dir)
+            .with_context(|| format!("could not load wasi-nn model '{name}'"))?;
+        runtime_config.add_model(name, graph);
+    }
+    Ok(Some(runtime_config))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WasiNnToml {
+    #[serde(default)]
+    models: HashMap<String, ModelToml>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ModelToml {
+    Onnx { path: PathBuf },
+}
+
+impl ModelToml {
+    fn load(self, runtime_config_dir: &Path) -> anyhow::Result<Graph> {
+        match self {
+            Self::Onnx { path } => load_onnx(&runtime_config_dir.join(path)),
+        }
+    }
+}
+
+#[cfg(feature = "onnx")]
+fn load_onnx(dir: &Path) -> anyhow::Result<Graph> {
+    use wasmtime_wasi_nn::{
+        backend::{onnx::OnnxBackend, BackendFromDir},
+        wit::ExecutionTarget,
+    };
+
+    anyhow::ensure!(
+        dir.join("model.onnx").is_file(),
+        "{dir:?} does not contain a model.onnx file"
+    );
+    Ok(OnnxBackend::default().load_from_dir(dir, ExecutionTarget::Cpu)?)
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_onnx(_dir: &Path) -> anyhow::Result<Graph> {
+    anyhow::bail!("this build of Spin does not support ONNX models")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_table_is_none() {
+        let table = toml::Table::new();
+        assert!(config_from_table(&table, Path::new(".")).unwrap().is_none());
+    }
+
+    #[test]
+    fn missing_model_is_an_error() {
+        let table: toml::Table = toml::toml! {
+            [wasi_nn.models.classifier]
+            type = "onnx"
+            path = "does-not-exist"
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn unknown_model_type_is_an_error() {
+        let table: toml::Table = toml::toml! {
+            [wasi_nn.models.classifier]
+            type = "tensorflow"
+            path = "models/classifier"
+        };
+        assert!(config_from_table(&table, Path::new(".")).is_err());
+    }
+
+    #[cfg(feature = "onnx")]
+    #[test]
+    fn onnx_model_is_loaded_and_runs() -> anyhow::Result<()> {
+        use wasmtime_wasi_nn::{backend::Id, wit::TensorType, Tensor};
+
+        // `double` adds its input of four floats to itself
+        let table: toml::Table = toml::toml! {
+            [wasi_nn.models.double]
+            type = "onnx"
+            path = "double"
+        };
+        let models_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/models");
+        let config = config_from_table(&table, &models_dir)?.unwrap();
+        let graph = &config.models["double"];
+
+        let input: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        let mut ctx = graph.init_execution_context()?;
+        ctx.set_input(
+            Id::Index(0),
+            &Tensor {
+                dimensions: vec![4],
+                ty: TensorType::Fp32,
+                data: input,
+            },
+        )?;
+        ctx.compute()?;
+        let output = ctx.get_output(Id::Index(0))?;
+
+        let output: Vec<f32> = output
+            .data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(output, [2.0, 4.0, 6.0, 8.0]);
+        Ok(())
+    }
+}
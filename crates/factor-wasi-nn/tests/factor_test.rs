@@ -0,0 +1,21 @@
+use spin_factor_wasi_nn::WasiNnFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    wasi_nn: WasiNnFactor,
+}
+
+#[tokio::test]
+async fn instance_builds_without_models() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(TestFactors {
+        wasi_nn: WasiNnFactor::new(),
+    })
+    .extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    env.build_instance_state().await?;
+    Ok(())
+}
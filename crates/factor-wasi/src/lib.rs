@@ -1,27 +1,45 @@
 mod io;
+mod quota;
+pub mod runtime_config;
 pub mod spin;
 mod wasi_2023_10_18;
 mod wasi_2023_11_10;
 
 use std::{
+    collections::HashMap,
     future::Future,
     io::{Read, Write},
     net::SocketAddr,
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
 use io::{PipeReadStream, PipedWriteStream};
+use quota::{FilesImpl, FilesQuota};
+use runtime_config::{RuntimeConfig, VirtualClock};
 use spin_factors::{
-    anyhow, AppComponent, Factor, FactorInstanceBuilder, InitContext, PrepareContext,
-    RuntimeFactors, RuntimeFactorsInstanceState,
+    anyhow::{self, Context},
+    AppComponent, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+    RuntimeFactorsInstanceState,
 };
+use spin_locked_app::MetadataKey;
 use wasmtime_wasi::p2::{
     IoImpl, IoView, StdinStream, StdoutStream, WasiCtx, WasiCtxBuilder, WasiImpl, WasiView,
 };
-use wasmtime_wasi::{DirPerms, FilePerms, ResourceTable};
+use wasmtime_wasi::{DirPerms, FilePerms, HostWallClock, ResourceTable};
 
 pub use wasmtime_wasi::SocketAddrUse;
 
+/// Metadata key for guest paths at which each instance gets an empty,
+/// writable directory that is removed when the instance is dropped.
+pub const TMPFS_MOUNTS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("tmpfs_mounts");
+/// Metadata key for whether a component's files must be mounted read-only.
+pub const READ_ONLY_FILES_KEY: MetadataKey<bool> = MetadataKey::new("read_only_files");
+/// Metadata key for the most disk space a component's files, including its
+/// tmpfs mounts, may use. Writes beyond it fail.
+pub const FILES_QUOTA_BYTES_KEY: MetadataKey<u64> = MetadataKey::new("files_quota_bytes");
+
 pub struct WasiFactor {
     files_mounter: Box<dyn FilesMounter>,
 }
@@ -40,6 +58,7 @@ impl WasiFactor {
         Some(WasiImpl(IoImpl(WasiImplInner {
             ctx: &mut state.ctx,
             table,
+            files_quota: state.files_quota.as_ref(),
         })))
     }
 }
@@ -54,6 +73,7 @@ trait InitContextExt: InitContext<WasiFactor> {
         IoImpl(WasiImplInner {
             ctx: &mut state.ctx,
             table,
+            files_quota: state.files_quota.as_ref(),
         })
     }
 
@@ -81,6 +101,20 @@ trait InitContextExt: InitContext<WasiFactor> {
         add_to_linker(self.linker(), Self::get_wasi)
     }
 
+    fn get_files(data: &mut Self::StoreData) -> FilesImpl<'_> {
+        FilesImpl(Self::get_wasi(data))
+    }
+
+    fn link_files_bindings(
+        &mut self,
+        add_to_linker: fn(
+            &mut wasmtime::component::Linker<Self::StoreData>,
+            fn(&mut Self::StoreData) -> FilesImpl<'_>,
+        ) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        add_to_linker(self.linker(), Self::get_files)
+    }
+
     fn link_wasi_default_bindings<O>(
         &mut self,
         add_to_linker: fn(
@@ -99,8 +133,8 @@ trait InitContextExt: InitContext<WasiFactor> {
 impl<T> InitContextExt for T where T: InitContext<WasiFactor> {}
 
 impl Factor for WasiFactor {
-    type RuntimeConfig = ();
-    type AppState = ();
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
     type InstanceBuilder = InstanceBuilder;
 
     fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
@@ -108,7 +142,7 @@ impl Factor for WasiFactor {
 
         ctx.link_wasi_bindings(bindings::clocks::wall_clock::add_to_linker_get_host)?;
         ctx.link_wasi_bindings(bindings::clocks::monotonic_clock::add_to_linker_get_host)?;
-        ctx.link_wasi_bindings(bindings::filesystem::types::add_to_linker_get_host)?;
+        ctx.link_files_bindings(bindings::filesystem::types::add_to_linker_get_host)?;
         ctx.link_wasi_bindings(bindings::filesystem::preopens::add_to_linker_get_host)?;
         ctx.link_io_bindings(bindings::io::error::add_to_linker_get_host)?;
         ctx.link_io_bindings(bindings::io::poll::add_to_linker_get_host)?;
@@ -141,9 +175,18 @@ impl Factor for WasiFactor {
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: spin_factors::ConfigureAppContext<T, Self>,
+        mut ctx: spin_factors::ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let mut files_quotas = HashMap::new();
+        for component in ctx.app().components() {
+            if let Some(quota) = component.get_metadata(FILES_QUOTA_BYTES_KEY)? {
+                files_quotas.insert(component.id().to_string(), Arc::new(FilesQuota::new(quota)));
+            }
+        }
+        Ok(AppState {
+            runtime_config: Arc::new(ctx.take_runtime_config().unwrap_or_default()),
+            files_quotas,
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
@@ -151,21 +194,84 @@ impl Factor for WasiFactor {
         ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<InstanceBuilder> {
         let mut wasi_ctx = WasiCtxBuilder::new();
+        let app_component = ctx.app_component();
+        let runtime_config = ctx.app_state().runtime_config.clone();
+
+        // Virtualize the clock and randomness
+        if let Some(clock) = runtime_config.clock {
+            wasi_ctx.wall_clock(VirtualWallClock(clock));
+        }
+        if let Some(seed) = runtime_config.random_seed {
+            wasi_ctx.secure_random(SeededRng::new(seed));
+            wasi_ctx.insecure_random(SeededRng::new(!seed));
+            wasi_ctx.insecure_random_seed(seed.into());
+        }
 
         // Mount files
-        let mount_ctx = MountFilesContext { ctx: &mut wasi_ctx };
-        self.files_mounter
-            .mount_files(ctx.app_component(), mount_ctx)?;
+        let mut mounted_paths = vec![];
+        let mount_ctx = MountFilesContext {
+            ctx: &mut wasi_ctx,
+            read_only: app_component
+                .get_metadata(READ_ONLY_FILES_KEY)?
+                .unwrap_or_default(),
+            mounted_paths: &mut mounted_paths,
+        };
+        self.files_mounter.mount_files(app_component, mount_ctx)?;
+        let files_quota = ctx
+            .app_state()
+            .files_quotas
+            .get(app_component.id())
+            .cloned();
+        if let Some(quota) = &files_quota {
+            quota.measure(&mounted_paths)?;
+        }
 
-        let mut builder = InstanceBuilder { ctx: wasi_ctx };
+        // Create a fresh directory for each tmpfs mount
+        let mut tmpfs_dirs = vec![];
+        for guest_path in app_component
+            .get_metadata(TMPFS_MOUNTS_KEY)?
+            .unwrap_or_default()
+        {
+            let dir = tempfile::Builder::new()
+                .prefix("spin-tmpfs-")
+                .tempdir()
+                .context("failed to create tmpfs directory")?;
+            wasi_ctx.preopened_dir(dir.path(), &guest_path, DirPerms::all(), FilePerms::all())?;
+            tmpfs_dirs.push(dir);
+        }
 
-        // Apply environment variables
-        builder.env(ctx.app_component().environment());
+        // Apply environment variables, with the component's taking precedence
+        // over any inherited from the host
+        let component_env = app_component.environment().into_iter().collect::<Vec<_>>();
+        let inherited_env = std::env::vars()
+            .filter(|(name, _)| {
+                runtime_config
+                    .inherit_env
+                    .iter()
+                    .any(|pattern| pattern.matches(name))
+                    && !component_env.iter().any(|(k, _)| *k == name.as_str())
+            })
+            .collect::<Vec<_>>();
+
+        let mut builder = InstanceBuilder {
+            ctx: wasi_ctx,
+            tmpfs_dirs,
+            files_quota,
+            runtime_config,
+        };
+        builder.env(inherited_env);
+        builder.env(component_env);
 
         Ok(builder)
     }
 }
 
+pub struct AppState {
+    runtime_config: Arc<RuntimeConfig>,
+    // Shared by all of each component's instances
+    files_quotas: HashMap<String, Arc<FilesQuota>>,
+}
+
 pub trait FilesMounter: Send + Sync {
     fn mount_files(
         &self,
@@ -192,16 +298,22 @@ impl FilesMounter for DummyFilesMounter {
 
 pub struct MountFilesContext<'a> {
     ctx: &'a mut WasiCtxBuilder,
+    read_only: bool,
+    mounted_paths: &'a mut Vec<PathBuf>,
 }
 
 impl MountFilesContext<'_> {
+    /// "Mounts" the given `host_path` into the WASI filesystem at the given
+    /// `guest_path`. The mount is read-only, regardless of `writable`, if the
+    /// component's files must be read-only.
     pub fn preopened_dir(
         &mut self,
         host_path: impl AsRef<Path>,
         guest_path: impl AsRef<str>,
         writable: bool,
     ) -> anyhow::Result<()> {
-        let (dir_perms, file_perms) = if writable {
+        self.mounted_paths.push(host_path.as_ref().to_owned());
+        let (dir_perms, file_perms) = if writable && !self.read_only {
             (DirPerms::all(), FilePerms::all())
         } else {
             (DirPerms::READ, FilePerms::READ)
@@ -212,8 +324,61 @@ impl MountFilesContext<'_> {
     }
 }
 
+/// A [`HostWallClock`] reading a [`VirtualClock`].
+struct VirtualWallClock(VirtualClock);
+
+impl HostWallClock for VirtualWallClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.0.now()
+    }
+}
+
+/// A deterministic (and _not_ cryptographically secure) random number
+/// generator, for reproducible runs. This is SplitMix64.
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl cap_rand::RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), cap_rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
 pub struct InstanceBuilder {
     ctx: WasiCtxBuilder,
+    // Removed when the instance is dropped
+    tmpfs_dirs: Vec<tempfile::TempDir>,
+    files_quota: Option<Arc<FilesQuota>>,
+    runtime_config: Arc<RuntimeConfig>,
 }
 
 impl InstanceBuilder {
@@ -254,10 +419,13 @@ impl InstanceBuilder {
         }
     }
 
-    /// Sets the given key/value string entries on the WASI 'env'.
+    /// Sets the given key/value string entries on the WASI 'env', except for
+    /// any denied by runtime config.
     pub fn env(&mut self, vars: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>) {
         for (k, v) in vars {
-            self.ctx.env(k, v);
+            if self.runtime_config.env_allowed(k.as_ref()) {
+                self.ctx.env(k, v);
+            }
         }
     }
 
@@ -284,14 +452,26 @@ impl FactorInstanceBuilder for InstanceBuilder {
     type InstanceState = InstanceState;
 
     fn build(self) -> anyhow::Result<Self::InstanceState> {
-        let InstanceBuilder { ctx: mut wasi_ctx } = self;
+        let InstanceBuilder {
+            ctx: mut wasi_ctx,
+            tmpfs_dirs,
+            files_quota,
+            runtime_config: _,
+        } = self;
         Ok(InstanceState {
             ctx: wasi_ctx.build(),
+            tmpfs_dirs,
+            files_quota,
         })
     }
 }
 
 impl InstanceBuilder {
+    /// Allows or denies guest host name resolution via `wasi:sockets/ip-name-lookup`.
+    pub fn allow_ip_name_lookup(&mut self, enable: bool) {
+        self.ctx.allow_ip_name_lookup(enable);
+    }
+
     pub fn outbound_socket_addr_check<F, Fut>(&mut self, check: F)
     where
         F: Fn(SocketAddr, SocketAddrUse) -> Fut + Send + Sync + Clone + 'static,
@@ -316,11 +496,31 @@ impl InstanceBuilder {
 
 pub struct InstanceState {
     ctx: WasiCtx,
+    tmpfs_dirs: Vec<tempfile::TempDir>,
+    files_quota: Option<Arc<FilesQuota>>,
+}
+
+impl Drop for InstanceState {
+    fn drop(&mut self) {
+        // Credit the quota with what this instance's tmpfs mounts were
+        // charged, as they are about to be removed
+        if let Some(quota) = &self.files_quota {
+            let paths = self
+                .tmpfs_dirs
+                .iter()
+                .map(|dir| dir.path().to_owned())
+                .collect::<Vec<_>>();
+            if let Ok(used) = quota::disk_usage(&paths) {
+                quota.release(used);
+            }
+        }
+    }
 }
 
 struct WasiImplInner<'a> {
     ctx: &'a mut WasiCtx,
     table: &'a mut ResourceTable,
+    files_quota: Option<&'a Arc<FilesQuota>>,
 }
 
 impl WasiView for WasiImplInner<'_> {
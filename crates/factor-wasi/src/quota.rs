@@ -0,0 +1,673 @@
+//! Enforcement of per-component files quotas.
+//!
+//! Writes that grow a file are charged to the component's quota as they
+//! happen, and space freed by truncating, unlinking or replacing a file is
+//! credited back. Only the sizes of regular files count.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use spin_factors::anyhow;
+use wasmtime::component::Resource;
+use wasmtime_wasi::p2::bindings::filesystem::types::{
+    self, Advice, Descriptor, DescriptorFlags, DescriptorStat, DescriptorType, DirectoryEntry,
+    DirectoryEntryStream, ErrorCode, Filesize, HostDescriptor, HostDirectoryEntryStream,
+    MetadataHashValue, NewTimestamp, OpenFlags, PathFlags,
+};
+use wasmtime_wasi::p2::{
+    DynInputStream, DynOutputStream, FsError, FsResult, IoImpl, IoView, OutputStream, Pollable,
+    StreamError, StreamResult, WasiImpl,
+};
+
+use crate::WasiImplInner;
+
+/// The most disk space a component's files may use, shared by all of its
+/// instances.
+pub(crate) struct FilesQuota {
+    limit: u64,
+    // Measured when the component is first prepared
+    used: OnceLock<AtomicU64>,
+}
+
+impl FilesQuota {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: OnceLock::new(),
+        }
+    }
+
+    /// Measures the files under the given paths, unless they already have
+    /// been.
+    pub fn measure(&self, paths: &[PathBuf]) -> anyhow::Result<()> {
+        if self.used.get().is_none() {
+            let used = disk_usage(paths)?;
+            self.used.get_or_init(|| AtomicU64::new(used));
+        }
+        Ok(())
+    }
+
+    fn used(&self) -> &AtomicU64 {
+        self.used.get_or_init(AtomicU64::default)
+    }
+
+    /// Charges `bytes` to the quota, unless that would exceed it.
+    fn charge(&self, bytes: u64) -> Result<(), ErrorCode> {
+        if bytes == 0 {
+            return Ok(());
+        }
+        self.used()
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&used| used <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|_| ErrorCode::InsufficientSpace)
+    }
+
+    /// Credits `bytes` that are no longer used back to the quota.
+    pub fn release(&self, bytes: u64) {
+        let _ = self
+            .used()
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+}
+
+/// Returns the total size of the files under the given paths.
+pub(crate) fn disk_usage(paths: &[PathBuf]) -> anyhow::Result<u64> {
+    let mut total = 0;
+    for path in paths {
+        for entry in walkdir::WalkDir::new(path) {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Returns how much writing `len` bytes at `offset` grows a file of
+/// `size` bytes.
+fn growth(size: u64, offset: u64, len: u64) -> u64 {
+    if len == 0 {
+        return 0;
+    }
+    offset.saturating_add(len).saturating_sub(size)
+}
+
+fn borrow(fd: &Resource<Descriptor>) -> Resource<Descriptor> {
+    Resource::new_borrow(fd.rep())
+}
+
+/// A `wasi:filesystem/types` host that charges writes to the component's
+/// [`FilesQuota`], if it has one.
+pub(crate) struct FilesImpl<'a>(pub WasiImpl<WasiImplInner<'a>>);
+
+impl<'a> FilesImpl<'a> {
+    /// Reborrows the given host, for the bindings of older WASI versions.
+    pub fn new(wasi: &'a mut WasiImpl<WasiImplInner<'_>>) -> Self {
+        let inner = &mut wasi.0 .0;
+        Self(WasiImpl(IoImpl(WasiImplInner {
+            ctx: &mut *inner.ctx,
+            table: &mut *inner.table,
+            files_quota: inner.files_quota,
+        })))
+    }
+
+    fn quota(&self) -> Option<&'a Arc<FilesQuota>> {
+        self.0 .0 .0.files_quota
+    }
+
+    /// Returns the current size of the file open at `fd`.
+    fn file_size(&mut self, fd: &Resource<Descriptor>) -> FsResult<u64> {
+        let file = self.0.table().get(fd)?.file()?;
+        Ok(file.file.metadata()?.len())
+    }
+
+    /// Returns the status of the regular file at `path`, if there is one.
+    async fn file_stat_at(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        path_flags: PathFlags,
+        path: &str,
+    ) -> Option<DescriptorStat> {
+        HostDescriptor::stat_at(&mut self.0, borrow(fd), path_flags, path.to_owned())
+            .await
+            .ok()
+            .filter(|stat| stat.type_ == DescriptorType::RegularFile)
+    }
+
+    /// Returns the space that unlinking the file at `path` would free.
+    async fn freed_by_unlink(&mut self, fd: &Resource<Descriptor>, path: &str) -> u64 {
+        match self.file_stat_at(fd, PathFlags::empty(), path).await {
+            Some(stat) if stat.link_count == 1 => stat.size,
+            _ => 0,
+        }
+    }
+
+    /// Wraps the output stream `stream` to charge writes to `quota`.
+    fn charge_stream(
+        &mut self,
+        stream: Resource<DynOutputStream>,
+        quota: &Arc<FilesQuota>,
+        position: Option<u64>,
+        size: u64,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let table = self.0.table();
+        let inner = table.delete(stream)?;
+        let stream: DynOutputStream = Box::new(QuotaOutputStream {
+            inner,
+            quota: quota.clone(),
+            position,
+            size,
+        });
+        Ok(table.push(stream)?)
+    }
+}
+
+impl types::Host for FilesImpl<'_> {
+    fn convert_error_code(&mut self, err: FsError) -> anyhow::Result<ErrorCode> {
+        types::Host::convert_error_code(&mut self.0, err)
+    }
+
+    fn filesystem_error_code(
+        &mut self,
+        err: Resource<anyhow::Error>,
+    ) -> anyhow::Result<Option<ErrorCode>> {
+        types::Host::filesystem_error_code(&mut self.0, err)
+    }
+}
+
+impl HostDescriptor for FilesImpl<'_> {
+    fn read_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: Filesize,
+    ) -> FsResult<Resource<DynInputStream>> {
+        HostDescriptor::read_via_stream(&mut self.0, fd, offset)
+    }
+
+    fn write_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: Filesize,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let Some(quota) = self.quota() else {
+            return HostDescriptor::write_via_stream(&mut self.0, fd, offset);
+        };
+        let size = self.file_size(&fd)?;
+        let stream = HostDescriptor::write_via_stream(&mut self.0, fd, offset)?;
+        self.charge_stream(stream, quota, Some(offset), size)
+    }
+
+    fn append_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let Some(quota) = self.quota() else {
+            return HostDescriptor::append_via_stream(&mut self.0, fd);
+        };
+        let stream = HostDescriptor::append_via_stream(&mut self.0, fd)?;
+        self.charge_stream(stream, quota, None, 0)
+    }
+
+    async fn advise(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: Filesize,
+        length: Filesize,
+        advice: Advice,
+    ) -> FsResult<()> {
+        HostDescriptor::advise(&mut self.0, fd, offset, length, advice).await
+    }
+
+    async fn sync_data(&mut self, fd: Resource<Descriptor>) -> FsResult<()> {
+        HostDescriptor::sync_data(&mut self.0, fd).await
+    }
+
+    async fn get_flags(&mut self, fd: Resource<Descriptor>) -> FsResult<DescriptorFlags> {
+        HostDescriptor::get_flags(&mut self.0, fd).await
+    }
+
+    async fn get_type(&mut self, fd: Resource<Descriptor>) -> FsResult<DescriptorType> {
+        HostDescriptor::get_type(&mut self.0, fd).await
+    }
+
+    async fn set_size(&mut self, fd: Resource<Descriptor>, size: Filesize) -> FsResult<()> {
+        let Some(quota) = self.quota() else {
+            return HostDescriptor::set_size(&mut self.0, fd, size).await;
+        };
+        let old_size = self.file_size(&fd)?;
+        quota.charge(size.saturating_sub(old_size))?;
+        let result = HostDescriptor::set_size(&mut self.0, fd, size).await;
+        if result.is_ok() {
+            quota.release(old_size.saturating_sub(size));
+        } else {
+            quota.release(size.saturating_sub(old_size));
+        }
+        result
+    }
+
+    async fn set_times(
+        &mut self,
+        fd: Resource<Descriptor>,
+        atim: NewTimestamp,
+        mtim: NewTimestamp,
+    ) -> FsResult<()> {
+        HostDescriptor::set_times(&mut self.0, fd, atim, mtim).await
+    }
+
+    async fn read(
+        &mut self,
+        fd: Resource<Descriptor>,
+        length: Filesize,
+        offset: Filesize,
+    ) -> FsResult<(Vec<u8>, bool)> {
+        HostDescriptor::read(&mut self.0, fd, length, offset).await
+    }
+
+    async fn write(
+        &mut self,
+        fd: Resource<Descriptor>,
+        buffer: Vec<u8>,
+        offset: Filesize,
+    ) -> FsResult<Filesize> {
+        let Some(quota) = self.quota() else {
+            return HostDescriptor::write(&mut self.0, fd, buffer, offset).await;
+        };
+        let size = self.file_size(&fd)?;
+        let charged = growth(size, offset, buffer.len() as u64);
+        quota.charge(charged)?;
+        let result = HostDescriptor::write(&mut self.0, fd, buffer, offset).await;
+        // Credit back whatever wasn't written
+        let written = result.as_ref().copied().unwrap_or_default();
+        quota.release(charged - growth(size, offset, written).min(charged));
+        result
+    }
+
+    async fn read_directory(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<Resource<DirectoryEntryStream>> {
+        HostDescriptor::read_directory(&mut self.0, fd).await
+    }
+
+    async fn sync(&mut self, fd: Resource<Descriptor>) -> FsResult<()> {
+        HostDescriptor::sync(&mut self.0, fd).await
+    }
+
+    async fn create_directory_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        HostDescriptor::create_directory_at(&mut self.0, fd, path).await
+    }
+
+    async fn stat(&mut self, fd: Resource<Descriptor>) -> FsResult<DescriptorStat> {
+        HostDescriptor::stat(&mut self.0, fd).await
+    }
+
+    async fn stat_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: PathFlags,
+        path: String,
+    ) -> FsResult<DescriptorStat> {
+        HostDescriptor::stat_at(&mut self.0, fd, path_flags, path).await
+    }
+
+    async fn set_times_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: PathFlags,
+        path: String,
+        atim: NewTimestamp,
+        mtim: NewTimestamp,
+    ) -> FsResult<()> {
+        HostDescriptor::set_times_at(&mut self.0, fd, path_flags, path, atim, mtim).await
+    }
+
+    async fn link_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        old_path_flags: PathFlags,
+        old_path: String,
+        new_descriptor: Resource<Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        HostDescriptor::link_at(
+            &mut self.0,
+            fd,
+            old_path_flags,
+            old_path,
+            new_descriptor,
+            new_path,
+        )
+        .await
+    }
+
+    async fn open_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: PathFlags,
+        path: String,
+        oflags: OpenFlags,
+        flags: DescriptorFlags,
+    ) -> FsResult<Resource<Descriptor>> {
+        let quota = self.quota();
+        let truncated = match quota {
+            Some(_) if oflags.contains(OpenFlags::TRUNCATE) => self
+                .file_stat_at(&fd, path_flags, &path)
+                .await
+                .map_or(0, |stat| stat.size),
+            _ => 0,
+        };
+        let result =
+            HostDescriptor::open_at(&mut self.0, fd, path_flags, path, oflags, flags).await;
+        if let (Some(quota), Ok(_)) = (quota, &result) {
+            quota.release(truncated);
+        }
+        result
+    }
+
+    async fn readlink_at(&mut self, fd: Resource<Descriptor>, path: String) -> FsResult<String> {
+        HostDescriptor::readlink_at(&mut self.0, fd, path).await
+    }
+
+    async fn remove_directory_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        HostDescriptor::remove_directory_at(&mut self.0, fd, path).await
+    }
+
+    async fn rename_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        old_path: String,
+        new_descriptor: Resource<Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        let quota = self.quota();
+        let mut replaced = 0;
+        if quota.is_some() {
+            // Renaming a file over itself frees nothing
+            let old_hash = HostDescriptor::metadata_hash_at(
+                &mut self.0,
+                borrow(&fd),
+                PathFlags::empty(),
+                old_path.clone(),
+            )
+            .await;
+            let new_hash = HostDescriptor::metadata_hash_at(
+                &mut self.0,
+                borrow(&new_descriptor),
+                PathFlags::empty(),
+                new_path.clone(),
+            )
+            .await;
+            let same_file = matches!(
+                (old_hash, new_hash),
+                (Ok(old), Ok(new)) if old.lower == new.lower && old.upper == new.upper
+            );
+            if !same_file {
+                replaced = self.freed_by_unlink(&new_descriptor, &new_path).await;
+            }
+        }
+        let result =
+            HostDescriptor::rename_at(&mut self.0, fd, old_path, new_descriptor, new_path).await;
+        if let (Some(quota), Ok(())) = (quota, &result) {
+            quota.release(replaced);
+        }
+        result
+    }
+
+    async fn symlink_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        old_path: String,
+        new_path: String,
+    ) -> FsResult<()> {
+        HostDescriptor::symlink_at(&mut self.0, fd, old_path, new_path).await
+    }
+
+    async fn unlink_file_at(&mut self, fd: Resource<Descriptor>, path: String) -> FsResult<()> {
+        let quota = self.quota();
+        let freed = match quota {
+            Some(_) => self.freed_by_unlink(&fd, &path).await,
+            None => 0,
+        };
+        let result = HostDescriptor::unlink_file_at(&mut self.0, fd, path).await;
+        if let (Some(quota), Ok(())) = (quota, &result) {
+            quota.release(freed);
+        }
+        result
+    }
+
+    async fn is_same_object(
+        &mut self,
+        fd: Resource<Descriptor>,
+        other: Resource<Descriptor>,
+    ) -> anyhow::Result<bool> {
+        HostDescriptor::is_same_object(&mut self.0, fd, other).await
+    }
+
+    async fn metadata_hash(&mut self, fd: Resource<Descriptor>) -> FsResult<MetadataHashValue> {
+        HostDescriptor::metadata_hash(&mut self.0, fd).await
+    }
+
+    async fn metadata_hash_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: PathFlags,
+        path: String,
+    ) -> FsResult<MetadataHashValue> {
+        HostDescriptor::metadata_hash_at(&mut self.0, fd, path_flags, path).await
+    }
+
+    fn drop(&mut self, fd: Resource<Descriptor>) -> anyhow::Result<()> {
+        HostDescriptor::drop(&mut self.0, fd)
+    }
+}
+
+impl HostDirectoryEntryStream for FilesImpl<'_> {
+    async fn read_directory_entry(
+        &mut self,
+        stream: Resource<DirectoryEntryStream>,
+    ) -> FsResult<Option<DirectoryEntry>> {
+        HostDirectoryEntryStream::read_directory_entry(&mut self.0, stream).await
+    }
+
+    fn drop(&mut self, stream: Resource<DirectoryEntryStream>) -> anyhow::Result<()> {
+        HostDirectoryEntryStream::drop(&mut self.0, stream)
+    }
+}
+
+/// An [`OutputStream`] to a file that charges the writes growing it to a
+/// [`FilesQuota`].
+struct QuotaOutputStream {
+    inner: DynOutputStream,
+    quota: Arc<FilesQuota>,
+    // `None` when appending
+    position: Option<u64>,
+    size: u64,
+}
+
+#[async_trait]
+impl OutputStream for QuotaOutputStream {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        let len = bytes.len() as u64;
+        let charged = match self.position {
+            Some(position) => growth(self.size, position, len),
+            None => len,
+        };
+        if self.quota.charge(charged).is_err() {
+            let err = std::io::Error::from(std::io::ErrorKind::StorageFull);
+            return Err(StreamError::LastOperationFailed(err.into()));
+        }
+        if let Err(err) = self.inner.write(bytes) {
+            self.quota.release(charged);
+            return Err(err);
+        }
+        if let Some(position) = &mut self.position {
+            *position += len;
+        }
+        self.size += charged;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        self.inner.flush()
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        self.inner.check_write()
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await
+    }
+}
+
+#[async_trait]
+impl Pollable for QuotaOutputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime_wasi::p2::{bindings::filesystem::preopens::Host as _, WasiCtx, WasiCtxBuilder};
+    use wasmtime_wasi::{DirPerms, FilePerms, ResourceTable};
+
+    use super::*;
+
+    fn error_code(err: FsError) -> ErrorCode {
+        err.downcast().unwrap()
+    }
+
+    struct Fixture {
+        ctx: WasiCtx,
+        table: ResourceTable,
+        quota: Arc<FilesQuota>,
+        _dir: tempfile::TempDir,
+    }
+
+    impl Fixture {
+        fn new(limit: u64, existing: &[u8]) -> anyhow::Result<Self> {
+            let dir = tempfile::tempdir()?;
+            std::fs::write(dir.path().join("existing"), existing)?;
+            let quota = Arc::new(FilesQuota::new(limit));
+            quota.measure(&[dir.path().to_owned()])?;
+            let ctx = WasiCtxBuilder::new()
+                .preopened_dir(dir.path(), "/", DirPerms::all(), FilePerms::all())?
+                .build();
+            Ok(Self {
+                ctx,
+                table: ResourceTable::new(),
+                quota,
+                _dir: dir,
+            })
+        }
+
+        fn files(&mut self) -> FilesImpl<'_> {
+            FilesImpl(WasiImpl(IoImpl(WasiImplInner {
+                ctx: &mut self.ctx,
+                table: &mut self.table,
+                files_quota: Some(&self.quota),
+            })))
+        }
+
+        fn used(&self) -> u64 {
+            self.quota.used().load(Ordering::Relaxed)
+        }
+    }
+
+    async fn open(files: &mut FilesImpl<'_>, path: &str) -> FsResult<Resource<Descriptor>> {
+        let (root, _) = files.0.get_directories()?.remove(0);
+        files
+            .open_at(
+                root,
+                PathFlags::empty(),
+                path.into(),
+                OpenFlags::CREATE,
+                DescriptorFlags::READ | DescriptorFlags::WRITE,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn writes_are_charged_to_the_quota() -> anyhow::Result<()> {
+        let mut fixture = Fixture::new(10, b"1234")?;
+        assert_eq!(fixture.used(), 4);
+
+        let mut files = fixture.files();
+        let file = open(&mut files, "new").await?;
+        assert_eq!(files.write(borrow(&file), vec![0; 4], 0).await?, 4);
+        // Overwriting doesn't grow the file
+        assert_eq!(files.write(borrow(&file), vec![1; 4], 0).await?, 4);
+        let err = files.write(borrow(&file), vec![0; 4], 4).await.unwrap_err();
+        assert_eq!(error_code(err), ErrorCode::InsufficientSpace);
+        let err = files.set_size(borrow(&file), 7).await.unwrap_err();
+        assert_eq!(error_code(err), ErrorCode::InsufficientSpace);
+        files.set_size(borrow(&file), 6).await?;
+
+        assert_eq!(fixture.used(), 10);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_writes_are_charged_to_the_quota() -> anyhow::Result<()> {
+        let mut fixture = Fixture::new(10, b"")?;
+
+        let mut files = fixture.files();
+        let file = open(&mut files, "new").await?;
+        let stream = files.append_via_stream(borrow(&file))?;
+        let stream = files.0.table().get_mut(&stream)?;
+        stream.write(Bytes::from_static(b"12345678"))?;
+        assert!(matches!(
+            stream.write(Bytes::from_static(b"123")),
+            Err(StreamError::LastOperationFailed(_))
+        ));
+
+        assert_eq!(fixture.used(), 8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn freed_space_is_credited_back() -> anyhow::Result<()> {
+        let mut fixture = Fixture::new(10, b"12345678")?;
+
+        let mut files = fixture.files();
+        let (root, _) = files.0.get_directories()?.remove(0);
+        let file = open(&mut files, "new").await?;
+        files.write(borrow(&file), vec![0; 2], 0).await?;
+        // Replacing the existing file frees its space
+        files
+            .rename_at(
+                borrow(&root),
+                "new".into(),
+                borrow(&root),
+                "existing".into(),
+            )
+            .await?;
+        assert_eq!(fixture.used(), 2);
+
+        let mut files = fixture.files();
+        files.unlink_file_at(root, "existing".into()).await?;
+        assert_eq!(fixture.used(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+pub mod spin;
+
+use std::time::{Duration, SystemTime};
+
+/// Runtime configuration for WASI.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeConfig {
+    /// The time components see; the host's time if unset.
+    pub clock: Option<VirtualClock>,
+    /// Seeds components' randomness so that it is the same on every run; the
+    /// host's randomness if unset.
+    pub random_seed: Option<u64>,
+    /// Host environment variables passed through to components.
+    pub inherit_env: Vec<EnvPattern>,
+    /// Environment variables never passed to components, whoever sets them.
+    pub deny_env: Vec<EnvPattern>,
+}
+
+impl RuntimeConfig {
+    /// Returns whether the given environment variable may be passed to
+    /// components.
+    pub fn env_allowed(&self, name: &str) -> bool {
+        !self.deny_env.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+/// A virtualized wall clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualClock {
+    /// The clock always reads the given time.
+    Fixed(SystemTime),
+    /// The clock reads the host's time, shifted by the given number of
+    /// seconds.
+    Offset(i64),
+}
+
+impl VirtualClock {
+    /// The time the clock reads, as a duration since the Unix epoch.
+    pub fn now(&self) -> Duration {
+        let now = match self {
+            Self::Fixed(time) => *time,
+            Self::Offset(secs) => {
+                let offset = Duration::from_secs(secs.unsigned_abs());
+                if *secs < 0 {
+                    SystemTime::now() - offset
+                } else {
+                    SystemTime::now() + offset
+                }
+            }
+        };
+        now.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// An environment variable name, or a name prefix ending in `*`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvPattern(String);
+
+impl EnvPattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    /// Returns whether the given variable name matches the pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        match self.0.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == self.0,
+        }
+    }
+}
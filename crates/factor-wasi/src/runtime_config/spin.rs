@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use spin_factors::{
+    anyhow::{self, Context},
+    runtime_config::toml::GetTomlValue,
+};
+
+use super::{EnvPattern, RuntimeConfig, VirtualClock};
+
+/// Get the runtime configuration for WASI from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [wasi]
+/// clock = { fixed = "2024-01-01T00:00:00Z" }  # or { offset_secs = -3600 }
+/// random_seed = 42
+/// inherit_env = ["TZ", "LC_*"]
+/// deny_env = ["AWS_*"]
+/// ```
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("wasi") else {
+        return Ok(None);
+    };
+    let toml: WasiToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [wasi] table")?;
+    let clock = match toml.clock {
+        None => None,
+        Some(ClockToml::Fixed(time)) => {
+            let time = chrono::DateTime::parse_from_rfc3339(&time)
+                .with_context(|| format!("invalid [wasi] fixed clock time {time:?}"))?;
+            Some(VirtualClock::Fixed(SystemTime::from(time)))
+        }
+        Some(ClockToml::OffsetSecs(secs)) => Some(VirtualClock::Offset(secs)),
+    };
+    Ok(Some(RuntimeConfig {
+        clock,
+        random_seed: toml.random_seed,
+        inherit_env: toml.inherit_env.into_iter().map(EnvPattern::new).collect(),
+        deny_env: toml.deny_env.into_iter().map(EnvPattern::new).collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WasiToml {
+    clock: Option<ClockToml>,
+    random_seed: Option<u64>,
+    #[serde(default)]
+    inherit_env: Vec<String>,
+    #[serde(default)]
+    deny_env: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum ClockToml {
+    Fixed(String),
+    OffsetSecs(i64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_wasi_table() {
+        let table: toml::Table = toml::toml! {
+            [wasi]
+            clock = { fixed = "1970-01-01T00:01:40Z" }
+            random_seed = 42
+            inherit_env = ["TZ"]
+            deny_env = ["AWS_*"]
+        };
+        let config = config_from_table(&table).unwrap().unwrap();
+        assert_eq!(
+            config.clock.unwrap().now(),
+            std::time::Duration::from_secs(100)
+        );
+        assert_eq!(config.random_seed, Some(42));
+        assert!(config.inherit_env[0].matches("TZ"));
+        assert!(!config.env_allowed("AWS_SECRET_ACCESS_KEY"));
+        assert!(config.env_allowed("AWS"));
+    }
+
+    #[test]
+    fn missing_table_is_none() {
+        let table = toml::Table::new();
+        assert!(config_from_table(&table).unwrap().is_none());
+    }
+}
@@ -109,6 +109,7 @@ use wasi::sockets::udp::{
     IncomingDatagram, IncomingDatagramStream, OutgoingDatagram, OutgoingDatagramStream, UdpSocket,
 };
 
+use crate::quota::FilesImpl;
 use crate::WasiImplInner;
 
 pub fn add_to_linker<T>(
@@ -194,10 +195,7 @@ where
     }
 }
 
-impl<T> wasi::filesystem::types::HostDescriptor for WasiImpl<T>
-where
-    T: WasiView,
-{
+impl wasi::filesystem::types::HostDescriptor for WasiImpl<WasiImplInner<'_>> {
     fn read_via_stream(
         &mut self,
         self_: Resource<Descriptor>,
@@ -214,7 +212,9 @@ where
         offset: Filesize,
     ) -> wasmtime::Result<Result<Resource<OutputStream>, FsErrorCode>> {
         convert_result(latest::filesystem::types::HostDescriptor::write_via_stream(
-            self, self_, offset,
+            &mut FilesImpl::new(self),
+            self_,
+            offset,
         ))
     }
 
@@ -222,7 +222,12 @@ where
         &mut self,
         self_: Resource<Descriptor>,
     ) -> wasmtime::Result<Result<Resource<OutputStream>, FsErrorCode>> {
-        convert_result(latest::filesystem::types::HostDescriptor::append_via_stream(self, self_))
+        convert_result(
+            latest::filesystem::types::HostDescriptor::append_via_stream(
+                &mut FilesImpl::new(self),
+                self_,
+            ),
+        )
     }
 
     async fn advise(
@@ -270,7 +275,14 @@ where
         self_: Resource<Descriptor>,
         size: Filesize,
     ) -> wasmtime::Result<Result<(), FsErrorCode>> {
-        convert_result(latest::filesystem::types::HostDescriptor::set_size(self, self_, size).await)
+        convert_result(
+            latest::filesystem::types::HostDescriptor::set_size(
+                &mut FilesImpl::new(self),
+                self_,
+                size,
+            )
+            .await,
+        )
     }
 
     async fn set_times(
@@ -308,7 +320,13 @@ where
         offset: Filesize,
     ) -> wasmtime::Result<Result<Filesize, FsErrorCode>> {
         convert_result(
-            latest::filesystem::types::HostDescriptor::write(self, self_, buffer, offset).await,
+            latest::filesystem::types::HostDescriptor::write(
+                &mut FilesImpl::new(self),
+                self_,
+                buffer,
+                offset,
+            )
+            .await,
         )
     }
 
@@ -412,7 +430,7 @@ where
     ) -> wasmtime::Result<Result<Resource<Descriptor>, FsErrorCode>> {
         convert_result(
             latest::filesystem::types::HostDescriptor::open_at(
-                self,
+                &mut FilesImpl::new(self),
                 self_,
                 path_flags.into(),
                 path,
@@ -452,7 +470,7 @@ where
     ) -> wasmtime::Result<Result<(), FsErrorCode>> {
         convert_result(
             latest::filesystem::types::HostDescriptor::rename_at(
-                self,
+                &mut FilesImpl::new(self),
                 self_,
                 old_path,
                 new_descriptor,
@@ -480,7 +498,12 @@ where
         path: String,
     ) -> wasmtime::Result<Result<(), FsErrorCode>> {
         convert_result(
-            latest::filesystem::types::HostDescriptor::unlink_file_at(self, self_, path).await,
+            latest::filesystem::types::HostDescriptor::unlink_file_at(
+                &mut FilesImpl::new(self),
+                self_,
+                path,
+            )
+            .await,
         )
     }
 
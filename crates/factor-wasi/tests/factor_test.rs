@@ -1,5 +1,10 @@
-use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
-use spin_factors::{anyhow, RuntimeFactors};
+use std::path::PathBuf;
+
+use spin_factor_wasi::{
+    runtime_config::{EnvPattern, RuntimeConfig, VirtualClock},
+    DummyFilesMounter, FilesMounter, MountFilesContext, WasiFactor,
+};
+use spin_factors::{anyhow, AppComponent, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use wasmtime_wasi::p2::bindings::cli::environment::Host;
 
@@ -28,3 +33,119 @@ async fn environment_works() -> anyhow::Result<()> {
     assert_eq!(val.as_deref(), Some("bar"));
     Ok(())
 }
+
+#[tokio::test]
+async fn tmpfs_mounts_are_preopened() -> anyhow::Result<()> {
+    use wasmtime_wasi::p2::bindings::filesystem::preopens::Host as _;
+
+    let factors = TestFactors {
+        wasi: WasiFactor::new(DummyFilesMounter),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        files = [{ tmpfs = "/tmp" }]
+    });
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+
+    let guest_paths = wasi
+        .get_directories()?
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect::<Vec<_>>();
+    assert_eq!(guest_paths, ["/tmp"]);
+    Ok(())
+}
+
+/// Mounts a writable directory at `/`, whatever the component's files.
+struct WritableDirMounter(PathBuf);
+
+impl FilesMounter for WritableDirMounter {
+    fn mount_files(
+        &self,
+        _app_component: &AppComponent,
+        mut ctx: MountFilesContext,
+    ) -> anyhow::Result<()> {
+        ctx.preopened_dir(&self.0, "/", true)
+    }
+}
+
+#[tokio::test]
+async fn read_only_files_are_not_writable() -> anyhow::Result<()> {
+    use wasmtime_wasi::p2::bindings::filesystem::{
+        preopens::Host as _,
+        types::{ErrorCode, HostDescriptor as _},
+    };
+
+    let dir = tempfile::tempdir()?;
+
+    let factors = TestFactors {
+        wasi: WasiFactor::new(WritableDirMounter(dir.path().to_owned())),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+    let (root, _) = wasi.get_directories()?.remove(0);
+    wasi.create_directory_at(root, "writable".into()).await?;
+
+    let factors = TestFactors {
+        wasi: WasiFactor::new(WritableDirMounter(dir.path().to_owned())),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        read_only_files = true
+    });
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+    let (root, _) = wasi.get_directories()?.remove(0);
+    let err = wasi
+        .create_directory_at(root, "read-only".into())
+        .await
+        .unwrap_err();
+    assert_eq!(err.downcast()?, ErrorCode::NotPermitted);
+
+    assert!(dir.path().join("writable").is_dir());
+    assert!(!dir.path().join("read-only").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn runtime_config_virtualizes_clock_and_env() -> anyhow::Result<()> {
+    use std::time::{Duration, SystemTime};
+    use wasmtime_wasi::p2::bindings::clocks::wall_clock::Host as _;
+
+    let factors = TestFactors {
+        wasi: WasiFactor::new(DummyFilesMounter),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            environment = { FOO = "bar", SECRET_TOKEN = "hunter2" }
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            wasi: Some(RuntimeConfig {
+                clock: Some(VirtualClock::Fixed(
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(100),
+                )),
+                deny_env: vec![EnvPattern::new("SECRET_*")],
+                ..Default::default()
+            }),
+        })?;
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+
+    assert_eq!(wasi.now()?.seconds, 100);
+    let names = wasi
+        .get_environment()?
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["FOO"]);
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use spin_world::spin::workflow::workflow::{self, Error, RunId, RunStatus};
+use tracing::{instrument, Level};
+
+use crate::{Run, RunState, WorkflowStore};
+
+pub struct InstanceState {
+    store: WorkflowStore,
+    /// The names of the app's workflows.
+    workflows: Vec<String>,
+}
+
+impl InstanceState {
+    pub(crate) fn new(store: WorkflowStore, workflows: Vec<String>) -> Self {
+        Self { store, workflows }
+    }
+
+    /// Returns the run with the given ID.
+    async fn run(&self, id: &str) -> Result<Run, Error> {
+        self.store
+            .get(id)
+            .await
+            .map_err(other_error)?
+            .ok_or_else(|| Error::NoSuchRun(id.to_owned()))
+    }
+
+    /// Returns the run with the given ID, if it has not finished.
+    async fn unfinished_run(&self, id: &str) -> Result<Run, Error> {
+        let run = self.run(id).await?;
+        if run.state.is_finished() || self.store.is_cancelled(id).await.map_err(other_error)? {
+            return Err(Error::RunFinished(id.to_owned()));
+        }
+        Ok(run)
+    }
+}
+
+impl workflow::Host for InstanceState {
+    #[instrument(name = "spin_workflow.start", skip(self, input), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn start(&mut self, workflow: String, input: Vec<u8>) -> Result<RunId, Error> {
+        if !self.workflows.contains(&workflow) {
+            return Err(Error::NoSuchWorkflow(workflow));
+        }
+        self.store
+            .start(&workflow, input)
+            .await
+            .map_err(other_error)
+    }
+
+    #[instrument(name = "spin_workflow.signal", skip(self, payload), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn signal(&mut self, id: RunId, name: String, payload: Vec<u8>) -> Result<(), Error> {
+        self.unfinished_run(&id).await?;
+        self.store
+            .signal(&id, &name, &payload)
+            .await
+            .map_err(other_error)
+    }
+
+    #[instrument(name = "spin_workflow.status", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn status(&mut self, id: RunId) -> Result<RunStatus, Error> {
+        let run = self.run(&id).await?;
+        let state = if !run.state.is_finished()
+            && self.store.is_cancelled(&id).await.map_err(other_error)?
+        {
+            RunState::Cancelled
+        } else {
+            run.state
+        };
+        Ok(RunStatus {
+            workflow: run.workflow,
+            state: state.into(),
+            steps_completed: run.step,
+            output: (state == RunState::Completed).then_some(run.data),
+            error: run.error,
+        })
+    }
+
+    #[instrument(name = "spin_workflow.cancel", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn cancel(&mut self, id: RunId) -> Result<(), Error> {
+        self.unfinished_run(&id).await?;
+        self.store.cancel(&id).await.map_err(other_error)
+    }
+
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl From<RunState> for workflow::RunState {
+    fn from(state: RunState) -> Self {
+        match state {
+            RunState::Running => Self::Running,
+            RunState::Sleeping => Self::Sleeping,
+            RunState::Waiting => Self::Waiting,
+            RunState::Completed => Self::Completed,
+            RunState::Failed => Self::Failed,
+            RunState::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+fn other_error(err: anyhow::Error) -> Error {
+    Error::Other(format!("{err:#}"))
+}
@@ -0,0 +1,141 @@
+mod host;
+pub mod runtime_config;
+mod store;
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Context};
+use serde::Deserialize;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
+};
+
+pub use host::InstanceState;
+pub use runtime_config::RuntimeConfig;
+pub use store::{Outcome, Run, RunState, Signal, WorkflowStore};
+
+/// The trigger type that runs workflow steps.
+pub const WORKFLOW_TRIGGER_TYPE: &str = "workflow";
+
+/// A factor that lets components start, signal and query durable workflows,
+/// whose steps the workflow trigger runs.
+///
+/// Runs are persisted in one of the app's key-value stores, so this factor
+/// must come after [`KeyValueFactor`].
+#[derive(Default)]
+pub struct WorkflowsFactor {
+    _priv: (),
+}
+
+impl WorkflowsFactor {
+    /// Create a new WorkflowsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for WorkflowsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceBuilder;
+
+    fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::workflow::workflow::add_to_linker)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        let store_manager = ctx.app_state::<KeyValueFactor>()?.store_manager();
+
+        // Each workflow trigger names a workflow whose steps its component runs.
+        let mut workflow_components = HashMap::new();
+        for trigger in ctx.app().triggers_with_type(WORKFLOW_TRIGGER_TYPE) {
+            let config: WorkflowTriggerConfig = trigger
+                .typed_config()
+                .with_context(|| format!("invalid workflow trigger {:?}", trigger.id()))?;
+            let component_id = trigger.component()?.id().to_string();
+            if workflow_components
+                .insert(config.workflow.clone(), component_id)
+                .is_some()
+            {
+                bail!(
+                    "workflow {:?} is defined by more than one workflow trigger",
+                    config.workflow
+                );
+            }
+        }
+        let label = runtime_config.key_value_store.clone();
+        ensure!(
+            workflow_components.is_empty() || store_manager.is_defined(&label),
+            "workflows are configured to use key-value store {label:?}, which is not defined"
+        );
+        let store = WorkflowStore::new(store_manager, label);
+
+        Ok(AppState {
+            store,
+            workflow_components,
+            runtime_config,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<InstanceBuilder> {
+        let app_state = ctx.app_state();
+        Ok(InstanceBuilder {
+            store: app_state.store.clone(),
+            workflows: app_state.workflow_components.keys().cloned().collect(),
+        })
+    }
+}
+
+/// The part of a workflow trigger's config this factor needs.
+#[derive(Deserialize)]
+struct WorkflowTriggerConfig {
+    workflow: String,
+}
+
+pub struct AppState {
+    /// Where the app's runs are persisted.
+    store: WorkflowStore,
+    /// The ID of the component running each workflow's steps, keyed by
+    /// workflow name.
+    workflow_components: HashMap<String, String>,
+    runtime_config: RuntimeConfig,
+}
+
+impl AppState {
+    /// Returns the store the app's runs are persisted in.
+    pub fn workflow_store(&self) -> &WorkflowStore {
+        &self.store
+    }
+
+    /// Returns the ID of the component which runs the named workflow's steps.
+    pub fn workflow_component(&self, workflow: &str) -> Option<&str> {
+        self.workflow_components.get(workflow).map(String::as_str)
+    }
+
+    /// Returns the retry and storage configuration for runs.
+    pub fn runtime_config(&self) -> &RuntimeConfig {
+        &self.runtime_config
+    }
+}
+
+pub struct InstanceBuilder {
+    store: WorkflowStore,
+    workflows: Vec<String>,
+}
+
+impl FactorInstanceBuilder for InstanceBuilder {
+    type InstanceState = InstanceState;
+
+    fn build(self) -> anyhow::Result<Self::InstanceState> {
+        Ok(InstanceState::new(self.store, self.workflows))
+    }
+}
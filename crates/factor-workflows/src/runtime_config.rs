@@ -0,0 +1,41 @@
+pub mod spin;
+
+use std::time::Duration;
+
+/// The key-value store runs are persisted in if runtime config doesn't say.
+pub const DEFAULT_KEY_VALUE_STORE: &str = "default";
+
+/// The longest delay before retrying a failed step.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Runtime configuration for workflows.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store runs are persisted in.
+    pub key_value_store: String,
+    /// How many times a step is attempted before its run fails.
+    pub max_attempts: u32,
+    /// The delay before the first retry of a failed step, which doubles with
+    /// each further attempt.
+    pub retry_backoff: Duration,
+}
+
+impl RuntimeConfig {
+    /// The delay before retrying a step whose `attempt` failed.
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.retry_backoff
+            .saturating_mul(factor)
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            key_value_store: DEFAULT_KEY_VALUE_STORE.into(),
+            max_attempts: 5,
+            retry_backoff: Duration::from_secs(1),
+        }
+    }
+}
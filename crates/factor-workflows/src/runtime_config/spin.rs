@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use serde::Deserialize;
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for workflows from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [workflows]
+/// key_value_store = "workflows"
+/// max_attempts = 5
+/// retry_backoff_ms = 1000
+/// ```
+///
+/// Each key is optional and defaults to the value shown, except for
+/// `key_value_store`, which defaults to `"default"`.
+pub fn config_from_table(table: &impl GetTomlValue) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("workflows") else {
+        return Ok(None);
+    };
+    let toml: WorkflowsToml = value
+        .clone()
+        .try_into()
+        .context("failed to parse [workflows] table")?;
+    let default = RuntimeConfig::default();
+    let max_attempts = toml.max_attempts.unwrap_or(default.max_attempts);
+    ensure!(
+        max_attempts > 0,
+        "[workflows] max_attempts must be at least 1"
+    );
+    Ok(Some(RuntimeConfig {
+        key_value_store: toml.key_value_store.unwrap_or(default.key_value_store),
+        max_attempts,
+        retry_backoff: toml
+            .retry_backoff_ms
+            .map_or(default.retry_backoff, Duration::from_millis),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkflowsToml {
+    key_value_store: Option<String>,
+    max_attempts: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+}
@@ -0,0 +1,467 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, StoreManager};
+
+use crate::RuntimeConfig;
+
+/// Prefix for the keys of unfinished runs in the backing store.
+const ACTIVE_PREFIX: &str = "spin-workflows:active:";
+/// Prefix for the keys of finished runs in the backing store.
+const FINISHED_PREFIX: &str = "spin-workflows:finished:";
+/// Prefix for the keys of signals waiting to be delivered.
+const SIGNAL_PREFIX: &str = "spin-workflows:signal:";
+/// Prefix for the keys marking runs to be cancelled.
+const CANCEL_PREFIX: &str = "spin-workflows:cancel:";
+
+/// Where a run is in its life.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Running,
+    Sleeping,
+    Waiting,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl RunState {
+    /// Whether the run will never run another step.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A persisted run of a workflow.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub workflow: String,
+    pub state: RunState,
+    /// The index of the next step, which is also the number of steps that
+    /// have finished.
+    pub step: u32,
+    /// The attempt at the next step, starting from 1.
+    pub attempt: u32,
+    /// The state for the next step or, once the run has completed, its
+    /// output.
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+    /// When the next step is due, in milliseconds since the Unix epoch. For a
+    /// waiting run, this is when its wait times out.
+    pub wake_at: Option<u64>,
+    /// The signal a waiting run is waiting for.
+    pub waiting_for: Option<String>,
+    /// Whether the next step follows a wait that timed out.
+    pub timed_out: bool,
+    /// Why the run failed.
+    pub error: Option<String>,
+}
+
+/// What happens to a run after a step.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Next(Vec<u8>),
+    Sleep {
+        duration_ms: u64,
+        state: Vec<u8>,
+    },
+    Wait {
+        signal: String,
+        timeout_ms: Option<u64>,
+        state: Vec<u8>,
+    },
+    Complete(Vec<u8>),
+    Fail(String),
+}
+
+impl Run {
+    fn new(workflow: &str, input: Vec<u8>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            workflow: workflow.to_owned(),
+            state: RunState::Running,
+            step: 0,
+            attempt: 1,
+            data: input,
+            wake_at: None,
+            waiting_for: None,
+            timed_out: false,
+            error: None,
+        }
+    }
+
+    /// Wakes the run if its next step is due at `now`, given the signals
+    /// waiting for it, and returns whether it is.
+    pub fn wake(&mut self, now: u64, signals: &[Signal]) -> bool {
+        let timer_due = self.wake_at.is_some_and(|wake_at| wake_at <= now);
+        let due = match self.state {
+            RunState::Running | RunState::Sleeping => self.wake_at.is_none() || timer_due,
+            RunState::Waiting => {
+                let signalled = signals
+                    .iter()
+                    .any(|signal| Some(&signal.name) == self.waiting_for.as_ref());
+                if signalled || timer_due {
+                    self.timed_out = !signalled;
+                }
+                signalled || timer_due
+            }
+            _ => false,
+        };
+        if due {
+            self.state = RunState::Running;
+            self.wake_at = None;
+            self.waiting_for = None;
+        }
+        due
+    }
+
+    /// Records that the next step finished with `outcome` at `now`.
+    pub fn step_finished(&mut self, outcome: Outcome, now: u64) {
+        self.step += 1;
+        self.attempt = 1;
+        self.timed_out = false;
+        match outcome {
+            Outcome::Next(state) => {
+                self.state = RunState::Running;
+                self.data = state;
+            }
+            Outcome::Sleep { duration_ms, state } => {
+                self.state = RunState::Sleeping;
+                self.wake_at = Some(now.saturating_add(duration_ms));
+                self.data = state;
+            }
+            Outcome::Wait {
+                signal,
+                timeout_ms,
+                state,
+            } => {
+                self.state = RunState::Waiting;
+                self.waiting_for = Some(signal);
+                self.wake_at = timeout_ms.map(|timeout| now.saturating_add(timeout));
+                self.data = state;
+            }
+            Outcome::Complete(output) => {
+                self.state = RunState::Completed;
+                self.data = output;
+            }
+            Outcome::Fail(error) => {
+                self.state = RunState::Failed;
+                self.error = Some(error);
+            }
+        }
+    }
+
+    /// Records that an attempt at the next step failed at `now`, scheduling a
+    /// retry unless the step has run out of attempts.
+    pub fn step_failed(&mut self, error: String, now: u64, config: &RuntimeConfig) {
+        if self.attempt >= config.max_attempts {
+            self.state = RunState::Failed;
+            self.error = Some(format!(
+                "step {} failed after {} attempts: {error}",
+                self.step, self.attempt
+            ));
+            return;
+        }
+        let delay = config.retry_delay(self.attempt);
+        self.wake_at = Some(now.saturating_add(delay.as_millis().try_into().unwrap_or(u64::MAX)));
+        self.attempt += 1;
+    }
+}
+
+/// A signal waiting to be delivered to a run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signal {
+    /// Orders the run's signals by when they were sent.
+    pub seq: String,
+    pub name: String,
+    pub payload: Vec<u8>,
+}
+
+/// Workflow runs persisted in a key-value store.
+///
+/// Each run is a JSON document, which is only written by the workflow trigger
+/// once the run has started. Signals and cancellations are stored under keys
+/// of their own, which the trigger applies to the run, so that they can be
+/// sent while a step is running without a compare-and-swap.
+#[derive(Clone)]
+pub struct WorkflowStore {
+    store_manager: Arc<dyn StoreManager>,
+    label: Arc<str>,
+}
+
+impl WorkflowStore {
+    pub fn new(store_manager: Arc<dyn StoreManager>, label: impl Into<Arc<str>>) -> Self {
+        Self {
+            store_manager,
+            label: label.into(),
+        }
+    }
+
+    /// The label of the backing key-value store.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    async fn store(&self) -> Result<Arc<dyn Store>> {
+        self.store_manager
+            .get(&self.label)
+            .await
+            .with_context(|| format!("failed to open workflow store {:?}", self.label))
+    }
+
+    /// Starts a run of `workflow`, returning its ID.
+    pub async fn start(&self, workflow: &str, input: Vec<u8>) -> Result<String> {
+        let run = Run::new(workflow, input);
+        self.save(&run).await?;
+        Ok(run.id)
+    }
+
+    /// Returns the run with the given ID.
+    pub async fn get(&self, id: &str) -> Result<Option<Run>> {
+        let store = self.store().await?;
+        for key in [
+            format!("{ACTIVE_PREFIX}{id}"),
+            format!("{FINISHED_PREFIX}{id}"),
+        ] {
+            if let Some(json) = store.get(&key).await.context("failed to read run")? {
+                return Ok(Some(
+                    serde_json::from_slice(&json).context("failed to parse run")?,
+                ));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Persists `run`, moving it out of the active runs once it has finished.
+    pub async fn save(&self, run: &Run) -> Result<()> {
+        let store = self.store().await?;
+        let json = serde_json::to_vec(run)?;
+        let active_key = format!("{ACTIVE_PREFIX}{}", run.id);
+        if run.state.is_finished() {
+            store
+                .set(&format!("{FINISHED_PREFIX}{}", run.id), &json)
+                .await
+                .context("failed to persist run")?;
+            store
+                .delete(&active_key)
+                .await
+                .context("failed to delete run")?;
+            store
+                .delete(&format!("{CANCEL_PREFIX}{}", run.id))
+                .await
+                .context("failed to delete run cancellation")?;
+        } else {
+            store
+                .set(&active_key, &json)
+                .await
+                .context("failed to persist run")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the runs which have not finished.
+    ///
+    /// Listing runs lists every key in the backing store, so runs are best
+    /// kept in a store of their own if the app keeps many other keys.
+    pub async fn active(&self) -> Result<Vec<Run>> {
+        let store = self.store().await?;
+        let mut runs = Vec::new();
+        for key in store.get_keys().await.context("failed to list runs")? {
+            if !key.starts_with(ACTIVE_PREFIX) {
+                continue;
+            }
+            // The run may have finished since it was listed.
+            let Some(json) = store.get(&key).await.context("failed to read run")? else {
+                continue;
+            };
+            runs.push(serde_json::from_slice(&json).context("failed to parse run")?);
+        }
+        Ok(runs)
+    }
+
+    /// Queues a signal for the run with the given ID.
+    pub async fn signal(&self, id: &str, name: &str, payload: &[u8]) -> Result<()> {
+        let seq = format!("{:020}-{}", now_millis(), uuid::Uuid::new_v4().simple());
+        self.store()
+            .await?
+            .set(&format!("{SIGNAL_PREFIX}{id}:{seq}:{name}"), payload)
+            .await
+            .context("failed to persist signal")
+    }
+
+    /// Returns the signals waiting for the run with the given ID, in the
+    /// order they were sent.
+    pub async fn signals(&self, id: &str) -> Result<Vec<Signal>> {
+        let store = self.store().await?;
+        let prefix = format!("{SIGNAL_PREFIX}{id}:");
+        let mut keys: Vec<String> = store
+            .get_keys()
+            .await
+            .context("failed to list signals")?
+            .into_iter()
+            .filter(|key| key.starts_with(&prefix))
+            .collect();
+        keys.sort();
+
+        let mut signals = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some((seq, name)) = key[prefix.len()..].split_once(':') else {
+                continue;
+            };
+            let Some(payload) = store.get(&key).await.context("failed to read signal")? else {
+                continue;
+            };
+            signals.push(Signal {
+                seq: seq.to_owned(),
+                name: name.to_owned(),
+                payload,
+            });
+        }
+        Ok(signals)
+    }
+
+    /// Removes signals which have been delivered to the run with the given
+    /// ID.
+    pub async fn remove_signals(&self, id: &str, signals: &[Signal]) -> Result<()> {
+        let store = self.store().await?;
+        for signal in signals {
+            store
+                .delete(&format!(
+                    "{SIGNAL_PREFIX}{id}:{}:{}",
+                    signal.seq, signal.name
+                ))
+                .await
+                .context("failed to delete signal")?;
+        }
+        Ok(())
+    }
+
+    /// Marks the run with the given ID to be cancelled.
+    pub async fn cancel(&self, id: &str) -> Result<()> {
+        self.store()
+            .await?
+            .set(&format!("{CANCEL_PREFIX}{id}"), b"")
+            .await
+            .context("failed to persist run cancellation")
+    }
+
+    /// Returns whether the run with the given ID has been marked to be
+    /// cancelled.
+    pub async fn is_cancelled(&self, id: &str) -> Result<bool> {
+        self.store()
+            .await?
+            .exists(&format!("{CANCEL_PREFIX}{id}"))
+            .await
+            .context("failed to look up run cancellation")
+    }
+}
+
+mod base64_bytes {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn config() -> RuntimeConfig {
+        RuntimeConfig {
+            max_attempts: 2,
+            retry_backoff: Duration::from_millis(100),
+            ..Default::default()
+        }
+    }
+
+    fn signal(name: &str) -> Signal {
+        Signal {
+            seq: "0".into(),
+            name: name.into(),
+            payload: vec![],
+        }
+    }
+
+    #[test]
+    fn runs_sleep_until_due() {
+        let mut run = Run::new("order", b"input".to_vec());
+        assert!(run.wake(0, &[]));
+
+        run.step_finished(
+            Outcome::Sleep {
+                duration_ms: 1000,
+                state: b"slept".to_vec(),
+            },
+            10,
+        );
+        assert_eq!(run.state, RunState::Sleeping);
+        assert!(!run.wake(500, &[]));
+        assert!(run.wake(1010, &[]));
+        assert_eq!(
+            (run.state, run.step, run.data.as_slice()),
+            (RunState::Running, 1, &b"slept"[..])
+        );
+    }
+
+    #[test]
+    fn waiting_runs_wake_on_signal_or_timeout() {
+        let mut run = Run::new("order", vec![]);
+        let wait = Outcome::Wait {
+            signal: "approved".into(),
+            timeout_ms: Some(1000),
+            state: vec![],
+        };
+        run.step_finished(wait.clone(), 0);
+        assert!(!run.wake(10, &[signal("rejected")]));
+        assert!(run.wake(10, &[signal("rejected"), signal("approved")]));
+        assert!(!run.timed_out);
+
+        run.step_finished(wait, 0);
+        assert!(run.wake(1000, &[]));
+        assert!(run.timed_out);
+    }
+
+    #[test]
+    fn failed_steps_are_retried_then_fail_the_run() {
+        let mut run = Run::new("order", vec![]);
+        run.wake(0, &[]);
+        run.step_failed("boom".into(), 0, &config());
+        assert_eq!(
+            (run.state, run.attempt, run.wake_at),
+            (RunState::Running, 2, Some(100))
+        );
+        assert!(!run.wake(50, &[]));
+        assert!(run.wake(100, &[]));
+
+        run.step_failed("boom".into(), 100, &config());
+        assert_eq!(run.state, RunState::Failed);
+        assert_eq!(
+            run.error.as_deref(),
+            Some("step 0 failed after 2 attempts: boom")
+        );
+    }
+
+    #[test]
+    fn runs_round_trip_through_json() {
+        let run = Run::new("order", b"\x00\xff".to_vec());
+        let json = serde_json::to_vec(&run).unwrap();
+        assert_eq!(serde_json::from_slice::<Run>(&json).unwrap(), run);
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use spin_factor_key_value::{runtime_config::spin::MakeKeyValueStore, KeyValueFactor};
+use spin_factor_workflows::WorkflowsFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_test::{toml, TestEnvironment};
+use spin_key_value_spin::MemoryKeyValueStore;
+use spin_world::spin::workflow::workflow::{Error, Host, RunState};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    key_value: KeyValueFactor,
+    workflows: WorkflowsFactor,
+}
+
+fn env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(TestFactors {
+        key_value: KeyValueFactor::new(),
+        workflows: WorkflowsFactor::new(),
+    })
+}
+
+fn runtime_config() -> anyhow::Result<TestFactorsRuntimeConfig> {
+    let mut key_value = spin_factor_key_value::RuntimeConfig::default();
+    let store_manager = MemoryKeyValueStore::new().make_store(Default::default())?;
+    key_value.add_store_manager("default".into(), Arc::new(store_manager));
+    Ok(TestFactorsRuntimeConfig {
+        key_value: Some(key_value),
+        workflows: None,
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn starts_signals_and_cancels_runs() -> anyhow::Result<()> {
+    let env = env().extend_manifest(toml! {
+        [[trigger.workflow]]
+        component = "test-component"
+        workflow = "fulfil-order"
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env
+        .runtime_config(runtime_config()?)?
+        .build_instance_state()
+        .await?;
+    let workflows = &mut state.workflows;
+
+    assert!(matches!(
+        workflows.start("refund".into(), vec![]).await,
+        Err(Error::NoSuchWorkflow(_))
+    ));
+    assert!(matches!(
+        workflows.status("unknown".into()).await,
+        Err(Error::NoSuchRun(_))
+    ));
+
+    let id = workflows
+        .start("fulfil-order".into(), b"order-1".to_vec())
+        .await?;
+    let status = workflows.status(id.clone()).await?;
+    assert_eq!(status.workflow, "fulfil-order");
+    assert_eq!(status.state, RunState::Running);
+    assert_eq!(status.steps_completed, 0);
+    assert_eq!(status.output, None);
+
+    workflows
+        .signal(id.clone(), "paid".into(), b"receipt".to_vec())
+        .await?;
+    workflows.cancel(id.clone()).await?;
+    assert_eq!(
+        workflows.status(id.clone()).await?.state,
+        RunState::Cancelled
+    );
+    assert!(matches!(
+        workflows.signal(id, "paid".into(), vec![]).await,
+        Err(Error::RunFinished(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn workflow_names_must_be_unique() -> anyhow::Result<()> {
+    let env = env().extend_manifest(toml! {
+        [[trigger.workflow]]
+        component = "test-component"
+        workflow = "fulfil-order"
+
+        [[trigger.workflow]]
+        component = "test-component"
+        workflow = "fulfil-order"
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let Err(err) = env
+        .runtime_config(runtime_config()?)?
+        .build_instance_state()
+        .await
+    else {
+        anyhow::bail!("expected instance build to fail but it didn't");
+    };
+    assert!(err.to_string().contains("more than one workflow trigger"));
+    Ok(())
+}
@@ -128,18 +128,23 @@ fn expand_factors(input: &DeriveInput) -> syn::Result<TokenStream> {
                 let mut app_state = #app_state_name {
                     #( #factor_names: None, )*
                 };
+                // Keep configuring after an error, so that all errors are
+                // reported at once
+                let mut errors = Vec::new();
                 #(
-                    app_state.#factor_names = Some(
-                        #Factor::configure_app(
-                            &self.#factor_names,
-                            #factors_path::ConfigureAppContext::<Self, #factor_types>::new(
-                                &app,
-                                &app_state,
-                                runtime_config.#factor_names,
-                            )?,
-                        ).map_err(#Error::factor_configure_app_error::<#factor_types>)?
-                    );
+                    match #Factor::configure_app(
+                        &self.#factor_names,
+                        #factors_path::ConfigureAppContext::<Self, #factor_types>::new(
+                            &app,
+                            &app_state,
+                            runtime_config.#factor_names,
+                        )?,
+                    ) {
+                        Ok(state) => app_state.#factor_names = Some(state),
+                        Err(err) => errors.push(#Error::factor_configure_app_error::<#factor_types>(err)),
+                    }
                 )*
+                #Error::configure_app_errors(errors)?;
                 Ok(#ConfiguredApp::new(app, app_state))
             }
 
@@ -291,14 +296,20 @@ fn expand_factors(input: &DeriveInput) -> syn::Result<TokenStream> {
 
         impl #runtime_config_name {
             /// Get the runtime configuration from the given source.
+            ///
+            /// Every factor's configuration is read before failing, so that
+            /// the error reports all of the problems found.
             #[allow(dead_code)]
             pub fn from_source<T>(mut source: T) -> anyhow::Result<Self>
                 where T: #(#factors_path::FactorRuntimeConfigSource<#factor_types> +)* #factors_path::RuntimeConfigSourceFinalizer
             {
+                let mut errors = #factors_path::runtime_config::RuntimeConfigErrors::default();
                 #(
-                    let #factor_names = <T as #factors_path::FactorRuntimeConfigSource<#factor_types>>::get_runtime_config(&mut source)?;
+                    let result = <T as #factors_path::FactorRuntimeConfigSource<#factor_types>>::get_runtime_config(&mut source);
+                    let #factor_names = errors.check::<#factor_types>(result, &mut source);
                 )*
-                source.finalize()?;
+                errors.finalize(&mut source);
+                errors.into_result()?;
                 Ok(#runtime_config_name {
                     #(
                         #factor_names,
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use anyhow::Context;
 use spin_app::{App, AppComponent};
@@ -75,8 +79,42 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
 
         Ok(FactorsExecutorApp {
             executor: self.clone(),
-            configured_app,
-            component_instance_pres,
+            configured_app: Arc::new(configured_app),
+            components: Arc::new(AppComponents::Eager(component_instance_pres)),
+        })
+    }
+
+    /// Loads a [`App`] with this executor, deferring loading each component
+    /// until it is first used so that startup time doesn't grow with the
+    /// number of components.
+    ///
+    /// If `max_loaded_bytes` is set, the least recently used components are
+    /// evicted, to be loaded again when next used, whenever the compiled code
+    /// of the loaded components exceeds that size.
+    pub async fn load_app_lazily(
+        self: Arc<Self>,
+        app: App,
+        runtime_config: T::RuntimeConfig,
+        component_loader: Arc<dyn ComponentLoader<T, U> + Send>,
+        max_loaded_bytes: Option<usize>,
+    ) -> anyhow::Result<FactorsExecutorApp<T, U>> {
+        let configured_app = self
+            .factors
+            .configure_app(app, runtime_config)
+            .context("failed to configure app")?;
+
+        for hooks in &self.hooks {
+            hooks.configure_app(&configured_app).await?;
+        }
+
+        Ok(FactorsExecutorApp {
+            executor: self.clone(),
+            configured_app: Arc::new(configured_app),
+            components: Arc::new(AppComponents::Lazy(LazyComponents {
+                loader: component_loader,
+                max_loaded_bytes,
+                slots: Default::default(),
+            })),
         })
     }
 }
@@ -126,12 +164,137 @@ type InstancePre<T, U> =
 /// A FactorsExecutorApp represents a loaded Spin app, ready for instantiation.
 ///
 /// It is generic over the executor's [`RuntimeFactors`] and any ad-hoc additional
-/// per-instance state needed by the caller.
+/// per-instance state needed by the caller. Clones share the loaded app.
 pub struct FactorsExecutorApp<T: RuntimeFactors, U> {
     executor: Arc<FactorsExecutor<T, U>>,
-    configured_app: ConfiguredApp<T>,
-    // Maps component IDs -> InstancePres
-    component_instance_pres: HashMap<String, InstancePre<T, U>>,
+    configured_app: Arc<ConfiguredApp<T>>,
+    components: Arc<AppComponents<T, U>>,
+}
+
+impl<T: RuntimeFactors, U> Clone for FactorsExecutorApp<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: self.executor.clone(),
+            configured_app: self.configured_app.clone(),
+            components: self.components.clone(),
+        }
+    }
+}
+
+/// The loaded components of a [`FactorsExecutorApp`].
+enum AppComponents<T: RuntimeFactors, U> {
+    /// Every component, loaded with the app. Maps component IDs -> InstancePres
+    Eager(HashMap<String, InstancePre<T, U>>),
+    /// Components loaded on first use.
+    Lazy(LazyComponents<T, U>),
+}
+
+struct LazyComponents<T: RuntimeFactors, U> {
+    loader: Arc<dyn ComponentLoader<T, U> + Send>,
+    max_loaded_bytes: Option<usize>,
+    slots: Mutex<LazySlots<T, U>>,
+}
+
+struct LazySlots<T: RuntimeFactors, U> {
+    // Maps component IDs -> loaded (or loading) InstancePres
+    entries: HashMap<String, LazySlot<T, U>>,
+    // Incremented on each use, to order uses for eviction.
+    clock: u64,
+}
+
+impl<T: RuntimeFactors, U> Default for LazySlots<T, U> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+            clock: 0,
+        }
+    }
+}
+
+struct LazySlot<T: RuntimeFactors, U> {
+    // Shared so that concurrent first uses load the component only once.
+    cell: Arc<tokio::sync::OnceCell<InstancePre<T, U>>>,
+    last_used: u64,
+}
+
+impl<T: RuntimeFactors, U: Send + 'static> LazyComponents<T, U> {
+    async fn get(
+        &self,
+        engine: &spin_core::Engine<InstanceState<T::InstanceState, U>>,
+        component: &AppComponent<'_>,
+    ) -> anyhow::Result<InstancePre<T, U>> {
+        let cell = {
+            let mut slots = self.slots.lock().unwrap();
+            slots.clock += 1;
+            let now = slots.clock;
+            let slot = slots
+                .entries
+                .entry(component.id().to_owned())
+                .or_insert_with(|| LazySlot {
+                    cell: Default::default(),
+                    last_used: now,
+                });
+            slot.last_used = now;
+            slot.cell.clone()
+        };
+        if let Some(instance_pre) = cell.get() {
+            return Ok(instance_pre.clone());
+        }
+
+        let instance_pre = cell
+            .get_or_try_init(|| async {
+                let start = Instant::now();
+                let instance_pre = self.loader.load_instance_pre(engine, component).await?;
+                tracing::debug!(
+                    "Loaded component {:?} in {:?}",
+                    component.id(),
+                    start.elapsed()
+                );
+                anyhow::Ok(instance_pre)
+            })
+            .await?
+            .clone();
+        self.evict_idle(component.id());
+        Ok(instance_pre)
+    }
+
+    /// Evicts the least recently used components, other than `keep`, until
+    /// the loaded components fit in `max_loaded_bytes`.
+    fn evict_idle(&self, keep: &str) {
+        let Some(max_loaded_bytes) = self.max_loaded_bytes else {
+            return;
+        };
+        let mut slots = self.slots.lock().unwrap();
+        loop {
+            let loaded = slots
+                .entries
+                .iter()
+                .filter_map(|(id, slot)| {
+                    let size = compiled_size(slot.cell.get()?);
+                    Some((id, slot.last_used, size))
+                })
+                .collect::<Vec<_>>();
+            if loaded.iter().map(|(_, _, size)| size).sum::<usize>() <= max_loaded_bytes {
+                return;
+            }
+            let Some(victim) = loaded
+                .into_iter()
+                .filter(|(id, _, _)| *id != keep)
+                .min_by_key(|(_, last_used, _)| *last_used)
+                .map(|(id, _, _)| id.clone())
+            else {
+                return;
+            };
+            tracing::debug!("Evicting idle component {victim:?}");
+            slots.entries.remove(&victim);
+        }
+    }
+}
+
+/// The size of a component's compiled code.
+fn compiled_size<T>(instance_pre: &spin_core::InstancePre<T>) -> usize {
+    let range = instance_pre.component().image_range();
+    range.end as usize - range.start as usize
 }
 
 impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
@@ -147,25 +310,68 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
         self.configured_app.app()
     }
 
-    pub fn get_component(&self, component_id: &str) -> anyhow::Result<&Component> {
-        Ok(self.get_instance_pre(component_id)?.component())
+    /// Returns whether components are loaded on first use, rather than with
+    /// the app.
+    pub fn is_lazily_loaded(&self) -> bool {
+        matches!(*self.components, AppComponents::Lazy(_))
+    }
+
+    pub async fn get_component(&self, component_id: &str) -> anyhow::Result<Component> {
+        Ok(self
+            .get_instance_pre(component_id)
+            .await?
+            .component()
+            .clone())
+    }
+
+    /// Returns the [`InstancePre`] for the given component ID, loading the
+    /// component if necessary.
+    pub async fn get_instance_pre(&self, component_id: &str) -> anyhow::Result<InstancePre<T, U>> {
+        match &*self.components {
+            AppComponents::Eager(instance_pres) => instance_pres
+                .get(component_id)
+                .cloned()
+                .with_context(|| format!("no such component {component_id:?}")),
+            AppComponents::Lazy(lazy) => {
+                let app_component = self
+                    .configured_app
+                    .app()
+                    .get_component(component_id)
+                    .with_context(|| format!("no such component {component_id:?}"))?;
+                lazy.get(&self.executor.core_engine, &app_component).await
+            }
+        }
     }
 
-    pub fn get_instance_pre(&self, component_id: &str) -> anyhow::Result<&InstancePre<T, U>> {
-        self.component_instance_pres
-            .get(component_id)
-            .with_context(|| format!("no such component {component_id:?}"))
+    /// Returns the [`InstancePre`] for the given component ID if it is
+    /// already loaded; eagerly loaded components always are.
+    pub fn loaded_instance_pre(&self, component_id: &str) -> Option<InstancePre<T, U>> {
+        match &*self.components {
+            AppComponents::Eager(instance_pres) => instance_pres.get(component_id).cloned(),
+            AppComponents::Lazy(lazy) => lazy
+                .slots
+                .lock()
+                .unwrap()
+                .entries
+                .get(component_id)?
+                .cell
+                .get()
+                .cloned(),
+        }
     }
 
     /// Returns an instance builder for the given component ID.
-    pub fn prepare(&self, component_id: &str) -> anyhow::Result<FactorsInstanceBuilder<T, U>> {
+    pub async fn prepare(
+        &self,
+        component_id: &str,
+    ) -> anyhow::Result<FactorsInstanceBuilder<T, U>> {
         let app_component = self
             .configured_app
             .app()
             .get_component(component_id)
             .with_context(|| format!("no such component {component_id:?}"))?;
 
-        let instance_pre = self.component_instance_pres.get(component_id).unwrap();
+        let instance_pre = self.get_instance_pre(component_id).await?;
 
         let factor_builders = self
             .executor
@@ -198,7 +404,7 @@ pub struct FactorsInstanceBuilder<'a, F: RuntimeFactors, U> {
     app_component: AppComponent<'a>,
     store_builder: spin_core::StoreBuilder,
     factor_builders: F::InstanceBuilders,
-    instance_pre: &'a InstancePre<F, U>,
+    instance_pre: InstancePre<F, U>,
     factors: &'a F,
 }
 
@@ -232,6 +438,11 @@ impl<T: RuntimeFactors, U> FactorsInstanceBuilder<'_, T, U> {
     pub fn component(&self) -> &Component {
         self.instance_pre.component()
     }
+
+    /// Returns the [`InstancePre`] the instance will be instantiated from.
+    pub fn instance_pre(&self) -> &InstancePre<T, U> {
+        &self.instance_pre
+    }
 }
 
 impl<T: RuntimeFactors, U: Send> FactorsInstanceBuilder<'_, T, U> {
@@ -243,6 +454,7 @@ impl<T: RuntimeFactors, U: Send> FactorsInstanceBuilder<'_, T, U> {
         spin_core::Instance,
         spin_core::Store<InstanceState<T::InstanceState, U>>,
     )> {
+        let start = Instant::now();
         let instance_state = InstanceState {
             core: Default::default(),
             factors: self.factors.build_instance_state(self.factor_builders)?,
@@ -250,6 +462,11 @@ impl<T: RuntimeFactors, U: Send> FactorsInstanceBuilder<'_, T, U> {
         };
         let mut store = self.store_builder.build(instance_state)?;
         let instance = self.instance_pre.instantiate_async(&mut store).await?;
+        spin_telemetry::metrics::histogram!(
+            spin.instantiation_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            app_id = self.app_component.app.id(),
+            component_id = self.app_component.id()
+        );
         Ok((instance, store))
     }
 }
@@ -337,7 +554,7 @@ mod tests {
             .load_app(app, Default::default(), &DummyComponentLoader)
             .await?;
 
-        let mut instance_builder = factors_app.prepare("empty")?;
+        let mut instance_builder = factors_app.prepare("empty").await?;
 
         assert_eq!(instance_builder.app_component().id(), "empty");
 
@@ -352,6 +569,38 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn lazy_loading_loads_on_first_use() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+
+        // A zero budget evicts every component but the one just used.
+        let factors_app = executor
+            .load_app_lazily(
+                app,
+                Default::default(),
+                Arc::new(DummyComponentLoader),
+                Some(0),
+            )
+            .await?;
+        assert!(factors_app.is_lazily_loaded());
+        assert!(factors_app.loaded_instance_pre("empty").is_none());
+
+        let instance_builder = factors_app.prepare("empty").await?;
+        let (_instance, _store) = instance_builder.instantiate(()).await?;
+        assert!(factors_app.loaded_instance_pre("empty").is_some());
+
+        assert!(factors_app.prepare("missing").await.is_err());
+        Ok(())
+    }
+
     struct DummyComponentLoader;
 
     #[async_trait]
@@ -41,6 +41,8 @@ pub enum Error {
         factor: &'static str,
         source: anyhow::Error,
     },
+    #[error("{} factors failed to configure the app:{}", errors.len(), errors.iter().map(|err| format!("\n  - {err}")).collect::<String>())]
+    FactorConfigureAppErrors { errors: Vec<Error> },
     #[error("{factor}::init failed: {source}")]
     FactorInitError {
         factor: &'static str,
@@ -89,6 +91,39 @@ impl Error {
         Self::FactorConfigureAppError { factor, source }
     }
 
+    /// Combines the errors from configuring each factor, leaving out errors
+    /// caused by depending on a factor which failed.
+    #[doc(hidden)]
+    pub fn configure_app_errors(mut errors: Vec<Self>) -> Result<()> {
+        let failed = errors
+            .iter()
+            .filter_map(|err| match err {
+                Self::FactorConfigureAppError { factor, .. } => Some(*factor),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        errors.retain(|err| {
+            !err.missing_factor()
+                .is_some_and(|factor| failed.contains(&factor))
+        });
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(Self::FactorConfigureAppErrors { errors }),
+        }
+    }
+
+    /// The factor a factor failed to configure the app without, if any.
+    fn missing_factor(&self) -> Option<&'static str> {
+        let Self::FactorConfigureAppError { source, .. } = self else {
+            return None;
+        };
+        source.chain().find_map(|err| match err.downcast_ref() {
+            Some(Self::NoSuchFactor(factor)) => Some(*factor),
+            _ => None,
+        })
+    }
+
     #[doc(hidden)]
     pub fn factor_prepare_error<T: Factor>(source: anyhow::Error) -> Self {
         let factor = std::any::type_name::<T>();
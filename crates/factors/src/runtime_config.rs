@@ -1,5 +1,7 @@
 pub mod toml;
 
+use std::fmt;
+
 use crate::Factor;
 
 /// The source of runtime configuration for a particular [`Factor`].
@@ -18,6 +20,15 @@ impl<F: Factor> FactorRuntimeConfigSource<F> for () {
 pub trait RuntimeConfigSourceFinalizer {
     /// Finalize the runtime config source.
     fn finalize(&mut self) -> anyhow::Result<()>;
+
+    /// Where the configuration read since the last call is in the source,
+    /// e.g. a file and line, if known.
+    ///
+    /// This is called after each factor's runtime configuration is read, so
+    /// that errors in it can point to where it is.
+    fn last_read_location(&mut self) -> Option<String> {
+        None
+    }
 }
 
 impl RuntimeConfigSourceFinalizer for () {
@@ -25,3 +36,109 @@ impl RuntimeConfigSourceFinalizer for () {
         Ok(())
     }
 }
+
+/// An error in the runtime configuration of a factor.
+#[derive(Debug)]
+pub struct FactorRuntimeConfigError {
+    /// The factor whose configuration is in error, or `None` for errors in
+    /// the source as a whole, such as unused keys.
+    pub factor: Option<&'static str>,
+    /// Where the configuration is in the source, if known.
+    pub location: Option<String>,
+    pub source: anyhow::Error,
+}
+
+/// The errors found in runtime configuration.
+///
+/// Every factor's configuration is read even after an error, so that all
+/// the errors can be reported at once.
+#[derive(Debug, Default)]
+pub struct RuntimeConfigErrors {
+    errors: Vec<FactorRuntimeConfigError>,
+}
+
+impl RuntimeConfigErrors {
+    /// Takes a factor's runtime configuration from `result`, recording the
+    /// error if there is one.
+    pub fn check<F: Factor>(
+        &mut self,
+        result: anyhow::Result<Option<F::RuntimeConfig>>,
+        source: &mut impl RuntimeConfigSourceFinalizer,
+    ) -> Option<F::RuntimeConfig> {
+        let location = source.last_read_location();
+        match result {
+            Ok(config) => config,
+            Err(source) => {
+                self.errors.push(FactorRuntimeConfigError {
+                    factor: Some(std::any::type_name::<F>()),
+                    location,
+                    source,
+                });
+                None
+            }
+        }
+    }
+
+    /// Finalizes `source`, recording the error if there is one.
+    pub fn finalize(&mut self, source: &mut impl RuntimeConfigSourceFinalizer) {
+        if let Err(source) = source.finalize() {
+            self.errors.push(FactorRuntimeConfigError {
+                factor: None,
+                location: None,
+                source,
+            });
+        }
+    }
+
+    /// The errors found.
+    pub fn errors(&self) -> &[FactorRuntimeConfigError] {
+        &self.errors
+    }
+
+    /// Fails with the errors found, if any.
+    ///
+    /// A single error is returned as it is, with the factor and location as
+    /// context.
+    pub fn into_result(mut self) -> anyhow::Result<()> {
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => {
+                let error = self.errors.pop().unwrap();
+                match error.label() {
+                    Some(label) => Err(error.source.context(label)),
+                    None => Err(error.source),
+                }
+            }
+            _ => Err(self.into()),
+        }
+    }
+}
+
+impl FactorRuntimeConfigError {
+    /// Describes the factor and location, if known.
+    fn label(&self) -> Option<String> {
+        match (self.factor, &self.location) {
+            (Some(factor), Some(location)) => {
+                Some(format!("invalid runtime config for {factor} at {location}"))
+            }
+            (Some(factor), None) => Some(format!("invalid runtime config for {factor}")),
+            (None, Some(location)) => Some(format!("invalid runtime config at {location}")),
+            (None, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "found {} errors in runtime config:", self.errors.len())?;
+        for error in &self.errors {
+            match error.label() {
+                Some(label) => write!(f, "\n  - {label}: {:#}", error.source)?,
+                None => write!(f, "\n  - {:#}", error.source)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeConfigErrors {}
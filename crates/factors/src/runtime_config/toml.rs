@@ -17,6 +17,9 @@ impl GetTomlValue for toml::Table {
 /// A helper for tracking which keys have been used in a TOML table.
 pub struct TomlKeyTracker<'a> {
     unused_keys: RefCell<HashSet<&'a str>>,
+    /// The keys present in the table which have been looked up since the
+    /// last call to [`Self::take_read_keys`], in the order they were.
+    read_keys: RefCell<Vec<String>>,
     table: &'a toml::Table,
 }
 
@@ -24,6 +27,7 @@ impl<'a> TomlKeyTracker<'a> {
     pub fn new(table: &'a toml::Table) -> Self {
         Self {
             unused_keys: RefCell::new(table.keys().map(String::as_str).collect()),
+            read_keys: Default::default(),
             table,
         }
     }
@@ -41,12 +45,20 @@ impl<'a> TomlKeyTracker<'a> {
         }
         Ok(())
     }
+
+    /// Takes the keys present in the table which have been looked up since
+    /// the last call, in the order they were.
+    pub fn take_read_keys(&self) -> Vec<String> {
+        self.read_keys.take()
+    }
 }
 
 impl GetTomlValue for TomlKeyTracker<'_> {
     fn get(&self, key: &str) -> Option<&toml::Value> {
         self.unused_keys.borrow_mut().remove(key);
-        self.table.get(key)
+        let value = self.table.get(key)?;
+        self.read_keys.borrow_mut().push(key.to_owned());
+        Some(value)
     }
 }
 
@@ -11,6 +11,270 @@ pub struct HttpTriggerConfig {
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// The GraphQL root fields the component resolves, if it serves part of
+    /// the app's GraphQL endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphql: Option<GraphqlFieldsConfig>,
+    /// The OpenAPI document requests to the component are validated against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openapi: Option<OpenApiConfig>,
+    /// Whether the trigger may compress the component's responses (defaults to true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+    /// A shadow component to mirror a share of the component's requests to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<MirrorConfig>,
+    /// Replaying of responses to requests retried with the same
+    /// `Idempotency-Key` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency: Option<IdempotencyConfig>,
+    /// Verification of the signatures of webhook requests to the component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookConfig>,
+    /// Signing in through an OpenID Connect provider before requests reach
+    /// the component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<OidcConfig>,
+    /// Reuse of the component's instances across requests, rather than
+    /// instantiating the component for every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_reuse: Option<InstanceReuseConfig>,
+    /// Parsing of `multipart/form-data` request bodies, with large parts
+    /// written to files the component can read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multipart: Option<MultipartConfig>,
+}
+
+/// App-wide configuration for the HTTP trigger
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpTriggerMetadata {
+    /// Deprecated base path for all routes
+    pub base: Option<String>,
+    /// The route of the app's GraphQL endpoint, which splits each operation
+    /// across the components that resolve its root fields
+    pub graphql_route: Option<String>,
+    /// The smallest response body, in bytes, which the trigger compresses
+    pub compression_min_size: Option<u64>,
+}
+
+/// The GraphQL root fields resolved by a component.
+///
+/// Each component receives a GraphQL request containing only the root fields
+/// it resolves, and must accept it as a JSON `POST` request.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphqlFieldsConfig {
+    /// Fields of the `Query` root type
+    #[serde(default)]
+    pub query: Vec<String>,
+    /// Fields of the `Mutation` root type
+    #[serde(default)]
+    pub mutation: Vec<String>,
+}
+
+/// An OpenAPI document describing a component's routes.
+///
+/// Requests which the document does not allow are rejected before the
+/// component is instantiated.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenApiConfig {
+    /// Path to the document (JSON or YAML), relative to the manifest
+    pub spec: String,
+    /// Route to serve the document on, if any
+    #[serde(default)]
+    pub route: Option<String>,
+    /// The contents of the document, inlined when the app is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+}
+
+/// Mirroring of requests to a shadow component.
+///
+/// Mirrored requests are handled after the component has responded, and the
+/// shadow component's responses are discarded. They are compared with the
+/// component's responses to record divergence metrics.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorConfig {
+    /// Component ID of the shadow component
+    pub component: String,
+    /// Percentage of requests to mirror, from 0 to 100
+    pub percent: f64,
+}
+
+/// Replaying of responses to requests retried with the same
+/// `Idempotency-Key` header.
+///
+/// The first response to each key is stored in a key-value store, and
+/// replayed for retries of the same request, without invoking the component.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdempotencyConfig {
+    /// The label of the key-value store responses are stored in
+    #[serde(default = "default_idempotency_store")]
+    pub store: String,
+    /// How long responses are replayed for, in seconds (defaults to a day)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            store: default_idempotency_store(),
+            ttl_secs: None,
+        }
+    }
+}
+
+fn default_idempotency_store() -> String {
+    "default".into()
+}
+
+/// Verification of the signatures of inbound webhook requests.
+///
+/// Requests without a valid signature are rejected before the component is
+/// instantiated.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// The signature scheme of the webhook's sender
+    pub scheme: WebhookScheme,
+    /// The signing secret, usually a reference to an application variable
+    /// such as `"{{ webhook_secret }}"`
+    pub secret: String,
+    /// The header carrying the signature (`hmac-sha256` scheme only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// A prefix of the signature to strip, e.g. `sha256=` (`hmac-sha256`
+    /// scheme only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// How the signature is encoded (`hmac-sha256` scheme only)
+    #[serde(default)]
+    pub encoding: WebhookSignatureEncoding,
+    /// How old a signed timestamp may be, in seconds, for the schemes which
+    /// sign one (defaults to 5 minutes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance_secs: Option<u64>,
+    /// The largest body, in bytes, buffered to check its signature (defaults
+    /// to 25 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+}
+
+/// A webhook signature scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookScheme {
+    /// GitHub's `X-Hub-Signature-256` header
+    Github,
+    /// Stripe's `Stripe-Signature` header
+    Stripe,
+    /// Slack's `X-Slack-Signature` and `X-Slack-Request-Timestamp` headers
+    Slack,
+    /// An HMAC-SHA256 of the body in a configured header
+    HmacSha256,
+}
+
+impl WebhookScheme {
+    /// The name of the scheme in configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Stripe => "stripe",
+            Self::Slack => "slack",
+            Self::HmacSha256 => "hmac-sha256",
+        }
+    }
+}
+
+/// How a webhook signature is encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookSignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+/// Signing in through an OpenID Connect provider.
+///
+/// Requests without a session are redirected to the provider to sign in
+/// (or rejected, if they can't be redirected) before the component is
+/// instantiated. Requests with a session carry the signed-in user's claims.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// The provider's issuer URL, from which its endpoints are discovered;
+    /// it must use HTTPS unless it is on a loopback address
+    pub issuer: String,
+    /// The client ID registered with the provider
+    pub client_id: String,
+    /// The client secret, usually a reference to an application variable
+    /// such as `"{{ oidc_client_secret }}"`
+    pub client_secret: String,
+    /// The scopes to request (defaults to `openid`, `profile` and `email`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    /// The label of the key-value store sessions are stored in
+    #[serde(default = "default_oidc_store")]
+    pub store: String,
+    /// How long a session lasts, in seconds (defaults to 8 hours)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_ttl_secs: Option<u64>,
+}
+
+fn default_oidc_store() -> String {
+    "default".into()
+}
+
+/// Reuse of a component's instances across sequential requests.
+///
+/// This suits components which are slow to instantiate, such as those built
+/// with interpreter-based SDKs, and which keep no state between requests: an
+/// instance handles one request at a time, but any state it keeps in memory
+/// is seen by later requests. Instances are recycled after a number of
+/// requests, or as soon as one traps. Only components which implement the
+/// `wasi:http/incoming-handler` interface can be reused.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceReuseConfig {
+    /// How many requests an instance handles before it is recycled (defaults
+    /// to 1000)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests: Option<u32>,
+    /// How many idle instances are kept for reuse (defaults to 16)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle: Option<usize>,
+}
+
+/// Parsing of `multipart/form-data` request bodies before the component is
+/// instantiated.
+///
+/// The body is streamed rather than buffered, so uploads may be larger than
+/// the component's memory. Small text fields are passed inline, and other
+/// parts are written to temporary files in a directory mounted read-only
+/// into the component. The component receives a JSON description of the
+/// parts in place of the body.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultipartConfig {
+    /// The guest path the directory of uploaded files is mounted at
+    /// (defaults to `/uploads`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount: Option<String>,
+    /// The largest text field passed inline, in bytes (defaults to 64 KiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_max_size: Option<u64>,
+    /// The largest part, in bytes (defaults to the largest body)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_part_size: Option<u64>,
+    /// The largest body, in bytes (defaults to 100 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
 }
 
 /// An HTTP trigger route
@@ -96,6 +360,118 @@ impl Default for WagiTriggerConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn graphql_fields_are_optional() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "users"
+            route = "/users"
+        }
+        .try_into()
+        .unwrap();
+        assert!(config.graphql.is_none());
+
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "users"
+            route = "/users"
+            graphql = { query = ["user", "users"] }
+        }
+        .try_into()
+        .unwrap();
+        let graphql = config.graphql.unwrap();
+        assert_eq!(graphql.query, ["user", "users"]);
+        assert!(graphql.mutation.is_empty());
+    }
+
+    #[test]
+    fn openapi_route_is_optional() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "users"
+            route = "/users/..."
+            openapi = { spec = "openapi.yaml" }
+        }
+        .try_into()
+        .unwrap();
+        let openapi = config.openapi.unwrap();
+        assert_eq!(openapi.spec, "openapi.yaml");
+        assert!(openapi.route.is_none());
+        assert!(openapi.document.is_none());
+    }
+
+    #[test]
+    fn idempotency_store_defaults_to_default() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "payments"
+            route = "/payments"
+            idempotency = { ttl_secs = 3600 }
+        }
+        .try_into()
+        .unwrap();
+        let idempotency = config.idempotency.unwrap();
+        assert_eq!(idempotency.store, "default");
+        assert_eq!(idempotency.ttl_secs, Some(3600));
+    }
+
+    #[test]
+    fn webhook_config_parses() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "hooks"
+            route = "/hooks"
+            webhook = { scheme = "hmac-sha256", secret = "{{ secret }}", header = "x-signature", encoding = "base64" }
+        }
+        .try_into()
+        .unwrap();
+        let webhook = config.webhook.unwrap();
+        assert_eq!(webhook.scheme, WebhookScheme::HmacSha256);
+        assert_eq!(webhook.secret, "{{ secret }}");
+        assert_eq!(webhook.encoding, WebhookSignatureEncoding::Base64);
+        assert_eq!(webhook.tolerance_secs, None);
+    }
+
+    #[test]
+    fn oidc_config_parses() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "admin"
+            route = "/admin/..."
+            oidc = { issuer = "https://accounts.example.com", client_id = "admin", client_secret = "{{ oidc_secret }}" }
+        }
+        .try_into()
+        .unwrap();
+        let oidc = config.oidc.unwrap();
+        assert_eq!(oidc.issuer, "https://accounts.example.com");
+        assert_eq!(oidc.store, "default");
+        assert!(oidc.scopes.is_none());
+        assert!(oidc.session_ttl_secs.is_none());
+    }
+
+    #[test]
+    fn instance_reuse_limits_are_optional() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "python"
+            route = "/..."
+            instance_reuse = { max_requests = 500 }
+        }
+        .try_into()
+        .unwrap();
+        let reuse = config.instance_reuse.unwrap();
+        assert_eq!(reuse.max_requests, Some(500));
+        assert!(reuse.max_idle.is_none());
+    }
+
+    #[test]
+    fn multipart_limits_are_optional() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "uploads"
+            route = "/upload"
+            multipart = { max_size = 1073741824 }
+        }
+        .try_into()
+        .unwrap();
+        let multipart = config.multipart.unwrap();
+        assert_eq!(multipart.max_size, Some(1 << 30));
+        assert!(multipart.mount.is_none());
+        assert!(multipart.max_part_size.is_none());
+    }
+
     #[test]
     fn wagi_config_smoke_test() {
         let HttpExecutorType::Wagi(config) = toml::toml! { type = "wagi" }.try_into().unwrap()
@@ -243,6 +243,25 @@ impl RouteMatch<'_, '_> {
         }
     }
 
+    /// A copy of this match which dispatches to a different component.
+    pub fn for_component(&self, component_id: String) -> RouteMatch<'static, 'static> {
+        let route_handler = RouteHandler {
+            component_id,
+            ..self.inner.route_handler().clone()
+        };
+        RouteMatch {
+            inner: RouteMatchKind::Detached {
+                route_handler,
+                named_wildcards: self
+                    .named_wildcards()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                    .collect(),
+                trailing_wildcard: self.trailing_wildcard().into_owned(),
+            },
+        }
+    }
+
     /// The matched component.
     pub fn component_id(&self) -> &str {
         &self.inner.route_handler().component_id
@@ -298,6 +317,15 @@ enum RouteMatchKind<'router, 'path> {
         /// The trailing wildcard part of the path
         trailing_wildcard: String,
     },
+    /// A copy of another match, which no longer borrows from the router.
+    Detached {
+        /// The route handler that matched the path.
+        route_handler: RouteHandler,
+        /// The named wildcards captured from the path
+        named_wildcards: HashMap<String, String>,
+        /// The trailing wildcard part of the path
+        trailing_wildcard: String,
+    },
     /// A real match.
     Real {
         /// The route handler that matched the path.
@@ -314,16 +342,23 @@ impl RouteMatchKind<'_, '_> {
     fn route_handler(&self) -> &RouteHandler {
         match self {
             RouteMatchKind::Synthetic { route_handler, .. } => route_handler,
+            RouteMatchKind::Detached { route_handler, .. } => route_handler,
             RouteMatchKind::Real { route_handler, .. } => route_handler,
         }
     }
 
     /// The named wildcards captured from the path, if any
     pub fn named_wildcards(&self) -> HashMap<&str, &str> {
-        let Self::Real { captures, .. } = &self else {
-            return HashMap::new();
-        };
-        captures.iter().collect()
+        match self {
+            Self::Synthetic { .. } => HashMap::new(),
+            Self::Detached {
+                named_wildcards, ..
+            } => named_wildcards
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+            Self::Real { captures, .. } => captures.iter().collect(),
+        }
     }
 
     /// The trailing wildcard part of the path, if any
@@ -332,6 +367,9 @@ impl RouteMatchKind<'_, '_> {
             // If we have a synthetic match, we already have the trailing wildcard.
             Self::Synthetic {
                 trailing_wildcard, ..
+            }
+            | Self::Detached {
+                trailing_wildcard, ..
             } => return trailing_wildcard.into(),
             Self::Real { captures, path, .. } => (captures, path),
         };
@@ -680,6 +718,17 @@ mod route_tests {
         assert_eq!("2", m.named_wildcards()["two"]);
     }
 
+    #[test]
+    fn match_for_other_component_keeps_captures() {
+        let routes = Router::build("/", vec![("comp", &"/1/:two/...".into())], None).unwrap();
+        let m = routes.route("/1/2/3").expect("/1/2/3 should have matched");
+        let shadow = m.for_component("shadow".into());
+        assert_eq!("shadow", shadow.component_id());
+        assert_eq!("/1/:two/...", shadow.raw_route());
+        assert_eq!("2", shadow.named_wildcards()["two"]);
+        assert_eq!("/3", shadow.trailing_wildcard());
+    }
+
     #[test]
     fn reserved_routes_are_reserved() {
         let routes =
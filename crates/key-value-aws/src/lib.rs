@@ -1,5 +1,6 @@
 mod store;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
 use store::{
@@ -20,7 +21,8 @@ impl AwsDynamoKeyValueStore {
 }
 
 /// Runtime configuration for the AWS Dynamo key-value store.
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct AwsDynamoKeyValueRuntimeConfig {
     /// The access key for the AWS Dynamo DB account role.
     access_key: Option<String>,
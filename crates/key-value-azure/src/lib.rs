@@ -1,5 +1,6 @@
 mod store;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
 
@@ -23,7 +24,8 @@ impl AzureKeyValueStore {
 }
 
 /// Runtime configuration for the Azure Cosmos key-value store.
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct AzureCosmosKeyValueRuntimeConfig {
     /// The authorization token for the Azure Cosmos DB account.
     key: Option<String>,
@@ -8,7 +8,9 @@ use azure_data_cosmos::{
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use spin_factor_key_value::{log_cas_error, log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{
+    json, log_cas_error, log_error, Cas, Error, Store, StoreManager, SwapError,
+};
 use std::sync::{Arc, Mutex};
 
 pub struct KeyValueAzureCosmos {
@@ -150,22 +152,15 @@ struct AzureCosmosStore {
 impl Store for AzureCosmosStore {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
         let pair = self.get_entity::<Pair>(key).await?;
-        Ok(pair.map(|p| p.value))
+        Ok(pair.map(Pair::into_value))
     }
 
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        let illegal_chars = ['/', '\\', '?', '#'];
-
-        if key.contains(|c| illegal_chars.contains(&c)) {
-            return Err(Error::Other(format!(
-                "Key contains an illegal character. Keys must not include any of: {}",
-                illegal_chars.iter().collect::<String>()
-            )));
-        }
-
+        check_key(key)?;
         let pair = Pair {
             id: key.to_string(),
             value: value.to_vec(),
+            json: None,
             store_id: self.store_id.clone(),
         };
         self.client
@@ -211,7 +206,7 @@ impl Store for AzureCosmosStore {
             res.extend(
                 resp.results
                     .into_iter()
-                    .map(|(pair, _)| (pair.id, Some(pair.value))),
+                    .map(|(pair, _)| (pair.id.clone(), Some(pair.into_value()))),
             );
         }
         Ok(res)
@@ -299,6 +294,81 @@ impl Store for AzureCosmosStore {
             store_id: self.store_id.clone(),
         }))
     }
+
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        json::validate_path(path)?;
+        match self.get_entity::<Pair>(key).await? {
+            Some(Pair {
+                json: Some(document),
+                ..
+            }) => document
+                .pointer(path)
+                .map(|value| serde_json::to_string(value).map_err(log_error))
+                .transpose(),
+            Some(pair) => json::get_json_path(&pair.value, path),
+            None => Ok(None),
+        }
+    }
+
+    /// Patches documents written by `patch_json` with Cosmos partial document
+    /// updates. Values written by `set` fall back to rewriting the whole value.
+    async fn patch_json(&self, key: &str, path: &str, value: &str) -> Result<(), Error> {
+        json::validate_path(path)?;
+        let value: serde_json::Value = serde_json::from_str(value)
+            .map_err(|err| Error::Other(format!("invalid JSON: {err}")))?;
+        if path.is_empty() {
+            check_key(key)?;
+            let document = JsonDocument {
+                id: key.to_string(),
+                json: value,
+                store_id: self.store_id.clone(),
+            };
+            self.client
+                .create_document(document)
+                .is_upsert(true)
+                .await
+                .map_err(log_error)?;
+            return Ok(());
+        }
+
+        let target = format!("/json{path}");
+        let operation = if path.ends_with("/-") {
+            Operation::add(target, &value)
+        } else {
+            Operation::set(target, &value)
+        };
+        match self
+            .client
+            .document_client(key, &self.store_id.clone().unwrap_or(key.to_string()))
+            .map_err(log_error)?
+            .patch_document(vec![operation.map_err(log_error)?])
+            .await
+        {
+            Ok(_) => Ok(()),
+            // The document doesn't exist, isn't a JSON document, or lacks the
+            // parent of `path`: these are reported by the fallback
+            Err(e)
+                if e.as_http_error()
+                    .is_some_and(|e| e.status() == 400 || e.status() == 404) =>
+            {
+                json::patch_json(self, key, path, &value.to_string()).await
+            }
+            Err(e) => Err(log_error(e)),
+        }
+    }
+}
+
+/// Checks that a key can be used as a document ID.
+fn check_key(key: &str) -> Result<(), Error> {
+    let illegal_chars = ['/', '\\', '?', '#'];
+
+    if key.contains(|c| illegal_chars.contains(&c)) {
+        return Err(Error::Other(format!(
+            "Key contains an illegal character. Keys must not include any of: {}",
+            illegal_chars.iter().collect::<String>()
+        )));
+    }
+    Ok(())
 }
 
 struct CompareAndSwap {
@@ -338,9 +408,9 @@ impl Cas for CompareAndSwap {
                 let r = r.map_err(log_error)?;
                 match r.results.first() {
                     Some((item, Some(attr))) => {
-                        Some((item.clone().value, Some(attr.etag().to_string())))
+                        Some((item.clone().into_value(), Some(attr.etag().to_string())))
                     }
-                    Some((item, None)) => Some((item.clone().value, None)),
+                    Some((item, None)) => Some((item.clone().into_value(), None)),
                     _ => None,
                 }
             }
@@ -362,6 +432,7 @@ impl Cas for CompareAndSwap {
         let pair = Pair {
             id: self.key.clone(),
             value,
+            json: None,
             store_id: self.store_id.clone(),
         };
 
@@ -491,11 +562,25 @@ fn append_store_id_condition(
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Pair {
     pub id: String,
+    #[serde(default)]
     pub value: Vec<u8>,
+    /// The document, if the value was written as JSON by `patch_json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store_id: Option<String>,
 }
 
+impl Pair {
+    /// Returns the value, serializing it if it was written as JSON.
+    pub fn into_value(self) -> Vec<u8> {
+        match self.json {
+            Some(json) => json.to_string().into_bytes(),
+            None => self.value,
+        }
+    }
+}
+
 impl CosmosEntity for Pair {
     type Entity = String;
 
@@ -521,6 +606,23 @@ impl CosmosEntity for Counter {
     }
 }
 
+// JsonDocument structure for JSON patch operations
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JsonDocument {
+    pub id: String,
+    pub json: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_id: Option<String>,
+}
+
+impl CosmosEntity for JsonDocument {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.store_id.clone().unwrap_or_else(|| self.id.clone())
+    }
+}
+
 // Key structure for operations with generic value types
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Key {
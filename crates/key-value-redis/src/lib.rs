@@ -1,5 +1,6 @@
 mod store;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
 use store::KeyValueRedis;
@@ -18,7 +19,8 @@ impl RedisKeyValueStore {
 }
 
 /// Runtime configuration for the Redis key-value store.
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RedisKeyValueRuntimeConfig {
     /// The URL of the Redis server.
     url: String,
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use redis::{aio::ConnectionManager, parse_redis_url, AsyncCommands, Client, RedisError};
 use spin_core::async_trait;
-use spin_factor_key_value::{log_error, Cas, Error, Store, StoreManager, SwapError};
+use spin_factor_key_value::{json, log_error, Cas, Error, Store, StoreManager, SwapError};
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 use url::Url;
@@ -140,6 +140,70 @@ impl Store for RedisStore {
             bucket_rep,
         }))
     }
+
+    /// Reads part of a RedisJSON document natively. Other values, or servers
+    /// without RedisJSON, fall back to fetching the whole value.
+    async fn get_json(&self, key: &str, path: &str) -> Result<Option<String>, Error> {
+        let result: Result<Option<String>, RedisError> = redis::cmd("JSON.GET")
+            .arg(key)
+            .arg(json_path(path)?)
+            .query_async(&mut self.connection.clone())
+            .await;
+        match result {
+            Ok(None) => Ok(None),
+            // A JSONPath query returns an array of the values it matched
+            Ok(Some(matches)) => {
+                let matches: Vec<serde_json::Value> =
+                    serde_json::from_str(&matches).map_err(log_error)?;
+                matches
+                    .first()
+                    .map(|value| serde_json::to_string(value).map_err(log_error))
+                    .transpose()
+            }
+            Err(err) if err.code().is_some() => json::get_json(self, key, path).await,
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    /// Updates part of a RedisJSON document natively. Other values, or
+    /// servers without RedisJSON, fall back to rewriting the whole value.
+    async fn patch_json(&self, key: &str, path: &str, value: &str) -> Result<(), Error> {
+        // Whole documents are stored as plain values so that `get` can read them
+        if path.is_empty() || path.ends_with("/-") {
+            return json::patch_json(self, key, path, value).await;
+        }
+        let result: Result<Option<String>, RedisError> = redis::cmd("JSON.SET")
+            .arg(key)
+            .arg(json_path(path)?)
+            .arg(value)
+            .query_async(&mut self.connection.clone())
+            .await;
+        match result {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(Error::Other(format!(
+                "the parent of {path:?} doesn't exist"
+            ))),
+            Err(err) if err.code().is_some() => json::patch_json(self, key, path, value).await,
+            Err(err) => Err(log_error(err)),
+        }
+    }
+}
+
+/// Converts a JSON Pointer to the equivalent RedisJSON JSONPath.
+///
+/// Numeric reference tokens are treated as array indices.
+fn json_path(pointer: &str) -> Result<String, Error> {
+    let mut path = String::from("$");
+    for token in json::path_tokens(pointer)? {
+        match token.parse::<usize>() {
+            Ok(index) => path.push_str(&format!("[{index}]")),
+            Err(_) => {
+                let token = serde_json::to_string(&token).map_err(log_error)?;
+                path.push_str(&format!("[{token}]"));
+            }
+        }
+    }
+    Ok(path)
 }
 
 #[async_trait]
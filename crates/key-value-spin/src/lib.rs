@@ -1,11 +1,14 @@
+pub mod maintenance;
 mod store;
 
 use std::{
     fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Context as _;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
 use store::{DatabaseLocation, KeyValueSqlite};
@@ -60,25 +63,75 @@ impl MakeKeyValueStore for SpinKeyValueStore {
                 })?;
             }
         }
-        Ok(KeyValueSqlite::new(location))
+        let store = KeyValueSqlite::new(location);
+        Ok(match runtime_config.maintenance_interval_secs {
+            Some(0) => store,
+            Some(secs) => store.with_maintenance(Duration::from_secs(secs)),
+            None => store.with_maintenance(maintenance::DEFAULT_MAINTENANCE_INTERVAL),
+        })
     }
 }
 
 /// The serialized runtime configuration for the SQLite key-value store.
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SpinKeyValueRuntimeConfig {
     /// The path to the SQLite database file.
     path: Option<PathBuf>,
+    /// How often, in seconds, to delete expired records from and vacuum the
+    /// database file. Defaults to hourly; 0 disables maintenance.
+    #[serde(default)]
+    maintenance_interval_secs: Option<u64>,
 }
 
 impl SpinKeyValueRuntimeConfig {
     /// Create a new SpinKeyValueRuntimeConfig with the given parent directory
     /// where the key-value store will live.
     pub fn new(path: Option<PathBuf>) -> Self {
-        Self { path }
+        Self {
+            path,
+            maintenance_interval_secs: None,
+        }
+    }
+}
+
+/// A key-value store that is always in-memory, whatever the base path.
+///
+/// Each configured store gets its own database, which lasts as long as the
+/// app is running.
+#[derive(Default)]
+pub struct MemoryKeyValueStore {
+    _priv: (),
+}
+
+impl MemoryKeyValueStore {
+    /// Create a new MemoryKeyValueStore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MakeKeyValueStore for MemoryKeyValueStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "memory";
+
+    type RuntimeConfig = MemoryKeyValueRuntimeConfig;
+
+    type StoreManager = KeyValueSqlite;
+
+    fn make_store(
+        &self,
+        _runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::StoreManager> {
+        Ok(KeyValueSqlite::new(DatabaseLocation::InMemory))
     }
 }
 
+/// The serialized runtime configuration for the in-memory key-value store,
+/// which has no options.
+#[derive(Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryKeyValueRuntimeConfig {}
+
 /// Resolve a relative path against a base dir.
 ///
 /// If the path is absolute, it is returned as is. Otherwise, it is resolved against the base dir.
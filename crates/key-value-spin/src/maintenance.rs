@@ -0,0 +1,206 @@
+//! Periodic maintenance of file-backed stores.
+//!
+//! Deleting keys doesn't shrink a SQLite file, and the expired records that
+//! Spin's caches and sessions leave behind are only removed when they happen
+//! to be read again, so without maintenance a long-running app's store only
+//! ever grows.
+
+use std::{
+    sync::{Mutex, Weak},
+    time::Duration,
+};
+
+use rusqlite::{named_params, Connection};
+use spin_common::time::now_millis;
+
+/// The interval between maintenance runs, unless configured otherwise.
+pub const DEFAULT_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Key prefixes of records written with an expiry time, and the offset of the
+/// big-endian expiry time (milliseconds since the Unix epoch, zero meaning
+/// never) in their values.
+///
+/// These mirror the encodings used by the cache and session factors and by
+/// triggers' message deduplication.
+const EXPIRING_RECORDS: &[(&str, usize)] = &[
+    ("spin-cache:entry:", 0),
+    ("spin-cache:lock:", 16),
+    ("spin-dedupe:", 0),
+    ("spin-session:", 0),
+];
+
+/// What a maintenance run did.
+#[derive(Debug, Default, PartialEq)]
+pub struct MaintenanceReport {
+    /// The number of expired records deleted.
+    pub expired: usize,
+    /// The number of records left in the database.
+    pub entries: u64,
+    /// The size of the database file in bytes.
+    pub size_bytes: u64,
+}
+
+/// Runs maintenance on the database every `interval` for as long as
+/// `connection` is in use.
+pub(crate) fn spawn(connection: Weak<Mutex<Connection>>, summary: String, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately; there's nothing to do at startup
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let Some(connection) = connection.upgrade() else {
+                return;
+            };
+            let report = tokio::task::spawn_blocking(move || {
+                run_maintenance(&connection.lock().unwrap(), now_millis())
+            })
+            .await;
+            match report {
+                Ok(Ok(report)) => record(&summary, &report),
+                Ok(Err(err)) => {
+                    tracing::warn!("Maintenance of key-value store {summary} failed: {err}")
+                }
+                Err(err) => {
+                    tracing::warn!("Maintenance of key-value store {summary} panicked: {err}")
+                }
+            }
+        }
+    });
+}
+
+/// Deletes expired records, vacuums the database and measures what's left.
+pub fn run_maintenance(
+    connection: &Connection,
+    now_millis: u64,
+) -> rusqlite::Result<MaintenanceReport> {
+    let mut expired = 0;
+    for (prefix, offset) in EXPIRING_RECORDS {
+        // Big-endian times compare as blobs in the same order as numbers.
+        // Tenants' keys have their tenant prefix in front.
+        expired += connection
+            .prepare_cached(
+                "DELETE FROM spin_key_value
+                 WHERE (key GLOB :prefix || '*' OR key GLOB '*/' || :prefix || '*')
+                   AND length(value) >= :start + 7
+                   AND substr(value, :start, 8) != zeroblob(8)
+                   AND substr(value, :start, 8) <= :now",
+            )?
+            .execute(named_params! {
+                ":prefix": prefix,
+                ":start": offset + 1,
+                ":now": now_millis.to_be_bytes(),
+            })?;
+    }
+    connection.execute_batch("VACUUM")?;
+
+    let entries =
+        connection.query_row("SELECT COUNT(*) FROM spin_key_value", [], |row| row.get(0))?;
+    let size_bytes = connection.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(MaintenanceReport {
+        expired,
+        entries,
+        size_bytes,
+    })
+}
+
+fn record(summary: &str, report: &MaintenanceReport) {
+    tracing::debug!(
+        "Maintained key-value store {summary}: removed {} expired records, {} records left in {} bytes",
+        report.expired,
+        report.entries,
+        report.size_bytes
+    );
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.key_value_expired_records = report.expired as u64,
+        store = summary
+    );
+    spin_telemetry::metrics::histogram!(
+        spin.key_value_store_size_bytes = report.size_bytes,
+        store = summary
+    );
+    spin_telemetry::metrics::histogram!(
+        spin.key_value_store_entries = report.entries,
+        store = summary
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute(
+                "CREATE TABLE spin_key_value (
+                    store TEXT NOT NULL,
+                    key   TEXT NOT NULL,
+                    value BLOB NOT NULL,
+                    PRIMARY KEY (store, key)
+                )",
+                [],
+            )
+            .unwrap();
+        connection
+    }
+
+    fn insert(connection: &Connection, key: &str, value: &[u8]) {
+        connection
+            .execute(
+                "INSERT INTO spin_key_value (store, key, value) VALUES ('default', ?1, ?2)",
+                rusqlite::params![key, value],
+            )
+            .unwrap();
+    }
+
+    fn keys(connection: &Connection) -> Vec<String> {
+        connection
+            .prepare("SELECT key FROM spin_key_value ORDER BY key")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn expiring(expires_at: u64) -> Vec<u8> {
+        let mut value = expires_at.to_be_bytes().to_vec();
+        value.extend_from_slice(b"data");
+        value
+    }
+
+    #[test]
+    fn maintenance_deletes_only_expired_records() {
+        let connection = database();
+        insert(&connection, "spin-cache:entry:old", &expiring(1_000));
+        insert(&connection, "spin-cache:entry:new", &expiring(3_000));
+        insert(&connection, "spin-cache:entry:forever", &expiring(0));
+        insert(&connection, "tenant/spin-session:old", &expiring(1_999));
+        let mut lock = 7u128.to_be_bytes().to_vec();
+        lock.extend_from_slice(&1_000u64.to_be_bytes());
+        insert(&connection, "spin-cache:lock:old", &lock);
+        insert(&connection, "user-key", &expiring(1_000));
+        insert(&connection, "spin-session:short", b"x");
+
+        let report = run_maintenance(&connection, 2_000).unwrap();
+
+        assert_eq!(3, report.expired);
+        assert_eq!(4, report.entries);
+        assert!(report.size_bytes > 0);
+        assert_eq!(
+            vec![
+                "spin-cache:entry:forever",
+                "spin-cache:entry:new",
+                "spin-session:short",
+                "user-key",
+            ],
+            keys(&connection)
+        );
+    }
+}
@@ -7,6 +7,7 @@ use std::{
     path::PathBuf,
     sync::OnceLock,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use tokio::task;
 
@@ -19,6 +20,8 @@ pub enum DatabaseLocation {
 pub struct KeyValueSqlite {
     location: DatabaseLocation,
     connection: OnceLock<Arc<Mutex<Connection>>>,
+    /// How often to maintain a file-backed database, if at all.
+    maintenance_interval: Option<Duration>,
 }
 
 impl KeyValueSqlite {
@@ -31,9 +34,17 @@ impl KeyValueSqlite {
         Self {
             location,
             connection: OnceLock::new(),
+            maintenance_interval: None,
         }
     }
 
+    /// Periodically deletes expired records from and vacuums the database,
+    /// once it's opened, if it's stored in a file.
+    pub fn with_maintenance(mut self, interval: Duration) -> Self {
+        self.maintenance_interval = Some(interval);
+        self
+    }
+
     fn create_connection(&self) -> Result<Arc<Mutex<Connection>>, Error> {
         let connection = match &self.location {
             DatabaseLocation::InMemory => Connection::open_in_memory(),
@@ -71,7 +82,16 @@ impl StoreManager for KeyValueSqlite {
             // Only create the connection if we failed to get it.
             // We might do duplicate work here if there's a race, but that's fine.
             let new = self.create_connection()?;
-            Ok(self.connection.get_or_init(|| new))
+            let connection = self.connection.get_or_init(|| new.clone());
+            if let (Some(interval), DatabaseLocation::Path(path), true) = (
+                self.maintenance_interval,
+                &self.location,
+                Arc::ptr_eq(connection, &new),
+            ) {
+                let summary = format!("\"{}\"", path.display());
+                crate::maintenance::spawn(Arc::downgrade(connection), summary, interval);
+            }
+            Ok(connection)
         })?;
 
         Ok(Arc::new(SqliteStore {
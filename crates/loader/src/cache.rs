@@ -3,17 +3,24 @@
 use anyhow::{ensure, Context, Result};
 
 use std::{
-    path::PathBuf,
-    sync::atomic::{AtomicBool, Ordering},
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
 };
 
-use crate::fs::{create_dir_all, write_file};
+use crate::fs::{create_dir_all, rename, write_file};
 
 const CONFIG_DIR: &str = "spin";
 const REGISTRY_CACHE_DIR: &str = "registry";
 const MANIFESTS_DIR: &str = "manifests";
 const WASM_DIR: &str = "wasm";
 const DATA_DIR: &str = "data";
+const REMOTE_FILES_DIR: &str = "remote-files";
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// Distinguishes temporary files written concurrently by the same process.
+static PARTIAL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// Cache for registry entities.
 #[derive(Debug)]
@@ -57,6 +64,11 @@ impl Cache {
         self.root.join(DATA_DIR)
     }
 
+    /// The directory for files synced from remote file mounts.
+    pub fn remote_files_dir(&self) -> PathBuf {
+        self.root.join(REMOTE_FILES_DIR)
+    }
+
     /// Return the path to a wasm file given its digest.
     pub fn wasm_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
         // Check the expected wasm directory first; else check the data directory as a fallback.
@@ -89,15 +101,81 @@ impl Cache {
     /// Write the contents in the cache's wasm directory.
     pub async fn write_wasm(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
         self.ensure_dirs().await?;
-        write_file(&self.wasm_path(digest), bytes.as_ref()).await?;
-        Ok(())
+        write_file_atomic(&self.wasm_path(digest), bytes.as_ref()).await
     }
 
     /// Write the contents in the cache's data directory.
     pub async fn write_data(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
         self.ensure_dirs().await?;
-        write_file(&self.data_path(digest), bytes.as_ref()).await?;
-        Ok(())
+        write_file_atomic(&self.data_path(digest), bytes.as_ref()).await
+    }
+
+    /// Record that a cached file was used, so that [`Cache::gc`] keeps it.
+    pub fn mark_used(&self, path: &Path) {
+        let touched = std::fs::File::options()
+            .append(true)
+            .open(path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            tracing::debug!("failed to mark {} as used: {e}", path.display());
+        }
+    }
+
+    /// Remove cache entries that have not been used for `max_age`: first
+    /// the manifests of such apps, then any Wasm and data files that no
+    /// remaining manifest refers to.
+    ///
+    /// Files used within `max_age` are kept even if unreferenced, so that
+    /// a pull or load running concurrently does not lose files it has just
+    /// found in the cache.
+    pub async fn gc(&self, max_age: Duration) -> Result<GcSummary> {
+        let mut summary = GcSummary::default();
+        let is_stale = |path: &Path| -> bool {
+            std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > max_age)
+        };
+
+        let mut referenced = HashSet::new();
+        for manifest_dir in app_manifest_dirs(&self.manifests_dir())? {
+            if is_stale(&manifest_dir.join(MANIFEST_FILE)) {
+                std::fs::remove_dir_all(&manifest_dir)
+                    .with_context(|| format!("failed to remove `{}`", manifest_dir.display()))?;
+                summary.manifests_removed += 1;
+                continue;
+            }
+            for file in [MANIFEST_FILE, CONFIG_FILE] {
+                if let Ok(json) = std::fs::read(manifest_dir.join(file)) {
+                    if let Ok(value) = serde_json::from_slice(&json) {
+                        collect_digests(&value, &mut referenced);
+                    }
+                }
+            }
+        }
+        let referenced = referenced
+            .iter()
+            .flat_map(|digest| [self.wasm_path(digest), self.data_path(digest)])
+            .collect::<HashSet<_>>();
+
+        for dir in [self.wasm_dir(), self.data_dir()] {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry?.path();
+                if !path.is_file() || referenced.contains(&path) || !is_stale(&path) {
+                    continue;
+                }
+                let size = path.metadata().map(|m| m.len()).unwrap_or_default();
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove `{}`", path.display()))?;
+                summary.files_removed += 1;
+                summary.bytes_freed += size;
+            }
+        }
+        Ok(summary)
     }
 
     /// The path of contents in the cache's wasm directory, which may or may not exist.
@@ -157,6 +235,73 @@ impl Cache {
     }
 }
 
+/// What [`Cache::gc`] removed.
+#[derive(Debug, Default, PartialEq)]
+pub struct GcSummary {
+    /// The number of cached app manifests removed.
+    pub manifests_removed: usize,
+    /// The number of Wasm and data files removed.
+    pub files_removed: usize,
+    /// The total size of the Wasm and data files removed.
+    pub bytes_freed: u64,
+}
+
+/// The name of the manifest file in a cached app's manifest directory.
+const MANIFEST_FILE: &str = "manifest.json";
+/// The name of the locked app config file in a cached app's manifest directory.
+const CONFIG_FILE: &str = "config.json";
+
+/// Writes the file under a temporary name and then renames it into place, so
+/// that concurrent readers never see a partially written file.
+async fn write_file_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let count = PARTIAL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let partial = path.with_extension(format!(
+        "{}.{count}.{PARTIAL_EXTENSION}",
+        std::process::id()
+    ));
+    write_file(&partial, bytes).await?;
+    rename(&partial, path).await
+}
+
+/// Finds the directories under `manifests_dir` that contain a cached app
+/// manifest.
+fn app_manifest_dirs(manifests_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    let mut pending = vec![manifests_dir.to_owned()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        if dir.join(MANIFEST_FILE).is_file() {
+            found.push(dir);
+            continue;
+        }
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Collects all the digests in the given JSON.
+fn collect_digests(value: &serde_json::Value, digests: &mut HashSet<String>) {
+    match value {
+        serde_json::Value::String(s) if s.starts_with("sha256:") => {
+            digests.insert(s.clone());
+        }
+        serde_json::Value::Array(values) => {
+            values.iter().for_each(|v| collect_digests(v, digests));
+        }
+        serde_json::Value::Object(map) => {
+            map.values().for_each(|v| collect_digests(v, digests));
+        }
+        _ => {}
+    }
+}
+
 #[cfg(windows)]
 fn safe_name(digest: impl AsRef<str>) -> impl AsRef<std::path::Path> {
     digest.as_ref().replace(':', "_")
@@ -190,4 +335,33 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn gc_removes_only_stale_unreferenced_files() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache = Cache::new(Some(temp_dir.path().to_owned())).await?;
+
+        let digest_of = |bytes: &[u8]| format!("sha256:{}", hex_digest_from_bytes(bytes));
+        let (referenced, unreferenced) = (digest_of(b"used"), digest_of(b"unused"));
+        cache.write_wasm(b"used", &referenced).await?;
+        cache.write_data(b"unused", &unreferenced).await?;
+
+        let manifest_dir = cache.manifests_dir().join("example.com/app/1.0");
+        std::fs::create_dir_all(&manifest_dir)?;
+        let manifest = serde_json::json!({ "layers": [{ "digest": referenced }] });
+        std::fs::write(manifest_dir.join(MANIFEST_FILE), manifest.to_string())?;
+
+        // Everything was used recently
+        let summary = cache.gc(Duration::from_secs(3600)).await?;
+        assert_eq!(summary, GcSummary::default());
+
+        // The manifest is stale too, so it goes, and then so does its layer
+        let summary = cache.gc(Duration::ZERO).await?;
+        assert_eq!(summary.manifests_removed, 1);
+        assert_eq!(summary.files_removed, 2);
+        assert!(cache.wasm_file(&referenced).is_err());
+        assert!(cache.data_file(&unreferenced).is_err());
+
+        Ok(())
+    }
 }
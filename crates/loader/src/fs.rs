@@ -22,6 +22,10 @@ mod io {
     pub async fn metadata(path: &Path) -> Result<std::fs::Metadata> {
         tokio::fs::metadata(path).await.map_err(Into::into)
     }
+
+    pub async fn rename(from: &Path, to: &Path) -> Result<()> {
+        tokio::fs::rename(from, to).await.map_err(Into::into)
+    }
 }
 
 #[cfg(not(feature = "async-io"))]
@@ -45,6 +49,10 @@ mod io {
     pub async fn metadata(path: &Path) -> Result<std::fs::Metadata> {
         Ok(std::fs::metadata(path)?)
     }
+
+    pub async fn rename(from: &Path, to: &Path) -> Result<()> {
+        Ok(std::fs::rename(from, to)?)
+    }
 }
 
 pub use io::*;
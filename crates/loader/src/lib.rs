@@ -22,6 +22,10 @@ mod fs;
 #[cfg(feature = "async-io")]
 mod http;
 mod local;
+pub mod lockfile;
+mod remote_files;
+
+pub use remote_files::is_remote_files_source;
 
 /// Maximum number of files to copy (or download) concurrently
 pub(crate) const MAX_FILE_LOADING_CONCURRENCY: usize = 16;
@@ -33,11 +37,22 @@ pub async fn from_file(
     manifest_path: impl AsRef<Path>,
     files_mount_strategy: FilesMountStrategy,
     cache_root: Option<PathBuf>,
+) -> Result<LockedApp> {
+    from_file_with_profile(manifest_path, None, files_mount_strategy, cache_root).await
+}
+
+/// Load a Spin locked app from a spin.toml manifest file, as [`from_file`],
+/// first overlaying the given `[profile.<name>]` if any.
+pub async fn from_file_with_profile(
+    manifest_path: impl AsRef<Path>,
+    profile: Option<&str>,
+    files_mount_strategy: FilesMountStrategy,
+    cache_root: Option<PathBuf>,
 ) -> Result<LockedApp> {
     let path = manifest_path.as_ref();
     let app_root = parent_dir(path).context("manifest path has no parent directory")?;
     let loader = LocalLoader::new(&app_root, files_mount_strategy, cache_root).await?;
-    loader.load_file(path).await
+    loader.load_file(path, profile).await
 }
 
 /// Load a Spin locked app from a standalone Wasm file.
@@ -48,6 +63,20 @@ pub async fn from_wasm_file(wasm_path: impl AsRef<Path>) -> Result<LockedApp> {
     loader.load_manifest(manifest).await
 }
 
+/// Syncs the app's remote file mounts, i.e. Azure Blob Storage or S3
+/// prefixes, to the cache, and points the mounts at the synced files. Only
+/// objects whose ETag has changed since the last sync are downloaded.
+///
+/// Loaders leave remote file mounts unresolved, so that they are not copied
+/// into registry artifacts; this must be called before running the app.
+pub async fn sync_remote_files(
+    locked_app: &mut LockedApp,
+    cache_root: Option<PathBuf>,
+) -> Result<()> {
+    let cache = cache::Cache::new(cache_root).await?;
+    remote_files::sync_app(locked_app, &cache).await
+}
+
 /// The strategy to use for mounting WASI files into a guest.
 #[derive(Debug)]
 pub enum FilesMountStrategy {
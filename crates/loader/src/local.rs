@@ -45,16 +45,29 @@ impl LocalLoader {
     }
 
     // Load the manifest file (spin.toml) at the given path into a LockedApp,
-    // preparing all its content for execution.
-    pub async fn load_file(&self, path: impl AsRef<Path>) -> Result<LockedApp> {
+    // preparing all its content for execution. If a profile is given, it is
+    // overlaid on the manifest first.
+    pub async fn load_file(
+        &self,
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+    ) -> Result<LockedApp> {
         // Parse manifest
         let path = path.as_ref();
-        let manifest = spin_manifest::manifest_from_file(path).with_context(|| {
+        let mut manifest = spin_manifest::manifest_from_file(path).with_context(|| {
             format!(
                 "Failed to read Spin app manifest from {}",
                 quoted_path(path)
             )
         })?;
+        if let Some(profile) = profile {
+            spin_manifest::profile::apply_profile(&mut manifest, profile).with_context(|| {
+                format!(
+                    "Failed to apply profile {profile:?} to {}",
+                    quoted_path(path)
+                )
+            })?;
+        }
         let mut locked = self
             .load_manifest(manifest)
             .await
@@ -65,6 +78,12 @@ impl LocalLoader {
             .metadata
             .insert("origin".into(), file_url(path)?.into());
 
+        // Carry the lockfile, if any, so it is published along with the app
+        if let Some(lockfile) = crate::lockfile::AppLockfile::load(path)? {
+            let lockfile = serde_json::to_value(lockfile).context("invalid lockfile")?;
+            locked.metadata.insert("lockfile".into(), lockfile);
+        }
+
         Ok(locked)
     }
 
@@ -80,6 +99,7 @@ impl LocalLoader {
             variables,
             triggers,
             components,
+            profiles: _,
         } = manifest;
 
         let metadata = locked_metadata(application, triggers.keys().cloned())?;
@@ -94,16 +114,28 @@ impl LocalLoader {
             .flat_map(|(trigger_type, configs)| {
                 configs
                     .into_iter()
-                    .map(|trigger| locked_trigger(trigger_type.clone(), trigger))
+                    .map(|mut trigger| {
+                        if trigger_type == "http" {
+                            self.inline_openapi_document(&mut trigger.config)?;
+                        }
+                        locked_trigger(trigger_type.clone(), trigger)
+                    })
                     .collect::<Vec<_>>()
             })
             .collect::<Result<Vec<_>>>()?;
 
+        // Sources of all components, for dependencies on other components
+        let component_sources = components
+            .iter()
+            .map(|(id, c)| (id.clone(), c.source.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let component_sources = &component_sources;
+
         let sloth_guard = warn_if_component_load_slothful();
 
         // Load all components concurrently
         let components = try_join_all(components.into_iter().map(|(id, c)| async move {
-            self.load_component(&id, c)
+            self.load_component(&id, c, component_sources)
                 .await
                 .with_context(|| format!("Failed to load component `{id}`"))
         }))
@@ -133,11 +165,28 @@ impl LocalLoader {
         })
     }
 
+    // Inline the OpenAPI document referenced by an HTTP trigger, so that the
+    // locked app carries it wherever the app is run.
+    fn inline_openapi_document(&self, config: &mut toml::Table) -> Result<()> {
+        let Some(toml::Value::Table(openapi)) = config.get_mut("openapi") else {
+            return Ok(());
+        };
+        let Some(toml::Value::String(spec)) = openapi.get("spec") else {
+            bail!("HTTP trigger `openapi` must have a `spec` path");
+        };
+        let path = self.app_root.join(spec);
+        let document = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read OpenAPI document {}", quoted_path(&path)))?;
+        openapi.insert("document".into(), document.into());
+        Ok(())
+    }
+
     // Load the given component into a LockedComponent, ready for execution.
     async fn load_component(
         &self,
         id: &KebabId,
         component: v2::Component,
+        component_sources: &BTreeMap<KebabId, v2::ComponentSource>,
     ) -> Result<LockedComponent> {
         let allowed_outbound_hosts = component
             .normalized_allowed_outbound_hosts()
@@ -147,12 +196,64 @@ impl LocalLoader {
 
         let component_requires_service_chaining = requires_service_chaining(&component);
 
+        let tmpfs_mounts = component
+            .files
+            .iter()
+            .filter_map(|f| match f {
+                WasiFilesMount::Tmpfs { tmpfs } => Some(tmpfs.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(tmpfs) = tmpfs_mounts.iter().find(|tmpfs| !tmpfs.starts_with('/')) {
+            bail!("tmpfs mount path {tmpfs:?} must be absolute");
+        }
+        if let Some(templates) = component.templates.as_ref().filter(|t| !t.starts_with('/')) {
+            bail!("templates directory {templates:?} must be absolute");
+        }
+        let content_mounts = component
+            .files
+            .iter()
+            .filter(|f| {
+                !matches!(
+                    f,
+                    WasiFilesMount::Tmpfs { .. } | WasiFilesMount::Remote { .. }
+                )
+            })
+            .collect::<Vec<_>>();
+        // Remote mounts stay remote in the locked app; they are synced when
+        // the app is run.
+        let remote_mounts = component
+            .files
+            .iter()
+            .filter_map(|f| match f {
+                WasiFilesMount::Remote {
+                    remote,
+                    destination,
+                } => Some(remote_mount(remote, destination)),
+                _ => None,
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let metadata = ValuesMapBuilder::new()
             .string("description", component.description)
             .string_array("allowed_outbound_hosts", allowed_outbound_hosts)
             .string_array("key_value_stores", component.key_value_stores)
             .string_array("databases", component.sqlite_databases)
+            .string_array("blob_containers", component.blob_containers)
+            .string_array("message_brokers", component.message_brokers)
+            .string_array("host_plugins", component.host_plugins)
+            .string_array("vector_stores", component.vector_stores)
             .string_array("ai_models", component.ai_models)
+            .string_array("image_operations", component.image_operations)
+            .string_array("tmpfs_mounts", tmpfs_mounts)
+            .serializable("read_only_files", component.read_only_files.then_some(true))?
+            .serializable(
+                "files_quota_bytes",
+                component
+                    .files_quota_mb
+                    .map(|mb| mb.saturating_mul(1024 * 1024)),
+            )?
+            .serializable("templates", component.templates)?
             .serializable("build", component.build)?
             .take();
 
@@ -160,25 +261,33 @@ impl LocalLoader {
             .load_component_source(id, component.source.clone())
             .await
             .with_context(|| format!("Failed to load Wasm source {}", component.source))?;
+        let source = if component.middleware.is_empty() {
+            source
+        } else {
+            self.compose_middleware(id, source, &component.middleware)
+                .await
+                .with_context(|| format!("Failed to compose middleware for `{id}`"))?
+        };
 
         let dependencies = self
             .load_component_dependencies(
                 id,
                 component.dependencies_inherit_configuration,
                 &component.dependencies,
+                component_sources,
             )
             .await?;
 
         let env = component.environment.into_iter().collect();
 
-        let files = if component.files.is_empty() {
+        let mut files = if content_mounts.is_empty() {
             vec![]
         } else {
             match &self.files_mount_strategy {
                 FilesMountStrategy::Copy(files_mount_root) => {
                     let component_mount_root = files_mount_root.join(id.as_ref());
                     // Copy mounted files into component mount root, concurrently
-                    try_join_all(component.files.iter().map(|f| {
+                    try_join_all(content_mounts.iter().map(|f| {
                         self.copy_file_mounts(f, &component_mount_root, &component.exclude_files)
                     }))
                     .await?;
@@ -195,7 +304,7 @@ impl LocalLoader {
                         "Cannot load a component with `exclude_files` using --direct-mounts"
                     );
                     let mut files = vec![];
-                    for mount in &component.files {
+                    for mount in &content_mounts {
                         // Validate (and canonicalize) direct mount directory
                         files.push(self.resolve_direct_mount(mount).await?);
                     }
@@ -204,6 +313,8 @@ impl LocalLoader {
             }
         };
 
+        files.extend(remote_mounts);
+
         let config = component
             .variables
             .into_iter()
@@ -236,6 +347,7 @@ impl LocalLoader {
         id: &KebabId,
         inherit_configuration: bool,
         dependencies: &v2::ComponentDependencies,
+        component_sources: &BTreeMap<KebabId, v2::ComponentSource>,
     ) -> Result<BTreeMap<DependencyName, LockedComponentDependency>> {
         Ok(try_join_all(dependencies.inner.iter().map(
             |(dependency_name, dependency)| async move {
@@ -244,6 +356,7 @@ impl LocalLoader {
                         inherit_configuration,
                         dependency_name.clone(),
                         dependency.clone(),
+                        component_sources,
                     )
                     .await
                     .with_context(|| {
@@ -265,6 +378,7 @@ impl LocalLoader {
         inherit_configuration: bool,
         dependency_name: DependencyName,
         dependency: v2::ComponentDependency,
+        component_sources: &BTreeMap<KebabId, v2::ComponentSource>,
     ) -> Result<LockedComponentDependency> {
         let (content, export) = match dependency {
             v2::ComponentDependency::Version(version) => {
@@ -328,6 +442,17 @@ impl LocalLoader {
                 let content = self.load_http_source(&url, &digest).await?;
                 (content, export)
             }
+            v2::ComponentDependency::AppComponent { component, export } => {
+                // The manifest has already been validated, so the component exists
+                let source = component_sources.get(&component).with_context(|| {
+                    format!("Component dependency {dependency_name:?} refers to unknown component {component:?}")
+                })?;
+                let content = self
+                    .load_component_source(&component, source.clone())
+                    .await?
+                    .content;
+                (content, export)
+            }
         };
 
         Ok(LockedComponentDependency {
@@ -374,6 +499,48 @@ impl LocalLoader {
         })
     }
 
+    // Compose the component's middleware around its source, returning the
+    // composed Wasm, which is written to the cache.
+    async fn compose_middleware(
+        &self,
+        component_id: &KebabId,
+        source: LockedComponentSource,
+        middleware: &[v2::ComponentSource],
+    ) -> Result<LockedComponentSource> {
+        use spin_compose::ComponentSourceLoader;
+
+        let loader = spin_compose::ComponentSourceLoaderFs;
+        let component = loader.load_component_source(&source).await?;
+        let mut layers = Vec::with_capacity(middleware.len());
+        for layer in middleware {
+            let layer_source = self
+                .load_component_source(component_id, layer.clone())
+                .await
+                .with_context(|| format!("Failed to load middleware source {layer}"))?;
+            layers.push(loader.load_component_source(&layer_source).await?);
+        }
+        let composed = spin_compose::compose_middleware(component, layers)?;
+
+        let digest = format!(
+            "sha256:{}",
+            spin_common::sha256::hex_digest_from_bytes(&composed)
+        );
+        let path = match self.cache.wasm_file(&digest) {
+            Ok(cached_path) => {
+                self.cache.mark_used(&cached_path);
+                cached_path
+            }
+            Err(_) => {
+                self.cache.write_wasm(&composed, &digest).await?;
+                self.cache.wasm_file(&digest)?
+            }
+        };
+        Ok(LockedComponentSource {
+            content_type: source.content_type,
+            content: file_content_ref(path)?,
+        })
+    }
+
     // Load a Wasm source from the given HTTP ContentRef source URL and
     // return a ContentRef an absolute path to the local copy.
     async fn load_http_source(&self, url: &str, digest: &str) -> Result<ContentRef> {
@@ -382,6 +549,7 @@ impl LocalLoader {
             "invalid `digest` {digest:?}; must start with 'sha256:'"
         );
         let path = if let Ok(cached_path) = self.cache.wasm_file(digest) {
+            self.cache.mark_used(&cached_path);
             cached_path
         } else {
             let _loading_permit = self.file_loading_permits.acquire().await?;
@@ -440,6 +608,7 @@ impl LocalLoader {
         };
 
         let path = if let Ok(cached_path) = self.cache.wasm_file(&digest) {
+            self.cache.mark_used(&cached_path);
             cached_path
         } else {
             let mut stm = pkg_loader.stream_content(package, &release).await?;
@@ -447,13 +616,26 @@ impl LocalLoader {
             self.cache.ensure_dirs().await?;
             let dest = self.cache.wasm_path(&digest);
 
-            let mut file = tokio::fs::File::create(&dest).await?;
-            while let Some(block) = stm.next().await {
-                let bytes = block.context("Failed to get content from registry")?;
-                file.write_all(&bytes)
-                    .await
-                    .context("Failed to save registry content to cache")?;
+            // Download to a tempfile and move into place when complete, so
+            // an interrupted download is never mistaken for cached content.
+            let prefix = format!("download-{}", digest.replace(':', "-"));
+            let dest_dir = dest.parent().context("invalid dest")?;
+            let (temp_file, temp_path) = tempfile::NamedTempFile::with_prefix_in(prefix, dest_dir)
+                .context("error creating download tempfile")?
+                .into_parts();
+            {
+                let mut file = tokio::fs::File::from_std(temp_file);
+                while let Some(block) = stm.next().await {
+                    let bytes = block.context("Failed to get content from registry")?;
+                    file.write_all(&bytes)
+                        .await
+                        .context("Failed to save registry content to cache")?;
+                }
+                file.flush().await?;
             }
+            temp_path
+                .persist(&dest)
+                .context("Failed to save registry content to cache")?;
 
             dest
         };
@@ -482,6 +664,10 @@ impl LocalLoader {
                 self.copy_file_or_directory(src, &dest, destination, exclude_files)
                     .await
             }
+            // A tmpfs has no content; the host creates it for each instance.
+            WasiFilesMount::Tmpfs { .. } => Ok(()),
+            // Remote content is synced when the app is run.
+            WasiFilesMount::Remote { .. } => Ok(()),
         }
     }
 
@@ -679,6 +865,8 @@ impl LocalLoader {
                 source,
                 destination,
             } => (source, destination),
+            WasiFilesMount::Tmpfs { tmpfs } => bail!("tmpfs mount {tmpfs:?} has no content"),
+            WasiFilesMount::Remote { remote, .. } => bail!("remote mount {remote:?} is not local"),
         };
         let path = self.app_root.join(src);
         if !path.is_dir() {
@@ -691,6 +879,22 @@ impl LocalLoader {
     }
 }
 
+/// Checks a remote mount and returns its unresolved locked form.
+fn remote_mount(remote: &str, destination: &str) -> Result<ContentPath> {
+    ensure!(
+        destination.starts_with('/'),
+        "remote mount destination {destination:?} must be absolute"
+    );
+    crate::remote_files::RemotePrefix::parse(remote)?;
+    Ok(ContentPath {
+        content: ContentRef {
+            source: Some(remote.to_owned()),
+            ..Default::default()
+        },
+        path: destination.into(),
+    })
+}
+
 fn explain_file_mount_source_error(e: anyhow::Error, src: &Path) -> anyhow::Error {
     if let Some(io_error) = e.downcast_ref::<std::io::Error>() {
         if io_error.kind() == std::io::ErrorKind::NotFound {
@@ -877,7 +1081,7 @@ mod test {
         )
         .await?;
         let err = loader
-            .load_file(app_root.join("bad.toml"))
+            .load_file(app_root.join("bad.toml"), None)
             .await
             .expect_err("loader should not have succeeded");
         let err_ctx = format!("{err:#}");
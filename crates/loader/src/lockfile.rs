@@ -0,0 +1,323 @@
+//! The application lockfile (`spin-lock.toml`), which records what an
+//! application was built from so that builds and deploys can be reproduced.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spin_common::{paths::parent_dir, sha256::hex_digest_from_file, ui::quoted_path};
+use spin_manifest::schema::v2::{AppManifest, ComponentSource};
+
+/// The lockfile name, relative to the application manifest.
+pub const LOCKFILE_NAME: &str = "spin-lock.toml";
+
+/// The lockfile format version written by this version of Spin.
+const LOCKFILE_VERSION: u32 = 1;
+
+/// The contents of an application lockfile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppLockfile {
+    /// The lockfile format version.
+    pub version: u32,
+    /// The templates the application was created from, by template ID.
+    #[serde(
+        default,
+        rename = "template",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub templates: BTreeMap<String, LockedTemplate>,
+    /// The resolved source of each component, by component ID.
+    #[serde(
+        default,
+        rename = "component",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub components: BTreeMap<String, LockedComponent>,
+}
+
+impl Default for AppLockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            templates: Default::default(),
+            components: Default::default(),
+        }
+    }
+}
+
+/// A template recorded in the lockfile.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedTemplate {
+    /// Where the template was installed from, e.g. a Git repository URL.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub installed_from: String,
+}
+
+/// A component source recorded in the lockfile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedComponent {
+    /// The component source, as written in the manifest.
+    pub source: String,
+    /// The digest of the component Wasm, in the form `sha256:<hex>`. This is
+    /// absent for registry sources, which are identified by package version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+/// A difference between an application and its lockfile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LockfileMismatch {
+    /// The application has a component the lockfile doesn't record.
+    ComponentAdded(String),
+    /// The lockfile records a component the application doesn't have.
+    ComponentRemoved(String),
+    /// The component's source differs from the one recorded.
+    SourceChanged {
+        /// The component ID.
+        id: String,
+        /// The source recorded in the lockfile.
+        locked: String,
+        /// The source in the manifest.
+        current: String,
+    },
+    /// The component's Wasm differs from the one recorded.
+    DigestChanged {
+        /// The component ID.
+        id: String,
+        /// The digest recorded in the lockfile.
+        locked: Option<String>,
+        /// The digest of the current Wasm.
+        current: Option<String>,
+    },
+}
+
+impl std::fmt::Display for LockfileMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_none = |d: &Option<String>| d.clone().unwrap_or_else(|| "<none>".into());
+        match self {
+            Self::ComponentAdded(id) => write!(f, "component '{id}' is not in the lockfile"),
+            Self::ComponentRemoved(id) => {
+                write!(
+                    f,
+                    "component '{id}' is in the lockfile but not the manifest"
+                )
+            }
+            Self::SourceChanged {
+                id,
+                locked,
+                current,
+            } => write!(
+                f,
+                "component '{id}' source is {current} but the lockfile has {locked}"
+            ),
+            Self::DigestChanged {
+                id,
+                locked,
+                current,
+            } => write!(
+                f,
+                "component '{id}' digest is {} but the lockfile has {}",
+                or_none(current),
+                or_none(locked)
+            ),
+        }
+    }
+}
+
+impl AppLockfile {
+    /// Returns the lockfile path for the given manifest.
+    pub fn path_for(manifest_path: &Path) -> Result<PathBuf> {
+        Ok(parent_dir(manifest_path)?.join(LOCKFILE_NAME))
+    }
+
+    /// Reads the lockfile for the given manifest, if there is one.
+    pub fn load(manifest_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(manifest_path)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read lockfile {}", quoted_path(&path)))?;
+        let lockfile = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse lockfile {}", quoted_path(&path)))?;
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile for the given manifest.
+    pub fn save(&self, manifest_path: &Path) -> Result<()> {
+        let path = Self::path_for(manifest_path)?;
+        let contents = toml::to_string_pretty(self).context("Failed to serialize lockfile")?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write lockfile {}", quoted_path(&path)))
+    }
+
+    /// Resolves the component sources of the given manifest as they are now.
+    /// Local component files are digested, so this should be called after
+    /// the application is built. Templates are not included.
+    pub fn resolve(manifest_path: &Path) -> Result<Self> {
+        let manifest = spin_manifest::manifest_from_file(manifest_path).with_context(|| {
+            format!(
+                "Failed to read Spin app manifest from {}",
+                quoted_path(manifest_path)
+            )
+        })?;
+        Self::resolve_manifest(&manifest, &parent_dir(manifest_path)?)
+    }
+
+    fn resolve_manifest(manifest: &AppManifest, app_root: &Path) -> Result<Self> {
+        let components = manifest
+            .components
+            .iter()
+            .map(|(id, component)| {
+                let locked = locked_component(&component.source, app_root)
+                    .with_context(|| format!("Failed to resolve component '{id}'"))?;
+                Ok((id.to_string(), locked))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            components,
+            ..Default::default()
+        })
+    }
+
+    /// Replaces the recorded components with those of `resolved`, keeping
+    /// the recorded templates.
+    pub fn update_components(&mut self, resolved: Self) {
+        self.version = LOCKFILE_VERSION;
+        self.components = resolved.components;
+    }
+
+    /// Compares the recorded components against `resolved`, returning any
+    /// differences.
+    pub fn verify(&self, resolved: &Self) -> Vec<LockfileMismatch> {
+        let mut mismatches = vec![];
+        for (id, current) in &resolved.components {
+            let Some(locked) = self.components.get(id) else {
+                mismatches.push(LockfileMismatch::ComponentAdded(id.clone()));
+                continue;
+            };
+            if locked.source != current.source {
+                mismatches.push(LockfileMismatch::SourceChanged {
+                    id: id.clone(),
+                    locked: locked.source.clone(),
+                    current: current.source.clone(),
+                });
+            } else if locked.digest != current.digest {
+                mismatches.push(LockfileMismatch::DigestChanged {
+                    id: id.clone(),
+                    locked: locked.digest.clone(),
+                    current: current.digest.clone(),
+                });
+            }
+        }
+        for id in self.components.keys() {
+            if !resolved.components.contains_key(id) {
+                mismatches.push(LockfileMismatch::ComponentRemoved(id.clone()));
+            }
+        }
+        mismatches
+    }
+}
+
+fn locked_component(source: &ComponentSource, app_root: &Path) -> Result<LockedComponent> {
+    let digest = match source {
+        ComponentSource::Local(path) => {
+            let path = app_root.join(path);
+            // An unbuilt component has no digest yet; this is reported as a
+            // mismatch when verifying rather than failing outright.
+            if path.exists() {
+                let hex = hex_digest_from_file(&path)
+                    .with_context(|| format!("Failed to digest {}", quoted_path(&path)))?;
+                Some(format!("sha256:{hex}"))
+            } else {
+                None
+            }
+        }
+        ComponentSource::Remote { digest, .. } => Some(digest.clone()),
+        ComponentSource::Registry { .. } => None,
+    };
+    Ok(LockedComponent {
+        source: source.to_string(),
+        digest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(source: &str) -> AppManifest {
+        toml::from_str(&format!(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [component.api]
+            source = "api.wasm"
+            [component.remote]
+            source = {{ url = "https://example.com/remote.wasm", digest = "sha256:abc" }}
+            [component.pkg]
+            source = {source}
+            "#
+        ))
+        .unwrap()
+    }
+
+    const REGISTRY_SOURCE: &str = r#"{ package = "example:pkg", version = "1.0.0" }"#;
+
+    #[test]
+    fn lockfile_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("api.wasm"), "spin").unwrap();
+        let mut lockfile =
+            AppLockfile::resolve_manifest(&manifest(REGISTRY_SOURCE), dir.path()).unwrap();
+        lockfile.templates.insert(
+            "http-rust".into(),
+            LockedTemplate {
+                installed_from: "https://github.com/spinframework/spin".into(),
+            },
+        );
+
+        let manifest_path = dir.path().join("spin.toml");
+        lockfile.save(&manifest_path).unwrap();
+        let loaded = AppLockfile::load(&manifest_path).unwrap().unwrap();
+        assert_eq!(loaded, lockfile);
+        assert_eq!(
+            loaded.components["api"].digest.as_deref(),
+            Some("sha256:a5a2729ffa0eeacc15323a9168807c72d18d1cb375dbde899c44d6803dad2b19")
+        );
+        assert_eq!(loaded.components["pkg"].digest, None);
+    }
+
+    #[test]
+    fn changes_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("api.wasm"), "spin").unwrap();
+        let locked = AppLockfile::resolve_manifest(&manifest(REGISTRY_SOURCE), dir.path()).unwrap();
+        assert!(locked.verify(&locked).is_empty());
+
+        std::fs::write(dir.path().join("api.wasm"), "spin2").unwrap();
+        let other_registry = r#"{ package = "example:pkg", version = "2.0.0" }"#;
+        let mut resolved =
+            AppLockfile::resolve_manifest(&manifest(other_registry), dir.path()).unwrap();
+        resolved.components.remove("remote");
+        let mismatches = locked.verify(&resolved);
+        assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+        assert!(
+            matches!(&mismatches[0], LockfileMismatch::DigestChanged { id, .. } if id == "api")
+        );
+        assert!(
+            matches!(&mismatches[1], LockfileMismatch::SourceChanged { id, .. } if id == "pkg")
+        );
+        assert_eq!(
+            mismatches[2],
+            LockfileMismatch::ComponentRemoved("remote".into())
+        );
+    }
+}
@@ -0,0 +1,540 @@
+//! File mounts whose content is the objects under an Azure Blob Storage or S3
+//! prefix, rather than files in the application directory.
+//!
+//! The objects are synced to the cache when the app starts, so only those
+//! whose ETag has changed since the last sync are downloaded again.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use futures::StreamExt;
+use reqwest::Url;
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path};
+use spin_locked_app::locked::{ContentRef, LockedApp};
+
+use crate::cache::Cache;
+
+/// The file in a synced prefix's cache directory recording the ETag of each
+/// object, keyed by path.
+const ETAGS_FILE: &str = "etags.json";
+/// The directory in a synced prefix's cache directory holding the objects.
+const FILES_DIR: &str = "files";
+
+/// Whether a locked file mount source refers to a remote prefix, which must
+/// be synced with [`crate::sync_remote_files`] before it can be mounted.
+pub fn is_remote_files_source(source: &str) -> bool {
+    matches!(
+        Url::parse(source).as_ref().map(Url::scheme),
+        Ok("https" | "s3")
+    )
+}
+
+pub(crate) async fn sync_app(locked_app: &mut LockedApp, cache: &Cache) -> Result<()> {
+    let client = reqwest::Client::new();
+    for component in &mut locked_app.components {
+        for file in &mut component.files {
+            let Some(source) = file.content.source.as_deref() else {
+                continue;
+            };
+            if !is_remote_files_source(source) {
+                continue;
+            }
+            let prefix = RemotePrefix::parse(source)?;
+            let dir = prefix.sync(&client, cache).await.with_context(|| {
+                // The source may contain credentials, so isn't shown.
+                format!(
+                    "Failed to sync files for component {:?} from {}{}",
+                    component.id, prefix.container_url, prefix.prefix
+                )
+            })?;
+            let url = Url::from_file_path(&dir)
+                .map_err(|_| anyhow::anyhow!("couldn't build file URL for {}", dir.display()))?;
+            file.content = ContentRef {
+                source: Some(url.to_string()),
+                ..Default::default()
+            };
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Provider {
+    AzureBlob,
+    S3,
+}
+
+/// A prefix of objects in an Azure Blob Storage container or S3 bucket.
+#[derive(Debug)]
+pub(crate) struct RemotePrefix {
+    provider: Provider,
+    /// The URL of the container or bucket.
+    container_url: Url,
+    /// The prefix of the object names, which is empty or ends in `/`.
+    prefix: String,
+    /// A query string added to every request, e.g. an Azure shared access
+    /// signature.
+    credentials: Option<String>,
+}
+
+impl RemotePrefix {
+    /// Parses a remote file mount source. This may be:
+    ///
+    /// - `https://<account>.blob.core.windows.net/<container>/<prefix>`,
+    ///   optionally with a shared access signature as the query string
+    /// - `https://<bucket>.s3[.<region>].amazonaws.com/<prefix>`
+    /// - `s3://<bucket>/<prefix>`
+    ///
+    /// S3 buckets must allow anonymous reads.
+    pub(crate) fn parse(source: &str) -> Result<Self> {
+        let url = Url::parse(source).with_context(|| format!("invalid remote {source:?}"))?;
+        let host = url.host_str().unwrap_or_default().to_owned();
+        let path = url.path().trim_start_matches('/');
+        let (provider, container_url, prefix) = match url.scheme() {
+            "s3" => {
+                ensure!(!host.is_empty(), "remote {source:?} must name a bucket");
+                let container_url = Url::parse(&format!("https://{host}.s3.amazonaws.com"))?;
+                (Provider::S3, container_url, path)
+            }
+            "https" if host.ends_with(".blob.core.windows.net") => {
+                let (container, prefix) = path.split_once('/').unwrap_or((path, ""));
+                ensure!(
+                    !container.is_empty(),
+                    "remote {source:?} must name a container"
+                );
+                let mut container_url = Url::parse(&format!("https://{host}"))?;
+                container_url.set_path(container);
+                (Provider::AzureBlob, container_url, prefix)
+            }
+            "https" if host.contains(".s3.") && host.ends_with(".amazonaws.com") => {
+                (Provider::S3, Url::parse(&format!("https://{host}"))?, path)
+            }
+            _ => bail!(
+                "unsupported remote {source:?}; expected an Azure Blob Storage URL (https://<account>.blob.core.windows.net/<container>/<prefix>) or an S3 URL (s3://<bucket>/<prefix>)"
+            ),
+        };
+        let credentials = url.query().map(str::to_owned);
+        ensure!(
+            credentials.is_none() || provider == Provider::AzureBlob,
+            "S3 remote {source:?} must not have a query string"
+        );
+        let prefix = match prefix {
+            "" => String::new(),
+            p if p.ends_with('/') => p.to_owned(),
+            p => format!("{p}/"),
+        };
+        Ok(Self {
+            provider,
+            container_url,
+            prefix,
+            credentials,
+        })
+    }
+
+    /// Identifies the prefix independently of its credentials, which may be
+    /// rotated.
+    fn cache_key(&self) -> String {
+        hex_digest_from_bytes(format!("{}/{}", self.container_url, self.prefix))
+    }
+
+    /// Adds the credentials to `url`'s query string.
+    fn authorize(&self, mut url: Url) -> Url {
+        if let Some(credentials) = &self.credentials {
+            let query = match url.query() {
+                Some(query) => format!("{query}&{credentials}"),
+                None => credentials.clone(),
+            };
+            url.set_query(Some(&query));
+        }
+        url
+    }
+
+    fn object_url(&self, name: &str) -> Url {
+        let mut url = self.container_url.clone();
+        url.path_segments_mut()
+            .expect("container URLs are https")
+            .pop_if_empty()
+            .extend(name.split('/'));
+        self.authorize(url)
+    }
+
+    /// Lists the objects under the prefix.
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<RemoteObject>> {
+        let mut objects = vec![];
+        let mut continuation: Option<String> = None;
+        loop {
+            let mut url = self.container_url.clone();
+            {
+                let mut query = url.query_pairs_mut();
+                match self.provider {
+                    Provider::AzureBlob => {
+                        query
+                            .append_pair("restype", "container")
+                            .append_pair("comp", "list")
+                            .append_pair("prefix", &self.prefix);
+                        if let Some(marker) = &continuation {
+                            query.append_pair("marker", marker);
+                        }
+                    }
+                    Provider::S3 => {
+                        query
+                            .append_pair("list-type", "2")
+                            .append_pair("prefix", &self.prefix);
+                        if let Some(token) = &continuation {
+                            query.append_pair("continuation-token", token);
+                        }
+                    }
+                }
+            }
+            let body = fetch(client, self.authorize(url))
+                .await?
+                .text()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+            let page = match self.provider {
+                Provider::AzureBlob => parse_azure_listing(&body),
+                Provider::S3 => parse_s3_listing(&body),
+            };
+            objects.extend(page.objects);
+            match page.continuation {
+                Some(next) if !next.is_empty() => continuation = Some(next),
+                _ => break,
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Syncs the objects under the prefix into `cache`, returning the
+    /// directory holding them.
+    ///
+    /// If the objects can't be listed, e.g. because the host is offline, a
+    /// previously synced copy is used.
+    async fn sync(&self, client: &reqwest::Client, cache: &Cache) -> Result<PathBuf> {
+        let dir = cache.remote_files_dir().join(self.cache_key());
+        let files_dir = dir.join(FILES_DIR);
+        let etags_path = dir.join(ETAGS_FILE);
+        let mut etags: BTreeMap<String, String> = std::fs::read(&etags_path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+
+        let objects = match self.list(client).await {
+            Ok(objects) => objects,
+            Err(e) if files_dir.is_dir() => {
+                tracing::warn!(
+                    "Failed to list {}{}; using the files synced previously: {e:#}",
+                    self.container_url,
+                    self.prefix
+                );
+                return Ok(files_dir);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut listed = BTreeMap::new();
+        for object in objects {
+            let Some(path) = self.relative_path(&object.name)? else {
+                continue;
+            };
+            listed.insert(path, object);
+        }
+
+        // Remove files whose objects have gone.
+        for path in etags.keys().filter(|path| !listed.contains_key(*path)) {
+            let file = files_dir.join(path);
+            if let Err(e) = std::fs::remove_file(&file) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(e)
+                        .with_context(|| format!("Failed to remove {}", quoted_path(&file)));
+                }
+            }
+        }
+        etags.retain(|path, _| listed.contains_key(path));
+
+        let stale = listed
+            .iter()
+            .filter(|(path, object)| {
+                etags.get(*path) != Some(&object.etag) || !files_dir.join(path).is_file()
+            })
+            .collect::<Vec<_>>();
+        tracing::debug!(
+            "{} of {} objects under {}{} changed since the last sync",
+            stale.len(),
+            listed.len(),
+            self.container_url,
+            self.prefix
+        );
+        let downloads = stale.into_iter().map(|(path, object)| {
+            let dest = files_dir.join(path);
+            async move {
+                self.download(client, &object.name, &dest)
+                    .await
+                    .with_context(|| format!("Failed to download {:?}", object.name))?;
+                Ok::<_, anyhow::Error>((path.clone(), object.etag.clone()))
+            }
+        });
+        let results = futures::stream::iter(downloads)
+            .buffer_unordered(crate::MAX_FILE_LOADING_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        // Record what was downloaded even if some downloads failed, so they
+        // aren't downloaded again.
+        let mut result = Ok(());
+        for downloaded in results {
+            match downloaded {
+                Ok((path, etag)) => {
+                    etags.insert(path, etag);
+                }
+                Err(e) => result = Err(e),
+            }
+        }
+        crate::fs::create_dir_all(&files_dir).await?;
+        crate::fs::write_file(&etags_path, &serde_json::to_vec(&etags)?).await?;
+        result?;
+        Ok(files_dir)
+    }
+
+    /// The path of an object relative to the prefix, or `None` for
+    /// directory placeholders.
+    fn relative_path(&self, name: &str) -> Result<Option<String>> {
+        let Some(path) = name.strip_prefix(&self.prefix) else {
+            bail!(
+                "listed object {name:?} is not under prefix {:?}",
+                self.prefix
+            );
+        };
+        if path.is_empty() || path.ends_with('/') {
+            return Ok(None);
+        }
+        ensure!(
+            Path::new(path)
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_))),
+            "object name {name:?} cannot be mapped to a file"
+        );
+        Ok(Some(path.to_owned()))
+    }
+
+    async fn download(&self, client: &reqwest::Client, name: &str, dest: &Path) -> Result<()> {
+        let bytes = fetch(client, self.object_url(name))
+            .await?
+            .bytes()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        let parent = dest.parent().context("invalid destination")?;
+        crate::fs::create_dir_all(parent).await?;
+        // Write under a temporary name so that an interrupted sync never
+        // leaves a partial file in place.
+        let partial = parent.join(format!(
+            ".{}.partial",
+            dest.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        crate::fs::write_file(&partial, &bytes).await?;
+        crate::fs::rename(&partial, dest).await
+    }
+}
+
+/// Gets `url`, leaving the URL, which may contain credentials, out of errors.
+async fn fetch(client: &reqwest::Client, url: Url) -> reqwest::Result<reqwest::Response> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(reqwest::Error::without_url)
+}
+
+#[derive(Debug, PartialEq)]
+struct RemoteObject {
+    name: String,
+    etag: String,
+}
+
+/// A page of a listing.
+struct Listing {
+    objects: Vec<RemoteObject>,
+    /// Where to continue listing from, if there are more objects.
+    continuation: Option<String>,
+}
+
+/// Parses an Azure Blob Storage List Blobs response.
+fn parse_azure_listing(xml: &str) -> Listing {
+    let objects = xml_elements(xml, "Blob")
+        .into_iter()
+        .filter_map(|blob| {
+            Some(RemoteObject {
+                name: xml_text(blob, "Name")?,
+                etag: xml_text(blob, "Etag").unwrap_or_default(),
+            })
+        })
+        .collect();
+    Listing {
+        objects,
+        continuation: xml_text(xml, "NextMarker"),
+    }
+}
+
+/// Parses an S3 ListObjectsV2 response.
+fn parse_s3_listing(xml: &str) -> Listing {
+    let objects = xml_elements(xml, "Contents")
+        .into_iter()
+        .filter_map(|contents| {
+            Some(RemoteObject {
+                name: xml_text(contents, "Key")?,
+                etag: xml_text(contents, "ETag").unwrap_or_default(),
+            })
+        })
+        .collect();
+    let truncated = xml_text(xml, "IsTruncated").is_some_and(|t| t == "true");
+    Listing {
+        objects,
+        continuation: xml_text(xml, "NextContinuationToken").filter(|_| truncated),
+    }
+}
+
+/// The contents of each `<tag>` element in `xml`, in order.
+///
+/// Listings are simple enough that this avoids needing an XML parser; it
+/// does not handle elements with attributes, which listings don't use for
+/// the elements read here.
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    let mut elements = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after_open = &rest[start + open.len()..];
+        let Some(end) = after_open.find(&close) else {
+            break;
+        };
+        elements.push(&after_open[..end]);
+        rest = &after_open[end + close.len()..];
+    }
+    elements
+}
+
+/// The unescaped text of the first `<tag>` element in `xml`.
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let text = xml_elements(xml, tag).into_iter().next()?;
+    Some(
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_remote_sources() -> Result<()> {
+        let azure = RemotePrefix::parse(
+            "https://acct.blob.core.windows.net/assets/site/v2?sv=2024&sig=abc%3D",
+        )?;
+        assert_eq!(azure.provider, Provider::AzureBlob);
+        assert_eq!(
+            azure.container_url.as_str(),
+            "https://acct.blob.core.windows.net/assets"
+        );
+        assert_eq!(azure.prefix, "site/v2/");
+        assert_eq!(
+            azure.object_url("site/v2/a b.css").as_str(),
+            "https://acct.blob.core.windows.net/assets/site/v2/a%20b.css?sv=2024&sig=abc%3D"
+        );
+
+        let s3 = RemotePrefix::parse("s3://bucket/static/")?;
+        assert_eq!(s3.provider, Provider::S3);
+        assert_eq!(
+            s3.container_url.as_str(),
+            "https://bucket.s3.amazonaws.com/"
+        );
+        assert_eq!(s3.prefix, "static/");
+
+        let whole_bucket = RemotePrefix::parse("https://bucket.s3.eu-west-1.amazonaws.com")?;
+        assert_eq!(whole_bucket.prefix, "");
+
+        assert!(RemotePrefix::parse("https://example.com/assets").is_err());
+        assert!(RemotePrefix::parse("https://acct.blob.core.windows.net/").is_err());
+        assert!(RemotePrefix::parse("s3://bucket/static?token=x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn credentials_do_not_change_cache_key() -> Result<()> {
+        let a = RemotePrefix::parse("https://acct.blob.core.windows.net/assets/site?sig=a")?;
+        let b = RemotePrefix::parse("https://acct.blob.core.windows.net/assets/site/?sig=b")?;
+        assert_eq!(a.cache_key(), b.cache_key());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_objects_outside_prefix() -> Result<()> {
+        let prefix = RemotePrefix::parse("s3://bucket/static")?;
+        assert_eq!(
+            prefix.relative_path("static/css/site.css")?.as_deref(),
+            Some("css/site.css")
+        );
+        assert_eq!(prefix.relative_path("static/css/")?, None);
+        assert!(prefix.relative_path("static/../secret").is_err());
+        assert!(prefix.relative_path("other/file").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_azure_listing() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ServiceEndpoint="https://acct.blob.core.windows.net/" ContainerName="assets">
+  <Prefix>site/</Prefix>
+  <Blobs>
+    <Blob><Name>site/index.html</Name><Properties><Etag>0x8D1</Etag></Properties></Blob>
+    <Blob><Name>site/a&amp;b.txt</Name><Properties><Etag>0x8D2</Etag></Properties></Blob>
+  </Blobs>
+  <NextMarker>2!88!MDAw</NextMarker>
+</EnumerationResults>"#;
+        let listing = parse_azure_listing(xml);
+        assert_eq!(
+            listing.objects,
+            vec![
+                RemoteObject {
+                    name: "site/index.html".into(),
+                    etag: "0x8D1".into()
+                },
+                RemoteObject {
+                    name: "site/a&b.txt".into(),
+                    etag: "0x8D2".into()
+                },
+            ]
+        );
+        assert_eq!(listing.continuation.as_deref(), Some("2!88!MDAw"));
+
+        let last_page =
+            parse_azure_listing("<EnumerationResults><Blobs /><NextMarker /></EnumerationResults>");
+        assert!(last_page.objects.is_empty());
+        assert_eq!(last_page.continuation, None);
+    }
+
+    #[test]
+    fn parses_s3_listing() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>bucket</Name>
+  <Prefix>static/</Prefix>
+  <IsTruncated>false</IsTruncated>
+  <Contents><Key>static/app.js</Key><ETag>&quot;9b2cf535f27731c974343645a3985328&quot;</ETag><Size>12</Size></Contents>
+</ListBucketResult>"#;
+        let listing = parse_s3_listing(xml);
+        assert_eq!(
+            listing.objects,
+            vec![RemoteObject {
+                name: "static/app.js".into(),
+                etag: "\"9b2cf535f27731c974343645a3985328\"".into()
+            }]
+        );
+        assert_eq!(listing.continuation, None);
+    }
+}
@@ -68,15 +68,24 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
                 environment: component.environment,
                 files: component.files,
                 exclude_files: component.exclude_files,
+                read_only_files: false,
+                files_quota_mb: None,
+                templates: None,
                 key_value_stores: component.key_value_stores,
                 sqlite_databases: component.sqlite_databases,
+                blob_containers: Vec::new(),
+                message_brokers: Vec::new(),
+                host_plugins: Vec::new(),
+                vector_stores: Vec::new(),
                 ai_models,
+                image_operations: Vec::new(),
                 build: component.build,
                 tool: Default::default(),
                 allowed_outbound_hosts,
                 allowed_http_hosts: Vec::new(),
                 dependencies_inherit_configuration: false,
                 dependencies: Default::default(),
+                middleware: Vec::new(),
             },
         );
         triggers
@@ -95,6 +104,7 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
         variables: app_variables,
         triggers,
         components,
+        profiles: Default::default(),
     })
 }
 
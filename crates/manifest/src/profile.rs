@@ -0,0 +1,111 @@
+//! Environment profiles.
+
+use anyhow::anyhow;
+
+use crate::{schema::v2::AppManifest, Error};
+
+/// Overlays the named `[profile.<name>]` onto the rest of the manifest:
+/// - Profile variables replace app variables of the same name, or are added.
+/// - Component variables and environment variables are merged into the
+///   component's, replacing any of the same name.
+/// - Component `allowed_outbound_hosts` replace the component's.
+///
+/// All profiles are removed from the manifest afterwards.
+pub fn apply_profile(manifest: &mut AppManifest, name: &str) -> Result<(), Error> {
+    let mut profiles = std::mem::take(&mut manifest.profiles);
+    let Some(profile) = profiles.shift_remove(name) else {
+        let known = profiles.keys().map(String::as_str).collect::<Vec<_>>();
+        let reason = if known.is_empty() {
+            "the manifest doesn't define any profiles".to_owned()
+        } else {
+            format!("defined profiles are: {}", known.join(", "))
+        };
+        return Err(Error::ValidationError(anyhow!(
+            "unknown profile {name:?}; {reason}"
+        )));
+    };
+
+    manifest.variables.extend(profile.variables);
+
+    for (id, overrides) in profile.components {
+        let component = manifest.components.get_mut(&id).ok_or_else(|| {
+            Error::ValidationError(anyhow!(
+                "profile {name:?} overrides component {id:?}, which does not exist"
+            ))
+        })?;
+        component.variables.extend(overrides.variables);
+        component.environment.extend(overrides.environment);
+        if let Some(allowed_outbound_hosts) = overrides.allowed_outbound_hosts {
+            component.allowed_outbound_hosts = allowed_outbound_hosts;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_serde::{KebabId, LowerSnakeId};
+
+    use super::*;
+
+    fn id<T: TryFrom<String>>(id: &str) -> T
+    where
+        T::Error: std::fmt::Debug,
+    {
+        id.to_owned().try_into().unwrap()
+    }
+
+    fn manifest() -> AppManifest {
+        toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [variables]
+            api_url = { default = "http://localhost:8080" }
+            [component.api]
+            source = "api.wasm"
+            allowed_outbound_hosts = ["http://localhost:8080"]
+            variables = { mode = "dev", region = "local" }
+
+            [profile.production]
+            runtime_config = "runtime-config.production.toml"
+            variables = { api_url = { default = "https://api.example.com" } }
+            [profile.production.component.api]
+            allowed_outbound_hosts = ["https://api.example.com"]
+            variables = { mode = "prod" }
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn profile_is_overlaid() {
+        let mut manifest = manifest();
+        apply_profile(&mut manifest, "production").unwrap();
+        assert!(manifest.profiles.is_empty());
+
+        let api_url = &manifest.variables[&id::<LowerSnakeId>("api_url")];
+        assert_eq!(api_url.default.as_deref(), Some("https://api.example.com"));
+
+        let api = &manifest.components[&id::<KebabId>("api")];
+        assert_eq!(api.allowed_outbound_hosts, ["https://api.example.com"]);
+        assert_eq!(api.variables[&id::<LowerSnakeId>("mode")], "prod");
+        assert_eq!(api.variables[&id::<LowerSnakeId>("region")], "local");
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let err = apply_profile(&mut manifest(), "staging").unwrap_err();
+        assert!(err.to_string().contains("production"), "{err}");
+    }
+
+    #[test]
+    fn unknown_component_is_an_error() {
+        let mut manifest = manifest();
+        let profile = manifest.profiles.get_mut("production").unwrap();
+        profile.components.insert(id("web"), Default::default());
+        let err = apply_profile(&mut manifest, "production").unwrap_err();
+        assert!(err.to_string().contains("web"), "{err}");
+    }
+}
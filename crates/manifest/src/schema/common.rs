@@ -79,6 +79,21 @@ pub enum WasiFilesMount {
         /// `destination = "/"`
         destination: String,
     },
+    /// `{ tmpfs = "/tmp" }`: an empty, writable directory which is wiped
+    /// after each invocation
+    Tmpfs {
+        /// `tmpfs = "/tmp"`
+        tmpfs: String,
+    },
+    /// `{ remote = "https://account.blob.core.windows.net/container/prefix", destination = "/static" }`:
+    /// the objects under an Azure Blob Storage or S3 prefix, synced to a
+    /// local cache when the app starts
+    Remote {
+        /// `remote = "s3://bucket/prefix"`
+        remote: String,
+        /// `destination = "/static"`
+        destination: String,
+    },
 }
 
 /// Component build configuration
@@ -93,6 +108,10 @@ pub struct ComponentBuildConfig {
     /// watch = ["src/**/*.rs"]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub watch: Vec<String>,
+    /// `depends_on = ["other-component"]`: components which must be built
+    /// before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
 }
 
 impl ComponentBuildConfig {
@@ -30,6 +30,10 @@ pub struct AppManifest {
     #[serde(rename = "component")]
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub components: Map<KebabId, Component>,
+    /// `[profile.<name>]`
+    #[serde(rename = "profile")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub profiles: Map<String, Profile>,
 }
 
 impl AppManifest {
@@ -39,10 +43,45 @@ impl AppManifest {
             component
                 .dependencies
                 .validate()
+                .and_then(|()| self.validate_app_component_dependencies(component_id, component))
                 .with_context(|| format!("component {component_id:?} has invalid dependencies"))?;
         }
         Ok(())
     }
+
+    /// This method ensures that dependencies on other components of the app
+    /// refer to components that exist and can be composed. A component can't
+    /// depend on itself, and a component another depends on can't have
+    /// dependencies of its own.
+    fn validate_app_component_dependencies(
+        &self,
+        component_id: &KebabId,
+        component: &Component,
+    ) -> anyhow::Result<()> {
+        for (dependency_name, dependency) in &component.dependencies.inner {
+            let ComponentDependency::AppComponent {
+                component: dependency_id,
+                ..
+            } = dependency
+            else {
+                continue;
+            };
+            anyhow::ensure!(
+                dependency_id != component_id,
+                "dependency {dependency_name:?} refers to the component itself"
+            );
+            let Some(dependency_component) = self.components.get(dependency_id) else {
+                anyhow::bail!(
+                    "dependency {dependency_name:?} refers to component {dependency_id:?}, which does not exist"
+                );
+            };
+            anyhow::ensure!(
+                dependency_component.dependencies.is_empty(),
+                "dependency {dependency_name:?} refers to component {dependency_id:?}, which has dependencies of its own; this is not currently supported"
+            );
+        }
+        Ok(())
+    }
 }
 
 /// App details
@@ -70,6 +109,40 @@ pub struct AppDetails {
     pub tool: Map<String, toml::Table>,
 }
 
+/// Environment profile, overlaid on the rest of the manifest when selected
+/// with `--env-profile <name>`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// `runtime_config = "runtime-config.production.toml"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_config: Option<PathBuf>,
+    /// `[profile.<name>.variables]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, Variable>,
+    /// `[profile.<name>.component.<id>]`
+    #[serde(rename = "component")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub components: Map<KebabId, ComponentProfile>,
+}
+
+/// Per-component overrides in an environment profile
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentProfile {
+    /// `variables = { name = "{{ app_var }}"}`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, String>,
+    /// `environment = { VAR = "value" }`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub environment: Map<String, String>,
+    /// `allowed_outbound_hosts = ["https://api.example.com"]`
+    ///
+    /// Replaces the component's allowed hosts rather than adding to them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_outbound_hosts: Option<Vec<String>>,
+}
+
 /// Trigger configuration
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Trigger {
@@ -154,6 +227,16 @@ pub enum ComponentDependency {
         /// Optional export name
         export: Option<String>,
     },
+    /// `... = { component = "other-component", export = "my-export" }`
+    ///
+    /// The dependency is satisfied by the source of another component in the
+    /// same app, so calls to it are made in-process rather than over HTTP.
+    AppComponent {
+        /// The ID of the component in this app that provides the dependency
+        component: KebabId,
+        /// Optional export name
+        export: Option<String>,
+    },
 }
 
 /// Component definition
@@ -177,6 +260,18 @@ pub struct Component {
     /// `exclude_files = ["secrets/*"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub exclude_files: Vec<String>,
+    /// `read_only_files = true`: never allow the component to write to its
+    /// files, even if the host allows transient writes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only_files: bool,
+    /// `files_quota_mb = 64`: the most disk space the component's files,
+    /// including its tmpfs mounts, may use, in megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_quota_mb: Option<u64>,
+    /// `templates = "/templates"`: the directory of the component's files
+    /// holding templates which the host parses once and renders on request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<String>,
     /// `allowed_http_hosts = ["example.com"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[deprecated]
@@ -200,9 +295,44 @@ pub struct Component {
     )]
     #[schemars(with = "Vec<String>")]
     pub sqlite_databases: Vec<String>,
+    /// `blob_containers = ["uploads", "thumbnails"]`
+    #[serde(
+        default,
+        with = "kebab_or_snake_case",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Vec<String>")]
+    pub blob_containers: Vec<String>,
+    /// `message_brokers = ["events"]`
+    #[serde(
+        default,
+        with = "kebab_or_snake_case",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Vec<String>")]
+    pub message_brokers: Vec<String>,
+    /// `host_plugins = ["billing"]`
+    #[serde(
+        default,
+        with = "kebab_or_snake_case",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Vec<String>")]
+    pub host_plugins: Vec<String>,
+    /// `vector_stores = ["default", "embeddings"]`
+    #[serde(
+        default,
+        with = "kebab_or_snake_case",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Vec<String>")]
+    pub vector_stores: Vec<String>,
     /// `ai_models = ["llama2-chat"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ai_models: Vec<KebabId>,
+    /// `image_operations = ["resize", "convert"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub image_operations: Vec<String>,
     /// Build configuration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub build: Option<ComponentBuildConfig>,
@@ -216,6 +346,11 @@ pub struct Component {
     /// Component dependencies
     #[serde(default, skip_serializing_if = "ComponentDependencies::is_empty")]
     pub dependencies: ComponentDependencies,
+    /// `middleware = ["auth.wasm", ...]`: components which wrap this one,
+    /// outermost first. These are composed with the component when the
+    /// application is loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub middleware: Vec<ComponentSource>,
 }
 
 /// Component dependencies
@@ -265,6 +400,7 @@ impl ComponentDependencies {
                     let export = match dependency {
                         ComponentDependency::Package { export, .. } => export,
                         ComponentDependency::Local { export, .. } => export,
+                        ComponentDependency::AppComponent { export, .. } => export,
                         _ => continue,
                     };
 
@@ -526,6 +662,10 @@ mod tests {
             source = "dummy"
             key_value_stores = ["default", "snake_case", "kebab-case"]
             sqlite_databases = ["default", "snake_case", "kebab-case"]
+            blob_containers = ["default", "snake_case", "kebab-case"]
+            message_brokers = ["default", "snake_case", "kebab-case"]
+            host_plugins = ["default", "snake_case", "kebab-case"]
+            vector_stores = ["default", "snake_case", "kebab-case"]
         })
         .unwrap();
     }
@@ -554,15 +694,24 @@ mod tests {
             environment: Map::new(),
             files: vec![],
             exclude_files: vec![],
+            read_only_files: false,
+            files_quota_mb: None,
+            templates: None,
             allowed_http_hosts: vec![],
             allowed_outbound_hosts: vec![],
             key_value_stores: labels.clone(),
-            sqlite_databases: labels,
+            sqlite_databases: labels.clone(),
+            blob_containers: labels.clone(),
+            message_brokers: labels.clone(),
+            host_plugins: labels.clone(),
+            vector_stores: labels,
             ai_models: vec![],
+            image_operations: vec![],
             build: None,
             tool: Map::new(),
             dependencies_inherit_configuration: false,
             dependencies: Default::default(),
+            middleware: vec![],
         }
     }
 
@@ -646,6 +795,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_app_component_dependencies() {
+        fn manifest(dependencies: toml::Table) -> AppManifest {
+            let mut manifest = toml! {
+                spin_manifest_version = 2
+                [application]
+                name = "app"
+                [component.front]
+                source = "front.wasm"
+                [component.back]
+                source = "back.wasm"
+                [component.layered]
+                source = "layered.wasm"
+                dependencies = { "foo:bar/baz" = { path = "baz.wasm" } }
+            };
+            manifest["component"]["front"]
+                .as_table_mut()
+                .unwrap()
+                .insert("dependencies".into(), dependencies.into());
+            AppManifest::deserialize(manifest).unwrap()
+        }
+
+        // Depending on another component is ok
+        manifest(toml! { "foo:bar/api" = { component = "back" } })
+            .validate_dependencies()
+            .unwrap();
+
+        // Depending on a component that doesn't exist is an error
+        assert!(
+            manifest(toml! { "foo:bar/api" = { component = "missing" } })
+                .validate_dependencies()
+                .is_err()
+        );
+
+        // Depending on the component itself is an error
+        assert!(manifest(toml! { "foo:bar/api" = { component = "front" } })
+            .validate_dependencies()
+            .is_err());
+
+        // Depending on a component with dependencies of its own is an error
+        assert!(
+            manifest(toml! { "foo:bar/api" = { component = "layered" } })
+                .validate_dependencies()
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_validate_dependencies() {
         // Specifying a dependency name as a plain-name without a package is an error
@@ -672,6 +868,15 @@ mod tests {
         .validate()
         .is_err());
 
+        // Specifying an export to satisfy a package dependency name is an error,
+        // including for dependencies on other components
+        assert!(ComponentDependencies::deserialize(toml! {
+            "foo:baz@0.1.0" = { component = "other", export = "foo"}
+        })
+        .unwrap()
+        .validate()
+        .is_err());
+
         // Two compatible versions of the same package is an error
         assert!(ComponentDependencies::deserialize(toml! {
             "foo:baz@0.1.0" = "0.1.0"
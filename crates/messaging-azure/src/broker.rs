@@ -0,0 +1,412 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use azure_core::auth::TokenCredential;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    StatusCode,
+};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use spin_core::async_trait;
+use spin_factor_messaging::{Error, Message, MessageBroker, RequestOptions};
+
+/// The version of the Service Bus REST API, which Event Hubs also serves.
+const API_VERSION: &str = "2014-01";
+
+/// The shared access policy every namespace is created with.
+const DEFAULT_KEY_NAME: &str = "RootManageSharedAccessKey";
+
+/// How long a shared access signature is valid for.
+const SAS_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// The content type of a batch of messages.
+const BATCH_CONTENT_TYPE: &str = "application/vnd.microsoft.servicebus.json";
+
+/// The content type of messages which don't set one.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+const BROKER_PROPERTIES_HEADER: &str = "BrokerProperties";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The Azure messaging service a broker sends to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    /// Service Bus queues and topics
+    ServiceBus,
+    /// Event Hubs event hubs
+    EventHubs,
+}
+
+impl Service {
+    fn name(self) -> &'static str {
+        match self {
+            Self::ServiceBus => "Azure Service Bus",
+            Self::EventHubs => "Azure Event Hubs",
+        }
+    }
+
+    /// The scope of the Microsoft Entra tokens the service accepts.
+    fn token_scope(self) -> &'static str {
+        match self {
+            Self::ServiceBus => "https://servicebus.azure.net/.default",
+            Self::EventHubs => "https://eventhubs.azure.net/.default",
+        }
+    }
+}
+
+pub struct MessagingAzure {
+    service: Service,
+    /// The namespace's fully qualified host name
+    host: String,
+    auth: Auth,
+    client: reqwest::Client,
+}
+
+/// Azure messaging runtime config literal options for authentication
+#[derive(Clone, Debug)]
+pub struct MessagingAzureRuntimeConfigOptions {
+    key_name: String,
+    key: String,
+}
+
+impl MessagingAzureRuntimeConfigOptions {
+    pub fn new(key_name: Option<String>, key: String) -> Self {
+        Self {
+            key_name: key_name.unwrap_or_else(|| DEFAULT_KEY_NAME.to_owned()),
+            key,
+        }
+    }
+}
+
+/// Azure messaging enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum MessagingAzureAuthOptions {
+    /// Runtime Config values indicates a shared access key has been specified directly
+    RuntimeConfigValues(MessagingAzureRuntimeConfigOptions),
+    /// Environmental indicates that the environment variables of the process should be used to
+    /// create the TokenCredential for the broker. This will use the Azure Rust SDK's
+    /// DefaultCredentialChain to derive the TokenCredential based on what environment variables
+    /// have been set, including managed identities and workload identities.
+    ///
+    /// See https://github.com/Azure/azure-sdk-for-rust/blob/main/sdk/identity/README.md
+    Environmental,
+}
+
+enum Auth {
+    SharedAccessKey(MessagingAzureRuntimeConfigOptions),
+    Credential(Arc<dyn TokenCredential>),
+}
+
+impl MessagingAzure {
+    pub fn new(
+        service: Service,
+        namespace: &str,
+        auth_options: MessagingAzureAuthOptions,
+    ) -> anyhow::Result<Self> {
+        // A bare namespace name is in the public cloud.
+        let host = if namespace.contains('.') {
+            namespace.to_owned()
+        } else {
+            format!("{namespace}.servicebus.windows.net")
+        };
+        let auth = match auth_options {
+            MessagingAzureAuthOptions::RuntimeConfigValues(config) => Auth::SharedAccessKey(config),
+            MessagingAzureAuthOptions::Environmental => {
+                Auth::Credential(azure_identity::create_default_credential()?)
+            }
+        };
+        Ok(Self {
+            service,
+            host,
+            auth,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// The URL of the entity, i.e. queue, topic or event hub, named `topic`.
+    fn entity_url(&self, topic: &str) -> Result<url::Url, Error> {
+        let mut url = url::Url::parse(&format!("https://{}/", self.host))
+            .map_err(|e| Error::Other(format!("invalid namespace {:?}: {e}", self.host)))?;
+        url.path_segments_mut()
+            .map_err(|()| Error::Other(format!("invalid namespace {:?}", self.host)))?
+            .pop_if_empty()
+            .push(topic);
+        Ok(url)
+    }
+
+    async fn authorization(&self, entity_url: &url::Url) -> Result<String, Error> {
+        match &self.auth {
+            Auth::SharedAccessKey(config) => {
+                let expiry = SystemTime::now() + SAS_TOKEN_LIFETIME;
+                Ok(sas_token(
+                    entity_url.as_str(),
+                    &config.key_name,
+                    &config.key,
+                    expiry,
+                ))
+            }
+            Auth::Credential(credential) => {
+                let token = credential
+                    .get_token(&[self.service.token_scope()])
+                    .await
+                    .map_err(|e| Error::PermissionDenied(format!("failed to get token: {e}")))?;
+                Ok(format!("Bearer {}", token.token.secret()))
+            }
+        }
+    }
+
+    /// Posts `body` to the entity named `topic`.
+    async fn post(&self, topic: &str, mut headers: HeaderMap, body: Vec<u8>) -> Result<(), Error> {
+        let entity_url = self.entity_url(topic)?;
+        let authorization = self.authorization(&entity_url).await?;
+        headers.insert(AUTHORIZATION, header_value(&authorization)?);
+        let mut url = entity_url;
+        url.path_segments_mut()
+            .map_err(|()| Error::Other("invalid entity URL".into()))?
+            .push("messages");
+        url.query_pairs_mut()
+            .append_pair("api-version", API_VERSION);
+
+        let response = self
+            .client
+            .post(url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    Error::Timeout
+                } else {
+                    Error::Connection(e.to_string())
+                }
+            })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let detail = response.text().await.unwrap_or_default();
+        Err(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::PermissionDenied(format!(
+                "{} rejected the request to {topic:?}: {detail}",
+                self.service.name()
+            )),
+            StatusCode::NOT_FOUND => Error::Other(format!(
+                "{} has no entity named {topic:?}",
+                self.service.name()
+            )),
+            _ => Error::Other(format!(
+                "{} returned {status} for {topic:?}: {detail}",
+                self.service.name()
+            )),
+        })
+    }
+
+    async fn send_one(
+        &self,
+        topic: &str,
+        message: Message,
+        deliver_at: Option<SystemTime>,
+    ) -> Result<(), Error> {
+        let mut headers = HeaderMap::new();
+        let content_type = message
+            .content_type
+            .as_deref()
+            .unwrap_or(DEFAULT_CONTENT_TYPE);
+        headers.insert(CONTENT_TYPE, header_value(content_type)?);
+        let broker_properties = broker_properties(None, message.reply_to, deliver_at);
+        if !broker_properties.is_empty() {
+            headers.insert(
+                BROKER_PROPERTIES_HEADER,
+                header_value(&Value::Object(broker_properties).to_string())?,
+            );
+        }
+        // Custom properties are headers with JSON values.
+        for (key, value) in &message.metadata {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| Error::Other(format!("invalid metadata key {key:?}")))?;
+            headers.append(
+                name,
+                header_value(&Value::from(value.as_str()).to_string())?,
+            );
+        }
+        self.post(topic, headers, message.data).await
+    }
+}
+
+#[async_trait]
+impl MessageBroker for MessagingAzure {
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error> {
+        self.send_one(topic, message, None).await
+    }
+
+    async fn send_batch(&self, topic: &str, messages: Vec<Message>) -> Result<(), Error> {
+        // Batched message bodies are JSON strings, so must be text.
+        let batch = messages
+            .into_iter()
+            .map(|message| {
+                let body = String::from_utf8(message.data).map_err(|_| {
+                    Error::Other(format!(
+                        "{} batches may only contain UTF-8 messages",
+                        self.service.name()
+                    ))
+                })?;
+                let user_properties: Map<String, Value> = message
+                    .metadata
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value)))
+                    .collect();
+                Ok(json!({
+                    "Body": body,
+                    "BrokerProperties": broker_properties(message.content_type, message.reply_to, None),
+                    "UserProperties": user_properties,
+                }))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(BATCH_CONTENT_TYPE));
+        let body = serde_json::to_vec(&batch).map_err(|e| Error::Other(e.to_string()))?;
+        self.post(topic, headers, body).await
+    }
+
+    async fn schedule(
+        &self,
+        topic: &str,
+        message: Message,
+        deliver_at: SystemTime,
+    ) -> Result<(), Error> {
+        if self.service != Service::ServiceBus {
+            return Err(Error::Other(format!(
+                "scheduled delivery is not supported by {}",
+                self.service.name()
+            )));
+        }
+        self.send_one(topic, message, Some(deliver_at)).await
+    }
+
+    async fn request(
+        &self,
+        _topic: &str,
+        _message: Message,
+        _options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        Err(Error::Other(format!(
+            "request-reply is not supported by {}",
+            self.service.name()
+        )))
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("{} namespace: {}", self.service.name(), self.host))
+    }
+}
+
+/// The `BrokerProperties` of a message, omitting those which are unset.
+fn broker_properties(
+    content_type: Option<String>,
+    reply_to: Option<String>,
+    deliver_at: Option<SystemTime>,
+) -> Map<String, Value> {
+    let mut properties = Map::new();
+    if let Some(content_type) = content_type {
+        properties.insert("ContentType".into(), content_type.into());
+    }
+    if let Some(reply_to) = reply_to {
+        properties.insert("ReplyTo".into(), reply_to.into());
+    }
+    if let Some(deliver_at) = deliver_at {
+        properties.insert(
+            "ScheduledEnqueueTimeUtc".into(),
+            httpdate::fmt_http_date(deliver_at).into(),
+        );
+    }
+    properties
+}
+
+/// Creates a shared access signature for `resource_uri`, valid until `expiry`.
+///
+/// See https://learn.microsoft.com/en-us/azure/service-bus-messaging/service-bus-sas
+fn sas_token(resource_uri: &str, key_name: &str, key: &str, expiry: SystemTime) -> String {
+    let expiry = expiry
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let encoded_uri = url_encode(resource_uri);
+    let mut mac =
+        HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{encoded_uri}\n{expiry}").as_bytes());
+    let signature = STANDARD.encode(mac.finalize().into_bytes());
+    format!(
+        "SharedAccessSignature sr={encoded_uri}&sig={}&se={expiry}&skn={}",
+        url_encode(&signature),
+        url_encode(key_name)
+    )
+}
+
+fn url_encode(s: &str) -> String {
+    url::form_urlencoded::byte_serialize(s.as_bytes()).collect()
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(value)
+        .map_err(|_| Error::Other(format!("invalid header value {value:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sas_token_is_signed_for_resource() {
+        let expiry = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let token = sas_token(
+            "https://example.servicebus.windows.net/orders",
+            "send-only",
+            "c2VjcmV0",
+            expiry,
+        );
+        let fields = token
+            .strip_prefix("SharedAccessSignature ")
+            .unwrap()
+            .split('&')
+            .map(|field| field.split_once('=').unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields[0],
+            (
+                "sr",
+                "https%3A%2F%2Fexample.servicebus.windows.net%2Forders"
+            )
+        );
+        assert_eq!(fields[1].0, "sig");
+        assert_eq!(fields[2], ("se", "1700000000"));
+        assert_eq!(fields[3], ("skn", "send-only"));
+
+        let mut mac = HmacSha256::new_from_slice(b"c2VjcmV0").unwrap();
+        mac.update(b"https%3A%2F%2Fexample.servicebus.windows.net%2Forders\n1700000000");
+        assert_eq!(
+            fields[1].1,
+            url_encode(&STANDARD.encode(mac.finalize().into_bytes()))
+        );
+    }
+
+    #[test]
+    fn broker_properties_omit_unset_fields() {
+        assert!(broker_properties(None, None, None).is_empty());
+        let deliver_at = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        let properties = broker_properties(None, Some("replies".into()), Some(deliver_at));
+        assert_eq!(
+            Value::Object(properties),
+            json!({
+                "ReplyTo": "replies",
+                "ScheduledEnqueueTimeUtc": "Sun, 06 Nov 1994 08:49:37 GMT",
+            })
+        );
+    }
+}
@@ -0,0 +1,92 @@
+mod broker;
+
+use broker::{
+    MessagingAzure, MessagingAzureAuthOptions, MessagingAzureRuntimeConfigOptions, Service,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_messaging::runtime_config::spin::MakeMessageBroker;
+
+/// A message broker that uses Azure Service Bus queues and topics as the backend.
+#[derive(Default)]
+pub struct AzureServiceBusMessageBroker {
+    _priv: (),
+}
+
+impl AzureServiceBusMessageBroker {
+    /// Creates a new `AzureServiceBusMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A message broker that uses Azure Event Hubs as the backend.
+#[derive(Default)]
+pub struct AzureEventHubsMessageBroker {
+    _priv: (),
+}
+
+impl AzureEventHubsMessageBroker {
+    /// Creates a new `AzureEventHubsMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Azure Service Bus and Event Hubs message brokers.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AzureMessageBrokerRuntimeConfig {
+    /// The namespace name, e.g. `my-namespace`, or its fully qualified host
+    /// name, e.g. `my-namespace.servicebus.usgovcloudapi.net`.
+    namespace: String,
+    /// The name of the shared access policy that `key` belongs to.
+    ///
+    /// Defaults to `RootManageSharedAccessKey`.
+    key_name: Option<String>,
+    /// A shared access key for the namespace. If not set, credentials are
+    /// taken from the environment, e.g. a managed identity.
+    key: Option<String>,
+}
+
+impl AzureMessageBrokerRuntimeConfig {
+    fn make_broker(self, service: Service) -> anyhow::Result<MessagingAzure> {
+        let auth_options = match self.key {
+            Some(key) => MessagingAzureAuthOptions::RuntimeConfigValues(
+                MessagingAzureRuntimeConfigOptions::new(self.key_name, key),
+            ),
+            None => MessagingAzureAuthOptions::Environmental,
+        };
+        MessagingAzure::new(service, &self.namespace, auth_options)
+    }
+}
+
+impl MakeMessageBroker for AzureServiceBusMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "azure_service_bus";
+
+    type RuntimeConfig = AzureMessageBrokerRuntimeConfig;
+
+    type MessageBroker = MessagingAzure;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        runtime_config.make_broker(Service::ServiceBus)
+    }
+}
+
+impl MakeMessageBroker for AzureEventHubsMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "azure_event_hubs";
+
+    type RuntimeConfig = AzureMessageBrokerRuntimeConfig;
+
+    type MessageBroker = MessagingAzure;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        runtime_config.make_broker(Service::EventHubs)
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use async_nats::{header::CONTENT_TYPE, Client, ConnectOptions, HeaderMap};
+use futures::StreamExt;
+use spin_core::async_trait;
+use spin_factor_messaging::{
+    collect_replies, Error, Message, MessageBroker, RequestOptions, Subscription,
+};
+use tokio::sync::OnceCell;
+
+pub struct MessagingNats {
+    url: String,
+    credentials_file: Option<PathBuf>,
+    client: OnceCell<Client>,
+}
+
+impl MessagingNats {
+    pub fn new(url: String, credentials_file: Option<PathBuf>) -> Self {
+        Self {
+            url,
+            credentials_file,
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn client(&self) -> Result<Client, Error> {
+        self.client
+            .get_or_try_init(|| async {
+                let mut options = ConnectOptions::new();
+                if let Some(path) = &self.credentials_file {
+                    options = options.credentials_file(path).await.map_err(|e| {
+                        Error::Connection(format!(
+                            "failed to read NATS credentials file {}: {e}",
+                            path.display()
+                        ))
+                    })?;
+                }
+                options
+                    .connect(&self.url)
+                    .await
+                    .map_err(|e| Error::Connection(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl MessageBroker for MessagingNats {
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error> {
+        let client = self.client().await?;
+        let headers = headers(&message);
+        let payload = message.data.into();
+        match message.reply_to {
+            Some(reply_to) => client
+                .publish_with_reply_and_headers(topic.to_owned(), reply_to, headers, payload)
+                .await
+                .map_err(other_error)?,
+            None => client
+                .publish_with_headers(topic.to_owned(), headers, payload)
+                .await
+                .map_err(other_error)?,
+        }
+        // Publishing only buffers the message, so flush to surface delivery errors.
+        client.flush().await.map_err(other_error)
+    }
+
+    async fn request(
+        &self,
+        topic: &str,
+        mut message: Message,
+        options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        let client = self.client().await?;
+        let inbox = client.new_inbox();
+        // Subscribe before publishing so that no replies are missed.
+        let subscriber = client.subscribe(inbox.clone()).await.map_err(other_error)?;
+        message.reply_to = Some(inbox);
+        self.send(topic, message).await?;
+        collect_replies(
+            subscriber.map(|reply| Ok(from_nats_message(reply))),
+            &options,
+        )
+        .await
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, Error> {
+        let client = self.client().await?;
+        let subscriber = client
+            .subscribe(topic.to_owned())
+            .await
+            .map_err(other_error)?;
+        Ok(Box::pin(
+            subscriber.map(|message| Ok(from_nats_message(message))),
+        ))
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("NATS at {}", self.url))
+    }
+}
+
+fn headers(message: &Message) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(content_type) = &message.content_type {
+        headers.insert(CONTENT_TYPE, content_type.as_str());
+    }
+    for (key, value) in &message.metadata {
+        headers.append(key.as_str(), value.as_str());
+    }
+    headers
+}
+
+fn from_nats_message(message: async_nats::Message) -> Message {
+    let mut content_type = None;
+    let mut metadata = vec![];
+    for (name, values) in message.headers.iter().flat_map(|headers| headers.iter()) {
+        for value in values {
+            if name.as_ref() == CONTENT_TYPE.as_ref() {
+                content_type = Some(value.to_string());
+            } else {
+                metadata.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+    Message {
+        topic: Some(message.subject.to_string()),
+        content_type,
+        data: message.payload.to_vec(),
+        metadata,
+        reply_to: message.reply.map(|reply| reply.to_string()),
+    }
+}
+
+fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
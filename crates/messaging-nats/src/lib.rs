@@ -0,0 +1,49 @@
+mod broker;
+
+use std::path::PathBuf;
+
+use broker::MessagingNats;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_messaging::runtime_config::spin::MakeMessageBroker;
+
+/// A message broker that uses NATS as the backend.
+#[derive(Default)]
+pub struct NatsMessageBroker {
+    _priv: (),
+}
+
+impl NatsMessageBroker {
+    /// Creates a new `NatsMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the NATS message broker.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NatsMessageBrokerRuntimeConfig {
+    /// The URL of the NATS server.
+    url: String,
+    /// A NATS credentials file used to authenticate with the server.
+    credentials_file: Option<PathBuf>,
+}
+
+impl MakeMessageBroker for NatsMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "nats";
+
+    type RuntimeConfig = NatsMessageBrokerRuntimeConfig;
+
+    type MessageBroker = MessagingNats;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        Ok(MessagingNats::new(
+            runtime_config.url,
+            runtime_config.credentials_file,
+        ))
+    }
+}
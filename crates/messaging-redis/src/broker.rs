@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use futures::{Stream, TryStreamExt};
+use redis::{
+    aio::{ConnectionManager, MultiplexedConnection},
+    parse_redis_url,
+    streams::{StreamId, StreamRangeReply, StreamReadOptions, StreamReadReply},
+    AsyncCommands, Client, RedisError,
+};
+use spin_core::async_trait;
+use spin_factor_messaging::{
+    collect_replies, Error, Message, MessageBroker, RequestOptions, Subscription,
+};
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// How long a single `XREAD` blocks while waiting for new entries.
+const READ_POLL_INTERVAL_MS: usize = 1000;
+
+const DATA_FIELD: &str = "data";
+const CONTENT_TYPE_FIELD: &str = "content-type";
+const REPLY_TO_FIELD: &str = "reply-to";
+const METADATA_FIELD_PREFIX: &str = "meta.";
+
+pub struct MessagingRedis {
+    database_url: Url,
+    client: Client,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl MessagingRedis {
+    pub fn new(address: String) -> Result<Self> {
+        let database_url = parse_redis_url(&address).context("Invalid Redis URL")?;
+        let client = Client::open(database_url.clone())?;
+
+        Ok(Self {
+            database_url,
+            client,
+            connection: OnceCell::new(),
+        })
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager, Error> {
+        self.connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await
+            .cloned()
+            .map_err(connection_error)
+    }
+}
+
+#[async_trait]
+impl MessageBroker for MessagingRedis {
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error> {
+        let mut connection = self.connection().await?;
+        let _: String = connection
+            .xadd(topic, "*", &stream_fields(message))
+            .await
+            .map_err(connection_error)?;
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        topic: &str,
+        mut message: Message,
+        options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        let reply_stream = format!("{topic}.reply.{}", uuid::Uuid::new_v4());
+        // Blocking reads would stall every other command sharing the
+        // connection manager, so replies are read on their own connection.
+        let reply_connection = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(connection_error)?;
+
+        message.reply_to = Some(reply_stream.clone());
+        self.send(topic, message).await?;
+        let replies = collect_replies(
+            read_entries(reply_connection.clone(), reply_stream.clone(), "0".into()),
+            &options,
+        )
+        .await;
+
+        // Best effort: the stream is only read by this request.
+        let mut connection = reply_connection;
+        let _: Result<(), _> = connection.del(&reply_stream).await;
+        replies
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, Error> {
+        // Blocking reads would stall every other command sharing the
+        // connection manager, so each subscription reads on its own connection.
+        let mut connection = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(connection_error)?;
+        // Deliver only entries added after subscribing, resolving the latest
+        // ID now so that nothing added between reads is skipped.
+        let latest: StreamRangeReply = connection
+            .xrevrange_count(topic, "+", "-", 1)
+            .await
+            .map_err(connection_error)?;
+        let last_id = latest
+            .ids
+            .into_iter()
+            .next()
+            .map_or_else(|| "0".to_owned(), |entry| entry.id);
+        Ok(Box::pin(read_entries(
+            connection,
+            topic.to_owned(),
+            last_id,
+        )))
+    }
+
+    fn summary(&self) -> Option<String> {
+        let redis::ConnectionInfo { addr, .. } = self.database_url.as_str().parse().ok()?;
+        Some(format!("Redis at {addr}"))
+    }
+}
+
+/// Encodes a message as the fields of a stream entry.
+fn stream_fields(message: Message) -> Vec<(String, Vec<u8>)> {
+    let mut fields = vec![(DATA_FIELD.to_owned(), message.data)];
+    if let Some(content_type) = message.content_type {
+        fields.push((CONTENT_TYPE_FIELD.to_owned(), content_type.into_bytes()));
+    }
+    if let Some(reply_to) = message.reply_to {
+        fields.push((REPLY_TO_FIELD.to_owned(), reply_to.into_bytes()));
+    }
+    for (key, value) in message.metadata {
+        fields.push((format!("{METADATA_FIELD_PREFIX}{key}"), value.into_bytes()));
+    }
+    fields
+}
+
+/// Decodes a stream entry read from `topic`.
+fn message_from_entry(topic: &str, entry: &StreamId) -> Message {
+    let metadata = entry
+        .map
+        .keys()
+        .filter_map(|field| {
+            let key = field.strip_prefix(METADATA_FIELD_PREFIX)?;
+            Some((key.to_owned(), entry.get(field)?))
+        })
+        .collect();
+    Message {
+        topic: Some(topic.to_owned()),
+        content_type: entry.get(CONTENT_TYPE_FIELD),
+        data: entry.get(DATA_FIELD).unwrap_or_default(),
+        metadata,
+        reply_to: entry.get(REPLY_TO_FIELD),
+    }
+}
+
+/// Reads the entries added to `stream` after `last_id` as they arrive.
+fn read_entries(
+    connection: MultiplexedConnection,
+    stream: String,
+    last_id: String,
+) -> impl Stream<Item = Result<Message, Error>> + Send + 'static {
+    futures::stream::try_unfold((connection, last_id), move |(mut connection, last_id)| {
+        let stream = stream.clone();
+        async move {
+            loop {
+                let options = StreamReadOptions::default().block(READ_POLL_INTERVAL_MS);
+                let reply: Option<StreamReadReply> = connection
+                    .xread_options(&[&stream], &[&last_id], &options)
+                    .await
+                    .map_err(connection_error)?;
+                let entries: Vec<StreamId> = reply
+                    .unwrap_or_default()
+                    .keys
+                    .into_iter()
+                    .flat_map(|key| key.ids)
+                    .collect();
+                let Some(last) = entries.last() else {
+                    continue;
+                };
+                let last_id = last.id.clone();
+                let messages: Vec<Result<Message, Error>> = entries
+                    .iter()
+                    .map(|entry| Ok(message_from_entry(&stream, entry)))
+                    .collect();
+                return Ok(Some((
+                    futures::stream::iter(messages),
+                    (connection, last_id),
+                )));
+            }
+        }
+    })
+    .try_flatten()
+}
+
+fn connection_error(e: RedisError) -> Error {
+    Error::Connection(e.to_string())
+}
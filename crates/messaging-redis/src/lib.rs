@@ -0,0 +1,42 @@
+mod broker;
+
+use broker::MessagingRedis;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_messaging::runtime_config::spin::MakeMessageBroker;
+
+/// A message broker that uses Redis streams as the backend.
+#[derive(Default)]
+pub struct RedisMessageBroker {
+    _priv: (),
+}
+
+impl RedisMessageBroker {
+    /// Creates a new `RedisMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Redis message broker.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RedisMessageBrokerRuntimeConfig {
+    /// The URL of the Redis server.
+    url: String,
+}
+
+impl MakeMessageBroker for RedisMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "redis";
+
+    type RuntimeConfig = RedisMessageBrokerRuntimeConfig;
+
+    type MessageBroker = MessagingRedis;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        MessagingRedis::new(runtime_config.url)
+    }
+}
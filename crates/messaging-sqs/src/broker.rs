@@ -0,0 +1,154 @@
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_credential_types::Credentials;
+use aws_sdk_sqs::{
+    config::{ProvideCredentials, SharedCredentialsProvider},
+    types::MessageAttributeValue,
+    Client,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use spin_core::async_trait;
+use spin_factor_messaging::{Error, Message, MessageBroker, RequestOptions};
+
+/// The message attribute holding a message's content type.
+const CONTENT_TYPE_ATTRIBUTE: &str = "content-type";
+
+pub struct MessagingSqs {
+    /// AWS region
+    region: String,
+    /// SQS client
+    client: async_once_cell::Lazy<
+        Client,
+        std::pin::Pin<Box<dyn std::future::Future<Output = Client> + Send>>,
+    >,
+}
+
+/// Amazon SQS runtime config literal options for authentication
+#[derive(Clone, Debug)]
+pub struct MessagingSqsRuntimeConfigOptions {
+    access_key: String,
+    secret_key: String,
+    token: Option<String>,
+}
+
+impl MessagingSqsRuntimeConfigOptions {
+    pub fn new(access_key: String, secret_key: String, token: Option<String>) -> Self {
+        Self {
+            access_key,
+            secret_key,
+            token,
+        }
+    }
+}
+
+impl ProvideCredentials for MessagingSqsRuntimeConfigOptions {
+    fn provide_credentials<'a>(
+        &'a self,
+    ) -> aws_credential_types::provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        aws_credential_types::provider::future::ProvideCredentials::ready(Ok(Credentials::new(
+            self.access_key.clone(),
+            self.secret_key.clone(),
+            self.token.clone(),
+            None, // Optional expiration time
+            "spin_custom_aws_provider",
+        )))
+    }
+}
+
+/// Amazon SQS enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum MessagingSqsAuthOptions {
+    /// Runtime Config values indicates credentials have been specified directly
+    RuntimeConfigValues(MessagingSqsRuntimeConfigOptions),
+    /// Environmental indicates that the environment variables of the process should be used to
+    /// create the SDK Config for the SQS client. This will use the AWS Rust SDK's
+    /// aws_config::load_defaults to derive credentials based on what environment variables
+    /// have been set.
+    ///
+    /// See https://docs.aws.amazon.com/cli/latest/userguide/cli-chap-authentication.html for options.
+    Environmental,
+}
+
+impl MessagingSqs {
+    pub fn new(region: String, auth_options: MessagingSqsAuthOptions) -> Self {
+        let region_clone = region.clone();
+        let client_fut = Box::pin(async move {
+            let sdk_config = match auth_options {
+                MessagingSqsAuthOptions::RuntimeConfigValues(config) => SdkConfig::builder()
+                    .credentials_provider(SharedCredentialsProvider::new(config))
+                    .region(Region::new(region_clone))
+                    .behavior_version(BehaviorVersion::latest())
+                    .build(),
+                MessagingSqsAuthOptions::Environmental => {
+                    aws_config::defaults(BehaviorVersion::latest())
+                        .region(Region::new(region_clone))
+                        .load()
+                        .await
+                }
+            };
+            Client::new(&sdk_config)
+        });
+
+        Self {
+            region,
+            client: async_once_cell::Lazy::from_future(client_fut),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageBroker for MessagingSqs {
+    async fn send(&self, topic: &str, message: Message) -> Result<(), Error> {
+        let client = self.client.get_unpin().await;
+        // Topics name queues, which are addressed by URL.
+        let queue_url = client
+            .get_queue_url()
+            .queue_name(topic)
+            .send()
+            .await
+            .map_err(|e| Error::Connection(format!("could not find queue {topic:?}: {e}")))?
+            .queue_url
+            .ok_or_else(|| Error::Connection(format!("could not find queue {topic:?}")))?;
+
+        let mut request = client
+            .send_message()
+            .queue_url(queue_url)
+            // SQS message bodies are text.
+            .message_body(STANDARD.encode(&message.data));
+        let attributes = message
+            .content_type
+            .map(|content_type| (CONTENT_TYPE_ATTRIBUTE.to_owned(), content_type))
+            .into_iter()
+            .chain(message.metadata);
+        for (key, value) in attributes {
+            let value = MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(value)
+                .build()
+                .map_err(|e| Error::Other(e.to_string()))?;
+            request = request.message_attributes(key, value);
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        _topic: &str,
+        _message: Message,
+        _options: RequestOptions,
+    ) -> Result<Vec<Message>, Error> {
+        Err(Error::Other(
+            "request-reply is not supported by Amazon SQS".into(),
+        ))
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("Amazon SQS region: {}", self.region))
+    }
+}
@@ -0,0 +1,60 @@
+mod broker;
+
+use broker::{MessagingSqs, MessagingSqsAuthOptions, MessagingSqsRuntimeConfigOptions};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_messaging::runtime_config::spin::MakeMessageBroker;
+
+/// A message broker that uses Amazon SQS as the backend.
+#[derive(Default)]
+pub struct SqsMessageBroker {
+    _priv: (),
+}
+
+impl SqsMessageBroker {
+    /// Creates a new `SqsMessageBroker`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Amazon SQS message broker.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SqsMessageBrokerRuntimeConfig {
+    /// The access key for the AWS account role.
+    access_key: Option<String>,
+    /// The secret key for authorization on the AWS account.
+    secret_key: Option<String>,
+    /// The token for authorization on the AWS account.
+    token: Option<String>,
+    /// The AWS region where the queues are located.
+    region: String,
+}
+
+impl MakeMessageBroker for SqsMessageBroker {
+    const RUNTIME_CONFIG_TYPE: &'static str = "sqs";
+
+    type RuntimeConfig = SqsMessageBrokerRuntimeConfig;
+
+    type MessageBroker = MessagingSqs;
+
+    fn make_store(
+        &self,
+        runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::MessageBroker> {
+        let SqsMessageBrokerRuntimeConfig {
+            access_key,
+            secret_key,
+            token,
+            region,
+        } = runtime_config;
+        let auth_options = match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) => MessagingSqsAuthOptions::RuntimeConfigValues(
+                MessagingSqsRuntimeConfigOptions::new(access_key, secret_key, token),
+            ),
+            _ => MessagingSqsAuthOptions::Environmental,
+        };
+        Ok(MessagingSqs::new(region, auth_options))
+    }
+}
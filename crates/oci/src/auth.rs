@@ -1,3 +1,5 @@
+mod cloud;
+
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
@@ -8,6 +10,8 @@ use oci_distribution::secrets::RegistryAuth;
 use serde::{Deserialize, Serialize};
 use spin_common::ui::quoted_path;
 
+pub(crate) use cloud::{CloudRegistry, ACR_TOKEN_USERNAME};
+
 #[derive(Serialize, Deserialize)]
 pub struct AuthConfig {
     /// Map between registry server and base64 encoded username:password credential set.
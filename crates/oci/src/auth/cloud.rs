@@ -0,0 +1,127 @@
+//! Registry credentials for the major cloud providers' registries, obtained
+//! by exchanging the user's cloud CLI login for a registry token.
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::secrets::RegistryAuth;
+
+/// The username Azure Container Registry expects alongside a token.
+pub(crate) const ACR_TOKEN_USERNAME: &str = "00000000-0000-0000-0000-000000000000";
+
+/// A registry run by a cloud provider, for which credentials can be obtained
+/// from the provider's CLI.
+#[derive(Debug, PartialEq)]
+pub(crate) enum CloudRegistry {
+    /// Amazon Elastic Container Registry in the given region.
+    Ecr { region: String },
+    /// Google Container Registry or Artifact Registry.
+    Gcr,
+    /// Azure Container Registry with the given registry name.
+    Acr { name: String },
+}
+
+impl CloudRegistry {
+    /// Recognises a cloud provider registry from its server name.
+    pub fn detect(server: &str) -> Option<Self> {
+        let host = server.split(':').next().unwrap_or(server);
+        // <account>.dkr.ecr.<region>.amazonaws.com(.cn)
+        let labels = host.split('.').collect::<Vec<_>>();
+        if let [_account, "dkr", "ecr", region, "amazonaws", "com", ..] = labels.as_slice() {
+            return Some(Self::Ecr {
+                region: region.to_string(),
+            });
+        }
+        if host == "gcr.io" || host.ends_with(".gcr.io") || host.ends_with("-docker.pkg.dev") {
+            return Some(Self::Gcr);
+        }
+        if let Some(name) = host.strip_suffix(".azurecr.io") {
+            return Some(Self::Acr {
+                name: name.to_owned(),
+            });
+        }
+        None
+    }
+
+    /// Obtains registry credentials from the provider's CLI.
+    pub async fn auth(&self) -> Result<RegistryAuth> {
+        let (username, program, args): (&str, &str, Vec<&str>) = match self {
+            Self::Ecr { region } => (
+                "AWS",
+                "aws",
+                vec!["ecr", "get-login-password", "--region", region.as_str()],
+            ),
+            Self::Gcr => (
+                "oauth2accesstoken",
+                "gcloud",
+                vec!["auth", "print-access-token"],
+            ),
+            Self::Acr { name } => (
+                ACR_TOKEN_USERNAME,
+                "az",
+                vec![
+                    "acr",
+                    "login",
+                    "--name",
+                    name.as_str(),
+                    "--expose-token",
+                    "--output",
+                    "tsv",
+                    "--query",
+                    "accessToken",
+                ],
+            ),
+        };
+        let output = tokio::process::Command::new(program)
+            .args(&args)
+            .output()
+            .await
+            .with_context(|| format!("could not run `{program}`"))?;
+        if !output.status.success() {
+            bail!(
+                "`{program} {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let token = String::from_utf8(output.stdout)
+            .with_context(|| format!("`{program}` returned a token that is not UTF-8"))?;
+        let token = token.trim();
+        if token.is_empty() {
+            bail!("`{program}` returned an empty token");
+        }
+        Ok(RegistryAuth::Basic(username.to_owned(), token.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloud_registries_are_detected() {
+        assert_eq!(
+            CloudRegistry::detect("123456789012.dkr.ecr.eu-west-1.amazonaws.com"),
+            Some(CloudRegistry::Ecr {
+                region: "eu-west-1".into()
+            })
+        );
+        assert_eq!(
+            CloudRegistry::detect("123456789012.dkr.ecr.cn-north-1.amazonaws.com.cn"),
+            Some(CloudRegistry::Ecr {
+                region: "cn-north-1".into()
+            })
+        );
+        assert_eq!(CloudRegistry::detect("eu.gcr.io"), Some(CloudRegistry::Gcr));
+        assert_eq!(
+            CloudRegistry::detect("us-central1-docker.pkg.dev"),
+            Some(CloudRegistry::Gcr)
+        );
+        assert_eq!(
+            CloudRegistry::detect("myregistry.azurecr.io"),
+            Some(CloudRegistry::Acr {
+                name: "myregistry".into()
+            })
+        );
+        assert_eq!(CloudRegistry::detect("ghcr.io"), None);
+        assert_eq!(CloudRegistry::detect("localhost:5000"), None);
+    }
+}
@@ -0,0 +1,263 @@
+//! Application bundles, which package an application and all of its content
+//! into a single archive so that it can be run without registry access.
+//!
+//! A bundle is a gzipped tar containing a `spin.lock` locked app whose
+//! content sources are paths relative to the bundle root, alongside the
+//! component Wasm (under `wasm/`) and static assets (under `files/`).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use reqwest::Url;
+use spin_common::{sha256::hex_digest_from_file, ui::quoted_path};
+use spin_locked_app::locked::{ContentRef, LockedApp, LockedComponent};
+
+/// The locked app file within a bundle.
+pub const BUNDLE_LOCKED_APP_FILE: &str = "spin.lock";
+
+/// The bundle directory holding component Wasm, named by digest.
+const WASM_DIR: &str = "wasm";
+
+/// The bundle directory holding static assets, by component.
+const FILES_DIR: &str = "files";
+
+/// Writes a bundle of the given application to `dest`. The application's
+/// content must be local files, as produced by the Spin loaders.
+pub async fn save(mut locked_app: LockedApp, dest: &Path) -> Result<()> {
+    let staging = tempfile::tempdir().context("cannot create staging directory")?;
+    let root = staging.path().join("bundle");
+    std::fs::create_dir_all(root.join(WASM_DIR))?;
+
+    for component in &mut locked_app.components {
+        bundle_component(component, &root).with_context(|| {
+            format!("failed to bundle content for component {:?}", component.id)
+        })?;
+    }
+    // The origin of the bundled app isn't meaningful where it is loaded.
+    locked_app.metadata.remove("origin");
+    let locked_app_contents =
+        serde_json::to_vec_pretty(&locked_app).context("failed to serialize locked app")?;
+    std::fs::write(root.join(BUNDLE_LOCKED_APP_FILE), locked_app_contents)?;
+
+    let archive = crate::utils::archive(&root, staging.path()).await?;
+    tokio::fs::copy(&archive, dest)
+        .await
+        .with_context(|| format!("failed to write bundle {}", quoted_path(dest)))?;
+    Ok(())
+}
+
+/// Unpacks a bundle archive into the `dest` directory.
+pub async fn unpack(bundle: &Path, dest: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(dest)
+        .await
+        .with_context(|| format!("failed to create directory {}", quoted_path(dest)))?;
+    crate::utils::unarchive(bundle, dest)
+        .await
+        .with_context(|| format!("failed to unpack bundle {}", quoted_path(bundle)))?;
+    ensure!(
+        dest.join(BUNDLE_LOCKED_APP_FILE).exists(),
+        "{} is not a Spin application bundle",
+        quoted_path(bundle)
+    );
+    Ok(())
+}
+
+/// Loads the application from a bundle. This may be a bundle archive, which
+/// is unpacked into `working_dir`, or a directory a bundle was unpacked into.
+pub async fn load(bundle: &Path, working_dir: &Path) -> Result<LockedApp> {
+    let root = if bundle.is_dir() {
+        bundle.to_owned()
+    } else {
+        let root = working_dir.join("bundle");
+        unpack(bundle, &root).await?;
+        root
+    };
+    let root = std::fs::canonicalize(&root)
+        .with_context(|| format!("cannot resolve bundle directory {}", quoted_path(&root)))?;
+
+    let locked_path = root.join(BUNDLE_LOCKED_APP_FILE);
+    let locked_content = tokio::fs::read(&locked_path)
+        .await
+        .with_context(|| format!("failed to read from {}", quoted_path(&locked_path)))?;
+    let mut locked_app = LockedApp::from_json(&locked_content).with_context(|| {
+        format!(
+            "failed to decode locked app from {}",
+            quoted_path(&locked_path)
+        )
+    })?;
+
+    let origin = Url::from_file_path(std::fs::canonicalize(bundle)?)
+        .map_err(|_| anyhow!("cannot convert to file URL: {}", quoted_path(bundle)))?;
+    locked_app
+        .metadata
+        .insert("origin".to_string(), origin.to_string().into());
+
+    for component in &mut locked_app.components {
+        resolve_component(component, &root).with_context(|| {
+            format!("failed to resolve content for component {:?}", component.id)
+        })?;
+    }
+    Ok(locked_app)
+}
+
+fn bundle_component(component: &mut LockedComponent, root: &Path) -> Result<()> {
+    bundle_wasm(&mut component.source.content, root)?;
+    for dep in component.dependencies.values_mut() {
+        bundle_wasm(&mut dep.source.content, root)?;
+    }
+    for (index, file) in component.files.iter_mut().enumerate() {
+        let Some(source) = &file.content.source else {
+            // Inline content is carried in the locked app.
+            continue;
+        };
+        if spin_loader::is_remote_files_source(source) {
+            // Remote files are synced when the app runs.
+            continue;
+        }
+        let src = local_path(&file.content)?;
+        let rel = format!("{FILES_DIR}/{}/{index}", component.id);
+        let dest = root.join(&rel);
+        if src.is_dir() {
+            copy_dir(&src, &dest)?;
+        } else {
+            std::fs::create_dir_all(dest.parent().unwrap())?;
+            std::fs::copy(&src, &dest)
+                .with_context(|| format!("failed to copy {}", quoted_path(&src)))?;
+        }
+        file.content.source = Some(rel);
+    }
+    Ok(())
+}
+
+fn bundle_wasm(content: &mut ContentRef, root: &Path) -> Result<()> {
+    if content.source.is_none() {
+        return Ok(());
+    }
+    let src = local_path(content)?;
+    let digest = hex_digest_from_file(&src)
+        .with_context(|| format!("failed to digest {}", quoted_path(&src)))?;
+    let rel = format!("{WASM_DIR}/sha256-{digest}.wasm");
+    let dest = root.join(&rel);
+    // Components sharing Wasm share a bundle entry.
+    if !dest.exists() {
+        std::fs::copy(&src, &dest)
+            .with_context(|| format!("failed to copy {}", quoted_path(&src)))?;
+    }
+    content.source = Some(rel);
+    Ok(())
+}
+
+fn resolve_component(component: &mut LockedComponent, root: &Path) -> Result<()> {
+    resolve_content(&mut component.source.content, root)?;
+    for dep in component.dependencies.values_mut() {
+        resolve_content(&mut dep.source.content, root)?;
+    }
+    for file in &mut component.files {
+        let is_remote = file
+            .content
+            .source
+            .as_deref()
+            .is_some_and(spin_loader::is_remote_files_source);
+        if !is_remote {
+            resolve_content(&mut file.content, root)?;
+        }
+    }
+    Ok(())
+}
+
+fn resolve_content(content: &mut ContentRef, root: &Path) -> Result<()> {
+    let Some(rel) = &content.source else {
+        return Ok(());
+    };
+    ensure!(
+        Path::new(rel)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_))),
+        "invalid bundle content path {rel:?}"
+    );
+    let path = root.join(rel);
+    ensure!(
+        path.exists(),
+        "bundle is missing {rel:?}; it may be incomplete"
+    );
+    let url = Url::from_file_path(&path).map_err(|_| anyhow!("couldn't build file URL"))?;
+    content.source = Some(url.to_string());
+    Ok(())
+}
+
+fn local_path(content: &ContentRef) -> Result<PathBuf> {
+    let source = content.source.as_deref().context("content has no source")?;
+    let url = Url::parse(source).with_context(|| format!("invalid content source {source:?}"))?;
+    if url.scheme() != "file" {
+        bail!("content source {source:?} is not a local file");
+    }
+    url.to_file_path()
+        .map_err(|_| anyhow!("invalid file URL {source:?}"))
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(src).follow_links(true) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy {}", quoted_path(entry.path())))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bundle_round_trips() {
+        let src = tempfile::tempdir().unwrap();
+        let wasm_path = src.path().join("app.wasm");
+        std::fs::write(&wasm_path, b"\0asm").unwrap();
+        let assets = src.path().join("assets");
+        std::fs::create_dir_all(assets.join("css")).unwrap();
+        std::fs::write(assets.join("css/site.css"), "body {}").unwrap();
+
+        let url = |p: &Path| Url::from_file_path(p).unwrap().to_string();
+        let locked_app = LockedApp::from_json(
+            serde_json::json!({
+                "spin_lock_version": 1,
+                "triggers": [],
+                "components": [{
+                    "id": "web",
+                    "source": { "content_type": "application/wasm", "source": url(&wasm_path) },
+                    "files": [
+                        { "source": url(&assets), "path": "/" },
+                        { "inline": "aGk=", "path": "/inline.txt" },
+                    ],
+                }],
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let bundle_path = src.path().join("app.tar.gz");
+        save(locked_app, &bundle_path).await.unwrap();
+
+        let work = tempfile::tempdir().unwrap();
+        let loaded = load(&bundle_path, work.path()).await.unwrap();
+        let component = &loaded.components[0];
+        let wasm = local_path(&component.source.content).unwrap();
+        assert!(wasm.starts_with(std::fs::canonicalize(work.path()).unwrap()));
+        assert_eq!(std::fs::read(wasm).unwrap(), b"\0asm");
+        let mounted = local_path(&component.files[0].content).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(mounted.join("css/site.css")).unwrap(),
+            "body {}"
+        );
+        assert_eq!(
+            component.files[1].content.inline.as_deref(),
+            Some(&b"hi"[..])
+        );
+    }
+}
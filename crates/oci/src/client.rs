@@ -23,7 +23,11 @@ use spin_locked_app::locked::{ContentPath, ContentRef, LockedApp, LockedComponen
 use tokio::fs;
 use walkdir::WalkDir;
 
-use crate::auth::AuthConfig;
+use crate::auth::{AuthConfig, CloudRegistry, ACR_TOKEN_USERNAME};
+use crate::signing::{
+    signature_tag, AppSigningKey, VerificationPolicy, SIGNATURE_ANNOTATION,
+    SIMPLE_SIGNING_MEDIA_TYPE,
+};
 
 // TODO: the media types for application, data and archive layer are not final
 /// Media type for a layer representing a locked Spin application configuration
@@ -93,6 +97,9 @@ pub struct Client {
 pub struct ClientOpts {
     /// Inline content into ContentRef iff < this size.
     pub content_ref_inline_max_size: usize,
+    /// If set, pulled applications must have a signature satisfying this
+    /// policy.
+    pub verification_policy: Option<VerificationPolicy>,
 }
 
 /// Controls whether predefined annotations are generated when pushing an application.
@@ -113,6 +120,7 @@ impl Client {
         let cache = Cache::new(cache_root).await?;
         let opts = ClientOpts {
             content_ref_inline_max_size: DEFAULT_CONTENT_REF_INLINE_MAX_SIZE,
+            verification_policy: None,
         };
 
         Ok(Self {
@@ -268,6 +276,143 @@ impl Client {
         Ok(digest)
     }
 
+    /// Sign a pushed application, pushing the signature alongside it, and
+    /// return the signed digest. If the digest of the push is not known, it
+    /// is looked up from the registry.
+    pub async fn sign(
+        &mut self,
+        reference: impl AsRef<str>,
+        digest: Option<String>,
+        key: &AppSigningKey,
+        annotations: BTreeMap<String, String>,
+    ) -> Result<String> {
+        let reference: Reference = reference
+            .as_ref()
+            .parse()
+            .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
+        let auth = Self::auth(&reference).await?;
+        let digest = match digest {
+            Some(digest) => digest,
+            None => self.oci.pull_image_manifest(&reference, &auth).await?.1,
+        };
+
+        let repository = format!("{}/{}", reference.registry(), reference.repository());
+        let (payload, signature) = key.sign(&repository, &digest, annotations)?;
+        let layer_annotations = HashMap::from([(SIGNATURE_ANNOTATION.to_owned(), signature)]);
+        let layer = ImageLayer::new(
+            payload,
+            SIMPLE_SIGNING_MEDIA_TYPE.to_owned(),
+            Some(layer_annotations),
+        );
+        let config = oci_distribution::client::Config::oci_v1(b"{}".to_vec(), None);
+        let layers = [layer];
+        let manifest = OciImageManifest::build(&layers, &config, None);
+
+        let signature_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            signature_tag(&digest),
+        );
+        self.oci
+            .push(&signature_reference, &layers, config, &auth, Some(manifest))
+            .await
+            .context("cannot push signature")?;
+        Ok(digest)
+    }
+
+    /// Attach content, such as a bill of materials, to a pushed application,
+    /// storing it under the tag `tag` derives from the application's digest.
+    /// Returns the digest the content is attached to, looking it up from the
+    /// registry if the digest of the push is not known.
+    pub async fn attach(
+        &mut self,
+        reference: impl AsRef<str>,
+        digest: Option<String>,
+        tag: fn(&str) -> String,
+        media_type: &str,
+        content: Vec<u8>,
+    ) -> Result<String> {
+        let reference: Reference = reference
+            .as_ref()
+            .parse()
+            .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
+        let auth = Self::auth(&reference).await?;
+        let digest = match digest {
+            Some(digest) => digest,
+            None => self.oci.pull_image_manifest(&reference, &auth).await?.1,
+        };
+
+        let layers = [ImageLayer::new(content, media_type.to_owned(), None)];
+        let config = oci_distribution::client::Config::oci_v1(b"{}".to_vec(), None);
+        let manifest = OciImageManifest::build(&layers, &config, None);
+        let attachment_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            tag(&digest),
+        );
+        self.oci
+            .push(
+                &attachment_reference,
+                &layers,
+                config,
+                &auth,
+                Some(manifest),
+            )
+            .await
+            .with_context(|| format!("cannot push {media_type} attachment"))?;
+        Ok(digest)
+    }
+
+    /// Check that the manifest with the given digest has a signature
+    /// satisfying the policy.
+    async fn verify_signature(
+        &self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        digest: &str,
+        policy: &VerificationPolicy,
+    ) -> Result<()> {
+        let signature_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            signature_tag(digest),
+        );
+        let (signature_manifest, _) = self
+            .oci
+            .pull_image_manifest(&signature_reference, auth)
+            .await
+            .with_context(|| format!("no signature found for {reference}@{digest}"))?;
+
+        let mut problems = vec![];
+        for layer in &signature_manifest.layers {
+            if layer.media_type != SIMPLE_SIGNING_MEDIA_TYPE {
+                continue;
+            }
+            let Some(signature) = layer
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(SIGNATURE_ANNOTATION))
+            else {
+                continue;
+            };
+            let mut payload = Vec::new();
+            self.oci
+                .pull_blob(&signature_reference, layer, &mut payload)
+                .await?;
+            match policy.verify(digest, &payload, signature) {
+                Ok(()) => return Ok(()),
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        if problems.is_empty() {
+            bail!("no signature found for {reference}@{digest}");
+        }
+        bail!(
+            "no valid signature found for {reference}@{digest}: {}",
+            problems.join("; ")
+        )
+    }
+
     /// Assemble ImageLayers for a locked application using the provided
     /// AssemblyMode and return the resulting Vec<ImageLayer>.
     async fn assemble_layers(
@@ -389,6 +534,11 @@ impl Client {
                 .source
                 .as_ref()
                 .context("file mount loaded from disk should contain a file source")?;
+            // Remote files are synced when the app runs, rather than pushed.
+            if spin_loader::is_remote_files_source(source) {
+                files.push(f.clone());
+                continue;
+            }
             let source = parse_file_url(source.as_str())?;
 
             match assembly_mode {
@@ -495,10 +645,31 @@ impl Client {
     /// Pull a Spin application from an OCI registry.
     pub async fn pull(&mut self, reference: &str) -> Result<()> {
         let reference: Reference = reference.parse().context("cannot parse reference")?;
-        let auth = Self::auth(&reference).await?;
+        let mut auth = Self::auth(&reference).await?;
+
+        // Pull the manifest from the registry. Credentials can be stale or
+        // scoped to other repositories, so if they are refused, fall back
+        // to pulling anonymously in case the app is public.
+        let (manifest, digest) = match self.oci.pull_image_manifest(&reference, &auth).await {
+            Ok(pulled) => pulled,
+            Err(e) if !matches!(auth, RegistryAuth::Anonymous) => {
+                tracing::debug!("Pull with credentials failed, retrying anonymously: {e}");
+                let pulled = self
+                    .oci
+                    .pull_image_manifest(&reference, &RegistryAuth::Anonymous)
+                    .await
+                    .map_err(|_| e)?;
+                auth = RegistryAuth::Anonymous;
+                pulled
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        // Pull the manifest from the registry.
-        let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
+        if let Some(policy) = &self.opts.verification_policy {
+            self.verify_signature(&reference, &auth, &digest, policy)
+                .await
+                .context("application signature verification failed")?;
+        }
 
         let manifest_json = serde_json::to_string(&manifest)?;
         tracing::debug!("Pulled manifest: {}", manifest_json);
@@ -520,22 +691,27 @@ impl Client {
 
         // If a layer is a Wasm module, write it in the Wasm directory.
         // Otherwise, write it in the data directory (after unpacking if archive layer)
-        stream::iter(manifest.layers)
+        // Layers shared between components are only fetched once.
+        stream::iter(manifest.layers.into_iter().unique_by(|l| l.digest.clone()))
             .map(|layer| {
                 let this = &self;
                 let reference = reference.clone();
                 async move {
                     // Skip pulling if the digest already exists in the wasm or data directories.
-                    if this.cache.wasm_file(&layer.digest).is_ok()
-                        || this.cache.data_file(&layer.digest).is_ok()
-                    {
+                    let cached = this
+                        .cache
+                        .wasm_file(&layer.digest)
+                        .or_else(|_| this.cache.data_file(&layer.digest));
+                    if let Ok(cached) = cached {
                         tracing::debug!("Layer {} already exists in cache", &layer.digest);
+                        this.cache.mark_used(&cached);
                         return anyhow::Ok(());
                     }
 
                     tracing::debug!("Pulling layer {}", &layer.digest);
                     let mut bytes = Vec::with_capacity(layer.size.try_into()?);
                     this.oci.pull_blob(&reference, &layer, &mut bytes).await?;
+                    verify_layer_digest(&layer.digest, &bytes)?;
                     match layer.media_type.as_str() {
                         SPIN_APPLICATION_MEDIA_TYPE => {
                             this.write_locked_app_config(&reference.to_string(), &bytes)
@@ -715,31 +891,56 @@ impl Client {
         }
     }
 
-    /// Construct the registry authentication based on the reference.
+    /// Construct the registry authentication based on the reference. This
+    /// tries, in order: credentials saved by `spin registry login`; Docker
+    /// credentials, including credential helpers; a token from the cloud
+    /// provider's CLI for ECR, GCR/Artifact Registry and ACR; and finally
+    /// anonymous access.
     async fn auth(reference: &Reference) -> Result<RegistryAuth> {
         let server = reference
             .resolve_registry()
             .strip_suffix('/')
             .unwrap_or_else(|| reference.resolve_registry());
 
-        match AuthConfig::get_auth_from_default(server).await {
-            Ok(c) => Ok(c),
-            Err(_) => match docker_credential::get_credential(server) {
-                Err(e) => {
-                    tracing::trace!("Cannot retrieve credentials from Docker, attempting to use anonymous auth: {}", e);
-                    Ok(RegistryAuth::Anonymous)
+        if let Ok(c) = AuthConfig::get_auth_from_default(server).await {
+            return Ok(c);
+        }
+
+        let cloud_registry = CloudRegistry::detect(server);
+        match docker_credential::get_credential(server) {
+            Err(e) => {
+                tracing::trace!("Cannot retrieve credentials from Docker: {}", e);
+            }
+            Ok(DockerCredential::UsernamePassword(username, password)) => {
+                tracing::trace!("Found Docker credentials");
+                return Ok(RegistryAuth::Basic(username, password));
+            }
+            Ok(DockerCredential::IdentityToken(token)) => {
+                // ACR accepts its identity tokens in place of a password.
+                if matches!(cloud_registry, Some(CloudRegistry::Acr { .. })) {
+                    tracing::trace!("Found Docker identity token for ACR");
+                    return Ok(RegistryAuth::Basic(ACR_TOKEN_USERNAME.to_owned(), token));
                 }
+                tracing::trace!(
+                    "Cannot use contents of Docker config, identity token not supported"
+                );
+            }
+        }
 
-                Ok(DockerCredential::UsernamePassword(username, password)) => {
-                    tracing::trace!("Found Docker credentials");
-                    Ok(RegistryAuth::Basic(username, password))
+        if let Some(cloud_registry) = cloud_registry {
+            match cloud_registry.auth().await {
+                Ok(auth) => {
+                    tracing::trace!("Obtained registry token for {cloud_registry:?}");
+                    return Ok(auth);
                 }
-                Ok(DockerCredential::IdentityToken(_)) => {
-                    tracing::trace!("Cannot use contents of Docker config, identity token not supported. Using anonymous auth");
-                    Ok(RegistryAuth::Anonymous)
+                Err(e) => {
+                    tracing::trace!("Cannot obtain registry token for {cloud_registry:?}: {e:#}");
                 }
-            },
+            }
         }
+
+        tracing::trace!("Using anonymous auth");
+        Ok(RegistryAuth::Anonymous)
     }
 
     /// Build the OCI client configuration given the insecure option.
@@ -798,6 +999,20 @@ pub async fn unpack_archive_layer(
     Ok(())
 }
 
+/// Checks pulled layer content against its digest before it is cached, so a
+/// corrupted download can't be reused by later pulls.
+fn verify_layer_digest(digest: &str, bytes: &[u8]) -> Result<()> {
+    let Some(expected) = digest.strip_prefix("sha256:") else {
+        tracing::debug!("Not verifying layer with unsupported digest {digest}");
+        return Ok(());
+    };
+    let actual = sha256::hex_digest_from_bytes(bytes);
+    if actual != expected {
+        bail!("layer {digest} was corrupted in transfer: content has digest sha256:{actual}");
+    }
+    Ok(())
+}
+
 fn digest_from_url(manifest_url: &str) -> Option<String> {
     // The URL is in the form "https://host/v2/refname/manifests/sha256:..."
     let manifest_url = Url::parse(manifest_url).ok()?;
@@ -2,8 +2,11 @@
 #![deny(missing_docs)]
 
 mod auth;
+pub mod bundle;
 pub mod client;
 mod loader;
+pub mod sbom;
+pub mod signing;
 pub mod utils;
 
 pub use client::{Client, ComposeMode};
@@ -88,9 +88,18 @@ impl OciLoader {
             dep.source.content = content_ref(dep_wasm_path)?;
         }
 
-        if !component.files.is_empty() {
+        // Remote files are synced when the app runs, rather than pulled.
+        let (remote_files, mut files): (Vec<_>, Vec<_>) = std::mem::take(&mut component.files)
+            .into_iter()
+            .partition(|file| {
+                file.content
+                    .source
+                    .as_deref()
+                    .is_some_and(spin_loader::is_remote_files_source)
+            });
+        if !files.is_empty() {
             let mount_dir = self.working_dir.join("assets").join(&component.id);
-            for file in &mut component.files {
+            for file in &mut files {
                 ensure!(is_safe_to_join(&file.path), "invalid file mount {file:?}");
                 let mount_path = mount_dir.join(&file.path);
 
@@ -127,11 +136,12 @@ impl OciLoader {
                 }
             }
 
-            component.files = vec![ContentPath {
+            files = vec![ContentPath {
                 content: content_ref(mount_dir)?,
                 path: "/".into(),
             }]
         }
+        component.files = files.into_iter().chain(remote_files).collect();
 
         Ok(())
     }
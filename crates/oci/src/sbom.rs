@@ -0,0 +1,554 @@
+//! Software bills of materials and build provenance for applications.
+//!
+//! The bill of materials is a CycloneDX JSON document listing each
+//! component's Wasm, the SDKs and toolchains that produced it (from the Wasm
+//! `producers` section), its dependencies and its static assets. Provenance is
+//! an in-toto statement carrying a SLSA v1 provenance predicate.
+//!
+//! Both can be attached to a pushed application following the cosign
+//! conventions, as images tagged `sha256-<digest>.sbom` and
+//! `sha256-<digest>.att`.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use spin_common::{sha256, ui::quoted_path, url::parse_file_url};
+use spin_locked_app::{
+    locked::{ContentRef, LockedApp, LockedComponent},
+    APP_NAME_KEY, APP_VERSION_KEY,
+};
+
+use crate::signing::AppSigningKey;
+
+/// Media type of a CycloneDX JSON bill of materials.
+pub const SBOM_MEDIA_TYPE: &str = "application/vnd.cyclonedx+json";
+
+/// Media type of a DSSE envelope, which carries attestations.
+pub const DSSE_MEDIA_TYPE: &str = "application/vnd.dsse.envelope.v1+json";
+
+/// The payload type of an in-toto statement in a DSSE envelope.
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// The build type recorded in provenance.
+const BUILD_TYPE: &str = "https://spinframework.dev/spin-build/v1";
+
+/// Returns the tag of the bill of materials of the given manifest.
+pub fn sbom_tag(manifest_digest: &str) -> String {
+    format!("{}.sbom", manifest_digest.replace(':', "-"))
+}
+
+/// Returns the tag of the attestations of the given manifest.
+pub fn attestation_tag(manifest_digest: &str) -> String {
+    format!("{}.att", manifest_digest.replace(':', "-"))
+}
+
+/// The contents of an application, as recorded in its bill of materials.
+#[derive(Debug)]
+pub struct AppInventory {
+    name: String,
+    version: Option<String>,
+    components: Vec<ComponentInventory>,
+}
+
+#[derive(Debug)]
+struct ComponentInventory {
+    id: String,
+    source: Artifact,
+    producers: BTreeSet<Producer>,
+    dependencies: Vec<(String, Artifact)>,
+    files: Vec<Artifact>,
+}
+
+/// A piece of content and its SHA-256 digest.
+#[derive(Debug)]
+struct Artifact {
+    name: String,
+    sha256: String,
+}
+
+/// An entry of a Wasm `producers` section, e.g. `sdk`, `spin-sdk`, `3.0.0`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Producer {
+    field: String,
+    name: String,
+    version: String,
+}
+
+impl AppInventory {
+    /// Inventories an application whose content is local files, as produced
+    /// by the Spin loaders. Wasm under `app_dir` is named by its path
+    /// relative to it.
+    pub fn from_locked_app(locked_app: &LockedApp, app_dir: &Path) -> Result<Self> {
+        let name = locked_app
+            .get_metadata(APP_NAME_KEY)?
+            .unwrap_or_else(|| "spin-app".into());
+        let version = locked_app.get_metadata(APP_VERSION_KEY)?;
+        let components = locked_app
+            .components
+            .iter()
+            .map(|component| {
+                ComponentInventory::new(component, app_dir)
+                    .with_context(|| format!("failed to inventory component {:?}", component.id))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name,
+            version,
+            components,
+        })
+    }
+
+    /// Returns the CycloneDX bill of materials, crediting it to `spin_version`.
+    pub fn cyclonedx(&self, spin_version: &str) -> Value {
+        let components = self
+            .components
+            .iter()
+            .map(|component| {
+                let mut children: Vec<Value> = component
+                    .producers
+                    .iter()
+                    .map(|producer| {
+                        // SDKs are frameworks; languages and tools are applications.
+                        let kind = if producer.field == "sdk" {
+                            "framework"
+                        } else {
+                            "application"
+                        };
+                        json!({
+                            "type": kind,
+                            "name": producer.name,
+                            "version": producer.version,
+                            "properties": [
+                                { "name": "wasm:producers:field", "value": producer.field },
+                            ],
+                        })
+                    })
+                    .collect();
+                children.extend(component.dependencies.iter().map(|(name, artifact)| {
+                    json!({
+                        "type": "library",
+                        "bom-ref": format!("component/{}/dependency/{name}", component.id),
+                        "name": name,
+                        "hashes": artifact.hashes(),
+                        "properties": [{ "name": "spin:source", "value": artifact.name }],
+                    })
+                }));
+                children.extend(component.files.iter().map(|file| {
+                    json!({
+                        "type": "file",
+                        "name": file.name,
+                        "hashes": file.hashes(),
+                    })
+                }));
+                json!({
+                    "type": "application",
+                    "bom-ref": format!("component/{}", component.id),
+                    "name": component.id,
+                    "hashes": component.source.hashes(),
+                    "properties": [{ "name": "spin:source", "value": component.source.name }],
+                    "components": children,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut app = json!({
+            "type": "application",
+            "bom-ref": self.name,
+            "name": self.name,
+        });
+        if let Some(version) = &self.version {
+            app["version"] = version.as_str().into();
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": Utc::now().to_rfc3339(),
+                "tools": {
+                    "components": [
+                        { "type": "application", "name": "spin", "version": spin_version },
+                    ],
+                },
+                "component": app,
+            },
+            "components": components,
+            "dependencies": [{
+                "ref": self.name,
+                "dependsOn": self
+                    .components
+                    .iter()
+                    .map(|c| format!("component/{}", c.id))
+                    .collect::<Vec<_>>(),
+            }],
+        })
+    }
+
+    /// Returns an in-toto statement of the SLSA provenance of `subjects`,
+    /// which were built from this application by `spin_version` between
+    /// `started_on` and now.
+    pub fn provenance(
+        &self,
+        subjects: &[Subject],
+        spin_version: &str,
+        started_on: DateTime<Utc>,
+    ) -> Value {
+        let components = self
+            .components
+            .iter()
+            .map(|component| {
+                let sdks = component
+                    .producers
+                    .iter()
+                    .map(|producer| format!("{} {}", producer.name, producer.version))
+                    .collect::<Vec<_>>();
+                json!({ "id": component.id, "producers": sdks })
+            })
+            .collect::<Vec<_>>();
+        let resolved_dependencies = self
+            .components
+            .iter()
+            .flat_map(|component| {
+                std::iter::once(&component.source)
+                    .chain(component.dependencies.iter().map(|(_, artifact)| artifact))
+                    .chain(&component.files)
+            })
+            .map(
+                |artifact| json!({ "uri": artifact.name, "digest": { "sha256": artifact.sha256 } }),
+            )
+            .collect::<Vec<_>>();
+        json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": subjects,
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {
+                "buildDefinition": {
+                    "buildType": BUILD_TYPE,
+                    "externalParameters": {
+                        "application": self.name,
+                        "version": self.version,
+                        "components": components,
+                    },
+                    "resolvedDependencies": resolved_dependencies,
+                },
+                "runDetails": {
+                    "builder": {
+                        "id": BUILD_TYPE,
+                        "version": { "spin": spin_version },
+                    },
+                    "metadata": {
+                        "startedOn": started_on.to_rfc3339(),
+                        "finishedOn": Utc::now().to_rfc3339(),
+                    },
+                },
+            },
+        })
+    }
+
+    /// Returns the provenance subjects for the components' Wasm.
+    pub fn component_subjects(&self) -> Vec<Subject> {
+        self.components
+            .iter()
+            .map(|component| Subject::new(&component.id, &component.source.sha256))
+            .collect()
+    }
+}
+
+/// Something built, as named in provenance.
+#[derive(Debug, Serialize)]
+pub struct Subject {
+    name: String,
+    digest: Digest,
+}
+
+#[derive(Debug, Serialize)]
+struct Digest {
+    sha256: String,
+}
+
+impl Subject {
+    /// Creates a subject from its name and its hex SHA-256 digest, with or
+    /// without a `sha256:` prefix.
+    pub fn new(name: impl Into<String>, digest: &str) -> Self {
+        let sha256 = digest.strip_prefix("sha256:").unwrap_or(digest).to_owned();
+        Self {
+            name: name.into(),
+            digest: Digest { sha256 },
+        }
+    }
+}
+
+/// Wraps an in-toto statement in a DSSE envelope, signed with `key` if given.
+pub fn envelope(statement: &Value, key: Option<&AppSigningKey>) -> Result<Vec<u8>> {
+    let payload = serde_json::to_vec(statement)?;
+    let signatures = key
+        .map(|key| {
+            let sig = key.sign_envelope_payload(IN_TOTO_PAYLOAD_TYPE, &payload);
+            json!({ "keyid": "", "sig": sig })
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+    let envelope = json!({
+        "payloadType": IN_TOTO_PAYLOAD_TYPE,
+        "payload": base64::engine::general_purpose::STANDARD.encode(payload),
+        "signatures": signatures,
+    });
+    Ok(serde_json::to_vec(&envelope)?)
+}
+
+impl ComponentInventory {
+    fn new(component: &LockedComponent, app_dir: &Path) -> Result<Self> {
+        let (source, wasm) = wasm_artifact(&component.source.content, app_dir)?;
+        let producers = producers(&wasm).context("failed to read the Wasm producers section")?;
+        let dependencies = component
+            .dependencies
+            .iter()
+            .map(|(name, dep)| {
+                let (artifact, _) = wasm_artifact(&dep.source.content, app_dir)?;
+                Ok((name.to_string(), artifact))
+            })
+            .collect::<Result<_>>()?;
+        let mut files = vec![];
+        for file in &component.files {
+            add_files(&mut files, &file.content, &file.path)?;
+        }
+        Ok(Self {
+            id: component.id.clone(),
+            source,
+            producers,
+            dependencies,
+            files,
+        })
+    }
+}
+
+impl Artifact {
+    fn hashes(&self) -> Value {
+        json!([{ "alg": "SHA-256", "content": self.sha256 }])
+    }
+}
+
+/// Reads Wasm content, returning it with its artifact.
+fn wasm_artifact(content: &ContentRef, app_dir: &Path) -> Result<(Artifact, Vec<u8>)> {
+    let (name, wasm) = match (&content.source, &content.inline) {
+        (Some(source), _) => {
+            let path = parse_file_url(source)?;
+            let wasm = std::fs::read(&path)
+                .with_context(|| format!("failed to read {}", quoted_path(&path)))?;
+            let name = match path.strip_prefix(app_dir) {
+                Ok(rel) => portable_name(rel),
+                Err(_) => spin_common::url::remove_credentials(source)?,
+            };
+            (name, wasm)
+        }
+        (None, Some(inline)) => ("inline".to_owned(), inline.clone()),
+        (None, None) => anyhow::bail!("content has no source"),
+    };
+    let sha256 = sha256::hex_digest_from_bytes(&wasm);
+    Ok((Artifact { name, sha256 }, wasm))
+}
+
+/// Adds the files of mounted content, named by their mount paths.
+fn add_files(files: &mut Vec<Artifact>, content: &ContentRef, mount: &Path) -> Result<()> {
+    let Some(source) = &content.source else {
+        let sha256 = sha256::hex_digest_from_bytes(content.inline.as_deref().unwrap_or_default());
+        files.push(Artifact {
+            name: portable_name(mount),
+            sha256,
+        });
+        return Ok(());
+    };
+    // Remote files are synced when the app runs, so aren't part of it.
+    if spin_loader::is_remote_files_source(source) {
+        return Ok(());
+    }
+    let path = parse_file_url(source)?;
+    for entry in walkdir::WalkDir::new(&path)
+        .follow_links(true)
+        .sort_by_file_name()
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(&path)?;
+        let sha256 = sha256::hex_digest_from_file(entry.path())
+            .with_context(|| format!("failed to digest {}", quoted_path(entry.path())))?;
+        let mounted: PathBuf = if rel.as_os_str().is_empty() {
+            mount.to_owned()
+        } else {
+            mount.join(rel)
+        };
+        files.push(Artifact {
+            name: portable_name(&mounted),
+            sha256,
+        });
+    }
+    Ok(())
+}
+
+fn portable_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Reads the `producers` sections of a module or component, including
+/// those of nested modules.
+fn producers(wasm: &[u8]) -> Result<BTreeSet<Producer>> {
+    let mut producers = BTreeSet::new();
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        let wasmparser::Payload::CustomSection(section) = payload? else {
+            continue;
+        };
+        let wasmparser::KnownCustom::Producers(reader) = section.as_known() else {
+            continue;
+        };
+        for field in reader {
+            let field = field?;
+            for value in field.values {
+                let value = value?;
+                producers.insert(Producer {
+                    field: field.name.to_owned(),
+                    name: value.name.to_owned(),
+                    version: value.version.to_owned(),
+                });
+            }
+        }
+    }
+    Ok(producers)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::*;
+
+    fn module_built_with_sdk() -> Vec<u8> {
+        let mut sdk = wasm_encoder::ProducersField::new();
+        sdk.value("spin-sdk", "3.1.0");
+        let mut language = wasm_encoder::ProducersField::new();
+        language.value("Rust", "1.84.0");
+        let mut producers = wasm_encoder::ProducersSection::new();
+        producers.field("language", &language);
+        producers.field("sdk", &sdk);
+        let mut module = wasm_encoder::Module::new();
+        module.section(&producers);
+        module.finish()
+    }
+
+    fn test_app(dir: &Path) -> LockedApp {
+        let wasm_path = dir.join("app.wasm");
+        std::fs::write(&wasm_path, module_built_with_sdk()).unwrap();
+        let assets = dir.join("assets");
+        std::fs::create_dir_all(assets.join("css")).unwrap();
+        std::fs::write(assets.join("css/site.css"), "body {}").unwrap();
+
+        let url = |p: &Path| Url::from_file_path(p).unwrap().to_string();
+        LockedApp::from_json(
+            json!({
+                "spin_lock_version": 1,
+                "metadata": { "name": "shop", "version": "1.2.0" },
+                "triggers": [],
+                "components": [{
+                    "id": "web",
+                    "source": { "content_type": "application/wasm", "source": url(&wasm_path) },
+                    "files": [
+                        { "source": url(&assets), "path": "/static" },
+                        { "inline": "aGk=", "path": "/inline.txt" },
+                    ],
+                }],
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sbom_lists_sdks_and_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let inventory = AppInventory::from_locked_app(&test_app(dir.path()), dir.path()).unwrap();
+        let sbom = inventory.cyclonedx("3.2.0");
+
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        assert_eq!(sbom["metadata"]["component"]["name"], "shop");
+        assert_eq!(sbom["metadata"]["component"]["version"], "1.2.0");
+        let web = &sbom["components"][0];
+        assert_eq!(web["name"], "web");
+        assert_eq!(web["properties"][0]["value"], "app.wasm");
+        assert_eq!(
+            web["hashes"][0]["content"],
+            sha256::hex_digest_from_bytes(module_built_with_sdk())
+        );
+        let children = web["components"].as_array().unwrap();
+        let names: Vec<_> = children
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            ["Rust", "spin-sdk", "/static/css/site.css", "/inline.txt"]
+        );
+        assert_eq!(children[1]["type"], "framework");
+        assert_eq!(children[1]["version"], "3.1.0");
+        assert_eq!(
+            children[3]["hashes"][0]["content"],
+            sha256::hex_digest_from_bytes(b"hi")
+        );
+    }
+
+    #[test]
+    fn provenance_names_subjects_and_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let inventory = AppInventory::from_locked_app(&test_app(dir.path()), dir.path()).unwrap();
+        let subjects = [Subject::new("example.com/shop", "sha256:abc123")];
+        let statement = inventory.provenance(&subjects, "3.2.0", Utc::now());
+
+        assert_eq!(statement["predicateType"], "https://slsa.dev/provenance/v1");
+        assert_eq!(statement["subject"][0]["digest"]["sha256"], "abc123");
+        let predicate = &statement["predicate"];
+        assert_eq!(
+            predicate["buildDefinition"]["externalParameters"]["components"][0]["producers"][1],
+            "spin-sdk 3.1.0"
+        );
+        // The Wasm and both assets
+        assert_eq!(
+            predicate["buildDefinition"]["resolvedDependencies"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            predicate["runDetails"]["builder"]["version"]["spin"],
+            "3.2.0"
+        );
+    }
+
+    #[test]
+    fn envelopes_are_signed_with_the_key() {
+        let statement = json!({ "_type": "https://in-toto.io/Statement/v1" });
+        let unsigned: Value = serde_json::from_slice(&envelope(&statement, None).unwrap()).unwrap();
+        assert_eq!(unsigned["payloadType"], IN_TOTO_PAYLOAD_TYPE);
+        assert!(unsigned["signatures"].as_array().unwrap().is_empty());
+        let payload = base64::engine::general_purpose::STANDARD
+            .decode(unsigned["payload"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&payload).unwrap(),
+            statement
+        );
+    }
+
+    #[test]
+    fn attachment_tags_are_derived_from_digest() {
+        assert_eq!(sbom_tag("sha256:0123"), "sha256-0123.sbom");
+        assert_eq!(attestation_tag("sha256:0123"), "sha256-0123.att");
+    }
+}
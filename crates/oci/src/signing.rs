@@ -0,0 +1,290 @@
+//! Signing and verification of pushed applications, in the format used by
+//! cosign for key-based signatures.
+//!
+//! A signature is stored as a separate image in the application's repository,
+//! tagged `sha256-<digest>.sig`. Its layer is a "simple signing" payload
+//! naming the signed manifest digest, and the layer's annotations carry the
+//! base64 ECDSA P-256 signature of that payload. Signatures made by Spin can
+//! be checked with `cosign verify --key`, and Spin can verify signatures made
+//! by `cosign sign --key`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::Engine;
+use p256::{
+    ecdsa::{
+        signature::{Signer, Verifier},
+        Signature, SigningKey, VerifyingKey,
+    },
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+};
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// Media type of a signature layer.
+pub const SIMPLE_SIGNING_MEDIA_TYPE: &str = "application/vnd.dev.cosign.simplesigning.v1+json";
+
+/// The signature layer annotation holding the base64 signature.
+pub const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+
+/// The `critical.type` of a simple signing payload.
+const SIGNATURE_TYPE: &str = "cosign container image signature";
+
+/// A private key for signing applications.
+#[derive(Clone)]
+pub struct AppSigningKey(SigningKey);
+
+impl AppSigningKey {
+    /// Reads an unencrypted PKCS#8 PEM ECDSA P-256 private key.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read signing key {}", quoted_path(path)))?;
+        let key = SigningKey::from_pkcs8_pem(&pem).with_context(|| {
+            format!(
+                "Signing key {} is not an unencrypted PKCS#8 PEM ECDSA P-256 private key",
+                quoted_path(path)
+            )
+        })?;
+        Ok(Self(key))
+    }
+
+    /// Returns the payload and base64 signature for the given manifest.
+    pub fn sign(
+        &self,
+        repository: &str,
+        manifest_digest: &str,
+        annotations: BTreeMap<String, String>,
+    ) -> Result<(Vec<u8>, String)> {
+        let payload = SimpleSigningPayload::new(repository, manifest_digest, annotations);
+        let payload = serde_json::to_vec(&payload)?;
+        let signature: Signature = self.0.sign(&payload);
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(signature.to_der().as_bytes());
+        Ok((payload, signature))
+    }
+
+    /// Returns the base64 signature of a DSSE envelope's payload, such as an
+    /// in-toto attestation.
+    pub fn sign_envelope_payload(&self, payload_type: &str, payload: &[u8]) -> String {
+        let signature: Signature = self
+            .0
+            .sign(&pre_authentication_encoding(payload_type, payload));
+        base64::engine::general_purpose::STANDARD.encode(signature.to_der().as_bytes())
+    }
+}
+
+/// The DSSE pre-authentication encoding of a payload, which is what is
+/// actually signed.
+fn pre_authentication_encoding(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+/// What a pulled application's signatures must satisfy for it to be loaded.
+#[derive(Clone, Debug, Default)]
+pub struct VerificationPolicy {
+    /// The application must be signed by one of these keys.
+    pub keys: Vec<VerifyingKey>,
+    /// The signature payload must carry these annotations.
+    pub required_annotations: BTreeMap<String, String>,
+}
+
+impl VerificationPolicy {
+    /// Creates a policy from PEM public key files, such as `cosign.pub`.
+    pub fn from_key_files(
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+        required_annotations: BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let keys = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                let pem = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read verification key {}", quoted_path(path))
+                })?;
+                VerifyingKey::from_public_key_pem(&pem).with_context(|| {
+                    format!(
+                        "Verification key {} is not a PEM ECDSA P-256 public key",
+                        quoted_path(path)
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            !keys.is_empty(),
+            "At least one verification key is required"
+        );
+        Ok(Self {
+            keys,
+            required_annotations,
+        })
+    }
+
+    /// Checks a single signature of the given manifest digest.
+    pub fn verify(&self, manifest_digest: &str, payload: &[u8], signature: &str) -> Result<()> {
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(signature)
+            .context("signature is not valid base64")?;
+        let signature = Signature::from_der(&signature).context("signature is malformed")?;
+        ensure!(
+            self.keys
+                .iter()
+                .any(|key| key.verify(payload, &signature).is_ok()),
+            "signature was not made by a trusted key"
+        );
+
+        let payload: SimpleSigningPayload =
+            serde_json::from_slice(payload).context("signature payload is malformed")?;
+        ensure!(
+            payload.critical.signature_type == SIGNATURE_TYPE,
+            "signature payload has unexpected type {:?}",
+            payload.critical.signature_type
+        );
+        ensure!(
+            payload.critical.image.docker_manifest_digest == manifest_digest,
+            "signature is for {}, not {manifest_digest}",
+            payload.critical.image.docker_manifest_digest
+        );
+        let annotations = payload.optional.unwrap_or_default();
+        for (key, value) in &self.required_annotations {
+            match annotations.get(key) {
+                Some(actual) if actual == value => {}
+                Some(actual) => bail!("signature annotation {key:?} is {actual:?}, not {value:?}"),
+                None => bail!("signature is missing required annotation {key:?}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tag under which the signature of the given manifest digest is stored.
+pub fn signature_tag(manifest_digest: &str) -> String {
+    format!("{}.sig", manifest_digest.replace(':', "-"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SimpleSigningPayload {
+    critical: Critical,
+    optional: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Critical {
+    identity: Identity,
+    image: Image,
+    #[serde(rename = "type")]
+    signature_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Identity {
+    #[serde(rename = "docker-reference")]
+    docker_reference: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Image {
+    #[serde(rename = "docker-manifest-digest")]
+    docker_manifest_digest: String,
+}
+
+impl SimpleSigningPayload {
+    fn new(repository: &str, manifest_digest: &str, annotations: BTreeMap<String, String>) -> Self {
+        Self {
+            critical: Critical {
+                identity: Identity {
+                    docker_reference: repository.to_owned(),
+                },
+                image: Image {
+                    docker_manifest_digest: manifest_digest.to_owned(),
+                },
+                signature_type: SIGNATURE_TYPE.to_owned(),
+            },
+            optional: (!annotations.is_empty()).then_some(annotations),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:0123456789abcdef";
+
+    fn keys() -> (AppSigningKey, VerifyingKey) {
+        let key = SigningKey::from_slice(&[7; 32]).unwrap();
+        let verifying_key = *key.verifying_key();
+        (AppSigningKey(key), verifying_key)
+    }
+
+    fn policy(key: VerifyingKey, required: &[(&str, &str)]) -> VerificationPolicy {
+        VerificationPolicy {
+            keys: vec![key],
+            required_annotations: required
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn signature_is_verified() {
+        let (signing_key, verifying_key) = keys();
+        let annotations = [("env".to_owned(), "prod".to_owned())].into();
+        let (payload, signature) = signing_key
+            .sign("ghcr.io/example/app", DIGEST, annotations)
+            .unwrap();
+
+        policy(verifying_key, &[("env", "prod")])
+            .verify(DIGEST, &payload, &signature)
+            .unwrap();
+
+        let err = policy(verifying_key, &[("env", "staging")])
+            .verify(DIGEST, &payload, &signature)
+            .unwrap_err();
+        assert!(err.to_string().contains("env"), "{err}");
+    }
+
+    #[test]
+    fn signature_for_other_digest_is_rejected() {
+        let (signing_key, verifying_key) = keys();
+        let (payload, signature) = signing_key
+            .sign("ghcr.io/example/app", DIGEST, Default::default())
+            .unwrap();
+        let err = policy(verifying_key, &[])
+            .verify("sha256:fedcba", &payload, &signature)
+            .unwrap_err();
+        assert!(err.to_string().contains("not sha256:fedcba"), "{err}");
+    }
+
+    #[test]
+    fn untrusted_or_tampered_signature_is_rejected() {
+        let (signing_key, _) = keys();
+        let other_key = *SigningKey::from_slice(&[9; 32]).unwrap().verifying_key();
+        let (mut payload, signature) = signing_key
+            .sign("ghcr.io/example/app", DIGEST, Default::default())
+            .unwrap();
+        assert!(policy(other_key, &[])
+            .verify(DIGEST, &payload, &signature)
+            .is_err());
+
+        let (_, verifying_key) = keys();
+        payload.push(b' ');
+        assert!(policy(verifying_key, &[])
+            .verify(DIGEST, &payload, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn signature_tag_is_derived_from_digest() {
+        assert_eq!(signature_tag(DIGEST), "sha256-0123456789abcdef.sig");
+    }
+}
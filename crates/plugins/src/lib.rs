@@ -5,6 +5,7 @@ pub mod lookup;
 pub mod manager;
 pub mod manifest;
 mod store;
+pub mod verification;
 pub use store::PluginStore;
 
 /// List of Spin internal subcommands
@@ -24,5 +25,6 @@ pub(crate) const SPIN_INTERNAL_COMMANDS: &[&str] = &[
     "doctor",
     "registry",
     "watch",
+    "logs",
     "oci",
 ];
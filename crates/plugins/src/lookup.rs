@@ -1,8 +1,9 @@
 use crate::{error::*, git::GitSource, manifest::PluginManifest, store::manifest_file_name};
-use semver::Version;
+use semver::{Version, VersionReq};
 use std::{
     fs::File,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 use url::Url;
 
@@ -15,10 +16,31 @@ pub(crate) const PLUGINS_REPO_MANIFESTS_DIRECTORY: &str = "manifests";
 
 pub(crate) const SPIN_PLUGINS_REPO: &str = "https://github.com/spinframework/spin-plugins/";
 
+/// The version of a plugin to install: either an exact version, or a range
+/// such as `^1.2` or `>=0.4, <0.6` which resolves to the highest matching
+/// version in the plugins repository.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VersionConstraint {
+    Exact(Version),
+    Range(VersionReq),
+}
+
+impl FromStr for VersionConstraint {
+    type Err = semver::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Version::parse(s) {
+            Ok(version) => Ok(Self::Exact(version)),
+            Err(_) => VersionReq::parse(s).map(Self::Range),
+        }
+    }
+}
+
 /// Looks up plugin manifests in centralized spin plugin repository.
 pub struct PluginLookup {
     pub name: String,
     pub version: Option<Version>,
+    pub range: Option<VersionReq>,
 }
 
 impl PluginLookup {
@@ -26,6 +48,18 @@ impl PluginLookup {
         Self {
             name: name.to_lowercase(),
             version,
+            range: None,
+        }
+    }
+
+    pub fn with_constraint(name: &str, constraint: Option<VersionConstraint>) -> Self {
+        match constraint {
+            Some(VersionConstraint::Range(range)) => Self {
+                range: Some(range),
+                ..Self::new(name, None)
+            },
+            Some(VersionConstraint::Exact(version)) => Self::new(name, Some(version)),
+            None => Self::new(name, None),
         }
     }
 
@@ -35,6 +69,17 @@ impl PluginLookup {
         skip_compatibility_check: bool,
         spin_version: &str,
     ) -> PluginLookupResult<PluginManifest> {
+        if let Some(range) = &self.range {
+            return self
+                .resolve_manifest_in_range(
+                    range,
+                    plugins_dir,
+                    skip_compatibility_check,
+                    spin_version,
+                )
+                .await;
+        }
+
         let exact = self.resolve_manifest_exact(plugins_dir).await?;
         if skip_compatibility_check
             || self.version.is_some()
@@ -56,6 +101,48 @@ impl PluginLookup {
         Ok(highest_compatible_manifest.unwrap_or(exact))
     }
 
+    /// Resolves the highest version of the plugin in the repository which
+    /// matches the range, skipping versions incompatible with this Spin and
+    /// platform unless the compatibility check is skipped.
+    async fn resolve_manifest_in_range(
+        &self,
+        range: &VersionReq,
+        plugins_dir: &Path,
+        skip_compatibility_check: bool,
+        spin_version: &str,
+    ) -> PluginLookupResult<PluginManifest> {
+        let url = plugins_repo_url()?;
+        tracing::info!("Resolving plugin {} matching {range} from {url}", self.name);
+        fetch_plugins_repo(&url, plugins_dir, false)
+            .await
+            .map_err(|e| {
+                Error::ConnectionFailed(ConnectionFailedError::new(url.to_string(), e.to_string()))
+            })?;
+
+        let store = crate::store::PluginStore::new(plugins_dir.to_owned());
+        let highest_matching = store
+            .catalogue_manifests()?
+            .into_iter()
+            .filter(|m| m.name() == self.name)
+            .filter(|m| m.try_version().is_ok_and(|v| range.matches(&v)))
+            .filter(|m| {
+                skip_compatibility_check
+                    || (m.has_compatible_package() && m.is_compatible_spin_version(spin_version))
+            })
+            .max_by_key(|m| m.try_version().unwrap_or_else(|_| null_version()));
+
+        highest_matching.ok_or_else(|| {
+            Error::NotFound(NotFoundError::new(
+                Some(self.name.clone()),
+                spin_plugins_repo_manifest_dir(plugins_dir)
+                    .join(&self.name)
+                    .display()
+                    .to_string(),
+                format!("no compatible version matches {range}"),
+            ))
+        })
+    }
+
     pub async fn resolve_manifest_exact(
         &self,
         plugins_dir: &Path,
@@ -242,6 +329,46 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn if_range_given_then_highest_compatible_match() -> PluginLookupResult<()> {
+        let range = "^99".parse().unwrap();
+        let lookup = PluginLookup::with_constraint(TEST_NAME, Some(range));
+        let resolved = lookup
+            .resolve_manifest(&tests_store_dir(), false, "99.0.0")
+            .await?;
+        assert_eq!("99.0.1", resolved.version);
+
+        let range = ">=98, <99".parse().unwrap();
+        let lookup = PluginLookup::with_constraint(TEST_NAME, Some(range));
+        let resolved = lookup
+            .resolve_manifest(&tests_store_dir(), false, "99.0.0")
+            .await?;
+        assert_eq!("98.0.0", resolved.version);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn if_range_matches_nothing_then_error() {
+        let range = "^100".parse().unwrap();
+        let lookup = PluginLookup::with_constraint(TEST_NAME, Some(range));
+        lookup
+            .resolve_manifest(&tests_store_dir(), true, "99.0.0")
+            .await
+            .expect_err("Should have errored because no version matches ^100");
+    }
+
+    #[test]
+    fn exact_versions_are_not_ranges() {
+        assert_eq!(
+            VersionConstraint::Exact(Version::new(1, 2, 3)),
+            "1.2.3".parse().unwrap()
+        );
+        assert!(matches!(
+            "1.2".parse().unwrap(),
+            VersionConstraint::Range(_)
+        ));
+    }
+
     #[tokio::test]
     async fn if_non_existent_version_given_then_error() -> PluginLookupResult<()> {
         let lookup = PluginLookup::new(TEST_NAME, Some(semver::Version::parse("177.7.7").unwrap()));
@@ -2,7 +2,8 @@ use crate::{
     error::*,
     lookup::PluginLookup,
     manifest::{warn_unsupported_version, PluginManifest, PluginPackage},
-    store::PluginStore,
+    store::{manifest_file_name, PluginStore},
+    verification::TrustedKeys,
     SPIN_INTERNAL_COMMANDS,
 };
 
@@ -118,10 +119,31 @@ impl PluginManager {
                 .await?
             }
         };
-        verify_checksum(&plugin_tarball_path, &plugin_package.sha256)?;
+        self.install_from_file(
+            plugin_manifest,
+            plugin_package,
+            &plugin_tarball_path,
+            source,
+        )
+    }
+
+    /// Installs a plugin from a package tarball which is already on disk, for
+    /// example to install plugins without network access. The tarball must
+    /// match the checksum, and signature if required, of the given package.
+    /// Returns name of plugin that was successfully installed.
+    pub fn install_from_file(
+        &self,
+        plugin_manifest: &PluginManifest,
+        plugin_package: &PluginPackage,
+        plugin_tarball_path: &Path,
+        source: &ManifestLocation,
+    ) -> Result<String> {
+        verify_checksum(plugin_tarball_path, &plugin_package.sha256)?;
+        TrustedKeys::load(&self.store.trusted_keys_directory())?
+            .verify(plugin_tarball_path, plugin_package.signature.as_deref())?;
 
         self.store
-            .untar_plugin(&plugin_tarball_path, &plugin_manifest.name())
+            .untar_plugin(plugin_tarball_path, &plugin_manifest.name())
             .with_context(|| format!("Failed to untar {}", plugin_tarball_path.display()))?;
 
         // Save manifest to installed plugins directory
@@ -131,6 +153,47 @@ impl PluginManager {
         Ok(plugin_manifest.name())
     }
 
+    /// Copies an installed plugin so that it can be restored if upgrading it
+    /// fails.
+    pub fn back_up(&self, plugin_name: &str) -> Result<PluginBackup> {
+        let plugins_dir = self.store.get_plugins_directory();
+        let dir = tempfile::Builder::new()
+            .prefix(".backup-")
+            .tempdir_in(plugins_dir)
+            .context("Failed to create plugin backup directory")?;
+        copy_dir_all(
+            &self.store.plugin_subdirectory_path(plugin_name),
+            &dir.path().join(plugin_name),
+        )
+        .with_context(|| format!("Failed to back up plugin '{plugin_name}'"))?;
+        fs::copy(
+            self.store.installed_manifest_path(plugin_name),
+            dir.path().join(manifest_file_name(plugin_name)),
+        )
+        .with_context(|| format!("Failed to back up manifest of plugin '{plugin_name}'"))?;
+        Ok(PluginBackup {
+            name: plugin_name.to_owned(),
+            dir,
+        })
+    }
+
+    /// Restores a plugin to the state it was backed up in.
+    pub fn restore(&self, backup: PluginBackup) -> Result<()> {
+        let name = &backup.name;
+        let plugin_dir = self.store.plugin_subdirectory_path(name);
+        if plugin_dir.exists() {
+            fs::remove_dir_all(&plugin_dir)?;
+        }
+        fs::rename(backup.dir.path().join(name), &plugin_dir)
+            .with_context(|| format!("Failed to restore plugin '{name}'"))?;
+        fs::copy(
+            backup.dir.path().join(manifest_file_name(name)),
+            self.store.installed_manifest_path(name),
+        )
+        .with_context(|| format!("Failed to restore manifest of plugin '{name}'"))?;
+        Ok(())
+    }
+
     /// Uninstalls a plugin with a given name, removing it and it's manifest from the local plugins
     /// directory.
     /// Returns true if plugin was successfully uninstalled and false if plugin did not exist.
@@ -332,6 +395,19 @@ impl PluginManagerUpdateGuard<'_> {
     }
 }
 
+/// A copy of an installed plugin, taken before upgrading it. The copy is
+/// deleted when this is dropped.
+pub struct PluginBackup {
+    name: String,
+    dir: TempDir,
+}
+
+impl PluginBackup {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 /// The action required to install a plugin to the desired version.
 pub enum InstallAction {
     /// The installation needs to continue.
@@ -382,9 +458,14 @@ async fn download_plugin(
 }
 
 fn verify_checksum(plugin_file: &Path, expected_sha256: &str) -> Result<()> {
+    let is_sha256 =
+        expected_sha256.len() == 64 && expected_sha256.chars().all(|c| c.is_ascii_hexdigit());
+    if !is_sha256 {
+        bail!("Plugin package has no valid SHA-256 checksum, aborting installation.");
+    }
     let actual_sha256 = sha256::hex_digest_from_file(plugin_file)
         .with_context(|| format!("Cannot get digest for {}", plugin_file.display()))?;
-    if actual_sha256 == expected_sha256 {
+    if actual_sha256.eq_ignore_ascii_case(expected_sha256) {
         tracing::info!("Package checksum verified successfully");
         Ok(())
     } else {
@@ -392,6 +473,20 @@ fn verify_checksum(plugin_file: &Path, expected_sha256: &str) -> Result<()> {
     }
 }
 
+fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
 /// Get the request headers for a call to the plugin API
 ///
 /// If set, this will include the user provided authorization header.
@@ -436,4 +531,51 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn malformed_checksums_are_rejected() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let package = temp_dir.path().join("plugin.tar.gz");
+        fs::write(&package, b"plugin")?;
+        let digest = sha256::hex_digest_from_file(&package)?;
+
+        verify_checksum(&package, &digest)?;
+        verify_checksum(&package, &digest.to_uppercase())?;
+        assert!(verify_checksum(&package, "").is_err());
+        assert!(verify_checksum(&package, "11111111").is_err());
+        assert!(verify_checksum(&package, &"0".repeat(64)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn backups_can_be_restored() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let store = PluginStore::new(temp_dir.path());
+        fs::create_dir_all(store.plugin_subdirectory_path("example").join("lib"))?;
+        fs::create_dir_all(store.installed_manifests_directory())?;
+        let binary = store.installed_binary_path("example");
+        fs::write(&binary, "v1")?;
+        fs::write(store.installed_manifest_path("example"), "manifest v1")?;
+        let manager = PluginManager { store };
+
+        let backup = manager.back_up("example")?;
+        fs::write(&binary, "v2")?;
+        fs::write(
+            manager.store.installed_manifest_path("example"),
+            "manifest v2",
+        )?;
+        manager.restore(backup)?;
+
+        assert_eq!("v1", fs::read_to_string(&binary)?);
+        assert_eq!(
+            "manifest v1",
+            fs::read_to_string(manager.store.installed_manifest_path("example"))?
+        );
+        assert!(manager
+            .store
+            .plugin_subdirectory_path("example")
+            .join("lib")
+            .is_dir());
+        Ok(())
+    }
 }
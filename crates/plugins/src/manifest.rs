@@ -93,6 +93,9 @@ pub struct PluginPackage {
     pub(crate) url: String,
     /// Checksum to verify the plugin before installation.
     pub(crate) sha256: String,
+    /// Base64 signature of the package, checked against trusted keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) signature: Option<String>,
 }
 
 impl PluginPackage {
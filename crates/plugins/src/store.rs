@@ -13,6 +13,8 @@ use crate::{error::*, manifest::PluginManifest};
 /// Directory where the manifests of installed plugins are stored.
 pub const PLUGIN_MANIFESTS_DIRECTORY_NAME: &str = "manifests";
 const INSTALLATION_RECORD_FILE_NAME: &str = ".install.json";
+/// Directory of public keys trusted to sign plugin packages.
+const TRUSTED_KEYS_DIRECTORY_NAME: &str = "trusted-keys";
 
 /// Houses utilities for getting the path to Spin plugin directories.
 pub struct PluginStore {
@@ -44,6 +46,12 @@ impl PluginStore {
         self.root.join(PLUGIN_MANIFESTS_DIRECTORY_NAME)
     }
 
+    /// Get the path to the directory of public keys trusted to sign plugin
+    /// packages.
+    pub fn trusted_keys_directory(&self) -> PathBuf {
+        self.root.join(TRUSTED_KEYS_DIRECTORY_NAME)
+    }
+
     pub fn installed_manifest_path(&self, plugin_name: &str) -> PathBuf {
         self.installed_manifests_directory()
             .join(manifest_file_name(plugin_name))
@@ -154,7 +162,7 @@ impl PluginStore {
         Ok(())
     }
 
-    pub(crate) fn untar_plugin(&self, plugin_file_name: &PathBuf, plugin_name: &str) -> Result<()> {
+    pub(crate) fn untar_plugin(&self, plugin_file_name: &Path, plugin_name: &str) -> Result<()> {
         // Get handle to file
         let tar_gz = File::open(plugin_file_name)?;
         // Unzip file
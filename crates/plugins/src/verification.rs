@@ -0,0 +1,116 @@
+//! Verification of plugin package signatures.
+//!
+//! A package may carry the base64 ECDSA P-256 signature of its tarball, as
+//! made by `cosign sign-blob --key`. Publishers' PEM public keys are trusted by
+//! placing them in the `trusted-keys` subdirectory of the plugins directory;
+//! once any key is trusted, every package installed must be signed by one.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use p256::{
+    ecdsa::{signature::Verifier, Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+
+/// The public keys trusted to sign plugin packages.
+pub struct TrustedKeys(Vec<VerifyingKey>);
+
+impl TrustedKeys {
+    /// Reads the `.pem` and `.pub` key files in `dir`, which need not exist.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let Ok(entries) = dir.read_dir() else {
+            return Ok(Self(vec![]));
+        };
+        let mut keys = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            let is_key = path
+                .extension()
+                .is_some_and(|ext| ext == "pem" || ext == "pub");
+            if !path.is_file() || !is_key {
+                continue;
+            }
+            let pem = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read trusted key {}", path.display()))?;
+            let key = VerifyingKey::from_public_key_pem(&pem).with_context(|| {
+                format!(
+                    "Trusted key {} is not a PEM ECDSA P-256 public key",
+                    path.display()
+                )
+            })?;
+            keys.push(key);
+        }
+        Ok(Self(keys))
+    }
+
+    /// Checks that the package tarball was signed by a trusted key. Packages
+    /// needn't be signed if no keys are trusted.
+    pub fn verify(&self, package_file: &Path, signature: Option<&str>) -> Result<()> {
+        if self.0.is_empty() {
+            if signature.is_some() {
+                tracing::info!("Not verifying package signature as no keys are trusted");
+            }
+            return Ok(());
+        }
+        let Some(signature) = signature else {
+            bail!("Plugin package is not signed, but signatures are required because trusted keys are configured. Aborting installation.");
+        };
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(signature)
+            .context("Package signature is not valid base64")?;
+        let signature =
+            Signature::from_der(&signature).context("Package signature is malformed")?;
+        let content = std::fs::read(package_file)
+            .with_context(|| format!("Cannot read {}", package_file.display()))?;
+        if !self
+            .0
+            .iter()
+            .any(|key| key.verify(&content, &signature).is_ok())
+        {
+            bail!("Plugin package was not signed by a trusted key, aborting installation.");
+        }
+        tracing::info!("Package signature verified successfully");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::{
+        ecdsa::{signature::Signer, SigningKey},
+        pkcs8::{EncodePublicKey, LineEnding},
+    };
+
+    use super::*;
+
+    fn sign(key: &SigningKey, content: &[u8]) -> String {
+        let signature: Signature = key.sign(content);
+        base64::engine::general_purpose::STANDARD.encode(signature.to_der().as_bytes())
+    }
+
+    #[test]
+    fn signatures_are_required_once_keys_are_trusted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let package = dir.path().join("plugin.tar.gz");
+        std::fs::write(&package, b"not really a tarball")?;
+        let key = SigningKey::from_slice(&[7; 32]).unwrap();
+        let signature = sign(&key, b"not really a tarball");
+
+        let keys_dir = dir.path().join("trusted-keys");
+        TrustedKeys::load(&keys_dir)?.verify(&package, None)?;
+
+        std::fs::create_dir(&keys_dir)?;
+        let pem = key.verifying_key().to_public_key_pem(LineEnding::LF)?;
+        std::fs::write(keys_dir.join("publisher.pub"), pem)?;
+        let trusted = TrustedKeys::load(&keys_dir)?;
+        trusted.verify(&package, Some(&signature))?;
+        assert!(trusted.verify(&package, None).is_err());
+
+        let other_key = SigningKey::from_slice(&[9; 32]).unwrap();
+        let other_signature = sign(&other_key, b"not really a tarball");
+        assert!(trusted.verify(&package, Some(&other_signature)).is_err());
+        Ok(())
+    }
+}
@@ -1,27 +1,70 @@
+pub mod overrides;
+pub mod schema;
+pub mod test_isolation;
+
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Context as _;
 use spin_common::ui::quoted_path;
+use spin_factor_audit::AuditFactor;
+use spin_factor_auth::AuthFactor;
+use spin_factor_background_tasks::BackgroundTasksFactor;
+use spin_factor_blobstore::runtime_config::spin::{self as blobstore};
+use spin_factor_blobstore::BlobStoreFactor;
+use spin_factor_cache::CacheFactor;
+use spin_factor_component_metadata::ComponentMetadataFactor;
+use spin_factor_crypto::CryptoFactor;
+use spin_factor_entities::EntitiesFactor;
+use spin_factor_fault_injection::FaultInjectionFactor;
+use spin_factor_feature_flags::FeatureFlagsFactor;
+use spin_factor_host_plugins::HostPluginsFactor;
+use spin_factor_i18n::I18nFactor;
+use spin_factor_ids::IdsFactor;
+use spin_factor_image::ImageFactor;
 use spin_factor_key_value::runtime_config::spin::{self as key_value};
 use spin_factor_key_value::KeyValueFactor;
+use spin_factor_leader_election::LeaderElectionFactor;
 use spin_factor_llm::{spin as llm, LlmFactor};
+use spin_factor_logging::LoggingFactor;
+use spin_factor_messaging::runtime_config::spin::{self as messaging};
+use spin_factor_messaging::MessagingFactor;
+use spin_factor_metrics::MetricsFactor;
+use spin_factor_outbound_amqp::OutboundAmqpFactor;
+use spin_factor_outbound_file_transfer::OutboundFileTransferFactor;
 use spin_factor_outbound_http::OutboundHttpFactor;
+use spin_factor_outbound_ldap::OutboundLdapFactor;
 use spin_factor_outbound_mqtt::OutboundMqttFactor;
 use spin_factor_outbound_mysql::OutboundMysqlFactor;
 use spin_factor_outbound_networking::runtime_config::spin::SpinRuntimeConfig as OutboundNetworkingSpinRuntimeConfig;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_outbound_pg::OutboundPgFactor;
 use spin_factor_outbound_redis::OutboundRedisFactor;
+use spin_factor_outbound_smtp::OutboundSmtpFactor;
+use spin_factor_outbound_websocket::OutboundWebSocketFactor;
+use spin_factor_policy::PolicyFactor;
+use spin_factor_rate_limit::RateLimitFactor;
+use spin_factor_request_context::RequestContextFactor;
+use spin_factor_session::SessionFactor;
 use spin_factor_sqlite::SqliteFactor;
+use spin_factor_templates::TemplatesFactor;
+use spin_factor_timers::TimersFactor;
 use spin_factor_variables::VariablesFactor;
+use spin_factor_vector_store::runtime_config::spin::{self as vector_store};
+use spin_factor_vector_store::VectorStoreFactor;
 use spin_factor_wasi::WasiFactor;
+use spin_factor_wasi_nn::WasiNnFactor;
+use spin_factor_workflows::WorkflowsFactor;
 use spin_factors::runtime_config::toml::GetTomlValue as _;
 use spin_factors::{
     runtime_config::toml::TomlKeyTracker, FactorRuntimeConfigSource, RuntimeConfigSourceFinalizer,
 };
 use spin_key_value_spin::{SpinKeyValueRuntimeConfig, SpinKeyValueStore};
 use spin_sqlite as sqlite;
-use spin_trigger::cli::UserProvidedPath;
+use spin_telemetry::sampling::{SamplingConfig, SamplingStrategy};
+use spin_trigger::cli::{
+    ComponentLimits, ComponentLimitsConfig, ExecutorConfig, ExecutorTuning, UserProvidedPath,
+};
 use toml::Value;
 
 /// The default state directory for the trigger.
@@ -35,8 +78,14 @@ pub struct ResolvedRuntimeConfig<T> {
     pub runtime_config: T,
     /// The resolver used to resolve key-value stores from runtime configuration.
     pub key_value_resolver: key_value::RuntimeConfigResolver,
+    /// The resolver used to resolve blob containers from runtime configuration.
+    pub blobstore_resolver: blobstore::RuntimeConfigResolver,
+    /// The resolver used to resolve message brokers from runtime configuration.
+    pub messaging_resolver: messaging::RuntimeConfigResolver,
     /// The resolver used to resolve sqlite databases from runtime configuration.
     pub sqlite_resolver: sqlite::RuntimeConfigResolver,
+    /// The resolver used to resolve vector stores from runtime configuration.
+    pub vector_store_resolver: vector_store::RuntimeConfigResolver,
     /// The fully resolved state directory.
     ///
     /// `None` is used for an "unset" state directory which each factor will treat differently.
@@ -47,6 +96,12 @@ pub struct ResolvedRuntimeConfig<T> {
     pub log_dir: Option<PathBuf>,
     /// The maximum memory allocation limit.
     pub max_instance_memory: Option<usize>,
+    /// Resource limits for component instances.
+    pub component_limits: ComponentLimitsConfig,
+    /// Tuning of the runtimes triggers run on.
+    pub executor: ExecutorConfig,
+    /// How traces are sampled before they are exported.
+    pub trace_sampling: SamplingConfig,
     /// The input TOML, for informational summaries.
     pub toml: toml::Table,
 }
@@ -70,6 +125,31 @@ impl<T> ResolvedRuntimeConfig<T> {
         summaries.extend(summarize_labeled_typed_tables("key_value_store"));
         // [sqlite_database.<label>: <type>]
         summaries.extend(summarize_labeled_typed_tables("sqlite_database"));
+        // [blob_container.<label>: <type>]
+        summaries.extend(summarize_labeled_typed_tables("blob_container"));
+        // [message_broker.<label>: <type>]
+        summaries.extend(summarize_labeled_typed_tables("message_broker"));
+        // [host_plugin.<label>: <type>]
+        summaries.extend(summarize_labeled_typed_tables("host_plugin"));
+        // [vector_store.<label>: <type>]
+        summaries.extend(summarize_labeled_typed_tables("vector_store"));
+        // [component_stores.<component>: <key>.<label> = <label>]
+        if let Some(components) = self.toml.get("component_stores").and_then(Value::as_table) {
+            for (component_id, stores) in components {
+                let Some(stores) = stores.as_table() else {
+                    continue;
+                };
+                for (key, labels) in stores {
+                    for (label, store_label) in labels.as_table().into_iter().flatten() {
+                        if let Some(store_label) = store_label.as_str() {
+                            summaries.push(format!(
+                                "[component_stores.{component_id}: {key}.{label} = {store_label}]"
+                            ));
+                        }
+                    }
+                }
+            }
+        }
         // [llm_compute: <type>]
         if let Some(table) = self.toml.get("llm_compute").and_then(Value::as_table) {
             if let Some(ty) = table.get("type").and_then(Value::as_str) {
@@ -94,29 +174,33 @@ where
     /// Creates a new resolved runtime configuration from a runtime config source TOML file.
     ///
     /// `provided_state_dir` is the explicitly provided state directory, if any.
-    pub fn from_file(
+    pub async fn from_file(
         runtime_config_path: Option<&Path>,
         local_app_dir: Option<PathBuf>,
         provided_state_dir: UserProvidedPath,
         provided_log_dir: UserProvidedPath,
     ) -> anyhow::Result<Self> {
-        let toml = match runtime_config_path {
-            Some(runtime_config_path) => {
-                let file = std::fs::read_to_string(runtime_config_path).with_context(|| {
-                    format!(
-                        "failed to read runtime config file '{}'",
-                        runtime_config_path.display()
-                    )
-                })?;
-                toml::from_str(&file).with_context(|| {
-                    format!(
-                        "failed to parse runtime config file '{}' as toml",
-                        runtime_config_path.display()
-                    )
-                })?
-            }
-            None => Default::default(),
-        };
+        let mut toml = read_toml_file(runtime_config_path)?;
+        spin_variables::resolve_secret_references(&mut toml).await?;
+        let toml_resolver =
+            TomlResolver::new(&toml, local_app_dir, provided_state_dir, provided_log_dir);
+
+        Self::new(toml_resolver, runtime_config_path)
+    }
+
+    /// Like [`Self::from_file`], but with the runtime config TOML adjusted
+    /// before it is resolved, e.g. to apply [`overrides`] or
+    /// [`test_isolation`].
+    pub async fn from_file_with(
+        runtime_config_path: Option<&Path>,
+        local_app_dir: Option<PathBuf>,
+        provided_state_dir: UserProvidedPath,
+        provided_log_dir: UserProvidedPath,
+        adjust: impl FnOnce(&mut toml::Table) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Self> {
+        let mut toml = read_toml_file(runtime_config_path)?;
+        adjust(&mut toml)?;
+        spin_variables::resolve_secret_references(&mut toml).await?;
         let toml_resolver =
             TomlResolver::new(&toml, local_app_dir, provided_state_dir, provided_log_dir);
 
@@ -135,19 +219,31 @@ where
         let outbound_networking = runtime_config_dir
             .clone()
             .map(OutboundNetworkingSpinRuntimeConfig::new);
-        let key_value_resolver = key_value_config_resolver(runtime_config_dir, state_dir.clone());
+        let key_value_resolver =
+            key_value_config_resolver(runtime_config_dir.clone(), state_dir.clone());
+        let blobstore_resolver = blobstore_config_resolver();
+        let messaging_resolver = messaging_config_resolver();
         let sqlite_resolver = sqlite_config_resolver(state_dir.clone())
             .context("failed to resolve sqlite runtime config")?;
+        let vector_store_resolver =
+            vector_store_config_resolver(runtime_config_dir.clone(), state_dir.clone());
 
         let toml = toml_resolver.toml();
         let log_dir = toml_resolver.log_dir()?;
         let max_instance_memory = toml_resolver.max_instance_memory()?;
+        let component_limits = toml_resolver.component_limits()?;
+        let executor = toml_resolver.executor()?;
+        let trace_sampling = toml_resolver.trace_sampling()?;
 
         let source = TomlRuntimeConfigSource::new(
             toml_resolver,
             &key_value_resolver,
+            &blobstore_resolver,
+            &messaging_resolver,
             outbound_networking.as_ref(),
             &sqlite_resolver,
+            &vector_store_resolver,
+            runtime_config_path.map(ToOwned::to_owned),
         );
 
         // Note: all valid fields in the runtime config must have been referenced at
@@ -158,10 +254,16 @@ where
         Ok(Self {
             runtime_config,
             key_value_resolver,
+            blobstore_resolver,
+            messaging_resolver,
             sqlite_resolver,
+            vector_store_resolver,
             state_dir,
             log_dir,
             max_instance_memory,
+            component_limits,
+            executor,
+            trace_sampling,
             toml,
         })
     }
@@ -180,6 +282,21 @@ where
     pub fn max_instance_memory(&self) -> Option<usize> {
         self.max_instance_memory
     }
+
+    /// Resource limits for component instances.
+    pub fn component_limits(&self) -> &ComponentLimitsConfig {
+        &self.component_limits
+    }
+
+    /// Tuning of the runtimes triggers run on.
+    pub fn executor(&self) -> &ExecutorConfig {
+        &self.executor
+    }
+
+    /// How traces are sampled before they are exported.
+    pub fn trace_sampling(&self) -> &SamplingConfig {
+        &self.trace_sampling
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -284,6 +401,95 @@ impl<'a> TomlResolver<'a> {
             .map_err(Into::into)
     }
 
+    /// Get the configured resource limits for component instances.
+    pub fn component_limits(&self) -> anyhow::Result<ComponentLimitsConfig> {
+        let Some(table) = self.table.get("component_limits") else {
+            return Ok(Default::default());
+        };
+        let config: ComponentLimitsConfigToml = table
+            .clone()
+            .try_into()
+            .context("invalid `component_limits` runtime config")?;
+        let defaults = ComponentLimitsToml {
+            max_memory: config.max_memory,
+            max_execution_time_ms: config.max_execution_time_ms,
+            max_fuel: config.max_fuel,
+            max_instances: config.max_instances,
+        };
+        Ok(ComponentLimitsConfig {
+            default: defaults.into(),
+            components: config
+                .components
+                .into_iter()
+                .map(|(id, limits)| (id, limits.into()))
+                .collect(),
+        })
+    }
+
+    /// Get the configured tuning of trigger runtimes from the `[executor]` table.
+    pub fn executor(&self) -> anyhow::Result<ExecutorConfig> {
+        let Some(table) = self.table.get("executor") else {
+            return Ok(Default::default());
+        };
+        let config: ExecutorConfigToml = table
+            .clone()
+            .try_into()
+            .context("invalid `executor` runtime config")?;
+        let defaults = ExecutorTuningToml {
+            worker_threads: config.worker_threads,
+            max_blocking_threads: config.max_blocking_threads,
+        };
+        Ok(ExecutorConfig {
+            default: defaults
+                .try_into()
+                .context("invalid `executor` runtime config")?,
+            triggers: config
+                .triggers
+                .into_iter()
+                .map(|(trigger_type, tuning)| {
+                    let tuning = tuning.try_into().with_context(|| {
+                        format!("invalid `executor.triggers.{trigger_type}` runtime config")
+                    })?;
+                    Ok((trigger_type, tuning))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// Get the configured trace sampling from the `[telemetry.sampling]` table.
+    pub fn trace_sampling(&self) -> anyhow::Result<SamplingConfig> {
+        let Some(table) = self.table.get("telemetry") else {
+            return Ok(Default::default());
+        };
+        let telemetry: TelemetryToml = table
+            .clone()
+            .try_into()
+            .context("invalid `telemetry` runtime config")?;
+        let Some(sampling) = telemetry.sampling else {
+            return Ok(Default::default());
+        };
+        let default = SamplingStrategyToml {
+            strategy: sampling.strategy,
+            ratio: sampling.ratio,
+            slow_threshold_ms: sampling.slow_threshold_ms,
+        };
+        Ok(SamplingConfig {
+            default: default
+                .try_into()
+                .context("invalid `telemetry.sampling` runtime config")?,
+            components: sampling
+                .components
+                .into_iter()
+                .map(|(id, strategy)| {
+                    let strategy = strategy.try_into().with_context(|| {
+                        format!("invalid `telemetry.sampling.components.{id}` runtime config")
+                    })?;
+                    Ok((id, strategy))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
     /// Validate that all keys in the TOML file have been used.
     pub fn validate_all_keys_used(&self) -> spin_factors::Result<()> {
         self.table.validate_all_keys_used()
@@ -294,28 +500,205 @@ impl<'a> TomlResolver<'a> {
     }
 }
 
+/// The `[component_limits]` table: default limits plus per-component overrides.
+///
+/// The default limits are listed explicitly because serde doesn't support
+/// `deny_unknown_fields` together with `flatten`.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComponentLimitsConfigToml {
+    max_memory: Option<usize>,
+    max_execution_time_ms: Option<u64>,
+    max_fuel: Option<u64>,
+    max_instances: Option<usize>,
+    #[serde(default)]
+    components: std::collections::HashMap<String, ComponentLimitsToml>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComponentLimitsToml {
+    max_memory: Option<usize>,
+    max_execution_time_ms: Option<u64>,
+    max_fuel: Option<u64>,
+    max_instances: Option<usize>,
+}
+
+impl From<ComponentLimitsToml> for ComponentLimits {
+    fn from(toml: ComponentLimitsToml) -> Self {
+        Self {
+            max_memory: toml.max_memory,
+            max_execution_time: toml.max_execution_time_ms.map(Duration::from_millis),
+            max_fuel: toml.max_fuel,
+            max_instances: toml.max_instances,
+        }
+    }
+}
+
+/// The `[executor]` table: default tuning plus per-trigger overrides.
+///
+/// The default tuning is listed explicitly because serde doesn't support
+/// `deny_unknown_fields` together with `flatten`.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecutorConfigToml {
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+    #[serde(default)]
+    triggers: std::collections::HashMap<String, ExecutorTuningToml>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecutorTuningToml {
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+}
+
+impl TryFrom<ExecutorTuningToml> for ExecutorTuning {
+    type Error = anyhow::Error;
+
+    fn try_from(toml: ExecutorTuningToml) -> anyhow::Result<Self> {
+        // Tokio panics on runtimes with no threads
+        anyhow::ensure!(
+            toml.worker_threads != Some(0),
+            "`worker_threads` must be greater than 0"
+        );
+        anyhow::ensure!(
+            toml.max_blocking_threads != Some(0),
+            "`max_blocking_threads` must be greater than 0"
+        );
+        Ok(Self {
+            worker_threads: toml.worker_threads,
+            max_blocking_threads: toml.max_blocking_threads,
+        })
+    }
+}
+
+/// The `[telemetry]` table.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TelemetryToml {
+    sampling: Option<TraceSamplingToml>,
+}
+
+/// The `[telemetry.sampling]` table: the default strategy plus per-component
+/// overrides.
+///
+/// The default strategy is listed explicitly because serde doesn't support
+/// `deny_unknown_fields` together with `flatten`.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TraceSamplingToml {
+    #[serde(default)]
+    strategy: SamplingStrategyName,
+    ratio: Option<f64>,
+    slow_threshold_ms: Option<u64>,
+    #[serde(default)]
+    components: std::collections::HashMap<String, SamplingStrategyToml>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingStrategyToml {
+    #[serde(default)]
+    strategy: SamplingStrategyName,
+    ratio: Option<f64>,
+    slow_threshold_ms: Option<u64>,
+}
+
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum SamplingStrategyName {
+    #[default]
+    Always,
+    Ratio,
+    TailKeepErrorsAndSlow,
+}
+
+impl TryFrom<SamplingStrategyToml> for SamplingStrategy {
+    type Error = anyhow::Error;
+
+    fn try_from(toml: SamplingStrategyToml) -> anyhow::Result<Self> {
+        if let Some(ratio) = toml.ratio {
+            anyhow::ensure!(
+                (0.0..=1.0).contains(&ratio),
+                "`ratio` must be between 0 and 1"
+            );
+        }
+        Ok(match toml.strategy {
+            SamplingStrategyName::Always => {
+                anyhow::ensure!(
+                    toml.ratio.is_none() && toml.slow_threshold_ms.is_none(),
+                    "the `always` strategy takes no `ratio` or `slow_threshold_ms`"
+                );
+                Self::Always
+            }
+            SamplingStrategyName::Ratio => {
+                anyhow::ensure!(
+                    toml.slow_threshold_ms.is_none(),
+                    "the `ratio` strategy takes no `slow_threshold_ms`"
+                );
+                Self::Ratio(
+                    toml.ratio
+                        .context("the `ratio` strategy requires a `ratio`")?,
+                )
+            }
+            SamplingStrategyName::TailKeepErrorsAndSlow => Self::TailKeepErrorsAndSlow {
+                slow_threshold: Duration::from_millis(toml.slow_threshold_ms.context(
+                    "the `tail-keep-errors-and-slow` strategy requires a `slow_threshold_ms`",
+                )?),
+                ratio: toml.ratio.unwrap_or(0.0),
+            },
+        })
+    }
+}
+
 /// The TOML based runtime configuration source Spin CLI.
 pub struct TomlRuntimeConfigSource<'a, 'b> {
     toml: TomlResolver<'b>,
     key_value: &'a key_value::RuntimeConfigResolver,
+    blobstore: &'a blobstore::RuntimeConfigResolver,
+    messaging: &'a messaging::RuntimeConfigResolver,
     outbound_networking: Option<&'a OutboundNetworkingSpinRuntimeConfig>,
     sqlite: &'a sqlite::RuntimeConfigResolver,
+    vector_store: &'a vector_store::RuntimeConfigResolver,
+    /// The runtime config file, if there is one.
+    runtime_config_path: Option<PathBuf>,
 }
 
 impl<'a, 'b> TomlRuntimeConfigSource<'a, 'b> {
     pub fn new(
         toml_resolver: TomlResolver<'b>,
         key_value: &'a key_value::RuntimeConfigResolver,
+        blobstore: &'a blobstore::RuntimeConfigResolver,
+        messaging: &'a messaging::RuntimeConfigResolver,
         outbound_networking: Option<&'a OutboundNetworkingSpinRuntimeConfig>,
         sqlite: &'a sqlite::RuntimeConfigResolver,
+        vector_store: &'a vector_store::RuntimeConfigResolver,
+        runtime_config_path: Option<PathBuf>,
     ) -> Self {
+        // Keys read while resolving e.g. the state dir aren't any factor's
+        toml_resolver.table.take_read_keys();
         Self {
             toml: toml_resolver,
             key_value,
+            blobstore,
+            messaging,
             outbound_networking,
             sqlite,
+            vector_store,
+            runtime_config_path,
         }
     }
+
+    /// The directory relative paths in the runtime config are resolved against.
+    fn runtime_config_dir(&self) -> &Path {
+        self.runtime_config_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new("."))
+    }
 }
 
 impl FactorRuntimeConfigSource<KeyValueFactor> for TomlRuntimeConfigSource<'_, '_> {
@@ -326,6 +709,192 @@ impl FactorRuntimeConfigSource<KeyValueFactor> for TomlRuntimeConfigSource<'_, '
     }
 }
 
+impl FactorRuntimeConfigSource<CacheFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
+        Ok(None)
+    }
+}
+
+impl FactorRuntimeConfigSource<CryptoFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_crypto::RuntimeConfig>> {
+        spin_factor_crypto::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<TemplatesFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
+        Ok(None)
+    }
+}
+
+impl FactorRuntimeConfigSource<MetricsFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
+        Ok(None)
+    }
+}
+
+impl FactorRuntimeConfigSource<EntitiesFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
+        Ok(None)
+    }
+}
+
+impl FactorRuntimeConfigSource<BackgroundTasksFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_background_tasks::RuntimeConfig>> {
+        spin_factor_background_tasks::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<TimersFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_timers::RuntimeConfig>> {
+        spin_factor_timers::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<WorkflowsFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_workflows::RuntimeConfig>> {
+        spin_factor_workflows::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<BlobStoreFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_blobstore::RuntimeConfig>> {
+        Ok(Some(self.blobstore.resolve(Some(&self.toml.table))?))
+    }
+}
+
+impl FactorRuntimeConfigSource<MessagingFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_messaging::RuntimeConfig>> {
+        Ok(Some(self.messaging.resolve(Some(&self.toml.table))?))
+    }
+}
+
+impl FactorRuntimeConfigSource<VectorStoreFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_vector_store::RuntimeConfig>> {
+        Ok(Some(self.vector_store.resolve(Some(&self.toml.table))?))
+    }
+}
+
+impl FactorRuntimeConfigSource<HostPluginsFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_host_plugins::RuntimeConfig>> {
+        spin_factor_host_plugins::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
+    }
+}
+
+impl FactorRuntimeConfigSource<ImageFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_image::RuntimeConfig>> {
+        spin_factor_image::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<IdsFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_ids::RuntimeConfig>> {
+        spin_factor_ids::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<I18nFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
+        Ok(None)
+    }
+}
+
+impl FactorRuntimeConfigSource<RequestContextFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_request_context::RuntimeConfig>> {
+        spin_factor_request_context::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<ComponentMetadataFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_component_metadata::RuntimeConfig>> {
+        spin_factor_component_metadata::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<SessionFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_session::RuntimeConfig>> {
+        spin_factor_session::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<FeatureFlagsFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_feature_flags::RuntimeConfig>> {
+        spin_factor_feature_flags::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<AuthFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_auth::RuntimeConfig>> {
+        spin_factor_auth::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<RateLimitFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_rate_limit::RuntimeConfig>> {
+        spin_factor_rate_limit::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<LeaderElectionFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_leader_election::RuntimeConfig>> {
+        spin_factor_leader_election::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<LoggingFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_logging::RuntimeConfig>> {
+        spin_factor_logging::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<FaultInjectionFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_fault_injection::RuntimeConfig>> {
+        spin_factor_fault_injection::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<PolicyFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_policy::RuntimeConfig>> {
+        spin_factor_policy::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<AuditFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_audit::RuntimeConfig>> {
+        spin_factor_audit::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
+    }
+}
+
 impl FactorRuntimeConfigSource<OutboundNetworkingFactor> for TomlRuntimeConfigSource<'_, '_> {
     fn get_runtime_config(
         &mut self,
@@ -349,14 +918,24 @@ impl FactorRuntimeConfigSource<VariablesFactor> for TomlRuntimeConfigSource<'_,
 }
 
 impl FactorRuntimeConfigSource<OutboundPgFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_pg::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_pg::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
     }
 }
 
 impl FactorRuntimeConfigSource<OutboundMysqlFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_mysql::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_mysql::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
     }
 }
 
@@ -367,29 +946,88 @@ impl FactorRuntimeConfigSource<LlmFactor> for TomlRuntimeConfigSource<'_, '_> {
 }
 
 impl FactorRuntimeConfigSource<OutboundRedisFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_redis::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_redis::runtime_config::spin::config_from_table(&self.toml.table)
     }
 }
 
 impl FactorRuntimeConfigSource<WasiFactor> for TomlRuntimeConfigSource<'_, '_> {
-    fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
-        Ok(None)
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_wasi::runtime_config::RuntimeConfig>> {
+        spin_factor_wasi::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<WasiNnFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_wasi_nn::RuntimeConfig>> {
+        spin_factor_wasi_nn::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
     }
 }
 
 impl FactorRuntimeConfigSource<OutboundHttpFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_http::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_http::runtime_config::spin::config_from_table(
+            &self.toml.table,
+            self.runtime_config_dir(),
+        )
+    }
+}
+
+impl FactorRuntimeConfigSource<OutboundAmqpFactor> for TomlRuntimeConfigSource<'_, '_> {
     fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
         Ok(None)
     }
 }
 
-impl FactorRuntimeConfigSource<OutboundMqttFactor> for TomlRuntimeConfigSource<'_, '_> {
+impl FactorRuntimeConfigSource<OutboundWebSocketFactor> for TomlRuntimeConfigSource<'_, '_> {
     fn get_runtime_config(&mut self) -> anyhow::Result<Option<()>> {
         Ok(None)
     }
 }
 
+impl FactorRuntimeConfigSource<OutboundSmtpFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_smtp::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_smtp::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<OutboundFileTransferFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_file_transfer::runtime_config::RuntimeConfig>>
+    {
+        spin_factor_outbound_file_transfer::runtime_config::spin::config_from_table(
+            &self.toml.table,
+        )
+    }
+}
+
+impl FactorRuntimeConfigSource<OutboundLdapFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_ldap::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_ldap::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
+impl FactorRuntimeConfigSource<OutboundMqttFactor> for TomlRuntimeConfigSource<'_, '_> {
+    fn get_runtime_config(
+        &mut self,
+    ) -> anyhow::Result<Option<spin_factor_outbound_mqtt::runtime_config::RuntimeConfig>> {
+        spin_factor_outbound_mqtt::runtime_config::spin::config_from_table(&self.toml.table)
+    }
+}
+
 impl FactorRuntimeConfigSource<SqliteFactor> for TomlRuntimeConfigSource<'_, '_> {
     fn get_runtime_config(&mut self) -> anyhow::Result<Option<spin_factor_sqlite::RuntimeConfig>> {
         Ok(Some(self.sqlite.resolve(&self.toml.table)?))
@@ -400,10 +1038,86 @@ impl RuntimeConfigSourceFinalizer for TomlRuntimeConfigSource<'_, '_> {
     fn finalize(&mut self) -> anyhow::Result<()> {
         Ok(self.toml.validate_all_keys_used()?)
     }
+
+    fn last_read_location(&mut self) -> Option<String> {
+        let keys = self.toml.table.take_read_keys();
+        let path = self.runtime_config_path.as_deref()?;
+        let key = keys.first()?;
+        // Only read when there's a key to look for, and only the line of the
+        // first is reported
+        let text = std::fs::read_to_string(path).ok()?;
+        let line = key_line(&text, key)?;
+        Some(format!("{}:{line}", path.display()))
+    }
+}
+
+/// The line number of the first line in a TOML document which sets the
+/// top-level `key`, either as a table header or a key/value pair.
+fn key_line(text: &str, key: &str) -> Option<usize> {
+    let defines_key = |rest: &str| {
+        let rest = rest.trim_start();
+        let rest = rest
+            .strip_prefix(key)
+            .or_else(|| rest.strip_prefix(&format!("\"{key}\"")));
+        rest.is_some_and(|rest| rest.trim_start().starts_with([']', '.', '=']))
+    };
+    text.lines()
+        .position(|line| {
+            let line = line.trim_start();
+            match line.strip_prefix("[[").or_else(|| line.strip_prefix('[')) {
+                Some(header) => defines_key(header),
+                None => defines_key(line),
+            }
+        })
+        .map(|index| index + 1)
 }
 
 const DEFAULT_KEY_VALUE_STORE_LABEL: &str = "default";
 
+/// Reads the tuning of trigger runtimes from the `[executor]` table of a
+/// runtime config file.
+///
+/// Unlike the rest of the runtime config, this is needed before the runtime
+/// that resolves it is built, so it is read on its own.
+pub fn executor_config_from_file(
+    runtime_config_path: Option<&Path>,
+) -> anyhow::Result<ExecutorConfig> {
+    let toml = read_toml_file(runtime_config_path)?;
+    TomlResolver::new(
+        &toml,
+        None,
+        UserProvidedPath::Default,
+        UserProvidedPath::Default,
+    )
+    .executor()
+}
+
+/// Reads a runtime config file, or returns an empty table if there is none.
+fn read_toml_file(runtime_config_path: Option<&Path>) -> anyhow::Result<toml::Table> {
+    let Some(runtime_config_path) = runtime_config_path else {
+        return Ok(Default::default());
+    };
+    let file = std::fs::read_to_string(runtime_config_path).with_context(|| {
+        format!(
+            "failed to read runtime config file '{}'",
+            runtime_config_path.display()
+        )
+    })?;
+    let toml = toml::from_str(&file).with_context(|| {
+        format!(
+            "failed to parse runtime config file '{}' as toml",
+            runtime_config_path.display()
+        )
+    })?;
+    schema::validate(&file).with_context(|| {
+        format!(
+            "invalid runtime config file '{}'",
+            runtime_config_path.display()
+        )
+    })?;
+    Ok(toml)
+}
+
 /// The key-value runtime configuration resolver.
 ///
 /// Takes a base path that all local key-value stores which are configured with
@@ -431,6 +1145,16 @@ pub fn key_value_config_resolver(
     key_value
         .register_store_type(spin_key_value_aws::AwsDynamoKeyValueStore::new())
         .unwrap();
+    key_value
+        .register_store_type(spin_key_value_spin::MemoryKeyValueStore::new())
+        .unwrap();
+    // Registered last so that its primary and regional stores can be of any
+    // of the types above.
+    key_value
+        .register_store_type(spin_factor_key_value::GeoReplicatedKeyValueStore::new(
+            key_value.clone(),
+        ))
+        .unwrap();
 
     // Add handling of "default" store.
     let default_store_path = default_store_base_path.map(|p| p.join(DEFAULT_SPIN_STORE_FILENAME));
@@ -445,6 +1169,104 @@ pub fn key_value_config_resolver(
     key_value
 }
 
+/// The blob store runtime configuration resolver.
+pub fn blobstore_config_resolver() -> blobstore::RuntimeConfigResolver {
+    let mut blobstore = blobstore::RuntimeConfigResolver::new();
+
+    // Register the supported store types.
+    // Unwraps are safe because the store types are known to not overlap.
+    blobstore
+        .register_store_type(spin_blobstore_s3::S3BlobStore::new())
+        .unwrap();
+    blobstore
+        .register_store_type(spin_blobstore_azure::AzureBlobStore::new())
+        .unwrap();
+    blobstore
+        .register_store_type(spin_blobstore_gcs::GcsBlobStore::new())
+        .unwrap();
+
+    blobstore
+}
+
+/// The messaging runtime configuration resolver.
+pub fn messaging_config_resolver() -> messaging::RuntimeConfigResolver {
+    let mut messaging = messaging::RuntimeConfigResolver::new();
+
+    // Register the supported broker types.
+    // Unwraps are safe because the broker types are known to not overlap.
+    messaging
+        .register_store_type(spin_messaging_redis::RedisMessageBroker::new())
+        .unwrap();
+    messaging
+        .register_store_type(spin_messaging_nats::NatsMessageBroker::new())
+        .unwrap();
+    messaging
+        .register_store_type(spin_messaging_sqs::SqsMessageBroker::new())
+        .unwrap();
+    messaging
+        .register_store_type(spin_messaging_azure::AzureServiceBusMessageBroker::new())
+        .unwrap();
+    messaging
+        .register_store_type(spin_messaging_azure::AzureEventHubsMessageBroker::new())
+        .unwrap();
+    messaging
+        .register_store_type(spin_factor_messaging::InMemoryMessageBroker::new())
+        .unwrap();
+    // `memory` is the in-memory type's name for the other factors.
+    messaging
+        .register_store_type_alias("memory", "in_memory")
+        .unwrap();
+
+    messaging
+}
+
+/// The default filename for the vector store database.
+const DEFAULT_VECTOR_STORE_FILENAME: &str = "vector_store.db";
+
+/// The vector store runtime configuration resolver.
+///
+/// Takes a base path that local vector stores configured with relative paths
+/// will be relative to, and the directory the default store is kept in. The
+/// default store is in memory if there is no such directory.
+pub fn vector_store_config_resolver(
+    local_store_base_path: Option<PathBuf>,
+    default_store_base_path: Option<PathBuf>,
+) -> vector_store::RuntimeConfigResolver {
+    let mut vector_store = vector_store::RuntimeConfigResolver::new();
+
+    // Register the supported store types.
+    // Unwraps are safe because the store types are known to not overlap.
+    vector_store
+        .register_store_type(spin_vector_store_sqlite::SpinVectorStore::new(
+            local_store_base_path,
+        ))
+        .unwrap();
+    vector_store
+        .register_store_type(spin_vector_store_sqlite::LibsqlVectorStore::new())
+        .unwrap();
+    vector_store
+        .register_store_type(spin_vector_store_qdrant::QdrantVectorStore::new())
+        .unwrap();
+    vector_store
+        .register_store_type(spin_vector_store_pgvector::PgvectorVectorStore::new())
+        .unwrap();
+    vector_store
+        .register_store_type(spin_factor_vector_store::InMemoryVectorStore::new())
+        .unwrap();
+
+    // Add handling of "default" store.
+    let default_store_path = default_store_base_path.map(|p| p.join(DEFAULT_VECTOR_STORE_FILENAME));
+    // Unwraps are safe because the store is known to be serializable as toml.
+    vector_store
+        .add_default_store::<spin_vector_store_sqlite::SpinVectorStore>(
+            spin_factor_vector_store::DEFAULT_VECTOR_STORE_LABEL,
+            spin_vector_store_sqlite::SpinVectorStoreRuntimeConfig::new(default_store_path),
+        )
+        .unwrap();
+
+    vector_store
+}
+
 /// The default filename for the SQLite database.
 const DEFAULT_SPIN_STORE_FILENAME: &str = "sqlite_key_value.db";
 
@@ -563,6 +1385,143 @@ mod tests {
             .all(|label| runtime_config.has_store_manager(label)));
     }
 
+    #[test]
+    fn blob_containers_are_configured_correctly() {
+        define_test_factor!(blob_store: BlobStoreFactor);
+
+        let toml = toml::toml! {
+            [blob_container.uploads]
+            type = "s3"
+            region = "us-west-2"
+            bucket = "my-uploads"
+        };
+        let runtime_config = resolve_toml(toml, "config.toml").unwrap().runtime_config;
+        let blob_store = runtime_config.blob_store.unwrap();
+        assert!(blob_store.has_container_manager("uploads"));
+        assert!(!blob_store.has_container_manager("default"));
+
+        let toml = toml::toml! {
+            [blob_container.uploads]
+            type = "ftp"
+        };
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
+    #[test]
+    fn message_brokers_are_configured_correctly() {
+        define_test_factor!(messaging: MessagingFactor);
+
+        let toml = toml::toml! {
+            [message_broker.events]
+            type = "nats"
+            url = "nats://localhost:4222"
+        };
+        let runtime_config = resolve_toml(toml, "config.toml").unwrap().runtime_config;
+        let messaging = runtime_config.messaging.unwrap();
+        assert!(messaging.has_broker("events"));
+        assert!(!messaging.has_broker("default"));
+
+        let toml = toml::toml! {
+            [message_broker.local]
+            type = "in_memory"
+        };
+        let runtime_config = resolve_toml(toml, "config.toml").unwrap().runtime_config;
+        assert!(runtime_config.messaging.unwrap().has_broker("local"));
+
+        let toml = toml::toml! {
+            [message_broker.events]
+            type = "kafka"
+        };
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
+    #[test]
+    fn errors_are_reported_together() -> anyhow::Result<()> {
+        #[derive(RuntimeFactors)]
+        struct TestFactors {
+            blob_store: BlobStoreFactor,
+            messaging: MessagingFactor,
+        }
+        impl TryFrom<TomlRuntimeConfigSource<'_, '_>> for TestFactorsRuntimeConfig {
+            type Error = anyhow::Error;
+
+            fn try_from(value: TomlRuntimeConfigSource<'_, '_>) -> Result<Self, Self::Error> {
+                Self::from_source(value)
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("runtime-config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[blob_container.uploads]
+type = "ftp"
+
+[message_broker.events]
+type = "kafka"
+
+[unknown]
+key = "value"
+"#,
+        )?;
+        let toml = read_toml_file(Some(&path))?;
+        let Err(err) = ResolvedRuntimeConfig::<TestFactorsRuntimeConfig>::new(
+            toml_resolver(&toml),
+            Some(&path),
+        ) else {
+            panic!("expected runtime config errors");
+        };
+        let errors = err
+            .downcast_ref::<spin_factors::runtime_config::RuntimeConfigErrors>()
+            .expect("errors should be collected")
+            .errors();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].factor.unwrap().ends_with("BlobStoreFactor"));
+        assert_eq!(errors[0].location, Some(format!("{}:2", path.display())));
+        assert!(errors[1].factor.unwrap().ends_with("MessagingFactor"));
+        assert_eq!(errors[1].location, Some(format!("{}:5", path.display())));
+        assert_eq!(errors[2].factor, None);
+        assert!(errors[2].source.to_string().contains("unknown"));
+        Ok(())
+    }
+
+    #[test]
+    fn key_lines_are_found() {
+        let text = "a = 1\n\n[b.c]\nd = 2\n[[\"e\"]]\nf.g = 3\n";
+        assert_eq!(key_line(text, "a"), Some(1));
+        assert_eq!(key_line(text, "b"), Some(3));
+        assert_eq!(key_line(text, "d"), Some(4));
+        assert_eq!(key_line(text, "e"), Some(5));
+        assert_eq!(key_line(text, "f"), Some(6));
+        assert_eq!(key_line(text, "g"), None);
+    }
+
+    #[test]
+    fn vector_stores_are_configured_correctly() {
+        define_test_factor!(vector_store: VectorStoreFactor);
+
+        // Test that the default label is added if not provided.
+        let toml = toml::toml! {
+            [vector_store.docs]
+            type = "qdrant"
+            url = "http://localhost:6333"
+            collection = "docs"
+        };
+        let runtime_config = resolve_toml(toml, "config.toml").unwrap().runtime_config;
+        let vector_store = runtime_config.vector_store.unwrap();
+        assert!(vector_store.has_store("docs"));
+        assert!(vector_store.has_store("default"));
+
+        let toml = toml::toml! {
+            [vector_store.docs]
+            type = "pgvector"
+            address = "host=localhost user=postgres"
+        };
+        // pgvector stores need to know their dimensions
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn custom_spin_key_value_works_with_custom_paths() -> anyhow::Result<()> {
         use spin_world::v2::key_value::HostStore;
@@ -633,6 +1592,105 @@ mod tests {
         resolve_toml(toml, "config.toml").unwrap();
     }
 
+    #[test]
+    fn component_limits_are_resolved() {
+        define_test_factor!(sqlite: SqliteFactor);
+
+        let toml = toml::toml! {
+            [component_limits]
+            max_memory = 1048576
+            max_execution_time_ms = 500
+
+            [component_limits.components.hot]
+            max_instances = 2
+            max_fuel = 1000
+        };
+        let config = resolve_toml(toml, "config.toml").unwrap();
+        let limits = config.component_limits();
+        assert_eq!(limits.default.max_memory, Some(1048576));
+        assert_eq!(limits.default.max_instances, None);
+
+        let hot = limits.limits_for("hot");
+        assert_eq!(hot.max_memory, Some(1048576));
+        assert_eq!(hot.max_execution_time, Some(Duration::from_millis(500)));
+        assert_eq!(hot.max_instances, Some(2));
+        assert_eq!(hot.max_fuel, Some(1000));
+    }
+
+    #[test]
+    fn executor_tuning_is_resolved() {
+        define_test_factor!(sqlite: SqliteFactor);
+
+        let toml = toml::toml! {
+            [executor]
+            max_blocking_threads = 16
+
+            [executor.triggers.http]
+            worker_threads = 4
+        };
+        let config = resolve_toml(toml, "config.toml").unwrap();
+        let http = config.executor().tuning_for("http");
+        assert_eq!(http.worker_threads, Some(4));
+        assert_eq!(http.max_blocking_threads, Some(16));
+        assert_eq!(config.executor().tuning_for("redis").worker_threads, None);
+
+        let toml = toml::toml! {
+            [executor.triggers.http]
+            worker_threads = 0
+        };
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
+    #[test]
+    fn executor_config_is_read_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runtime-config.toml");
+        std::fs::write(&path, "[executor.triggers.http]\nworker_threads = 2\n").unwrap();
+
+        let executor = executor_config_from_file(Some(&path)).unwrap();
+        assert_eq!(executor.tuning_for("http").worker_threads, Some(2));
+        assert_eq!(
+            executor_config_from_file(None).unwrap(),
+            ExecutorConfig::default()
+        );
+    }
+
+    #[test]
+    fn trace_sampling_is_resolved() {
+        define_test_factor!(sqlite: SqliteFactor);
+
+        let toml = toml::toml! {
+            [telemetry.sampling]
+            strategy = "tail-keep-errors-and-slow"
+            slow_threshold_ms = 250
+            ratio = 0.1
+
+            [telemetry.sampling.components.hot]
+            strategy = "ratio"
+            ratio = 0.01
+        };
+        let config = resolve_toml(toml, "config.toml").unwrap();
+        let sampling = config.trace_sampling();
+        assert_eq!(
+            sampling.default,
+            SamplingStrategy::TailKeepErrorsAndSlow {
+                slow_threshold: Duration::from_millis(250),
+                ratio: 0.1
+            }
+        );
+        assert_eq!(
+            sampling.strategy_for(Some("hot")),
+            &SamplingStrategy::Ratio(0.01)
+        );
+        assert_eq!(sampling.strategy_for(Some("cold")), &sampling.default);
+
+        let toml = toml::toml! {
+            [telemetry.sampling]
+            strategy = "ratio"
+        };
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
     #[test]
     fn fails_to_resolve_with_unused_key() {
         define_test_factor!(sqlite: SqliteFactor);
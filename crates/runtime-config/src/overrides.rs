@@ -0,0 +1,168 @@
+//! Overriding individual runtime config values.
+//!
+//! Overrides are layered on top of the runtime config file: environment
+//! variables override the file, and values set on the command line override
+//! both. This lets container deployments change a single backend setting,
+//! such as the account of a Cosmos key-value store, without templating the
+//! whole file.
+
+use anyhow::Context as _;
+use toml::{Table, Value};
+
+/// The prefix of environment variables that override runtime config values.
+///
+/// The rest of the variable name is the path to the value, with path
+/// segments separated by `__`. For example,
+/// `SPIN_RUNTIME_CONFIG__KEY_VALUE_STORE__DEFAULT__ACCOUNT` overrides the
+/// `account` of the `default` key-value store. Names are lowercased.
+pub const ENV_PREFIX: &str = "SPIN_RUNTIME_CONFIG__";
+
+/// Overrides of runtime config values, in increasing order of precedence.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    values: Vec<(Vec<String>, Value)>,
+}
+
+impl Overrides {
+    /// Creates overrides from the environment variables starting with
+    /// [`ENV_PREFIX`].
+    pub fn from_env_vars(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut values: Vec<(Vec<String>, Value)> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let path = name.strip_prefix(ENV_PREFIX)?;
+                let path = path.split("__").map(str::to_lowercase).collect();
+                Some((path, parse_value(&value)))
+            })
+            .collect();
+        // Environment variables are unordered, so apply them in a stable order
+        values.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self { values }
+    }
+
+    /// Adds an override of the value at a dotted path, such as
+    /// `key_value_store.default.account`, taking precedence over those
+    /// already added.
+    ///
+    /// The value is parsed as a TOML value if it is one, and is otherwise
+    /// taken as a string.
+    pub fn add(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        let path: Vec<String> = key.split('.').map(ToOwned::to_owned).collect();
+        anyhow::ensure!(
+            path.iter().all(|segment| !segment.is_empty()),
+            "invalid runtime config key {key:?}"
+        );
+        self.values.push((path, parse_value(value)));
+        Ok(())
+    }
+
+    /// Applies the overrides to a runtime config, creating tables as needed.
+    pub fn apply(&self, toml: &mut Table) -> anyhow::Result<()> {
+        for (path, value) in &self.values {
+            set(toml, path, value.clone()).with_context(|| {
+                format!("failed to override runtime config value {}", path.join("."))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an override's value as TOML, falling back to a string.
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {raw}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_owned()))
+}
+
+fn set(toml: &mut Table, path: &[String], value: Value) -> anyhow::Result<()> {
+    let (last, parents) = path.split_last().context("empty path")?;
+    let mut table = toml;
+    for segment in parents {
+        let entry = table
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = entry
+            .as_table_mut()
+            .with_context(|| format!("`{segment}` is not a table"))?;
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Overrides {
+        Overrides::from_env_vars(
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        )
+    }
+
+    #[test]
+    fn env_vars_override_file_values() -> anyhow::Result<()> {
+        let mut toml: Table = toml::toml! {
+            [key_value_store.default]
+            type = "azure_cosmos"
+            account = "dev"
+            database = "apps"
+            container = "data"
+        };
+        env(&[
+            (
+                "SPIN_RUNTIME_CONFIG__KEY_VALUE_STORE__DEFAULT__ACCOUNT",
+                "prod",
+            ),
+            ("SPIN_RUNTIME_CONFIG__MAX_INSTANCE_MEMORY", "1048576"),
+            ("SPIN_OTHER", "ignored"),
+        ])
+        .apply(&mut toml)?;
+        assert_eq!(
+            toml["key_value_store"]["default"]["account"].as_str(),
+            Some("prod")
+        );
+        assert_eq!(
+            toml["key_value_store"]["default"]["database"].as_str(),
+            Some("apps")
+        );
+        assert_eq!(toml["max_instance_memory"].as_integer(), Some(1048576));
+        assert_eq!(toml.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn later_overrides_take_precedence() -> anyhow::Result<()> {
+        let mut overrides = env(&[("SPIN_RUNTIME_CONFIG__OUTBOUND_REDIS__IN_MEMORY", "false")]);
+        overrides.add("outbound_redis.in_memory", "true")?;
+        let mut toml = Table::new();
+        overrides.apply(&mut toml)?;
+        assert_eq!(toml["outbound_redis"]["in_memory"].as_bool(), Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn values_are_parsed_as_toml_or_strings() {
+        assert_eq!(parse_value("42"), Value::Integer(42));
+        assert_eq!(parse_value("\"42\""), Value::String("42".into()));
+        assert_eq!(
+            parse_value("[\"a\", \"b\"]").as_array().map(Vec::len),
+            Some(2)
+        );
+        assert_eq!(
+            parse_value("https://example.com"),
+            Value::String("https://example.com".into())
+        );
+    }
+
+    #[test]
+    fn overriding_inside_a_non_table_fails() -> anyhow::Result<()> {
+        let mut toml: Table = toml::toml! { state_dir = ".spin" };
+        let mut overrides = Overrides::default();
+        overrides.add("state_dir.path", "x")?;
+        assert!(overrides.apply(&mut toml).is_err());
+        assert!(overrides.add("key_value_store..account", "x").is_err());
+        Ok(())
+    }
+}
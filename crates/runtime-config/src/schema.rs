@@ -0,0 +1,563 @@
+//! The JSON schema of the runtime config, and validation against it.
+//!
+//! The schema covers the labeled sections whose entries are configured by
+//! type: key-value stores, SQLite databases, message brokers, blob
+//! containers and vector stores. It is generated from the runtime config types of the
+//! registered store types, which reject unknown fields. Validation reports
+//! where in the file each error is, so that a typo in a store's settings is
+//! easy to find. It also covers the stores components use in place of their
+//! labels. Other sections are checked by their factors when the runtime
+//! config is resolved.
+
+use std::{fmt, ops::Range};
+
+use schemars::schema::RootSchema;
+use serde_json::{json, Map, Value};
+use spin_sqlite as sqlite;
+
+use crate::{
+    blobstore_config_resolver, key_value_config_resolver, messaging_config_resolver,
+    vector_store_config_resolver,
+};
+
+/// Generates the JSON schema of the runtime config.
+pub fn json_schema() -> Value {
+    let key_value = key_value_config_resolver(None, None);
+    let sqlite = sqlite::RuntimeConfigResolver::new(None, Default::default());
+    let messaging = messaging_config_resolver();
+    let blobstore = blobstore_config_resolver();
+    let vector_store = vector_store_config_resolver(None, None);
+
+    let mut definitions = Map::new();
+    let mut properties = Map::new();
+    let mut add_section = |key: &str, description: &str, types: Vec<(&str, RootSchema)>| {
+        let section = labeled_section(description, types, &mut definitions);
+        properties.insert(key.to_owned(), section);
+    };
+    add_section(
+        "key_value_store",
+        "Key-value stores, by label.",
+        owned(key_value.store_type_schemas()),
+    );
+    add_section(
+        "sqlite_database",
+        "SQLite databases, by label.",
+        sqlite.database_type_schemas(),
+    );
+    add_section(
+        "message_broker",
+        "Message brokers, by label.",
+        owned(messaging.store_type_schemas()),
+    );
+    add_section(
+        "blob_container",
+        "Blob containers, by label.",
+        owned(blobstore.store_type_schemas()),
+    );
+    add_section(
+        "vector_store",
+        "Vector stores, by label.",
+        owned(vector_store.store_type_schemas()),
+    );
+
+    properties.insert("component_stores".to_owned(), component_stores_section());
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Spin runtime config",
+        "type": "object",
+        "properties": properties,
+        "definitions": definitions,
+    })
+}
+
+/// The schema of the section mapping the labels components use to other
+/// stores, by component ID.
+fn component_stores_section() -> Value {
+    let labels = json!({
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+    });
+    json!({
+        "description": "Stores components use in place of the labels in their manifest, by component ID.",
+        "type": "object",
+        "additionalProperties": {
+            "type": "object",
+            "properties": {
+                "key_value_store": labels,
+                "sqlite_database": labels,
+            },
+            "additionalProperties": false,
+        },
+    })
+}
+
+fn owned<'a>(
+    schemas: impl Iterator<Item = (&'static str, &'a RootSchema)>,
+) -> Vec<(&'static str, RootSchema)> {
+    schemas
+        .map(|(store_type, schema)| (store_type, schema.clone()))
+        .collect()
+}
+
+/// The schema of a section of labeled tables, each of which has a `type`
+/// selecting one of `types`.
+fn labeled_section(
+    description: &str,
+    mut types: Vec<(&str, RootSchema)>,
+    definitions: &mut Map<String, Value>,
+) -> Value {
+    types.sort_by_key(|(store_type, _)| *store_type);
+    let variants: Vec<Value> = types
+        .into_iter()
+        .map(|(store_type, root)| {
+            for (name, definition) in root.definitions {
+                definitions.insert(name, to_json(&definition));
+            }
+            let mut schema = to_json(&root.schema);
+            let object = schema.as_object_mut().expect("struct schemas are objects");
+            let properties = object.entry("properties").or_insert_with(|| json!({}));
+            properties["type"] = json!({ "const": store_type });
+            let required = object.entry("required").or_insert_with(|| json!([]));
+            if let Some(required) = required.as_array_mut() {
+                required.push("type".into());
+            }
+            schema
+        })
+        .collect();
+    json!({
+        "description": description,
+        "type": "object",
+        "additionalProperties": { "oneOf": variants },
+    })
+}
+
+fn to_json(schema: &impl serde::Serialize) -> Value {
+    serde_json::to_value(schema).expect("schemas serialize to JSON")
+}
+
+/// Checks the contents of a runtime config file against the schema,
+/// failing with every error found.
+pub fn validate(source: &str) -> anyhow::Result<()> {
+    let errors = schema_errors(source)?;
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let errors: Vec<String> = errors.iter().map(|err| format!("  {err}")).collect();
+    anyhow::bail!(
+        "runtime config doesn't match its schema:\n{}",
+        errors.join("\n")
+    )
+}
+
+/// Returns the ways in which the contents of a runtime config file don't
+/// match the schema.
+pub fn schema_errors(source: &str) -> anyhow::Result<Vec<SchemaError>> {
+    let table: toml::Table = toml::from_str(source)?;
+    let document = toml_edit::ImDocument::parse(source)?;
+    let schema = json_schema();
+
+    let mut validator = Validator {
+        root: &schema,
+        path: vec![],
+        errors: vec![],
+    };
+    validator.check(&schema, &serde_json::to_value(&table)?);
+
+    Ok(validator
+        .errors
+        .into_iter()
+        .map(|(path, message)| SchemaError {
+            position: locate(document.as_table(), &path).map(|span| position(source, span)),
+            path: path_string(&path),
+            message,
+        })
+        .collect())
+}
+
+/// A way in which a runtime config doesn't match the schema.
+#[derive(Debug, PartialEq)]
+pub struct SchemaError {
+    /// The dotted path to the offending value, e.g. `key_value_store.default`.
+    pub path: String,
+    /// The line and column, both starting at 1, of the offending key or value.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn path_string(path: &[Segment]) -> String {
+    let mut string = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if string.is_empty() => string.push_str(key),
+            Segment::Key(key) => {
+                string.push('.');
+                string.push_str(key);
+            }
+            Segment::Index(index) => string.push_str(&format!("[{index}]")),
+        }
+    }
+    if string.is_empty() {
+        string.push_str("<root>");
+    }
+    string
+}
+
+/// Checks values against the subset of JSON schema the generated schema
+/// uses.
+struct Validator<'a> {
+    root: &'a Value,
+    path: Vec<Segment>,
+    errors: Vec<(Vec<Segment>, String)>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, message: impl Into<String>) {
+        self.errors.push((self.path.clone(), message.into()));
+    }
+
+    fn check(&mut self, schema: &Value, value: &Value) {
+        let Some(schema) = schema.as_object() else {
+            // `true` and `false` schemas
+            if schema == &Value::Bool(false) {
+                self.error("is not allowed");
+            }
+            return;
+        };
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let root = self.root;
+            let definition = reference
+                .strip_prefix("#/definitions/")
+                .and_then(|name| root["definitions"].get(name));
+            if let Some(definition) = definition {
+                self.check(definition, value);
+            }
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(ty) => vec![ty],
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+                self.error(format!("expected {}", types.join(" or ")));
+                return;
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                self.error(format!("expected {expected}"));
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                self.error(format!("expected one of {}", allowed.join(", ")));
+            }
+        }
+        if let Some(Value::Array(variants)) = schema.get("oneOf").or(schema.get("anyOf")) {
+            self.check_variants(variants, value);
+        }
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for schema in schemas {
+                self.check(schema, value);
+            }
+        }
+        match value {
+            Value::Object(object) => self.check_object(schema, object),
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.path.push(Segment::Index(index));
+                        self.check(item_schema, item);
+                        self.path.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_object(&mut self, schema: &Map<String, Value>, object: &Map<String, Value>) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(field) {
+                    self.error(format!("missing field `{field}`"));
+                }
+            }
+        }
+        for (key, value) in object {
+            self.path.push(Segment::Key(key.clone()));
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => self.check(property, value),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        let expected = properties
+                            .map(|properties| {
+                                let names: Vec<String> =
+                                    properties.keys().map(|name| format!("`{name}`")).collect();
+                                format!(", expected one of {}", names.join(", "))
+                            })
+                            .unwrap_or_default();
+                        self.error(format!("unknown field `{key}`{expected}"));
+                    }
+                    Some(additional) => self.check(additional, value),
+                    None => {}
+                },
+            }
+            self.path.pop();
+        }
+    }
+
+    /// Checks a value against `oneOf` or `anyOf` variants.
+    ///
+    /// Variants discriminated by their `type` field, like the store types of
+    /// a labeled section, are selected by it so that errors are reported
+    /// against the selected variant.
+    fn check_variants(&mut self, variants: &[Value], value: &Value) {
+        let discriminants: Option<Vec<&str>> = variants
+            .iter()
+            .map(|variant| variant.pointer("/properties/type/const")?.as_str())
+            .collect();
+        if let (Some(discriminants), Some(object)) = (discriminants, value.as_object()) {
+            let Some(ty) = object.get("type").and_then(Value::as_str) else {
+                self.error("missing field `type`");
+                return;
+            };
+            match discriminants.iter().position(|&d| d == ty) {
+                Some(index) => self.check(&variants[index], value),
+                None => {
+                    let expected: Vec<String> =
+                        discriminants.iter().map(|d| format!("`{d}`")).collect();
+                    self.error(format!(
+                        "unknown type `{ty}`, expected one of {}",
+                        expected.join(", ")
+                    ));
+                }
+            }
+            return;
+        }
+        let matches = variants.iter().any(|variant| {
+            let mut validator = Validator {
+                root: self.root,
+                path: vec![],
+                errors: vec![],
+            };
+            validator.check(variant, value);
+            validator.errors.is_empty()
+        });
+        if !matches {
+            self.error("doesn't match any of the allowed forms");
+        }
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// A node of a parsed TOML document, for finding where values are.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Item(&'a toml_edit::Item),
+    Table(&'a toml_edit::Table),
+    Value(&'a toml_edit::Value),
+}
+
+impl<'a> Node<'a> {
+    fn table_like(self) -> Option<&'a dyn toml_edit::TableLike> {
+        match self {
+            Node::Item(item) => item.as_table_like(),
+            Node::Table(table) => Some(table as &dyn toml_edit::TableLike),
+            Node::Value(value) => value
+                .as_inline_table()
+                .map(|table| table as &dyn toml_edit::TableLike),
+        }
+    }
+
+    fn index(self, index: usize) -> Option<Node<'a>> {
+        match self {
+            Node::Item(toml_edit::Item::ArrayOfTables(tables)) => {
+                tables.get(index).map(Node::Table)
+            }
+            Node::Item(toml_edit::Item::Value(value)) | Node::Value(value) => {
+                value.as_array()?.get(index).map(Node::Value)
+            }
+            _ => None,
+        }
+    }
+
+    fn span(self) -> Option<Range<usize>> {
+        match self {
+            Node::Item(toml_edit::Item::Value(value)) | Node::Value(value) => value.span(),
+            Node::Item(toml_edit::Item::Table(table)) | Node::Table(table) => table.span(),
+            Node::Item(toml_edit::Item::ArrayOfTables(tables)) => tables.span(),
+            Node::Item(toml_edit::Item::None) => None,
+        }
+    }
+}
+
+/// Finds the span of the deepest key or value on `path` in a document.
+fn locate(document: &toml_edit::Table, path: &[Segment]) -> Option<Range<usize>> {
+    let mut node = Node::Table(document);
+    let mut span = None;
+    for segment in path {
+        let next = match segment {
+            Segment::Key(key) => node
+                .table_like()
+                .and_then(|table| table.get_key_value(key))
+                .map(|(key, item)| (key.span(), Node::Item(item))),
+            Segment::Index(index) => node.index(*index).map(|node| (node.span(), node)),
+        };
+        let Some((next_span, next)) = next else {
+            break;
+        };
+        span = next_span.or(span);
+        node = next;
+    }
+    span
+}
+
+/// The line and column, both starting at 1, of a span's start.
+fn position(source: &str, span: Range<usize>) -> (usize, usize) {
+    let before = source.get(..span.start).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_covers_registered_store_types() {
+        let schema = json_schema();
+        let key_value_types: Vec<&str> = schema
+            .pointer("/properties/key_value_store/additionalProperties/oneOf")
+            .and_then(Value::as_array)
+            .unwrap()
+            .iter()
+            .filter_map(|variant| variant.pointer("/properties/type/const")?.as_str())
+            .collect();
+        assert!(key_value_types.contains(&"azure_cosmos"));
+        assert!(key_value_types.contains(&"spin"));
+        assert!(schema.pointer("/properties/sqlite_database").is_some());
+        assert!(schema.pointer("/properties/message_broker").is_some());
+        assert!(schema.pointer("/properties/vector_store").is_some());
+    }
+
+    #[test]
+    fn valid_config_has_no_errors() -> anyhow::Result<()> {
+        let source = r#"
+[key_value_store.default]
+type = "azure_cosmos"
+account = "spin"
+database = "apps"
+container = "data"
+
+[sqlite_database.orders]
+type = "libsql"
+url = "https://orders.example.com"
+token = "secret"
+
+[some_other_section]
+anything = "goes"
+"#;
+        assert_eq!(schema_errors(source)?, []);
+        validate(source)
+    }
+
+    #[test]
+    fn unknown_fields_are_located() -> anyhow::Result<()> {
+        let source = r#"
+[key_value_store.default]
+type = "azure_cosmos"
+acount = "spin"
+database = "apps"
+container = "data"
+"#;
+        let errors = schema_errors(source)?;
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors
+            .iter()
+            .any(|err| err.path == "key_value_store.default"
+                && err.message == "missing field `account`"));
+        let unknown = errors
+            .iter()
+            .find(|err| err.path == "key_value_store.default.acount")
+            .unwrap();
+        assert_eq!(unknown.position, Some((4, 1)));
+        assert!(unknown.message.starts_with("unknown field `acount`"));
+        Ok(())
+    }
+
+    #[test]
+    fn component_stores_are_checked() -> anyhow::Result<()> {
+        let source = r#"
+[component_stores.ingest]
+key_value_store = { default = "cache" }
+sqlite_database = { default = "events" }
+
+[component_stores.web]
+key_value_stores = { default = "cache" }
+"#;
+        let errors = schema_errors(source)?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "component_stores.web.key_value_stores");
+        assert_eq!(errors[0].position, Some((7, 1)));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_store_types_are_rejected() -> anyhow::Result<()> {
+        let source = "message_broker.events = { type = \"kafka\", url = \"kafka://localhost\" }\n";
+        let errors = schema_errors(source)?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "message_broker.events");
+        assert!(errors[0].message.starts_with("unknown type `kafka`"));
+        assert_eq!(errors[0].position, Some((1, 16)));
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_value_types_are_rejected() -> anyhow::Result<()> {
+        let source = "[sqlite_database.default]\ntype = \"spin\"\npath = 42\n";
+        let errors = schema_errors(source)?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "sqlite_database.default.path");
+        assert_eq!(errors[0].position, Some((3, 1)));
+        Ok(())
+    }
+}
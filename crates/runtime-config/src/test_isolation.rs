@@ -0,0 +1,136 @@
+//! Isolating an app from the services its runtime config points it at.
+//!
+//! Under test isolation every key-value store, SQLite database, message
+//! broker and vector store is replaced by an in-memory one, outbound Redis is served from
+//! memory, and variables only come from static providers and the
+//! environment. Integration tests can then run against the app's real
+//! runtime config without touching real Cosmos, Redis or libSQL endpoints.
+
+use toml::{Table, Value};
+
+use crate::DEFAULT_KEY_VALUE_STORE_LABEL;
+
+/// The store type of the in-memory implementations.
+const MEMORY_TYPE: &str = "memory";
+
+/// Variables provider types which don't reach outside the process.
+const ISOLATED_VARIABLES_PROVIDER_TYPES: &[&str] = &["static", "memory", "env"];
+
+/// Rewrites a runtime config TOML table for test isolation.
+pub fn isolate(toml: &mut Table) {
+    // The default stores would otherwise be files in the state directory
+    isolate_labeled_tables(toml, "key_value_store", Some(DEFAULT_KEY_VALUE_STORE_LABEL));
+    isolate_labeled_tables(toml, "sqlite_database", Some("default"));
+    isolate_labeled_tables(toml, "message_broker", None);
+    isolate_labeled_tables(toml, "vector_store", Some("default"));
+
+    toml.insert(
+        "outbound_redis".into(),
+        Value::Table(Table::from_iter([("in_memory".into(), true.into())])),
+    );
+
+    for key in ["variables_provider", "config_provider"] {
+        if let Some(Value::Array(providers)) = toml.get_mut(key) {
+            providers.retain(|provider| {
+                provider
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .is_some_and(|ty| ISOLATED_VARIABLES_PROVIDER_TYPES.contains(&ty))
+            });
+        }
+    }
+}
+
+/// Replaces the configuration of every label under `key` with the in-memory
+/// type, adding `default_label` if it isn't configured.
+fn isolate_labeled_tables(toml: &mut Table, key: &str, default_label: Option<&str>) {
+    let mut labels: Vec<String> = toml
+        .get(key)
+        .and_then(Value::as_table)
+        .map(|tables| tables.keys().cloned().collect())
+        .unwrap_or_default();
+    if let Some(default_label) = default_label {
+        if !labels.iter().any(|label| label == default_label) {
+            labels.push(default_label.to_owned());
+        }
+    }
+    if labels.is_empty() {
+        return;
+    }
+    let tables = labels
+        .into_iter()
+        .map(|label| {
+            let config = Table::from_iter([("type".into(), MEMORY_TYPE.into())]);
+            (label, Value::Table(config))
+        })
+        .collect();
+    toml.insert(key.into(), Value::Table(tables));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_stores_with_memory() {
+        let mut toml: Table = toml::toml! {
+            [key_value_store.cache]
+            type = "redis"
+            url = "redis://localhost"
+
+            [sqlite_database.orders]
+            type = "libsql"
+            url = "https://orders.example.com"
+            token = "secret"
+
+            [message_broker.events]
+            type = "nats"
+            url = "nats://localhost"
+        };
+        isolate(&mut toml);
+        let expected: Table = toml::toml! {
+            [key_value_store.cache]
+            type = "memory"
+
+            [key_value_store.default]
+            type = "memory"
+
+            [sqlite_database.orders]
+            type = "memory"
+
+            [sqlite_database.default]
+            type = "memory"
+
+            [message_broker.events]
+            type = "memory"
+
+            [vector_store.default]
+            type = "memory"
+
+            [outbound_redis]
+            in_memory = true
+        };
+        assert_eq!(toml, expected);
+    }
+
+    #[test]
+    fn keeps_only_local_variables_providers() {
+        let mut toml: Table = toml::toml! {
+            [[variables_provider]]
+            type = "vault"
+            url = "https://vault.example.com"
+
+            [[variables_provider]]
+            type = "static"
+            values = { api_url = "http://localhost:3000" }
+
+            [[variables_provider]]
+            type = "azure_key_vault"
+            vault_url = "https://example.vault.azure.net"
+        };
+        isolate(&mut toml);
+        let providers = toml["variables_provider"].as_array().unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0]["type"].as_str(), Some("static"));
+    }
+}
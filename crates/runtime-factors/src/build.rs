@@ -1,13 +1,14 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::{TriggerAppArgs, TriggerFactors, TriggerFactorsRuntimeConfig};
 
 use anyhow::Context as _;
 use spin_factors_executor::FactorsExecutor;
-use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_config::{overrides::Overrides, test_isolation, ResolvedRuntimeConfig};
 use spin_trigger::cli::{
-    FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook, MaxInstanceMemoryHook,
-    RuntimeFactorsBuilder, SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook,
+    ComponentLimitsHook, ExecutorTuning, FactorsConfig, InitialKvSetterHook,
+    KeyValueDefaultStoreSummaryHook, MaxInstanceMemoryHook, RuntimeFactorsBuilder,
+    SecretAccessSummaryHook, SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook,
     StdioLoggingExecutorHooks,
 };
 
@@ -19,18 +20,38 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
     type Factors = TriggerFactors;
     type RuntimeConfig = ResolvedRuntimeConfig<TriggerFactorsRuntimeConfig>;
 
-    fn build(
+    async fn build(
         config: &FactorsConfig,
         args: &Self::CliArgs,
     ) -> anyhow::Result<(Self::Factors, Self::RuntimeConfig)> {
-        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file(
+        let mut overrides = Overrides::from_env_vars(std::env::vars());
+        for (key, value) in &args.runtime_config_overrides {
+            overrides.add(key, value)?;
+        }
+        if args.test_isolation {
+            terminal::einfo!(
+                "Test isolation:",
+                "using in-memory stores, databases and message brokers"
+            );
+        }
+        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file_with(
             config.runtime_config_file.clone().as_deref(),
             config.local_app_dir.clone().map(PathBuf::from),
             config.state_dir.clone(),
             config.log_dir.clone(),
-        )?;
+            |toml| {
+                overrides.apply(toml)?;
+                // Isolation comes last so that overrides can't undo it
+                if args.test_isolation {
+                    test_isolation::isolate(toml);
+                }
+                Ok(())
+            },
+        )
+        .await?;
 
         runtime_config.summarize(config.runtime_config_file.as_deref());
+        spin_telemetry::sampling::set_sampling_config(runtime_config.trace_sampling().clone());
 
         let factors = TriggerFactors::new(
             runtime_config.state_dir(),
@@ -41,22 +62,49 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
         Ok((factors, runtime_config))
     }
 
+    fn update_core_config(
+        runtime_config: &Self::RuntimeConfig,
+        config: &mut spin_core::Config,
+    ) -> anyhow::Result<()> {
+        // Fuel metering slows down all execution, so only enable it if needed.
+        if runtime_config.component_limits().uses_fuel() {
+            config.consume_fuel(true);
+        }
+        Ok(())
+    }
+
+    fn executor_tuning(
+        runtime_config_file: Option<&Path>,
+        trigger_type: &str,
+    ) -> anyhow::Result<ExecutorTuning> {
+        let executor = spin_runtime_config::executor_config_from_file(runtime_config_file)?;
+        Ok(executor.tuning_for(trigger_type))
+    }
+
     fn configure_app<U: Send + 'static>(
         executor: &mut FactorsExecutor<Self::Factors, U>,
         runtime_config: &Self::RuntimeConfig,
         config: &FactorsConfig,
         args: &Self::CliArgs,
     ) -> anyhow::Result<()> {
-        executor.add_hooks(StdioLoggingExecutorHooks::new(
-            config.follow_components.clone(),
-            runtime_config.log_dir(),
-        ));
-        executor.add_hooks(SqlStatementExecutorHook::new(
-            args.sqlite_statements.clone(),
-        ));
-        executor.add_hooks(InitialKvSetterHook::new(args.key_values.clone()));
+        executor.add_hooks(
+            StdioLoggingExecutorHooks::new(
+                config.follow_components.clone(),
+                runtime_config.log_dir(),
+            )
+            .with_format(config.log_format)
+            .with_rotation(config.log_rotation),
+        );
+        // Checking an app mustn't change its stores or databases
+        if !config.check_only {
+            executor.add_hooks(SqlStatementExecutorHook::new(
+                args.sqlite_statements.clone(),
+            ));
+            executor.add_hooks(InitialKvSetterHook::new(args.key_values.clone()));
+        }
         executor.add_hooks(SqliteDefaultStoreSummaryHook);
         executor.add_hooks(KeyValueDefaultStoreSummaryHook);
+        executor.add_hooks(SecretAccessSummaryHook);
 
         let max_instance_memory = args
             .max_instance_memory
@@ -67,6 +115,13 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
             executor.add_hooks(MaxInstanceMemoryHook::new(max_instance_memory));
         }
 
+        // Added after the max instance memory hook so per-component memory
+        // limits take precedence.
+        let component_limits = runtime_config.component_limits();
+        if !component_limits.is_empty() {
+            executor.add_hooks(ComponentLimitsHook::new(component_limits.clone()));
+        }
+
         Ok(())
     }
 }
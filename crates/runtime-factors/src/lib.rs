@@ -6,33 +6,97 @@ use std::path::PathBuf;
 
 use anyhow::Context as _;
 use spin_common::arg_parser::parse_kv;
+use spin_factor_audit::AuditFactor;
+use spin_factor_auth::AuthFactor;
+use spin_factor_background_tasks::BackgroundTasksFactor;
+use spin_factor_blobstore::BlobStoreFactor;
+use spin_factor_cache::CacheFactor;
+use spin_factor_component_metadata::ComponentMetadataFactor;
+use spin_factor_crypto::CryptoFactor;
+use spin_factor_entities::EntitiesFactor;
+use spin_factor_fault_injection::FaultInjectionFactor;
+use spin_factor_feature_flags::FeatureFlagsFactor;
+use spin_factor_host_plugins::HostPluginsFactor;
+use spin_factor_i18n::I18nFactor;
+use spin_factor_ids::IdsFactor;
+use spin_factor_image::ImageFactor;
 use spin_factor_key_value::KeyValueFactor;
+use spin_factor_leader_election::LeaderElectionFactor;
 use spin_factor_llm::LlmFactor;
+use spin_factor_logging::LoggingFactor;
+use spin_factor_messaging::MessagingFactor;
+use spin_factor_metrics::MetricsFactor;
+use spin_factor_outbound_amqp::{NetworkedAmqpClient, OutboundAmqpFactor};
+use spin_factor_outbound_file_transfer::OutboundFileTransferFactor;
 use spin_factor_outbound_http::OutboundHttpFactor;
+use spin_factor_outbound_ldap::OutboundLdapFactor;
 use spin_factor_outbound_mqtt::{NetworkedMqttClient, OutboundMqttFactor};
 use spin_factor_outbound_mysql::OutboundMysqlFactor;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_outbound_pg::OutboundPgFactor;
 use spin_factor_outbound_redis::OutboundRedisFactor;
+use spin_factor_outbound_smtp::OutboundSmtpFactor;
+use spin_factor_outbound_websocket::{NetworkedWebSocketClient, OutboundWebSocketFactor};
+use spin_factor_policy::PolicyFactor;
+use spin_factor_rate_limit::RateLimitFactor;
+use spin_factor_request_context::RequestContextFactor;
+use spin_factor_session::SessionFactor;
 use spin_factor_sqlite::SqliteFactor;
+use spin_factor_templates::TemplatesFactor;
+use spin_factor_timers::TimersFactor;
 use spin_factor_variables::VariablesFactor;
+use spin_factor_vector_store::VectorStoreFactor;
 use spin_factor_wasi::{spin::SpinFilesMounter, WasiFactor};
+use spin_factor_wasi_nn::WasiNnFactor;
+use spin_factor_workflows::WorkflowsFactor;
 use spin_factors::RuntimeFactors;
 use spin_runtime_config::{ResolvedRuntimeConfig, TomlRuntimeConfigSource};
 
 #[derive(RuntimeFactors)]
 pub struct TriggerFactors {
     pub wasi: WasiFactor,
+    pub request_context: RequestContextFactor,
+    pub fault_injection: FaultInjectionFactor,
+    pub policy: PolicyFactor,
     pub variables: VariablesFactor,
+    pub crypto: CryptoFactor,
+    pub image: ImageFactor,
+    pub ids: IdsFactor,
+    pub i18n: I18nFactor,
     pub key_value: KeyValueFactor,
+    pub cache: CacheFactor,
+    pub session: SessionFactor,
+    pub feature_flags: FeatureFlagsFactor,
+    pub rate_limit: RateLimitFactor,
+    pub leader_election: LeaderElectionFactor,
+    pub entities: EntitiesFactor,
+    pub timers: TimersFactor,
+    pub workflows: WorkflowsFactor,
+    pub background_tasks: BackgroundTasksFactor,
+    pub blob_store: BlobStoreFactor,
+    pub messaging: MessagingFactor,
+    pub templates: TemplatesFactor,
+    pub vector_store: VectorStoreFactor,
+    pub host_plugins: HostPluginsFactor,
+    pub component_metadata: ComponentMetadataFactor,
+    pub logging: LoggingFactor,
+    pub metrics: MetricsFactor,
+    pub audit: AuditFactor,
     pub outbound_networking: OutboundNetworkingFactor,
     pub outbound_http: OutboundHttpFactor,
+    pub auth: AuthFactor,
     pub sqlite: SqliteFactor,
     pub redis: OutboundRedisFactor,
     pub mqtt: OutboundMqttFactor,
+    pub amqp: OutboundAmqpFactor,
+    pub websocket: OutboundWebSocketFactor,
+    pub smtp: OutboundSmtpFactor,
+    pub file_transfer: OutboundFileTransferFactor,
+    pub ldap: OutboundLdapFactor,
     pub pg: OutboundPgFactor,
     pub mysql: OutboundMysqlFactor,
     pub llm: LlmFactor,
+    pub wasi_nn: WasiNnFactor,
 }
 
 impl TriggerFactors {
@@ -43,19 +107,51 @@ impl TriggerFactors {
     ) -> anyhow::Result<Self> {
         Ok(Self {
             wasi: wasi_factor(working_dir, allow_transient_writes),
+            request_context: RequestContextFactor::new(),
+            fault_injection: FaultInjectionFactor::new(),
+            policy: PolicyFactor::new(),
             variables: VariablesFactor::default(),
+            crypto: CryptoFactor::new(),
+            image: ImageFactor::new(),
+            ids: IdsFactor::new(),
+            i18n: I18nFactor::new(),
             key_value: KeyValueFactor::new(),
+            cache: CacheFactor::new(),
+            session: SessionFactor::new(),
+            feature_flags: FeatureFlagsFactor::new(),
+            rate_limit: RateLimitFactor::new(),
+            leader_election: LeaderElectionFactor::new(),
+            entities: EntitiesFactor::new(),
+            timers: TimersFactor::new(),
+            workflows: WorkflowsFactor::new(),
+            background_tasks: BackgroundTasksFactor::new(),
+            blob_store: BlobStoreFactor::new(),
+            messaging: MessagingFactor::new(),
+            templates: TemplatesFactor::new(),
+            vector_store: VectorStoreFactor::new(),
+            host_plugins: HostPluginsFactor::new(),
+            component_metadata: ComponentMetadataFactor::new(),
+            logging: LoggingFactor::new(),
+            metrics: MetricsFactor::new(),
+            audit: AuditFactor::new(),
             outbound_networking: outbound_networking_factor(),
             outbound_http: OutboundHttpFactor::default(),
+            auth: AuthFactor::new(),
             sqlite: SqliteFactor::new(),
             redis: OutboundRedisFactor::new(),
             mqtt: OutboundMqttFactor::new(NetworkedMqttClient::creator()),
+            amqp: OutboundAmqpFactor::new(NetworkedAmqpClient::creator()),
+            websocket: OutboundWebSocketFactor::new(NetworkedWebSocketClient::creator()),
+            smtp: OutboundSmtpFactor::new(),
+            file_transfer: OutboundFileTransferFactor::new(),
+            ldap: OutboundLdapFactor::new(),
             pg: OutboundPgFactor::new(),
             mysql: OutboundMysqlFactor::new(),
             llm: LlmFactor::new(
                 spin_factor_llm::spin::default_engine_creator(state_dir)
                     .context("failed to configure LLM factor")?,
             ),
+            wasi_nn: WasiNnFactor::new(),
         })
     }
 }
@@ -84,7 +180,7 @@ fn outbound_networking_factor() -> OutboundNetworkingFactor {
 }
 
 /// Options for building a [`TriggerFactors`].
-#[derive(Default, clap::Args)]
+#[derive(Clone, Default, clap::Args)]
 pub struct TriggerAppArgs {
     /// Set the static assets of the components in the temporary directory as writable.
     #[clap(long = "allow-transient-write")]
@@ -104,6 +200,19 @@ pub struct TriggerAppArgs {
     /// Sets the maxmimum memory allocation limit for an instance in bytes.
     #[clap(long, env = "SPIN_MAX_INSTANCE_MEMORY")]
     pub max_instance_memory: Option<usize>,
+
+    /// Replace every key-value store, SQLite database, message broker and
+    /// Redis connection with an in-memory one, and ignore variables providers
+    /// other than static ones and the environment. For integration tests.
+    #[clap(long = "test-isolation", env = "SPIN_TEST_ISOLATION")]
+    pub test_isolation: bool,
+
+    /// Override a runtime config value (key=value), where the key is a dotted
+    /// path such as `key_value_store.default.account`. Takes precedence over
+    /// the runtime config file and `SPIN_RUNTIME_CONFIG__*` environment
+    /// variables. Can be used multiple times.
+    #[clap(long = "runtime-config-set", parse(try_from_str = parse_kv))]
+    pub runtime_config_overrides: Vec<(String, String)>,
 }
 
 impl From<ResolvedRuntimeConfig<TriggerFactorsRuntimeConfig>> for TriggerFactorsRuntimeConfig {
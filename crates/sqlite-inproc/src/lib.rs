@@ -7,7 +7,8 @@ use std::{
 use anyhow::Context as _;
 use async_trait::async_trait;
 use spin_factor_sqlite::Connection;
-use spin_world::spin::sqlite::sqlite;
+use spin_world::spin::sql::types::{DatabaseError, ErrorKind};
+use spin_world::spin::sqlite4_0_0::sqlite;
 
 /// The location of an in-process sqlite database.
 #[derive(Debug, Clone)]
@@ -43,14 +44,18 @@ impl InProcDatabaseLocation {
 }
 
 /// A connection to a sqlite database
+///
+/// Clones share the underlying connection, so clones of a connection to an
+/// in-memory database see the same data.
+#[derive(Clone)]
 pub struct InProcConnection {
     location: InProcDatabaseLocation,
-    connection: OnceLock<Arc<Mutex<rusqlite::Connection>>>,
+    connection: Arc<OnceLock<Arc<Mutex<rusqlite::Connection>>>>,
 }
 
 impl InProcConnection {
     pub fn new(location: InProcDatabaseLocation) -> Result<Self, sqlite::Error> {
-        let connection = OnceLock::new();
+        let connection = Arc::new(OnceLock::new());
         Ok(Self {
             location,
             connection,
@@ -133,9 +138,7 @@ fn execute_query(
     parameters: Vec<sqlite::Value>,
 ) -> Result<sqlite::QueryResult, sqlite::Error> {
     let conn = connection.lock().unwrap();
-    let mut statement = conn
-        .prepare_cached(query)
-        .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+    let mut statement = conn.prepare_cached(query).map_err(query_failed)?;
     let columns = statement
         .column_names()
         .into_iter()
@@ -157,14 +160,31 @@ fn execute_query(
                 Ok(sqlite::RowResult { values })
             },
         )
-        .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+        .map_err(query_failed)?;
     let rows = rows
         .into_iter()
-        .map(|r| r.map_err(|e| sqlite::Error::Io(e.to_string())))
+        .map(|r| r.map_err(query_failed))
         .collect::<Result<_, sqlite::Error>>()?;
     Ok(sqlite::QueryResult { columns, rows })
 }
 
+/// Converts an error from running a statement into a [`sqlite::Error::QueryFailed`],
+/// classified by its SQLite result code.
+fn query_failed(error: rusqlite::Error) -> sqlite::Error {
+    let message = error.to_string();
+    let code = match &error {
+        rusqlite::Error::SqliteFailure(failure, _) => Some(failure.extended_code),
+        _ => None,
+    };
+    sqlite::Error::QueryFailed(DatabaseError {
+        kind: code.map_or(ErrorKind::Other, |code| {
+            ErrorKind::from_sqlite_code(code, &message)
+        }),
+        code: code.map(|code| code.to_string()),
+        message,
+    })
+}
+
 fn convert_data(
     arguments: impl Iterator<Item = sqlite::Value>,
 ) -> impl Iterator<Item = rusqlite::types::Value> {
@@ -0,0 +1,208 @@
+//! Branches of libSQL databases hosted by Turso, managed through the Turso
+//! Platform API.
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde_json::{json, Value as Json};
+use spin_factor_sqlite::{Connection, ConnectionCreator};
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
+use tokio::sync::OnceCell;
+
+use crate::LazyLibSqlConnection;
+
+/// The URL of the Turso Platform API.
+pub const DEFAULT_API_URL: &str = "https://api.turso.tech";
+
+/// A client for managing the databases of a Turso organization.
+#[derive(Clone)]
+pub struct TursoPlatform {
+    client: Client,
+    api_url: String,
+    organization: String,
+    api_token: String,
+}
+
+/// A database in a Turso organization.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TursoDatabase {
+    pub name: String,
+    /// The URL to connect to the database over HTTPS.
+    pub url: String,
+}
+
+impl TursoPlatform {
+    pub fn new(organization: String, api_token: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_url: DEFAULT_API_URL.to_owned(),
+            organization,
+            api_token,
+        }
+    }
+
+    /// Uses a Platform API other than Turso's own, e.g. for testing.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!(
+            "{}/v1/organizations/{}/databases{path}",
+            self.api_url.trim_end_matches('/'),
+            self.organization
+        );
+        self.client
+            .request(method, url)
+            .bearer_auth(&self.api_token)
+    }
+
+    /// Looks up the database `name`, returning `None` if it doesn't exist.
+    pub async fn database(&self, name: &str) -> anyhow::Result<Option<TursoDatabase>> {
+        let Some(info) = self.database_info(name).await? else {
+            return Ok(None);
+        };
+        to_database(&info).map(Some)
+    }
+
+    /// Creates the database `name` as a branch of the database `source`.
+    ///
+    /// The branch starts out with the contents `source` had at `timestamp`, or
+    /// its current contents if `timestamp` is `None`. It is created in the same
+    /// group as `source`, so group tokens for `source` can access it too.
+    pub async fn create_branch(
+        &self,
+        source: &str,
+        name: &str,
+        timestamp: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<TursoDatabase> {
+        let source_info = self
+            .database_info(source)
+            .await?
+            .with_context(|| format!("Turso database {source:?} doesn't exist"))?;
+        let mut seed = json!({ "type": "database", "name": source });
+        if let Some(timestamp) = timestamp {
+            seed["timestamp"] = timestamp.to_rfc3339_opts(SecondsFormat::Secs, true).into();
+        }
+        let body = json!({
+            "name": name,
+            "group": source_info["group"],
+            "seed": seed,
+        });
+        let response = self
+            .request(Method::POST, "")
+            .json(&body)
+            .send()
+            .await
+            .context("failed to reach the Turso Platform API")?;
+        let created = parse_response(response)
+            .await
+            .with_context(|| format!("failed to branch Turso database {source:?} as {name:?}"))?;
+        to_database(&created["database"])
+    }
+
+    async fn database_info(&self, name: &str) -> anyhow::Result<Option<Json>> {
+        let response = self
+            .request(Method::GET, &format!("/{name}"))
+            .send()
+            .await
+            .context("failed to reach the Turso Platform API")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let info = parse_response(response)
+            .await
+            .with_context(|| format!("failed to look up Turso database {name:?}"))?;
+        Ok(Some(info["database"].clone()))
+    }
+}
+
+async fn parse_response(response: reqwest::Response) -> anyhow::Result<Json> {
+    let status = response.status();
+    let body: Json = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = body["error"].as_str().unwrap_or("no details given");
+        anyhow::bail!("Turso Platform API returned {status}: {message}");
+    }
+    Ok(body)
+}
+
+fn to_database(info: &Json) -> anyhow::Result<TursoDatabase> {
+    let (Some(name), Some(hostname)) = (info["Name"].as_str(), info["Hostname"].as_str()) else {
+        anyhow::bail!("unexpected database description from the Turso Platform API: {info}");
+    };
+    Ok(TursoDatabase {
+        name: name.to_owned(),
+        url: format!("https://{hostname}"),
+    })
+}
+
+/// A [`ConnectionCreator`] for a branch of a Turso database.
+///
+/// The branch is created the first time a connection is made, unless a
+/// database with its name already exists.
+pub struct LibSqlBranch {
+    platform: TursoPlatform,
+    source: String,
+    name: String,
+    timestamp: Option<DateTime<Utc>>,
+    token: String,
+    url: OnceCell<String>,
+}
+
+impl LibSqlBranch {
+    /// `token` is used to connect to the branch, so should be a group token
+    /// for `source`'s group.
+    pub fn new(
+        platform: TursoPlatform,
+        source: String,
+        name: String,
+        timestamp: Option<DateTime<Utc>>,
+        token: String,
+    ) -> Self {
+        Self {
+            platform,
+            source,
+            name,
+            timestamp,
+            token,
+            url: OnceCell::new(),
+        }
+    }
+
+    async fn url(&self) -> anyhow::Result<&str> {
+        self.url
+            .get_or_try_init(|| async {
+                let database = match self.platform.database(&self.name).await? {
+                    Some(database) => database,
+                    None => {
+                        self.platform
+                            .create_branch(&self.source, &self.name, self.timestamp)
+                            .await?
+                    }
+                };
+                Ok(database.url)
+            })
+            .await
+            .map(String::as_str)
+    }
+}
+
+#[async_trait]
+impl ConnectionCreator for LibSqlBranch {
+    async fn create_connection(
+        &self,
+        label: &str,
+    ) -> Result<Box<dyn Connection + 'static>, v4::Error> {
+        let url = self.url().await.map_err(|e| {
+            v4::Error::Io(format!(
+                "failed to set up branch {:?} for SQLite database {label:?}: {e:?}",
+                self.name
+            ))
+        })?;
+        let connection = LazyLibSqlConnection::new(url.to_owned(), self.token.clone());
+        Ok(Box::new(connection))
+    }
+}
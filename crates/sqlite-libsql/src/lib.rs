@@ -1,10 +1,24 @@
+mod branch;
+mod replica;
+
+use std::time::{Duration, Instant};
+
 use anyhow::Context;
 use async_trait::async_trait;
 use spin_factor_sqlite::Connection;
-use spin_world::spin::sqlite::sqlite as v3;
-use spin_world::spin::sqlite::sqlite::{self, RowResult};
+use spin_world::spin::sql::types::{DatabaseError, ErrorKind};
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
+use spin_world::spin::sqlite4_0_0::sqlite::{self, RowResult};
 use tokio::sync::OnceCell;
 
+pub use branch::{LibSqlBranch, TursoDatabase, TursoPlatform};
+pub use replica::LibSqlReplica;
+
+/// How many times a query is attempted while the database is busy.
+const MAX_QUERY_ATTEMPTS: u32 = 3;
+/// How long to wait before retrying a query, multiplied by the attempt number.
+const RETRY_BACKOFF: Duration = Duration::from_millis(20);
+
 /// A lazy wrapper around a [`LibSqlConnection`] that implements the [`Connection`] trait.
 pub struct LazyLibSqlConnection {
     url: String,
@@ -24,7 +38,7 @@ impl LazyLibSqlConnection {
         }
     }
 
-    pub async fn get_or_create_connection(&self) -> Result<&LibSqlConnection, v3::Error> {
+    pub async fn get_or_create_connection(&self) -> Result<&LibSqlConnection, v4::Error> {
         self.inner
             .get_or_try_init(|| async {
                 LibSqlConnection::create(self.url.clone(), self.token.clone())
@@ -32,7 +46,7 @@ impl LazyLibSqlConnection {
                     .context("failed to create SQLite client")
             })
             .await
-            .map_err(|_| v3::Error::InvalidConnection)
+            .map_err(|_| v4::Error::InvalidConnection)
     }
 }
 
@@ -41,8 +55,8 @@ impl Connection for LazyLibSqlConnection {
     async fn query(
         &self,
         query: &str,
-        parameters: Vec<v3::Value>,
-    ) -> Result<v3::QueryResult, v3::Error> {
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
         let client = self.get_or_create_connection().await?;
         client.query(query, parameters).await
     }
@@ -71,33 +85,67 @@ impl Connection for LazyLibSqlConnection {
 #[derive(Clone)]
 pub struct LibSqlConnection {
     inner: libsql::Connection,
+    /// The URL of the database, used to attribute metrics.
+    url: String,
 }
 
 impl LibSqlConnection {
     pub async fn create(url: String, token: String) -> anyhow::Result<Self> {
-        let db = libsql::Builder::new_remote(url, token).build().await?;
+        let db = libsql::Builder::new_remote(url.clone(), token)
+            .build()
+            .await?;
         let inner = db.connect()?;
-        Ok(Self { inner })
+        Ok(Self { inner, url })
     }
 }
 
 impl LibSqlConnection {
+    /// Runs `query`, retrying it a few times if the database is busy.
     pub async fn query(
         &self,
         query: &str,
         parameters: Vec<sqlite::Value>,
+    ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        let start = Instant::now();
+        let mut attempt = 1;
+        let result = loop {
+            match self.query_once(query, &parameters).await {
+                Err(sqlite::Error::QueryFailed(DatabaseError {
+                    kind: ErrorKind::SerializationFailure,
+                    ..
+                })) if attempt < MAX_QUERY_ATTEMPTS => {
+                    spin_telemetry::metrics::monotonic_counter!(
+                        spin.libsql.query_retries = 1,
+                        database = self.url.as_str()
+                    );
+                    tokio::time::sleep(RETRY_BACKOFF * attempt).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+        spin_telemetry::metrics::histogram!(
+            spin.libsql.query_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            database = self.url.as_str(),
+            success = result.is_ok()
+        );
+        result
+    }
+
+    async fn query_once(
+        &self,
+        query: &str,
+        parameters: &[sqlite::Value],
     ) -> Result<sqlite::QueryResult, sqlite::Error> {
         let result = self
             .inner
-            .query(query, convert_parameters(&parameters))
+            .query(query, convert_parameters(parameters))
             .await
-            .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+            .map_err(query_failed)?;
 
         Ok(sqlite::QueryResult {
             columns: columns(&result),
-            rows: convert_rows(result)
-                .await
-                .map_err(|e| sqlite::Error::Io(e.to_string()))?,
+            rows: convert_rows(result).await.map_err(query_failed)?,
         })
     }
 
@@ -116,13 +164,55 @@ impl LibSqlConnection {
     }
 }
 
+/// Converts a libSQL error from running a statement into a
+/// [`sqlite::Error::QueryFailed`], classified by its SQLite result code.
+fn query_failed(error: libsql::Error) -> sqlite::Error {
+    let message = error.to_string();
+    let code = match &error {
+        libsql::Error::SqliteFailure(code, _) => Some(*code),
+        libsql::Error::ConnectionFailed(_) => {
+            return sqlite::Error::QueryFailed(DatabaseError {
+                kind: ErrorKind::ConnectionLost,
+                code: None,
+                message,
+            })
+        }
+        _ => remote_result_code(&message),
+    };
+    sqlite::Error::QueryFailed(DatabaseError {
+        kind: code.map_or(ErrorKind::Other, |code| {
+            ErrorKind::from_sqlite_code(code, &message)
+        }),
+        code: code.map(|code| code.to_string()),
+        message,
+    })
+}
+
+/// Errors from a remote libSQL server only name their result code in the
+/// message, e.g. `SQLITE_CONSTRAINT`.
+fn remote_result_code(message: &str) -> Option<i32> {
+    const RESULT_CODES: &[(&str, i32)] = &[
+        ("SQLITE_ERROR", 1),
+        ("SQLITE_PERM", 3),
+        ("SQLITE_BUSY", 5),
+        ("SQLITE_LOCKED", 6),
+        ("SQLITE_READONLY", 8),
+        ("SQLITE_CONSTRAINT", 19),
+        ("SQLITE_AUTH", 23),
+    ];
+    RESULT_CODES
+        .iter()
+        .find(|(name, _)| message.contains(name))
+        .map(|(_, code)| *code)
+}
+
 fn columns(rows: &libsql::Rows) -> Vec<String> {
     (0..rows.column_count())
         .map(|index| rows.column_name(index).unwrap_or("").to_owned())
         .collect()
 }
 
-async fn convert_rows(mut rows: libsql::Rows) -> anyhow::Result<Vec<RowResult>> {
+async fn convert_rows(mut rows: libsql::Rows) -> libsql::Result<Vec<RowResult>> {
     let mut result_rows = vec![];
 
     let column_count = rows.column_count();
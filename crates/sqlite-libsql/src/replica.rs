@@ -0,0 +1,212 @@
+//! Embedded replicas of libSQL databases: local copies of a remote database
+//! that are kept up to date by periodically syncing from it.
+//!
+//! Reads are served from the local copy, so they may lag behind the remote
+//! database. Writes are forwarded to the remote database.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use spin_factor_sqlite::{Connection, ConnectionCreator};
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
+use tokio::sync::OnceCell;
+
+use crate::LibSqlConnection;
+
+/// A [`ConnectionCreator`] for an embedded replica of a libSQL database.
+///
+/// The replica is opened and synced the first time a connection is made, and
+/// is then synced in the background every `sync_interval`.
+pub struct LibSqlReplica {
+    url: String,
+    token: String,
+    path: PathBuf,
+    sync_interval: Duration,
+    replica: OnceCell<Arc<Replica>>,
+}
+
+impl LibSqlReplica {
+    pub fn new(url: String, token: String, path: PathBuf, sync_interval: Duration) -> Self {
+        Self {
+            url,
+            token,
+            path,
+            sync_interval,
+            replica: OnceCell::new(),
+        }
+    }
+
+    async fn replica(&self) -> anyhow::Result<&Arc<Replica>> {
+        self.replica
+            .get_or_try_init(|| async {
+                let database =
+                    open_database(self.path.clone(), self.url.clone(), self.token.clone())
+                        .await
+                        .with_context(|| {
+                            format!("failed to open replica at {}", self.path.display())
+                        })?;
+                let replica = Arc::new(Replica {
+                    database,
+                    url: self.url.clone(),
+                    last_synced: Mutex::new(Instant::now()),
+                });
+                replica.sync().await.context("failed to sync replica")?;
+                spawn_sync_task(Arc::downgrade(&replica), self.sync_interval);
+                Ok(replica)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl ConnectionCreator for LibSqlReplica {
+    async fn create_connection(
+        &self,
+        label: &str,
+    ) -> Result<Box<dyn Connection + 'static>, v4::Error> {
+        let replica = self.replica().await.map_err(|e| {
+            v4::Error::Io(format!(
+                "failed to set up replica of SQLite database {label:?}: {e:?}"
+            ))
+        })?;
+        let inner = replica
+            .database
+            .connect()
+            .map_err(|_| v4::Error::InvalidConnection)?;
+        Ok(Box::new(ReplicaConnection {
+            connection: LibSqlConnection {
+                inner,
+                url: self.url.clone(),
+            },
+            replica: replica.clone(),
+            path: self.path.clone(),
+        }))
+    }
+}
+
+struct Replica {
+    database: libsql::Database,
+    url: String,
+    /// When the replica last finished syncing successfully.
+    last_synced: Mutex<Instant>,
+}
+
+impl Replica {
+    async fn sync(&self) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let result = sync_database(&self.database).await;
+        spin_telemetry::metrics::histogram!(
+            spin.libsql.sync_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            database = self.url.as_str(),
+            success = result.is_ok()
+        );
+        match &result {
+            Ok(()) => *self.last_synced.lock().unwrap() = Instant::now(),
+            Err(_) => spin_telemetry::metrics::monotonic_counter!(
+                spin.libsql.sync_errors = 1,
+                database = self.url.as_str()
+            ),
+        }
+        result
+    }
+
+    /// How far the replica may be behind the remote database.
+    fn lag(&self) -> Duration {
+        self.last_synced.lock().unwrap().elapsed()
+    }
+}
+
+/// Syncs the replica every `interval` for as long as it is in use.
+fn spawn_sync_task(replica: Weak<Replica>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        // The first tick completes immediately, but the replica was just synced.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(replica) = replica.upgrade() else {
+                break;
+            };
+            if let Err(e) = replica.sync().await {
+                tracing::warn!("failed to sync libSQL replica of {}: {e:?}", replica.url);
+            }
+        }
+    });
+}
+
+#[cfg(feature = "replica")]
+async fn open_database(
+    path: PathBuf,
+    url: String,
+    token: String,
+) -> anyhow::Result<libsql::Database> {
+    Ok(libsql::Builder::new_remote_replica(path, url, token)
+        .build()
+        .await?)
+}
+
+#[cfg(not(feature = "replica"))]
+async fn open_database(
+    _path: PathBuf,
+    _url: String,
+    _token: String,
+) -> anyhow::Result<libsql::Database> {
+    anyhow::bail!("this build of Spin does not support libSQL embedded replicas")
+}
+
+#[cfg(feature = "replica")]
+async fn sync_database(database: &libsql::Database) -> anyhow::Result<()> {
+    database.sync().await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "replica"))]
+async fn sync_database(_database: &libsql::Database) -> anyhow::Result<()> {
+    anyhow::bail!("this build of Spin does not support libSQL embedded replicas")
+}
+
+/// A connection to an embedded replica, which records the replica's lag
+/// each time it is queried.
+struct ReplicaConnection {
+    connection: LibSqlConnection,
+    replica: Arc<Replica>,
+    path: PathBuf,
+}
+
+#[async_trait]
+impl Connection for ReplicaConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<v4::Value>,
+    ) -> Result<v4::QueryResult, v4::Error> {
+        spin_telemetry::metrics::histogram!(
+            spin.libsql.replication_lag_ms = self.replica.lag().as_secs_f64() * 1000.0,
+            database = self.replica.url.as_str()
+        );
+        self.connection.query(query, parameters).await
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        self.connection.execute_batch(statements).await
+    }
+
+    async fn changes(&self) -> Result<u64, v4::Error> {
+        Ok(self.connection.changes())
+    }
+
+    async fn last_insert_rowid(&self) -> Result<i64, v4::Error> {
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!(
+            "libSQL replica of {} at {}",
+            self.replica.url,
+            self.path.display()
+        ))
+    }
+}
@@ -4,8 +4,11 @@ use std::{
     collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
+use chrono::{DateTime, Utc};
+use schemars::{schema::RootSchema, JsonSchema};
 use serde::Deserialize;
 use spin_factor_sqlite::ConnectionCreator;
 use spin_factors::{
@@ -13,7 +16,7 @@ use spin_factors::{
     runtime_config::toml::GetTomlValue,
 };
 use spin_sqlite_inproc::InProcDatabaseLocation;
-use spin_sqlite_libsql::LazyLibSqlConnection;
+use spin_sqlite_libsql::{LazyLibSqlConnection, LibSqlBranch, LibSqlReplica, TursoPlatform};
 
 /// Spin's default resolution of runtime configuration for SQLite databases.
 ///
@@ -52,6 +55,13 @@ impl RuntimeConfigResolver {
     /// ```
     ///
     /// Configuration is automatically added for the 'default' label if it is not provided.
+    ///
+    /// Components may use other databases in place of the labels in their
+    /// manifest:
+    /// ```toml
+    /// [component_stores.$component-id]
+    /// sqlite_database = { $label = "$database-label" }
+    /// ```
     pub fn resolve(
         &self,
         table: &impl GetTomlValue,
@@ -63,6 +73,13 @@ impl RuntimeConfigResolver {
                 .connection_creators
                 .insert("default".to_owned(), self.default());
         }
+        if let Some(table) = table.get("component_stores") {
+            let components: HashMap<String, ComponentStores> = table.clone().try_into()?;
+            runtime_config.component_labels = components
+                .into_iter()
+                .map(|(component_id, stores)| (component_id, stores.sqlite_database))
+                .collect();
+        }
 
         Ok(runtime_config)
     }
@@ -84,6 +101,7 @@ impl RuntimeConfigResolver {
 
         Ok(Some(spin_factor_sqlite::runtime_config::RuntimeConfig {
             connection_creators,
+            ..Default::default()
         }))
     }
 
@@ -102,11 +120,34 @@ impl RuntimeConfigResolver {
             }
             "libsql" => {
                 let config: LibSqlDatabase = config.config.try_into()?;
+                config.connection_creator(&self.local_database_dir)
+            }
+            "memory" => {
+                let config: MemoryDatabase = config.config.try_into()?;
                 Ok(Arc::new(config.connection_creator()?))
             }
             _ => anyhow::bail!("Unknown database kind: {database_kind}"),
         }
     }
+
+    /// The JSON schemas of the supported database types' runtime config, by
+    /// database type.
+    pub fn database_type_schemas(&self) -> Vec<(&'static str, RootSchema)> {
+        vec![
+            ("spin", schemars::schema_for!(InProcDatabase)),
+            ("libsql", schemars::schema_for!(LibSqlDatabase)),
+            ("memory", schemars::schema_for!(MemoryDatabase)),
+        ]
+    }
+}
+
+/// The databases a component uses in place of the labels in the manifest.
+///
+/// Other kinds of stores may be overridden in the same table.
+#[derive(Deserialize)]
+struct ComponentStores {
+    #[serde(default)]
+    sqlite_database: HashMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -136,7 +177,7 @@ impl RuntimeConfigResolver {
 const DEFAULT_SQLITE_DB_FILENAME: &str = "sqlite_db.db";
 
 /// Configuration for a local SQLite database.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct InProcDatabase {
     pub path: Option<PathBuf>,
@@ -160,6 +201,24 @@ impl InProcDatabase {
     }
 }
 
+/// Configuration for an in-memory SQLite database.
+///
+/// Unlike a local database without a path, every connection to the database
+/// shares its contents, which last as long as the app is running.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryDatabase {}
+
+impl MemoryDatabase {
+    /// Get a new connection creator for an in-memory database.
+    fn connection_creator(self) -> anyhow::Result<impl ConnectionCreator> {
+        let connection =
+            spin_sqlite_inproc::InProcConnection::new(InProcDatabaseLocation::InMemory)?;
+        let factory = move || Ok(Box::new(connection.clone()) as _);
+        Ok(factory)
+    }
+}
+
 /// Resolve a relative path against a base dir.
 ///
 /// If the path is absolute, it is returned as is. Otherwise, it is resolved against the base dir.
@@ -173,29 +232,129 @@ fn resolve_relative_path(path: &Path, base_dir: &Path) -> PathBuf {
 /// Configuration for a libSQL database.
 ///
 /// This is used to deserialize the specific runtime config toml for libSQL databases.
-#[derive(Clone, Debug, Deserialize)]
+///
+/// Instead of a `url`, a database may be a branch of a Turso database, which
+/// is created on first use if it doesn't exist:
+/// ```toml
+/// [sqlite_database.staging]
+/// type = "libsql"
+/// token = "$group-token"
+/// branch = { organization = "acme", api_token = "$platform-token", from = "prod", name = "prod-staging" }
+/// ```
+///
+/// A database with a `url` may also be read from an embedded replica, a local
+/// copy which is synced from the remote database in the background:
+/// ```toml
+/// [sqlite_database.default]
+/// type = "libsql"
+/// url = "https://example.turso.io"
+/// token = "$token"
+/// replica = { path = "replica.db", sync_interval_secs = 5 }
+/// ```
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct LibSqlDatabase {
-    url: String,
+    url: Option<String>,
     token: String,
+    branch: Option<LibSqlBranchConfig>,
+    replica: Option<LibSqlReplicaConfig>,
+}
+
+/// Configuration for an embedded replica of a libSQL database.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LibSqlReplicaConfig {
+    /// Where to store the replica.
+    path: PathBuf,
+    /// How often to sync the replica from the remote database. Defaults to
+    /// 60 seconds.
+    sync_interval_secs: Option<u64>,
+}
+
+const DEFAULT_REPLICA_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Configuration for a branch of a Turso database.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LibSqlBranchConfig {
+    /// The Turso organization the databases belong to.
+    organization: String,
+    /// A Turso Platform API token for the organization.
+    api_token: String,
+    /// The database to branch from.
+    from: String,
+    /// The name of the branch database.
+    name: String,
+    /// Branch from the contents the database had at this time (RFC 3339)
+    /// rather than its current contents.
+    timestamp: Option<String>,
 }
 
 impl LibSqlDatabase {
     /// Get a new connection creator for a libSQL database.
-    fn connection_creator(self) -> anyhow::Result<impl ConnectionCreator> {
-        let url = check_url(&self.url)
-            .with_context(|| {
-                format!(
-                    "unexpected libSQL URL '{}' in runtime config file ",
-                    self.url
-                )
-            })?
-            .to_owned();
-        let factory = move || {
-            let connection = LazyLibSqlConnection::new(url.clone(), self.token.clone());
-            Ok(Box::new(connection) as _)
-        };
-        Ok(factory)
+    ///
+    /// `base_dir` is the base directory path from which a replica's `path` is
+    /// resolved if it is a relative path.
+    fn connection_creator(self, base_dir: &Path) -> anyhow::Result<Arc<dyn ConnectionCreator>> {
+        match (self.url, self.branch) {
+            (Some(url), None) => {
+                let url = check_url(&url)
+                    .with_context(|| {
+                        format!("unexpected libSQL URL '{url}' in runtime config file ")
+                    })?
+                    .to_owned();
+                let token = self.token;
+                if let Some(replica) = self.replica {
+                    let path = resolve_relative_path(&replica.path, base_dir);
+                    let sync_interval = replica
+                        .sync_interval_secs
+                        .map_or(DEFAULT_REPLICA_SYNC_INTERVAL, Duration::from_secs);
+                    anyhow::ensure!(
+                        !sync_interval.is_zero(),
+                        "a libSQL replica's `sync_interval_secs` must be at least 1"
+                    );
+                    return Ok(Arc::new(LibSqlReplica::new(
+                        url,
+                        token,
+                        path,
+                        sync_interval,
+                    )));
+                }
+                let factory = move || {
+                    let connection = LazyLibSqlConnection::new(url.clone(), token.clone());
+                    Ok(Box::new(connection) as _)
+                };
+                Ok(Arc::new(factory))
+            }
+            (None, Some(branch)) => {
+                anyhow::ensure!(
+                    self.replica.is_none(),
+                    "a libSQL branch can't have a `replica`"
+                );
+                let timestamp = branch
+                    .timestamp
+                    .map(|timestamp| {
+                        DateTime::parse_from_rfc3339(&timestamp)
+                            .map(|timestamp| timestamp.with_timezone(&Utc))
+                            .with_context(|| {
+                                format!("invalid libSQL branch timestamp '{timestamp}' in runtime config file")
+                            })
+                    })
+                    .transpose()?;
+                let platform = TursoPlatform::new(branch.organization, branch.api_token);
+                Ok(Arc::new(LibSqlBranch::new(
+                    platform,
+                    branch.from,
+                    branch.name,
+                    timestamp,
+                    self.token,
+                )))
+            }
+            (Some(_), Some(_)) => {
+                anyhow::bail!("a libSQL database can't have both a `url` and a `branch`")
+            }
+            (None, None) => anyhow::bail!("a libSQL database needs a `url` or a `branch`"),
+        }
     }
 }
 
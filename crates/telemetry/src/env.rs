@@ -11,6 +11,7 @@ const OTEL_EXPORTER_OTLP_TRACES_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_TRACES_PROT
 const OTEL_EXPORTER_OTLP_METRICS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL";
 const OTEL_EXPORTER_OTLP_LOGS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_LOGS_PROTOCOL";
 const SPIN_DISABLE_LOG_TO_TRACING: &str = "SPIN_DISABLE_LOG_TO_TRACING";
+const SPIN_LOG_FORMAT: &str = "SPIN_LOG_FORMAT";
 
 /// Returns a boolean indicating if the OTEL tracing layer should be enabled.
 ///
@@ -63,6 +64,13 @@ pub(crate) fn spin_disable_log_to_tracing() -> bool {
     any_vars_set(&[SPIN_DISABLE_LOG_TO_TRACING])
 }
 
+/// Returns a boolean indicating if Spin's own logs should be written as JSON.
+///
+/// They are written as JSON if the environment variable `SPIN_LOG_FORMAT` is set to `json`.
+pub(crate) fn json_logs_enabled() -> bool {
+    std::env::var(SPIN_LOG_FORMAT).is_ok_and(|format| format.eq_ignore_ascii_case("json"))
+}
+
 fn any_vars_set(enabling_vars: &[&str]) -> bool {
     enabling_vars
         .iter()
@@ -1,10 +1,12 @@
 use std::io::IsTerminal;
 
 use anyhow::Context;
+use env::json_logs_enabled;
 use env::otel_logs_enabled;
 use env::otel_metrics_enabled;
 use env::otel_tracing_enabled;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
+use redaction::RedactingMakeWriter;
 use tracing_subscriber::{fmt, prelude::*, registry, EnvFilter, Layer};
 
 mod alert_in_dev;
@@ -13,19 +15,23 @@ mod env;
 pub mod logs;
 pub mod metrics;
 mod propagation;
+pub mod redaction;
+pub mod sampling;
 mod traces;
 
 #[cfg(feature = "testing")]
 pub mod testing;
 
 pub use propagation::extract_trace_context;
+pub use propagation::extract_trace_context_from_payload;
 pub use propagation::inject_trace_context;
+pub use propagation::inject_trace_context_into_payload;
 
 /// Initializes telemetry for Spin using the [tracing] library.
 ///
 /// Under the hood this involves initializing a [tracing::Subscriber] with multiple [Layer]s. One
 /// [Layer] emits [tracing] events to stderr, another sends spans to an OTel collector, and another
-/// sends metrics to an OTel collector.
+/// sends metrics to an OTel collector and, if enabled, collects them for Prometheus.
 ///
 /// Configuration for the OTel layers is pulled from the environment.
 ///
@@ -53,21 +59,30 @@ pub use propagation::inject_trace_context;
 /// spin_telemetry::metrics::monotonic_counter!(spin.metric_name = 1, metric_attribute = "value");
 /// ```
 pub fn init(spin_version: String) -> anyhow::Result<()> {
-    // This layer will print all tracing library log messages to stderr.
-    let fmt_layer = fmt::layer()
-        .with_writer(std::io::stderr)
-        .with_ansi(std::io::stderr().is_terminal())
-        .with_filter(
-            // Filter directives explained here https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives
-            EnvFilter::from_default_env()
-                // Wasmtime is too noisy
-                .add_directive("wasmtime_wasi_http=warn".parse()?)
-                // Watchexec is too noisy
-                .add_directive("watchexec=off".parse()?)
-                // We don't want to duplicate application logs
-                .add_directive("[{app_log}]=off".parse()?)
-                .add_directive("[{app_log_non_utf8}]=off".parse()?),
-        );
+    // This layer will print all tracing library log messages to stderr, as JSON objects if
+    // SPIN_LOG_FORMAT=json. Secrets are redacted from them.
+    let fmt_layer = if json_logs_enabled() {
+        fmt::layer()
+            .json()
+            .with_writer(RedactingMakeWriter(std::io::stderr))
+            .boxed()
+    } else {
+        fmt::layer()
+            .with_writer(RedactingMakeWriter(std::io::stderr))
+            .with_ansi(std::io::stderr().is_terminal())
+            .boxed()
+    }
+    .with_filter(
+        // Filter directives explained here https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives
+        EnvFilter::from_default_env()
+            // Wasmtime is too noisy
+            .add_directive("wasmtime_wasi_http=warn".parse()?)
+            // Watchexec is too noisy
+            .add_directive("watchexec=off".parse()?)
+            // We don't want to duplicate application logs
+            .add_directive("[{app_log}]=off".parse()?)
+            .add_directive("[{app_log_non_utf8}]=off".parse()?),
+    );
 
     let otel_tracing_layer = if otel_tracing_enabled() {
         Some(
@@ -78,21 +93,16 @@ pub fn init(spin_version: String) -> anyhow::Result<()> {
         None
     };
 
-    let otel_metrics_layer = if otel_metrics_enabled() {
-        Some(
-            metrics::otel_metrics_layer(spin_version.clone())
-                .context("failed to initialize otel metrics")?,
-        )
-    } else {
-        None
-    };
+    // Always present so that Prometheus collection can be enabled later.
+    let metrics_layer = metrics::metrics_layer(spin_version.clone(), otel_metrics_enabled())
+        .context("failed to initialize metrics")?;
 
     let alert_in_dev_layer = alert_in_dev::alert_in_dev_layer();
 
     // Build a registry subscriber with the layers we want to use.
     registry()
         .with(otel_tracing_layer)
-        .with(otel_metrics_layer)
+        .with(metrics_layer)
         .with(fmt_layer)
         .with(alert_in_dev_layer)
         .init();
@@ -1,7 +1,7 @@
 use std::{ascii::escape_default, sync::OnceLock};
 
 use anyhow::bail;
-use opentelemetry::logs::{LogRecord, Logger, LoggerProvider};
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
 use opentelemetry_sdk::{
     logs::{BatchConfigBuilder, BatchLogProcessor, SdkLogger},
     resource::{EnvResourceDetector, ResourceDetector, TelemetryResourceDetector},
@@ -10,40 +10,250 @@ use opentelemetry_sdk::{
 
 use crate::{
     detector::SpinResourceDetector,
-    env::{self, otel_logs_enabled, OtlpProtocol},
+    env::{self, OtlpProtocol},
+    redaction::redact,
 };
 
 static LOGGER: OnceLock<SdkLogger> = OnceLock::new();
 
+/// Where an application log came from.
+#[derive(Clone, Copy, Debug)]
+pub struct AppLogSource<'a> {
+    /// The ID of the component that wrote the log.
+    pub component_id: &'a str,
+    /// The stream the log was written to.
+    pub stream: AppLogStream,
+    /// The ID of the request the component was handling, if known.
+    pub request_id: Option<&'a str>,
+}
+
+/// The stream an application log was written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppLogStream {
+    Stdout,
+    Stderr,
+}
+
+impl AppLogStream {
+    /// The name of the stream, e.g. for use in file names.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+
+    /// The level logs written to the stream are reported at.
+    pub fn level(&self) -> &'static str {
+        match self {
+            Self::Stdout => "info",
+            Self::Stderr => "error",
+        }
+    }
+}
+
 /// Handle an application log. Has the potential to both forward the log to OTel and to emit it as a
 /// tracing event.
-pub fn handle_app_log(buf: &[u8]) {
-    app_log_to_otel(buf);
+pub fn handle_app_log(buf: &[u8], source: AppLogSource) {
+    app_log_to_otel(buf, source);
     app_log_to_tracing_event(buf);
 }
 
 /// Forward the app log to OTel.
-fn app_log_to_otel(buf: &[u8]) {
-    if !otel_logs_enabled() {
+fn app_log_to_otel(buf: &[u8], source: AppLogSource) {
+    if !env::otel_logs_enabled() {
         return;
     }
 
     if let Some(logger) = LOGGER.get() {
+        let mut record = logger.create_log_record();
         if let Ok(s) = std::str::from_utf8(buf) {
-            let mut record = logger.create_log_record();
-            record.set_body(s.to_string().into());
-            logger.emit(record);
+            record.set_body(redact(s).into_owned().into());
         } else {
-            let mut record = logger.create_log_record();
             record.set_body(escape_non_utf8_buf(buf).into());
             record.add_attribute("app_log_non_utf8", true);
-            logger.emit(record);
         }
+        record.set_severity_number(match source.stream {
+            AppLogStream::Stdout => Severity::Info,
+            AppLogStream::Stderr => Severity::Error,
+        });
+        record.set_severity_text(source.stream.level());
+        record.add_attribute("spin.component_id", source.component_id.to_owned());
+        record.add_attribute("log.iostream", source.stream.as_str());
+        if let Some(request_id) = source.request_id {
+            record.add_attribute("spin.request_id", request_id.to_owned());
+        }
+        logger.emit(record);
     } else {
         tracing::trace!("OTel logger not initialized, failed to log");
     }
 }
 
+/// The severity of a structured log record from a guest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GuestLogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl GuestLogLevel {
+    /// The name of the level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self {
+            Self::Trace => Severity::Trace,
+            Self::Debug => Severity::Debug,
+            Self::Info => Severity::Info,
+            Self::Warn => Severity::Warn,
+            Self::Error => Severity::Error,
+        }
+    }
+}
+
+/// The value of a structured field of a guest log record.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuestLogValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl std::fmt::Display for GuestLogValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String(s) => write!(f, "{s:?}"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::Boolean(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// A structured log record written by a guest.
+#[derive(Clone, Copy, Debug)]
+pub struct GuestLogRecord<'a> {
+    /// The ID of the component that wrote the record.
+    pub component_id: &'a str,
+    /// The ID of the request the component was handling, if known.
+    pub request_id: Option<&'a str>,
+    pub level: GuestLogLevel,
+    /// The part of the component the record comes from, if given.
+    pub target: Option<&'a str>,
+    pub message: &'a str,
+    pub fields: &'a [(String, GuestLogValue)],
+}
+
+/// Handle a structured log record from a guest, forwarding it to OTel and
+/// emitting it as a tracing event.
+pub fn handle_guest_log(record: GuestLogRecord) {
+    guest_log_to_otel(&record);
+    guest_log_to_tracing_event(&record);
+}
+
+fn guest_log_to_otel(record: &GuestLogRecord) {
+    if !env::otel_logs_enabled() {
+        return;
+    }
+    let Some(logger) = LOGGER.get() else {
+        tracing::trace!("OTel logger not initialized, failed to log");
+        return;
+    };
+    let mut otel_record = logger.create_log_record();
+    otel_record.set_body(redact(record.message).into_owned().into());
+    otel_record.set_severity_number(record.level.severity());
+    otel_record.set_severity_text(record.level.as_str());
+    otel_record.add_attribute("spin.component_id", record.component_id.to_owned());
+    if let Some(request_id) = record.request_id {
+        otel_record.add_attribute("spin.request_id", request_id.to_owned());
+    }
+    if let Some(target) = record.target {
+        otel_record.add_attribute("spin.log.target", target.to_owned());
+    }
+    for (key, value) in record.fields {
+        let value: AnyValue = match value {
+            GuestLogValue::String(s) => redact(s).into_owned().into(),
+            GuestLogValue::Integer(i) => (*i).into(),
+            GuestLogValue::Float(x) => (*x).into(),
+            GuestLogValue::Boolean(b) => (*b).into(),
+        };
+        otel_record.add_attribute(key.clone(), value);
+    }
+    logger.emit(otel_record);
+}
+
+fn guest_log_to_tracing_event(record: &GuestLogRecord) {
+    static CELL: OnceLock<bool> = OnceLock::new();
+    if *CELL.get_or_init(env::spin_disable_log_to_tracing) {
+        return;
+    }
+
+    let fields = record
+        .fields
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let target = record.target.unwrap_or_default();
+    macro_rules! event {
+        ($level:expr) => {
+            tracing::event!(
+                $level,
+                component_id = record.component_id,
+                guest_target = target,
+                guest_fields = fields,
+                guest_log = record.message,
+            )
+        };
+    }
+    match record.level {
+        GuestLogLevel::Trace => event!(tracing::Level::TRACE),
+        GuestLogLevel::Debug => event!(tracing::Level::DEBUG),
+        GuestLogLevel::Info => event!(tracing::Level::INFO),
+        GuestLogLevel::Warn => event!(tracing::Level::WARN),
+        GuestLogLevel::Error => event!(tracing::Level::ERROR),
+    }
+}
+
+/// Returns whether logs are exported with OTLP.
+pub fn otel_logs_enabled() -> bool {
+    env::otel_logs_enabled()
+}
+
+/// Exports an audit record as an OTel log with the given attributes.
+///
+/// Errors if OTel log export isn't initialized.
+pub fn export_audit_record(
+    body: String,
+    attributes: Vec<(&'static str, String)>,
+) -> anyhow::Result<()> {
+    let Some(logger) = LOGGER.get() else {
+        bail!("OTel logger not initialized");
+    };
+    let mut record = logger.create_log_record();
+    record.set_body(body.into());
+    record.set_severity_number(Severity::Info);
+    record.set_severity_text("info");
+    record.add_attribute("event.name", "spin.audit.invocation");
+    for (key, value) in attributes {
+        record.add_attribute(key, value);
+    }
+    logger.emit(record);
+    Ok(())
+}
+
 /// Takes a Spin application log and emits it as a tracing event. This acts as a compatibility layer
 /// to easily get Spin app logs as events in our OTel traces.
 fn app_log_to_tracing_event(buf: &[u8]) {
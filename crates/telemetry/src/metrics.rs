@@ -1,23 +1,52 @@
-use anyhow::{bail, Result};
-use opentelemetry::global;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use anyhow::{bail, Context, Result};
+use opentelemetry::{
+    global,
+    metrics::{Counter, Gauge, Histogram},
+    KeyValue,
+};
 use opentelemetry_sdk::{
     metrics::{PeriodicReader, SdkMeterProvider},
     resource::{EnvResourceDetector, ResourceDetector, TelemetryResourceDetector},
     Resource,
 };
-use tracing::Subscriber;
+use tracing::{span, Subscriber};
 use tracing_opentelemetry::MetricsLayer;
-use tracing_subscriber::{registry::LookupSpan, Layer};
+use tracing_subscriber::{
+    filter::filter_fn, layer::Context as LayerContext, registry::LookupSpan, Layer,
+};
 
 use crate::{detector::SpinResourceDetector, env::OtlpProtocol};
 
-/// Constructs a layer for the tracing subscriber that sends metrics to an OTEL collector.
+/// Span name prefixes of factor host calls which are counted in
+/// `spin.factor_call_count`, and the factor name each is recorded under.
+const FACTOR_SPAN_PREFIXES: &[(&str, &str)] = &[
+    ("spin_key_value", "key_value"),
+    ("spin_outbound_redis", "redis"),
+    ("spin_sqlite", "sqlite"),
+];
+
+static PROMETHEUS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Constructs a layer for the tracing subscriber that records metrics.
+///
+/// Metrics are sent to an OTEL collector if `otlp_enabled` is set, and are
+/// collected for Prometheus once [`enable_prometheus`] has been called. Until
+/// either is the case, metric events are ignored.
 ///
 /// It pulls OTEL configuration from the environment based on the variables defined
 /// [here](https://opentelemetry.io/docs/specs/otel/protocol/exporter/) and
 /// [here](https://opentelemetry.io/docs/specs/otel/configuration/sdk-environment-variables/#general-sdk-configuration).
-pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
+pub(crate) fn metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
     spin_version: String,
+    otlp_enabled: bool,
 ) -> Result<impl Layer<S>> {
     let resource = Resource::builder()
         .with_detectors(&[
@@ -31,29 +60,228 @@ pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
         ])
         .build();
 
-    // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
-    // currently default to using the HTTP exporter but in the future we could select off of the
-    // combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL to
-    // determine whether we should use http/protobuf or grpc.
-    let exporter = match OtlpProtocol::metrics_protocol_from_env() {
-        OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
-            .with_tonic()
-            .build()?,
-        OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
-            .with_http()
-            .build()?,
-        OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
-    };
+    let mut builder = SdkMeterProvider::builder().with_resource(resource);
 
-    let reader = PeriodicReader::builder(exporter).build();
-    let meter_provider = SdkMeterProvider::builder()
-        .with_reader(reader)
-        .with_resource(resource)
-        .build();
+    if otlp_enabled {
+        // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
+        // currently default to using the HTTP exporter but in the future we could select off of the
+        // combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL to
+        // determine whether we should use http/protobuf or grpc.
+        let exporter = match OtlpProtocol::metrics_protocol_from_env() {
+            OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .build()?,
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .build()?,
+            OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
+        };
+        builder = builder.with_reader(PeriodicReader::builder(exporter).build());
+    }
+
+    let prometheus_exporter = opentelemetry_prometheus::exporter()
+        .with_registry(prometheus_registry().clone())
+        .build()
+        .context("failed to initialize Prometheus exporter")?;
+    let meter_provider = builder.with_reader(prometheus_exporter).build();
 
     global::set_meter_provider(meter_provider.clone());
 
-    Ok(MetricsLayer::new(meter_provider))
+    let factor_calls = FactorCallLayer {
+        counter: global::meter("spin")
+            .u64_counter("spin.factor_call_count")
+            .with_description("Calls from components to host factors")
+            .build(),
+    };
+
+    Ok(MetricsLayer::new(meter_provider)
+        .and_then(factor_calls)
+        .with_filter(filter_fn(move |_| {
+            otlp_enabled || PROMETHEUS_ENABLED.load(Ordering::Relaxed)
+        })))
+}
+
+/// Starts collecting metrics for Prometheus.
+///
+/// Metrics emitted before this is called are not collected.
+pub fn enable_prometheus() {
+    PROMETHEUS_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Returns the collected metrics in the Prometheus text exposition format.
+pub fn prometheus_text() -> Result<String> {
+    use prometheus::Encoder;
+
+    let mut buf = Vec::new();
+    prometheus::TextEncoder::new()
+        .encode(&prometheus_registry().gather(), &mut buf)
+        .context("failed to encode Prometheus metrics")?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// The registry that Prometheus metrics are collected in.
+fn prometheus_registry() -> &'static prometheus::Registry {
+    static REGISTRY: OnceLock<prometheus::Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let registry = prometheus::Registry::new();
+        // Reports the memory and CPU usage of the Spin process
+        #[cfg(target_os = "linux")]
+        if let Err(err) = registry.register(Box::new(
+            prometheus::process_collector::ProcessCollector::for_self(),
+        )) {
+            tracing::warn!("Failed to register process metrics: {err}");
+        }
+        registry
+    })
+}
+
+/// Counts factor host calls from the spans that instrument them.
+///
+/// Host calls are instrumented with spans named `<factor prefix>.<operation>`,
+/// e.g. `spin_key_value.get`, so counting spans avoids instrumenting every
+/// host call a second time.
+struct FactorCallLayer {
+    counter: Counter<u64>,
+}
+
+impl<S: Subscriber + for<'span> LookupSpan<'span>> Layer<S> for FactorCallLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: LayerContext<'_, S>) {
+        if let Some((factor, operation)) = factor_call(attrs.metadata().name()) {
+            self.counter.add(
+                1,
+                &[
+                    KeyValue::new("factor", factor),
+                    KeyValue::new("operation", operation.to_owned()),
+                ],
+            );
+        }
+    }
+}
+
+/// Returns the factor and operation of a factor host call span name.
+fn factor_call(span_name: &str) -> Option<(&'static str, &str)> {
+    let (prefix, operation) = span_name.split_once('.')?;
+    FACTOR_SPAN_PREFIXES
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, factor)| (*factor, operation))
+}
+
+/// The most distinct metrics guests may record, to bound the memory and
+/// cardinality of exported metrics.
+const MAX_GUEST_METRICS: usize = 1000;
+
+/// The most labels a guest may attach to a measurement.
+const MAX_GUEST_LABELS: usize = 16;
+
+/// The kind of a metric recorded by a guest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuestMetricKind {
+    /// A monotonic sum.
+    Counter,
+    /// A value which is set rather than added to.
+    Gauge,
+    /// A distribution of values.
+    Histogram,
+}
+
+/// Why a guest measurement was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GuestMetricError {
+    /// The metric or label name is malformed or reserved.
+    InvalidName,
+    /// The value isn't finite, or is negative for a counter.
+    InvalidValue,
+    /// The metric was recorded before as a different kind.
+    KindMismatch,
+    /// Guests have recorded too many distinct metrics, or the measurement
+    /// has too many labels.
+    TooManyMetrics,
+}
+
+enum GuestInstrument {
+    Counter(Counter<f64>),
+    Gauge(Gauge<f64>),
+    Histogram(Histogram<f64>),
+}
+
+impl GuestInstrument {
+    fn new(kind: GuestMetricKind, name: &str) -> Self {
+        let meter = global::meter("spin.guest");
+        let name = name.to_owned();
+        match kind {
+            GuestMetricKind::Counter => Self::Counter(meter.f64_counter(name).build()),
+            GuestMetricKind::Gauge => Self::Gauge(meter.f64_gauge(name).build()),
+            GuestMetricKind::Histogram => Self::Histogram(meter.f64_histogram(name).build()),
+        }
+    }
+
+    fn kind(&self) -> GuestMetricKind {
+        match self {
+            Self::Counter(_) => GuestMetricKind::Counter,
+            Self::Gauge(_) => GuestMetricKind::Gauge,
+            Self::Histogram(_) => GuestMetricKind::Histogram,
+        }
+    }
+}
+
+/// Records a measurement of a metric on behalf of a guest.
+///
+/// Instruments are created on first use and are shared by all components,
+/// which are told apart by a `component_id` label. Names starting with
+/// `spin.` are reserved for the runtime's own metrics.
+pub fn record_guest_metric(
+    component_id: &str,
+    kind: GuestMetricKind,
+    name: &str,
+    value: f64,
+    labels: &[(String, String)],
+) -> Result<(), GuestMetricError> {
+    static INSTRUMENTS: OnceLock<Mutex<HashMap<String, GuestInstrument>>> = OnceLock::new();
+
+    if !is_valid_guest_metric_name(name) || name.starts_with("spin.") {
+        return Err(GuestMetricError::InvalidName);
+    }
+    if !value.is_finite() || (kind == GuestMetricKind::Counter && value < 0.0) {
+        return Err(GuestMetricError::InvalidValue);
+    }
+    if labels.len() > MAX_GUEST_LABELS {
+        return Err(GuestMetricError::TooManyMetrics);
+    }
+    let mut attributes = Vec::with_capacity(labels.len() + 1);
+    for (key, value) in labels {
+        if !is_valid_guest_metric_name(key) || key == "component_id" {
+            return Err(GuestMetricError::InvalidName);
+        }
+        attributes.push(KeyValue::new(key.clone(), value.clone()));
+    }
+    attributes.push(KeyValue::new("component_id", component_id.to_owned()));
+
+    let mut instruments = INSTRUMENTS.get_or_init(Default::default).lock().unwrap();
+    if !instruments.contains_key(name) && instruments.len() >= MAX_GUEST_METRICS {
+        return Err(GuestMetricError::TooManyMetrics);
+    }
+    let instrument = instruments
+        .entry(name.to_owned())
+        .or_insert_with(|| GuestInstrument::new(kind, name));
+    if instrument.kind() != kind {
+        return Err(GuestMetricError::KindMismatch);
+    }
+    match instrument {
+        GuestInstrument::Counter(counter) => counter.add(value, &attributes),
+        GuestInstrument::Gauge(gauge) => gauge.record(value, &attributes),
+        GuestInstrument::Histogram(histogram) => histogram.record(value, &attributes),
+    }
+    Ok(())
+}
+
+/// Returns whether `name` is valid as the name of a guest metric or label:
+/// an ASCII letter followed by up to 254 ASCII alphanumerics, `_` or `.`.
+fn is_valid_guest_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    name.len() <= 255
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
 }
 
 #[macro_export]
@@ -104,3 +332,63 @@ macro_rules! monotonic_counter {
 pub use counter;
 pub use histogram;
 pub use monotonic_counter;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factor_calls_are_recognized_by_span_name() {
+        assert_eq!(
+            factor_call("spin_key_value.get"),
+            Some(("key_value", "get"))
+        );
+        assert_eq!(
+            factor_call("spin_outbound_redis.publish"),
+            Some(("redis", "publish"))
+        );
+        assert_eq!(
+            factor_call("spin_sqlite.execute"),
+            Some(("sqlite", "execute"))
+        );
+        assert_eq!(factor_call("spin_outbound_http.send_request"), None);
+        assert_eq!(factor_call("spin_key_value"), None);
+    }
+
+    #[test]
+    fn guest_metric_names_are_validated() {
+        assert!(is_valid_guest_metric_name("orders.processed"));
+        assert!(is_valid_guest_metric_name("queue_depth"));
+        assert!(!is_valid_guest_metric_name(""));
+        assert!(!is_valid_guest_metric_name("1st"));
+        assert!(!is_valid_guest_metric_name("has space"));
+    }
+
+    #[test]
+    fn guest_metrics_are_checked() {
+        let record = |kind, name, value, labels: &[(String, String)]| {
+            record_guest_metric("test-component", kind, name, value, labels)
+        };
+        assert_eq!(
+            record(GuestMetricKind::Counter, "test.orders", 1.0, &[]),
+            Ok(())
+        );
+        assert_eq!(
+            record(GuestMetricKind::Gauge, "test.orders", 1.0, &[]),
+            Err(GuestMetricError::KindMismatch)
+        );
+        assert_eq!(
+            record(GuestMetricKind::Counter, "test.orders", -1.0, &[]),
+            Err(GuestMetricError::InvalidValue)
+        );
+        assert_eq!(
+            record(GuestMetricKind::Histogram, "spin.request_count", 1.0, &[]),
+            Err(GuestMetricError::InvalidName)
+        );
+        let reserved = [("component_id".to_owned(), "other".to_owned())];
+        assert_eq!(
+            record(GuestMetricKind::Gauge, "test.depth", 1.0, &reserved),
+            Err(GuestMetricError::InvalidName)
+        );
+    }
+}
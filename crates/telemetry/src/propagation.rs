@@ -1,9 +1,14 @@
+use std::collections::HashMap;
+
 use opentelemetry::{
     global,
     propagation::{Extractor, Injector},
 };
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+/// The field which starts a trace context embedded in a message payload.
+const TRACEPARENT: &str = "traceparent";
+
 /// Injects the current W3C TraceContext into the provided request.
 pub fn inject_trace_context<'a>(req: impl Into<HeaderInjector<'a>>) {
     let mut injector = req.into();
@@ -22,6 +27,65 @@ pub fn extract_trace_context<'a>(req: impl Into<HeaderExtractor<'a>>) {
     tracing::Span::current().set_parent(parent_context);
 }
 
+/// Prefixes a message payload with the current W3C TraceContext, for messaging
+/// systems such as Redis pub/sub which have no headers to carry it in.
+///
+/// The context is embedded as `name: value` lines, starting with
+/// `traceparent`, followed by an empty line and then the original payload. If
+/// there is no current trace the payload is returned unchanged.
+pub fn inject_trace_context_into_payload(payload: &[u8]) -> Vec<u8> {
+    let mut fields = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        let context = tracing::Span::current().context();
+        propagator.inject_context(&context, &mut fields);
+    });
+    let Some(traceparent) = fields.remove(TRACEPARENT) else {
+        return payload.to_vec();
+    };
+    let mut fields: Vec<_> = fields.into_iter().collect();
+    fields.sort();
+
+    let mut message = format!("{TRACEPARENT}: {traceparent}\n");
+    for (name, value) in fields {
+        message.push_str(&format!("{name}: {value}\n"));
+    }
+    message.push('\n');
+    let mut message = message.into_bytes();
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Extracts a W3C TraceContext embedded in a message payload by
+/// [`inject_trace_context_into_payload`] and sets it as the parent of the
+/// current span. Returns the fields embedded with the context, sorted by name,
+/// and the payload without them.
+pub fn extract_trace_context_from_payload(payload: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let Some((fields, body)) = split_payload_trace_context(payload) else {
+        return (vec![], payload);
+    };
+    let parent_context = global::get_text_map_propagator(|propagator| propagator.extract(&fields));
+    tracing::Span::current().set_parent(parent_context);
+    let mut fields: Vec<_> = fields.into_iter().collect();
+    fields.sort();
+    (fields, body)
+}
+
+/// Splits a payload into the trace context fields embedded at its start, if
+/// any, and the rest of the payload.
+fn split_payload_trace_context(payload: &[u8]) -> Option<(HashMap<String, String>, &[u8])> {
+    if !payload.starts_with(format!("{TRACEPARENT}:").as_bytes()) {
+        return None;
+    }
+    let end = payload.windows(2).position(|w| w == b"\n\n")?;
+    let preamble = std::str::from_utf8(&payload[..end]).ok()?;
+    let mut fields = HashMap::new();
+    for line in preamble.lines() {
+        let (name, value) = line.split_once(':')?;
+        fields.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+    }
+    Some((fields, &payload[end + 2..]))
+}
+
 pub enum HeaderInjector<'a> {
     Http0(&'a mut http0::HeaderMap),
     Http1(&'a mut http1::HeaderMap),
@@ -108,3 +172,34 @@ impl<'a, T> From<&'a http1::Request<T>> for HeaderExtractor<'a> {
         Self::Http1(req.headers())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_trace_context_is_split_from_body() {
+        let payload = b"traceparent: 00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01\ntracestate: congo=t61rcWkgMzE\n\nhello\n\nworld";
+        let (fields, body) = split_payload_trace_context(payload).unwrap();
+        assert_eq!(
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            fields[TRACEPARENT]
+        );
+        assert_eq!("congo=t61rcWkgMzE", fields["tracestate"]);
+        assert_eq!(b"hello\n\nworld", body);
+    }
+
+    #[test]
+    fn payloads_without_trace_context_are_untouched() {
+        assert!(split_payload_trace_context(b"hello").is_none());
+        assert!(split_payload_trace_context(b"traceparent: unterminated").is_none());
+        let (fields, body) = extract_trace_context_from_payload(b"hello");
+        assert!(fields.is_empty());
+        assert_eq!(b"hello", body);
+        // With no current trace, nothing is injected
+        assert_eq!(
+            b"hello".to_vec(),
+            inject_trace_context_into_payload(b"hello")
+        );
+    }
+}
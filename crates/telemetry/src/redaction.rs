@@ -0,0 +1,266 @@
+//! Redaction of secrets from logs and traces.
+//!
+//! Secrets registered with [`add_secret`] are replaced with [`REDACTED`]
+//! wherever they appear in the log lines Spin writes to stderr, the spans it
+//! exports, and the logs it exports with OTel. This catches secrets which
+//! find their way into e.g. error messages that quote a connection URL.
+
+use std::{
+    borrow::Cow,
+    io,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use opentelemetry::{trace::Status, Array, Context, KeyValue, StringValue, Value};
+use opentelemetry_sdk::{
+    error::OTelSdkResult,
+    trace::{Span, SpanData, SpanProcessor},
+    Resource,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// What secrets are replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The length of the shortest secret which is redacted. Redacting shorter
+/// values would mangle unrelated text.
+pub const MIN_SECRET_LEN: usize = 6;
+
+/// Registers a secret to be redacted from now on.
+///
+/// The secret's percent-encoded form, as it would appear in a URL, is
+/// redacted too. Secrets shorter than [`MIN_SECRET_LEN`] are ignored.
+pub fn add_secret(secret: &str) {
+    if secret.len() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut secrets = secrets_lock().write().unwrap();
+    let encoded = percent_encode(secret);
+    let new = [secret.to_owned(), encoded]
+        .into_iter()
+        .filter(|value| !secrets.contains(value))
+        .collect::<Vec<_>>();
+    if new.is_empty() {
+        return;
+    }
+    let mut updated = secrets.to_vec();
+    updated.extend(new);
+    updated.dedup();
+    // Longer secrets first, so that a secret containing another is redacted
+    // as a whole
+    updated.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    *secrets = updated.into();
+}
+
+/// Returns `text` with any registered secrets replaced with [`REDACTED`].
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let secrets = secrets_lock().read().unwrap().clone();
+    let mut redacted = Cow::Borrowed(text);
+    for secret in secrets.iter() {
+        if redacted.contains(secret.as_str()) {
+            redacted = Cow::Owned(redacted.replace(secret.as_str(), REDACTED));
+        }
+    }
+    redacted
+}
+
+fn secrets_lock() -> &'static RwLock<Arc<[String]>> {
+    static SECRETS: OnceLock<RwLock<Arc<[String]>>> = OnceLock::new();
+    SECRETS.get_or_init(|| RwLock::new(Arc::new([])))
+}
+
+/// Percent-encodes everything but the characters URLs leave unreserved.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// A [`MakeWriter`] whose writers redact secrets from what they write.
+pub(crate) struct RedactingMakeWriter<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+pub(crate) struct RedactingWriter<W>(W);
+
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The fmt layer writes each event with a single call, so a secret
+        // isn't split between writes
+        if let Ok(text) = std::str::from_utf8(buf) {
+            if let Cow::Owned(redacted) = redact(text) {
+                self.0.write_all(redacted.as_bytes())?;
+                return Ok(buf.len());
+            }
+        }
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A [`SpanProcessor`] that redacts secrets from spans before passing them on
+/// to another processor.
+#[derive(Debug)]
+pub(crate) struct RedactingSpanProcessor<P> {
+    inner: P,
+}
+
+impl<P: SpanProcessor> RedactingSpanProcessor<P> {
+    pub(crate) fn new(inner: P) -> Self {
+        Self { inner }
+    }
+}
+
+impl<P: SpanProcessor> SpanProcessor for RedactingSpanProcessor<P> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        redact_span(&mut span);
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.inner.shutdown()
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+fn redact_span(span: &mut SpanData) {
+    redact_cow(&mut span.name);
+    redact_attributes(&mut span.attributes);
+    for event in span.events.events.iter_mut() {
+        redact_cow(&mut event.name);
+        redact_attributes(&mut event.attributes);
+    }
+    if let Status::Error { description } = &mut span.status {
+        redact_cow(description);
+    }
+}
+
+fn redact_cow(text: &mut Cow<'static, str>) {
+    if let Cow::Owned(redacted) = redact(text) {
+        *text = Cow::Owned(redacted);
+    }
+}
+
+fn redact_attributes(attributes: &mut [KeyValue]) {
+    for attribute in attributes {
+        match &mut attribute.value {
+            Value::String(value) => redact_string_value(value),
+            Value::Array(Array::String(values)) => values.iter_mut().for_each(redact_string_value),
+            _ => {}
+        }
+    }
+}
+
+fn redact_string_value(value: &mut StringValue) {
+    if let Cow::Owned(redacted) = redact(value.as_str()) {
+        *value = redacted.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use opentelemetry::{
+        trace::{SpanContext, SpanId, SpanKind, TraceFlags, TraceId, TraceState},
+        InstrumentationScope,
+    };
+    use opentelemetry_sdk::trace::{SpanEvents, SpanLinks};
+
+    use super::*;
+
+    // Secrets are registered globally, so each test uses its own
+
+    #[test]
+    fn registered_secrets_are_redacted() {
+        add_secret("hunter2-password");
+        assert_eq!(
+            redact("connecting to postgres://app:hunter2-password@db/orders"),
+            "connecting to postgres://app:[REDACTED]@db/orders"
+        );
+        assert!(matches!(redact("nothing to see"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn encoded_secrets_are_redacted() {
+        add_secret("p@ss/word");
+        assert_eq!(
+            redact("redis://:p%40ss%2Fword@cache"),
+            "redis://:[REDACTED]@cache"
+        );
+    }
+
+    #[test]
+    fn short_values_are_not_redacted() {
+        add_secret("abc");
+        assert_eq!(redact("abcdef"), "abcdef");
+    }
+
+    #[test]
+    fn writer_redacts_lines() {
+        add_secret("writer-secret");
+        let mut out = vec![];
+        RedactingWriter(&mut out)
+            .write_all(b"failed with writer-secret\n")
+            .unwrap();
+        assert_eq!(out, b"failed with [REDACTED]\n");
+    }
+
+    #[test]
+    fn spans_are_redacted() {
+        add_secret("span-secret");
+        let start_time = std::time::SystemTime::UNIX_EPOCH;
+        let mut span = SpanData {
+            span_context: SpanContext::new(
+                TraceId::from_bytes(1u128.to_be_bytes()),
+                SpanId::from_bytes(1u64.to_be_bytes()),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id: SpanId::INVALID,
+            span_kind: SpanKind::Client,
+            name: Cow::Borrowed("test"),
+            start_time,
+            end_time: start_time,
+            attributes: vec![KeyValue::new("db.url", "mysql://root:span-secret@db")],
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::error("bad password span-secret"),
+            instrumentation_scope: InstrumentationScope::builder("test").build(),
+        };
+        redact_span(&mut span);
+        assert_eq!(
+            span.attributes[0].value.as_str(),
+            "mysql://root:[REDACTED]@db"
+        );
+        assert_eq!(span.status, Status::error("bad password [REDACTED]"));
+    }
+}
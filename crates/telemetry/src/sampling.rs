@@ -0,0 +1,327 @@
+//! Trace sampling.
+//!
+//! Spin decides whether to export a trace once the trace's local root span
+//! ends, so that the decision can take the whole trace into account: whether
+//! any span failed, how long the trace took, and which component handled it.
+//! Until then, the trace's spans are buffered.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+use opentelemetry::{
+    trace::{SpanId, Status, TraceContextExt, TraceId},
+    Context,
+};
+use opentelemetry_sdk::{
+    error::OTelSdkResult,
+    trace::{Span, SpanData, SpanProcessor},
+    Resource,
+};
+
+/// The span attribute identifying the component a trace is for.
+const COMPONENT_ID_ATTRIBUTE: &str = "component_id";
+
+/// The most traces that may be buffered awaiting a sampling decision.
+const MAX_PENDING_TRACES: usize = 4096;
+
+/// The most spans of one trace that are buffered; later spans are dropped.
+const MAX_SPANS_PER_TRACE: usize = 1024;
+
+/// How traces are sampled, by default and for particular components.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SamplingConfig {
+    /// The strategy for traces with no component override.
+    pub default: SamplingStrategy,
+    /// Strategies for the traces of particular components, by component ID.
+    pub components: HashMap<String, SamplingStrategy>,
+}
+
+impl SamplingConfig {
+    /// Returns the strategy for the traces of the given component.
+    pub fn strategy_for(&self, component_id: Option<&str>) -> &SamplingStrategy {
+        component_id
+            .and_then(|id| self.components.get(id))
+            .unwrap_or(&self.default)
+    }
+
+    fn exports_everything(&self) -> bool {
+        self.default == SamplingStrategy::Always
+            && self
+                .components
+                .values()
+                .all(|strategy| *strategy == SamplingStrategy::Always)
+    }
+}
+
+/// A strategy for deciding which traces are exported.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SamplingStrategy {
+    /// Export every trace.
+    #[default]
+    Always,
+    /// Export this fraction of traces, between 0 and 1.
+    Ratio(f64),
+    /// Export every trace that has a failed span or took at least
+    /// `slow_threshold`, and this fraction of the rest.
+    TailKeepErrorsAndSlow {
+        slow_threshold: Duration,
+        ratio: f64,
+    },
+}
+
+/// Sets how traces are sampled from now on.
+pub fn set_sampling_config(config: SamplingConfig) {
+    *current_config_lock().write().unwrap() = Arc::new(config);
+}
+
+fn current_config() -> Arc<SamplingConfig> {
+    current_config_lock().read().unwrap().clone()
+}
+
+fn current_config_lock() -> &'static RwLock<Arc<SamplingConfig>> {
+    static CONFIG: OnceLock<RwLock<Arc<SamplingConfig>>> = OnceLock::new();
+    CONFIG.get_or_init(Default::default)
+}
+
+/// A [`SpanProcessor`] that passes the spans of sampled traces on to another
+/// processor, according to the current [`SamplingConfig`].
+#[derive(Debug)]
+pub(crate) struct SamplingSpanProcessor<P> {
+    inner: P,
+    pending: Mutex<HashMap<TraceId, PendingTrace>>,
+}
+
+#[derive(Debug, Default)]
+struct PendingTrace {
+    /// The span which started the trace in this process.
+    local_root: Option<SpanId>,
+    spans: Vec<SpanData>,
+}
+
+impl<P: SpanProcessor> SamplingSpanProcessor<P> {
+    pub(crate) fn new(inner: P) -> Self {
+        Self {
+            inner,
+            pending: Default::default(),
+        }
+    }
+}
+
+impl<P: SpanProcessor> SpanProcessor for SamplingSpanProcessor<P> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        use opentelemetry::trace::Span as _;
+
+        let parent_is_local = cx.has_active_span() && !cx.span().span_context().is_remote();
+        if !parent_is_local && !current_config().exports_everything() {
+            let span_context = span.span_context();
+            let mut pending = self.pending.lock().unwrap();
+            if pending.len() < MAX_PENDING_TRACES {
+                pending
+                    .entry(span_context.trace_id())
+                    .or_default()
+                    .local_root = Some(span_context.span_id());
+            } else {
+                tracing::debug!("Too many traces awaiting sampling; dropping a trace");
+            }
+        }
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        let trace_id = span.span_context.trace_id();
+        let trace = {
+            let mut pending = self.pending.lock().unwrap();
+            let Some(trace) = pending.get_mut(&trace_id) else {
+                // The trace started while everything was being exported
+                drop(pending);
+                if current_config().exports_everything() {
+                    self.inner.on_end(span);
+                }
+                return;
+            };
+            let is_local_root = trace.local_root == Some(span.span_context.span_id());
+            if trace.spans.len() < MAX_SPANS_PER_TRACE || is_local_root {
+                trace.spans.push(span);
+            }
+            if !is_local_root {
+                return;
+            }
+            pending.remove(&trace_id).unwrap()
+        };
+
+        if should_export(&current_config(), trace_id, &trace.spans) {
+            for span in trace.spans {
+                self.inner.on_end(span);
+            }
+        }
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.inner.shutdown()
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+/// Decides whether to export a trace whose local root span has ended. The
+/// local root span is the last of `spans`.
+fn should_export(config: &SamplingConfig, trace_id: TraceId, spans: &[SpanData]) -> bool {
+    let component_id = spans.iter().find_map(|span| {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == COMPONENT_ID_ATTRIBUTE)
+            .map(|kv| kv.value.as_str())
+    });
+    match config.strategy_for(component_id.as_deref()) {
+        SamplingStrategy::Always => true,
+        SamplingStrategy::Ratio(ratio) => ratio_sampled(trace_id, *ratio),
+        SamplingStrategy::TailKeepErrorsAndSlow {
+            slow_threshold,
+            ratio,
+        } => {
+            let failed = spans
+                .iter()
+                .any(|span| matches!(span.status, Status::Error { .. }));
+            let slow = spans.last().is_some_and(|root| {
+                root.end_time
+                    .duration_since(root.start_time)
+                    .unwrap_or_default()
+                    >= *slow_threshold
+            });
+            failed || slow || ratio_sampled(trace_id, *ratio)
+        }
+    }
+}
+
+/// Decides from its ID whether a trace is in the sampled fraction, in the
+/// same way as the OpenTelemetry trace ID ratio sampler, so that the decision
+/// is consistent for a trace across processes.
+fn ratio_sampled(trace_id: TraceId, ratio: f64) -> bool {
+    if ratio >= 1.0 {
+        return true;
+    }
+    if ratio <= 0.0 {
+        return false;
+    }
+    let bytes = trace_id.to_bytes();
+    let low = u64::from_be_bytes(bytes[8..16].try_into().unwrap()) >> 1;
+    low < (ratio * (1u64 << 63) as f64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, time::SystemTime};
+
+    use opentelemetry::{
+        trace::{SpanContext, SpanKind, TraceFlags, TraceState},
+        InstrumentationScope, KeyValue,
+    };
+    use opentelemetry_sdk::trace::{SpanEvents, SpanLinks};
+
+    use super::*;
+
+    fn trace_id(id: u128) -> TraceId {
+        TraceId::from_bytes(id.to_be_bytes())
+    }
+
+    fn span(component_id: Option<&str>, duration: Duration, status: Status) -> SpanData {
+        let start_time = SystemTime::UNIX_EPOCH;
+        SpanData {
+            span_context: SpanContext::new(
+                trace_id(1),
+                SpanId::from_bytes(1u64.to_be_bytes()),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id: SpanId::INVALID,
+            span_kind: SpanKind::Server,
+            name: Cow::Borrowed("test"),
+            start_time,
+            end_time: start_time + duration,
+            attributes: component_id
+                .map(|id| KeyValue::new(COMPONENT_ID_ATTRIBUTE, id.to_owned()))
+                .into_iter()
+                .collect(),
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status,
+            instrumentation_scope: InstrumentationScope::builder("test").build(),
+        }
+    }
+
+    #[test]
+    fn ratio_sampling_is_by_trace_id() {
+        assert!(ratio_sampled(trace_id(u128::MAX), 1.0));
+        assert!(!ratio_sampled(trace_id(0), 0.0));
+        assert!(ratio_sampled(trace_id(1), 0.5));
+        assert!(!ratio_sampled(trace_id(u64::MAX as u128), 0.5));
+    }
+
+    #[test]
+    fn tail_sampling_keeps_errors_and_slow_traces() {
+        let config = SamplingConfig {
+            default: SamplingStrategy::TailKeepErrorsAndSlow {
+                slow_threshold: Duration::from_millis(100),
+                ratio: 0.0,
+            },
+            components: Default::default(),
+        };
+        let trace_id = trace_id(1);
+        let fast = Duration::from_millis(10);
+        let slow = Duration::from_millis(200);
+
+        assert!(!should_export(
+            &config,
+            trace_id,
+            &[span(None, fast, Status::Ok)]
+        ));
+        assert!(should_export(
+            &config,
+            trace_id,
+            &[span(None, slow, Status::Unset)]
+        ));
+        assert!(should_export(
+            &config,
+            trace_id,
+            &[
+                span(None, fast, Status::error("failed")),
+                span(None, fast, Status::Unset)
+            ]
+        ));
+    }
+
+    #[test]
+    fn component_overrides_apply_to_their_traces() {
+        let config = SamplingConfig {
+            default: SamplingStrategy::Ratio(0.0),
+            components: [("hot".to_owned(), SamplingStrategy::Always)].into(),
+        };
+        let trace_id = trace_id(1);
+        let duration = Duration::from_millis(10);
+
+        assert!(should_export(
+            &config,
+            trace_id,
+            &[
+                span(Some("hot"), duration, Status::Unset),
+                span(None, duration, Status::Unset)
+            ]
+        ));
+        assert!(!should_export(
+            &config,
+            trace_id,
+            &[span(Some("cold"), duration, Status::Unset)]
+        ));
+    }
+}
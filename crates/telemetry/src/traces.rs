@@ -9,6 +9,8 @@ use tracing_subscriber::{registry::LookupSpan, EnvFilter, Layer};
 
 use crate::detector::SpinResourceDetector;
 use crate::env::OtlpProtocol;
+use crate::redaction::RedactingSpanProcessor;
+use crate::sampling::SamplingSpanProcessor;
 
 /// Constructs a layer for the tracing subscriber that sends spans to an OTEL collector.
 ///
@@ -41,7 +43,11 @@ pub(crate) fn otel_tracing_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
         OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
     };
 
-    let span_processor = opentelemetry_sdk::trace::BatchSpanProcessor::builder(exporter).build();
+    // Spans are exported in batches once the sampling processor has decided to keep their trace,
+    // with any secrets redacted.
+    let span_processor = SamplingSpanProcessor::new(RedactingSpanProcessor::new(
+        opentelemetry_sdk::trace::BatchSpanProcessor::builder(exporter).build(),
+    ));
 
     let tracer_provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
         .with_resource(resource)
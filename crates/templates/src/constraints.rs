@@ -25,3 +25,60 @@ impl StringConstraints {
         Ok(text)
     }
 }
+
+#[derive(Clone, Debug)]
+pub(crate) struct IntegerConstraints {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl IntegerConstraints {
+    pub fn validate(&self, text: String) -> anyhow::Result<String> {
+        let Ok(value) = text.trim().parse::<i64>() else {
+            anyhow::bail!("Input '{}' is not a whole number", text);
+        };
+        if let Some(min) = self.min {
+            if value < min {
+                anyhow::bail!("Input '{}' is less than the minimum of {}", text, min);
+            }
+        }
+        if let Some(max) = self.max {
+            if value > max {
+                anyhow::bail!("Input '{}' is greater than the maximum of {}", text, max);
+            }
+        }
+        Ok(value.to_string())
+    }
+}
+
+pub(crate) fn validate_bool(text: String) -> anyhow::Result<String> {
+    match text.trim().to_lowercase().as_str() {
+        "true" | "yes" | "y" => Ok("true".to_owned()),
+        "false" | "no" | "n" => Ok("false".to_owned()),
+        _ => anyhow::bail!("Input '{}' is not 'true' or 'false'", text),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integers_are_checked_against_bounds() {
+        let constraints = IntegerConstraints {
+            min: Some(1024),
+            max: Some(65535),
+        };
+        assert_eq!("3000", constraints.validate(" 3000".to_owned()).unwrap());
+        assert!(constraints.validate("80".to_owned()).is_err());
+        assert!(constraints.validate("70000".to_owned()).is_err());
+        assert!(constraints.validate("lots".to_owned()).is_err());
+    }
+
+    #[test]
+    fn bools_are_normalised() {
+        assert_eq!("true", validate_bool("Yes".to_owned()).unwrap());
+        assert_eq!("false", validate_bool("false".to_owned()).unwrap());
+        assert!(validate_bool("maybe".to_owned()).is_err());
+    }
+}
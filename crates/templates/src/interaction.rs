@@ -20,6 +20,17 @@ pub(crate) trait InteractionStrategy {
         for parameter in run.template.parameters(&run.options.variant) {
             match self.populate_parameter(run, parameter) {
                 Cancellable::Ok(value) => {
+                    // Normalise typed values, e.g. `yes` to `true`, so that
+                    // conditions can compare them.
+                    let value = match parameter.validate_value(value) {
+                        Ok(value) => value,
+                        Err(e) => {
+                            return Cancellable::Err(e.context(format!(
+                                "Invalid value for parameter '{}'",
+                                parameter.id()
+                            )))
+                        }
+                    };
                     values.insert(parameter.id().to_owned(), value);
                 }
                 Cancellable::Cancelled => return Cancellable::Cancelled,
@@ -115,6 +126,8 @@ pub(crate) fn prompt_parameter(parameter: &TemplateParameter) -> Option<String>
                 Some(allowed_values) => ask_choice(prompt, default_value, allowed_values),
                 None => ask_free_text(prompt, default_value),
             },
+            TemplateParameterDataType::Bool => ask_yes_no(prompt, default_value),
+            TemplateParameterDataType::Integer(_) => ask_free_text(prompt, default_value),
         };
 
         match input {
@@ -141,6 +154,17 @@ fn ask_free_text(prompt: &str, default_value: &Option<String>) -> anyhow::Result
     Ok(result)
 }
 
+fn ask_yes_no(prompt: &str, default_value: &Option<String>) -> anyhow::Result<String> {
+    let mut confirm = Confirm::new().with_prompt(prompt);
+    if let Some(s) = default_value {
+        if let Ok(default) = crate::constraints::validate_bool(s.to_owned()) {
+            confirm = confirm.default(default == "true");
+        }
+    }
+    let result = confirm.interact()?;
+    Ok(result.to_string())
+}
+
 fn ask_choice(
     prompt: &str,
     default_value: &Option<String>,
@@ -539,6 +539,7 @@ mod tests {
             accept_defaults: false,
             no_vcs: false,
             allow_overwrite: false,
+            no_hooks: false,
         };
         rest(&mut options);
         options
@@ -1222,6 +1223,66 @@ mod tests {
         assert_eq!("Partial 2: Value is myvalue", generated_lines[2]);
     }
 
+    #[tokio::test]
+    async fn typed_parameters_select_files_and_run_hooks() {
+        let manager = TempManager::new();
+        manager.install_test_data_templates().await;
+
+        let template = manager.get("full-app").unwrap().unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let output_dir = dest_temp_dir.path().join("myproj");
+        let options = run_options("my project", &output_dir, |opts| {
+            opts.values = make_values([("include-worker", "no"), ("port", "8080")]);
+        });
+
+        template.run(options).silent().await.unwrap();
+
+        let api = std::fs::read_to_string(output_dir.join("api/main.txt")).unwrap();
+        assert_contains(&api, "Next port: 8081");
+        assert_contains(&api, "Worker: not included");
+        assert!(!output_dir.join("worker/main.txt").exists());
+        assert!(output_dir.join("hook.txt").exists());
+
+        // Hooks can be turned off, and defaults are typed too
+        let template = manager.get("full-app").unwrap().unwrap();
+        let output_dir = dest_temp_dir.path().join("myproj2");
+        let options = run_options("my project", &output_dir, |opts| {
+            opts.values = HashMap::new();
+            opts.accept_defaults = true;
+            opts.no_hooks = true;
+        });
+
+        template.run(options).silent().await.unwrap();
+
+        let api = std::fs::read_to_string(output_dir.join("api/main.txt")).unwrap();
+        assert_contains(&api, "Next port: 3001");
+        assert_contains(&api, "Worker: included");
+        assert!(output_dir.join("worker/main.txt").exists());
+        assert!(!output_dir.join("hook.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn typed_parameters_are_validated() {
+        let manager = TempManager::new();
+        manager.install_test_data_templates().await;
+
+        let template = manager.get("full-app").unwrap().unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let output_dir = dest_temp_dir.path().join("myproj");
+        let options = run_options("my project", &output_dir, |opts| {
+            opts.values = make_values([("include-worker", "true"), ("port", "80")]);
+        });
+
+        let err = template
+            .run(options)
+            .silent()
+            .await
+            .expect_err("Expected out-of-range port to be rejected");
+        assert_contains(&err.to_string(), "port");
+    }
+
     #[tokio::test]
     async fn fails_on_unknown_filter() {
         let manager = TempManager::new();
@@ -53,6 +53,14 @@ pub(crate) struct RawConditional {
 )]
 pub(crate) enum RawCondition {
     ManifestEntryExists(String),
+    ParameterEquals(RawParameterEquals),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) struct RawParameterEquals {
+    pub parameter: String,
+    pub value: String,
 }
 
 impl TryFrom<toml::Value> for RawCondition {
@@ -65,6 +73,12 @@ impl TryFrom<toml::Value> for RawCondition {
         if table.keys().len() != 1 {
             anyhow::bail!("Invalid condition: should be a single-entry table");
         }
+        if let Some(value) = table.get("parameter_equals") {
+            let parameter_equals = value.clone().try_into().context(
+                "Invalid condition: 'parameter_equals' should be a table with 'parameter' and 'value' strings",
+            )?;
+            return Ok(Self::ParameterEquals(parameter_equals));
+        }
         let Some(value) = table.get("manifest_entry_exists") else {
             anyhow::bail!("Invalid condition: unknown condition type");
         };
@@ -87,12 +101,15 @@ pub(crate) struct RawParameter {
     pub default_value: Option<String>,
     pub pattern: Option<String>,
     pub allowed_values: Option<Vec<String>>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", tag = "action")]
 pub(crate) enum RawExtraOutput {
     CreateDir(RawCreateDir),
+    RunCommand(RawRunCommand),
 }
 
 #[derive(Debug, Deserialize)]
@@ -102,6 +119,13 @@ pub(crate) struct RawCreateDir {
     pub at: Option<CreateLocation>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) struct RawRunCommand {
+    pub command: String,
+    pub at: Option<CreateLocation>,
+}
+
 #[derive(Debug, Deserialize, Clone, Copy, Default)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub(crate) enum CreateLocation {
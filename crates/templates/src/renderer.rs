@@ -8,7 +8,7 @@ use crate::writer::{TemplateOutput, TemplateOutputs};
 // it needs to render.
 pub(crate) struct TemplateRenderer {
     pub render_operations: Vec<RenderOperation>,
-    pub parameter_values: HashMap<String, String>,
+    pub parameter_values: HashMap<String, liquid_core::Value>,
 }
 
 pub(crate) enum TemplateContent {
@@ -21,6 +21,7 @@ pub(crate) enum RenderOperation {
     MergeToml(PathBuf, MergeTarget, TemplateContent), // file to merge into, table to merge into, content to merge
     WriteFile(PathBuf, TemplateContent),
     CreateDirectory(PathBuf, std::sync::Arc<liquid::Template>),
+    RunCommand(PathBuf, std::sync::Arc<liquid::Template>), // working directory, command
 }
 
 pub(crate) enum MergeTarget {
@@ -48,10 +49,7 @@ impl TemplateRenderer {
         let mut object = liquid::Object::new();
 
         for (k, v) in &self.parameter_values {
-            object.insert(
-                k.to_owned().into(),
-                liquid_core::Value::Scalar(v.to_owned().into()),
-            );
+            object.insert(k.to_owned().into(), v.clone());
         }
 
         object
@@ -81,6 +79,10 @@ impl RenderOperation {
                 let path = path.join(rendered); // TODO: should we validate that `rendered` was relative?`
                 Ok(TemplateOutput::CreateDirectory(path))
             }
+            Self::RunCommand(working_dir, template) => {
+                let command = template.render(globals)?;
+                Ok(TemplateOutput::RunCommand(working_dir, command))
+            }
         }
     }
 }
@@ -46,6 +46,8 @@ pub struct RunOptions {
     /// Skip the overwrite prompt if the output directory already contains files
     /// (or, if silent, allow overwrite instead of erroring).
     pub allow_overwrite: bool,
+    /// If true, do not run the template's post-generation commands
+    pub no_hooks: bool,
 }
 
 impl Run {
@@ -112,19 +114,26 @@ impl Run {
 
         self.validate_provided_values()?;
 
+        // Which files and snippets are included may depend on parameter values.
+        let parameter_values = match interaction.populate_parameters(self) {
+            Cancellable::Ok(parameter_values) => parameter_values,
+            Cancellable::Cancelled => return Ok(None),
+            Cancellable::Err(e) => return Err(e),
+        };
+
         let files = match self.template.content_dir() {
             None => vec![],
             Some(path) => {
                 let from = path
                     .absolutize()
                     .context("Failed to get absolute path of template directory")?;
-                self.included_files(&from, &to, &parser)?
+                self.included_files(&from, &to, &parser, &parameter_values)?
             }
         };
 
         let snippets = self
             .template
-            .snippets(&self.options.variant)
+            .snippets(&self.options.variant, &parameter_values)
             .iter()
             .map(|(id, path)| self.snippet_operation(id, path, &parser))
             .collect::<anyhow::Result<Vec<_>>>()?;
@@ -133,28 +142,30 @@ impl Run {
             .template
             .extra_outputs()
             .iter()
+            .filter(|extra| !(self.options.no_hooks && extra.is_hook()))
             .map(|extra| self.extra_operation(extra))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
         let render_operations = files.into_iter().chain(snippets).chain(extras).collect();
 
-        match interaction.populate_parameters(self) {
-            Cancellable::Ok(parameter_values) => {
-                let values = self
-                    .special_values()
-                    .await
-                    .into_iter()
-                    .chain(parameter_values)
-                    .collect();
-                let prepared_template = TemplateRenderer {
-                    render_operations,
-                    parameter_values: values,
+        let values = self
+            .special_values()
+            .await
+            .into_iter()
+            .map(|(id, value)| (id, liquid_core::Value::scalar(value)))
+            .chain(parameter_values.iter().map(|(id, value)| {
+                let value = match self.template.parameter(id) {
+                    Some(parameter) => parameter.liquid_value(value),
+                    None => liquid_core::Value::scalar(value.clone()),
                 };
-                Ok(Some(prepared_template))
-            }
-            Cancellable::Cancelled => Ok(None),
-            Cancellable::Err(e) => Err(e),
-        }
+                (id.clone(), value)
+            }))
+            .collect();
+        let prepared_template = TemplateRenderer {
+            render_operations,
+            parameter_values: values,
+        };
+        Ok(Some(prepared_template))
     }
 
     fn included_files(
@@ -162,6 +173,7 @@ impl Run {
         from: &Path,
         to: &Path,
         parser: &liquid::Parser,
+        parameter_values: &HashMap<String, String>,
     ) -> anyhow::Result<Vec<RenderOperation>> {
         let gitignore = ".gitignore";
         let mut all_content_files = Self::list_content_files(from)?;
@@ -172,9 +184,12 @@ impl Run {
                 Some(file_name) => file_name.to_os_string() != gitignore,
             });
         }
-        let included_files =
-            self.template
-                .included_files(from, all_content_files, &self.options.variant);
+        let included_files = self.template.included_files(
+            from,
+            all_content_files,
+            &self.options.variant,
+            parameter_values,
+        );
         let template_contents = self.read_all(included_files, parser)?;
         let outputs = Self::to_output_paths(from, to, template_contents);
         let file_ops = outputs
@@ -350,6 +365,19 @@ impl Run {
                     template.clone(),
                 ))
             }
+            ExtraOutputAction::RunCommand(_, template, at) => {
+                let working_dir = match (at, &self.options.variant) {
+                    (
+                        crate::reader::CreateLocation::Manifest,
+                        TemplateVariantInfo::AddComponent { manifest_path },
+                    ) => manifest_path
+                        .parent()
+                        .map(|p| p.to_owned())
+                        .unwrap_or_default(),
+                    _ => self.generation_target_dir(),
+                };
+                Ok(RenderOperation::RunCommand(working_dir, template.clone()))
+            }
         }
     }
 
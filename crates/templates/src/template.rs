@@ -9,7 +9,7 @@ use itertools::Itertools;
 use regex::Regex;
 
 use crate::{
-    constraints::StringConstraints,
+    constraints::{IntegerConstraints, StringConstraints},
     reader::{
         RawCondition, RawConditional, RawExtraOutput, RawParameter, RawTemplateManifest,
         RawTemplateManifestV1, RawTemplateVariant,
@@ -113,6 +113,9 @@ pub(crate) struct Conditional {
 #[derive(Clone, Debug)]
 pub(crate) enum Condition {
     ManifestEntryExists(Vec<String>),
+    /// The parameter with the given ID has the given value. This is only
+    /// known once the parameters have been populated.
+    ParameterEquals(String, String),
     #[cfg(test)]
     Always(bool),
 }
@@ -126,6 +129,8 @@ pub(crate) enum TemplateTriggerCompatibility {
 #[derive(Clone, Debug)]
 pub(crate) enum TemplateParameterDataType {
     String(StringConstraints),
+    Bool,
+    Integer(IntegerConstraints),
 }
 
 #[derive(Debug)]
@@ -142,6 +147,11 @@ pub(crate) enum ExtraOutputAction {
         std::sync::Arc<liquid::Template>,
         crate::reader::CreateLocation,
     ),
+    RunCommand(
+        String,
+        std::sync::Arc<liquid::Template>,
+        crate::reader::CreateLocation,
+    ),
 }
 
 impl std::fmt::Debug for ExtraOutputAction {
@@ -150,6 +160,7 @@ impl std::fmt::Debug for ExtraOutputAction {
             Self::CreateDirectory(orig, ..) => {
                 f.debug_tuple("CreateDirectory").field(orig).finish()
             }
+            Self::RunCommand(orig, ..) => f.debug_tuple("RunCommand").field(orig).finish(),
         }
     }
 }
@@ -208,6 +219,7 @@ impl Template {
                 content_dir,
             },
         };
+        template.validate_conditions()?;
         Ok(template)
     }
 
@@ -270,10 +282,20 @@ impl Template {
 
     // TODO: we should resolve this once at the start of Run and then use that forever
     fn variant(&self, variant_info: &TemplateVariantInfo) -> Option<TemplateVariant> {
+        self.variant_with_values(variant_info, None)
+    }
+
+    /// Resolves the variant, including conditions on parameter values if
+    /// the parameters have been populated.
+    fn variant_with_values(
+        &self,
+        variant_info: &TemplateVariantInfo,
+        values: Option<&HashMap<String, String>>,
+    ) -> Option<TemplateVariant> {
         let kind = variant_info.kind();
         self.variants
             .get(&kind)
-            .map(|vt| vt.resolve_conditions(variant_info))
+            .map(|vt| vt.resolve_conditions(variant_info, values))
     }
 
     pub(crate) fn parameters(
@@ -311,8 +333,14 @@ impl Template {
         self.variants.contains_key(&variant.kind())
     }
 
-    pub(crate) fn snippets(&self, variant_kind: &TemplateVariantInfo) -> HashMap<String, String> {
-        let variant = self.variant(variant_kind).unwrap(); // TODO: for now
+    pub(crate) fn snippets(
+        &self,
+        variant_kind: &TemplateVariantInfo,
+        values: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        let variant = self
+            .variant_with_values(variant_kind, Some(values))
+            .unwrap(); // TODO: for now
         variant.snippets
     }
 
@@ -412,9 +440,32 @@ impl Template {
             RawCondition::ManifestEntryExists(path) => {
                 Condition::ManifestEntryExists(path.split('.').map(|s| s.to_string()).collect_vec())
             }
+            RawCondition::ParameterEquals(pe) => Condition::ParameterEquals(pe.parameter, pe.value),
         }
     }
 
+    // Parameter conditions are resolved after the parameters are populated,
+    // so they can't affect which parameters are asked for.
+    fn validate_conditions(&self) -> anyhow::Result<()> {
+        for conditional in self.variants.values().flat_map(|v| &v.conditions) {
+            let Condition::ParameterEquals(parameter, value) = &conditional.condition else {
+                continue;
+            };
+            let Some(p) = self.parameter(parameter) else {
+                anyhow::bail!("Condition refers to unknown parameter '{parameter}'");
+            };
+            p.validate_value(value).with_context(|| {
+                format!("Condition value '{value}' is not valid for parameter '{parameter}'")
+            })?;
+            if !conditional.skip_parameters.is_empty() {
+                anyhow::bail!(
+                    "Conditions on parameter '{parameter}' cannot skip parameters, only files and snippets"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn parse_parameters(
         raw: &Option<IndexMap<String, RawParameter>>,
     ) -> anyhow::Result<Vec<TemplateParameter>> {
@@ -444,8 +495,11 @@ impl Template {
         base: &std::path::Path,
         all_files: Vec<PathBuf>,
         variant_kind: &TemplateVariantInfo,
+        values: &HashMap<String, String>,
     ) -> Vec<PathBuf> {
-        let variant = self.variant(variant_kind).unwrap(); // TODO: for now
+        let variant = self
+            .variant_with_values(variant_kind, Some(values))
+            .unwrap(); // TODO: for now
         all_files
             .into_iter()
             .filter(|path| !variant.skip_file(base, path))
@@ -539,12 +593,41 @@ impl TemplateParameter {
     pub fn validate_value(&self, value: impl AsRef<str>) -> anyhow::Result<String> {
         self.data_type.validate_value(value.as_ref().to_owned())
     }
+
+    /// The value as seen by templates, so that e.g. a `false` bool is falsy.
+    /// The value must already have been validated.
+    pub fn liquid_value(&self, value: &str) -> liquid_core::Value {
+        match self.data_type {
+            TemplateParameterDataType::String(_) => liquid_core::Value::scalar(value.to_owned()),
+            TemplateParameterDataType::Bool => liquid_core::Value::scalar(value == "true"),
+            TemplateParameterDataType::Integer(_) => match value.parse::<i64>() {
+                Ok(n) => liquid_core::Value::scalar(n),
+                Err(_) => liquid_core::Value::scalar(value.to_owned()),
+            },
+        }
+    }
 }
 
 impl TemplateParameterDataType {
     fn parse(raw: &RawParameter) -> anyhow::Result<Self> {
+        let has_string_constraints = raw.pattern.is_some() || raw.allowed_values.is_some();
+        let has_integer_constraints = raw.min.is_some() || raw.max.is_some();
         match &raw.data_type[..] {
+            "string" if has_integer_constraints => Err(anyhow!(
+                "Only 'integer' parameters can have a 'min' or 'max'"
+            )),
             "string" => Ok(Self::String(parse_string_constraints(raw)?)),
+            "bool" if has_string_constraints || has_integer_constraints => Err(anyhow!(
+                "'bool' parameters cannot have a pattern, allowed values, 'min' or 'max'"
+            )),
+            "bool" => Ok(Self::Bool),
+            "integer" if has_string_constraints => Err(anyhow!(
+                "Only 'string' parameters can have a pattern or allowed values"
+            )),
+            "integer" => Ok(Self::Integer(IntegerConstraints {
+                min: raw.min,
+                max: raw.max,
+            })),
             _ => Err(anyhow!("Unrecognised data type '{}'", raw.data_type)),
         }
     }
@@ -552,24 +635,36 @@ impl TemplateParameterDataType {
     fn validate_value(&self, value: String) -> anyhow::Result<String> {
         match self {
             TemplateParameterDataType::String(constraints) => constraints.validate(value),
+            TemplateParameterDataType::Bool => crate::constraints::validate_bool(value),
+            TemplateParameterDataType::Integer(constraints) => constraints.validate(value),
         }
     }
 }
 
 impl ExtraOutputAction {
+    /// Whether the action is a post-generation command, rather than output.
+    pub(crate) fn is_hook(&self) -> bool {
+        matches!(self, Self::RunCommand(..))
+    }
+
     fn from_raw(id: &str, raw: &RawExtraOutput) -> anyhow::Result<Self> {
+        let parse = |text: &str| {
+            liquid::Parser::new()
+                .parse(text)
+                .map(std::sync::Arc::new)
+                .with_context(|| format!("Template error: output {id} is not a valid template"))
+        };
         Ok(match raw {
-            RawExtraOutput::CreateDir(create) => {
-                let path_template =
-                    liquid::Parser::new().parse(&create.path).with_context(|| {
-                        format!("Template error: output {id} is not a valid template")
-                    })?;
-                Self::CreateDirectory(
-                    create.path.clone(),
-                    std::sync::Arc::new(path_template),
-                    create.at.unwrap_or_default(),
-                )
-            }
+            RawExtraOutput::CreateDir(create) => Self::CreateDirectory(
+                create.path.clone(),
+                parse(&create.path)?,
+                create.at.unwrap_or_default(),
+            ),
+            RawExtraOutput::RunCommand(run) => Self::RunCommand(
+                run.command.clone(),
+                parse(&run.command)?,
+                run.at.unwrap_or_default(),
+            ),
         })
     }
 }
@@ -586,10 +681,14 @@ impl TemplateVariant {
         self.skip_parameters.iter().any(|p| &parameter.id == p)
     }
 
-    fn resolve_conditions(&self, variant_info: &TemplateVariantInfo) -> Self {
+    fn resolve_conditions(
+        &self,
+        variant_info: &TemplateVariantInfo,
+        values: Option<&HashMap<String, String>>,
+    ) -> Self {
         let mut resolved = self.clone();
         for condition in &self.conditions {
-            if condition.condition.is_true(variant_info) {
+            if condition.condition.is_true(variant_info, values) {
                 resolved
                     .skip_files
                     .append(&mut condition.skip_files.clone());
@@ -606,7 +705,11 @@ impl TemplateVariant {
 }
 
 impl Condition {
-    fn is_true(&self, variant_info: &TemplateVariantInfo) -> bool {
+    fn is_true(
+        &self,
+        variant_info: &TemplateVariantInfo,
+        values: Option<&HashMap<String, String>>,
+    ) -> bool {
         match self {
             Self::ManifestEntryExists(path) => match variant_info {
                 TemplateVariantInfo::NewApplication => false,
@@ -620,6 +723,9 @@ impl Condition {
                     crate::toml::get_at(table, path).is_some()
                 }
             },
+            Self::ParameterEquals(parameter, value) => {
+                values.and_then(|values| values.get(parameter)) == Some(value)
+            }
             #[cfg(test)]
             Self::Always(b) => *b,
         }
@@ -690,7 +796,7 @@ mod test {
         let condition = Template::parse_condition(RawCondition::ManifestEntryExists(
             "application.trigger.redis".to_owned(),
         ));
-        assert!(!condition.is_true(&TemplateVariantInfo::NewApplication));
+        assert!(!condition.is_true(&TemplateVariantInfo::NewApplication, None));
     }
 
     #[test]
@@ -700,9 +806,12 @@ mod test {
         let condition = Template::parse_condition(RawCondition::ManifestEntryExists(
             "application.trigger.redis".to_owned(),
         ));
-        assert!(!condition.is_true(&TemplateVariantInfo::AddComponent {
-            manifest_path: temp_file.path()
-        }));
+        assert!(!condition.is_true(
+            &TemplateVariantInfo::AddComponent {
+                manifest_path: temp_file.path()
+            },
+            None
+        ));
     }
 
     #[test]
@@ -713,9 +822,12 @@ mod test {
         let condition = Template::parse_condition(RawCondition::ManifestEntryExists(
             "application.trigger.redis".to_owned(),
         ));
-        assert!(condition.is_true(&TemplateVariantInfo::AddComponent {
-            manifest_path: temp_file.path()
-        }));
+        assert!(condition.is_true(
+            &TemplateVariantInfo::AddComponent {
+                manifest_path: temp_file.path()
+            },
+            None
+        ));
     }
 
     #[test]
@@ -723,9 +835,30 @@ mod test {
         let condition = Template::parse_condition(RawCondition::ManifestEntryExists(
             "application.trigger.redis".to_owned(),
         ));
-        assert!(!condition.is_true(&TemplateVariantInfo::AddComponent {
-            manifest_path: PathBuf::from("this/file/does/not.exist")
-        }));
+        assert!(!condition.is_true(
+            &TemplateVariantInfo::AddComponent {
+                manifest_path: PathBuf::from("this/file/does/not.exist")
+            },
+            None
+        ));
+    }
+
+    #[test]
+    fn parameter_equals_condition_is_false_until_parameters_are_populated() {
+        let condition = Template::parse_condition(RawCondition::ParameterEquals(
+            crate::reader::RawParameterEquals {
+                parameter: "database".to_owned(),
+                value: "none".to_owned(),
+            },
+        ));
+        let variant_info = TemplateVariantInfo::NewApplication;
+        assert!(!condition.is_true(&variant_info, None));
+
+        let values = [("database".to_owned(), "none".to_owned())].into();
+        assert!(condition.is_true(&variant_info, Some(&values)));
+
+        let values = [("database".to_owned(), "sqlite".to_owned())].into();
+        assert!(!condition.is_true(&variant_info, Some(&values)));
     }
 
     #[test]
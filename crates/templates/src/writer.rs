@@ -11,6 +11,7 @@ pub(crate) enum TemplateOutput {
     AppendToml(PathBuf, String),
     MergeToml(PathBuf, &'static str, String), // only have to worry about merging into root table for now
     CreateDirectory(PathBuf),
+    RunCommand(PathBuf, String), // working directory, command
 }
 
 impl TemplateOutputs {
@@ -63,6 +64,24 @@ impl TemplateOutput {
                     .await
                     .with_context(|| format!("Failed to create directory {}", dir.display()))?;
             }
+            TemplateOutput::RunCommand(dir, command) => {
+                println!("Running `{command}`");
+                let (shell, flag) = if cfg!(windows) {
+                    ("cmd", "/C")
+                } else {
+                    ("sh", "-c")
+                };
+                let status = tokio::process::Command::new(shell)
+                    .arg(flag)
+                    .arg(command)
+                    .current_dir(dir)
+                    .status()
+                    .await
+                    .with_context(|| format!("Failed to run `{command}`"))?;
+                if !status.success() {
+                    anyhow::bail!("Post-generation command `{command}` failed with {status}");
+                }
+            }
         }
         Ok(())
     }
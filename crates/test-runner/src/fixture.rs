@@ -0,0 +1,198 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+/// A suite of tests for an app, usually read from a `spin-test.toml` file.
+///
+/// ```toml
+/// [variables]
+/// api_token = "test-token"
+///
+/// [[test]]
+/// name = "home page"
+/// request = { method = "GET", path = "/", headers = { accept = "text/html" } }
+/// expect = { status = 200, body_contains = "Welcome" }
+///
+/// [[test]]
+/// name = "unit tests"
+/// component = "api"
+/// interface = "my:app/tests"
+/// function = "run"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestSuite {
+    /// Values of the app's variables while testing.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    #[serde(default, rename = "test")]
+    pub tests: Vec<TestCase>,
+}
+
+impl TestSuite {
+    /// Reads a suite from a TOML file.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_toml(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Parses a suite from TOML.
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        let suite: Self = toml::from_str(contents)?;
+        for test in &suite.tests {
+            test.kind()?;
+        }
+        Ok(suite)
+    }
+}
+
+/// A single test: either an HTTP request fixture or an exported test function.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    pub name: String,
+    request: Option<RequestFixture>,
+    #[serde(default)]
+    expect: Expectation,
+    component: Option<String>,
+    interface: Option<String>,
+    function: Option<String>,
+}
+
+/// What a [`TestCase`] runs.
+pub enum TestKind<'a> {
+    /// Sends a request to the app and checks the response.
+    Http {
+        request: &'a RequestFixture,
+        expect: &'a Expectation,
+    },
+    /// Calls a `func() -> result<_, string>` exported by a component.
+    Function {
+        component: &'a str,
+        /// The exported interface containing the function, if it isn't
+        /// exported directly.
+        interface: Option<&'a str>,
+        function: &'a str,
+    },
+}
+
+impl TestCase {
+    pub fn kind(&self) -> anyhow::Result<TestKind<'_>> {
+        match (&self.request, &self.component, &self.function) {
+            (Some(request), None, None) if self.interface.is_none() => Ok(TestKind::Http {
+                request,
+                expect: &self.expect,
+            }),
+            (None, Some(component), Some(function)) if self.expect.is_empty() => {
+                Ok(TestKind::Function {
+                    component,
+                    interface: self.interface.as_deref(),
+                    function,
+                })
+            }
+            _ => bail!(
+                "test '{}' must have either a `request` (and optionally `expect`), or a `component` and `function`",
+                self.name
+            ),
+        }
+    }
+}
+
+/// The request an HTTP test sends.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestFixture {
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// The path and query, e.g. `/api/items?page=2`.
+    pub path: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+/// What an HTTP test expects of the response.
+///
+/// If no status is given, any successful (2xx) status passes.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    pub status: Option<u16>,
+    /// Headers which must have exactly these values.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The exact response body.
+    pub body: Option<String>,
+    /// Text the response body must contain.
+    pub body_contains: Option<String>,
+}
+
+impl Expectation {
+    fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.headers.is_empty()
+            && self.body.is_none()
+            && self.body_contains.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_are_parsed() {
+        let suite = TestSuite::from_toml(
+            r#"
+            variables = { token = "t" }
+
+            [[test]]
+            name = "home"
+            request = { path = "/" }
+            expect = { status = 200, headers = { content-type = "text/plain" } }
+
+            [[test]]
+            name = "unit"
+            component = "api"
+            function = "run"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(suite.variables["token"], "t");
+        let TestKind::Http { request, expect } = suite.tests[0].kind().unwrap() else {
+            panic!("expected an HTTP test");
+        };
+        assert_eq!(request.method, "GET");
+        assert_eq!(expect.status, Some(200));
+        assert!(matches!(
+            suite.tests[1].kind().unwrap(),
+            TestKind::Function {
+                component: "api",
+                interface: None,
+                function: "run"
+            }
+        ));
+    }
+
+    #[test]
+    fn tests_must_be_one_kind() {
+        let err = TestSuite::from_toml(
+            r#"
+            [[test]]
+            name = "confused"
+            request = { path = "/" }
+            component = "api"
+            function = "run"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("confused"), "{err}");
+    }
+}
@@ -0,0 +1,251 @@
+//! Runs tests against a Spin app without any real backing services.
+//!
+//! The app runs in-process with in-memory key-value stores, SQLite databases
+//! and Redis, and with its variables taken from the [`TestSuite`]. Tests
+//! either send HTTP requests to the app and check the responses, or call test
+//! functions exported by its components.
+
+mod fixture;
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use http::uri::Scheme;
+use http_body_util::BodyExt;
+use spin_app::App;
+use spin_factor_key_value::KEY_VALUE_STORES_KEY;
+use spin_factor_sqlite::ALLOWED_DATABASES_KEY;
+use spin_factors::RuntimeFactors;
+use spin_trigger_http::HttpServer;
+
+pub use fixture::{Expectation, RequestFixture, TestCase, TestKind, TestSuite};
+
+/// The client address requests appear to come from.
+const CLIENT_ADDR: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// Builds the runtime config to test `app` with.
+///
+/// Every key-value store and SQLite database the app uses is in-memory,
+/// Redis connections go to an in-memory server, and variables come from the
+/// suite.
+pub fn runtime_config(app: &App, suite: &TestSuite) -> anyhow::Result<toml::Table> {
+    let mut key_value_stores = toml::Table::new();
+    let mut sqlite_databases = toml::Table::new();
+    for component in app.components() {
+        for label in component
+            .get_metadata(KEY_VALUE_STORES_KEY)?
+            .unwrap_or_default()
+        {
+            key_value_stores.insert(label, in_memory_store());
+        }
+        for label in component
+            .get_metadata(ALLOWED_DATABASES_KEY)?
+            .unwrap_or_default()
+        {
+            sqlite_databases.insert(label, in_memory_store());
+        }
+    }
+
+    let variables: toml::Table = suite
+        .variables
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone().into()))
+        .collect();
+    let mut variables_provider = toml::toml! { type = "static" };
+    variables_provider.insert("values".into(), variables.into());
+
+    let mut config = toml::Table::new();
+    if !key_value_stores.is_empty() {
+        config.insert("key_value_store".into(), key_value_stores.into());
+    }
+    if !sqlite_databases.is_empty() {
+        config.insert("sqlite_database".into(), sqlite_databases.into());
+    }
+    config.insert(
+        "variables_provider".into(),
+        toml::Value::Array(vec![variables_provider.into()]),
+    );
+    config.insert(
+        "outbound_redis".into(),
+        toml::toml! { in_memory = true }.into(),
+    );
+    Ok(config)
+}
+
+/// A store of the in-memory type, whose data lasts for the whole test run.
+fn in_memory_store() -> toml::Value {
+    toml::toml! { type = "memory" }.into()
+}
+
+/// Runs tests against an app.
+pub struct TestRunner<F: RuntimeFactors> {
+    server: Arc<HttpServer<F>>,
+}
+
+/// The result of running a test.
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    /// The test failed, for the given reason.
+    Failed(String),
+}
+
+impl<F: RuntimeFactors> TestRunner<F> {
+    /// Runs tests against the app `server` serves.
+    ///
+    /// The server never listens for requests; tests call it directly.
+    pub fn new(server: Arc<HttpServer<F>>) -> Self {
+        Self { server }
+    }
+
+    /// Runs every test in the suite in order, calling `on_result` as each
+    /// finishes.
+    pub async fn run_all(
+        &self,
+        suite: &TestSuite,
+        mut on_result: impl FnMut(&TestResult),
+    ) -> Vec<TestResult> {
+        let mut results = Vec::with_capacity(suite.tests.len());
+        for test in &suite.tests {
+            let result = self.run(test).await;
+            on_result(&result);
+            results.push(result);
+        }
+        results
+    }
+
+    /// Runs a single test.
+    pub async fn run(&self, test: &TestCase) -> TestResult {
+        let start = Instant::now();
+        let outcome = match self.run_inner(test).await {
+            Ok(()) => TestOutcome::Passed,
+            Err(err) => TestOutcome::Failed(format!("{err:#}")),
+        };
+        TestResult {
+            name: test.name.clone(),
+            outcome,
+            duration: start.elapsed(),
+        }
+    }
+
+    async fn run_inner(&self, test: &TestCase) -> anyhow::Result<()> {
+        match test.kind()? {
+            TestKind::Http { request, expect } => self.run_http(request, expect).await,
+            TestKind::Function {
+                component,
+                interface,
+                function,
+            } => self.run_function(component, interface, function).await,
+        }
+    }
+
+    async fn run_http(&self, fixture: &RequestFixture, expect: &Expectation) -> anyhow::Result<()> {
+        let mut builder = http::Request::builder()
+            .method(fixture.method.as_str())
+            .uri(&fixture.path);
+        if !fixture
+            .headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("host"))
+        {
+            builder = builder.header(http::header::HOST, "localhost");
+        }
+        for (name, value) in &fixture.headers {
+            builder = builder.header(name, value);
+        }
+        let request = builder
+            .body(spin_http::body::full(fixture.body.clone().into()))
+            .context("invalid request")?;
+
+        let response = self
+            .server
+            .handle(request, Scheme::HTTP, CLIENT_ADDR)
+            .await?;
+        let (parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .context("failed to read response body")?
+            .to_bytes();
+        let body = String::from_utf8_lossy(&body);
+
+        match expect.status {
+            Some(status) if parts.status != status => {
+                bail!("expected status {status}, got {}", parts.status.as_u16())
+            }
+            None if !parts.status.is_success() => {
+                bail!(
+                    "expected a successful status, got {}",
+                    parts.status.as_u16()
+                )
+            }
+            _ => (),
+        }
+        for (name, expected) in &expect.headers {
+            let actual = parts.headers.get(name).map(|v| v.to_str().unwrap_or(""));
+            if actual != Some(expected.as_str()) {
+                bail!("expected header {name}: {expected:?}, got {actual:?}");
+            }
+        }
+        if let Some(expected) = &expect.body {
+            if body != *expected {
+                bail!("expected body {expected:?}, got {body:?}");
+            }
+        }
+        if let Some(expected) = &expect.body_contains {
+            if !body.contains(expected.as_str()) {
+                bail!("expected body to contain {expected:?}, got {body:?}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_function(
+        &self,
+        component: &str,
+        interface: Option<&str>,
+        function: &str,
+    ) -> anyhow::Result<()> {
+        let trigger_app = self.server.trigger_app();
+        let (instance, mut store) = trigger_app
+            .prepare(component)
+            .await?
+            .instantiate(())
+            .await?;
+
+        let interface_index = match interface {
+            Some(name) => Some(
+                instance
+                    .get_export_index(&mut store, None, name)
+                    .with_context(|| format!("component '{component}' doesn't export '{name}'"))?,
+            ),
+            None => None,
+        };
+        let function_index = instance
+            .get_export_index(&mut store, interface_index.as_ref(), function)
+            .with_context(|| {
+                format!("component '{component}' doesn't export the function '{function}'")
+            })?;
+        let func = instance
+            .get_typed_func::<(), (Result<(), String>,)>(&mut store, &function_index)
+            .with_context(|| {
+                format!(
+                    "test function '{function}' must have the type `func() -> result<_, string>`"
+                )
+            })?;
+        let (result,) = func.call_async(&mut store, ()).await?;
+        func.post_return_async(&mut store).await?;
+        result.map_err(anyhow::Error::msg)
+    }
+}
@@ -0,0 +1,291 @@
+//! A trigger whose events come from an out-of-tree executor.
+//!
+//! The executor is a separate program which speaks the stdio protocol in
+//! [`protocol`]. Spin hosts the app and runs its components; the executor only
+//! decides when to invoke them and with what payload. This lets triggers such
+//! as Kafka consumers be shipped independently of Spin.
+
+pub mod protocol;
+
+use std::{collections::HashMap, path::PathBuf, process::Stdio, sync::Arc, time::Instant};
+
+use anyhow::{bail, ensure, Context};
+use clap::Args;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factors::RuntimeFactors;
+use spin_trigger::{App, Trigger, TriggerApp};
+use spin_world::exports::spin::trigger::handler;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{ChildStdin, ChildStdout},
+    sync::mpsc,
+};
+use tracing::{instrument, Level};
+
+use protocol::{
+    AppMetadata, ExecutorMessage, HostMessage, InvocationOutcome, TriggerMetadata, PROTOCOL_VERSION,
+};
+
+/// How many messages to the executor may be queued before senders wait.
+const OUTGOING_MESSAGE_BUFFER: usize = 64;
+
+/// Runs an app's triggers of one type by driving an external executor.
+pub struct ExternalTrigger {
+    /// The trigger type the executor handles.
+    trigger_type: String,
+    /// The executor program.
+    executor: PathBuf,
+    /// The app's triggers of `trigger_type`.
+    triggers: Vec<TriggerMetadata>,
+}
+
+/// [`ExternalTrigger`] specific CLI arguments.
+#[derive(Args, Clone, Debug)]
+pub struct CliArgs {
+    /// The trigger type to run with the external executor.
+    #[clap(long = "external-trigger-type", hide = true)]
+    pub trigger_type: String,
+
+    /// The external trigger executor program.
+    #[clap(long = "external-trigger-executor", hide = true)]
+    pub executor: PathBuf,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for ExternalTrigger {
+    const TYPE: &'static str = "external";
+
+    type CliArgs = CliArgs;
+
+    type InstanceState = ();
+
+    fn new(cli_args: Self::CliArgs, app: &App) -> anyhow::Result<Self> {
+        let triggers = app
+            .triggers_with_type(&cli_args.trigger_type)
+            .map(|trigger| {
+                Ok(TriggerMetadata {
+                    id: trigger.id().to_owned(),
+                    component: trigger.component()?.id().to_owned(),
+                    config: trigger.typed_config()?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        ensure!(
+            !triggers.is_empty(),
+            "The app has no '{}' triggers",
+            cli_args.trigger_type
+        );
+        Ok(Self {
+            trigger_type: cli_args.trigger_type,
+            executor: cli_args.executor,
+            triggers,
+        })
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        let mut child = tokio::process::Command::new(&self.executor)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "Failed to start '{}' trigger executor {}",
+                    self.trigger_type,
+                    self.executor.display()
+                )
+            })?;
+        let stdin = child.stdin.take().context("executor stdin not piped")?;
+        let mut messages =
+            BufReader::new(child.stdout.take().context("executor stdout not piped")?).lines();
+
+        let (sender, receiver) = mpsc::channel(OUTGOING_MESSAGE_BUFFER);
+        let writer = tokio::spawn(write_messages(stdin, receiver));
+
+        self.start(&sender, &mut messages, trigger_app.app().id())
+            .await
+            .with_context(|| format!("'{}' trigger executor failed to start", self.trigger_type))?;
+
+        let components: Arc<HashMap<_, _>> = Arc::new(
+            self.triggers
+                .iter()
+                .map(|t| (t.id.clone(), t.component.clone()))
+                .collect(),
+        );
+        let trigger_app = Arc::new(trigger_app);
+
+        while let Some(message) = read_message(&mut messages).await? {
+            match message {
+                ExecutorMessage::Invoke {
+                    id,
+                    trigger_id,
+                    payload,
+                } => {
+                    let trigger_app = trigger_app.clone();
+                    let components = components.clone();
+                    let sender = sender.clone();
+                    tokio::spawn(async move {
+                        let outcome = match components.get(&trigger_id) {
+                            Some(component_id) => {
+                                match handle_event(&trigger_app, component_id, &trigger_id, payload)
+                                    .await
+                                {
+                                    Ok(payload) => InvocationOutcome::Ok { payload },
+                                    Err(err) => InvocationOutcome::Error {
+                                        message: format!("{err:#}"),
+                                    },
+                                }
+                            }
+                            None => InvocationOutcome::Error {
+                                message: format!("unknown trigger {trigger_id:?}"),
+                            },
+                        };
+                        // The executor has gone away if this fails, which
+                        // the read loop will notice.
+                        _ = sender.send(HostMessage::Result { id, outcome }).await;
+                    });
+                }
+                ExecutorMessage::Description { .. } => {
+                    tracing::warn!("Ignoring unexpected description from trigger executor");
+                }
+            }
+        }
+
+        // The executor closed its stdout; stop sending to it and wait for it
+        // to exit.
+        drop(sender);
+        writer.abort();
+        let status = child.wait().await?;
+        if !status.success() {
+            bail!(
+                "'{}' trigger executor exited with {status}",
+                self.trigger_type
+            );
+        }
+        Ok(())
+    }
+}
+
+impl ExternalTrigger {
+    /// Describes and starts the executor.
+    async fn start(
+        &self,
+        sender: &mpsc::Sender<HostMessage>,
+        messages: &mut Lines<BufReader<ChildStdout>>,
+        app_id: &str,
+    ) -> anyhow::Result<()> {
+        sender
+            .send(HostMessage::Describe {
+                protocol_version: PROTOCOL_VERSION,
+            })
+            .await?;
+        let fields = match read_message(messages).await? {
+            Some(ExecutorMessage::Description {
+                protocol_version,
+                config,
+            }) => {
+                ensure!(
+                    protocol_version == PROTOCOL_VERSION,
+                    "executor speaks protocol version {protocol_version}, but Spin speaks version {PROTOCOL_VERSION}"
+                );
+                config
+            }
+            Some(other) => bail!("expected a description from the executor, got {other:?}"),
+            None => bail!("executor exited without describing itself"),
+        };
+        for trigger in &self.triggers {
+            protocol::validate_config(&fields, &trigger.id, &trigger.config)?;
+        }
+
+        sender
+            .send(HostMessage::Start {
+                app: AppMetadata {
+                    id: app_id.to_owned(),
+                    trigger_type: self.trigger_type.clone(),
+                    triggers: self.triggers.clone(),
+                },
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// Reads the next message from the executor, or `None` if it closed stdout.
+async fn read_message(
+    messages: &mut Lines<BufReader<ChildStdout>>,
+) -> anyhow::Result<Option<ExecutorMessage>> {
+    loop {
+        let Some(line) = messages.next_line().await? else {
+            return Ok(None);
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = serde_json::from_str(&line)
+            .with_context(|| format!("invalid message from trigger executor: {line}"))?;
+        return Ok(Some(message));
+    }
+}
+
+/// Writes messages to the executor's stdin until the channel closes.
+async fn write_messages(
+    mut stdin: ChildStdin,
+    mut receiver: mpsc::Receiver<HostMessage>,
+) -> anyhow::Result<()> {
+    while let Some(message) = receiver.recv().await {
+        let mut line = serde_json::to_vec(&message)?;
+        line.push(b'\n');
+        stdin.write_all(&line).await?;
+        stdin.flush().await?;
+    }
+    Ok(())
+}
+
+#[instrument(name = "spin_trigger_external.handle_event", skip_all, err(level = Level::INFO), fields(
+    otel.name = format!("{trigger_id} event"),
+    otel.kind = "consumer",
+    component_id = component_id,
+))]
+async fn handle_event<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<ExternalTrigger, F>,
+    component_id: &str,
+    trigger_id: &str,
+    payload: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "external",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let start = Instant::now();
+    let mut audit = None;
+    let result = async {
+        let mut instance_builder = trigger_app.prepare(component_id).await?;
+        audit = instance_builder
+            .factor_builder::<AuditFactor>()
+            .and_then(|audit| audit.handle());
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+
+        let pre = instance.instance_pre(&store);
+        let guest_indices = handler::GuestIndices::new(&pre)?;
+        let guest = guest_indices.load(&mut store, &instance)?;
+
+        guest
+            .call_handle_event(&mut store, trigger_id, &payload)
+            .await?
+            .map_err(|e| anyhow::anyhow!("event handler returned an error: {e}"))
+    }
+    .await;
+    spin_telemetry::metrics::histogram!(
+        spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+        trigger_type = "external",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+    if let Some(audit) = audit {
+        audit.finish("external", trigger_id, AuditOutcome::from_result(&result));
+    }
+    result
+}
@@ -0,0 +1,262 @@
+//! The protocol between Spin and an external trigger executor.
+//!
+//! Spin starts the executor as a child process and exchanges messages with it
+//! over the executor's stdin and stdout, one JSON object per line. The
+//! executor's stderr is passed through to Spin's stderr, so executors should
+//! log there.
+//!
+//! 1. Spin sends [`HostMessage::Describe`]. The executor replies with
+//!    [`ExecutorMessage::Description`], declaring the fields its trigger
+//!    configuration accepts.
+//! 2. Spin checks the app's trigger configs against those fields and sends
+//!    [`HostMessage::Start`] with the app's metadata and trigger configs.
+//! 3. The executor sends [`ExecutorMessage::Invoke`] for each event. Spin runs
+//!    the trigger's component and replies with [`HostMessage::Result`] for the
+//!    same `id`. Invocations may run concurrently, so results can arrive in
+//!    any order.
+//!
+//! Spin closes the executor's stdin when it shuts down; the executor should
+//! then exit. If the executor exits, the trigger stops.
+
+use std::collections::HashSet;
+
+use anyhow::{bail, ensure};
+use serde::{Deserialize, Serialize};
+
+/// The version of the protocol described by this module.
+///
+/// The version is incremented when a change would break existing executors.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A message from Spin to an executor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum HostMessage {
+    /// Asks the executor to describe itself.
+    Describe {
+        /// The protocol version Spin speaks.
+        protocol_version: u32,
+    },
+    /// Tells the executor to start delivering events for the app.
+    Start {
+        /// The app the executor is delivering events to.
+        app: AppMetadata,
+    },
+    /// The result of an [`ExecutorMessage::Invoke`].
+    Result {
+        /// The `id` of the invocation.
+        id: u64,
+        /// The outcome of the invocation.
+        outcome: InvocationOutcome,
+    },
+}
+
+/// A message from an executor to Spin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ExecutorMessage {
+    /// The reply to [`HostMessage::Describe`].
+    Description {
+        /// The protocol version the executor speaks.
+        protocol_version: u32,
+        /// The fields the executor's trigger configuration accepts, other than
+        /// `component`.
+        #[serde(default)]
+        config: Vec<ConfigField>,
+    },
+    /// Asks Spin to run the component of a trigger.
+    Invoke {
+        /// An ID chosen by the executor to match the invocation to its
+        /// [`HostMessage::Result`].
+        id: u64,
+        /// The ID of the trigger the event is for.
+        trigger_id: String,
+        /// The event payload, passed to the component's handler.
+        #[serde(with = "base64_bytes")]
+        payload: Vec<u8>,
+    },
+}
+
+/// A trigger configuration field accepted by an executor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfigField {
+    /// The name of the field.
+    pub name: String,
+    /// Whether every trigger must set the field.
+    #[serde(default)]
+    pub required: bool,
+    /// A description of the field, for error messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The app metadata sent to an executor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppMetadata {
+    /// The app ID.
+    pub id: String,
+    /// The trigger type the executor is delivering events for.
+    pub trigger_type: String,
+    /// The app's triggers of that type.
+    pub triggers: Vec<TriggerMetadata>,
+}
+
+/// A trigger sent to an executor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TriggerMetadata {
+    /// The trigger ID.
+    pub id: String,
+    /// The ID of the component the trigger invokes.
+    pub component: String,
+    /// The trigger configuration from the app manifest.
+    pub config: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The outcome of an invocation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum InvocationOutcome {
+    /// The handler succeeded.
+    Ok {
+        /// The bytes the handler returned.
+        #[serde(with = "base64_bytes")]
+        payload: Vec<u8>,
+    },
+    /// The handler failed, or the component couldn't be run.
+    Error {
+        /// A description of the error.
+        message: String,
+    },
+}
+
+/// Checks a trigger config against the fields an executor accepts.
+pub fn validate_config(
+    fields: &[ConfigField],
+    trigger_id: &str,
+    config: &serde_json::Map<String, serde_json::Value>,
+) -> anyhow::Result<()> {
+    let known = fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .chain(["component"])
+        .collect::<HashSet<_>>();
+    if let Some(unknown) = config.keys().find(|key| !known.contains(key.as_str())) {
+        bail!("trigger {trigger_id:?} has unknown config field {unknown:?}");
+    }
+    for field in fields.iter().filter(|f| f.required) {
+        ensure!(
+            config.contains_key(&field.name),
+            "trigger {trigger_id:?} is missing required config field {:?}{}",
+            field.name,
+            field
+                .description
+                .as_ref()
+                .map(|d| format!(" ({d})"))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+mod base64_bytes {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn messages_use_documented_format() {
+        let invoke: ExecutorMessage = serde_json::from_value(json!({
+            "type": "invoke",
+            "id": 7,
+            "trigger_id": "orders",
+            "payload": "aGVsbG8=",
+        }))
+        .unwrap();
+        assert_eq!(
+            invoke,
+            ExecutorMessage::Invoke {
+                id: 7,
+                trigger_id: "orders".into(),
+                payload: b"hello".to_vec(),
+            }
+        );
+
+        let result = HostMessage::Result {
+            id: 7,
+            outcome: InvocationOutcome::Ok {
+                payload: b"done".to_vec(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({
+                "type": "result",
+                "id": 7,
+                "outcome": { "status": "ok", "payload": "ZG9uZQ==" },
+            })
+        );
+    }
+
+    #[test]
+    fn description_config_defaults_to_empty() {
+        let description: ExecutorMessage = serde_json::from_value(json!({
+            "type": "description",
+            "protocol_version": 1,
+        }))
+        .unwrap();
+        assert_eq!(
+            description,
+            ExecutorMessage::Description {
+                protocol_version: 1,
+                config: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn config_is_validated_against_fields() {
+        let fields = [
+            ConfigField {
+                name: "topic".into(),
+                required: true,
+                description: None,
+            },
+            ConfigField {
+                name: "group".into(),
+                required: false,
+                description: None,
+            },
+        ];
+        let config = |value: serde_json::Value| value.as_object().unwrap().clone();
+
+        validate_config(
+            &fields,
+            "t",
+            &config(json!({ "component": "c", "topic": "orders" })),
+        )
+        .unwrap();
+        validate_config(&fields, "t", &config(json!({ "component": "c" })))
+            .expect_err("missing required field should be rejected");
+        validate_config(
+            &fields,
+            "t",
+            &config(json!({ "component": "c", "topic": "orders", "partition": 1 })),
+        )
+        .expect_err("unknown field should be rejected");
+    }
+}
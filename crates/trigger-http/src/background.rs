@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::{anyhow, Context};
+use spin_factor_background_tasks::{
+    AppState, BackgroundTasksFactor, RetryPolicy, Task, TaskQueue, TaskReceiver,
+};
+use spin_factors::RuntimeFactors;
+use spin_world::exports::spin::background::task_handler;
+use tokio::task;
+use tracing::{instrument, Instrument};
+
+use crate::{server::HttpServer, TriggerInstanceBuilder};
+
+/// Runs the tasks components spawn to run after their response is sent.
+pub(crate) struct BackgroundTasks {
+    queue: TaskQueue,
+    /// Taken when the server starts running tasks.
+    receiver: Mutex<Option<TaskReceiver>>,
+    retry_policy: RetryPolicy,
+}
+
+impl BackgroundTasks {
+    pub(crate) fn new(app_state: &AppState) -> Self {
+        let (queue, receiver) = TaskQueue::new(app_state.queue_capacity());
+        Self {
+            queue,
+            receiver: Mutex::new(Some(receiver)),
+            retry_policy: app_state.retry_policy().clone(),
+        }
+    }
+
+    /// Lets the instance being built spawn background tasks.
+    pub(crate) fn prepare_instance<F: RuntimeFactors>(
+        &self,
+        instance_builder: &mut TriggerInstanceBuilder<F>,
+    ) {
+        if let Some(builder) = instance_builder.factor_builder::<BackgroundTasksFactor>() {
+            builder.set_task_queue(self.queue.clone());
+        }
+    }
+
+    /// Starts running spawned tasks on behalf of `server`.
+    ///
+    /// Only the first call has any effect. Tasks stop being run once `server`
+    /// is dropped, e.g. after its app has been reloaded; tasks that are
+    /// already running are allowed to finish.
+    pub(crate) fn start<F: RuntimeFactors>(&self, server: Arc<HttpServer<F>>) {
+        let Some(mut receiver) = self.receiver.lock().unwrap().take() else {
+            return;
+        };
+        let server = Arc::downgrade(&server);
+        let retry_policy = self.retry_policy.clone();
+        task::spawn(async move {
+            while let Some(task) = receiver.recv().await {
+                let Some(server) = Weak::upgrade(&server) else {
+                    tracing::warn!(
+                        "Dropping background task {:?} for component {}: app has been unloaded",
+                        task.name(),
+                        task.component_id()
+                    );
+                    break;
+                };
+                task::spawn(run_task(server, task, retry_policy.clone()).in_current_span());
+            }
+        });
+    }
+}
+
+#[instrument(name = "spin_trigger_http.background_task", skip_all, fields(
+    otel.name = format!("background task {}", task.name()),
+    otel.kind = "consumer",
+    component_id = task.component_id(),
+))]
+async fn run_task<F: RuntimeFactors>(
+    server: Arc<HttpServer<F>>,
+    task: Task,
+    retry_policy: RetryPolicy,
+) {
+    let component_id = task.component_id();
+    let name = task.name();
+    let outcome = match run_attempts(&server, &task, &retry_policy).await {
+        Ok(()) => "succeeded",
+        Err(err) => {
+            tracing::error!(
+                "Background task {name:?} for component {component_id} failed: {err:?}"
+            );
+            "failed"
+        }
+    };
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.background_task_count = 1,
+        app_id = server.trigger_app().app().id(),
+        component_id = component_id,
+        outcome = outcome
+    );
+}
+
+/// Runs the task until it succeeds or the retry policy gives up on it.
+async fn run_attempts<F: RuntimeFactors>(
+    server: &Arc<HttpServer<F>>,
+    task: &Task,
+    retry_policy: &RetryPolicy,
+) -> anyhow::Result<()> {
+    let component_id = task.component_id();
+    let pre = server.trigger_app().get_instance_pre(component_id).await?;
+    // A component without a task handler will never succeed, so don't retry.
+    guest_indices(component_id, &pre)?;
+
+    let mut attempt = 1;
+    loop {
+        let err = match run_attempt(server, task).await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let Some(backoff) = retry_policy.backoff(attempt) else {
+            return Err(err.context(format!("gave up after {attempt} attempt(s)")));
+        };
+        tracing::warn!(
+            "Background task {:?} for component {component_id} failed on attempt {attempt}, retrying in {backoff:?}: {err:?}",
+            task.name()
+        );
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+fn guest_indices<T>(
+    component_id: &str,
+    pre: &spin_core::InstancePre<T>,
+) -> anyhow::Result<task_handler::GuestIndices> {
+    task_handler::GuestIndices::new(pre).with_context(|| {
+        format!("component {component_id} does not export spin:background/task-handler")
+    })
+}
+
+async fn run_attempt<F: RuntimeFactors>(
+    server: &Arc<HttpServer<F>>,
+    task: &Task,
+) -> anyhow::Result<()> {
+    let mut instance_builder = server.trigger_app().prepare(task.component_id()).await?;
+    // A lazily loaded component may have been reloaded since the last attempt.
+    let indices = guest_indices(task.component_id(), instance_builder.instance_pre())?;
+    // Tasks may spawn further tasks.
+    if let Some(background_tasks) = server.background_tasks() {
+        background_tasks.prepare_instance(&mut instance_builder);
+    }
+    let (instance, mut store) = instance_builder.instantiate(()).await?;
+    let guest = indices.load(&mut store, &instance)?;
+    guest
+        .call_run_task(&mut store, task.name(), task.payload())
+        .await?
+        .map_err(|e| anyhow!("task handler returned an error: {e}"))
+}
@@ -0,0 +1,294 @@
+//! Compression of response bodies, negotiated with the client.
+
+use std::{io, pin::Pin};
+
+use async_compression::{
+    tokio::bufread::{BrotliEncoder, GzipEncoder},
+    Level,
+};
+use futures::TryStreamExt;
+use http::{
+    header::{self, HeaderValue},
+    HeaderMap, Response, StatusCode,
+};
+use http_body_util::{combinators::BoxBody, BodyExt, StreamBody};
+use hyper::body::{Body as _, Frame};
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Responses smaller than this many bytes are not compressed by default.
+pub(crate) const DEFAULT_MIN_SIZE: u64 = 1024;
+
+/// Brotli quality used for responses. The maximum (11) is too slow for
+/// compressing on the fly.
+const BROTLI_QUALITY: i32 = 4;
+
+/// A content coding the HTTP trigger can compress responses with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// Picks the encoding the client most prefers according to its
+    /// `Accept-Encoding` headers, preferring brotli when it has no preference.
+    pub(crate) fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        let mut brotli = None;
+        let mut gzip = None;
+        let mut wildcard = None;
+        for coding in headers
+            .get_all(header::ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+        {
+            let mut params = coding.split(';');
+            let name = params
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match name.as_str() {
+                "br" => brotli = Some(quality),
+                "gzip" | "x-gzip" => gzip = Some(quality),
+                "*" => wildcard = Some(quality),
+                _ => {}
+            }
+        }
+        let brotli = brotli.or(wildcard).unwrap_or(0.0);
+        let gzip = gzip.or(wildcard).unwrap_or(0.0);
+        if brotli <= 0.0 && gzip <= 0.0 {
+            None
+        } else if brotli >= gzip {
+            Some(Self::Brotli)
+        } else {
+            Some(Self::Gzip)
+        }
+    }
+}
+
+/// Compresses `response` with `encoding`, unless it is already encoded, too
+/// small, not compressible, or must not be transformed.
+///
+/// The body is compressed as it streams; any trailers are dropped.
+pub(crate) fn compress(
+    response: Response<Body>,
+    encoding: Encoding,
+    min_size: u64,
+) -> Response<Body> {
+    if !should_compress(&response, min_size) {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    add_vary_accept_encoding(&mut parts.headers);
+    // The compressed representation is no longer byte-for-byte identical
+    if let Some(etag) = parts.headers.get(header::ETAG) {
+        if !etag.as_bytes().starts_with(b"W/") {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            if let Ok(weak) = HeaderValue::from_bytes(&weak) {
+                parts.headers.insert(header::ETAG, weak);
+            }
+        }
+    }
+
+    let reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+    let encoder: Pin<Box<dyn AsyncRead + Send + Sync>> = match encoding {
+        Encoding::Brotli => Box::pin(BrotliEncoder::with_quality(
+            reader,
+            Level::Precise(BROTLI_QUALITY),
+        )),
+        Encoding::Gzip => Box::pin(GzipEncoder::new(reader)),
+    };
+    let frames = ReaderStream::new(encoder)
+        .map_ok(Frame::data)
+        .map_err(|err| ErrorCode::InternalError(Some(format!("compression failed: {err}"))));
+    Response::from_parts(parts, BoxBody::new(StreamBody::new(frames)))
+}
+
+fn should_compress(response: &Response<Body>, min_size: u64) -> bool {
+    let status = response.status();
+    if status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::PARTIAL_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+    {
+        return false;
+    }
+    let headers = response.headers();
+    if headers.contains_key(header::CONTENT_ENCODING) || headers.contains_key(header::CONTENT_RANGE)
+    {
+        return false;
+    }
+    if headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.to_ascii_lowercase().contains("no-transform"))
+    {
+        return false;
+    }
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    if !is_compressible(content_type) {
+        return false;
+    }
+    let size = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+        .or_else(|| response.body().size_hint().exact());
+    // Streamed bodies of unknown size are compressed
+    size.is_none_or(|size| size >= min_size)
+}
+
+/// Whether compressing a body of `content_type` is likely to make it smaller.
+fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/javascript"
+                | "application/xml"
+                | "application/wasm"
+                | "application/x-ndjson"
+        )
+}
+
+fn add_vary_accept_encoding(headers: &mut HeaderMap) {
+    let already_varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|name| {
+            let name = name.trim();
+            name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+        });
+    if !already_varies {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use hyper::body::Bytes;
+    use spin_http::body;
+
+    use super::*;
+
+    fn accepting(accept_encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(accept_encoding).unwrap(),
+        );
+        headers
+    }
+
+    fn response(content_type: &str, len: usize) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, len)
+            .header(header::ETAG, "\"v1\"")
+            .body(body::full(Bytes::from("a".repeat(len))))
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiates_preferred_encoding() {
+        assert_eq!(Encoding::negotiate(&HeaderMap::new()), None);
+        assert_eq!(
+            Encoding::negotiate(&accepting("gzip, deflate, br")),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(
+            Encoding::negotiate(&accepting("br;q=0.5, gzip")),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            Encoding::negotiate(&accepting("br;q=0, *")),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(Encoding::negotiate(&accepting("identity, deflate")), None);
+        assert_eq!(Encoding::negotiate(&accepting("*;q=0")), None);
+    }
+
+    #[test]
+    fn skips_unsuitable_responses() {
+        assert!(should_compress(
+            &response("text/html; charset=utf-8", 2048),
+            1024
+        ));
+        assert!(should_compress(
+            &response("application/ld+json", 2048),
+            1024
+        ));
+        assert!(!should_compress(&response("text/plain", 100), 1024));
+        assert!(!should_compress(&response("image/png", 2048), 1024));
+
+        let mut encoded = response("text/plain", 2048);
+        encoded
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+        assert!(!should_compress(&encoded, 1024));
+
+        let mut no_transform = response("text/plain", 2048);
+        no_transform.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("public, no-transform"),
+        );
+        assert!(!should_compress(&no_transform, 1024));
+    }
+
+    #[tokio::test]
+    async fn compresses_body_and_adjusts_headers() {
+        let compressed = compress(response("text/plain", 4096), Encoding::Gzip, 1024);
+        let headers = compressed.headers();
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        assert_eq!(headers[header::ETAG], "W/\"v1\"");
+        assert!(!headers.contains_key(header::CONTENT_LENGTH));
+
+        let bytes = compressed.into_body().collect().await.unwrap().to_bytes();
+        assert!(bytes.len() < 4096);
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&bytes[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, "a".repeat(4096));
+    }
+}
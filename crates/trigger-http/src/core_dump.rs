@@ -0,0 +1,49 @@
+use hyper::Request;
+use spin_trigger::core_dump::CoreDumper;
+
+use crate::Body;
+
+/// Headers whose values are left out of core dump metadata.
+const REDACTED_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// Captures a core dump if the guest handling a request traps.
+#[derive(Clone)]
+pub(crate) struct CoreDumpOnTrap {
+    core_dumper: CoreDumper,
+    /// The request's method, URI and headers.
+    payload: Vec<(String, String)>,
+}
+
+impl CoreDumpOnTrap {
+    pub fn new(core_dumper: &CoreDumper, req: &Request<Body>) -> Self {
+        let mut payload = vec![
+            ("method".to_owned(), req.method().to_string()),
+            ("uri".to_owned(), req.uri().to_string()),
+        ];
+        for (name, value) in req.headers() {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "<redacted>".into()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            payload.push((name.to_string(), value));
+        }
+        Self {
+            core_dumper: core_dumper.clone(),
+            payload,
+        }
+    }
+
+    /// Writes the core dump if the invocation failed with a trap.
+    pub fn capture<T, R>(
+        self,
+        store: &mut spin_core::Store<T>,
+        result: &anyhow::Result<R>,
+        component_id: &str,
+    ) {
+        if let Err(err) = result {
+            self.core_dumper
+                .capture(store, err, "http", component_id, self.payload);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Cancellation of guest invocations whose clients have disconnected.
+//!
+//! Hyper drops the future handling a request when its client disconnects,
+//! and drops the response body if the client disconnects while it is being
+//! sent. Executors that run the guest inline are cancelled along with the
+//! future, but the `wasi:http` executor runs the guest in a task of its own,
+//! so that it can keep running after the response is sent. It holds an
+//! [`InvocationGuard`] until the response has been sent in full, which
+//! cancels the guest if it is dropped first, so that abandoned requests stop
+//! using the resources the guest would otherwise go on using.
+
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{ready, Context, Poll},
+};
+
+use http_body_util::BodyExt;
+use hyper::body::{Body as HttpBody, Bytes, Frame, SizeHint};
+use spin_core::StoreInterrupt;
+use tokio::task::AbortHandle;
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Cancels a guest invocation when dropped, unless disarmed.
+///
+/// Cancelling aborts the invocation's task, which drops any outbound calls
+/// it is waiting on, and interrupts the guest if it is executing.
+pub(crate) struct InvocationGuard {
+    task: AbortHandle,
+    interrupt: Option<StoreInterrupt>,
+    armed: AtomicBool,
+}
+
+impl InvocationGuard {
+    pub(crate) fn new(task: AbortHandle, interrupt: Option<StoreInterrupt>) -> Self {
+        Self {
+            task,
+            interrupt,
+            armed: AtomicBool::new(true),
+        }
+    }
+
+    /// Lets the invocation run to completion.
+    pub(crate) fn disarm(&self) {
+        self.armed.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `body`, cancelling the invocation if the body is dropped
+    /// before its end.
+    pub(crate) fn guard_body(self, body: Body) -> Body {
+        GuardedBody {
+            inner: body,
+            guard: self,
+        }
+        .boxed()
+    }
+}
+
+impl Drop for InvocationGuard {
+    fn drop(&mut self) {
+        if !*self.armed.get_mut() || self.task.is_finished() {
+            return;
+        }
+        tracing::info!("Client disconnected; cancelling guest invocation");
+        if let Some(interrupt) = &self.interrupt {
+            interrupt.interrupt();
+        }
+        self.task.abort();
+    }
+}
+
+/// A body which disarms an [`InvocationGuard`] at its end.
+struct GuardedBody {
+    inner: Body,
+    guard: InvocationGuard,
+}
+
+impl HttpBody for GuardedBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, ErrorCode>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if frame.is_none() {
+            this.guard.disarm();
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        // Hyper stops polling bodies which report their end early
+        let end = self.inner.is_end_stream();
+        if end {
+            self.guard.disarm();
+        }
+        end
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use spin_http::body;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    fn pending_task() -> JoinHandle<()> {
+        tokio::spawn(std::future::pending())
+    }
+
+    #[tokio::test]
+    async fn dropped_guard_cancels_invocation() {
+        let task = pending_task();
+        let interrupt = StoreInterrupt::default();
+        drop(InvocationGuard::new(
+            task.abort_handle(),
+            Some(interrupt.clone()),
+        ));
+        assert!(interrupt.is_interrupted());
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn disarmed_guard_does_not_cancel_invocation() {
+        let task = pending_task();
+        let interrupt = StoreInterrupt::default();
+        InvocationGuard::new(task.abort_handle(), Some(interrupt.clone())).disarm();
+        assert!(!interrupt.is_interrupted());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!task.is_finished());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn body_disarms_guard_at_its_end() {
+        let interrupt = StoreInterrupt::default();
+        let guard = InvocationGuard::new(pending_task().abort_handle(), Some(interrupt.clone()));
+        let body = guard.guard_body(body::full(Bytes::from_static(b"done")));
+        assert_eq!(body.collect().await.unwrap().to_bytes(), "done");
+        assert!(!interrupt.is_interrupted());
+
+        let interrupt = StoreInterrupt::default();
+        let guard = InvocationGuard::new(pending_task().abort_handle(), Some(interrupt.clone()));
+        drop(guard.guard_body(body::full(Bytes::from_static(b"abandoned"))));
+        assert!(interrupt.is_interrupted());
+    }
+}
@@ -0,0 +1,729 @@
+//! A GraphQL endpoint which splits each operation across the components that
+//! resolve its root fields.
+//!
+//! The gateway does not know the app's schema. It routes each root field of
+//! an operation to the component configured to resolve it, sends each
+//! component a request containing only its fields (and the variables and
+//! fragments those fields use), and merges the responses.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use anyhow::{bail, ensure, Context};
+use graphql_parser::query::{
+    parse_query, Definition, Directive, Document, FragmentDefinition, OperationDefinition,
+    Selection, SelectionSet, Value, VariableDefinition,
+};
+use http::{header, uri::Scheme, Method, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use serde::Deserialize;
+use serde_json::{json, Map, Value as Json};
+use spin_factors::RuntimeFactors;
+use spin_http::{body, config::HttpTriggerConfig, routes::RouteMatch};
+
+use crate::{server::Tenant, Body, HttpServer};
+
+type Doc = Document<'static, String>;
+
+/// Routes GraphQL root fields to the components that resolve them.
+pub(crate) struct GraphqlGateway {
+    /// The route the GraphQL endpoint is served on.
+    route: String,
+    /// Query root field -> component ID
+    query_fields: HashMap<String, String>,
+    /// Mutation root field -> component ID
+    mutation_fields: HashMap<String, String>,
+}
+
+impl GraphqlGateway {
+    /// Builds the gateway from the app's trigger configs.
+    pub(crate) fn new<'a>(
+        route: String,
+        trigger_configs: impl IntoIterator<Item = (&'a String, &'a HttpTriggerConfig)>,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            route.starts_with('/'),
+            "GraphQL route {route:?} must start with '/'"
+        );
+        ensure!(
+            !route.starts_with(spin_http::WELL_KNOWN_PREFIX),
+            "GraphQL route {route:?} is reserved by the Spin runtime"
+        );
+        let mut query_fields = HashMap::new();
+        let mut mutation_fields = HashMap::new();
+        for (component_id, config) in trigger_configs {
+            let Some(graphql) = &config.graphql else {
+                continue;
+            };
+            for (root, fields, owners) in [
+                ("Query", &graphql.query, &mut query_fields),
+                ("Mutation", &graphql.mutation, &mut mutation_fields),
+            ] {
+                for field in fields {
+                    if let Some(other) = owners.insert(field.clone(), component_id.clone()) {
+                        bail!(
+                            "GraphQL field {root}.{field} is resolved by both {other:?} and {component_id:?}"
+                        );
+                    }
+                }
+            }
+        }
+        if query_fields.is_empty() && mutation_fields.is_empty() {
+            tracing::warn!(
+                "GraphQL route {route} is configured but no component resolves any fields"
+            );
+        }
+        Ok(Self {
+            route,
+            query_fields,
+            mutation_fields,
+        })
+    }
+
+    /// The route the GraphQL endpoint is served on.
+    pub(crate) fn route(&self) -> &str {
+        &self.route
+    }
+
+    /// Splits a GraphQL request into one request per component.
+    fn plan(&self, request: &GraphqlRequest) -> Result<Plan, String> {
+        let document = parse_query::<String>(&request.query)
+            .map_err(|e| e.to_string())?
+            .into_static();
+        let operation = select_operation(&document, request.operation_name.as_deref())?;
+        let (root, owners, parts) = match operation {
+            OperationDefinition::SelectionSet(selection_set) => (
+                "Query",
+                &self.query_fields,
+                OperationParts::shorthand(selection_set),
+            ),
+            OperationDefinition::Query(query) => (
+                "Query",
+                &self.query_fields,
+                OperationParts {
+                    name: query.name.as_deref(),
+                    variable_definitions: &query.variable_definitions,
+                    directives: &query.directives,
+                    selection_set: &query.selection_set,
+                },
+            ),
+            OperationDefinition::Mutation(mutation) => (
+                "Mutation",
+                &self.mutation_fields,
+                OperationParts {
+                    name: mutation.name.as_deref(),
+                    variable_definitions: &mutation.variable_definitions,
+                    directives: &mutation.directives,
+                    selection_set: &mutation.selection_set,
+                },
+            ),
+            OperationDefinition::Subscription(_) => {
+                return Err("subscriptions are not supported".into())
+            }
+        };
+        let fragments: HashMap<&str, &FragmentDefinition<'static, String>> = document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some((fragment.name.as_str(), fragment)),
+                Definition::Operation(_) => None,
+            })
+            .collect();
+
+        let mut plan = Plan::default();
+        // Component ID -> the root fields sent to it, in query order
+        let mut component_fields: Vec<(String, Vec<Selection<'static, String>>)> = vec![];
+        for selection in &parts.selection_set.items {
+            let Selection::Field(field) = selection else {
+                return Err("fragments are not supported on the root operation type".into());
+            };
+            let key = field.alias.clone().unwrap_or_else(|| field.name.clone());
+            plan.keys.push(key.clone());
+            if field.name == "__typename" {
+                plan.local.push((key, root.to_owned()));
+                continue;
+            }
+            let Some(component_id) = owners.get(&field.name) else {
+                return Err(format!(
+                    "Cannot query field \"{}\" on type \"{root}\".",
+                    field.name
+                ));
+            };
+            match component_fields
+                .iter_mut()
+                .find(|(id, _)| id == component_id)
+            {
+                Some((_, fields)) => fields.push(selection.clone()),
+                None => component_fields.push((component_id.clone(), vec![selection.clone()])),
+            }
+        }
+
+        for (component_id, fields) in component_fields {
+            let mut usage = Usage::default();
+            usage.visit_directives(parts.directives);
+            usage.visit_selections(&fields, &fragments);
+            let keys = fields
+                .iter()
+                .filter_map(|selection| match selection {
+                    Selection::Field(field) => {
+                        Some(field.alias.clone().unwrap_or_else(|| field.name.clone()))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let query = parts.render(root, fields, &usage, &fragments);
+            let variables = request.variables.as_ref().map(|variables| {
+                variables
+                    .iter()
+                    .filter(|(name, _)| usage.variables.contains(name.as_str()))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect::<Map<_, _>>()
+            });
+            let mut body = json!({ "query": query });
+            if let Some(operation_name) = &request.operation_name {
+                body["operationName"] = operation_name.clone().into();
+            }
+            if let Some(variables) = variables {
+                body["variables"] = variables.into();
+            }
+            plan.requests.push(SubRequest {
+                component_id,
+                body,
+                keys,
+            });
+        }
+        Ok(plan)
+    }
+}
+
+/// A GraphQL request as sent over HTTP.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlRequest {
+    query: String,
+    #[serde(default)]
+    operation_name: Option<String>,
+    #[serde(default)]
+    variables: Option<Map<String, Json>>,
+}
+
+/// How a GraphQL request is split across components.
+#[derive(Debug, Default)]
+struct Plan {
+    /// The response keys of the root fields, in query order.
+    keys: Vec<String>,
+    /// Root fields answered by the gateway: (response key, value)
+    local: Vec<(String, String)>,
+    /// The requests to send, one per component.
+    requests: Vec<SubRequest>,
+}
+
+#[derive(Debug)]
+struct SubRequest {
+    component_id: String,
+    /// The JSON request body.
+    body: Json,
+    /// The response keys the component answers.
+    keys: Vec<String>,
+}
+
+impl Plan {
+    /// Merges the components' responses, in the order of `self.requests`,
+    /// into a single response.
+    fn merge(&self, responses: Vec<Result<Json, String>>) -> Json {
+        let mut values: HashMap<&str, Json> = self
+            .local
+            .iter()
+            .map(|(key, value)| (key.as_str(), Json::from(value.as_str())))
+            .collect();
+        let mut errors = vec![];
+        for (request, response) in self.requests.iter().zip(responses) {
+            match response {
+                Ok(mut response) => {
+                    let mut data = match response.get_mut("data").map(Json::take) {
+                        Some(Json::Object(data)) => data,
+                        _ => Map::new(),
+                    };
+                    for key in &request.keys {
+                        values.insert(key, data.remove(key).unwrap_or(Json::Null));
+                    }
+                    if let Some(Json::Array(component_errors)) =
+                        response.get_mut("errors").map(Json::take)
+                    {
+                        errors.extend(component_errors);
+                    }
+                }
+                Err(message) => {
+                    for key in &request.keys {
+                        values.insert(key, Json::Null);
+                        errors.push(json!({ "message": message, "path": [key] }));
+                    }
+                }
+            }
+        }
+        let data: Map<String, Json> = self
+            .keys
+            .iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    values.remove(key.as_str()).unwrap_or(Json::Null),
+                )
+            })
+            .collect();
+        let mut response = json!({ "data": data });
+        if !errors.is_empty() {
+            response["errors"] = errors.into();
+        }
+        response
+    }
+}
+
+/// The parts of an operation definition shared by every component's request.
+struct OperationParts<'a> {
+    name: Option<&'a str>,
+    variable_definitions: &'a [VariableDefinition<'static, String>],
+    directives: &'a [Directive<'static, String>],
+    selection_set: &'a SelectionSet<'static, String>,
+}
+
+impl<'a> OperationParts<'a> {
+    fn shorthand(selection_set: &'a SelectionSet<'static, String>) -> Self {
+        Self {
+            name: None,
+            variable_definitions: &[],
+            directives: &[],
+            selection_set,
+        }
+    }
+
+    /// Renders the operation restricted to `fields`, with only the variable
+    /// definitions and fragments they use.
+    fn render(
+        &self,
+        root: &str,
+        fields: Vec<Selection<'static, String>>,
+        usage: &Usage,
+        fragments: &HashMap<&str, &FragmentDefinition<'static, String>>,
+    ) -> String {
+        let selection_set = SelectionSet {
+            span: self.selection_set.span,
+            items: fields,
+        };
+        let variable_definitions = self
+            .variable_definitions
+            .iter()
+            .filter(|definition| usage.variables.contains(definition.name.as_str()))
+            .cloned()
+            .collect();
+        let position = self.selection_set.span.0;
+        let name = self.name.map(str::to_owned);
+        let directives = self.directives.to_vec();
+        let operation = match root {
+            "Mutation" => OperationDefinition::Mutation(graphql_parser::query::Mutation {
+                position,
+                name,
+                variable_definitions,
+                directives,
+                selection_set,
+            }),
+            _ => OperationDefinition::Query(graphql_parser::query::Query {
+                position,
+                name,
+                variable_definitions,
+                directives,
+                selection_set,
+            }),
+        };
+        let mut definitions = vec![Definition::Operation(operation)];
+        definitions.extend(
+            usage
+                .fragments
+                .iter()
+                .filter_map(|name| fragments.get(name.as_str()))
+                .map(|fragment| Definition::Fragment((*fragment).clone())),
+        );
+        Doc { definitions }.to_string()
+    }
+}
+
+/// Finds the operation to execute, following the GraphQL spec's rules.
+fn select_operation<'d>(
+    document: &'d Doc,
+    operation_name: Option<&str>,
+) -> Result<&'d OperationDefinition<'static, String>, String> {
+    let mut operations = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Operation(operation) => Some(operation),
+            Definition::Fragment(_) => None,
+        });
+    match operation_name {
+        Some(name) => operations
+            .find(|operation| operation_name_of(operation) == Some(name))
+            .ok_or_else(|| format!("Unknown operation named \"{name}\".")),
+        None => {
+            let operation = operations
+                .next()
+                .ok_or_else(|| "The document contains no operations.".to_string())?;
+            if operations.next().is_some() {
+                return Err(
+                    "Must provide operation name if query contains multiple operations.".into(),
+                );
+            }
+            Ok(operation)
+        }
+    }
+}
+
+fn operation_name_of<'d>(operation: &'d OperationDefinition<'static, String>) -> Option<&'d str> {
+    match operation {
+        OperationDefinition::SelectionSet(_) => None,
+        OperationDefinition::Query(query) => query.name.as_deref(),
+        OperationDefinition::Mutation(mutation) => mutation.name.as_deref(),
+        OperationDefinition::Subscription(subscription) => subscription.name.as_deref(),
+    }
+}
+
+/// The variables and fragments used by a set of selections.
+#[derive(Default)]
+struct Usage {
+    variables: BTreeSet<String>,
+    /// Fragment names, in the order they were first used.
+    fragments: Vec<String>,
+}
+
+impl Usage {
+    fn visit_selections(
+        &mut self,
+        selections: &[Selection<'static, String>],
+        fragments: &HashMap<&str, &FragmentDefinition<'static, String>>,
+    ) {
+        for selection in selections {
+            match selection {
+                Selection::Field(field) => {
+                    for (_, value) in &field.arguments {
+                        self.visit_value(value);
+                    }
+                    self.visit_directives(&field.directives);
+                    self.visit_selections(&field.selection_set.items, fragments);
+                }
+                Selection::FragmentSpread(spread) => {
+                    self.visit_directives(&spread.directives);
+                    if self.fragments.contains(&spread.fragment_name) {
+                        continue;
+                    }
+                    self.fragments.push(spread.fragment_name.clone());
+                    if let Some(fragment) = fragments.get(spread.fragment_name.as_str()) {
+                        self.visit_directives(&fragment.directives);
+                        self.visit_selections(&fragment.selection_set.items, fragments);
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    self.visit_directives(&inline.directives);
+                    self.visit_selections(&inline.selection_set.items, fragments);
+                }
+            }
+        }
+    }
+
+    fn visit_directives(&mut self, directives: &[Directive<'static, String>]) {
+        for directive in directives {
+            for (_, value) in &directive.arguments {
+                self.visit_value(value);
+            }
+        }
+    }
+
+    fn visit_value(&mut self, value: &Value<'static, String>) {
+        match value {
+            Value::Variable(name) => {
+                self.variables.insert(name.clone());
+            }
+            Value::List(values) => values.iter().for_each(|value| self.visit_value(value)),
+            Value::Object(fields) => fields.values().for_each(|value| self.visit_value(value)),
+            _ => {}
+        }
+    }
+}
+
+/// Serves a request to the GraphQL endpoint.
+pub(crate) async fn handle<F: RuntimeFactors>(
+    server: &Arc<HttpServer<F>>,
+    gateway: &GraphqlGateway,
+    req: Request<Body>,
+    server_scheme: Scheme,
+    client_addr: SocketAddr,
+) -> anyhow::Result<Response<Body>> {
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "POST")
+            .body(body::empty())?);
+    }
+    let (parts, request_body) = req.into_parts();
+    let request_body = request_body.collect().await?.to_bytes();
+    let request: GraphqlRequest = match serde_json::from_slice(&request_body) {
+        Ok(request) => request,
+        Err(err) => {
+            return graphql_response(
+                StatusCode::BAD_REQUEST,
+                json!({ "errors": [{ "message": format!("invalid GraphQL request: {err}") }] }),
+            )
+        }
+    };
+    let plan = match gateway.plan(&request) {
+        Ok(plan) => plan,
+        Err(message) => {
+            return graphql_response(
+                StatusCode::BAD_REQUEST,
+                json!({ "errors": [{ "message": message }] }),
+            )
+        }
+    };
+
+    let responses = futures::future::join_all(plan.requests.iter().map(|sub_request| {
+        let mut component_req = Request::builder()
+            .method(Method::POST)
+            .uri(parts.uri.clone())
+            .version(parts.version);
+        for (name, value) in &parts.headers {
+            // The gateway must be able to read the component's response
+            if name != header::CONTENT_LENGTH
+                && name != header::CONTENT_TYPE
+                && name != header::ACCEPT_ENCODING
+            {
+                component_req = component_req.header(name, value);
+            }
+        }
+        if let Some(tenant) = parts.extensions.get::<Tenant>() {
+            component_req = component_req.extension(tenant.clone());
+        }
+        let component_req = component_req
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body::full(Bytes::from(sub_request.body.to_string())));
+        let route_match =
+            RouteMatch::synthetic(sub_request.component_id.clone(), gateway.route().to_owned());
+        let server_scheme = server_scheme.clone();
+        async move {
+            let response = server
+                .handle_trigger_route(component_req?, route_match, server_scheme, client_addr)
+                .await?;
+            component_response(response).await
+        }
+    }))
+    .await;
+    let responses = responses
+        .into_iter()
+        .zip(&plan.requests)
+        .map(|(response, sub_request)| {
+            response.map_err(|err| {
+                let component_id = &sub_request.component_id;
+                tracing::error!("GraphQL request to component {component_id} failed: {err:?}");
+                format!("component {component_id} failed to resolve this field")
+            })
+        })
+        .collect();
+    graphql_response(StatusCode::OK, plan.merge(responses))
+}
+
+/// Reads a component's GraphQL response.
+async fn component_response(response: Response<Body>) -> anyhow::Result<Json> {
+    let status = response.status();
+    let response_body = response.into_body().collect().await?.to_bytes();
+    let json: Json = serde_json::from_slice(&response_body)
+        .with_context(|| format!("component returned a non-JSON response with status {status}"))?;
+    ensure!(
+        json.is_object(),
+        "component returned a JSON response which is not an object"
+    );
+    Ok(json)
+}
+
+fn graphql_response(status: StatusCode, response: Json) -> anyhow::Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body::full(Bytes::from(response.to_string())))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_http::config::GraphqlFieldsConfig;
+
+    use super::*;
+
+    fn gateway() -> GraphqlGateway {
+        let config = |component: &str, query: &[&str], mutation: &[&str]| {
+            (
+                component.to_string(),
+                HttpTriggerConfig {
+                    component: component.into(),
+                    graphql: Some(GraphqlFieldsConfig {
+                        query: query.iter().map(|f| f.to_string()).collect(),
+                        mutation: mutation.iter().map(|f| f.to_string()).collect(),
+                    }),
+                    ..Default::default()
+                },
+            )
+        };
+        let configs = [
+            config("users", &["user", "users"], &["createUser"]),
+            config("orders", &["orders"], &[]),
+        ];
+        GraphqlGateway::new(
+            "/graphql".into(),
+            configs.iter().map(|(id, config)| (id, config)),
+        )
+        .unwrap()
+    }
+
+    fn request(query: &str, variables: Json) -> GraphqlRequest {
+        GraphqlRequest {
+            query: query.into(),
+            operation_name: None,
+            variables: variables.as_object().cloned(),
+        }
+    }
+
+    fn reparse(query: &Json) -> Doc {
+        parse_query::<String>(query.as_str().unwrap())
+            .unwrap()
+            .into_static()
+    }
+
+    #[test]
+    fn rejects_fields_resolved_twice() {
+        let config = HttpTriggerConfig {
+            component: "a".into(),
+            graphql: Some(GraphqlFieldsConfig {
+                query: vec!["user".into()],
+                mutation: vec![],
+            }),
+            ..Default::default()
+        };
+        let (a, b) = ("a".to_string(), "b".to_string());
+        let result = GraphqlGateway::new("/graphql".into(), [(&a, &config), (&b, &config)]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn splits_root_fields_by_component() {
+        let plan = gateway()
+            .plan(&request(
+                "query Q($id: ID!, $first: Int) {
+                    me: user(id: $id) { ...UserFields }
+                    orders(first: $first) { id }
+                    __typename
+                }
+                fragment UserFields on User { name }
+                fragment Unused on User { id }",
+                json!({ "id": "1", "first": 10 }),
+            ))
+            .unwrap();
+        assert_eq!(plan.keys, ["me", "orders", "__typename"]);
+        assert_eq!(plan.local, [("__typename".into(), "Query".into())]);
+        assert_eq!(plan.requests.len(), 2);
+
+        let users = &plan.requests[0];
+        assert_eq!(users.component_id, "users");
+        assert_eq!(users.keys, ["me"]);
+        assert_eq!(users.body["variables"], json!({ "id": "1" }));
+        let users_query = reparse(&users.body["query"]).to_string();
+        assert!(users_query.contains("query Q($id: ID!)"));
+        assert!(users_query.contains("fragment UserFields on User"));
+        assert!(!users_query.contains("Unused"));
+        assert!(!users_query.contains("orders"));
+
+        let orders = &plan.requests[1];
+        assert_eq!(orders.component_id, "orders");
+        assert_eq!(orders.body["variables"], json!({ "first": 10 }));
+        let orders_query = reparse(&orders.body["query"]).to_string();
+        assert!(orders_query.contains("query Q($first: Int)"));
+        assert!(!orders_query.contains("fragment"));
+    }
+
+    #[test]
+    fn routes_mutations_separately_from_queries() {
+        let gateway = gateway();
+        let plan = gateway
+            .plan(&request(
+                "mutation { createUser(name: \"a\") { id } }",
+                Json::Null,
+            ))
+            .unwrap();
+        assert_eq!(plan.requests[0].component_id, "users");
+        assert!(gateway
+            .plan(&request("mutation { orders { id } }", Json::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_subscriptions() {
+        let gateway = gateway();
+        let err = gateway
+            .plan(&request("{ products { id } }", Json::Null))
+            .unwrap_err();
+        assert!(err.contains("products"), "{err}");
+        assert!(gateway
+            .plan(&request("subscription { orders { id } }", Json::Null))
+            .is_err());
+    }
+
+    #[test]
+    fn requires_operation_name_for_multiple_operations() {
+        let gateway = gateway();
+        let query = "query A { users { id } } query B { orders { id } }";
+        assert!(gateway.plan(&request(query, Json::Null)).is_err());
+        let plan = gateway
+            .plan(&GraphqlRequest {
+                query: query.into(),
+                operation_name: Some("B".into()),
+                variables: None,
+            })
+            .unwrap();
+        assert_eq!(plan.requests[0].component_id, "orders");
+    }
+
+    #[test]
+    fn merges_responses_in_query_order() {
+        let plan = gateway()
+            .plan(&request(
+                "{ orders { id } users { id } __typename }",
+                Json::Null,
+            ))
+            .unwrap();
+        let merged = plan.merge(vec![
+            Ok(json!({ "data": { "orders": [{ "id": 1 }] } })),
+            Ok(json!({
+                "data": { "users": null },
+                "errors": [{ "message": "boom", "path": ["users"] }]
+            })),
+        ]);
+        assert_eq!(
+            merged,
+            json!({
+                "data": { "orders": [{ "id": 1 }], "users": null, "__typename": "Query" },
+                "errors": [{ "message": "boom", "path": ["users"] }]
+            })
+        );
+    }
+
+    #[test]
+    fn reports_failed_components_per_field() {
+        let plan = gateway()
+            .plan(&request("{ users { id } orders { id } }", Json::Null))
+            .unwrap();
+        let merged = plan.merge(vec![
+            Ok(json!({ "data": { "users": [] } })),
+            Err("component orders failed to resolve this field".into()),
+        ]);
+        assert_eq!(merged["data"], json!({ "users": [], "orders": null }));
+        assert_eq!(merged["errors"][0]["path"], json!(["orders"]));
+    }
+}
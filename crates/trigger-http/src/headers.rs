@@ -82,6 +82,11 @@ pub fn compute_default_headers<'a>(
 
 pub fn strip_forbidden_headers(req: &mut Request<Body>) {
     let headers = req.headers_mut();
+    // Only the trigger may vouch for a webhook's signature
+    headers.remove(crate::webhook::VERIFIED_HEADER);
+    // Only the trigger may vouch for a signed-in user
+    headers.remove(crate::oidc::SUBJECT_HEADER);
+    headers.remove(crate::oidc::CLAIMS_HEADER);
     if let Some(host_header) = headers.get("Host") {
         if let Ok(host) = host_header.to_str() {
             if is_service_chaining_host(host) {
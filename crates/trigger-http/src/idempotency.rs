@@ -0,0 +1,526 @@
+//! Replaying responses to requests retried with the same `Idempotency-Key`.
+//!
+//! The first request with a key is handled by the component and its response
+//! stored in a key-value store. Retries with the same key within the TTL get
+//! the stored response without invoking the component, so a client can retry
+//! e.g. a payment without it being made twice. A key reused for a different
+//! request is rejected with `422`, and a retry while the first request is
+//! still being handled with `409`. Requests and responses with bodies over
+//! 1 MiB are streamed through without being stored.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+    time::Duration,
+};
+
+use anyhow::{ensure, Context};
+use http::{HeaderName, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body as HttpBody, Bytes, Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spin_common::time::now_secs;
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+use spin_http::{body, config::IdempotencyConfig};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// The request header carrying the idempotency key.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// The response header marking a replayed response.
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+/// Prefix for the keys of stored responses in the backing store.
+const RECORD_KEY_PREFIX: &str = "spin-idempotency:";
+/// The longest idempotency key accepted.
+const MAX_KEY_LEN: usize = 255;
+/// How long stored responses are replayed for, by default.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a request's claim on its key lasts if its response is never
+/// stored, e.g. because the host exited while handling it.
+const IN_PROGRESS_TTL: Duration = Duration::from_secs(5 * 60);
+/// The largest request or response body which is buffered. Requests with
+/// larger bodies are handled without an idempotency key, and larger
+/// responses aren't replayed, so the request may be retried; either is
+/// streamed through once it passes this size.
+const MAX_STORED_BODY: usize = 1024 * 1024;
+/// How many times to retry claiming a key which changed under us.
+const MAX_SWAP_ATTEMPTS: usize = 4;
+
+/// A component's idempotency key handling.
+pub(crate) struct Idempotency {
+    store_manager: Arc<dyn StoreManager>,
+    store: String,
+    ttl: Duration,
+}
+
+impl Idempotency {
+    pub(crate) fn new(
+        config: &IdempotencyConfig,
+        store_manager: Arc<dyn StoreManager>,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            store_manager.is_defined(&config.store),
+            "idempotency store {:?} is not a defined key-value store",
+            config.store
+        );
+        let ttl = config.ttl_secs.map_or(DEFAULT_TTL, Duration::from_secs);
+        ensure!(
+            !ttl.is_zero(),
+            "idempotency ttl_secs must be greater than 0"
+        );
+        Ok(Self {
+            store_manager,
+            store: config.store.clone(),
+            ttl,
+        })
+    }
+}
+
+/// Returns the idempotency key of `req`, if it has one and is of a method
+/// which it applies to.
+///
+/// Safe methods are idempotent anyway, so they are handled normally.
+pub(crate) fn request_key(req: &Request<Body>) -> Result<Option<String>, InvalidKey> {
+    if matches!(
+        *req.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    ) {
+        return Ok(None);
+    }
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => Ok(Some(key.to_owned())),
+        _ => Err(InvalidKey),
+    }
+}
+
+/// An `Idempotency-Key` header which is empty, too long or not ASCII.
+#[derive(Debug)]
+pub(crate) struct InvalidKey;
+
+/// Handles `req` with `handler` unless a response to it has already been
+/// stored under `key`, in which case that response is replayed.
+///
+/// `scope` distinguishes the keys of different components and tenants.
+pub(crate) async fn handle<F, Fut>(
+    idempotency: &Idempotency,
+    scope: &str,
+    key: &str,
+    req: Request<Body>,
+    handler: F,
+) -> anyhow::Result<Response<Body>>
+where
+    F: FnOnce(Request<Body>) -> Fut,
+    Fut: Future<Output = anyhow::Result<Response<Body>>>,
+{
+    let (mut parts, req_body) = req.into_parts();
+    let req_body = match buffer(req_body, MAX_STORED_BODY).await? {
+        Buffered::Complete(req_body) => req_body,
+        Buffered::Partial(req_body) => {
+            tracing::info!(
+                "Ignoring idempotency key {key:?}: request body is over {MAX_STORED_BODY} bytes"
+            );
+            return handler(Request::from_parts(parts, req_body)).await;
+        }
+    };
+    let fingerprint = fingerprint(&parts.method, &parts.uri, &req_body);
+    // Responses are stored uncompressed, so they can be replayed to any client
+    parts.headers.remove(http::header::ACCEPT_ENCODING);
+    let req = Request::from_parts(parts, body::full(req_body));
+
+    let store = idempotency
+        .store_manager
+        .get(&idempotency.store)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to open idempotency store: {e:?}"))?;
+    let record_key = format!("{RECORD_KEY_PREFIX}{scope}:{key}");
+
+    match claim(store.as_ref(), &record_key, &fingerprint).await? {
+        Claim::Claimed => {}
+        Claim::Replay(stored) => {
+            tracing::info!("Replaying stored response for idempotency key {key:?}");
+            return stored.into_response();
+        }
+        Claim::InProgress => {
+            return status_response(
+                StatusCode::CONFLICT,
+                "a request with this idempotency key is still being handled",
+            );
+        }
+        Claim::Mismatch => {
+            return status_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "this idempotency key was used for a different request",
+            );
+        }
+    }
+
+    let res = match handler(req).await {
+        Ok(res) => res,
+        Err(err) => {
+            release(store.as_ref(), &record_key).await;
+            return Err(err);
+        }
+    };
+    let (parts, res_body) = res.into_parts();
+    let res_body = match buffer(res_body, MAX_STORED_BODY).await {
+        Ok(Buffered::Complete(res_body)) => res_body,
+        // Oversized responses aren't stored, so that the request may be
+        // retried.
+        Ok(Buffered::Partial(res_body)) => {
+            release(store.as_ref(), &record_key).await;
+            return Ok(Response::from_parts(parts, res_body));
+        }
+        Err(err) => {
+            release(store.as_ref(), &record_key).await;
+            return Err(anyhow::anyhow!("failed to read response body: {err:?}"));
+        }
+    };
+
+    // Server errors aren't stored, so that the request may be retried.
+    if parts.status.is_server_error() {
+        release(store.as_ref(), &record_key).await;
+    } else {
+        let stored = StoredResponse {
+            fingerprint,
+            expires_at: expiry(idempotency.ttl),
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                .collect(),
+            body: res_body.to_vec(),
+        };
+        let record = Record::Completed(stored).encode()?;
+        if let Err(e) = store.set(&record_key, &record).await {
+            tracing::error!("Failed to store response for idempotency key {key:?}: {e:?}");
+        }
+    }
+    Ok(Response::from_parts(parts, body::full(res_body)))
+}
+
+/// A body read up to a size limit.
+enum Buffered {
+    /// The whole of a body within the limit.
+    Complete(Bytes),
+    /// A body over the limit, with the data read so far streamed ahead of
+    /// the rest of it.
+    Partial(Body),
+}
+
+/// Reads `body` to its end, unless it passes `limit` bytes.
+async fn buffer(mut body: Body, limit: usize) -> Result<Buffered, ErrorCode> {
+    let mut data = Vec::new();
+    while let Some(frame) = body.frame().await {
+        // Trailers are dropped, as a buffered body doesn't carry them
+        let Ok(chunk) = frame?.into_data() else {
+            continue;
+        };
+        data.extend_from_slice(&chunk);
+        if data.len() > limit {
+            return Ok(Buffered::Partial(BoxBody::new(PrefixedBody {
+                prefix: Some(data.into()),
+                rest: body,
+            })));
+        }
+    }
+    Ok(Buffered::Complete(data.into()))
+}
+
+/// A body which streams data already read from it ahead of the rest of it.
+struct PrefixedBody {
+    prefix: Option<Bytes>,
+    rest: Body,
+}
+
+impl HttpBody for PrefixedBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, ErrorCode>>> {
+        let this = self.get_mut();
+        if let Some(prefix) = this.prefix.take() {
+            return Poll::Ready(Some(Ok(Frame::data(prefix))));
+        }
+        Pin::new(&mut this.rest).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.prefix.is_none() && self.rest.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let prefix_len = self.prefix.as_ref().map_or(0, |prefix| prefix.len() as u64);
+        let rest = self.rest.size_hint();
+        let mut hint = SizeHint::new();
+        hint.set_lower(rest.lower() + prefix_len);
+        if let Some(upper) = rest.upper() {
+            hint.set_upper(upper + prefix_len);
+        }
+        hint
+    }
+}
+
+/// The outcome of claiming an idempotency key for a request.
+enum Claim {
+    /// The request should be handled, and its response stored.
+    Claimed,
+    /// The request was already handled, with the given response.
+    Replay(StoredResponse),
+    /// The same request is already being handled.
+    InProgress,
+    /// The key was used for a different request.
+    Mismatch,
+}
+
+async fn claim(store: &dyn Store, record_key: &str, fingerprint: &str) -> anyhow::Result<Claim> {
+    for _ in 0..MAX_SWAP_ATTEMPTS {
+        let cas = store
+            .new_compare_and_swap(0, record_key)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to read idempotency record: {e:?}"))?;
+        let current = cas
+            .current()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to read idempotency record: {e:?}"))?;
+        let now = now_secs();
+        // A malformed record can't be replayed, so treat it as expired.
+        match current.as_deref().and_then(Record::decode) {
+            Some(record) if record.expires_at() > now => {
+                if record.fingerprint() != fingerprint {
+                    return Ok(Claim::Mismatch);
+                }
+                return Ok(match record {
+                    Record::InProgress { .. } => Claim::InProgress,
+                    Record::Completed(stored) => Claim::Replay(stored),
+                });
+            }
+            _ => {}
+        }
+        let claim = Record::InProgress {
+            fingerprint: fingerprint.to_owned(),
+            expires_at: expiry(IN_PROGRESS_TTL),
+        };
+        match cas.swap(claim.encode()?).await {
+            Ok(()) => return Ok(Claim::Claimed),
+            // Another request claimed the key first; check it again.
+            Err(SwapError::CasFailed(_)) => {}
+            Err(SwapError::Other(e)) => anyhow::bail!("failed to claim idempotency key: {e}"),
+        }
+    }
+    anyhow::bail!("too many concurrent requests with the same idempotency key")
+}
+
+/// Gives up a request's claim on its key, so that it may be retried.
+///
+/// This is best effort: an unreleased claim expires on its own.
+async fn release(store: &dyn Store, record_key: &str) {
+    if let Err(e) = store.delete(record_key).await {
+        tracing::warn!("Failed to release idempotency record {record_key:?}: {e:?}");
+    }
+}
+
+/// Identifies a request, so that a key reused for a different request can be
+/// detected.
+fn fingerprint(method: &Method, uri: &http::Uri, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update([0]);
+    hasher.update(uri.path_and_query().map_or("", |p| p.as_str()));
+    hasher.update([0]);
+    hasher.update(body);
+    format!("{:x}", hasher.finalize())
+}
+
+/// The state of an idempotency key in the backing store.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum Record {
+    /// The first request with the key is being handled.
+    InProgress {
+        fingerprint: String,
+        expires_at: u64,
+    },
+    /// The first request with the key was handled.
+    Completed(StoredResponse),
+}
+
+impl Record {
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        serde_json::to_vec(self).context("failed to encode idempotency record")
+    }
+
+    /// Decodes a record, returning `None` if it is malformed.
+    fn decode(record: &[u8]) -> Option<Self> {
+        serde_json::from_slice(record).ok()
+    }
+
+    fn fingerprint(&self) -> &str {
+        match self {
+            Self::InProgress { fingerprint, .. } => fingerprint,
+            Self::Completed(stored) => &stored.fingerprint,
+        }
+    }
+
+    fn expires_at(&self) -> u64 {
+        match self {
+            Self::InProgress { expires_at, .. } => *expires_at,
+            Self::Completed(stored) => stored.expires_at,
+        }
+    }
+}
+
+/// A response stored for replay.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct StoredResponse {
+    fingerprint: String,
+    /// Seconds since the Unix epoch after which the response isn't replayed.
+    expires_at: u64,
+    status: u16,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl StoredResponse {
+    fn into_response(self) -> anyhow::Result<Response<Body>> {
+        let mut res = Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            res = res.header(
+                HeaderName::try_from(name)?,
+                HeaderValue::from_bytes(&value)?,
+            );
+        }
+        Ok(res
+            .header(REPLAYED_HEADER, "true")
+            .body(body::full(self.body.into()))?)
+    }
+}
+
+fn status_response(status: StatusCode, message: &'static str) -> anyhow::Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(status)
+        .body(body::full(message.into()))?)
+}
+
+fn expiry(ttl: Duration) -> u64 {
+    now_secs().saturating_add(ttl.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn request(method: Method, key: Option<&str>) -> Request<Body> {
+        let mut req = Request::builder().method(method).uri("/payments");
+        if let Some(key) = key {
+            req = req.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        req.body(body::empty()).unwrap()
+    }
+
+    #[test]
+    fn keys_apply_to_unsafe_methods() {
+        assert_eq!(
+            request_key(&request(Method::POST, Some("abc"))).unwrap(),
+            Some("abc".into())
+        );
+        assert_eq!(request_key(&request(Method::POST, None)).unwrap(), None);
+        assert_eq!(
+            request_key(&request(Method::GET, Some("abc"))).unwrap(),
+            None
+        );
+        request_key(&request(Method::PUT, Some(""))).unwrap_err();
+        request_key(&request(Method::PUT, Some(&"k".repeat(MAX_KEY_LEN + 1)))).unwrap_err();
+    }
+
+    #[test]
+    fn fingerprints_distinguish_requests() {
+        let uri = "/payments?x=1".parse().unwrap();
+        let other_uri = "/payments?x=2".parse().unwrap();
+        let fp = fingerprint(&Method::POST, &uri, b"{}");
+        assert_eq!(fp, fingerprint(&Method::POST, &uri, b"{}"));
+        assert_ne!(fp, fingerprint(&Method::PUT, &uri, b"{}"));
+        assert_ne!(fp, fingerprint(&Method::POST, &other_uri, b"{}"));
+        assert_ne!(fp, fingerprint(&Method::POST, &uri, b"{ }"));
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let record = Record::Completed(StoredResponse {
+            fingerprint: "fp".into(),
+            expires_at: 42,
+            status: 201,
+            headers: vec![("location".into(), b"/payments/1".to_vec())],
+            body: b"created".to_vec(),
+        });
+        assert_eq!(Record::decode(&record.encode().unwrap()), Some(record));
+        assert_eq!(Record::decode(b"garbage"), None);
+    }
+
+    /// A body of `chunks` which never ends.
+    fn endless(chunks: &[&'static str]) -> Body {
+        let frames = chunks
+            .iter()
+            .map(|chunk| Ok::<_, ErrorCode>(Frame::data(Bytes::from_static(chunk.as_bytes()))))
+            .collect::<Vec<_>>();
+        let frames = futures::stream::iter(frames).chain(futures::stream::pending());
+        BoxBody::new(http_body_util::StreamBody::new(frames))
+    }
+
+    #[tokio::test]
+    async fn bodies_within_the_limit_are_buffered() {
+        let body = BoxBody::new(http_body_util::StreamBody::new(futures::stream::iter([
+            Ok::<_, ErrorCode>(Frame::data(Bytes::from_static(b"ab"))),
+            Ok(Frame::data(Bytes::from_static(b"cd"))),
+        ])));
+        let Buffered::Complete(data) = buffer(body, 4).await.unwrap() else {
+            panic!("body should be buffered");
+        };
+        assert_eq!(data, "abcd");
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_streamed_through() {
+        // Reading stops once the limit is passed, so the body needn't end
+        let Buffered::Partial(mut body) =
+            buffer(endless(&["ab", "cd", "ef", "gh"]), 4).await.unwrap()
+        else {
+            panic!("body should be streamed");
+        };
+        let mut data = Vec::new();
+        while data.len() < 8 {
+            let frame = body.frame().await.unwrap().unwrap();
+            data.extend_from_slice(&frame.into_data().unwrap());
+        }
+        assert_eq!(data, b"abcdefgh");
+    }
+
+    #[tokio::test]
+    async fn stored_responses_are_marked_as_replayed() {
+        let stored = StoredResponse {
+            fingerprint: "fp".into(),
+            expires_at: 42,
+            status: 201,
+            headers: vec![("location".into(), b"/payments/1".to_vec())],
+            body: b"created".to_vec(),
+        };
+        let res = stored.into_response().unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()["location"], "/payments/1");
+        assert_eq!(res.headers()[REPLAYED_HEADER], "true");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "created");
+    }
+}
@@ -0,0 +1,102 @@
+//! Reuse of component instances across sequential requests.
+//!
+//! Instantiating a component can take longer than handling a request, in
+//! particular for components built with interpreter-based SDKs, which start
+//! their interpreter when instantiated. Components which opt in to reuse keep
+//! their instances in a pool once a request has been handled, so a later
+//! request can be handled by an idle instance rather than a new one. An
+//! instance still handles one request at a time.
+
+use std::sync::Mutex;
+
+use anyhow::Context;
+use spin_core::Instance;
+use spin_factors::RuntimeFactors;
+use spin_http::config::InstanceReuseConfig;
+
+use crate::{Store, TriggerInstanceBuilder};
+
+/// How many requests an instance handles before it is recycled, by default.
+const DEFAULT_MAX_REQUESTS: u32 = 1000;
+
+/// How many idle instances are kept, by default.
+const DEFAULT_MAX_IDLE: usize = 16;
+
+/// A component's idle instances.
+pub(crate) struct InstancePool<F: RuntimeFactors> {
+    component_id: String,
+    max_requests: u32,
+    max_idle: usize,
+    idle: Mutex<Vec<PooledInstance<F>>>,
+}
+
+/// An instance which handles requests for an [`InstancePool`].
+pub(crate) struct PooledInstance<F: RuntimeFactors> {
+    pub instance: Instance,
+    pub store: Store<F>,
+    /// The number of requests the instance has handled.
+    pub requests: u32,
+}
+
+/// The instance a request to a pooled component is handled by.
+pub(crate) enum Checkout<'a, F: RuntimeFactors> {
+    /// A new instance, which joins the pool after handling the request.
+    New(TriggerInstanceBuilder<'a, F>),
+    /// An idle instance from the pool.
+    Idle(PooledInstance<F>),
+}
+
+impl<F: RuntimeFactors> InstancePool<F> {
+    pub fn new(component_id: &str, config: &InstanceReuseConfig) -> anyhow::Result<Self> {
+        let max_requests = config.max_requests.unwrap_or(DEFAULT_MAX_REQUESTS);
+        anyhow::ensure!(max_requests > 0, "`max_requests` must be greater than 0");
+        Ok(Self {
+            component_id: component_id.to_owned(),
+            max_requests,
+            max_idle: config.max_idle.unwrap_or(DEFAULT_MAX_IDLE),
+            idle: Default::default(),
+        })
+    }
+
+    /// Takes an idle instance, if there is one, renewing its limits for a new
+    /// request.
+    pub fn take(&self) -> anyhow::Result<Option<PooledInstance<F>>> {
+        let Some(mut pooled) = self.idle.lock().unwrap().pop() else {
+            return Ok(None);
+        };
+        pooled
+            .store
+            .renew_limits()
+            .context("failed to renew limits of pooled instance")?;
+        Ok(Some(pooled))
+    }
+
+    /// Returns an instance to the pool once it has handled a request.
+    ///
+    /// Instances which trapped, or have handled as many requests as they may,
+    /// are dropped, as are instances for which there is no room in the pool.
+    pub fn put(&self, mut pooled: PooledInstance<F>, trapped: bool) {
+        pooled.requests += 1;
+        let reason = if trapped {
+            "trap"
+        } else if pooled.requests >= self.max_requests {
+            "max_requests"
+        } else {
+            let mut idle = self.idle.lock().unwrap();
+            if idle.len() < self.max_idle {
+                idle.push(pooled);
+            }
+            return;
+        };
+        tracing::debug!(
+            "Recycling instance of component {} after {} requests ({reason})",
+            self.component_id,
+            pooled.requests
+        );
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.instance_pool.recycled = 1,
+            component_id = self.component_id.as_str(),
+            reason = reason
+        );
+    }
+}
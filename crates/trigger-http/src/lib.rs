@@ -1,13 +1,28 @@
 //! Implementation for the Spin HTTP engine.
 
+mod background;
+mod compression;
+mod core_dump;
+mod disconnect;
+mod graphql;
 mod headers;
+mod idempotency;
+mod instance_pool;
 mod instrument;
+mod mirror;
+mod multi;
+mod multipart;
+mod oidc;
+mod openapi;
 mod outbound_http;
+mod reload;
 mod server;
 mod spin;
 mod tls;
+mod usage;
 mod wagi;
 mod wasi;
+mod webhook;
 
 use std::{
     error::Error,
@@ -18,12 +33,14 @@ use std::{
 
 use anyhow::{bail, Context};
 use clap::Args;
-use serde::Deserialize;
 use spin_app::App;
 use spin_factors::RuntimeFactors;
-use spin_trigger::Trigger;
+use spin_http::config::HttpTriggerMetadata;
+use spin_trigger::{core_dump::CoreDumper, recording::Recorder, AppReloads, Trigger};
 use wasmtime_wasi_http::bindings::http::types::ErrorCode;
 
+pub use multi::{AppMount, MultiAppServer};
+pub use reload::ReloadableServer;
 pub use server::HttpServer;
 
 pub use tls::TlsConfig;
@@ -33,13 +50,17 @@ pub(crate) use wasmtime_wasi_http::body::HyperIncomingBody as Body;
 /// A [`spin_trigger::TriggerApp`] for the HTTP trigger.
 pub(crate) type TriggerApp<F> = spin_trigger::TriggerApp<HttpTrigger, F>;
 
+/// A [`spin_trigger::Store`] for the HTTP trigger.
+pub(crate) type Store<F> = spin_trigger::Store<HttpTrigger, F>;
+
 /// A [`spin_trigger::TriggerInstanceBuilder`] for the HTTP trigger.
 pub(crate) type TriggerInstanceBuilder<'a, F> =
     spin_trigger::TriggerInstanceBuilder<'a, HttpTrigger, F>;
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct CliArgs {
-    /// IP address and port to listen on
+    /// IP address and port to listen on. Listening on `[::]` accepts both
+    /// IPv6 and IPv4 connections.
     #[clap(long = "listen", env = "SPIN_HTTP_LISTEN_ADDR", default_value = "127.0.0.1:3000", value_parser = parse_listen_addr)]
     pub address: SocketAddr,
 
@@ -73,6 +94,10 @@ pub struct HttpTrigger {
     /// If the port is set to 0, the actual address will be determined by the OS.
     listen_addr: SocketAddr,
     tls_config: Option<TlsConfig>,
+    /// Records each request, if recording is enabled.
+    recorder: Option<Recorder>,
+    /// Captures core dumps of traps, if enabled.
+    core_dumper: Option<CoreDumper>,
 }
 
 impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
@@ -93,6 +118,30 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
         Ok(())
     }
 
+    async fn run_reloadable(
+        self,
+        trigger_app: TriggerApp<F>,
+        reloads: AppReloads<Self, F>,
+    ) -> anyhow::Result<()> {
+        let server = Arc::new(ReloadableServer::new(
+            self.listen_addr,
+            self.tls_config,
+            trigger_app,
+            self.recorder,
+            self.core_dumper,
+        )?);
+
+        server.serve(reloads).await
+    }
+
+    fn record_invocations(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    fn capture_core_dumps(&mut self, core_dumper: CoreDumper) {
+        self.core_dumper = Some(core_dumper);
+    }
+
     fn supported_host_requirements() -> Vec<&'static str> {
         vec![spin_app::locked::SERVICE_CHAINING_KEY]
     }
@@ -110,6 +159,8 @@ impl HttpTrigger {
         Ok(Self {
             listen_addr,
             tls_config,
+            recorder: None,
+            core_dumper: None,
         })
     }
 
@@ -121,18 +172,24 @@ impl HttpTrigger {
         let Self {
             listen_addr,
             tls_config,
+            recorder,
+            core_dumper,
         } = self;
-        let server = Arc::new(HttpServer::new(listen_addr, tls_config, trigger_app)?);
+        let server = Arc::new(HttpServer::new(
+            listen_addr,
+            tls_config,
+            trigger_app,
+            recorder,
+            core_dumper,
+        )?);
         Ok(server)
     }
 
     fn validate_app(app: &App) -> anyhow::Result<()> {
-        #[derive(Deserialize)]
-        #[serde(deny_unknown_fields)]
-        struct TriggerMetadata {
-            base: Option<String>,
-        }
-        if let Some(TriggerMetadata { base: Some(base) }) = app.get_trigger_metadata("http")? {
+        let metadata = app
+            .get_trigger_metadata::<HttpTriggerMetadata>("http")?
+            .unwrap_or_default();
+        if let Some(base) = metadata.base {
             if base == "/" {
                 tracing::warn!("This application has the deprecated trigger 'base' set to the default value '/'. This may be an error in the future!");
             } else {
@@ -0,0 +1,247 @@
+//! Mirroring of requests to shadow components.
+
+use std::{
+    hash::{DefaultHasher, Hasher},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use anyhow::ensure;
+use http::{Request, Response, StatusCode};
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body as HttpBody, Bytes, Frame, SizeHint};
+use spin_http::{body, config::MirrorConfig};
+use tokio::sync::oneshot;
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Where, and how often, a component's requests are mirrored.
+pub(crate) struct Mirror {
+    component_id: String,
+    percent: f64,
+}
+
+impl Mirror {
+    pub(crate) fn new(config: &MirrorConfig) -> anyhow::Result<Self> {
+        ensure!(
+            (0.0..=100.0).contains(&config.percent),
+            "mirror percent must be between 0 and 100 (got {})",
+            config.percent
+        );
+        Ok(Self {
+            component_id: config.component.clone(),
+            percent: config.percent,
+        })
+    }
+
+    /// The shadow component.
+    pub(crate) fn component_id(&self) -> &str {
+        &self.component_id
+    }
+
+    /// Decides whether to mirror a request.
+    pub(crate) fn sample(&self) -> bool {
+        rand::random::<f64>() * 100.0 < self.percent
+    }
+}
+
+/// Buffers the body of `req` and returns two copies of it.
+pub(crate) async fn duplicate_request(
+    req: Request<Body>,
+) -> anyhow::Result<(Request<Body>, Request<Body>)> {
+    let (parts, req_body) = req.into_parts();
+    let bytes = req_body.collect().await?.to_bytes();
+    let mut shadow_req = Request::builder()
+        .method(parts.method.clone())
+        .uri(parts.uri.clone())
+        .version(parts.version)
+        .body(body::full(bytes.clone()))?;
+    *shadow_req.headers_mut() = parts.headers.clone();
+    *shadow_req.extensions_mut() = parts.extensions.clone();
+    Ok((Request::from_parts(parts, body::full(bytes)), shadow_req))
+}
+
+/// What is compared between the component's and the shadow's responses.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ResponseDigest {
+    status: StatusCode,
+    body_hash: u64,
+}
+
+/// Wraps the body of `res` so that its digest is sent once it has been
+/// streamed to the client.
+pub(crate) fn observe_response(
+    res: Response<Body>,
+) -> (Response<Body>, oneshot::Receiver<ResponseDigest>) {
+    let (tx, rx) = oneshot::channel();
+    let status = res.status();
+    let res = res.map(|inner| {
+        BoxBody::new(DigestBody {
+            inner,
+            status,
+            hasher: DefaultHasher::new(),
+            tx: Some(tx),
+        })
+    });
+    (res, rx)
+}
+
+/// Compares the shadow's response with the component's, recording the outcome.
+pub(crate) async fn compare(
+    component_id: &str,
+    shadow_component_id: &str,
+    primary: oneshot::Receiver<ResponseDigest>,
+    shadow: anyhow::Result<Response<Body>>,
+) {
+    let shadow = match shadow {
+        Ok(res) => digest(res).await,
+        Err(err) => Err(err),
+    };
+    let outcome = match (primary.await, shadow) {
+        // The client went away before the component's response was streamed
+        (Err(_), _) => return,
+        (Ok(_), Err(err)) => {
+            tracing::info!("Shadow component {shadow_component_id} failed: {err:?}");
+            "shadow_error"
+        }
+        (Ok(primary), Ok(shadow)) if primary == shadow => "match",
+        (Ok(primary), Ok(shadow)) if primary.status != shadow.status => {
+            tracing::info!(
+                "Shadow component {shadow_component_id} responded with {} where {component_id} responded with {}",
+                shadow.status,
+                primary.status
+            );
+            "status_mismatch"
+        }
+        (Ok(_), Ok(_)) => {
+            tracing::info!(
+                "Shadow component {shadow_component_id} responded with a different body to {component_id}"
+            );
+            "body_mismatch"
+        }
+    };
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.mirrored_request_count = 1,
+        component_id = component_id,
+        shadow_component_id = shadow_component_id,
+        outcome = outcome
+    );
+}
+
+async fn digest(res: Response<Body>) -> anyhow::Result<ResponseDigest> {
+    let status = res.status();
+    let mut hasher = DefaultHasher::new();
+    let mut res_body = res.into_body();
+    while let Some(frame) = res_body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            hasher.write(&data);
+        }
+    }
+    Ok(ResponseDigest {
+        status,
+        body_hash: hasher.finish(),
+    })
+}
+
+/// A body which hashes its data as it streams.
+struct DigestBody {
+    inner: Body,
+    status: StatusCode,
+    hasher: DefaultHasher,
+    tx: Option<oneshot::Sender<ResponseDigest>>,
+}
+
+impl HttpBody for DigestBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, ErrorCode>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        match &frame {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    this.hasher.write(data);
+                }
+            }
+            // An errored body can't be compared
+            Some(Err(_)) => this.tx = None,
+            None => {
+                if let Some(tx) = this.tx.take() {
+                    _ = tx.send(ResponseDigest {
+                        status: this.status,
+                        body_hash: this.hasher.finish(),
+                    });
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: StatusCode, text: &'static str) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .body(body::full(Bytes::from_static(text.as_bytes())))
+            .unwrap()
+    }
+
+    async fn streamed_digest(res: Response<Body>) -> ResponseDigest {
+        let (res, rx) = observe_response(res);
+        res.into_body().collect().await.unwrap();
+        rx.await.unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_percentages() {
+        let config = |percent| MirrorConfig {
+            component: "shadow".into(),
+            percent,
+        };
+        assert!(Mirror::new(&config(-1.0)).is_err());
+        assert!(Mirror::new(&config(100.5)).is_err());
+        assert!(!Mirror::new(&config(0.0)).unwrap().sample());
+        assert!(Mirror::new(&config(100.0)).unwrap().sample());
+    }
+
+    #[tokio::test]
+    async fn duplicates_requests() {
+        let req = Request::builder()
+            .method("POST")
+            .uri("http://localhost/orders")
+            .header("x-id", "1")
+            .body(body::full(Bytes::from_static(b"order")))
+            .unwrap();
+        let (primary, shadow) = duplicate_request(req).await.unwrap();
+        assert_eq!(shadow.method(), primary.method());
+        assert_eq!(shadow.uri(), primary.uri());
+        assert_eq!(shadow.headers()["x-id"], "1");
+        let bytes = shadow.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            bytes,
+            primary.into_body().collect().await.unwrap().to_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn streamed_digest_matches_collected_digest() {
+        let streamed = streamed_digest(response(StatusCode::OK, "hello")).await;
+        let collected = digest(response(StatusCode::OK, "hello")).await.unwrap();
+        assert_eq!(streamed, collected);
+
+        let different = digest(response(StatusCode::OK, "goodbye")).await.unwrap();
+        assert_ne!(streamed, different);
+    }
+}
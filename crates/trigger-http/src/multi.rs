@@ -0,0 +1,271 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{bail, Context};
+use http::{uri::Scheme, Request, Response, StatusCode, Uri};
+use hyper::body::Incoming;
+use spin_factors::RuntimeFactors;
+use spin_http::body;
+use tokio::net::TcpListener;
+use wasmtime_wasi_http::body::HyperOutgoingBody;
+
+use crate::{
+    server::{bind_listener, serve_connections, HttpServer},
+    TlsConfig,
+};
+
+/// Where an app is mounted on a [`MultiAppServer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppMount {
+    /// The hostname requests must be addressed to, or `None` to match any host.
+    pub host: Option<String>,
+    /// The path prefix requests must start with. The prefix is stripped from
+    /// the request path before it is routed within the app.
+    pub path_prefix: String,
+}
+
+impl AppMount {
+    /// Creates a new [`AppMount`], normalizing the host and path prefix.
+    pub fn new(host: Option<String>, path_prefix: Option<String>) -> anyhow::Result<Self> {
+        let host = host.map(|h| h.to_ascii_lowercase());
+        let path_prefix = path_prefix.unwrap_or_else(|| "/".into());
+        if !path_prefix.starts_with('/') {
+            bail!("path prefix {path_prefix:?} must start with '/'");
+        }
+        let path_prefix = match path_prefix.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+        Ok(Self { host, path_prefix })
+    }
+
+    /// Returns the request path relative to this mount, if the mount matches.
+    fn match_request(&self, host: Option<&str>, path: &str) -> Option<String> {
+        if let Some(mount_host) = &self.host {
+            if !host.is_some_and(|h| h.eq_ignore_ascii_case(mount_host)) {
+                return None;
+            }
+        }
+        if self.path_prefix == "/" {
+            return Some(path.to_string());
+        }
+        match path.strip_prefix(&self.path_prefix)? {
+            "" => Some("/".to_string()),
+            rest if rest.starts_with('/') => Some(rest.to_string()),
+            _ => None,
+        }
+    }
+
+    /// How specific this mount is; more specific mounts take precedence.
+    fn specificity(&self) -> (bool, usize) {
+        (self.host.is_some(), self.path_prefix.len())
+    }
+}
+
+impl std::fmt::Display for AppMount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let host = self.host.as_deref().unwrap_or("*");
+        write!(f, "{host}{}", self.path_prefix)
+    }
+}
+
+/// An HTTP server which runs multiple Spin apps, routing each request to an
+/// app by hostname and path prefix.
+///
+/// Each app is served by its own [`HttpServer`], so apps have isolated
+/// factors and runtime config.
+pub struct MultiAppServer<F: RuntimeFactors> {
+    /// The address the server is listening on.
+    listen_addr: SocketAddr,
+    /// The TLS configuration for the server.
+    tls_config: Option<TlsConfig>,
+    /// The mounted apps, most specific mount first.
+    apps: Vec<(AppMount, Arc<HttpServer<F>>)>,
+}
+
+impl<F: RuntimeFactors> MultiAppServer<F> {
+    /// Create a new [`MultiAppServer`].
+    pub fn new(
+        listen_addr: SocketAddr,
+        tls_config: Option<TlsConfig>,
+        mut apps: Vec<(AppMount, Arc<HttpServer<F>>)>,
+    ) -> anyhow::Result<Self> {
+        if apps.is_empty() {
+            bail!("at least one app must be mounted");
+        }
+        for (i, (mount, _)) in apps.iter().enumerate() {
+            if apps[..i].iter().any(|(other, _)| other == mount) {
+                bail!("more than one app is mounted at {mount}");
+            }
+        }
+        apps.sort_by_key(|(mount, _)| std::cmp::Reverse(mount.specificity()));
+        Ok(Self {
+            listen_addr,
+            tls_config,
+            apps,
+        })
+    }
+
+    /// Serve incoming requests over the provided [`TcpListener`].
+    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
+        let listener = bind_listener(self.listen_addr)
+            .with_context(|| format!("Unable to listen on {}", self.listen_addr))?;
+        for (_, server) in &self.apps {
+            server.start_background_tasks();
+        }
+        let scheme = if self.tls_config.is_some() {
+            Scheme::HTTPS
+        } else {
+            Scheme::HTTP
+        };
+        self.print_startup_msgs(&scheme, &listener)?;
+
+        let tls_config = self.tls_config.clone();
+        serve_connections(
+            listener,
+            tls_config.as_ref(),
+            move |scheme, addr, request| self.clone().dispatch(scheme, addr, request),
+        )
+        .await
+    }
+
+    /// Routes a request to the app it is addressed to.
+    async fn dispatch(
+        self: Arc<Self>,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+        mut request: Request<Incoming>,
+    ) -> anyhow::Result<Response<HyperOutgoingBody>> {
+        let host = request_host(&request);
+        let path = request.uri().path().to_string();
+        let matched = self.apps.iter().find_map(|(mount, server)| {
+            let app_path = mount.match_request(host.as_deref(), &path)?;
+            Some((server.clone(), app_path))
+        });
+        let Some((server, app_path)) = matched else {
+            tracing::info!("Request to {path} matched no app");
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(body::empty())?);
+        };
+        if app_path != path {
+            *request.uri_mut() = with_path(request.uri(), &app_path)?;
+        }
+        server
+            .instrumented_service_fn(server_scheme, client_addr, request)
+            .await
+    }
+
+    fn print_startup_msgs(&self, scheme: &Scheme, listener: &TcpListener) -> anyhow::Result<()> {
+        let local_addr = listener.local_addr()?;
+        terminal::step!("\nServing", "{scheme}://{local_addr:?}");
+        tracing::info!("Serving {scheme}://{local_addr:?}");
+
+        for (mount, server) in &self.apps {
+            println!("App at {mount}:");
+            let authority = match &mount.host {
+                Some(host) => format!("{host}:{}", local_addr.port()),
+                None => format!("{local_addr:?}"),
+            };
+            let prefix = mount.path_prefix.trim_end_matches('/');
+            server.print_routes(&format!("{scheme}://{authority}{prefix}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the hostname (without port) a request is addressed to.
+pub(crate) fn request_host<B>(request: &Request<B>) -> Option<String> {
+    let host = match request.headers().get(http::header::HOST) {
+        Some(host) => host.to_str().ok()?.to_string(),
+        None => request.uri().host()?.to_string(),
+    };
+    let host = match host.parse::<http::uri::Authority>() {
+        Ok(authority) => authority.host().to_string(),
+        Err(_) => host,
+    };
+    Some(host)
+}
+
+/// Returns `uri` with its path replaced, keeping the query.
+pub(crate) fn with_path(uri: &Uri, path: &str) -> anyhow::Result<Uri> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse()?);
+    Ok(Uri::from_parts(parts)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(host: Option<&str>, prefix: &str) -> AppMount {
+        AppMount::new(host.map(Into::into), Some(prefix.into())).unwrap()
+    }
+
+    #[test]
+    fn mount_normalizes_prefix() {
+        assert_eq!(mount(None, "/blog/").path_prefix, "/blog");
+        assert_eq!(mount(None, "/").path_prefix, "/");
+        assert_eq!(AppMount::new(None, None).unwrap().path_prefix, "/");
+        assert!(AppMount::new(None, Some("blog".into())).is_err());
+    }
+
+    #[test]
+    fn mount_matches_path_prefix() {
+        let blog = mount(None, "/blog");
+        assert_eq!(blog.match_request(None, "/blog").as_deref(), Some("/"));
+        assert_eq!(
+            blog.match_request(None, "/blog/posts/1").as_deref(),
+            Some("/posts/1")
+        );
+        assert_eq!(blog.match_request(None, "/blogger"), None);
+        assert_eq!(blog.match_request(None, "/"), None);
+
+        let root = mount(None, "/");
+        assert_eq!(root.match_request(None, "/a/b").as_deref(), Some("/a/b"));
+    }
+
+    #[test]
+    fn mount_matches_host() {
+        let shop = mount(Some("Shop.Example.com"), "/");
+        assert_eq!(
+            shop.match_request(Some("shop.example.com"), "/cart")
+                .as_deref(),
+            Some("/cart")
+        );
+        assert_eq!(shop.match_request(Some("blog.example.com"), "/cart"), None);
+        assert_eq!(shop.match_request(None, "/cart"), None);
+    }
+
+    #[test]
+    fn more_specific_mounts_sort_first() {
+        let mut mounts = [
+            mount(None, "/"),
+            mount(None, "/api"),
+            mount(Some("example.com"), "/"),
+        ];
+        mounts.sort_by_key(|m| std::cmp::Reverse(m.specificity()));
+        assert_eq!(mounts[0].host.as_deref(), Some("example.com"));
+        assert_eq!(mounts[1].path_prefix, "/api");
+        assert_eq!(mounts[2].path_prefix, "/");
+    }
+
+    #[test]
+    fn request_host_strips_port() {
+        let request = Request::builder()
+            .uri("/")
+            .header("host", "example.com:3000")
+            .body(())
+            .unwrap();
+        assert_eq!(request_host(&request).as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn with_path_keeps_query() {
+        let uri: Uri = "/blog/posts?page=2".parse().unwrap();
+        assert_eq!(with_path(&uri, "/posts").unwrap(), "/posts?page=2");
+    }
+}
@@ -0,0 +1,360 @@
+//! Streaming parsing of `multipart/form-data` request bodies.
+//!
+//! A component's trigger can ask for its multipart requests to be parsed
+//! before the component runs. The body is streamed, with each part other than
+//! a small text field written to a file in a temporary directory, which is
+//! mounted read-only into the component's instance and removed when the
+//! instance is dropped. The component receives a JSON description of the
+//! parts in place of the body:
+//!
+//! ```json
+//! {"parts": [
+//!   {"name": "title", "size": 5, "value": "Hello"},
+//!   {"name": "video", "filename": "talk.mp4", "content_type": "video/mp4", "size": 734003200, "path": "/uploads/part-1"}
+//! ]}
+//! ```
+
+use anyhow::Context;
+use futures::TryStreamExt;
+use http::{header, HeaderValue, Request, StatusCode};
+use http_body_util::BodyStream;
+use hyper::body::Bytes;
+use serde::Serialize;
+use spin_factor_wasi::WasiFactor;
+use spin_factors::RuntimeFactors;
+use spin_http::{body, config::MultipartConfig};
+use tempfile::TempDir;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{Body, TriggerInstanceBuilder};
+
+/// The guest path uploaded files are mounted at by default.
+const DEFAULT_MOUNT: &str = "/uploads";
+
+/// The largest text field passed inline by default.
+const DEFAULT_INLINE_MAX_SIZE: u64 = 64 * 1024;
+
+/// The largest body by default.
+const DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Parses a component's multipart requests.
+pub(crate) struct Multipart {
+    mount: String,
+    inline_max_size: u64,
+    max_part_size: u64,
+    max_size: u64,
+}
+
+/// Files uploaded with a request, to be mounted into the instance which
+/// handles it.
+pub(crate) struct Uploads {
+    dir: TempDir,
+    mount: String,
+}
+
+#[derive(Serialize)]
+struct Parts {
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize)]
+struct Part {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    size: u64,
+    /// The part's content, for text fields passed inline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    /// The guest path of the file holding the part's content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+impl Multipart {
+    pub fn new(config: &MultipartConfig) -> anyhow::Result<Self> {
+        let mount = config.mount.as_deref().unwrap_or(DEFAULT_MOUNT);
+        anyhow::ensure!(
+            mount.starts_with('/'),
+            "`mount` must be an absolute path, not {mount:?}"
+        );
+        let max_size = config.max_size.unwrap_or(DEFAULT_MAX_SIZE);
+        Ok(Self {
+            mount: mount.trim_end_matches('/').to_owned(),
+            inline_max_size: config.inline_max_size.unwrap_or(DEFAULT_INLINE_MAX_SIZE),
+            max_part_size: config.max_part_size.unwrap_or(max_size).min(max_size),
+            max_size,
+        })
+    }
+
+    /// Parses the body of a multipart request, returning the request with
+    /// the body replaced by a description of its parts, along with any files
+    /// the parts were written to.
+    ///
+    /// Requests which aren't multipart are returned unchanged. Requests which
+    /// are malformed or too large are rejected with the returned status.
+    pub async fn parse(
+        &self,
+        req: Request<Body>,
+    ) -> anyhow::Result<Result<(Request<Body>, Option<Uploads>), StatusCode>> {
+        let Some(boundary) = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|content_type| is_form_data(content_type))
+            .map(multer::parse_boundary)
+        else {
+            return Ok(Ok((req, None)));
+        };
+        let Ok(boundary) = boundary else {
+            return Ok(Err(StatusCode::BAD_REQUEST));
+        };
+
+        let (mut parts, req_body) = req.into_parts();
+        let stream = BodyStream::new(req_body)
+            .try_filter_map(|frame| futures::future::ok(frame.into_data().ok()));
+        let constraints = multer::Constraints::new().size_limit(
+            multer::SizeLimit::new()
+                .whole_stream(self.max_size)
+                .per_field(self.max_part_size),
+        );
+        let mut multipart = multer::Multipart::with_constraints(stream, boundary, constraints);
+
+        let mut dir = None;
+        let mut described = Vec::new();
+        loop {
+            let mut field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(err) => return rejection(err),
+            };
+            let file_name = format!("part-{}", described.len());
+            let mut part = Part {
+                name: field.name().map(str::to_owned),
+                filename: field.file_name().map(str::to_owned),
+                content_type: field.content_type().map(|mime| mime.to_string()),
+                size: 0,
+                value: None,
+                path: None,
+            };
+            let is_file = part.filename.is_some();
+            let mut buffer = Vec::new();
+            let mut file = None;
+            loop {
+                let chunk = match field.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(err) => return rejection(err),
+                };
+                part.size += chunk.len() as u64;
+                match &mut file {
+                    Some(file) => write(file, &chunk).await?,
+                    None if is_file || part.size > self.inline_max_size => {
+                        let mut spilled = create_file(&mut dir, &file_name).await?;
+                        write(&mut spilled, &buffer).await?;
+                        write(&mut spilled, &chunk).await?;
+                        buffer = Vec::new();
+                        file = Some(spilled);
+                    }
+                    None => buffer.extend_from_slice(&chunk),
+                }
+            }
+            if file.is_none() {
+                match String::from_utf8(buffer) {
+                    Ok(value) if !is_file => part.value = Some(value),
+                    // Binary fields and empty files are written out like others
+                    value => {
+                        let content = value.map_or_else(|e| e.into_bytes(), String::into_bytes);
+                        let mut spilled = create_file(&mut dir, &file_name).await?;
+                        write(&mut spilled, &content).await?;
+                        file = Some(spilled);
+                    }
+                }
+            }
+            if let Some(mut file) = file {
+                file.flush()
+                    .await
+                    .context("failed to write uploaded file")?;
+                part.path = Some(format!("{}/{file_name}", self.mount));
+            }
+            described.push(part);
+        }
+
+        let description = serde_json::to_vec(&Parts { parts: described })?;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        let uploads = dir.map(|dir| Uploads {
+            dir,
+            mount: self.mount.clone(),
+        });
+        Ok(Ok((
+            Request::from_parts(parts, body::full(Bytes::from(description))),
+            uploads,
+        )))
+    }
+}
+
+impl Uploads {
+    /// Mounts the uploaded files into the instance being built. They are
+    /// removed once the instance is dropped.
+    pub fn mount<F: RuntimeFactors>(
+        self,
+        instance_builder: &mut TriggerInstanceBuilder<F>,
+    ) -> anyhow::Result<()> {
+        instance_builder
+            .factor_builder::<WasiFactor>()
+            .context("uploaded files require WASI filesystem support")?
+            .preopened_dir(self.dir.path(), &self.mount, false)?;
+        instance_builder.store_builder().keep_alive(self.dir);
+        Ok(())
+    }
+}
+
+fn is_form_data(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("multipart/form-data"))
+}
+
+/// The status to reject a request with for a parsing error, or the error if
+/// the body couldn't be read.
+fn rejection<T>(err: multer::Error) -> anyhow::Result<Result<T, StatusCode>> {
+    match err {
+        multer::Error::StreamSizeExceeded { .. } | multer::Error::FieldSizeExceeded { .. } => {
+            tracing::info!("Rejecting multipart request: {err}");
+            Ok(Err(StatusCode::PAYLOAD_TOO_LARGE))
+        }
+        multer::Error::StreamReadFailed(_) => {
+            Err(anyhow::Error::new(err).context("failed to read multipart request body"))
+        }
+        _ => {
+            tracing::info!("Rejecting malformed multipart request: {err}");
+            Ok(Err(StatusCode::BAD_REQUEST))
+        }
+    }
+}
+
+/// Creates a file for a part in the request's upload directory, creating
+/// the directory for the first file.
+async fn create_file(dir: &mut Option<TempDir>, file_name: &str) -> anyhow::Result<File> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => dir.insert(
+            tempfile::Builder::new()
+                .prefix("spin-uploads-")
+                .tempdir()
+                .context("failed to create upload directory")?,
+        ),
+    };
+    let path = dir.path().join(file_name);
+    File::create(&path)
+        .await
+        .with_context(|| format!("failed to create uploaded file {}", path.display()))
+}
+
+async fn write(file: &mut File, bytes: &[u8]) -> anyhow::Result<()> {
+    file.write_all(bytes)
+        .await
+        .context("failed to write uploaded file")
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    const BOUNDARY: &str = "X-BOUNDARY";
+
+    fn request(body: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(body::full(Bytes::from(body.replace('\n', "\r\n"))))
+            .unwrap()
+    }
+
+    fn form(fields: &[(&str, Option<&str>, &str)]) -> String {
+        let mut body = String::new();
+        for (name, filename, content) in fields {
+            body.push_str(&format!("--{BOUNDARY}\n"));
+            match filename {
+                Some(filename) => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\nContent-Type: text/plain\n\n"
+                )),
+                None => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{name}\"\n\n"
+                )),
+            }
+            body.push_str(content);
+            body.push('\n');
+        }
+        body.push_str(&format!("--{BOUNDARY}--\n"));
+        body
+    }
+
+    #[tokio::test]
+    async fn large_and_file_parts_are_spilled() {
+        let multipart = Multipart::new(&MultipartConfig {
+            inline_max_size: Some(8),
+            ..Default::default()
+        })
+        .unwrap();
+        let body = form(&[
+            ("title", None, "Hello"),
+            ("notes", None, "longer than eight bytes"),
+            ("upload", Some("hello.txt"), "file contents"),
+        ]);
+        let (req, uploads) = multipart.parse(request(&body)).await.unwrap().unwrap();
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+
+        let description: serde_json::Value =
+            serde_json::from_slice(&req.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        let parts = description["parts"].as_array().unwrap();
+        assert_eq!(parts[0]["value"], "Hello");
+        assert_eq!(parts[1]["path"], "/uploads/part-1");
+        assert_eq!(parts[2]["filename"], "hello.txt");
+        assert_eq!(parts[2]["size"], 13);
+        assert_eq!(parts[2]["path"], "/uploads/part-2");
+
+        let uploads = uploads.unwrap();
+        let read = |name| std::fs::read_to_string(uploads.dir.path().join(name)).unwrap();
+        assert_eq!(read("part-1"), "longer than eight bytes");
+        assert_eq!(read("part-2"), "file contents");
+    }
+
+    #[tokio::test]
+    async fn oversized_requests_are_rejected() {
+        let multipart = Multipart::new(&MultipartConfig {
+            max_part_size: Some(4),
+            ..Default::default()
+        })
+        .unwrap();
+        let body = form(&[("upload", Some("big.txt"), "too big")]);
+        let rejection = multipart.parse(request(&body)).await.unwrap().unwrap_err();
+        assert_eq!(rejection, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn other_requests_are_unchanged() {
+        let multipart = Multipart::new(&MultipartConfig::default()).unwrap();
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body::full(Bytes::from_static(b"{}")))
+            .unwrap();
+        let (req, uploads) = multipart.parse(req).await.unwrap().unwrap();
+        assert!(uploads.is_none());
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+    }
+}
@@ -0,0 +1,731 @@
+//! Signing in through an OpenID Connect provider before requests reach a
+//! component.
+//!
+//! A request without a session is redirected to the provider to sign in,
+//! using the authorization code flow with PKCE. The provider redirects back
+//! to [`CALLBACK_PATH`] under the well-known prefix, where the code is
+//! exchanged for an ID token and a session is stored in a key-value store.
+//! Requests with a session carry the user's subject in [`SUBJECT_HEADER`]
+//! and the ID token's claims, as base64url-encoded JSON, in
+//! [`CLAIMS_HEADER`].
+//!
+//! The browser which starts a sign-in is given a short-lived cookie holding
+//! a hash of the sign-in's `state`, and only that browser can complete it.
+//! Sign-ins and sessions are deleted from the store once used or expired.
+
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Duration};
+
+use anyhow::{ensure, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use http::{
+    header, uri::Scheme, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use spin_common::time::now_secs;
+use spin_factor_key_value::{Store, StoreManager};
+use spin_http::{body, config::OidcConfig};
+use tokio::sync::OnceCell;
+
+use crate::Body;
+
+/// The path, under the well-known prefix, the provider redirects back to.
+pub(crate) const CALLBACK_PATH: &str = "oidc/callback";
+/// Set on signed-in requests to the user's subject identifier.
+pub(crate) const SUBJECT_HEADER: &str = "spin-oidc-subject";
+/// Set on signed-in requests to the ID token's claims, as base64url-encoded
+/// JSON.
+pub(crate) const CLAIMS_HEADER: &str = "spin-oidc-claims";
+
+/// Prefix for the keys of sessions in the backing store.
+const SESSION_KEY_PREFIX: &str = "spin-oidc-session:";
+/// Prefix for the keys of sign-ins in progress in the backing store.
+const LOGIN_KEY_PREFIX: &str = "spin-oidc-login:";
+/// The scopes requested by default.
+const DEFAULT_SCOPES: &[&str] = &["openid", "profile", "email"];
+/// How long a session lasts by default.
+const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(8 * 60 * 60);
+/// How long a user has to sign in with the provider.
+const LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+/// How long to wait for the provider.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often expired sign-ins and sessions are deleted from the store.
+pub(crate) const SWEEP_INTERVAL: Duration = LOGIN_TTL;
+
+/// A component's sign-in through an OIDC provider.
+pub(crate) struct Oidc {
+    component_id: String,
+    issuer: String,
+    client_id: String,
+    /// The client secret, as an expression over the app's variables.
+    client_secret: String,
+    scopes: String,
+    store_manager: Arc<dyn StoreManager>,
+    store: String,
+    session_ttl: Duration,
+    client: reqwest::Client,
+    /// The provider's endpoints, discovered on first use.
+    provider: OnceCell<ProviderMetadata>,
+}
+
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+/// A sign-in which is waiting for the provider to redirect back.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Login {
+    nonce: String,
+    /// The PKCE code verifier.
+    verifier: String,
+    redirect_uri: String,
+    /// Where to send the user once they have signed in.
+    return_to: String,
+    /// Seconds since the Unix epoch after which the sign-in can't complete.
+    expires_at: u64,
+}
+
+/// The expiry time shared by sign-ins and sessions.
+#[derive(Deserialize)]
+struct Expiring {
+    expires_at: u64,
+}
+
+/// A signed-in user's session.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Session {
+    /// The claims of the user's ID token.
+    claims: Json,
+    /// Seconds since the Unix epoch after which the session is invalid.
+    expires_at: u64,
+}
+
+impl Oidc {
+    pub fn new(
+        component_id: &str,
+        config: &OidcConfig,
+        store_manager: Arc<dyn StoreManager>,
+    ) -> anyhow::Result<Self> {
+        let issuer: Uri = config
+            .issuer
+            .parse()
+            .with_context(|| format!("OIDC issuer {:?} is not a URL", config.issuer))?;
+        match (issuer.scheme_str(), issuer.host()) {
+            (Some("https"), Some(_)) => (),
+            (Some("http"), Some(host)) => ensure!(
+                is_loopback(host),
+                "OIDC issuer {:?} must use HTTPS unless it is on a loopback address",
+                config.issuer
+            ),
+            _ => anyhow::bail!("OIDC issuer {:?} is not an HTTPS URL", config.issuer),
+        }
+        ensure!(
+            store_manager.is_defined(&config.store),
+            "OIDC session store {:?} is not a defined key-value store",
+            config.store
+        );
+        let scopes = match &config.scopes {
+            Some(scopes) => {
+                ensure!(
+                    scopes.iter().any(|scope| scope == "openid"),
+                    "OIDC scopes must include \"openid\""
+                );
+                scopes.join(" ")
+            }
+            None => DEFAULT_SCOPES.join(" "),
+        };
+        let session_ttl = config
+            .session_ttl_secs
+            .map_or(DEFAULT_SESSION_TTL, Duration::from_secs);
+        ensure!(
+            !session_ttl.is_zero(),
+            "OIDC session_ttl_secs must be greater than 0"
+        );
+        Ok(Self {
+            component_id: component_id.to_owned(),
+            issuer: config.issuer.trim_end_matches('/').to_owned(),
+            client_id: config.client_id.clone(),
+            client_secret: config.client_secret.clone(),
+            scopes,
+            store_manager,
+            store: config.store.clone(),
+            session_ttl,
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            provider: OnceCell::new(),
+        })
+    }
+
+    /// The client secret, as an expression over the app's variables.
+    pub fn secret_expression(&self) -> &str {
+        &self.client_secret
+    }
+
+    fn cookie_name(&self) -> String {
+        format!("spin-oidc-{}", self.component_id)
+    }
+
+    /// The name of the cookie binding a sign-in to the browser that started
+    /// it.
+    fn state_cookie_name(&self) -> String {
+        format!("spin-oidc-state-{}", self.component_id)
+    }
+
+    /// Checks that a request to the component has a session.
+    ///
+    /// Returns the request, carrying the user's claims, if it does, or else
+    /// a response which sends the user to sign in.
+    pub async fn authenticate(
+        &self,
+        mut req: Request<Body>,
+        scheme: &Scheme,
+    ) -> anyhow::Result<Result<Request<Body>, Response<Body>>> {
+        let store = self.open_store().await?;
+        if let Some(id) = request_cookie(req.headers(), &self.cookie_name()) {
+            let key = self.session_key(&id);
+            let session: Option<Session> = read(store.as_ref(), &key).await?;
+            match session {
+                Some(session) if session.expires_at > now_secs() => {
+                    let subject = session.claims["sub"].as_str().unwrap_or_default();
+                    let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&session.claims)?);
+                    let headers = req.headers_mut();
+                    headers.insert(SUBJECT_HEADER, HeaderValue::from_str(subject)?);
+                    headers.insert(CLAIMS_HEADER, HeaderValue::from_str(&claims)?);
+                    return Ok(Ok(req));
+                }
+                Some(_) => delete(store.as_ref(), &key).await,
+                None => (),
+            }
+        }
+
+        // Only a user's browser navigating to a page can be sent to sign in
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return Ok(Err(status_response(StatusCode::UNAUTHORIZED)?));
+        }
+        let redirect_uri = callback_uri(&req, scheme)?;
+        if !is_secure_uri(&redirect_uri) {
+            tracing::warn!(
+                "Refusing OIDC sign-in for component {:?}: the app must be served over HTTPS \
+                 unless on a loopback address",
+                self.component_id
+            );
+            return Ok(Err(status_response(StatusCode::FORBIDDEN)?));
+        }
+        let provider = self.provider().await?;
+        let state = format!("{}.{}", self.component_id, random_token());
+        let login = Login {
+            nonce: random_token(),
+            verifier: random_token(),
+            redirect_uri,
+            return_to: return_path(req.uri()),
+            expires_at: expiry(LOGIN_TTL),
+        };
+        let authorization_url = append_query(
+            &provider.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &login.redirect_uri),
+                ("scope", &self.scopes),
+                ("state", &state),
+                ("nonce", &login.nonce),
+                ("code_challenge", &pkce_challenge(&login.verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        );
+        store
+            .set(&login_key(&state), &serde_json::to_vec(&login)?)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to store OIDC sign-in: {e:?}"))?;
+        let state_cookie = cookie(
+            &self.state_cookie_name(),
+            &state_hash(&state),
+            LOGIN_TTL,
+            &login.redirect_uri,
+        );
+        Ok(Err(redirect(&authorization_url, &[state_cookie])?))
+    }
+
+    /// Completes the sign-in with `state` when the provider redirects back
+    /// with `params`, using the resolved client `secret`.
+    ///
+    /// The callback must come from the browser which started the sign-in, as
+    /// shown by the `headers` carrying its state cookie. Returns a response
+    /// which sets the session cookie and sends the user back to where they
+    /// were going.
+    pub async fn complete_login(
+        &self,
+        state: &str,
+        headers: &HeaderMap,
+        params: &HashMap<String, String>,
+        secret: &str,
+    ) -> anyhow::Result<Response<Body>> {
+        if request_cookie(headers, &self.state_cookie_name()) != Some(state_hash(state)) {
+            tracing::info!("Rejecting OIDC callback from a browser which didn't start the sign-in");
+            return status_response(StatusCode::BAD_REQUEST);
+        }
+        let store = self.open_store().await?;
+        let key = login_key(state);
+        let login: Option<Login> = read(store.as_ref(), &key).await?;
+        // A sign-in can only be completed once
+        delete(store.as_ref(), &key).await;
+        let Some(login) = login.filter(|l| l.expires_at > now_secs()) else {
+            tracing::info!("Rejecting OIDC callback for an unknown or expired sign-in");
+            return status_response(StatusCode::BAD_REQUEST);
+        };
+        if let Some(error) = params.get("error") {
+            tracing::info!("OIDC provider rejected sign-in: {error}");
+            return status_response(StatusCode::UNAUTHORIZED);
+        }
+        let Some(code) = params.get("code") else {
+            return status_response(StatusCode::BAD_REQUEST);
+        };
+
+        let provider = self.provider().await?;
+        let id_token = self.exchange_code(provider, code, &login, secret).await?;
+        let claims = match validate_id_token(
+            &id_token,
+            &provider.issuer,
+            &self.client_id,
+            &login.nonce,
+            now_secs(),
+        ) {
+            Ok(claims) => claims,
+            Err(reason) => {
+                tracing::info!("Rejecting OIDC sign-in: {reason}");
+                return status_response(StatusCode::UNAUTHORIZED);
+            }
+        };
+
+        let session_id = random_token();
+        let session = Session {
+            claims,
+            expires_at: expiry(self.session_ttl),
+        };
+        store
+            .set(
+                &self.session_key(&session_id),
+                &serde_json::to_vec(&session)?,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to store OIDC session: {e:?}"))?;
+        let session_cookie = cookie(
+            &self.cookie_name(),
+            &session_id,
+            self.session_ttl,
+            &login.redirect_uri,
+        );
+        let clear_state_cookie = cookie(
+            &self.state_cookie_name(),
+            "",
+            Duration::ZERO,
+            &login.redirect_uri,
+        );
+        redirect(&login.return_to, &[session_cookie, clear_state_cookie])
+    }
+
+    /// Deletes the component's expired sign-ins and sessions from the store.
+    ///
+    /// Sign-ins which are never completed, and sessions which are never used
+    /// again, would otherwise stay in the store forever. This lists the whole
+    /// store, so it runs every [`SWEEP_INTERVAL`] in the background rather
+    /// than while handling requests.
+    pub(crate) async fn sweep(&self) {
+        let store = match self.open_store().await {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::warn!("Failed to sweep OIDC records: {e:#}");
+                return;
+            }
+        };
+        let store = store.as_ref();
+        let now = now_secs();
+        let keys = match store.get_keys().await {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("Failed to list OIDC records: {e:?}");
+                return;
+            }
+        };
+        let login_prefix = login_key(&format!("{}.", self.component_id));
+        let session_prefix = self.session_key("");
+        for key in keys {
+            if !key.starts_with(&login_prefix) && !key.starts_with(&session_prefix) {
+                continue;
+            }
+            // Records which can't be read can't be used either
+            let record: Option<Expiring> = read(store, &key).await.unwrap_or(None);
+            if record.is_none_or(|record| record.expires_at <= now) {
+                delete(store, &key).await;
+            }
+        }
+    }
+
+    /// Exchanges an authorization code for an ID token.
+    ///
+    /// The ID token comes straight from the token endpoint, so, as OIDC Core
+    /// §3.1.3.7 allows, the connection rather than the token's signature
+    /// vouches for it.
+    async fn exchange_code(
+        &self,
+        provider: &ProviderMetadata,
+        code: &str,
+        login: &Login,
+        secret: &str,
+    ) -> anyhow::Result<String> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            id_token: String,
+        }
+
+        let form = form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "authorization_code")
+            .append_pair("code", code)
+            .append_pair("redirect_uri", &login.redirect_uri)
+            .append_pair("code_verifier", &login.verifier)
+            .append_pair("client_id", &self.client_id)
+            .append_pair("client_secret", secret)
+            .finish();
+        let body = self
+            .client
+            .post(&provider.token_endpoint)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .header(reqwest::header::ACCEPT, "application/json")
+            .body(form)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .context("failed to exchange OIDC authorization code")?
+            .bytes()
+            .await?;
+        let tokens: TokenResponse =
+            serde_json::from_slice(&body).context("invalid OIDC token response")?;
+        Ok(tokens.id_token)
+    }
+
+    /// Discovers the provider's endpoints the first time they are needed.
+    async fn provider(&self) -> anyhow::Result<&ProviderMetadata> {
+        self.provider
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer);
+                let body = self
+                    .client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|resp| resp.error_for_status())
+                    .with_context(|| format!("failed to fetch OIDC configuration from {url}"))?
+                    .bytes()
+                    .await?;
+                let metadata: ProviderMetadata = serde_json::from_slice(&body)
+                    .with_context(|| format!("invalid OIDC configuration from {url}"))?;
+                ensure!(
+                    metadata.issuer.trim_end_matches('/') == self.issuer,
+                    "OIDC configuration from {url} is for issuer {:?}",
+                    metadata.issuer
+                );
+                Ok(metadata)
+            })
+            .await
+    }
+
+    async fn open_store(&self) -> anyhow::Result<Arc<dyn Store>> {
+        self.store_manager
+            .get(&self.store)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to open OIDC session store: {e:?}"))
+    }
+
+    fn session_key(&self, id: &str) -> String {
+        format!("{SESSION_KEY_PREFIX}{}:{id}", self.component_id)
+    }
+}
+
+/// Returns the component ID named by the `state` parameter of a callback
+/// from the provider with `query`, along with the callback's parameters.
+pub(crate) fn callback_state(query: &str) -> Option<(String, HashMap<String, String>)> {
+    let params: HashMap<String, String> = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let (component_id, _) = params.get("state")?.split_once('.')?;
+    Some((component_id.to_owned(), params))
+}
+
+fn login_key(state: &str) -> String {
+    format!("{LOGIN_KEY_PREFIX}{state}")
+}
+
+/// The value of the state cookie for a sign-in with `state`.
+fn state_hash(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+/// Checks the claims of an ID token from the token endpoint, returning them
+/// if they are for this client and sign-in.
+fn validate_id_token(
+    id_token: &str,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+    now: u64,
+) -> Result<Json, &'static str> {
+    let claims: Json = id_token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+        .and_then(|payload| serde_json::from_slice(&payload).ok())
+        .ok_or("malformed ID token")?;
+    if claims["iss"].as_str() != Some(issuer) {
+        return Err("ID token is from another issuer");
+    }
+    let for_client = match &claims["aud"] {
+        Json::String(aud) => aud == client_id,
+        Json::Array(auds) => auds.iter().any(|aud| aud == client_id),
+        _ => false,
+    };
+    if !for_client {
+        return Err("ID token is for another client");
+    }
+    if !claims["exp"].as_u64().is_some_and(|exp| exp > now) {
+        return Err("ID token has expired");
+    }
+    if claims["nonce"].as_str() != Some(nonce) {
+        return Err("ID token is for another sign-in");
+    }
+    if claims["sub"].as_str().is_none() {
+        return Err("ID token has no subject");
+    }
+    Ok(claims)
+}
+
+/// The URL the provider should redirect back to after a user signs in from
+/// `req`.
+fn callback_uri(req: &Request<Body>, scheme: &Scheme) -> anyhow::Result<String> {
+    let host = match req.headers().get(header::HOST) {
+        Some(host) => host.to_str()?.to_owned(),
+        None => req
+            .uri()
+            .authority()
+            .context("request has no host")?
+            .to_string(),
+    };
+    Ok(format!(
+        "{scheme}://{host}{}{CALLBACK_PATH}",
+        spin_http::WELL_KNOWN_PREFIX
+    ))
+}
+
+/// Whether `uri` uses HTTPS or is on a loopback address, so that it is safe
+/// to send credentials to.
+fn is_secure_uri(uri: &str) -> bool {
+    let Ok(uri) = uri.parse::<Uri>() else {
+        return false;
+    };
+    match (uri.scheme_str(), uri.host()) {
+        (Some("https"), _) => true,
+        (Some("http"), Some(host)) => is_loopback(host),
+        _ => false,
+    }
+}
+
+/// Whether `host`, a host name or IP literal, is a loopback address.
+fn is_loopback(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => host.eq_ignore_ascii_case("localhost"),
+    }
+}
+
+/// The path and query to send a user back to after signing in.
+fn return_path(uri: &http::Uri) -> String {
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    // A path starting with `//` would be a redirect to another host
+    if path.starts_with("//") {
+        "/".to_owned()
+    } else {
+        path.to_owned()
+    }
+}
+
+fn append_query(url: &str, params: &[(&str, &str)]) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{query}")
+}
+
+/// Finds the cookie `name` in a request's `Cookie` headers.
+fn request_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (n, v) = pair.trim().split_once('=')?;
+            (n == name).then(|| v.to_owned())
+        })
+}
+
+/// The S256 PKCE code challenge for `verifier`.
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+/// Reads a JSON record, treating a malformed record as missing.
+async fn read<T: DeserializeOwned>(store: &dyn Store, key: &str) -> anyhow::Result<Option<T>> {
+    let record = store
+        .get(key)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to read OIDC record {key:?}: {e:?}"))?;
+    Ok(record.and_then(|record| serde_json::from_slice(&record).ok()))
+}
+
+/// Deletes a record, logging rather than failing if it can't be deleted.
+async fn delete(store: &dyn Store, key: &str) {
+    if let Err(e) = store.delete(key).await {
+        tracing::warn!("Failed to delete OIDC record {key:?}: {e:?}");
+    }
+}
+
+/// A `Set-Cookie` value for a cookie lasting `max_age`, which is only sent
+/// over HTTPS if the app is served at `redirect_uri` over HTTPS.
+fn cookie(name: &str, value: &str, max_age: Duration, redirect_uri: &str) -> String {
+    let secure = if redirect_uri.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{name}={value}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{secure}",
+        max_age.as_secs()
+    )
+}
+
+fn redirect(location: &str, cookies: &[String]) -> anyhow::Result<Response<Body>> {
+    let mut res = Response::builder()
+        .status(StatusCode::FOUND)
+        .header(header::LOCATION, location)
+        .header(header::CACHE_CONTROL, "no-store");
+    for cookie in cookies {
+        res = res.header(header::SET_COOKIE, cookie);
+    }
+    Ok(res.body(body::empty())?)
+}
+
+fn status_response(status: StatusCode) -> anyhow::Result<Response<Body>> {
+    Ok(Response::builder().status(status).body(body::empty())?)
+}
+
+fn expiry(ttl: Duration) -> u64 {
+    now_secs().saturating_add(ttl.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id_token(claims: Json) -> String {
+        format!(
+            "e30.{}.c2ln",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap())
+        )
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1zhT8ZIOxm94HKZAY"
+        );
+    }
+
+    #[test]
+    fn id_tokens_are_checked_against_the_sign_in() {
+        let claims = serde_json::json!({
+            "iss": "https://accounts.example.com",
+            "aud": ["other", "admin"],
+            "exp": 2000,
+            "nonce": "n-1",
+            "sub": "user-1",
+        });
+        let check = |claims: &Json, now| {
+            validate_id_token(
+                &id_token(claims.clone()),
+                "https://accounts.example.com",
+                "admin",
+                "n-1",
+                now,
+            )
+        };
+        assert_eq!(check(&claims, 1000).unwrap()["sub"], "user-1");
+        assert!(check(&claims, 2000).is_err());
+        for (claim, value) in [
+            ("iss", "https://evil.example.com"),
+            ("aud", "other"),
+            ("nonce", "n-2"),
+        ] {
+            let mut claims = claims.clone();
+            claims[claim] = value.into();
+            assert!(check(&claims, 1000).is_err(), "{claim}");
+        }
+        assert!(validate_id_token("garbage", "", "", "", 0).is_err());
+    }
+
+    #[test]
+    fn credentials_are_only_sent_over_https_or_loopback() {
+        assert!(is_secure_uri("https://app.example.com/cb"));
+        assert!(is_secure_uri("http://localhost:3000/cb"));
+        assert!(is_secure_uri("http://127.0.0.1:3000/cb"));
+        assert!(is_secure_uri("http://[::1]:3000/cb"));
+        assert!(!is_secure_uri("http://app.example.com/cb"));
+        assert!(!is_secure_uri("http://10.0.0.1/cb"));
+        assert!(!is_secure_uri("garbage"));
+    }
+
+    #[test]
+    fn state_cookies_hash_the_state() {
+        assert_eq!(state_hash("admin.xyz"), state_hash("admin.xyz"));
+        assert_ne!(state_hash("admin.xyz"), state_hash("admin.xyz2"));
+    }
+
+    #[test]
+    fn callbacks_name_their_component() {
+        let (component_id, params) = callback_state("code=abc&state=admin.xyz").unwrap();
+        assert_eq!(component_id, "admin");
+        assert_eq!(params["code"], "abc");
+        assert!(callback_state("code=abc").is_none());
+    }
+
+    #[test]
+    fn session_cookies_are_found_and_return_paths_stay_local() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("a=1; spin-oidc-admin=s3ss10n"),
+        );
+        assert_eq!(
+            request_cookie(&headers, "spin-oidc-admin").as_deref(),
+            Some("s3ss10n")
+        );
+        assert_eq!(request_cookie(&headers, "spin-oidc-other"), None);
+
+        assert_eq!(
+            return_path(&"/admin?tab=2".parse().unwrap()),
+            "/admin?tab=2"
+        );
+        assert_eq!(return_path(&"//evil.example.com/".parse().unwrap()), "/");
+    }
+}
@@ -0,0 +1,804 @@
+//! Validation of requests against a component's OpenAPI document.
+//!
+//! Only the parts of OpenAPI 3 that describe requests are checked: the path,
+//! the method, path/query/header parameters and JSON request bodies. Schemas
+//! are checked for the commonly used JSON Schema keywords; unsupported
+//! keywords (such as `format`) are ignored.
+
+use std::collections::HashMap;
+
+use anyhow::{ensure, Context};
+use http::{header, HeaderMap, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value as Json};
+use spin_http::{body, config::OpenApiConfig};
+
+use crate::Body;
+
+/// The maximum depth of nested schemas, which bounds recursive `$ref`s.
+const MAX_SCHEMA_DEPTH: usize = 64;
+
+/// A component's OpenAPI document.
+pub(crate) struct OpenApiSpec {
+    /// The document as written, served on `route`.
+    source: String,
+    /// The media type of `source`.
+    content_type: &'static str,
+    /// The document, as JSON.
+    document: Json,
+    /// The route the document is served on, if any.
+    route: Option<String>,
+    /// The document's paths, most specific first.
+    paths: Vec<PathTemplate>,
+}
+
+impl OpenApiSpec {
+    /// Parses the document inlined in `config`.
+    pub(crate) fn new(config: &OpenApiConfig) -> anyhow::Result<Self> {
+        let source = config.document.clone().with_context(|| {
+            format!(
+                "OpenAPI document {:?} was not included when the app was loaded",
+                config.spec
+            )
+        })?;
+        let (document, content_type) = match serde_json::from_str::<Json>(&source) {
+            Ok(document) => (document, "application/json"),
+            Err(_) => (
+                serde_yaml::from_str::<Json>(&source)
+                    .with_context(|| format!("invalid OpenAPI document {:?}", config.spec))?,
+                "application/yaml",
+            ),
+        };
+        let version = document["openapi"].as_str().unwrap_or_default();
+        ensure!(
+            version.starts_with("3."),
+            "OpenAPI document {:?} must be OpenAPI 3 (found version {version:?})",
+            config.spec
+        );
+        if let Some(route) = &config.route {
+            ensure!(
+                route.starts_with('/'),
+                "OpenAPI route {route:?} must start with '/'"
+            );
+            ensure!(
+                !route.starts_with(spin_http::WELL_KNOWN_PREFIX),
+                "OpenAPI route {route:?} is reserved by the Spin runtime"
+            );
+        }
+        let mut paths: Vec<_> = document["paths"]
+            .as_object()
+            .map(|paths| paths.keys().map(|p| PathTemplate::parse(p)).collect())
+            .unwrap_or_default();
+        paths.sort_by_key(|p| std::cmp::Reverse(p.literal_segments()));
+        Ok(Self {
+            source,
+            content_type,
+            document,
+            route: config.route.clone(),
+            paths,
+        })
+    }
+
+    /// The route the document is served on, if any.
+    pub(crate) fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// A response serving the document.
+    pub(crate) fn document_response(&self) -> anyhow::Result<Response<Body>> {
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, self.content_type)
+            .body(body::full(Bytes::from(self.source.clone())))?)
+    }
+
+    /// Validates `req`, returning it (with its body buffered if the body was
+    /// checked) if the document allows it.
+    pub(crate) async fn validate(
+        &self,
+        req: Request<Body>,
+    ) -> anyhow::Result<Result<Request<Body>, Rejection>> {
+        let path = req.uri().path();
+        let Some((template, path_params)) = self
+            .paths
+            .iter()
+            .find_map(|template| Some((template, template.matches(path)?)))
+        else {
+            return Ok(Err(Rejection::new(
+                StatusCode::NOT_FOUND,
+                format!("path {path} is not described by the OpenAPI document"),
+            )));
+        };
+        let path_item = self.resolve(&self.document["paths"][&template.template]);
+        let method = req.method().as_str().to_ascii_lowercase();
+        let Some(operation) = path_item.get(&method).map(|o| self.resolve(o)) else {
+            return Ok(Err(Rejection::new(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!(
+                    "method {} is not allowed on {} by the OpenAPI document",
+                    req.method(),
+                    template.template
+                ),
+            )));
+        };
+
+        let query = query_pairs(req.uri().query());
+        let mut errors = vec![];
+        for parameter in self.parameters(path_item, operation) {
+            self.check_parameter(parameter, &path_params, &query, req.headers(), &mut errors);
+        }
+
+        let Some(request_body) = operation.get("requestBody").map(|b| self.resolve(b)) else {
+            return Ok(if errors.is_empty() {
+                Ok(req)
+            } else {
+                Err(Rejection::invalid(errors))
+            });
+        };
+        let (parts, req_body) = req.into_parts();
+        let bytes = req_body.collect().await?.to_bytes();
+        if bytes.is_empty() {
+            if request_body["required"].as_bool().unwrap_or(false) {
+                errors.push("request body is required".into());
+            }
+        } else {
+            let content_type = parts
+                .headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| {
+                    v.split(';')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_ascii_lowercase()
+                })
+                .unwrap_or_default();
+            let Some(media_type) = media_type_for(&request_body["content"], &content_type) else {
+                return Ok(Err(Rejection::new(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!(
+                        "content type {content_type:?} is not accepted by the OpenAPI document"
+                    ),
+                )));
+            };
+            if is_json(&content_type) {
+                if let Some(schema) = media_type.get("schema") {
+                    match serde_json::from_slice::<Json>(&bytes) {
+                        Ok(value) => self.check_value(schema, &value, "body", 0, &mut errors),
+                        Err(err) => errors.push(format!("body is not valid JSON: {err}")),
+                    }
+                }
+            }
+        }
+        Ok(if errors.is_empty() {
+            Ok(Request::from_parts(parts, body::full(bytes)))
+        } else {
+            Err(Rejection::invalid(errors))
+        })
+    }
+
+    /// The parameters of an operation, including those inherited from its path.
+    fn parameters<'a>(&'a self, path_item: &'a Json, operation: &'a Json) -> Vec<&'a Json> {
+        let mut parameters: Vec<&Json> = vec![];
+        for parameter in [path_item, operation]
+            .into_iter()
+            .filter_map(|item| item["parameters"].as_array())
+            .flatten()
+            .map(|p| self.resolve(p))
+        {
+            // Operation parameters override path parameters with the same name and location
+            parameters.retain(|p| p["name"] != parameter["name"] || p["in"] != parameter["in"]);
+            parameters.push(parameter);
+        }
+        parameters
+    }
+
+    fn check_parameter(
+        &self,
+        parameter: &Json,
+        path_params: &HashMap<&str, String>,
+        query: &[(String, String)],
+        headers: &HeaderMap,
+        errors: &mut Vec<String>,
+    ) {
+        let name = parameter["name"].as_str().unwrap_or_default();
+        let location = parameter["in"].as_str().unwrap_or_default();
+        let values: Vec<String> = match location {
+            "path" => path_params.get(name).cloned().into_iter().collect(),
+            "query" => query
+                .iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .collect(),
+            "header" => headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .map(str::to_owned)
+                .collect(),
+            // Cookie parameters are not checked
+            _ => return,
+        };
+        let required = location == "path" || parameter["required"].as_bool().unwrap_or(false);
+        if values.is_empty() {
+            if required {
+                errors.push(format!("missing required {location} parameter `{name}`"));
+            }
+            return;
+        }
+        let Some(schema) = parameter.get("schema") else {
+            return;
+        };
+        let value = self.coerce_parameter(schema, values);
+        self.check_value(
+            schema,
+            &value,
+            &format!("{location} parameter `{name}`"),
+            0,
+            errors,
+        );
+    }
+
+    /// Converts the string values of a parameter to JSON, guided by its schema.
+    ///
+    /// Values which can't be converted are left as strings so that checking
+    /// them reports a type error.
+    fn coerce_parameter(&self, schema: &Json, mut values: Vec<String>) -> Json {
+        let schema = self.resolve(schema);
+        if schema_type(schema) == Some("array") {
+            if values.len() == 1 {
+                values = values[0].split(',').map(str::to_owned).collect();
+            }
+            let items = schema.get("items").unwrap_or(&Json::Null);
+            return values
+                .into_iter()
+                .map(|value| self.coerce_scalar(items, value))
+                .collect();
+        }
+        self.coerce_scalar(schema, values.swap_remove(0))
+    }
+
+    fn coerce_scalar(&self, schema: &Json, value: String) -> Json {
+        let coerced = match schema_type(self.resolve(schema)) {
+            Some("integer") => value.parse::<i64>().ok().map(Json::from),
+            Some("number") => value.parse::<f64>().ok().map(Json::from),
+            Some("boolean") => value.parse::<bool>().ok().map(Json::from),
+            _ => None,
+        };
+        coerced.unwrap_or(Json::String(value))
+    }
+
+    /// Checks `value` against `schema`, adding any violations to `errors`.
+    fn check_value(
+        &self,
+        schema: &Json,
+        value: &Json,
+        location: &str,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        if depth > MAX_SCHEMA_DEPTH {
+            errors.push(format!("{location}: schema is nested too deeply"));
+            return;
+        }
+        let schema = self.resolve(schema);
+        let depth = depth + 1;
+        if value.is_null() && schema["nullable"].as_bool().unwrap_or(false) {
+            return;
+        }
+
+        if let Some(all_of) = schema["allOf"].as_array() {
+            for sub_schema in all_of {
+                self.check_value(sub_schema, value, location, depth, errors);
+            }
+        }
+        for (keyword, matches_needed) in [("anyOf", 1..=usize::MAX), ("oneOf", 1..=1)] {
+            if let Some(sub_schemas) = schema[keyword].as_array() {
+                let matched = sub_schemas
+                    .iter()
+                    .filter(|sub_schema| {
+                        let mut sub_errors = vec![];
+                        self.check_value(sub_schema, value, location, depth, &mut sub_errors);
+                        sub_errors.is_empty()
+                    })
+                    .count();
+                if !matches_needed.contains(&matched) {
+                    errors.push(format!("{location}: does not match `{keyword}`"));
+                }
+            }
+        }
+        if let Some(allowed) = schema["enum"].as_array() {
+            if !allowed.contains(value) {
+                errors.push(format!(
+                    "{location}: must be one of {}",
+                    Json::from(allowed.clone())
+                ));
+            }
+        }
+
+        let types: Vec<&str> = match &schema["type"] {
+            Json::String(t) => vec![t],
+            Json::Array(ts) => ts.iter().filter_map(Json::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{location}: expected {}", types.join(" or ")));
+            return;
+        }
+
+        match value {
+            Json::String(s) => {
+                let len = s.chars().count() as u64;
+                if schema["minLength"].as_u64().is_some_and(|min| len < min) {
+                    errors.push(format!(
+                        "{location}: is shorter than {}",
+                        schema["minLength"]
+                    ));
+                }
+                if schema["maxLength"].as_u64().is_some_and(|max| len > max) {
+                    errors.push(format!(
+                        "{location}: is longer than {}",
+                        schema["maxLength"]
+                    ));
+                }
+                if let Some(pattern) = schema["pattern"].as_str() {
+                    match regex::Regex::new(pattern) {
+                        Ok(re) if re.is_match(s) => {}
+                        Ok(_) => errors.push(format!("{location}: does not match {pattern:?}")),
+                        Err(err) => {
+                            tracing::warn!("Ignoring invalid OpenAPI pattern {pattern:?}: {err}")
+                        }
+                    }
+                }
+            }
+            Json::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                check_bound(schema, "minimum", "exclusiveMinimum", n, location, errors);
+                check_bound(schema, "maximum", "exclusiveMaximum", n, location, errors);
+            }
+            Json::Array(items) => {
+                let len = items.len() as u64;
+                if schema["minItems"].as_u64().is_some_and(|min| len < min) {
+                    errors.push(format!(
+                        "{location}: has fewer than {} items",
+                        schema["minItems"]
+                    ));
+                }
+                if schema["maxItems"].as_u64().is_some_and(|max| len > max) {
+                    errors.push(format!(
+                        "{location}: has more than {} items",
+                        schema["maxItems"]
+                    ));
+                }
+                if schema["uniqueItems"].as_bool().unwrap_or(false)
+                    && items
+                        .iter()
+                        .enumerate()
+                        .any(|(i, item)| items[..i].contains(item))
+                {
+                    errors.push(format!("{location}: items must be unique"));
+                }
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.check_value(
+                            item_schema,
+                            item,
+                            &format!("{location}[{i}]"),
+                            depth,
+                            errors,
+                        );
+                    }
+                }
+            }
+            Json::Object(fields) => {
+                for required in schema["required"].as_array().into_iter().flatten() {
+                    let Some(required) = required.as_str() else {
+                        continue;
+                    };
+                    if !fields.contains_key(required) {
+                        errors.push(format!(
+                            "{location}: missing required property `{required}`"
+                        ));
+                    }
+                }
+                let properties = schema["properties"].as_object();
+                for (name, field) in fields {
+                    let field_location = format!("{location}.{name}");
+                    match properties.and_then(|p| p.get(name)) {
+                        Some(field_schema) => {
+                            self.check_value(field_schema, field, &field_location, depth, errors)
+                        }
+                        None => match &schema["additionalProperties"] {
+                            Json::Bool(false) => {
+                                errors.push(format!("{field_location}: is not an allowed property"))
+                            }
+                            additional @ Json::Object(_) => {
+                                self.check_value(additional, field, &field_location, depth, errors)
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+            }
+            Json::Null | Json::Bool(_) => {}
+        }
+    }
+
+    /// Follows a local `$ref`, returning `value` itself if it isn't one.
+    ///
+    /// References to other documents can't be followed and resolve to an
+    /// empty schema, which allows anything.
+    fn resolve<'a>(&'a self, mut value: &'a Json) -> &'a Json {
+        static EMPTY: Json = Json::Null;
+        for _ in 0..MAX_SCHEMA_DEPTH {
+            let Some(reference) = value.get("$ref").and_then(Json::as_str) else {
+                return value;
+            };
+            let Some(target) = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.document.pointer(pointer))
+            else {
+                tracing::warn!("Ignoring unresolvable OpenAPI reference {reference:?}");
+                return &EMPTY;
+            };
+            value = target;
+        }
+        &EMPTY
+    }
+}
+
+/// A request rejected by OpenAPI validation.
+#[derive(Debug)]
+pub(crate) struct Rejection {
+    status: StatusCode,
+    errors: Vec<String>,
+}
+
+impl Rejection {
+    fn new(status: StatusCode, error: String) -> Self {
+        Self {
+            status,
+            errors: vec![error],
+        }
+    }
+
+    fn invalid(errors: Vec<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            errors,
+        }
+    }
+
+    /// A JSON response describing why the request was rejected.
+    pub(crate) fn into_response(self) -> anyhow::Result<Response<Body>> {
+        let body = json!({ "errors": self.errors });
+        Ok(Response::builder()
+            .status(self.status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body::full(Bytes::from(body.to_string())))?)
+    }
+}
+
+/// An OpenAPI path template, e.g. `/users/{id}`.
+struct PathTemplate {
+    template: String,
+    segments: Vec<TemplateSegment>,
+}
+
+enum TemplateSegment {
+    Literal(String),
+    Parameter(String),
+}
+
+impl PathTemplate {
+    fn parse(template: &str) -> Self {
+        let segments = template
+            .trim_start_matches('/')
+            .split('/')
+            .map(
+                |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => TemplateSegment::Parameter(name.to_owned()),
+                    None => TemplateSegment::Literal(segment.to_owned()),
+                },
+            )
+            .collect();
+        Self {
+            template: template.to_owned(),
+            segments,
+        }
+    }
+
+    fn literal_segments(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s, TemplateSegment::Literal(_)))
+            .count()
+    }
+
+    /// Matches `path`, returning the (percent-decoded) path parameters.
+    fn matches<'a>(&'a self, path: &str) -> Option<HashMap<&'a str, String>> {
+        let path_segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        if path_segments.len() != self.segments.len() {
+            return None;
+        }
+        let mut params = HashMap::new();
+        for (segment, path_segment) in self.segments.iter().zip(path_segments) {
+            match segment {
+                TemplateSegment::Literal(literal) if literal == path_segment => {}
+                TemplateSegment::Literal(_) => return None,
+                TemplateSegment::Parameter(name) => {
+                    if path_segment.is_empty() {
+                        return None;
+                    }
+                    let value = percent_decode_str(path_segment)
+                        .decode_utf8_lossy()
+                        .into_owned();
+                    params.insert(name.as_str(), value);
+                }
+            }
+        }
+        Some(params)
+    }
+}
+
+fn query_pairs(query: Option<&str>) -> Vec<(String, String)> {
+    form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// Finds the media type object in `content` for `content_type`, allowing
+/// wildcards such as `application/*`.
+fn media_type_for<'a>(content: &'a Json, content_type: &str) -> Option<&'a Json> {
+    let content = content.as_object()?;
+    let wildcard = content_type
+        .split_once('/')
+        .map(|(kind, _)| format!("{kind}/*"))
+        .unwrap_or_default();
+    [content_type, wildcard.as_str(), "*/*"]
+        .into_iter()
+        .find_map(|key| content.get(key))
+}
+
+fn is_json(content_type: &str) -> bool {
+    content_type == "application/json" || content_type.ends_with("+json")
+}
+
+fn schema_type(schema: &Json) -> Option<&str> {
+    match &schema["type"] {
+        Json::String(t) => Some(t),
+        Json::Array(ts) => ts.iter().filter_map(Json::as_str).find(|t| *t != "null"),
+        _ => None,
+    }
+}
+
+fn has_type(value: &Json, schema_type: &str) -> bool {
+    match schema_type {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+/// Checks an inclusive bound such as `minimum`, and its exclusive form, which
+/// is a flag in OpenAPI 3.0 and a number in OpenAPI 3.1.
+fn check_bound(
+    schema: &Json,
+    inclusive: &str,
+    exclusive: &str,
+    n: f64,
+    location: &str,
+    errors: &mut Vec<String>,
+) {
+    let is_min = inclusive == "minimum";
+    let violates = |bound: f64, exclusive: bool| match (is_min, exclusive) {
+        (true, false) => n < bound,
+        (true, true) => n <= bound,
+        (false, false) => n > bound,
+        (false, true) => n >= bound,
+    };
+    let relation = if is_min { "at least" } else { "at most" };
+    if let Some(bound) = schema[inclusive].as_f64() {
+        let exclusive = schema[exclusive].as_bool().unwrap_or(false);
+        if violates(bound, exclusive) {
+            let relation = if exclusive {
+                if is_min {
+                    "greater than"
+                } else {
+                    "less than"
+                }
+            } else {
+                relation
+            };
+            errors.push(format!("{location}: must be {relation} {bound}"));
+        }
+    }
+    if let Some(bound) = schema[exclusive].as_f64() {
+        if violates(bound, true) {
+            let relation = if is_min { "greater than" } else { "less than" };
+            errors.push(format!("{location}: must be {relation} {bound}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r##"
+openapi: 3.0.3
+info: { title: Users, version: "1.0" }
+paths:
+  /users:
+    get:
+      parameters:
+        - { name: limit, in: query, schema: { type: integer, minimum: 1, maximum: 100 } }
+        - { name: tags, in: query, schema: { type: array, items: { type: string } } }
+    post:
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/NewUser" }
+  /users/me:
+    get: {}
+  /users/{id}:
+    parameters:
+      - { $ref: "#/components/parameters/UserId" }
+    get:
+      parameters:
+        - { name: x-request-id, in: header, required: true, schema: { type: string } }
+components:
+  parameters:
+    UserId: { name: id, in: path, required: true, schema: { type: integer } }
+  schemas:
+    NewUser:
+      type: object
+      required: [name]
+      additionalProperties: false
+      properties:
+        name: { type: string, minLength: 1 }
+        email: { type: string, pattern: "^[^@]+@[^@]+$", nullable: true }
+        roles: { type: array, items: { enum: [admin, member] }, uniqueItems: true }
+"##;
+
+    fn spec() -> OpenApiSpec {
+        OpenApiSpec::new(&OpenApiConfig {
+            spec: "openapi.yaml".into(),
+            route: Some("/openapi.yaml".into()),
+            document: Some(DOCUMENT.into()),
+        })
+        .unwrap()
+    }
+
+    fn request(method: &str, uri: &str, json_body: Option<&str>) -> Request<Body> {
+        let builder = Request::builder().method(method).uri(uri);
+        match json_body {
+            Some(json_body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body::full(Bytes::from(json_body.to_owned())))
+                .unwrap(),
+            None => builder.body(body::empty()).unwrap(),
+        }
+    }
+
+    async fn errors(req: Request<Body>) -> Vec<String> {
+        match spec().validate(req).await.unwrap() {
+            Ok(_) => vec![],
+            Err(rejection) => rejection.errors,
+        }
+    }
+
+    async fn status(req: Request<Body>) -> Option<StatusCode> {
+        spec()
+            .validate(req)
+            .await
+            .unwrap()
+            .err()
+            .map(|rejection| rejection.status)
+    }
+
+    #[test]
+    fn requires_an_inlined_openapi_3_document() {
+        let config = |document: Option<&str>| OpenApiConfig {
+            spec: "openapi.json".into(),
+            route: None,
+            document: document.map(str::to_owned),
+        };
+        assert!(OpenApiSpec::new(&config(None)).is_err());
+        assert!(OpenApiSpec::new(&config(Some(r#"{"swagger": "2.0"}"#))).is_err());
+        let spec = OpenApiSpec::new(&config(Some(r#"{"openapi": "3.1.0"}"#))).unwrap();
+        assert_eq!(spec.content_type, "application/json");
+    }
+
+    #[tokio::test]
+    async fn rejects_undescribed_paths_and_methods() {
+        assert_eq!(
+            status(request("GET", "/orders", None)).await,
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            status(request("DELETE", "/users", None)).await,
+            Some(StatusCode::METHOD_NOT_ALLOWED)
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_literal_path_segments() {
+        // `/users/me` would fail the integer `id` check if matched by `/users/{id}`
+        assert!(errors(request("GET", "/users/me", None)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn checks_parameters() {
+        assert!(errors(request("GET", "/users?limit=10&tags=a,b", None))
+            .await
+            .is_empty());
+        let errs = errors(request("GET", "/users?limit=0", None)).await;
+        assert_eq!(errs, ["query parameter `limit`: must be at least 1"]);
+        let errs = errors(request("GET", "/users?limit=ten", None)).await;
+        assert_eq!(errs, ["query parameter `limit`: expected integer"]);
+
+        let errs = errors(request("GET", "/users/abc", None)).await;
+        assert_eq!(
+            errs,
+            [
+                "path parameter `id`: expected integer",
+                "missing required header parameter `x-request-id`",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn checks_json_bodies() {
+        let valid = r#"{"name": "Ada", "email": null, "roles": ["admin"]}"#;
+        assert!(errors(request("POST", "/users", Some(valid)))
+            .await
+            .is_empty());
+
+        let errs = errors(request("POST", "/users", None)).await;
+        assert_eq!(errs, ["request body is required"]);
+
+        let invalid = r#"{"email": "ada", "roles": ["admin", "admin", "owner"], "age": 36}"#;
+        let mut errs = errors(request("POST", "/users", Some(invalid))).await;
+        errs.sort();
+        assert_eq!(
+            errs,
+            [
+                "body.age: is not an allowed property",
+                "body.email: does not match \"^[^@]+@[^@]+$\"",
+                "body.roles: items must be unique",
+                "body.roles[2]: must be one of [\"admin\",\"member\"]",
+                "body: missing required property `name`",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unaccepted_content_types() {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/users")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(body::full(Bytes::from_static(b"Ada")))
+            .unwrap();
+        assert_eq!(status(req).await, Some(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    }
+
+    #[tokio::test]
+    async fn preserves_validated_bodies() {
+        let req = request("POST", "/users", Some(r#"{"name": "Ada"}"#));
+        let req = spec().validate(req).await.unwrap().unwrap();
+        let bytes = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, r#"{"name": "Ada"}"#);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::Arc,
+    sync::Weak,
 };
 
 use http::uri::Scheme;
@@ -14,12 +14,15 @@ use wasmtime_wasi_http::{HttpError, HttpResult};
 use crate::HttpServer;
 
 /// An outbound HTTP interceptor that handles service chaining requests.
+///
+/// The interceptor doesn't keep the server alive, as the server may keep the
+/// instance the interceptor belongs to in an instance pool.
 pub struct OutboundHttpInterceptor<F: RuntimeFactors> {
-    server: Arc<HttpServer<F>>,
+    server: Weak<HttpServer<F>>,
 }
 
 impl<F: RuntimeFactors> OutboundHttpInterceptor<F> {
-    pub fn new(server: Arc<HttpServer<F>>) -> Self {
+    pub fn new(server: Weak<HttpServer<F>>) -> Self {
         Self { server }
     }
 }
@@ -34,8 +37,11 @@ impl<F: RuntimeFactors> intercept::OutboundHttpInterceptor for OutboundHttpInter
             let req = request.into_hyper_request();
             let path = req.uri().path().to_owned();
             let route_match = RouteMatch::synthetic(component_id, path);
-            let resp = self
+            let server = self
                 .server
+                .upgrade()
+                .ok_or_else(|| HttpError::trap(anyhow::anyhow!("the app has been unloaded")))?;
+            let resp = server
                 .handle_trigger_route(req, route_match, Scheme::HTTP, CHAINED_CLIENT_ADDR)
                 .await
                 .map_err(HttpError::trap)?;
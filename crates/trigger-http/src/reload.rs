@@ -0,0 +1,133 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use http::{uri::Scheme, Request, Response};
+use hyper::body::Incoming;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{core_dump::CoreDumper, recording::Recorder, AppReloads};
+use tokio::{net::TcpListener, task};
+use wasmtime_wasi_http::body::HyperOutgoingBody;
+
+use crate::{
+    server::{bind_listener, serve_connections, HttpServer},
+    HttpTrigger, TlsConfig, TriggerApp,
+};
+
+/// An HTTP server whose app can be replaced while it is running.
+///
+/// Each request is handled by the app that is current when the request
+/// arrives. Requests in flight when the app is replaced finish on the old
+/// app, which is dropped once they have all completed, and open connections
+/// carry on with the new app.
+pub struct ReloadableServer<F: RuntimeFactors> {
+    /// The address the server is listening on.
+    listen_addr: SocketAddr,
+    /// The TLS configuration for the server.
+    tls_config: Option<TlsConfig>,
+    /// The server for the current app.
+    current: RwLock<Arc<HttpServer<F>>>,
+    /// Records each request, if recording is enabled.
+    recorder: Option<Recorder>,
+    /// Captures core dumps of traps, if enabled.
+    core_dumper: Option<CoreDumper>,
+}
+
+impl<F: RuntimeFactors> ReloadableServer<F> {
+    /// Create a new [`ReloadableServer`] serving `trigger_app`.
+    pub fn new(
+        listen_addr: SocketAddr,
+        tls_config: Option<TlsConfig>,
+        trigger_app: TriggerApp<F>,
+        recorder: Option<Recorder>,
+        core_dumper: Option<CoreDumper>,
+    ) -> anyhow::Result<Self> {
+        let server = HttpServer::new(
+            listen_addr,
+            tls_config.clone(),
+            trigger_app,
+            recorder.clone(),
+            core_dumper.clone(),
+        )?;
+        Ok(Self {
+            listen_addr,
+            tls_config,
+            current: RwLock::new(Arc::new(server)),
+            recorder,
+            core_dumper,
+        })
+    }
+
+    /// Serve incoming requests, switching to each app received from `reloads`.
+    pub async fn serve(
+        self: Arc<Self>,
+        mut reloads: AppReloads<HttpTrigger, F>,
+    ) -> anyhow::Result<()> {
+        let listener = bind_listener(self.listen_addr)
+            .with_context(|| format!("Unable to listen on {}", self.listen_addr))?;
+        let scheme = if self.tls_config.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let current = self.current();
+        current.start_background_tasks();
+        current.print_startup_msgs(scheme, &listener)?;
+
+        let base_url = format!("{scheme}://{:?}", listener.local_addr()?);
+        let this = self.clone();
+        task::spawn(async move {
+            while let Some(trigger_app) = reloads.recv().await {
+                match this.reload(trigger_app, &base_url) {
+                    Ok(()) => tracing::info!("Reloaded application"),
+                    Err(err) => {
+                        terminal::error!("Failed to reload application: {err:#}");
+                        terminal::einfo!("Continuing with", "the previously loaded application.");
+                    }
+                }
+            }
+        });
+
+        let tls_config = self.tls_config.clone();
+        serve_connections(
+            listener,
+            tls_config.as_ref(),
+            move |scheme, addr, request| self.clone().dispatch(scheme, addr, request),
+        )
+        .await
+    }
+
+    /// Replaces the current app with `trigger_app`.
+    fn reload(&self, trigger_app: TriggerApp<F>, base_url: &str) -> anyhow::Result<()> {
+        let server = Arc::new(HttpServer::new(
+            self.listen_addr,
+            self.tls_config.clone(),
+            trigger_app,
+            self.recorder.clone(),
+            self.core_dumper.clone(),
+        )?);
+        server.start_background_tasks();
+        *self.current.write().unwrap() = server.clone();
+
+        terminal::step!("\nReloaded", "{base_url}");
+        println!("Available Routes:");
+        server.print_routes(base_url)
+    }
+
+    fn current(&self) -> Arc<HttpServer<F>> {
+        self.current.read().unwrap().clone()
+    }
+
+    async fn dispatch(
+        self: Arc<Self>,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+        request: Request<Incoming>,
+    ) -> anyhow::Result<Response<HyperOutgoingBody>> {
+        self.current()
+            .instrumented_service_fn(server_scheme, client_addr, request)
+            .await
+    }
+}
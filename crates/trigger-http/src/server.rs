@@ -1,9 +1,16 @@
-use std::{collections::HashMap, future::Future, io::IsTerminal, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::IsTerminal,
+    net::SocketAddr,
+    sync::{Arc, Weak},
+    time::Instant,
+};
 
 use anyhow::{bail, Context};
 use http::{
     uri::{Authority, Scheme},
-    Request, Response, StatusCode, Uri,
+    Method, Request, Response, StatusCode, Uri,
 };
 use http_body_util::BodyExt;
 use hyper::{
@@ -13,15 +20,28 @@ use hyper::{
 };
 use hyper_util::rt::TokioIo;
 use spin_app::{APP_DESCRIPTION_KEY, APP_NAME_KEY};
+use spin_core::LimitExceeded;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_background_tasks::BackgroundTasksFactor;
+use spin_factor_key_value::KeyValueFactor;
 use spin_factor_outbound_http::{OutboundHttpFactor, SelfRequestOrigin};
+use spin_factor_request_context::RequestContextFactor;
+use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
 use spin_http::{
     app_info::AppInfo,
     body,
-    config::{HttpExecutorType, HttpTriggerConfig},
+    config::{
+        HttpExecutorType, HttpPrivateEndpoint, HttpTriggerConfig, HttpTriggerMetadata,
+        HttpTriggerRouteConfig,
+    },
     routes::{RouteMatch, Router},
     trigger::HandlerType,
 };
+use spin_trigger::{
+    core_dump::CoreDumper,
+    recording::{RecordedPayload, Recorder, Recording},
+};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpListener,
@@ -32,12 +52,25 @@ use wasmtime_wasi::p2::bindings::CommandIndices;
 use wasmtime_wasi_http::body::HyperOutgoingBody;
 
 use crate::{
+    background::BackgroundTasks,
+    compression::{self, Encoding},
+    core_dump::CoreDumpOnTrap,
+    graphql::GraphqlGateway,
     headers::strip_forbidden_headers,
+    idempotency::{self, Idempotency},
+    instance_pool::{Checkout, InstancePool},
     instrument::{finalize_http_span, http_span, instrument_error, MatchedRoute},
+    mirror::{self, Mirror},
+    multi::{request_host, with_path},
+    multipart::Multipart,
+    oidc::{self, Oidc},
+    openapi::OpenApiSpec,
     outbound_http::OutboundHttpInterceptor,
     spin::SpinHttpExecutor,
+    usage::{self, Usage},
     wagi::WagiHttpExecutor,
     wasi::WasiHttpExecutor,
+    webhook::Webhook,
     Body, NotFoundRouteKind, TlsConfig, TriggerApp, TriggerInstanceBuilder,
 };
 
@@ -55,6 +88,32 @@ pub struct HttpServer<F: RuntimeFactors> {
     component_trigger_configs: HashMap<String, HttpTriggerConfig>,
     // Component ID -> handler type
     component_handler_types: HashMap<String, HandlerType>,
+    /// Splits GraphQL operations across components, if the app has a GraphQL endpoint.
+    graphql: Option<GraphqlGateway>,
+    // Component ID -> OpenAPI document requests to the component are validated against
+    openapi_specs: HashMap<String, OpenApiSpec>,
+    // Component ID -> verification of its webhook requests' signatures
+    webhooks: HashMap<String, Webhook>,
+    // Component ID -> shadow component its requests are mirrored to
+    mirrors: HashMap<String, Mirror>,
+    // Component ID -> how retries with the same idempotency key are replayed
+    idempotency: HashMap<String, Idempotency>,
+    // Component ID -> signing in through an OpenID Connect provider
+    oidc: HashMap<String, Oidc>,
+    // Component ID -> idle instances of the component, if they are reused
+    instance_pools: HashMap<String, Arc<InstancePool<F>>>,
+    // Component ID -> parsing of its multipart requests
+    multipart: HashMap<String, Multipart>,
+    /// The smallest response body which is compressed.
+    compression_min_size: u64,
+    /// Whether responses report the resources used to produce them.
+    usage_header: bool,
+    /// Runs tasks spawned to run after a response, if the app supports them.
+    background_tasks: Option<BackgroundTasks>,
+    /// Records each request, if recording is enabled.
+    recorder: Option<Recorder>,
+    /// Captures core dumps of traps, if enabled.
+    core_dumper: Option<CoreDumper>,
 }
 
 impl<F: RuntimeFactors> HttpServer<F> {
@@ -63,6 +122,8 @@ impl<F: RuntimeFactors> HttpServer<F> {
         listen_addr: SocketAddr,
         tls_config: Option<TlsConfig>,
         trigger_app: TriggerApp<F>,
+        recorder: Option<Recorder>,
+        core_dumper: Option<CoreDumper>,
     ) -> anyhow::Result<Self> {
         // This needs to be a vec before building the router to handle duplicate routes
         let component_trigger_configs = Vec::from_iter(
@@ -104,28 +165,187 @@ impl<F: RuntimeFactors> HttpServer<F> {
         );
 
         // Now that router is built we can merge duplicate routes by component
-        let component_trigger_configs = HashMap::from_iter(component_trigger_configs);
+        let mut component_trigger_configs = HashMap::from_iter(component_trigger_configs);
 
-        let component_handler_types = component_trigger_configs
+        let mirrors = component_trigger_configs
             .iter()
-            .map(|(component_id, trigger_config)| {
-                let pre = trigger_app.get_instance_pre(component_id)?;
-                let handler_type = match &trigger_config.executor {
-                    None | Some(HttpExecutorType::Http) => {
-                        HandlerType::from_instance_pre(pre)?
-                    }
-                    Some(HttpExecutorType::Wagi(wagi_config)) => {
-                        anyhow::ensure!(
-                            wagi_config.entrypoint == "_start",
-                            "Wagi component '{component_id}' cannot use deprecated 'entrypoint' field"
-                        );
-                        HandlerType::Wagi(CommandIndices::new(pre)
-                            .context("failed to find wasi command interface for wagi executor")?)
-                    }
+            .filter_map(|(component_id, config)| {
+                let mirror = Mirror::new(config.mirror.as_ref()?).with_context(|| {
+                    format!("invalid request mirroring for component {component_id:?}")
+                });
+                Some(mirror.map(|mirror| (component_id.clone(), mirror)))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        for (component_id, mirror) in &mirrors {
+            let shadow_id = mirror.component_id();
+            anyhow::ensure!(
+                shadow_id != component_id,
+                "component {component_id:?} cannot mirror requests to itself"
+            );
+            anyhow::ensure!(
+                trigger_app.app().get_component(shadow_id).is_some(),
+                "component {component_id:?} mirrors requests to unknown component {shadow_id:?}"
+            );
+            // A shadow component without its own trigger runs like the component it shadows
+            if !component_trigger_configs.contains_key(shadow_id) {
+                let shadow_config = HttpTriggerConfig {
+                    component: shadow_id.to_owned(),
+                    route: HttpTriggerRouteConfig::Private(HttpPrivateEndpoint { private: true }),
+                    executor: component_trigger_configs[component_id].executor.clone(),
+                    ..Default::default()
                 };
-                Ok((component_id.clone(), handler_type))
+                component_trigger_configs.insert(shadow_id.to_owned(), shadow_config);
+            }
+        }
+
+        for (component_id, trigger_config) in &component_trigger_configs {
+            if let Some(HttpExecutorType::Wagi(wagi_config)) = &trigger_config.executor {
+                anyhow::ensure!(
+                    wagi_config.entrypoint == "_start",
+                    "Wagi component '{component_id}' cannot use deprecated 'entrypoint' field"
+                );
+            }
+        }
+
+        let metadata = trigger_app
+            .app()
+            .get_trigger_metadata::<HttpTriggerMetadata>("http")?
+            .unwrap_or_default();
+        let compression_min_size = metadata
+            .compression_min_size
+            .unwrap_or(compression::DEFAULT_MIN_SIZE);
+
+        let graphql = metadata
+            .graphql_route
+            .map(|route| GraphqlGateway::new(route, &component_trigger_configs))
+            .transpose()?;
+        if graphql.is_none()
+            && component_trigger_configs
+                .values()
+                .any(|config| config.graphql.is_some())
+        {
+            bail!("components declare GraphQL fields but the HTTP trigger has no `graphql_route`");
+        }
+
+        let openapi_specs = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let openapi = config.openapi.as_ref()?;
+                let spec = OpenApiSpec::new(openapi).with_context(|| {
+                    format!("invalid OpenAPI document for component {component_id:?}")
+                });
+                Some(spec.map(|spec| (component_id.clone(), spec)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let webhooks: HashMap<_, _> = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let webhook = Webhook::new(config.webhook.as_ref()?).with_context(|| {
+                    format!("invalid webhook verification for component {component_id:?}")
+                });
+                Some(webhook.map(|webhook| (component_id.clone(), webhook)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        if !webhooks.is_empty() {
+            // Webhook secrets are resolved from the app's variables
+            trigger_app
+                .configured_app()
+                .app_state::<VariablesFactor>()
+                .context("webhook verification requires variables support")?;
+        }
+
+        let idempotency = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let config = config.idempotency.as_ref()?;
+                let idempotency = trigger_app
+                    .configured_app()
+                    .app_state::<KeyValueFactor>()
+                    .context("idempotency requires key-value store support")
+                    .and_then(|key_value| Idempotency::new(config, key_value.store_manager()))
+                    .with_context(|| {
+                        format!("invalid idempotency configuration for component {component_id:?}")
+                    });
+                Some(idempotency.map(|idempotency| (component_id.clone(), idempotency)))
             })
             .collect::<anyhow::Result<_>>()?;
+
+        let oidc: HashMap<_, _> = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let config = config.oidc.as_ref()?;
+                let oidc = trigger_app
+                    .configured_app()
+                    .app_state::<KeyValueFactor>()
+                    .context("OIDC sign-in requires key-value store support")
+                    .and_then(|key_value| {
+                        Oidc::new(component_id, config, key_value.store_manager())
+                    })
+                    .with_context(|| {
+                        format!("invalid OIDC configuration for component {component_id:?}")
+                    });
+                Some(oidc.map(|oidc| (component_id.clone(), oidc)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        if !oidc.is_empty() {
+            // Client secrets are resolved from the app's variables
+            trigger_app
+                .configured_app()
+                .app_state::<VariablesFactor>()
+                .context("OIDC sign-in requires variables support")?;
+        }
+
+        let instance_pools = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let pool = InstancePool::new(component_id, config.instance_reuse.as_ref()?)
+                    .with_context(|| {
+                        format!(
+                            "invalid instance reuse configuration for component {component_id:?}"
+                        )
+                    });
+                Some(pool.map(|pool| (component_id.clone(), Arc::new(pool))))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let multipart = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, config)| {
+                let multipart = Multipart::new(config.multipart.as_ref()?).with_context(|| {
+                    format!("invalid multipart configuration for component {component_id:?}")
+                });
+                Some(multipart.map(|multipart| (component_id.clone(), multipart)))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        for component_id in multipart.keys() {
+            // Uploaded files are mounted when an instance is built
+            anyhow::ensure!(
+                !instance_pools.contains_key(component_id),
+                "component {component_id:?} cannot both parse multipart requests and reuse instances"
+            );
+        }
+
+        // Lazily loaded components have their handler types found per request.
+        let component_handler_types = if trigger_app.is_lazily_loaded() {
+            HashMap::new()
+        } else {
+            component_trigger_configs
+                .iter()
+                .map(|(component_id, trigger_config)| {
+                    let pre = trigger_app
+                        .loaded_instance_pre(component_id)
+                        .with_context(|| format!("no such component {component_id:?}"))?;
+                    let handler_type = Self::handler_type(trigger_config, &pre)?;
+                    Ok((component_id.clone(), handler_type))
+                })
+                .collect::<anyhow::Result<_>>()?
+        };
+        let background_tasks = trigger_app
+            .configured_app()
+            .app_state::<BackgroundTasksFactor>()
+            .ok()
+            .map(BackgroundTasks::new);
         Ok(Self {
             listen_addr,
             tls_config,
@@ -133,56 +353,73 @@ impl<F: RuntimeFactors> HttpServer<F> {
             trigger_app,
             component_trigger_configs,
             component_handler_types,
+            graphql,
+            openapi_specs,
+            webhooks,
+            mirrors,
+            idempotency,
+            oidc,
+            instance_pools,
+            multipart,
+            compression_min_size,
+            usage_header: usage::usage_header_enabled(),
+            background_tasks,
+            recorder,
+            core_dumper,
         })
     }
 
+    /// The app being served.
+    pub fn trigger_app(&self) -> &TriggerApp<F> {
+        &self.trigger_app
+    }
+
+    pub(crate) fn background_tasks(&self) -> Option<&BackgroundTasks> {
+        self.background_tasks.as_ref()
+    }
+
     /// Serve incoming requests over the provided [`TcpListener`].
     pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
-        let listener = TcpListener::bind(self.listen_addr).await.with_context(|| {
+        let listener = bind_listener(self.listen_addr).with_context(|| {
             format!(
                 "Unable to listen on {listen_addr}",
                 listen_addr = self.listen_addr
             )
         })?;
-        if let Some(tls_config) = self.tls_config.clone() {
-            self.serve_https(listener, tls_config).await?;
+        self.start_background_tasks();
+        let scheme = if self.tls_config.is_some() {
+            "https"
         } else {
-            self.serve_http(listener).await?;
-        }
-        Ok(())
+            "http"
+        };
+        self.print_startup_msgs(scheme, &listener)?;
+        let tls_config = self.tls_config.clone();
+        serve_connections(
+            listener,
+            tls_config.as_ref(),
+            move |scheme, addr, request| {
+                self.clone().instrumented_service_fn(scheme, addr, request)
+            },
+        )
+        .await
     }
 
-    async fn serve_http(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
-        self.print_startup_msgs("http", &listener)?;
-        loop {
-            let (stream, client_addr) = listener.accept().await?;
-            self.clone()
-                .serve_connection(stream, Scheme::HTTP, client_addr);
+    /// Starts running background tasks, if the app supports them, and
+    /// sweeping expired OIDC sign-ins and sessions, if any component uses
+    /// OIDC.
+    pub(crate) fn start_background_tasks(self: &Arc<Self>) {
+        if let Some(background_tasks) = &self.background_tasks {
+            background_tasks.start(self.clone());
         }
-    }
-
-    async fn serve_https(
-        self: Arc<Self>,
-        listener: TcpListener,
-        tls_config: TlsConfig,
-    ) -> anyhow::Result<()> {
-        self.print_startup_msgs("https", &listener)?;
-        let acceptor = tls_config.server_config()?;
-        loop {
-            let (stream, client_addr) = listener.accept().await?;
-            match acceptor.accept(stream).await {
-                Ok(stream) => self
-                    .clone()
-                    .serve_connection(stream, Scheme::HTTPS, client_addr),
-                Err(err) => tracing::error!(?err, "Failed to start TLS session"),
-            }
+        if !self.oidc.is_empty() {
+            spawn_oidc_sweeps(Arc::downgrade(self));
         }
     }
 
     /// Handles incoming requests using an HTTP executor.
     ///
-    /// This method handles well known paths and routes requests to the handler when the router
-    /// matches the requests path.
+    /// This method handles well known paths, OpenAPI document routes and the GraphQL endpoint, and
+    /// routes requests to the handler when the router matches the requests path.
     pub async fn handle(
         self: &Arc<Self>,
         mut req: Request<Body>,
@@ -193,7 +430,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
 
         spin_telemetry::extract_trace_context(&req);
 
-        let path = req.uri().path().to_string();
+        let mut path = req.uri().path().to_string();
 
         tracing::info!("Processing request on path '{path}'");
 
@@ -205,19 +442,233 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     path,
                 )),
                 "info" => self.app_info(path),
+                oidc::CALLBACK_PATH => Ok(MatchedRoute::with_response_extension(
+                    self.oidc_callback(&req).await?,
+                    path,
+                )),
                 _ => Self::not_found(NotFoundRouteKind::WellKnown),
             };
         }
 
-        match self.router.route(&path) {
-            Ok(route_match) => {
+        // The tenant is resolved before routing, as it may prefix the path
+        if let Ok(request_context) = self
+            .trigger_app
+            .configured_app()
+            .app_state::<RequestContextFactor>()
+        {
+            let host = request_host(&req);
+            let (tenant_id, tenant_path) =
+                match request_context.resolve_tenant(req.headers(), host.as_deref(), &path) {
+                    Ok(tenant) => (
+                        tenant.tenant_id,
+                        (tenant.path != path).then(|| tenant.path.to_owned()),
+                    ),
+                    Err(err) => {
+                        tracing::info!("Rejecting request on path '{path}': {err}");
+                        return Self::bad_request();
+                    }
+                };
+            if let Some(tenant_path) = tenant_path {
+                *req.uri_mut() = with_path(req.uri(), &tenant_path)?;
+                path = tenant_path;
+            }
+            if let Some(tenant_id) = tenant_id {
+                req.extensions_mut().insert(Tenant(tenant_id));
+            }
+        }
+
+        if req.method() == Method::GET {
+            if let Some(spec) = self
+                .openapi_specs
+                .values()
+                .find(|spec| spec.route() == Some(path.as_str()))
+            {
+                return Ok(MatchedRoute::with_response_extension(
+                    spec.document_response()?,
+                    path,
+                ));
+            }
+        }
+
+        if let Some(graphql) = self.graphql.as_ref().filter(|g| g.route() == path) {
+            return crate::graphql::handle(self, graphql, req, server_scheme, client_addr).await;
+        }
+
+        let route_match = match self.router.route(&path) {
+            Ok(route_match) => route_match,
+            Err(_) => return Self::not_found(NotFoundRouteKind::Normal(path.to_string())),
+        };
+
+        // Forged webhook requests are rejected before anything else
+        if let Some(webhook) = self.webhooks.get(route_match.component_id()) {
+            req = match self.verify_webhook(webhook, req).await? {
+                Ok(req) => req,
+                Err(status) => {
+                    return Ok(MatchedRoute::with_response_extension(
+                        Response::builder().status(status).body(body::empty())?,
+                        route_match.raw_route(),
+                    ))
+                }
+            };
+        }
+
+        if let Some(oidc) = self.oidc.get(route_match.component_id()) {
+            req = match oidc.authenticate(req, &server_scheme).await? {
+                Ok(req) => req,
+                Err(res) => {
+                    return Ok(MatchedRoute::with_response_extension(
+                        res,
+                        route_match.raw_route(),
+                    ))
+                }
+            };
+        }
+
+        match self.idempotency.get(route_match.component_id()) {
+            Some(idempotency) => {
+                self.handle_idempotent(req, route_match, idempotency, server_scheme, client_addr)
+                    .await
+            }
+            None => {
+                self.handle_routed(req, route_match, server_scheme, client_addr)
+                    .await
+            }
+        }
+    }
+
+    /// Verifies the signature of a webhook request, returning the request if
+    /// it is valid or the status to reject it with.
+    async fn verify_webhook(
+        &self,
+        webhook: &Webhook,
+        req: Request<Body>,
+    ) -> anyhow::Result<Result<Request<Body>, StatusCode>> {
+        let secret = self
+            .trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()?
+            .resolve_expression(webhook.secret_expression())
+            .await
+            .context("failed to resolve webhook secret")?;
+        webhook.verify(&secret, req).await
+    }
+
+    /// Completes a sign-in when an OIDC provider redirects back to the app.
+    async fn oidc_callback(&self, req: &Request<Body>) -> anyhow::Result<Response<Body>> {
+        let Some((oidc, state, params)) = oidc::callback_state(req.uri().query().unwrap_or(""))
+            .and_then(|(component_id, params)| {
+                let oidc = self.oidc.get(&component_id)?;
+                Some((oidc, params.get("state")?.clone(), params))
+            })
+        else {
+            return Self::bad_request();
+        };
+        let secret = self
+            .trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()?
+            .resolve_expression(oidc.secret_expression())
+            .await
+            .context("failed to resolve OIDC client secret")?;
+        oidc.complete_login(&state, req.headers(), &params, &secret)
+            .await
+    }
+
+    /// Handles a request to a component which replays responses to requests
+    /// retried with the same idempotency key.
+    async fn handle_idempotent(
+        self: &Arc<Self>,
+        req: Request<Body>,
+        route_match: RouteMatch<'_, '_>,
+        idempotency: &Idempotency,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        let key = match idempotency::request_key(&req) {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                return self
+                    .handle_routed(req, route_match, server_scheme, client_addr)
+                    .await
+            }
+            Err(_) => {
+                tracing::info!("Rejecting request with an invalid idempotency key");
+                return Self::bad_request();
+            }
+        };
+        let component_id = route_match.component_id();
+        let scope = match req.extensions().get() {
+            Some(Tenant(tenant_id)) => format!("{component_id}:{tenant_id}"),
+            None => component_id.to_owned(),
+        };
+        let raw_route = route_match.raw_route().to_owned();
+        let res = idempotency::handle(idempotency, &scope, &key, req, |req| {
+            self.handle_routed(req, route_match, server_scheme, client_addr)
+        })
+        .await?;
+        Ok(MatchedRoute::with_response_extension(res, raw_route))
+    }
+
+    /// Handles a request matched to a component, mirroring it if the
+    /// component's requests are mirrored.
+    async fn handle_routed(
+        self: &Arc<Self>,
+        req: Request<Body>,
+        route_match: RouteMatch<'_, '_>,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        match self.mirrors.get(route_match.component_id()) {
+            Some(mirror) if mirror.sample() => {
+                self.handle_mirrored(req, route_match, mirror, server_scheme, client_addr)
+                    .await
+            }
+            _ => {
                 self.handle_trigger_route(req, route_match, server_scheme, client_addr)
                     .await
             }
-            Err(_) => Self::not_found(NotFoundRouteKind::Normal(path.to_string())),
         }
     }
 
+    /// Handles a request which is also mirrored to a shadow component once the
+    /// matched component has responded.
+    async fn handle_mirrored(
+        self: &Arc<Self>,
+        req: Request<Body>,
+        route_match: RouteMatch<'_, '_>,
+        mirror: &Mirror,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        let (req, shadow_req) = mirror::duplicate_request(req).await?;
+        let component_id = route_match.component_id().to_owned();
+        let shadow_component_id = mirror.component_id().to_owned();
+        let shadow_match = route_match.for_component(shadow_component_id.clone());
+
+        let res = self
+            .handle_trigger_route(req, route_match, server_scheme.clone(), client_addr)
+            .await?;
+        let (res, primary_digest) = mirror::observe_response(res);
+
+        let server = self.clone();
+        task::spawn(
+            async move {
+                let shadow_res = server
+                    .handle_trigger_route(shadow_req, shadow_match, server_scheme, client_addr)
+                    .await;
+                mirror::compare(
+                    &component_id,
+                    &shadow_component_id,
+                    primary_digest,
+                    shadow_res,
+                )
+                .await;
+            }
+            .in_current_span(),
+        );
+        Ok(res)
+    }
+
     /// Handles a successful route match.
     pub async fn handle_trigger_route(
         self: &Arc<Self>,
@@ -226,6 +677,9 @@ impl<F: RuntimeFactors> HttpServer<F> {
         server_scheme: Scheme,
         client_addr: SocketAddr,
     ) -> anyhow::Result<Response<Body>> {
+        if let Some(recorder) = &self.recorder {
+            req = record_request(recorder, req, route_match.component_id()).await?;
+        }
         set_req_uri(&mut req, server_scheme.clone())?;
         let app_id = self
             .trigger_app
@@ -235,73 +689,259 @@ impl<F: RuntimeFactors> HttpServer<F> {
 
         let component_id = route_match.component_id();
 
+        if let Some(spec) = self.openapi_specs.get(component_id) {
+            req = match spec.validate(req).await? {
+                Ok(req) => req,
+                Err(rejection) => {
+                    tracing::info!(
+                        "Rejecting request to component {component_id} not allowed by its OpenAPI document"
+                    );
+                    return Ok(MatchedRoute::with_response_extension(
+                        rejection.into_response()?,
+                        route_match.raw_route(),
+                    ));
+                }
+            };
+        }
+
+        let mut uploads = None;
+        if let Some(multipart) = self.multipart.get(component_id) {
+            (req, uploads) = match multipart.parse(req).await? {
+                Ok(parsed) => parsed,
+                Err(status) => {
+                    return Ok(MatchedRoute::with_response_extension(
+                        Response::builder().status(status).body(body::empty())?,
+                        route_match.raw_route(),
+                    ));
+                }
+            };
+        }
+
+        let trigger_config = self.component_trigger_configs.get(component_id).unwrap();
+        let response_encoding =
+            if req.method() == Method::HEAD || trigger_config.compress == Some(false) {
+                None
+            } else {
+                Encoding::negotiate(req.headers())
+            };
+
         spin_telemetry::metrics::monotonic_counter!(
             spin.request_count = 1,
             trigger_type = "http",
             app_id = app_id,
             component_id = component_id
         );
+        let start = Instant::now();
 
-        let mut instance_builder = self.trigger_app.prepare(component_id)?;
-
-        // Set up outbound HTTP request origin and service chaining
-        // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
-        // implementations assume they use the same underlying wasmtime resource storage.
-        // Eventually, we may be able to factor this out to a separate factor.
-        let outbound_http = instance_builder
-            .factor_builder::<OutboundHttpFactor>()
-            .context(
-            "The wasi HTTP trigger was configured without the required wasi outbound http support",
-        )?;
         let origin = SelfRequestOrigin::create(server_scheme, &self.listen_addr.to_string())?;
-        outbound_http.set_self_request_origin(origin);
-        outbound_http.set_request_interceptor(OutboundHttpInterceptor::new(self.clone()))?;
-
-        // Prepare HTTP executor
-        let trigger_config = self.component_trigger_configs.get(component_id).unwrap();
-        let handler_type = self.component_handler_types.get(component_id).unwrap();
-        let executor = trigger_config
-            .executor
+        let core_dump = self
+            .core_dumper
             .as_ref()
-            .unwrap_or(&HttpExecutorType::Http);
-
-        let res = match executor {
-            HttpExecutorType::Http => match handler_type {
-                HandlerType::Spin => {
-                    SpinHttpExecutor
-                        .execute(instance_builder, &route_match, req, client_addr)
-                        .await
+            .map(|core_dumper| CoreDumpOnTrap::new(core_dumper, &req));
+        let pool = self.instance_pools.get(component_id);
+        let idle = match pool {
+            Some(pool) => pool.take()?,
+            None => None,
+        };
+
+        let (audit, res) = match (pool, idle) {
+            (Some(pool), Some(mut pooled)) => {
+                // The instance was set up for the request it was instantiated
+                // for, so only the per-request state is renewed
+                let state = pooled.store.data_mut().factors_instance_state_mut();
+                if let Some(request_context) = state.get::<RequestContextFactor>() {
+                    let request_context = request_context.handle();
+                    request_context.restart();
+                    request_context.set_from_http_headers(req.headers());
+                    if let Some(Tenant(tenant_id)) = req.extensions().get() {
+                        request_context.set_tenant_id(Some(tenant_id.clone()));
+                    }
                 }
-                HandlerType::Wasi0_2(_)
-                | HandlerType::Wasi2023_11_10(_)
-                | HandlerType::Wasi2023_10_18(_) => {
-                    WasiHttpExecutor { handler_type }
-                        .execute(instance_builder, &route_match, req, client_addr)
-                        .await
+                state
+                    .get::<OutboundHttpFactor>()
+                    .context("missing OutboundHttpFactor")?
+                    .set_self_request_origin(origin);
+                let audit = state
+                    .get::<AuditFactor>()
+                    .and_then(|audit| audit.handle().cloned());
+                if let Some(audit) = &audit {
+                    audit.restart();
                 }
-                HandlerType::Wagi(_) => unreachable!(),
-            },
-            HttpExecutorType::Wagi(wagi_config) => {
-                let indices = match handler_type {
-                    HandlerType::Wagi(indices) => indices,
-                    _ => unreachable!(),
+
+                let lazy_handler_type;
+                let handler_type = match self.component_handler_types.get(component_id) {
+                    Some(handler_type) => handler_type,
+                    None => {
+                        let pre = pooled.instance.instance_pre(&pooled.store);
+                        lazy_handler_type = Self::handler_type(trigger_config, &pre)?;
+                        &lazy_handler_type
+                    }
                 };
-                let executor = WagiHttpExecutor {
-                    wagi_config,
-                    indices,
+                let res = WasiHttpExecutor { handler_type }
+                    .execute_pooled(
+                        pool.clone(),
+                        Checkout::Idle(pooled),
+                        &route_match,
+                        req,
+                        client_addr,
+                        core_dump,
+                    )
+                    .await;
+                (audit, res)
+            }
+            (pool, _) => {
+                let mut instance_builder = match self.trigger_app.prepare(component_id).await {
+                    Err(err) if LimitExceeded::from_error(&err).is_some() => {
+                        tracing::warn!("Rejecting request to component {component_id}: {err:#}");
+                        instrument_error(&err);
+                        return Self::service_unavailable(route_match.raw_route());
+                    }
+                    res => res?,
                 };
-                executor
-                    .execute(instance_builder, &route_match, req, client_addr)
-                    .await
+
+                // Set up outbound HTTP request origin and service chaining
+                // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
+                // implementations assume they use the same underlying wasmtime resource storage.
+                // Eventually, we may be able to factor this out to a separate factor.
+                let outbound_http = instance_builder
+                    .factor_builder::<OutboundHttpFactor>()
+                    .context(
+                    "The wasi HTTP trigger was configured without the required wasi outbound http support",
+                )?;
+                outbound_http.set_self_request_origin(origin);
+                outbound_http
+                    .set_request_interceptor(OutboundHttpInterceptor::new(Arc::downgrade(self)))?;
+                if let Some(request_context) =
+                    instance_builder.factor_builder::<RequestContextFactor>()
+                {
+                    request_context.set_from_http_headers(req.headers());
+                    if let Some(Tenant(tenant_id)) = req.extensions().get() {
+                        request_context.set_tenant_id(Some(tenant_id.clone()));
+                    }
+                }
+                let audit = instance_builder
+                    .factor_builder::<AuditFactor>()
+                    .and_then(|audit| audit.handle());
+                if let Some(background_tasks) = &self.background_tasks {
+                    background_tasks.prepare_instance(&mut instance_builder);
+                }
+                if let Some(uploads) = uploads {
+                    uploads.mount(&mut instance_builder)?;
+                }
+
+                // Prepare HTTP executor
+                let lazy_handler_type;
+                let handler_type = match self.component_handler_types.get(component_id) {
+                    Some(handler_type) => handler_type,
+                    None => {
+                        lazy_handler_type =
+                            Self::handler_type(trigger_config, instance_builder.instance_pre())?;
+                        &lazy_handler_type
+                    }
+                };
+                let executor = trigger_config
+                    .executor
+                    .as_ref()
+                    .unwrap_or(&HttpExecutorType::Http);
+
+                let res = match executor {
+                    HttpExecutorType::Http => match handler_type {
+                        HandlerType::Spin => {
+                            SpinHttpExecutor
+                                .execute(
+                                    instance_builder,
+                                    &route_match,
+                                    req,
+                                    client_addr,
+                                    core_dump,
+                                )
+                                .await
+                        }
+                        HandlerType::Wasi0_2(_)
+                        | HandlerType::Wasi2023_11_10(_)
+                        | HandlerType::Wasi2023_10_18(_) => {
+                            let executor = WasiHttpExecutor { handler_type };
+                            match pool {
+                                // Only wasi:http components' instances are reused
+                                Some(pool) => {
+                                    executor
+                                        .execute_pooled(
+                                            pool.clone(),
+                                            Checkout::New(instance_builder),
+                                            &route_match,
+                                            req,
+                                            client_addr,
+                                            core_dump,
+                                        )
+                                        .await
+                                }
+                                None => {
+                                    executor
+                                        .execute(
+                                            instance_builder,
+                                            &route_match,
+                                            req,
+                                            client_addr,
+                                            core_dump,
+                                        )
+                                        .await
+                                }
+                            }
+                        }
+                        HandlerType::Wagi(_) => unreachable!(),
+                    },
+                    HttpExecutorType::Wagi(wagi_config) => {
+                        let indices = match handler_type {
+                            HandlerType::Wagi(indices) => indices,
+                            _ => unreachable!(),
+                        };
+                        let executor = WagiHttpExecutor {
+                            wagi_config,
+                            indices,
+                        };
+                        executor
+                            .execute(instance_builder, &route_match, req, client_addr, core_dump)
+                            .await
+                    }
+                };
+                (audit, res)
             }
         };
-        match res {
-            Ok(res) => Ok(MatchedRoute::with_response_extension(
-                res,
+        // For WASI HTTP, this is the time until the response headers were sent
+        spin_telemetry::metrics::histogram!(
+            spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            trigger_type = "http",
+            app_id = app_id,
+            component_id = component_id
+        );
+        if let Some(audit) = audit {
+            audit.finish(
+                "http",
                 route_match.raw_route(),
-            )),
+                AuditOutcome::from_result(&res),
+            );
+        }
+        match res {
+            Ok(mut res) => {
+                if self.usage_header {
+                    if let Some(usage) = res.extensions().get::<Usage>().cloned() {
+                        usage.add_header(&mut res);
+                    }
+                }
+                if let Some(encoding) = response_encoding {
+                    res = compression::compress(res, encoding, self.compression_min_size);
+                }
+                Ok(MatchedRoute::with_response_extension(
+                    res,
+                    route_match.raw_route(),
+                ))
+            }
             Err(err) => {
-                tracing::error!("Error processing request: {err:?}");
+                if let Some(limit) = LimitExceeded::from_error(&err) {
+                    tracing::error!("Component {component_id} exceeded a resource limit: {limit}");
+                } else {
+                    tracing::error!("Error processing request: {err:?}");
+                }
                 instrument_error(&err);
                 Self::internal_error(None, route_match.raw_route())
             }
@@ -309,6 +949,20 @@ impl<F: RuntimeFactors> HttpServer<F> {
     }
 
     /// Returns spin status information.
+    /// Finds how the component for `trigger_config` handles requests.
+    fn handler_type<T>(
+        trigger_config: &HttpTriggerConfig,
+        pre: &spin_core::InstancePre<T>,
+    ) -> anyhow::Result<HandlerType> {
+        match &trigger_config.executor {
+            None | Some(HttpExecutorType::Http) => HandlerType::from_instance_pre(pre),
+            Some(HttpExecutorType::Wagi(_)) => Ok(HandlerType::Wagi(
+                CommandIndices::new(pre)
+                    .context("failed to find wasi command interface for wagi executor")?,
+            )),
+        }
+    }
+
     fn app_info(&self, route: String) -> anyhow::Result<Response<Body>> {
         let info = AppInfo::new(self.trigger_app.app());
         let body = serde_json::to_vec_pretty(&info)?;
@@ -338,6 +992,23 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Creates an HTTP 400 response.
+    fn bad_request() -> anyhow::Result<Response<Body>> {
+        Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(body::empty())?)
+    }
+
+    /// Creates an HTTP 503 response.
+    fn service_unavailable(route: impl Into<String>) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(body::empty())?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 404 response.
     fn not_found(kind: NotFoundRouteKind) -> anyhow::Result<Response<Body>> {
         use std::sync::atomic::{AtomicBool, Ordering};
@@ -354,33 +1025,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
             .body(body::empty())?)
     }
 
-    fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
-        self: Arc<Self>,
-        stream: S,
-        server_scheme: Scheme,
-        client_addr: SocketAddr,
-    ) {
-        task::spawn(async move {
-            if let Err(err) = http1::Builder::new()
-                .keep_alive(true)
-                .serve_connection(
-                    TokioIo::new(stream),
-                    service_fn(move |request| {
-                        self.clone().instrumented_service_fn(
-                            server_scheme.clone(),
-                            client_addr,
-                            request,
-                        )
-                    }),
-                )
-                .await
-            {
-                tracing::warn!("Error serving HTTP connection: {err:?}");
-            }
-        });
-    }
-
-    async fn instrumented_service_fn(
+    pub(crate) async fn instrumented_service_fn(
         self: Arc<Self>,
         server_scheme: Scheme,
         client_addr: SocketAddr,
@@ -405,13 +1050,22 @@ impl<F: RuntimeFactors> HttpServer<F> {
         .await
     }
 
-    fn print_startup_msgs(&self, scheme: &str, listener: &TcpListener) -> anyhow::Result<()> {
+    pub(crate) fn print_startup_msgs(
+        &self,
+        scheme: &str,
+        listener: &TcpListener,
+    ) -> anyhow::Result<()> {
         let local_addr = listener.local_addr()?;
         let base_url = format!("{scheme}://{local_addr:?}");
         terminal::step!("\nServing", "{base_url}");
         tracing::info!("Serving {base_url}");
 
         println!("Available Routes:");
+        self.print_routes(&base_url)
+    }
+
+    /// Prints the app's routes relative to `base_url`.
+    pub(crate) fn print_routes(&self, base_url: &str) -> anyhow::Result<()> {
         for (route, component_id) in self.router.routes() {
             println!("  {}: {}{}", component_id, base_url, route);
             if let Some(component) = self.trigger_app.app().get_component(component_id) {
@@ -424,6 +1078,135 @@ impl<F: RuntimeFactors> HttpServer<F> {
     }
 }
 
+/// Deletes expired OIDC sign-ins and sessions every
+/// [`oidc::SWEEP_INTERVAL`] for as long as `server` is running.
+fn spawn_oidc_sweeps<F: RuntimeFactors>(server: Weak<HttpServer<F>>) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(oidc::SWEEP_INTERVAL);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately; nothing has expired at startup
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let Some(server) = server.upgrade() else {
+                return;
+            };
+            for oidc in server.oidc.values() {
+                oidc.sweep().await;
+            }
+        }
+    });
+}
+
+/// Binds a listener to `addr`.
+///
+/// Listening on the IPv6 wildcard address (`[::]`) also accepts IPv4
+/// connections, whatever the platform's default for `IPV6_V6ONLY`.
+pub(crate) fn bind_listener(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    // As `TcpListener::bind` does, so that restarts needn't wait out TIME_WAIT
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Accepts connections on `listener` until an error occurs, serving the
+/// requests on each connection with `handler`.
+pub(crate) async fn serve_connections<H, Fut>(
+    listener: TcpListener,
+    tls_config: Option<&TlsConfig>,
+    handler: H,
+) -> anyhow::Result<()>
+where
+    H: Fn(Scheme, SocketAddr, Request<Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = anyhow::Result<Response<HyperOutgoingBody>>> + Send + 'static,
+{
+    let acceptor = tls_config.map(TlsConfig::server_config).transpose()?;
+    loop {
+        let (stream, client_addr) = listener.accept().await?;
+        match &acceptor {
+            None => serve_connection(stream, Scheme::HTTP, client_addr, handler.clone()),
+            Some(acceptor) => match acceptor.accept(stream).await {
+                Ok(stream) => serve_connection(stream, Scheme::HTTPS, client_addr, handler.clone()),
+                Err(err) => tracing::error!(?err, "Failed to start TLS session"),
+            },
+        }
+    }
+}
+
+fn serve_connection<S, H, Fut>(
+    stream: S,
+    server_scheme: Scheme,
+    client_addr: SocketAddr,
+    handler: H,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    H: Fn(Scheme, SocketAddr, Request<Incoming>) -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<Response<HyperOutgoingBody>>> + Send + 'static,
+{
+    task::spawn(async move {
+        if let Err(err) = http1::Builder::new()
+            .keep_alive(true)
+            .serve_connection(
+                TokioIo::new(stream),
+                service_fn(move |request| handler(server_scheme.clone(), client_addr, request)),
+            )
+            .await
+        {
+            tracing::warn!("Error serving HTTP connection: {err:?}");
+        }
+    });
+}
+
+/// Records a request for replay.
+///
+/// The body is read into memory to record it, and the returned request
+/// carries the same body.
+async fn record_request(
+    recorder: &Recorder,
+    req: Request<Body>,
+    component_id: &str,
+) -> anyhow::Result<Request<Body>> {
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await?.to_bytes();
+    let payload = RecordedPayload::Http {
+        method: parts.method.to_string(),
+        path_and_query: parts
+            .uri
+            .path_and_query()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| parts.uri.path().to_owned()),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect(),
+        body: body.to_vec(),
+    };
+    if let Err(err) = recorder.record(&Recording::new("http", component_id, payload)) {
+        tracing::error!("Failed to record request to component {component_id}: {err:#}");
+    }
+    Ok(Request::from_parts(parts, body::full(body)))
+}
+
+/// The tenant a request is on behalf of, carried as a request extension from
+/// routing to the component.
+#[derive(Clone)]
+pub(crate) struct Tenant(String);
+
 /// The incoming request's scheme and authority
 ///
 /// The incoming request's URI is relative to the server, so we need to set the scheme and authority.
@@ -474,5 +1257,6 @@ pub(crate) trait HttpExecutor {
         route_match: &RouteMatch<'_, '_>,
         req: Request<Body>,
         client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
     ) -> impl Future<Output = anyhow::Result<Response<Body>>>;
 }
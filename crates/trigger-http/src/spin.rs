@@ -10,8 +10,10 @@ use spin_world::v1::http_types;
 use tracing::{instrument, Level};
 
 use crate::{
+    core_dump::CoreDumpOnTrap,
     headers::{append_headers, prepare_request_headers},
     server::HttpExecutor,
+    usage::{CpuTimer, Usage},
     Body, TriggerInstanceBuilder,
 };
 
@@ -20,13 +22,14 @@ use crate::{
 pub struct SpinHttpExecutor;
 
 impl HttpExecutor for SpinHttpExecutor {
-    #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.component_id())))]
+    #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.component_id()), component_id = route_match.component_id()))]
     async fn execute<F: RuntimeFactors>(
         &self,
         instance_builder: TriggerInstanceBuilder<'_, F>,
         route_match: &RouteMatch<'_, '_>,
         req: Request<Body>,
         client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
     ) -> Result<Response<Body>> {
         let component_id = route_match.component_id();
 
@@ -77,7 +80,14 @@ impl HttpExecutor for SpinHttpExecutor {
             body: Some(bytes),
         };
 
-        let (resp,) = func.call_async(&mut store, (req,)).await?;
+        let timer = CpuTimer::default();
+        let result = timer.measure(func.call_async(&mut store, (req,))).await;
+        if let Some(core_dump) = core_dump {
+            core_dump.capture(&mut store, &result, component_id);
+        }
+        let (resp,) = result?;
+        let usage = Usage::from_store(timer.elapsed(), &store);
+        usage.record(component_id);
 
         if resp.status < 100 || resp.status > 600 {
             tracing::error!("malformed HTTP status code");
@@ -96,7 +106,9 @@ impl HttpExecutor for SpinHttpExecutor {
             None => body::empty(),
         };
 
-        Ok(response.body(body)?)
+        let mut response = response.body(body)?;
+        response.extensions_mut().insert(usage);
+        Ok(response)
     }
 }
 
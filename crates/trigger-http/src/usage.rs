@@ -0,0 +1,166 @@
+//! Reporting of the resources used by each invocation of a component.
+//!
+//! Each invocation's CPU time, fuel and linear memory are recorded as
+//! metrics. If [`USAGE_HEADER_ENV`] is set, they are also reported to the
+//! client in a [`USAGE_HEADER`] on the response, to help find the routes
+//! that are the most expensive to run.
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use http::HeaderValue;
+use hyper::Response;
+use spin_factors::RuntimeFactors;
+
+use crate::HttpTrigger;
+
+/// The response header reporting the resources used by an invocation.
+pub(crate) const USAGE_HEADER: &str = "spin-invocation-usage";
+
+/// Set to `1` to add a [`USAGE_HEADER`] to responses.
+pub(crate) const USAGE_HEADER_ENV: &str = "SPIN_HTTP_USAGE_HEADER";
+
+/// Returns whether responses should carry a [`USAGE_HEADER`].
+pub(crate) fn usage_header_enabled() -> bool {
+    std::env::var(USAGE_HEADER_ENV).is_ok_and(|val| val == "1")
+}
+
+/// Measures the CPU time used by a guest: the time spent polling the futures
+/// which run it.
+///
+/// This excludes time spent waiting on I/O, but includes time spent in host
+/// calls which complete without yielding.
+#[derive(Clone, Default)]
+pub(crate) struct CpuTimer {
+    nanos: Arc<AtomicU64>,
+}
+
+impl CpuTimer {
+    /// Runs `fut`, adding the time spent polling it to this timer.
+    pub async fn measure<F: Future>(&self, fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        std::future::poll_fn(|cx| {
+            let start = Instant::now();
+            let poll = fut.as_mut().poll(cx);
+            let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+            self.nanos.fetch_add(nanos, Ordering::Relaxed);
+            poll
+        })
+        .await
+    }
+
+    /// The time measured so far.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// The resources used by an invocation.
+///
+/// Executors add this to the extensions of the responses they return.
+#[derive(Clone, Debug)]
+pub(crate) struct Usage {
+    pub cpu_time: Duration,
+    /// The fuel consumed, if the component's fuel is metered.
+    pub fuel: Option<u64>,
+    /// The peak linear memory in bytes, if known.
+    pub memory: Option<u64>,
+}
+
+impl Usage {
+    /// Reads the usage of an invocation from its store once it has finished.
+    pub fn from_store<F: RuntimeFactors>(
+        cpu_time: Duration,
+        store: &spin_trigger::Store<HttpTrigger, F>,
+    ) -> Self {
+        Self {
+            cpu_time,
+            fuel: store.fuel_consumed(),
+            // Linear memories never shrink, so the memory consumed is the peak
+            memory: Some(store.data().core_state().memory_consumed()),
+        }
+    }
+
+    /// Records this usage in the invocation metrics.
+    pub fn record(&self, component_id: &str) {
+        tracing::trace!("Invocation of {component_id} used {}", self.header_value());
+        spin_telemetry::metrics::histogram!(
+            spin.invocation_cpu_time_ms = self.cpu_time.as_secs_f64() * 1000.0,
+            component_id = component_id
+        );
+        if let Some(fuel) = self.fuel {
+            spin_telemetry::metrics::histogram!(
+                spin.invocation_fuel_consumed = fuel as f64,
+                component_id = component_id
+            );
+        }
+        if let Some(memory) = self.memory {
+            spin_telemetry::metrics::histogram!(
+                spin.instance_memory_bytes = memory as f64,
+                component_id = component_id
+            );
+        }
+    }
+
+    /// Formats this usage as e.g. `cpu=1.250ms, fuel=12000, memory=1114112`,
+    /// omitting unknown values.
+    fn header_value(&self) -> String {
+        let mut value = format!("cpu={:.3}ms", self.cpu_time.as_secs_f64() * 1000.0);
+        if let Some(fuel) = self.fuel {
+            value.push_str(&format!(", fuel={fuel}"));
+        }
+        if let Some(memory) = self.memory {
+            value.push_str(&format!(", memory={memory}"));
+        }
+        value
+    }
+
+    /// Reports this usage in a [`USAGE_HEADER`] on `res`.
+    pub fn add_header<B>(&self, res: &mut Response<B>) {
+        if let Ok(value) = HeaderValue::from_str(&self.header_value()) {
+            res.headers_mut().insert(USAGE_HEADER, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cpu_timer_excludes_time_spent_waiting() {
+        let timer = CpuTimer::default();
+        timer
+            .measure(tokio::time::sleep(Duration::from_millis(50)))
+            .await;
+        assert!(timer.elapsed() < Duration::from_millis(50));
+
+        timer
+            .measure(async { std::thread::sleep(Duration::from_millis(10)) })
+            .await;
+        assert!(timer.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn header_omits_unknown_values() {
+        let mut usage = Usage {
+            cpu_time: Duration::from_micros(1250),
+            fuel: Some(12000),
+            memory: Some(1114112),
+        };
+        assert_eq!(
+            usage.header_value(),
+            "cpu=1.250ms, fuel=12000, memory=1114112"
+        );
+        usage.fuel = None;
+        usage.memory = None;
+        assert_eq!(usage.header_value(), "cpu=1.250ms");
+    }
+}
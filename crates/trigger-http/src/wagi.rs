@@ -11,7 +11,13 @@ use wasmtime_wasi::p2::bindings::CommandIndices;
 use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
 use wasmtime_wasi_http::body::HyperIncomingBody as Body;
 
-use crate::{headers::compute_default_headers, server::HttpExecutor, TriggerInstanceBuilder};
+use crate::{
+    core_dump::CoreDumpOnTrap,
+    headers::compute_default_headers,
+    server::HttpExecutor,
+    usage::{CpuTimer, Usage},
+    TriggerInstanceBuilder,
+};
 
 pub struct WagiHttpExecutor<'a> {
     pub wagi_config: &'a WagiTriggerConfig,
@@ -19,13 +25,14 @@ pub struct WagiHttpExecutor<'a> {
 }
 
 impl HttpExecutor for WagiHttpExecutor<'_> {
-    #[instrument(name = "spin_trigger_http.execute_wagi", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wagi_component {}", route_match.component_id())))]
+    #[instrument(name = "spin_trigger_http.execute_wagi", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wagi_component {}", route_match.component_id()), component_id = route_match.component_id()))]
     async fn execute<F: RuntimeFactors>(
         &self,
         mut instance_builder: TriggerInstanceBuilder<'_, F>,
         route_match: &RouteMatch<'_, '_>,
         req: Request<Body>,
         client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
     ) -> Result<Response<Body>> {
         let component = route_match.component_id();
 
@@ -91,15 +98,20 @@ impl HttpExecutor for WagiHttpExecutor<'_> {
         let command = self.indices.load(&mut store, &instance)?;
 
         tracing::trace!("Calling Wasm entry point");
-        if let Err(()) = command
-            .wasi_cli_run()
-            .call_run(&mut store)
+        let timer = CpuTimer::default();
+        let result = timer
+            .measure(command.wasi_cli_run().call_run(&mut store))
             .await
-            .or_else(ignore_successful_proc_exit_trap)?
-        {
+            .or_else(ignore_successful_proc_exit_trap);
+        if let Some(core_dump) = core_dump {
+            core_dump.capture(&mut store, &result, component);
+        }
+        if let Err(()) = result? {
             tracing::error!("Wagi main function returned unsuccessful result");
         }
         tracing::info!("Wagi execution complete");
+        let usage = Usage::from_store(timer.elapsed(), &store);
+        usage.record(component);
 
         // Drop the store so we're left with a unique reference to `stdout`:
         drop(store);
@@ -111,7 +123,9 @@ impl HttpExecutor for WagiHttpExecutor<'_> {
              but did not write to stdout. Check the `executor` in spin.toml."
         );
 
-        wagi::compose_response(&stdout)
+        let mut response = wagi::compose_response(&stdout)?;
+        response.extensions_mut().insert(usage);
+        Ok(response)
     }
 }
 
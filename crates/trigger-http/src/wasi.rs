@@ -1,10 +1,12 @@
 use std::io::IsTerminal;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Result};
 use futures::TryFutureExt;
 use http::{HeaderName, HeaderValue};
 use hyper::{Request, Response};
+use spin_core::Instance;
 use spin_factor_outbound_http::wasi_2023_10_18::Proxy as Proxy2023_10_18;
 use spin_factor_outbound_http::wasi_2023_11_10::Proxy as Proxy2023_11_10;
 use spin_factors::RuntimeFactors;
@@ -16,7 +18,15 @@ use wasmtime_wasi::p2::IoView;
 use wasmtime_wasi_http::bindings::http::types::Scheme;
 use wasmtime_wasi_http::{bindings::Proxy, body::HyperIncomingBody as Body, WasiHttpView};
 
-use crate::{headers::prepare_request_headers, server::HttpExecutor, TriggerInstanceBuilder};
+use crate::{
+    core_dump::CoreDumpOnTrap,
+    disconnect::InvocationGuard,
+    headers::prepare_request_headers,
+    instance_pool::{Checkout, InstancePool, PooledInstance},
+    server::HttpExecutor,
+    usage::{CpuTimer, Usage},
+    Store, TriggerInstanceBuilder,
+};
 
 /// An [`HttpExecutor`] that uses the `wasi:http/incoming-handler` interface.
 pub struct WasiHttpExecutor<'a> {
@@ -24,19 +34,107 @@ pub struct WasiHttpExecutor<'a> {
 }
 
 impl HttpExecutor for WasiHttpExecutor<'_> {
-    #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.component_id())))]
+    #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.component_id()), component_id = route_match.component_id()))]
     async fn execute<F: RuntimeFactors>(
         &self,
-        instance_builder: TriggerInstanceBuilder<'_, F>,
+        mut instance_builder: TriggerInstanceBuilder<'_, F>,
         route_match: &RouteMatch<'_, '_>,
-        mut req: Request<Body>,
+        req: Request<Body>,
         client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
     ) -> Result<Response<Body>> {
         let component_id = route_match.component_id();
 
         tracing::trace!("Executing request using the Wasi executor for component {component_id}");
 
-        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        instance_builder.store_builder().interruptible();
+        let (instance, store) = instance_builder.instantiate(()).await?;
+        self.handle(
+            instance,
+            store,
+            route_match,
+            req,
+            client_addr,
+            core_dump,
+            |_, _| {},
+        )
+        .await
+    }
+}
+
+impl WasiHttpExecutor<'_> {
+    /// Executes a request with an instance from `pool`, returning the instance
+    /// to the pool once the guest has handled the request.
+    #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.component_id()), component_id = route_match.component_id()))]
+    pub(crate) async fn execute_pooled<F: RuntimeFactors>(
+        &self,
+        pool: Arc<InstancePool<F>>,
+        checkout: Checkout<'_, F>,
+        route_match: &RouteMatch<'_, '_>,
+        req: Request<Body>,
+        client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
+    ) -> Result<Response<Body>> {
+        let component_id = route_match.component_id();
+
+        let PooledInstance {
+            instance,
+            store,
+            requests,
+        } = match checkout {
+            Checkout::New(mut instance_builder) => {
+                tracing::trace!(
+                    "Executing request with a new pooled instance of component {component_id}"
+                );
+                instance_builder.store_builder().interruptible();
+                let (instance, store) = instance_builder.instantiate(()).await?;
+                PooledInstance {
+                    instance,
+                    store,
+                    requests: 0,
+                }
+            }
+            Checkout::Idle(pooled) => {
+                tracing::trace!(
+                    "Executing request with an instance of component {component_id} which has handled {} requests",
+                    pooled.requests
+                );
+                pooled
+            }
+        };
+        self.handle(
+            instance,
+            store,
+            route_match,
+            req,
+            client_addr,
+            core_dump,
+            move |store, trapped| {
+                let pooled = PooledInstance {
+                    instance,
+                    store,
+                    requests,
+                };
+                pool.put(pooled, trapped);
+            },
+        )
+        .await
+    }
+
+    /// Handles a request with `instance`, passing its store to `on_done`,
+    /// along with whether the guest trapped, once the guest returns.
+    #[allow(clippy::too_many_arguments)]
+    async fn handle<F: RuntimeFactors>(
+        &self,
+        instance: Instance,
+        mut store: Store<F>,
+        route_match: &RouteMatch<'_, '_>,
+        mut req: Request<Body>,
+        client_addr: SocketAddr,
+        core_dump: Option<CoreDumpOnTrap>,
+        on_done: impl FnOnce(Store<F>, bool) + Send + 'static,
+    ) -> Result<Response<Body>> {
+        let component_id = route_match.component_id();
 
         let headers = prepare_request_headers(&req, route_match, client_addr)?;
         req.headers_mut().clear();
@@ -94,52 +192,65 @@ impl HttpExecutor for WasiHttpExecutor<'_> {
             HandlerType::Wagi(_) => unreachable!("should have used WagiExecutor instead"),
         };
 
+        let interrupt = store.interrupt_handle();
         let span = tracing::debug_span!("execute_wasi");
+        let component_id = component_id.to_owned();
+        let timer = CpuTimer::default();
+        let task_timer = timer.clone();
         let handle = task::spawn(
             async move {
-                let result = match handler {
-                    Handler::Latest(handler) => {
-                        handler
-                            .wasi_http_incoming_handler()
-                            .call_handle(&mut store, request, response)
-                            .instrument(span)
-                            .await
-                    }
-                    Handler::Handler2023_10_18(handler) => {
-                        handler
-                            .wasi_http0_2_0_rc_2023_10_18_incoming_handler()
-                            .call_handle(&mut store, request, response)
-                            .instrument(span)
-                            .await
-                    }
-                    Handler::Handler2023_11_10(handler) => {
-                        handler
-                            .wasi_http0_2_0_rc_2023_11_10_incoming_handler()
-                            .call_handle(&mut store, request, response)
-                            .instrument(span)
-                            .await
+                let call = async {
+                    match handler {
+                        Handler::Latest(handler) => {
+                            handler
+                                .wasi_http_incoming_handler()
+                                .call_handle(&mut store, request, response)
+                                .instrument(span)
+                                .await
+                        }
+                        Handler::Handler2023_10_18(handler) => {
+                            handler
+                                .wasi_http0_2_0_rc_2023_10_18_incoming_handler()
+                                .call_handle(&mut store, request, response)
+                                .instrument(span)
+                                .await
+                        }
+                        Handler::Handler2023_11_10(handler) => {
+                            handler
+                                .wasi_http0_2_0_rc_2023_11_10_incoming_handler()
+                                .call_handle(&mut store, request, response)
+                                .instrument(span)
+                                .await
+                        }
                     }
                 };
+                let result = task_timer.measure(call).await;
+                if let Some(core_dump) = core_dump {
+                    core_dump.capture(&mut store, &result, &component_id);
+                }
 
-                tracing::trace!(
-                    "wasi-http memory consumed: {}",
-                    store.data().core_state().memory_consumed()
-                );
+                Usage::from_store(task_timer.elapsed(), &store).record(&component_id);
 
+                on_done(store, result.is_err());
                 result
             }
             .in_current_span(),
         );
+        // Until the response has been sent, the client going away cancels
+        // the guest
+        let guard = InvocationGuard::new(handle.abort_handle(), interrupt);
 
         match response_rx.await {
             Ok(response) => {
                 task::spawn(
                     async move {
-                        handle
-                            .await
-                            .context("guest invocation panicked")?
-                            .context("guest invocation failed")?;
-
+                        match handle.await {
+                            // The client disconnected before the response was sent
+                            Err(err) if err.is_cancelled() => {}
+                            result => result
+                                .context("guest invocation panicked")?
+                                .context("guest invocation failed")?,
+                        }
                         Ok(())
                     }
                     .map_err(|e: anyhow::Error| {
@@ -151,7 +262,20 @@ impl HttpExecutor for WasiHttpExecutor<'_> {
                     }),
                 );
 
-                Ok(response.context("guest failed to produce a response")?)
+                let mut response = match response {
+                    Ok(response) => response.map(|body| guard.guard_body(body)),
+                    Err(err) => {
+                        guard.disarm();
+                        return Err(err).context("guest failed to produce a response");
+                    }
+                };
+                // The guest may still be running, so report its usage so far
+                response.extensions_mut().insert(Usage {
+                    cpu_time: timer.elapsed(),
+                    fuel: None,
+                    memory: None,
+                });
+                Ok(response)
             }
 
             Err(_) => {
@@ -0,0 +1,333 @@
+//! Verification of the signatures of inbound webhook requests.
+//!
+//! A component's trigger can declare the signature scheme of the webhooks it
+//! receives. Requests without a valid signature are rejected before the
+//! component runs, and verified requests carry a [`VERIFIED_HEADER`] naming
+//! the scheme.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use sha2::Sha256;
+use spin_http::{
+    body,
+    config::{WebhookConfig, WebhookScheme, WebhookSignatureEncoding},
+};
+
+use crate::Body;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Set on verified requests to the name of the webhook's signature scheme.
+pub(crate) const VERIFIED_HEADER: &str = "spin-webhook-verified";
+
+/// How old a signed timestamp may be by default.
+const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// The largest body buffered by default, which is the largest payload GitHub
+/// sends.
+const DEFAULT_MAX_SIZE: u64 = 25 * 1024 * 1024;
+
+/// Verifies the signatures of a component's webhook requests.
+pub(crate) struct Webhook {
+    scheme: WebhookScheme,
+    /// The secret, as an expression over the app's variables.
+    secret: String,
+    /// The header carrying the signature for the generic scheme.
+    header: Option<HeaderName>,
+    prefix: String,
+    encoding: WebhookSignatureEncoding,
+    tolerance: Duration,
+    max_size: u64,
+}
+
+impl Webhook {
+    pub fn new(config: &WebhookConfig) -> anyhow::Result<Self> {
+        let header = config.header.as_deref().map(str::parse).transpose()?;
+        if config.scheme == WebhookScheme::HmacSha256 {
+            anyhow::ensure!(
+                header.is_some(),
+                "the hmac-sha256 webhook scheme requires a `header`"
+            );
+        } else {
+            anyhow::ensure!(
+                config.header.is_none() && config.prefix.is_none(),
+                "only the hmac-sha256 webhook scheme takes a `header` or `prefix`"
+            );
+        }
+        Ok(Self {
+            scheme: config.scheme,
+            secret: config.secret.clone(),
+            header,
+            prefix: config.prefix.clone().unwrap_or_default(),
+            encoding: config.encoding,
+            tolerance: config
+                .tolerance_secs
+                .map_or(DEFAULT_TOLERANCE, Duration::from_secs),
+            max_size: config.max_size.unwrap_or(DEFAULT_MAX_SIZE),
+        })
+    }
+
+    /// The secret, as an expression over the app's variables.
+    pub fn secret_expression(&self) -> &str {
+        &self.secret
+    }
+
+    /// Verifies the signature of a request with the resolved `secret`.
+    ///
+    /// Returns the request, with its body buffered, if the signature is
+    /// valid, or the status to reject it with.
+    pub async fn verify(
+        &self,
+        secret: &str,
+        req: Request<Body>,
+    ) -> anyhow::Result<Result<Request<Body>, StatusCode>> {
+        let (mut parts, req_body) = req.into_parts();
+        let max_size = usize::try_from(self.max_size).unwrap_or(usize::MAX);
+        let req_body = match Limited::new(req_body, max_size).collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(err) if err.is::<LengthLimitError>() => {
+                tracing::info!(
+                    "Rejecting {} webhook request: body exceeds {} bytes",
+                    self.scheme.as_str(),
+                    self.max_size
+                );
+                return Ok(Err(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+            Err(err) => return Err(anyhow::anyhow!(err).context("failed to read webhook body")),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        if let Err(reason) = self.check(secret.as_bytes(), &parts.headers, &req_body, now) {
+            tracing::info!(
+                "Rejecting {} webhook request: {reason}",
+                self.scheme.as_str()
+            );
+            return Ok(Err(StatusCode::UNAUTHORIZED));
+        }
+        parts.headers.insert(
+            VERIFIED_HEADER,
+            HeaderValue::from_static(self.scheme.as_str()),
+        );
+        Ok(Ok(Request::from_parts(parts, body::full(req_body))))
+    }
+
+    /// Checks the signature of a request at time `now` since the Unix epoch.
+    fn check(
+        &self,
+        secret: &[u8],
+        headers: &HeaderMap,
+        body: &[u8],
+        now: Duration,
+    ) -> Result<(), &'static str> {
+        match self.scheme {
+            WebhookScheme::Github => {
+                let signature = header_str(headers, "x-hub-signature-256")?
+                    .strip_prefix("sha256=")
+                    .ok_or("malformed signature")?;
+                verify_hmac(secret, &[body], &decode_hex(signature)?)
+            }
+            WebhookScheme::Stripe => {
+                // t=<timestamp>,v1=<signature>[,v1=<signature>...]
+                let header = header_str(headers, "stripe-signature")?;
+                let mut timestamp = None;
+                let mut signatures = vec![];
+                for item in header.split(',') {
+                    match item.trim().split_once('=') {
+                        Some(("t", t)) => timestamp = Some(t),
+                        Some(("v1", signature)) => signatures.push(signature),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or("missing timestamp")?;
+                self.check_timestamp(timestamp, now)?;
+                let message: [&[u8]; 3] = [timestamp.as_bytes(), b".", body];
+                // The sender signs with each of its active secrets
+                let valid = signatures
+                    .into_iter()
+                    .filter_map(|signature| decode_hex(signature).ok())
+                    .any(|signature| verify_hmac(secret, &message, &signature).is_ok());
+                if valid {
+                    Ok(())
+                } else {
+                    Err("signature mismatch")
+                }
+            }
+            WebhookScheme::Slack => {
+                let timestamp = header_str(headers, "x-slack-request-timestamp")?;
+                self.check_timestamp(timestamp, now)?;
+                let signature = header_str(headers, "x-slack-signature")?
+                    .strip_prefix("v0=")
+                    .ok_or("malformed signature")?;
+                let message: [&[u8]; 4] = [b"v0:", timestamp.as_bytes(), b":", body];
+                verify_hmac(secret, &message, &decode_hex(signature)?)
+            }
+            WebhookScheme::HmacSha256 => {
+                let header = self.header.as_ref().expect("checked in Webhook::new");
+                let signature = header_str(headers, header.as_str())?
+                    .strip_prefix(self.prefix.as_str())
+                    .ok_or("malformed signature")?;
+                let signature = match self.encoding {
+                    WebhookSignatureEncoding::Hex => decode_hex(signature)?,
+                    WebhookSignatureEncoding::Base64 => base64::engine::general_purpose::STANDARD
+                        .decode(signature)
+                        .map_err(|_| "malformed signature")?,
+                };
+                verify_hmac(secret, &[body], &signature)
+            }
+        }
+    }
+
+    /// Checks that a signed Unix timestamp is within the tolerance of `now`,
+    /// so that captured requests can't be replayed later.
+    fn check_timestamp(&self, timestamp: &str, now: Duration) -> Result<(), &'static str> {
+        let timestamp = Duration::from_secs(timestamp.parse().map_err(|_| "malformed timestamp")?);
+        if now.abs_diff(timestamp) > self.tolerance {
+            return Err("timestamp outside tolerance");
+        }
+        Ok(())
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, &'static str> {
+    headers
+        .get(name)
+        .ok_or("missing signature header")?
+        .to_str()
+        .map_err(|_| "malformed signature header")
+}
+
+fn decode_hex(signature: &str) -> Result<Vec<u8>, &'static str> {
+    hex::decode(signature).map_err(|_| "malformed signature")
+}
+
+/// Verifies, in constant time, the HMAC of the concatenated `message`.
+fn verify_hmac(secret: &[u8], message: &[&[u8]], signature: &[u8]) -> Result<(), &'static str> {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any size");
+    for part in message {
+        mac.update(part);
+    }
+    mac.verify_slice(signature)
+        .map_err(|_| "signature mismatch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+    const NOW: Duration = Duration::from_secs(1700000000);
+
+    fn config(scheme: WebhookScheme) -> WebhookConfig {
+        WebhookConfig {
+            scheme,
+            secret: "{{ secret }}".into(),
+            header: None,
+            prefix: None,
+            encoding: WebhookSignatureEncoding::Hex,
+            tolerance_secs: None,
+            max_size: None,
+        }
+    }
+
+    fn sign(message: &[u8]) -> Vec<u8> {
+        let mut mac = HmacSha256::new_from_slice(SECRET).unwrap();
+        mac.update(message);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn headers(headers: &[(&'static str, String)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| (HeaderName::from_static(name), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn verifies_github_signatures() {
+        let webhook = Webhook::new(&config(WebhookScheme::Github)).unwrap();
+        let signature = format!("sha256={}", hex::encode(sign(b"body")));
+        let valid = headers(&[("x-hub-signature-256", signature)]);
+        assert_eq!(webhook.check(SECRET, &valid, b"body", NOW), Ok(()));
+        assert!(webhook.check(SECRET, &valid, b"forged", NOW).is_err());
+        assert!(webhook
+            .check(SECRET, &HeaderMap::new(), b"body", NOW)
+            .is_err());
+    }
+
+    #[test]
+    fn verifies_stripe_signatures_within_tolerance() {
+        let webhook = Webhook::new(&config(WebhookScheme::Stripe)).unwrap();
+        let signature = hex::encode(sign(b"1700000000.body"));
+        let valid = headers(&[(
+            "stripe-signature",
+            format!("t=1700000000,v1=00,v1={signature}"),
+        )]);
+        assert_eq!(webhook.check(SECRET, &valid, b"body", NOW), Ok(()));
+        assert!(webhook.check(SECRET, &valid, b"forged", NOW).is_err());
+        let later = NOW + Duration::from_secs(301);
+        assert_eq!(
+            webhook.check(SECRET, &valid, b"body", later),
+            Err("timestamp outside tolerance")
+        );
+    }
+
+    #[test]
+    fn verifies_slack_signatures() {
+        let webhook = Webhook::new(&config(WebhookScheme::Slack)).unwrap();
+        let signature = hex::encode(sign(b"v0:1700000000:body"));
+        let valid = headers(&[
+            ("x-slack-request-timestamp", "1700000000".into()),
+            ("x-slack-signature", format!("v0={signature}")),
+        ]);
+        assert_eq!(webhook.check(SECRET, &valid, b"body", NOW), Ok(()));
+        assert!(webhook.check(SECRET, &valid, b"forged", NOW).is_err());
+    }
+
+    #[test]
+    fn verifies_generic_hmac_signatures() {
+        let webhook = Webhook::new(&WebhookConfig {
+            header: Some("x-signature".into()),
+            prefix: Some("sig=".into()),
+            encoding: WebhookSignatureEncoding::Base64,
+            ..config(WebhookScheme::HmacSha256)
+        })
+        .unwrap();
+        let signature = base64::engine::general_purpose::STANDARD.encode(sign(b"body"));
+        let valid = headers(&[("x-signature", format!("sig={signature}"))]);
+        assert_eq!(webhook.check(SECRET, &valid, b"body", NOW), Ok(()));
+        assert!(webhook.check(b"other", &valid, b"body", NOW).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_over_the_limit() {
+        let webhook = Webhook::new(&WebhookConfig {
+            max_size: Some(4),
+            ..config(WebhookScheme::Github)
+        })
+        .unwrap();
+        let signature = format!("sha256={}", hex::encode(sign(b"body")));
+        let req = |body: &'static [u8]| {
+            Request::builder()
+                .header("x-hub-signature-256", &signature)
+                .body(body::full(body.into()))
+                .unwrap()
+        };
+
+        let verified = webhook.verify("secret", req(b"body")).await.unwrap();
+        assert!(verified.is_ok());
+        let rejected = webhook.verify("secret", req(b"body!")).await.unwrap();
+        assert_eq!(rejected.unwrap_err(), StatusCode::PAYLOAD_TOO_LARGE);
+        let forged = webhook.verify("secret", req(b"bod")).await.unwrap();
+        assert_eq!(forged.unwrap_err(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn generic_scheme_requires_header() {
+        assert!(Webhook::new(&config(WebhookScheme::HmacSha256)).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use anyhow::Context;
+use futures::{StreamExt, TryFutureExt};
+use serde::Deserialize;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_key_value::KeyValueFactor;
+use spin_factor_messaging::{Message, MessageBroker, MessagingFactor};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{RuntimeFactors, RuntimeFactorsInstanceState};
+use spin_trigger::{
+    cli::NoCliArgs,
+    dedupe::{DedupeConfig, Deduplicator},
+    App, Trigger, TriggerApp,
+};
+use spin_world::exports::wasi::messaging::incoming_handler;
+use tracing::{instrument, Level};
+
+/// Delivers messages from the brokers configured in runtime config to
+/// components exporting `wasi:messaging/incoming-handler`.
+///
+/// Messages are delivered at most once, in the order each subscription
+/// receives them. A message is handed to every component subscribed to its
+/// topic. Components whose trigger sets `dedupe` skip messages whose ID, taken
+/// from their metadata, they have already processed, so that a message
+/// redelivered by the broker is only handled once.
+pub struct MessagingTrigger;
+
+/// Messaging trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Label of the message broker to subscribe through
+    broker: String,
+    /// Topics to subscribe to
+    topics: Vec<String>,
+    /// Skip messages the component has already processed
+    dedupe: Option<DedupeConfig>,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for MessagingTrigger {
+    const TYPE: &'static str = "messaging";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("MessagingTrigger depends on VariablesFactor")?;
+        let messaging = trigger_app
+            .configured_app()
+            .app_state::<MessagingFactor>()
+            .context("MessagingTrigger depends on MessagingFactor")?;
+
+        // Maps <broker label> -> <topic> -> <component IDs>
+        let mut broker_topic_components: HashMap<String, TopicComponents> = HashMap::new();
+        // Maps <broker label> -> <component ID> -> <deduplicator>
+        let mut broker_dedupers: HashMap<String, Dedupers> = HashMap::new();
+
+        // Resolve trigger configs before starting any subscriptions
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        for (_, config) in trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+        {
+            let component_id = config.component;
+            anyhow::ensure!(
+                messaging.broker(&config.broker).is_some(),
+                "unknown message broker {:?} for messaging trigger of component {component_id}",
+                config.broker
+            );
+            if let Some(dedupe) = config.dedupe {
+                let store_manager = trigger_app
+                    .configured_app()
+                    .app_state::<KeyValueFactor>()
+                    .context("MessagingTrigger dedupe depends on KeyValueFactor")?
+                    .store_manager();
+                let deduplicator = Deduplicator::new(store_manager, dedupe).with_context(|| {
+                    format!("invalid messaging trigger dedupe for component {component_id}")
+                })?;
+                broker_dedupers
+                    .entry(config.broker.clone())
+                    .or_default()
+                    .insert(component_id.clone(), deduplicator);
+            }
+            let topic_components = broker_topic_components.entry(config.broker).or_default();
+            for topic_expr in config.topics {
+                let topic = app_variables
+                    .resolve_expression(topic_expr.clone())
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "failed to resolve messaging trigger topic {topic_expr:?} for component {component_id}"
+                        )
+                    })?;
+                topic_components
+                    .entry(topic)
+                    .or_default()
+                    .push(component_id.clone());
+            }
+        }
+
+        // Start subscriptions
+        let trigger_app = Arc::new(trigger_app);
+        let mut subscription_tasks = Vec::new();
+        for (label, topic_components) in broker_topic_components {
+            let broker = messaging
+                .broker(&label)
+                .expect("broker labels were checked above");
+            let dedupers = broker_dedupers.remove(&label).unwrap_or_default();
+            for (topic, component_ids) in topic_components {
+                println!(
+                    "Subscribed to {label}/{topic}: [{}]",
+                    component_ids.join(",")
+                );
+                let subscription = Subscription {
+                    trigger_app: trigger_app.clone(),
+                    broker: broker.clone(),
+                    label: label.clone(),
+                    topic,
+                    component_ids,
+                    dedupers: dedupers.clone(),
+                };
+                subscription_tasks.push(tokio::spawn(subscription.run()));
+            }
+        }
+        if subscription_tasks.is_empty() {
+            return Ok(());
+        }
+
+        // Wait for any task to complete
+        let (res, _, _) = futures::future::select_all(subscription_tasks).await;
+        res?
+    }
+}
+
+/// Maps <topic> -> <component IDs>
+type TopicComponents = HashMap<String, Vec<String>>;
+
+/// Maps <component ID> -> <deduplicator>
+type Dedupers = HashMap<String, Deduplicator>;
+
+/// Delivers the messages sent to a single topic of a single broker.
+struct Subscription<F: RuntimeFactors> {
+    trigger_app: Arc<TriggerApp<MessagingTrigger, F>>,
+    broker: Arc<dyn MessageBroker>,
+    label: String,
+    topic: String,
+    component_ids: Vec<String>,
+    dedupers: Dedupers,
+}
+
+impl<F: RuntimeFactors> Subscription<F> {
+    async fn run(self) -> anyhow::Result<()> {
+        let label = &self.label;
+        let topic = &self.topic;
+        tracing::info!("Subscribing to {topic:?} on message broker {label:?}");
+        let mut messages = self.broker.subscribe(topic).await.with_context(|| {
+            format!(
+                "messaging trigger failed to subscribe to {topic:?} on message broker {label:?}"
+            )
+        })?;
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(message) => self.handle_message(message).await,
+                Err(err) => {
+                    tracing::error!("Error receiving message from {label}/{topic}: {err:?}")
+                }
+            }
+        }
+        Err(anyhow::anyhow!(
+            "subscription to {topic:?} on message broker {label:?} ended"
+        ))
+    }
+
+    #[instrument(name = "spin_trigger_messaging.handle_message", skip_all, fields(
+        otel.name = format!("{} receive", self.topic),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.destination.name = %self.topic
+    ))]
+    async fn handle_message(&self, message: Message) {
+        tracing::trace!(broker = %self.label, topic = %self.topic, "Received message");
+        let dispatch_futures = self.component_ids.iter().map(|component_id| {
+            let message = message.clone();
+            async move {
+                let handle = handle_message(
+                    &self.trigger_app,
+                    &self.broker,
+                    component_id,
+                    &self.topic,
+                    message.clone(),
+                );
+                match self.dedupers.get(component_id) {
+                    Some(dedupe) => {
+                        let id = dedupe.message_id(&message.metadata, None);
+                        let scope = format!("{component_id}:{}/{}", self.label, self.topic);
+                        dedupe.run(&scope, id, handle).await
+                    }
+                    None => handle.await,
+                }
+            }
+            .inspect_err(move |err| {
+                tracing::info!("Component {component_id} handler failed: {err}");
+            })
+        });
+        futures::future::join_all(dispatch_futures).await;
+    }
+}
+
+/// Invokes a component's incoming message handler with a message received
+/// from `broker` on `topic`.
+#[instrument(name = "spin_trigger_messaging.dispatch_handler", skip_all, err(level = Level::INFO), fields(
+    otel.name = format!("execute_wasm_component {component_id}"),
+    component_id = component_id
+))]
+pub async fn handle_message<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<MessagingTrigger, F>,
+    broker: &Arc<dyn MessageBroker>,
+    component_id: &str,
+    topic: &str,
+    message: Message,
+) -> anyhow::Result<()> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "messaging",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let start = Instant::now();
+    let mut audit = None;
+    let result = async {
+        let mut instance_builder = trigger_app.prepare(component_id).await?;
+        audit = instance_builder
+            .factor_builder::<AuditFactor>()
+            .and_then(|audit| audit.handle());
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+
+        let message = store
+            .data_mut()
+            .factors_instance_state_mut()
+            .get::<MessagingFactor>()
+            .context("missing MessagingFactor")?
+            .push_received_message(message, broker.clone())
+            .map_err(|e| anyhow::anyhow!("failed to pass message to component: {e}"))?;
+
+        let pre = instance.instance_pre(&store);
+        let guest_indices = incoming_handler::GuestIndices::new(&pre)?;
+        let guest = guest_indices.load(&mut store, &instance)?;
+
+        guest
+            .call_handle(&mut store, message)
+            .await?
+            .map_err(|e| anyhow::anyhow!("messaging handler returned an error: {e}"))
+    }
+    .await;
+    spin_telemetry::metrics::histogram!(
+        spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+        trigger_type = "messaging",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+    if let Some(audit) = audit {
+        audit.finish("messaging", topic, AuditOutcome::from_result(&result));
+    }
+    result
+}
@@ -0,0 +1,350 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use anyhow::Context;
+use futures::TryFutureExt;
+use serde::Deserialize;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_outbound_pg::{
+    client::{server_addr, ClientFactory, PooledTokioClientFactory},
+    OutboundPgFactor,
+};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
+use spin_world::exports::spin::postgres4_0_0::inbound_postgres;
+use spin_world::spin::postgres4_0_0::postgres::Notification;
+use tracing::{instrument, Level};
+
+pub struct PostgresTrigger;
+
+/// Postgres trigger metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerMetadata {
+    address: String,
+}
+
+/// Postgres trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Channel to listen on
+    channel: String,
+    /// Optionally override address for trigger
+    address: Option<String>,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for PostgresTrigger {
+    const TYPE: &'static str = "postgres";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: spin_trigger::TriggerApp<Self, F>) -> anyhow::Result<()> {
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("PostgresTrigger depends on VariablesFactor")?;
+        // Listeners are opened with the runtime config's TLS settings
+        let client_factory = trigger_app
+            .configured_app()
+            .app_state::<OutboundPgFactor>()
+            .context("PostgresTrigger depends on OutboundPgFactor")?
+            .client_factory()
+            .clone();
+
+        let app = trigger_app.app();
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        let metadata = app
+            .get_trigger_metadata::<TriggerMetadata>(trigger_type)?
+            .unwrap_or_default();
+        let default_address_expr = &metadata.address;
+        let default_address = app_variables
+            .resolve_expression(default_address_expr.clone())
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to resolve postgres trigger default address {default_address_expr:?}"
+                )
+            })?;
+
+        // Resolve trigger configs before starting any listeners
+        let mut subscriptions = Vec::new();
+        for (_, config) in app
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>()
+        {
+            let component_id = config.component;
+
+            let address_expr = config.address.as_ref().unwrap_or(&default_address);
+            let address = app_variables
+                .resolve_expression(address_expr.clone())
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to resolve postgres trigger address {address_expr:?} for component {component_id}"
+                    )
+                })?;
+
+            let channel_expr = &config.channel;
+            let channel = app_variables
+                .resolve_expression(channel_expr.clone())
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to resolve postgres trigger channel {channel_expr:?} for component {component_id}"
+                    )
+                })?;
+
+            subscriptions.push(Subscription {
+                address,
+                channel,
+                component_id,
+            });
+        }
+
+        // Start listener(s)
+        let trigger_app = Arc::new(trigger_app);
+        let mut listener_tasks = Vec::new();
+        for (address, channel_components) in group_by_server(subscriptions) {
+            let listener = Listener::new(
+                address,
+                trigger_app.clone(),
+                client_factory.clone(),
+                channel_components,
+            );
+            let task = tokio::spawn(listener.run_listener());
+            listener_tasks.push(task);
+        }
+
+        // Wait for any task to complete
+        let (res, _, _) = futures::future::select_all(listener_tasks).await;
+        res?
+    }
+}
+
+/// A component listening to a channel on a server.
+struct Subscription {
+    address: String,
+    channel: String,
+    component_id: String,
+}
+
+/// Maps <channel> -> <component IDs>
+type ChannelComponents = HashMap<String, Vec<String>>;
+
+/// Groups subscriptions by server address, so that each server is listened
+/// to on one connection.
+fn group_by_server(
+    subscriptions: impl IntoIterator<Item = Subscription>,
+) -> HashMap<String, ChannelComponents> {
+    let mut server_channel_components: HashMap<String, ChannelComponents> = HashMap::new();
+    for subscription in subscriptions {
+        server_channel_components
+            .entry(subscription.address)
+            .or_default()
+            .entry(subscription.channel)
+            .or_default()
+            .push(subscription.component_id);
+    }
+    server_channel_components
+}
+
+/// Listens to channels on a single Postgres server.
+struct Listener<F: RuntimeFactors> {
+    address: String,
+    /// The address without credentials, for logging
+    server_addr: String,
+    trigger_app: Arc<TriggerApp<PostgresTrigger, F>>,
+    client_factory: Arc<PooledTokioClientFactory>,
+    channel_components: ChannelComponents,
+}
+
+impl<F: RuntimeFactors> Listener<F> {
+    fn new(
+        address: String,
+        trigger_app: Arc<TriggerApp<PostgresTrigger, F>>,
+        client_factory: Arc<PooledTokioClientFactory>,
+        channel_components: ChannelComponents,
+    ) -> Self {
+        Self {
+            server_addr: server_addr(&address),
+            address,
+            trigger_app,
+            client_factory,
+            channel_components,
+        }
+    }
+
+    async fn run_listener(self) -> anyhow::Result<()> {
+        let server_addr = &self.server_addr;
+
+        tracing::info!("Connecting to Postgres server at {server_addr}");
+        let channels = self.channel_components.keys().cloned().collect::<Vec<_>>();
+        let mut listener = self
+            .client_factory
+            .listen(&self.address, &[], &channels)
+            .await
+            .with_context(|| format!("Postgres trigger failed to listen on {server_addr}"))?;
+
+        println!("Active Channels on {server_addr}:");
+        for (channel, components) in &self.channel_components {
+            tracing::info!("Listening to {channel:?} on {server_addr}");
+            println!("\t{server_addr}/{channel}: [{}]", components.join(","));
+        }
+
+        while let Some(notification) = listener
+            .next()
+            .await
+            .with_context(|| format!("Postgres trigger lost connection to {server_addr}"))?
+        {
+            let notification = Notification {
+                channel: notification.channel().to_owned(),
+                payload: notification.payload().to_owned(),
+                process_id: notification.process_id(),
+            };
+            if let Err(err) = self.handle_notification(notification).await {
+                tracing::error!("Error handling notification from {server_addr}: {err}");
+            }
+        }
+        Err(anyhow::anyhow!("disconnected from {server_addr}"))
+    }
+
+    #[instrument(name = "spin_trigger_postgres.handle_notification", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", notification.channel),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "postgresql"
+    ))]
+    async fn handle_notification(&self, notification: Notification) -> anyhow::Result<()> {
+        let server_addr = &self.server_addr;
+        let channel = &notification.channel;
+        tracing::trace!(%server_addr, %channel, "Received notification");
+
+        let Some(component_ids) = self.channel_components.get(channel) else {
+            anyhow::bail!("notification from unexpected channel {channel:?}");
+        };
+
+        let dispatch_futures = component_ids.iter().map(|component_id| {
+            tracing::trace!("Executing Postgres component {component_id}");
+            self.dispatch_handler(&notification, component_id)
+                .inspect_err(move |err| {
+                    tracing::info!("Component {component_id} handler failed: {err}");
+                })
+        });
+        futures::future::join_all(dispatch_futures).await;
+
+        Ok(())
+    }
+
+    #[instrument(name = "spin_trigger_postgres.dispatch_handler", skip_all, fields(
+        otel.name = format!("execute_wasm_component {component_id}"),
+        component_id = component_id
+    ))]
+    async fn dispatch_handler(
+        &self,
+        notification: &Notification,
+        component_id: &str,
+    ) -> anyhow::Result<()> {
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "postgres",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let start = Instant::now();
+        let mut audit = None;
+        let result = async {
+            let mut instance_builder = self.trigger_app.prepare(component_id).await?;
+            audit = instance_builder
+                .factor_builder::<AuditFactor>()
+                .and_then(|audit| audit.handle());
+            let (instance, mut store) = instance_builder.instantiate(()).await?;
+
+            let pre = instance.instance_pre(&store);
+            let guest_indices = inbound_postgres::GuestIndices::new(&pre)?;
+            let guest = guest_indices.load(&mut store, &instance)?;
+
+            guest
+                .call_handle_notification(&mut store, notification)
+                .await?
+                .map_err(|e| anyhow::anyhow!("Postgres handler returned an error: {e}"))
+        }
+        .await;
+        spin_telemetry::metrics::histogram!(
+            spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            trigger_type = "postgres",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+        if let Some(audit) = audit {
+            audit.finish(
+                "postgres",
+                &notification.channel,
+                AuditOutcome::from_result(&result),
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(address: &str, channel: &str, component_id: &str) -> Subscription {
+        Subscription {
+            address: address.into(),
+            channel: channel.into(),
+            component_id: component_id.into(),
+        }
+    }
+
+    #[test]
+    fn subscriptions_share_a_connection_per_server() {
+        let servers = group_by_server([
+            subscription("host=db1", "orders", "billing"),
+            subscription("host=db1", "orders", "shipping"),
+            subscription("host=db1", "refunds", "billing"),
+            subscription("host=db2", "orders", "audit"),
+        ]);
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers["host=db1"]["orders"], ["billing", "shipping"]);
+        assert_eq!(servers["host=db1"]["refunds"], ["billing"]);
+        assert_eq!(servers["host=db2"]["orders"], ["audit"]);
+    }
+
+    #[test]
+    fn trigger_configs_are_parsed() {
+        let config: TriggerConfig = toml::from_str(
+            r#"
+            component = "billing"
+            channel = "orders"
+            address = "host=db1"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.channel, "orders");
+        assert_eq!(config.address.as_deref(), Some("host=db1"));
+
+        let metadata: TriggerMetadata = toml::from_str(r#"address = "host=db""#).unwrap();
+        assert_eq!(metadata.address, "host=db");
+
+        toml::from_str::<TriggerConfig>(
+            r#"
+            component = "billing"
+            channels = ["orders"]
+            "#,
+        )
+        .unwrap_err();
+    }
+}
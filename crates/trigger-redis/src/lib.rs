@@ -1,16 +1,39 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc, time::Instant};
 
 use anyhow::Context;
 use futures::{StreamExt, TryFutureExt};
-use redis::{Client, Msg};
+use redis::{
+    streams::{StreamId, StreamRangeReply, StreamReadOptions, StreamReadReply},
+    AsyncCommands, Client, Msg,
+};
 use serde::Deserialize;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_key_value::KeyValueFactor;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
-use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
-use spin_world::exports::fermyon::spin::inbound_redis;
+use spin_trigger::{
+    cli::NoCliArgs,
+    core_dump::CoreDumper,
+    dedupe::{DedupeConfig, Deduplicator},
+    recording::{RecordedPayload, Recorder, Recording},
+    App, Trigger, TriggerApp,
+};
+use spin_world::exports::{fermyon::spin::inbound_redis as v1, spin::redis::inbound_redis};
 use tracing::{instrument, Level};
 
-pub struct RedisTrigger;
+/// How long a single `XREAD` blocks while waiting for new stream entries.
+const READ_POLL_INTERVAL_MS: usize = 1000;
+
+/// The stream entry field holding the message payload. Other fields are
+/// passed to the handler as headers.
+const PAYLOAD_FIELD: &str = "payload";
+
+pub struct RedisTrigger {
+    /// Records each message, if recording is enabled.
+    recorder: Option<Recorder>,
+    /// Captures core dumps of traps, if enabled.
+    core_dumper: Option<CoreDumper>,
+}
 
 /// Redis trigger metadata.
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -26,9 +49,101 @@ struct TriggerConfig {
     /// Component ID to invoke
     component: String,
     /// Channel to subscribe to
-    channel: String,
+    channel: Option<String>,
+    /// Channel pattern to subscribe to
+    pattern: Option<String>,
+    /// Stream to read entries from
+    stream: Option<String>,
     /// Optionally override address for trigger
     address: Option<String>,
+    /// Skip messages the component has already processed
+    dedupe: Option<DedupeConfig>,
+}
+
+impl TriggerConfig {
+    /// Returns where the trigger receives messages from, with its name still
+    /// an unresolved expression.
+    fn source(&self) -> anyhow::Result<Source> {
+        match (&self.channel, &self.pattern, &self.stream) {
+            (Some(channel), None, None) => Ok(Source::Channel(channel.clone())),
+            (None, Some(pattern), None) => Ok(Source::Pattern(pattern.clone())),
+            (None, None, Some(stream)) => Ok(Source::Stream(stream.clone())),
+            _ => anyhow::bail!(
+                "Redis trigger for component {} must set exactly one of 'channel', 'pattern' or 'stream'",
+                self.component
+            ),
+        }
+    }
+}
+
+/// Where a Redis trigger receives messages from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Source {
+    /// A pub/sub channel.
+    Channel(String),
+    /// The pub/sub channels matching a pattern.
+    Pattern(String),
+    /// A stream, from which entries added after the trigger starts are read.
+    Stream(String),
+}
+
+impl Source {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Channel(_) => "channel",
+            Self::Pattern(_) => "pattern",
+            Self::Stream(_) => "stream",
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Self::Channel(name) | Self::Pattern(name) | Self::Stream(name) => name,
+        }
+    }
+
+    fn with_name(&self, name: String) -> Self {
+        match self {
+            Self::Channel(_) => Self::Channel(name),
+            Self::Pattern(_) => Self::Pattern(name),
+            Self::Stream(_) => Self::Stream(name),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stream(name) => write!(f, "{name} (stream)"),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// A message received by the Redis trigger.
+#[derive(Clone, Debug, Default)]
+pub struct RedisMessage {
+    /// The channel the message was published to, or the stream it was added to.
+    pub channel: String,
+    /// The pattern the channel matched, if subscribed to by pattern.
+    pub pattern: Option<String>,
+    /// The ID of the stream entry, if read from a stream.
+    pub id: Option<String>,
+    /// Headers supplied by the publisher.
+    pub headers: Vec<(String, String)>,
+    pub payload: Vec<u8>,
+}
+
+impl From<&RedisMessage> for inbound_redis::Message {
+    fn from(message: &RedisMessage) -> Self {
+        Self {
+            channel: message.channel.clone(),
+            pattern: message.pattern.clone(),
+            id: message.id.clone(),
+            headers: message.headers.clone(),
+            payload: message.payload.clone(),
+        }
+    }
 }
 
 impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
@@ -39,7 +154,18 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
     type InstanceState = ();
 
     fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
-        Ok(Self)
+        Ok(Self {
+            recorder: None,
+            core_dumper: None,
+        })
+    }
+
+    fn record_invocations(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    fn capture_core_dumps(&mut self, core_dumper: CoreDumper) {
+        self.core_dumper = Some(core_dumper);
     }
 
     async fn run(self, trigger_app: spin_trigger::TriggerApp<Self, F>) -> anyhow::Result<()> {
@@ -61,8 +187,10 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
                 format!("failed to resolve redis trigger default address {default_address_expr:?}")
             })?;
 
-        // Maps <server address> -> <channel> -> <component IDs>
-        let mut server_channel_components: HashMap<String, ChannelComponents> = HashMap::new();
+        // Maps <server address> -> <source> -> <component IDs>
+        let mut server_source_components: HashMap<String, SourceComponents> = HashMap::new();
+        // Maps <server address> -> (<source>, <component ID>) -> <deduplicator>
+        let mut server_dedupers: HashMap<String, Dedupers> = HashMap::new();
 
         // Resolve trigger configs before starting any subscribers
         for (_, config) in app
@@ -70,6 +198,7 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
             .into_iter()
             .collect::<Vec<_>>()
         {
+            let source = config.source()?;
             let component_id = config.component;
 
             let address_expr = config.address.as_ref().unwrap_or(&default_address);
@@ -82,20 +211,36 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
                     )
                 })?;
 
-            let channel_expr = &config.channel;
-            let channel = app_variables
-                .resolve_expression(channel_expr.clone())
+            let name_expr = source.name();
+            let name = app_variables
+                .resolve_expression(name_expr.to_owned())
                 .await
                 .with_context(|| {
                     format!(
-                        "failed to resolve redis trigger channel {channel_expr:?} for component {component_id}"
+                        "failed to resolve redis trigger {} {name_expr:?} for component {component_id}",
+                        source.kind()
                     )
                 })?;
 
-            server_channel_components
+            let source = source.with_name(name);
+            if let Some(dedupe) = config.dedupe {
+                let store_manager = trigger_app
+                    .configured_app()
+                    .app_state::<KeyValueFactor>()
+                    .context("RedisTrigger dedupe depends on KeyValueFactor")?
+                    .store_manager();
+                let deduplicator = Deduplicator::new(store_manager, dedupe).with_context(|| {
+                    format!("invalid redis trigger dedupe for component {component_id}")
+                })?;
+                server_dedupers
+                    .entry(address.clone())
+                    .or_default()
+                    .insert((source.clone(), component_id.clone()), deduplicator);
+            }
+            server_source_components
                 .entry(address)
                 .or_default()
-                .entry(channel)
+                .entry(source)
                 .or_default()
                 .push(component_id);
         }
@@ -103,8 +248,16 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
         // Start subscriber(s)
         let trigger_app = Arc::new(trigger_app);
         let mut subscriber_tasks = Vec::new();
-        for (address, channel_components) in server_channel_components {
-            let subscriber = Subscriber::new(address, trigger_app.clone(), channel_components)?;
+        for (address, source_components) in server_source_components {
+            let dedupers = server_dedupers.remove(&address).unwrap_or_default();
+            let subscriber = Subscriber::new(
+                address,
+                trigger_app.clone(),
+                source_components,
+                dedupers,
+                self.recorder.clone(),
+                self.core_dumper.clone(),
+            )?;
             let task = tokio::spawn(subscriber.run_listener());
             subscriber_tasks.push(task);
         }
@@ -115,32 +268,59 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
     }
 }
 
-/// Maps <channel> -> <component IDs>
-type ChannelComponents = HashMap<String, Vec<String>>;
+/// Maps <source> -> <component IDs>
+type SourceComponents = HashMap<Source, Vec<String>>;
+
+/// Maps (<source>, <component ID>) -> <deduplicator>
+type Dedupers = HashMap<(Source, String), Deduplicator>;
 
-/// Subscribes to channels from a single Redis server.
+/// Subscribes to channels and reads streams from a single Redis server.
 struct Subscriber<F: RuntimeFactors> {
     client: Client,
     trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
-    channel_components: ChannelComponents,
+    source_components: SourceComponents,
+    dedupers: Dedupers,
+    recorder: Option<Recorder>,
+    core_dumper: Option<CoreDumper>,
 }
 
 impl<F: RuntimeFactors> Subscriber<F> {
     fn new(
         address: String,
         trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
-        channel_components: ChannelComponents,
+        source_components: SourceComponents,
+        dedupers: Dedupers,
+        recorder: Option<Recorder>,
+        core_dumper: Option<CoreDumper>,
     ) -> anyhow::Result<Self> {
         let client = Client::open(address)?;
         Ok(Self {
             client,
             trigger_app,
-            channel_components,
+            source_components,
+            dedupers,
+            recorder,
+            core_dumper,
         })
     }
 
     async fn run_listener(self) -> anyhow::Result<()> {
         let server_addr = &self.client.get_connection_info().addr;
+        println!("Active Channels on {server_addr}:");
+        tokio::try_join!(self.listen_pubsub(), self.read_streams())?;
+        Ok(())
+    }
+
+    /// Receives messages on the subscribed channels and patterns.
+    async fn listen_pubsub(&self) -> anyhow::Result<()> {
+        let server_addr = &self.client.get_connection_info().addr;
+        if self
+            .source_components
+            .keys()
+            .all(|source| matches!(source, Source::Stream(_)))
+        {
+            return Ok(());
+        }
 
         tracing::info!("Connecting to Redis server at {server_addr}");
         let mut pubsub = self
@@ -149,15 +329,24 @@ impl<F: RuntimeFactors> Subscriber<F> {
             .await
             .with_context(|| format!("Redis trigger failed to connect to {server_addr}"))?;
 
-        println!("Active Channels on {server_addr}:");
-
-        // Subscribe to channels
-        for (channel, components) in &self.channel_components {
-            tracing::info!("Subscribing to {channel:?} on {server_addr}");
-            pubsub.subscribe(channel).await.with_context(|| {
-                format!("Redis trigger failed to subscribe to channel {channel:?} on {server_addr}")
-            })?;
-            println!("\t{server_addr}/{channel}: [{}]", components.join(","));
+        // Subscribe to channels and patterns
+        for (source, components) in &self.source_components {
+            match source {
+                Source::Channel(channel) => {
+                    tracing::info!("Subscribing to {channel:?} on {server_addr}");
+                    pubsub.subscribe(channel).await.with_context(|| {
+                        format!("Redis trigger failed to subscribe to channel {channel:?} on {server_addr}")
+                    })?;
+                }
+                Source::Pattern(pattern) => {
+                    tracing::info!("Subscribing to pattern {pattern:?} on {server_addr}");
+                    pubsub.psubscribe(pattern).await.with_context(|| {
+                        format!("Redis trigger failed to subscribe to pattern {pattern:?} on {server_addr}")
+                    })?;
+                }
+                Source::Stream(_) => continue,
+            }
+            println!("\t{server_addr}/{source}: [{}]", components.join(","));
         }
 
         let mut message_stream = pubsub.on_message();
@@ -169,6 +358,74 @@ impl<F: RuntimeFactors> Subscriber<F> {
         Err(anyhow::anyhow!("disconnected from {server_addr}"))
     }
 
+    /// Reads the entries added to the streams after the trigger starts.
+    async fn read_streams(&self) -> anyhow::Result<()> {
+        let server_addr = &self.client.get_connection_info().addr;
+        let streams: Vec<&str> = self
+            .source_components
+            .keys()
+            .filter_map(|source| match source {
+                Source::Stream(stream) => Some(stream.as_str()),
+                _ => None,
+            })
+            .collect();
+        if streams.is_empty() {
+            return Ok(());
+        }
+
+        // Blocking reads hold up the connection, so streams are read on
+        // their own.
+        let mut connection = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .with_context(|| format!("Redis trigger failed to connect to {server_addr}"))?;
+
+        // Resolve the latest ID of each stream now so that nothing added
+        // between reads is skipped.
+        let mut last_ids = Vec::with_capacity(streams.len());
+        for stream in &streams {
+            let latest: StreamRangeReply = connection
+                .xrevrange_count(stream, "+", "-", 1)
+                .await
+                .with_context(|| {
+                    format!("Redis trigger failed to read stream {stream:?} on {server_addr}")
+                })?;
+            let last_id = latest
+                .ids
+                .into_iter()
+                .next()
+                .map_or_else(|| "0".to_owned(), |entry| entry.id);
+            last_ids.push(last_id);
+            let components = &self.source_components[&Source::Stream(stream.to_string())];
+            println!(
+                "\t{server_addr}/{stream} (stream): [{}]",
+                components.join(",")
+            );
+        }
+
+        let options = StreamReadOptions::default().block(READ_POLL_INTERVAL_MS);
+        loop {
+            let reply: Option<StreamReadReply> = connection
+                .xread_options(&streams, &last_ids, &options)
+                .await
+                .with_context(|| {
+                    format!("Redis trigger failed to read streams on {server_addr}")
+                })?;
+            for key in reply.unwrap_or_default().keys {
+                let Some(index) = streams.iter().position(|stream| *stream == key.key) else {
+                    continue;
+                };
+                for entry in key.ids {
+                    last_ids[index] = entry.id.clone();
+                    if let Err(err) = self.handle_stream_entry(&key.key, entry).await {
+                        tracing::error!("Error handling stream entry from {server_addr}: {err}");
+                    }
+                }
+            }
+        }
+    }
+
     #[instrument(name = "spin_trigger_redis.handle_message", skip_all, err(level = Level::INFO), fields(
         otel.name = format!("{} receive", msg.get_channel_name()),
         otel.kind = "consumer",
@@ -178,47 +435,231 @@ impl<F: RuntimeFactors> Subscriber<F> {
     async fn handle_message(&self, msg: Msg) -> anyhow::Result<()> {
         let server_addr = &self.client.get_connection_info().addr;
         let channel = msg.get_channel_name();
-        tracing::trace!(%server_addr, %channel, "Received message");
+        let pattern: Option<String> = msg.from_pattern().then(|| msg.get_pattern()).transpose()?;
+        tracing::trace!(%server_addr, %channel, ?pattern, "Received message");
 
-        let Some(component_ids) = self.channel_components.get(channel) else {
-            anyhow::bail!("message from unexpected channel {channel:?}");
+        let source = match &pattern {
+            Some(pattern) => Source::Pattern(pattern.clone()),
+            None => Source::Channel(channel.to_owned()),
+        };
+        let Some(component_ids) = self.source_components.get(&source) else {
+            anyhow::bail!(
+                "message from unexpected {} {:?}",
+                source.kind(),
+                source.name()
+            );
         };
 
+        // Continue the publisher's trace, if it embedded one in the message
+        let (headers, payload) =
+            spin_telemetry::extract_trace_context_from_payload(msg.get_payload_bytes());
+        let message = RedisMessage {
+            channel: channel.to_owned(),
+            pattern,
+            id: None,
+            headers,
+            payload: payload.to_vec(),
+        };
+        self.dispatch(&source, component_ids, &message).await;
+        Ok(())
+    }
+
+    #[instrument(name = "spin_trigger_redis.handle_stream_entry", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{stream} receive"),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "redis",
+        messaging.message.id = %entry.id
+    ))]
+    async fn handle_stream_entry(&self, stream: &str, entry: StreamId) -> anyhow::Result<()> {
+        let source = Source::Stream(stream.to_owned());
+        let Some(component_ids) = self.source_components.get(&source) else {
+            anyhow::bail!("entry from unexpected stream {stream:?}");
+        };
+
+        let mut headers = vec![];
+        let mut payload = vec![];
+        for (field, value) in &entry.map {
+            if field == PAYLOAD_FIELD {
+                payload = redis::from_redis_value(value)
+                    .with_context(|| format!("invalid {PAYLOAD_FIELD:?} field"))?;
+            } else if let Ok(value) = redis::from_redis_value::<String>(value) {
+                headers.push((field.clone(), value));
+            }
+        }
+        headers.sort();
+        let message = RedisMessage {
+            channel: stream.to_owned(),
+            pattern: None,
+            id: Some(entry.id),
+            headers,
+            payload,
+        };
+        self.dispatch(&source, component_ids, &message).await;
+        Ok(())
+    }
+
+    /// Invokes each component's handler with the message.
+    async fn dispatch(&self, source: &Source, component_ids: &[String], message: &RedisMessage) {
         let dispatch_futures = component_ids.iter().map(|component_id| {
             tracing::trace!("Executing Redis component {component_id}");
-            self.dispatch_handler(&msg, component_id)
+            self.dispatch_handler(source, message, component_id)
                 .inspect_err(move |err| {
                     tracing::info!("Component {component_id} handler failed: {err}");
                 })
         });
         futures::future::join_all(dispatch_futures).await;
-
-        Ok(())
     }
 
-    async fn dispatch_handler(&self, msg: &Msg, component_id: &str) -> anyhow::Result<()> {
-        spin_telemetry::metrics::monotonic_counter!(
-            spin.request_count = 1,
-            trigger_type = "redis",
-            app_id = self.trigger_app.app().id(),
-            component_id = component_id
+    #[instrument(name = "spin_trigger_redis.dispatch_handler", skip_all, fields(
+        otel.name = format!("execute_wasm_component {component_id}"),
+        component_id = component_id
+    ))]
+    async fn dispatch_handler(
+        &self,
+        source: &Source,
+        message: &RedisMessage,
+        component_id: &str,
+    ) -> anyhow::Result<()> {
+        if let Some(recorder) = &self.recorder {
+            let recording = Recording::new(
+                "redis",
+                component_id,
+                RecordedPayload::Redis {
+                    channel: message.channel.clone(),
+                    pattern: message.pattern.clone(),
+                    id: message.id.clone(),
+                    headers: message.headers.clone(),
+                    payload: message.payload.clone(),
+                },
+            );
+            if let Err(err) = recorder.record(&recording) {
+                tracing::error!("Failed to record message to component {component_id}: {err:#}");
+            }
+        }
+        let handle = handle_message(
+            &self.trigger_app,
+            component_id,
+            message.clone(),
+            self.core_dumper.as_ref(),
         );
+        match self
+            .dedupers
+            .get(&(source.clone(), component_id.to_owned()))
+        {
+            Some(dedupe) => {
+                let id = dedupe.message_id(&message.headers, message.id.as_deref());
+                let scope = format!("{component_id}:{}", message.channel);
+                dedupe.run(&scope, id, handle).await
+            }
+            None => handle.await,
+        }
+    }
+}
 
-        let (instance, mut store) = self
-            .trigger_app
-            .prepare(component_id)?
-            .instantiate(())
-            .await?;
+/// Invokes a component's Redis message handler with `message`, capturing a
+/// core dump with `core_dumper` if it traps.
+///
+/// Components exporting the `spin:redis` handler receive the message's
+/// metadata; those exporting the older `fermyon:spin` handler receive only
+/// its payload.
+pub async fn handle_message<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<RedisTrigger, F>,
+    component_id: &str,
+    message: RedisMessage,
+    core_dumper: Option<&CoreDumper>,
+) -> anyhow::Result<()> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "redis",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let start = Instant::now();
+    let mut audit = None;
+    let result = async {
+        let mut instance_builder = trigger_app.prepare(component_id).await?;
+        audit = instance_builder
+            .factor_builder::<AuditFactor>()
+            .and_then(|audit| audit.handle());
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
 
         let pre = instance.instance_pre(&store);
-        let guest_indices = inbound_redis::GuestIndices::new(&pre)?;
-        let guest = guest_indices.load(&mut store, &instance)?;
+        let result = if let Ok(guest_indices) = inbound_redis::GuestIndices::new(&pre) {
+            let guest = guest_indices.load(&mut store, &instance)?;
+            guest
+                .call_handle_message(&mut store, &(&message).into())
+                .await
+                .map(|res| res.map_err(anyhow::Error::msg))
+        } else {
+            let guest_indices = v1::GuestIndices::new(&pre)?;
+            let guest = guest_indices.load(&mut store, &instance)?;
+            guest
+                .call_handle_message(&mut store, &message.payload)
+                .await
+                .map(|res| res.map_err(anyhow::Error::from))
+        };
+        if let (Some(core_dumper), Err(err)) = (core_dumper, &result) {
+            let mut metadata = vec![
+                ("channel".to_owned(), message.channel.clone()),
+                ("payload_size".to_owned(), message.payload.len().to_string()),
+            ];
+            metadata.extend(message.pattern.clone().map(|p| ("pattern".to_owned(), p)));
+            metadata.extend(message.id.clone().map(|id| ("id".to_owned(), id)));
+            core_dumper.capture(&mut store, err, "redis", component_id, metadata);
+        }
+        result?.context("Redis handler returned an error")
+    }
+    .await;
+    spin_telemetry::metrics::histogram!(
+        spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+        trigger_type = "redis",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+    if let Some(audit) = audit {
+        audit.finish(
+            "redis",
+            &message.channel,
+            AuditOutcome::from_result(&result),
+        );
+    }
+    result
+}
 
-        let payload = msg.get_payload_bytes().to_vec();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(channel: Option<&str>, pattern: Option<&str>, stream: Option<&str>) -> TriggerConfig {
+        TriggerConfig {
+            component: "handler".into(),
+            channel: channel.map(Into::into),
+            pattern: pattern.map(Into::into),
+            stream: stream.map(Into::into),
+            address: None,
+            dedupe: None,
+        }
+    }
 
-        guest
-            .call_handle_message(&mut store, &payload)
-            .await?
-            .context("Redis handler returned an error")
+    #[test]
+    fn exactly_one_source_must_be_set() {
+        assert_eq!(
+            config(Some("messages"), None, None).source().unwrap(),
+            Source::Channel("messages".into())
+        );
+        assert_eq!(
+            config(None, Some("orders.*"), None).source().unwrap(),
+            Source::Pattern("orders.*".into())
+        );
+        assert_eq!(
+            config(None, None, Some("events")).source().unwrap(),
+            Source::Stream("events".into())
+        );
+        assert!(config(None, None, None).source().is_err());
+        assert!(config(Some("messages"), Some("orders.*"), None)
+            .source()
+            .is_err());
     }
 }
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::Deserialize;
+use spin_common::time::now_millis;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_timers::{Timer, TimerStore, TimersFactor};
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
+use spin_world::exports::spin::timers::handler;
+use tracing::{instrument, Level};
+
+/// How often the timer store is checked for due timers.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delivers timers scheduled through the `spin:timers/scheduler` interface.
+///
+/// Timers are delivered at least once: a timer is removed from the store only
+/// after its handler has run, whether or not the handler succeeded. Each Spin
+/// instance sharing a timer store delivers the timers it finds, so a store
+/// should be shared by a single running app.
+pub struct TimerTrigger;
+
+/// Timer trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for TimerTrigger {
+    const TYPE: &'static str = spin_factor_timers::TIMER_TRIGGER_TYPE;
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        let store = trigger_app
+            .configured_app()
+            .app_state::<TimersFactor>()
+            .context("TimerTrigger depends on TimersFactor")?
+            .timer_store()
+            .clone();
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        let component_ids = trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .map(|(_, config)| config.component)
+            .collect::<Vec<_>>();
+
+        println!(
+            "Delivering timers from key-value store {:?} to: [{}]",
+            store.label(),
+            component_ids.join(",")
+        );
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            for component_id in &component_ids {
+                if let Err(err) = deliver_due(&trigger_app, &store, component_id).await {
+                    tracing::error!("Error delivering timers to {component_id}: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Runs the handler for each of the component's due timers, removing each
+/// timer once its handler has run.
+async fn deliver_due<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<TimerTrigger, F>,
+    store: &TimerStore,
+    component_id: &str,
+) -> anyhow::Result<()> {
+    for timer in store.due(component_id, now_millis()).await? {
+        if let Err(err) = handle_timer(trigger_app, component_id, &timer).await {
+            tracing::info!("Component {component_id} timer handler failed: {err}");
+        }
+        store.remove(component_id, &timer.id).await?;
+    }
+    Ok(())
+}
+
+#[instrument(name = "spin_trigger_timer.handle_timer", skip_all, err(level = Level::INFO), fields(
+    otel.name = format!("{component_id} timer"),
+    otel.kind = "consumer",
+    timer.id = %timer.id,
+    component_id = component_id
+))]
+async fn handle_timer<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<TimerTrigger, F>,
+    component_id: &str,
+    timer: &Timer,
+) -> anyhow::Result<()> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "timer",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let start = Instant::now();
+    let mut audit = None;
+    let result = async {
+        let mut instance_builder = trigger_app.prepare(component_id).await?;
+        audit = instance_builder
+            .factor_builder::<AuditFactor>()
+            .and_then(|audit| audit.handle());
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+
+        let pre = instance.instance_pre(&store);
+        let guest_indices = handler::GuestIndices::new(&pre)?;
+        let guest = guest_indices.load(&mut store, &instance)?;
+
+        guest
+            .call_handle_timer(&mut store, &timer.id, &timer.payload)
+            .await?
+            .map_err(|e| anyhow::anyhow!("timer handler returned an error: {e}"))
+    }
+    .await;
+    spin_telemetry::metrics::histogram!(
+        spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+        trigger_type = "timer",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+    if let Some(audit) = audit {
+        audit.finish("timer", &timer.id, AuditOutcome::from_result(&result));
+    }
+    result
+}
@@ -0,0 +1,228 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::Deserialize;
+use spin_common::time::now_millis;
+use spin_factor_audit::{AuditFactor, AuditOutcome};
+use spin_factor_workflows::{Outcome, Run, RunState, Signal, WorkflowStore, WorkflowsFactor};
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
+use spin_world::exports::spin::workflow::handler;
+use tracing::{instrument, Level};
+
+/// How often the workflow store is checked for runs with steps due.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most steps of one run to run in a row before moving on to other runs.
+const MAX_STEPS_PER_POLL: usize = 100;
+
+/// Runs the steps of workflows started through the `spin:workflow/workflow`
+/// interface.
+///
+/// A run's progress is saved after each step, so steps run at least once: a
+/// step interrupted by a restart runs again. Each Spin instance sharing a
+/// workflow store runs the steps it finds due, so a store should be shared by
+/// a single running app.
+pub struct WorkflowTrigger;
+
+/// Workflow trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Name of the workflow whose steps the component runs
+    workflow: String,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for WorkflowTrigger {
+    const TYPE: &'static str = spin_factor_workflows::WORKFLOW_TRIGGER_TYPE;
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        let workflows = trigger_app
+            .configured_app()
+            .app_state::<WorkflowsFactor>()
+            .context("WorkflowTrigger depends on WorkflowsFactor")?;
+        let store = workflows.workflow_store().clone();
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        for (_, config) in trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+        {
+            println!(
+                "Running workflow {:?} with component {} from key-value store {:?}",
+                config.workflow,
+                config.component,
+                store.label()
+            );
+        }
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let runs = match store.active().await {
+                Ok(runs) => runs,
+                Err(err) => {
+                    tracing::error!("Error listing workflow runs: {err:?}");
+                    continue;
+                }
+            };
+            for run in runs {
+                let id = run.id.clone();
+                if let Err(err) = advance(&trigger_app, &store, run).await {
+                    tracing::error!("Error advancing workflow run {id}: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Runs a run's steps for as long as they are due, up to
+/// [`MAX_STEPS_PER_POLL`], saving its progress after each one.
+async fn advance<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<WorkflowTrigger, F>,
+    store: &WorkflowStore,
+    mut run: Run,
+) -> anyhow::Result<()> {
+    let workflows = trigger_app
+        .configured_app()
+        .app_state::<WorkflowsFactor>()
+        .context("WorkflowTrigger depends on WorkflowsFactor")?;
+    let Some(component_id) = workflows.workflow_component(&run.workflow) else {
+        tracing::warn!(
+            "Workflow run {} is for unknown workflow {:?}; leaving it for an app which defines it",
+            run.id,
+            run.workflow
+        );
+        return Ok(());
+    };
+
+    for _ in 0..MAX_STEPS_PER_POLL {
+        if store.is_cancelled(&run.id).await? {
+            run.state = RunState::Cancelled;
+            let signals = store.signals(&run.id).await?;
+            store.remove_signals(&run.id, &signals).await?;
+            return store.save(&run).await;
+        }
+        let signals = store.signals(&run.id).await?;
+        if !run.wake(now_millis(), &signals) {
+            return Ok(());
+        }
+
+        match run_step(trigger_app, component_id, &run, &signals).await {
+            Ok(outcome) => {
+                run.step_finished(outcome, now_millis());
+                store.save(&run).await?;
+                store.remove_signals(&run.id, &signals).await?;
+            }
+            Err(err) => {
+                tracing::info!("Workflow run {} step {} failed: {err:#}", run.id, run.step);
+                run.step_failed(format!("{err:#}"), now_millis(), workflows.runtime_config());
+                store.save(&run).await?;
+            }
+        }
+        if run.state.is_finished() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[instrument(name = "spin_trigger_workflow.run_step", skip_all, err(level = Level::INFO), fields(
+    otel.name = format!("{component_id} workflow step"),
+    otel.kind = "consumer",
+    workflow.name = %run.workflow,
+    workflow.run_id = %run.id,
+    workflow.step = run.step,
+    component_id = component_id
+))]
+async fn run_step<F: RuntimeFactors>(
+    trigger_app: &TriggerApp<WorkflowTrigger, F>,
+    component_id: &str,
+    run: &Run,
+    signals: &[Signal],
+) -> anyhow::Result<Outcome> {
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.request_count = 1,
+        trigger_type = "workflow",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+
+    let start = Instant::now();
+    let mut audit = None;
+    let result = async {
+        let mut instance_builder = trigger_app.prepare(component_id).await?;
+        audit = instance_builder
+            .factor_builder::<AuditFactor>()
+            .and_then(|audit| audit.handle());
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+
+        let pre = instance.instance_pre(&store);
+        let guest_indices = handler::GuestIndices::new(&pre)?;
+        let guest = guest_indices.load(&mut store, &instance)?;
+
+        let step = handler::Step {
+            id: run.id.clone(),
+            workflow: run.workflow.clone(),
+            index: run.step,
+            attempt: run.attempt,
+            state: run.data.clone(),
+            signals: signals
+                .iter()
+                .map(|signal| handler::Signal {
+                    name: signal.name.clone(),
+                    payload: signal.payload.clone(),
+                })
+                .collect(),
+            timed_out: run.timed_out,
+        };
+        let outcome = guest
+            .call_run_step(&mut store, &step)
+            .await?
+            .map_err(|e| anyhow::anyhow!("workflow step returned an error: {e}"))?;
+        Ok(outcome_from_wit(outcome))
+    }
+    .await;
+    spin_telemetry::metrics::histogram!(
+        spin.request_duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+        trigger_type = "workflow",
+        app_id = trigger_app.app().id(),
+        component_id = component_id
+    );
+    if let Some(audit) = audit {
+        audit.finish(
+            "workflow",
+            &run.workflow,
+            AuditOutcome::from_result(&result),
+        );
+    }
+    result
+}
+
+fn outcome_from_wit(outcome: handler::Outcome) -> Outcome {
+    match outcome {
+        handler::Outcome::Next(state) => Outcome::Next(state),
+        handler::Outcome::Sleep(sleep) => Outcome::Sleep {
+            duration_ms: sleep.duration_ms,
+            state: sleep.state,
+        },
+        handler::Outcome::WaitForSignal(wait) => Outcome::Wait {
+            signal: wait.signal,
+            timeout_ms: wait.timeout_ms,
+            state: wait.state,
+        },
+        handler::Outcome::Complete(output) => Outcome::Complete(output),
+        handler::Outcome::Fail(error) => Outcome::Fail(error),
+    }
+}
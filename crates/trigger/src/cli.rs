@@ -1,15 +1,28 @@
+mod admin;
+mod check;
+mod component_limits;
+mod executor;
 mod initial_kv_setter;
 mod launch_metadata;
+mod lifecycle;
 mod max_instance_memory;
+mod metrics_server;
+mod profiling;
+mod reload;
 mod sqlite_statements;
 mod stdio;
 mod summary;
 
-use std::path::PathBuf;
-use std::{future::Future, sync::Arc};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
 
 use anyhow::{Context, Result};
 use clap::{Args, IntoApp, Parser};
+use futures::future::Either;
 use spin_app::App;
 use spin_common::sloth;
 use spin_common::ui::quoted_path;
@@ -17,19 +30,36 @@ use spin_common::url::parse_file_url;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{ComponentLoader, FactorsExecutor};
 
-use crate::{loader::ComponentLoader as ComponentLoaderImpl, Trigger, TriggerApp};
+use crate::{
+    core_dump::CoreDumper,
+    loader::{AotCache, ComponentLoader as ComponentLoaderImpl},
+    recording::Recorder,
+    Trigger, TriggerApp,
+};
+pub use admin::{serve_admin, AdminHook, AdminSocket, AdminState};
+pub use check::{check_app, CheckReport};
+pub use component_limits::{ComponentLimits, ComponentLimitsConfig, ComponentLimitsHook};
+pub use executor::{ExecutorConfig, ExecutorTuning};
 pub use initial_kv_setter::InitialKvSetterHook;
 pub use launch_metadata::LaunchMetadata;
+pub use lifecycle::{run_shutdown_hooks, run_startup_hooks};
 pub use max_instance_memory::MaxInstanceMemoryHook;
+pub use metrics_server::serve_metrics;
+pub use profiling::{ProfileFormat, ProfilingConfig, ProfilingHook};
+pub use reload::ReloadSignals;
 pub use sqlite_statements::SqlStatementExecutorHook;
-use stdio::FollowComponents;
+pub use stdio::FollowComponents;
 pub use stdio::StdioLoggingExecutorHooks;
-pub use summary::{KeyValueDefaultStoreSummaryHook, SqliteDefaultStoreSummaryHook};
+pub use stdio::{history_dir, history_file, LogFormat, LogRotation};
+pub use summary::{
+    KeyValueDefaultStoreSummaryHook, SecretAccessSummaryHook, SqliteDefaultStoreSummaryHook,
+};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
 pub const DISABLE_WASMTIME_CACHE: &str = "DISABLE_WASMTIME_CACHE";
 pub const FOLLOW_LOG_OPT: &str = "FOLLOW_ID";
 pub const WASMTIME_CACHE_FILE: &str = "WASMTIME_CACHE_FILE";
+pub const SPIN_AOT_CACHE_DIR: &str = "SPIN_AOT_CACHE_DIR";
 pub const RUNTIME_CONFIG_FILE: &str = "RUNTIME_CONFIG_FILE";
 
 // Set by `spin up`
@@ -54,6 +84,27 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub log: Option<PathBuf>,
 
+    /// The format of component logs: `text` logs output as the component
+    /// wrote it, and `json` logs each line as a JSON object with its
+    /// timestamp, component, level and request ID. Setting
+    /// SPIN_LOG_FORMAT=json also formats Spin's own logs as JSON.
+    #[clap(
+        long = "log-format",
+        env = "SPIN_LOG_FORMAT",
+        value_enum,
+        default_value = "text"
+    )]
+    pub log_format: LogFormat,
+
+    /// Rotate a component's log files once they grow past this many bytes.
+    #[clap(long = "log-max-size")]
+    pub log_max_size: Option<u64>,
+
+    /// How many rotated log files to keep for each component stream, if
+    /// --log-max-size is set.
+    #[clap(long = "log-max-files", default_value = "5")]
+    pub log_max_files: usize,
+
     /// Disable Wasmtime cache.
     #[clap(
         name = DISABLE_WASMTIME_CACHE,
@@ -73,6 +124,28 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub cache: Option<PathBuf>,
 
+    /// Load components compiled ahead of time from this directory, saving
+    /// them there after compiling if they are not yet present. Components
+    /// can be compiled in advance with `spin build --precompile`. The
+    /// directory may be shared between hosts, but must be trusted.
+    #[clap(long = "aot-cache-dir", env = SPIN_AOT_CACHE_DIR)]
+    pub aot_cache_dir: Option<PathBuf>,
+
+    /// Load each component when it is first invoked rather than at startup,
+    /// so that startup time doesn't grow with the number of components.
+    #[clap(long = "lazy-load-components", env = "SPIN_LAZY_LOAD_COMPONENTS")]
+    pub lazy_load_components: bool,
+
+    /// Evict the compiled code of the least recently invoked components once
+    /// the loaded components take more than this many megabytes, loading them
+    /// again when next invoked.
+    #[clap(
+        long = "max-loaded-components-mb",
+        env = "SPIN_MAX_LOADED_COMPONENTS_MB",
+        requires = "lazy-load-components"
+    )]
+    pub max_loaded_components_mb: Option<usize>,
+
     /// Disable Wasmtime's pooling instance allocator.
     #[clap(long = "disable-pooling")]
     pub disable_pooling: bool,
@@ -111,6 +184,72 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     #[clap(long)]
     pub state_dir: Option<String>,
 
+    /// Serve Prometheus metrics at `/metrics` on the given address, e.g.
+    /// 127.0.0.1:9090. Each trigger type runs in its own process, so this
+    /// can't be used with apps that have more than one trigger type.
+    #[clap(long = "metrics-listen", env = "SPIN_METRICS_LISTEN")]
+    pub metrics_listen: Option<SocketAddr>,
+
+    /// Serve the admin API on a Unix socket at this path. The API reports
+    /// the app's status, components and configuration, and can reload or
+    /// drain the app and disable individual components. Each trigger type
+    /// runs in its own process, so this can't be used with apps that have
+    /// more than one trigger type.
+    #[clap(long = "admin-socket", env = "SPIN_ADMIN_SOCKET")]
+    pub admin_socket: Option<PathBuf>,
+
+    /// Record the payload of every invocation (e.g. HTTP requests and Redis
+    /// messages) to a file in this directory, for use with `spin replay`.
+    #[clap(long = "record-dir", env = "SPIN_RECORD_DIR")]
+    pub record_dir: Option<PathBuf>,
+
+    /// Compile components with their DWARF debug info and without
+    /// optimizations, so that a native debugger such as LLDB or GDB attached
+    /// to this process can set breakpoints in them.
+    #[clap(long = "debug-guests", env = "SPIN_DEBUG_GUESTS")]
+    pub debug_guests: bool,
+
+    /// Write a Wasm core dump, with metadata describing the invocation, to
+    /// this directory whenever a component traps.
+    #[clap(long = "core-dump-dir", env = "SPIN_CORE_DUMP_DIR")]
+    pub core_dump_dir: Option<PathBuf>,
+
+    /// The number of core dumps to keep; older core dumps are deleted.
+    #[clap(
+        long = "core-dump-max-files",
+        default_value = "10",
+        requires = "core-dump-dir"
+    )]
+    pub core_dump_max_files: usize,
+
+    /// Profile the given component(s), sampling their stacks while they run
+    /// and writing flamegraph profiles to the profile directory.
+    #[clap(long = "profile", multiple_occurrences = true)]
+    pub profile_components: Vec<String>,
+
+    /// The directory to write profiles to.
+    #[clap(long = "profile-dir", default_value = "spin-profiles")]
+    pub profile_dir: PathBuf,
+
+    /// The format to write profiles in.
+    #[clap(long = "profile-format", value_enum, default_value = "speedscope")]
+    pub profile_format: ProfileFormat,
+
+    /// Write one profile per component covering all of its invocations,
+    /// rather than one profile per invocation.
+    #[clap(long = "profile-aggregate")]
+    pub profile_aggregate: bool,
+
+    /// Check that the app is ready to run, then exit rather than running
+    /// it: load the app, compile its components, configure the runtime and
+    /// connect once to each key-value store (whatever its backend) and
+    /// SQLite database the app uses. Connections components open themselves,
+    /// such as to Redis, PostgreSQL or MySQL servers, and LLM backends are
+    /// not checked. Nothing is written to any store or database. Reports each
+    /// check, and exits with a failure status if any of them failed.
+    #[clap(long = "check")]
+    pub check: bool,
+
     #[clap(flatten)]
     pub trigger_args: T::CliArgs,
 
@@ -125,7 +264,7 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
 }
 
 /// Configuration options that are common to all triggers.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct FactorsConfig {
     /// The Spin working directory.
     pub working_dir: PathBuf,
@@ -139,14 +278,29 @@ pub struct FactorsConfig {
     pub follow_components: FollowComponents,
     /// Log directory for component stdout/stderr.
     pub log_dir: UserProvidedPath,
+    /// The format of component logs.
+    pub log_format: LogFormat,
+    /// When to rotate component log files, if at all.
+    pub log_rotation: Option<LogRotation>,
+    /// Whether the app is only being checked rather than run, in which case
+    /// hooks which change the app's stores or databases are skipped.
+    pub check_only: bool,
 }
 
 /// An empty implementation of clap::Args to be used as TriggerExecutor::RunConfig
 /// for executors that do not need additional CLI args.
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct NoCliArgs;
 
-impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T, B> {
+impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T, B>
+where
+    T::InstanceState: Default,
+{
+    /// The tuning of the runtime this command should run on.
+    pub fn executor_tuning(&self) -> Result<ExecutorTuning> {
+        B::executor_tuning(self.runtime_config_file.as_deref(), T::TYPE)
+    }
+
     /// Create a new TriggerExecutorBuilder from this TriggerExecutorCommand.
     pub async fn run(self) -> Result<()> {
         // Handle --help-args-only
@@ -173,34 +327,6 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
 
         let follow_components = self.follow_components();
 
-        // Load App
-        let app = {
-            let path = parse_file_url(&locked_url)?;
-            let contents = std::fs::read(&path)
-                .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
-            let locked =
-                serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
-            App::new(locked_url, locked)
-        };
-
-        // Validate required host features
-        if let Err(unmet) = app.ensure_needs_only(T::TYPE, &T::supported_host_requirements()) {
-            anyhow::bail!("This application requires the following features that are not available in this version of the '{}' trigger: {unmet}", T::TYPE);
-        }
-
-        let trigger = T::new(self.trigger_args, &app)?;
-        let mut builder: TriggerAppBuilder<T, B> = TriggerAppBuilder::new(trigger);
-        let config = builder.engine_config();
-
-        // Apply --cache / --disable-cache
-        if !self.disable_cache {
-            config.enable_cache(&self.cache)?;
-        }
-
-        if self.disable_pooling {
-            config.disable_pooling();
-        }
-
         let state_dir = match &self.state_dir {
             // Make sure `--state-dir=""` unsets the state dir
             Some(s) if s.is_empty() => UserProvidedPath::Unset,
@@ -220,20 +346,79 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             local_app_dir: local_app_dir.clone(),
             follow_components,
             log_dir,
+            log_format: self.log_format,
+            log_rotation: self.log_max_size.map(|max_size| LogRotation {
+                max_size,
+                max_files: self.log_max_files,
+            }),
+            check_only: self.check,
         };
 
-        let run_fut = builder
-            .run(
-                app,
-                common_options,
-                self.builder_args,
-                &ComponentLoaderImpl::new(),
-            )
+        if self.check {
+            return self.run_checks(&locked_url, &common_options).await;
+        }
+
+        if let Some(metrics_listen) = self.metrics_listen {
+            serve_metrics(metrics_listen).await?;
+        }
+
+        // The admin socket is removed when this is dropped on shutdown.
+        let (admin, admin_reloads, _admin_socket) = match &self.admin_socket {
+            Some(admin_socket) => {
+                let (admin, reloads) =
+                    AdminState::new(T::TYPE, self.admin_options(&common_options));
+                let socket = serve_admin(admin_socket, admin.clone()).await?;
+                (Some(admin), Some(reloads), Some(socket))
+            }
+            None => (None, None, None),
+        };
+
+        let app = load_app(&locked_url)?;
+        let (trigger, trigger_app) = self
+            .build_trigger_app(app, &common_options, admin.as_ref())
             .await?;
+        if self.debug_guests {
+            print_debugging_instructions();
+        }
+        run_startup_hooks(&trigger_app).await?;
+        // The app whose shutdown hooks are run when the trigger stops,
+        // replaced whenever the app is reloaded.
+        let current_app = Mutex::new(trigger_app.clone());
+
+        let (reload_tx, reloads) = tokio::sync::mpsc::channel(1);
+        let run_fut = async {
+            let run_fut = std::pin::pin!(trigger.run_reloadable(trigger_app, reloads));
+            let reload_fut = std::pin::pin!(self.reload_on_signal(
+                &locked_url,
+                &common_options,
+                admin.as_ref(),
+                &current_app,
+                reload_tx,
+                admin_reloads
+            ));
+            let run_fut = async {
+                match futures::future::select(run_fut, reload_fut).await {
+                    Either::Left((res, _)) => res,
+                    // Reloading has stopped; keep running the current app.
+                    Either::Right(((), run_fut)) => run_fut.await,
+                }
+            };
+            let Some(admin) = &admin else {
+                return run_fut.await;
+            };
+            let drained = std::pin::pin!(admin.drained());
+            match futures::future::select(std::pin::pin!(run_fut), drained).await {
+                Either::Left((res, _)) => res,
+                Either::Right(((), _)) => {
+                    tracing::info!("Application drained through the admin API: exiting");
+                    Ok(())
+                }
+            }
+        };
 
         let (abortable, abort_handle) = futures::future::abortable(run_fut);
         ctrlc::set_handler(move || abort_handle.abort())?;
-        match abortable.await {
+        let result = match abortable.await {
             Ok(Ok(())) => {
                 tracing::info!("Trigger executor shut down: exiting");
                 Ok(())
@@ -246,7 +431,181 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
                 tracing::info!("User requested shutdown: exiting");
                 Ok(())
             }
+        };
+
+        if let Some(admin) = &admin {
+            admin.stopped();
         }
+        run_shutdown_hooks(&current_app.into_inner().unwrap()).await;
+        result
+    }
+
+    /// Builds the trigger and its app.
+    async fn build_trigger_app(
+        &self,
+        app: App,
+        common_options: &FactorsConfig,
+        admin: Option<&Arc<AdminState>>,
+    ) -> Result<(T, TriggerApp<T, B::Factors>)> {
+        // Validate required host features
+        if let Err(unmet) = app.ensure_needs_only(T::TYPE, &T::supported_host_requirements()) {
+            anyhow::bail!("This application requires the following features that are not available in this version of the '{}' trigger: {unmet}", T::TYPE);
+        }
+
+        let mut trigger = T::new(self.trigger_args.clone(), &app)?;
+        if let Some(record_dir) = &self.record_dir {
+            trigger.record_invocations(Recorder::new(record_dir)?);
+        }
+        if let Some(core_dump_dir) = &self.core_dump_dir {
+            trigger.capture_core_dumps(CoreDumper::new(core_dump_dir, self.core_dump_max_files)?);
+        }
+        let mut builder: TriggerAppBuilder<T, B> = TriggerAppBuilder::new(trigger);
+        let config = builder.engine_config();
+
+        // Apply --cache / --disable-cache
+        if !self.disable_cache {
+            config.enable_cache(&self.cache)?;
+        }
+
+        if self.disable_pooling {
+            config.disable_pooling();
+        }
+
+        if self.debug_guests {
+            config.debug_guests(true);
+        }
+
+        if self.core_dump_dir.is_some() {
+            config.wasmtime_config().coredump_on_trap(true);
+        }
+
+        // Checks compile every component up front
+        if self.lazy_load_components && !self.check {
+            builder.load_components_lazily(
+                self.max_loaded_components_mb
+                    .map(|mb| mb.saturating_mul(1024 * 1024)),
+            );
+        }
+
+        if !self.profile_components.is_empty() {
+            builder.profile(ProfilingConfig {
+                components: self.profile_components.iter().cloned().collect(),
+                dir: self.profile_dir.clone(),
+                format: self.profile_format,
+                aggregate: self.profile_aggregate,
+            });
+        }
+
+        if let Some(admin) = admin {
+            builder.admin(admin.clone());
+        }
+
+        let mut loader = ComponentLoaderImpl::new();
+        if let Some(aot_cache_dir) = &self.aot_cache_dir {
+            loader = loader.with_aot_cache(AotCache::new(aot_cache_dir));
+        }
+
+        let trigger_app = builder
+            .build(
+                app,
+                common_options.clone(),
+                self.builder_args.clone(),
+                &loader,
+            )
+            .await?;
+        Ok((builder.trigger, trigger_app))
+    }
+
+    /// Runs the `--check` preflight checks, failing if any check fails.
+    async fn run_checks(&self, locked_url: &str, common_options: &FactorsConfig) -> Result<()> {
+        terminal::step!("Checking", "the '{}' trigger", T::TYPE);
+        let mut report = CheckReport::default();
+        let built = async {
+            let app = load_app(locked_url)?;
+            self.build_trigger_app(app, common_options, None).await
+        };
+        match built.await {
+            Ok((_, trigger_app, _)) => {
+                report.record("app and runtime config", Ok(()));
+                check_app(&trigger_app, &mut report).await;
+            }
+            Err(err) => report.record("app and runtime config", Err(err)),
+        }
+        report.finish(T::TYPE)
+    }
+
+    /// Rebuilds the app from the lock file and runtime config each time a
+    /// reload is requested, running its startup hooks and then sending it to
+    /// the running trigger in place of `current_app`.
+    ///
+    /// Returns if reloading isn't possible, e.g. because the trigger doesn't
+    /// support it.
+    async fn reload_on_signal(
+        &self,
+        locked_url: &str,
+        common_options: &FactorsConfig,
+        admin: Option<&Arc<AdminState>>,
+        current_app: &Mutex<TriggerApp<T, B::Factors>>,
+        reloads: tokio::sync::mpsc::Sender<TriggerApp<T, B::Factors>>,
+        admin_reloads: Option<tokio::sync::mpsc::Receiver<()>>,
+    ) {
+        let mut signals = match ReloadSignals::new() {
+            Ok(signals) => signals,
+            Err(err) => {
+                tracing::warn!("Reloading is unavailable: {err:#}");
+                return;
+            }
+        };
+        if let Some(admin_reloads) = admin_reloads {
+            signals = signals.with_requests(admin_reloads);
+        }
+        while signals.recv().await {
+            if reloads.is_closed() {
+                terminal::warn!("The '{}' trigger doesn't support reloading.", T::TYPE);
+                return;
+            }
+            tracing::info!("Reloading application");
+            let rebuilt = async {
+                let app = load_app(locked_url)?;
+                let (_, trigger_app, _) =
+                    self.build_trigger_app(app, common_options, admin).await?;
+                run_startup_hooks(&trigger_app).await?;
+                anyhow::Ok(trigger_app)
+            };
+            match rebuilt.await {
+                Ok(trigger_app) => {
+                    *current_app.lock().unwrap() = trigger_app.clone();
+                    if reloads.send(trigger_app).await.is_err() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    terminal::error!("Failed to reload application: {err:#}");
+                    terminal::einfo!("Continuing with", "the previously loaded application.");
+                }
+            }
+        }
+    }
+
+    /// The runtime options reported by the admin API's config dump.
+    fn admin_options(&self, common_options: &FactorsConfig) -> serde_json::Value {
+        fn path(path: &UserProvidedPath) -> serde_json::Value {
+            match path {
+                UserProvidedPath::Provided(path) => path.display().to_string().into(),
+                UserProvidedPath::Default => "default".into(),
+                UserProvidedPath::Unset => serde_json::Value::Null,
+            }
+        }
+        serde_json::json!({
+            "working_dir": common_options.working_dir,
+            "runtime_config_file": common_options.runtime_config_file,
+            "state_dir": path(&common_options.state_dir),
+            "log_dir": path(&common_options.log_dir),
+            "log_format": common_options.log_format,
+            "lazy_load_components": self.lazy_load_components,
+            "metrics_listen": self.metrics_listen,
+            "record_dir": self.record_dir,
+        })
     }
 
     fn follow_components(&self) -> FollowComponents {
@@ -261,6 +620,30 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
     }
 }
 
+/// Loads the app from the lock file at `locked_url`.
+fn load_app(locked_url: &str) -> Result<App> {
+    let path = parse_file_url(locked_url)?;
+    let contents = std::fs::read(&path)
+        .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
+    let locked = serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
+    Ok(App::new(locked_url, locked))
+}
+
+/// Explains how to attach a debugger to this process to debug guests.
+fn print_debugging_instructions() {
+    let pid = std::process::id();
+    tracing::info!(pid, "Guest debugging is enabled");
+    terminal::einfo!(
+        "Debugging",
+        "components in process {pid}: attach a debugger to set breakpoints and inspect variables"
+    );
+    terminal::einfo!(
+        "  LLDB:",
+        "lldb --attach-pid {pid}, then `settings set plugin.jit-loader.gdb.enable on`"
+    );
+    terminal::einfo!("  GDB:", "gdb --pid {pid}");
+}
+
 const SLOTH_WARNING_DELAY_MILLIS: u64 = 1250;
 
 fn warn_if_wasm_build_slothful() -> sloth::SlothGuard {
@@ -288,14 +671,29 @@ fn help_heading<T: Trigger<F>, F: RuntimeFactors>() -> Option<&'static str> {
 /// A builder for a [`TriggerApp`].
 pub struct TriggerAppBuilder<T, B> {
     engine_config: spin_core::Config,
+    component_loading: ComponentLoading,
+    profiling: Option<ProfilingConfig>,
+    admin: Option<Arc<AdminState>>,
     pub trigger: T,
     _factors_builder: std::marker::PhantomData<B>,
 }
 
+/// When a [`TriggerApp`]'s components are loaded.
+enum ComponentLoading {
+    /// All components are loaded when the app is built.
+    Eager,
+    /// Each component is loaded when first used, and evicted when idle if
+    /// loaded components exceed `max_loaded_bytes`.
+    Lazy { max_loaded_bytes: Option<usize> },
+}
+
 impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
     pub fn new(trigger: T) -> Self {
         Self {
             engine_config: spin_core::Config::default(),
+            component_loading: ComponentLoading::Eager,
+            profiling: None,
+            admin: None,
             trigger,
             _factors_builder: Default::default(),
         }
@@ -305,32 +703,69 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         &mut self.engine_config
     }
 
+    /// Load components when they are first used rather than when the app is
+    /// built. If `max_loaded_bytes` is set, the least recently used components
+    /// are evicted once the compiled code of loaded components exceeds it.
+    pub fn load_components_lazily(&mut self, max_loaded_bytes: Option<usize>) {
+        self.component_loading = ComponentLoading::Lazy { max_loaded_bytes };
+    }
+
+    /// Sample the stacks of the given components' instances and write them
+    /// as profiles.
+    pub fn profile(&mut self, config: ProfilingConfig) {
+        self.profiling = Some(config);
+    }
+
+    /// Report the app to, and apply the controls of, the given admin API.
+    pub fn admin(&mut self, admin: Arc<AdminState>) {
+        self.admin = Some(admin);
+    }
+
     /// Build a [`TriggerApp`] from the given [`App`] and options.
     pub async fn build(
         &mut self,
         app: App,
         common_options: FactorsConfig,
         options: B::CliArgs,
-        loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+        loader: &(impl ComponentLoader<B::Factors, T::InstanceState> + Clone + Send + 'static),
     ) -> anyhow::Result<TriggerApp<T, B::Factors>> {
+        let (factors, runtime_config) = B::build(&common_options, &options).await?;
+
         let mut core_engine_builder = {
             self.trigger.update_core_config(&mut self.engine_config)?;
+            B::update_core_config(&runtime_config, &mut self.engine_config)?;
 
             spin_core::Engine::builder(&self.engine_config)?
         };
         self.trigger.add_to_linker(core_engine_builder.linker())?;
 
-        let (factors, runtime_config) = B::build(&common_options, &options)?;
-
         let mut executor = FactorsExecutor::new(core_engine_builder, factors)?;
         B::configure_app(&mut executor, &runtime_config, &common_options, &options)?;
+        if let Some(profiling) = &self.profiling {
+            executor.add_hooks(ProfilingHook::new(profiling.clone())?);
+        }
+        if let Some(admin) = &self.admin {
+            executor.add_hooks(admin.hook());
+        }
         let executor = Arc::new(executor);
 
-        let configured_app = {
-            let _sloth_guard = warn_if_wasm_build_slothful();
-            executor
-                .load_app(app, runtime_config.into(), loader)
-                .await?
+        let configured_app = match self.component_loading {
+            ComponentLoading::Eager => {
+                let _sloth_guard = warn_if_wasm_build_slothful();
+                executor
+                    .load_app(app, runtime_config.into(), loader)
+                    .await?
+            }
+            ComponentLoading::Lazy { max_loaded_bytes } => {
+                executor
+                    .load_app_lazily(
+                        app,
+                        runtime_config.into(),
+                        Arc::new(loader.clone()),
+                        max_loaded_bytes,
+                    )
+                    .await?
+            }
         };
 
         Ok(configured_app)
@@ -342,7 +777,7 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         app: App,
         common_options: FactorsConfig,
         options: B::CliArgs,
-        loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+        loader: &(impl ComponentLoader<B::Factors, T::InstanceState> + Clone + Send + 'static),
     ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
         let configured_app = self.build(app, common_options, options, loader).await?;
         Ok(self.trigger.run(configured_app))
@@ -354,7 +789,7 @@ pub trait RuntimeFactorsBuilder {
     /// The factors type to build.
     type Factors: RuntimeFactors;
     /// CLI arguments not included in [`FactorsConfig`] needed  to build the [`RuntimeFactors`].
-    type CliArgs: clap::Args;
+    type CliArgs: clap::Args + Clone;
     /// The wrapped runtime config type.
     type RuntimeConfig: Into<<Self::Factors as RuntimeFactors>::RuntimeConfig>;
 
@@ -362,7 +797,26 @@ pub trait RuntimeFactorsBuilder {
     fn build(
         config: &FactorsConfig,
         args: &Self::CliArgs,
-    ) -> anyhow::Result<(Self::Factors, Self::RuntimeConfig)>;
+    ) -> impl Future<Output = anyhow::Result<(Self::Factors, Self::RuntimeConfig)>> + Send;
+
+    /// Update the engine config based on the runtime config.
+    fn update_core_config(
+        runtime_config: &Self::RuntimeConfig,
+        config: &mut spin_core::Config,
+    ) -> anyhow::Result<()> {
+        let _ = (runtime_config, config);
+        Ok(())
+    }
+
+    /// The tuning of the runtime triggers of the given type run on, read
+    /// from the runtime config file before that runtime is built.
+    fn executor_tuning(
+        runtime_config_file: Option<&Path>,
+        trigger_type: &str,
+    ) -> anyhow::Result<ExecutorTuning> {
+        let _ = (runtime_config_file, trigger_type);
+        Ok(ExecutorTuning::default())
+    }
 
     /// Configure the factors in the executor.
     fn configure_app<U: Send + 'static>(
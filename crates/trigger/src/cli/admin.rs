@@ -0,0 +1,466 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use spin_core::async_trait;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ExecutorHooks, FactorsInstanceBuilder};
+use tokio::{
+    sync::{mpsc, Notify},
+    task::AbortHandle,
+};
+
+/// The state of a running application exposed and controlled by the admin
+/// API.
+pub struct AdminState {
+    trigger_type: &'static str,
+    started_at: DateTime<Utc>,
+    /// The runtime options the trigger was started with.
+    options: Value,
+    loaded: Mutex<LoadedApp>,
+    disabled: Mutex<HashSet<String>>,
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Notified when the last in-flight instance finishes while draining.
+    drained: Notify,
+    /// Set once the trigger has stopped, so that shutdown hooks may run even
+    /// though the app was drained.
+    stopped: AtomicBool,
+    reload_requests: mpsc::Sender<()>,
+}
+
+/// The currently loaded application, updated when it is reloaded.
+#[derive(Default)]
+struct LoadedApp {
+    loaded_at: Option<DateTime<Utc>>,
+    components: Vec<String>,
+    config: Value,
+}
+
+impl AdminState {
+    /// Creates the admin state for a trigger, returning it along with the
+    /// receiver of reloads requested through the API.
+    pub fn new(trigger_type: &'static str, options: Value) -> (Arc<Self>, mpsc::Receiver<()>) {
+        let (reload_requests, reloads) = mpsc::channel(1);
+        let state = Self {
+            trigger_type,
+            started_at: Utc::now(),
+            options,
+            loaded: Default::default(),
+            disabled: Default::default(),
+            draining: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            drained: Notify::new(),
+            stopped: AtomicBool::new(false),
+            reload_requests,
+        };
+        (Arc::new(state), reloads)
+    }
+
+    /// A hook that keeps this state up to date with the app and applies its
+    /// controls to new instances.
+    pub fn hook(self: &Arc<Self>) -> AdminHook {
+        AdminHook {
+            state: self.clone(),
+        }
+    }
+
+    /// Waits until the app has been drained through the API.
+    pub async fn drained(&self) {
+        loop {
+            // Created before checking, so that a notification in between
+            // isn't missed.
+            let notified = self.drained.notified();
+            if self.is_drained() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Records that the trigger has stopped taking work, allowing instances
+    /// for the app's shutdown hooks even if it was drained.
+    pub fn stopped(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    fn is_drained(&self) -> bool {
+        self.draining.load(Ordering::SeqCst) && self.in_flight.load(Ordering::SeqCst) == 0
+    }
+
+    fn is_enabled(&self, component_id: &str) -> bool {
+        !self.disabled.lock().unwrap().contains(component_id)
+    }
+
+    /// Handles an admin API request, returning the response status and body.
+    fn handle(&self, method: &hyper::Method, path: &str) -> (StatusCode, Value) {
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        let is_get = method == hyper::Method::GET;
+        let is_post = method == hyper::Method::POST;
+        match segments.as_slice() {
+            ["status"] if is_get => (StatusCode::OK, self.status()),
+            ["components"] if is_get => (StatusCode::OK, self.components()),
+            ["config"] if is_get => {
+                let loaded = self.loaded.lock().unwrap();
+                let config = json!({ "options": self.options, "app": loaded.config });
+                (StatusCode::OK, config)
+            }
+            ["reload"] if is_post => match self.reload_requests.try_send(()) {
+                // A full channel means a reload is already pending.
+                Ok(()) | Err(mpsc::error::TrySendError::Full(())) => {
+                    (StatusCode::ACCEPTED, json!({ "reload": "requested" }))
+                }
+                Err(mpsc::error::TrySendError::Closed(())) => error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!(
+                        "The '{}' trigger doesn't support reloading",
+                        self.trigger_type
+                    ),
+                ),
+            },
+            ["drain"] if is_post => {
+                self.draining.store(true, Ordering::SeqCst);
+                self.drained.notify_waiters();
+                let in_flight = self.in_flight.load(Ordering::SeqCst);
+                (StatusCode::ACCEPTED, json!({ "in_flight": in_flight }))
+            }
+            ["components", id, action @ ("enable" | "disable")] if is_post => {
+                let exists = self
+                    .loaded
+                    .lock()
+                    .unwrap()
+                    .components
+                    .iter()
+                    .any(|c| c == *id);
+                if !exists {
+                    return error(StatusCode::NOT_FOUND, format!("No component '{id}'"));
+                }
+                let mut disabled = self.disabled.lock().unwrap();
+                if *action == "enable" {
+                    disabled.remove(*id);
+                } else {
+                    disabled.insert(id.to_string());
+                }
+                let enabled = !disabled.contains(*id);
+                (StatusCode::OK, json!({ "id": id, "enabled": enabled }))
+            }
+            ["status" | "components" | "config" | "reload" | "drain"]
+            | ["components", _, "enable" | "disable"] => error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("{method} is not allowed on {path}"),
+            ),
+            _ => error(StatusCode::NOT_FOUND, format!("No admin endpoint {path}")),
+        }
+    }
+
+    fn status(&self) -> Value {
+        let loaded = self.loaded.lock().unwrap();
+        let uptime = Utc::now() - self.started_at;
+        let state = if self.draining.load(Ordering::SeqCst) {
+            "draining"
+        } else {
+            "running"
+        };
+        json!({
+            "trigger": self.trigger_type,
+            "pid": std::process::id(),
+            "state": state,
+            "started_at": self.started_at.to_rfc3339(),
+            "uptime_secs": uptime.num_seconds(),
+            "app_loaded_at": loaded.loaded_at.map(|t| t.to_rfc3339()),
+            "in_flight": self.in_flight.load(Ordering::SeqCst),
+        })
+    }
+
+    fn components(&self) -> Value {
+        let loaded = self.loaded.lock().unwrap();
+        let components: Vec<_> = loaded
+            .components
+            .iter()
+            .map(|id| json!({ "id": id, "enabled": self.is_enabled(id) }))
+            .collect();
+        Value::Array(components)
+    }
+
+    fn app_loaded(&self, app: &spin_app::App) {
+        let components: Vec<_> = app.components().map(|c| c.id().to_owned()).collect();
+        let triggers: Vec<_> = app
+            .triggers_with_type(self.trigger_type)
+            .map(|trigger| {
+                json!({
+                    "id": trigger.id(),
+                    "config": trigger.typed_config::<Value>().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let config = json!({ "components": components, "triggers": triggers });
+        *self.loaded.lock().unwrap() = LoadedApp {
+            loaded_at: Some(Utc::now()),
+            components,
+            config,
+        };
+    }
+
+    fn start_instance(self: &Arc<Self>, component_id: &str) -> anyhow::Result<InFlight> {
+        if self.draining.load(Ordering::SeqCst) && !self.stopped.load(Ordering::SeqCst) {
+            bail!("The application is draining and isn't accepting new work");
+        }
+        if !self.is_enabled(component_id) {
+            bail!("Component '{component_id}' is disabled through the admin API");
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(InFlight {
+            state: self.clone(),
+        })
+    }
+}
+
+/// Counts an instance as in flight until it is dropped along with its store.
+struct InFlight {
+    state: Arc<AdminState>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.state.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 && self.state.is_drained() {
+            self.state.drained.notify_waiters();
+        }
+    }
+}
+
+fn error(status: StatusCode, message: String) -> (StatusCode, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// An [`ExecutorHooks`] that reports the loaded app to the admin API, and
+/// refuses new instances of disabled components or while draining.
+pub struct AdminHook {
+    state: Arc<AdminState>,
+}
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for AdminHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        self.state.app_loaded(configured_app.app());
+        Ok(())
+    }
+
+    fn prepare_instance(&self, builder: &mut FactorsInstanceBuilder<F, U>) -> anyhow::Result<()> {
+        let component_id = builder.app_component().id().to_string();
+        let in_flight = self.state.start_instance(&component_id)?;
+        builder.store_builder().keep_alive(in_flight);
+        Ok(())
+    }
+}
+
+/// The admin API's socket, which stops being served and is removed when
+/// dropped.
+pub struct AdminSocket {
+    path: PathBuf,
+    server: AbortHandle,
+}
+
+impl Drop for AdminSocket {
+    fn drop(&mut self) {
+        self.server.abort();
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            tracing::warn!(
+                "Failed to remove admin socket {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Serves the admin API on a Unix socket at `socket_path` until the returned
+/// [`AdminSocket`] is dropped.
+///
+/// Binds the socket before returning, so that a bad path is reported at
+/// startup; connections are then served in the background.
+#[cfg(unix)]
+pub async fn serve_admin(
+    socket_path: &Path,
+    state: Arc<AdminState>,
+) -> anyhow::Result<AdminSocket> {
+    use std::os::unix::fs::FileTypeExt;
+
+    use anyhow::Context;
+    use http_body_util::Full;
+    use hyper::{body::Bytes, header::CONTENT_TYPE, server::conn::http1, service::service_fn};
+    use hyper_util::rt::TokioIo;
+    use spin_common::ui::quoted_path;
+
+    // A socket left behind by a previous run would make binding fail, but
+    // anything else at the path is left alone.
+    match std::fs::symlink_metadata(socket_path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(socket_path).with_context(|| {
+                format!(
+                    "Failed to remove stale admin socket {}",
+                    quoted_path(socket_path)
+                )
+            })?;
+        }
+        Ok(_) => bail!(
+            "Unable to listen for admin requests on {}: a file which isn't a socket already exists there",
+            quoted_path(socket_path)
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!("Failed to inspect admin socket {}", quoted_path(socket_path))
+            })
+        }
+    }
+    let listener = tokio::net::UnixListener::bind(socket_path).with_context(|| {
+        format!(
+            "Unable to listen for admin requests on {}",
+            quoted_path(socket_path)
+        )
+    })?;
+    terminal::step!("Serving", "admin API on {}", quoted_path(socket_path));
+
+    let server = tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::error!("Failed to accept admin connection: {err}");
+                    continue;
+                }
+            };
+            let state = state.clone();
+            let service = service_fn(move |req: hyper::Request<hyper::body::Incoming>| {
+                let (status, body) = state.handle(req.method(), req.uri().path());
+                let response = hyper::Response::builder()
+                    .status(status)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Full::new(Bytes::from(body.to_string())))
+                    .unwrap();
+                async move { Ok::<_, std::convert::Infallible>(response) }
+            });
+            tokio::spawn(async move {
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::warn!("Error serving admin connection: {err:?}");
+                }
+            });
+        }
+    });
+    Ok(AdminSocket {
+        path: socket_path.to_owned(),
+        server: server.abort_handle(),
+    })
+}
+
+/// Serves the admin API on a Unix socket at `socket_path`.
+#[cfg(not(unix))]
+pub async fn serve_admin(
+    socket_path: &Path,
+    state: Arc<AdminState>,
+) -> anyhow::Result<AdminSocket> {
+    let _ = (socket_path, state);
+    bail!("The admin API requires Unix sockets, which aren't supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Method;
+
+    use super::*;
+
+    fn state() -> (Arc<AdminState>, mpsc::Receiver<()>) {
+        let (state, reloads) = AdminState::new("http", json!({}));
+        state.loaded.lock().unwrap().components = vec!["hello".into(), "goodbye".into()];
+        (state, reloads)
+    }
+
+    #[test]
+    fn components_can_be_disabled() {
+        let (state, _reloads) = state();
+        let (status, _) = state.handle(&Method::POST, "/components/hello/disable");
+        assert_eq!(StatusCode::OK, status);
+        assert!(state.start_instance("hello").is_err());
+        assert!(state.start_instance("goodbye").is_ok());
+
+        let (_, components) = state.handle(&Method::GET, "/components");
+        assert_eq!(json!({ "id": "hello", "enabled": false }), components[0]);
+
+        state.handle(&Method::POST, "/components/hello/enable");
+        assert!(state.start_instance("hello").is_ok());
+
+        let (status, _) = state.handle(&Method::POST, "/components/nope/disable");
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let (status, _) = state.handle(&Method::GET, "/components/hello/disable");
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, status);
+    }
+
+    #[tokio::test]
+    async fn draining_waits_for_in_flight_instances() {
+        let (state, _reloads) = state();
+        let in_flight = state.start_instance("hello").unwrap();
+
+        let (status, body) = state.handle(&Method::POST, "/drain");
+        assert_eq!(StatusCode::ACCEPTED, status);
+        assert_eq!(1, body["in_flight"]);
+        assert!(state.start_instance("hello").is_err());
+        assert!(!state.is_drained());
+
+        let drained = tokio::spawn({
+            let state = state.clone();
+            async move { state.drained().await }
+        });
+        drop(in_flight);
+        drained.await.unwrap();
+        assert_eq!("draining", state.status()["state"]);
+
+        // Once the trigger stops, shutdown hooks may still run
+        state.stopped();
+        assert!(state.start_instance("hello").is_ok());
+    }
+
+    #[test]
+    fn reloads_are_requested() {
+        let (state, mut reloads) = state();
+        let (status, _) = state.handle(&Method::POST, "/reload");
+        assert_eq!(StatusCode::ACCEPTED, status);
+        assert!(reloads.try_recv().is_ok());
+
+        drop(reloads);
+        let (status, _) = state.handle(&Method::POST, "/reload");
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn admin_socket_replaces_only_stale_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state, _reloads) = state();
+
+        let file_path = dir.path().join("not-a-socket");
+        std::fs::write(&file_path, "keep me").unwrap();
+        assert!(serve_admin(&file_path, state.clone()).await.is_err());
+        assert_eq!("keep me", std::fs::read_to_string(&file_path).unwrap());
+
+        let socket_path = dir.path().join("admin.sock");
+        drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+        let socket = serve_admin(&socket_path, state).await.unwrap();
+        assert!(socket_path.exists());
+        drop(socket);
+        assert!(!socket_path.exists());
+    }
+}
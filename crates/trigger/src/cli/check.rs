@@ -0,0 +1,131 @@
+//! The `--check` preflight mode.
+//!
+//! Rather than running the trigger, `--check` loads the app, compiles its
+//! components, configures all factors and makes one test connection to each
+//! key-value store and SQLite database the app uses, then reports which of
+//! these passed. It exits with a failure status if any did, so it can gate
+//! deployments or serve as an init container.
+//!
+//! The checks don't change any store or database: hooks which would, such as
+//! those for `--key-value` and `--sqlite`, are skipped. Connections which
+//! components open themselves, e.g. to Redis, PostgreSQL or MySQL servers,
+//! go to addresses chosen at runtime, so they aren't checked, and neither
+//! are LLM backends.
+
+use std::{future::Future, time::Duration};
+
+use anyhow::Context as _;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factor_sqlite::SqliteFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::FactorsExecutorApp;
+
+/// How long a backend has to respond to its test connection.
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The key looked up to test a connection to a key-value store.
+const PROBE_KEY: &str = "spin-check-probe";
+
+/// The outcomes of the preflight checks.
+#[derive(Default)]
+pub struct CheckReport {
+    checks: Vec<(String, anyhow::Result<()>)>,
+}
+
+impl CheckReport {
+    /// Records the outcome of the check with the given description.
+    pub fn record(&mut self, description: impl Into<String>, result: anyhow::Result<()>) {
+        self.checks.push((description.into(), result));
+    }
+
+    /// Prints each check's outcome and a summary, failing if any check did.
+    pub fn finish(self, trigger_type: &str) -> anyhow::Result<()> {
+        for (description, result) in &self.checks {
+            match result {
+                Ok(()) => {
+                    terminal::cprint!(terminal::colors::bold_green(), "  pass");
+                    println!("  {description}");
+                }
+                Err(err) => {
+                    terminal::cprint!(terminal::colors::bold_red(), "  FAIL");
+                    println!("  {description}: {err:#}");
+                }
+            }
+        }
+        let failures = self.checks.iter().filter(|(_, res)| res.is_err()).count();
+        let total = self.checks.len();
+        if failures > 0 {
+            anyhow::bail!("{failures} of {total} checks failed for the '{trigger_type}' trigger");
+        }
+        terminal::step!(
+            "Passed",
+            "all {total} checks for the '{trigger_type}' trigger"
+        );
+        Ok(())
+    }
+}
+
+/// Checks that each component can be compiled and prepared for
+/// instantiation, and that each backend the app uses can be connected to.
+pub async fn check_app<F: RuntimeFactors, U: Send + 'static>(
+    app: &FactorsExecutorApp<F, U>,
+    report: &mut CheckReport,
+) {
+    for component in app.app().components() {
+        let id = component.id();
+        let result = app.prepare(id).await.map(|_| ());
+        report.record(format!("component '{id}'"), result);
+    }
+
+    let configured_app = app.configured_app();
+    if let Ok(kv) = configured_app.app_state::<KeyValueFactor>() {
+        for label in kv.used_stores() {
+            let description = match kv.store_summary(&label) {
+                Some(summary) => format!("key-value store '{label}' ({summary})"),
+                None => format!("key-value store '{label}'"),
+            };
+            let result = with_timeout(async {
+                let store = kv
+                    .store_manager()
+                    .get(&label)
+                    .await
+                    .context("failed to open store")?;
+                store
+                    .exists(PROBE_KEY)
+                    .await
+                    .context("failed to read from store")?;
+                Ok(())
+            })
+            .await;
+            report.record(description, result);
+        }
+    }
+    if let Ok(sqlite) = configured_app.app_state::<SqliteFactor>() {
+        for label in sqlite.used_databases() {
+            let mut description = format!("SQLite database '{label}'");
+            let result = with_timeout(async {
+                let connection = sqlite
+                    .get_connection(&label)
+                    .await
+                    .context("no database is configured with this label")?
+                    .context("failed to connect to database")?;
+                if let Some(summary) = connection.summary() {
+                    description = format!("{description} ({summary})");
+                }
+                connection
+                    .query("SELECT 1", vec![])
+                    .await
+                    .context("failed to query database")?;
+                Ok(())
+            })
+            .await;
+            report.record(description, result);
+        }
+    }
+}
+
+async fn with_timeout(check: impl Future<Output = anyhow::Result<()>>) -> anyhow::Result<()> {
+    tokio::time::timeout(BACKEND_TIMEOUT, check)
+        .await
+        .with_context(|| format!("timed out after {}s", BACKEND_TIMEOUT.as_secs()))?
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use spin_core::{async_trait, LimitExceeded};
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ExecutorHooks, FactorsInstanceBuilder};
+use tokio::sync::Semaphore;
+
+/// Resource limits applied to each instance of a component.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComponentLimits {
+    /// The maximum memory an instance may allocate, in bytes.
+    pub max_memory: Option<usize>,
+    /// How long an instance may execute before it is interrupted.
+    pub max_execution_time: Option<Duration>,
+    /// The fuel an instance may consume before it is interrupted.
+    pub max_fuel: Option<u64>,
+    /// The maximum number of concurrent instances of the component.
+    pub max_instances: Option<usize>,
+}
+
+impl ComponentLimits {
+    /// Returns these limits with any unset limit taken from `fallback`.
+    pub fn or(&self, fallback: &Self) -> Self {
+        Self {
+            max_memory: self.max_memory.or(fallback.max_memory),
+            max_execution_time: self.max_execution_time.or(fallback.max_execution_time),
+            max_fuel: self.max_fuel.or(fallback.max_fuel),
+            max_instances: self.max_instances.or(fallback.max_instances),
+        }
+    }
+}
+
+/// Resource limits for all components of an app.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComponentLimitsConfig {
+    /// Limits for every component.
+    pub default: ComponentLimits,
+    /// Per-component overrides of the default limits, by component ID.
+    pub components: HashMap<String, ComponentLimits>,
+}
+
+impl ComponentLimitsConfig {
+    /// Returns the limits for the given component.
+    pub fn limits_for(&self, component_id: &str) -> ComponentLimits {
+        match self.components.get(component_id) {
+            Some(limits) => limits.or(&self.default),
+            None => self.default.clone(),
+        }
+    }
+
+    /// Returns true if any component has a fuel limit, which requires fuel
+    /// metering to be enabled in the engine.
+    pub fn uses_fuel(&self) -> bool {
+        self.default.max_fuel.is_some() || self.components.values().any(|l| l.max_fuel.is_some())
+    }
+
+    /// Returns true if no limits are set.
+    pub fn is_empty(&self) -> bool {
+        self.default == ComponentLimits::default()
+            && self
+                .components
+                .values()
+                .all(|l| *l == ComponentLimits::default())
+    }
+}
+
+/// An [`ExecutorHooks`] that applies [`ComponentLimits`] to each instance.
+pub struct ComponentLimitsHook {
+    config: ComponentLimitsConfig,
+    instance_permits: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ComponentLimitsHook {
+    pub fn new(config: ComponentLimitsConfig) -> Self {
+        Self {
+            config,
+            instance_permits: Default::default(),
+        }
+    }
+
+    fn instance_permits(&self, component_id: &str, max_instances: usize) -> Arc<Semaphore> {
+        self.instance_permits
+            .lock()
+            .unwrap()
+            .entry(component_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_instances)))
+            .clone()
+    }
+}
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for ComponentLimitsHook {
+    fn prepare_instance(&self, builder: &mut FactorsInstanceBuilder<F, U>) -> anyhow::Result<()> {
+        let component_id = builder.app_component().id().to_string();
+        let limits = self.config.limits_for(&component_id);
+        let store_builder = builder.store_builder();
+
+        if let Some(max_instances) = limits.max_instances {
+            let permit = self
+                .instance_permits(&component_id, max_instances)
+                .try_acquire_owned()
+                .map_err(|_| LimitExceeded::Instances(max_instances))
+                .with_context(|| format!("couldn't instantiate component {component_id:?}"))?;
+            store_builder.keep_alive(permit);
+        }
+        if let Some(max_memory) = limits.max_memory {
+            store_builder.max_memory_size(max_memory);
+        }
+        if let Some(max_execution_time) = limits.max_execution_time {
+            store_builder.execution_time_limit(max_execution_time);
+        }
+        if let Some(max_fuel) = limits.max_fuel {
+            store_builder.fuel(max_fuel);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn component_limits_fall_back_to_default() {
+        let config = ComponentLimitsConfig {
+            default: ComponentLimits {
+                max_memory: Some(1024),
+                max_instances: Some(10),
+                ..Default::default()
+            },
+            components: [(
+                "hot".to_string(),
+                ComponentLimits {
+                    max_instances: Some(2),
+                    max_fuel: Some(1000),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+
+        let hot = config.limits_for("hot");
+        assert_eq!(hot.max_memory, Some(1024));
+        assert_eq!(hot.max_instances, Some(2));
+        assert_eq!(hot.max_fuel, Some(1000));
+
+        let other = config.limits_for("other");
+        assert_eq!(other, config.default);
+
+        assert!(config.uses_fuel());
+        assert!(!config.is_empty());
+        assert!(ComponentLimitsConfig::default().is_empty());
+    }
+}
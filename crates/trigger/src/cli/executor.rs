@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use tokio::runtime::Runtime;
+
+/// Tuning of the Tokio runtime a trigger's process runs on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutorTuning {
+    /// The number of worker threads which run async tasks, such as handling
+    /// requests.
+    pub worker_threads: Option<usize>,
+    /// The maximum number of threads which run blocking work, such as local
+    /// LLM inferencing and SQLite queries.
+    pub max_blocking_threads: Option<usize>,
+}
+
+impl ExecutorTuning {
+    /// Returns this tuning with any unset option taken from `fallback`.
+    pub fn or(&self, fallback: &Self) -> Self {
+        Self {
+            worker_threads: self.worker_threads.or(fallback.worker_threads),
+            max_blocking_threads: self.max_blocking_threads.or(fallback.max_blocking_threads),
+        }
+    }
+
+    /// Builds a multi-threaded runtime with this tuning.
+    ///
+    /// Each trigger type runs in its own process, so this is used to build
+    /// that process's runtime before anything runs on it.
+    pub fn build_runtime(&self) -> anyhow::Result<Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        builder.build().context("failed to build the Tokio runtime")
+    }
+}
+
+/// Runtime tuning for all triggers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutorConfig {
+    /// Tuning for every trigger.
+    pub default: ExecutorTuning,
+    /// Per-trigger overrides of the default tuning, by trigger type.
+    pub triggers: HashMap<String, ExecutorTuning>,
+}
+
+impl ExecutorConfig {
+    /// Returns the tuning for the given trigger type.
+    pub fn tuning_for(&self, trigger_type: &str) -> ExecutorTuning {
+        match self.triggers.get(trigger_type) {
+            Some(tuning) => tuning.or(&self.default),
+            None => self.default.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trigger_tuning_falls_back_to_default() {
+        let config = ExecutorConfig {
+            default: ExecutorTuning {
+                worker_threads: Some(8),
+                max_blocking_threads: Some(64),
+            },
+            triggers: [(
+                "http".to_string(),
+                ExecutorTuning {
+                    worker_threads: Some(4),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+        assert_eq!(
+            config.tuning_for("http"),
+            ExecutorTuning {
+                worker_threads: Some(4),
+                max_blocking_threads: Some(64),
+            }
+        );
+        assert_eq!(config.tuning_for("redis"), config.default);
+    }
+
+    #[test]
+    fn runtimes_are_built_with_the_tuned_threads() {
+        let tuning = ExecutorTuning {
+            worker_threads: Some(1),
+            max_blocking_threads: Some(1),
+        };
+        let runtime = tuning.build_runtime().unwrap();
+        let threads = runtime.block_on(async {
+            let tasks = (0..8)
+                .map(|_| {
+                    tokio::spawn(async {
+                        tokio::task::yield_now().await;
+                        std::thread::current().id()
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut threads = std::collections::HashSet::new();
+            for task in tasks {
+                threads.insert(task.await.unwrap());
+            }
+            threads
+        });
+        assert_eq!(threads.len(), 1);
+    }
+}
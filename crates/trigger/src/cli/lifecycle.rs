@@ -0,0 +1,105 @@
+//! Hooks which components may export to run code when the app starts and
+//! stops, e.g. to warm caches, run migrations or flush buffered data.
+//!
+//! Each hook runs in its own instance of the component, which has the same
+//! access to host interfaces as any other.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use spin_factors::RuntimeFactors;
+use spin_world::exports::spin::lifecycle::{shutdown, startup};
+
+use crate::{Trigger, TriggerApp, TriggerInstanceState};
+
+/// How long the app's shutdown hooks may take, together, before they are
+/// abandoned.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Calls `on-start` in each component of the app which exports it, one at a
+/// time.
+///
+/// Fails if any hook fails, so that the app isn't run half prepared. Lazily
+/// loaded components aren't loaded to look for hooks, so only those already
+/// loaded are started.
+pub async fn run_startup_hooks<T, F>(trigger_app: &TriggerApp<T, F>) -> anyhow::Result<()>
+where
+    T: Trigger<F>,
+    T::InstanceState: Default,
+    F: RuntimeFactors,
+{
+    let component_ids =
+        exporting_components(trigger_app, |pre| startup::GuestIndices::new(pre).is_ok());
+    for component_id in component_ids {
+        tracing::info!("Running startup hook of component {component_id}");
+        let instance_builder = trigger_app.prepare(&component_id).await?;
+        let (instance, mut store) = instance_builder.instantiate(Default::default()).await?;
+        let pre = instance.instance_pre(&store);
+        let guest = startup::GuestIndices::new(&pre)?.load(&mut store, &instance)?;
+        guest
+            .call_on_start(&mut store)
+            .await?
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("startup hook of component {component_id:?} failed"))?;
+    }
+    Ok(())
+}
+
+/// Calls `on-shutdown` in each loaded component of the app which exports it,
+/// all at once.
+///
+/// The app is stopping regardless, so failures are logged rather than
+/// returned.
+pub async fn run_shutdown_hooks<T, F>(trigger_app: &TriggerApp<T, F>)
+where
+    T: Trigger<F>,
+    T::InstanceState: Default,
+    F: RuntimeFactors,
+{
+    let component_ids =
+        exporting_components(trigger_app, |pre| shutdown::GuestIndices::new(pre).is_ok());
+    let hooks = component_ids.iter().map(|component_id| async move {
+        tracing::info!("Running shutdown hook of component {component_id}");
+        let result = async {
+            let instance_builder = trigger_app.prepare(component_id).await?;
+            let (instance, mut store) = instance_builder.instantiate(Default::default()).await?;
+            let pre = instance.instance_pre(&store);
+            let guest = shutdown::GuestIndices::new(&pre)?.load(&mut store, &instance)?;
+            guest
+                .call_on_shutdown(&mut store)
+                .await?
+                .map_err(anyhow::Error::msg)
+        };
+        if let Err(err) = result.await {
+            tracing::error!("Shutdown hook of component {component_id} failed: {err:#}");
+        }
+    });
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, futures::future::join_all(hooks))
+        .await
+        .is_err()
+    {
+        tracing::warn!("Shutdown hooks didn't finish within {SHUTDOWN_TIMEOUT:?}; exiting anyway");
+    }
+}
+
+/// Returns the IDs of the loaded components of the app for which `exports`
+/// returns true.
+fn exporting_components<T, F>(
+    trigger_app: &TriggerApp<T, F>,
+    exports: impl Fn(&spin_core::InstancePre<TriggerInstanceState<T, F>>) -> bool,
+) -> Vec<String>
+where
+    T: Trigger<F>,
+    F: RuntimeFactors,
+{
+    trigger_app
+        .app()
+        .components()
+        .filter(|component| {
+            trigger_app
+                .loaded_instance_pre(component.id())
+                .is_some_and(|pre| exports(&pre))
+        })
+        .map(|component| component.id().to_owned())
+        .collect()
+}
@@ -0,0 +1,79 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use anyhow::Context;
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+
+/// The path metrics are served on.
+const METRICS_PATH: &str = "/metrics";
+
+/// The content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Serves Prometheus metrics on `listen_addr`.
+///
+/// Binds the listener before returning, so that a bad address is reported
+/// at startup; connections are then served in the background.
+pub async fn serve_metrics(listen_addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen_addr)
+        .await
+        .with_context(|| format!("Unable to listen for metrics on {listen_addr}"))?;
+    spin_telemetry::metrics::enable_prometheus();
+    terminal::step!("Serving", "metrics on http://{listen_addr}{METRICS_PATH}");
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::error!("Failed to accept metrics connection: {err}");
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .await
+                {
+                    tracing::warn!("Error serving metrics connection: {err:?}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let response = if req.uri().path() != METRICS_PATH {
+        status_response(StatusCode::NOT_FOUND)
+    } else if req.method() != Method::GET {
+        status_response(StatusCode::METHOD_NOT_ALLOWED)
+    } else {
+        match spin_telemetry::metrics::prometheus_text() {
+            Ok(text) => Response::builder()
+                .header(CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+                .body(Full::new(Bytes::from(text)))
+                .unwrap(),
+            Err(err) => {
+                tracing::error!("Failed to collect metrics: {err:#}");
+                status_response(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    };
+    Ok(response)
+}
+
+fn status_response(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::default())
+        .unwrap()
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::Context;
+use serde_json::json;
+use spin_core::{
+    async_trait,
+    wasmtime::{FrameInfo, WasmBacktrace},
+};
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ExecutorHooks, FactorsInstanceBuilder};
+
+/// The format profiles are written in.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// speedscope's JSON format, viewable at <https://www.speedscope.app>.
+    #[default]
+    Speedscope,
+    /// Collapsed stacks, as taken by `flamegraph.pl` and `inferno-flamegraph`.
+    Collapsed,
+}
+
+impl ProfileFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Speedscope => "speedscope.json",
+            Self::Collapsed => "folded",
+        }
+    }
+}
+
+/// Which components are profiled, and how their profiles are written.
+#[derive(Clone, Debug)]
+pub struct ProfilingConfig {
+    /// The IDs of the components to profile.
+    pub components: HashSet<String>,
+    /// The directory profiles are written to.
+    pub dir: PathBuf,
+    pub format: ProfileFormat,
+    /// Whether to write one profile per component covering all of its
+    /// invocations, rather than one profile per invocation.
+    pub aggregate: bool,
+}
+
+/// An [`ExecutorHooks`] that samples the stacks of the configured
+/// components' instances and writes them as profiles.
+///
+/// Stacks are sampled once every epoch tick while the guest executes, so a
+/// profile shows where the guest spends its CPU time, excluding time spent
+/// waiting on the host.
+pub struct ProfilingHook {
+    config: ProfilingConfig,
+    /// The number of invocations profiled so far, to name their profiles.
+    invocations: AtomicU64,
+    /// The profiles of all invocations, by component ID, if aggregating.
+    aggregates: Arc<Mutex<HashMap<String, Profile>>>,
+}
+
+impl ProfilingHook {
+    pub fn new(config: ProfilingConfig) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&config.dir).with_context(|| {
+            format!(
+                "failed to create profile directory {}",
+                config.dir.display()
+            )
+        })?;
+        Ok(Self {
+            config,
+            invocations: AtomicU64::new(0),
+            aggregates: Default::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for ProfilingHook {
+    fn prepare_instance(&self, builder: &mut FactorsInstanceBuilder<F, U>) -> anyhow::Result<()> {
+        let component_id = builder.app_component().id().to_string();
+        if !self.config.components.contains(&component_id) {
+            return Ok(());
+        }
+
+        let profile = Arc::new(Mutex::new(Profile::default()));
+        let sampled = profile.clone();
+        let store_builder = builder.store_builder();
+        store_builder.sample_stacks(move |backtrace| sampled.lock().unwrap().add(backtrace));
+
+        let component_file = sanitize_filename::sanitize(&component_id);
+        let extension = self.config.format.extension();
+        let (name, file_name, aggregate) = if self.config.aggregate {
+            let file_name = format!("{component_file}.{extension}");
+            (component_id, file_name, Some(self.aggregates.clone()))
+        } else {
+            let invocation = self.invocations.fetch_add(1, Ordering::Relaxed);
+            let file_name = format!("{component_file}-{invocation}.{extension}");
+            (format!("{component_id} #{invocation}"), file_name, None)
+        };
+        // Written once the instance's store is dropped
+        store_builder.keep_alive(ProfileWriter {
+            profile,
+            name,
+            path: self.config.dir.join(file_name),
+            format: self.config.format,
+            aggregate,
+        });
+        Ok(())
+    }
+}
+
+/// Writes an instance's profile when dropped along with its store.
+struct ProfileWriter {
+    profile: Arc<Mutex<Profile>>,
+    /// The name of the profile, as shown by viewers.
+    name: String,
+    path: PathBuf,
+    format: ProfileFormat,
+    /// Where to add the profile to its component's profile, if aggregating.
+    aggregate: Option<Arc<Mutex<HashMap<String, Profile>>>>,
+}
+
+impl Drop for ProfileWriter {
+    fn drop(&mut self) {
+        let profile = std::mem::take(&mut *self.profile.lock().unwrap());
+        let result = match &self.aggregate {
+            Some(aggregates) => {
+                let mut aggregates = aggregates.lock().unwrap();
+                let aggregate = aggregates.entry(self.name.clone()).or_default();
+                aggregate.merge(profile);
+                aggregate.write(&self.path, self.format, &self.name)
+            }
+            None => profile.write(&self.path, self.format, &self.name),
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to write profile {}: {err:#}", self.path.display());
+        }
+    }
+}
+
+/// Sampled stacks, from their outermost frames, with how many times each
+/// was sampled.
+#[derive(Debug, Default)]
+struct Profile {
+    stacks: BTreeMap<Vec<String>, u64>,
+}
+
+impl Profile {
+    fn add(&mut self, backtrace: &WasmBacktrace) {
+        // Backtraces start at the innermost frame
+        let stack = backtrace.frames().iter().rev().map(frame_name).collect();
+        self.add_stack(stack, 1);
+    }
+
+    fn add_stack(&mut self, stack: Vec<String>, count: u64) {
+        if !stack.is_empty() {
+            *self.stacks.entry(stack).or_default() += count;
+        }
+    }
+
+    fn merge(&mut self, other: Profile) {
+        for (stack, count) in other.stacks {
+            self.add_stack(stack, count);
+        }
+    }
+
+    fn write(&self, path: &Path, format: ProfileFormat, name: &str) -> anyhow::Result<()> {
+        let contents = match format {
+            ProfileFormat::Speedscope => serde_json::to_string(&self.speedscope(name))?,
+            ProfileFormat::Collapsed => self.collapsed(),
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Formats the profile as lines of `outer;inner <count>`.
+    fn collapsed(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, count)| {
+                // Frame names can't contain the separator
+                let frames: Vec<_> = stack.iter().map(|frame| frame.replace(';', ":")).collect();
+                format!("{} {count}\n", frames.join(";"))
+            })
+            .collect()
+    }
+
+    /// Formats the profile as a speedscope sampled profile.
+    ///
+    /// See <https://github.com/jlfwong/speedscope/wiki/Importing-from-custom-sources>.
+    fn speedscope(&self, name: &str) -> serde_json::Value {
+        let mut frames: Vec<&str> = vec![];
+        let mut frame_indices: HashMap<&str, usize> = HashMap::new();
+        let mut samples = vec![];
+        let mut weights = vec![];
+        for (stack, count) in &self.stacks {
+            let sample: Vec<usize> = stack
+                .iter()
+                .map(|frame| {
+                    *frame_indices.entry(frame.as_str()).or_insert_with(|| {
+                        frames.push(frame.as_str());
+                        frames.len() - 1
+                    })
+                })
+                .collect();
+            samples.push(sample);
+            weights.push(*count);
+        }
+        let total: u64 = weights.iter().sum();
+        json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "name": name,
+            "exporter": "spin",
+            "shared": {
+                "frames": frames.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+            },
+            "profiles": [{
+                "type": "sampled",
+                "name": name,
+                "unit": "none",
+                "startValue": 0,
+                "endValue": total,
+                "samples": samples,
+                "weights": weights,
+            }],
+        })
+    }
+}
+
+fn frame_name(frame: &FrameInfo) -> String {
+    match frame.func_name() {
+        Some(name) => name.to_string(),
+        None => format!(
+            "{}!wasm-function[{}]",
+            frame.module().name().unwrap_or("<module>"),
+            frame.func_index()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(frames: &[&str]) -> Vec<String> {
+        frames.iter().map(|frame| frame.to_string()).collect()
+    }
+
+    fn profile() -> Profile {
+        let mut profile = Profile::default();
+        profile.add_stack(stack(&["main", "handle", "parse"]), 3);
+        profile.add_stack(stack(&["main", "handle"]), 1);
+        profile.add_stack(stack(&["main", "handle", "parse"]), 2);
+        profile
+    }
+
+    #[test]
+    fn collapses_stacks() {
+        assert_eq!(
+            profile().collapsed(),
+            "main;handle 1\nmain;handle;parse 5\n"
+        );
+    }
+
+    #[test]
+    fn merges_profiles() {
+        let mut merged = profile();
+        merged.merge(profile());
+        assert_eq!(merged.collapsed(), "main;handle 2\nmain;handle;parse 10\n");
+    }
+
+    #[test]
+    fn formats_speedscope_profiles() {
+        let json = profile().speedscope("test");
+        assert_eq!(
+            json["shared"]["frames"],
+            json!([{ "name": "main" }, { "name": "handle" }, { "name": "parse" }])
+        );
+        let sampled = &json["profiles"][0];
+        assert_eq!(sampled["samples"], json!([[0, 1], [0, 1, 2]]));
+        assert_eq!(sampled["weights"], json!([1, 5]));
+        assert_eq!(sampled["endValue"], 6);
+    }
+}
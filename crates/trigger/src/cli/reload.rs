@@ -0,0 +1,61 @@
+use futures::future::Either;
+
+/// Receives requests to reload the running application.
+///
+/// On Unix, a reload is requested by sending the process `SIGHUP`. Reloads
+/// may also be requested through the admin API. Signals aren't supported on
+/// other platforms, so no signals are ever received.
+pub struct ReloadSignals {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+    requests: Option<tokio::sync::mpsc::Receiver<()>>,
+}
+
+impl ReloadSignals {
+    /// Starts listening for reload requests.
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+            requests: None,
+        })
+    }
+
+    /// Also receives the reload requests sent on the given channel.
+    pub fn with_requests(self, requests: tokio::sync::mpsc::Receiver<()>) -> Self {
+        Self {
+            requests: Some(requests),
+            ..self
+        }
+    }
+
+    /// Waits for the next reload request.
+    ///
+    /// Returns false if no more requests will be received.
+    pub async fn recv(&mut self) -> bool {
+        let Self {
+            #[cfg(unix)]
+            signal,
+            requests,
+        } = self;
+
+        let requested = async {
+            match requests {
+                Some(requests) => match requests.recv().await {
+                    Some(()) => true,
+                    // Requests have stopped, but signals may still arrive.
+                    None => std::future::pending().await,
+                },
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(unix)]
+        let signalled = async { signal.recv().await.is_some() };
+        #[cfg(not(unix))]
+        let signalled = std::future::pending::<bool>();
+
+        match futures::future::select(std::pin::pin!(requested), std::pin::pin!(signalled)).await {
+            Either::Left((received, _)) | Either::Right((received, _)) => received,
+        }
+    }
+}
@@ -109,7 +109,7 @@ mod tests {
 
     use spin_core::async_trait;
     use spin_factor_sqlite::{Connection, ConnectionCreator};
-    use spin_world::spin::sqlite::sqlite as v3;
+    use spin_world::spin::sqlite4_0_0::sqlite as v4;
     use tempfile::NamedTempFile;
 
     use super::*;
@@ -183,7 +183,7 @@ mod tests {
         async fn create_connection(
             &self,
             label: &str,
-        ) -> Result<Box<dyn Connection + 'static>, v3::Error> {
+        ) -> Result<Box<dyn Connection + 'static>, v4::Error> {
             self.push(label);
             Ok(Box::new(MockConnection {
                 tx: self.tx.clone(),
@@ -200,11 +200,11 @@ mod tests {
         async fn query(
             &self,
             query: &str,
-            parameters: Vec<v3::Value>,
-        ) -> Result<v3::QueryResult, v3::Error> {
+            parameters: Vec<v4::Value>,
+        ) -> Result<v4::QueryResult, v4::Error> {
             self.tx.send(Action::Query(query.to_string())).unwrap();
             let _ = parameters;
-            Ok(v3::QueryResult {
+            Ok(v4::QueryResult {
                 columns: Vec::new(),
                 rows: Vec::new(),
             })
@@ -217,12 +217,12 @@ mod tests {
             Ok(())
         }
 
-        async fn changes(&self) -> Result<u64, v3::Error> {
+        async fn changes(&self) -> Result<u64, v4::Error> {
             self.tx.send(Action::Changes).unwrap();
             Ok(123)
         }
 
-        async fn last_insert_rowid(&self) -> Result<i64, v3::Error> {
+        async fn last_insert_rowid(&self) -> Result<i64, v4::Error> {
             self.tx.send(Action::LastInsertRowid).unwrap();
             Ok(456)
         }
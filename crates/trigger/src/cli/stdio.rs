@@ -1,17 +1,49 @@
 use std::{
     collections::HashSet,
+    io::Write as _,
     path::{Path, PathBuf},
+    sync::Mutex,
     task::Poll,
 };
 
 use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
 use spin_common::ui::quoted_path;
 use spin_core::async_trait;
+use spin_factor_request_context::{RequestContextFactor, RequestContextHandle};
 use spin_factor_wasi::WasiFactor;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::ExecutorHooks;
+use spin_telemetry::logs::{AppLogSource, AppLogStream};
 use tokio::io::AsyncWrite;
 
+/// The longest line buffered in JSON mode before it is written as a log
+/// record anyway.
+const MAX_JSON_LINE_LEN: usize = 16 * 1024;
+
+/// The directory within the log directory holding component log history.
+const HISTORY_DIR: &str = "history";
+
+/// The size in bytes past which a component's log history is rotated. Only
+/// one rotated file is kept, so history takes at most about twice this.
+const HISTORY_MAX_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The file holding the log history of a component, in which each line of
+/// its output, from either stream, is a JSON object with its timestamp. The
+/// previous history is kept alongside it with a `.1` suffix.
+pub fn history_file(log_dir: &Path, component_id: &str) -> PathBuf {
+    let sanitized_component_id = sanitize_filename::sanitize(component_id);
+    log_dir
+        .join(HISTORY_DIR)
+        .join(format!("{sanitized_component_id}.jsonl"))
+}
+
+/// The directory holding the log history of all components.
+pub fn history_dir(log_dir: &Path) -> PathBuf {
+    log_dir.join(HISTORY_DIR)
+}
+
 /// Which components should have their logs followed on stdout/stderr.
 #[derive(Clone, Debug, Default)]
 pub enum FollowComponents {
@@ -35,10 +67,33 @@ impl FollowComponents {
     }
 }
 
+/// The format of component logs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Output is logged as the component wrote it.
+    #[default]
+    Text,
+    /// Each line of output is logged as a JSON object with its timestamp,
+    /// component, level and request ID.
+    Json,
+}
+
+/// When to rotate component log files.
+#[derive(Clone, Copy, Debug)]
+pub struct LogRotation {
+    /// The size in bytes past which a log file is rotated.
+    pub max_size: u64,
+    /// How many rotated files to keep in addition to the current one.
+    pub max_files: usize,
+}
+
 /// Implements TriggerHooks, writing logs to a log file and (optionally) stderr
 pub struct StdioLoggingExecutorHooks {
     follow_components: FollowComponents,
     log_dir: Option<PathBuf>,
+    format: LogFormat,
+    rotation: Option<LogRotation>,
 }
 
 impl StdioLoggingExecutorHooks {
@@ -46,26 +101,69 @@ impl StdioLoggingExecutorHooks {
         Self {
             follow_components,
             log_dir,
+            format: LogFormat::default(),
+            rotation: None,
         }
     }
 
+    /// Sets the format component output is logged in.
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets when log files are rotated. Files are only rotated when a
+    /// component instance opens them, so a file may grow past the limit
+    /// while instances are writing to it.
+    pub fn with_rotation(mut self, rotation: Option<LogRotation>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
     fn component_stdio_writer(
         &self,
-        component_id: &str,
-        log_suffix: &str,
+        source: LogSource,
         log_dir: Option<&Path>,
     ) -> Result<ComponentStdioWriter> {
-        let sanitized_component_id = sanitize_filename::sanitize(component_id);
-        let log_path = log_dir
-            .map(|log_dir| log_dir.join(format!("{sanitized_component_id}_{log_suffix}.txt",)));
+        let sanitized_component_id = sanitize_filename::sanitize(&source.component_id);
+        let log_path = log_dir.map(|log_dir| {
+            log_dir.join(format!(
+                "{sanitized_component_id}_{}.txt",
+                source.stream.as_str()
+            ))
+        });
         let log_path = log_path.as_deref();
 
-        let follow = self.follow_components.should_follow(component_id);
-        match log_path {
-            Some(log_path) => ComponentStdioWriter::new_forward(log_path, follow)
-                .with_context(|| format!("Failed to open log file {}", quoted_path(log_path))),
-            None => ComponentStdioWriter::new_inherit(),
+        if let (Some(log_path), Some(rotation)) = (log_path, &self.rotation) {
+            rotate_if_full(log_path, rotation)
+                .with_context(|| format!("Failed to rotate log file {}", quoted_path(log_path)))?;
         }
+
+        let history = log_dir
+            .map(|log_dir| {
+                let history_path = history_file(log_dir, &source.component_id);
+                LogHistory::open(&history_path).with_context(|| {
+                    format!("Failed to open log history {}", quoted_path(&history_path))
+                })
+            })
+            .transpose()?;
+
+        let follow = self.follow_components.should_follow(&source.component_id);
+        let mut writer = match (log_path, self.format) {
+            (Some(log_path), LogFormat::Text) => {
+                ComponentStdioWriter::new_forward(log_path, follow, source)
+                    .with_context(|| format!("Failed to open log file {}", quoted_path(log_path)))
+            }
+            (None, LogFormat::Text) => ComponentStdioWriter::new_inherit(source),
+            (Some(log_path), LogFormat::Json) => {
+                ComponentStdioWriter::new_json(Some(log_path), follow, source)
+                    .with_context(|| format!("Failed to open log file {}", quoted_path(log_path)))
+            }
+            // Without a log file, output goes to stderr as it does in text mode.
+            (None, LogFormat::Json) => ComponentStdioWriter::new_json(None, true, source),
+        }?;
+        writer.history = history;
+        Ok(writer)
     }
 
     fn validate_follows(&self, app: &spin_app::App) -> anyhow::Result<()> {
@@ -97,7 +195,8 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         self.validate_follows(configured_app.app())?;
         if let Some(dir) = &self.log_dir {
             // Ensure log dir exists if set
-            std::fs::create_dir_all(dir)
+            let history_dir = history_dir(dir);
+            std::fs::create_dir_all(&history_dir)
                 .with_context(|| format!("Failed to create log dir {}", quoted_path(dir)))?;
 
             println!("Logging component stdio to {}", quoted_path(dir.join("")))
@@ -110,27 +209,169 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         builder: &mut spin_factors_executor::FactorsInstanceBuilder<F, U>,
     ) -> anyhow::Result<()> {
         let component_id = builder.app_component().id().to_string();
+        let request_context = builder
+            .factor_builder::<RequestContextFactor>()
+            .map(|request_context| request_context.handle());
         let Some(wasi_builder) = builder.factor_builder::<WasiFactor>() else {
             return Ok(());
         };
-        wasi_builder.stdout_pipe(self.component_stdio_writer(
-            &component_id,
-            "stdout",
-            self.log_dir.as_deref(),
-        )?);
-        wasi_builder.stderr_pipe(self.component_stdio_writer(
-            &component_id,
-            "stderr",
-            self.log_dir.as_deref(),
-        )?);
+        let source = |stream| LogSource {
+            component_id: component_id.clone(),
+            stream,
+            request_context: request_context.clone(),
+        };
+        wasi_builder.stdout_pipe(
+            self.component_stdio_writer(source(AppLogStream::Stdout), self.log_dir.as_deref())?,
+        );
+        wasi_builder.stderr_pipe(
+            self.component_stdio_writer(source(AppLogStream::Stderr), self.log_dir.as_deref())?,
+        );
         Ok(())
     }
 }
 
+/// Rotates the log file at `log_path` if it is larger than the rotation
+/// allows, renaming `<file>` to `<file>.1`, `<file>.1` to `<file>.2` and so on.
+fn rotate_if_full(log_path: &Path, rotation: &LogRotation) -> std::io::Result<()> {
+    // Instances of a component share its log files, so only one may rotate
+    // them at a time.
+    static ROTATION_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = ROTATION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    match std::fs::metadata(log_path) {
+        Ok(metadata) if metadata.len() > rotation.max_size => {}
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    let rotated_path = |n: usize| {
+        let mut path = log_path.as_os_str().to_owned();
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    };
+    if rotation.max_files == 0 {
+        return std::fs::remove_file(log_path);
+    }
+    for n in (1..rotation.max_files).rev() {
+        match std::fs::rename(rotated_path(n), rotated_path(n + 1)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    std::fs::rename(log_path, rotated_path(1))
+}
+
+/// The timestamped history of a component's output, kept whatever the log
+/// format so that `spin logs` can filter it by time.
+struct LogHistory {
+    file: std::fs::File,
+    /// Output received since the last complete line.
+    pending: Vec<u8>,
+}
+
+impl LogHistory {
+    fn open(history_path: &Path) -> std::io::Result<Self> {
+        rotate_if_full(
+            history_path,
+            &LogRotation {
+                max_size: HISTORY_MAX_SIZE,
+                max_files: 1,
+            },
+        )?;
+        let file = std::fs::File::options()
+            .create(true)
+            .append(true)
+            .open(history_path)?;
+        Ok(Self {
+            file,
+            pending: Vec::new(),
+        })
+    }
+
+    fn record(&mut self, source: &LogSource, buf: &[u8]) -> std::io::Result<()> {
+        for line in take_lines(&mut self.pending, buf) {
+            self.file.write_all(&json_log_line(source, &line)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, source: &LogSource) -> std::io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let line = std::mem::take(&mut self.pending);
+        self.file.write_all(&json_log_line(source, &line)?)
+    }
+}
+
+/// Appends `buf` to the `pending` output and removes and returns the complete
+/// lines in it, without their line endings.
+fn take_lines(pending: &mut Vec<u8>, buf: &[u8]) -> Vec<Vec<u8>> {
+    pending.extend_from_slice(buf);
+    let mut lines = Vec::new();
+    while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+        let mut line: Vec<u8> = pending.drain(..=end).collect();
+        line.pop();
+        lines.push(line);
+    }
+    // Don't wait indefinitely for the end of a very long line
+    if pending.len() >= MAX_JSON_LINE_LEN {
+        lines.push(std::mem::take(pending));
+    }
+    lines
+}
+
+/// Formats a line of a component's output as a JSON log record, ending in a
+/// newline.
+fn json_log_line(source: &LogSource, line: &[u8]) -> std::io::Result<Vec<u8>> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let record = JsonLogLine {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        component: &source.component_id,
+        stream: source.stream.as_str(),
+        level: source.stream.level(),
+        request_id: source.request_id(),
+        message: String::from_utf8_lossy(line),
+    };
+    let mut json = serde_json::to_vec(&record)?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+/// The component stream a [`ComponentStdioWriter`] is writing.
+struct LogSource {
+    component_id: String,
+    stream: AppLogStream,
+    request_context: Option<RequestContextHandle>,
+}
+
+impl LogSource {
+    fn request_id(&self) -> Option<String> {
+        self.request_context
+            .as_ref()
+            .map(|context| context.get().request_id)
+    }
+}
+
+/// A component log line in [`LogFormat::Json`].
+#[derive(Serialize)]
+struct JsonLogLine<'a> {
+    timestamp: String,
+    component: &'a str,
+    stream: &'static str,
+    level: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    message: std::borrow::Cow<'a, str>,
+}
+
 /// ComponentStdioWriter forwards output to a log file, (optionally) stderr, and (optionally) to a
 /// tracing compatibility layer.
 pub struct ComponentStdioWriter {
     inner: ComponentStdioWriterInner,
+    source: LogSource,
+    history: Option<LogHistory>,
 }
 
 enum ComponentStdioWriterInner {
@@ -143,6 +384,13 @@ enum ComponentStdioWriterInner {
         state: ComponentStdioWriterState,
         follow: bool,
     },
+    /// Write each line as a JSON object to a file and/or stderr.
+    Json {
+        file: Option<std::fs::File>,
+        follow: bool,
+        /// Output received since the last complete line.
+        pending: Vec<u8>,
+    },
 }
 
 #[derive(Debug)]
@@ -152,7 +400,7 @@ enum ComponentStdioWriterState {
 }
 
 impl ComponentStdioWriter {
-    fn new_forward(log_path: &Path, follow: bool) -> anyhow::Result<Self> {
+    fn new_forward(log_path: &Path, follow: bool, source: LogSource) -> anyhow::Result<Self> {
         let sync_file = std::fs::File::options()
             .create(true)
             .append(true)
@@ -170,26 +418,86 @@ impl ComponentStdioWriter {
                 state: ComponentStdioWriterState::File,
                 follow,
             },
+            source,
+            history: None,
         })
     }
 
-    fn new_inherit() -> anyhow::Result<Self> {
+    fn new_inherit(source: LogSource) -> anyhow::Result<Self> {
         Ok(Self {
             inner: ComponentStdioWriterInner::Inherit,
+            source,
+            history: None,
         })
     }
-}
 
-impl AsyncWrite for ComponentStdioWriter {
-    fn poll_write(
-        self: std::pin::Pin<&mut Self>,
+    fn new_json(log_path: Option<&Path>, follow: bool, source: LogSource) -> anyhow::Result<Self> {
+        let file = log_path
+            .map(|log_path| {
+                std::fs::File::options()
+                    .create(true)
+                    .append(true)
+                    .open(log_path)
+            })
+            .transpose()?;
+        Ok(Self {
+            inner: ComponentStdioWriterInner::Json {
+                file,
+                follow,
+                pending: Vec::new(),
+            },
+            source,
+            history: None,
+        })
+    }
+
+    /// Buffers output in JSON mode, logging each complete line.
+    fn write_json(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        let ComponentStdioWriterInner::Json { pending, .. } = &mut self.inner else {
+            unreachable!("write_json is only called in JSON mode");
+        };
+        for line in take_lines(pending, buf) {
+            self.log_json_line(&line)?;
+        }
+        Ok(())
+    }
+
+    fn log_json_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let json = json_log_line(&self.source, line)?;
+
+        let ComponentStdioWriterInner::Json { file, follow, .. } = &mut self.inner else {
+            unreachable!("log_json_line is only called in JSON mode");
+        };
+        if let Some(file) = file {
+            file.write_all(&json)?;
+        }
+        if *follow {
+            std::io::stderr().write_all(&json)?;
+        }
+        Ok(())
+    }
+
+    /// Records output the component has written in its log history. Failing
+    /// to do so doesn't fail the component's write.
+    fn record_history(&mut self, written: &[u8]) {
+        if let Some(history) = &mut self.history {
+            if let Err(e) = history.record(&self.source, written) {
+                tracing::warn!("Failed to record component log history: {e}");
+            }
+        }
+    }
+
+    fn poll_write_inner(
+        &mut self,
         cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> Poll<std::result::Result<usize, std::io::Error>> {
-        let this = self.get_mut();
-
         loop {
-            match &mut this.inner {
+            match &mut self.inner {
+                // Log records are small, so they are written synchronously.
+                ComponentStdioWriterInner::Json { .. } => {
+                    return Poll::Ready(self.write_json(buf).map(|()| buf.len()));
+                }
                 ComponentStdioWriterInner::Inherit => {
                     let written = futures::ready!(
                         std::pin::Pin::new(&mut tokio::io::stderr()).poll_write(cx, buf)
@@ -240,6 +548,36 @@ impl AsyncWrite for ComponentStdioWriter {
             }
         }
     }
+}
+
+impl Drop for ComponentStdioWriter {
+    fn drop(&mut self) {
+        // Log any final line that the component didn't end with a newline.
+        if let ComponentStdioWriterInner::Json { pending, .. } = &mut self.inner {
+            if !pending.is_empty() {
+                let line = std::mem::take(pending);
+                _ = self.log_json_line(&line);
+            }
+        }
+        if let Some(history) = &mut self.history {
+            _ = history.finish(&self.source);
+        }
+    }
+}
+
+impl AsyncWrite for ComponentStdioWriter {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::result::Result<usize, std::io::Error>> {
+        let this = self.get_mut();
+        let result = this.poll_write_inner(cx, buf);
+        if let Poll::Ready(Ok(written)) = &result {
+            this.record_history(&buf[..*written]);
+        }
+        result
+    }
 
     fn poll_flush(
         self: std::pin::Pin<&mut Self>,
@@ -251,6 +589,7 @@ impl AsyncWrite for ComponentStdioWriter {
             ComponentStdioWriterInner::Inherit => {
                 std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
             }
+            ComponentStdioWriterInner::Json { .. } => Poll::Ready(std::io::Write::flush(this)),
             ComponentStdioWriterInner::Forward {
                 async_file, state, ..
             } => match state {
@@ -272,6 +611,7 @@ impl AsyncWrite for ComponentStdioWriter {
             ComponentStdioWriterInner::Inherit => {
                 std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
             }
+            ComponentStdioWriterInner::Json { .. } => Poll::Ready(std::io::Write::flush(this)),
             ComponentStdioWriterInner::Forward {
                 async_file, state, ..
             } => match state {
@@ -286,12 +626,20 @@ impl AsyncWrite for ComponentStdioWriter {
 
 impl std::io::Write for ComponentStdioWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        spin_telemetry::logs::handle_app_log(buf);
+        let request_id = self.source.request_id();
+        spin_telemetry::logs::handle_app_log(
+            buf,
+            AppLogSource {
+                component_id: &self.source.component_id,
+                stream: self.source.stream,
+                request_id: request_id.as_deref(),
+            },
+        );
 
-        match &mut self.inner {
+        let written = match &mut self.inner {
             ComponentStdioWriterInner::Inherit => {
                 std::io::stderr().write_all(buf)?;
-                Ok(buf.len())
+                buf.len()
             }
             ComponentStdioWriterInner::Forward {
                 sync_file, follow, ..
@@ -300,9 +648,15 @@ impl std::io::Write for ComponentStdioWriter {
                 if *follow {
                     std::io::stderr().write_all(&buf[..written])?;
                 }
-                Ok(written)
+                written
             }
-        }
+            ComponentStdioWriterInner::Json { .. } => {
+                self.write_json(buf)?;
+                buf.len()
+            }
+        };
+        self.record_history(&buf[..written]);
+        Ok(written)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -317,6 +671,16 @@ impl std::io::Write for ComponentStdioWriter {
                 }
                 Ok(())
             }
+            // Partial lines are kept until they are complete.
+            ComponentStdioWriterInner::Json { file, follow, .. } => {
+                if let Some(file) = file {
+                    file.flush()?;
+                }
+                if *follow {
+                    std::io::stderr().flush()?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -328,3 +692,102 @@ fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> Stri
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn source() -> LogSource {
+        LogSource {
+            component_id: "hello".into(),
+            stream: AppLogStream::Stderr,
+            request_context: None,
+        }
+    }
+
+    #[test]
+    fn json_mode_logs_each_line_as_an_object() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stderr.txt");
+
+        let mut writer = ComponentStdioWriter::new_json(Some(&log_path), false, source())?;
+        writer.write_all(b"first\r\nsec")?;
+        writer.write_all(b"ond\nunterminated")?;
+        drop(writer);
+
+        let lines = std::fs::read_to_string(&log_path)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        let messages: Vec<_> = lines
+            .iter()
+            .map(|l| l["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(messages, ["first", "second", "unterminated"]);
+        assert_eq!(lines[0]["component"], "hello");
+        assert_eq!(lines[0]["stream"], "stderr");
+        assert_eq!(lines[0]["level"], "error");
+        assert!(lines[0].get("request_id").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn history_is_recorded_in_text_mode() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let history_path = history_file(dir.path(), "hello");
+        std::fs::create_dir_all(history_path.parent().unwrap())?;
+
+        let mut writer = ComponentStdioWriter::new_forward(
+            &dir.path().join("hello_stderr.txt"),
+            false,
+            source(),
+        )?;
+        writer.history = Some(LogHistory::open(&history_path)?);
+        writer.write_all(b"hello\nwor")?;
+        writer.write_all(b"ld\n")?;
+        drop(writer);
+
+        let lines = std::fs::read_to_string(&history_path)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        let messages: Vec<_> = lines
+            .iter()
+            .map(|l| l["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(messages, ["hello", "world"]);
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("hello_stderr.txt"))?,
+            "hello\nworld\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn full_log_files_are_rotated() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stdout.txt");
+        let rotated = |n| dir.path().join(format!("hello_stdout.txt.{n}"));
+        let rotation = LogRotation {
+            max_size: 4,
+            max_files: 2,
+        };
+
+        std::fs::write(&log_path, "1234")?;
+        rotate_if_full(&log_path, &rotation)?;
+        assert!(log_path.exists(), "file at the limit should not be rotated");
+
+        for contents in ["first", "second", "third"] {
+            std::fs::write(&log_path, contents)?;
+            rotate_if_full(&log_path, &rotation)?;
+            assert!(!log_path.exists());
+        }
+        assert_eq!(std::fs::read_to_string(rotated(1))?, "third");
+        assert_eq!(std::fs::read_to_string(rotated(2))?, "second");
+        assert!(!rotated(3).exists());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use spin_core::async_trait;
 use spin_factor_key_value::KeyValueFactor;
 use spin_factor_sqlite::SqliteFactor;
+use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::ExecutorHooks;
 
@@ -54,3 +55,33 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for SqliteDefaultStoreSummaryHook
         Ok(())
     }
 }
+
+/// An [`ExecutorHooks`] that prints which components can read which secret
+/// variables.
+pub struct SecretAccessSummaryHook;
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for SecretAccessSummaryHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let Ok(variables_app_state) = configured_app.app_state::<VariablesFactor>() else {
+            return Ok(());
+        };
+        let readers = variables_app_state
+            .component_secrets()
+            .iter()
+            .filter(|(_, secrets)| !secrets.is_empty())
+            .collect::<Vec<_>>();
+        if readers.is_empty() {
+            return Ok(());
+        }
+        println!("Components with access to secrets:");
+        for (component_id, secrets) in readers {
+            let secrets = secrets.iter().map(String::as_str).collect::<Vec<_>>();
+            println!("    {component_id}: {}", secrets.join(", "));
+        }
+        Ok(())
+    }
+}
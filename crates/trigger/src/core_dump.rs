@@ -0,0 +1,179 @@
+//! Capturing Wasm core dumps when components trap.
+//!
+//! Each core dump is written next to a JSON file describing the invocation
+//! that trapped, so that crashes can be debugged after the fact. Only the
+//! most recent dumps are kept.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// Describes the invocation a core dump was captured from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoreDumpMetadata {
+    /// When the core dump was captured, in RFC 3339 format.
+    pub captured_at: String,
+    /// The type of the trigger that invoked the component, e.g. `http`.
+    pub trigger_type: String,
+    pub component_id: String,
+    /// The error the component trapped with.
+    pub trap: String,
+    /// Metadata of the payload the component was invoked with, e.g. the
+    /// method and path of an HTTP request.
+    pub payload: Vec<(String, String)>,
+}
+
+/// Writes core dumps to a directory, deleting the oldest beyond a limit.
+#[derive(Clone, Debug)]
+pub struct CoreDumper {
+    dir: Arc<PathBuf>,
+    max_dumps: usize,
+    /// Distinguishes core dumps captured in the same instant.
+    sequence: Arc<AtomicU64>,
+    /// Serializes pruning of old core dumps.
+    pruning: Arc<Mutex<()>>,
+}
+
+impl CoreDumper {
+    /// Creates a core dumper writing to `dir`, creating it if needed, and
+    /// keeping at most `max_dumps` core dumps.
+    pub fn new(dir: &Path, max_dumps: usize) -> anyhow::Result<Self> {
+        let dir = std::path::absolute(dir)?;
+        std::fs::create_dir_all(&dir).with_context(|| {
+            format!("failed to create core dump directory {}", quoted_path(&dir))
+        })?;
+        Ok(Self {
+            dir: Arc::new(dir),
+            max_dumps,
+            sequence: Default::default(),
+            pruning: Default::default(),
+        })
+    }
+
+    /// Writes the core dump of a component invocation that failed with
+    /// `err`, if it trapped.
+    ///
+    /// Failing to write a core dump shouldn't affect the invocation's
+    /// outcome, so errors are logged.
+    pub fn capture<T>(
+        &self,
+        store: &mut spin_core::Store<T>,
+        err: &anyhow::Error,
+        trigger_type: &str,
+        component_id: &str,
+        payload: Vec<(String, String)>,
+    ) {
+        let Some(dump) = store.core_dump(err, component_id) else {
+            return;
+        };
+        let metadata = CoreDumpMetadata {
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            trigger_type: trigger_type.to_owned(),
+            component_id: component_id.to_owned(),
+            trap: format!("{err:#}"),
+            payload,
+        };
+        match self.write(&dump, &metadata) {
+            Ok(path) => {
+                tracing::info!(
+                    "Wrote core dump of component {component_id} to {}",
+                    quoted_path(&path)
+                );
+            }
+            Err(err) => {
+                tracing::error!("Failed to write core dump of component {component_id}: {err:#}");
+            }
+        }
+    }
+
+    /// Writes a core dump and its metadata, returning the path of the dump.
+    fn write(&self, dump: &[u8], metadata: &CoreDumpMetadata) -> anyhow::Result<PathBuf> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        // Names start with the time, so they sort from oldest to newest
+        let name = sanitize_filename::sanitize(format!(
+            "{}-{}-{}-{sequence}",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+            metadata.trigger_type,
+            metadata.component_id,
+        ));
+        let path = self.dir.join(format!("{name}.coredump"));
+        std::fs::write(&path, dump)
+            .with_context(|| format!("failed to write core dump {}", quoted_path(&path)))?;
+        std::fs::write(
+            path.with_extension("json"),
+            serde_json::to_vec_pretty(metadata)?,
+        )?;
+        self.prune()?;
+        Ok(path)
+    }
+
+    /// Deletes the oldest core dumps, and their metadata, beyond the limit.
+    fn prune(&self) -> anyhow::Result<()> {
+        let _pruning = self.pruning.lock().unwrap();
+        let mut dumps = vec![];
+        for entry in std::fs::read_dir(&*self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "coredump") {
+                dumps.push(path);
+            }
+        }
+        dumps.sort();
+        let excess = dumps.len().saturating_sub(self.max_dumps);
+        for path in &dumps[..excess] {
+            std::fs::remove_file(path)?;
+            // The metadata may already be gone
+            let _ = std::fs::remove_file(path.with_extension("json"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(component_id: &str) -> CoreDumpMetadata {
+        CoreDumpMetadata {
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            trigger_type: "http".into(),
+            component_id: component_id.into(),
+            trap: "wasm trap: wasm `unreachable` instruction executed".into(),
+            payload: vec![("method".into(), "GET".into())],
+        }
+    }
+
+    #[test]
+    fn writes_core_dumps_with_metadata() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dumper = CoreDumper::new(&dir.path().join("dumps"), 10)?;
+        let metadata = metadata("api");
+        let path = dumper.write(b"\0asm", &metadata)?;
+        assert_eq!(std::fs::read(&path)?, b"\0asm");
+        let written: CoreDumpMetadata =
+            serde_json::from_slice(&std::fs::read(path.with_extension("json"))?)?;
+        assert_eq!(written, metadata);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_only_the_newest_core_dumps() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dumper = CoreDumper::new(dir.path(), 2)?;
+        let oldest = dumper.write(b"1", &metadata("api"))?;
+        let older = dumper.write(b"2", &metadata("api"))?;
+        let newest = dumper.write(b"3", &metadata("api"))?;
+        assert!(!oldest.exists());
+        assert!(!oldest.with_extension("json").exists());
+        assert!(older.exists());
+        assert!(newest.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! Skipping messages which have already been processed.
+//!
+//! Brokers may deliver a message more than once, and every Spin instance
+//! reading a Redis stream reads every entry. A trigger configured to
+//! deduplicate records the ID of each message it hands to a component in a
+//! key-value store, and skips messages whose ID is already recorded, so that
+//! idempotent but expensive handlers process each message effectively once.
+//!
+//! A message is claimed before it is handled, so that two instances don't
+//! handle it at the same time, and the claim is released if the handler
+//! fails, so that the message is handled again if it is delivered again.
+//! Records expire after a TTL; the value of each is its big-endian expiry
+//! time in milliseconds since the Unix epoch, so that stores which clean up
+//! expired records can find them.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_common::time::now_millis;
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+
+/// Prefix for the keys of processed message records.
+const KEY_PREFIX: &str = "spin-dedupe:";
+
+/// The header carrying a message's ID, unless configured otherwise.
+const DEFAULT_ID_HEADER: &str = "message-id";
+
+/// How long processed message IDs are remembered, unless configured
+/// otherwise.
+const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Deduplication settings of a trigger, as set in the manifest:
+///
+/// ```toml
+/// [[trigger.redis]]
+/// stream = "orders"
+/// component = "fulfil"
+/// dedupe = { key_value_store = "default", ttl_secs = 3600 }
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DedupeConfig {
+    /// The label of the key-value store to record message IDs in.
+    pub key_value_store: String,
+    /// How long message IDs are remembered, in seconds.
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// The header carrying the message ID set by the publisher. Messages
+    /// without the header are identified by the ID the broker gave them, if
+    /// any.
+    #[serde(default = "default_id_header")]
+    pub id_header: String,
+}
+
+fn default_ttl_secs() -> u64 {
+    DEFAULT_TTL_SECS
+}
+
+fn default_id_header() -> String {
+    DEFAULT_ID_HEADER.to_owned()
+}
+
+/// Records the IDs of processed messages in a key-value store.
+#[derive(Clone)]
+pub struct Deduplicator {
+    store_manager: Arc<dyn StoreManager>,
+    config: Arc<DedupeConfig>,
+}
+
+impl Deduplicator {
+    /// Creates a deduplicator recording IDs in the store `config` names,
+    /// which must be defined in `store_manager`.
+    pub fn new(store_manager: Arc<dyn StoreManager>, config: DedupeConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            store_manager.is_defined(&config.key_value_store),
+            "key-value store {:?} for deduplicating messages is not defined",
+            config.key_value_store
+        );
+        Ok(Self {
+            store_manager,
+            config: Arc::new(config),
+        })
+    }
+
+    /// Returns a message's ID: the value of the configured header, or else
+    /// the ID the broker gave it.
+    pub fn message_id<'a>(
+        &self,
+        headers: &'a [(String, String)],
+        broker_id: Option<&'a str>,
+    ) -> Option<&'a str> {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&self.config.id_header))
+            .map(|(_, value)| value.as_str())
+            .or(broker_id)
+    }
+
+    /// Runs `handle` unless the message with ID `id` has already been
+    /// claimed in `scope`, releasing the claim if it fails.
+    ///
+    /// Messages without an ID are always handled, as are messages whose
+    /// claim can't be checked: handling a message twice is better than not
+    /// at all.
+    pub async fn run(
+        &self,
+        scope: &str,
+        id: Option<&str>,
+        handle: impl Future<Output = anyhow::Result<()>>,
+    ) -> anyhow::Result<()> {
+        let Some(id) = id else {
+            tracing::debug!("Message in {scope} has no ID to deduplicate it by");
+            return handle.await;
+        };
+        match self.claim(scope, id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::debug!("Skipping already processed message {id:?} in {scope}");
+                return Ok(());
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to check whether message {id:?} in {scope} was processed: {err:#}"
+                );
+                return handle.await;
+            }
+        }
+        let result = handle.await;
+        if result.is_err() {
+            if let Err(err) = self.release(scope, id).await {
+                tracing::warn!("Failed to release message {id:?} in {scope} for retry: {err:#}");
+            }
+        }
+        result
+    }
+
+    /// Claims the message with ID `id` for handling in `scope`, e.g. a
+    /// component and the channel it received the message on.
+    ///
+    /// Returns false if the message has already been claimed.
+    pub async fn claim(&self, scope: &str, id: &str) -> anyhow::Result<bool> {
+        let key = record_key(scope, id);
+        let store = self.store().await?;
+        let cas = store
+            .new_compare_and_swap(0, &key)
+            .await
+            .context("failed to read processed message record")?;
+        let current = cas
+            .current()
+            .await
+            .context("failed to read processed message record")?;
+        let now = now_millis();
+        if current
+            .as_deref()
+            .and_then(decode_expiry)
+            .is_some_and(|expires_at| expires_at > now)
+        {
+            return Ok(false);
+        }
+        let ttl_ms = self.config.ttl_secs.saturating_mul(1000);
+        let expires_at = now.saturating_add(ttl_ms);
+        match cas.swap(expires_at.to_be_bytes().to_vec()).await {
+            Ok(()) => Ok(true),
+            // Another instance claimed it first
+            Err(SwapError::CasFailed(_)) => Ok(false),
+            Err(SwapError::Other(err)) => {
+                Err(anyhow::anyhow!(err).context("failed to record processed message"))
+            }
+        }
+    }
+
+    /// Releases a claim, after the message's handler failed.
+    pub async fn release(&self, scope: &str, id: &str) -> anyhow::Result<()> {
+        self.store()
+            .await?
+            .delete(&record_key(scope, id))
+            .await
+            .context("failed to delete processed message record")
+    }
+
+    async fn store(&self) -> anyhow::Result<Arc<dyn Store>> {
+        let label = &self.config.key_value_store;
+        self.store_manager
+            .get(label)
+            .await
+            .with_context(|| format!("failed to open key-value store {label:?}"))
+    }
+}
+
+fn record_key(scope: &str, id: &str) -> String {
+    format!("{KEY_PREFIX}{scope}:{id}")
+}
+
+/// Decodes the expiry time of a record, treating malformed records as
+/// expired.
+fn decode_expiry(value: &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(value.get(..8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+    use spin_key_value_spin::MemoryKeyValueStore;
+
+    use super::*;
+
+    fn deduplicator(ttl_secs: u64) -> Deduplicator {
+        let store_manager = MemoryKeyValueStore::new()
+            .make_store(Default::default())
+            .unwrap();
+        Deduplicator::new(
+            Arc::new(store_manager),
+            DedupeConfig {
+                key_value_store: "default".into(),
+                ttl_secs,
+                id_header: default_id_header(),
+            },
+        )
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn messages_are_claimed_once() -> anyhow::Result<()> {
+        let dedupe = deduplicator(60);
+        assert!(dedupe.claim("handler:orders", "1").await?);
+        assert!(!dedupe.claim("handler:orders", "1").await?);
+        // Other scopes have claims of their own
+        assert!(dedupe.claim("auditor:orders", "1").await?);
+
+        dedupe.release("handler:orders", "1").await?;
+        assert!(dedupe.claim("handler:orders", "1").await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn claims_expire() -> anyhow::Result<()> {
+        let dedupe = deduplicator(0);
+        assert!(dedupe.claim("handler:orders", "1").await?);
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        assert!(dedupe.claim("handler:orders", "1").await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_messages_are_handled_again() -> anyhow::Result<()> {
+        let dedupe = deduplicator(60);
+        let err = dedupe
+            .run("handler:orders", Some("1"), async {
+                anyhow::bail!("failed")
+            })
+            .await;
+        assert!(err.is_err());
+
+        let mut handled = 0;
+        for _ in 0..2 {
+            dedupe
+                .run("handler:orders", Some("1"), async {
+                    handled += 1;
+                    Ok(())
+                })
+                .await?;
+        }
+        assert_eq!(handled, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn header_ids_take_precedence() {
+        let dedupe = deduplicator(60);
+        let headers = [("Message-ID".to_owned(), "abc".to_owned())];
+        assert_eq!(dedupe.message_id(&headers, Some("1-0")), Some("abc"));
+        assert_eq!(dedupe.message_id(&[], Some("1-0")), Some("1-0"));
+        assert_eq!(dedupe.message_id(&[], None), None);
+    }
+}
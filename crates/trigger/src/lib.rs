@@ -1,5 +1,8 @@
 pub mod cli;
+pub mod core_dump;
+pub mod dedupe;
 pub mod loader;
+pub mod recording;
 
 use std::future::Future;
 
@@ -8,6 +11,9 @@ use spin_core::Linker;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{FactorsExecutorApp, FactorsInstanceBuilder};
 
+use core_dump::CoreDumper;
+use recording::Recorder;
+
 pub use spin_app::App;
 
 /// Type alias for a [`spin_factors_executor::FactorsExecutorApp`] specialized to a [`Trigger`].
@@ -17,6 +23,11 @@ pub type TriggerApp<T, F> = FactorsExecutorApp<F, <T as Trigger<F>>::InstanceSta
 pub type TriggerInstanceBuilder<'a, T, F> =
     FactorsInstanceBuilder<'a, F, <T as Trigger<F>>::InstanceState>;
 
+/// Receives newly built apps to replace a running trigger's app.
+///
+/// See [`Trigger::run_reloadable`].
+pub type AppReloads<T, F> = tokio::sync::mpsc::Receiver<TriggerApp<T, F>>;
+
 /// Type alias for a [`spin_core::Store`] specialized to a [`Trigger`].
 pub type Store<T, F> = spin_core::Store<TriggerInstanceState<T, F>>;
 
@@ -32,7 +43,7 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
     const TYPE: &'static str;
 
     /// The specific CLI arguments for this trigger.
-    type CliArgs: Args;
+    type CliArgs: Args + Clone;
 
     /// The instance state for this trigger.
     type InstanceState: Send + 'static;
@@ -65,6 +76,39 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
         trigger_app: TriggerApp<Self, F>,
     ) -> impl Future<Output = anyhow::Result<()>> + Send;
 
+    /// Run this trigger, replacing the running app with each app received
+    /// from `reloads`.
+    ///
+    /// The default implementation doesn't support reloading; it drops
+    /// `reloads` and calls [`Trigger::run`].
+    fn run_reloadable(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        reloads: AppReloads<Self, F>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        drop(reloads);
+        self.run(trigger_app)
+    }
+
+    /// Records the payload of each invocation with `recorder`, so that it can
+    /// be replayed later.
+    ///
+    /// The default implementation doesn't support recording; it warns and
+    /// ignores `recorder`.
+    fn record_invocations(&mut self, recorder: Recorder) {
+        drop(recorder);
+        terminal::warn!("The '{}' trigger doesn't support recording.", Self::TYPE);
+    }
+
+    /// Captures a core dump with `core_dumper` whenever a component traps.
+    ///
+    /// The default implementation doesn't support core dumps; it warns and
+    /// ignores `core_dumper`.
+    fn capture_core_dumps(&mut self, core_dumper: CoreDumper) {
+        drop(core_dumper);
+        terminal::warn!("The '{}' trigger doesn't support core dumps.", Self::TYPE);
+    }
+
     /// Returns a list of host requirements supported by this trigger specifically.
     ///
     /// See [`App::ensure_needs_only`].
@@ -1,14 +1,21 @@
+use std::{
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
 use anyhow::Context as _;
-use spin_common::{ui::quoted_path, url::parse_file_url};
+use spin_app::locked::LockedApp;
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path, url::parse_file_url};
 use spin_compose::ComponentSourceLoaderFs;
 use spin_core::{async_trait, wasmtime, Component};
 use spin_factors::{AppComponent, RuntimeFactors};
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct ComponentLoader {
     _private: (),
     #[cfg(feature = "unsafe-aot-compilation")]
     aot_compilation_enabled: bool,
+    aot_cache: Option<AotCache>,
 }
 
 impl ComponentLoader {
@@ -17,6 +24,13 @@ impl ComponentLoader {
         Self::default()
     }
 
+    /// Updates the loader to load compiled components from, and save them
+    /// to, the given AOT cache.
+    pub fn with_aot_cache(mut self, aot_cache: AotCache) -> Self {
+        self.aot_cache = Some(aot_cache);
+        self
+    }
+
     /// Updates the TriggerLoader to load AOT precompiled components
     ///
     /// **Warning: This feature may bypass important security guarantees of the
@@ -98,7 +112,131 @@ impl<T: RuntimeFactors, U> spin_factors_executor::ComponentLoader<T, U> for Comp
                 )
             })?;
 
+        if let Some(aot_cache) = &self.aot_cache {
+            return aot_cache.load(engine, &composed).with_context(|| {
+                format!("failed to compile component from {}", quoted_path(&path))
+            });
+        }
+
         spin_core::Component::new(engine, composed)
             .with_context(|| format!("failed to compile component from {}", quoted_path(&path)))
     }
 }
+
+/// A directory of components compiled ahead of time by Wasmtime, keyed by
+/// the digest of the component and the configuration of the engine that
+/// compiled it, so that it can be shared by runs, and by hosts whose engines
+/// are compatible.
+///
+/// Artifacts are native code loaded without validation, so the directory
+/// must only be writable by trusted users.
+#[derive(Clone, Debug)]
+pub struct AotCache {
+    dir: PathBuf,
+}
+
+impl AotCache {
+    /// Creates an AOT cache in the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Loads the compiled component from the cache, compiling and caching it
+    /// first if necessary.
+    pub fn load(&self, engine: &wasmtime::Engine, component: &[u8]) -> anyhow::Result<Component> {
+        let path = self.artifact_path(engine, component);
+        if path.exists() {
+            // SAFETY: artifacts in the cache were compiled by `store` from the
+            // component with this digest, and the cache directory is trusted.
+            // Wasmtime rejects artifacts from incompatible engines.
+            match unsafe { Component::deserialize_file(engine, &path) } {
+                Ok(compiled) => return Ok(compiled),
+                Err(e) => {
+                    tracing::warn!("Recompiling unusable AOT artifact {path:?}: {e}");
+                }
+            }
+        }
+        let serialized = self.store(engine, component)?;
+        // SAFETY: this was just compiled by the same engine.
+        unsafe { Component::deserialize(engine, serialized) }
+    }
+
+    /// Compiles the component and saves it to the cache, returning the
+    /// compiled artifact.
+    pub fn store(&self, engine: &wasmtime::Engine, component: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let serialized = engine.precompile_component(component)?;
+        let path = self.artifact_path(engine, component);
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create AOT cache {}", quoted_path(dir)))?;
+        // Write then rename so that concurrent loaders never see a partial
+        // artifact.
+        let temp = tempfile::NamedTempFile::new_in(dir)?;
+        std::fs::write(temp.path(), &serialized)?;
+        temp.persist(&path)
+            .with_context(|| format!("failed to write AOT artifact {}", quoted_path(&path)))?;
+        Ok(serialized)
+    }
+
+    fn artifact_path(&self, engine: &wasmtime::Engine, component: &[u8]) -> PathBuf {
+        // The default hasher is deterministic for a given build of Spin,
+        // which is all that is needed: artifacts are also specific to the
+        // Wasmtime version.
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        self.dir
+            .join(format!("{:016x}", hasher.finish()))
+            .join(format!("{}.cwasm", hex_digest_from_bytes(component)))
+    }
+}
+
+/// Compiles the components of the given app into the AOT cache, using the
+/// default Spin engine configuration. Components are stored as they will be
+/// loaded, i.e. composed with their dependencies.
+///
+/// Artifacts are only used by hosts whose engine configuration matches,
+/// e.g. an app whose runtime config enables fuel metering is recompiled when
+/// it runs.
+pub async fn precompile_app(app: &LockedApp, aot_cache: &AotCache) -> anyhow::Result<()> {
+    let mut engine_builder = spin_core::Engine::<()>::builder(&spin_core::Config::default())?;
+    engine_builder.epoch_ticker_thread(false);
+    let engine = engine_builder.build();
+
+    for component in &app.components {
+        let composed = spin_compose::compose(&ComponentSourceLoaderFs, component)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to resolve dependencies for component {:?}",
+                    component.id
+                )
+            })?;
+        aot_cache
+            .store(engine.as_ref(), &composed)
+            .with_context(|| format!("failed to precompile component {:?}", component.id))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty component, in the binary format.
+    const EMPTY_COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+    #[test]
+    fn aot_cache_reuses_compiled_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let aot_cache = AotCache::new(dir.path());
+        let engine = wasmtime::Engine::default();
+
+        aot_cache.load(&engine, EMPTY_COMPONENT).unwrap();
+        let path = aot_cache.artifact_path(&engine, EMPTY_COMPONENT);
+        let compiled_at = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        aot_cache.load(&engine, EMPTY_COMPONENT).unwrap();
+        let loaded_at = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(compiled_at, loaded_at);
+    }
+}
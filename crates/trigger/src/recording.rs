@@ -0,0 +1,160 @@
+//! Recording trigger payloads so that invocations can be replayed later.
+//!
+//! Each recorded invocation is written to its own JSON file, which `spin
+//! replay` can read back to invoke the same component with the same payload.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// A recorded invocation of a component.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// When the invocation started, in RFC 3339 format.
+    pub recorded_at: String,
+    /// The type of the trigger that invoked the component, e.g. `http`.
+    pub trigger_type: String,
+    pub component_id: String,
+    pub payload: RecordedPayload,
+}
+
+/// The payload a trigger invoked a component with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedPayload {
+    /// An inbound HTTP request.
+    Http {
+        method: String,
+        /// The request's path and query.
+        path_and_query: String,
+        headers: Vec<(String, String)>,
+        #[serde(with = "base64_bytes")]
+        body: Vec<u8>,
+    },
+    /// A message received on a Redis channel or stream.
+    Redis {
+        channel: String,
+        /// The pattern the channel matched, if subscribed to by pattern.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+        /// The ID of the stream entry, if read from a stream.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        headers: Vec<(String, String)>,
+        #[serde(with = "base64_bytes")]
+        payload: Vec<u8>,
+    },
+}
+
+impl Recording {
+    /// Creates a recording of an invocation starting now.
+    pub fn new(trigger_type: &str, component_id: &str, payload: RecordedPayload) -> Self {
+        Self {
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            trigger_type: trigger_type.to_owned(),
+            component_id: component_id.to_owned(),
+            payload,
+        }
+    }
+
+    /// Reads a recording written by a [`Recorder`].
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read(path)
+            .with_context(|| format!("failed to read recording {}", quoted_path(path)))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("failed to parse recording {}", quoted_path(path)))
+    }
+}
+
+/// Writes recordings to a directory, one file per invocation.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    dir: Arc<PathBuf>,
+    /// Distinguishes recordings made in the same instant.
+    sequence: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Creates a recorder writing to `dir`, creating it if needed.
+    pub fn new(dir: &Path) -> anyhow::Result<Self> {
+        let dir = std::path::absolute(dir)?;
+        std::fs::create_dir_all(&dir).with_context(|| {
+            format!("failed to create recording directory {}", quoted_path(&dir))
+        })?;
+        Ok(Self {
+            dir: Arc::new(dir),
+            sequence: Default::default(),
+        })
+    }
+
+    /// Writes a recording, returning the path it was written to.
+    ///
+    /// Failing to record shouldn't fail the invocation, so callers usually
+    /// just log errors.
+    pub fn record(&self, recording: &Recording) -> anyhow::Result<PathBuf> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let file_name = sanitize_filename::sanitize(format!(
+            "{}-{}-{}-{sequence}.json",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+            recording.trigger_type,
+            recording.component_id,
+        ));
+        let path = self.dir.join(file_name);
+        std::fs::write(&path, serde_json::to_vec_pretty(recording)?)
+            .with_context(|| format!("failed to write recording {}", quoted_path(&path)))?;
+        Ok(path)
+    }
+}
+
+mod base64_bytes {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_round_trip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let recorder = Recorder::new(&dir.path().join("recordings"))?;
+        let recording = Recording::new(
+            "http",
+            "api",
+            RecordedPayload::Http {
+                method: "POST".into(),
+                path_and_query: "/orders?dry-run=1".into(),
+                headers: vec![("content-type".into(), "application/json".into())],
+                body: b"{\"id\":1}".to_vec(),
+            },
+        );
+        let first = recorder.record(&recording)?;
+        let second = recorder.record(&recording)?;
+        assert_ne!(first, second);
+        assert_eq!(Recording::from_file(&first)?, recording);
+
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&first)?)?;
+        assert_eq!(json["payload"]["type"], "http");
+        assert_eq!(json["payload"]["body"], "eyJpZCI6MX0=");
+        Ok(())
+    }
+}
@@ -132,6 +132,14 @@ impl Provider for EnvVariablesProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
         tokio::task::block_in_place(|| self.get_sync(key))
     }
+
+    /// References are the names of environment variables, without the
+    /// provider's prefix. They are only resolved at startup, so this doesn't
+    /// move off the runtime to read the dotenv file, which may be a
+    /// single-threaded one.
+    async fn get_reference(&self, reference: &str) -> anyhow::Result<Option<String>> {
+        self.query_env(reference)
+    }
 }
 
 #[cfg(test)]
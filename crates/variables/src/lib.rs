@@ -2,11 +2,13 @@
 
 mod azure_key_vault;
 mod env;
+mod secret_references;
 mod statik;
 mod vault;
 
 pub use azure_key_vault::*;
 pub use env::*;
+pub use secret_references::*;
 pub use statik::*;
 pub use vault::*;
 
@@ -45,6 +47,10 @@ pub enum VariableProviderConfiguration {
     /// A provider that uses Azure Key Vault.
     AzureKeyVault(AzureKeyVaultVariablesConfig),
     /// A static provider of variables.
+    ///
+    /// Also accepted as `memory`, for consistency with the in-memory
+    /// implementations of other factors.
+    #[serde(alias = "memory")]
     Static(StaticVariablesProvider),
     /// A provider that uses HashiCorp Vault.
     Vault(VaultVariablesProvider),
@@ -53,6 +59,16 @@ pub enum VariableProviderConfiguration {
 }
 
 impl VariableProviderConfiguration {
+    /// The provider's type, as written in the runtime config.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            VariableProviderConfiguration::AzureKeyVault(_) => "azure_key_vault",
+            VariableProviderConfiguration::Static(_) => "static",
+            VariableProviderConfiguration::Vault(_) => "vault",
+            VariableProviderConfiguration::Env(_) => "env",
+        }
+    }
+
     /// Returns the provider for the configuration.
     pub fn into_provider(self) -> anyhow::Result<Box<dyn Provider>> {
         let provider: Box<dyn Provider> = match self {
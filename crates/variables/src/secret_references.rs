@@ -0,0 +1,279 @@
+//! Secret references in runtime config.
+//!
+//! A string in the runtime config can refer to a secret held by one of the
+//! configured variables providers as `{{ <provider type>:<reference> }}`,
+//! e.g. `{{ vault:databases/orders/token }}` or `{{ env:COSMOS_KEY }}`. What
+//! a reference means is up to the provider; see
+//! [`Provider::get_reference`]. References are replaced with the secrets
+//! when the runtime config is loaded, so secrets don't have to be written in
+//! it.
+
+use std::{collections::HashMap, ops::Range};
+
+use spin_expressions::Provider;
+use spin_factors::anyhow::{self, Context as _};
+use toml::{Table, Value};
+
+use crate::{EnvVariablesProvider, VariableProviderConfiguration};
+
+/// The keys of the sections configuring variables providers, which can't
+/// contain secret references themselves.
+const PROVIDER_KEYS: &[&str] = &["variables_provider", "config_provider"];
+
+/// Returns whether any string in the runtime config contains a secret
+/// reference.
+pub fn has_secret_references(toml: &Table) -> bool {
+    let mut found = false;
+    visit_strings(toml, &mut |string| {
+        found |= !find_references(string).is_empty();
+    });
+    found
+}
+
+/// Replaces the secret references in the runtime config with the secrets
+/// they refer to, looked up in the variables providers it configures. The
+/// secrets are registered to be redacted from logs and traces.
+///
+/// Fails if a reference names a provider type which isn't configured, or
+/// none of the providers of that type has the secret.
+pub async fn resolve_secret_references(toml: &mut Table) -> anyhow::Result<()> {
+    let mut references = vec![];
+    visit_strings(toml, &mut |string| {
+        for reference in find_references(string) {
+            references.push((
+                reference.provider.to_owned(),
+                reference.reference.to_owned(),
+            ));
+        }
+    });
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let providers = providers(toml)?;
+    let mut secrets = HashMap::new();
+    for (provider_type, reference) in references {
+        if secrets.contains_key(&(provider_type.clone(), reference.clone())) {
+            continue;
+        }
+        let secret = lookup(&providers, &provider_type, &reference)
+            .await
+            .with_context(|| {
+                format!("failed to resolve secret reference `{provider_type}:{reference}`")
+            })?;
+        // Secrets may end up in e.g. connection errors
+        spin_telemetry::redaction::add_secret(&secret);
+        secrets.insert((provider_type, reference), secret);
+    }
+
+    visit_strings_mut(toml, &mut |string| {
+        let references = find_references(string);
+        // Replace from the end so that earlier ranges stay valid
+        for reference in references.iter().rev() {
+            let key = (
+                reference.provider.to_owned(),
+                reference.reference.to_owned(),
+            );
+            string.replace_range(reference.range.clone(), &secrets[&key]);
+        }
+    });
+    Ok(())
+}
+
+/// The configured variables providers, by type, in the order they are
+/// consulted.
+fn providers(toml: &Table) -> anyhow::Result<Vec<(&'static str, Box<dyn Provider>)>> {
+    let configs: Vec<VariableProviderConfiguration> =
+        match PROVIDER_KEYS.iter().find_map(|key| toml.get(*key)) {
+            Some(value) => value.clone().try_into()?,
+            None => vec![],
+        };
+    let mut providers = configs
+        .into_iter()
+        .map(|config| Ok((config.type_name(), config.into_provider()?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // The environment variable provider is always available
+    providers.push(("env", Box::<EnvVariablesProvider>::default() as _));
+    Ok(providers)
+}
+
+async fn lookup(
+    providers: &[(&'static str, Box<dyn Provider>)],
+    provider_type: &str,
+    reference: &str,
+) -> anyhow::Result<String> {
+    let mut candidates = providers
+        .iter()
+        .filter(|(ty, _)| *ty == provider_type)
+        .peekable();
+    anyhow::ensure!(
+        candidates.peek().is_some(),
+        "no variables provider of type `{provider_type}` is configured"
+    );
+    for (_, provider) in candidates {
+        if let Some(secret) = provider.get_reference(reference).await? {
+            return Ok(secret);
+        }
+    }
+    anyhow::bail!("the secret was not found")
+}
+
+/// A secret reference in a string.
+#[derive(Debug, PartialEq)]
+struct SecretReference<'a> {
+    /// Where the reference is in the string, including its braces.
+    range: Range<usize>,
+    provider: &'a str,
+    reference: &'a str,
+}
+
+fn find_references(string: &str) -> Vec<SecretReference<'_>> {
+    let mut references = vec![];
+    let mut start = 0;
+    while let Some(open) = string[start..].find("{{").map(|i| start + i) {
+        let Some(close) = string[open..].find("}}").map(|i| open + i) else {
+            break;
+        };
+        let inner = &string[open + 2..close];
+        if let Some((provider, reference)) = inner.trim().split_once(':') {
+            let is_provider_type = !provider.is_empty()
+                && provider
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b == b'_');
+            if is_provider_type && !reference.trim().is_empty() {
+                references.push(SecretReference {
+                    range: open..close + 2,
+                    provider,
+                    reference: reference.trim(),
+                });
+            }
+        }
+        start = close + 2;
+    }
+    references
+}
+
+fn visit_strings(toml: &Table, f: &mut impl FnMut(&str)) {
+    fn visit(value: &Value, f: &mut impl FnMut(&str)) {
+        match value {
+            Value::String(string) => f(string),
+            Value::Array(values) => values.iter().for_each(|value| visit(value, f)),
+            Value::Table(table) => table.values().for_each(|value| visit(value, f)),
+            _ => {}
+        }
+    }
+    for (key, value) in toml {
+        if !PROVIDER_KEYS.contains(&key.as_str()) {
+            visit(value, f);
+        }
+    }
+}
+
+fn visit_strings_mut(toml: &mut Table, f: &mut impl FnMut(&mut String)) {
+    fn visit(value: &mut Value, f: &mut impl FnMut(&mut String)) {
+        match value {
+            Value::String(string) => f(string),
+            Value::Array(values) => values.iter_mut().for_each(|value| visit(value, f)),
+            Value::Table(table) => table.values_mut().for_each(|value| visit(value, f)),
+            _ => {}
+        }
+    }
+    for (key, value) in toml.iter_mut() {
+        if !PROVIDER_KEYS.contains(&key.as_str()) {
+            visit(value, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_references() {
+        let references = find_references("libsql://{{ vault:db/token }}@{{static:host}}");
+        assert_eq!(
+            references,
+            [
+                SecretReference {
+                    range: 9..29,
+                    provider: "vault",
+                    reference: "db/token",
+                },
+                SecretReference {
+                    range: 30..45,
+                    provider: "static",
+                    reference: "host",
+                },
+            ]
+        );
+        assert!(find_references("{{ no_reference }} and {{ unclosed").is_empty());
+    }
+
+    #[tokio::test]
+    async fn resolves_references_through_providers() -> anyhow::Result<()> {
+        let mut toml: Table = toml::toml! {
+            [[variables_provider]]
+            type = "static"
+            values = { cosmos_key = "s3cr3t", token = "t0k3n" }
+
+            [key_value_store.default]
+            type = "azure_cosmos"
+            key = "{{ static:cosmos_key }}"
+            account = "spin"
+
+            [sqlite_database.default]
+            type = "libsql"
+            token = "{{ static:token }}"
+        };
+        assert!(has_secret_references(&toml));
+        resolve_secret_references(&mut toml).await?;
+        assert_eq!(
+            toml["key_value_store"]["default"]["key"].as_str(),
+            Some("s3cr3t")
+        );
+        assert_eq!(
+            toml["sqlite_database"]["default"]["token"].as_str(),
+            Some("t0k3n")
+        );
+        assert!(!has_secret_references(&toml));
+        Ok(())
+    }
+
+    // `#[tokio::test]` runs on a single-threaded runtime, as the CLI may.
+    #[tokio::test]
+    async fn resolves_environment_references() -> anyhow::Result<()> {
+        std::env::set_var("SPIN_TEST_SECRET_REFERENCE", "from-env");
+        let mut toml: Table = toml::toml! {
+            [key_value_store.default]
+            key = "{{ env:SPIN_TEST_SECRET_REFERENCE }}"
+        };
+        resolve_secret_references(&mut toml).await?;
+        assert_eq!(
+            toml["key_value_store"]["default"]["key"].as_str(),
+            Some("from-env")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unresolvable_references_fail() {
+        let mut toml: Table = toml::toml! {
+            [[variables_provider]]
+            type = "static"
+            values = {}
+
+            [key_value_store.default]
+            key = "{{ static:missing }}"
+        };
+        let err = resolve_secret_references(&mut toml).await.unwrap_err();
+        assert!(format!("{err:#}").contains("not found"));
+
+        let mut toml: Table = toml::toml! {
+            [key_value_store.default]
+            key = "{{ vault:db/key }}"
+        };
+        let err = resolve_secret_references(&mut toml).await.unwrap_err();
+        assert!(format!("{err:#}").contains("no variables provider of type `vault`"));
+    }
+}
@@ -7,6 +7,7 @@ use spin_factors::anyhow;
 /// A [`Provider`] that reads variables from an static map.
 #[derive(Debug, Deserialize, Clone)]
 pub struct StaticVariablesProvider {
+    #[serde(default)]
     values: Arc<HashMap<String, String>>,
 }
 
@@ -29,6 +29,18 @@ pub struct VaultVariablesProvider {
 impl Provider for VaultVariablesProvider {
     #[instrument(name = "spin_variables.get_from_vault", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        self.read(key.as_str()).await
+    }
+
+    /// References are paths of secrets in the KV engine, under the prefix.
+    async fn get_reference(&self, reference: &str) -> anyhow::Result<Option<String>> {
+        self.read(reference).await
+    }
+}
+
+impl VaultVariablesProvider {
+    /// Reads the secret at `key` under the prefix.
+    async fn read(&self, key: &str) -> anyhow::Result<Option<String>> {
         let client = VaultClient::new(
             VaultClientSettingsBuilder::default()
                 .address(&self.url)
@@ -36,8 +48,8 @@ impl Provider for VaultVariablesProvider {
                 .build()?,
         )?;
         let path = match &self.prefix {
-            Some(prefix) => format!("{}/{}", prefix, key.as_str()),
-            None => key.as_str().to_string(),
+            Some(prefix) => format!("{prefix}/{key}"),
+            None => key.to_string(),
         };
 
         #[derive(Deserialize, Serialize)]
@@ -0,0 +1,73 @@
+mod store;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_vector_store::{runtime_config::spin::MakeVectorStore, Metric};
+use store::PgvectorStore;
+
+/// The default name of the table vectors are stored in.
+const DEFAULT_TABLE: &str = "spin_vectors";
+
+/// A vector store kept in a Postgres table using the pgvector extension.
+#[derive(Default)]
+pub struct PgvectorVectorStore {
+    _priv: (),
+}
+
+impl PgvectorVectorStore {
+    /// Creates a new `PgvectorVectorStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The serialized runtime configuration for the pgvector vector store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PgvectorVectorStoreRuntimeConfig {
+    /// The connection string of the Postgres database, e.g.
+    /// `host=localhost user=postgres dbname=docs`.
+    address: String,
+    /// The table to store vectors in, created if it doesn't exist.
+    /// Defaults to `spin_vectors`.
+    table: Option<String>,
+    /// How distances between vectors are measured. Defaults to cosine.
+    #[serde(default)]
+    metric: Metric,
+    /// The number of dimensions every vector has.
+    dimensions: u32,
+}
+
+impl MakeVectorStore for PgvectorVectorStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "pgvector";
+
+    type RuntimeConfig = PgvectorVectorStoreRuntimeConfig;
+
+    type VectorStore = PgvectorStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore> {
+        let table = runtime_config.table.unwrap_or_else(|| DEFAULT_TABLE.into());
+        anyhow::ensure!(
+            is_identifier(&table),
+            "invalid table name {table:?}: only letters, digits and underscores are allowed"
+        );
+        let config = runtime_config
+            .address
+            .parse::<tokio_postgres::Config>()
+            .map_err(|e| anyhow::anyhow!("invalid Postgres address: {e}"))?;
+        Ok(PgvectorStore::new(
+            config,
+            table,
+            runtime_config.metric,
+            runtime_config.dimensions,
+        ))
+    }
+}
+
+/// Whether a name can be used unquoted as an SQL identifier.
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
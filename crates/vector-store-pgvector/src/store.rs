@@ -0,0 +1,285 @@
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use spin_core::async_trait;
+use spin_factor_vector_store::{
+    check_dimensions, metadata_from_json, metadata_to_json, Comparison, Error, Filter, Metric,
+    Point, QueryOptions, ScoredPoint, Value, VectorStore,
+};
+use tokio::sync::OnceCell;
+use tokio_postgres::{
+    config::{Host, SslMode},
+    types::ToSql,
+    Client, NoTls, Row,
+};
+
+/// A vector store in a Postgres table with a pgvector `vector` column.
+///
+/// Queries are ordered by pgvector's distance operators, so they can use any
+/// index created on the table, with metadata filters applied in SQL.
+pub struct PgvectorStore {
+    config: tokio_postgres::Config,
+    table: String,
+    metric: Metric,
+    dimensions: u32,
+    client: OnceCell<Client>,
+}
+
+impl PgvectorStore {
+    pub fn new(
+        config: tokio_postgres::Config,
+        table: String,
+        metric: Metric,
+        dimensions: u32,
+    ) -> Self {
+        Self {
+            config,
+            table,
+            metric,
+            dimensions,
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn client(&self) -> Result<&Client, Error> {
+        self.client
+            .get_or_try_init(|| async {
+                let client = if self.config.get_ssl_mode() == SslMode::Disable {
+                    let (client, connection) =
+                        self.config.connect(NoTls).await.map_err(to_store_error)?;
+                    spawn_connection(connection);
+                    client
+                } else {
+                    let connector = TlsConnector::new().map_err(|e| Error::Other(e.to_string()))?;
+                    let (client, connection) = self
+                        .config
+                        .connect(MakeTlsConnector::new(connector))
+                        .await
+                        .map_err(to_store_error)?;
+                    spawn_connection(connection);
+                    client
+                };
+                client
+                    .batch_execute(&format!(
+                        "CREATE EXTENSION IF NOT EXISTS vector;
+                         CREATE TABLE IF NOT EXISTS {} (
+                             id        TEXT PRIMARY KEY,
+                             embedding vector({}) NOT NULL,
+                             metadata  JSONB NOT NULL
+                         )",
+                        self.table, self.dimensions
+                    ))
+                    .await
+                    .map_err(to_store_error)?;
+                Ok(client)
+            })
+            .await
+    }
+
+    fn distance_operator(&self) -> &'static str {
+        match self.metric {
+            Metric::Cosine => "<=>",
+            Metric::Euclidean => "<->",
+            // pgvector's `<#>` is the negative inner product.
+            Metric::Dot => "<#>",
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for PgvectorStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        for point in &points {
+            check_dimensions(Some(self.dimensions), &point.vector)?;
+        }
+        // Upsert everything in one statement so that it happens atomically.
+        // Postgres rejects a statement updating the same row twice, so only
+        // the last point with each ID is kept.
+        let mut ids: Vec<&str> = vec![];
+        let mut vectors = vec![];
+        let mut metadata = vec![];
+        for point in points.iter().rev() {
+            if ids.contains(&point.id.as_str()) {
+                continue;
+            }
+            ids.push(&point.id);
+            vectors.push(vector_text(&point.vector));
+            metadata.push(serde_json::Value::Object(metadata_to_json(&point.metadata)).to_string());
+        }
+        let sql = format!(
+            "INSERT INTO {} (id, embedding, metadata)
+             SELECT id, embedding::vector, metadata::jsonb
+             FROM unnest($1::text[], $2::text[], $3::text[]) AS points (id, embedding, metadata)
+             ON CONFLICT (id) DO UPDATE SET embedding = excluded.embedding, metadata = excluded.metadata",
+            self.table
+        );
+        let client = self.client().await?;
+        client
+            .execute(&sql, &[&ids, &vectors, &metadata])
+            .await
+            .map_err(to_store_error)?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        check_dimensions(Some(self.dimensions), &vector)?;
+        let mut params = vec![vector_text(&vector)];
+        let mut conditions = vec![];
+        for filter in &options.filters {
+            conditions.push(filter_condition(filter, &mut params));
+        }
+        let conditions = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT id, metadata::text, embedding::text, (embedding {} $1::text::vector)::float8 AS distance
+             FROM {} {conditions} ORDER BY distance LIMIT {}",
+            self.distance_operator(),
+            self.table,
+            options.top_k
+        );
+
+        let client = self.client().await?;
+        let params: Vec<_> = params.iter().map(|p| p as &(dyn ToSql + Sync)).collect();
+        let rows = client.query(&sql, &params).await.map_err(to_store_error)?;
+        rows.iter()
+            .map(|row| {
+                let point = point_from_row(row)?;
+                let distance: f64 = row.try_get(3).map_err(to_store_error)?;
+                Ok(ScoredPoint {
+                    id: point.id,
+                    distance: distance as f32,
+                    metadata: point.metadata,
+                    vector: options.include_vectors.then_some(point.vector),
+                })
+            })
+            .collect()
+    }
+
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        let client = self.client().await?;
+        let sql = format!(
+            "SELECT id, metadata::text, embedding::text FROM {} WHERE id = ANY($1)",
+            self.table
+        );
+        let rows = client.query(&sql, &[&ids]).await.map_err(to_store_error)?;
+        let mut points = rows
+            .iter()
+            .map(point_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+        points.sort_by_key(|point| ids.iter().position(|id| id == &point.id));
+        Ok(points)
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        let client = self.client().await?;
+        let sql = format!("DELETE FROM {} WHERE id = ANY($1)", self.table);
+        client
+            .execute(&sql, &[&ids])
+            .await
+            .map_err(to_store_error)?;
+        Ok(())
+    }
+
+    fn summary(&self) -> Option<String> {
+        let host = match self.config.get_hosts().first() {
+            Some(Host::Tcp(host)) => host.clone(),
+            #[cfg(unix)]
+            Some(Host::Unix(path)) => path.display().to_string(),
+            None => "localhost".into(),
+        };
+        Some(format!("Postgres table {:?} on {host}", self.table))
+    }
+}
+
+/// Builds the SQL condition for a metadata filter, adding its parameters to
+/// `params`.
+///
+/// The condition checks the JSON type of the stored value so that values of
+/// different types never match, as with other stores. A `CASE` guards the
+/// cast since Postgres doesn't promise to evaluate `AND` in order.
+fn filter_condition(filter: &Filter, params: &mut Vec<String>) -> String {
+    let operator = match filter.comparison {
+        Comparison::Equal => "=",
+        Comparison::NotEqual => "<>",
+        Comparison::LessThan => "<",
+        Comparison::LessThanOrEqual => "<=",
+        Comparison::GreaterThan => ">",
+        Comparison::GreaterThanOrEqual => ">=",
+    };
+    let (json_type, cast, value) = match &filter.value {
+        Value::Text(s) => ("string", "text", s.clone()),
+        Value::Integer(i) => ("number", "numeric", i.to_string()),
+        Value::Float(f) => ("number", "numeric", f.to_string()),
+        Value::Boolean(b) => ("boolean", "boolean", b.to_string()),
+    };
+    params.push(filter.key.clone());
+    let key = params.len();
+    params.push(value);
+    let value = params.len();
+    format!(
+        "(CASE WHEN jsonb_typeof(metadata -> ${key}::text) = '{json_type}' THEN (metadata ->> ${key}::text)::{cast} {operator} ${value}::text::{cast} END)"
+    )
+}
+
+fn point_from_row(row: &Row) -> Result<Point, Error> {
+    let id: String = row.try_get(0).map_err(to_store_error)?;
+    let metadata: String = row.try_get(1).map_err(to_store_error)?;
+    let vector: String = row.try_get(2).map_err(to_store_error)?;
+    let metadata = match serde_json::from_str(&metadata) {
+        Ok(serde_json::Value::Object(object)) => metadata_from_json(object),
+        _ => vec![],
+    };
+    // pgvector's text format is a JSON array.
+    let vector = serde_json::from_str(&vector).map_err(|e| Error::Other(e.to_string()))?;
+    Ok(Point {
+        id,
+        vector,
+        metadata,
+    })
+}
+
+/// Formats a vector as the text pgvector accepts.
+fn vector_text(vector: &[f32]) -> String {
+    serde_json::to_string(vector).unwrap()
+}
+
+fn spawn_connection<T>(connection: T)
+where
+    T: std::future::Future<Output = Result<(), tokio_postgres::Error>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres vector store connection error: {e}");
+        }
+    });
+}
+
+fn to_store_error(err: tokio_postgres::Error) -> Error {
+    Error::Other(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_are_parameterized() {
+        let mut params = vec!["[1,0]".to_owned()];
+        let filter = Filter {
+            key: "year".into(),
+            comparison: Comparison::LessThan,
+            value: Value::Integer(2020),
+        };
+        assert_eq!(
+            "(CASE WHEN jsonb_typeof(metadata -> $2::text) = 'number' THEN (metadata ->> $2::text)::numeric < $3::text::numeric END)",
+            filter_condition(&filter, &mut params)
+        );
+        assert_eq!(vec!["[1,0]", "year", "2020"], params);
+    }
+}
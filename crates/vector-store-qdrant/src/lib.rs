@@ -0,0 +1,58 @@
+mod store;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use spin_factor_vector_store::{runtime_config::spin::MakeVectorStore, Metric};
+use store::QdrantStore;
+
+/// A vector store kept in a Qdrant collection, accessed over Qdrant's REST API.
+#[derive(Default)]
+pub struct QdrantVectorStore {
+    _priv: (),
+}
+
+impl QdrantVectorStore {
+    /// Creates a new `QdrantVectorStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The serialized runtime configuration for the Qdrant vector store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QdrantVectorStoreRuntimeConfig {
+    /// The URL of the Qdrant server, e.g. `http://localhost:6333`.
+    url: String,
+    /// The API key to authenticate with, if the server requires one.
+    api_key: Option<String>,
+    /// The collection to store vectors in.
+    collection: String,
+    /// How distances between vectors are measured. Defaults to cosine.
+    ///
+    /// This is only used when creating the collection; an existing
+    /// collection keeps the metric it was created with.
+    #[serde(default)]
+    metric: Metric,
+    /// The number of dimensions every vector has. If set, the collection is
+    /// created on first use if it doesn't exist.
+    dimensions: Option<u32>,
+}
+
+impl MakeVectorStore for QdrantVectorStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "qdrant";
+
+    type RuntimeConfig = QdrantVectorStoreRuntimeConfig;
+
+    type VectorStore = QdrantStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore> {
+        QdrantStore::new(
+            &runtime_config.url,
+            runtime_config.api_key,
+            runtime_config.collection,
+            runtime_config.metric,
+            runtime_config.dimensions,
+        )
+    }
+}
@@ -0,0 +1,419 @@
+use anyhow::Context as _;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as Json};
+use spin_core::async_trait;
+use spin_factor_vector_store::{
+    check_dimensions, metadata_from_json, metadata_to_json, Comparison, Error, Filter, Metric,
+    Point, QueryOptions, ScoredPoint, Value, VectorStore,
+};
+use tokio::sync::OnceCell;
+use url::Url;
+use uuid::Uuid;
+
+/// The payload field holding a point's original ID.
+///
+/// Qdrant only accepts integers and UUIDs as point IDs, so points are stored
+/// under a UUID derived from their ID.
+const ID_FIELD: &str = "spin_id";
+/// The payload field holding a point's metadata.
+const METADATA_FIELD: &str = "metadata";
+
+/// A vector store in a Qdrant collection.
+pub struct QdrantStore {
+    client: Client,
+    /// The URL of the collection, e.g. `http://localhost:6333/collections/docs/`.
+    collection_url: Url,
+    api_key: Option<String>,
+    collection: String,
+    metric: Metric,
+    dimensions: Option<u32>,
+    /// The metric of the collection, found (or the collection created) on
+    /// first use.
+    collection_metric: OnceCell<Metric>,
+}
+
+impl QdrantStore {
+    pub fn new(
+        url: &str,
+        api_key: Option<String>,
+        collection: String,
+        metric: Metric,
+        dimensions: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        let mut collection_url =
+            Url::parse(url).with_context(|| format!("invalid Qdrant URL {url:?}"))?;
+        collection_url
+            .path_segments_mut()
+            .map_err(|()| anyhow::anyhow!("invalid Qdrant URL {url:?}"))?
+            .pop_if_empty()
+            .extend(["collections", &collection, ""]);
+        Ok(Self {
+            client: Client::new(),
+            collection_url,
+            api_key,
+            collection,
+            metric,
+            dimensions,
+            collection_metric: OnceCell::new(),
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        // Joining a relative path to a URL ending in a slash can't fail.
+        let url = self.collection_url.join(path).unwrap();
+        let request = self.client.request(method, url);
+        match &self.api_key {
+            Some(api_key) => request.header("api-key", api_key),
+            None => request,
+        }
+    }
+
+    /// Returns the collection's metric, creating the collection first if it
+    /// doesn't exist and the store knows its dimensions.
+    async fn collection_metric(&self) -> Result<Metric, Error> {
+        self.collection_metric
+            .get_or_try_init(|| async {
+                let response = self
+                    .request(Method::GET, "")
+                    .send()
+                    .await
+                    .map_err(to_store_error)?;
+                if response.status() != StatusCode::NOT_FOUND {
+                    let info: Json = parse_response(response).await?;
+                    let distance = &info["config"]["params"]["vectors"]["distance"];
+                    return metric_from_distance(distance.as_str().unwrap_or_default());
+                }
+
+                let Some(dimensions) = self.dimensions else {
+                    return Err(Error::Other(format!(
+                        "Qdrant collection {:?} doesn't exist and can't be created without `dimensions`",
+                        self.collection
+                    )));
+                };
+                let body = json!({
+                    "vectors": { "size": dimensions, "distance": distance_name(self.metric) }
+                });
+                let response = self
+                    .request(Method::PUT, "")
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(to_store_error)?;
+                parse_response::<Json>(response).await?;
+                Ok(self.metric)
+            })
+            .await
+            .copied()
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        for point in &points {
+            check_dimensions(self.dimensions, &point.vector)?;
+        }
+        self.collection_metric().await?;
+        let points: Vec<_> = points
+            .iter()
+            .map(|point| {
+                json!({
+                    "id": point_uuid(&point.id),
+                    "vector": point.vector,
+                    "payload": {
+                        ID_FIELD: point.id,
+                        METADATA_FIELD: metadata_to_json(&point.metadata),
+                    },
+                })
+            })
+            .collect();
+        let response = self
+            .request(Method::PUT, "points?wait=true")
+            .json(&json!({ "points": points }))
+            .send()
+            .await
+            .map_err(to_store_error)?;
+        parse_response::<Json>(response).await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        check_dimensions(self.dimensions, &vector)?;
+        let metric = self.collection_metric().await?;
+        let body = json!({
+            "vector": vector,
+            "limit": options.top_k,
+            "filter": filter_json(&options.filters)?,
+            "with_payload": true,
+            "with_vector": options.include_vectors,
+        });
+        let response = self
+            .request(Method::POST, "points/search")
+            .json(&body)
+            .send()
+            .await
+            .map_err(to_store_error)?;
+        let results: Vec<Json> = parse_response(response).await?;
+        results
+            .into_iter()
+            .map(|result| {
+                let score = result["score"].as_f64().unwrap_or_default() as f32;
+                let point = point_from_json(result)?;
+                Ok(ScoredPoint {
+                    id: point.id,
+                    distance: distance_from_score(metric, score),
+                    metadata: point.metadata,
+                    vector: options.include_vectors.then_some(point.vector),
+                })
+            })
+            .collect()
+    }
+
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        self.collection_metric().await?;
+        let uuids: Vec<_> = ids.iter().map(|id| point_uuid(id)).collect();
+        let body = json!({ "ids": uuids, "with_payload": true, "with_vector": true });
+        let response = self
+            .request(Method::POST, "points")
+            .json(&body)
+            .send()
+            .await
+            .map_err(to_store_error)?;
+        let results: Vec<Json> = parse_response(response).await?;
+        let mut points = results
+            .into_iter()
+            .map(point_from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        // Qdrant doesn't return points in the order they were asked for.
+        points.sort_by_key(|point| ids.iter().position(|id| id == &point.id));
+        Ok(points)
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        self.collection_metric().await?;
+        let uuids: Vec<_> = ids.iter().map(|id| point_uuid(id)).collect();
+        let response = self
+            .request(Method::POST, "points/delete?wait=true")
+            .json(&json!({ "points": uuids }))
+            .send()
+            .await
+            .map_err(to_store_error)?;
+        parse_response::<Json>(response).await?;
+        Ok(())
+    }
+
+    fn summary(&self) -> Option<String> {
+        let mut server = self.collection_url.clone();
+        server.set_path("");
+        Some(format!(
+            "Qdrant collection {:?} at {}",
+            self.collection,
+            server.as_str().trim_end_matches('/')
+        ))
+    }
+}
+
+/// The UUID a point is stored under in Qdrant.
+fn point_uuid(id: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, id.as_bytes())
+}
+
+/// Qdrant's name for a metric.
+fn distance_name(metric: Metric) -> &'static str {
+    match metric {
+        Metric::Cosine => "Cosine",
+        Metric::Euclidean => "Euclid",
+        Metric::Dot => "Dot",
+    }
+}
+
+fn metric_from_distance(distance: &str) -> Result<Metric, Error> {
+    match distance {
+        "Cosine" => Ok(Metric::Cosine),
+        "Euclid" => Ok(Metric::Euclidean),
+        "Dot" => Ok(Metric::Dot),
+        other => Err(Error::Other(format!(
+            "unsupported Qdrant collection distance {other:?}"
+        ))),
+    }
+}
+
+/// Converts a Qdrant score, where higher is closer for cosine and dot
+/// product, to a distance where lower is always closer.
+fn distance_from_score(metric: Metric, score: f32) -> f32 {
+    match metric {
+        Metric::Cosine => 1.0 - score,
+        Metric::Euclidean => score,
+        Metric::Dot => -score,
+    }
+}
+
+/// Builds a Qdrant filter requiring all of `filters` to be satisfied.
+fn filter_json(filters: &[Filter]) -> Result<Json, Error> {
+    let mut must = vec![];
+    let mut must_not = vec![];
+    for filter in filters {
+        // Qdrant treats dots and brackets in keys as paths into nested values.
+        if filter.key.contains(['.', '[', ']']) {
+            return Err(Error::InvalidInput(format!(
+                "metadata key {:?} can't be filtered on",
+                filter.key
+            )));
+        }
+        let key = format!("{METADATA_FIELD}.{}", filter.key);
+        let range = |bound: &str, value: f64| json!({ "key": key, "range": { bound: value } });
+        match (filter.comparison, &filter.value) {
+            (Comparison::Equal | Comparison::NotEqual, value) => {
+                let condition = match value {
+                    Value::Text(s) => json!({ "key": key, "match": { "value": s } }),
+                    Value::Integer(i) => json!({ "key": key, "match": { "value": i } }),
+                    Value::Boolean(b) => json!({ "key": key, "match": { "value": b } }),
+                    // Qdrant only matches keywords, integers and booleans exactly.
+                    Value::Float(f) => json!({ "key": key, "range": { "gte": f, "lte": f } }),
+                };
+                if filter.comparison == Comparison::Equal {
+                    must.push(condition);
+                } else {
+                    // As with other stores, points without the key don't match.
+                    must_not.push(json!({ "is_empty": { "key": key } }));
+                    must_not.push(condition);
+                }
+            }
+            (comparison, Value::Integer(_) | Value::Float(_)) => {
+                let value = match filter.value {
+                    Value::Integer(i) => i as f64,
+                    Value::Float(f) => f,
+                    _ => unreachable!(),
+                };
+                let bound = match comparison {
+                    Comparison::LessThan => "lt",
+                    Comparison::LessThanOrEqual => "lte",
+                    Comparison::GreaterThan => "gt",
+                    _ => "gte",
+                };
+                must.push(range(bound, value));
+            }
+            _ => {
+                return Err(Error::InvalidInput(format!(
+                    "Qdrant stores can only order numbers, but the filter on {:?} compares another type",
+                    filter.key
+                )))
+            }
+        }
+    }
+    Ok(json!({ "must": must, "must_not": must_not }))
+}
+
+fn point_from_json(mut json: Json) -> Result<Point, Error> {
+    let payload = json["payload"].take();
+    let Some(id) = payload[ID_FIELD].as_str() else {
+        return Err(Error::Other(format!(
+            "Qdrant point {} has no `{ID_FIELD}` payload field",
+            json["id"]
+        )));
+    };
+    let metadata = match &payload[METADATA_FIELD] {
+        Json::Object(object) => metadata_from_json(object.clone()),
+        _ => vec![],
+    };
+    let vector = serde_json::from_value(json["vector"].take()).unwrap_or_default();
+    Ok(Point {
+        id: id.to_owned(),
+        vector,
+        metadata,
+    })
+}
+
+/// Reads the `result` of a Qdrant response, or the error it reports.
+async fn parse_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+    let status = response.status();
+    let mut body: Json = response.json().await.map_err(to_store_error)?;
+    if !status.is_success() {
+        let message = body["status"]["error"].as_str().unwrap_or("unknown error");
+        return Err(Error::Other(format!(
+            "Qdrant request failed ({status}): {message}"
+        )));
+    }
+    serde_json::from_value(body["result"].take()).map_err(|e| Error::Other(e.to_string()))
+}
+
+fn to_store_error(err: reqwest::Error) -> Error {
+    Error::Other(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_url_is_built_from_server_url() {
+        let store = QdrantStore::new(
+            "http://localhost:6333/",
+            None,
+            "my docs".into(),
+            Metric::Cosine,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            "http://localhost:6333/collections/my%20docs/points/search",
+            store
+                .request(Method::POST, "points/search")
+                .build()
+                .unwrap()
+                .url()
+                .as_str()
+        );
+        assert_eq!(
+            Some("Qdrant collection \"my docs\" at http://localhost:6333".to_owned()),
+            store.summary()
+        );
+    }
+
+    #[test]
+    fn filters_translate_to_qdrant_conditions() {
+        let filters = [
+            Filter {
+                key: "lang".into(),
+                comparison: Comparison::NotEqual,
+                value: Value::Text("en".into()),
+            },
+            Filter {
+                key: "year".into(),
+                comparison: Comparison::GreaterThan,
+                value: Value::Integer(2020),
+            },
+        ];
+        assert_eq!(
+            json!({
+                "must": [
+                    { "key": "metadata.year", "range": { "gt": 2020.0 } },
+                ],
+                "must_not": [
+                    { "is_empty": { "key": "metadata.lang" } },
+                    { "key": "metadata.lang", "match": { "value": "en" } },
+                ],
+            }),
+            filter_json(&filters).unwrap()
+        );
+
+        let ordered_text = Filter {
+            key: "lang".into(),
+            comparison: Comparison::LessThan,
+            value: Value::Text("en".into()),
+        };
+        assert!(filter_json(&[ordered_text]).is_err());
+    }
+
+    #[test]
+    fn scores_become_distances() {
+        assert_eq!(0.25, distance_from_score(Metric::Cosine, 0.75));
+        assert_eq!(-3.0, distance_from_score(Metric::Dot, 3.0));
+        assert_eq!(2.0, distance_from_score(Metric::Euclidean, 2.0));
+    }
+}
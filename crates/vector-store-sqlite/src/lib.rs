@@ -0,0 +1,206 @@
+mod libsql_store;
+mod sqlite_store;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use libsql_store::LibsqlStore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use spin_factor_vector_store::{runtime_config::spin::MakeVectorStore, Metric};
+use sqlite_store::{DatabaseLocation, SqliteStore};
+
+/// The default name of the table vectors are stored in.
+const DEFAULT_TABLE: &str = "spin_vectors";
+
+/// A vector store kept in a local SQLite database.
+///
+/// Queries compare the query vector with every stored vector, so this suits
+/// development and modest collections rather than large ones.
+pub struct SpinVectorStore {
+    /// The base path or directory for the SQLite database file.
+    base_path: Option<PathBuf>,
+}
+
+impl SpinVectorStore {
+    /// Create a new SpinVectorStore with the given base path.
+    ///
+    /// If the base path is None, relative database paths are used as given.
+    /// Stores without a path are always in-memory.
+    pub fn new(base_path: Option<PathBuf>) -> Self {
+        Self { base_path }
+    }
+}
+
+/// The serialized runtime configuration for the SQLite vector store.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SpinVectorStoreRuntimeConfig {
+    /// The path to the SQLite database file.
+    path: Option<PathBuf>,
+    /// How distances between vectors are measured. Defaults to cosine.
+    #[serde(default)]
+    metric: Metric,
+    /// The number of dimensions every vector must have, if any.
+    dimensions: Option<u32>,
+}
+
+impl SpinVectorStoreRuntimeConfig {
+    /// Create a new SpinVectorStoreRuntimeConfig for a database at the given
+    /// path, or in memory if `None`.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            metric: Metric::default(),
+            dimensions: None,
+        }
+    }
+}
+
+impl MakeVectorStore for SpinVectorStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "spin";
+
+    type RuntimeConfig = SpinVectorStoreRuntimeConfig;
+
+    type VectorStore = SqliteStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore> {
+        let location = match (&self.base_path, &runtime_config.path) {
+            (Some(base_path), Some(path)) => {
+                DatabaseLocation::Path(resolve_relative_path(path, base_path))
+            }
+            (None, Some(path)) => DatabaseLocation::Path(path.clone()),
+            (_, None) => DatabaseLocation::InMemory,
+        };
+        if let DatabaseLocation::Path(path) = &location {
+            // Create the store's parent directory if necessary
+            if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!(
+                        "failed to create vector store's parent directory: '{}",
+                        parent.display()
+                    )
+                })?;
+            }
+        }
+        Ok(SqliteStore::new(
+            location,
+            runtime_config.metric,
+            runtime_config.dimensions,
+        ))
+    }
+}
+
+/// A vector store kept in a remote libSQL database, using libSQL's vector
+/// functions.
+#[derive(Default)]
+pub struct LibsqlVectorStore {
+    _priv: (),
+}
+
+impl LibsqlVectorStore {
+    /// Creates a new `LibsqlVectorStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The serialized runtime configuration for the libSQL vector store.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LibsqlVectorStoreRuntimeConfig {
+    /// The URL of the libSQL database.
+    url: String,
+    /// The token to authenticate with.
+    token: String,
+    /// The table to store vectors in, created if it doesn't exist.
+    /// Defaults to `spin_vectors`.
+    table: Option<String>,
+    /// How distances between vectors are measured: `cosine` (the default)
+    /// or `euclidean`.
+    #[serde(default)]
+    metric: Metric,
+    /// The number of dimensions every vector has.
+    dimensions: u32,
+}
+
+impl MakeVectorStore for LibsqlVectorStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "libsql";
+
+    type RuntimeConfig = LibsqlVectorStoreRuntimeConfig;
+
+    type VectorStore = LibsqlStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::VectorStore> {
+        let table = runtime_config.table.unwrap_or_else(|| DEFAULT_TABLE.into());
+        anyhow::ensure!(
+            is_identifier(&table),
+            "invalid table name {table:?}: only letters, digits and underscores are allowed"
+        );
+        anyhow::ensure!(
+            runtime_config.metric != Metric::Dot,
+            "libSQL vector stores support the cosine and euclidean metrics"
+        );
+        Ok(LibsqlStore::new(
+            runtime_config.url,
+            runtime_config.token,
+            table,
+            runtime_config.metric,
+            runtime_config.dimensions,
+        ))
+    }
+}
+
+/// Whether a name can be used unquoted as an SQL identifier.
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Resolves a relative path against an absolute base path.
+///
+/// If the path is absolute, it is returned as is. Otherwise, it is resolved against the base path.
+fn resolve_relative_path(path: &Path, base_path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_owned();
+    }
+    base_path.join(path)
+}
+
+/// Encodes a vector as the little-endian bytes of its components.
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// Decodes a vector encoded by [`encode_vector`].
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_round_trip() {
+        let vector = vec![1.5, -2.0, 0.0, f32::MAX];
+        assert_eq!(vector, decode_vector(&encode_vector(&vector)));
+    }
+
+    #[test]
+    fn table_names_must_be_identifiers() {
+        assert!(is_identifier("spin_vectors"));
+        assert!(is_identifier("_docs2"));
+        assert!(!is_identifier("2docs"));
+        assert!(!is_identifier("docs; DROP TABLE x"));
+        assert!(!is_identifier(""));
+    }
+}
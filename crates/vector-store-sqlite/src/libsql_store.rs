@@ -0,0 +1,263 @@
+use spin_core::async_trait;
+use spin_factor_vector_store::{
+    check_dimensions, metadata_from_json, metadata_to_json, Comparison, Error, Filter, Metric,
+    Point, QueryOptions, ScoredPoint, Value, VectorStore,
+};
+use tokio::sync::OnceCell;
+
+/// A vector store in a table of a remote libSQL database.
+///
+/// Vectors are stored in an `F32_BLOB` column and queries are ordered by
+/// libSQL's distance functions, with metadata filters applied in SQL.
+pub struct LibsqlStore {
+    url: String,
+    token: String,
+    table: String,
+    metric: Metric,
+    dimensions: u32,
+    // The client can only be created asynchronously, so it is created on
+    // first use.
+    connection: OnceCell<libsql::Connection>,
+}
+
+impl LibsqlStore {
+    pub fn new(url: String, token: String, table: String, metric: Metric, dimensions: u32) -> Self {
+        Self {
+            url,
+            token,
+            table,
+            metric,
+            dimensions,
+            connection: OnceCell::new(),
+        }
+    }
+
+    async fn connection(&self) -> Result<&libsql::Connection, Error> {
+        self.connection
+            .get_or_try_init(|| async {
+                let db = libsql::Builder::new_remote(self.url.clone(), self.token.clone())
+                    .build()
+                    .await
+                    .map_err(to_store_error)?;
+                let connection = db.connect().map_err(to_store_error)?;
+                connection
+                    .execute(
+                        &format!(
+                            "CREATE TABLE IF NOT EXISTS {} (
+                                id        TEXT PRIMARY KEY,
+                                embedding F32_BLOB({}) NOT NULL,
+                                metadata  TEXT NOT NULL
+                            )",
+                            self.table, self.dimensions
+                        ),
+                        (),
+                    )
+                    .await
+                    .map_err(to_store_error)?;
+                Ok(connection)
+            })
+            .await
+    }
+
+    fn distance_function(&self) -> &'static str {
+        match self.metric {
+            Metric::Euclidean => "vector_distance_l2",
+            // Dot products are rejected when the store is configured
+            Metric::Cosine | Metric::Dot => "vector_distance_cos",
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for LibsqlStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        for point in &points {
+            check_dimensions(Some(self.dimensions), &point.vector)?;
+        }
+        let connection = self.connection().await?;
+        let sql = format!(
+            "INSERT INTO {} (id, embedding, metadata) VALUES (?1, vector32(?2), ?3)
+             ON CONFLICT (id) DO UPDATE SET embedding = excluded.embedding, metadata = excluded.metadata",
+            self.table
+        );
+        let transaction = connection.transaction().await.map_err(to_store_error)?;
+        for point in points {
+            let metadata = serde_json::Value::Object(metadata_to_json(&point.metadata));
+            let params = vec![
+                libsql::Value::Text(point.id),
+                libsql::Value::Text(vector_text(&point.vector)),
+                libsql::Value::Text(metadata.to_string()),
+            ];
+            transaction
+                .execute(&sql, params)
+                .await
+                .map_err(to_store_error)?;
+        }
+        transaction.commit().await.map_err(to_store_error)
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        check_dimensions(Some(self.dimensions), &vector)?;
+        let mut params = vec![libsql::Value::Text(vector_text(&vector))];
+        let mut conditions = vec![];
+        for filter in &options.filters {
+            conditions.push(filter_condition(filter, &mut params)?);
+        }
+        let conditions = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT id, metadata, vector_extract(embedding), {}(embedding, vector32(?1)) AS distance
+             FROM {} {conditions} ORDER BY distance LIMIT {}",
+            self.distance_function(),
+            self.table,
+            options.top_k
+        );
+
+        let connection = self.connection().await?;
+        let mut rows = connection
+            .query(&sql, params)
+            .await
+            .map_err(to_store_error)?;
+        let mut results = vec![];
+        while let Some(row) = rows.next().await.map_err(to_store_error)? {
+            let point = point_from_row(&row)?;
+            let distance: f64 = row.get(3).map_err(to_store_error)?;
+            results.push(ScoredPoint {
+                id: point.id,
+                distance: distance as f32,
+                metadata: point.metadata,
+                vector: options.include_vectors.then_some(point.vector),
+            });
+        }
+        Ok(results)
+    }
+
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        let connection = self.connection().await?;
+        let sql = format!(
+            "SELECT id, metadata, vector_extract(embedding) FROM {} WHERE id = ?1",
+            self.table
+        );
+        let mut points = vec![];
+        for id in ids {
+            let mut rows = connection
+                .query(&sql, vec![libsql::Value::Text(id)])
+                .await
+                .map_err(to_store_error)?;
+            if let Some(row) = rows.next().await.map_err(to_store_error)? {
+                points.push(point_from_row(&row)?);
+            }
+        }
+        Ok(points)
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        let connection = self.connection().await?;
+        let sql = format!("DELETE FROM {} WHERE id = ?1", self.table);
+        for id in ids {
+            connection
+                .execute(&sql, vec![libsql::Value::Text(id)])
+                .await
+                .map_err(to_store_error)?;
+        }
+        Ok(())
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("libSQL table {:?} at {}", self.table, self.url))
+    }
+}
+
+/// Builds the SQL condition for a metadata filter, adding its parameters to
+/// `params`.
+///
+/// The condition checks the type of the stored value so that values of
+/// different types never match, as with other stores.
+fn filter_condition(filter: &Filter, params: &mut Vec<libsql::Value>) -> Result<String, Error> {
+    if filter.key.contains('"') {
+        return Err(Error::InvalidInput(format!(
+            "metadata key {:?} can't be filtered on",
+            filter.key
+        )));
+    }
+    let operator = match filter.comparison {
+        Comparison::Equal => "=",
+        Comparison::NotEqual => "!=",
+        Comparison::LessThan => "<",
+        Comparison::LessThanOrEqual => "<=",
+        Comparison::GreaterThan => ">",
+        Comparison::GreaterThanOrEqual => ">=",
+    };
+    let (types, value) = match &filter.value {
+        Value::Text(s) => ("'text'", libsql::Value::Text(s.clone())),
+        Value::Integer(i) => ("'integer', 'real'", libsql::Value::Integer(*i)),
+        Value::Float(f) => ("'integer', 'real'", libsql::Value::Real(*f)),
+        Value::Boolean(b) => ("'true', 'false'", libsql::Value::Integer(i64::from(*b))),
+    };
+    params.push(libsql::Value::Text(format!("$.\"{}\"", filter.key)));
+    let path = params.len();
+    params.push(value);
+    let value = params.len();
+    Ok(format!(
+        "(json_type(metadata, ?{path}) IN ({types}) AND json_extract(metadata, ?{path}) {operator} ?{value})"
+    ))
+}
+
+fn point_from_row(row: &libsql::Row) -> Result<Point, Error> {
+    let id: String = row.get(0).map_err(to_store_error)?;
+    let metadata: String = row.get(1).map_err(to_store_error)?;
+    let vector: String = row.get(2).map_err(to_store_error)?;
+    let metadata = match serde_json::from_str(&metadata) {
+        Ok(serde_json::Value::Object(object)) => metadata_from_json(object),
+        _ => vec![],
+    };
+    let vector = serde_json::from_str(&vector).map_err(|e| Error::Other(e.to_string()))?;
+    Ok(Point {
+        id,
+        vector,
+        metadata,
+    })
+}
+
+/// Formats a vector as the text `vector32` accepts.
+fn vector_text(vector: &[f32]) -> String {
+    serde_json::to_string(vector).unwrap()
+}
+
+fn to_store_error(err: libsql::Error) -> Error {
+    Error::Other(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_are_parameterized() {
+        let mut params = vec![libsql::Value::Text("[1,0]".into())];
+        let filter = Filter {
+            key: "year".into(),
+            comparison: Comparison::GreaterThanOrEqual,
+            value: Value::Integer(2020),
+        };
+        let condition = filter_condition(&filter, &mut params).unwrap();
+        assert_eq!(
+            "(json_type(metadata, ?2) IN ('integer', 'real') AND json_extract(metadata, ?2) >= ?3)",
+            condition
+        );
+        assert_eq!(3, params.len());
+
+        let filter = Filter {
+            key: "a\"b".into(),
+            ..filter
+        };
+        assert!(filter_condition(&filter, &mut params).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use spin_core::async_trait;
+use spin_factor_vector_store::{
+    check_dimensions, exact_search, metadata_from_json, metadata_to_json, Error, Metric, Point,
+    QueryOptions, ScoredPoint, VectorStore,
+};
+use tokio::task;
+
+use crate::{decode_vector, encode_vector};
+
+#[derive(Clone, Debug)]
+pub enum DatabaseLocation {
+    InMemory,
+    Path(PathBuf),
+}
+
+/// A vector store in a local SQLite database, searched exhaustively.
+pub struct SqliteStore {
+    location: DatabaseLocation,
+    metric: Metric,
+    dimensions: Option<u32>,
+    connection: OnceLock<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    pub fn new(location: DatabaseLocation, metric: Metric, dimensions: Option<u32>) -> Self {
+        Self {
+            location,
+            metric,
+            dimensions,
+            connection: OnceLock::new(),
+        }
+    }
+
+    /// Runs `f` with the store's connection, opening it first if necessary.
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, Error> {
+        task::block_in_place(|| {
+            let connection = match self.connection.get() {
+                Some(connection) => connection,
+                None => {
+                    // We might do duplicate work here if there's a race, but that's fine.
+                    let new = self.create_connection().map_err(to_store_error)?;
+                    self.connection.get_or_init(|| Mutex::new(new))
+                }
+            };
+            f(&mut connection.lock().unwrap()).map_err(to_store_error)
+        })
+    }
+
+    fn create_connection(&self) -> rusqlite::Result<Connection> {
+        let connection = match &self.location {
+            DatabaseLocation::InMemory => Connection::open_in_memory(),
+            DatabaseLocation::Path(path) => Connection::open(path),
+        }?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS spin_vectors (
+                id       TEXT PRIMARY KEY,
+                vector   BLOB NOT NULL,
+                metadata TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(connection)
+    }
+}
+
+#[async_trait]
+impl VectorStore for SqliteStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        for point in &points {
+            check_dimensions(self.dimensions, &point.vector)?;
+        }
+        self.with_connection(|connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(
+                    "INSERT INTO spin_vectors (id, vector, metadata) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (id) DO UPDATE SET vector = excluded.vector, metadata = excluded.metadata",
+                )?;
+                for point in &points {
+                    let metadata = serde_json::Value::Object(metadata_to_json(&point.metadata));
+                    statement.execute(params![
+                        point.id,
+                        encode_vector(&point.vector),
+                        metadata.to_string()
+                    ])?;
+                }
+            }
+            transaction.commit()
+        })
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        options: &QueryOptions,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        check_dimensions(self.dimensions, &vector)?;
+        let points = self.with_connection(|connection| {
+            let mut statement =
+                connection.prepare_cached("SELECT id, vector, metadata FROM spin_vectors")?;
+            let points = statement
+                .query_map([], |row| Ok(point(row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(points)
+        })?;
+        Ok(exact_search(points, self.metric, &vector, options))
+    }
+
+    async fn get(&self, ids: Vec<String>) -> Result<Vec<Point>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection
+                .prepare_cached("SELECT id, vector, metadata FROM spin_vectors WHERE id = ?1")?;
+            let mut points = vec![];
+            for id in &ids {
+                let found = statement
+                    .query_row([id], |row| Ok(point(row.get(0)?, row.get(1)?, row.get(2)?)))
+                    .optional()?;
+                points.extend(found);
+            }
+            Ok(points)
+        })
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement =
+                    transaction.prepare_cached("DELETE FROM spin_vectors WHERE id = ?1")?;
+                for id in &ids {
+                    statement.execute([id])?;
+                }
+            }
+            transaction.commit()
+        })
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(match &self.location {
+            DatabaseLocation::InMemory => "a temporary in-memory store".into(),
+            DatabaseLocation::Path(path) => format!("\"{}\"", path.display()),
+        })
+    }
+}
+
+fn point(id: String, vector: Vec<u8>, metadata: String) -> Point {
+    let metadata = match serde_json::from_str(&metadata) {
+        Ok(serde_json::Value::Object(object)) => metadata_from_json(object),
+        _ => vec![],
+    };
+    Point {
+        id,
+        vector: decode_vector(&vector),
+        metadata,
+    }
+}
+
+fn to_store_error(err: rusqlite::Error) -> Error {
+    Error::Other(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_vector_store::Value;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn points_are_persisted() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let location = DatabaseLocation::Path(dir.path().join("vectors.db"));
+        let store = SqliteStore::new(location.clone(), Metric::Cosine, Some(2));
+        store
+            .upsert(vec![Point {
+                id: "a".into(),
+                vector: vec![1.0, 0.0],
+                metadata: vec![("lang".into(), Value::Text("en".into()))],
+            }])
+            .await?;
+
+        let reopened = SqliteStore::new(location, Metric::Cosine, Some(2));
+        let options = QueryOptions {
+            top_k: 5,
+            filters: vec![],
+            include_vectors: true,
+        };
+        let results = reopened.query(vec![1.0, 0.0], &options).await?;
+        assert_eq!(1, results.len());
+        assert_eq!("a", results[0].id);
+        assert_eq!(Some(vec![1.0, 0.0]), results[0].vector);
+        assert!(
+            matches!(&results[0].metadata[..], [(key, Value::Text(lang))] if key == "lang" && lang == "en")
+        );
+
+        reopened.delete(vec!["a".into()]).await?;
+        assert!(reopened.get(vec!["a".into()]).await?.is_empty());
+        Ok(())
+    }
+}
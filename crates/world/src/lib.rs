@@ -12,6 +12,15 @@ wasmtime::component::bindgen!({
         include fermyon:spin/platform@3.0.0;
         include spin:up/platform@3.2.0;
         include wasi:keyvalue/imports@0.2.0-draft2;
+        export spin:postgres/inbound-postgres@4.0.0;
+        export spin:redis/inbound-redis@3.0.0;
+        export spin:timers/handler@3.0.0;
+        export spin:workflow/handler@3.0.0;
+        export wasi:messaging/incoming-handler@0.2.0-draft;
+        export spin:background/task-handler@3.0.0;
+        export spin:trigger/handler@3.0.0;
+        export spin:lifecycle/startup@3.0.0;
+        export spin:lifecycle/shutdown@3.0.0;
     }
     "#,
     path: "../../wit",
@@ -31,13 +40,46 @@ wasmtime::component::bindgen!({
         "fermyon:spin/sqlite@2.0.0/error" => v2::sqlite::Error,
         "fermyon:spin/sqlite/error" => v1::sqlite::Error,
         "fermyon:spin/variables@2.0.0/error" => v2::variables::Error,
-        "spin:postgres/postgres/error" => spin::postgres::postgres::Error,
-        "spin:sqlite/sqlite/error" => spin::sqlite::sqlite::Error,
+        "spin:amqp/amqp/error" => spin::amqp::amqp::Error,
+        "spin:auth/jwt/error" => spin::auth::jwt::Error,
+        "spin:background/tasks/error" => spin::background::tasks::Error,
+        "spin:blobstore/presign/error" => spin::blobstore::presign::Error,
+        "spin:cache/cache/error" => spin::cache::cache::Error,
+        "spin:crypto/crypto/error" => spin::crypto::crypto::Error,
+        "spin:entities/entities/error" => spin::entities::entities::Error,
+        "spin:file-transfer/file-transfer/error" => spin::file_transfer::file_transfer::Error,
+        "spin:grpc/client/error" => spin::grpc::client::Error,
+        "spin:host-plugins/host-plugins/error" => spin::host_plugins::host_plugins::Error,
+        "spin:i18n/i18n/error" => spin::i18n::i18n::Error,
+        "spin:ids/ids/error" => spin::ids::ids::Error,
+        "spin:image/image/error" => spin::image::image::Error,
+        "spin:ldap/ldap/error" => spin::ldap::ldap::Error,
+        "spin:leader-election/leader-election/error" => spin::leader_election::leader_election::Error,
+        "spin:metrics/metrics/error" => spin::metrics::metrics::Error,
+        "spin:networking/allowed-hosts/error" => spin::networking::allowed_hosts::Error,
+        "spin:networking/dns/error" => spin::networking::dns::Error,
+        "spin:postgres/postgres@3.0.0/error" => spin::postgres3_0_0::postgres::Error,
+        "spin:rate-limit/rate-limit/error" => spin::rate_limit::rate_limit::Error,
+        "spin:session/session/error" => spin::session::session::Error,
+        "spin:smtp/smtp/error" => spin::smtp::smtp::Error,
+        "spin:sse/client/error" => spin::sse::client::Error,
+        "spin:sql/types/error" => spin::sql::types::Error,
+        "spin:sqlite/sqlite@3.0.0/error" => spin::sqlite3_0_0::sqlite::Error,
+        "spin:sqlite/sqlite@4.0.0/error" => spin::sqlite4_0_0::sqlite::Error,
+        "spin:templates/templates/error" => spin::templates::templates::Error,
+        "spin:timers/scheduler/error" => spin::timers::scheduler::Error,
+        "spin:vector-store/vector-store/error" => spin::vector_store::vector_store::Error,
+        "spin:websocket/websocket/error" => spin::websocket::websocket::Error,
+        "spin:workflow/workflow/error" => spin::workflow::workflow::Error,
         "wasi:config/store@0.2.0-draft-2024-09-27/error" => wasi::config::store::Error,
         "wasi:keyvalue/store/error" => wasi::keyvalue::store::Error,
         "wasi:keyvalue/atomics/cas-error" => wasi::keyvalue::atomics::CasError,
+        "wasi:messaging/types/error" => wasi::messaging::types::Error,
     },
     trappable_imports: true,
+    with: {
+        "wasi:io": wasmtime_wasi::p2::bindings::io,
+    },
 });
 
 pub use fermyon::spin as v1;
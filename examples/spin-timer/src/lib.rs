@@ -11,7 +11,7 @@ wasmtime::component::bindgen!({
     async: true
 });
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct CliArgs {
     /// If true, run each component once and exit
     #[clap(long)]
@@ -8,23 +8,34 @@ use spin_cli::commands::{
     cloud::{DeployCommand, LoginCommand},
     doctor::DoctorCommand,
     external::execute_external_subcommand,
+    kv::KvCommands,
+    logs::LogsCommand,
     new::{AddCommand, NewCommand},
     plugins::PluginCommands,
     registry::RegistryCommands,
+    replay::ReplayCommand,
+    serve::ServeCommand,
+    sqlite::SqliteCommands,
     templates::TemplateCommands,
+    test::TestCommand,
     up::UpCommand,
     watch::WatchCommand,
 };
 use spin_cli::{build_info::*, subprocess::ExitStatusError};
 use spin_runtime_factors::FactorsBuilder;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
-use spin_trigger::cli::FactorsTriggerCommand;
+use spin_trigger::cli::{ExecutorTuning, FactorsTriggerCommand};
+use spin_trigger_external::ExternalTrigger;
 use spin_trigger_http::HttpTrigger;
+use spin_trigger_messaging::MessagingTrigger;
+use spin_trigger_postgres::PostgresTrigger;
 use spin_trigger_redis::RedisTrigger;
+use spin_trigger_timer::TimerTrigger;
+use spin_trigger_workflow::WorkflowTrigger;
 
-#[tokio::main]
-async fn main() {
-    if let Err(err) = _main().await {
+fn main() {
+    let result = build_runtime().and_then(|runtime| runtime.block_on(_main()));
+    if let Err(err) = result {
         let code = match err.downcast_ref::<ExitStatusError>() {
             // If we encounter an `ExitStatusError` it means a subprocess has already
             // exited unsuccessfully and thus already printed error messages. No need
@@ -42,6 +53,18 @@ async fn main() {
     }
 }
 
+/// Builds the runtime Spin runs on. A trigger's process runs on a runtime
+/// tuned by the `[executor]` table of its runtime config.
+fn build_runtime() -> anyhow::Result<tokio::runtime::Runtime> {
+    // Any error parsing the arguments is reported once they are parsed for
+    // real, on the runtime.
+    let tuning = match SpinApp::try_parse() {
+        Ok(SpinApp::Trigger(cmd)) => cmd.executor_tuning()?,
+        _ => Default::default(),
+    };
+    tuning.build_runtime()
+}
+
 async fn _main() -> anyhow::Result<()> {
     spin_telemetry::init(VERSION.to_string()).context("Failed to initialize telemetry")?;
 
@@ -118,6 +141,7 @@ enum SpinApp {
     Add(AddCommand),
     #[clap(alias = "u")]
     Up(UpCommand),
+    Serve(ServeCommand),
     // acts as a cross-level subcommand shortcut -> `spin cloud deploy`
     #[clap(alias = "d")]
     Deploy(DeployCommand),
@@ -135,6 +159,13 @@ enum SpinApp {
     External(Vec<String>),
     #[clap(alias = "w")]
     Watch(WatchCommand),
+    Test(TestCommand),
+    Replay(ReplayCommand),
+    #[clap(subcommand)]
+    Kv(KvCommands),
+    #[clap(subcommand)]
+    Sqlite(SqliteCommands),
+    Logs(LogsCommand),
     Doctor(DoctorCommand),
     #[clap(subcommand, hide = true)]
     Maintenance(MaintenanceCommands),
@@ -144,16 +175,39 @@ enum SpinApp {
 enum TriggerCommands {
     Http(FactorsTriggerCommand<HttpTrigger, FactorsBuilder>),
     Redis(FactorsTriggerCommand<RedisTrigger, FactorsBuilder>),
+    Postgres(FactorsTriggerCommand<PostgresTrigger, FactorsBuilder>),
+    Timer(FactorsTriggerCommand<TimerTrigger, FactorsBuilder>),
+    Workflow(FactorsTriggerCommand<WorkflowTrigger, FactorsBuilder>),
+    Messaging(FactorsTriggerCommand<MessagingTrigger, FactorsBuilder>),
+    #[clap(hide = true)]
+    External(FactorsTriggerCommand<ExternalTrigger, FactorsBuilder>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(FactorsTriggerCommand<HelpArgsOnlyTrigger, FactorsBuilder>),
 }
 
+impl TriggerCommands {
+    /// The tuning of the runtime the trigger should run on.
+    fn executor_tuning(&self) -> anyhow::Result<ExecutorTuning> {
+        match self {
+            Self::Http(cmd) => cmd.executor_tuning(),
+            Self::Redis(cmd) => cmd.executor_tuning(),
+            Self::Postgres(cmd) => cmd.executor_tuning(),
+            Self::Timer(cmd) => cmd.executor_tuning(),
+            Self::Workflow(cmd) => cmd.executor_tuning(),
+            Self::Messaging(cmd) => cmd.executor_tuning(),
+            Self::External(cmd) => cmd.executor_tuning(),
+            Self::HelpArgsOnly(cmd) => cmd.executor_tuning(),
+        }
+    }
+}
+
 impl SpinApp {
     /// The main entry point to Spin.
     pub async fn run(self, app: clap::Command<'_>) -> Result<(), Error> {
         match self {
             Self::Templates(cmd) => cmd.run().await,
             Self::Up(cmd) => cmd.run().await,
+            Self::Serve(cmd) => cmd.run().await,
             Self::New(cmd) => cmd.run().await,
             Self::Add(cmd) => cmd.run().await,
             Self::Deploy(cmd) => cmd.run(SpinApp::command()).await,
@@ -162,10 +216,20 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Postgres(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Timer(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Workflow(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Messaging(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::External(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
             Self::Watch(cmd) => cmd.run().await,
+            Self::Test(cmd) => cmd.run().await,
+            Self::Replay(cmd) => cmd.run().await,
+            Self::Kv(cmd) => cmd.run().await,
+            Self::Sqlite(cmd) => cmd.run().await,
+            Self::Logs(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
             Self::Maintenance(cmd) => cmd.run(SpinApp::command()).await,
         }
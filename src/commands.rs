@@ -1,5 +1,7 @@
 //! Commands for the Spin CLI.
 
+/// Command for adding a service to an existing application.
+pub mod add_service;
 /// Commands for building Spin applications.
 pub mod build;
 /// Commands for publishing applications to the Fermyon Platform.
@@ -8,6 +10,10 @@ pub mod cloud;
 pub mod doctor;
 /// Commands for external subcommands (i.e. plugins)
 pub mod external;
+/// Commands for working with an application's key-value stores.
+pub mod kv;
+/// Command for showing the logs of an application's components.
+pub mod logs;
 /// Commands for Spin maintenance tasks.
 pub mod maintenance;
 /// Command for creating a new application.
@@ -16,8 +22,16 @@ pub mod new;
 pub mod plugins;
 /// Commands for working with OCI registries.
 pub mod registry;
+/// Command for replaying recorded invocations.
+pub mod replay;
+/// Command for serving multiple applications from a single process.
+pub mod serve;
+/// Commands for working with an application's SQLite databases.
+pub mod sqlite;
 /// Commands for working with templates.
 pub mod templates;
+/// Command for running an application's tests.
+pub mod test;
 /// Commands for starting the runtime.
 pub mod up;
 /// Command for rebuilding and restarting a Spin app when files change.
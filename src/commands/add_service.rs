@@ -0,0 +1,706 @@
+//! Adding a capability such as a key-value store or a Redis trigger to an
+//! existing application: the manifest is updated, the runtime config gets a
+//! matching entry, and example guest code is scaffolded next to the component.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use path_absolutize::Absolutize;
+use spin_common::ui::quoted_path;
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table};
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+const DEFAULT_LABEL: &str = "default";
+const DEFAULT_RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+const DEFAULT_REDIS_ADDRESS: &str = "redis://localhost:6379";
+
+/// Add a key-value store, SQLite database or Redis trigger to a component,
+/// updating the manifest and runtime config and scaffolding example code.
+#[derive(Parser, Debug)]
+pub struct AddServiceCommand {
+    /// The kind of service to add.
+    #[clap(value_enum)]
+    pub kind: ServiceKind,
+
+    /// The component to add the service to. May be omitted if the application
+    /// has only one component.
+    #[clap(short = 'c', long = "component")]
+    pub component: Option<String>,
+
+    /// The label of the store or database, or the channel to subscribe to
+    /// for a Redis trigger.
+    #[clap(short = 'l', long = "label")]
+    pub label: Option<String>,
+
+    /// The address of the Redis server, if the application doesn't already
+    /// set one.
+    #[clap(long = "redis-address", default_value = DEFAULT_REDIS_ADDRESS)]
+    pub redis_address: String,
+
+    /// The language of the example code. If omitted, it is detected from the
+    /// component's project files.
+    #[clap(long = "language", value_enum)]
+    pub language: Option<Language>,
+
+    /// Path to spin.toml.
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "file",
+    )]
+    pub app: Option<PathBuf>,
+
+    /// The runtime config file to add the store or database to. The default
+    /// is runtime-config.toml next to the manifest.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Don't scaffold example guest code.
+    #[clap(long = "no-example", takes_value = false)]
+    pub no_example: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceKind {
+    KeyValue,
+    Sqlite,
+    RedisTrigger,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    #[clap(alias = "ts", alias = "javascript", alias = "js")]
+    Typescript,
+    #[clap(alias = "py")]
+    Python,
+    #[clap(alias = "tinygo")]
+    Go,
+}
+
+impl AddServiceCommand {
+    pub async fn run(&self) -> Result<()> {
+        let app_file = self
+            .app
+            .as_deref()
+            .unwrap_or_else(|| DEFAULT_MANIFEST_FILE.as_ref());
+        let manifest_path = app_file
+            .absolutize()
+            .with_context(|| format!("Can't get absolute path for {}", quoted_path(app_file)))?
+            .into_owned();
+        let app_dir = manifest_path
+            .parent()
+            .context("manifest path has no parent directory")?
+            .to_owned();
+
+        let mut manifest = read_document(&manifest_path).await?.with_context(|| {
+            format!(
+                "Can't add service: {} does not exist",
+                quoted_path(&manifest_path)
+            )
+        })?;
+        let component = resolve_component(&manifest, self.component.as_deref())?;
+        let label = self.label.as_deref().unwrap_or(DEFAULT_LABEL);
+
+        let changed = match self.kind {
+            ServiceKind::KeyValue => {
+                add_component_label(&mut manifest, &component, "key_value_stores", label)?
+            }
+            ServiceKind::Sqlite => {
+                add_component_label(&mut manifest, &component, "sqlite_databases", label)?
+            }
+            ServiceKind::RedisTrigger => {
+                add_redis_trigger(&mut manifest, &component, label, &self.redis_address)?
+            }
+        };
+        if changed {
+            tokio::fs::write(&manifest_path, manifest.to_string())
+                .await
+                .with_context(|| format!("Failed to write {}", quoted_path(&manifest_path)))?;
+            terminal::step!("Updated", "{}", quoted_path(&manifest_path));
+        } else {
+            println!(
+                "Component `{component}` already uses {} `{label}`",
+                self.kind.description()
+            );
+        }
+
+        if let Some(table) = self.kind.runtime_config_table() {
+            // Spin provides default stores without any runtime config.
+            if label != DEFAULT_LABEL {
+                let runtime_config_path = self
+                    .runtime_config_file
+                    .clone()
+                    .unwrap_or_else(|| app_dir.join(DEFAULT_RUNTIME_CONFIG_FILE));
+                let mut runtime_config = read_document(&runtime_config_path)
+                    .await?
+                    .unwrap_or_default();
+                if add_runtime_config(&mut runtime_config, table, label)? {
+                    tokio::fs::write(&runtime_config_path, runtime_config.to_string())
+                        .await
+                        .with_context(|| {
+                            format!("Failed to write {}", quoted_path(&runtime_config_path))
+                        })?;
+                    terminal::step!("Updated", "{}", quoted_path(&runtime_config_path));
+                    println!(
+                        "Run the application with `spin up --runtime-config-file {}`",
+                        runtime_config_path.display()
+                    );
+                }
+            }
+        }
+
+        if !self.no_example {
+            let component_dir = component_dir(&manifest, &component, &app_dir);
+            let language = match self.language.or_else(|| Language::detect(&component_dir)) {
+                Some(language) => language,
+                None => {
+                    println!("Couldn't detect the component's language, so no example code was generated. Use `--language` to choose one.");
+                    return Ok(());
+                }
+            };
+            // Not `examples`, which Cargo would try to build as example programs.
+            let example_path = component_dir.join("snippets").join(format!(
+                "{}.{}",
+                self.kind.file_stem(),
+                language.extension()
+            ));
+            if example_path.exists() {
+                println!(
+                    "Not generating example code: {} already exists",
+                    quoted_path(&example_path)
+                );
+            } else {
+                tokio::fs::create_dir_all(example_path.parent().unwrap()).await?;
+                tokio::fs::write(&example_path, example(self.kind, language, label))
+                    .await
+                    .with_context(|| format!("Failed to write {}", quoted_path(&example_path)))?;
+                terminal::step!("Created", "example code in {}", quoted_path(&example_path));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ServiceKind {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::KeyValue => "key-value store",
+            Self::Sqlite => "SQLite database",
+            Self::RedisTrigger => "Redis channel",
+        }
+    }
+
+    /// The runtime config table in which stores of this kind are defined.
+    fn runtime_config_table(&self) -> Option<&'static str> {
+        match self {
+            Self::KeyValue => Some("key_value_store"),
+            Self::Sqlite => Some("sqlite_database"),
+            Self::RedisTrigger => None,
+        }
+    }
+
+    fn file_stem(&self) -> &'static str {
+        match self {
+            Self::KeyValue => "key_value",
+            Self::Sqlite => "sqlite",
+            Self::RedisTrigger => "redis_trigger",
+        }
+    }
+}
+
+impl Language {
+    /// Guesses the language of the component built in `dir`.
+    fn detect(dir: &Path) -> Option<Self> {
+        [
+            ("Cargo.toml", Self::Rust),
+            ("package.json", Self::Typescript),
+            ("pyproject.toml", Self::Python),
+            ("requirements.txt", Self::Python),
+            ("go.mod", Self::Go),
+        ]
+        .into_iter()
+        .find(|(file, _)| dir.join(file).exists())
+        .map(|(_, language)| language)
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Rust => "rs",
+            Self::Typescript => "ts",
+            Self::Python => "py",
+            Self::Go => "go",
+        }
+    }
+}
+
+/// Reads a TOML document, or `None` if the file doesn't exist.
+async fn read_document(path: &Path) -> Result<Option<DocumentMut>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+    let document = text
+        .parse()
+        .with_context(|| format!("{} is not valid TOML", quoted_path(path)))?;
+    Ok(Some(document))
+}
+
+/// Resolves the component to add a service to, defaulting to the only
+/// component if there is just one.
+fn resolve_component(manifest: &DocumentMut, requested: Option<&str>) -> Result<String> {
+    let ids: Vec<&str> = manifest
+        .get("component")
+        .and_then(Item::as_table_like)
+        .map(|components| components.iter().map(|(id, _)| id).collect())
+        .unwrap_or_default();
+    match requested {
+        Some(id) if ids.contains(&id) => Ok(id.to_owned()),
+        Some(id) => bail!(
+            "The application has no component `{id}`. Components are: {}",
+            ids.join(", ")
+        ),
+        None => match ids.as_slice() {
+            [id] => Ok(id.to_string()),
+            [] => bail!("The application has no components. Add one with `spin add` first."),
+            _ => bail!(
+                "The application has several components: use `--component` to choose one of {}",
+                ids.join(", ")
+            ),
+        },
+    }
+}
+
+fn component_table<'a>(
+    manifest: &'a mut DocumentMut,
+    component: &str,
+) -> Result<&'a mut dyn toml_edit::TableLike> {
+    manifest
+        .get_mut("component")
+        .and_then(|components| components.get_mut(component))
+        .and_then(Item::as_table_like_mut)
+        .with_context(|| format!("Component `{component}` is not a table"))
+}
+
+/// Adds `label` to the list named `key` in a component's table, returning
+/// whether it was added.
+fn add_component_label(
+    manifest: &mut DocumentMut,
+    component: &str,
+    key: &str,
+    label: &str,
+) -> Result<bool> {
+    let table = component_table(manifest, component)?;
+    let labels = table
+        .entry(key)
+        .or_insert_with(|| value(Array::new()))
+        .as_array_mut()
+        .with_context(|| format!("`{key}` of component `{component}` is not an array"))?;
+    if labels
+        .iter()
+        .any(|existing| existing.as_str() == Some(label))
+    {
+        return Ok(false);
+    }
+    labels.push(label);
+    Ok(true)
+}
+
+/// Subscribes a component to a Redis channel, setting the application's
+/// Redis address if it has none. Returns whether the manifest changed.
+fn add_redis_trigger(
+    manifest: &mut DocumentMut,
+    component: &str,
+    channel: &str,
+    address: &str,
+) -> Result<bool> {
+    let application = manifest
+        .get_mut("application")
+        .and_then(Item::as_table_mut)
+        .context("The manifest has no [application] table")?;
+    let redis_config = implicit_table(application, "trigger")?
+        .entry("redis")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .context("`application.trigger.redis` is not a table")?;
+    let mut changed = false;
+    if !redis_config.contains_key("address") {
+        redis_config.insert("address", value(address));
+        changed = true;
+    }
+
+    let triggers = implicit_table(manifest.as_table_mut(), "trigger")?
+        .entry("redis")
+        .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .context("`trigger.redis` is not an array of tables")?;
+    let exists = triggers.iter().any(|trigger| {
+        trigger.get("component").and_then(Item::as_str) == Some(component)
+            && trigger.get("channel").and_then(Item::as_str) == Some(channel)
+    });
+    if !exists {
+        let mut trigger = Table::new();
+        trigger.insert("channel", value(channel));
+        trigger.insert("component", value(component));
+        triggers.push(trigger);
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Gets a child table, creating it as implicit (i.e. without its own header)
+/// if it doesn't exist.
+fn implicit_table<'a>(parent: &'a mut Table, key: &str) -> Result<&'a mut Table> {
+    parent
+        .entry(key)
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut()
+        .with_context(|| format!("`{key}` is not a table"))
+}
+
+/// Defines a store backed by a local file in the runtime config, returning
+/// whether it was added.
+fn add_runtime_config(runtime_config: &mut DocumentMut, table: &str, label: &str) -> Result<bool> {
+    let stores = implicit_table(runtime_config.as_table_mut(), table)?;
+    if stores.contains_key(label) {
+        return Ok(false);
+    }
+    let mut store = Table::new();
+    store.insert("type", value("spin"));
+    store.insert("path", value(format!(".spin/{label}.db")));
+    stores.insert(label, Item::Table(store));
+    Ok(true)
+}
+
+/// The directory the component is built in, where its project files are.
+fn component_dir(manifest: &DocumentMut, component: &str, app_dir: &Path) -> PathBuf {
+    let workdir = manifest
+        .get("component")
+        .and_then(|components| components.get(component))
+        .and_then(|component| component.get("build"))
+        .and_then(|build| build.get("workdir"))
+        .and_then(Item::as_str);
+    match workdir {
+        Some(workdir) => app_dir.join(workdir),
+        None => app_dir.to_owned(),
+    }
+}
+
+fn example(kind: ServiceKind, language: Language, label: &str) -> String {
+    let template = match (kind, language) {
+        (ServiceKind::KeyValue, Language::Rust) => RUST_KEY_VALUE,
+        (ServiceKind::KeyValue, Language::Typescript) => TYPESCRIPT_KEY_VALUE,
+        (ServiceKind::KeyValue, Language::Python) => PYTHON_KEY_VALUE,
+        (ServiceKind::KeyValue, Language::Go) => GO_KEY_VALUE,
+        (ServiceKind::Sqlite, Language::Rust) => RUST_SQLITE,
+        (ServiceKind::Sqlite, Language::Typescript) => TYPESCRIPT_SQLITE,
+        (ServiceKind::Sqlite, Language::Python) => PYTHON_SQLITE,
+        (ServiceKind::Sqlite, Language::Go) => GO_SQLITE,
+        (ServiceKind::RedisTrigger, Language::Rust) => RUST_REDIS_TRIGGER,
+        (ServiceKind::RedisTrigger, Language::Typescript) => TYPESCRIPT_REDIS_TRIGGER,
+        (ServiceKind::RedisTrigger, Language::Python) => PYTHON_REDIS_TRIGGER,
+        (ServiceKind::RedisTrigger, Language::Go) => GO_REDIS_TRIGGER,
+    };
+    template.replace("{{label}}", label)
+}
+
+const RUST_KEY_VALUE: &str = r#"use spin_sdk::key_value::Store;
+
+/// Increments a counter in the `{{label}}` key-value store.
+pub fn increment_counter() -> anyhow::Result<u64> {
+    let store = Store::open("{{label}}")?;
+    let count = match store.get("count")? {
+        Some(bytes) => String::from_utf8(bytes)?.parse::<u64>()? + 1,
+        None => 1,
+    };
+    store.set("count", count.to_string().as_bytes())?;
+    Ok(count)
+}
+"#;
+
+const TYPESCRIPT_KEY_VALUE: &str = r#"import { Kv } from "@fermyon/spin-sdk";
+
+// Increments a counter in the `{{label}}` key-value store.
+export function incrementCounter(): number {
+  const store = Kv.open("{{label}}");
+  const current = store.get("count");
+  const count = current ? parseInt(new TextDecoder().decode(current)) + 1 : 1;
+  store.set("count", count.toString());
+  return count;
+}
+"#;
+
+const PYTHON_KEY_VALUE: &str = r#"from spin_sdk import key_value
+
+
+def increment_counter() -> int:
+    """Increments a counter in the `{{label}}` key-value store."""
+    with key_value.open("{{label}}") as store:
+        current = store.get("count")
+        count = int(current.decode()) + 1 if current else 1
+        store.set("count", str(count).encode())
+        return count
+"#;
+
+const GO_KEY_VALUE: &str = r#"package main
+
+import (
+	"strconv"
+
+	"github.com/fermyon/spin/sdk/go/v2/kv"
+)
+
+// incrementCounter increments a counter in the `{{label}}` key-value store.
+func incrementCounter() (int, error) {
+	store, err := kv.OpenStore("{{label}}")
+	if err != nil {
+		return 0, err
+	}
+	defer store.Close()
+
+	count := 1
+	if exists, err := store.Exists("count"); err != nil {
+		return 0, err
+	} else if exists {
+		current, err := store.Get("count")
+		if err != nil {
+			return 0, err
+		}
+		if count, err = strconv.Atoi(string(current)); err != nil {
+			return 0, err
+		}
+		count++
+	}
+	return count, store.Set("count", []byte(strconv.Itoa(count)))
+}
+"#;
+
+const RUST_SQLITE: &str = r#"use spin_sdk::sqlite::{Connection, Value};
+
+/// Adds a todo to the `{{label}}` database and lists them all.
+pub fn add_todo(description: &str) -> anyhow::Result<Vec<String>> {
+    let connection = Connection::open("{{label}}")?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS todos (id INTEGER PRIMARY KEY, description TEXT NOT NULL)",
+        &[],
+    )?;
+    connection.execute(
+        "INSERT INTO todos (description) VALUES (?)",
+        &[Value::Text(description.to_owned())],
+    )?;
+    let result = connection.execute("SELECT description FROM todos", &[])?;
+    Ok(result
+        .rows()
+        .filter_map(|row| row.get::<&str>("description").map(str::to_owned))
+        .collect())
+}
+"#;
+
+const TYPESCRIPT_SQLITE: &str = r#"import { Sqlite } from "@fermyon/spin-sdk";
+
+// Adds a todo to the `{{label}}` database and lists them all.
+export function addTodo(description: string): string[] {
+  const db = Sqlite.open("{{label}}");
+  db.execute(
+    "CREATE TABLE IF NOT EXISTS todos (id INTEGER PRIMARY KEY, description TEXT NOT NULL)",
+    [],
+  );
+  db.execute("INSERT INTO todos (description) VALUES (?)", [description]);
+  const result = db.execute("SELECT description FROM todos", []);
+  return result.rows.map((row) => row["description"] as string);
+}
+"#;
+
+const PYTHON_SQLITE: &str = r#"from spin_sdk import sqlite
+from spin_sdk.sqlite import Value_Text
+
+
+def add_todo(description: str) -> list[str]:
+    """Adds a todo to the `{{label}}` database and lists them all."""
+    with sqlite.open("{{label}}") as db:
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS todos (id INTEGER PRIMARY KEY, description TEXT NOT NULL)",
+            [],
+        )
+        db.execute("INSERT INTO todos (description) VALUES (?)", [Value_Text(description)])
+        result = db.execute("SELECT description FROM todos", [])
+        return [row.values[0].value for row in result.rows]
+"#;
+
+const GO_SQLITE: &str = r#"package main
+
+import (
+	"github.com/fermyon/spin/sdk/go/v2/sqlite"
+)
+
+// addTodo adds a todo to the `{{label}}` database and lists them all.
+func addTodo(description string) ([]string, error) {
+	db := sqlite.Open("{{label}}")
+	defer db.Close()
+
+	if _, err := db.Exec("CREATE TABLE IF NOT EXISTS todos (id INTEGER PRIMARY KEY, description TEXT NOT NULL)"); err != nil {
+		return nil, err
+	}
+	if _, err := db.Exec("INSERT INTO todos (description) VALUES (?)", description); err != nil {
+		return nil, err
+	}
+	rows, err := db.Query("SELECT description FROM todos")
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+
+	var todos []string
+	for rows.Next() {
+		var todo string
+		if err := rows.Scan(&todo); err != nil {
+			return nil, err
+		}
+		todos = append(todos, todo)
+	}
+	return todos, rows.Err()
+}
+"#;
+
+const RUST_REDIS_TRIGGER: &str = r#"use bytes::Bytes;
+use spin_sdk::redis_component;
+
+/// Handles messages published to the `{{label}}` channel.
+#[redis_component]
+fn on_message(message: Bytes) -> anyhow::Result<()> {
+    println!("{}", String::from_utf8_lossy(&message));
+    Ok(())
+}
+"#;
+
+const TYPESCRIPT_REDIS_TRIGGER: &str = r#"// Handles messages published to the `{{label}}` channel.
+export const inboundRedis = {
+  async handleMessage(message: Uint8Array) {
+    console.log(new TextDecoder().decode(message));
+  },
+};
+"#;
+
+const PYTHON_REDIS_TRIGGER: &str = r#"from spin_sdk.wit import exports
+
+
+class InboundRedis(exports.InboundRedis):
+    """Handles messages published to the `{{label}}` channel."""
+
+    def handle_message(self, message: bytes):
+        print(message.decode())
+"#;
+
+const GO_REDIS_TRIGGER: &str = r#"package main
+
+import (
+	"fmt"
+
+	"github.com/fermyon/spin/sdk/go/v2/redis"
+)
+
+// Handles messages published to the `{{label}}` channel.
+func init() {
+	redis.Handle(func(payload []byte) error {
+		fmt.Println(string(payload))
+		return nil
+	})
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"spin_manifest_version = 2
+
+[application]
+name = "test"
+
+[[trigger.http]]
+route = "/..."
+component = "web"
+
+# The web frontend
+[component.web]
+source = "web.wasm"
+key_value_stores = ["default"]
+"#;
+
+    fn manifest() -> DocumentMut {
+        MANIFEST.parse().unwrap()
+    }
+
+    #[test]
+    fn adds_stores_to_components() {
+        let mut manifest = manifest();
+        assert!(add_component_label(&mut manifest, "web", "key_value_stores", "cache").unwrap());
+        assert!(!add_component_label(&mut manifest, "web", "key_value_stores", "cache").unwrap());
+        assert!(add_component_label(&mut manifest, "web", "sqlite_databases", "default").unwrap());
+
+        let text = manifest.to_string();
+        assert!(text.contains("# The web frontend"));
+        assert!(text.contains(r#"key_value_stores = ["default", "cache"]"#));
+        assert!(text.contains(r#"sqlite_databases = ["default"]"#));
+    }
+
+    #[test]
+    fn adds_redis_triggers() {
+        let mut manifest = manifest();
+        assert!(
+            add_redis_trigger(&mut manifest, "web", "messages", DEFAULT_REDIS_ADDRESS).unwrap()
+        );
+        assert!(!add_redis_trigger(&mut manifest, "web", "messages", "redis://other").unwrap());
+
+        let parsed: toml::Table = toml::from_str(&manifest.to_string()).unwrap();
+        assert_eq!(
+            parsed["application"]["trigger"]["redis"]["address"].as_str(),
+            Some(DEFAULT_REDIS_ADDRESS)
+        );
+        let triggers = parsed["trigger"]["redis"].as_array().unwrap();
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0]["channel"].as_str(), Some("messages"));
+        assert_eq!(triggers[0]["component"].as_str(), Some("web"));
+        // Existing triggers are kept
+        assert_eq!(parsed["trigger"]["http"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn adds_runtime_config() {
+        let mut runtime_config = DocumentMut::new();
+        assert!(add_runtime_config(&mut runtime_config, "key_value_store", "cache").unwrap());
+        assert!(!add_runtime_config(&mut runtime_config, "key_value_store", "cache").unwrap());
+
+        let parsed: toml::Table = toml::from_str(&runtime_config.to_string()).unwrap();
+        let store = &parsed["key_value_store"]["cache"];
+        assert_eq!(store["type"].as_str(), Some("spin"));
+        assert_eq!(store["path"].as_str(), Some(".spin/cache.db"));
+    }
+
+    #[test]
+    fn resolves_components() {
+        let mut manifest = manifest();
+        assert_eq!(resolve_component(&manifest, None).unwrap(), "web");
+        assert!(resolve_component(&manifest, Some("api")).is_err());
+
+        manifest["component"]["api"] = Item::Table(Table::new());
+        assert!(resolve_component(&manifest, None).is_err());
+        assert_eq!(resolve_component(&manifest, Some("api")).unwrap(), "api");
+    }
+
+    #[test]
+    fn examples_use_the_label() {
+        let example = example(ServiceKind::Sqlite, Language::Go, "todos");
+        assert!(example.contains(r#"sqlite.Open("todos")"#));
+        assert!(!example.contains("{{label}}"));
+    }
+}
@@ -1,15 +1,41 @@
-use std::{ffi::OsString, path::PathBuf};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
 use clap::Parser;
+use path_absolutize::Absolutize;
+use spin_build::{
+    cache::{BuildCache, RemoteCache},
+    BuildOptions,
+};
+use spin_common::ui::quoted_path;
+use spin_loader::{
+    lockfile::{AppLockfile, LOCKFILE_NAME},
+    FilesMountStrategy,
+};
+use spin_oci::sbom::AppInventory;
+use spin_trigger::{cli::SPIN_AOT_CACHE_DIR, loader::AotCache};
 
 use crate::{
+    build_info::SPIN_VERSION,
     directory_rels::notify_if_nondefault_rel,
     opts::{APP_MANIFEST_FILE_OPT, BUILD_UP_OPT},
 };
 
 use super::up::UpCommand;
 
+/// The bill of materials written by `spin build --sbom`.
+const SBOM_FILE: &str = "spin-sbom.cdx.json";
+
+/// The component provenance written by `spin build --sbom`.
+const PROVENANCE_FILE: &str = "spin-provenance.intoto.json";
+
+/// The default build cache directory, relative to the manifest.
+const DEFAULT_BUILD_CACHE_DIR: &str = ".spin/build-cache";
+
 /// Run the build command for each component.
 #[derive(Parser, Debug)]
 #[clap(about = "Build the Spin application", allow_hyphen_values = true)]
@@ -29,6 +55,62 @@ pub struct BuildCommand {
     #[clap(short = 'c', long, multiple = true)]
     pub component_id: Vec<String>,
 
+    /// The most components to build at once. Components are built after any
+    /// components named in their `build.depends_on`. When building several
+    /// components at once, each line of output is prefixed with its
+    /// component ID.
+    #[clap(short = 'j', long = "jobs", default_value = "1")]
+    pub jobs: usize,
+
+    /// Check that the built application matches its lockfile, instead of
+    /// updating the lockfile.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
+    /// Compile the application's components ahead of time into the AOT cache
+    /// directory, so that hosts using the same directory (with
+    /// `spin up --aot-cache-dir`) skip compiling them at startup.
+    #[clap(long, takes_value = false, requires = "aot-cache-dir")]
+    pub precompile: bool,
+
+    /// The AOT cache directory to compile components into.
+    #[clap(long = "aot-cache-dir", env = SPIN_AOT_CACHE_DIR)]
+    pub aot_cache_dir: Option<PathBuf>,
+
+    /// Write a CycloneDX bill of materials (spin-sbom.cdx.json) and SLSA
+    /// provenance for the components (spin-provenance.intoto.json) next to
+    /// the manifest.
+    #[clap(long, takes_value = false)]
+    pub sbom: bool,
+
+    /// Always run build commands, rather than restoring components whose
+    /// watched source files are unchanged from the build cache.
+    #[clap(long = "no-cache", takes_value = false)]
+    pub no_cache: bool,
+
+    /// The directory in which to cache built components. The default is
+    /// .spin/build-cache next to the manifest.
+    #[clap(
+        long = "cache-dir",
+        env = "SPIN_BUILD_CACHE_DIR",
+        conflicts_with = "no-cache"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    /// A cache shared with other machines, from which to restore components
+    /// not in the local cache: an http(s) URL, or s3://bucket/prefix. A
+    /// bearer token for an HTTP cache may be set in SPIN_BUILD_CACHE_TOKEN.
+    #[clap(
+        long = "remote-cache",
+        env = "SPIN_BUILD_REMOTE_CACHE",
+        conflicts_with = "no-cache"
+    )]
+    pub remote_cache: Option<RemoteCache>,
+
+    /// Upload components built locally to the remote cache.
+    #[clap(long = "push-cache", takes_value = false, requires = "remote-cache")]
+    pub push_cache: bool,
+
     /// Run the application after building.
     #[clap(name = BUILD_UP_OPT, short = 'u', long = "up")]
     pub up: bool,
@@ -43,7 +125,23 @@ impl BuildCommand {
             spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
         notify_if_nondefault_rel(&manifest_file, distance);
 
-        spin_build::build(&manifest_file, &self.component_id).await?;
+        let started_on = Utc::now();
+        let options = BuildOptions {
+            cache: self.build_cache(&manifest_file)?,
+            jobs: self.jobs,
+        };
+        spin_build::build_with_options(&manifest_file, &self.component_id, &options).await?;
+        update_lockfile(&manifest_file, self.locked)?;
+
+        if self.sbom {
+            write_sbom(&manifest_file, started_on).await?;
+        }
+
+        if self.precompile {
+            if let Some(aot_cache_dir) = &self.aot_cache_dir {
+                precompile(&manifest_file, aot_cache_dir).await?;
+            }
+        }
 
         if self.up {
             let mut cmd = UpCommand::parse_from(
@@ -60,3 +158,125 @@ impl BuildCommand {
         }
     }
 }
+
+impl BuildCommand {
+    fn build_cache(&self, manifest_file: &Path) -> Result<Option<BuildCache>> {
+        if self.no_cache {
+            return Ok(None);
+        }
+        let dir = match &self.cache_dir {
+            Some(dir) => dir.clone(),
+            None => spin_common::paths::parent_dir(manifest_file)?.join(DEFAULT_BUILD_CACHE_DIR),
+        };
+        let cache = BuildCache::new(dir);
+        Ok(Some(match &self.remote_cache {
+            Some(remote) => cache.with_remote(remote.clone(), self.push_cache),
+            None => cache,
+        }))
+    }
+}
+
+async fn precompile(manifest_file: &Path, aot_cache_dir: &Path) -> Result<()> {
+    let working_dir = tempfile::tempdir()?;
+    let app = spin_loader::from_file(
+        manifest_file,
+        FilesMountStrategy::Copy(working_dir.path().join("assets")),
+        None,
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to load manifest from {}",
+            quoted_path(manifest_file)
+        )
+    })?;
+    terminal::step!(
+        "Precompiling",
+        "components into {}",
+        quoted_path(aot_cache_dir)
+    );
+    spin_trigger::loader::precompile_app(&app, &AotCache::new(aot_cache_dir)).await
+}
+
+/// Writes the bill of materials and component provenance of a built
+/// application next to its manifest.
+async fn write_sbom(manifest_file: &Path, started_on: DateTime<Utc>) -> Result<()> {
+    let inventory = app_inventory(manifest_file).await?;
+    let app_dir = spin_common::paths::parent_dir(manifest_file)?;
+    let provenance =
+        inventory.provenance(&inventory.component_subjects(), SPIN_VERSION, started_on);
+    for (file, content) in [
+        (SBOM_FILE, inventory.cyclonedx(SPIN_VERSION)),
+        (PROVENANCE_FILE, provenance),
+    ] {
+        let path = app_dir.join(file);
+        std::fs::write(&path, serde_json::to_vec_pretty(&content)?)
+            .with_context(|| format!("Failed to write {}", quoted_path(&path)))?;
+    }
+    terminal::step!(
+        "Wrote",
+        "bill of materials to {SBOM_FILE} and provenance to {PROVENANCE_FILE}"
+    );
+    Ok(())
+}
+
+/// Inventories the content of the application, for its bill of materials.
+pub(crate) async fn app_inventory(manifest_file: &Path) -> Result<AppInventory> {
+    let working_dir = tempfile::tempdir()?;
+    let app = spin_loader::from_file(
+        manifest_file,
+        FilesMountStrategy::Copy(working_dir.path().join("assets")),
+        None,
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to load manifest from {}",
+            quoted_path(manifest_file)
+        )
+    })?;
+    let app_dir = spin_common::paths::parent_dir(manifest_file)?;
+    AppInventory::from_locked_app(&app, &app_dir.absolutize()?)
+        .context("Failed to inventory the application for its bill of materials")
+}
+
+/// Records the built application's component sources in its lockfile. If
+/// `locked` is set, the lockfile must already exist and match instead.
+pub(crate) fn update_lockfile(manifest_file: &Path, locked: bool) -> Result<()> {
+    if locked {
+        return check_lockfile(manifest_file, true);
+    }
+    let resolved = AppLockfile::resolve(manifest_file)?;
+    let mut lockfile = AppLockfile::load(manifest_file)?.unwrap_or_default();
+    lockfile.update_components(resolved);
+    lockfile.save(manifest_file)
+}
+
+/// Checks the application against its lockfile. Differences are errors if
+/// `locked` is set, and warnings otherwise. An application without a
+/// lockfile is only an error if `locked` is set.
+pub(crate) fn check_lockfile(manifest_file: &Path, locked: bool) -> Result<()> {
+    let Some(lockfile) = AppLockfile::load(manifest_file)? else {
+        if locked {
+            bail!(
+                "No {LOCKFILE_NAME} found for {}. Run `spin build` without `--locked` to create one.",
+                quoted_path(manifest_file)
+            );
+        }
+        return Ok(());
+    };
+    let mismatches = lockfile.verify(&AppLockfile::resolve(manifest_file)?);
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let details = mismatches
+        .iter()
+        .map(|m| format!("  - {m}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if locked {
+        bail!("The application does not match {LOCKFILE_NAME}:\n{details}\nRun `spin build` without `--locked` to update it.");
+    }
+    terminal::warn!("The application does not match {LOCKFILE_NAME}:\n{details}");
+    Ok(())
+}
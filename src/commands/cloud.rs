@@ -1,10 +1,15 @@
+mod k8s;
+
 use crate::commands::external::execute_external_subcommand;
-use anyhow::Result;
-use clap::Args;
+use anyhow::{bail, Result};
+use clap::{Args, Parser};
+
+/// The option selecting where `spin deploy` deploys to.
+const TARGET_OPT: &str = "--target";
 
 #[derive(Debug, Args, PartialEq)]
 #[clap(
-    about = "Package and upload an application to the Fermyon Cloud.",
+    about = "Package and upload an application to the Fermyon Cloud, or render Kubernetes manifests with `--target k8s`.",
     allow_hyphen_values = true,
     disable_help_flag = true
 )]
@@ -28,12 +33,43 @@ pub struct LoginCommand {
 
 impl DeployCommand {
     pub async fn run(self, app: clap::App<'_>) -> Result<()> {
-        let mut cmd = vec!["cloud".to_string(), "deploy".to_string()];
-        cmd.append(&mut self.args.clone());
-        execute_external_subcommand(cmd, app).await
+        let (target, args) = split_target(self.args);
+        match target.as_deref() {
+            None | Some("cloud") => {
+                let mut cmd = vec!["cloud".to_string(), "deploy".to_string()];
+                cmd.extend(args);
+                execute_external_subcommand(cmd, app).await
+            }
+            Some("k8s" | "kubernetes") => {
+                let cmd =
+                    k8s::K8sDeploy::parse_from(std::iter::once("deploy".to_owned()).chain(args));
+                cmd.run().await
+            }
+            Some(target) => {
+                bail!("Unknown deploy target '{target}'. Supported targets are 'cloud' and 'k8s'.")
+            }
+        }
     }
 }
 
+/// Removes the `--target` option, if any, from the deploy args, returning
+/// its value and the remaining args.
+fn split_target(args: Vec<String>) -> (Option<String>, Vec<String>) {
+    let mut target = None;
+    let mut rest = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == TARGET_OPT {
+            target = args.next();
+        } else if let Some(value) = arg.strip_prefix(&format!("{TARGET_OPT}=")) {
+            target = Some(value.to_owned());
+        } else {
+            rest.push(arg);
+        }
+    }
+    (target, rest)
+}
+
 impl LoginCommand {
     pub async fn run(self, app: clap::App<'_>) -> Result<()> {
         let mut cmd = vec!["cloud".to_string(), "login".to_string()];
@@ -41,3 +77,26 @@ impl LoginCommand {
         execute_external_subcommand(cmd, app).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn target_is_split_from_args() {
+        let (target, rest) = split_target(args(&["--target", "k8s", "--image", "app:1"]));
+        assert_eq!(target.as_deref(), Some("k8s"));
+        assert_eq!(rest, args(&["--image", "app:1"]));
+
+        let (target, rest) = split_target(args(&["-f", "app", "--target=cloud"]));
+        assert_eq!(target.as_deref(), Some("cloud"));
+        assert_eq!(rest, args(&["-f", "app"]));
+
+        let (target, _) = split_target(args(&["--readiness-timeout", "60"]));
+        assert_eq!(target, None);
+    }
+}
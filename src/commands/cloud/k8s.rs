@@ -0,0 +1,316 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use serde_json::{json, Value};
+use spin_common::{arg_parser::parse_kv, ui::quoted_path};
+use spin_manifest::schema::v2::AppManifest;
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+/// The containerd runtime class provided by SpinKube for Spin apps.
+const RUNTIME_CLASS_NAME: &str = "wasmtime-spin-v2";
+
+/// Where containerd-shim-spin looks for the runtime config file.
+const SHIM_RUNTIME_CONFIG_PATH: &str = "/runtime-config.toml";
+
+/// The key under which the runtime config is stored in its secret.
+const RUNTIME_CONFIG_SECRET_KEY: &str = "runtime-config.toml";
+
+/// The port containerd-shim-spin serves HTTP triggers on.
+const SHIM_HTTP_PORT: u16 = 80;
+
+/// Render Kubernetes manifests for running the application on SpinKube.
+#[derive(Parser, Debug)]
+#[clap(name = "spin deploy --target k8s")]
+pub struct K8sDeploy {
+    /// The application to deploy. This may be a manifest (spin.toml) file, or
+    /// a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// The registry reference the application was pushed to, e.g. with
+    /// `spin registry push`.
+    #[clap(long = "image")]
+    pub image: String,
+
+    /// The kind of workload to render.
+    #[clap(value_enum, long = "format", default_value = "spin-app")]
+    pub format: K8sFormat,
+
+    /// The name of the Kubernetes resources. Defaults to the application name.
+    #[clap(long = "name")]
+    pub name: Option<String>,
+
+    /// The namespace of the Kubernetes resources.
+    #[clap(long = "namespace")]
+    pub namespace: Option<String>,
+
+    /// The number of replicas to run.
+    #[clap(long = "replicas", default_value_t = 2)]
+    pub replicas: u32,
+
+    /// The SpinKube executor to run the application with.
+    #[clap(long = "executor", default_value = "containerd-shim-spin")]
+    pub executor: String,
+
+    /// A runtime config file to provide to the application as a secret.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set an application variable (key=value). Can be used multiple times.
+    #[clap(long = "variable", parse(try_from_str = parse_kv))]
+    pub variables: Vec<(String, String)>,
+
+    /// Write the manifests to this file instead of stdout.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+/// The kind of workload rendered for the application.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum K8sFormat {
+    /// A SpinApp resource, for clusters running the Spin Operator.
+    SpinApp,
+    /// A plain Deployment and Service using the Spin runtime class.
+    Deployment,
+}
+
+impl K8sDeploy {
+    pub async fn run(self) -> Result<()> {
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+        let manifest = spin_manifest::manifest_from_file(&manifest_file).with_context(|| {
+            format!(
+                "Failed to read Spin app manifest from {}",
+                quoted_path(&manifest_file)
+            )
+        })?;
+        let runtime_config = self
+            .runtime_config_file
+            .as_deref()
+            .map(read_runtime_config)
+            .transpose()?;
+
+        let resources = self.render(&manifest, runtime_config);
+        let list = json!({ "apiVersion": "v1", "kind": "List", "items": resources });
+        // JSON is valid YAML, so this can be applied with `kubectl apply -f`.
+        let contents = serde_json::to_string_pretty(&list)?;
+        match &self.output {
+            Some(path) => std::fs::write(path, contents)
+                .with_context(|| format!("Failed to write {}", quoted_path(path)))?,
+            None => println!("{contents}"),
+        }
+        Ok(())
+    }
+
+    fn render(&self, manifest: &AppManifest, runtime_config: Option<String>) -> Vec<Value> {
+        let name = self
+            .name
+            .clone()
+            .unwrap_or_else(|| resource_name(&manifest.application.name));
+        let secret_name = format!("{name}-runtime-config");
+
+        let mut resources = vec![];
+        if let Some(runtime_config) = runtime_config {
+            resources.push(json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": self.metadata(&secret_name),
+                "type": "Opaque",
+                "stringData": { RUNTIME_CONFIG_SECRET_KEY: runtime_config },
+            }));
+        }
+        let has_runtime_config = !resources.is_empty();
+
+        match self.format {
+            K8sFormat::SpinApp => {
+                let mut spec = json!({
+                    "image": self.image,
+                    "executor": self.executor,
+                    "replicas": self.replicas,
+                });
+                if has_runtime_config {
+                    spec["runtimeConfig"] = json!({ "loadFromSecret": secret_name });
+                }
+                if !self.variables.is_empty() {
+                    spec["variables"] = self
+                        .variables
+                        .iter()
+                        .map(|(name, value)| json!({ "name": name, "value": value }))
+                        .collect();
+                }
+                resources.push(json!({
+                    "apiVersion": "core.spinkube.dev/v1alpha1",
+                    "kind": "SpinApp",
+                    "metadata": self.metadata(&name),
+                    "spec": spec,
+                }));
+            }
+            K8sFormat::Deployment => {
+                let labels = json!({ "app": name });
+                let env = self
+                    .variables
+                    .iter()
+                    .map(|(name, value)| json!({ "name": variable_env_name(name), "value": value }))
+                    .collect::<Vec<_>>();
+                let mut container = json!({
+                    "name": name,
+                    "image": self.image,
+                    "command": ["/"],
+                    "env": env,
+                });
+                let mut pod_spec = json!({ "runtimeClassName": RUNTIME_CLASS_NAME });
+                if has_runtime_config {
+                    container["volumeMounts"] = json!([{
+                        "name": "runtime-config",
+                        "mountPath": SHIM_RUNTIME_CONFIG_PATH,
+                        "subPath": RUNTIME_CONFIG_SECRET_KEY,
+                        "readOnly": true,
+                    }]);
+                    pod_spec["volumes"] = json!([{
+                        "name": "runtime-config",
+                        "secret": { "secretName": secret_name },
+                    }]);
+                }
+                let is_http = manifest.triggers.contains_key("http");
+                if is_http {
+                    container["ports"] = json!([{ "containerPort": SHIM_HTTP_PORT }]);
+                }
+                pod_spec["containers"] = json!([container]);
+                resources.push(json!({
+                    "apiVersion": "apps/v1",
+                    "kind": "Deployment",
+                    "metadata": self.metadata(&name),
+                    "spec": {
+                        "replicas": self.replicas,
+                        "selector": { "matchLabels": labels },
+                        "template": {
+                            "metadata": { "labels": labels },
+                            "spec": pod_spec,
+                        },
+                    },
+                }));
+                if is_http {
+                    resources.push(json!({
+                        "apiVersion": "v1",
+                        "kind": "Service",
+                        "metadata": self.metadata(&name),
+                        "spec": {
+                            "selector": labels,
+                            "ports": [{ "port": SHIM_HTTP_PORT, "targetPort": SHIM_HTTP_PORT }],
+                        },
+                    }));
+                }
+            }
+        }
+        resources
+    }
+
+    fn metadata(&self, name: &str) -> Value {
+        let mut metadata = json!({ "name": name });
+        if let Some(namespace) = &self.namespace {
+            metadata["namespace"] = namespace.as_str().into();
+        }
+        metadata
+    }
+}
+
+fn read_runtime_config(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read runtime config {}", quoted_path(path)))
+}
+
+/// Converts an application name to a valid Kubernetes resource name.
+fn resource_name(app_name: &str) -> String {
+    let name = app_name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    name.trim_matches('-').to_owned()
+}
+
+/// The environment variable from which Spin reads an application variable.
+fn variable_env_name(variable: &str) -> String {
+    format!("SPIN_VARIABLE_{}", variable.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> AppManifest {
+        toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "My App"
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "web.wasm"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn deploy(args: &[&str]) -> K8sDeploy {
+        K8sDeploy::try_parse_from(
+            ["deploy", "--image", "ghcr.io/example/app:1.0"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn spin_app_references_runtime_config_secret() {
+        let resources = deploy(&["--variable", "api_key=secret"])
+            .render(&manifest(), Some("[key_value_store.default]".into()));
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0]["kind"], "Secret");
+        assert_eq!(resources[0]["metadata"]["name"], "my-app-runtime-config");
+
+        let spin_app = &resources[1];
+        assert_eq!(spin_app["kind"], "SpinApp");
+        assert_eq!(spin_app["metadata"]["name"], "my-app");
+        assert_eq!(spin_app["spec"]["image"], "ghcr.io/example/app:1.0");
+        assert_eq!(
+            spin_app["spec"]["runtimeConfig"]["loadFromSecret"],
+            "my-app-runtime-config"
+        );
+        assert_eq!(spin_app["spec"]["variables"][0]["name"], "api_key");
+    }
+
+    #[test]
+    fn deployment_runs_with_spin_runtime_class() {
+        let resources = deploy(&[
+            "--format",
+            "deployment",
+            "--name",
+            "web",
+            "--namespace",
+            "apps",
+        ])
+        .render(&manifest(), None);
+        let kinds = resources.iter().map(|r| &r["kind"]).collect::<Vec<_>>();
+        assert_eq!(kinds, ["Deployment", "Service"]);
+
+        let pod_spec = &resources[0]["spec"]["template"]["spec"];
+        assert_eq!(pod_spec["runtimeClassName"], RUNTIME_CLASS_NAME);
+        assert_eq!(
+            pod_spec["containers"][0]["image"],
+            "ghcr.io/example/app:1.0"
+        );
+        assert_eq!(resources[1]["metadata"]["namespace"], "apps");
+    }
+}
@@ -210,6 +210,7 @@ fn installer_for(plugin_name: &str) -> Install {
         override_compatibility_check: false,
         version: None,
         auth_header_value: None,
+        tarball: None,
     }
 }
 
@@ -0,0 +1,179 @@
+use std::{io::Write, sync::Arc};
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use spin_factor_key_value::{KeyValueFactor, Store};
+
+use crate::configured_app::ConfiguredAppOptions;
+
+/// The store used if none is given.
+const DEFAULT_STORE_LABEL: &str = "default";
+
+/// Commands for working with the key-value stores an application is
+/// configured to use.
+#[derive(Subcommand, Debug)]
+pub enum KvCommands {
+    /// Print the value of a key.
+    Get(Get),
+    /// Set the value of a key.
+    Set(Set),
+    /// Delete a key.
+    Delete(Delete),
+    /// List the keys in a store.
+    List(List),
+}
+
+impl KvCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            KvCommands::Get(cmd) => cmd.run().await,
+            KvCommands::Set(cmd) => cmd.run().await,
+            KvCommands::Delete(cmd) => cmd.run().await,
+            KvCommands::List(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Identifies a store of an application.
+#[derive(Args, Debug)]
+struct StoreOptions {
+    #[clap(flatten)]
+    app: ConfiguredAppOptions,
+
+    /// The label of the store to use.
+    #[clap(short = 's', long = "store", default_value = DEFAULT_STORE_LABEL)]
+    store: String,
+}
+
+impl StoreOptions {
+    /// Opens the store through the application's configured backends.
+    async fn open(&self) -> Result<Arc<dyn Store>> {
+        let configured_app = self.app.configure().await?;
+        let key_value = configured_app.app_state::<KeyValueFactor>()?;
+        key_value
+            .store_manager()
+            .get(&self.store)
+            .await
+            .with_context(|| format!("Failed to open key-value store '{}'", self.store))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Get {
+    /// The key to get.
+    key: String,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+}
+
+impl Get {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        let value = store
+            .get(&self.key)
+            .await
+            .with_context(|| format!("Failed to get '{}'", self.key))?
+            .with_context(|| format!("No key '{}' in store '{}'", self.key, self.store.store))?;
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&value)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Set {
+    /// The key to set.
+    key: String,
+
+    /// The value to set it to.
+    value: String,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+}
+
+impl Set {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        store
+            .set(&self.key, self.value.as_bytes())
+            .await
+            .with_context(|| format!("Failed to set '{}'", self.key))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Delete {
+    /// The key to delete.
+    key: String,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+}
+
+impl Delete {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        store
+            .delete(&self.key)
+            .await
+            .with_context(|| format!("Failed to delete '{}'", self.key))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct List {
+    /// Only list keys starting with this prefix.
+    #[clap(long = "prefix")]
+    prefix: Option<String>,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+}
+
+impl List {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        let mut keys = store
+            .get_keys()
+            .await
+            .with_context(|| format!("Failed to list keys in store '{}'", self.store.store))?;
+        keys.retain(|key| self.prefix.as_ref().is_none_or(|p| key.starts_with(p)));
+        keys.sort();
+        for key in keys {
+            println!("{key}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(subcommand)]
+        kv: KvCommands,
+    }
+
+    #[test]
+    fn kv_args_are_parsed() {
+        let cli =
+            Cli::try_parse_from(["kv", "get", "greeting", "-s", "cache", "-f", "app"]).unwrap();
+        let KvCommands::Get(get) = cli.kv else {
+            panic!("expected get");
+        };
+        assert_eq!(get.key, "greeting");
+        assert_eq!(get.store.store, "cache");
+
+        let cli = Cli::try_parse_from(["kv", "list", "--prefix", "user:"]).unwrap();
+        let KvCommands::List(list) = cli.kv else {
+            panic!("expected list");
+        };
+        assert_eq!(list.prefix.as_deref(), Some("user:"));
+        assert_eq!(list.store.store, DEFAULT_STORE_LABEL);
+    }
+}
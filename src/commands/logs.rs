@@ -0,0 +1,451 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_factors::TriggerFactorsRuntimeConfig;
+use spin_trigger::cli::{history_dir, history_file, UserProvidedPath, RUNTIME_CONFIG_FILE};
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+/// How often log history is checked for new lines when following it.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Show the output of an application's components, as recorded by `spin up`.
+#[derive(Parser, Debug)]
+#[clap(about = "Show the logs of an application's components")]
+pub struct LogsCommand {
+    /// The component whose logs to show. If omitted, the logs of all
+    /// components are shown.
+    pub component: Option<String>,
+
+    /// The application whose logs to show. This may be a manifest (spin.toml)
+    /// file, or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Configuration file for config providers and wasmtime config.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE,
+    )]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set the application state directory path. This defaults to `.spin/`
+    /// relative to the `spin.toml` file.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+
+    /// The log directory the application was run with, if it was run with
+    /// `--log-dir`.
+    #[clap(long = "log-dir")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Keep showing output as components write it.
+    #[clap(long = "follow", takes_value = false)]
+    pub follow: bool,
+
+    /// Only show output written since this long ago, such as `30s`, `10m`,
+    /// `2h` or `1d`, or since an RFC 3339 timestamp.
+    #[clap(long = "since", parse(try_from_str = parse_since))]
+    pub since: Option<DateTime<FixedOffset>>,
+
+    /// Only show output written to this stream.
+    #[clap(long = "stream", value_enum)]
+    pub stream: Option<LogStream>,
+
+    /// Only show lines containing this text.
+    #[clap(long = "grep")]
+    pub grep: Option<String>,
+
+    /// Only show this many of the most recent lines before following.
+    #[clap(short = 'n', long = "tail")]
+    pub tail: Option<usize>,
+
+    /// Print each line as the JSON record it is stored as.
+    #[clap(long = "json", takes_value = false)]
+    pub json: bool,
+}
+
+/// A component output stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+impl LogsCommand {
+    pub async fn run(self) -> Result<()> {
+        let log_dir = self.resolve_log_dir().await?;
+        let history_dir = history_dir(&log_dir);
+        if !self.follow && !history_dir.exists() {
+            bail!(
+                "No logs found in {}. Logs are recorded while the application runs with `spin up`.",
+                quoted_path(&log_dir)
+            );
+        }
+        if let (Some(component), false) = (&self.component, self.follow) {
+            if !history_file(&log_dir, component).exists() {
+                let logged = logged_components(&history_dir)?;
+                bail!(
+                    "No logs found for component '{component}'. Components with logs are: {}",
+                    logged.join(", ")
+                );
+            }
+        }
+
+        let mut reader = HistoryReader::default();
+        let mut entries = vec![];
+        for path in self.history_files(&log_dir, &history_dir)? {
+            entries.extend(read_all(&rotated(&path))?);
+            entries.extend(reader.read_new(&path)?);
+        }
+        let mut entries: Vec<_> = entries.into_iter().filter(|e| self.matches(e)).collect();
+        entries.sort_by_key(|e| e.timestamp);
+        if let Some(tail) = self.tail {
+            entries.drain(..entries.len().saturating_sub(tail));
+        }
+        for entry in &entries {
+            self.print(entry);
+        }
+
+        while self.follow {
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+            let mut entries = vec![];
+            for path in self.history_files(&log_dir, &history_dir)? {
+                entries.extend(reader.read_new(&path)?);
+            }
+            entries.retain(|e| self.matches(e));
+            entries.sort_by_key(|e| e.timestamp);
+            for entry in &entries {
+                self.print(entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the log directory the same way `spin up` does.
+    async fn resolve_log_dir(&self) -> Result<PathBuf> {
+        if let Some(log_dir) = &self.log_dir {
+            return Ok(log_dir.clone());
+        }
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+        let app_dir = manifest_file
+            .parent()
+            .context("manifest path has no parent directory")?
+            .to_owned();
+        let state_dir = match &self.state_dir {
+            Some(s) => UserProvidedPath::Provided(PathBuf::from(s)),
+            None => UserProvidedPath::Default,
+        };
+        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file(
+            self.runtime_config_file.as_deref(),
+            Some(app_dir),
+            state_dir,
+            UserProvidedPath::Default,
+        )
+        .await?;
+        runtime_config
+            .log_dir()
+            .context("The application's runtime config disables logging to files")
+    }
+
+    /// The current history files to read. Components that start logging
+    /// while following are picked up as they do.
+    fn history_files(&self, log_dir: &Path, history_dir: &Path) -> Result<Vec<PathBuf>> {
+        match &self.component {
+            Some(component) => Ok(vec![history_file(log_dir, component)]),
+            None => all_history_files(history_dir),
+        }
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        // History files are named after sanitized component IDs, so check
+        // the ID itself.
+        if let Some(component) = &self.component {
+            if &entry.record.component != component {
+                return false;
+            }
+        }
+        if let Some(since) = &self.since {
+            if entry.timestamp < *since {
+                return false;
+            }
+        }
+        if let Some(stream) = &self.stream {
+            if entry.record.stream != stream.as_str() {
+                return false;
+            }
+        }
+        if let Some(grep) = &self.grep {
+            if !entry.record.message.contains(grep.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn print(&self, entry: &LogEntry) {
+        if self.json {
+            println!("{}", entry.raw);
+        } else {
+            let timestamp = entry
+                .timestamp
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            let record = &entry.record;
+            println!("{timestamp} [{}] {}", record.component, record.message);
+        }
+    }
+}
+
+/// A line of component output as recorded in its log history.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LogRecord {
+    pub timestamp: String,
+    pub component: String,
+    pub stream: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct LogEntry {
+    pub timestamp: DateTime<FixedOffset>,
+    pub record: LogRecord,
+    raw: String,
+}
+
+impl LogEntry {
+    /// Parses a history line, returning `None` if it isn't a log record.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        let record: LogRecord = serde_json::from_str(raw).ok()?;
+        let timestamp = DateTime::parse_from_rfc3339(&record.timestamp).ok()?;
+        Some(Self {
+            timestamp,
+            record,
+            raw: raw.to_owned(),
+        })
+    }
+}
+
+/// Reads the lines added to history files since they were last read.
+#[derive(Default)]
+struct HistoryReader {
+    offsets: HashMap<PathBuf, u64>,
+}
+
+impl HistoryReader {
+    fn read_new(&mut self, path: &Path) -> Result<Vec<LogEntry>> {
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open {}", quoted_path(path)))
+            }
+        };
+        let offset = self.offsets.entry(path.to_owned()).or_default();
+        // A file smaller than what was read from it has been rotated, so the
+        // current file is a new one.
+        if file.metadata()?.len() < *offset {
+            *offset = 0;
+        }
+        file.seek(SeekFrom::Start(*offset))?;
+        let mut content = vec![];
+        file.read_to_end(&mut content)?;
+        // Leave any partially written line until it is complete.
+        let complete = content
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |end| end + 1);
+        *offset += complete as u64;
+        Ok(parse_lines(&content[..complete]))
+    }
+}
+
+/// Reads the log history of all components in `log_dir`, oldest first.
+pub(crate) fn read_history(log_dir: &Path) -> Result<Vec<LogEntry>> {
+    let mut entries = vec![];
+    for path in all_history_files(&history_dir(log_dir))? {
+        entries.extend(read_all(&rotated(&path))?);
+        entries.extend(read_all(&path)?);
+    }
+    entries.sort_by_key(|e| e.timestamp);
+    Ok(entries)
+}
+
+/// The current history files of all components that have logged.
+fn all_history_files(history_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(history_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).context("Failed to read log history"),
+    };
+    let mut files = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "jsonl") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_all(path: &Path) -> Result<Vec<LogEntry>> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(parse_lines(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", quoted_path(path))),
+    }
+}
+
+fn parse_lines(content: &[u8]) -> Vec<LogEntry> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter_map(LogEntry::parse)
+        .collect()
+}
+
+/// The previous history of the component whose current history is at `path`.
+fn rotated(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".1");
+    PathBuf::from(path)
+}
+
+fn logged_components(history_dir: &Path) -> Result<Vec<String>> {
+    let files = all_history_files(history_dir)?;
+    Ok(files
+        .iter()
+        .filter_map(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Parses a `--since` value, either a duration before now or a timestamp.
+fn parse_since(s: &str) -> Result<DateTime<FixedOffset>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+        return Ok(timestamp);
+    }
+    let unit_start = s
+        .find(|c: char| !c.is_ascii_digit())
+        .with_context(|| format!("'{s}' needs a unit: use e.g. 30s, 10m, 2h or 1d"))?;
+    let (amount, unit) = s.split_at(unit_start);
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("'{s}' is not a duration or RFC 3339 timestamp"))?;
+    let duration = match unit {
+        "s" => chrono::Duration::seconds(amount),
+        "m" => chrono::Duration::minutes(amount),
+        "h" => chrono::Duration::hours(amount),
+        "d" => chrono::Duration::days(amount),
+        _ => bail!("Unknown unit '{unit}' in '{s}': use s, m, h or d"),
+    };
+    Ok((Utc::now() - duration).fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn record(timestamp: &str, stream: &str, message: &str) -> String {
+        format!(
+            r#"{{"timestamp":"{timestamp}","component":"hello","stream":"{stream}","level":"info","message":"{message}"}}"#
+        )
+    }
+
+    fn command(args: &[&str]) -> LogsCommand {
+        LogsCommand::parse_from(std::iter::once("logs").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn since_accepts_durations_and_timestamps() -> Result<()> {
+        let ten_minutes_ago = parse_since("10m")?;
+        let elapsed = Utc::now().fixed_offset() - ten_minutes_ago;
+        assert!((600..=601).contains(&elapsed.num_seconds()));
+
+        assert_eq!(
+            DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")?,
+            parse_since("2024-05-01T12:00:00Z")?
+        );
+        assert!(parse_since("10").is_err());
+        assert!(parse_since("10y").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn entries_are_filtered() {
+        let old = LogEntry::parse(&record("2024-05-01T12:00:00Z", "stdout", "starting")).unwrap();
+        let error = LogEntry::parse(&record("2024-05-01T12:05:00Z", "stderr", "failed")).unwrap();
+
+        let cmd = command(&["hello", "--since", "2024-05-01T12:01:00Z"]);
+        assert!(!cmd.matches(&old));
+        assert!(cmd.matches(&error));
+
+        let cmd = command(&["--stream", "stdout"]);
+        assert!(cmd.matches(&old));
+        assert!(!cmd.matches(&error));
+
+        let cmd = command(&["other", "--grep", "fail"]);
+        assert!(!cmd.matches(&error));
+        assert!(command(&["--grep", "fail"]).matches(&error));
+    }
+
+    #[test]
+    fn reader_returns_only_complete_new_lines() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hello.jsonl");
+        let mut file = std::fs::File::create(&path)?;
+        writeln!(file, "{}", record("2024-05-01T12:00:00Z", "stdout", "one"))?;
+        write!(file, r#"{{"timestamp":"#)?;
+
+        let mut reader = HistoryReader::default();
+        let messages = |entries: Vec<LogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.record.message).collect()
+        };
+        assert_eq!(vec!["one"], messages(reader.read_new(&path)?));
+
+        writeln!(
+            file,
+            r#""2024-05-01T12:00:01Z","component":"hello","stream":"stdout","message":"two"}}"#
+        )?;
+        assert_eq!(vec!["two"], messages(reader.read_new(&path)?));
+        assert!(reader.read_new(&path)?.is_empty());
+
+        // After rotation, the new file is read from the start
+        std::fs::write(
+            &path,
+            format!("{}\n", record("2024-05-01T12:00:02Z", "stdout", "3")),
+        )?;
+        assert_eq!(vec!["3"], messages(reader.read_new(&path)?));
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@ pub enum MaintenanceCommands {
     GenerateReference(GenerateReference),
     /// Generate JSON schema for application manifest.
     GenerateManifestSchema(GenerateSchema),
+    /// Generate JSON schema for runtime config.
+    GenerateRuntimeConfigSchema(GenerateRuntimeConfigSchema),
 }
 
 impl MaintenanceCommands {
@@ -16,6 +18,7 @@ impl MaintenanceCommands {
         match self {
             MaintenanceCommands::GenerateReference(cmd) => cmd.run(app).await,
             MaintenanceCommands::GenerateManifestSchema(cmd) => cmd.run().await,
+            MaintenanceCommands::GenerateRuntimeConfigSchema(cmd) => cmd.run().await,
         }
     }
 }
@@ -51,6 +54,22 @@ impl GenerateSchema {
     }
 }
 
+#[derive(Parser, Debug)]
+pub struct GenerateRuntimeConfigSchema {
+    /// The file to which to generate the JSON schema. If omitted, it is generated to stdout.
+    #[clap(short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+impl GenerateRuntimeConfigSchema {
+    async fn run(&self) -> anyhow::Result<()> {
+        let schema = spin_runtime_config::schema::json_schema();
+        let schema_json = serde_json::to_string_pretty(&schema)?;
+        write(&self.output, &schema_json)?;
+        Ok(())
+    }
+}
+
 fn write(output: &Option<PathBuf>, text: &str) -> anyhow::Result<()> {
     match output {
         Some(path) => std::fs::write(path, text)?,
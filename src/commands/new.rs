@@ -6,15 +6,18 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use itertools::Itertools;
 use path_absolutize::Absolutize;
 use tokio;
 
+use spin_loader::lockfile::{AppLockfile, LockedTemplate};
 use spin_templates::{RunOptions, Template, TemplateManager, TemplateVariantInfo};
 
 use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
 
+use super::add_service::AddServiceCommand;
+
 /// Scaffold a new application based on a template.
 #[derive(Parser, Debug)]
 pub struct TemplateNewCommandCore {
@@ -76,6 +79,11 @@ pub struct TemplateNewCommandCore {
         takes_value = false
     )]
     pub allow_overwrite: bool,
+
+    /// An optional argument that allows to skip running the template's
+    /// post-generation commands, such as installing dependencies
+    #[clap(long = "no-hooks", takes_value = false)]
+    pub no_hooks: bool,
 }
 
 /// Scaffold a new application based on a template.
@@ -87,7 +95,11 @@ pub struct NewCommand {
 
 /// Scaffold a new component into an existing application.
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct AddCommand {
+    #[clap(subcommand)]
+    service: Option<AddSubcommand>,
+
     #[clap(flatten)]
     options: TemplateNewCommandCore,
 
@@ -100,6 +112,12 @@ pub struct AddCommand {
     pub app: Option<PathBuf>,
 }
 
+#[derive(Subcommand, Debug)]
+enum AddSubcommand {
+    /// Add a key-value store, SQLite database or Redis trigger to a component.
+    Service(AddServiceCommand),
+}
+
 impl NewCommand {
     pub async fn run(&self) -> Result<()> {
         self.options.run(TemplateVariantInfo::NewApplication).await
@@ -108,6 +126,9 @@ impl NewCommand {
 
 impl AddCommand {
     pub async fn run(&self) -> Result<()> {
+        if let Some(AddSubcommand::Service(cmd)) = &self.service {
+            return cmd.run().await;
+        }
         let app_file = self
             .app
             .as_deref()
@@ -187,6 +208,10 @@ impl TemplateNewCommandCore {
             merge_values(&mut values, &self.values);
             values
         };
+        let manifest_path = match &variant {
+            TemplateVariantInfo::NewApplication => output_path.join(DEFAULT_MANIFEST_FILE),
+            TemplateVariantInfo::AddComponent { manifest_path } => manifest_path.clone(),
+        };
         let options = RunOptions {
             variant,
             name: name.clone(),
@@ -195,15 +220,23 @@ impl TemplateNewCommandCore {
             accept_defaults: self.accept_defaults,
             no_vcs: self.no_vcs,
             allow_overwrite: self.allow_overwrite,
+            no_hooks: self.no_hooks,
         };
 
         let run = template.run(options);
 
         if std::io::stderr().is_terminal() {
-            run.interactive().await
+            run.interactive().await?;
         } else {
-            run.silent().await
+            run.silent().await?;
+        }
+
+        // The run may have been cancelled, or the template may not create an
+        // application at all.
+        if manifest_path.exists() {
+            lock_template(&manifest_path, &template)?;
         }
+        Ok(())
     }
 
     // Try to guess if the user is using v1 or v2 syntax, and fix things up so
@@ -300,6 +333,17 @@ fn merge_values(from_file: &mut HashMap<String, String>, from_cli: &[ParameterVa
     }
 }
 
+/// Records the template an application was created from, or had a
+/// component added from, in the application's lockfile.
+fn lock_template(manifest_path: &Path, template: &Template) -> Result<()> {
+    let mut lockfile = AppLockfile::load(manifest_path)?.unwrap_or_default();
+    let locked = LockedTemplate {
+        installed_from: template.installed_from_or_empty().to_owned(),
+    };
+    lockfile.templates.insert(template.id().to_owned(), locked);
+    lockfile.save(manifest_path)
+}
+
 async fn prompt_template(
     template_manager: &TemplateManager,
     variant: &TemplateVariantInfo,
@@ -3,11 +3,10 @@
 
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
-use semver::Version;
 use spin_plugins::{
     error::Error,
-    lookup::{fetch_plugins_repo, plugins_repo_url, PluginLookup},
-    manager::{self, InstallAction, ManifestLocation, PluginManager},
+    lookup::{fetch_plugins_repo, plugins_repo_url, PluginLookup, VersionConstraint},
+    manager::{self, InstallAction, ManifestLocation, PluginBackup, PluginManager},
     manifest::{PluginManifest, PluginPackage},
 };
 use std::path::{Path, PathBuf};
@@ -105,7 +104,8 @@ pub struct Install {
     pub auth_header_value: Option<String>,
 
     /// Specific version of a plugin to be install from the centralized plugins
-    /// repository.
+    /// repository, or a version range such as "^1.2" to install the highest
+    /// compatible version matching it.
     #[clap(
         long = "version",
         short = 'v',
@@ -113,7 +113,16 @@ pub struct Install {
         conflicts_with = PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT,
         requires(PLUGIN_NAME_OPT)
     )]
-    pub version: Option<Version>,
+    pub version: Option<VersionConstraint>,
+
+    /// Install the plugin package from a local tarball instead of downloading
+    /// it, e.g. to install without network access. The tarball must match the
+    /// checksum in the plugin manifest.
+    #[clap(
+        long = "tarball",
+        conflicts_with = PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT,
+    )]
+    pub tarball: Option<PathBuf>,
 }
 
 impl Install {
@@ -121,7 +130,7 @@ impl Install {
         let manifest_location = match (&self.local_manifest_src, &self.remote_manifest_src, &self.name) {
             (Some(path), None, None) => ManifestLocation::Local(path.to_path_buf()),
             (None, Some(url), None) => ManifestLocation::Remote(url.clone()),
-            (None, None, Some(name)) => ManifestLocation::PluginsRepository(PluginLookup::new(name, self.version.clone())),
+            (None, None, Some(name)) => ManifestLocation::PluginsRepository(PluginLookup::with_constraint(name, self.version.clone())),
             _ => return Err(anyhow::anyhow!("For plugin lookup, must provide exactly one of: plugin name, url to manifest, local path to manifest")),
         };
         let manager = PluginManager::try_default()?;
@@ -143,6 +152,7 @@ impl Install {
             downgrade,
             &manifest_location,
             &self.auth_header_value,
+            self.tarball.as_deref(),
         )
         .await?;
         Ok(())
@@ -225,7 +235,8 @@ pub struct Upgrade {
     pub override_compatibility_check: bool,
 
     /// Specific version of a plugin to be install from the centralized plugins
-    /// repository.
+    /// repository, or a version range such as "^1.2" to upgrade to the highest
+    /// compatible version matching it.
     #[clap(
         long = "version",
         short = 'v',
@@ -234,7 +245,7 @@ pub struct Upgrade {
         conflicts_with = PLUGIN_ALL_OPT,
         requires(PLUGIN_NAME_OPT)
     )]
-    pub version: Option<Version>,
+    pub version: Option<VersionConstraint>,
 
     /// Allow downgrading a plugin's version.
     #[clap(short = 'd', long = "downgrade", takes_value = false)]
@@ -367,6 +378,7 @@ impl Upgrade {
                 false,
                 &manifest_location,
                 &self.auth_header_value,
+                None,
             )
             .await?;
         }
@@ -374,9 +386,40 @@ impl Upgrade {
         Ok(())
     }
 
-    // Install the latest of all currently installed plugins
+    // Install the latest of all currently installed plugins. If any upgrade
+    // fails, the plugins upgraded so far are restored to their previous versions
+    // so that the installation isn't left half-upgraded.
     async fn upgrade_all(&self, manifests_dir: impl AsRef<Path>) -> Result<()> {
         let manager = PluginManager::try_default()?;
+        let mut upgraded = vec![];
+        match self
+            .upgrade_all_inner(&manager, manifests_dir, &mut upgraded)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                for backup in upgraded.into_iter().rev() {
+                    let name = backup.name().to_owned();
+                    match manager.restore(backup) {
+                        Ok(()) => eprintln!("Rolled back plugin '{name}'"),
+                        Err(restore_err) => {
+                            terminal::error!("Failed to roll back plugin '{name}': {restore_err:#}")
+                        }
+                    }
+                }
+                Err(e.context("Failed to upgrade all plugins"))
+            }
+        }
+    }
+
+    // Upgrades each plugin in turn, recording a backup of every plugin it
+    // touches so that the caller can roll back.
+    async fn upgrade_all_inner(
+        &self,
+        manager: &PluginManager,
+        manifests_dir: impl AsRef<Path>,
+        upgraded: &mut Vec<PluginBackup>,
+    ) -> Result<()> {
         for plugin in std::fs::read_dir(manifests_dir)? {
             let path = plugin?.path();
             let name = path
@@ -403,16 +446,28 @@ impl Upgrade {
                 Err(e) => return Err(e.into()),
                 Ok(m) => m,
             };
-            try_install(
+            // Back up before installing, because a failed install may leave the
+            // plugin's files partially replaced.
+            let backup = manager.back_up(&name)?;
+            let result = try_install(
                 &manifest,
-                &manager,
+                manager,
                 self.yes_to_all,
                 self.override_compatibility_check,
                 self.downgrade,
                 &manifest_location,
                 &self.auth_header_value,
+                None,
             )
-            .await?;
+            .await;
+            match result {
+                Ok(true) => upgraded.push(backup),
+                Ok(false) => {}
+                Err(e) => {
+                    upgraded.push(backup);
+                    return Err(e.context(format!("Failed to upgrade plugin '{name}'")));
+                }
+            }
         }
         Ok(())
     }
@@ -422,7 +477,7 @@ impl Upgrade {
         let manifest_location = match (self.local_manifest_src, self.remote_manifest_src) {
             (Some(path), None) => ManifestLocation::Local(path),
             (None, Some(url)) => ManifestLocation::Remote(url),
-            _ => ManifestLocation::PluginsRepository(PluginLookup::new(
+            _ => ManifestLocation::PluginsRepository(PluginLookup::with_constraint(
                 self.name
                     .as_ref()
                     .context("plugin name is required for upgrades")?,
@@ -445,6 +500,7 @@ impl Upgrade {
             self.downgrade,
             &manifest_location,
             &self.auth_header_value,
+            None,
         )
         .await?;
         Ok(())
@@ -871,6 +927,7 @@ fn prompt_confirm_install(manifest: &PluginManifest, package: &PluginPackage) ->
     Ok(install)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn try_install(
     manifest: &PluginManifest,
     manager: &PluginManager,
@@ -879,6 +936,7 @@ async fn try_install(
     downgrade: bool,
     source: &ManifestLocation,
     auth_header_value: &Option<String>,
+    tarball: Option<&Path>,
 ) -> Result<bool> {
     let install_action = manager.check_manifest(
         manifest,
@@ -894,9 +952,14 @@ async fn try_install(
 
     let package = manager::get_package(manifest)?;
     if continue_to_install(manifest, package, yes_to_all)? {
-        let installed = manager
-            .install(manifest, package, source, auth_header_value)
-            .await?;
+        let installed = match tarball {
+            Some(tarball) => manager.install_from_file(manifest, package, tarball, source)?,
+            None => {
+                manager
+                    .install(manifest, package, source, auth_header_value)
+                    .await?
+            }
+        };
         println!("Plugin '{installed}' was installed successfully!");
 
         if let Some(description) = manifest.description() {
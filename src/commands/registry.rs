@@ -1,10 +1,20 @@
-use crate::{directory_rels::notify_if_nondefault_rel, opts::*};
+use crate::{build_info::SPIN_VERSION, directory_rels::notify_if_nondefault_rel, opts::*};
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
 use spin_common::arg_parser::parse_kv;
-use spin_oci::{client::InferPredefinedAnnotations, Client, ComposeMode};
-use std::{io::Read, path::PathBuf, time::Duration};
+use spin_common::ui::quoted_path;
+use spin_loader::{lockfile::AppLockfile, FilesMountStrategy};
+use spin_oci::{
+    client::InferPredefinedAnnotations,
+    sbom::{self, attestation_tag, sbom_tag, Subject, DSSE_MEDIA_TYPE, SBOM_MEDIA_TYPE},
+    signing::{AppSigningKey, VerificationPolicy},
+    Client, ComposeMode, OciLoader,
+};
+use std::{collections::BTreeMap, io::Read, path::PathBuf, time::Duration};
+
+/// The OCI annotation recording the digest of the application's lockfile.
+const LOCKFILE_DIGEST_ANNOTATION: &str = "dev.spinframework.app.lockfile.digest";
 
 /// Commands for working with OCI registries to distribute applications.
 #[derive(Subcommand, Debug)]
@@ -15,6 +25,14 @@ pub enum RegistryCommands {
     Pull(Pull),
     /// Log in to a registry.
     Login(Login),
+    /// Remove applications and layers that haven't been used recently from
+    /// the local registry cache.
+    Gc(Gc),
+    /// Save an application and all of its content to a bundle file, which
+    /// can be run without registry access using `spin up --from-bundle`.
+    Save(Save),
+    /// Unpack an application bundle created with `spin registry save`.
+    Load(Load),
 }
 
 impl RegistryCommands {
@@ -23,6 +41,9 @@ impl RegistryCommands {
             RegistryCommands::Push(cmd) => cmd.run().await,
             RegistryCommands::Pull(cmd) => cmd.run().await,
             RegistryCommands::Login(cmd) => cmd.run().await,
+            RegistryCommands::Gc(cmd) => cmd.run().await,
+            RegistryCommands::Save(cmd) => cmd.run().await,
+            RegistryCommands::Load(cmd) => cmd.run().await,
         }
     }
 }
@@ -62,6 +83,11 @@ pub struct Push {
     #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
     pub build: bool,
 
+    /// Require the application to match its lockfile (spin-lock.toml),
+    /// instead of warning if it does not.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
     /// Reference in the registry of the Spin application.
     /// This is a string whose format is defined by the registry standard, and generally consists of <registry>/<username>/<application-name>:<version>. E.g. ghcr.io/ogghead/spin-test-app:0.1.0
     #[clap()]
@@ -75,24 +101,72 @@ pub struct Push {
     /// Any existing value will be overwritten. Can be used multiple times.
     #[clap(long = "annotation", parse(try_from_str = parse_kv))]
     pub annotations: Vec<(String, String)>,
+
+    /// Sign the pushed application with this key. This must be an
+    /// unencrypted PKCS#8 PEM ECDSA P-256 private key. The signature can be
+    /// verified with `cosign verify --key`.
+    #[clap(long = "signing-key")]
+    pub signing_key: Option<PathBuf>,
+
+    /// Add an annotation (in key=value format) to the signature, which
+    /// verifiers can require. Can be used multiple times.
+    #[clap(
+        long = "signature-annotation",
+        parse(try_from_str = parse_kv),
+        requires = "signing-key"
+    )]
+    pub signature_annotations: Vec<(String, String)>,
+
+    /// Attach a CycloneDX bill of materials and SLSA provenance to the
+    /// pushed application. The provenance is signed if `--signing-key` is
+    /// given.
+    #[clap(long, takes_value = false)]
+    pub sbom: bool,
 }
 
 impl Push {
     pub async fn run(self) -> Result<()> {
+        let started_on = chrono::Utc::now();
         let (app_file, distance) =
             spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
         notify_if_nondefault_rel(&app_file, distance);
 
         if self.build {
             spin_build::build(&app_file, &[]).await?;
+            super::build::update_lockfile(&app_file, self.locked)?;
+        } else {
+            super::build::check_lockfile(&app_file, self.locked)?;
         }
 
-        let annotations = if self.annotations.is_empty() {
-            None
+        // The lockfile itself is published in the app's metadata; its
+        // digest is also annotated so it can be compared without pulling.
+        let mut annotations = BTreeMap::new();
+        let lockfile_path = AppLockfile::path_for(&app_file)?;
+        if lockfile_path.exists() {
+            let digest = spin_common::sha256::hex_digest_from_file(&lockfile_path)?;
+            annotations.insert(
+                LOCKFILE_DIGEST_ANNOTATION.to_owned(),
+                format!("sha256:{digest}"),
+            );
+        }
+        annotations.extend(self.annotations.iter().cloned());
+        let annotations = (!annotations.is_empty()).then_some(annotations);
+
+        // Likewise, inventory the app before pushing it.
+        let inventory = if self.sbom {
+            Some(super::build::app_inventory(&app_file).await?)
         } else {
-            Some(self.annotations.iter().cloned().collect())
+            None
         };
 
+        // Load the key before pushing so that a bad key doesn't leave an
+        // unsigned app in the registry.
+        let signing_key = self
+            .signing_key
+            .as_ref()
+            .map(AppSigningKey::from_file)
+            .transpose()?;
+
         let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
 
         let _spinner = create_dotted_spinner(2000, "Pushing app to the Registry".to_owned());
@@ -112,11 +186,42 @@ impl Push {
                 compose_mode,
             )
             .await?;
-        match digest {
+        match &digest {
             Some(digest) => println!("Pushed with digest {digest}"),
             None => println!("Pushed; the registry did not return the digest"),
         };
 
+        if let Some(signing_key) = &signing_key {
+            let annotations = self.signature_annotations.iter().cloned().collect();
+            let digest = client
+                .sign(&self.reference, digest.clone(), signing_key, annotations)
+                .await
+                .context("Failed to sign the pushed application")?;
+            println!("Signed digest {digest}");
+        }
+
+        if let Some(inventory) = &inventory {
+            let sbom = serde_json::to_vec(&inventory.cyclonedx(SPIN_VERSION))?;
+            let digest = client
+                .attach(&self.reference, digest, sbom_tag, SBOM_MEDIA_TYPE, sbom)
+                .await
+                .context("Failed to attach the bill of materials")?;
+            let subjects = [Subject::new(&self.reference, &digest)];
+            let provenance = inventory.provenance(&subjects, SPIN_VERSION, started_on);
+            let envelope = sbom::envelope(&provenance, signing_key.as_ref())?;
+            client
+                .attach(
+                    &self.reference,
+                    Some(digest),
+                    attestation_tag,
+                    DSSE_MEDIA_TYPE,
+                    envelope,
+                )
+                .await
+                .context("Failed to attach the provenance")?;
+            println!("Attached bill of materials and provenance");
+        }
+
         Ok(())
     }
 }
@@ -140,12 +245,16 @@ pub struct Pull {
     /// Cache directory for downloaded registry data.
     #[clap(long)]
     pub cache_dir: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub verify: VerifyOptions,
 }
 
 impl Pull {
     /// Pull a Spin application from an OCI registry
     pub async fn run(self) -> Result<()> {
         let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
+        client.opts.verification_policy = self.verify.policy()?;
 
         let _spinner = create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
 
@@ -155,6 +264,176 @@ impl Pull {
     }
 }
 
+#[derive(Parser, Debug)]
+pub struct Gc {
+    /// Remove entries that have not been used for this many days.
+    #[clap(long = "older-than-days", default_value_t = 30)]
+    pub older_than_days: u64,
+
+    /// Cache directory for downloaded registry data.
+    #[clap(long)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Gc {
+    pub async fn run(self) -> Result<()> {
+        let cache = spin_loader::cache::Cache::new(self.cache_dir).await?;
+        let max_age = Duration::from_secs(self.older_than_days * 24 * 60 * 60);
+        let summary = cache.gc(max_age).await?;
+        println!(
+            "Removed {} cached application(s) and {} file(s), freeing {} bytes",
+            summary.manifests_removed, summary.files_removed, summary.bytes_freed
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Save {
+    /// The application to save. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Save an application from a registry instead of a local application.
+    #[clap(
+        name = FROM_REGISTRY_OPT,
+        long = "from-registry",
+        conflicts_with = APP_MANIFEST_FILE_OPT,
+    )]
+    pub registry_source: Option<String>,
+
+    /// The bundle file to write, e.g. app.tar.gz.
+    #[clap(short = 'o', long = "output")]
+    pub output: PathBuf,
+
+    /// Ignore server certificate errors
+    #[clap(
+        name = INSECURE_OPT,
+        short = 'k',
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+
+    /// Specifies to perform `spin build` before saving a local application.
+    #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
+    pub build: bool,
+
+    /// Require a local application to match its lockfile (spin-lock.toml),
+    /// instead of warning if it does not.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
+    /// Cache directory for downloaded components and registry data.
+    #[clap(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub verify: VerifyOptions,
+}
+
+impl Save {
+    pub async fn run(self) -> Result<()> {
+        let working_dir = tempfile::tempdir()?;
+        let locked_app = match &self.registry_source {
+            Some(reference) => {
+                let mut client =
+                    spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
+                client.opts.verification_policy = self.verify.policy()?;
+                let _spinner =
+                    create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
+                OciLoader::new(working_dir.path())
+                    .load_app(&mut client, reference)
+                    .await?
+            }
+            None => {
+                let (app_file, distance) =
+                    spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+                notify_if_nondefault_rel(&app_file, distance);
+                if self.build {
+                    spin_build::build(&app_file, &[]).await?;
+                    super::build::update_lockfile(&app_file, self.locked)?;
+                } else {
+                    super::build::check_lockfile(&app_file, self.locked)?;
+                }
+                spin_loader::from_file(
+                    &app_file,
+                    FilesMountStrategy::Copy(working_dir.path().join("assets")),
+                    self.cache_dir.clone(),
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to load manifest from {}", quoted_path(&app_file))
+                })?
+            }
+        };
+
+        spin_oci::bundle::save(locked_app, &self.output).await?;
+        println!("Saved application bundle to {}", quoted_path(&self.output));
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Load {
+    /// The bundle file to unpack, created with `spin registry save`.
+    #[clap()]
+    pub bundle: PathBuf,
+
+    /// The directory to unpack the bundle into.
+    #[clap(short = 'o', long = "output")]
+    pub output: PathBuf,
+}
+
+impl Load {
+    pub async fn run(self) -> Result<()> {
+        spin_oci::bundle::unpack(&self.bundle, &self.output).await?;
+        println!(
+            "Unpacked application bundle to {}. Run it with `spin up --from-bundle {}`",
+            quoted_path(&self.output),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+/// Options for requiring applications pulled from a registry to be signed.
+#[derive(Args, Debug, Default)]
+pub struct VerifyOptions {
+    /// Require the application to be signed by this key, a PEM ECDSA P-256
+    /// public key such as `cosign.pub`. Can be used multiple times, in which
+    /// case a signature by any of the keys is accepted.
+    #[clap(long = "verify-key")]
+    pub verify_keys: Vec<PathBuf>,
+
+    /// Require the application's signature to carry this annotation (in
+    /// key=value format). Can be used multiple times.
+    #[clap(
+        long = "require-signature-annotation",
+        parse(try_from_str = parse_kv),
+        requires = "verify-keys"
+    )]
+    pub required_annotations: Vec<(String, String)>,
+}
+
+impl VerifyOptions {
+    /// The verification policy to apply, if any keys were given.
+    pub fn policy(&self) -> Result<Option<VerificationPolicy>> {
+        if self.verify_keys.is_empty() {
+            return Ok(None);
+        }
+        let required_annotations = self.required_annotations.iter().cloned().collect();
+        VerificationPolicy::from_key_files(&self.verify_keys, required_annotations).map(Some)
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct Login {
     /// Username for the registry
@@ -0,0 +1,222 @@
+use std::{
+    io::Write,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use http_body_util::BodyExt;
+use spin_app::App;
+use spin_common::ui::quoted_path;
+use spin_loader::FilesMountStrategy;
+use spin_runtime_factors::{FactorsBuilder, TriggerAppArgs, TriggerFactors};
+use spin_trigger::{
+    cli::{
+        FactorsConfig, FollowComponents, LogFormat, NoCliArgs, TriggerAppBuilder, UserProvidedPath,
+    },
+    loader::ComponentLoader,
+    recording::{RecordedPayload, Recording},
+    Trigger, TriggerApp,
+};
+use spin_trigger_http::HttpTrigger;
+use spin_trigger_redis::{RedisMessage, RedisTrigger};
+use tempfile::TempDir;
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+/// The address the HTTP trigger acts as if it were listening on.
+const HTTP_LISTEN_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 3000);
+
+/// Re-invoke a component with a payload recorded by `spin up --record-dir`.
+#[derive(Parser, Debug)]
+#[clap(about = "Replay a recorded invocation against the Spin application")]
+pub struct ReplayCommand {
+    /// The recording to replay.
+    pub recording: PathBuf,
+
+    /// The application to replay against. This may be a manifest (spin.toml)
+    /// file, or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Configuration file for config providers and wasmtime config.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set the application state directory path. This defaults to `.spin/`
+    /// relative to the `spin.toml` file. Passing an empty value forces the
+    /// value to be unset.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+}
+
+impl ReplayCommand {
+    pub async fn run(self) -> Result<()> {
+        let recording = Recording::from_file(&self.recording)?;
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+
+        let locked =
+            spin_loader::from_file(&manifest_file, FilesMountStrategy::Direct, None).await?;
+        let app = App::new(manifest_file.display().to_string(), locked);
+        app.get_component(&recording.component_id)
+            .with_context(|| {
+                format!(
+                    "The recorded component '{}' is not in {}",
+                    recording.component_id,
+                    quoted_path(&manifest_file)
+                )
+            })?;
+
+        let working_dir = TempDir::with_prefix("spinreplay-")?;
+        let options = FactorsConfig {
+            working_dir: working_dir.path().to_owned(),
+            runtime_config_file: self.runtime_config_file.clone(),
+            state_dir: match &self.state_dir {
+                Some(s) if s.is_empty() => UserProvidedPath::Unset,
+                Some(s) => UserProvidedPath::Provided(PathBuf::from(s)),
+                None => UserProvidedPath::Default,
+            },
+            local_app_dir: manifest_file.parent().map(|dir| dir.display().to_string()),
+            follow_components: FollowComponents::All,
+            log_dir: UserProvidedPath::Unset,
+            log_format: LogFormat::default(),
+            log_rotation: None,
+            check_only: false,
+        };
+
+        terminal::step!(
+            "Replaying",
+            "{} invocation of component {} recorded at {}",
+            recording.trigger_type,
+            recording.component_id,
+            recording.recorded_at
+        );
+        match recording.payload {
+            RecordedPayload::Http {
+                method,
+                path_and_query,
+                headers,
+                body,
+            } => {
+                let trigger = HttpTrigger::new(&app, HTTP_LISTEN_ADDR, None)?;
+                let (trigger, trigger_app) = build_trigger_app(trigger, app, options).await?;
+                let server = trigger.into_server(trigger_app)?;
+
+                let mut builder = http::Request::builder()
+                    .method(method.as_str())
+                    .uri(&path_and_query);
+                for (name, value) in &headers {
+                    builder = builder.header(name, value);
+                }
+                let request = builder.body(spin_http::body::full(body.into()))?;
+                let response = server
+                    .handle(request, http::uri::Scheme::HTTP, HTTP_LISTEN_ADDR)
+                    .await?;
+                print_response(response).await
+            }
+            RecordedPayload::Redis {
+                channel,
+                pattern,
+                id,
+                headers,
+                payload,
+            } => {
+                let trigger = <RedisTrigger as Trigger<TriggerFactors>>::new(NoCliArgs, &app)?;
+                let (_, trigger_app) = build_trigger_app(trigger, app, options).await?;
+                let message = RedisMessage {
+                    channel: channel.clone(),
+                    pattern,
+                    id,
+                    headers,
+                    payload,
+                };
+                spin_trigger_redis::handle_message(
+                    &trigger_app,
+                    &recording.component_id,
+                    message,
+                    None,
+                )
+                .await?;
+                terminal::step!("Handled", "message on channel {channel}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Builds the app for a trigger, returning the trigger alongside it.
+async fn build_trigger_app<T>(
+    trigger: T,
+    app: App,
+    options: FactorsConfig,
+) -> Result<(T, TriggerApp<T, TriggerFactors>)>
+where
+    T: Trigger<TriggerFactors, InstanceState = ()>,
+{
+    let mut builder: TriggerAppBuilder<T, FactorsBuilder> = TriggerAppBuilder::new(trigger);
+    let trigger_app = builder
+        .build(
+            app,
+            options,
+            TriggerAppArgs::default(),
+            &ComponentLoader::new(),
+        )
+        .await?;
+    Ok((builder.trigger, trigger_app))
+}
+
+/// Prints the status and headers of a response to stderr, and its body to
+/// stdout.
+async fn print_response(response: http::Response<spin_http::Body>) -> Result<()> {
+    let (parts, body) = response.into_parts();
+    eprintln!("{:?} {}", parts.version, parts.status);
+    for (name, value) in &parts.headers {
+        eprintln!("{name}: {}", String::from_utf8_lossy(value.as_bytes()));
+    }
+    eprintln!();
+    let body = body
+        .collect()
+        .await
+        .context("failed to read response body")?
+        .to_bytes();
+    let mut stdout = std::io::stdout();
+    stdout.write_all(&body)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn replay_args_are_parsed() {
+        let cmd = ReplayCommand::try_parse_from([
+            "replay",
+            "recordings/20240101T000000.000000Z-http-api-0.json",
+            "-f",
+            "app",
+            "--state-dir",
+            "",
+        ])
+        .unwrap();
+        assert_eq!(
+            cmd.recording,
+            Path::new("recordings/20240101T000000.000000Z-http-api-0.json")
+        );
+        assert_eq!(cmd.app_source.as_deref(), Some(Path::new("app")));
+        assert_eq!(cmd.state_dir.as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use spin_app::App;
+use spin_common::ui::quoted_path;
+use spin_loader::FilesMountStrategy;
+use spin_runtime_factors::{FactorsBuilder, TriggerAppArgs, TriggerFactors};
+use spin_trigger::{
+    cli::{
+        serve_metrics, FactorsConfig, FollowComponents, LogFormat, TriggerAppBuilder,
+        UserProvidedPath,
+    },
+    loader::ComponentLoader,
+    Trigger,
+};
+use spin_trigger_http::{AppMount, HttpServer, HttpTrigger, MultiAppServer, TlsConfig};
+use tempfile::TempDir;
+
+use super::up::WorkingDirectory;
+
+/// The default multi-app configuration file.
+const DEFAULT_CONFIG_FILE: &str = "spin-apps.toml";
+
+/// Serve multiple HTTP applications from a single process.
+#[derive(Parser, Debug)]
+#[clap(about = "Serve multiple HTTP applications from a single process")]
+pub struct ServeCommand {
+    /// The configuration file listing the applications to serve.
+    #[clap(short = 'c', long = "config", default_value = DEFAULT_CONFIG_FILE)]
+    pub config: PathBuf,
+
+    #[clap(flatten)]
+    pub http: spin_trigger_http::CliArgs,
+
+    /// Temporary directory for the static assets of the components.
+    #[clap(long = "temp", alias = "tmp")]
+    pub tmp: Option<PathBuf>,
+
+    /// Cache directory for downloaded components and assets.
+    #[clap(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Serve Prometheus metrics at `/metrics` on the given address, e.g.
+    /// 127.0.0.1:9090.
+    #[clap(long = "metrics-listen", env = "SPIN_METRICS_LISTEN")]
+    pub metrics_listen: Option<SocketAddr>,
+}
+
+/// The multi-app configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServeConfig {
+    #[serde(rename = "app", default)]
+    apps: Vec<AppConfig>,
+}
+
+/// An application to serve, and where to mount it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AppConfig {
+    /// The application manifest, or a directory containing a spin.toml file.
+    manifest: PathBuf,
+    /// The hostname requests must be addressed to; any host if omitted.
+    host: Option<String>,
+    /// The path prefix to mount the application at; "/" if omitted.
+    path_prefix: Option<String>,
+    /// The runtime config file for the application.
+    runtime_config_file: Option<PathBuf>,
+    /// The state directory for the application; defaults to `.spin` next to
+    /// the manifest.
+    state_dir: Option<PathBuf>,
+}
+
+impl ServeCommand {
+    pub async fn run(self) -> Result<()> {
+        let config_dir = self
+            .config
+            .parent()
+            .map(ToOwned::to_owned)
+            .unwrap_or_default();
+        let contents = std::fs::read_to_string(&self.config)
+            .with_context(|| format!("failed to read {}", quoted_path(&self.config)))?;
+        let config: ServeConfig = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", quoted_path(&self.config)))?;
+        if config.apps.is_empty() {
+            bail!("No applications in {}", quoted_path(&self.config));
+        }
+
+        if let Some(metrics_listen) = self.metrics_listen {
+            serve_metrics(metrics_listen).await?;
+        }
+
+        let working_dir_holder = match &self.tmp {
+            None => WorkingDirectory::Temporary(TempDir::with_prefix("spinserve-")?),
+            Some(d) => WorkingDirectory::Given(d.to_owned()),
+        };
+
+        let mut apps = Vec::with_capacity(config.apps.len());
+        for (index, app_config) in config.apps.iter().enumerate() {
+            let mount = AppMount::new(app_config.host.clone(), app_config.path_prefix.clone())?;
+            let working_dir = working_dir_holder.path().join(format!("app-{index}"));
+            let server = self
+                .load_app(app_config, &config_dir, &working_dir)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load application {}",
+                        quoted_path(&app_config.manifest)
+                    )
+                })?;
+            apps.push((mount, server));
+        }
+
+        let tls_config = match (self.http.tls_cert, self.http.tls_key) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+            }),
+            _ => None,
+        };
+        let server = Arc::new(MultiAppServer::new(self.http.address, tls_config, apps)?);
+
+        let (abortable, abort_handle) = futures::future::abortable(server.serve());
+        ctrlc::set_handler(move || abort_handle.abort())?;
+        match abortable.await {
+            Ok(res) => res,
+            Err(_aborted) => {
+                tracing::info!("User requested shutdown: exiting");
+                Ok(())
+            }
+        }
+    }
+
+    /// Loads an application into its own [`HttpServer`], with its own
+    /// factors and runtime config.
+    async fn load_app(
+        &self,
+        app_config: &AppConfig,
+        config_dir: &Path,
+        working_dir: &Path,
+    ) -> Result<Arc<HttpServer<TriggerFactors>>> {
+        let manifest_path =
+            spin_common::paths::resolve_manifest_file_path(config_dir.join(&app_config.manifest))?;
+        let app_dir = manifest_path
+            .parent()
+            .context("manifest path has no parent directory")?
+            .to_owned();
+        std::fs::create_dir_all(working_dir)
+            .with_context(|| format!("failed to create {}", quoted_path(working_dir)))?;
+
+        let locked = spin_loader::from_file(
+            &manifest_path,
+            FilesMountStrategy::Copy(working_dir.join("assets")),
+            self.cache_dir.clone(),
+        )
+        .await?;
+        if let Some(trigger) = locked.triggers.iter().find(|t| t.trigger_type != "http") {
+            bail!(
+                "Only HTTP applications can be served together, but this application has a '{}' trigger",
+                trigger.trigger_type
+            );
+        }
+        let app = App::new(manifest_path.display().to_string(), locked);
+
+        // Validate required host features
+        let supported = <HttpTrigger as Trigger<TriggerFactors>>::supported_host_requirements();
+        if let Err(unmet) = app.ensure_needs_only("http", &supported) {
+            bail!(
+                "This application requires the following features that are not available: {unmet}"
+            );
+        }
+
+        let trigger = HttpTrigger::new(&app, self.http.address, None)?;
+        let mut builder: TriggerAppBuilder<_, FactorsBuilder> = TriggerAppBuilder::new(trigger);
+        builder.engine_config().enable_cache(&None)?;
+
+        let common_options = FactorsConfig {
+            working_dir: working_dir.to_owned(),
+            runtime_config_file: app_config
+                .runtime_config_file
+                .as_ref()
+                .map(|path| config_dir.join(path)),
+            state_dir: match &app_config.state_dir {
+                Some(path) => UserProvidedPath::Provided(config_dir.join(path)),
+                None => UserProvidedPath::Default,
+            },
+            local_app_dir: Some(app_dir.display().to_string()),
+            follow_components: FollowComponents::All,
+            log_dir: UserProvidedPath::Default,
+            log_format: LogFormat::default(),
+            log_rotation: None,
+            check_only: false,
+        };
+        let trigger_app = builder
+            .build(
+                app,
+                common_options,
+                TriggerAppArgs::default(),
+                &ComponentLoader::new(),
+            )
+            .await?;
+        builder.trigger.into_server(trigger_app)
+    }
+}
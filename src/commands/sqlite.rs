@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use comfy_table::Table;
+use spin_factor_sqlite::{Connection, SqliteFactor};
+use spin_sqlite_libsql::TursoPlatform;
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
+
+use crate::configured_app::ConfiguredAppOptions;
+
+/// The database used if none is given.
+const DEFAULT_DATABASE_LABEL: &str = "default";
+
+/// Lists the statements that created the user's tables, indexes, views and
+/// triggers.
+const SCHEMA_QUERY: &str = "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' ORDER BY type DESC, name";
+
+/// Commands for working with the SQLite databases an application is
+/// configured to use.
+#[derive(Subcommand, Debug)]
+pub enum SqliteCommands {
+    /// Run a SQL statement and print any rows it returns.
+    Execute(Execute),
+    /// Print the statements that created the database's schema.
+    Schema(Schema),
+    /// Create a Turso database as a branch of another, optionally as it was
+    /// at a point in time.
+    Branch(Branch),
+}
+
+impl SqliteCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            SqliteCommands::Execute(cmd) => cmd.run().await,
+            SqliteCommands::Schema(cmd) => cmd.run().await,
+            SqliteCommands::Branch(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Identifies a database of an application.
+#[derive(Args, Debug)]
+struct DatabaseOptions {
+    #[clap(flatten)]
+    app: ConfiguredAppOptions,
+
+    /// The label of the database to use.
+    #[clap(short = 'd', long = "database", default_value = DEFAULT_DATABASE_LABEL)]
+    database: String,
+}
+
+impl DatabaseOptions {
+    /// Connects to the database through the application's configured
+    /// backends.
+    async fn connect(&self) -> Result<Box<dyn Connection>> {
+        let configured_app = self.app.configure().await?;
+        let sqlite = configured_app.app_state::<SqliteFactor>()?;
+        sqlite
+            .get_connection(&self.database)
+            .await
+            .with_context(|| format!("No SQLite database '{}' is configured", self.database))?
+            .with_context(|| format!("Failed to connect to SQLite database '{}'", self.database))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Execute {
+    /// The SQL statement to run.
+    statement: String,
+
+    #[clap(flatten)]
+    database: DatabaseOptions,
+}
+
+impl Execute {
+    pub async fn run(self) -> Result<()> {
+        let connection = self.database.connect().await?;
+        let result = connection
+            .query(&self.statement, vec![])
+            .await
+            .context("Failed to execute statement")?;
+        if result.columns.is_empty() {
+            let changes = connection.changes().await.unwrap_or_default();
+            println!("{changes} row(s) changed");
+            return Ok(());
+        }
+
+        let mut table = Table::new();
+        table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        table.set_header(&result.columns);
+        for row in &result.rows {
+            table.add_row(row.values.iter().map(display_value));
+        }
+        println!("{table}");
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Schema {
+    #[clap(flatten)]
+    database: DatabaseOptions,
+}
+
+impl Schema {
+    pub async fn run(self) -> Result<()> {
+        let connection = self.database.connect().await?;
+        let result = connection
+            .query(SCHEMA_QUERY, vec![])
+            .await
+            .context("Failed to read schema")?;
+        for row in &result.rows {
+            if let Some(v4::Value::Text(sql)) = row.values.first() {
+                println!("{sql};");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Branch {
+    /// The name of the branch database to create.
+    name: String,
+
+    /// The Turso database to branch from.
+    #[clap(long = "from")]
+    from: String,
+
+    /// Branch from the contents the database had at this time (RFC 3339),
+    /// rather than its current contents.
+    #[clap(long = "at", parse(try_from_str = parse_timestamp))]
+    at: Option<DateTime<Utc>>,
+
+    /// The Turso organization the databases belong to.
+    #[clap(long = "organization", env = "TURSO_ORGANIZATION")]
+    organization: String,
+
+    /// A Turso Platform API token for the organization.
+    #[clap(long = "api-token", env = "TURSO_API_TOKEN", hide_env_values = true)]
+    api_token: String,
+}
+
+impl Branch {
+    pub async fn run(self) -> Result<()> {
+        let platform = TursoPlatform::new(self.organization, self.api_token);
+        let branch = platform
+            .create_branch(&self.from, &self.name, self.at)
+            .await?;
+        println!(
+            "Created database '{}' as a branch of '{}' at {}",
+            branch.name, self.from, branch.url
+        );
+        println!();
+        println!("To use it in place of a database, add to your runtime config file:");
+        println!();
+        println!("[sqlite_database.{}]", branch.name);
+        println!("type = \"libsql\"");
+        println!("url = \"{}\"", branch.url);
+        println!("token = \"<a token for the database's group>\"");
+        println!();
+        println!("[component_stores.<component-id>]");
+        println!("sqlite_database = {{ <label> = \"{}\" }}", branch.name);
+        Ok(())
+    }
+}
+
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    let timestamp = DateTime::parse_from_rfc3339(s)
+        .with_context(|| format!("'{s}' is not an RFC 3339 timestamp"))?;
+    Ok(timestamp.with_timezone(&Utc))
+}
+
+fn display_value(value: &v4::Value) -> String {
+    match value {
+        v4::Value::Integer(i) => i.to_string(),
+        v4::Value::Real(r) => r.to_string(),
+        v4::Value::Text(t) => t.clone(),
+        v4::Value::Blob(b) => format!("<{} byte blob>", b.len()),
+        v4::Value::Null => "NULL".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(subcommand)]
+        sqlite: SqliteCommands,
+    }
+
+    #[test]
+    fn sqlite_args_are_parsed() {
+        let cli = Cli::try_parse_from([
+            "sqlite",
+            "execute",
+            "SELECT * FROM todos",
+            "-d",
+            "todos",
+            "--state-dir",
+            "",
+        ])
+        .unwrap();
+        let SqliteCommands::Execute(execute) = cli.sqlite else {
+            panic!("expected execute");
+        };
+        assert_eq!(execute.statement, "SELECT * FROM todos");
+        assert_eq!(execute.database.database, "todos");
+        assert_eq!(execute.database.app.state_dir.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn branch_args_are_parsed() {
+        let cli = Cli::try_parse_from([
+            "sqlite",
+            "branch",
+            "prod-staging",
+            "--from",
+            "prod",
+            "--at",
+            "2024-06-01T12:00:00+02:00",
+            "--organization",
+            "acme",
+            "--api-token",
+            "secret",
+        ])
+        .unwrap();
+        let SqliteCommands::Branch(branch) = cli.sqlite else {
+            panic!("expected branch");
+        };
+        assert_eq!(branch.name, "prod-staging");
+        assert_eq!(branch.from, "prod");
+        assert_eq!(branch.at.unwrap().to_rfc3339(), "2024-06-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn values_are_displayed() {
+        assert_eq!(display_value(&v4::Value::Integer(3)), "3");
+        assert_eq!(display_value(&v4::Value::Null), "NULL");
+        assert_eq!(display_value(&v4::Value::Blob(vec![1, 2])), "<2 byte blob>");
+    }
+}
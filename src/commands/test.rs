@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use spin_app::App;
+use spin_common::ui::quoted_path;
+use spin_loader::FilesMountStrategy;
+use spin_runtime_factors::{FactorsBuilder, TriggerAppArgs, TriggerFactors};
+use spin_test_runner::{TestOutcome, TestRunner, TestSuite};
+use spin_trigger::{
+    cli::{FactorsConfig, FollowComponents, LogFormat, TriggerAppBuilder, UserProvidedPath},
+    loader::ComponentLoader,
+};
+use spin_trigger_http::HttpTrigger;
+use tempfile::TempDir;
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+/// The default test suite file, relative to the manifest.
+const DEFAULT_TESTS_FILE: &str = "spin-test.toml";
+
+/// Run an application's tests against in-memory services.
+#[derive(Parser, Debug)]
+#[clap(about = "Run the Spin application's tests against in-memory services")]
+pub struct TestCommand {
+    /// The application to test. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// The test suite file. If omitted, it defaults to "spin-test.toml" next
+    /// to the manifest.
+    #[clap(short = 't', long = "tests")]
+    pub tests: Option<PathBuf>,
+
+    /// Only run tests whose names contain this text.
+    pub filter: Option<String>,
+}
+
+impl TestCommand {
+    pub async fn run(self) -> Result<()> {
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+        let app_dir = manifest_file
+            .parent()
+            .context("manifest path has no parent directory")?
+            .to_owned();
+
+        let tests_file = self
+            .tests
+            .clone()
+            .unwrap_or_else(|| app_dir.join(DEFAULT_TESTS_FILE));
+        let mut suite = TestSuite::from_file(&tests_file)?;
+        if let Some(filter) = &self.filter {
+            suite
+                .tests
+                .retain(|test| test.name.contains(filter.as_str()));
+        }
+        if suite.tests.is_empty() {
+            bail!("No tests to run in {}", quoted_path(&tests_file));
+        }
+
+        let working_dir = TempDir::with_prefix("spintest-")?;
+        let runner = load_app(&manifest_file, &app_dir, &suite, working_dir.path())
+            .await
+            .with_context(|| {
+                format!("Failed to load application {}", quoted_path(&manifest_file))
+            })?;
+
+        println!("\nrunning {} tests", suite.tests.len());
+        let results = runner
+            .run_all(&suite, |result| {
+                let status = match result.outcome {
+                    TestOutcome::Passed => "ok",
+                    TestOutcome::Failed(_) => "FAILED",
+                };
+                println!(
+                    "test {} ... {status} ({}ms)",
+                    result.name,
+                    result.duration.as_millis()
+                );
+            })
+            .await;
+
+        let failures = results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                TestOutcome::Passed => None,
+                TestOutcome::Failed(reason) => Some((&result.name, reason)),
+            })
+            .collect::<Vec<_>>();
+        if !failures.is_empty() {
+            println!("\nfailures:");
+            for (name, reason) in &failures {
+                println!("    {name}: {reason}");
+            }
+        }
+        println!(
+            "\ntest result: {}. {} passed; {} failed\n",
+            if failures.is_empty() { "ok" } else { "FAILED" },
+            results.len() - failures.len(),
+            failures.len()
+        );
+
+        if !failures.is_empty() {
+            bail!("{} of {} tests failed", failures.len(), results.len());
+        }
+        Ok(())
+    }
+}
+
+/// Loads the application with in-memory services and the suite's variables.
+async fn load_app(
+    manifest_file: &Path,
+    app_dir: &Path,
+    suite: &TestSuite,
+    working_dir: &Path,
+) -> Result<TestRunner<TriggerFactors>> {
+    let mut locked =
+        spin_loader::from_file(manifest_file, FilesMountStrategy::Direct, None).await?;
+    spin_loader::sync_remote_files(&mut locked, None).await?;
+    let app = App::new(manifest_file.display().to_string(), locked);
+
+    let runtime_config_file = working_dir.join("runtime-config.toml");
+    let runtime_config = spin_test_runner::runtime_config(&app, suite)?;
+    std::fs::write(&runtime_config_file, toml::to_string(&runtime_config)?)
+        .with_context(|| format!("failed to write {}", quoted_path(&runtime_config_file)))?;
+
+    let trigger = HttpTrigger::new(&app, ([127, 0, 0, 1], 0).into(), None)?;
+    let mut builder: TriggerAppBuilder<_, FactorsBuilder> = TriggerAppBuilder::new(trigger);
+    let common_options = FactorsConfig {
+        working_dir: working_dir.to_owned(),
+        runtime_config_file: Some(runtime_config_file),
+        // Without a state directory, the default stores are in-memory
+        state_dir: UserProvidedPath::Unset,
+        local_app_dir: Some(app_dir.display().to_string()),
+        follow_components: FollowComponents::All,
+        log_dir: UserProvidedPath::Unset,
+        log_format: LogFormat::default(),
+        log_rotation: None,
+        check_only: false,
+    };
+    let trigger_app = builder
+        .build(
+            app,
+            common_options,
+            TriggerAppArgs::default(),
+            &ComponentLoader::new(),
+        )
+        .await?;
+    let server = builder.trigger.into_server(trigger_app)?;
+    Ok(TestRunner::new(server))
+}
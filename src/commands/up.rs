@@ -1,9 +1,13 @@
 mod app_source;
+mod dashboard;
+mod permissions;
+mod services;
 
 use std::{
     collections::{HashMap, HashSet},
     ffi::OsString,
     fmt::Debug,
+    net::{Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
     process::Stdio,
 };
@@ -16,12 +20,22 @@ use spin_common::ui::quoted_path;
 use spin_factor_outbound_networking::validate_service_chaining_for_components;
 use spin_loader::FilesMountStrategy;
 use spin_oci::OciLoader;
-use spin_trigger::cli::{LaunchMetadata, SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR};
+use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_factors::TriggerFactorsRuntimeConfig;
+use spin_trigger::cli::{
+    LaunchMetadata, ReloadSignals, UserProvidedPath, RUNTIME_CONFIG_FILE, SPIN_LOCAL_APP_DIR,
+    SPIN_LOCKED_URL, SPIN_WORKING_DIR,
+};
 use tempfile::TempDir;
 
-use crate::{directory_rels::notify_if_nondefault_rel, opts::*};
+use crate::{commands::registry::VerifyOptions, directory_rels::notify_if_nondefault_rel, opts::*};
 
-use self::app_source::{AppSource, ResolvedAppSource};
+use self::{
+    app_source::{AppSource, ResolvedAppSource},
+    dashboard::Dashboard,
+    permissions::{PermissionsFormat, PermissionsReport},
+    services::RunningServices,
+};
 
 const APPLICATION_OPT: &str = "APPLICATION";
 
@@ -36,6 +50,13 @@ const MULTI_TRIGGER_START_OFFSET: tokio::time::Duration = tokio::time::Duration:
 // any exited" check.
 const MULTI_TRIGGER_LET_ALL_START: tokio::time::Duration = tokio::time::Duration::from_millis(500);
 
+/// The address the dashboard is served on unless `--dashboard-listen` is set.
+const DEFAULT_DASHBOARD_LISTEN: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 3031);
+
+/// Set for triggers to record invocations for the dashboard.
+const SPIN_RECORD_DIR: &str = "SPIN_RECORD_DIR";
+
 /// Start the Fermyon runtime.
 #[derive(Parser, Debug, Default)]
 #[clap(
@@ -79,6 +100,12 @@ pub struct UpCommand {
     )]
     pub registry_source: Option<String>,
 
+    /// Run an application bundle created with `spin registry save`, or a
+    /// directory one was unpacked into with `spin registry load`. Bundles
+    /// contain all of the application's content, so need no registry access.
+    #[clap(long = "from-bundle", group = "source")]
+    pub bundle_source: Option<PathBuf>,
+
     /// Ignore server certificate errors from a registry
     #[clap(
         name = INSECURE_OPT,
@@ -88,6 +115,9 @@ pub struct UpCommand {
     )]
     pub insecure: bool,
 
+    #[clap(flatten)]
+    pub verify: VerifyOptions,
+
     /// Pass an environment variable (key=value) to all components of the application.
     #[clap(short = 'e', long = "env", parse(try_from_str = parse_env_var))]
     pub env: Vec<(String, String)>,
@@ -114,6 +144,43 @@ pub struct UpCommand {
     #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
     pub build: bool,
 
+    /// For local apps, require the application to match its lockfile
+    /// (spin-lock.toml), instead of warning if it does not.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
+    /// Apply the named `[profile.<name>]` from the manifest, overriding
+    /// variables, allowed hosts and the runtime config file for that
+    /// environment.
+    #[clap(name = ENV_PROFILE_OPT, long = "env-profile", env = ENV_PROFILE_ENV)]
+    pub env_profile: Option<String>,
+
+    /// Start the companion services (such as databases) declared in this
+    /// file before the application, setting the application variables they
+    /// provide, and stop them when the application exits.
+    #[clap(long = "services")]
+    pub services: Option<PathBuf>,
+
+    /// Print the capabilities granted to each component, such as the hosts
+    /// it may connect to and the stores it may use, instead of running the
+    /// application.
+    #[clap(long = "print-permissions", takes_value = false)]
+    pub print_permissions: bool,
+
+    /// The format of the report printed by `--print-permissions`.
+    #[clap(long = "permissions-format", value_enum, default_value = "table")]
+    pub permissions_format: PermissionsFormat,
+
+    /// Serve a local web dashboard showing the application's routes, recent
+    /// invocations with the output they produced, live logs, and the
+    /// contents of its key-value stores and SQLite databases.
+    #[clap(long = "dashboard", takes_value = false)]
+    pub dashboard: bool,
+
+    /// The address to serve the dashboard on. Defaults to 127.0.0.1:3031.
+    #[clap(long = "dashboard-listen")]
+    pub dashboard_listen: Option<SocketAddr>,
+
     /// [Experimental] Component ID to run. This can be specified multiple times. The default is all components.
     #[clap(short = 'c', long = "component-id")]
     pub components: Vec<String>,
@@ -190,28 +257,23 @@ impl UpCommand {
             return Ok(());
         }
 
+        let runtime_config_file = match &self.env_profile {
+            Some(profile) => resolved_app_source.profile_runtime_config(profile)?,
+            None => None,
+        };
+
         if self.build {
-            app_source.build().await?;
+            app_source.build(self.locked).await?;
+        } else {
+            app_source.check_lockfile(self.locked)?;
         }
         let mut locked_app = self
-            .load_resolved_app_source(resolved_app_source, &working_dir)
-            .await
-            .context("Failed to load application")?;
-
-        if !self.components.is_empty() {
-            locked_app = spin_app::retain_components(
-                locked_app,
-                &self
-                    .components
-                    .iter()
-                    .map(|s| s.as_str())
-                    .collect::<Vec<&str>>(),
-                &[&validate_service_chaining_for_components],
-            )
-            .context(
-                "failed to resolve application with only components selected with --component",
-            )?;
+            .load_locked_app(resolved_app_source, &working_dir)
+            .await?;
+        if self.print_permissions {
+            return PermissionsReport::new(&locked_app)?.print(self.permissions_format);
         }
+        let app_trigger_types = trigger_types(&locked_app);
 
         let trigger_types: HashSet<&str> = locked_app
             .triggers
@@ -230,12 +292,31 @@ impl UpCommand {
 
         let local_app_dir = app_source.local_app_dir().map(Into::into);
 
-        let run_opts = RunTriggerOpts {
+        // Held until the app exits, at which point the services are stopped.
+        let services = match &self.services {
+            Some(path) => RunningServices::start(path).await?,
+            None => RunningServices::default(),
+        };
+
+        let mut run_opts = RunTriggerOpts {
             locked_url,
-            working_dir,
+            working_dir: working_dir.clone(),
             local_app_dir,
+            runtime_config_file,
+            variables: services.variables().to_vec(),
+            record_dir: None,
         };
 
+        if self.dashboard {
+            let dashboard = self.dashboard(locked_app, &run_opts).await?;
+            run_opts.record_dir = Some(dashboard.recordings_dir().to_owned());
+            dashboard
+                .serve(self.dashboard_listen.unwrap_or(DEFAULT_DASHBOARD_LISTEN))
+                .await?;
+        }
+
+        // With `--check`, each trigger exits once it has checked the app
+        let check_only = self.trigger_args.iter().any(|arg| arg == "--check");
         let trigger_processes = self.start_trigger_processes(trigger_cmds, run_opts).await?;
         let pids = get_pids(&trigger_processes);
 
@@ -246,11 +327,30 @@ impl UpCommand {
             .map(|mut ch| tokio::task::spawn(async move { ch.wait().await }))
             .collect::<Vec<_>>();
 
+        if check_only {
+            return wait_for_checks(trigger_tasks).await;
+        }
+
         if is_multi {
             tokio::time::sleep(MULTI_TRIGGER_LET_ALL_START).await;
         }
 
-        let (first_to_finish, _index, _rest) = futures::future::select_all(trigger_tasks).await;
+        let mut reload_signals = ReloadSignals::new()?;
+        let mut trigger_tasks = futures::future::select_all(trigger_tasks);
+        let first_to_finish = loop {
+            tokio::select! {
+                (first_to_finish, _index, _rest) = &mut trigger_tasks => break first_to_finish,
+                true = reload_signals.recv() => {
+                    match self.reload_locked_app(&app_source, &working_dir, &app_trigger_types).await {
+                        Ok(()) => reload_child_processes(&pids),
+                        Err(err) => {
+                            terminal::error!("Failed to reload application: {err:#}");
+                            terminal::einfo!("Continuing with", "the previously loaded application.");
+                        }
+                    }
+                }
+            }
+        };
 
         if let Ok(process_result) = first_to_finish {
             let status = process_result?;
@@ -266,6 +366,61 @@ impl UpCommand {
         Ok(())
     }
 
+    /// Creates a dashboard for the app, which reads the same runtime config,
+    /// logs and recordings as its triggers.
+    async fn dashboard(
+        &self,
+        locked_app: LockedApp,
+        run_opts: &RunTriggerOpts,
+    ) -> Result<Dashboard> {
+        let user_path = |value: Option<String>| match value {
+            Some(path) if path.is_empty() => UserProvidedPath::Unset,
+            Some(path) => UserProvidedPath::Provided(PathBuf::from(path)),
+            None => UserProvidedPath::Default,
+        };
+        let runtime_config_file = self
+            .trigger_arg("--runtime-config-file")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os(RUNTIME_CONFIG_FILE).map(PathBuf::from))
+            .or_else(|| run_opts.runtime_config_file.clone());
+        let state_dir = user_path(self.trigger_arg("--state-dir"));
+        let log_dir = user_path(
+            self.trigger_arg("--log-dir")
+                .or_else(|| std::env::var("SPIN_LOG_DIR").ok()),
+        );
+        let record_dir = self
+            .trigger_arg("--record-dir")
+            .or_else(|| std::env::var(SPIN_RECORD_DIR).ok())
+            .map(PathBuf::from);
+
+        let app_dir = run_opts
+            .local_app_dir
+            .clone()
+            .unwrap_or_else(|| run_opts.working_dir.clone());
+        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file(
+            runtime_config_file.as_deref(),
+            run_opts.local_app_dir.clone(),
+            state_dir,
+            log_dir,
+        )
+        .await?;
+        Dashboard::new(locked_app, &app_dir, runtime_config, record_dir)
+    }
+
+    /// The value of an option passed through to the trigger, if it was given.
+    fn trigger_arg(&self, name: &str) -> Option<String> {
+        let mut args = self.trigger_args.iter().map(|arg| arg.to_string_lossy());
+        while let Some(arg) = args.next() {
+            if arg == name {
+                return args.next().map(|value| value.into_owned());
+            }
+            if let Some(value) = arg.strip_prefix(name).and_then(|v| v.strip_prefix('=')) {
+                return Some(value.to_owned());
+            }
+        }
+        None
+    }
+
     fn get_canonical_working_dir(&self) -> Result<WorkingDirectory, anyhow::Error> {
         let working_dir_holder = match &self.tmp {
             None => WorkingDirectory::Temporary(TempDir::with_prefix("spinup-")?),
@@ -370,6 +525,9 @@ impl UpCommand {
             locked_url,
             working_dir,
             local_app_dir,
+            runtime_config_file,
+            variables,
+            record_dir,
         }) = opts
         {
             cmd.env(SPIN_LOCKED_URL, locked_url)
@@ -380,6 +538,28 @@ impl UpCommand {
                 cmd.env(SPIN_LOCAL_APP_DIR, local_app_dir);
             }
 
+            // A profile's runtime config is only a default: an explicit
+            // `--runtime-config-file` or environment variable takes precedence.
+            if let Some(runtime_config_file) = runtime_config_file {
+                if std::env::var_os(RUNTIME_CONFIG_FILE).is_none() {
+                    cmd.env(RUNTIME_CONFIG_FILE, runtime_config_file);
+                }
+            }
+
+            // Likewise, variables provided by services don't override ones
+            // the user has set.
+            for (name, value) in variables {
+                let env_name = format!("SPIN_VARIABLE_{}", name.to_uppercase());
+                if std::env::var_os(&env_name).is_none() {
+                    cmd.env(env_name, value);
+                }
+            }
+
+            // An explicit `--record-dir` takes precedence over the environment.
+            if let Some(record_dir) = record_dir {
+                cmd.env(SPIN_RECORD_DIR, record_dir);
+            }
+
             cmd.kill_on_drop(true);
         } else {
             cmd.env("SPIN_PLUGINS_SUPPRESS_COMPATIBILITY_WARNINGS", "1");
@@ -393,11 +573,17 @@ impl UpCommand {
     }
 
     fn app_source(&self) -> AppSource {
-        match (&self.app_source, &self.file_source, &self.registry_source) {
-            (None, None, None) => self.default_manifest_or_none(),
-            (Some(source), None, None) => AppSource::infer_source(source),
-            (None, Some(file), None) => AppSource::infer_file_source(file.to_owned()),
-            (None, None, Some(reference)) => AppSource::OciRegistry(reference.to_owned()),
+        match (
+            &self.app_source,
+            &self.file_source,
+            &self.registry_source,
+            &self.bundle_source,
+        ) {
+            (None, None, None, None) => self.default_manifest_or_none(),
+            (Some(source), None, None, None) => AppSource::infer_source(source),
+            (None, Some(file), None, None) => AppSource::infer_file_source(file.to_owned()),
+            (None, None, Some(reference), None) => AppSource::OciRegistry(reference.to_owned()),
+            (None, None, None, Some(bundle)) => AppSource::Bundle(bundle.to_owned()),
             _ => AppSource::unresolvable("More than one application source was specified"),
         }
     }
@@ -466,6 +652,7 @@ impl UpCommand {
                 let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone())
                     .await
                     .context("cannot create registry client")?;
+                client.opts.verification_policy = self.verify.policy()?;
 
                 let locked_app = OciLoader::new(working_dir)
                     .load_app(&mut client, reference)
@@ -475,6 +662,12 @@ impl UpCommand {
             AppSource::BareWasm(path) => ResolvedAppSource::BareWasm {
                 wasm_path: path.clone(),
             },
+            AppSource::Bundle(path) => {
+                let locked_app = spin_oci::bundle::load(path, working_dir)
+                    .await
+                    .with_context(|| format!("Failed to load bundle {}", quoted_path(path)))?;
+                ResolvedAppSource::Bundle { locked_app }
+            }
             AppSource::Unresolvable(err) => bail!("{err}"),
             AppSource::None => bail!("Internal error - should have shown help"),
         })
@@ -493,16 +686,22 @@ impl UpCommand {
                 } else {
                     FilesMountStrategy::Copy(working_dir.join("assets"))
                 };
-                spin_loader::from_file(&manifest_path, files_mount_strategy, self.cache_dir.clone())
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "Failed to load manifest from {}",
-                            quoted_path(&manifest_path)
-                        )
-                    })
+                spin_loader::from_file_with_profile(
+                    &manifest_path,
+                    self.env_profile.as_deref(),
+                    files_mount_strategy,
+                    self.cache_dir.clone(),
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load manifest from {}",
+                        quoted_path(&manifest_path)
+                    )
+                })
             }
-            ResolvedAppSource::OciRegistry { locked_app } => Ok(locked_app),
+            ResolvedAppSource::OciRegistry { locked_app }
+            | ResolvedAppSource::Bundle { locked_app } => Ok(locked_app),
             ResolvedAppSource::BareWasm { wasm_path } => spin_loader::from_wasm_file(&wasm_path)
                 .await
                 .with_context(|| {
@@ -511,6 +710,64 @@ impl UpCommand {
         }
     }
 
+    /// Loads the application, keeping only the components selected with
+    /// `--component-id`.
+    async fn load_locked_app(
+        &self,
+        resolved: ResolvedAppSource,
+        working_dir: &Path,
+    ) -> anyhow::Result<LockedApp> {
+        let mut locked_app = self
+            .load_resolved_app_source(resolved, working_dir)
+            .await
+            .context("Failed to load application")?;
+
+        if !self.components.is_empty() {
+            locked_app = spin_app::retain_components(
+                locked_app,
+                &self
+                    .components
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<&str>>(),
+                &[&validate_service_chaining_for_components],
+            )
+            .context(
+                "failed to resolve application with only components selected with --component",
+            )?;
+        }
+
+        spin_loader::sync_remote_files(&mut locked_app, self.cache_dir.clone())
+            .await
+            .context("Failed to sync remote files")?;
+        Ok(locked_app)
+    }
+
+    /// Reloads the application and rewrites its lock file, ready for the
+    /// trigger processes to be told to reload it.
+    async fn reload_locked_app(
+        &self,
+        app_source: &AppSource,
+        working_dir: &Path,
+        app_trigger_types: &HashSet<String>,
+    ) -> anyhow::Result<()> {
+        terminal::step!("Reloading", "{app_source}");
+        let resolved_app_source = self.resolve_app_source(app_source, working_dir).await?;
+        if self.build {
+            app_source.build(self.locked).await?;
+        }
+        let mut locked_app = self
+            .load_locked_app(resolved_app_source, working_dir)
+            .await?;
+        ensure!(
+            trigger_types(&locked_app) == *app_trigger_types,
+            "the application's trigger types have changed; restart `spin up` to apply this change"
+        );
+        self.update_locked_app(&mut locked_app);
+        self.write_locked_app(&locked_app, working_dir).await?;
+        Ok(())
+    }
+
     fn update_locked_app(&self, locked_app: &mut LockedApp) {
         // Apply --env to component environments
         if !self.env.is_empty() {
@@ -550,6 +807,27 @@ impl UpCommand {
     }
 }
 
+/// Waits for every trigger to finish checking the app, failing if any
+/// trigger's checks failed.
+async fn wait_for_checks(
+    trigger_tasks: Vec<tokio::task::JoinHandle<std::io::Result<std::process::ExitStatus>>>,
+) -> Result<()> {
+    let mut failure = None;
+    for task in trigger_tasks {
+        let Ok(process_result) = task.await else {
+            continue;
+        };
+        let status = process_result?;
+        if !status.success() {
+            failure.get_or_insert(status);
+        }
+    }
+    match failure {
+        Some(status) => Err(crate::subprocess::ExitStatusError::new(status).into()),
+        None => Ok(()),
+    }
+}
+
 fn is_flag_arg(arg: &OsString) -> bool {
     if let Some(s) = arg.to_str() {
         s.starts_with('-')
@@ -600,20 +878,50 @@ fn kill_child_processes(pids: &[nix::unistd::Pid]) {
     }
 }
 
+#[cfg(windows)]
+fn reload_child_processes(_pids: &[usize]) {}
+
+#[cfg(not(windows))]
+fn reload_child_processes(pids: &[nix::unistd::Pid]) {
+    for pid in pids {
+        if let Err(err) = nix::sys::signal::kill(*pid, nix::sys::signal::SIGHUP) {
+            tracing::warn!(
+                "Failed to signal trigger handler process to reload: {:?}",
+                err
+            )
+        }
+    }
+}
+
+fn trigger_types(locked_app: &LockedApp) -> HashSet<String> {
+    locked_app
+        .triggers
+        .iter()
+        .map(|t| t.trigger_type.clone())
+        .collect()
+}
+
 #[derive(Clone)]
 struct RunTriggerOpts {
     locked_url: String,
     working_dir: PathBuf,
     local_app_dir: Option<PathBuf>,
+    /// The runtime config file selected by the environment profile, if any.
+    runtime_config_file: Option<PathBuf>,
+    /// Application variables provided by `--services`.
+    variables: Vec<(String, String)>,
+    /// Where triggers should record invocations for the dashboard, if it is
+    /// running.
+    record_dir: Option<PathBuf>,
 }
 
-enum WorkingDirectory {
+pub(crate) enum WorkingDirectory {
     Given(PathBuf),
     Temporary(TempDir),
 }
 
 impl WorkingDirectory {
-    fn path(&self) -> &Path {
+    pub(crate) fn path(&self) -> &Path {
         match self {
             Self::Given(p) => p,
             Self::Temporary(t) => t.path(),
@@ -630,22 +938,36 @@ fn parse_env_var(s: &str) -> Result<(String, String)> {
     Ok((parts[0].to_owned(), parts[1].to_owned()))
 }
 
-fn resolve_trigger_plugin(trigger_type: &str) -> Result<String> {
+/// Resolves the command to run a trigger type that isn't built in.
+///
+/// A `trigger-<type>` plugin is a complete Spin runtime for the trigger type
+/// and is run as a subcommand. A `trigger-executor-<type>` plugin only
+/// delivers events, over the protocol in `spin_trigger_external::protocol`,
+/// and is driven by the built-in external trigger.
+fn resolve_trigger_plugin(trigger_type: &str) -> Result<Vec<String>> {
     use crate::commands::plugins::PluginCompatibility;
     use spin_plugins::manager::PluginManager;
 
     let subcommand = format!("trigger-{trigger_type}");
+    let executor = format!("trigger-executor-{trigger_type}");
     let plugin_manager = PluginManager::try_default()
         .with_context(|| format!("Failed to access plugins looking for '{subcommand}'"))?;
     let plugin_store = plugin_manager.store();
-    let is_installed = plugin_store
-        .installed_manifests()
-        .unwrap_or_default()
-        .iter()
-        .any(|m| m.name() == subcommand);
+    let installed = plugin_store.installed_manifests().unwrap_or_default();
 
-    if is_installed {
-        return Ok(subcommand);
+    if installed.iter().any(|m| m.name() == subcommand) {
+        return Ok(vec![subcommand]);
+    }
+    if installed.iter().any(|m| m.name() == executor) {
+        let executor_path = plugin_store.installed_binary_path(&executor);
+        let mut cmd = trigger_command("external");
+        cmd.extend([
+            "--external-trigger-type".to_owned(),
+            trigger_type.to_owned(),
+            "--external-trigger-executor".to_owned(),
+            executor_path.display().to_string(),
+        ]);
+        return Ok(cmd);
     }
 
     if let Some(known) = plugin_store
@@ -671,11 +993,10 @@ fn trigger_commands_for_trigger_types(trigger_types: Vec<&str>) -> Result<Vec<Ve
     trigger_types
         .iter()
         .map(|&t| match t {
-            "http" | "redis" => Ok(trigger_command(t)),
-            _ => {
-                let cmd = resolve_trigger_plugin(t)?;
-                Ok(vec![cmd])
+            "http" | "redis" | "postgres" | "timer" | "workflow" | "messaging" => {
+                Ok(trigger_command(t))
             }
+            _ => resolve_trigger_plugin(t),
         })
         .collect()
 }
@@ -787,6 +1108,19 @@ mod test {
         assert_eq!(AppSource::OciRegistry(reference), source);
     }
 
+    #[test]
+    fn bundle_source_is_not_inferred() {
+        let bundle = PathBuf::from("app.tar.gz");
+
+        let source = UpCommand {
+            bundle_source: Some(bundle.clone()),
+            ..Default::default()
+        }
+        .app_source();
+
+        assert_eq!(AppSource::Bundle(bundle), source);
+    }
+
     #[test]
     fn can_reject_complete_gibberish() {
         let garbage = repo_path("ftp://🤡***🤡 HELLO MR CLOWN?!");
@@ -916,4 +1250,19 @@ mod test {
         assert_eq!("-L", groups[2][0]);
         assert_eq!("/fie", groups[2][1]);
     }
+
+    #[test]
+    fn can_find_trigger_arg_values() {
+        let cmd = UpCommand::try_parse_from([
+            "up",
+            "--state-dir",
+            "/state",
+            "--log-dir=/logs",
+            "--quiet",
+        ])
+        .unwrap();
+        assert_eq!(Some("/state".to_owned()), cmd.trigger_arg("--state-dir"));
+        assert_eq!(Some("/logs".to_owned()), cmd.trigger_arg("--log-dir"));
+        assert_eq!(None, cmd.trigger_arg("--runtime-config-file"));
+    }
 }
@@ -13,6 +13,7 @@ pub enum AppSource {
     File(PathBuf),
     OciRegistry(String),
     BareWasm(PathBuf),
+    Bundle(PathBuf),
     Unresolvable(String),
     None,
 }
@@ -56,9 +57,22 @@ impl AppSource {
         }
     }
 
-    pub async fn build(&self) -> anyhow::Result<()> {
+    /// Builds a local application and updates its lockfile, or with
+    /// `locked`, checks it matches its lockfile.
+    pub async fn build(&self, locked: bool) -> anyhow::Result<()> {
         match self {
-            Self::File(path) => spin_build::build(path, &[]).await,
+            Self::File(path) => {
+                spin_build::build(path, &[]).await?;
+                crate::commands::build::update_lockfile(path, locked)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a local application against its lockfile, if it has one.
+    pub fn check_lockfile(&self, locked: bool) -> anyhow::Result<()> {
+        match self {
+            Self::File(path) => crate::commands::build::check_lockfile(path, locked),
             _ => Ok(()),
         }
     }
@@ -75,6 +89,7 @@ impl std::fmt::Display for AppSource {
             Self::File(path) => write!(f, "local app {}", quoted_path(path)),
             Self::OciRegistry(reference) => write!(f, "remote app {reference:?}"),
             Self::BareWasm(path) => write!(f, "Wasm file {}", quoted_path(path)),
+            Self::Bundle(path) => write!(f, "app bundle {}", quoted_path(path)),
             Self::Unresolvable(s) => write!(f, "unknown app source: {s:?}"),
             Self::None => write!(f, "<no source>"),
         }
@@ -96,6 +111,9 @@ pub enum ResolvedAppSource {
     OciRegistry {
         locked_app: LockedApp,
     },
+    Bundle {
+        locked_app: LockedApp,
+    },
 }
 
 impl ResolvedAppSource {
@@ -106,7 +124,8 @@ impl ResolvedAppSource {
                 .keys()
                 .map(|s| s.as_str())
                 .collect::<HashSet<_>>(),
-            ResolvedAppSource::OciRegistry { locked_app } => locked_app
+            ResolvedAppSource::OciRegistry { locked_app }
+            | ResolvedAppSource::Bundle { locked_app } => locked_app
                 .triggers
                 .iter()
                 .map(|t| t.trigger_type.as_str())
@@ -116,4 +135,26 @@ impl ResolvedAppSource {
 
         types.into_iter().collect()
     }
+
+    /// Returns the runtime config file selected by the given profile, if it
+    /// selects one. Relative paths are resolved against the manifest's
+    /// directory.
+    pub fn profile_runtime_config(&self, profile: &str) -> anyhow::Result<Option<PathBuf>> {
+        let ResolvedAppSource::File {
+            manifest_path,
+            manifest,
+        } = self
+        else {
+            anyhow::bail!("profiles can only be used with applications loaded from a manifest");
+        };
+        let Some(profile) = manifest.profiles.get(profile) else {
+            // The loader reports unknown profiles.
+            return Ok(None);
+        };
+        let app_dir = spin_common::paths::parent_dir(manifest_path)?;
+        Ok(profile
+            .runtime_config
+            .as_ref()
+            .map(|path| app_dir.join(path)))
+    }
 }
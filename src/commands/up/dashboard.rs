@@ -0,0 +1,544 @@
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{HeaderName, CONTENT_TYPE, HOST, ORIGIN},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use spin_app::{locked::LockedApp, App};
+use spin_common::ui::quoted_path;
+use spin_factor_key_value::{KeyValueFactor, KEY_VALUE_STORES_KEY};
+use spin_factor_sqlite::{SqliteFactor, ALLOWED_DATABASES_KEY};
+use spin_factors::ConfiguredApp;
+use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_factors::{TriggerFactors, TriggerFactorsRuntimeConfig};
+use spin_trigger::recording::{RecordedPayload, Recording};
+use spin_world::spin::sqlite4_0_0::sqlite as v4;
+use tokio::net::TcpListener;
+
+use crate::{
+    commands::logs::{read_history, LogEntry, LogRecord},
+    configured_app::configure_app,
+};
+
+/// The dashboard's single page, which fetches everything else from the API.
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// The most recent invocations listed.
+const MAX_INVOCATIONS: usize = 50;
+/// The most log lines returned at once.
+const MAX_LOG_LINES: usize = 500;
+/// The most keys of a store listed.
+const MAX_KEYS: usize = 200;
+/// Longer values are truncated.
+const MAX_VALUE_LEN: usize = 1024;
+/// The most rows of each table listed.
+const MAX_ROWS: usize = 50;
+/// How long after an invocation starts its component's output is attributed
+/// to it, unless the component is invoked again sooner.
+const INVOCATION_OUTPUT_SECS: i64 = 30;
+
+/// Lists the user's tables.
+const TABLES_QUERY: &str =
+    "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name";
+
+/// A local web dashboard for an application run with `spin up --dashboard`.
+pub(super) struct Dashboard {
+    app: App,
+    /// The app configured with its runtime config, for access to its stores
+    /// and databases, if that succeeded.
+    configured_app: Option<ConfiguredApp<TriggerFactors>>,
+    log_dir: Option<PathBuf>,
+    recordings_dir: PathBuf,
+}
+
+impl Dashboard {
+    /// Creates a dashboard for the app. Invocations are read from
+    /// `record_dir`, if the user is recording them, or otherwise from a
+    /// directory owned by the dashboard, which is cleared.
+    pub(super) fn new(
+        locked_app: LockedApp,
+        app_dir: &Path,
+        runtime_config: ResolvedRuntimeConfig<TriggerFactorsRuntimeConfig>,
+        record_dir: Option<PathBuf>,
+    ) -> Result<Self> {
+        let log_dir = runtime_config.log_dir();
+        let recordings_dir = match record_dir {
+            Some(record_dir) => record_dir,
+            None => {
+                let dir = runtime_config
+                    .state_dir()
+                    .unwrap_or_else(|| app_dir.to_owned())
+                    .join("dashboard")
+                    .join("recordings");
+                if dir.exists() {
+                    std::fs::remove_dir_all(&dir).with_context(|| {
+                        format!("Failed to clear dashboard recordings {}", quoted_path(&dir))
+                    })?;
+                }
+                dir
+            }
+        };
+
+        let app = App::new("dashboard", locked_app);
+        let configured_app = match configure_app(app.clone(), app_dir, runtime_config) {
+            Ok(configured_app) => Some(configured_app),
+            Err(e) => {
+                terminal::warn!("The dashboard can't show the application's stores: {e:#}");
+                None
+            }
+        };
+        Ok(Self {
+            app,
+            configured_app,
+            log_dir,
+            recordings_dir,
+        })
+    }
+
+    /// The directory triggers should record invocations to.
+    pub(super) fn recordings_dir(&self) -> &Path {
+        &self.recordings_dir
+    }
+
+    /// Serves the dashboard on `listen_addr`.
+    ///
+    /// Binds the listener before returning, so that a bad address is
+    /// reported at startup; connections are then served in the background.
+    pub(super) async fn serve(self, listen_addr: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(listen_addr)
+            .await
+            .with_context(|| format!("Unable to listen for the dashboard on {listen_addr}"))?;
+        let listen_addr = listener.local_addr().unwrap_or(listen_addr);
+        terminal::step!("Dashboard", "available at http://{listen_addr}");
+
+        // Only requests addressed to the dashboard itself are served, so that
+        // other sites can't read the app's stores through the user's browser
+        // (including by DNS rebinding)
+        let hosts: Arc<[String]> = Arc::from([
+            listen_addr.to_string(),
+            format!("localhost:{}", listen_addr.port()),
+        ]);
+        let dashboard = Arc::new(self);
+        tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        tracing::error!("Failed to accept dashboard connection: {err}");
+                        continue;
+                    }
+                };
+                let dashboard = dashboard.clone();
+                let hosts = hosts.clone();
+                let service = service_fn(move |req| {
+                    let dashboard = dashboard.clone();
+                    let hosts = hosts.clone();
+                    async move {
+                        if !is_same_origin(&req, &hosts) {
+                            return Ok(json_response(StatusCode::FORBIDDEN, json!({})));
+                        }
+                        Ok::<_, Infallible>(dashboard.handle(req).await)
+                    }
+                });
+                tokio::spawn(async move {
+                    if let Err(err) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        tracing::warn!("Error serving dashboard connection: {err:?}");
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    async fn handle(&self, req: Request<Incoming>) -> Response<Full<Bytes>> {
+        if req.method() != Method::GET {
+            return json_response(StatusCode::METHOD_NOT_ALLOWED, json!({}));
+        }
+        let path = req.uri().path();
+        let query: Vec<(String, String)> =
+            url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+                .into_owned()
+                .collect();
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        let result = match segments.as_slice() {
+            [""] => {
+                return Response::builder()
+                    .header(CONTENT_TYPE, "text/html; charset=utf-8")
+                    .body(Full::new(Bytes::from_static(DASHBOARD_HTML.as_bytes())))
+                    .unwrap()
+            }
+            ["api", "app"] => Ok(self.app_info()),
+            ["api", "invocations"] => self.invocations(),
+            ["api", "logs"] => self.logs(param("since")),
+            ["api", "kv"] => Ok(json!(self.labels(KEY_VALUE_STORES_KEY))),
+            ["api", "kv", label] => self.kv_store(label).await,
+            ["api", "sqlite"] => Ok(json!(self.labels(ALLOWED_DATABASES_KEY))),
+            ["api", "sqlite", label] => self.sqlite_database(label).await,
+            _ => return json_response(StatusCode::NOT_FOUND, json!({})),
+        };
+        match result {
+            Ok(body) => json_response(StatusCode::OK, body),
+            Err(e) => json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": format!("{e:#}") }),
+            ),
+        }
+    }
+
+    /// The app's triggers, including the routes of HTTP triggers.
+    fn app_info(&self) -> Value {
+        let triggers: Vec<_> = self
+            .app
+            .triggers()
+            .map(|trigger| {
+                let config = trigger.typed_config::<Value>().unwrap_or_default();
+                json!({
+                    "id": trigger.id(),
+                    "type": trigger.trigger_type(),
+                    "component": config.get("component"),
+                    "route": config.get("route"),
+                })
+            })
+            .collect();
+        let components: Vec<_> = self.app.components().map(|c| c.id().to_owned()).collect();
+        json!({ "triggers": triggers, "components": components })
+    }
+
+    /// The most recent invocations, newest first, with the output their
+    /// components wrote while handling them.
+    fn invocations(&self) -> Result<Value> {
+        let mut files = match std::fs::read_dir(&self.recordings_dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).context("Failed to read recordings"),
+        };
+        // Recordings are named by the time they were made.
+        files.sort();
+        files.reverse();
+        files.truncate(MAX_INVOCATIONS);
+
+        let mut invocations = vec![];
+        for file in files {
+            let recording = Recording::from_file(&file)?;
+            let Ok(recorded_at) = DateTime::parse_from_rfc3339(&recording.recorded_at) else {
+                continue;
+            };
+            invocations.push((recorded_at, recording));
+        }
+
+        let history = match &self.log_dir {
+            Some(log_dir) => read_history(log_dir)?,
+            None => vec![],
+        };
+        let invocations: Vec<_> = invocations
+            .iter()
+            .enumerate()
+            .map(|(index, (recorded_at, recording))| {
+                // Invocations are newest first, so the next invocation of the
+                // same component comes earlier in the list.
+                let next = invocations[..index]
+                    .iter()
+                    .rev()
+                    .find(|(_, r)| r.component_id == recording.component_id)
+                    .map(|(at, _)| *at);
+                let output = invocation_output(&history, recording, *recorded_at, next);
+                json!({
+                    "recorded_at": recording.recorded_at,
+                    "trigger_type": recording.trigger_type,
+                    "component": recording.component_id,
+                    "summary": summary(&recording.payload),
+                    "output": output,
+                })
+            })
+            .collect();
+        Ok(json!(invocations))
+    }
+
+    /// The most recent log lines, or those written after `since`.
+    fn logs(&self, since: Option<&str>) -> Result<Value> {
+        let Some(log_dir) = &self.log_dir else {
+            return Ok(json!([]));
+        };
+        let since = since
+            .map(DateTime::parse_from_rfc3339)
+            .transpose()
+            .context("Invalid 'since' timestamp")?;
+        let mut entries = read_history(log_dir)?;
+        if let Some(since) = since {
+            entries.retain(|e| e.timestamp > since);
+        }
+        entries.drain(..entries.len().saturating_sub(MAX_LOG_LINES));
+        let records: Vec<_> = entries.iter().map(|e| &e.record).collect();
+        Ok(json!(records))
+    }
+
+    /// The labels of the stores or databases components may use.
+    fn labels(&self, key: spin_locked_app::MetadataKey<Vec<String>>) -> Vec<String> {
+        let labels: BTreeSet<_> = self
+            .app
+            .components()
+            .flat_map(|c| c.get_metadata(key).ok().flatten().unwrap_or_default())
+            .collect();
+        labels.into_iter().collect()
+    }
+
+    fn configured_app(&self) -> Result<&ConfiguredApp<TriggerFactors>> {
+        self.configured_app
+            .as_ref()
+            .context("The application's stores couldn't be configured")
+    }
+
+    async fn kv_store(&self, label: &str) -> Result<Value> {
+        let store = self
+            .configured_app()?
+            .app_state::<KeyValueFactor>()?
+            .store_manager()
+            .get(label)
+            .await
+            .with_context(|| format!("Failed to open key-value store '{label}'"))?;
+        let mut keys = store.get_keys().await?;
+        keys.sort();
+        let truncated = keys.len() > MAX_KEYS;
+        keys.truncate(MAX_KEYS);
+        let entries: Vec<_> = store
+            .get_many(keys)
+            .await?
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value.map(|v| truncate(&String::from_utf8_lossy(&v)));
+                json!({ "key": key, "value": value })
+            })
+            .collect();
+        Ok(json!({ "entries": entries, "truncated": truncated }))
+    }
+
+    async fn sqlite_database(&self, label: &str) -> Result<Value> {
+        let connection = self
+            .configured_app()?
+            .app_state::<SqliteFactor>()?
+            .get_connection(label)
+            .await
+            .with_context(|| format!("No SQLite database '{label}' is configured"))?
+            .with_context(|| format!("Failed to connect to SQLite database '{label}'"))?;
+        let tables = connection.query(TABLES_QUERY, vec![]).await?;
+        let mut result = vec![];
+        for row in &tables.rows {
+            let Some(v4::Value::Text(table)) = row.values.first() else {
+                continue;
+            };
+            let query = format!(
+                "SELECT * FROM \"{}\" LIMIT {MAX_ROWS}",
+                table.replace('"', "\"\"")
+            );
+            let contents = connection.query(&query, vec![]).await?;
+            let rows: Vec<Vec<Value>> = contents
+                .rows
+                .iter()
+                .map(|row| row.values.iter().map(json_value).collect())
+                .collect();
+            result.push(json!({ "name": table, "columns": contents.columns, "rows": rows }));
+        }
+        Ok(json!(result))
+    }
+}
+
+/// The output a component wrote between being invoked at `recorded_at` and
+/// its `next` invocation.
+fn invocation_output<'a>(
+    history: &'a [LogEntry],
+    recording: &Recording,
+    recorded_at: DateTime<FixedOffset>,
+    next: Option<DateTime<FixedOffset>>,
+) -> Vec<&'a LogRecord> {
+    let window_end = recorded_at + chrono::Duration::seconds(INVOCATION_OUTPUT_SECS);
+    let end = next.map_or(window_end, |next| next.min(window_end));
+    history
+        .iter()
+        .filter(|e| e.record.component == recording.component_id)
+        .filter(|e| e.timestamp >= recorded_at && e.timestamp < end)
+        .map(|e| &e.record)
+        .collect()
+}
+
+/// A one-line description of an invocation's payload.
+fn summary(payload: &RecordedPayload) -> String {
+    match payload {
+        RecordedPayload::Http {
+            method,
+            path_and_query,
+            ..
+        } => format!("{method} {path_and_query}"),
+        RecordedPayload::Redis {
+            channel, payload, ..
+        } => {
+            format!("{channel}: {}", truncate(&String::from_utf8_lossy(payload)))
+        }
+    }
+}
+
+fn truncate(s: &str) -> String {
+    match s.char_indices().nth(MAX_VALUE_LEN) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_owned(),
+    }
+}
+
+fn json_value(value: &v4::Value) -> Value {
+    match value {
+        v4::Value::Integer(i) => json!(i),
+        v4::Value::Real(r) => json!(r),
+        v4::Value::Text(t) => json!(t),
+        v4::Value::Blob(b) => json!(format!("<{} byte blob>", b.len())),
+        v4::Value::Null => Value::Null,
+    }
+}
+
+/// Whether a request is addressed to one of the dashboard's `hosts` and, if
+/// it comes from a web page, from a page the dashboard served.
+fn is_same_origin<B>(req: &Request<B>, hosts: &[String]) -> bool {
+    // Values which aren't ASCII match nothing
+    let header = |name: HeaderName| {
+        req.headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap_or_default())
+    };
+    let Some(host) = header(HOST) else {
+        return false;
+    };
+    if !hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+        return false;
+    }
+    match header(ORIGIN) {
+        Some(origin) => origin
+            .strip_prefix("http://")
+            .is_some_and(|origin| origin.eq_ignore_ascii_case(host)),
+        None => true,
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, component: &str, message: &str) -> LogEntry {
+        let raw = json!({
+            "timestamp": timestamp,
+            "component": component,
+            "stream": "stdout",
+            "message": message,
+        });
+        LogEntry::parse(&raw.to_string()).unwrap()
+    }
+
+    #[test]
+    fn output_is_attributed_to_invocations() {
+        let history = [
+            entry("2024-05-01T12:00:00Z", "hello", "before"),
+            entry("2024-05-01T12:00:01Z", "hello", "first"),
+            entry("2024-05-01T12:00:01Z", "other", "elsewhere"),
+            entry("2024-05-01T12:00:03Z", "hello", "second"),
+            entry("2024-05-01T12:01:00Z", "hello", "much later"),
+        ];
+        let recording = Recording {
+            recorded_at: "2024-05-01T12:00:01Z".into(),
+            trigger_type: "http".into(),
+            component_id: "hello".into(),
+            payload: RecordedPayload::Redis {
+                channel: "messages".into(),
+                pattern: None,
+                id: None,
+                headers: vec![],
+                payload: b"hi".to_vec(),
+            },
+        };
+        let at = |s| DateTime::parse_from_rfc3339(s).unwrap();
+        let messages = |next| -> Vec<String> {
+            invocation_output(&history, &recording, at("2024-05-01T12:00:01Z"), next)
+                .into_iter()
+                .map(|r| r.message.clone())
+                .collect()
+        };
+
+        assert_eq!(vec!["first", "second"], messages(None));
+        assert_eq!(vec!["first"], messages(Some(at("2024-05-01T12:00:02Z"))));
+        assert_eq!("messages: hi", summary(&recording.payload));
+    }
+
+    #[test]
+    fn only_same_origin_requests_are_served() {
+        let hosts = ["127.0.0.1:3031".to_owned(), "localhost:3031".to_owned()];
+        let request = |host: Option<&str>, origin: Option<&str>| {
+            let mut builder = Request::builder().uri("/api/kv/default");
+            if let Some(host) = host {
+                builder = builder.header(HOST, host);
+            }
+            if let Some(origin) = origin {
+                builder = builder.header(ORIGIN, origin);
+            }
+            builder.body(()).unwrap()
+        };
+        assert!(is_same_origin(
+            &request(Some("127.0.0.1:3031"), None),
+            &hosts
+        ));
+        assert!(is_same_origin(
+            &request(Some("localhost:3031"), Some("http://localhost:3031")),
+            &hosts
+        ));
+        assert!(!is_same_origin(&request(None, None), &hosts));
+        assert!(!is_same_origin(
+            &request(Some("evil.example.com:3031"), None),
+            &hosts
+        ));
+        assert!(!is_same_origin(
+            &request(Some("127.0.0.1:3031"), Some("http://evil.example.com")),
+            &hosts
+        ));
+        assert!(!is_same_origin(
+            &request(Some("127.0.0.1:3031"), Some("null")),
+            &hosts
+        ));
+    }
+
+    #[test]
+    fn long_values_are_truncated() {
+        let long = "x".repeat(MAX_VALUE_LEN + 1);
+        assert_eq!(MAX_VALUE_LEN + 1, truncate(&long).chars().count());
+        assert!(truncate(&long).ends_with('…'));
+        assert_eq!("short", truncate("short"));
+    }
+}
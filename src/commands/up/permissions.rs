@@ -0,0 +1,182 @@
+//! Reporting the capabilities an app's components are granted, so that they
+//! can be reviewed before the app is deployed.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use comfy_table::Table;
+use serde::Serialize;
+use spin_app::{locked::LockedApp, App};
+use spin_factor_key_value::KEY_VALUE_STORES_KEY;
+use spin_factor_outbound_networking::allowed_outbound_hosts;
+use spin_factor_sqlite::ALLOWED_DATABASES_KEY;
+
+/// The format of the `--print-permissions` report.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PermissionsFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// The capabilities granted to each component of an app.
+#[derive(Debug, Serialize)]
+pub struct PermissionsReport {
+    components: Vec<ComponentPermissions>,
+}
+
+/// The capabilities granted to a component.
+#[derive(Debug, Serialize)]
+struct ComponentPermissions {
+    id: String,
+    outbound_hosts: Vec<String>,
+    key_value_stores: Vec<String>,
+    sqlite_databases: Vec<String>,
+    /// The names of the component's variables.
+    variables: Vec<String>,
+    /// The guest paths files are mounted at.
+    files: Vec<String>,
+}
+
+impl PermissionsReport {
+    pub fn new(locked_app: &LockedApp) -> Result<Self> {
+        let app = App::new("permissions", locked_app.clone());
+        let components = app
+            .components()
+            .map(|component| {
+                let id = component.id().to_owned();
+                let metadata = |key| {
+                    component
+                        .get_metadata(key)
+                        .map(Option::unwrap_or_default)
+                        .with_context(|| format!("invalid metadata for component {id:?}"))
+                };
+                let mut permissions = ComponentPermissions {
+                    outbound_hosts: allowed_outbound_hosts(&component)?,
+                    key_value_stores: metadata(KEY_VALUE_STORES_KEY)?,
+                    sqlite_databases: metadata(ALLOWED_DATABASES_KEY)?,
+                    variables: component.locked.config.keys().cloned().collect(),
+                    files: component
+                        .locked
+                        .files
+                        .iter()
+                        .map(|file| file.path.display().to_string())
+                        .collect(),
+                    id,
+                };
+                permissions.sort();
+                Ok(permissions)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { components })
+    }
+
+    pub fn print(&self, format: PermissionsFormat) -> Result<()> {
+        match format {
+            PermissionsFormat::Table => println!("{}", self.table()),
+            PermissionsFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+        }
+        Ok(())
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(["Component", "Capability", "Granted"]);
+        table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        for component in &self.components {
+            let capabilities = component.capabilities();
+            if capabilities.is_empty() {
+                table.add_row([component.id.as_str(), "(none)", ""]);
+            }
+            for (capability, granted) in capabilities {
+                table.add_row([component.id.as_str(), capability, &granted.join("\n")]);
+            }
+        }
+        table
+    }
+}
+
+impl ComponentPermissions {
+    fn sort(&mut self) {
+        self.outbound_hosts.sort();
+        self.key_value_stores.sort();
+        self.sqlite_databases.sort();
+        self.variables.sort();
+        self.files.sort();
+    }
+
+    /// The capabilities the component has, by name.
+    fn capabilities(&self) -> Vec<(&'static str, &[String])> {
+        [
+            ("Outbound hosts", &self.outbound_hosts),
+            ("Key-value stores", &self.key_value_stores),
+            ("SQLite databases", &self.sqlite_databases),
+            ("Variables", &self.variables),
+            ("Files", &self.files),
+        ]
+        .into_iter()
+        .filter(|(_, granted)| !granted.is_empty())
+        .map(|(capability, granted)| (capability, granted.as_slice()))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_app() -> LockedApp {
+        serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 1,
+            "triggers": [],
+            "components": [
+                {
+                    "id": "api",
+                    "source": { "content_type": "application/wasm", "source": "file:///api.wasm" },
+                    "metadata": {
+                        "allowed_outbound_hosts": ["https://api.example.com", "redis://cache:6379"],
+                        "key_value_stores": ["default"],
+                        "databases": ["orders"],
+                    },
+                    "config": { "token": "{{ api_token }}" },
+                    "files": [{ "source": "file:///assets", "path": "/assets" }],
+                },
+                {
+                    "id": "static",
+                    "source": { "content_type": "application/wasm", "source": "file:///static.wasm" },
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_component_capabilities() -> Result<()> {
+        let report = PermissionsReport::new(&locked_app())?;
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(
+            json["components"][0],
+            serde_json::json!({
+                "id": "api",
+                "outbound_hosts": ["https://api.example.com", "redis://cache:6379"],
+                "key_value_stores": ["default"],
+                "sqlite_databases": ["orders"],
+                "variables": ["token"],
+                "files": ["/assets"],
+            })
+        );
+        assert_eq!(
+            json["components"][1]["outbound_hosts"],
+            serde_json::json!([])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn table_lists_granted_capabilities() -> Result<()> {
+        let table = PermissionsReport::new(&locked_app())?.table().to_string();
+        assert!(table.contains("Key-value stores"));
+        assert!(table.contains("redis://cache:6379"));
+        assert!(table.contains("(none)"));
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::BTreeMap, path::Path, process::Stdio, time::Duration};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::Deserialize;
+use spin_common::{paths::parent_dir, ui::quoted_path};
+
+/// The container CLI used to run `image` services.
+const CONTAINER_CLI: &str = "docker";
+
+/// How long to wait for a service's ports to accept connections, unless the
+/// service sets `ready_timeout_secs`.
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to retry connecting to a service's ports while waiting.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A services file, declaring companion services to run with the app.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServicesConfig {
+    #[serde(default, rename = "service")]
+    services: BTreeMap<String, ServiceConfig>,
+}
+
+/// `[service.<name>]`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceConfig {
+    /// A container image to run.
+    image: Option<String>,
+    /// A command to run as a local process.
+    command: Option<Vec<String>>,
+    /// Ports the service listens on. Container ports are published on the
+    /// same host port.
+    #[serde(default)]
+    ports: Vec<u16>,
+    /// Environment variables for the service.
+    #[serde(default)]
+    environment: BTreeMap<String, String>,
+    /// Application variables to set while the service is running, e.g. to
+    /// tell the app the service's address.
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+    /// How long to wait for the service's ports to accept connections.
+    ready_timeout_secs: Option<u64>,
+}
+
+impl ServicesConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read services file {}", quoted_path(path)))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Failed to parse services file {}", quoted_path(path)))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        for (name, service) in &config.services {
+            ensure!(
+                service.image.is_some() != service.command.is_some(),
+                "service '{name}' must set exactly one of `image` or `command`"
+            );
+            if let Some(command) = &service.command {
+                ensure!(
+                    !command.is_empty(),
+                    "service '{name}' has an empty `command`"
+                );
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The companion services started for `spin up --services`. Processes are
+/// killed and containers removed when this is dropped.
+#[derive(Default)]
+pub(crate) struct RunningServices {
+    processes: Vec<(String, tokio::process::Child)>,
+    containers: Vec<String>,
+    variables: Vec<(String, String)>,
+}
+
+impl RunningServices {
+    /// Starts the services declared in the given file and waits for them to
+    /// accept connections on their ports.
+    pub async fn start(path: &Path) -> Result<Self> {
+        let config = ServicesConfig::load(path)?;
+        let base_dir = parent_dir(path)?;
+
+        let mut running = Self::default();
+        for (name, service) in &config.services {
+            terminal::step!("Starting", "service {name}");
+            running
+                .start_service(name, service, &base_dir)
+                .await
+                .with_context(|| format!("Failed to start service '{name}'"))?;
+            running.variables.extend(service.variables.clone());
+        }
+        for (name, service) in &config.services {
+            let timeout = service
+                .ready_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_READY_TIMEOUT);
+            running
+                .wait_until_ready(name, &service.ports, timeout)
+                .await?;
+        }
+        Ok(running)
+    }
+
+    /// The application variables the services provide.
+    pub fn variables(&self) -> &[(String, String)] {
+        &self.variables
+    }
+
+    async fn start_service(
+        &mut self,
+        name: &str,
+        service: &ServiceConfig,
+        base_dir: &Path,
+    ) -> Result<()> {
+        if let Some(image) = &service.image {
+            let container_name = format!("spin-{name}-{}", uuid::Uuid::new_v4().simple());
+            let mut cmd = tokio::process::Command::new(CONTAINER_CLI);
+            cmd.args(["run", "--detach", "--rm", "--name", &container_name]);
+            for port in &service.ports {
+                cmd.args(["--publish", &format!("127.0.0.1:{port}:{port}")]);
+            }
+            for (key, value) in &service.environment {
+                cmd.args(["--env", &format!("{key}={value}")]);
+            }
+            cmd.arg(image);
+            let output =
+                cmd.stdout(Stdio::null()).output().await.with_context(|| {
+                    format!("Failed to run `{CONTAINER_CLI}`; is it installed?")
+                })?;
+            if !output.status.success() {
+                bail!(
+                    "`{CONTAINER_CLI} run` failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            self.containers.push(container_name);
+        } else if let Some([program, args @ ..]) = service.command.as_deref() {
+            let child = tokio::process::Command::new(program)
+                .args(args)
+                .envs(&service.environment)
+                .current_dir(base_dir)
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Failed to run {program:?}"))?;
+            self.processes.push((name.to_owned(), child));
+        }
+        Ok(())
+    }
+
+    async fn wait_until_ready(
+        &mut self,
+        name: &str,
+        ports: &[u16],
+        timeout: Duration,
+    ) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        for port in ports {
+            while tokio::net::TcpStream::connect(("127.0.0.1", *port))
+                .await
+                .is_err()
+            {
+                if let Some(status) = self.exited(name)? {
+                    bail!("Service '{name}' exited before it was ready ({status})");
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    bail!(
+                        "Service '{name}' was not listening on port {port} after {}s",
+                        timeout.as_secs()
+                    );
+                }
+                tokio::time::sleep(READY_POLL_INTERVAL).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the exit status of the named process service if it has exited.
+    fn exited(&mut self, name: &str) -> Result<Option<std::process::ExitStatus>> {
+        match self.processes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, child)) => Ok(child.try_wait()?),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Drop for RunningServices {
+    fn drop(&mut self) {
+        for container in &self.containers {
+            let result = std::process::Command::new(CONTAINER_CLI)
+                .args(["rm", "--force", container])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            if let Err(e) = result {
+                tracing::warn!("Failed to remove service container {container}: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_file_is_parsed() {
+        let config = ServicesConfig::parse(
+            r#"
+            [service.redis]
+            image = "redis:7"
+            ports = [6379]
+            variables = { redis_url = "redis://127.0.0.1:6379" }
+
+            [service.mock-api]
+            command = ["python3", "-m", "http.server", "9000"]
+            ports = [9000]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.services["redis"].image.as_deref(), Some("redis:7"));
+        assert_eq!(
+            config.services["redis"].variables["redis_url"],
+            "redis://127.0.0.1:6379"
+        );
+        assert_eq!(config.services["mock-api"].ports, [9000]);
+    }
+
+    #[test]
+    fn service_must_have_image_or_command() {
+        let err = ServicesConfig::parse("[service.empty]\nports = [80]").unwrap_err();
+        assert!(err.to_string().contains("exactly one"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn exited_process_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("services.toml");
+        std::fs::write(
+            &path,
+            "[service.quitter]\ncommand = [\"true\"]\nports = [1]\nready_timeout_secs = 5",
+        )
+        .unwrap();
+        let err = RunningServices::start(&path).await.err().unwrap();
+        assert!(err.to_string().contains("exited"), "{err}");
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
+    collections::HashSet,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -18,6 +19,7 @@ use crate::{
 };
 
 mod buildifier;
+mod dependencies;
 mod filters;
 mod reconfiguriser;
 mod uppificator;
@@ -86,6 +88,13 @@ impl WatchCommand {
         //   * Reconfiguration is supported by the ReconfigurableWatcher, which holds the watchexec instance,
         //     and the RuntimeConfigFactory, which holds the information needed to re-read the manifest
         //     and create a new configuration for the watchexec instances.
+        // * Changes are incremental where possible. Each watcher records the paths that changed, and
+        //   the DependencyGraph maps them to the components that depend on them:
+        //   * The Buildifier rebuilds only the components whose build inputs changed (or everything
+        //     if the manifest changed or it has never built successfully).
+        //   * The Uppificator asks the running `spin up` to reload rather than restarting it, unless
+        //     the manifest changed. Reloading keeps the app serving, and only components whose Wasm
+        //     changed need compiling again; the rest come from the compilation cache.
         // * In skip_build configurations, the Buildifier is not present.
         // * In clear configurations, both the Buildifier and the Uppificator clear the screen on a change.
         //   * There is a slight twist here that the Uppificator does _not_ clear the screen if the Buildifier
@@ -108,22 +117,28 @@ impl WatchCommand {
         let (source_code_tx, source_code_rx) = tokio::sync::watch::channel(Uuid::new_v4());
         let (manifest_tx, manifest_rx) = tokio::sync::watch::channel(Uuid::new_v4());
         let (stop_tx, stop_rx) = tokio::sync::watch::channel(Uuid::new_v4());
+        let changed_artifacts = ChangedPaths::default();
+        let changed_source_code = ChangedPaths::default();
 
         let mut buildifier = Buildifier {
             spin_bin: spin_bin.clone(),
             manifest: manifest_file.clone(),
+            manifest_dir: manifest_dir.clone(),
             clear_screen: self.clear,
             has_ever_built: false,
             watched_changes: source_code_rx,
+            changed_paths: changed_source_code.clone(),
             uppificator_pauser: pause_tx.clone(),
         };
 
         let mut uppificator = Uppificator {
             spin_bin: spin_bin.clone(),
             manifest: manifest_file.clone(),
+            manifest_dir: manifest_dir.clone(),
             up_args: self.up_args.clone(),
             clear_screen: self.clear,
             watched_changes: artifact_rx,
+            changed_paths: changed_artifacts.clone(),
             pause_feed: pause_rx,
             stopper: stop_rx,
         };
@@ -148,6 +163,7 @@ impl WatchCommand {
                 &manifest_dir,
                 artifact_filterer,
                 artifact_tx,
+                changed_artifacts,
                 "reload",
             )
             .await
@@ -162,6 +178,7 @@ impl WatchCommand {
                 &manifest_dir,
                 build_filterer,
                 source_code_tx,
+                changed_source_code,
                 "build",
             )
             .await
@@ -181,6 +198,7 @@ impl WatchCommand {
                 &manifest_dir,
                 manifest_filterer,
                 manifest_tx,
+                ChangedPaths::default(),
                 "reconfigure",
             )
             .await
@@ -244,6 +262,7 @@ impl WatchCommand {
         manifest_dir: &Path,
         filter_factory: Box<dyn FilterFactory>,
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: ChangedPaths,
         impact_description: &'static str,
     ) -> anyhow::Result<(ReconfigurableWatcher, tokio::task::JoinHandle<()>)> {
         let rtf = RuntimeConfigFactory {
@@ -251,6 +270,7 @@ impl WatchCommand {
             manifest_dir: manifest_dir.to_owned(),
             filter_factory,
             notifier,
+            changed_paths,
             impact_description,
             debounce: Duration::from_millis(self.debounce),
         };
@@ -274,6 +294,7 @@ pub struct RuntimeConfigFactory {
     manifest_dir: PathBuf,
     filter_factory: Box<dyn FilterFactory>,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: ChangedPaths,
     impact_description: &'static str,
     debounce: Duration,
 }
@@ -287,7 +308,11 @@ impl RuntimeConfigFactory {
             .build_filter(&self.manifest_file, &self.manifest_dir, &manifest)
             .await?;
 
-        let handler = NotifyOnFileChange::new(self.notifier.clone(), self.impact_description);
+        let handler = NotifyOnFileChange::new(
+            self.notifier.clone(),
+            self.changed_paths.clone(),
+            self.impact_description,
+        );
 
         let mut rt = watchexec::config::RuntimeConfig::default();
         rt.pathset([&self.manifest_dir]);
@@ -306,17 +331,20 @@ impl RuntimeConfigFactory {
 struct NotifyOnFileChange {
     despurifier: despurifier::Despurifier,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: ChangedPaths,
     impact_description: &'static str,
 }
 
 impl NotifyOnFileChange {
     fn new(
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: ChangedPaths,
         impact_description: &'static str,
     ) -> Self {
         Self {
             despurifier: despurifier::Despurifier::new(),
             notifier,
+            changed_paths,
             impact_description,
         }
     }
@@ -335,6 +363,8 @@ impl watchexec::handler::Handler<watchexec::action::Action> for NotifyOnFileChan
                 self.impact_description,
                 paths_of(&action)
             );
+            self.changed_paths
+                .record(action.events.iter().filter_map(path_of_event));
             _ = self.notifier.send(Uuid::new_v4());
         }
         action.outcome(watchexec::action::Outcome::DoNothing);
@@ -342,6 +372,26 @@ impl watchexec::handler::Handler<watchexec::action::Action> for NotifyOnFileChan
     }
 }
 
+// The paths that have changed since the receiver of a watcher's notifications
+// last looked. The notification channel only says *that* something changed,
+// and coalesces notifications, so the paths are accumulated here.
+#[derive(Clone, Default)]
+pub(crate) struct ChangedPaths(Arc<Mutex<HashSet<PathBuf>>>);
+
+impl ChangedPaths {
+    fn record<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) {
+        let mut changed = self.0.lock().unwrap();
+        changed.extend(paths.into_iter().map(ToOwned::to_owned));
+    }
+
+    /// Returns the paths that have changed, forgetting them.
+    pub fn take(&self) -> Vec<PathBuf> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+            .into_iter()
+            .collect()
+    }
+}
+
 fn paths_of(action: &watchexec::action::Action) -> String {
     action
         .events
@@ -2,14 +2,18 @@ use command_group::AsyncCommandGroup;
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use super::dependencies::{Affected, DependencyGraph};
 use super::uppificator::Pause;
+use super::ChangedPaths;
 
 pub(crate) struct Buildifier {
     pub spin_bin: PathBuf,
     pub manifest: PathBuf,
+    pub manifest_dir: PathBuf,
     pub clear_screen: bool,
     pub has_ever_built: bool,
-    pub watched_changes: tokio::sync::watch::Receiver<Uuid>, // TODO: refine which component(s) a change affects
+    pub watched_changes: tokio::sync::watch::Receiver<Uuid>,
+    pub changed_paths: ChangedPaths,
     pub uppificator_pauser: tokio::sync::mpsc::Sender<Pause>,
 }
 
@@ -19,6 +23,8 @@ impl Buildifier {
         // Other components may close channels as part of shutdown, so if any channels
         // fail, just exit the loop and fall out normally.
 
+        let mut to_build = Affected::All;
+
         loop {
             if self.clear_screen {
                 _ = clearscreen::clear();
@@ -28,7 +34,7 @@ impl Buildifier {
                 break;
             }
 
-            let build_result = self.build_once().await;
+            let build_result = self.build_once(&mut to_build).await;
             if !self.has_ever_built {
                 self.has_ever_built = matches!(build_result, Ok(true));
             }
@@ -42,13 +48,27 @@ impl Buildifier {
             if self.watched_changes.changed().await.is_err() {
                 break;
             }
+
+            // Until everything has built once, components that didn't change
+            // may still be unbuilt.
+            to_build = if self.has_ever_built {
+                self.affected_components().await
+            } else {
+                Affected::All
+            };
         }
     }
 
-    pub(crate) async fn build_once(&mut self) -> std::io::Result<bool> {
+    pub(crate) async fn build_once(&mut self, to_build: &mut Affected) -> std::io::Result<bool> {
         loop {
             let mut cmd = tokio::process::Command::new(&self.spin_bin);
             cmd.arg("build").arg("-f").arg(&self.manifest);
+            if let Affected::Components(ids) = to_build {
+                terminal::step!("Rebuilding", "{to_build}");
+                for id in ids.iter() {
+                    cmd.arg("-c").arg(id);
+                }
+            }
             let mut child = cmd.group_spawn()?;
 
             tokio::select! {
@@ -63,10 +83,31 @@ impl Buildifier {
                     if self.clear_screen {
                         _ = clearscreen::clear();
                     }
+                    // The cancelled build may have been part way through
+                    // building components that haven't changed again.
+                    to_build.extend(self.affected_components().await);
                     continue;
                 }
 
             }
         }
     }
+
+    /// Works out which components to rebuild for the changes since the last
+    /// build.
+    async fn affected_components(&self) -> Affected {
+        let paths = self.changed_paths.take();
+        if paths.contains(&self.manifest) {
+            return Affected::All;
+        }
+        match DependencyGraph::load(&self.manifest, &self.manifest_dir).await {
+            Ok(graph) => graph.components_to_rebuild(&paths),
+            Err(e) => {
+                tracing::debug!(
+                    "Rebuilding all components as the manifest couldn't be read: {e:#}"
+                );
+                Affected::All
+            }
+        }
+    }
 }
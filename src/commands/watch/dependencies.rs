@@ -0,0 +1,226 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use spin_manifest::schema::v2;
+
+use super::filters::{artifact_globs, source_globs};
+
+/// The files each component of an app depends on: the inputs to its build and
+/// the artifacts it runs from.
+///
+/// This lets `spin watch` rebuild and reload only the components a change
+/// affects.
+pub(crate) struct DependencyGraph {
+    manifest_dir: PathBuf,
+    components: Vec<ComponentDependencies>,
+}
+
+struct ComponentDependencies {
+    id: String,
+    /// The files whose changes require the component to be rebuilt. Empty if
+    /// the component has no build command or doesn't say what to watch.
+    build_inputs: Vec<glob::Pattern>,
+    /// The Wasm source and files the component runs from.
+    artifacts: Vec<glob::Pattern>,
+}
+
+/// The components affected by a change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Affected {
+    /// Every component.
+    All,
+    /// Only the given components.
+    Components(BTreeSet<String>),
+}
+
+impl Affected {
+    /// Adds the components affected by another change.
+    pub fn extend(&mut self, other: Affected) {
+        match (self, other) {
+            (Self::All, _) => (),
+            (this, Self::All) => *this = Self::All,
+            (Self::Components(these), Self::Components(others)) => these.extend(others),
+        }
+    }
+}
+
+impl std::fmt::Display for Affected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => f.write_str("all components"),
+            Self::Components(ids) => write!(f, "{}", ids.iter().join(", ")),
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// Reads the manifest and builds its dependency graph.
+    pub async fn load(manifest_file: &Path, manifest_dir: &Path) -> anyhow::Result<Self> {
+        let manifest_str = tokio::fs::read_to_string(manifest_file).await?;
+        let manifest = spin_manifest::manifest_from_str(&manifest_str)?;
+        Self::new(manifest_dir, &manifest)
+    }
+
+    pub fn new(manifest_dir: &Path, manifest: &v2::AppManifest) -> anyhow::Result<Self> {
+        let components = manifest
+            .components
+            .iter()
+            .map(|(id, component)| {
+                Ok(ComponentDependencies {
+                    id: id.to_string(),
+                    build_inputs: patterns(source_globs(component).unwrap_or_default())?,
+                    artifacts: patterns(artifact_globs(component))?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            manifest_dir: manifest_dir.to_owned(),
+            components,
+        })
+    }
+
+    /// The components that must be rebuilt after `paths` changed.
+    ///
+    /// If no component claims any of the paths, all components are rebuilt,
+    /// since a change the watcher noticed must matter to something.
+    pub fn components_to_rebuild(&self, paths: &[PathBuf]) -> Affected {
+        let affected = self.affected_by(paths, |c| &c.build_inputs);
+        if affected.is_empty() {
+            Affected::All
+        } else {
+            Affected::Components(affected)
+        }
+    }
+
+    /// The components that must be reloaded after `paths` changed.
+    pub fn components_to_reload(&self, paths: &[PathBuf]) -> Affected {
+        Affected::Components(self.affected_by(paths, |c| &c.artifacts))
+    }
+
+    fn affected_by(
+        &self,
+        paths: &[PathBuf],
+        globs: impl Fn(&ComponentDependencies) -> &[glob::Pattern],
+    ) -> BTreeSet<String> {
+        self.components
+            .iter()
+            .filter(|c| paths.iter().any(|path| self.matches(globs(c), path)))
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    fn matches(&self, patterns: &[glob::Pattern], path: &Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        // Globs in the manifest are relative to the manifest directory, unless
+        // they're absolute
+        let relative = path.strip_prefix(&self.manifest_dir).unwrap_or(path);
+        patterns.iter().any(|pattern| {
+            pattern.matches_path_with(relative, options) || pattern.matches_path_with(path, options)
+        })
+    }
+}
+
+fn patterns(globs: Vec<String>) -> anyhow::Result<Vec<glob::Pattern>> {
+    globs
+        .iter()
+        .map(|g| {
+            let g = g.strip_prefix("./").unwrap_or(g);
+            glob::Pattern::new(g).with_context(|| format!("invalid glob {g:?}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let manifest = spin_manifest::manifest_from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "deps"
+
+            [[trigger.http]]
+            route = "/api/..."
+            component = "api"
+
+            [[trigger.http]]
+            route = "/static/..."
+            component = "web"
+
+            [component.api]
+            source = "api/target/api.wasm"
+            [component.api.build]
+            command = "cargo build"
+            workdir = "api"
+            watch = ["src/**/*.rs", "Cargo.toml"]
+
+            [component.web]
+            source = "web.wasm"
+            files = [{ source = "assets", destination = "/" }]
+            [component.web.build]
+            command = "npm run build"
+            watch = ["web/**/*.ts"]
+            "#,
+        )
+        .unwrap();
+        DependencyGraph::new(Path::new("/app"), &manifest).unwrap()
+    }
+
+    fn components(ids: &[&str]) -> Affected {
+        Affected::Components(ids.iter().map(|id| id.to_string()).collect())
+    }
+
+    #[test]
+    fn only_components_whose_inputs_changed_are_rebuilt() {
+        let graph = graph();
+        assert_eq!(
+            graph.components_to_rebuild(&["/app/api/src/handlers/orders.rs".into()]),
+            components(&["api"])
+        );
+        assert_eq!(
+            graph
+                .components_to_rebuild(&["/app/api/Cargo.toml".into(), "/app/web/index.ts".into()]),
+            components(&["api", "web"])
+        );
+        // The api watches Cargo.toml in its workdir, not the app directory
+        assert_eq!(
+            graph.components_to_rebuild(&["/app/Cargo.toml".into()]),
+            Affected::All
+        );
+    }
+
+    #[test]
+    fn only_components_whose_artifacts_changed_are_reloaded() {
+        let graph = graph();
+        assert_eq!(
+            graph.components_to_reload(&["/app/api/target/api.wasm".into()]),
+            components(&["api"])
+        );
+        assert_eq!(
+            graph.components_to_reload(&["/app/assets/css/site.css".into()]),
+            components(&["web"])
+        );
+        assert_eq!(
+            graph.components_to_reload(&["/app/README.md".into()]),
+            components(&[])
+        );
+    }
+
+    #[test]
+    fn affected_components_accumulate() {
+        let mut affected = components(&["api"]);
+        affected.extend(components(&["web"]));
+        assert_eq!(affected, components(&["api", "web"]));
+        affected.extend(Affected::All);
+        assert_eq!(affected, Affected::All);
+    }
+}
@@ -40,13 +40,7 @@ impl FilterFactory for ArtifactFilterFactory {
         } else {
             vec![] // In this case, manifest changes trigger a rebuild, which will poke the uppificator anyway
         };
-        let wasm_globs = manifest
-            .components
-            .values()
-            .filter_map(|c| match &c.source {
-                v2::ComponentSource::Local(path) => Some(path.clone()),
-                _ => None,
-            });
+        let wasm_globs = manifest.components.values().filter_map(wasm_glob);
         let asset_globs = match self.skip_assets {
             true => {
                 tracing::debug!("Skipping asset globs from being watched");
@@ -72,12 +66,28 @@ impl FilterFactory for ArtifactFilterFactory {
     }
 }
 
+/// The globs of the files a component runs from: its Wasm source and its files.
+pub(super) fn artifact_globs(c: &v2::Component) -> Vec<String> {
+    wasm_glob(c)
+        .into_iter()
+        .chain(c.files.iter().filter_map(globbify))
+        .collect()
+}
+
+fn wasm_glob(c: &v2::Component) -> Option<String> {
+    match &c.source {
+        v2::ComponentSource::Local(path) => Some(path.clone()),
+        _ => None,
+    }
+}
+
 fn globbify(files_mount: &v2::WasiFilesMount) -> Option<String> {
     match files_mount {
         v2::WasiFilesMount::Placement { source, .. } => {
             Path::new(source).join("**/*").to_str().map(String::from)
         }
         v2::WasiFilesMount::Pattern(pattern) => Some(pattern.clone()),
+        v2::WasiFilesMount::Tmpfs { .. } | v2::WasiFilesMount::Remote { .. } => None,
     }
 }
 
@@ -118,6 +128,13 @@ fn create_source_globs(cid: &str, c: &v2::Component) -> Option<Vec<String>> {
         );
         return None;
     };
+    source_globs(c)
+}
+
+/// The globs of the files whose changes require a component to be rebuilt, or
+/// `None` if there are none.
+pub(super) fn source_globs(c: &v2::Component) -> Option<Vec<String>> {
+    let build = c.build.as_ref()?;
     let globs = build
         .workdir
         .as_deref()
@@ -1,13 +1,21 @@
 use command_group::AsyncCommandGroup;
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use uuid::Uuid;
 
+use super::dependencies::{Affected, DependencyGraph};
+use super::ChangedPaths;
+
 pub(crate) struct Uppificator {
     pub spin_bin: PathBuf,
     pub up_args: Vec<String>,
     pub manifest: PathBuf,
+    pub manifest_dir: PathBuf,
     pub clear_screen: bool,
     pub watched_changes: tokio::sync::watch::Receiver<Uuid>,
+    pub changed_paths: ChangedPaths,
     pub pause_feed: tokio::sync::mpsc::Receiver<Pause>,
     pub stopper: tokio::sync::watch::Receiver<Uuid>,
 }
@@ -42,6 +50,7 @@ impl Uppificator {
                 .arg("-f")
                 .arg(&self.manifest)
                 .args(&self.up_args);
+            let manifest_modified = modified_time(&self.manifest);
             let mut child = match cmd.group_spawn() {
                 Ok(ch) => ch,
                 Err(e) => {
@@ -49,11 +58,13 @@ impl Uppificator {
                     break 'run;
                 }
             };
+            // Changes up to now are included in this run
+            self.changed_paths.take();
 
             let mut resuming_after_build = false;
 
             loop {
-                match self.next_event(&mut child).await {
+                match self.next_event(&mut child, manifest_modified).await {
                     UppificatorAction::Restart => break,
                     UppificatorAction::Resume => {
                         resuming_after_build = true;
@@ -73,12 +84,16 @@ impl Uppificator {
     async fn next_event(
         &mut self,
         child: &mut command_group::AsyncGroupChild,
+        manifest_modified: Option<SystemTime>,
     ) -> UppificatorAction {
         tokio::select! {
             _ = child.wait() => {
                 UppificatorAction::Wait
             },
             _ = self.watched_changes.changed() => {
+                if self.try_reload(child, manifest_modified).await {
+                    return UppificatorAction::Wait;
+                }
                 stop(child).await;
                 UppificatorAction::Restart
             },
@@ -102,6 +117,73 @@ impl Uppificator {
     }
 }
 
+impl Uppificator {
+    /// Asks the running `spin up` to reload the app, if the changes allow it.
+    ///
+    /// Reloading keeps the app serving while changed components are swapped
+    /// in. It isn't used if the manifest has changed since `spin up` started,
+    /// since a manifest change can alter things that only a restart applies,
+    /// such as the app's trigger types.
+    async fn try_reload(
+        &self,
+        child: &mut command_group::AsyncGroupChild,
+        manifest_modified: Option<SystemTime>,
+    ) -> bool {
+        let paths = self.changed_paths.take();
+        if paths.contains(&self.manifest)
+            || manifest_modified.is_none()
+            || modified_time(&self.manifest) != manifest_modified
+            || !matches!(child.try_wait(), Ok(None))
+        {
+            return false;
+        }
+        let affected = match DependencyGraph::load(&self.manifest, &self.manifest_dir).await {
+            Ok(graph) => graph.components_to_reload(&paths),
+            Err(e) => {
+                tracing::debug!("Restarting as the manifest couldn't be read: {e:#}");
+                return false;
+            }
+        };
+        if !reload(child) {
+            return false;
+        }
+        match &affected {
+            Affected::Components(ids) if ids.is_empty() => {
+                terminal::step!("Reloading", "application")
+            }
+            _ => terminal::step!("Reloading", "{affected}"),
+        }
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Signals `spin up` to reload the app. Returns false if that isn't possible
+/// on this platform or the signal couldn't be sent.
+#[cfg(unix)]
+fn reload(child: &command_group::AsyncGroupChild) -> bool {
+    let Some(child_id) = child.id() else {
+        return false;
+    };
+    // Only `spin up` itself is signalled; it passes the reload on to its triggers
+    let pid = nix::unistd::Pid::from_raw(child_id as i32);
+    match nix::sys::signal::kill(pid, Some(nix::sys::signal::Signal::SIGHUP)) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("Could not send reload signal to child process: {e:#}");
+            false
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn reload(_child: &command_group::AsyncGroupChild) -> bool {
+    false
+}
+
 #[cfg(unix)]
 async fn stop(child: &mut command_group::AsyncGroupChild) {
     if let Some(child_id) = child.id() {
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use spin_app::App;
+use spin_common::ui::quoted_path;
+use spin_factors::{ConfiguredApp, RuntimeFactors};
+use spin_loader::FilesMountStrategy;
+use spin_runtime_config::ResolvedRuntimeConfig;
+use spin_runtime_factors::{TriggerFactors, TriggerFactorsRuntimeConfig};
+use spin_trigger::cli::{UserProvidedPath, RUNTIME_CONFIG_FILE};
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+/// Options for resolving an application's runtime config the same way
+/// `spin up` does, for commands that work with its configured backends.
+#[derive(Args, Debug)]
+pub struct ConfiguredAppOptions {
+    /// The application whose stores to use. This may be a manifest
+    /// (spin.toml) file, or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Configuration file for config providers and wasmtime config.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE,
+    )]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set the application state directory path. This defaults to `.spin/`
+    /// relative to the `spin.toml` file. Passing an empty value forces the
+    /// value to be unset.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+}
+
+impl ConfiguredAppOptions {
+    /// Loads the application and configures its factors with the resolved
+    /// runtime config. No components are compiled or instantiated.
+    pub async fn configure(&self) -> Result<ConfiguredApp<TriggerFactors>> {
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+        let app_dir = manifest_file
+            .parent()
+            .context("manifest path has no parent directory")?
+            .to_owned();
+
+        let locked = spin_loader::from_file(&manifest_file, FilesMountStrategy::Direct, None)
+            .await
+            .with_context(|| {
+                format!("Failed to load application {}", quoted_path(&manifest_file))
+            })?;
+        let app = App::new(manifest_file.display().to_string(), locked);
+
+        let state_dir = match &self.state_dir {
+            Some(s) if s.is_empty() => UserProvidedPath::Unset,
+            Some(s) => UserProvidedPath::Provided(PathBuf::from(s)),
+            None => UserProvidedPath::Default,
+        };
+        let runtime_config = ResolvedRuntimeConfig::<TriggerFactorsRuntimeConfig>::from_file(
+            self.runtime_config_file.as_deref(),
+            Some(app_dir.clone()),
+            state_dir,
+            UserProvidedPath::Unset,
+        )
+        .await?;
+        configure_app(app, &app_dir, runtime_config)
+    }
+}
+
+/// Configures the app's factors with the given resolved runtime config,
+/// without compiling or instantiating any components.
+pub(crate) fn configure_app(
+    app: App,
+    app_dir: &Path,
+    runtime_config: ResolvedRuntimeConfig<TriggerFactorsRuntimeConfig>,
+) -> Result<ConfiguredApp<TriggerFactors>> {
+    let factors = TriggerFactors::new(runtime_config.state_dir(), app_dir, false)
+        .context("failed to create factors")?;
+    factors
+        .configure_app(app, runtime_config.into())
+        .context("failed to configure app")
+}
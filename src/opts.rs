@@ -13,3 +13,5 @@ pub const WATCH_CLEAR_OPT: &str = "CLEAR";
 pub const WATCH_DEBOUNCE_OPT: &str = "DEBOUNCE";
 pub const WATCH_SKIP_BUILD_OPT: &str = "SKIP_BUILD";
 pub const ALWAYS_BUILD_ENV: &str = "SPIN_ALWAYS_BUILD";
+pub const ENV_PROFILE_OPT: &str = "ENV_PROFILE";
+pub const ENV_PROFILE_ENV: &str = "SPIN_ENV_PROFILE";
@@ -1,4 +1,4 @@
-use helper::http_trigger_bindings::spin::postgres::postgres;
+use helper::http_trigger_bindings::spin::postgres3_0_0::postgres;
 use helper::{ensure, ensure_eq, ensure_matches, ensure_ok};
 
 helper::define_component!(Component);